@@ -0,0 +1,236 @@
+// Core browser settings exposed through the settings registry
+use super::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use super::ConfigManager;
+use crate::core::{AutoplayPolicy, BrowserSettings, BrowserState, DataSaverProfile, SearchEngine};
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use std::sync::{Arc, Mutex};
+
+/// Settings held in `BrowserSettings`; values live in settings.json rather
+/// than the registry's own store
+pub struct BrowserSettingsProvider {
+    state: Arc<Mutex<BrowserState>>,
+    config: Arc<ConfigManager>,
+}
+
+impl BrowserSettingsProvider {
+    pub fn new(state: Arc<Mutex<BrowserState>>, config: Arc<ConfigManager>) -> Self {
+        Self { state, config }
+    }
+}
+
+impl SettingsProvider for BrowserSettingsProvider {
+    fn module(&self) -> &str {
+        "general"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let defaults = BrowserSettings::default();
+        vec![
+            SettingDefinition::text("general.home_page", "Home page", &defaults.home_page)
+                .with_description("Page opened in new windows and by the home button"),
+            SettingDefinition::choice(
+                "general.search_engine",
+                "Search engine",
+                search_engine_name(&defaults.search_engine),
+                &[
+                    ("google", "Google"),
+                    ("duckduckgo", "DuckDuckGo"),
+                    ("bing", "Bing"),
+                    ("brave", "Brave Search"),
+                ],
+            )
+            .with_description("Used for searches typed in the address bar"),
+            SettingDefinition::float("general.default_zoom", "Default zoom", defaults.default_zoom, 0.25, 5.0, 0.05)
+                .with_category(SettingCategory::Appearance),
+            SettingDefinition::toggle("general.block_popups", "Block pop-ups", defaults.block_popups)
+                .with_category(SettingCategory::Privacy),
+            SettingDefinition::toggle("general.enable_cookies", "Allow cookies", defaults.enable_cookies)
+                .with_category(SettingCategory::Privacy)
+                .with_restart_required(),
+            SettingDefinition::choice(
+                "general.autoplay",
+                "Autoplay",
+                autoplay_name(defaults.autoplay),
+                &[
+                    ("allow", "Allow all media"),
+                    ("block_audible", "Only muted media"),
+                    ("block_all", "Block all media"),
+                ],
+            )
+            .with_description("Which media may start playing before you interact with a page")
+            .with_category(SettingCategory::Privacy)
+            .with_restart_required(),
+            SettingDefinition::choice(
+                "general.data_saver",
+                "Data saver",
+                data_saver_name(defaults.data_saver),
+                &[("off", "Off"), ("moderate", "Moderate"), ("aggressive", "Aggressive")],
+            )
+            .with_description("Reduces prefetching and image quality on metered connections")
+            .with_category(SettingCategory::Network),
+            SettingDefinition::toggle("general.enable_javascript", "Enable JavaScript", defaults.enable_javascript)
+                .with_category(SettingCategory::Advanced)
+                .with_restart_required(),
+            SettingDefinition::toggle("general.enable_cache", "Enable cache", defaults.enable_cache)
+                .with_category(SettingCategory::Advanced)
+                .with_restart_required(),
+            SettingDefinition::text("general.user_agent", "Custom user agent", "")
+                .with_description("Leave empty to use the default")
+                .with_category(SettingCategory::Advanced)
+                .with_restart_required(),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let mut state = self.state.lock_or_recover();
+        let mut settings = state.settings.clone();
+        match key {
+            "general.home_page" => settings.home_page = text_value(value)?.to_string(),
+            "general.search_engine" => {
+                settings.search_engine = parse_search_engine(text_value(value)?).ok_or("Unknown search engine")?
+            }
+            "general.default_zoom" => settings.default_zoom = value.as_f64().ok_or("Expected a number")?,
+            "general.block_popups" => settings.block_popups = bool_value(value)?,
+            "general.enable_cookies" => settings.enable_cookies = bool_value(value)?,
+            "general.autoplay" => {
+                settings.autoplay = parse_autoplay(text_value(value)?).ok_or("Unknown autoplay policy")?
+            }
+            "general.data_saver" => {
+                settings.data_saver = parse_data_saver(text_value(value)?).ok_or("Unknown data saver profile")?
+            }
+            "general.enable_javascript" => settings.enable_javascript = bool_value(value)?,
+            "general.enable_cache" => settings.enable_cache = bool_value(value)?,
+            "general.user_agent" => {
+                let agent = text_value(value)?.trim();
+                settings.user_agent = (!agent.is_empty()).then(|| agent.to_string());
+            }
+            _ => return Err(format!("Unknown general setting {}", key).into()),
+        }
+
+        // Nothing changes in memory unless it could be written to disk
+        self.config.save_settings(&settings)?;
+        state.settings = settings;
+        Ok(())
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        let settings = self.state.lock_or_recover().settings.clone();
+        let value = match key {
+            "general.home_page" => SettingValue::String(settings.home_page),
+            "general.search_engine" => SettingValue::String(search_engine_name(&settings.search_engine).to_string()),
+            "general.default_zoom" => SettingValue::Float(settings.default_zoom),
+            "general.block_popups" => SettingValue::Bool(settings.block_popups),
+            "general.enable_cookies" => SettingValue::Bool(settings.enable_cookies),
+            "general.autoplay" => SettingValue::String(autoplay_name(settings.autoplay).to_string()),
+            "general.data_saver" => SettingValue::String(data_saver_name(settings.data_saver).to_string()),
+            "general.enable_javascript" => SettingValue::Bool(settings.enable_javascript),
+            "general.enable_cache" => SettingValue::Bool(settings.enable_cache),
+            "general.user_agent" => SettingValue::String(settings.user_agent.unwrap_or_default()),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Registry name of a data saver profile
+pub fn data_saver_name(profile: DataSaverProfile) -> &'static str {
+    match profile {
+        DataSaverProfile::Off => "off",
+        DataSaverProfile::Moderate => "moderate",
+        DataSaverProfile::Aggressive => "aggressive",
+    }
+}
+
+/// Data saver profile from its registry name
+pub fn parse_data_saver(name: &str) -> Option<DataSaverProfile> {
+    match name {
+        "off" => Some(DataSaverProfile::Off),
+        "moderate" => Some(DataSaverProfile::Moderate),
+        "aggressive" => Some(DataSaverProfile::Aggressive),
+        _ => None,
+    }
+}
+
+// Private helper functions
+
+fn text_value(value: &SettingValue) -> Result<&str, WebxError> {
+    Ok(value.as_str().ok_or("Expected a text value")?)
+}
+
+fn bool_value(value: &SettingValue) -> Result<bool, WebxError> {
+    Ok(value.as_bool().ok_or("Expected an on/off value")?)
+}
+
+fn search_engine_name(engine: &SearchEngine) -> &'static str {
+    match engine {
+        SearchEngine::Google => "google",
+        SearchEngine::DuckDuckGo => "duckduckgo",
+        SearchEngine::Bing => "bing",
+        SearchEngine::Brave => "brave",
+    }
+}
+
+fn parse_search_engine(name: &str) -> Option<SearchEngine> {
+    match name {
+        "google" => Some(SearchEngine::Google),
+        "duckduckgo" => Some(SearchEngine::DuckDuckGo),
+        "bing" => Some(SearchEngine::Bing),
+        "brave" => Some(SearchEngine::Brave),
+        _ => None,
+    }
+}
+
+fn autoplay_name(policy: AutoplayPolicy) -> &'static str {
+    match policy {
+        AutoplayPolicy::Allow => "allow",
+        AutoplayPolicy::BlockAudible => "block_audible",
+        AutoplayPolicy::BlockAll => "block_all",
+    }
+}
+
+fn parse_autoplay(name: &str) -> Option<AutoplayPolicy> {
+    match name {
+        "allow" => Some(AutoplayPolicy::Allow),
+        "block_audible" => Some(AutoplayPolicy::BlockAudible),
+        "block_all" => Some(AutoplayPolicy::BlockAll),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SettingsRegistry;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_values_live_in_browser_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Arc::new(ConfigManager::with_config_dir(temp_dir.path().join("config")).unwrap());
+        let state = Arc::new(Mutex::new(BrowserState::new()));
+        let registry = SettingsRegistry::new(Some(temp_dir.path().join("registry"))).unwrap();
+        registry
+            .register(Arc::new(BrowserSettingsProvider::new(state.clone(), config.clone())))
+            .unwrap();
+
+        assert_eq!(registry.get_string("general.search_engine").as_deref(), Some("google"));
+        registry
+            .set("general.search_engine", SettingValue::String("duckduckgo".to_string()))
+            .unwrap();
+        assert!(registry.set("general.default_zoom", SettingValue::Float(9.0)).is_err());
+
+        // A batch with one bad value applies nothing
+        let batch = vec![
+            ("general.home_page".to_string(), SettingValue::String("https://example.com".to_string())),
+            ("general.data_saver".to_string(), SettingValue::String("extreme".to_string())),
+        ];
+        assert!(registry.set_many(&batch).is_err());
+
+        let settings = config.load_settings();
+        assert_eq!(settings.search_engine, SearchEngine::DuckDuckGo);
+        assert_eq!(settings.home_page, BrowserSettings::default().home_page);
+        assert_eq!(state.lock_or_recover().settings.search_engine, SearchEngine::DuckDuckGo);
+        assert_eq!(registry.pending_restart(), Vec::<String>::new());
+    }
+}
@@ -1,27 +1,56 @@
 // Browser configuration and persistence
+mod browser_settings;
+mod page;
+pub mod policy;
+pub mod registry;
+pub mod storage;
+
+pub use browser_settings::*;
+pub use page::*;
+pub use policy::{ExtensionPolicy, PolicySet, ProxyPolicy, ProxyPolicyMode};
+pub use registry::{
+    SettingCategory, SettingDefinition, SettingValue, SettingsEvent, SettingsProvider, SettingsRegistry,
+};
+pub use storage::{LoadFailure, Loaded};
+
 use crate::core::{BrowserSettings, Bookmark, HistoryEntry};
+use crate::error::WebxError;
+use crate::utils::LockExt;
 use directories::ProjectDirs;
+use serde::de::DeserializeOwned;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Configuration manager for the browser
 pub struct ConfigManager {
     config_dir: PathBuf,
+    backup_count: usize,
+    load_failures: Mutex<Vec<LoadFailure>>,
 }
 
 impl ConfigManager {
     /// Create a new configuration manager
-    pub fn new() -> Result<Self, std::io::Error> {
+    pub fn new() -> Result<Self, WebxError> {
         let config_dir = if let Some(proj_dirs) = ProjectDirs::from("com", "Ledokoz", "WebX") {
             proj_dirs.config_dir().to_path_buf()
         } else {
             PathBuf::from(".webx")
         };
 
+        Self::with_config_dir(config_dir)
+    }
+
+    /// Create a configuration manager storing its files in a given directory
+    pub fn with_config_dir(config_dir: PathBuf) -> Result<Self, WebxError> {
         // Create config directory if it doesn't exist
         fs::create_dir_all(&config_dir)?;
 
-        Ok(Self { config_dir })
+        Ok(Self {
+            config_dir,
+            backup_count: storage::DEFAULT_BACKUP_COUNT,
+            load_failures: Mutex::new(Vec::new()),
+        })
     }
 
     /// Get the path to the settings file
@@ -41,71 +70,78 @@ impl ConfigManager {
 
     /// Load settings from disk
     pub fn load_settings(&self) -> BrowserSettings {
-        let path = self.settings_path();
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(settings) = serde_json::from_str(&content) {
-                    return settings;
-                }
-            }
-        }
-        BrowserSettings::default()
+        self.load_or_default(&self.settings_path())
     }
 
     /// Save settings to disk
-    pub fn save_settings(&self, settings: &BrowserSettings) -> Result<(), std::io::Error> {
-        let path = self.settings_path();
-        let content = serde_json::to_string_pretty(settings)?;
-        fs::write(path, content)?;
+    pub fn save_settings(&self, settings: &BrowserSettings) -> Result<(), WebxError> {
+        storage::save_json(&self.settings_path(), settings, self.backup_count)?;
         Ok(())
     }
 
     /// Load bookmarks from disk
     pub fn load_bookmarks(&self) -> Vec<Bookmark> {
-        let path = self.bookmarks_path();
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(bookmarks) = serde_json::from_str(&content) {
-                    return bookmarks;
-                }
-            }
-        }
-        Vec::new()
+        self.load_or_default(&self.bookmarks_path())
     }
 
     /// Save bookmarks to disk
-    pub fn save_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<(), std::io::Error> {
-        let path = self.bookmarks_path();
-        let content = serde_json::to_string_pretty(bookmarks)?;
-        fs::write(path, content)?;
+    pub fn save_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<(), WebxError> {
+        storage::save_json(&self.bookmarks_path(), bookmarks, self.backup_count)?;
         Ok(())
     }
 
     /// Load history from disk
     pub fn load_history(&self) -> Vec<HistoryEntry> {
-        let path = self.history_path();
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(history) = serde_json::from_str(&content) {
-                    return history;
-                }
-            }
-        }
-        Vec::new()
+        self.load_or_default(&self.history_path())
     }
 
     /// Save history to disk
-    pub fn save_history(&self, history: &[HistoryEntry]) -> Result<(), std::io::Error> {
-        let path = self.history_path();
-        let content = serde_json::to_string_pretty(history)?;
-        fs::write(path, content)?;
+    pub fn save_history(&self, history: &[HistoryEntry]) -> Result<(), WebxError> {
+        storage::save_json(&self.history_path(), history, self.backup_count)?;
         Ok(())
     }
 
+    /// Files that failed to load since startup, including ones that were
+    /// recovered from a backup
+    pub fn load_failures(&self) -> Vec<LoadFailure> {
+        self.load_failures.lock_or_recover().clone()
+    }
+
+    /// Set how many previous versions of each file are kept
+    pub fn set_backup_count(&mut self, backup_count: usize) {
+        self.backup_count = backup_count;
+    }
+
     /// Get the config directory path
     pub fn config_dir(&self) -> &PathBuf {
         &self.config_dir
     }
+
+    // Private helper methods
+
+    fn load_or_default<T: DeserializeOwned + Default>(&self, path: &Path) -> T {
+        match storage::load_json(path, self.backup_count) {
+            Ok(Some(loaded)) => {
+                for failure in &loaded.failures {
+                    tracing::warn!("Failed to load {}", failure);
+                }
+                if loaded.recovered() {
+                    tracing::warn!("Recovered {} from {}", path.display(), loaded.source.display());
+                }
+                self.load_failures.lock_or_recover().extend(loaded.failures);
+                loaded.value
+            }
+            Ok(None) => T::default(),
+            Err(e) => {
+                tracing::error!("{}", e);
+                self.load_failures.lock_or_recover().push(LoadFailure {
+                    path: path.to_path_buf(),
+                    error: e.to_string(),
+                });
+                T::default()
+            }
+        }
+    }
 }
 
 impl Default for ConfigManager {
@@ -113,3 +149,33 @@ impl Default for ConfigManager {
         Self::new().expect("Failed to create config manager")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_damaged_bookmarks_recovered_from_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_config_dir(temp_dir.path().to_path_buf()).unwrap();
+        let bookmark = Bookmark {
+            id: 1,
+            title: "Example".to_string(),
+            url: "https://example.com".to_string(),
+            favicon: None,
+            created_at: chrono::Utc::now(),
+            keyword: None,
+        };
+
+        config.save_bookmarks(std::slice::from_ref(&bookmark)).unwrap();
+        config.save_bookmarks(&[]).unwrap();
+        fs::write(temp_dir.path().join("bookmarks.json"), "[{\"id\": 1, \"ti").unwrap();
+
+        let bookmarks = config.load_bookmarks();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].url, bookmark.url);
+        assert_eq!(config.load_failures().len(), 1);
+        assert!(config.load_history().is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+// Settings Page
+use super::registry::{SettingKind, SettingValue, SettingView, SettingsPage};
+use crate::utils::escape_html;
+
+/// Address of the settings page
+pub const SETTINGS_PAGE_URL: &str = "webx://settings";
+
+/// Whether a URL is the settings page
+pub fn is_settings_page(url: &str) -> bool {
+    url.strip_prefix(SETTINGS_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Render every registered setting, one section per category. Controls send
+/// `type: 'settings'` IPC messages with an `action` of `set` (with `key` and
+/// `value`) or `reset` (with `key`). A rejected value is reported back by
+/// calling `webxSettingError(key, message)`.
+pub fn render_settings_page(pages: &[SettingsPage], pending_restart: &[String]) -> String {
+    let nav: String = pages
+        .iter()
+        .map(|page| format!(r##"<a href="#{}">{}</a>"##, anchor(&page.title), escape_html(&page.title)))
+        .collect();
+
+    let banner = if pending_restart.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<p class="restart">Restart WebX to apply changes to {} setting{}.</p>"#,
+            pending_restart.len(),
+            if pending_restart.len() == 1 { "" } else { "s" }
+        )
+    };
+
+    let managed = pages
+        .iter()
+        .flat_map(|page| page.sections.iter())
+        .any(|section| section.settings.iter().any(|view| view.locked));
+    let banner = if managed {
+        format!(
+            r#"{}<p class="restart">Some settings are managed by your organization. <a href="{}">See policies</a></p>"#,
+            banner,
+            super::policy::POLICY_PAGE_URL
+        )
+    } else {
+        banner
+    };
+
+    let body: String = pages
+        .iter()
+        .map(|page| {
+            let rows: String = page
+                .sections
+                .iter()
+                .flat_map(|section| section.settings.iter())
+                .map(render_setting)
+                .collect();
+            format!(
+                r#"<section id="{}"><h2>{}</h2>{}</section>"#,
+                anchor(&page.title),
+                escape_html(&page.title),
+                rows
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Settings</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Settings</h1><nav>{nav}</nav></header>
+    {banner}
+    {body}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        nav = nav,
+        banner = banner,
+        body = body,
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+nav a { margin-right: 1em; color: #1a73e8; text-decoration: none; font-size: 0.9em; }
+section { margin: 2em 0; }
+.setting { display: flex; align-items: flex-start; gap: 1em; padding: 0.7em 0; border-bottom: 1px solid #eee; }
+.setting .text { flex: 1; }
+.setting .description { display: block; color: #666; font-size: 0.85em; }
+.setting .tag { color: #b06000; font-size: 0.8em; margin-left: 0.5em; }
+.setting .error { display: block; color: #c5221f; font-size: 0.85em; }
+.setting input[type=text] { width: 16em; }
+.restart { background: #fef7e0; padding: 0.6em 0.8em; border-radius: 4px; }
+button.reset { font-size: 0.8em; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+function send(message) { window.ipc.send(Object.assign({ type: 'settings' }, message)); }
+function valueOf(control) {
+    if (control.type === 'checkbox') return control.checked;
+    if (control.type === 'number') return control.step === '1' ? parseInt(control.value, 10) : parseFloat(control.value);
+    return control.value;
+}
+document.addEventListener('change', (e) => {
+    const control = e.target.closest('[data-key]');
+    if (!control) return;
+    const value = valueOf(control);
+    if (typeof value === 'number' && isNaN(value)) {
+        webxSettingError(control.dataset.key, 'Enter a number');
+        return;
+    }
+    send({ action: 'set', key: control.dataset.key, value: value });
+});
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-reset]');
+    if (button) send({ action: 'reset', key: button.dataset.reset });
+});
+function webxSettingError(key, message) {
+    const error = document.querySelector('[data-error-for="' + CSS.escape(key) + '"]');
+    if (error) error.textContent = message;
+}
+"#;
+
+// Private helper functions
+
+fn render_setting(view: &SettingView) -> String {
+    let definition = &view.definition;
+    let key = escape_html(&definition.key);
+    // Settings fixed by policy are shown but cannot be changed
+    let disabled = if view.locked { " disabled" } else { "" };
+    let control = match (&definition.kind, &view.value) {
+        (SettingKind::Toggle, value) => format!(
+            r#"<input type="checkbox" data-key="{}"{}{}>"#,
+            key,
+            disabled,
+            if value.as_bool().unwrap_or(false) { " checked" } else { "" }
+        ),
+        (SettingKind::Integer { min, max }, value) => format!(
+            r#"<input type="number" data-key="{}"{} min="{}" max="{}" step="1" value="{}">"#,
+            key,
+            disabled,
+            min,
+            max,
+            value.as_i64().unwrap_or_default()
+        ),
+        (SettingKind::Float { min, max, step }, value) => format!(
+            r#"<input type="number" data-key="{}"{} min="{}" max="{}" step="{}" value="{}">"#,
+            key,
+            disabled,
+            min,
+            max,
+            step,
+            value.as_f64().unwrap_or_default()
+        ),
+        (SettingKind::Text { max_length }, value) => format!(
+            r#"<input type="text" data-key="{}"{} maxlength="{}" value="{}">"#,
+            key,
+            disabled,
+            max_length,
+            escape_html(value.as_str().unwrap_or_default())
+        ),
+        (SettingKind::Choice { options }, value) => {
+            let selected = value.as_str().unwrap_or_default();
+            let options: String = options
+                .iter()
+                .map(|option| {
+                    format!(
+                        r#"<option value="{}"{}>{}</option>"#,
+                        escape_html(&option.value),
+                        if option.value == selected { " selected" } else { "" },
+                        escape_html(&option.label)
+                    )
+                })
+                .collect();
+            format!(r#"<select data-key="{}"{}>{}</select>"#, key, disabled, options)
+        }
+    };
+
+    let tag = if view.locked {
+        r#"<span class="tag">Managed by your administrator</span>"#
+    } else if view.pending_restart {
+        r#"<span class="tag">Restart pending</span>"#
+    } else if definition.requires_restart {
+        r#"<span class="tag">Needs restart</span>"#
+    } else {
+        ""
+    };
+    let reset = if view.is_default || view.locked {
+        String::new()
+    } else {
+        format!(r#"<button class="reset" data-reset="{}" title="Default: {}">Reset</button>"#, key, escape_html(&display(&definition.default)))
+    };
+
+    format!(
+        r#"<div class="setting"><label class="text">{label}{tag}<span class="description">{description}</span><span class="error" data-error-for="{key}"></span></label>{control}{reset}</div>"#,
+        key = key,
+        label = escape_html(&definition.label),
+        tag = tag,
+        description = escape_html(&definition.description),
+        control = control,
+        reset = reset,
+    )
+}
+
+fn display(value: &SettingValue) -> String {
+    match value {
+        SettingValue::Bool(true) => "on".to_string(),
+        SettingValue::Bool(false) => "off".to_string(),
+        SettingValue::Integer(i) => i.to_string(),
+        SettingValue::Float(f) => f.to_string(),
+        SettingValue::String(s) if s.is_empty() => "empty".to_string(),
+        SettingValue::String(s) => s.clone(),
+    }
+}
+
+fn anchor(title: &str) -> String {
+    title.to_lowercase().replace(' ', "-")
+}
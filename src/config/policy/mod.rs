@@ -0,0 +1,395 @@
+// Administrator Policies
+//
+// Policy files are JSON or TOML documents in the system policy directory,
+// read in file name order:
+//
+//   {
+//     "mandatory": { "general.home_page": "https://intranet.example" },
+//     "recommended": { "general.default_zoom": 1.25 },
+//     "extensions": { "allowlist": ["reader"], "blocklist": ["*"] },
+//     "proxy": { "mode": "fixed", "server": "http://proxy.example:3128", "bypass": ["<local>"] },
+//     "disabled_features": ["devtools"]
+//   }
+//
+// Precedence, highest first: mandatory policies, which also lock the setting;
+// the user's own value; recommended policies, which replace the built-in
+// default; the built-in default. When two files set the same policy the later
+// file wins, and the clash is reported on webx://policy.
+pub mod page;
+
+pub use page::{is_policy_page, render_policy_page, POLICY_PAGE_URL};
+
+use super::registry::SettingValue;
+use crate::error::WebxError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where administrators put policy files
+#[cfg(unix)]
+pub const SYSTEM_POLICY_DIR: &str = "/etc/webx/policies.d";
+#[cfg(not(unix))]
+pub const SYSTEM_POLICY_DIR: &str = "C:\\ProgramData\\WebX\\policies.d";
+
+/// Features `disabled_features` can turn off, with what they are called on
+/// the policy page
+pub const POLICY_FEATURES: &[(&str, &str)] = &[
+    ("devtools", "Developer tools"),
+    ("backup", "WebDAV backups"),
+    ("translate", "Page translation"),
+];
+
+/// How strongly a settings policy applies
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyLevel {
+    /// Overrides and locks the user's setting
+    Mandatory,
+    /// Replaces the built-in default; the user may still change it
+    Recommended,
+}
+
+/// Which extensions may be installed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExtensionPolicy {
+    /// Extension IDs that may always be installed
+    pub allowlist: Vec<String>,
+    /// Extension IDs that may not be installed; "*" blocks all but the allowlist
+    pub blocklist: Vec<String>,
+}
+
+impl ExtensionPolicy {
+    /// Whether an extension may be installed
+    pub fn allows(&self, id: &str) -> bool {
+        if self.allowlist.iter().any(|allowed| allowed == id) {
+            return true;
+        }
+        !self.blocklist.iter().any(|blocked| blocked == id || blocked == "*")
+    }
+}
+
+/// How the administrator has the browser connect
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyPolicyMode {
+    Direct,
+    System,
+    Fixed,
+    Pac,
+}
+
+/// Proxy set by the administrator, replacing the user's proxy settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProxyPolicy {
+    pub mode: ProxyPolicyMode,
+    /// Proxy URL for the fixed mode, e.g. "http://proxy.example:3128" or
+    /// "socks5://proxy.example:1080"
+    #[serde(default)]
+    pub server: Option<String>,
+    /// PAC file for the pac mode
+    #[serde(default)]
+    pub pac_url: Option<String>,
+    /// Hosts reached directly in the fixed mode, in the proxy bypass syntax
+    #[serde(default)]
+    pub bypass: Vec<String>,
+}
+
+/// A settings policy and the file it came from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingPolicy {
+    pub key: String,
+    pub value: SettingValue,
+    pub level: PolicyLevel,
+    pub source: PathBuf,
+}
+
+/// Problem found while reading policy files
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyIssue {
+    pub source: PathBuf,
+    pub message: String,
+}
+
+/// One policy file as written by the administrator
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    mandatory: BTreeMap<String, SettingValue>,
+    recommended: BTreeMap<String, SettingValue>,
+    extensions: Option<ExtensionPolicy>,
+    proxy: Option<ProxyPolicy>,
+    disabled_features: Vec<String>,
+}
+
+/// Policies from every file in the policy directory, merged
+#[derive(Debug, Clone, Default)]
+pub struct PolicySet {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+    settings: BTreeMap<(String, PolicyLevel), SettingPolicy>,
+    extensions: Option<(ExtensionPolicy, PathBuf)>,
+    proxy: Option<(ProxyPolicy, PathBuf)>,
+    disabled_features: BTreeMap<String, PathBuf>,
+    issues: Vec<PolicyIssue>,
+}
+
+impl PolicySet {
+    /// Read the policy directory, the system one if `dir` is None. A missing
+    /// directory means no policies; unreadable files are reported as issues
+    /// and skipped.
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        let dir = dir.unwrap_or_else(|| PathBuf::from(SYSTEM_POLICY_DIR));
+        let mut set = Self {
+            dir: dir.clone(),
+            ..Self::default()
+        };
+
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "toml")))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                set.issue(&dir, format!("Cannot read the policy directory: {}", e));
+                Vec::new()
+            }
+        };
+        paths.sort();
+
+        for path in paths {
+            match parse_file(&path) {
+                Ok(file) => {
+                    set.merge(file, &path);
+                    set.files.push(path);
+                }
+                Err(e) => set.issue(&path, format!("Ignored: {}", e)),
+            }
+        }
+        set
+    }
+
+    /// Directory the policies were read from
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Policy files that were read, in the order they were applied
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Whether any policy applies
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+            && self.extensions.is_none()
+            && self.proxy.is_none()
+            && self.disabled_features.is_empty()
+    }
+
+    /// Settings policies, mandatory before recommended for each key
+    pub fn settings(&self) -> Vec<SettingPolicy> {
+        self.settings.values().cloned().collect()
+    }
+
+    /// Mandatory setting values by key
+    pub fn mandatory_settings(&self) -> BTreeMap<String, SettingValue> {
+        self.settings_at(PolicyLevel::Mandatory)
+    }
+
+    /// Recommended setting values by key
+    pub fn recommended_settings(&self) -> BTreeMap<String, SettingValue> {
+        self.settings_at(PolicyLevel::Recommended)
+    }
+
+    pub fn extensions(&self) -> Option<&ExtensionPolicy> {
+        self.extensions.as_ref().map(|(policy, _)| policy)
+    }
+
+    /// Whether an extension may be installed; any extension may without a policy
+    pub fn extension_allowed(&self, id: &str) -> bool {
+        self.extensions().is_none_or(|policy| policy.allows(id))
+    }
+
+    pub fn proxy(&self) -> Option<&ProxyPolicy> {
+        self.proxy.as_ref().map(|(policy, _)| policy)
+    }
+
+    /// Whether the administrator turned a feature off
+    pub fn feature_disabled(&self, feature: &str) -> bool {
+        self.disabled_features.contains_key(feature)
+    }
+
+    /// Disabled features and the file that disabled each
+    pub fn disabled_features(&self) -> &BTreeMap<String, PathBuf> {
+        &self.disabled_features
+    }
+
+    /// File a section ("extensions" or "proxy") was taken from
+    pub fn source_of(&self, section: &str) -> Option<&Path> {
+        match section {
+            "extensions" => self.extensions.as_ref().map(|(_, source)| source.as_path()),
+            "proxy" => self.proxy.as_ref().map(|(_, source)| source.as_path()),
+            _ => None,
+        }
+    }
+
+    /// Unreadable files, unknown features and policies that replaced others
+    pub fn issues(&self) -> &[PolicyIssue] {
+        &self.issues
+    }
+
+    // Private helper methods
+
+    fn merge(&mut self, file: PolicyFile, source: &Path) {
+        let levels = [
+            (PolicyLevel::Mandatory, file.mandatory),
+            (PolicyLevel::Recommended, file.recommended),
+        ];
+        for (level, values) in levels {
+            for (key, value) in values {
+                let policy = SettingPolicy {
+                    key: key.clone(),
+                    value,
+                    level,
+                    source: source.to_path_buf(),
+                };
+                if let Some(previous) = self.settings.insert((key.clone(), level), policy) {
+                    self.issue(source, format!("Replaces {} from {}", key, previous.source.display()));
+                }
+            }
+        }
+
+        if let Some(extensions) = file.extensions {
+            if let Some((_, previous)) = self.extensions.replace((extensions, source.to_path_buf())) {
+                self.issue(source, format!("Replaces the extension policy from {}", previous.display()));
+            }
+        }
+        if let Some(proxy) = file.proxy {
+            if let Err(e) = validate_proxy(&proxy) {
+                self.issue(source, format!("Proxy policy ignored: {}", e));
+            } else if let Some((_, previous)) = self.proxy.replace((proxy, source.to_path_buf())) {
+                self.issue(source, format!("Replaces the proxy policy from {}", previous.display()));
+            }
+        }
+        for feature in file.disabled_features {
+            if POLICY_FEATURES.iter().any(|(name, _)| *name == feature) {
+                self.disabled_features.entry(feature).or_insert_with(|| source.to_path_buf());
+            } else {
+                self.issue(source, format!("Unknown feature {} cannot be disabled", feature));
+            }
+        }
+    }
+
+    fn settings_at(&self, level: PolicyLevel) -> BTreeMap<String, SettingValue> {
+        self.settings
+            .values()
+            .filter(|policy| policy.level == level)
+            .map(|policy| (policy.key.clone(), policy.value.clone()))
+            .collect()
+    }
+
+    fn issue(&mut self, source: &Path, message: String) {
+        tracing::warn!("Policy {}: {}", source.display(), message);
+        self.issues.push(PolicyIssue {
+            source: source.to_path_buf(),
+            message,
+        });
+    }
+}
+
+// Private helper functions
+
+fn parse_file(path: &Path) -> Result<PolicyFile, WebxError> {
+    let content = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(toml::from_str(&content)?),
+        _ => Ok(serde_json::from_str(&content)?),
+    }
+}
+
+fn validate_proxy(policy: &ProxyPolicy) -> Result<(), WebxError> {
+    match policy.mode {
+        ProxyPolicyMode::Fixed => {
+            let server = policy.server.as_deref().ok_or("the fixed mode needs a server")?;
+            let url = url::Url::parse(server)?;
+            if !matches!(url.scheme(), "http" | "https" | "socks4" | "socks5") || url.host_str().is_none() {
+                return Err(format!("{} is not an http, https, socks4 or socks5 proxy URL", server).into());
+            }
+            if url.port().is_none() {
+                return Err(format!("{} has no port", server).into());
+            }
+        }
+        ProxyPolicyMode::Pac => {
+            let pac_url = policy.pac_url.as_deref().ok_or("the pac mode needs a pac_url")?;
+            url::Url::parse(pac_url)?;
+        }
+        ProxyPolicyMode::Direct | ProxyPolicyMode::System => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_later_files_win_and_issues_are_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("10-base.json"),
+            r#"{
+                "mandatory": { "general.home_page": "https://intranet.example" },
+                "recommended": { "general.default_zoom": 1.25 },
+                "extensions": { "blocklist": ["*"], "allowlist": ["reader"] },
+                "disabled_features": ["devtools", "teleport"]
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("20-site.toml"),
+            r#"
+disabled_features = ["backup"]
+
+[mandatory]
+"general.home_page" = "https://portal.example"
+
+[proxy]
+mode = "fixed"
+server = "http://proxy.example:3128"
+bypass = ["<local>"]
+"#,
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("30-broken.json"), "{ not json").unwrap();
+        std::fs::write(temp_dir.path().join("README"), "not a policy").unwrap();
+
+        let policies = PolicySet::load(Some(temp_dir.path().to_path_buf()));
+        assert_eq!(policies.files().len(), 2);
+        assert_eq!(
+            policies.mandatory_settings().get("general.home_page"),
+            Some(&SettingValue::String("https://portal.example".to_string()))
+        );
+        assert_eq!(
+            policies.recommended_settings().get("general.default_zoom"),
+            Some(&SettingValue::Float(1.25))
+        );
+        assert!(policies.extension_allowed("reader"));
+        assert!(!policies.extension_allowed("coupons"));
+        assert_eq!(policies.proxy().map(|p| p.mode), Some(ProxyPolicyMode::Fixed));
+        assert!(policies.feature_disabled("devtools"));
+        assert!(policies.feature_disabled("backup"));
+        assert!(!policies.feature_disabled("translate"));
+
+        // The replaced home page, the unknown feature and the broken file
+        let messages: Vec<&str> = policies.issues().iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages.iter().any(|m| m.starts_with("Replaces general.home_page")));
+        assert!(messages.iter().any(|m| m.contains("teleport")));
+        assert!(messages.iter().any(|m| m.starts_with("Ignored")));
+
+        assert!(PolicySet::load(Some(temp_dir.path().join("missing"))).is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+// Policy Report Page
+use super::{PolicyLevel, PolicySet, ProxyPolicyMode, POLICY_FEATURES};
+use crate::config::registry::{SettingValue, SettingsRegistry};
+use crate::utils::escape_html;
+
+/// Address of the policy report page
+pub const POLICY_PAGE_URL: &str = "webx://policy";
+
+/// Whether a URL is the policy report page
+pub fn is_policy_page(url: &str) -> bool {
+    url.strip_prefix(POLICY_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Render the policies in effect, where each came from, and whether it
+/// applied. Read-only: nothing on the page changes a policy.
+pub fn render_policy_page(policies: &PolicySet, registry: &SettingsRegistry) -> String {
+    let summary = if policies.is_empty() {
+        format!(
+            r#"<p class="empty">No policies are set. Administrators can add JSON or TOML files to {}.</p>"#,
+            escape_html(&policies.dir().display().to_string())
+        )
+    } else {
+        let files: String = policies
+            .files()
+            .iter()
+            .map(|file| format!("<li><code>{}</code></li>", escape_html(&file.display().to_string())))
+            .collect();
+        format!(
+            r#"<p>Your browser is managed by your organization. Policies were read from, in order:</p><ul>{}</ul>"#,
+            files
+        )
+    };
+
+    let mandatory = policies.mandatory_settings();
+    let rows: String = policies
+        .settings()
+        .iter()
+        .map(|policy| {
+            let status = match (registry.validate(&policy.key, &policy.value), policy.level) {
+                (Err(e), _) => format!(r#"<span class="bad">Ignored: {}</span>"#, escape_html(&e.to_string())),
+                (Ok(_), PolicyLevel::Mandatory) => "Enforced".to_string(),
+                (Ok(_), PolicyLevel::Recommended)
+                    if mandatory
+                        .get(&policy.key)
+                        .is_some_and(|value| registry.validate(&policy.key, value).is_ok()) =>
+                {
+                    "Overridden by a mandatory policy".to_string()
+                }
+                (Ok(_), PolicyLevel::Recommended) => "Default, can be changed".to_string(),
+            };
+            let label = registry
+                .definition(&policy.key)
+                .map(|definition| definition.label)
+                .unwrap_or_default();
+            format!(
+                "<tr><td><code>{}</code><br>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&policy.key),
+                escape_html(&label),
+                escape_html(&display(&policy.value)),
+                match policy.level {
+                    PolicyLevel::Mandatory => "Mandatory",
+                    PolicyLevel::Recommended => "Recommended",
+                },
+                escape_html(&file_name(&policy.source)),
+                status
+            )
+        })
+        .collect();
+    let settings = if rows.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<h2>Settings</h2><table><thead><tr><th>Setting</th><th>Value</th><th>Level</th><th>File</th><th>Status</th></tr></thead><tbody>{}</tbody></table>"#,
+            rows
+        )
+    };
+
+    let extensions = policies
+        .extensions()
+        .map(|policy| {
+            let list = |ids: &[String]| {
+                if ids.is_empty() {
+                    "none".to_string()
+                } else {
+                    escape_html(&ids.join(", "))
+                }
+            };
+            format!(
+                "<h2>Extensions</h2><p>Always allowed: {}<br>Blocked: {}</p><p class=\"source\">From {}</p>",
+                list(&policy.allowlist),
+                list(&policy.blocklist),
+                escape_html(&policies.source_of("extensions").map(file_name).unwrap_or_default())
+            )
+        })
+        .unwrap_or_default();
+
+    let proxy = policies
+        .proxy()
+        .map(|policy| {
+            let description = match policy.mode {
+                ProxyPolicyMode::Direct => "Connect directly, without a proxy".to_string(),
+                ProxyPolicyMode::System => "Use the system proxy settings".to_string(),
+                ProxyPolicyMode::Fixed => format!("Use {}", policy.server.as_deref().unwrap_or_default()),
+                ProxyPolicyMode::Pac => format!("Use the PAC file {}", policy.pac_url.as_deref().unwrap_or_default()),
+            };
+            let bypass = if policy.bypass.is_empty() {
+                String::new()
+            } else {
+                format!("<br>Direct for: {}", escape_html(&policy.bypass.join(", ")))
+            };
+            format!(
+                "<h2>Proxy</h2><p>{}{}</p><p class=\"source\">From {}. Proxy settings cannot be changed.</p>",
+                escape_html(&description),
+                bypass,
+                escape_html(&policies.source_of("proxy").map(file_name).unwrap_or_default())
+            )
+        })
+        .unwrap_or_default();
+
+    let disabled: String = policies
+        .disabled_features()
+        .iter()
+        .map(|(feature, source)| {
+            let name = POLICY_FEATURES
+                .iter()
+                .find(|(name, _)| name == feature)
+                .map(|(_, label)| *label)
+                .unwrap_or(feature);
+            format!("<li>{} <span class=\"source\">({})</span></li>", escape_html(name), escape_html(&file_name(source)))
+        })
+        .collect();
+    let disabled = if disabled.is_empty() {
+        String::new()
+    } else {
+        format!("<h2>Turned off</h2><ul>{}</ul>", disabled)
+    };
+
+    let issues: String = policies
+        .issues()
+        .iter()
+        .map(|issue| {
+            format!(
+                "<li><code>{}</code>: {}</li>",
+                escape_html(&file_name(&issue.source)),
+                escape_html(&issue.message)
+            )
+        })
+        .collect();
+    let issues = if issues.is_empty() {
+        String::new()
+    } else {
+        format!(r#"<h2>Problems</h2><ul class="bad">{}</ul>"#, issues)
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Policies</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Policies</h1></header>
+    {summary}
+    <p class="source">Mandatory policies override your settings and lock them. Recommended policies replace the
+    defaults; you can still change them. When two files set the same policy, the later file wins.</p>
+    {settings}
+    {extensions}
+    {proxy}
+    {disabled}
+    {issues}
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        summary = summary,
+        settings = settings,
+        extensions = extensions,
+        proxy = proxy,
+        disabled = disabled,
+        issues = issues,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 860px; margin: 2em auto; padding: 0 1em; color: #222; }
+table { width: 100%; border-collapse: collapse; font-size: 0.9em; }
+th, td { text-align: left; vertical-align: top; padding: 0.4em 0.5em; border-bottom: 1px solid #eee; }
+.source { color: #666; font-size: 0.85em; }
+.bad { color: #c5221f; }
+.empty { color: #888; }
+"#;
+
+// Private helper functions
+
+fn display(value: &SettingValue) -> String {
+    match value {
+        SettingValue::Bool(true) => "on".to_string(),
+        SettingValue::Bool(false) => "off".to_string(),
+        SettingValue::Integer(i) => i.to_string(),
+        SettingValue::Float(f) => f.to_string(),
+        SettingValue::String(s) => format!("\"{}\"", s),
+    }
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
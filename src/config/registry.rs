@@ -0,0 +1,780 @@
+// Declarative Settings Registry
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use super::policy::PolicySet;
+use super::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// A setting value as stored and shown in the settings UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum SettingValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl SettingValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SettingValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            SettingValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SettingValue::Float(f) => Some(*f),
+            SettingValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            SettingValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Type and allowed range of a setting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+    Toggle,
+    Integer { min: i64, max: i64 },
+    Float { min: f64, max: f64, step: f64 },
+    Text { max_length: usize },
+    Choice { options: Vec<SettingOption> },
+}
+
+/// One option of a choice setting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingOption {
+    pub value: String,
+    pub label: String,
+}
+
+/// Settings page a setting is shown on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SettingCategory {
+    General,
+    Appearance,
+    Privacy,
+    Security,
+    Downloads,
+    Languages,
+    Network,
+    Advanced,
+}
+
+impl SettingCategory {
+    pub fn title(&self) -> &'static str {
+        match self {
+            SettingCategory::General => "General",
+            SettingCategory::Appearance => "Appearance",
+            SettingCategory::Privacy => "Privacy",
+            SettingCategory::Security => "Security",
+            SettingCategory::Downloads => "Downloads",
+            SettingCategory::Languages => "Languages",
+            SettingCategory::Network => "Network",
+            SettingCategory::Advanced => "Advanced",
+        }
+    }
+}
+
+/// Declaration of a single setting by its owning module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingDefinition {
+    /// Dotted key, prefixed with the owning module (e.g. "spellcheck.language")
+    pub key: String,
+    pub label: String,
+    pub description: String,
+    pub category: SettingCategory,
+    pub kind: SettingKind,
+    pub default: SettingValue,
+    pub requires_restart: bool,
+}
+
+impl SettingDefinition {
+    /// On/off setting
+    pub fn toggle(key: &str, label: &str, default: bool) -> Self {
+        Self::new(key, label, SettingKind::Toggle, SettingValue::Bool(default))
+    }
+
+    /// Whole number within an inclusive range
+    pub fn integer(key: &str, label: &str, default: i64, min: i64, max: i64) -> Self {
+        Self::new(key, label, SettingKind::Integer { min, max }, SettingValue::Integer(default))
+    }
+
+    /// Decimal number within an inclusive range
+    pub fn float(key: &str, label: &str, default: f64, min: f64, max: f64, step: f64) -> Self {
+        Self::new(key, label, SettingKind::Float { min, max, step }, SettingValue::Float(default))
+    }
+
+    /// Free-form text
+    pub fn text(key: &str, label: &str, default: &str) -> Self {
+        Self::new(
+            key,
+            label,
+            SettingKind::Text { max_length: 2048 },
+            SettingValue::String(default.to_string()),
+        )
+    }
+
+    /// One of a fixed set of values, given as (value, label) pairs
+    pub fn choice(key: &str, label: &str, default: &str, options: &[(&str, &str)]) -> Self {
+        let options = options
+            .iter()
+            .map(|(value, label)| SettingOption {
+                value: value.to_string(),
+                label: label.to_string(),
+            })
+            .collect();
+        Self::new(key, label, SettingKind::Choice { options }, SettingValue::String(default.to_string()))
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_category(mut self, category: SettingCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_restart_required(mut self) -> Self {
+        self.requires_restart = true;
+        self
+    }
+
+    /// Check a value against this setting's type and range
+    pub fn validate(&self, value: &SettingValue) -> Result<SettingValue, String> {
+        match (&self.kind, value) {
+            (SettingKind::Toggle, SettingValue::Bool(_)) => Ok(value.clone()),
+            (SettingKind::Integer { min, max }, SettingValue::Integer(i)) => {
+                if i < min || i > max {
+                    Err(format!("{} must be between {} and {}", self.key, min, max))
+                } else {
+                    Ok(value.clone())
+                }
+            }
+            (SettingKind::Float { min, max, .. }, v) => match v.as_f64() {
+                Some(f) if f.is_finite() && f >= *min && f <= *max => Ok(SettingValue::Float(f)),
+                Some(_) => Err(format!("{} must be between {} and {}", self.key, min, max)),
+                None => Err(format!("{} expects a number", self.key)),
+            },
+            (SettingKind::Text { max_length }, SettingValue::String(s)) => {
+                if s.chars().count() > *max_length {
+                    Err(format!("{} is longer than {} characters", self.key, max_length))
+                } else {
+                    Ok(value.clone())
+                }
+            }
+            (SettingKind::Choice { options }, SettingValue::String(s)) => {
+                if options.iter().any(|o| &o.value == s) {
+                    Ok(value.clone())
+                } else {
+                    Err(format!("{} is not a valid option for {}", s, self.key))
+                }
+            }
+            _ => Err(format!("Wrong value type for {}", self.key)),
+        }
+    }
+
+    fn new(key: &str, label: &str, kind: SettingKind, default: SettingValue) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            description: String::new(),
+            category: SettingCategory::General,
+            kind,
+            default,
+            requires_restart: false,
+        }
+    }
+}
+
+/// Implemented by feature modules that own settings
+pub trait SettingsProvider: Send + Sync {
+    /// Module name; every key this provider declares starts with "<module>."
+    fn module(&self) -> &str;
+
+    /// Settings the module exposes
+    fn settings(&self) -> Vec<SettingDefinition>;
+
+    /// Apply a validated value. Called on registration for stored values and
+    /// on every change afterwards.
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError>;
+
+    /// Value in use, for modules that store a setting themselves. The
+    /// registry then leaves storing it to the module.
+    fn current_value(&self, _key: &str) -> Option<SettingValue> {
+        None
+    }
+}
+
+/// Setting with its current value, as shown in the settings UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingView {
+    pub definition: SettingDefinition,
+    pub value: SettingValue,
+    pub is_default: bool,
+    pub pending_restart: bool,
+    /// Set by an administrator policy and not changeable
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// Group of settings from one module on a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsSection {
+    pub module: String,
+    pub settings: Vec<SettingView>,
+}
+
+/// One settings page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsPage {
+    pub category: SettingCategory,
+    pub title: String,
+    pub sections: Vec<SettingsSection>,
+}
+
+/// Setting change notifications
+#[derive(Debug, Clone)]
+pub enum SettingsEvent {
+    Changed {
+        key: String,
+        value: SettingValue,
+        requires_restart: bool,
+    },
+    Reset(String),
+}
+
+struct RegisteredModule {
+    provider: Arc<dyn SettingsProvider>,
+    definitions: Vec<SettingDefinition>,
+}
+
+/// Central registry that owns setting values and routes changes to modules
+pub struct SettingsRegistry {
+    modules: Arc<Mutex<BTreeMap<String, RegisteredModule>>>,
+    values: Arc<Mutex<BTreeMap<String, SettingValue>>>,
+    pending_restart: Arc<Mutex<HashSet<String>>>,
+    store_path: PathBuf,
+    /// Change listeners; closed ones are dropped on the next event
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<SettingsEvent>>>>,
+    /// Administrator values that override and lock settings
+    mandatory: Arc<Mutex<BTreeMap<String, SettingValue>>>,
+    /// Administrator values that replace the built-in defaults
+    recommended: Arc<Mutex<BTreeMap<String, SettingValue>>>,
+}
+
+impl SettingsRegistry {
+    /// Create new settings registry
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        let registry = Self {
+            modules: Arc::new(Mutex::new(BTreeMap::new())),
+            values: Arc::new(Mutex::new(BTreeMap::new())),
+            pending_restart: Arc::new(Mutex::new(HashSet::new())),
+            store_path: config_dir.join("settings_registry.json"),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            mandatory: Arc::new(Mutex::new(BTreeMap::new())),
+            recommended: Arc::new(Mutex::new(BTreeMap::new())),
+        };
+
+        registry.load_values()?;
+
+        Ok(registry)
+    }
+
+    /// Register a module's settings and apply any stored values to it
+    pub fn register(&self, provider: Arc<dyn SettingsProvider>) -> Result<(), WebxError> {
+        let module = provider.module().to_string();
+        let definitions = provider.settings();
+        let prefix = format!("{}.", module);
+
+        {
+            let modules = self.modules.lock_or_recover();
+            if modules.contains_key(&module) {
+                return Err(format!("Settings module {} is already registered", module).into());
+            }
+            for definition in &definitions {
+                if !definition.key.starts_with(&prefix) {
+                    return Err(format!("Setting {} does not belong to module {}", definition.key, module).into());
+                }
+                definition.validate(&definition.default)?;
+            }
+        }
+
+        // Stored values that no longer validate (e.g. a range changed) are dropped
+        for definition in &definitions {
+            let stored = self.values.lock_or_recover().get(&definition.key).cloned();
+            if let Some(value) = stored {
+                match definition.validate(&value) {
+                    Ok(value) => provider.apply_setting(&definition.key, &value)?,
+                    Err(e) => {
+                        tracing::warn!("Discarding stored setting: {}", e);
+                        self.values.lock_or_recover().remove(&definition.key);
+                    }
+                }
+            }
+        }
+
+        self.apply_policies(provider.as_ref(), &definitions)?;
+
+        self.modules
+            .lock_or_recover()
+            .insert(module, RegisteredModule { provider, definitions });
+        Ok(())
+    }
+
+    /// Apply administrator policies, to registered modules now and to later
+    /// ones as they register. Policy values that do not validate are ignored.
+    pub fn set_policies(&self, policies: &PolicySet) -> Result<(), WebxError> {
+        *self.mandatory.lock_or_recover() = policies.mandatory_settings();
+        *self.recommended.lock_or_recover() = policies.recommended_settings();
+
+        let modules: Vec<(Arc<dyn SettingsProvider>, Vec<SettingDefinition>)> = self
+            .modules
+            .lock_or_recover()
+            .values()
+            .map(|m| (Arc::clone(&m.provider), m.definitions.clone()))
+            .collect();
+        for (provider, definitions) in modules {
+            self.apply_policies(provider.as_ref(), &definitions)?;
+        }
+        Ok(())
+    }
+
+    /// Whether an administrator policy fixes a setting's value
+    pub fn is_locked(&self, key: &str) -> bool {
+        self.definition(key)
+            .is_some_and(|definition| self.mandatory_value(&definition).is_some())
+    }
+
+    /// Definition of a registered setting
+    pub fn definition(&self, key: &str) -> Option<SettingDefinition> {
+        let modules = self.modules.lock_or_recover();
+        modules
+            .values()
+            .flat_map(|m| m.definitions.iter())
+            .find(|d| d.key == key)
+            .cloned()
+    }
+
+    /// Current value of a setting (its default if never changed)
+    pub fn get(&self, key: &str) -> Option<SettingValue> {
+        let (provider, definition) = self.lookup(key).ok()?;
+        Some(self.value_of(provider.as_ref(), &definition))
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|v| v.as_bool())
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(|v| v.as_i64())
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(|v| v.as_f64())
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<String> {
+        self.get(key).and_then(|v| v.as_str().map(str::to_string))
+    }
+
+    /// Check a value against a setting's type and range without applying it
+    pub fn validate(&self, key: &str, value: &SettingValue) -> Result<SettingValue, WebxError> {
+        let (_, definition) = self.lookup(key)?;
+        Ok(definition.validate(value)?)
+    }
+
+    /// Validate, persist and route a new value to the owning module
+    pub fn set(&self, key: &str, value: SettingValue) -> Result<(), WebxError> {
+        let (provider, definition) = self.lookup(key)?;
+        if self.mandatory_value(&definition).is_some() {
+            return Err(WebxError::Invalid(format!("{} is managed by your administrator", definition.label)));
+        }
+        let value = definition.validate(&value)?;
+
+        provider.apply_setting(key, &value)?;
+
+        {
+            let mut values = self.values.lock_or_recover();
+            if value == self.default_of(&definition) || provider.current_value(key).is_some() {
+                values.remove(key);
+            } else {
+                values.insert(key.to_string(), value.clone());
+            }
+        }
+        self.save_values()?;
+
+        if definition.requires_restart {
+            self.pending_restart.lock_or_recover().insert(key.to_string());
+        }
+
+        self.notify(SettingsEvent::Changed {
+            key: key.to_string(),
+            value,
+            requires_restart: definition.requires_restart,
+        });
+        Ok(())
+    }
+
+    /// Set several values, applying none unless all of them validate
+    pub fn set_many(&self, updates: &[(String, SettingValue)]) -> Result<(), WebxError> {
+        for (key, value) in updates {
+            self.validate(key, value)?;
+        }
+        for (key, value) in updates {
+            self.set(key, value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Restore a setting to its default
+    pub fn reset(&self, key: &str) -> Result<(), WebxError> {
+        let (_, definition) = self.lookup(key)?;
+        self.set(key, self.default_of(&definition))?;
+        self.notify(SettingsEvent::Reset(key.to_string()));
+        Ok(())
+    }
+
+    /// Restore every setting of a module to its default
+    pub fn reset_module(&self, module: &str) -> Result<(), WebxError> {
+        let keys: Vec<String> = {
+            let modules = self.modules.lock_or_recover();
+            let registered = modules.get(module).ok_or_else(|| WebxError::NotFound(format!("Settings module {}", module)))?;
+            registered.definitions.iter().map(|d| d.key.clone()).collect()
+        };
+        for key in keys.into_iter().filter(|key| !self.is_locked(key)) {
+            self.reset(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Settings changed since startup that only take effect after a restart
+    pub fn pending_restart(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.pending_restart.lock_or_recover().iter().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Settings UI data: one page per category, one section per module
+    pub fn pages(&self) -> Vec<SettingsPage> {
+        let mut pages: BTreeMap<SettingCategory, BTreeMap<String, Vec<SettingView>>> = BTreeMap::new();
+
+        for (module, provider, definition) in self.all_definitions() {
+            let view = self.view(provider.as_ref(), definition);
+            pages
+                .entry(view.definition.category)
+                .or_default()
+                .entry(module)
+                .or_default()
+                .push(view);
+        }
+
+        pages
+            .into_iter()
+            .map(|(category, sections)| SettingsPage {
+                category,
+                title: category.title().to_string(),
+                sections: sections
+                    .into_iter()
+                    .map(|(module, settings)| SettingsSection { module, settings })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Settings whose label, description or key match a search query
+    pub fn search(&self, query: &str) -> Vec<SettingView> {
+        let query = query.to_lowercase();
+        self.all_definitions()
+            .into_iter()
+            .filter(|(_, _, d)| {
+                d.key.to_lowercase().contains(&query)
+                    || d.label.to_lowercase().contains(&query)
+                    || d.description.to_lowercase().contains(&query)
+            })
+            .map(|(_, provider, d)| self.view(provider.as_ref(), d))
+            .collect()
+    }
+
+    /// Subscribe to setting changes. Each subscriber gets every change made
+    /// after it subscribed.
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<SettingsEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock_or_recover().push(tx);
+        rx
+    }
+
+    // Private helper methods
+
+    fn lookup(&self, key: &str) -> Result<(Arc<dyn SettingsProvider>, SettingDefinition), WebxError> {
+        let modules = self.modules.lock_or_recover();
+        modules
+            .values()
+            .find_map(|m| {
+                m.definitions
+                    .iter()
+                    .find(|d| d.key == key)
+                    .map(|d| (m.provider.clone(), d.clone()))
+            })
+            .ok_or_else(|| WebxError::NotFound(format!("Setting {}", key)))
+    }
+
+    fn all_definitions(&self) -> Vec<(String, Arc<dyn SettingsProvider>, SettingDefinition)> {
+        let modules = self.modules.lock_or_recover();
+        modules
+            .iter()
+            .flat_map(|(module, m)| {
+                m.definitions
+                    .iter()
+                    .map(move |d| (module.clone(), Arc::clone(&m.provider), d.clone()))
+            })
+            .collect()
+    }
+
+    /// Mandatory policy value, else the stored value, else the module's
+    /// own, else the default
+    fn value_of(&self, provider: &dyn SettingsProvider, definition: &SettingDefinition) -> SettingValue {
+        if let Some(value) = self.mandatory_value(definition) {
+            return value;
+        }
+        let stored = self.values.lock_or_recover().get(&definition.key).cloned();
+        stored
+            .or_else(|| provider.current_value(&definition.key))
+            .unwrap_or_else(|| self.default_of(definition))
+    }
+
+    /// Built-in default, unless a recommended policy replaces it
+    fn default_of(&self, definition: &SettingDefinition) -> SettingValue {
+        self.recommended
+            .lock_or_recover()
+            .get(&definition.key)
+            .and_then(|value| definition.validate(value).ok())
+            .unwrap_or_else(|| definition.default.clone())
+    }
+
+    fn mandatory_value(&self, definition: &SettingDefinition) -> Option<SettingValue> {
+        self.mandatory
+            .lock_or_recover()
+            .get(&definition.key)
+            .and_then(|value| definition.validate(value).ok())
+    }
+
+    /// Push policy values to a module: mandatory ones always, recommended
+    /// ones where the user has not changed the setting
+    fn apply_policies(&self, provider: &dyn SettingsProvider, definitions: &[SettingDefinition]) -> Result<(), WebxError> {
+        for definition in definitions {
+            if let Some(value) = self.mandatory_value(definition) {
+                provider.apply_setting(&definition.key, &value)?;
+                continue;
+            }
+            let recommended = self.default_of(definition);
+            let untouched = !self.values.lock_or_recover().contains_key(&definition.key)
+                && provider
+                    .current_value(&definition.key)
+                    .is_none_or(|current| current == definition.default);
+            if recommended != definition.default && untouched {
+                provider.apply_setting(&definition.key, &recommended)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn view(&self, provider: &dyn SettingsProvider, definition: SettingDefinition) -> SettingView {
+        let value = self.value_of(provider, &definition);
+        let pending_restart = self.pending_restart.lock_or_recover().contains(&definition.key);
+        SettingView {
+            is_default: value == self.default_of(&definition),
+            locked: self.mandatory_value(&definition).is_some(),
+            value,
+            pending_restart,
+            definition,
+        }
+    }
+
+    fn notify(&self, event: SettingsEvent) {
+        self.subscribers
+            .lock_or_recover()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn save_values(&self) -> Result<(), WebxError> {
+        let values = self.values.lock_or_recover();
+        storage::save_json(&self.store_path, &*values, storage::DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+
+    fn load_values(&self) -> Result<(), WebxError> {
+        if let Some(loaded) = storage::load_json(&self.store_path, storage::DEFAULT_BACKUP_COUNT)? {
+            for failure in &loaded.failures {
+                tracing::warn!("Failed to load {}", failure);
+            }
+            *self.values.lock_or_recover() = loaded.value;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct TestModule {
+        applied: Mutex<Vec<(String, SettingValue)>>,
+    }
+
+    impl SettingsProvider for TestModule {
+        fn module(&self) -> &str {
+            "test"
+        }
+
+        fn settings(&self) -> Vec<SettingDefinition> {
+            vec![
+                SettingDefinition::toggle("test.enabled", "Enabled", true),
+                SettingDefinition::integer("test.cache_mb", "Cache size", 256, 16, 4096)
+                    .with_category(SettingCategory::Advanced)
+                    .with_restart_required(),
+                SettingDefinition::choice("test.mode", "Mode", "auto", &[("auto", "Automatic"), ("off", "Off")])
+                    .with_description("How the test module behaves"),
+            ]
+        }
+
+        fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+            self.applied.lock_or_recover().push((key.to_string(), value.clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_validation_and_routing() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = SettingsRegistry::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let module = Arc::new(TestModule::default());
+        registry.register(module.clone()).unwrap();
+        let mut events = registry.subscribe_events();
+        let mut other_events = registry.subscribe_events();
+
+        assert_eq!(registry.get_i64("test.cache_mb"), Some(256));
+        assert!(registry.set("test.cache_mb", SettingValue::Integer(8)).is_err());
+        assert!(registry.set("test.cache_mb", SettingValue::Bool(true)).is_err());
+        assert!(registry.set("test.mode", SettingValue::String("fast".to_string())).is_err());
+        assert!(registry.set("other.key", SettingValue::Bool(true)).is_err());
+        assert!(module.applied.lock_or_recover().is_empty());
+
+        registry.set("test.cache_mb", SettingValue::Integer(512)).unwrap();
+        assert_eq!(
+            module.applied.lock_or_recover().last(),
+            Some(&("test.cache_mb".to_string(), SettingValue::Integer(512)))
+        );
+        assert_eq!(registry.pending_restart(), vec!["test.cache_mb".to_string()]);
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            SettingsEvent::Changed { requires_restart: true, .. }
+        ));
+        assert!(other_events.try_recv().is_ok());
+
+        // Stored values are applied to the module when it registers again
+        let registry = SettingsRegistry::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let module = Arc::new(TestModule::default());
+        registry.register(module.clone()).unwrap();
+        assert_eq!(registry.get_i64("test.cache_mb"), Some(512));
+        assert_eq!(
+            *module.applied.lock_or_recover(),
+            vec![("test.cache_mb".to_string(), SettingValue::Integer(512))]
+        );
+
+        registry.reset_module("test").unwrap();
+        assert!(registry.pages().iter().all(|p| p.sections.iter().all(|s| s.settings.iter().all(|v| v.is_default))));
+    }
+
+    #[test]
+    fn test_policies_lock_and_recommend() {
+        let temp_dir = TempDir::new().unwrap();
+        let policy_dir = temp_dir.path().join("policies.d");
+        std::fs::create_dir_all(&policy_dir).unwrap();
+        std::fs::write(
+            policy_dir.join("managed.json"),
+            r#"{ "mandatory": { "test.mode": "off", "test.enabled": 3 }, "recommended": { "test.cache_mb": 1024 } }"#,
+        )
+        .unwrap();
+
+        let registry = SettingsRegistry::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        registry.set_policies(&PolicySet::load(Some(policy_dir))).unwrap();
+        let module = Arc::new(TestModule::default());
+        registry.register(module.clone()).unwrap();
+
+        // The mandatory mode reaches the module and cannot be changed
+        assert!(module
+            .applied
+            .lock_or_recover()
+            .contains(&("test.mode".to_string(), SettingValue::String("off".to_string()))));
+        assert!(registry.is_locked("test.mode"));
+        assert!(registry.set("test.mode", SettingValue::String("auto".to_string())).is_err());
+        assert_eq!(registry.get_string("test.mode").as_deref(), Some("off"));
+
+        // A mandatory value of the wrong type is ignored
+        assert!(!registry.is_locked("test.enabled"));
+
+        // The recommended size is the new default until the user picks another
+        assert_eq!(registry.get_i64("test.cache_mb"), Some(1024));
+        registry.set("test.cache_mb", SettingValue::Integer(256)).unwrap();
+        assert_eq!(registry.get_i64("test.cache_mb"), Some(256));
+        registry.reset_module("test").unwrap();
+        assert_eq!(registry.get_i64("test.cache_mb"), Some(1024));
+        assert_eq!(registry.get_string("test.mode").as_deref(), Some("off"));
+    }
+
+    #[test]
+    fn test_pages_and_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = SettingsRegistry::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        registry.register(Arc::new(TestModule::default())).unwrap();
+        assert!(registry.register(Arc::new(TestModule::default())).is_err());
+
+        let pages = registry.pages();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].category, SettingCategory::General);
+        assert_eq!(pages[0].sections[0].module, "test");
+        assert_eq!(pages[0].sections[0].settings.len(), 2);
+        assert_eq!(pages[1].title, "Advanced");
+
+        let found = registry.search("behaves");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].definition.key, "test.mode");
+
+        // The UI data serializes with the setting type tagged
+        let json = serde_json::to_value(&pages[1]).unwrap();
+        assert_eq!(json["sections"][0]["settings"][0]["definition"]["kind"]["type"], "integer");
+    }
+}
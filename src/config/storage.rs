@@ -0,0 +1,225 @@
+// Crash-safe file persistence
+use crate::error::WebxError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Number of previous versions kept next to a config file
+pub const DEFAULT_BACKUP_COUNT: usize = 3;
+
+/// A file that could not be read or parsed while loading
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl std::fmt::Display for LoadFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+/// A value loaded from a file or, when that was damaged, from a backup
+#[derive(Debug)]
+pub struct Loaded<T> {
+    pub value: T,
+    /// File the value was read from
+    pub source: PathBuf,
+    /// Newer candidates that were skipped because they were unreadable
+    pub failures: Vec<LoadFailure>,
+}
+
+impl<T> Loaded<T> {
+    /// Check if the value came from a backup instead of the file itself
+    pub fn recovered(&self) -> bool {
+        !self.failures.is_empty()
+    }
+}
+
+/// Path of the nth backup of a file (1 is the newest)
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// Replace a file's contents so that a crash leaves either the old or the
+/// new contents, never a truncated file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    sync_parent_dir(path)
+}
+
+/// Serialize a value to JSON and write it atomically, first rotating the
+/// current file into the backups when its contents change
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T, backups: usize) -> std::io::Result<()> {
+    let content = serde_json::to_vec_pretty(value)?;
+    if let Ok(current) = fs::read(path) {
+        if current == content {
+            return Ok(());
+        }
+        rotate_backups(path, backups)?;
+    }
+    write_atomic(path, &content)
+}
+
+/// Load JSON from a file, falling back to the newest backup that parses.
+/// Returns `Ok(None)` when neither the file nor any backup exists and an
+/// error listing every failure when none of them is usable. A recovered
+/// backup is restored in place of the damaged file, which is kept as
+/// `<name>.corrupt` for inspection.
+pub fn load_json<T: DeserializeOwned>(
+    path: &Path,
+    backups: usize,
+) -> Result<Option<Loaded<T>>, WebxError> {
+    let candidates =
+        std::iter::once(path.to_path_buf()).chain((1..=backups).map(|index| backup_path(path, index)));
+    let mut failures = Vec::new();
+
+    for candidate in candidates {
+        let content = match fs::read(&candidate) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                failures.push(LoadFailure {
+                    path: candidate,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        match serde_json::from_slice(&content) {
+            Ok(value) => {
+                if candidate != path {
+                    restore_backup(path, &content)?;
+                }
+                return Ok(Some(Loaded {
+                    value,
+                    source: candidate,
+                    failures,
+                }));
+            }
+            Err(e) => failures.push(LoadFailure {
+                path: candidate,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(None);
+    }
+    let details: Vec<String> = failures.iter().map(|failure| failure.to_string()).collect();
+    Err(WebxError::Parse(format!(
+        "No readable copy of {}: {}",
+        path.display(),
+        details.join("; ")
+    )))
+}
+
+// Private helper functions
+
+fn rotate_backups(path: &Path, backups: usize) -> std::io::Result<()> {
+    if backups == 0 {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, backups);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (1..backups).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+fn restore_backup(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if path.exists() {
+        let mut corrupt_name = path.file_name().unwrap_or_default().to_os_string();
+        corrupt_name.push(".corrupt");
+        fs::rename(path, path.with_file_name(corrupt_name))?;
+    }
+    write_atomic(path, content)
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backups_rotate_and_skip_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("bookmarks.json");
+
+        for version in 1..=5 {
+            save_json(&path, &vec![version], 2).unwrap();
+            save_json(&path, &vec![version], 2).unwrap();
+        }
+
+        let read = |path: &Path| -> Vec<i32> { serde_json::from_slice(&fs::read(path).unwrap()).unwrap() };
+        assert_eq!(read(&path), vec![5]);
+        assert_eq!(read(&backup_path(&path, 1)), vec![4]);
+        assert_eq!(read(&backup_path(&path, 2)), vec![3]);
+        assert!(!backup_path(&path, 3).exists());
+        assert!(!temp_dir.path().join("bookmarks.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_corrupt_file_falls_back_to_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("bookmarks.json");
+        assert!(load_json::<Vec<i32>>(&path, 2).unwrap().is_none());
+
+        save_json(&path, &vec![1], 2).unwrap();
+        save_json(&path, &vec![1, 2], 2).unwrap();
+        fs::write(&path, b"[1, 2, 3").unwrap();
+
+        let loaded = load_json::<Vec<i32>>(&path, 2).unwrap().unwrap();
+        assert_eq!(loaded.value, vec![1]);
+        assert!(loaded.recovered());
+        assert_eq!(loaded.failures[0].path, path);
+
+        // The backup now stands in for the damaged file, which is kept aside
+        assert_eq!(load_json::<Vec<i32>>(&path, 2).unwrap().unwrap().source, path);
+        assert!(temp_dir.path().join("bookmarks.json.corrupt").exists());
+
+        fs::write(&path, b"not json").unwrap();
+        fs::write(backup_path(&path, 1), b"{}").unwrap();
+        assert!(load_json::<Vec<i32>>(&path, 1).is_err());
+    }
+}
@@ -1,4 +1,13 @@
 // Core browser types and structures
+pub mod navigation;
+pub mod search;
+
+pub use navigation::{NavigationEntry, NavigationHistory};
+pub use search::{
+    match_score, recency_factor, BookmarkSearch, SearchQuery, SearchResult, SearchSource, SearchSourceKind,
+    StateHistorySearch, UnifiedSearch,
+};
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
@@ -13,6 +22,23 @@ pub struct Tab {
     pub is_loading: bool,
     pub can_go_back: bool,
     pub can_go_forward: bool,
+    /// Identity container the tab browses in, `None` for the default context
+    #[serde(default)]
+    pub container_id: Option<String>,
+    /// The page is playing sound, muted or not
+    #[serde(default)]
+    pub is_audible: bool,
+    #[serde(default)]
+    pub muted: bool,
+    /// When the tab was last shown, for most recently used ordering
+    #[serde(default = "Utc::now")]
+    pub last_accessed: DateTime<Utc>,
+    /// Tab group the tab belongs to, e.g. its domain when grouped by domain
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Pages visited in the tab, for going back and forward
+    #[serde(default)]
+    pub history: NavigationHistory,
 }
 
 impl Tab {
@@ -20,13 +46,45 @@ impl Tab {
     pub fn new(id: usize, url: String) -> Self {
         Self {
             id,
-            title: "New Tab".to_string(),
+            title: crate::i18n::tr("tab-new"),
             url,
             favicon: None,
             is_loading: false,
             can_go_back: false,
             can_go_forward: false,
+            container_id: None,
+            is_audible: false,
+            muted: false,
+            last_accessed: Utc::now(),
+            group: None,
+            history: NavigationHistory::default(),
+        }
+    }
+
+    /// Record that the tab shows a page, adding it to the tab's history
+    pub fn record_navigation(&mut self, url: &str, title: &str) {
+        self.history.record(url, title);
+        self.url = url.to_string();
+        if !title.is_empty() {
+            self.title = title.to_string();
         }
+        self.sync_history_flags();
+    }
+
+    /// Move through the tab's history by a number of pages, negative going
+    /// back, returning the page to load
+    pub fn go_to_offset(&mut self, offset: isize) -> Option<NavigationEntry> {
+        let entry = self.history.go_to_offset(offset)?.clone();
+        self.url = entry.url.clone();
+        self.title = entry.title.clone();
+        self.sync_history_flags();
+        Some(entry)
+    }
+
+    /// Keep `can_go_back` and `can_go_forward` in step with the history
+    pub fn sync_history_flags(&mut self) {
+        self.can_go_back = self.history.can_go_back();
+        self.can_go_forward = self.history.can_go_forward();
     }
 }
 
@@ -38,6 +96,21 @@ pub struct Bookmark {
     pub url: String,
     pub favicon: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Address bar word that opens the bookmark, with the words after it
+    /// put in place of `%s` in the URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+}
+
+impl Bookmark {
+    /// URL with the words typed after the keyword in place of `%s`,
+    /// escaped but keeping slashes, or `%S`, as typed
+    pub fn keyword_url(&self, arguments: &str) -> String {
+        let arguments = arguments.trim();
+        self.url
+            .replace("%S", arguments)
+            .replace("%s", &urlencoding::encode_path(arguments))
+    }
 }
 
 /// Represents a history entry
@@ -60,6 +133,25 @@ pub struct Download {
     pub downloaded: u64,
     pub status: DownloadStatus,
     pub started_at: DateTime<Utc>,
+    /// Other URLs serving the same file, tried in order when `url` fails
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Every try at fetching the file, oldest first
+    #[serde(default)]
+    pub attempts: Vec<DownloadAttempt>,
+    /// MIME type the server or page gave the file
+    #[serde(default)]
+    pub mime: Option<String>,
+}
+
+/// One try at fetching a download from a URL
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DownloadAttempt {
+    pub url: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Why the attempt failed; `None` while running or after success
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -69,6 +161,10 @@ pub enum DownloadStatus {
     Completed,
     Failed,
     Cancelled,
+    /// Held until the user confirms the file type
+    AwaitingConfirmation,
+    /// Refused by the download policy
+    Blocked,
 }
 
 /// Browser settings
@@ -82,6 +178,16 @@ pub struct BrowserSettings {
     pub enable_cache: bool,
     pub block_popups: bool,
     pub user_agent: Option<String>,
+    #[serde(default)]
+    pub data_saver: DataSaverProfile,
+    #[serde(default)]
+    pub tray: TraySettings,
+    #[serde(default)]
+    pub autoplay: AutoplayPolicy,
+    #[serde(default)]
+    pub read_aloud: ReadAloudSettings,
+    #[serde(default)]
+    pub tab_policy: TabPolicySettings,
 }
 
 impl Default for BrowserSettings {
@@ -95,6 +201,94 @@ impl Default for BrowserSettings {
             enable_cache: true,
             block_popups: true,
             user_agent: None,
+            data_saver: DataSaverProfile::Off,
+            tray: TraySettings::default(),
+            autoplay: AutoplayPolicy::BlockAudible,
+            read_aloud: ReadAloudSettings::default(),
+            tab_policy: TabPolicySettings::default(),
+        }
+    }
+}
+
+/// How hard the browser works to reduce data usage
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum DataSaverProfile {
+    #[default]
+    Off,
+    Moderate,
+    Aggressive,
+}
+
+/// Which media may start playing before the user interacts with a page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum AutoplayPolicy {
+    Allow,
+    /// Only muted media may autoplay
+    #[default]
+    BlockAudible,
+    BlockAll,
+}
+
+/// System tray icon settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TraySettings {
+    pub enabled: bool,
+    /// Draw the number of active downloads on the icon
+    pub show_download_badge: bool,
+    /// Closing the window hides it to the tray instead of quitting
+    pub close_to_tray: bool,
+    /// How many recent downloads the tray menu lists
+    pub recent_downloads: usize,
+}
+
+impl Default for TraySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            show_download_badge: true,
+            close_to_tray: false,
+            recent_downloads: 5,
+        }
+    }
+}
+
+/// Read aloud voice settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReadAloudSettings {
+    /// Speaking rate, 1.0 being the voice's normal speed
+    pub rate: f32,
+    /// Voice name as the speech engine knows it; `None` for its default
+    pub voice: Option<String>,
+}
+
+impl Default for ReadAloudSettings {
+    fn default() -> Self {
+        Self { rate: 1.0, voice: None }
+    }
+}
+
+/// Policies that tidy up tabs automatically
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TabPolicySettings {
+    /// Group tabs of a window that share a domain as pages load
+    pub auto_group_by_domain: bool,
+    /// Days a tab must go unused before it is suggested for closing; 0 never
+    /// suggests any
+    pub stale_after_days: u32,
+    /// Bring up the review list of stale tabs on its own, rather than only
+    /// when asked for
+    pub auto_close_stale: bool,
+}
+
+impl Default for TabPolicySettings {
+    fn default() -> Self {
+        Self {
+            auto_group_by_domain: false,
+            stale_after_days: 14,
+            auto_close_stale: false,
         }
     }
 }
@@ -120,11 +314,104 @@ impl SearchEngine {
     }
 }
 
+/// Position and size of a top-level window, in physical pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// A top-level window and its tab strip
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WindowState {
+    pub id: usize,
+    /// Tabs in strip order
+    pub tab_ids: Vec<usize>,
+    /// Active tab while the window is not focused; the focused window's
+    /// active tab is `BrowserState::active_tab_id`
+    pub active_tab_id: Option<usize>,
+    pub geometry: Option<WindowGeometry>,
+    /// A second tab shown beside the active one
+    #[serde(default)]
+    pub split: Option<SplitView>,
+}
+
+impl WindowState {
+    /// Create an empty window
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            tab_ids: Vec::new(),
+            active_tab_id: None,
+            geometry: None,
+            split: None,
+        }
+    }
+}
+
+/// Smallest share of a split window either pane gets
+pub const MIN_SPLIT_RATIO: f64 = 0.2;
+
+/// How the panes of a split window are arranged
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SplitOrientation {
+    /// The active tab on the left, the other on the right
+    #[default]
+    SideBySide,
+    /// The active tab on top, the other below
+    Stacked,
+}
+
+/// Two tabs of a window shown at once: the window's active tab and another
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SplitView {
+    pub secondary_tab_id: usize,
+    /// Share of the window the active tab's pane gets
+    pub ratio: f64,
+    pub orientation: SplitOrientation,
+}
+
+impl SplitView {
+    /// Split evenly, side by side
+    pub fn new(secondary_tab_id: usize) -> Self {
+        Self {
+            secondary_tab_id,
+            ratio: 0.5,
+            orientation: SplitOrientation::SideBySide,
+        }
+    }
+
+    /// Bounds of the active tab's pane and of the other pane within a
+    /// window's inner size, as `(x, y, width, height)` in physical pixels
+    pub fn pane_bounds(&self, width: u32, height: u32) -> [(i32, i32, u32, u32); 2] {
+        let ratio = self.ratio.clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO);
+        match self.orientation {
+            SplitOrientation::SideBySide => {
+                let first = (f64::from(width) * ratio).round() as u32;
+                [(0, 0, first, height), (first as i32, 0, width - first, height)]
+            }
+            SplitOrientation::Stacked => {
+                let first = (f64::from(height) * ratio).round() as u32;
+                [(0, 0, width, first), (0, first as i32, width, height - first)]
+            }
+        }
+    }
+}
+
 /// Browser state
 pub struct BrowserState {
     pub tabs: HashMap<usize, Tab>,
+    /// Active tab of the focused window
     pub active_tab_id: Option<usize>,
     pub next_tab_id: usize,
+    /// Top-level windows; tabs belong to exactly one window
+    pub windows: HashMap<usize, WindowState>,
+    pub focused_window_id: Option<usize>,
+    pub next_window_id: usize,
     pub bookmarks: Vec<Bookmark>,
     pub history: Vec<HistoryEntry>,
     pub downloads: Vec<Download>,
@@ -138,6 +425,9 @@ impl BrowserState {
             tabs: HashMap::new(),
             active_tab_id: None,
             next_tab_id: 1,
+            windows: HashMap::new(),
+            focused_window_id: None,
+            next_window_id: 1,
             bookmarks: Vec::new(),
             history: Vec::new(),
             downloads: Vec::new(),
@@ -145,25 +435,306 @@ impl BrowserState {
         }
     }
 
-    /// Add a new tab
+    /// Add a new tab to the focused window, opening a window if there is none
     pub fn add_tab(&mut self, url: String) -> usize {
+        let window_id = self.ensure_window();
+        self.add_tab_to_window(window_id, url).unwrap_or_default()
+    }
+
+    /// Add a new tab to a window and make it the window's active tab
+    pub fn add_tab_to_window(&mut self, window_id: usize, url: String) -> Option<usize> {
+        if !self.windows.contains_key(&window_id) {
+            return None;
+        }
+
         let id = self.next_tab_id;
         self.next_tab_id += 1;
         
         let tab = Tab::new(id, url);
         self.tabs.insert(id, tab);
-        self.active_tab_id = Some(id);
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            window.tab_ids.push(id);
+        }
+        self.set_window_active_tab(window_id, Some(id));
         
-        id
+        Some(id)
     }
 
     /// Remove a tab
     pub fn remove_tab(&mut self, id: usize) {
         self.tabs.remove(&id);
         
-        // If we removed the active tab, switch to another
-        if self.active_tab_id == Some(id) {
-            self.active_tab_id = self.tabs.keys().next().copied();
+        // If we removed the active tab, switch to its neighbour in the strip
+        match self.window_of_tab(id) {
+            Some(window_id) => {
+                let window = self.windows.get_mut(&window_id).unwrap();
+                let index = window.tab_ids.iter().position(|&tab_id| tab_id == id).unwrap_or(0);
+                window.tab_ids.retain(|&tab_id| tab_id != id);
+                let neighbour = window
+                    .tab_ids
+                    .get(index)
+                    .or_else(|| window.tab_ids.last())
+                    .copied();
+                if self.window_active_tab(window_id) == Some(id) {
+                    self.set_window_active_tab(window_id, neighbour);
+                }
+                self.drop_broken_split(window_id);
+            }
+            None if self.active_tab_id == Some(id) => {
+                self.active_tab_id = self.tabs.keys().next().copied();
+            }
+            None => {}
+        }
+    }
+
+    /// Open a new, empty window and focus it
+    pub fn open_window(&mut self) -> usize {
+        let id = self.next_window_id;
+        self.next_window_id += 1;
+        self.windows.insert(id, WindowState::new(id));
+        self.focus_window(id);
+        id
+    }
+
+    /// Close a window and its tabs, returning the IDs of the closed tabs
+    pub fn close_window(&mut self, window_id: usize) -> Vec<usize> {
+        if self.focused_window_id == Some(window_id) {
+            self.active_tab_id = None;
+            self.focused_window_id = None;
+        }
+        let Some(window) = self.windows.remove(&window_id) else {
+            return Vec::new();
+        };
+        for tab_id in &window.tab_ids {
+            self.tabs.remove(tab_id);
+        }
+
+        if self.focused_window_id.is_none() {
+            if let Some(next) = self.windows.keys().min().copied() {
+                self.focus_window(next);
+            }
+        }
+        window.tab_ids
+    }
+
+    /// Focus a window, making its active tab the browser's active tab
+    pub fn focus_window(&mut self, window_id: usize) -> bool {
+        if !self.windows.contains_key(&window_id) {
+            return false;
+        }
+        if self.focused_window_id == Some(window_id) {
+            return true;
+        }
+
+        // Park the current window's active tab before switching
+        if let Some(focused) = self.focused_window_id.and_then(|id| self.windows.get_mut(&id)) {
+            focused.active_tab_id = self.active_tab_id;
+        }
+        self.focused_window_id = Some(window_id);
+        self.active_tab_id = self.windows[&window_id].active_tab_id;
+        if let Some(tab) = self.active_tab_id.and_then(|id| self.tabs.get_mut(&id)) {
+            tab.last_accessed = Utc::now();
+        }
+        true
+    }
+
+    /// Make a tab active, focusing its window
+    pub fn activate_tab(&mut self, tab_id: usize) -> bool {
+        if !self.tabs.contains_key(&tab_id) {
+            return false;
+        }
+        match self.window_of_tab(tab_id) {
+            Some(window_id) => {
+                self.focus_window(window_id);
+                self.set_window_active_tab(window_id, Some(tab_id));
+            }
+            None => self.active_tab_id = Some(tab_id),
+        }
+        true
+    }
+
+    /// Move a tab to the end of another window's strip and make it active
+    /// there
+    pub fn move_tab_to_window(&mut self, tab_id: usize, window_id: usize) -> bool {
+        if !self.tabs.contains_key(&tab_id) || !self.windows.contains_key(&window_id) {
+            return false;
+        }
+        if self.window_of_tab(tab_id) == Some(window_id) {
+            return true;
+        }
+        match self.detach_tab(tab_id) {
+            Some(tab) => self.attach_tab(window_id, tab, None).is_some(),
+            None => false,
+        }
+    }
+
+    /// Take a tab out of its window like a close, returning it so it can be
+    /// attached elsewhere
+    pub fn detach_tab(&mut self, tab_id: usize) -> Option<Tab> {
+        let tab = self.tabs.get(&tab_id)?.clone();
+        self.remove_tab(tab_id);
+        Some(tab)
+    }
+
+    /// Put a tab into a window's strip at an index, or at the end, and make
+    /// it active there. A window that is gone is replaced by the focused
+    /// one. The tab keeps its ID unless another tab has it. Returns the
+    /// tab's ID.
+    pub fn attach_tab(&mut self, window_id: usize, mut tab: Tab, index: Option<usize>) -> Option<usize> {
+        let window_id = if self.windows.contains_key(&window_id) { window_id } else { self.ensure_window() };
+        if self.tabs.contains_key(&tab.id) {
+            tab.id = self.next_tab_id;
+        }
+        self.next_tab_id = self.next_tab_id.max(tab.id + 1);
+
+        let tab_id = tab.id;
+        self.tabs.insert(tab_id, tab);
+        let window = self.windows.get_mut(&window_id)?;
+        let index = index.unwrap_or(window.tab_ids.len()).min(window.tab_ids.len());
+        window.tab_ids.insert(index, tab_id);
+        self.set_window_active_tab(window_id, Some(tab_id));
+        Some(tab_id)
+    }
+
+    /// Move a tab to another position in its window's strip, returning its
+    /// old position
+    pub fn reorder_tab(&mut self, tab_id: usize, index: usize) -> Option<usize> {
+        let window_id = self.window_of_tab(tab_id)?;
+        let window = self.windows.get_mut(&window_id)?;
+        let from = window.tab_ids.iter().position(|&id| id == tab_id)?;
+        window.tab_ids.remove(from);
+        let index = index.min(window.tab_ids.len());
+        window.tab_ids.insert(index, tab_id);
+        Some(from)
+    }
+
+    /// Move a tab into a new window of its own, returning the window ID
+    pub fn move_tab_to_new_window(&mut self, tab_id: usize) -> Option<usize> {
+        if !self.tabs.contains_key(&tab_id) {
+            return None;
+        }
+        let window_id = self.open_window();
+        self.move_tab_to_window(tab_id, window_id);
+        Some(window_id)
+    }
+
+    /// Window a tab belongs to
+    pub fn window_of_tab(&self, tab_id: usize) -> Option<usize> {
+        self.windows
+            .values()
+            .find(|window| window.tab_ids.contains(&tab_id))
+            .map(|window| window.id)
+    }
+
+    /// Tabs of a window in strip order
+    pub fn window_tabs(&self, window_id: usize) -> Vec<&Tab> {
+        self.windows
+            .get(&window_id)
+            .map(|window| window.tab_ids.iter().filter_map(|id| self.tabs.get(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Active tab of a window
+    pub fn window_active_tab(&self, window_id: usize) -> Option<usize> {
+        if self.focused_window_id == Some(window_id) {
+            self.active_tab_id
+        } else {
+            self.windows.get(&window_id).and_then(|window| window.active_tab_id)
+        }
+    }
+
+    /// Show another tab of a window beside its active tab
+    pub fn split_window(&mut self, window_id: usize, tab_id: usize) -> bool {
+        let in_window = self.windows.get(&window_id).is_some_and(|window| window.tab_ids.contains(&tab_id));
+        if !in_window || self.window_active_tab(window_id) == Some(tab_id) {
+            return false;
+        }
+        if let Some(tab) = self.tabs.get_mut(&tab_id) {
+            tab.last_accessed = Utc::now();
+        }
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            let split = window.split.get_or_insert(SplitView::new(tab_id));
+            split.secondary_tab_id = tab_id;
+        }
+        true
+    }
+
+    /// Show only a window's active tab again, returning the tab that was
+    /// beside it
+    pub fn unsplit_window(&mut self, window_id: usize) -> Option<usize> {
+        let split = self.windows.get_mut(&window_id)?.split.take()?;
+        Some(split.secondary_tab_id)
+    }
+
+    /// Remember a window's split layout, e.g. after resizing its panes
+    pub fn set_window_split(&mut self, window_id: usize, split: SplitView) -> bool {
+        match self.windows.get_mut(&window_id) {
+            Some(window) if window.split.is_some() => {
+                window.split = Some(SplitView {
+                    ratio: split.ratio.clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO),
+                    ..split
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remember where a window is on screen
+    pub fn set_window_geometry(&mut self, window_id: usize, geometry: WindowGeometry) {
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            window.geometry = Some(geometry);
+        }
+    }
+
+    /// Restore invariants an interrupted update may have broken: the active
+    /// tab exists, every tab is in exactly one window and new tab IDs cannot
+    /// collide with open tabs
+    pub fn repair(&mut self) {
+        let tabs = &self.tabs;
+        let mut seen = std::collections::HashSet::new();
+        for window in self.windows.values_mut() {
+            window.tab_ids.retain(|id| tabs.contains_key(id) && seen.insert(*id));
+            if window.active_tab_id.is_some_and(|id| !window.tab_ids.contains(&id)) {
+                window.active_tab_id = window.tab_ids.first().copied();
+            }
+        }
+
+        if !self.focused_window_id.is_some_and(|id| self.windows.contains_key(&id)) {
+            self.focused_window_id = None;
+            if let Some(id) = self.windows.keys().min().copied() {
+                self.focused_window_id = Some(id);
+                self.active_tab_id = self.windows[&id].active_tab_id;
+            }
+        }
+
+        let mut orphans: Vec<usize> = self.tabs.keys().filter(|id| !seen.contains(id)).copied().collect();
+        if !orphans.is_empty() {
+            orphans.sort_unstable();
+            let window_id = self.ensure_window();
+            if let Some(window) = self.windows.get_mut(&window_id) {
+                window.tab_ids.extend(orphans);
+            }
+        }
+
+        match self.focused_window_id {
+            Some(window_id) => {
+                let strip = &self.windows[&window_id].tab_ids;
+                if !self.active_tab_id.is_some_and(|id| strip.contains(&id)) {
+                    self.active_tab_id = strip.first().copied();
+                }
+            }
+            None => self.active_tab_id = None,
+        }
+        let window_ids: Vec<usize> = self.windows.keys().copied().collect();
+        for window_id in window_ids {
+            self.drop_broken_split(window_id);
+        }
+        if let Some(max_id) = self.tabs.keys().max() {
+            self.next_tab_id = self.next_tab_id.max(max_id + 1);
+        }
+        if let Some(max_id) = self.windows.keys().max() {
+            self.next_window_id = self.next_window_id.max(max_id + 1);
         }
     }
 
@@ -186,6 +757,7 @@ impl BrowserState {
             url,
             favicon: None,
             created_at: Utc::now(),
+            keyword: None,
         });
     }
 
@@ -215,8 +787,23 @@ impl BrowserState {
         }
     }
 
-    /// Process URL input (add protocol, handle search)
+    /// URL a bookmark keyword typed first in the input opens, e.g.
+    /// "gh rust-lang/rust"
+    pub fn keyword_url(&self, input: &str) -> Option<String> {
+        let input = input.trim_start();
+        let (word, arguments) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        self.bookmarks
+            .iter()
+            .find(|bookmark| bookmark.keyword.as_deref().is_some_and(|keyword| keyword.eq_ignore_ascii_case(word)))
+            .map(|bookmark| bookmark.keyword_url(arguments))
+    }
+
+    /// Process URL input (bookmark keywords, add protocol, handle search)
     pub fn process_url(&self, input: &str) -> String {
+        if let Some(url) = self.keyword_url(input) {
+            return url;
+        }
+
         // If it looks like a URL, add https if needed
         if input.contains('.') && !input.contains(' ') {
             if input.starts_with("http://") || input.starts_with("https://") {
@@ -231,6 +818,58 @@ impl BrowserState {
     }
 }
 
+impl BrowserState {
+    // Private helper methods
+
+    /// Focused window, opening one if there is none
+    fn ensure_window(&mut self) -> usize {
+        match self.focused_window_id {
+            Some(id) if self.windows.contains_key(&id) => id,
+            _ => match self.windows.keys().min().copied() {
+                Some(id) => {
+                    self.focus_window(id);
+                    id
+                }
+                None => self.open_window(),
+            },
+        }
+    }
+
+    fn set_window_active_tab(&mut self, window_id: usize, tab_id: Option<usize>) {
+        if let Some(tab) = tab_id.and_then(|id| self.tabs.get_mut(&id)) {
+            tab.last_accessed = Utc::now();
+        }
+        // Activating the tab beside the active one swaps the two
+        let previous = self.window_active_tab(window_id);
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            if window.split.is_some_and(|split| tab_id == Some(split.secondary_tab_id)) {
+                match (previous, window.split.as_mut()) {
+                    (Some(previous), Some(split)) => split.secondary_tab_id = previous,
+                    _ => window.split = None,
+                }
+            }
+        }
+        if self.focused_window_id == Some(window_id) {
+            self.active_tab_id = tab_id;
+        } else if let Some(window) = self.windows.get_mut(&window_id) {
+            window.active_tab_id = tab_id;
+        }
+    }
+
+    /// Unsplit a window whose other tab left it or became its active tab
+    fn drop_broken_split(&mut self, window_id: usize) {
+        let active = self.window_active_tab(window_id);
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            let broken = window
+                .split
+                .is_some_and(|split| !window.tab_ids.contains(&split.secondary_tab_id) || active == Some(split.secondary_tab_id));
+            if broken {
+                window.split = None;
+            }
+        }
+    }
+}
+
 impl Default for BrowserState {
     fn default() -> Self {
         Self::new()
@@ -242,4 +881,15 @@ mod urlencoding {
     pub fn encode(s: &str) -> String {
         url::form_urlencoded::byte_serialize(s.as_bytes()).collect()
     }
+
+    /// Percent-encode all but unreserved characters and `/`, so the text
+    /// can stand for path segments
+    pub fn encode_path(s: &str) -> String {
+        s.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (byte as char).to_string(),
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,163 @@
+// Navigation History
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Most pages a tab remembers; the oldest are forgotten first
+pub const MAX_NAVIGATION_ENTRIES: usize = 50;
+
+/// A page a tab visited
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NavigationEntry {
+    pub url: String,
+    pub title: String,
+    /// Where the page was scrolled when the tab left it
+    pub scroll_position: Option<(f64, f64)>,
+    pub visited_at: DateTime<Utc>,
+}
+
+/// A tab's back and forward stack
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct NavigationHistory {
+    entries: Vec<NavigationEntry>,
+    /// Index of the page shown; entries after it are forward
+    current: usize,
+}
+
+impl NavigationHistory {
+    /// History holding only the page a tab shows
+    pub fn starting_at(url: &str, title: &str) -> Self {
+        let mut history = Self::default();
+        history.record(url, title);
+        history
+    }
+
+    /// Record that the tab shows a page. Showing the current page again,
+    /// e.g. after a reload or going back, only updates its title; a new
+    /// page drops the forward entries.
+    pub fn record(&mut self, url: &str, title: &str) {
+        if let Some(entry) = self.current_mut().filter(|entry| entry.url == url) {
+            if !title.is_empty() {
+                entry.title = title.to_string();
+            }
+            return;
+        }
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.current + 1);
+        }
+        self.entries.push(NavigationEntry {
+            url: url.to_string(),
+            title: title.to_string(),
+            scroll_position: None,
+            visited_at: Utc::now(),
+        });
+        let excess = self.entries.len().saturating_sub(MAX_NAVIGATION_ENTRIES);
+        self.entries.drain(..excess);
+        self.current = self.entries.len() - 1;
+    }
+
+    /// The page shown
+    pub fn current(&self) -> Option<&NavigationEntry> {
+        self.entries.get(self.current)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut NavigationEntry> {
+        self.entries.get_mut(self.current)
+    }
+
+    /// Every entry, oldest first
+    pub fn entries(&self) -> &[NavigationEntry] {
+        &self.entries
+    }
+
+    /// Index of the page shown in `entries`
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current + 1 < self.entries.len()
+    }
+
+    /// Step back a page, returning the page to show
+    pub fn go_back(&mut self) -> Option<&NavigationEntry> {
+        self.go_to_offset(-1)
+    }
+
+    /// Step forward a page, returning the page to show
+    pub fn go_forward(&mut self) -> Option<&NavigationEntry> {
+        self.go_to_offset(1)
+    }
+
+    /// Move by a number of pages, negative going back, returning the page
+    /// to show. Nothing moves if the offset is out of range.
+    pub fn go_to_offset(&mut self, offset: isize) -> Option<&NavigationEntry> {
+        if offset == 0 {
+            return None;
+        }
+        let target = self.current.checked_add_signed(offset).filter(|&index| index < self.entries.len())?;
+        self.current = target;
+        self.entries.get(target)
+    }
+
+    /// Pages behind the current one, nearest first, with the offset that
+    /// reaches each, for a long-press back menu
+    pub fn back_entries(&self) -> Vec<(isize, &NavigationEntry)> {
+        self.entries[..self.current.min(self.entries.len())]
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, entry)| (-(i as isize) - 1, entry))
+            .collect()
+    }
+
+    /// Pages ahead of the current one, nearest first, with the offset that
+    /// reaches each
+    pub fn forward_entries(&self) -> Vec<(isize, &NavigationEntry)> {
+        self.entries
+            .iter()
+            .skip(self.current + 1)
+            .enumerate()
+            .map(|(i, entry)| (i as isize + 1, entry))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_forward_and_offsets() {
+        let mut history = NavigationHistory::starting_at("https://a.example", "A");
+        history.record("https://b.example", "B");
+        history.record("https://c.example", "C");
+        assert!(!history.can_go_forward());
+
+        assert_eq!(history.go_back().unwrap().url, "https://b.example");
+        // Loading the page gone back to does not add it again
+        history.record("https://b.example", "B again");
+        assert_eq!(history.entries().len(), 3);
+        assert_eq!(history.current().unwrap().title, "B again");
+
+        let back: Vec<isize> = history.back_entries().iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(back, vec![-1]);
+        assert_eq!(history.forward_entries()[0].1.url, "https://c.example");
+        assert!(history.go_to_offset(5).is_none());
+        assert_eq!(history.go_to_offset(-1).unwrap().url, "https://a.example");
+
+        // A new page drops what was ahead
+        history.record("https://d.example", "D");
+        let urls: Vec<&str> = history.entries().iter().map(|entry| entry.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://a.example", "https://d.example"]);
+
+        for i in 0..MAX_NAVIGATION_ENTRIES {
+            history.record(&format!("https://example.com/{}", i), "");
+        }
+        assert_eq!(history.entries().len(), MAX_NAVIGATION_ENTRIES);
+        assert_eq!(history.current_index(), MAX_NAVIGATION_ENTRIES - 1);
+    }
+}
@@ -0,0 +1,350 @@
+// Unified Search
+use super::BrowserState;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Where a search result comes from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SearchSourceKind {
+    History,
+    Bookmarks,
+    Downloads,
+    ReadingList,
+    Notes,
+}
+
+impl SearchSourceKind {
+    pub const ALL: [SearchSourceKind; 5] = [
+        SearchSourceKind::History,
+        SearchSourceKind::Bookmarks,
+        SearchSourceKind::Downloads,
+        SearchSourceKind::ReadingList,
+        SearchSourceKind::Notes,
+    ];
+
+    /// Name used in `in:` filters
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchSourceKind::History => "history",
+            SearchSourceKind::Bookmarks => "bookmarks",
+            SearchSourceKind::Downloads => "downloads",
+            SearchSourceKind::ReadingList => "reading-list",
+            SearchSourceKind::Notes => "notes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// How much a match here counts against other sources; things the user
+    /// saved on purpose rank above pages merely visited
+    pub fn weight(&self) -> f64 {
+        match self {
+            SearchSourceKind::Bookmarks => 1.3,
+            SearchSourceKind::ReadingList => 1.2,
+            SearchSourceKind::Notes => 1.1,
+            SearchSourceKind::History | SearchSourceKind::Downloads => 1.0,
+        }
+    }
+}
+
+/// A search across sources
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Sources to search, `None` for all
+    pub sources: Option<HashSet<SearchSourceKind>>,
+    /// Results returned overall
+    pub limit: usize,
+    /// Results taken from each source before merging
+    pub per_source_limit: usize,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.trim().to_string(),
+            sources: None,
+            limit: 50,
+            per_source_limit: 20,
+        }
+    }
+
+    /// Read a query typed into the search box, where `in:<source>` words
+    /// such as `in:notes` pick the sources to search
+    pub fn parse(input: &str) -> Self {
+        let mut sources = HashSet::new();
+        let mut words = Vec::new();
+        for word in input.split_whitespace() {
+            match word.strip_prefix("in:").and_then(SearchSourceKind::from_name) {
+                Some(kind) => {
+                    sources.insert(kind);
+                }
+                None => words.push(word),
+            }
+        }
+        let mut query = Self::new(&words.join(" "));
+        query.sources = (!sources.is_empty()).then_some(sources);
+        query
+    }
+
+    pub fn with_sources(mut self, sources: impl IntoIterator<Item = SearchSourceKind>) -> Self {
+        self.sources = Some(sources.into_iter().collect());
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Lowercase words of the query
+    pub fn words(&self) -> Vec<String> {
+        self.text.split_whitespace().map(str::to_lowercase).collect()
+    }
+
+    /// Whether a source takes part in the search
+    pub fn includes(&self, kind: SearchSourceKind) -> bool {
+        self.sources.as_ref().is_none_or(|sources| sources.contains(&kind))
+    }
+}
+
+/// A match in one of the sources
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchResult {
+    pub kind: SearchSourceKind,
+    /// Identifies the item within its source
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Secondary line, e.g. a download's path or an article excerpt
+    pub detail: Option<String>,
+    /// Relevance; higher is better
+    pub score: f64,
+    /// When the item was last visited, saved or downloaded
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// A store that can be searched
+pub trait SearchSource: Send + Sync {
+    fn kind(&self) -> SearchSourceKind;
+
+    /// Items matching the query, with their relevance before source
+    /// weighting. Only the query's text is used; limits and source filters
+    /// are applied by `UnifiedSearch`.
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult>;
+}
+
+/// Searches everything the user has: history, bookmarks, downloads, the
+/// reading list and notes, as one ranked list
+#[derive(Default)]
+pub struct UnifiedSearch {
+    sources: Vec<Arc<dyn SearchSource>>,
+}
+
+impl UnifiedSearch {
+    /// Create new search without sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_source(&mut self, source: Arc<dyn SearchSource>) {
+        self.sources.push(source);
+    }
+
+    /// Kinds of the sources searched
+    pub fn source_kinds(&self) -> Vec<SearchSourceKind> {
+        self.sources.iter().map(|source| source.kind()).collect()
+    }
+
+    /// Results of every included source, best first
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        if query.words().is_empty() {
+            return Vec::new();
+        }
+        let mut results: Vec<SearchResult> = Vec::new();
+        for source in self.sources.iter().filter(|source| query.includes(source.kind())) {
+            let mut found = source.search(query);
+            found.sort_by(compare_results);
+            found.truncate(query.per_source_limit);
+            results.extend(found.into_iter().map(|mut result| {
+                result.score *= result.kind.weight();
+                result
+            }));
+        }
+        results.sort_by(compare_results);
+        results.truncate(query.limit);
+        results
+    }
+}
+
+/// Bookmarks in the browser state
+pub struct BookmarkSearch(pub Arc<Mutex<BrowserState>>);
+
+impl SearchSource for BookmarkSearch {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::Bookmarks
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = Utc::now();
+        self.0
+            .lock_or_recover()
+            .bookmarks
+            .iter()
+            .filter_map(|bookmark| {
+                let score = match_score(&words, &[(&bookmark.title, 1.0), (&bookmark.url, 0.8)])?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::Bookmarks,
+                    id: bookmark.id.to_string(),
+                    title: bookmark.title.clone(),
+                    url: Some(bookmark.url.clone()),
+                    detail: None,
+                    score: score * recency_factor(bookmark.created_at, now),
+                    timestamp: Some(bookmark.created_at),
+                })
+            })
+            .collect()
+    }
+}
+
+/// History kept in the browser state
+pub struct StateHistorySearch(pub Arc<Mutex<BrowserState>>);
+
+impl SearchSource for StateHistorySearch {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::History
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = Utc::now();
+        let state = self.0.lock_or_recover();
+        // Entries are visits; keep the latest of each page
+        let mut seen = HashSet::new();
+        let mut entries: Vec<_> = state.history.iter().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.visited_at));
+        entries
+            .into_iter()
+            .filter(|entry| seen.insert(entry.url.as_str()))
+            .filter_map(|entry| {
+                let score = match_score(&words, &[(&entry.title, 1.0), (&entry.url, 0.8)])?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::History,
+                    id: entry.id.to_string(),
+                    title: entry.title.clone(),
+                    url: Some(entry.url.clone()),
+                    detail: None,
+                    score: score * recency_factor(entry.visited_at, now),
+                    timestamp: Some(entry.visited_at),
+                })
+            })
+            .collect()
+    }
+}
+
+/// How well weighted fields match query words, from 0 to the highest
+/// weight: each word scores its best field, 1.0 for a whole-word match,
+/// 0.8 for a word prefix and 0.5 anywhere else, times the field's weight.
+/// `None` unless every word matches somewhere.
+pub fn match_score(words: &[String], fields: &[(&str, f64)]) -> Option<f64> {
+    if words.is_empty() {
+        return None;
+    }
+    let fields: Vec<(String, f64)> = fields.iter().map(|(text, weight)| (text.to_lowercase(), *weight)).collect();
+    let mut total = 0.0;
+    for word in words {
+        let best = fields
+            .iter()
+            .filter_map(|(text, weight)| {
+                let mut tokens = text.split(|c: char| !c.is_alphanumeric());
+                let quality = if tokens.clone().any(|token| token == word) {
+                    1.0
+                } else if tokens.any(|token| token.starts_with(word.as_str())) {
+                    0.8
+                } else if text.contains(word.as_str()) {
+                    0.5
+                } else {
+                    return None;
+                };
+                Some(quality * weight)
+            })
+            .reduce(f64::max)?;
+        total += best;
+    }
+    Some(total / words.len() as f64)
+}
+
+/// Boost for recent items: 2 for now, falling to 1.5 after a month and
+/// towards 1 over time
+pub fn recency_factor(at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let days = (now - at).num_minutes().max(0) as f64 / (24.0 * 60.0);
+    1.0 + 1.0 / (1.0 + days / 30.0)
+}
+
+// Private helper functions
+
+fn compare_results(a: &SearchResult, b: &SearchResult) -> std::cmp::Ordering {
+    b.score.total_cmp(&a.score).then_with(|| b.timestamp.cmp(&a.timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Bookmark, HistoryEntry};
+
+    #[test]
+    fn test_search_ranks_and_filters_across_sources() {
+        let now = Utc::now();
+        let mut state = BrowserState::new();
+        state.bookmarks.push(Bookmark {
+            id: 1,
+            title: "Rust Book".to_string(),
+            url: "https://doc.rust-lang.org/book/".to_string(),
+            favicon: None,
+            created_at: now,
+            keyword: None,
+        });
+        for (id, title, url) in [
+            (1, "Rustacean station", "https://rustacean-station.org/"),
+            (2, "Rust Book", "https://doc.rust-lang.org/book/"),
+            (3, "Rust Book", "https://doc.rust-lang.org/book/"),
+            (4, "Weather", "https://weather.example.com/"),
+        ] {
+            state.history.push(HistoryEntry {
+                id,
+                title: title.to_string(),
+                url: url.to_string(),
+                visited_at: now,
+            });
+        }
+        let state = Arc::new(Mutex::new(state));
+        let mut search = UnifiedSearch::new();
+        search.add_source(Arc::new(BookmarkSearch(Arc::clone(&state))));
+        search.add_source(Arc::new(StateHistorySearch(Arc::clone(&state))));
+
+        let results = search.search(&SearchQuery::new("rust book"));
+        let found: Vec<(SearchSourceKind, &str)> = results.iter().map(|r| (r.kind, r.title.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![(SearchSourceKind::Bookmarks, "Rust Book"), (SearchSourceKind::History, "Rust Book")]
+        );
+
+        let query = SearchQuery::parse("in:history rust");
+        assert_eq!(query.text, "rust");
+        let results = search.search(&query);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.kind == SearchSourceKind::History));
+        assert!(search.search(&SearchQuery::parse("in:notes")).is_empty());
+
+        assert_eq!(match_score(&["rust".to_string()], &[("Rust", 1.0)]), Some(1.0));
+        assert_eq!(match_score(&["rust".to_string()], &[("Rustacean", 1.0)]), Some(0.8));
+        assert_eq!(match_score(&["cean".to_string()], &[("Rustacean", 1.0)]), Some(0.5));
+        assert_eq!(match_score(&["rust".to_string(), "go".to_string()], &[("Rust", 1.0)]), None);
+    }
+}
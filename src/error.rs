@@ -0,0 +1,285 @@
+// WebX Error Types
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Number of reported errors kept for the error log view
+const RECENT_ERROR_LIMIT: usize = 100;
+
+/// Result type used across the browser
+pub type Result<T, E = WebxError> = std::result::Result<T, E>;
+
+/// Error returned by browser APIs
+#[derive(Debug, thiserror::Error)]
+pub enum WebxError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("{0} is locked")]
+    Locked(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Category of an error, for matching without the payload
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Io,
+    Crypto,
+    Network,
+    Parse,
+    Storage,
+    Locked,
+    NotFound,
+    Invalid,
+}
+
+impl WebxError {
+    /// Category of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            WebxError::Io(_) => ErrorKind::Io,
+            WebxError::Crypto(_) => ErrorKind::Crypto,
+            WebxError::Network(_) => ErrorKind::Network,
+            WebxError::Parse(_) => ErrorKind::Parse,
+            WebxError::Storage(_) => ErrorKind::Storage,
+            WebxError::Locked(_) => ErrorKind::Locked,
+            WebxError::NotFound(_) => ErrorKind::NotFound,
+            WebxError::Invalid(_) => ErrorKind::Invalid,
+        }
+    }
+
+    /// What the user can do about the error, if anything
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            WebxError::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Some("Check that WebX may write to its data folder.")
+            }
+            WebxError::Io(_) | WebxError::Storage(_) => Some("Check free disk space and try again."),
+            WebxError::Network(_) => Some("Check your connection and proxy settings."),
+            WebxError::Locked(_) => Some("Unlock it with your master password and try again."),
+            WebxError::Crypto(_) => Some("The master password may be wrong or the data damaged."),
+            WebxError::Parse(_) | WebxError::NotFound(_) | WebxError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<String> for WebxError {
+    fn from(message: String) -> Self {
+        WebxError::Invalid(message)
+    }
+}
+
+impl From<&str> for WebxError {
+    fn from(message: &str) -> Self {
+        WebxError::Invalid(message.to_string())
+    }
+}
+
+impl From<serde_json::Error> for WebxError {
+    fn from(e: serde_json::Error) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<url::ParseError> for WebxError {
+    fn from(e: url::ParseError) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<regex::Error> for WebxError {
+    fn from(e: regex::Error) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for WebxError {
+    fn from(e: toml::de::Error) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for WebxError {
+    fn from(e: toml::ser::Error) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for WebxError {
+    fn from(e: base64::DecodeError) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for WebxError {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for WebxError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for WebxError {
+    fn from(e: image::ImageError) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for WebxError {
+    fn from(e: reqwest::Error) -> Self {
+        WebxError::Network(e.to_string())
+    }
+}
+
+impl From<sled::Error> for WebxError {
+    fn from(e: sled::Error) -> Self {
+        WebxError::Storage(e.to_string())
+    }
+}
+
+impl From<aes_gcm::Error> for WebxError {
+    fn from(_: aes_gcm::Error) -> Self {
+        WebxError::Crypto("Decryption failed".to_string())
+    }
+}
+
+impl From<notify::Error> for WebxError {
+    fn from(e: notify::Error) -> Self {
+        WebxError::Io(std::io::Error::other(e))
+    }
+}
+
+impl From<tokio::task::JoinError> for WebxError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        WebxError::Invalid(e.to_string())
+    }
+}
+
+/// An error shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorEvent {
+    /// Module the error came from, e.g. "downloads"
+    pub source: String,
+    pub kind: ErrorKind,
+    pub message: String,
+    pub hint: Option<String>,
+    pub occurred_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Collects errors from background work so the UI can show them
+pub struct ErrorReporter {
+    recent: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    tx: mpsc::UnboundedSender<ErrorEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ErrorEvent>>>>,
+}
+
+impl ErrorReporter {
+    /// Create new error reporter
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            recent: Arc::new(Mutex::new(VecDeque::new())),
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        }
+    }
+
+    /// Log an error and send it to the UI
+    pub fn report(&self, source: &str, error: &WebxError) -> ErrorEvent {
+        tracing::error!("{}: {}", source, error);
+        let event = ErrorEvent {
+            source: source.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+            hint: error.hint().map(str::to_string),
+            occurred_at: chrono::Utc::now(),
+        };
+
+        {
+            let mut recent = self.recent.lock_or_recover();
+            if recent.len() == RECENT_ERROR_LIMIT {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        let _ = self.tx.send(event.clone());
+        event
+    }
+
+    /// Report the error of a result, passing the value through
+    pub fn check<T>(&self, source: &str, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report(source, &e);
+                None
+            }
+        }
+    }
+
+    /// Recently reported errors, oldest first
+    pub fn recent(&self) -> Vec<ErrorEvent> {
+        self.recent.lock_or_recover().iter().cloned().collect()
+    }
+
+    /// Forget recently reported errors
+    pub fn clear(&self) {
+        self.recent.lock_or_recover().clear();
+    }
+
+    /// Subscribe to reported errors
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<ErrorEvent> {
+        let mut rx_guard = self.rx.lock_or_recover();
+        rx_guard.take().expect("Event receiver already taken")
+    }
+}
+
+impl Default for ErrorReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_missing() -> Result<String> {
+        Ok(std::fs::read_to_string("/nonexistent/webx/file")?)
+    }
+
+    #[test]
+    fn test_errors_convert_and_report() {
+        let error = read_missing().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io);
+
+        let parse: WebxError = serde_json::from_str::<u32>("x").unwrap_err().into();
+        assert!(matches!(parse, WebxError::Parse(_)));
+
+        let reporter = ErrorReporter::new();
+        let mut events = reporter.subscribe_events();
+        assert!(reporter.check("passwords", Err::<(), _>(WebxError::Locked("Password vault".into()))).is_none());
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, ErrorKind::Locked);
+        assert_eq!(event.message, "Password vault is locked");
+        assert!(event.hint.is_some());
+        assert_eq!(reporter.recent().len(), 1);
+    }
+}
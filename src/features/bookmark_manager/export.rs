@@ -0,0 +1,65 @@
+// Bookmark Export
+use crate::core::Bookmark;
+use crate::error::WebxError;
+use crate::utils::escape_html;
+use std::path::Path;
+
+/// Render bookmarks in the Netscape bookmark file format that other
+/// browsers import
+pub fn bookmarks_to_html(bookmarks: &[Bookmark]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <!-- This is an automatically generated file.\n     \
+         It will be read and overwritten.\n     \
+         DO NOT EDIT! -->\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for bookmark in bookmarks {
+        let keyword = match &bookmark.keyword {
+            Some(keyword) => format!(" SHORTCUTURL=\"{}\"", escape_html(keyword)),
+            None => String::new(),
+        };
+        html.push_str(&format!(
+            "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\"{}>{}</A>\n",
+            escape_html(&bookmark.url),
+            bookmark.created_at.timestamp(),
+            keyword,
+            escape_html(&bookmark.title)
+        ));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+/// Write bookmarks to an HTML file, returning how many were exported
+pub fn export_bookmarks_html(bookmarks: &[Bookmark], path: &Path) -> Result<usize, WebxError> {
+    std::fs::write(path, bookmarks_to_html(bookmarks))?;
+    Ok(bookmarks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_netscape_export_escapes_titles() {
+        let bookmarks = vec![Bookmark {
+            id: 1,
+            title: "Q&A <Forum>".to_string(),
+            url: "https://example.com/?a=1&b=\"2\"".to_string(),
+            favicon: None,
+            created_at: chrono::Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            keyword: None,
+        }];
+
+        let html = bookmarks_to_html(&bookmarks);
+        assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+        assert!(html.contains(
+            "<DT><A HREF=\"https://example.com/?a=1&amp;b=&quot;2&quot;\" ADD_DATE=\"1700000000\">Q&amp;A &lt;Forum&gt;</A>"
+        ));
+    }
+}
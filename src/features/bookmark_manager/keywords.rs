@@ -0,0 +1,84 @@
+// Bookmark Keywords
+use crate::core::Bookmark;
+use crate::error::WebxError;
+use std::collections::BTreeMap;
+
+/// Give a bookmark an address bar keyword, or take its keyword away with
+/// None. Keywords are single words without dots, colons or slashes, so
+/// they are never mistaken for addresses, and each opens one bookmark.
+pub fn set_bookmark_keyword(bookmarks: &mut [Bookmark], id: usize, keyword: Option<&str>) -> Result<(), WebxError> {
+    let keyword = keyword.map(str::trim).filter(|keyword| !keyword.is_empty()).map(str::to_lowercase);
+    if let Some(keyword) = &keyword {
+        if keyword.contains(|c: char| c.is_whitespace() || matches!(c, '.' | ':' | '/')) {
+            return Err(WebxError::Invalid(format!(
+                "\"{}\" cannot be a keyword: use one word without dots, colons or slashes",
+                keyword
+            )));
+        }
+        let taken = bookmarks.iter().find(|bookmark| {
+            bookmark.id != id && bookmark.keyword.as_deref().is_some_and(|other| other.eq_ignore_ascii_case(keyword))
+        });
+        if let Some(other) = taken {
+            return Err(WebxError::Invalid(format!("The keyword \"{}\" already opens {}", keyword, other.title)));
+        }
+    }
+
+    let bookmark = bookmarks
+        .iter_mut()
+        .find(|bookmark| bookmark.id == id)
+        .ok_or_else(|| WebxError::NotFound(format!("Bookmark {}", id)))?;
+    bookmark.keyword = keyword;
+    Ok(())
+}
+
+/// Keywords shared by several bookmarks, as imported or restored ones can
+/// be, with the IDs of those bookmarks. The address bar opens the first.
+pub fn keyword_collisions(bookmarks: &[Bookmark]) -> Vec<(String, Vec<usize>)> {
+    let mut owners: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for bookmark in bookmarks {
+        if let Some(keyword) = &bookmark.keyword {
+            owners.entry(keyword.to_lowercase()).or_default().push(bookmark.id);
+        }
+    }
+    owners.into_iter().filter(|(_, ids)| ids.len() > 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::BrowserState;
+
+    #[test]
+    fn test_keywords_expand_in_the_address_bar() {
+        let mut state = BrowserState::new();
+        state.add_bookmark("GitHub".to_string(), "https://github.com/%s".to_string());
+        state.add_bookmark("Wikipedia".to_string(), "https://en.wikipedia.org/w/index.php?search=%s".to_string());
+        state.add_bookmark("Mail".to_string(), "https://mail.example.com/".to_string());
+
+        set_bookmark_keyword(&mut state.bookmarks, 1, Some("GH")).unwrap();
+        set_bookmark_keyword(&mut state.bookmarks, 2, Some("w")).unwrap();
+        set_bookmark_keyword(&mut state.bookmarks, 3, Some("mail")).unwrap();
+        assert_eq!(state.bookmarks[0].keyword.as_deref(), Some("gh"));
+
+        assert_eq!(state.process_url("gh rust-lang/rust"), "https://github.com/rust-lang/rust");
+        assert_eq!(
+            state.process_url("w rust (programming language)"),
+            "https://en.wikipedia.org/w/index.php?search=rust%20%28programming%20language%29"
+        );
+        assert_eq!(state.process_url("mail"), "https://mail.example.com/");
+        // Keywords only count as the first word
+        assert!(state.process_url("ask gh").contains("q=ask+gh"));
+
+        // Taken and address-like keywords are refused
+        assert!(set_bookmark_keyword(&mut state.bookmarks, 3, Some("gh")).is_err());
+        assert!(set_bookmark_keyword(&mut state.bookmarks, 3, Some("example.com")).is_err());
+        assert!(set_bookmark_keyword(&mut state.bookmarks, 9, Some("nine")).is_err());
+        set_bookmark_keyword(&mut state.bookmarks, 1, Some("gh")).unwrap();
+
+        set_bookmark_keyword(&mut state.bookmarks, 3, None).unwrap();
+        assert_eq!(state.process_url("mail"), state.settings.search_engine.search_url("mail"));
+
+        state.bookmarks[2].keyword = Some("W".to_string());
+        assert_eq!(keyword_collisions(&state.bookmarks), vec![("w".to_string(), vec![2, 3])]);
+    }
+}
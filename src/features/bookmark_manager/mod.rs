@@ -1,4 +1,10 @@
 // Bookmark Manager Module - Placeholder
+pub mod export;
+pub mod keywords;
+
+pub use export::{bookmarks_to_html, export_bookmarks_html};
+pub use keywords::{keyword_collisions, set_bookmark_keyword};
+
 pub struct BookmarkManager;
 
 impl BookmarkManager {
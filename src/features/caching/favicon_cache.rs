@@ -0,0 +1,179 @@
+// Favicon Caching
+use crate::error::WebxError;
+use crate::features::caching::lru_cache::{CacheStats, LRUCache};
+use crate::utils::url_in_domain;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long a favicon is reused before it is downloaded again
+const FAVICON_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Largest icon kept in the cache
+const MAX_FAVICON_BYTES: usize = 256 * 1024;
+
+/// A downloaded site icon
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Favicon {
+    pub icon_url: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Favicon {
+    /// Data URL suitable for `Tab::favicon`
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.content_type, STANDARD.encode(&self.data))
+    }
+}
+
+/// Caches favicons per origin so tabs, history and bookmarks share them
+pub struct FaviconCache {
+    cache: LRUCache<String, Favicon>,
+}
+
+impl FaviconCache {
+    /// Create new favicon cache
+    pub fn new(max_size_mb: usize) -> Self {
+        Self {
+            cache: LRUCache::builder(max_size_mb * 1024 * 1024)
+                .time_to_live(FAVICON_TTL)
+                .weigher(|origin: &String, favicon: &Favicon| {
+                    origin.len() + favicon.icon_url.len() + favicon.data.len()
+                })
+                .build(),
+        }
+    }
+
+    /// Store the icon of the site a page belongs to
+    pub fn store(
+        &self,
+        page_url: &str,
+        icon_url: String,
+        content_type: String,
+        data: Vec<u8>,
+    ) -> Result<Favicon, WebxError> {
+        if !content_type.starts_with("image/") {
+            return Err(format!("Favicon has non-image content type {}", content_type).into());
+        }
+        if data.is_empty() || data.len() > MAX_FAVICON_BYTES {
+            return Err(format!("Favicon size {} bytes is out of range", data.len()).into());
+        }
+
+        let favicon = Favicon {
+            icon_url,
+            content_type,
+            data,
+            fetched_at: chrono::Utc::now(),
+        };
+        self.cache.insert(origin_key(page_url)?, favicon.clone());
+        Ok(favicon)
+    }
+
+    /// Get the cached icon for a page
+    pub fn get(&self, page_url: &str) -> Option<Favicon> {
+        self.cache.get(&origin_key(page_url).ok()?)
+    }
+
+    /// Get the cached icon for a page as a data URL
+    pub fn data_url(&self, page_url: &str) -> Option<String> {
+        self.get(page_url).map(|favicon| favicon.to_data_url())
+    }
+
+    /// Get a page's icon, downloading it when it is not cached. Without an
+    /// icon URL from the page, `/favicon.ico` of its origin is tried.
+    pub async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        page_url: &str,
+        icon_url: Option<&str>,
+    ) -> Result<Favicon, WebxError> {
+        if let Some(favicon) = self.get(page_url) {
+            return Ok(favicon);
+        }
+
+        let page = url::Url::parse(page_url)?;
+        let icon_url = match icon_url {
+            Some(icon_url) => page.join(icon_url)?,
+            None => page.join("/favicon.ico")?,
+        };
+
+        let response = client.get(icon_url.as_str()).send().await?.error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or(value).trim().to_string())
+            .unwrap_or_else(|| "image/x-icon".to_string());
+        let data = response.bytes().await?.to_vec();
+
+        self.store(page_url, icon_url.to_string(), content_type, data)
+    }
+
+    /// Forget the icon of a page's site
+    pub fn remove(&self, page_url: &str) -> Option<Favicon> {
+        self.cache.remove(&origin_key(page_url).ok()?)
+    }
+
+    /// Forget the icons of a domain and its subdomains
+    pub fn clear_site(&self, domain: &str) -> usize {
+        let before = self.cache.len();
+        self.cache.retain(|origin, _| !url_in_domain(origin, domain));
+        before - self.cache.len()
+    }
+
+    /// Clear all cached icons
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+
+    /// Get cache statistics
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+}
+
+// Private helper functions
+
+fn origin_key(page_url: &str) -> Result<String, WebxError> {
+    let url = url::Url::parse(page_url)?;
+    if url.host_str().is_none() {
+        return Err(format!("URL {} has no host", page_url).into());
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_favicons_shared_per_origin() {
+        let cache = FaviconCache::new(1);
+        cache
+            .store(
+                "https://example.com/page",
+                "https://example.com/favicon.png".to_string(),
+                "image/png".to_string(),
+                vec![1, 2, 3],
+            )
+            .unwrap();
+
+        let favicon = cache.get("https://example.com/other?q=1").unwrap();
+        assert_eq!(favicon.data, vec![1, 2, 3]);
+        assert_eq!(cache.data_url("https://example.com/").unwrap(), "data:image/png;base64,AQID");
+        assert!(cache.get("https://example.org/").is_none());
+        assert_eq!(cache.stats().hit_count, 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_icons() {
+        let cache = FaviconCache::new(1);
+        let page = "https://example.com/";
+        assert!(cache.store(page, String::new(), "text/html".to_string(), vec![1]).is_err());
+        assert!(cache.store(page, String::new(), "image/png".to_string(), Vec::new()).is_err());
+        assert!(cache.store("about:blank", String::new(), "image/png".to_string(), vec![1]).is_err());
+        assert!(cache.get(page).is_none());
+    }
+}
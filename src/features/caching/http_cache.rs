@@ -1,9 +1,13 @@
 // HTTP Response Caching
-use crate::features::caching::lru_cache::{LRUCache, CacheEntry};
+use crate::error::WebxError;
+use crate::features::caching::lru_cache::{CacheStats, LRUCache};
+use crate::features::system::metrics::Metrics;
+use crate::utils::url_in_domain;
 use flate2::{Compression, write::GzEncoder};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::Write;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// HTTP cache entry
@@ -27,6 +31,7 @@ pub struct HTTPCache {
     cache: LRUCache<String, HTTPCacheEntry>,
     default_ttl: Duration,
     compression_enabled: bool,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl HTTPCache {
@@ -34,12 +39,20 @@ impl HTTPCache {
     pub fn new(max_size_mb: usize, default_ttl_minutes: u64, compression_enabled: bool) -> Self {
         let max_size_bytes = max_size_mb * 1024 * 1024;
         Self {
-            cache: LRUCache::new(max_size_bytes),
+            cache: LRUCache::builder(max_size_bytes)
+                .weigher(|_, entry: &HTTPCacheEntry| calculate_entry_size(entry))
+                .build(),
             default_ttl: Duration::from_secs(default_ttl_minutes * 60),
             compression_enabled,
+            metrics: None,
         }
     }
 
+    /// Count lookups towards the cache hit rate in usage metrics
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
     /// Store HTTP response in cache
     pub fn store_response(
         &mut self,
@@ -47,7 +60,7 @@ impl HTTPCache {
         status_code: u16,
         headers: HashMap<String, String>,
         body: Vec<u8>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), WebxError> {
         let content_type = headers.get("content-type").cloned();
         let content_length = body.len();
         let cache_control = headers.get("cache-control").cloned();
@@ -80,8 +93,7 @@ impl HTTPCache {
             last_modified,
         };
 
-        let entry_size = self.calculate_entry_size(&entry);
-        self.cache.put(url, entry, entry_size);
+        self.cache.insert(url, entry);
 
         Ok(())
     }
@@ -89,16 +101,34 @@ impl HTTPCache {
     /// Get cached HTTP response
     pub fn get_response(&mut self, url: &str) -> Option<HTTPCacheEntry> {
         let key = url.to_string();
-        let entry = self.cache.get(&key)?.clone();
+        let mut entry = self.cache.get(&key);
         
         // Check if expired
-        if let Some(expires) = entry.expires {
+        if let Some(expires) = entry.as_ref().and_then(|entry| entry.expires) {
             if chrono::Utc::now() > expires {
                 self.cache.remove(&key);
+                entry = None;
+            }
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache_lookup(entry.is_some());
+        }
+        entry
+    }
+
+    /// Get a cached response, also accepting one that expired no longer
+    /// than `max_stale` ago. Stale entries are kept so they stay available.
+    pub fn get_response_allowing_stale(&mut self, url: &str, max_stale: Duration) -> Option<HTTPCacheEntry> {
+        let entry = self.cache.get(&url.to_string())?;
+
+        if let Some(expires) = entry.expires {
+            let max_stale = chrono::Duration::from_std(max_stale).unwrap_or(chrono::Duration::zero());
+            if chrono::Utc::now() > expires + max_stale {
                 return None;
             }
         }
-        
+
         Some(entry)
     }
 
@@ -127,41 +157,60 @@ impl HTTPCache {
     }
 
     /// Get cache statistics
-    pub fn stats(&mut self) -> HTTPCacheStats {
+    pub fn stats(&self) -> HTTPCacheStats {
         let lru_stats = self.cache.stats();
         HTTPCacheStats {
             entries: lru_stats.size,
             memory_usage_mb: lru_stats.current_memory_usage as f64 / (1024.0 * 1024.0),
             hit_count: lru_stats.hit_count,
+            miss_count: lru_stats.miss_count,
+            eviction_count: lru_stats.eviction_count,
             compression_ratio: self.calculate_compression_ratio(),
         }
     }
 
+    /// Get the underlying LRU cache statistics
+    pub fn lru_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
     /// Clear expired entries
     pub fn clear_expired(&mut self) {
         let now = chrono::Utc::now();
-        let keys_to_check: Vec<String> = self
-            .cache
-            .keys_lru_first()
-            .iter()
-            .map(|key| (**key).clone())
-            .collect();
-            
-        let mut expired_keys = Vec::new();
-            
-        for key in keys_to_check {
-            if let Some(entry) = self.cache.get(&key) {
-                if let Some(expires) = entry.expires {
-                    if now > expires {
-                        expired_keys.push(key);
-                    }
-                }
+        self.cache
+            .retain(|_, entry| entry.expires.is_none_or(|expires| now <= expires));
+    }
+
+    /// Remove the cached responses of a domain and its subdomains
+    pub fn clear_site(&mut self, domain: &str) -> usize {
+        let before = self.cache.len();
+        self.cache.retain(|url, _| !url_in_domain(url, domain));
+        before - self.cache.len()
+    }
+
+    /// URLs that must be evicted, least recently used first, to bring the
+    /// cache down to `max_bytes`
+    pub fn entries_over_size(&self, max_bytes: usize) -> Vec<String> {
+        let mut excess = self.cache.weighted_size().saturating_sub(max_bytes);
+        let mut urls = Vec::new();
+        for (url, entry) in self.cache.snapshot() {
+            if excess == 0 {
+                break;
             }
+            excess = excess.saturating_sub(entry.size);
+            urls.push(url);
         }
-            
-        for key in expired_keys {
-            self.cache.remove(&key);
+        urls
+    }
+
+    /// Evict least recently used entries until the cache fits in
+    /// `max_bytes`, returning how many were evicted
+    pub fn trim_to_size(&mut self, max_bytes: usize) -> usize {
+        let urls = self.entries_over_size(max_bytes);
+        for url in &urls {
+            self.cache.remove(url);
         }
+        urls.len()
     }
 
     /// Clear all cache entries
@@ -206,34 +255,12 @@ impl HTTPCache {
         None
     }
 
-    fn calculate_entry_size(&self, entry: &HTTPCacheEntry) -> usize {
-        let mut size = 0;
-        size += entry.url.len();
-        size += entry.body.len();
-        size += entry.headers.values().map(|v| v.len()).sum::<usize>();
-        size += entry.content_type.as_ref().map(|ct| ct.len()).unwrap_or(0);
-        size += entry.cache_control.as_ref().map(|cc| cc.len()).unwrap_or(0);
-        size += entry.etag.as_ref().map(|e| e.len()).unwrap_or(0);
-        size
-    }
-
-    fn calculate_compression_ratio(&mut self) -> f64 {
+    fn calculate_compression_ratio(&self) -> f64 {
         let mut total_original = 0;
         let mut total_compressed = 0;
-        
-        // Collect all entries first to avoid borrowing conflicts
-        let keys: Vec<_> = self.cache
-            .keys_mru_last()
-            .iter()
-            .map(|key| (**key).clone())
-            .collect();
-        
-        let entries: Vec<_> = keys
-            .iter()
-            .filter_map(|key| self.cache.get(key).cloned())
-            .collect();
-        
-        for cache_entry in entries {
+
+        for (_, entry) in self.cache.snapshot() {
+            let cache_entry = entry.value;
             total_original += cache_entry.body.len();
             if let Some(compressed) = &cache_entry.compressed_body {
                 total_compressed += compressed.len();
@@ -250,12 +277,26 @@ impl HTTPCache {
     }
 }
 
+/// Weight of an entry against the cache capacity
+fn calculate_entry_size(entry: &HTTPCacheEntry) -> usize {
+    let mut size = 0;
+    size += entry.url.len();
+    size += entry.body.len();
+    size += entry.headers.values().map(|v| v.len()).sum::<usize>();
+    size += entry.content_type.as_ref().map(|ct| ct.len()).unwrap_or(0);
+    size += entry.cache_control.as_ref().map(|cc| cc.len()).unwrap_or(0);
+    size += entry.etag.as_ref().map(|e| e.len()).unwrap_or(0);
+    size
+}
+
 /// HTTP cache statistics
 #[derive(Debug, Clone)]
 pub struct HTTPCacheStats {
     pub entries: usize,
     pub memory_usage_mb: f64,
     pub hit_count: u64,
+    pub miss_count: u64,
+    pub eviction_count: u64,
     pub compression_ratio: f64,
 }
 
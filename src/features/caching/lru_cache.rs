@@ -1,7 +1,21 @@
 // LRU (Least Recently Used) Cache Implementation
+use crate::utils::LockExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Smallest capacity worth giving its own shard; smaller caches use a single
+/// shard so eviction follows exact LRU order
+const MIN_SHARD_WEIGHT: usize = 1024 * 1024;
+/// Upper bound on shards picked automatically
+const MAX_SHARDS: usize = 16;
+
+/// Computes the weight an entry counts against the cache capacity
+pub type Weigher<K, V> = Arc<dyn Fn(&K, &V) -> usize + Send + Sync>;
 
 /// LRU Cache entry with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,126 +24,327 @@ pub struct CacheEntry<T> {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub access_count: u64,
     pub size: usize,
+    pub inserted_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl<T> CacheEntry<T> {
+    /// Check if the entry's time to live has passed
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// Builder for an `LRUCache`
+pub struct LRUCacheBuilder<K, V> {
+    max_size: usize,
+    shards: Option<usize>,
+    time_to_live: Option<Duration>,
+    weigher: Weigher<K, V>,
 }
 
-/// LRU Cache implementation
+impl<K, V> LRUCacheBuilder<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Number of independently locked shards (rounded up to at least one)
+    pub fn shards(mut self, shards: usize) -> Self {
+        self.shards = Some(shards.max(1));
+        self
+    }
+
+    /// Default time to live of entries
+    pub fn time_to_live(mut self, ttl: Duration) -> Self {
+        self.time_to_live = Some(ttl);
+        self
+    }
+
+    /// Weigh entries added with `insert`. Without a weigher every entry
+    /// weighs 1 and the capacity is an entry count.
+    pub fn weigher(mut self, weigher: impl Fn(&K, &V) -> usize + Send + Sync + 'static) -> Self {
+        self.weigher = Arc::new(weigher);
+        self
+    }
+
+    /// Build the cache
+    pub fn build(self) -> LRUCache<K, V> {
+        let shard_count = self
+            .shards
+            .unwrap_or_else(|| (self.max_size / MIN_SHARD_WEIGHT).clamp(1, MAX_SHARDS));
+        let shard_weight = self.max_size.div_ceil(shard_count);
+
+        LRUCache {
+            shards: (0..shard_count)
+                .map(|_| {
+                    Mutex::new(Shard {
+                        entries: HashMap::new(),
+                        order: BTreeMap::new(),
+                        weight: 0,
+                        max_weight: shard_weight,
+                    })
+                })
+                .collect(),
+            hasher: RandomState::new(),
+            weigher: self.weigher,
+            time_to_live: self.time_to_live,
+            max_size: self.max_size,
+            clock: AtomicU64::new(0),
+            metrics: CacheMetrics::default(),
+        }
+    }
+}
+
+/// Concurrent LRU cache. Keys are spread over independently locked shards,
+/// each evicting its least recently used entries once over its share of the
+/// capacity, so all methods take `&self` and the cache can be shared.
 pub struct LRUCache<K, V> {
-    cache: HashMap<K, CacheEntry<V>>,
-    access_order: Vec<K>, // Most recent at the end
+    shards: Vec<Mutex<Shard<K, V>>>,
+    hasher: RandomState,
+    weigher: Weigher<K, V>,
+    time_to_live: Option<Duration>,
     max_size: usize,
-    current_size: usize,
+    clock: AtomicU64,
+    metrics: CacheMetrics,
 }
 
 impl<K, V> LRUCache<K, V>
 where
-    K: Clone + Eq + Hash + std::fmt::Debug,
-    V: Clone + std::fmt::Debug,
+    K: Clone + Eq + Hash,
+    V: Clone,
 {
     /// Create new LRU cache with maximum size
     pub fn new(max_size: usize) -> Self {
-        Self {
-            cache: HashMap::new(),
-            access_order: Vec::new(),
+        Self::builder(max_size).build()
+    }
+
+    /// Start building a cache with maximum size
+    pub fn builder(max_size: usize) -> LRUCacheBuilder<K, V> {
+        LRUCacheBuilder {
             max_size,
-            current_size: 0,
+            shards: None,
+            time_to_live: None,
+            weigher: Arc::new(|_, _| 1),
         }
     }
 
-    /// Insert a value into the cache
-    pub fn put(&mut self, key: K, value: V, size: usize) -> Option<V> {
-        let timestamp = chrono::Utc::now();
-        
-        // If key already exists, remove old entry
-        if let Some(old_entry) = self.cache.remove(&key) {
-            self.current_size -= old_entry.size;
-            self.access_order.retain(|k| k != &key);
-            return Some(old_entry.value);
+    /// Insert a value with an explicit size, returning the value it replaced
+    pub fn put(&self, key: K, value: V, size: usize) -> Option<V> {
+        self.insert_entry(key, value, size, self.time_to_live)
+    }
+
+    /// Insert a value weighed by the cache's weigher
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let size = (self.weigher)(&key, &value);
+        self.insert_entry(key, value, size, self.time_to_live)
+    }
+
+    /// Insert a value with its own time to live (`None` never expires)
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        let size = (self.weigher)(&key, &value);
+        self.insert_entry(key, value, size, ttl)
+    }
+
+    /// Get a value from the cache, marking it most recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        let now = chrono::Utc::now();
+        let tick = self.next_tick();
+        let mut shard = self.shard(key);
+
+        if shard.entries.get(key).is_some_and(|slot| slot.entry.is_expired(now)) {
+            shard.remove(key);
+            self.metrics.expirations.fetch_add(1, Ordering::Relaxed);
         }
 
-        // Evict entries if needed
-        while self.current_size + size > self.max_size && !self.access_order.is_empty() {
-            if let Some(lru_key) = self.access_order.first() {
-                if let Some(entry) = self.cache.remove(lru_key) {
-                    self.current_size -= entry.size;
-                    self.access_order.remove(0);
-                }
+        match shard.touch(key, tick, now) {
+            Some(value) => {
+                self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            None => {
+                self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+                None
             }
         }
+    }
 
-        // Insert new entry
-        let entry = CacheEntry {
-            value: value.clone(),
-            timestamp,
-            access_count: 1,
-            size,
-        };
-
-        self.cache.insert(key.clone(), entry);
-        self.access_order.push(key);
-        self.current_size += size;
+    /// Get a value without changing its recency or the hit/miss metrics
+    pub fn peek(&self, key: &K) -> Option<V> {
+        let now = chrono::Utc::now();
+        self.shard(key)
+            .entries
+            .get(key)
+            .filter(|slot| !slot.entry.is_expired(now))
+            .map(|slot| slot.entry.value.clone())
+    }
 
-        None
+    /// Check if cache contains key
+    pub fn contains_key(&self, key: &K) -> bool {
+        let now = chrono::Utc::now();
+        self.shard(key)
+            .entries
+            .get(key)
+            .is_some_and(|slot| !slot.entry.is_expired(now))
     }
 
-    /// Get a value from the cache
-    pub fn get(&mut self, key: &K) -> Option<&V> {
-        if let Some(entry) = self.cache.get_mut(key) {
-            entry.access_count += 1;
-            entry.timestamp = chrono::Utc::now();
+    /// Remove a key from cache
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.shard(key).remove(key).map(|entry| entry.value)
+    }
 
-            // Move key to end of access order (most recent)
-            if let Some(pos) = self.access_order.iter().position(|k| k == key) {
-                self.access_order.remove(pos);
-                self.access_order.push(key.clone());
+    /// Keep only the entries the predicate accepts
+    pub fn retain(&self, mut keep: impl FnMut(&K, &V) -> bool) {
+        for shard in &self.shards {
+            let mut shard = shard.lock_or_recover();
+            let removed: Vec<K> = shard
+                .entries
+                .iter()
+                .filter(|(key, slot)| !keep(key, &slot.entry.value))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in removed {
+                shard.remove(&key);
             }
+        }
+    }
 
-            Some(&entry.value)
-        } else {
-            None
+    /// Drop every expired entry, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let now = chrono::Utc::now();
+        let mut purged = 0;
+        for shard in &self.shards {
+            let mut shard = shard.lock_or_recover();
+            let expired: Vec<K> = shard
+                .entries
+                .iter()
+                .filter(|(_, slot)| slot.entry.is_expired(now))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in expired {
+                shard.remove(&key);
+                purged += 1;
+            }
         }
+        self.metrics.expirations.fetch_add(purged as u64, Ordering::Relaxed);
+        purged
     }
 
-    /// Check if cache contains key
-    pub fn contains_key(&self, key: &K) -> bool {
-        self.cache.contains_key(key)
+    /// Number of entries, including expired ones not yet purged
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock_or_recover().entries.len()).sum()
     }
 
-    /// Remove a key from cache
-    pub fn remove(&mut self, key: &K) -> Option<V> {
-        if let Some(entry) = self.cache.remove(key) {
-            self.current_size -= entry.size;
-            self.access_order.retain(|k| k != key);
-            Some(entry.value)
-        } else {
-            None
-        }
+    /// Check if the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total weight of all entries
+    pub fn weighted_size(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock_or_recover().weight).sum()
     }
 
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
         CacheStats {
-            size: self.cache.len(),
-            current_memory_usage: self.current_size,
+            size: self.len(),
+            current_memory_usage: self.weighted_size(),
             max_memory_usage: self.max_size,
-            hit_count: self.cache.values().map(|e| e.access_count).sum(),
+            hit_count: self.metrics.hits.load(Ordering::Relaxed),
+            miss_count: self.metrics.misses.load(Ordering::Relaxed),
+            insert_count: self.metrics.insertions.load(Ordering::Relaxed),
+            eviction_count: self.metrics.evictions.load(Ordering::Relaxed),
+            expired_count: self.metrics.expirations.load(Ordering::Relaxed),
         }
     }
 
+    /// Reset the hit, miss, insert, eviction and expiry counters
+    pub fn reset_stats(&self) {
+        self.metrics.hits.store(0, Ordering::Relaxed);
+        self.metrics.misses.store(0, Ordering::Relaxed);
+        self.metrics.insertions.store(0, Ordering::Relaxed);
+        self.metrics.evictions.store(0, Ordering::Relaxed);
+        self.metrics.expirations.store(0, Ordering::Relaxed);
+    }
+
     /// Clear the cache
-    pub fn clear(&mut self) {
-        self.cache.clear();
-        self.access_order.clear();
-        self.current_size = 0;
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            let mut shard = shard.lock_or_recover();
+            shard.entries.clear();
+            shard.order.clear();
+            shard.weight = 0;
+        }
+    }
+
+    /// Copy the live entries, least recently used first. Shards are locked
+    /// one at a time and released before returning, so the snapshot can be
+    /// iterated while the cache keeps changing.
+    pub fn snapshot(&self) -> Vec<(K, CacheEntry<V>)> {
+        let now = chrono::Utc::now();
+        let mut entries: Vec<(u64, K, CacheEntry<V>)> = Vec::new();
+        for shard in &self.shards {
+            let shard = shard.lock_or_recover();
+            entries.extend(
+                shard
+                    .entries
+                    .iter()
+                    .filter(|(_, slot)| !slot.entry.is_expired(now))
+                    .map(|(key, slot)| (slot.tick, key.clone(), slot.entry.clone())),
+            );
+        }
+        entries.sort_by_key(|(tick, _, _)| *tick);
+        entries.into_iter().map(|(_, key, entry)| (key, entry)).collect()
     }
 
     /// Get keys in access order (LRU first)
-    pub fn keys_lru_first(&self) -> Vec<&K> {
-        self.access_order.iter().collect()
+    pub fn keys_lru_first(&self) -> Vec<K> {
+        self.snapshot().into_iter().map(|(key, _)| key).collect()
     }
 
-    /// Get keys in access order (MRU last)
-    pub fn keys_mru_last(&self) -> Vec<&K> {
-        self.access_order.iter().rev().collect()
+    // Private helper methods
+
+    fn insert_entry(&self, key: K, value: V, size: usize, ttl: Option<Duration>) -> Option<V> {
+        let now = chrono::Utc::now();
+        let tick = self.next_tick();
+        let mut shard = self.shard(&key);
+        let previous = shard.remove(&key).map(|entry| entry.value);
+
+        // An entry larger than a whole shard would only flush it
+        if size > shard.max_weight {
+            return previous;
+        }
+
+        while shard.weight + size > shard.max_weight {
+            if shard.evict_lru().is_none() {
+                break;
+            }
+            self.metrics.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let entry = CacheEntry {
+            value,
+            timestamp: now,
+            access_count: 0,
+            size,
+            inserted_at: now,
+            expires_at: ttl.and_then(|ttl| chrono::Duration::from_std(ttl).ok().map(|ttl| now + ttl)),
+        };
+        shard.order.insert(tick, key.clone());
+        shard.entries.insert(key, Slot { entry, tick });
+        shard.weight += size;
+        self.metrics.insertions.fetch_add(1, Ordering::Relaxed);
+
+        previous
+    }
+
+    fn shard(&self, key: &K) -> MutexGuard<'_, Shard<K, V>> {
+        let index = (self.hasher.hash_one(key) % self.shards.len() as u64) as usize;
+        self.shards[index].lock_or_recover()
+    }
+
+    fn next_tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
     }
 }
 
@@ -140,15 +355,20 @@ pub struct CacheStats {
     pub current_memory_usage: usize,
     pub max_memory_usage: usize,
     pub hit_count: u64,
+    pub miss_count: u64,
+    pub insert_count: u64,
+    pub eviction_count: u64,
+    pub expired_count: u64,
 }
 
 impl CacheStats {
     /// Get hit ratio (0.0 to 1.0)
     pub fn hit_ratio(&self) -> f64 {
-        if self.size == 0 {
+        let lookups = self.hit_count + self.miss_count;
+        if lookups == 0 {
             0.0
         } else {
-            self.hit_count as f64 / self.size as f64
+            self.hit_count as f64 / lookups as f64
         }
     }
 
@@ -162,49 +382,152 @@ impl CacheStats {
     }
 }
 
+#[derive(Default)]
+struct CacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    insertions: AtomicU64,
+    evictions: AtomicU64,
+    expirations: AtomicU64,
+}
+
+struct Slot<V> {
+    entry: CacheEntry<V>,
+    tick: u64,
+}
+
+/// One independently locked part of the cache. `order` maps each entry's
+/// last access tick to its key, so the first item is the LRU entry.
+struct Shard<K, V> {
+    entries: HashMap<K, Slot<V>>,
+    order: BTreeMap<u64, K>,
+    weight: usize,
+    max_weight: usize,
+}
+
+impl<K, V> Shard<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn touch(&mut self, key: &K, tick: u64, now: chrono::DateTime<chrono::Utc>) -> Option<V> {
+        let slot = self.entries.get_mut(key)?;
+        self.order.remove(&slot.tick);
+        self.order.insert(tick, key.clone());
+        slot.tick = tick;
+        slot.entry.timestamp = now;
+        slot.entry.access_count += 1;
+        Some(slot.entry.value.clone())
+    }
+
+    fn remove(&mut self, key: &K) -> Option<CacheEntry<V>> {
+        let slot = self.entries.remove(key)?;
+        self.order.remove(&slot.tick);
+        self.weight -= slot.entry.size;
+        Some(slot.entry)
+    }
+
+    fn evict_lru(&mut self) -> Option<K> {
+        let (_, key) = self.order.pop_first()?;
+        if let Some(slot) = self.entries.remove(&key) {
+            self.weight -= slot.entry.size;
+        }
+        Some(key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_lru_cache_basic_operations() {
-        let mut cache = LRUCache::new(100);
-        
+        let cache = LRUCache::new(100);
+
         // Test put and get
         cache.put("key1", "value1", 10);
-        assert_eq!(cache.get(&"key1"), Some(&"value1"));
-        
+        assert_eq!(cache.get(&"key1"), Some("value1"));
+
         cache.put("key2", "value2", 20);
-        assert_eq!(cache.get(&"key2"), Some(&"value2"));
-        
+        assert_eq!(cache.get(&"key2"), Some("value2"));
+
         // Test capacity and eviction
         cache.put("key3", "value3", 80); // This should evict key1
         assert_eq!(cache.get(&"key1"), None);
-        assert_eq!(cache.get(&"key3"), Some(&"value3"));
-        
+        assert_eq!(cache.get(&"key3"), Some("value3"));
+
         // Test stats
         let stats = cache.stats();
         assert_eq!(stats.size, 2);
         assert_eq!(stats.current_memory_usage, 100);
+        assert_eq!(stats.eviction_count, 1);
+        assert_eq!(stats.hit_ratio(), 0.75);
+
+        // Replacing a key returns the old value and keeps the new one
+        assert_eq!(cache.put("key3", "value3b", 10), Some("value3"));
+        assert_eq!(cache.get(&"key3"), Some("value3b"));
+        assert_eq!(cache.weighted_size(), 30);
     }
 
     #[test]
     fn test_lru_eviction() {
-        let mut cache = LRUCache::new(50);
-        
+        let cache = LRUCache::new(50);
+
         cache.put("a", "value_a", 10);
         cache.put("b", "value_b", 10);
         cache.put("c", "value_c", 10);
-        
+
         // Access 'a' to make it MRU
         cache.get(&"a");
-        
+
         // Add new item that should evict LRU (b)
         cache.put("d", "value_d", 30);
-        
-        assert_eq!(cache.get(&"a"), Some(&"value_a")); // Still exists
+
+        assert_eq!(cache.get(&"a"), Some("value_a")); // Still exists
         assert_eq!(cache.get(&"b"), None);             // Evicted
-        assert_eq!(cache.get(&"c"), Some(&"value_c")); // Still exists
-        assert_eq!(cache.get(&"d"), Some(&"value_d")); // New item
+        assert_eq!(cache.get(&"c"), Some("value_c")); // Still exists
+        assert_eq!(cache.get(&"d"), Some("value_d")); // New item
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_ttl_weigher_and_snapshot() {
+        let cache: LRUCache<String, Vec<u8>> = LRUCache::builder(64)
+            .shards(4)
+            .time_to_live(Duration::from_millis(30))
+            .weigher(|_, value: &Vec<u8>| value.len())
+            .build();
+
+        cache.insert("short".to_string(), vec![0; 8]);
+        cache.insert_with_ttl("forever".to_string(), vec![0; 8], None);
+        cache.insert("too-big".to_string(), vec![0; 17]);
+        assert_eq!(cache.weighted_size(), 16);
+        assert!(!cache.contains_key(&"too-big".to_string()));
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(cache.get(&"short".to_string()), None);
+        assert_eq!(cache.get(&"forever".to_string()).map(|v| v.len()), Some(8));
+        assert_eq!(cache.stats().expired_count, 1);
+
+        let snapshot = cache.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].1.access_count, 1);
+
+        // Shards can be used from many threads at once
+        let cache = Arc::new(cache);
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        cache.insert(format!("{}-{}", t, i), vec![0; 1]);
+                        cache.get(&format!("{}-{}", t, i / 2));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(cache.weighted_size() <= 64);
+    }
+}
@@ -2,7 +2,13 @@
 pub mod lru_cache;
 pub mod http_cache;
 pub mod offline_storage;
+pub mod favicon_cache;
+pub mod speculative;
+pub mod site_storage;
 
 pub use lru_cache::LRUCache;
 pub use http_cache::HTTPCache;
-pub use offline_storage::OfflineStorage;
\ No newline at end of file
+pub use offline_storage::OfflineStorage;
+pub use favicon_cache::FaviconCache;
+pub use site_storage::{OriginUsage, QuotaPolicy, SiteStorageManager};
+pub use speculative::{LikelyOrigin, OriginRecord, SpeculativeConfig, SpeculativeLoader, SpeculativeMode, WarmReport};
\ No newline at end of file
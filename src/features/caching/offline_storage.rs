@@ -1,4 +1,5 @@
 // Offline Storage for Web Pages
+use crate::error::WebxError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -35,7 +36,7 @@ pub struct OfflineStorage {
 
 impl OfflineStorage {
     /// Create new offline storage
-    pub fn new(storage_dir: Option<PathBuf>, max_size_mb: usize) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(storage_dir: Option<PathBuf>, max_size_mb: usize) -> Result<Self, WebxError> {
         let storage_dir = storage_dir.unwrap_or_else(|| {
             let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
             path.push("webx");
@@ -66,8 +67,9 @@ impl OfflineStorage {
         title: &str,
         html_content: &str,
         resources: Vec<(String, String, Vec<u8>)>, // (url, content_type, data)
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let page_id = format!("page_{}", uuid::Uuid::new_v4());
+    ) -> Result<String, WebxError> {
+        // Pages are found again by their URL, so saving a page again replaces it
+        let page_id = page_id(url);
         let page_dir = self.storage_dir.join(&page_id);
         fs::create_dir_all(&page_dir)?;
 
@@ -119,7 +121,7 @@ impl OfflineStorage {
     }
 
     /// Load offline page
-    pub fn load_page(&self, url: &str) -> Result<Option<OfflinePage>, Box<dyn std::error::Error>> {
+    pub fn load_page(&self, url: &str) -> Result<Option<OfflinePage>, WebxError> {
         if let Some(manifest) = self.manifests.get(url) {
             let page_dir = self.storage_dir.join(self.get_page_id_from_url(url)?);
             
@@ -154,20 +156,16 @@ impl OfflineStorage {
 
     /// List all offline pages
     pub fn list_pages(&self) -> Vec<OfflinePageInfo> {
-        self.manifests
-            .values()
-            .map(|manifest| OfflinePageInfo {
-                url: manifest.url.clone(),
-                title: manifest.title.clone(),
-                saved_at: manifest.saved_at,
-                resource_count: manifest.resources.len(),
-                total_size: manifest.resources.iter().map(|r| r.size).sum(),
-            })
-            .collect()
+        self.manifests.values().map(page_info).collect()
+    }
+
+    /// Information about a page saved for offline viewing
+    pub fn page_info(&self, url: &str) -> Option<OfflinePageInfo> {
+        self.manifests.get(url).map(page_info)
     }
 
     /// Delete offline page
-    pub fn delete_page(&mut self, url: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    pub fn delete_page(&mut self, url: &str) -> Result<bool, WebxError> {
         if let Some(_manifest) = self.manifests.remove(url) {
             let page_id = self.get_page_id_from_url(url)?;
             let page_dir = self.storage_dir.join(&page_id);
@@ -197,7 +195,7 @@ impl OfflineStorage {
     }
 
     /// Clear all offline storage
-    pub fn clear_all(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn clear_all(&mut self) -> Result<(), WebxError> {
         fs::remove_dir_all(&self.storage_dir)?;
         fs::create_dir_all(&self.storage_dir)?;
         self.manifests.clear();
@@ -207,7 +205,7 @@ impl OfflineStorage {
 
     // Private helper methods
     
-    fn load_manifests(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn load_manifests(&mut self) -> Result<(), WebxError> {
         let index_path = self.storage_dir.join("manifests.json");
         if index_path.exists() {
             let content = fs::read_to_string(&index_path)?;
@@ -217,14 +215,14 @@ impl OfflineStorage {
         Ok(())
     }
 
-    fn save_manifest_index(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn save_manifest_index(&self) -> Result<(), WebxError> {
         let index_path = self.storage_dir.join("manifests.json");
         let content = serde_json::to_string_pretty(&self.manifests)?;
         fs::write(index_path, content)?;
         Ok(())
     }
 
-    fn update_storage_size(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn update_storage_size(&mut self) -> Result<(), WebxError> {
         self.current_size = 0;
         for manifest in self.manifests.values() {
             self.current_size += manifest.resources.iter().map(|r| r.size).sum::<usize>();
@@ -234,7 +232,7 @@ impl OfflineStorage {
         Ok(())
     }
 
-    fn enforce_storage_limits(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn enforce_storage_limits(&mut self) -> Result<(), WebxError> {
         while self.current_size > self.max_storage_size && !self.manifests.is_empty() {
             // Remove oldest page
             if let Some(oldest_url) = self.manifests
@@ -282,14 +280,11 @@ impl OfflineStorage {
         format!("{:x}", md5::compute(data))
     }
 
-    fn get_page_id_from_url(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    fn get_page_id_from_url(&self, url: &str) -> Result<String, WebxError> {
         self.manifests
             .get(url)
-            .map(|manifest| {
-                // Extract page ID from manifest path or regenerate
-                format!("page_{:x}", md5::compute(&manifest.url))
-            })
-            .ok_or_else(|| "Page not found".into())
+            .map(|manifest| page_id(&manifest.url))
+            .ok_or_else(|| WebxError::NotFound(format!("Offline page {}", url)))
     }
 }
 
@@ -321,4 +316,21 @@ pub struct OfflineStorageStats {
     pub max_size_mb: f64,
     pub oldest_page: Option<chrono::DateTime<chrono::Utc>>,
     pub newest_page: Option<chrono::DateTime<chrono::Utc>>,
-}
\ No newline at end of file
+}
+
+// Private helper functions
+
+/// Directory name of a saved page
+fn page_id(url: &str) -> String {
+    format!("page_{:x}", md5::compute(url))
+}
+
+fn page_info(manifest: &OfflineManifest) -> OfflinePageInfo {
+    OfflinePageInfo {
+        url: manifest.url.clone(),
+        title: manifest.title.clone(),
+        saved_at: manifest.saved_at,
+        resource_count: manifest.resources.len(),
+        total_size: manifest.resources.iter().map(|r| r.size).sum(),
+    }
+}
@@ -0,0 +1,451 @@
+// Per-Site Storage Quotas
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::utils::{host_in_domain, LockExt};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// How deep to look for per-origin entries in a web view data directory
+const MAX_SCAN_DEPTH: usize = 6;
+
+/// How much sites may keep in local storage and IndexedDB
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct QuotaPolicy {
+    /// Quota of a site without its own
+    pub default_quota_mb: u64,
+    /// Quotas of single sites, by origin
+    pub site_quotas_mb: HashMap<String, u64>,
+    /// Origins whose storage is persistent: never evicted or cleared on exit
+    pub persistent_origins: Vec<String>,
+    /// Budget for all sites together; the least recently written
+    /// non-persistent sites are evicted past it
+    pub total_quota_mb: Option<u64>,
+}
+
+impl Default for QuotaPolicy {
+    fn default() -> Self {
+        Self {
+            default_quota_mb: 1024,
+            site_quotas_mb: HashMap::new(),
+            persistent_origins: Vec::new(),
+            total_quota_mb: None,
+        }
+    }
+}
+
+/// Storage one origin uses in the web view
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OriginUsage {
+    pub origin: String,
+    pub local_storage_bytes: u64,
+    pub indexed_db_bytes: u64,
+    /// Cache storage, service worker and other per-origin data
+    pub other_bytes: u64,
+    pub quota_bytes: u64,
+    pub persistent: bool,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl OriginUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.local_storage_bytes + self.indexed_db_bytes + self.other_bytes
+    }
+
+    pub fn over_quota(&self) -> bool {
+        self.total_bytes() > self.quota_bytes
+    }
+}
+
+/// Tracks what sites keep in the web view's local storage and IndexedDB,
+/// read from the engines' per-origin files and directories such as
+/// `https_example.com_0.localstorage` or
+/// `https_example.com_0.indexeddb.leveldb`, and clears it per site
+pub struct SiteStorageManager {
+    policy: Mutex<QuotaPolicy>,
+    policy_path: PathBuf,
+    data_dirs: Vec<PathBuf>,
+}
+
+impl SiteStorageManager {
+    /// Create new storage manager over the web view data directories
+    pub fn new(config_dir: Option<PathBuf>, data_dirs: Vec<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let policy_path = config_dir.join("site_storage.json");
+        let policy = match std::fs::read(&policy_path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => QuotaPolicy::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            policy: Mutex::new(policy),
+            policy_path,
+            data_dirs,
+        })
+    }
+
+    /// Where the web engines keep site data when the web view is given no
+    /// data directory of its own
+    pub fn default_data_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        #[cfg(target_os = "linux")]
+        dirs.extend(dirs::data_dir().map(|dir| dir.join("webx")));
+        #[cfg(target_os = "macos")]
+        dirs.extend(dirs::home_dir().map(|dir| dir.join("Library").join("WebKit").join("webx")));
+        #[cfg(target_os = "windows")]
+        dirs.extend(dirs::data_local_dir().map(|dir| dir.join("webx").join("EBWebView")));
+        dirs
+    }
+
+    pub fn policy(&self) -> QuotaPolicy {
+        self.policy.lock_or_recover().clone()
+    }
+
+    pub fn set_policy(&self, policy: QuotaPolicy) -> Result<(), WebxError> {
+        *self.policy.lock_or_recover() = policy;
+        self.save_policy()
+    }
+
+    /// Give a site its own quota, or None for the default one
+    pub fn set_site_quota(&self, origin: &str, quota_mb: Option<u64>) -> Result<(), WebxError> {
+        let origin = normalize_origin(origin);
+        {
+            let mut policy = self.policy.lock_or_recover();
+            match quota_mb {
+                Some(quota_mb) => {
+                    policy.site_quotas_mb.insert(origin, quota_mb);
+                }
+                None => {
+                    policy.site_quotas_mb.remove(&origin);
+                }
+            }
+        }
+        self.save_policy()
+    }
+
+    /// Make a site's storage persistent, as navigator.storage.persist()
+    /// asks, or best-effort again
+    pub fn set_persistent(&self, origin: &str, persistent: bool) -> Result<(), WebxError> {
+        let origin = normalize_origin(origin);
+        {
+            let mut policy = self.policy.lock_or_recover();
+            policy.persistent_origins.retain(|other| *other != origin);
+            if persistent {
+                policy.persistent_origins.push(origin);
+            }
+        }
+        self.save_policy()
+    }
+
+    pub fn is_persistent(&self, origin: &str) -> bool {
+        let origin = normalize_origin(origin);
+        self.policy.lock_or_recover().persistent_origins.contains(&origin)
+    }
+
+    /// Quota of a site in bytes
+    pub fn quota_for(&self, origin: &str) -> u64 {
+        let origin = normalize_origin(origin);
+        let policy = self.policy.lock_or_recover();
+        policy.site_quotas_mb.get(&origin).copied().unwrap_or(policy.default_quota_mb) * 1024 * 1024
+    }
+
+    /// Storage of every site, largest first
+    pub fn usage(&self) -> Vec<OriginUsage> {
+        let mut usage: HashMap<String, OriginUsage> = HashMap::new();
+        for entry in self.entries() {
+            let origin_usage = usage.entry(entry.origin.clone()).or_insert_with(|| OriginUsage {
+                quota_bytes: self.quota_for(&entry.origin),
+                persistent: self.is_persistent(&entry.origin),
+                origin: entry.origin.clone(),
+                local_storage_bytes: 0,
+                indexed_db_bytes: 0,
+                other_bytes: 0,
+                last_modified: DateTime::<Utc>::MIN_UTC,
+            });
+            match entry.kind {
+                StorageKind::LocalStorage => origin_usage.local_storage_bytes += entry.bytes,
+                StorageKind::IndexedDb => origin_usage.indexed_db_bytes += entry.bytes,
+                StorageKind::Other => origin_usage.other_bytes += entry.bytes,
+            }
+            origin_usage.last_modified = origin_usage.last_modified.max(DateTime::<Utc>::from(entry.modified));
+        }
+
+        let mut usage: Vec<OriginUsage> = usage.into_values().collect();
+        usage.sort_by(|a, b| b.total_bytes().cmp(&a.total_bytes()).then_with(|| a.origin.cmp(&b.origin)));
+        usage
+    }
+
+    /// Storage of one site
+    pub fn usage_for(&self, origin: &str) -> Option<OriginUsage> {
+        let origin = normalize_origin(origin);
+        self.usage().into_iter().find(|usage| usage.origin == origin)
+    }
+
+    /// Clear one origin's storage, returning the bytes freed
+    pub fn clear_origin(&self, origin: &str) -> Result<u64, WebxError> {
+        let origin = normalize_origin(origin);
+        self.remove_entries(|entry| entry.origin == origin)
+            .map(|(_, bytes)| bytes)
+    }
+
+    /// Clear the storage of a site and its subdomains, returning the
+    /// number of origins cleared
+    pub fn clear_site(&self, domain: &str) -> Result<usize, WebxError> {
+        self.remove_entries(|entry| origin_in_domain(&entry.origin, domain))
+            .map(|(origins, _)| origins)
+    }
+
+    /// Clear every site's storage except persistent origins and sites in
+    /// `keep_domains`, returning the number of origins cleared
+    pub fn clear_all_except(&self, keep_domains: &[String]) -> Result<usize, WebxError> {
+        self.remove_entries(|entry| self.clearable(&entry.origin, keep_domains))
+            .map(|(origins, _)| origins)
+    }
+
+    /// Origins `clear_all_except` would clear
+    pub fn count_all_except(&self, keep_domains: &[String]) -> usize {
+        self.usage()
+            .iter()
+            .filter(|usage| self.clearable(&usage.origin, keep_domains))
+            .count()
+    }
+
+    /// Sites `enforce_quotas` would clear: those over their quota, then the
+    /// least recently written ones until all fit the total budget.
+    /// Persistent sites are kept.
+    pub fn quota_evictions(&self) -> Vec<String> {
+        let (mut over, mut within): (Vec<OriginUsage>, Vec<OriginUsage>) = self
+            .usage()
+            .into_iter()
+            .filter(|site| !site.persistent)
+            .partition(OriginUsage::over_quota);
+
+        if let Some(total_mb) = self.policy.lock_or_recover().total_quota_mb {
+            let budget = total_mb * 1024 * 1024;
+            let mut total: u64 = self
+                .usage()
+                .iter()
+                .filter(|site| site.persistent || !site.over_quota())
+                .map(OriginUsage::total_bytes)
+                .sum();
+            within.sort_by_key(|site| site.last_modified);
+            for site in within {
+                if total <= budget {
+                    break;
+                }
+                total -= site.total_bytes();
+                over.push(site);
+            }
+        }
+        over.into_iter().map(|site| site.origin).collect()
+    }
+
+    /// Clear the sites `quota_evictions` picks, returning their origins
+    pub fn enforce_quotas(&self) -> Result<Vec<String>, WebxError> {
+        let evicted = self.quota_evictions();
+        for origin in &evicted {
+            self.clear_origin(origin)?;
+        }
+        if !evicted.is_empty() {
+            tracing::info!("Cleared storage of {} site(s) over quota", evicted.len());
+        }
+        Ok(evicted)
+    }
+
+    // Private helper methods
+
+    fn clearable(&self, origin: &str, keep_domains: &[String]) -> bool {
+        !self.is_persistent(origin) && !keep_domains.iter().any(|domain| origin_in_domain(origin, domain))
+    }
+
+    fn entries(&self) -> Vec<StorageEntry> {
+        let mut entries = Vec::new();
+        for dir in &self.data_dirs {
+            collect_entries(dir, MAX_SCAN_DEPTH, &mut entries);
+        }
+        entries
+    }
+
+    /// Remove matching entries; returns the origins they belonged to and
+    /// the bytes freed
+    fn remove_entries(&self, matches: impl Fn(&StorageEntry) -> bool) -> Result<(usize, u64), WebxError> {
+        let mut origins = Vec::new();
+        let mut bytes = 0;
+        for entry in self.entries().into_iter().filter(|entry| matches(entry)) {
+            if entry.path.is_dir() {
+                std::fs::remove_dir_all(&entry.path)?;
+            } else {
+                std::fs::remove_file(&entry.path)?;
+            }
+            bytes += entry.bytes;
+            if !origins.contains(&entry.origin) {
+                origins.push(entry.origin);
+            }
+        }
+        Ok((origins.len(), bytes))
+    }
+
+    fn save_policy(&self) -> Result<(), WebxError> {
+        let content = serde_json::to_vec_pretty(&*self.policy.lock_or_recover())?;
+        write_atomic(&self.policy_path, &content)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StorageKind {
+    LocalStorage,
+    IndexedDb,
+    Other,
+}
+
+/// A file or directory holding one origin's data
+#[derive(Debug, Clone)]
+struct StorageEntry {
+    origin: String,
+    kind: StorageKind,
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+// Private helper functions
+
+fn normalize_origin(origin: &str) -> String {
+    match url::Url::parse(origin) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => origin.trim_end_matches('/').to_lowercase(),
+    }
+}
+
+fn origin_in_domain(origin: &str, domain: &str) -> bool {
+    url::Url::parse(origin)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host_in_domain(host, domain)))
+        .unwrap_or(false)
+}
+
+/// Origin an engine named a storage entry after: `https_example.com_0`
+/// with port 0 for the scheme's default, and an optional extension
+fn entry_origin(name: &str) -> Option<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(r"^(https?)_([A-Za-z0-9.\-\[\]:]+)_(\d+)(\..+)?$").unwrap());
+    let captures = pattern.captures(name)?;
+    let origin = match &captures[3] {
+        "0" => format!("{}://{}", &captures[1], &captures[2]),
+        port => format!("{}://{}:{}", &captures[1], &captures[2], port),
+    };
+    Some(normalize_origin(&origin))
+}
+
+fn collect_entries(dir: &Path, depth: usize, entries: &mut Vec<StorageEntry>) {
+    let Ok(children) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for child in children.flatten() {
+        let path = child.path();
+        let name = child.file_name().to_string_lossy().to_string();
+        match entry_origin(&name) {
+            Some(origin) => {
+                let location = path.to_string_lossy().to_lowercase();
+                let kind = if location.contains("indexeddb") {
+                    StorageKind::IndexedDb
+                } else if location.contains("localstorage") || location.contains("local storage") {
+                    StorageKind::LocalStorage
+                } else {
+                    StorageKind::Other
+                };
+                let (bytes, modified) = disk_usage(&path);
+                entries.push(StorageEntry {
+                    origin,
+                    kind,
+                    path,
+                    bytes,
+                    modified,
+                });
+            }
+            None if depth > 0 && child.file_type().is_ok_and(|kind| kind.is_dir()) => {
+                collect_entries(&path, depth - 1, entries);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Size of a file or directory tree, and when it was last written
+fn disk_usage(path: &Path) -> (u64, SystemTime) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return (0, SystemTime::UNIX_EPOCH);
+    };
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if !metadata.is_dir() {
+        return (metadata.len(), modified);
+    }
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|child| disk_usage(&child.path()))
+        .fold((0, modified), |(bytes, latest), (size, time)| (bytes + size, latest.max(time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_usage_quotas_and_clearing_per_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("webview");
+        let write = |path: &str, size: usize| {
+            let path = data_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, vec![0; size]).unwrap();
+        };
+        write("IndexedDB/https_mail.example.com_0.indexeddb.leveldb/000003.log", 3000);
+        write("IndexedDB/https_mail.example.com_0.indexeddb.leveldb/MANIFEST", 1000);
+        write("localstorage/https_mail.example.com_0.localstorage", 500);
+        write("localstorage/https_www.example.com_0.localstorage", 200);
+        write("localstorage/https_other.org_8443.localstorage", 5000);
+        write("localstorage/StorageTracker.db", 4096);
+
+        let manager = SiteStorageManager::new(Some(temp_dir.path().to_path_buf()), vec![data_dir.clone()]).unwrap();
+        let usage = manager.usage();
+        assert_eq!(usage.len(), 3);
+        assert_eq!(usage[0].origin, "https://other.org:8443");
+        let mail = manager.usage_for("https://mail.example.com/inbox").unwrap();
+        assert_eq!((mail.indexed_db_bytes, mail.local_storage_bytes, mail.total_bytes()), (4000, 500, 4500));
+
+        // A site over its quota is cleared, unless its storage is persistent
+        manager.set_site_quota("https://other.org:8443", Some(0)).unwrap();
+        manager.set_site_quota("https://mail.example.com", Some(0)).unwrap();
+        manager.set_persistent("https://mail.example.com", true).unwrap();
+        assert_eq!(manager.enforce_quotas().unwrap(), ["https://other.org:8443"]);
+        assert!(manager.usage_for("https://other.org:8443").is_none());
+
+        // Clearing on exit keeps persistent and allowed sites
+        assert_eq!(manager.count_all_except(&[]), 1);
+        assert_eq!(manager.count_all_except(&["example.com".to_string()]), 0);
+
+        // Forgetting a site clears its subdomains, persistent or not
+        assert_eq!(manager.clear_site("example.com").unwrap(), 2);
+        assert!(manager.usage().is_empty());
+        assert!(data_dir.join("localstorage/StorageTracker.db").exists());
+
+        let reloaded = SiteStorageManager::new(Some(temp_dir.path().to_path_buf()), vec![data_dir]).unwrap();
+        assert!(reloaded.is_persistent("https://mail.example.com/"));
+    }
+}
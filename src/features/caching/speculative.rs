@@ -0,0 +1,618 @@
+// Speculative Loading
+use super::HTTPCache;
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::core::DataSaverProfile;
+use crate::error::WebxError;
+use crate::features::history_manager::{FrecencyModel, VisitTransition};
+use crate::features::resource_optimizer::DataSaverPolicy;
+use crate::features::system::conditions::{BackgroundWork, ConditionsMonitor};
+use crate::features::system::proxy::{ProxyManager, ProxyRoute};
+use crate::features::system::scheduler::{TaskPriority, TaskScheduler, TaskSpec};
+use crate::utils::{host_in_domain, LockExt};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How far ahead of the user the browser loads
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SpeculativeMode {
+    /// Nothing is learned or loaded ahead
+    Off,
+    /// Resolve likely origins and preconnect to hovered links; no content
+    /// is fetched before the user asks for it
+    #[default]
+    Preconnect,
+    /// Also fetch top sites into the HTTP cache while the browser is idle
+    Prefetch,
+}
+
+impl SpeculativeMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpeculativeMode::Off => "off",
+            SpeculativeMode::Preconnect => "preconnect",
+            SpeculativeMode::Prefetch => "prefetch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(SpeculativeMode::Off),
+            "preconnect" => Some(SpeculativeMode::Preconnect),
+            "prefetch" => Some(SpeculativeMode::Prefetch),
+            _ => None,
+        }
+    }
+}
+
+/// Speculative loading configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SpeculativeConfig {
+    pub mode: SpeculativeMode,
+    /// Likely origins whose host names are resolved ahead
+    pub resolve_origins: usize,
+    /// How long the pointer rests on a link before its origin is preconnected
+    pub hover_delay_ms: u64,
+    /// Top sites fetched into the cache while idle
+    pub prefetch_sites: usize,
+    /// Same-origin stylesheets, scripts and icons fetched with each top site
+    pub resources_per_site: usize,
+    /// Responses larger than this are not cached
+    pub max_resource_bytes: usize,
+    /// Time between two rounds of warming, run when the browser is idle
+    pub warm_interval_secs: u64,
+    pub request_timeout_secs: u64,
+    /// Origins not visited for this long are forgotten
+    pub retention_days: u32,
+}
+
+impl Default for SpeculativeConfig {
+    fn default() -> Self {
+        Self {
+            mode: SpeculativeMode::default(),
+            resolve_origins: 8,
+            hover_delay_ms: 150,
+            prefetch_sites: 3,
+            resources_per_site: 8,
+            max_resource_bytes: 2 * 1024 * 1024,
+            warm_interval_secs: 60 * 60,
+            request_timeout_secs: 15,
+            retention_days: 90,
+        }
+    }
+}
+
+/// How often and how recently an origin was visited
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OriginRecord {
+    pub origin: String,
+    /// Frecency rank, see `FrecencyModel`
+    pub rank: f64,
+    pub visits: u32,
+    pub last_visit: DateTime<Utc>,
+}
+
+/// An origin the user is likely to visit, most likely first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LikelyOrigin {
+    pub origin: String,
+    pub score: f64,
+}
+
+/// What a round of warming did
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WarmReport {
+    pub resolved_hosts: usize,
+    pub cached_responses: usize,
+    /// Origins skipped or failed, with why
+    pub skipped: Vec<String>,
+}
+
+/// Loads ahead of the user: learns which origins they visit by frecency,
+/// resolves those ahead, preconnects to links they hover, and with
+/// `SpeculativeMode::Prefetch` fills the HTTP cache with top sites while
+/// the browser is idle. Likely origins never reach web pages, and nothing
+/// is fetched while data saver is on or the conditions monitor holds
+/// background work back.
+pub struct SpeculativeLoader {
+    config: Mutex<SpeculativeConfig>,
+    db: Db,
+    meta: Tree,
+    origins: Tree,
+    frecency: FrecencyModel,
+    data_saver: Mutex<DataSaverProfile>,
+    http_cache: Option<Arc<Mutex<HTTPCache>>>,
+    proxy: Option<Arc<Mutex<ProxyManager>>>,
+    conditions: Option<Arc<ConditionsMonitor>>,
+}
+
+impl SpeculativeLoader {
+    /// Open the origin store. A given config replaces the stored one.
+    pub fn new(config: Option<SpeculativeConfig>, db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("speculative.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        let meta = db.open_tree("meta")?;
+        let stored = match meta.get("config")? {
+            Some(bytes) => Some(serde_json::from_slice(&bytes)?),
+            None => None,
+        };
+        Ok(Self {
+            config: Mutex::new(config.or(stored).unwrap_or_default()),
+            origins: db.open_tree("origins")?,
+            meta,
+            db,
+            frecency: FrecencyModel::default(),
+            data_saver: Mutex::new(DataSaverProfile::Off),
+            http_cache: None,
+            proxy: None,
+            conditions: None,
+        })
+    }
+
+    /// Cache prefetched responses here
+    pub fn set_http_cache(&mut self, http_cache: Arc<Mutex<HTTPCache>>) {
+        self.http_cache = Some(http_cache);
+    }
+
+    /// Fetch and resolve through the proxy manager's per-URL routes
+    pub fn set_proxy_manager(&mut self, proxy: Arc<Mutex<ProxyManager>>) {
+        self.proxy = Some(proxy);
+    }
+
+    /// Skip prefetching while on battery or a metered connection, as the
+    /// monitor judges it
+    pub fn set_conditions_monitor(&mut self, conditions: Arc<ConditionsMonitor>) {
+        self.conditions = Some(conditions);
+    }
+
+    pub fn config(&self) -> SpeculativeConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    pub fn mode(&self) -> SpeculativeMode {
+        self.config.lock_or_recover().mode
+    }
+
+    /// Change how far ahead the browser loads. Turning it off forgets the
+    /// learned origins.
+    pub fn set_mode(&self, mode: SpeculativeMode) -> Result<(), WebxError> {
+        if mode == SpeculativeMode::Off {
+            self.clear()?;
+        }
+        let mut config = self.config.lock_or_recover();
+        config.mode = mode;
+        self.meta.insert("config", serde_json::to_vec(&*config)?)?;
+        Ok(())
+    }
+
+    /// Follow the data saver profile; profiles that disable prefetching
+    /// stop cache warming
+    pub fn set_data_saver(&self, profile: DataSaverProfile) {
+        *self.data_saver.lock_or_recover() = profile;
+    }
+
+    /// Whether idle warming may fetch content
+    pub fn prefetch_allowed(&self) -> bool {
+        let prefetch = BackgroundWork::new(TaskPriority::Low).uses_network();
+        self.mode() == SpeculativeMode::Prefetch
+            && !DataSaverPolicy::for_profile(*self.data_saver.lock_or_recover()).disable_prefetch
+            && !self.conditions.as_ref().is_some_and(|conditions| conditions.should_defer(prefetch))
+    }
+
+    /// Learn from a visit to a page. Only web pages count, and nothing is
+    /// learned while speculative loading is off.
+    pub fn record_visit(&self, url: &str, transition: VisitTransition) -> Result<(), WebxError> {
+        if self.mode() == SpeculativeMode::Off {
+            return Ok(());
+        }
+        let Some(origin) = web_origin(url) else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let record = match self.origins.get(&origin)? {
+            Some(bytes) => {
+                let stored: OriginRecord = serde_json::from_slice(&bytes)?;
+                OriginRecord {
+                    rank: self.frecency.add_visit(Some(stored.rank), transition, now),
+                    visits: stored.visits + 1,
+                    last_visit: now,
+                    origin: stored.origin,
+                }
+            }
+            None => OriginRecord {
+                rank: self.frecency.add_visit(None, transition, now),
+                visits: 1,
+                last_visit: now,
+                origin: origin.clone(),
+            },
+        };
+        self.origins.insert(origin, serde_json::to_vec(&record)?)?;
+        Ok(())
+    }
+
+    /// Origins the user is most likely to visit, by frecency
+    pub fn likely_origins(&self, limit: usize) -> Result<Vec<LikelyOrigin>, WebxError> {
+        let now = Utc::now();
+        let mut likely = Vec::new();
+        for entry in self.origins.iter() {
+            let (_, bytes) = entry?;
+            let record: OriginRecord = serde_json::from_slice(&bytes)?;
+            likely.push(LikelyOrigin {
+                score: self.frecency.score(record.rank, now),
+                origin: record.origin,
+            });
+        }
+        likely.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.origin.cmp(&b.origin)));
+        likely.truncate(limit);
+        Ok(likely)
+    }
+
+    /// Forget learned origins of a site and its subdomains
+    pub fn clear_site(&self, domain: &str) -> Result<usize, WebxError> {
+        let mut removed = 0;
+        for key in self.origins.iter().keys() {
+            let key = key?;
+            let origin = String::from_utf8_lossy(&key).to_string();
+            let in_domain = url::Url::parse(&origin)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host_in_domain(host, domain)))
+                .unwrap_or(false);
+            if in_domain {
+                self.origins.remove(key)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Forget every learned origin
+    pub fn clear(&self) -> Result<(), WebxError> {
+        self.origins.clear()?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Script injected into every page that preconnects to a link's origin
+    /// once the pointer rests on it. Empty while speculative loading is off.
+    pub fn page_script(&self) -> String {
+        let config = self.config();
+        if config.mode == SpeculativeMode::Off {
+            return String::new();
+        }
+        format!(
+            r#"
+(function() {{
+    if (window.__webxPreconnect) return;
+    window.__webxPreconnect = true;
+    const connected = new Set([location.origin]);
+    let timer = null;
+    document.addEventListener('mouseover', (e) => {{
+        const link = e.target.closest && e.target.closest('a[href]');
+        if (!link) return;
+        clearTimeout(timer);
+        timer = setTimeout(() => {{
+            let origin;
+            try {{ origin = new URL(link.href).origin; }} catch (err) {{ return; }}
+            if (!/^https?:/.test(origin) || connected.has(origin)) return;
+            connected.add(origin);
+            const hint = document.createElement('link');
+            hint.rel = 'preconnect';
+            hint.href = origin;
+            document.head.appendChild(hint);
+        }}, {delay});
+    }}, {{ passive: true }});
+    document.addEventListener('mouseout', () => clearTimeout(timer), {{ passive: true }});
+}})();
+"#,
+            delay = config.hover_delay_ms,
+        )
+    }
+
+    /// Resolve the host names of likely origins so the first request to
+    /// them skips the lookup. Origins behind a proxy are skipped, since
+    /// resolving them here would leak the lookup past the proxy.
+    pub async fn resolve_likely(&self) -> Result<WarmReport, WebxError> {
+        let mut report = WarmReport::default();
+        if self.mode() == SpeculativeMode::Off {
+            return Ok(report);
+        }
+        let limit = self.config.lock_or_recover().resolve_origins;
+        for likely in self.likely_origins(limit)? {
+            if self.route_for(&likely.origin) != ProxyRoute::Direct {
+                report.skipped.push(format!("{}: proxied", likely.origin));
+                continue;
+            }
+            let Ok(url) = url::Url::parse(&likely.origin) else {
+                continue;
+            };
+            let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+                continue;
+            };
+            let address = format!("{}:{}", host, port);
+            match tokio::net::lookup_host(address).await {
+                Ok(_) => report.resolved_hosts += 1,
+                Err(e) => report.skipped.push(format!("{}: {}", likely.origin, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Fetch the top sites and their same-origin stylesheets, scripts and
+    /// icons into the HTTP cache. Does nothing unless prefetching is on
+    /// and allowed by data saver.
+    pub async fn prefetch_top_sites(&self) -> Result<WarmReport, WebxError> {
+        let mut report = WarmReport::default();
+        let Some(http_cache) = self.http_cache.clone().filter(|_| self.prefetch_allowed()) else {
+            return Ok(report);
+        };
+        let config = self.config();
+
+        for likely in self.likely_origins(config.prefetch_sites)? {
+            let page_url = format!("{}/", likely.origin);
+            let body = match self.fetch_into_cache(&page_url, &http_cache, &config).await {
+                Ok(Some(body)) => body,
+                Ok(None) => {
+                    report.skipped.push(format!("{}: not cacheable", page_url));
+                    continue;
+                }
+                Err(e) => {
+                    report.skipped.push(format!("{}: {}", page_url, e));
+                    continue;
+                }
+            };
+            report.cached_responses += 1;
+
+            let html = String::from_utf8_lossy(&body);
+            for resource in page_resources(&html, &page_url, config.resources_per_site) {
+                match self.fetch_into_cache(&resource, &http_cache, &config).await {
+                    Ok(Some(_)) => report.cached_responses += 1,
+                    Ok(None) => {}
+                    Err(e) => report.skipped.push(format!("{}: {}", resource, e)),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Resolve likely origins and, where allowed, prefetch top sites
+    pub async fn warm(&self) -> Result<WarmReport, WebxError> {
+        let mut report = self.resolve_likely().await?;
+        let prefetched = self.prefetch_top_sites().await?;
+        report.cached_responses = prefetched.cached_responses;
+        report.skipped.extend(prefetched.skipped);
+        self.prune()?;
+        Ok(report)
+    }
+
+    /// Warm whenever the browser is idle, as a low-priority network task
+    pub fn schedule(loader: Arc<Self>, scheduler: &TaskScheduler) {
+        let interval = Duration::from_secs(loader.config().warm_interval_secs);
+        let spec = TaskSpec::new("speculative-warming", interval)
+            .with_priority(TaskPriority::Low)
+            .idle_only()
+            .uses_network()
+            .run_at_start();
+        scheduler.register(spec, move || {
+            let loader = Arc::clone(&loader);
+            async move {
+                if loader.mode() == SpeculativeMode::Off {
+                    return Ok(());
+                }
+                let report = loader.warm().await?;
+                tracing::debug!(
+                    "Resolved {} hosts and cached {} responses ahead",
+                    report.resolved_hosts,
+                    report.cached_responses
+                );
+                Ok(())
+            }
+        });
+    }
+
+    // Private helper methods
+
+    fn route_for(&self, url: &str) -> ProxyRoute {
+        self.proxy
+            .as_ref()
+            .map(|proxy| proxy.lock_or_recover().route_for_url(url))
+            .unwrap_or(ProxyRoute::Direct)
+    }
+
+    /// Fetch a URL and cache it, returning the body if it was cacheable
+    async fn fetch_into_cache(
+        &self,
+        url: &str,
+        http_cache: &Mutex<HTTPCache>,
+        config: &SpeculativeConfig,
+    ) -> Result<Option<Vec<u8>>, WebxError> {
+        let client = match &self.proxy {
+            Some(proxy) => proxy.lock_or_recover().client_for_url(url).map_err(|e| e.to_string())?.0,
+            None => reqwest::Client::new(),
+        };
+        let response = client
+            .get(url)
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        if !http_cache.lock_or_recover().is_cacheable(status, &headers) {
+            return Ok(None);
+        }
+        if response.content_length().unwrap_or(0) as usize > config.max_resource_bytes {
+            return Ok(None);
+        }
+        let body = response.bytes().await?.to_vec();
+        if body.len() > config.max_resource_bytes {
+            return Ok(None);
+        }
+        http_cache
+            .lock_or_recover()
+            .store_response(url.to_string(), status, headers, body.clone())?;
+        Ok(Some(body))
+    }
+
+    /// Forget origins not visited within retention
+    fn prune(&self) -> Result<(), WebxError> {
+        let retention_days = self.config.lock_or_recover().retention_days;
+        let cutoff = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+        for entry in self.origins.iter() {
+            let (key, bytes) = entry?;
+            let record: OriginRecord = serde_json::from_slice(&bytes)?;
+            if record.last_visit < cutoff {
+                self.origins.remove(key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SettingsProvider for SpeculativeLoader {
+    fn module(&self) -> &str {
+        "speculative"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::choice(
+            "speculative.mode",
+            "Load pages ahead",
+            SpeculativeMode::default().name(),
+            &[
+                ("off", "Off"),
+                ("preconnect", "Connect to sites you are likely to visit"),
+                ("prefetch", "Also download your top sites while idle"),
+            ],
+        )
+        .with_description("Speeds up loading by learning which sites you visit; turning it off forgets them")
+        .with_category(SettingCategory::Privacy)]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "speculative.mode" => {
+                let name = value.as_str().ok_or("Expected a choice value")?;
+                self.set_mode(SpeculativeMode::from_name(name).ok_or("Unknown speculative loading mode")?)
+            }
+            _ => Err(format!("Unknown speculative loading setting {}", key).into()),
+        }
+    }
+}
+
+// Private helper functions
+
+/// `scheme://host[:port]` of an http(s) URL
+fn web_origin(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return None;
+    }
+    Some(url.origin().ascii_serialization())
+}
+
+/// Same-origin stylesheets, scripts and icons a page links to
+fn page_resources(html: &str, page_url: &str, limit: usize) -> Vec<String> {
+    let Ok(base) = url::Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse(r#"link[rel~="stylesheet"][href], link[rel~="icon"][href], script[src]"#)
+        .expect("static selector");
+    let mut resources: Vec<String> = Vec::new();
+    for element in document.select(&selector) {
+        let Some(href) = element.value().attr("href").or_else(|| element.value().attr("src")) else {
+            continue;
+        };
+        let Ok(url) = base.join(href) else {
+            continue;
+        };
+        let url = url.to_string();
+        if url::Url::parse(&url).map(|u| u.origin()) == Ok(base.origin()) && !resources.contains(&url) {
+            resources.push(url);
+        }
+        if resources.len() >= limit {
+            break;
+        }
+    }
+    resources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_likely_origins_follow_frecency() {
+        let temp_dir = TempDir::new().unwrap();
+        let loader = SpeculativeLoader::new(None, Some(temp_dir.path().join("speculative.db"))).unwrap();
+
+        loader.record_visit("https://news.example.com/today", VisitTransition::Link).unwrap();
+        for _ in 0..3 {
+            loader.record_visit("https://mail.example.org/inbox", VisitTransition::Typed).unwrap();
+        }
+        loader.record_visit("https://news.example.com/sports", VisitTransition::Link).unwrap();
+        loader.record_visit("webx://stats", VisitTransition::Typed).unwrap();
+
+        let likely = loader.likely_origins(10).unwrap();
+        let origins: Vec<&str> = likely.iter().map(|o| o.origin.as_str()).collect();
+        assert_eq!(origins, vec!["https://mail.example.org", "https://news.example.com"]);
+        assert!(loader.page_script().contains("preconnect"));
+
+        assert_eq!(loader.clear_site("example.com").unwrap(), 1);
+        assert_eq!(loader.likely_origins(10).unwrap().len(), 1);
+
+        // Prefetching is opt-in and yields to data saver
+        assert!(!loader.prefetch_allowed());
+        loader.set_mode(SpeculativeMode::Prefetch).unwrap();
+        assert!(loader.prefetch_allowed());
+        loader.set_data_saver(DataSaverProfile::Moderate);
+        assert!(!loader.prefetch_allowed());
+
+        // Turning it off forgets what was learned and stops learning
+        loader.set_mode(SpeculativeMode::Off).unwrap();
+        loader.record_visit("https://mail.example.org/inbox", VisitTransition::Typed).unwrap();
+        assert!(loader.likely_origins(10).unwrap().is_empty());
+        assert!(loader.page_script().is_empty());
+    }
+
+    #[test]
+    fn test_page_resources_stay_same_origin() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="icon" href="favicon.ico">
+            <link rel="stylesheet" href="https://cdn.example.net/lib.css">
+            <script src="/app.js"></script>
+            <script src="/app.js"></script>
+            <script>inline()</script>
+        </head></html>"#;
+        let resources = page_resources(html, "https://example.com/", 10);
+        assert_eq!(
+            resources,
+            vec![
+                "https://example.com/style.css",
+                "https://example.com/favicon.ico",
+                "https://example.com/app.js",
+            ]
+        );
+        assert_eq!(page_resources(html, "https://example.com/", 1).len(), 1);
+    }
+}
@@ -1,16 +1,68 @@
 // Download Manager Core
-use crate::core::{Download, DownloadStatus};
-use reqwest::Client;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use crate::error::WebxError;
+use super::policy::{DownloadPolicy, FileTypeAction, PolicyDecision};
+use super::media::{concatenate_segments, fetch_hls_media, MediaFormat};
+use super::retry::{AttemptFailure, FailureKind, RetryPolicy};
+use super::sources::{download_urls, DataUrl, MAX_INLINE_DOWNLOAD_BYTES};
+use super::storage::{DownloadStorage, ResumeInfo};
+use super::store::DownloadStore;
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::core::{
+    match_score, recency_factor, Download, DownloadAttempt, DownloadStatus, SearchQuery, SearchResult, SearchSource, SearchSourceKind,
+};
+use crate::runtime::BrowserRuntime;
+use crate::features::system::protocol_handlers::{Handoff, HandlerTarget, ProtocolHandlers};
+use crate::features::system::proxy::{ProxyManager, ProxyRequestError, ProxyRoute};
+use crate::utils::{LockExt, StateWatchdog, filename_from_url, open_with_system, sanitize_filename};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Client, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::runtime::Handle;
 use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+/// Number of leading bytes compared when a partial download has no validators
+const VERIFY_PREFIX_LEN: u64 = 64 * 1024;
+
+/// How often a batch's downloads are checked for progress
+const BATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the download list is checked for changes to save
+const STORE_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Why a download the last session left unfinished failed
+const INTERRUPTED_MESSAGE: &str = "Interrupted when WebX closed; retry it from Recent Downloads";
 
 /// Download manager for handling file downloads
 pub struct DownloadManager {
     downloads: Arc<Mutex<Vec<Download>>>,
+    /// ID of the next download, never reused after one is removed
+    next_id: AtomicUsize,
     download_dir: PathBuf,
     client: Client,
+    /// Routes downloads through the configured proxies when set
+    proxy: Option<Arc<Mutex<ProxyManager>>>,
+    /// Runtime transfers run on, the caller's when not set
+    runtime: Option<Handle>,
+    /// Decides which file types are saved, opened, held or blocked
+    policy: Arc<Mutex<DownloadPolicy>>,
+    /// Downloads waiting for the user to confirm them
+    pending: Arc<Mutex<HashMap<usize, PendingDownload>>>,
+    /// Retries and backoff after failed attempts
+    retry: Mutex<RetryPolicy>,
+    /// Applications finished files of some types go to, e.g. .torrent files
+    handlers: Option<Arc<ProtocolHandlers>>,
+    batches: Arc<Mutex<Vec<DownloadBatch>>>,
+    /// Where the download list is kept across restarts
+    store: Option<DownloadStore>,
     tx: mpsc::UnboundedSender<DownloadEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<DownloadEvent>>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -20,46 +72,1544 @@ pub enum DownloadEvent {
     Completed(usize),
     Failed(usize, String),
     Cancelled(usize),
+    Resumed(usize, u64),  // id, offset
+    Restarted(usize, String), // id, reason the partial data was discarded
+    /// The file type needs the user's go-ahead; answer with
+    /// `confirm_download` or `reject_download`
+    ConfirmationRequired(usize, PolicyDecision),
+    Blocked(usize, String), // id, reason
+    Retrying(usize, String, Duration), // id, URL tried next, wait before trying
+    /// A finished file goes to another application once the user agrees;
+    /// `open_download` launches it
+    HandoffReady(usize, Handoff),
+    BatchProgress(usize, usize, usize, usize), // batch id, completed, failed, total
+    BatchCompleted(usize, usize, usize), // batch id, completed, failed
+}
+
+/// Downloads started together into one folder, such as a page's images
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadBatch {
+    pub id: usize,
+    pub folder: PathBuf,
+    pub download_ids: Vec<usize>,
+}
+
+/// What recovering the last session's downloads did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadRecovery {
+    /// Unfinished downloads resumed from their partial data
+    pub resumed: Vec<usize>,
+    /// Unfinished downloads marked failed, to be retried
+    pub failed: Vec<usize>,
+}
+
+/// A file to fetch from its server or mirrors
+#[derive(Clone)]
+struct Transfer {
+    url: String,
+    mirrors: Vec<String>,
+    final_path: PathBuf,
+    decision: Option<ResumeDecision>,
+}
+
+/// How an attempt that did not fail ended
+enum AttemptOutcome {
+    /// The file is in place, with this many bytes
+    Finished(u64, PolicyDecision),
+    /// The server's MIME type needs the user's confirmation
+    Held(PolicyDecision),
+    Cancelled,
+}
+
+/// What a running transfer works with
+struct TransferJob {
+    download_id: usize,
+    transfer: Transfer,
+    partial_path: PathBuf,
+    downloads: Arc<Mutex<Vec<Download>>>,
+    policy: Arc<Mutex<DownloadPolicy>>,
+    storage: DownloadStorage,
+    proxy: Option<Arc<Mutex<ProxyManager>>>,
+    client: Client,
+    handlers: Option<Arc<ProtocolHandlers>>,
+    tx: mpsc::UnboundedSender<DownloadEvent>,
+}
+
+/// A download held until the user confirms it
+enum PendingDownload {
+    Transfer(Transfer),
+    /// Contents handed over by a page or a data: URL
+    Data { final_path: PathBuf, bytes: Vec<u8> },
+}
+
+/// Outcome of checking partial data against the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResumeDecision {
+    /// The partial data matches; the transfer continues from this offset
+    Resume(u64),
+    /// The server's file changed, so the partial data is of no use
+    Restart(String),
+    /// The server could not confirm the partial data either way, so it is
+    /// kept for a later retry
+    Unverified(String),
 }
 
 impl DownloadManager {
     /// Create a new download manager
-    pub fn new(download_dir: Option<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(download_dir: Option<PathBuf>) -> Result<Self, WebxError> {
         let download_dir = download_dir.unwrap_or_else(|| {
             dirs::download_dir().unwrap_or_else(|| PathBuf::from("./downloads"))
         });
-        
+
         // Create download directory if it doesn't exist
         std::fs::create_dir_all(&download_dir)?;
-        
+
         let client = Client::new();
-        let (tx, _) = mpsc::unbounded_channel();
-        
+        let (tx, rx) = mpsc::unbounded_channel();
+
         Ok(Self {
             downloads: Arc::new(Mutex::new(Vec::new())),
+            next_id: AtomicUsize::new(1),
             download_dir,
             client,
+            proxy: None,
+            runtime: None,
+            policy: Arc::new(Mutex::new(DownloadPolicy::default())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            retry: Mutex::new(RetryPolicy::default()),
+            handlers: None,
+            batches: Arc::new(Mutex::new(Vec::new())),
+            store: None,
             tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
         })
     }
 
+    /// Start a new download
+    pub async fn start_download(&self, url: &str) -> Result<usize, WebxError> {
+        self.start_download_with_mirrors(url, &[]).await
+    }
+
+    /// Start a download that other URLs serve too. When `url` keeps
+    /// failing, the mirrors are tried in order, resuming where the last
+    /// one stopped.
+    pub async fn start_download_with_mirrors(&self, url: &str, mirrors: &[String]) -> Result<usize, WebxError> {
+        self.start_transfer_in(self.storage(), url, mirrors).await
+    }
+
+    /// Download several files into a subfolder of the download folder,
+    /// reporting their progress together with `BatchProgress` events.
+    /// URLs that cannot start are counted as failed.
+    pub async fn start_batch_download(&self, folder: &str, urls: &[String]) -> Result<usize, WebxError> {
+        let folder = match sanitize_filename(folder.trim()) {
+            name if name.trim_matches('.').is_empty() => "Downloads".to_string(),
+            name => name,
+        };
+        let folder = self.download_dir.join(folder);
+        tokio::fs::create_dir_all(&folder).await?;
+
+        // Files may finish while others start, so the batch exists first
+        let batch_id = {
+            let mut batches = self.batches.lock_or_recover();
+            let batch_id = batches.last().map_or(1, |batch| batch.id + 1);
+            batches.push(DownloadBatch {
+                id: batch_id,
+                folder: folder.clone(),
+                download_ids: Vec::new(),
+            });
+            batch_id
+        };
+        let mut failed = 0;
+        for url in urls {
+            match self.start_transfer_in(DownloadStorage::new(folder.clone()), url, &[]).await {
+                Ok(download_id) => self.update_batch(batch_id, |batch| batch.download_ids.push(download_id)),
+                Err(e) => {
+                    tracing::warn!("Failed to start batch download of {}: {}", url, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        let batch = self.get_batch(batch_id).ok_or_else(|| WebxError::NotFound(format!("Batch {}", batch_id)))?;
+        if batch.download_ids.is_empty() {
+            self.batches.lock_or_recover().retain(|batch| batch.id != batch_id);
+            return Err(WebxError::Invalid("None of the files could be downloaded".to_string()));
+        }
+        let runtime = match &self.runtime {
+            Some(runtime) => runtime.clone(),
+            None => BrowserRuntime::current()?,
+        };
+        runtime.spawn(watch_batch(batch, failed, self.downloads.clone(), self.tx.clone()));
+        Ok(batch_id)
+    }
+
+    /// Get a batch of downloads
+    pub fn get_batch(&self, batch_id: usize) -> Option<DownloadBatch> {
+        self.batches.lock_or_recover().iter().find(|batch| batch.id == batch_id).cloned()
+    }
+
+    /// Batch a download belongs to
+    pub fn batch_of(&self, download_id: usize) -> Option<usize> {
+        self.batches
+            .lock_or_recover()
+            .iter()
+            .find(|batch| batch.download_ids.contains(&download_id))
+            .map(|batch| batch.id)
+    }
+
+    /// Download every URL in dropped or pasted text, such as links and
+    /// images dragged out of a page. data: URLs are saved directly.
+    pub async fn start_from_text(&self, text: &str) -> Result<Vec<usize>, WebxError> {
+        let urls = download_urls(text);
+        if urls.is_empty() {
+            return Err(WebxError::Invalid("No downloadable URL found".to_string()));
+        }
+        let mut download_ids = Vec::new();
+        for url in urls {
+            let download_id = match url.starts_with("data:") {
+                true => self.save_data_url(&url, None).await?,
+                false => self.start_download(&url).await?,
+            };
+            download_ids.push(download_id);
+        }
+        Ok(download_ids)
+    }
+
+    /// Download the URLs on the system clipboard
+    pub async fn start_from_clipboard(&self) -> Result<Vec<usize>, WebxError> {
+        let text = tokio::task::spawn_blocking(|| arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()))
+            .await?
+            .map_err(|e| WebxError::Invalid(format!("Clipboard has no text: {}", e)))?;
+        self.start_from_text(&text).await
+    }
+
+    /// Save the file inside a data: URL, named `filename` when given
+    pub async fn save_data_url(&self, data_url: &str, filename: Option<&str>) -> Result<usize, WebxError> {
+        let data = DataUrl::parse(data_url)?;
+        let filename = data.filename(filename);
+        self.save_bytes("data:", &filename, Some(&data.mime), data.bytes).await
+    }
+
+    /// Save contents a page produced itself, such as a blob: URL it offers
+    /// for download, under the page's URL for policy decisions
+    pub async fn save_bytes(
+        &self,
+        source_url: &str,
+        filename: &str,
+        mime: Option<&str>,
+        bytes: Vec<u8>,
+    ) -> Result<usize, WebxError> {
+        if bytes.len() > MAX_INLINE_DOWNLOAD_BYTES {
+            return Err(WebxError::Invalid(format!("{} is too large to save", filename)));
+        }
+        let filename = sanitize_filename(filename);
+        let policy = self.policy.lock_or_recover().decide(source_url, &filename, mime);
+        let storage = self.storage();
+        let final_path = tokio::task::spawn_blocking(move || storage.get_unique_filepath(&filename)).await?;
+        let download_id = self.register_download(source_url, &final_path);
+        self.update_download(download_id, |d| d.mime = mime.map(str::to_string));
+
+        if policy.needs_confirmation() {
+            let pending = PendingDownload::Data { final_path, bytes };
+            hold_download(&self.downloads, &self.pending, &self.tx, download_id, pending, policy);
+        } else {
+            self.write_data(download_id, final_path, bytes, policy.action == FileTypeAction::AutoOpen)
+                .await?;
+        }
+        Ok(download_id)
+    }
+
+    /// Save an HLS stream as one file: the playlist's segments, of its best
+    /// variant, fetched in order and joined. Live and encrypted streams
+    /// cannot be saved. `title` names the file when given.
+    pub async fn start_stream_download(&self, playlist_url: &str, title: Option<&str>) -> Result<usize, WebxError> {
+        let (client, _) = self.client_for_url(playlist_url)?;
+        let media = fetch_hls_media(&client, playlist_url).await?;
+        if media.encrypted {
+            return Err(WebxError::Invalid("Encrypted streams cannot be saved".to_string()));
+        }
+        if !media.ended {
+            return Err(WebxError::Invalid("Live streams cannot be saved".to_string()));
+        }
+        if media.segments.is_empty() {
+            return Err(WebxError::Parse("Playlist has no media segments".to_string()));
+        }
+
+        // fMP4 streams join into an MP4 file, MPEG-TS ones into a .ts file
+        let (extension, mime) = match media.init_segment {
+            Some(_) => ("mp4", "video/mp4"),
+            None => ("ts", "video/mp2t"),
+        };
+        let stem = title.map(str::to_string).unwrap_or_else(|| {
+            let name = filename_from_url(playlist_url);
+            name.rsplit_once('.').map_or(name.clone(), |(stem, _)| stem.to_string())
+        });
+        let filename = sanitize_filename(&format!("{}.{}", stem, extension));
+        let policy = self.policy.lock_or_recover().decide(playlist_url, &filename, Some(mime));
+        if policy.action == FileTypeAction::Block {
+            return Err(WebxError::Invalid(policy.reason));
+        }
+        let storage = self.storage();
+        let final_path = tokio::task::spawn_blocking(move || storage.get_unique_filepath(&filename)).await?;
+        let download_id = self.register_download(playlist_url, &final_path);
+        self.update_download(download_id, |d| {
+            d.mime = Some(mime.to_string());
+            d.status = DownloadStatus::Downloading;
+        });
+
+        let runtime = match &self.runtime {
+            Some(runtime) => runtime.clone(),
+            None => BrowserRuntime::current()?,
+        };
+        let segments: Vec<String> = media.init_segment.into_iter().chain(media.segments).collect();
+        let retry = self.retry_policy();
+        let downloads = self.downloads.clone();
+        let tx = self.tx.clone();
+        runtime.spawn(async move {
+            let _ = tx.send(DownloadEvent::Started(download_id));
+            let update = |f: &mut dyn FnMut(&mut Download)| {
+                if let Some(d) = downloads.lock_or_recover().iter_mut().find(|d| d.id == download_id) {
+                    f(d);
+                }
+            };
+            let partial_path = DownloadStorage::partial_path(&final_path);
+            let progress = |written: u64, done: usize| {
+                // Segments are of similar length, so the finished ones tell the total
+                let estimate = written / done as u64 * segments.len() as u64;
+                let mut cancelled = false;
+                update(&mut |d| {
+                    d.downloaded = written;
+                    d.size = estimate;
+                    cancelled = d.status == DownloadStatus::Cancelled;
+                });
+                let _ = tx.send(DownloadEvent::Progress(download_id, written, estimate));
+                !cancelled
+            };
+            let joined = concatenate_segments(&client, &segments, &partial_path, &retry, progress).await;
+            let finished = match joined {
+                Ok(Some(written)) => tokio::fs::rename(&partial_path, &final_path)
+                    .await
+                    .map(|_| Some(written))
+                    .map_err(WebxError::from),
+                other => other,
+            };
+            match finished {
+                Ok(Some(written)) => {
+                    update(&mut |d| {
+                        d.status = DownloadStatus::Completed;
+                        d.size = written;
+                        d.downloaded = written;
+                    });
+                    let _ = tx.send(DownloadEvent::Completed(download_id));
+                }
+                Ok(None) => {
+                    let _ = tokio::fs::remove_file(&partial_path).await;
+                }
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&partial_path).await;
+                    update(&mut |d| d.status = DownloadStatus::Failed);
+                    let _ = tx.send(DownloadEvent::Failed(download_id, e.to_string()));
+                }
+            }
+        });
+
+        Ok(download_id)
+    }
+
+    /// Resume a partial download left behind by a previous session.
+    ///
+    /// The partial data is verified against the server before anything is
+    /// appended; if the remote file changed it is discarded and the download
+    /// starts over. When the server cannot be asked, the partial data is
+    /// kept and the download marked failed, for `retry_download`.
+    pub async fn resume_download(&self, partial_path: &Path) -> Result<usize, WebxError> {
+        let storage = self.storage();
+        let info = {
+            let storage = storage.clone();
+            let partial_path = partial_path.to_path_buf();
+            tokio::task::spawn_blocking(move || storage.load_resume_info(&partial_path)).await?
+        }
+        .ok_or("No resume information for partial download")?;
+        let (client, _) = self.client_for_url(&info.url)?;
+        let download_id = self.register_download(&info.url, &info.final_path);
+
+        let decision = Self::verify_partial(&client, &info, partial_path).await;
+        match &decision {
+            ResumeDecision::Resume(_) => {}
+            ResumeDecision::Restart(reason) => {
+                tracing::warn!("Restarting download of {}: {}", info.url, reason);
+                let partial_path = partial_path.to_path_buf();
+                tokio::task::spawn_blocking(move || storage.discard_partial(&partial_path)).await??;
+                let _ = self.tx.send(DownloadEvent::Restarted(download_id, reason.clone()));
+            }
+            ResumeDecision::Unverified(reason) => {
+                tracing::warn!("Could not verify partial download of {}: {}", info.url, reason);
+                self.update_download(download_id, |d| d.status = DownloadStatus::Failed);
+                let _ = self.tx.send(DownloadEvent::Failed(download_id, reason.clone()));
+                return Ok(download_id);
+            }
+        }
+
+        let transfer = Transfer {
+            url: info.url.clone(),
+            mirrors: Vec::new(),
+            final_path: info.final_path.clone(),
+            decision: Some(decision),
+        };
+        self.spawn_transfer(download_id, transfer, false)?;
+
+        Ok(download_id)
+    }
+
+    /// Load the last session's downloads and keep saving the list. Ones it
+    /// left unfinished, because the browser quit or crashed, resume when
+    /// their partial data still matches the server's file, and are marked
+    /// failed otherwise, for `retry_download`.
+    pub async fn recover(&self) -> Result<DownloadRecovery, WebxError> {
+        let store = self.store.clone().ok_or("No download store set")?;
+        let saved = {
+            let store = store.clone();
+            tokio::task::spawn_blocking(move || store.load()).await??
+        };
+
+        let interrupted: Vec<Download> = {
+            let mut downloads = self.downloads.lock_or_recover();
+            let saved_max = saved.iter().map(|d| d.id).max().unwrap_or(0);
+            self.next_id.fetch_max(saved_max + 1, Ordering::SeqCst);
+            for mut download in saved.into_iter().rev() {
+                // Downloads started before recovery keep their IDs
+                if downloads.iter().any(|d| d.id == download.id) {
+                    download.id = self.next_id.fetch_add(1, Ordering::SeqCst);
+                }
+                downloads.insert(0, download);
+            }
+            downloads
+                .iter()
+                .filter(|d| matches!(d.status, DownloadStatus::Pending | DownloadStatus::Downloading | DownloadStatus::AwaitingConfirmation))
+                .cloned()
+                .collect()
+        };
+
+        let mut recovery = DownloadRecovery::default();
+        for download in interrupted {
+            let final_path = PathBuf::from(&download.path);
+            // A download held for confirmation is asked about again on retry
+            let decision = match download.status {
+                DownloadStatus::AwaitingConfirmation => None,
+                _ => self.check_partial(&final_path).await,
+            };
+            match decision {
+                Some(decision @ ResumeDecision::Resume(_)) => {
+                    let transfer = Transfer {
+                        url: download.url,
+                        mirrors: download.mirrors,
+                        final_path,
+                        decision: Some(decision),
+                    };
+                    self.spawn_transfer(download.id, transfer, false)?;
+                    recovery.resumed.push(download.id);
+                }
+                _ => {
+                    self.update_download(download.id, |d| d.status = DownloadStatus::Failed);
+                    let _ = self.tx.send(DownloadEvent::Failed(download.id, INTERRUPTED_MESSAGE.to_string()));
+                    recovery.failed.push(download.id);
+                }
+            }
+        }
+        tracing::info!(
+            "Recovered downloads: {} resumed, {} marked failed",
+            recovery.resumed.len(),
+            recovery.failed.len()
+        );
+
+        self.save_downloads()?;
+        let runtime = match &self.runtime {
+            Some(runtime) => runtime.clone(),
+            None => BrowserRuntime::current()?,
+        };
+        runtime.spawn(keep_saved(store, Arc::downgrade(&self.downloads)));
+        Ok(recovery)
+    }
+
+    /// Try a failed or cancelled download again, resuming its partial data
+    /// when the server still has the same file
+    pub async fn retry_download(&self, download_id: usize) -> Result<(), WebxError> {
+        let download = self
+            .get_download(download_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Download {}", download_id)))?;
+        if !matches!(download.status, DownloadStatus::Failed | DownloadStatus::Cancelled) {
+            return Err(WebxError::Invalid("Only failed or cancelled downloads can be retried".to_string()));
+        }
+        // Files pages made and joined streams are saved again from the page
+        let fetchable = url::Url::parse(&download.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !fetchable || matches!(MediaFormat::detect(&download.url, None), Some(MediaFormat::Hls)) {
+            return Err(WebxError::Invalid("Save the file again from its page".to_string()));
+        }
+
+        let final_path = PathBuf::from(&download.path);
+        let decision = self.check_partial(&final_path).await;
+        // Starting over would overwrite partial data that may still be good
+        if let Some(ResumeDecision::Unverified(reason)) = decision {
+            return Err(WebxError::Network(reason));
+        }
+        self.update_download(download_id, |d| {
+            d.status = DownloadStatus::Pending;
+            d.downloaded = match decision {
+                Some(ResumeDecision::Resume(offset)) => offset,
+                _ => 0,
+            };
+        });
+        let transfer = Transfer {
+            url: download.url,
+            mirrors: download.mirrors,
+            final_path,
+            decision,
+        };
+        self.spawn_transfer(download_id, transfer, false)
+    }
+
+    /// List partial downloads in the download directory that can be resumed
+    pub fn list_resumable(&self) -> Vec<(PathBuf, ResumeInfo)> {
+        self.storage().list_resumable()
+    }
+
+    /// Check partial data against the server to decide whether it can be resumed
+    pub async fn verify_partial(client: &Client, info: &ResumeInfo, partial_path: &Path) -> ResumeDecision {
+        let partial_len = tokio::fs::metadata(partial_path).await.map(|m| m.len()).unwrap_or(0);
+        if partial_len == 0 {
+            return ResumeDecision::Restart("No partial data".to_string());
+        }
+        if info.total_size > 0 && partial_len > info.total_size {
+            return ResumeDecision::Restart("Partial data is larger than the remote file".to_string());
+        }
+
+        let head = match client.head(&info.url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => return ResumeDecision::Unverified(format!("Server returned {}", response.status())),
+            Err(e) => return ResumeDecision::Unverified(e.to_string()),
+        };
+
+        let header = |name| head.headers().get(name).and_then(|v| v.to_str().ok());
+        // The header itself, as a HEAD response's body is always empty
+        if let Some(total) = header(CONTENT_LENGTH).and_then(|len| len.parse::<u64>().ok()) {
+            if info.total_size > 0 && total != info.total_size {
+                return ResumeDecision::Restart("Remote file size changed".to_string());
+            }
+        }
+
+        match info.validators_match(header(ETAG), header(LAST_MODIFIED)) {
+            Some(true) => ResumeDecision::Resume(partial_len),
+            Some(false) => ResumeDecision::Restart("Remote file changed".to_string()),
+            None => Self::verify_prefix(client, &info.url, partial_path, partial_len).await,
+        }
+    }
+
+    /// Go ahead with a download held for confirmation. Downloads blocked
+    /// by the deployment's lockdown list cannot be confirmed.
+    pub fn confirm_download(&self, download_id: usize) -> Result<(), WebxError> {
+        let pending = self
+            .pending
+            .lock_or_recover()
+            .remove(&download_id)
+            .ok_or_else(|| WebxError::NotFound(format!("No download {} awaiting confirmation", download_id)))?;
+        match pending {
+            PendingDownload::Transfer(transfer) => self.spawn_transfer(download_id, transfer, true),
+            PendingDownload::Data { final_path, bytes } => {
+                // Writing is quick; a runtime is only needed for network transfers
+                std::fs::write(&final_path, &bytes)?;
+                self.finish_data(download_id, bytes.len() as u64);
+                Ok(())
+            }
+        }
+    }
+
+    /// Drop a download held for confirmation
+    pub fn reject_download(&self, download_id: usize) -> bool {
+        if self.pending.lock_or_recover().remove(&download_id).is_none() {
+            return false;
+        }
+        self.cancel_download(download_id)
+    }
+
+    /// Cancel a download
+    pub fn cancel_download(&self, download_id: usize) -> bool {
+        self.pending.lock_or_recover().remove(&download_id);
+        let mut downloads = self.downloads.lock_or_recover();
+        if let Some(download) = downloads.iter_mut().find(|d| d.id == download_id) {
+            download.status = DownloadStatus::Cancelled;
+            let _ = self.tx.send(DownloadEvent::Cancelled(download_id));
+            true
+        } else {
+            false
+        }
+    }
+
     /// Get all downloads
     pub fn get_downloads(&self) -> Vec<Download> {
-        self.downloads.lock().unwrap().clone()
+        self.downloads.lock_or_recover().clone()
     }
 
     /// Get download by ID
     pub fn get_download(&self, download_id: usize) -> Option<Download> {
         self.downloads
-            .lock()
-            .unwrap()
+            .lock_or_recover()
             .iter()
             .find(|d| d.id == download_id)
             .cloned()
     }
 
+    /// Remove completed/cancelled download from list
+    pub fn remove_download(&self, download_id: usize) -> bool {
+        let mut downloads = self.downloads.lock_or_recover();
+        let len_before = downloads.len();
+        downloads.retain(|d| d.id != download_id);
+        downloads.len() != len_before
+    }
+
+    /// Open a finished download with its file type's handler, or the
+    /// system's default application
+    pub fn open_download(&self, download_id: usize) -> Result<(), WebxError> {
+        let download = self
+            .get_download(download_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Download {}", download_id)))?;
+        if download.status != DownloadStatus::Completed {
+            return Err(WebxError::Invalid("Download has not finished".to_string()));
+        }
+        let path = Path::new(&download.path);
+        let handoff = self
+            .handlers
+            .as_ref()
+            .and_then(|handlers| Some((handlers, handlers.resolve_download(path, download.mime.as_deref())?)));
+        match handoff {
+            Some((handlers, handoff)) if handoff.handler != HandlerTarget::Browser => handlers.launch(&handoff),
+            _ => open_with_system(path),
+        }
+    }
+
+    /// Clear all completed downloads
+    pub fn clear_completed(&self) {
+        let mut downloads = self.downloads.lock_or_recover();
+        downloads.retain(|d|
+            d.status == DownloadStatus::Downloading ||
+            d.status == DownloadStatus::Pending ||
+            d.status == DownloadStatus::AwaitingConfirmation
+        );
+    }
+
     /// Get download directory
     pub fn download_dir(&self) -> &PathBuf {
         &self.download_dir
     }
-}
\ No newline at end of file
+
+    /// Change download directory
+    pub fn set_download_dir(&mut self, new_dir: PathBuf) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(&new_dir)?;
+        self.download_dir = new_dir;
+        Ok(())
+    }
+
+    /// Run transfers on the given runtime instead of the caller's
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// Send downloads through the proxy manager's per-URL routes
+    pub fn set_proxy_manager(&mut self, proxy: Arc<Mutex<ProxyManager>>) {
+        self.proxy = Some(proxy);
+    }
+
+    /// Hand finished files of registered types to their applications
+    pub fn set_protocol_handlers(&mut self, handlers: Arc<ProtocolHandlers>) {
+        self.handlers = Some(handlers);
+    }
+
+    /// Decide file types with the given policy
+    pub fn set_policy(&mut self, policy: DownloadPolicy) {
+        self.policy = Arc::new(Mutex::new(policy));
+    }
+
+    /// The file type policy, for changing its rules
+    pub fn policy(&self) -> Arc<Mutex<DownloadPolicy>> {
+        Arc::clone(&self.policy)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry.lock_or_recover().clone()
+    }
+
+    /// Retry failed transfers with the given policy
+    pub fn set_retry_policy(&self, retry: RetryPolicy) {
+        *self.retry.lock_or_recover() = retry;
+    }
+
+    /// Keep the download list in a store across restarts; see `recover`
+    pub fn set_store(&mut self, store: DownloadStore) {
+        self.store = Some(store);
+    }
+
+    /// Save the download list now, as at exit
+    pub fn save_downloads(&self) -> Result<(), WebxError> {
+        match &self.store {
+            Some(store) => store.save(&self.get_downloads()),
+            None => Ok(()),
+        }
+    }
+
+    /// Let the watchdog recover the download list after a panic
+    pub fn watch_state(&self, watchdog: &StateWatchdog) {
+        watchdog.watch("downloads", &self.downloads);
+    }
+
+    /// Subscribe to download events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<DownloadEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    /// Start fetching a file into the folder of `storage`
+    async fn start_transfer_in(&self, storage: DownloadStorage, url: &str, mirrors: &[String]) -> Result<usize, WebxError> {
+        let filename = sanitize_filename(&filename_from_url(url));
+        let policy = self.policy.lock_or_recover().decide(url, &filename, None);
+        let taken = self.active_paths();
+        let final_path =
+            tokio::task::spawn_blocking(move || storage.get_unique_filepath_avoiding(&filename, &taken)).await?;
+        // Fail early on proxy settings that cannot work for any attempt
+        self.client_for_url(url)?;
+        let download_id = self.register_download(url, &final_path);
+        self.update_download(download_id, |d| d.mirrors = mirrors.to_vec());
+
+        let transfer = Transfer {
+            url: url.to_string(),
+            mirrors: mirrors.to_vec(),
+            final_path,
+            decision: None,
+        };
+        if policy.needs_confirmation() {
+            let pending = PendingDownload::Transfer(transfer);
+            hold_download(&self.downloads, &self.pending, &self.tx, download_id, pending, policy);
+        } else {
+            self.spawn_transfer(download_id, transfer, false)?;
+        }
+
+        Ok(download_id)
+    }
+
+    /// Check a download's partial data, when it left any with resume
+    /// information, against the server
+    async fn check_partial(&self, final_path: &Path) -> Option<ResumeDecision> {
+        let partial_path = DownloadStorage::partial_path(final_path);
+        let info = {
+            let storage = self.storage();
+            let partial_path = partial_path.clone();
+            tokio::task::spawn_blocking(move || storage.load_resume_info(&partial_path)).await.ok()??
+        };
+        let (client, _) = self.client_for_url(&info.url).ok()?;
+        Some(Self::verify_partial(&client, &info, &partial_path).await)
+    }
+
+    fn storage(&self) -> DownloadStorage {
+        DownloadStorage::new(self.download_dir.clone())
+    }
+
+    fn client_for_url(&self, url: &str) -> Result<(Client, ProxyRoute), ProxyRequestError> {
+        route_client(self.proxy.as_ref(), &self.client, url)
+    }
+
+    fn update_download(&self, download_id: usize, f: impl FnOnce(&mut Download)) {
+        if let Some(d) = self.downloads.lock_or_recover().iter_mut().find(|d| d.id == download_id) {
+            f(d);
+        }
+    }
+
+    /// Where downloads that have not finished will save their files
+    fn active_paths(&self) -> Vec<PathBuf> {
+        self.downloads
+            .lock_or_recover()
+            .iter()
+            .filter(|d| matches!(d.status, DownloadStatus::Pending | DownloadStatus::Downloading | DownloadStatus::AwaitingConfirmation))
+            .map(|d| PathBuf::from(&d.path))
+            .collect()
+    }
+
+    fn update_batch(&self, batch_id: usize, f: impl FnOnce(&mut DownloadBatch)) {
+        if let Some(batch) = self.batches.lock_or_recover().iter_mut().find(|batch| batch.id == batch_id) {
+            f(batch);
+        }
+    }
+
+    fn register_download(&self, url: &str, final_path: &Path) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut downloads = self.downloads.lock_or_recover();
+
+        downloads.push(Download {
+            id,
+            url: url.to_string(),
+            filename: final_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            path: final_path.to_string_lossy().to_string(),
+            size: 0,
+            downloaded: 0,
+            status: DownloadStatus::Pending,
+            started_at: chrono::Utc::now(),
+            mirrors: Vec::new(),
+            attempts: Vec::new(),
+            mime: None,
+        });
+
+        id
+    }
+
+    async fn verify_prefix(client: &Client, url: &str, partial_path: &Path, partial_len: u64) -> ResumeDecision {
+        let prefix_len = partial_len.min(VERIFY_PREFIX_LEN);
+
+        let mut local = vec![0u8; prefix_len as usize];
+        let read = match tokio::fs::File::open(partial_path).await {
+            Ok(mut file) => file.read_exact(&mut local).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = read {
+            return ResumeDecision::Unverified(format!("Partial data is unreadable: {}", e));
+        }
+
+        let response = match client
+            .get(url)
+            .header(RANGE, format!("bytes=0-{}", prefix_len - 1))
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return ResumeDecision::Unverified(e.to_string()),
+        };
+        // A full response means the server cannot append to the partial
+        // data at all; any other failure may pass
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {}
+            StatusCode::OK => return ResumeDecision::Restart("Server does not support range requests".to_string()),
+            status => return ResumeDecision::Unverified(format!("Server returned {}", status)),
+        }
+
+        match response.bytes().await {
+            Ok(remote) if Sha256::digest(&remote) == Sha256::digest(&local) => ResumeDecision::Resume(partial_len),
+            Ok(_) => ResumeDecision::Restart("Partial data does not match the remote file".to_string()),
+            Err(e) => ResumeDecision::Unverified(e.to_string()),
+        }
+    }
+
+    async fn write_data(&self, download_id: usize, final_path: PathBuf, bytes: Vec<u8>, open: bool) -> Result<(), WebxError> {
+        let len = bytes.len() as u64;
+        if let Err(e) = tokio::fs::write(&final_path, bytes).await {
+            self.update_download(download_id, |d| d.status = DownloadStatus::Failed);
+            let _ = self.tx.send(DownloadEvent::Failed(download_id, e.to_string()));
+            return Err(e.into());
+        }
+        self.finish_data(download_id, len);
+        if open {
+            if let Err(e) = open_with_system(&final_path) {
+                tracing::warn!("Failed to open download {}: {}", download_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark in-memory contents written to disk as downloaded
+    fn finish_data(&self, download_id: usize, len: u64) {
+        self.update_download(download_id, |d| {
+            d.status = DownloadStatus::Completed;
+            d.size = len;
+            d.downloaded = len;
+        });
+        let _ = self.tx.send(DownloadEvent::Started(download_id));
+        let _ = self.tx.send(DownloadEvent::Completed(download_id));
+    }
+
+    /// Transfer a download, retrying transient failures with backoff and
+    /// moving on to the next mirror when a URL keeps failing. Unless the
+    /// user already confirmed it, the file type is checked again once a
+    /// server names its MIME type.
+    fn spawn_transfer(&self, download_id: usize, transfer: Transfer, confirmed: bool) -> Result<(), WebxError> {
+        let runtime = match &self.runtime {
+            Some(runtime) => runtime.clone(),
+            None => BrowserRuntime::current()?,
+        };
+        let pending = self.pending.clone();
+        let retry = self.retry_policy();
+        let job = TransferJob {
+            download_id,
+            partial_path: DownloadStorage::partial_path(&transfer.final_path),
+            transfer,
+            downloads: self.downloads.clone(),
+            policy: self.policy.clone(),
+            storage: self.storage(),
+            proxy: self.proxy.clone(),
+            client: self.client.clone(),
+            handlers: self.handlers.clone(),
+            tx: self.tx.clone(),
+        };
+
+        runtime.spawn(async move {
+            let _ = job.tx.send(DownloadEvent::Started(download_id));
+            job.update(|d| d.status = DownloadStatus::Downloading);
+
+            let sources: Vec<String> = std::iter::once(job.transfer.url.clone())
+                .chain(job.transfer.mirrors.iter().cloned())
+                .collect();
+            let mut offset = match &job.transfer.decision {
+                Some(ResumeDecision::Resume(offset)) => *offset,
+                _ => 0,
+            };
+            let (mut source, mut tries) = (0, 0);
+            let (downloaded, file_policy) = loop {
+                let url = &sources[source];
+                job.update(|d| {
+                    d.attempts.push(DownloadAttempt {
+                        url: url.clone(),
+                        started_at: chrono::Utc::now(),
+                        finished_at: None,
+                        error: None,
+                    })
+                });
+                let result = job.attempt(url, offset, confirmed).await;
+                job.update(|d| {
+                    if let Some(attempt) = d.attempts.last_mut() {
+                        attempt.finished_at = Some(chrono::Utc::now());
+                        attempt.error = result.as_ref().err().map(|failure| failure.message.clone());
+                    }
+                });
+
+                let failure = match result {
+                    Ok(AttemptOutcome::Finished(downloaded, file_policy)) => break (downloaded, file_policy),
+                    Ok(AttemptOutcome::Held(decision)) => {
+                        let held = PendingDownload::Transfer(job.transfer.clone());
+                        hold_download(&job.downloads, &pending, &job.tx, download_id, held, decision);
+                        return;
+                    }
+                    Ok(AttemptOutcome::Cancelled) => return,
+                    Err(failure) => failure,
+                };
+                offset = failure.downloaded;
+                tries += 1;
+                let wait = match failure.kind {
+                    FailureKind::Transient if tries < retry.max_attempts => {
+                        Some(retry.backoff(tries, failure.retry_after))
+                    }
+                    FailureKind::Transient | FailureKind::Source if source + 1 < sources.len() => {
+                        tracing::warn!("Download {} failed at {}, trying a mirror: {}", download_id, url, failure.message);
+                        source += 1;
+                        tries = 0;
+                        Some(Duration::ZERO)
+                    }
+                    _ => None,
+                };
+                let Some(wait) = wait else {
+                    job.update(|d| d.status = DownloadStatus::Failed);
+                    let _ = job.tx.send(DownloadEvent::Failed(download_id, failure.message));
+                    return;
+                };
+                let _ = job.tx.send(DownloadEvent::Retrying(download_id, sources[source].clone(), wait));
+                tokio::time::sleep(wait).await;
+                if job.is_cancelled() {
+                    return;
+                }
+            };
+
+            let mut mime = None;
+            job.update(|d| {
+                d.status = DownloadStatus::Completed;
+                d.downloaded = downloaded;
+                mime = d.mime.clone();
+            });
+            let _ = job.tx.send(DownloadEvent::Completed(download_id));
+
+            // Registered file types go to their handler rather than being opened
+            let handoff = job
+                .handlers
+                .as_ref()
+                .and_then(|handlers| handlers.resolve_download(&job.transfer.final_path, mime.as_deref()));
+            let opened = match (&handoff, &job.handlers) {
+                (Some(handoff), _) if handoff.handler == HandlerTarget::Browser => Ok(()),
+                (Some(handoff), _) if handoff.prompt.is_some() => {
+                    let _ = job.tx.send(DownloadEvent::HandoffReady(download_id, handoff.clone()));
+                    Ok(())
+                }
+                (Some(handoff), Some(handlers)) => handlers.launch(handoff),
+                _ if file_policy.action == FileTypeAction::AutoOpen => open_with_system(&job.transfer.final_path),
+                _ => Ok(()),
+            };
+            if let Err(e) = opened {
+                tracing::warn!("Failed to open download {}: {}", download_id, e);
+            }
+        });
+        Ok(())
+    }
+}
+
+impl TransferJob {
+    fn update(&self, f: impl FnOnce(&mut Download)) {
+        if let Some(d) = self.downloads.lock_or_recover().iter_mut().find(|d| d.id == self.download_id) {
+            f(d);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.downloads
+            .lock_or_recover()
+            .iter()
+            .any(|d| d.id == self.download_id && d.status == DownloadStatus::Cancelled)
+    }
+
+    /// Fetch the file from one URL, resuming at `offset`
+    async fn attempt(&self, url: &str, mut offset: u64, confirmed: bool) -> Result<AttemptOutcome, AttemptFailure> {
+        let download_id = self.download_id;
+        let final_path = &self.transfer.final_path;
+        let partial_path = &self.partial_path;
+        let local = |e: &dyn std::fmt::Display, downloaded| AttemptFailure::new(FailureKind::Local, e.to_string(), downloaded);
+        let (client, route) = route_client(self.proxy.as_ref(), &self.client, url)
+            .map_err(|e| AttemptFailure::new(FailureKind::Source, e.to_string(), offset))?;
+        let saved_info = {
+            let storage = self.storage.clone();
+            let partial_path = partial_path.clone();
+            tokio::task::spawn_blocking(move || storage.load_resume_info(&partial_path))
+                .await
+                .ok()
+                .flatten()
+        };
+
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = saved_info.as_ref().and_then(|i| i.if_range()) {
+                request = request.header(IF_RANGE, validator);
+            }
+        }
+
+        let response = request.send().await.map_err(|e| {
+            AttemptFailure::network(&e, ProxyRequestError::from_reqwest(url, &route, &e).to_string(), offset)
+        })?;
+        if let Some(error) = ProxyRequestError::from_response(url, &route, &response) {
+            return Err(AttemptFailure::new(FailureKind::Source, error.to_string(), offset));
+        }
+        if !response.status().is_success() {
+            return Err(AttemptFailure::status(&response, offset));
+        }
+
+        let mime = response.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        let filename = final_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let file_policy = self.policy.lock_or_recover().decide(url, &filename, mime);
+        self.update(|d| d.mime = mime.map(str::to_string));
+        if !confirmed && file_policy.needs_confirmation() {
+            return Ok(AttemptOutcome::Held(file_policy));
+        }
+
+        // A full response to a ranged request means the server ignored the
+        // range (or If-Range found a newer file): start over
+        if offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+            let _ = self.tx.send(DownloadEvent::Restarted(
+                download_id,
+                "Server sent the full file instead of the requested range".to_string(),
+            ));
+        } else if offset > 0 {
+            let _ = self.tx.send(DownloadEvent::Resumed(download_id, offset));
+        }
+
+        let total_size = response.content_length().map(|len| len + offset).unwrap_or(0);
+        self.update(|d| d.size = total_size);
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let info = ResumeInfo {
+            url: url.to_string(),
+            final_path: final_path.clone(),
+            etag: header(ETAG).or_else(|| saved_info.as_ref().and_then(|i| i.etag.clone())),
+            last_modified: header(LAST_MODIFIED).or_else(|| saved_info.as_ref().and_then(|i| i.last_modified.clone())),
+            total_size,
+        };
+        let resume_from = if offset > 0 { Some(offset) } else { None };
+        let prepared = {
+            let storage = self.storage.clone();
+            let partial_path = partial_path.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = storage.save_resume_info(&partial_path, &info) {
+                    tracing::warn!("Failed to save resume info for {}: {}", info.url, e);
+                }
+                storage.create_download_file(&partial_path, resume_from)
+            })
+            .await
+        };
+        let mut file = match prepared {
+            Ok(Ok(file)) => tokio::fs::File::from_std(file),
+            Ok(Err(e)) => return Err(local(&e, offset)),
+            Err(e) => return Err(local(&e, offset)),
+        };
+
+        let mut stream = response.bytes_stream();
+        let mut downloaded = offset;
+
+        while let Some(item) = stream.next().await {
+            if self.is_cancelled() {
+                // Keep the partial data and metadata so the download can be resumed
+                return Ok(AttemptOutcome::Cancelled);
+            }
+
+            match item {
+                Ok(chunk) => {
+                    if let Err(e) = file.write_all(&chunk).await {
+                        return Err(local(&e, downloaded));
+                    }
+
+                    downloaded += chunk.len() as u64;
+                    let _ = self.tx.send(DownloadEvent::Progress(download_id, downloaded, total_size));
+                    self.update(|d| d.downloaded = downloaded);
+                }
+                Err(e) => {
+                    let message = ProxyRequestError::from_reqwest(url, &route, &e).to_string();
+                    // Written data is kept; the next attempt resumes after it
+                    let _ = file.flush().await;
+                    return Err(AttemptFailure::network(&e, message, downloaded));
+                }
+            }
+        }
+
+        if let Err(e) = file.flush().await {
+            return Err(local(&e, downloaded));
+        }
+        drop(file);
+        let storage = self.storage.clone();
+        let (partial_path, final_path) = (partial_path.clone(), final_path.clone());
+        match tokio::task::spawn_blocking(move || storage.finalize_partial(&partial_path, &final_path)).await {
+            Ok(Ok(())) => Ok(AttemptOutcome::Finished(downloaded, file_policy)),
+            Ok(Err(e)) => Err(local(&e, downloaded)),
+            Err(e) => Err(local(&e, downloaded)),
+        }
+    }
+}
+
+impl SearchSource for DownloadManager {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::Downloads
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = chrono::Utc::now();
+        self.get_downloads()
+            .into_iter()
+            .filter_map(|download| {
+                let score = match_score(&words, &[(&download.filename, 1.0), (&download.url, 0.6)])?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::Downloads,
+                    id: download.id.to_string(),
+                    title: download.filename.clone(),
+                    url: Some(download.url.clone()),
+                    detail: Some(download.path.clone()),
+                    score: score * recency_factor(download.started_at, now),
+                    timestamp: Some(download.started_at),
+                })
+            })
+            .collect()
+    }
+}
+
+impl SettingsProvider for DownloadManager {
+    fn module(&self) -> &str {
+        "downloads"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::integer("downloads.max_attempts", "Download attempts", 4, 1, 10)
+                .with_description("Tries per address when a download fails on a flaky connection, before using a mirror")
+                .with_category(SettingCategory::Downloads),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "downloads.max_attempts" => {
+                let attempts = value.as_i64().ok_or("Expected a number")?;
+                self.retry.lock_or_recover().max_attempts = attempts.clamp(1, 10) as u32;
+                Ok(())
+            }
+            _ => Err(format!("Unknown downloads setting {}", key).into()),
+        }
+    }
+}
+
+// Private helper functions
+
+/// Hold a download for the user's confirmation, or block it for good when
+/// the user may not override the policy
+fn hold_download(
+    downloads: &Mutex<Vec<Download>>,
+    pending: &Mutex<HashMap<usize, PendingDownload>>,
+    tx: &mpsc::UnboundedSender<DownloadEvent>,
+    download_id: usize,
+    download: PendingDownload,
+    policy: PolicyDecision,
+) {
+    let status = match policy.can_override() {
+        true => DownloadStatus::AwaitingConfirmation,
+        false => DownloadStatus::Blocked,
+    };
+    if let Some(d) = downloads.lock_or_recover().iter_mut().find(|d| d.id == download_id) {
+        d.status = status;
+    }
+    if policy.can_override() {
+        pending.lock_or_recover().insert(download_id, download);
+        let _ = tx.send(DownloadEvent::ConfirmationRequired(download_id, policy));
+    } else {
+        let _ = tx.send(DownloadEvent::Blocked(download_id, policy.reason));
+    }
+}
+
+/// Report a batch's progress as its downloads finish, until all have
+/// completed or failed. Downloads held, blocked, cancelled or removed
+/// count as failed.
+async fn watch_batch(
+    batch: DownloadBatch,
+    failed_to_start: usize,
+    downloads: Arc<Mutex<Vec<Download>>>,
+    tx: mpsc::UnboundedSender<DownloadEvent>,
+) {
+    let total = batch.download_ids.len() + failed_to_start;
+    let mut reported = None;
+    loop {
+        let (completed, failed, running) = {
+            let downloads = downloads.lock_or_recover();
+            batch.download_ids.iter().fold((0, failed_to_start, 0), |(completed, failed, running), id| {
+                match downloads.iter().find(|d| d.id == *id).map(|d| &d.status) {
+                    Some(DownloadStatus::Completed) => (completed + 1, failed, running),
+                    Some(DownloadStatus::Pending | DownloadStatus::Downloading) => (completed, failed, running + 1),
+                    _ => (completed, failed + 1, running),
+                }
+            })
+        };
+        if reported != Some((completed, failed)) {
+            reported = Some((completed, failed));
+            let _ = tx.send(DownloadEvent::BatchProgress(batch.id, completed, failed, total));
+        }
+        if running == 0 {
+            let _ = tx.send(DownloadEvent::BatchCompleted(batch.id, completed, failed));
+            return;
+        }
+        tokio::time::sleep(BATCH_POLL_INTERVAL).await;
+    }
+}
+
+/// Save the download list whenever a download is added, removed or
+/// changes state, until the manager is dropped
+async fn keep_saved(store: DownloadStore, downloads: Weak<Mutex<Vec<Download>>>) {
+    let mut saved = None;
+    loop {
+        tokio::time::sleep(STORE_SAVE_INTERVAL).await;
+        let Some(downloads) = downloads.upgrade() else {
+            return;
+        };
+        let (list, states) = {
+            let downloads = downloads.lock_or_recover();
+            let states: Vec<(usize, DownloadStatus, u64)> =
+                downloads.iter().map(|d| (d.id, d.status.clone(), d.size)).collect();
+            if saved.as_ref() == Some(&states) {
+                continue;
+            }
+            (downloads.clone(), states)
+        };
+        let store = store.clone();
+        match tokio::task::spawn_blocking(move || store.save(&list)).await {
+            Ok(Ok(())) => saved = Some(states),
+            Ok(Err(e)) => tracing::warn!("Failed to save the download list: {}", e),
+            Err(e) => tracing::warn!("Failed to save the download list: {}", e),
+        }
+    }
+}
+
+fn route_client(
+    proxy: Option<&Arc<Mutex<ProxyManager>>>,
+    client: &Client,
+    url: &str,
+) -> Result<(Client, ProxyRoute), ProxyRequestError> {
+    match proxy {
+        Some(proxy) => proxy.lock_or_recover().client_for_url(url),
+        None => Ok((client.clone(), ProxyRoute::Direct)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_download_manager_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        assert_eq!(manager.download_dir(), &temp_dir.path().to_path_buf());
+        assert_eq!(manager.get_downloads().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_empty_partial_restarts() {
+        let temp_dir = TempDir::new().unwrap();
+        let partial = temp_dir.path().join("file.bin.part");
+        std::fs::write(&partial, b"").unwrap();
+
+        let info = ResumeInfo {
+            url: "https://example.invalid/file.bin".to_string(),
+            final_path: temp_dir.path().join("file.bin"),
+            etag: None,
+            last_modified: None,
+            total_size: 0,
+        };
+        let decision = DownloadManager::verify_partial(&Client::new(), &info, &partial).await;
+        assert_eq!(decision, ResumeDecision::Restart("No partial data".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_keeps_partial_data() {
+        // Nothing listens on the port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let temp_dir = TempDir::new().unwrap();
+        let final_path = temp_dir.path().join("file.bin");
+        let partial = DownloadStorage::partial_path(&final_path);
+        std::fs::write(&partial, b"hello").unwrap();
+        let info = ResumeInfo {
+            url: format!("http://127.0.0.1:{}/file.bin", port),
+            final_path,
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            total_size: 11,
+        };
+        DownloadStorage::new(temp_dir.path().to_path_buf()).save_resume_info(&partial, &info).unwrap();
+
+        let manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let download_id = manager.resume_download(&partial).await.unwrap();
+        assert_eq!(manager.get_download(download_id).unwrap().status, DownloadStatus::Failed);
+        assert_eq!(std::fs::read(&partial).unwrap(), b"hello");
+        assert!(manager.retry_download(download_id).await.is_err());
+
+        // IDs are not handed out again once a download is removed
+        assert!(manager.remove_download(download_id));
+        assert_eq!(manager.register_download(&info.url, &info.final_path), download_id + 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_falls_back_to_mirror() {
+        use tokio::net::TcpListener;
+
+        // The primary keeps answering 503; the mirror serves the file
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let read = stream.read(&mut buf).await.unwrap_or(0);
+                let response = match String::from_utf8_lossy(&buf[..read]).starts_with("GET /mirror/") {
+                    true => "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                    false => "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        manager.set_retry_policy(RetryPolicy {
+            max_attempts: 2,
+            initial_backoff_ms: 10,
+            ..RetryPolicy::default()
+        });
+        let mut events = manager.subscribe_events();
+        let primary = format!("http://127.0.0.1:{}/files/data.txt", port);
+        let mirror = format!("http://127.0.0.1:{}/mirror/data.txt", port);
+        let download_id = manager.start_download_with_mirrors(&primary, std::slice::from_ref(&mirror)).await.unwrap();
+
+        let mut retries = Vec::new();
+        while let Some(event) = events.recv().await {
+            match event {
+                DownloadEvent::Retrying(_, url, _) => retries.push(url),
+                DownloadEvent::Completed(_) => break,
+                DownloadEvent::Failed(_, message) => panic!("Download failed: {}", message),
+                _ => {}
+            }
+        }
+        assert_eq!(retries, vec![primary.clone(), mirror.clone()]);
+
+        let download = manager.get_download(download_id).unwrap();
+        assert_eq!(std::fs::read(&download.path).unwrap(), b"hello");
+        let attempts: Vec<(&str, Option<&str>)> =
+            download.attempts.iter().map(|a| (a.url.as_str(), a.error.as_deref())).collect();
+        let unavailable = Some("Server returned 503 Service Unavailable");
+        assert_eq!(attempts, vec![(primary.as_str(), unavailable), (primary.as_str(), unavailable), (mirror.as_str(), None)]);
+        assert_eq!(download.mirrors, vec![mirror]);
+    }
+
+    #[tokio::test]
+    async fn test_data_url_saves_follow_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let ids = manager.start_from_text("data:text/plain,hello%20there").await.unwrap();
+        let download = manager.get_download(ids[0]).unwrap();
+        assert_eq!(download.status, DownloadStatus::Completed);
+        assert_eq!(std::fs::read(&download.path).unwrap(), b"hello there");
+        assert!(download.path.ends_with("download.txt"));
+
+        let held = manager
+            .save_data_url("data:application/x-msdownload;base64,TVo=", Some("setup.exe"))
+            .await
+            .unwrap();
+        assert_eq!(manager.get_download(held).unwrap().status, DownloadStatus::AwaitingConfirmation);
+        assert!(!temp_dir.path().join("setup.exe").exists());
+        manager.confirm_download(held).unwrap();
+        assert_eq!(std::fs::read(temp_dir.path().join("setup.exe")).unwrap(), b"MZ");
+        assert!(manager.confirm_download(held).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_saves_into_a_subfolder_and_reports_progress() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let read = stream.read(&mut buf).await.unwrap_or(0);
+                let response = match String::from_utf8_lossy(&buf[..read]).starts_with("GET /missing") {
+                    true => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    false => "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\npng",
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        manager.set_retry_policy(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+        let mut events = manager.subscribe_events();
+        let urls: Vec<String> = ["a/photo.png", "b/photo.png", "missing.png"]
+            .iter()
+            .map(|path| format!("http://127.0.0.1:{}/{}", port, path))
+            .collect();
+        let batch_id = manager.start_batch_download("Trip: day 1", &urls).await.unwrap();
+
+        let mut progress = Vec::new();
+        let finished = loop {
+            match events.recv().await.unwrap() {
+                DownloadEvent::BatchProgress(id, completed, failed, total) if id == batch_id => {
+                    progress.push((completed, failed, total))
+                }
+                DownloadEvent::BatchCompleted(id, completed, failed) if id == batch_id => break (completed, failed),
+                _ => {}
+            }
+        };
+        assert_eq!(finished, (2, 1));
+        assert_eq!(progress.last(), Some(&(2, 1, 3)));
+
+        let batch = manager.get_batch(batch_id).unwrap();
+        assert_eq!(batch.folder, temp_dir.path().join("Trip_ day 1"));
+        assert_eq!(manager.batch_of(batch.download_ids[0]), Some(batch_id));
+        assert!(batch.folder.join("photo.png").exists());
+        assert!(batch.folder.join("photo_1.png").exists());
+    }
+
+    #[tokio::test]
+    async fn test_recovery_resumes_partial_data_and_fails_the_rest() {
+        use tokio::net::TcpListener;
+
+        // Serves "hello world" with an ETag, honouring ranges
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let read = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..read]).to_lowercase();
+                let body: &[u8] = match request.split_once("range: bytes=") {
+                    Some((_, range)) => &b"hello world"[range.split('-').next().unwrap().parse::<usize>().unwrap()..],
+                    None => b"hello world",
+                };
+                let status = if body.len() < 11 { "206 Partial Content" } else { "200 OK" };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                if !request.starts_with("head") {
+                    let _ = stream.write_all(body).await;
+                }
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = DownloadStore::new(Some(temp_dir.path().join("downloads.json")));
+        let url = format!("http://127.0.0.1:{}/file.txt", port);
+        let saved = |id: usize, name: &str, status: DownloadStatus| Download {
+            id,
+            url: url.clone(),
+            filename: name.to_string(),
+            path: temp_dir.path().join(name).to_string_lossy().to_string(),
+            size: 11,
+            downloaded: 5,
+            status,
+            started_at: chrono::Utc::now(),
+            mirrors: Vec::new(),
+            attempts: Vec::new(),
+            mime: None,
+        };
+        store
+            .save(&[
+                saved(1, "done.txt", DownloadStatus::Completed),
+                saved(2, "partial.txt", DownloadStatus::Downloading),
+                saved(3, "lost.txt", DownloadStatus::Downloading),
+            ])
+            .unwrap();
+        let final_path = temp_dir.path().join("partial.txt");
+        let partial_path = DownloadStorage::partial_path(&final_path);
+        std::fs::write(&partial_path, b"hello").unwrap();
+        let info = ResumeInfo {
+            url: url.clone(),
+            final_path: final_path.clone(),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            total_size: 11,
+        };
+        DownloadStorage::new(temp_dir.path().to_path_buf()).save_resume_info(&partial_path, &info).unwrap();
+
+        let mut manager = DownloadManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        manager.set_store(store.clone());
+        let mut events = manager.subscribe_events();
+        let recovery = manager.recover().await.unwrap();
+        assert_eq!(recovery, DownloadRecovery { resumed: vec![2], failed: vec![3] });
+        assert_eq!(manager.get_download(3).unwrap().status, DownloadStatus::Failed);
+        assert!(manager.retry_download(1).await.is_err());
+
+        manager.retry_download(3).await.unwrap();
+        let mut completed = Vec::new();
+        while completed.len() < 2 {
+            match events.recv().await.unwrap() {
+                DownloadEvent::Completed(id) => completed.push(id),
+                DownloadEvent::Failed(id, message) if id != 3 || !message.starts_with("Interrupted") => {
+                    panic!("Download {} failed: {}", id, message)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(std::fs::read(&final_path).unwrap(), b"hello world");
+        assert_eq!(std::fs::read(temp_dir.path().join("lost.txt")).unwrap(), b"hello world");
+        manager.save_downloads().unwrap();
+        assert!(store.load().unwrap().iter().all(|d| d.status == DownloadStatus::Completed));
+        assert_eq!(manager.start_download(&url).await.unwrap(), 4);
+    }
+}
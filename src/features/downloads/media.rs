@@ -0,0 +1,402 @@
+// Media Stream Sniffer
+use super::retry::RetryPolicy;
+use crate::error::WebxError;
+use crate::features::web_inspector::NetworkRequest;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+/// Media kept per tab; the oldest finds are dropped first
+const MAX_MEDIA_PER_TAB: usize = 50;
+
+/// Script that reports audio, video and stream playlists the page loads
+pub const MEDIA_SNIFF_SCRIPT: &str = r#"
+(function() {
+    if (window.__webxMediaSniffer || !window.ipc) return;
+    window.__webxMediaSniffer = true;
+    const seen = new Set();
+    const media = /\.(mp4|m4v|webm|ogv|ogg|oga|mp3|m4a|flac|wav|opus|m3u8)(\?|#|$)/i;
+    let queue = [];
+    const flush = () => {
+        if (queue.length) window.ipc.send({ type: 'mediafound', resources: queue.splice(0) });
+    };
+    const found = (url, initiator, size) => {
+        if (!url || seen.has(url) || !/^https?:/.test(url)) return;
+        if (!media.test(url) && initiator !== 'video' && initiator !== 'audio') return;
+        seen.add(url);
+        if (!queue.length) setTimeout(flush, 500);
+        queue.push({ url: url, initiator: initiator || null, size: size || null });
+    };
+    if (window.PerformanceObserver) {
+        new PerformanceObserver((list) => list.getEntries().forEach((entry) => {
+            found(entry.name, entry.initiatorType, entry.encodedBodySize);
+        })).observe({ type: 'resource', buffered: true });
+    }
+    document.addEventListener('loadedmetadata', (e) => {
+        if (e.target instanceof HTMLMediaElement) {
+            found(e.target.currentSrc, e.target.tagName === 'VIDEO' ? 'video' : 'audio', null);
+        }
+    }, true);
+})();
+"#;
+
+/// Container of a media resource
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaFormat {
+    Mp4,
+    WebM,
+    Ogg,
+    Mp3,
+    M4a,
+    Aac,
+    Flac,
+    Wav,
+    /// HLS playlist of segments
+    Hls,
+}
+
+impl MediaFormat {
+    /// Recognize a media resource by MIME type or, failing that, by its
+    /// URL's extension. Stream segments, such as .ts, .m4s and .aac
+    /// files, are not media of their own.
+    pub fn detect(url: &str, mime: Option<&str>) -> Option<Self> {
+        let mime = mime.map(|mime| mime.split(';').next().unwrap_or_default().trim().to_lowercase());
+        let by_mime = match mime.as_deref() {
+            Some("application/vnd.apple.mpegurl" | "application/x-mpegurl" | "audio/mpegurl" | "audio/x-mpegurl") => {
+                Some(MediaFormat::Hls)
+            }
+            Some("video/mp4") => Some(MediaFormat::Mp4),
+            Some("video/webm" | "audio/webm") => Some(MediaFormat::WebM),
+            Some("video/ogg" | "audio/ogg") => Some(MediaFormat::Ogg),
+            Some("audio/mpeg" | "audio/mp3") => Some(MediaFormat::Mp3),
+            Some("audio/mp4" | "audio/x-m4a") => Some(MediaFormat::M4a),
+            Some("audio/aac") => Some(MediaFormat::Aac),
+            Some("audio/flac") => Some(MediaFormat::Flac),
+            Some("audio/wav" | "audio/x-wav") => Some(MediaFormat::Wav),
+            _ => None,
+        };
+        by_mime.or_else(|| {
+            let path = url::Url::parse(url).ok()?.path().to_lowercase();
+            match path.rsplit_once('.')?.1 {
+                "m3u8" => Some(MediaFormat::Hls),
+                "mp4" | "m4v" => Some(MediaFormat::Mp4),
+                "webm" => Some(MediaFormat::WebM),
+                "ogv" | "ogg" | "oga" | "opus" => Some(MediaFormat::Ogg),
+                "mp3" => Some(MediaFormat::Mp3),
+                "m4a" => Some(MediaFormat::M4a),
+                "flac" => Some(MediaFormat::Flac),
+                "wav" => Some(MediaFormat::Wav),
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether the media comes in segments to be joined into one file
+    pub fn is_stream(&self) -> bool {
+        *self == MediaFormat::Hls
+    }
+}
+
+/// A resource a page reported loading, from `MEDIA_SNIFF_SCRIPT`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SniffedResource {
+    pub url: String,
+    #[serde(default)]
+    pub mime: Option<String>,
+    /// What loaded it: `video`, `audio`, `xmlhttprequest`, ...
+    #[serde(default)]
+    pub initiator: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// Media found on a tab's page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DetectedMedia {
+    pub url: String,
+    pub format: MediaFormat,
+    pub size: Option<u64>,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Collects the audio, video and HLS playlists each tab's page loads, for
+/// the download manager to save
+pub struct MediaSniffer {
+    tabs: Mutex<HashMap<usize, Vec<DetectedMedia>>>,
+}
+
+impl MediaSniffer {
+    /// Create new media sniffer
+    pub fn new() -> Self {
+        Self {
+            tabs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Note a resource a page loaded; returns whether it is new media
+    pub fn observe(&self, tab_id: usize, resource: &SniffedResource) -> bool {
+        // Media elements can play URLs without a telling extension
+        let played = match resource.initiator.as_deref() {
+            Some("video") => Some(MediaFormat::Mp4),
+            Some("audio") => Some(MediaFormat::Mp3),
+            _ => None,
+        };
+        let format = MediaFormat::detect(&resource.url, resource.mime.as_deref()).or(played);
+        let Some(format) = format else {
+            return false;
+        };
+
+        let mut tabs = self.tabs.lock_or_recover();
+        let media = tabs.entry(tab_id).or_default();
+        if let Some(known) = media.iter_mut().find(|known| known.url == resource.url) {
+            known.size = resource.size.filter(|size| *size > 0).or(known.size);
+            return false;
+        }
+        if media.len() >= MAX_MEDIA_PER_TAB {
+            media.remove(0);
+        }
+        media.push(DetectedMedia {
+            url: resource.url.clone(),
+            format,
+            size: resource.size.filter(|size| *size > 0),
+            detected_at: Utc::now(),
+        });
+        true
+    }
+
+    /// Note a request seen by the network interception layer
+    pub fn observe_request(&self, request: &NetworkRequest) -> bool {
+        self.observe(
+            request.tab_id,
+            &SniffedResource {
+                url: request.url.clone(),
+                mime: request.mime_type.clone(),
+                initiator: None,
+                size: Some(request.response_size),
+            },
+        )
+    }
+
+    /// Media found on a tab's page, in the order it loaded
+    pub fn media(&self, tab_id: usize) -> Vec<DetectedMedia> {
+        self.tabs.lock_or_recover().get(&tab_id).cloned().unwrap_or_default()
+    }
+
+    /// The media most worth saving from a tab: a stream playlist, as it
+    /// holds the whole video, or else the largest file
+    pub fn main_media(&self, tab_id: usize) -> Option<DetectedMedia> {
+        let media = self.media(tab_id);
+        media
+            .iter()
+            .find(|media| media.format.is_stream())
+            .or_else(|| media.iter().max_by_key(|media| media.size.unwrap_or(0)))
+            .cloned()
+    }
+
+    /// Forget a tab's media when it navigates
+    pub fn clear_tab(&self, tab_id: usize) {
+        self.tabs.lock_or_recover().remove(&tab_id);
+    }
+}
+
+impl Default for MediaSniffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A variant stream of an HLS master playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsVariant {
+    pub url: String,
+    pub bandwidth: u64,
+}
+
+/// Segments of an HLS media playlist
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HlsMedia {
+    /// fMP4 initialization segment, written before the others
+    pub init_segment: Option<String>,
+    pub segments: Vec<String>,
+    /// The playlist is complete: not a live stream
+    pub ended: bool,
+    pub encrypted: bool,
+}
+
+/// A parsed HLS playlist
+#[derive(Debug, Clone, PartialEq)]
+pub enum HlsPlaylist {
+    Master(Vec<HlsVariant>),
+    Media(HlsMedia),
+}
+
+impl HlsPlaylist {
+    /// Parse an M3U8 playlist, resolving its URLs against `base_url`
+    pub fn parse(text: &str, base_url: &str) -> Result<Self, WebxError> {
+        let base = url::Url::parse(base_url)?;
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some("#EXTM3U") {
+            return Err(WebxError::Parse("Not an HLS playlist".to_string()));
+        }
+
+        let mut variants = Vec::new();
+        let mut media = HlsMedia::default();
+        let mut variant_bandwidth = None;
+        for line in lines {
+            if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+                variant_bandwidth = Some(attribute(attributes, "BANDWIDTH").and_then(|b| b.parse().ok()).unwrap_or(0));
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+                media.encrypted |= attribute(attributes, "METHOD").is_some_and(|method| method != "NONE");
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+                if let Some(uri) = attribute(attributes, "URI") {
+                    media.init_segment = Some(base.join(&uri)?.to_string());
+                }
+            } else if line == "#EXT-X-ENDLIST" || line == "#EXT-X-PLAYLIST-TYPE:VOD" {
+                media.ended = true;
+            } else if !line.starts_with('#') {
+                let url = base.join(line)?.to_string();
+                match variant_bandwidth.take() {
+                    Some(bandwidth) => variants.push(HlsVariant { url, bandwidth }),
+                    None => media.segments.push(url),
+                }
+            }
+        }
+
+        match variants.is_empty() {
+            true => Ok(HlsPlaylist::Media(media)),
+            false => Ok(HlsPlaylist::Master(variants)),
+        }
+    }
+}
+
+/// Fetch an HLS playlist; for a master playlist, the media playlist of its
+/// highest quality variant
+pub async fn fetch_hls_media(client: &Client, url: &str) -> Result<HlsMedia, WebxError> {
+    let mut url = url.to_string();
+    for _ in 0..2 {
+        let text = client.get(&url).send().await?.error_for_status()?.text().await?;
+        match HlsPlaylist::parse(&text, &url)? {
+            HlsPlaylist::Media(media) => return Ok(media),
+            HlsPlaylist::Master(variants) => {
+                let best = variants.into_iter().max_by_key(|variant| variant.bandwidth);
+                url = best.map(|variant| variant.url).ok_or("Playlist has no streams")?;
+            }
+        }
+    }
+    Err(WebxError::Parse("Playlist has no media segments".to_string()))
+}
+
+/// Download segments one after another into a single file, retrying each
+/// as the retry policy allows. `progress` gets the bytes and segments
+/// written so far and stops the download by returning false. Returns the
+/// bytes written, or None when stopped.
+pub(super) async fn concatenate_segments(
+    client: &Client,
+    segments: &[String],
+    path: &Path,
+    retry: &RetryPolicy,
+    mut progress: impl FnMut(u64, usize) -> bool,
+) -> Result<Option<u64>, WebxError> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut written = 0;
+    for (index, segment) in segments.iter().enumerate() {
+        let mut tries = 0;
+        let bytes = loop {
+            let fetched = async { client.get(segment).send().await?.error_for_status()?.bytes().await }.await;
+            match fetched {
+                Ok(bytes) => break bytes,
+                Err(e) if tries + 1 >= retry.max_attempts => {
+                    return Err(WebxError::Network(format!("Segment {} failed: {}", index + 1, e)));
+                }
+                Err(_) => {
+                    tries += 1;
+                    tokio::time::sleep(retry.backoff(tries, None)).await;
+                }
+            }
+        };
+        file.write_all(&bytes).await?;
+        written += bytes.len() as u64;
+        if !progress(written, index + 1) {
+            return Ok(None);
+        }
+    }
+    file.flush().await?;
+    Ok(Some(written))
+}
+
+// Private helper functions
+
+/// Value of a playlist tag attribute, without quotes
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, after) = quoted.split_once('"')?;
+                (value, after.strip_prefix(',').unwrap_or(after))
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        if key.trim() == name {
+            return Some(value.to_string());
+        }
+        rest = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniffs_media_and_parses_hls_playlists() {
+        let sniffer = MediaSniffer::new();
+        let resource = |url: &str, initiator: Option<&str>, size| SniffedResource {
+            url: url.to_string(),
+            mime: None,
+            initiator: initiator.map(str::to_string),
+            size,
+        };
+        assert!(sniffer.observe(1, &resource("https://cdn.example.com/clip.mp4?token=1", None, Some(5000))));
+        assert!(sniffer.observe(1, &resource("https://cdn.example.com/stream", Some("video"), None)));
+        assert!(!sniffer.observe(1, &resource("https://cdn.example.com/app.js", Some("script"), None)));
+        assert!(!sniffer.observe(1, &resource("https://cdn.example.com/seg1.ts", None, None)));
+        assert!(!sniffer.observe(1, &resource("https://cdn.example.com/clip.mp4?token=1", None, None)));
+        assert_eq!(sniffer.main_media(1).unwrap().url, "https://cdn.example.com/clip.mp4?token=1");
+        assert!(sniffer.observe(1, &resource("https://cdn.example.com/hls/master.m3u8", None, None)));
+        assert_eq!(sniffer.main_media(1).unwrap().format, MediaFormat::Hls);
+        sniffer.clear_tab(1);
+        assert!(sniffer.media(1).is_empty());
+
+        let master = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\
+            360p/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2500000,RESOLUTION=1280x720\n\
+            https://other.example.com/720p.m3u8\n";
+        let HlsPlaylist::Master(variants) = HlsPlaylist::parse(master, "https://cdn.example.com/hls/master.m3u8").unwrap()
+        else {
+            panic!("Expected a master playlist");
+        };
+        assert_eq!(variants[0].url, "https://cdn.example.com/hls/360p/index.m3u8");
+        assert_eq!(variants[1].bandwidth, 2_500_000);
+
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:6.0,\nseg1.m4s\n#EXTINF:4.5,\n/hls/seg2.m4s\n#EXT-X-ENDLIST\n";
+        let HlsPlaylist::Media(media) = HlsPlaylist::parse(media, "https://cdn.example.com/hls/360p/index.m3u8").unwrap()
+        else {
+            panic!("Expected a media playlist");
+        };
+        assert_eq!(media.init_segment.as_deref(), Some("https://cdn.example.com/hls/360p/init.mp4"));
+        assert_eq!(media.segments, ["https://cdn.example.com/hls/360p/seg1.m4s", "https://cdn.example.com/hls/seg2.m4s"]);
+        assert!(media.ended && !media.encrypted);
+        assert!(HlsPlaylist::parse("<html>", "https://cdn.example.com/").is_err());
+    }
+}
@@ -1,10 +1,27 @@
 // Download Management Module
 pub mod manager;
+pub mod media;
+mod policy;
 pub mod progress;
+mod retry;
+mod sources;
 pub mod storage;
+mod store;
 
-pub use manager::DownloadManager;
+pub use manager::{DownloadBatch, DownloadEvent, DownloadManager, DownloadRecovery};
+pub use media::{
+    fetch_hls_media, DetectedMedia, HlsMedia, HlsPlaylist, HlsVariant, MediaFormat, MediaSniffer, SniffedResource,
+    MEDIA_SNIFF_SCRIPT,
+};
+pub use policy::{
+    default_lockdown_path, is_dangerous_extension, is_dangerous_mime, DownloadLockdown, DownloadPolicy,
+    DownloadPolicyConfig, FileTypeAction, PolicyDecision, PolicySource, ANY_TYPE, DANGEROUS_EXTENSIONS,
+    DANGEROUS_MIME_TYPES,
+};
 pub use progress::DownloadProgress;
-pub use storage::DownloadStorage;
+pub use retry::RetryPolicy;
+pub use sources::{download_urls, extension_for_mime, DataUrl, MAX_INLINE_DOWNLOAD_BYTES};
+pub use storage::{DownloadStorage, ResumeInfo};
+pub use store::DownloadStore;
 
 use crate::core::Download;
\ No newline at end of file
@@ -0,0 +1,427 @@
+// Download File Type Policies
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::utils::{host_in_domain, site_domain};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files that run code when opened
+pub const DANGEROUS_EXTENSIONS: &[&str] = &[
+    "exe", "scr", "js", "jse", "vbs", "vbe", "bat", "cmd", "com", "msi", "msp", "ps1", "psm1", "jar", "hta", "pif",
+    "lnk", "reg", "wsf", "wsh", "cpl", "scf", "dll", "sys", "application", "appref-ms", "gadget", "app", "command",
+    "pkg", "dmg", "deb", "rpm", "run", "sh", "desktop", "appimage",
+];
+
+/// MIME types of files that run code when opened
+pub const DANGEROUS_MIME_TYPES: &[&str] = &[
+    "application/x-msdownload",
+    "application/x-msdos-program",
+    "application/x-ms-installer",
+    "application/x-msi",
+    "application/vnd.microsoft.portable-executable",
+    "application/x-executable",
+    "application/x-sh",
+    "application/java-archive",
+    "application/hta",
+    "application/x-apple-diskimage",
+];
+
+/// Override key that applies to every file type of a site
+pub const ANY_TYPE: &str = "*";
+
+/// What happens to a download of a file type
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum FileTypeAction {
+    /// Save to the download folder
+    #[default]
+    Save,
+    /// Save, then open with the system's default application
+    AutoOpen,
+    /// Wait until the user confirms the download
+    Ask,
+    /// Refuse unless the user explicitly confirms it
+    Block,
+}
+
+/// Which rule decided a download's action
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PolicySource {
+    Default,
+    FileType,
+    Site,
+    Lockdown,
+}
+
+/// What to do with one download
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyDecision {
+    pub action: FileTypeAction,
+    pub source: PolicySource,
+    /// The file can run code when opened
+    pub dangerous: bool,
+    /// Shown with a confirmation prompt or a block
+    pub reason: String,
+}
+
+impl PolicyDecision {
+    /// Whether the user may confirm a blocked or held download
+    pub fn can_override(&self) -> bool {
+        self.source != PolicySource::Lockdown
+    }
+
+    /// Whether the download has to wait for the user
+    pub fn needs_confirmation(&self) -> bool {
+        matches!(self.action, FileTypeAction::Ask | FileTypeAction::Block)
+    }
+}
+
+/// User file type rules
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DownloadPolicyConfig {
+    /// Actions by lowercase extension without the dot
+    pub extensions: BTreeMap<String, FileTypeAction>,
+    /// Actions by MIME type; `type/*` covers a whole type
+    pub mime_types: BTreeMap<String, FileTypeAction>,
+    /// Actions by site domain, then by extension, MIME type or `*`; a
+    /// site's rules also cover its subdomains
+    pub site_overrides: BTreeMap<String, BTreeMap<String, FileTypeAction>>,
+    /// Action for file types without a rule
+    pub default_action: FileTypeAction,
+}
+
+impl Default for DownloadPolicyConfig {
+    fn default() -> Self {
+        let mut extensions: BTreeMap<String, FileTypeAction> = DANGEROUS_EXTENSIONS
+            .iter()
+            .map(|extension| (extension.to_string(), FileTypeAction::Block))
+            .collect();
+        extensions.extend(["zip", "rar", "7z", "iso"].map(|extension| (extension.to_string(), FileTypeAction::Ask)));
+        Self {
+            extensions,
+            mime_types: DANGEROUS_MIME_TYPES
+                .iter()
+                .map(|mime| (mime.to_string(), FileTypeAction::Block))
+                .collect(),
+            site_overrides: BTreeMap::new(),
+            default_action: FileTypeAction::Save,
+        }
+    }
+}
+
+/// File type rules a deployment imposes, e.g. on Ledokoz OS machines,
+/// which neither the user nor site overrides can change
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DownloadLockdown {
+    /// Extensions that can never be downloaded
+    pub blocked_extensions: Vec<String>,
+    /// MIME types that can never be downloaded; `type/*` covers a whole type
+    pub blocked_mime_types: Vec<String>,
+    /// Never open downloads automatically
+    pub disable_auto_open: bool,
+    /// Dangerous files stay blocked even when the user confirms them
+    pub block_dangerous: bool,
+}
+
+/// Where deployments put the lockdown list
+pub fn default_lockdown_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(program_data).join("WebX").join("download-policy.json")
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        PathBuf::from("/etc/webx/download-policy.json")
+    }
+}
+
+/// Decides per file type, site and deployment lockdown whether a download
+/// is saved, opened, held for confirmation or blocked
+#[derive(Debug, Clone)]
+pub struct DownloadPolicy {
+    config: DownloadPolicyConfig,
+    lockdown: DownloadLockdown,
+    config_path: Option<PathBuf>,
+}
+
+impl DownloadPolicy {
+    /// Policy with the given rules, kept in memory only
+    pub fn new(config: Option<DownloadPolicyConfig>, lockdown: Option<DownloadLockdown>) -> Self {
+        Self {
+            config: config.unwrap_or_default(),
+            lockdown: lockdown.unwrap_or_default(),
+            config_path: None,
+        }
+    }
+
+    /// Load user rules from `config_path` (default: the config directory)
+    /// and the lockdown list from `lockdown_path` (default:
+    /// `default_lockdown_path()`). Missing files mean no rules of that kind.
+    pub fn load(config_path: Option<PathBuf>, lockdown_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_path = config_path.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("download_policy.json");
+            path
+        });
+        let config = read_json(&config_path)?;
+        let lockdown = read_json(&lockdown_path.unwrap_or_else(default_lockdown_path))?;
+        Ok(Self {
+            config_path: Some(config_path),
+            ..Self::new(config, lockdown)
+        })
+    }
+
+    pub fn config(&self) -> &DownloadPolicyConfig {
+        &self.config
+    }
+
+    pub fn lockdown(&self) -> &DownloadLockdown {
+        &self.lockdown
+    }
+
+    /// Set the action for an extension, `None` to remove the rule
+    pub fn set_extension_action(&mut self, extension: &str, action: Option<FileTypeAction>) -> Result<(), WebxError> {
+        let extension = normalize_extension(extension);
+        match action {
+            Some(action) => {
+                check_auto_open(action, is_dangerous_extension(&extension))?;
+                self.config.extensions.insert(extension, action);
+            }
+            None => {
+                self.config.extensions.remove(&extension);
+            }
+        }
+        self.save()
+    }
+
+    /// Set the action for a MIME type, `None` to remove the rule
+    pub fn set_mime_action(&mut self, mime: &str, action: Option<FileTypeAction>) -> Result<(), WebxError> {
+        let mime = mime.trim().to_lowercase();
+        match action {
+            Some(action) => {
+                check_auto_open(action, is_dangerous_mime(&mime))?;
+                self.config.mime_types.insert(mime, action);
+            }
+            None => {
+                self.config.mime_types.remove(&mime);
+            }
+        }
+        self.save()
+    }
+
+    /// Set a site's action for an extension, MIME type or `ANY_TYPE`, `None`
+    /// to remove it
+    pub fn set_site_override(&mut self, site: &str, file_type: &str, action: Option<FileTypeAction>) -> Result<(), WebxError> {
+        let domain = site_domain(site).ok_or_else(|| WebxError::Invalid(format!("Invalid site {}", site)))?;
+        let file_type = match file_type.contains('/') {
+            true => file_type.trim().to_lowercase(),
+            false => normalize_extension(file_type),
+        };
+        match action {
+            Some(action) => {
+                check_auto_open(action, is_dangerous_extension(&file_type) || is_dangerous_mime(&file_type))?;
+                self.config.site_overrides.entry(domain).or_default().insert(file_type, action);
+            }
+            None => {
+                if let Some(overrides) = self.config.site_overrides.get_mut(&domain) {
+                    overrides.remove(&file_type);
+                    if overrides.is_empty() {
+                        self.config.site_overrides.remove(&domain);
+                    }
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// Decide what to do with a file from `url`. The MIME type is known once
+    /// the server responds; before that, the file name decides alone.
+    pub fn decide(&self, url: &str, filename: &str, mime: Option<&str>) -> PolicyDecision {
+        let extension = file_extension(filename);
+        let mime = mime.map(|mime| mime.split(';').next().unwrap_or_default().trim().to_lowercase());
+        let dangerous = extension.as_deref().is_some_and(is_dangerous_extension)
+            || mime.as_deref().is_some_and(is_dangerous_mime);
+        let type_name = match (&extension, &mime) {
+            (Some(extension), _) => format!(".{} files", extension),
+            (None, Some(mime)) => format!("{} files", mime),
+            (None, None) => "This file".to_string(),
+        };
+
+        let locked = extension
+            .as_ref()
+            .is_some_and(|extension| self.lockdown.blocked_extensions.iter().any(|blocked| normalize_extension(blocked) == *extension))
+            || mime
+                .as_ref()
+                .is_some_and(|mime| self.lockdown.blocked_mime_types.iter().any(|blocked| mime_matches(blocked, mime)));
+        if locked || (dangerous && self.lockdown.block_dangerous) {
+            return PolicyDecision {
+                action: FileTypeAction::Block,
+                source: PolicySource::Lockdown,
+                dangerous,
+                reason: format!("{} are blocked by your administrator", type_name),
+            };
+        }
+
+        let (action, source) = self
+            .site_action(url, extension.as_deref(), mime.as_deref())
+            .map(|action| (action, PolicySource::Site))
+            .or_else(|| {
+                let by_extension = extension.as_ref().and_then(|extension| self.config.extensions.get(extension));
+                let by_mime = mime.as_deref().and_then(|mime| lookup_mime(&self.config.mime_types, mime));
+                by_extension.or(by_mime).map(|action| (*action, PolicySource::FileType))
+            })
+            .unwrap_or((self.config.default_action, PolicySource::Default));
+
+        // Files that run code are never opened without the user
+        let action = match action {
+            FileTypeAction::AutoOpen if dangerous || self.lockdown.disable_auto_open => FileTypeAction::Save,
+            action => action,
+        };
+        let reason = match (action, dangerous) {
+            (FileTypeAction::Block, true) => format!("{} can harm your computer", type_name),
+            (FileTypeAction::Block, false) => format!("{} are blocked", type_name),
+            (FileTypeAction::Ask, true) => format!("{} can harm your computer. Keep it anyway?", type_name),
+            (FileTypeAction::Ask, false) => "Keep this file?".to_string(),
+            _ => String::new(),
+        };
+        PolicyDecision {
+            action,
+            source,
+            dangerous,
+            reason,
+        }
+    }
+
+    // Private helper methods
+
+    /// Action of the most specific site rule covering the URL
+    fn site_action(&self, url: &str, extension: Option<&str>, mime: Option<&str>) -> Option<FileTypeAction> {
+        let host = url::Url::parse(url).ok()?.host_str()?.to_string();
+        let (_, overrides) = self
+            .config
+            .site_overrides
+            .iter()
+            .filter(|(domain, _)| host_in_domain(&host, domain))
+            .max_by_key(|(domain, _)| domain.len())?;
+        extension
+            .and_then(|extension| overrides.get(extension))
+            .or_else(|| mime.and_then(|mime| lookup_mime(overrides, mime)))
+            .or_else(|| overrides.get(ANY_TYPE))
+            .copied()
+    }
+
+    fn save(&self) -> Result<(), WebxError> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, &serde_json::to_vec_pretty(&self.config)?)?;
+        Ok(())
+    }
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+/// Whether files with an extension run code when opened
+pub fn is_dangerous_extension(extension: &str) -> bool {
+    DANGEROUS_EXTENSIONS.contains(&normalize_extension(extension).as_str())
+}
+
+/// Whether files of a MIME type run code when opened
+pub fn is_dangerous_mime(mime: &str) -> bool {
+    DANGEROUS_MIME_TYPES.contains(&mime.trim().to_lowercase().as_str())
+}
+
+// Private helper functions
+
+/// Lowercase extension of the last part of a file name
+fn file_extension(filename: &str) -> Option<String> {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    let (stem, extension) = name.rsplit_once('.')?;
+    (!stem.is_empty() && !extension.is_empty()).then(|| normalize_extension(extension))
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Exact MIME rule first, then a `type/*` rule
+fn lookup_mime<'a>(rules: &'a BTreeMap<String, FileTypeAction>, mime: &str) -> Option<&'a FileTypeAction> {
+    rules.get(mime).or_else(|| {
+        let (kind, _) = mime.split_once('/')?;
+        rules.get(&format!("{}/*", kind))
+    })
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    match pattern.strip_suffix("/*") {
+        Some(kind) => mime.split_once('/').is_some_and(|(mime_kind, _)| mime_kind == kind),
+        None => pattern == mime,
+    }
+}
+
+fn check_auto_open(action: FileTypeAction, dangerous: bool) -> Result<(), WebxError> {
+    if action == FileTypeAction::AutoOpen && dangerous {
+        return Err(WebxError::Invalid("Files that can run code cannot open automatically".to_string()));
+    }
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, WebxError> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_policy_layers() {
+        let temp_dir = TempDir::new().unwrap();
+        let lockdown_path = temp_dir.path().join("lockdown.json");
+        std::fs::write(&lockdown_path, r#"{ "blocked_extensions": ["torrent"], "disable_auto_open": false }"#).unwrap();
+        let mut policy = DownloadPolicy::load(Some(temp_dir.path().join("policy.json")), Some(lockdown_path.clone())).unwrap();
+
+        let url = "https://files.example.com/get";
+        assert_eq!(policy.decide(url, "report.pdf", Some("application/pdf")).action, FileTypeAction::Save);
+        let exe = policy.decide(url, "invoice.pdf.exe", None);
+        assert_eq!((exe.action, exe.dangerous, exe.can_override()), (FileTypeAction::Block, true, true));
+        let by_mime = policy.decide(url, "setup", Some("application/x-msdownload; charset=binary"));
+        assert_eq!(by_mime.action, FileTypeAction::Block);
+        assert_eq!(policy.decide(url, "archive.zip", None).action, FileTypeAction::Ask);
+
+        policy.set_extension_action("PDF", Some(FileTypeAction::AutoOpen)).unwrap();
+        assert_eq!(policy.decide(url, "report.pdf", None).action, FileTypeAction::AutoOpen);
+        assert!(policy.set_extension_action(".exe", Some(FileTypeAction::AutoOpen)).is_err());
+
+        // Sites override file types, but never the lockdown list
+        policy.set_site_override("example.com", "exe", Some(FileTypeAction::Ask)).unwrap();
+        policy.set_site_override("example.com", ANY_TYPE, Some(FileTypeAction::Save)).unwrap();
+        assert_eq!(policy.decide(url, "tool.exe", None).action, FileTypeAction::Ask);
+        assert_eq!(policy.decide(url, "archive.zip", None).action, FileTypeAction::Save);
+        assert_eq!(policy.decide("https://other.org/", "tool.exe", None).action, FileTypeAction::Block);
+        let locked = policy.decide(url, "movie.torrent", None);
+        assert_eq!((locked.action, locked.source, locked.can_override()), (FileTypeAction::Block, PolicySource::Lockdown, false));
+
+        // User rules are saved; the lockdown list is read again
+        let reloaded = DownloadPolicy::load(Some(temp_dir.path().join("policy.json")), Some(lockdown_path)).unwrap();
+        assert_eq!(reloaded.decide(url, "tool.exe", None).action, FileTypeAction::Ask);
+        assert_eq!(reloaded.decide("https://other.org/", "report.pdf", None).action, FileTypeAction::AutoOpen);
+    }
+}
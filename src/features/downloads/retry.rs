@@ -0,0 +1,127 @@
+// Download Retry Policy
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// When and how often failed transfers are tried again
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts per URL, the first included, before moving to the next mirror
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub initial_backoff_ms: u64,
+    /// Longest wait between attempts, also capping a server's Retry-After
+    pub max_backoff_ms: u64,
+    /// Factor the wait grows by with each retry
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60_000,
+            backoff_multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (1 for the first retry); a server's
+    /// Retry-After counts when it asks for longer
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let exponent = retry.saturating_sub(1).min(32) as i32;
+        let wait = self.initial_backoff_ms as f64 * self.backoff_multiplier.max(1.0).powi(exponent);
+        let max = Duration::from_millis(self.max_backoff_ms);
+        let wait = Duration::from_millis(wait.min(self.max_backoff_ms as f64) as u64);
+        retry_after.map_or(wait, |asked| asked.max(wait)).min(max)
+    }
+}
+
+/// How a failed attempt affects the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Likely to pass on a later try, e.g. a timeout or 503
+    Transient,
+    /// This URL will not serve the file; a mirror may
+    Source,
+    /// Writing the file failed; no URL can help
+    Local,
+}
+
+/// Why one attempt at a download failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttemptFailure {
+    pub message: String,
+    pub kind: FailureKind,
+    /// Wait the server asked for
+    pub retry_after: Option<Duration>,
+    /// Bytes of the partial file that are good to resume from
+    pub downloaded: u64,
+}
+
+impl AttemptFailure {
+    pub fn new(kind: FailureKind, message: String, downloaded: u64) -> Self {
+        Self {
+            message,
+            kind,
+            retry_after: None,
+            downloaded,
+        }
+    }
+
+    /// A request that failed on the way, described by `message`
+    pub fn network(error: &reqwest::Error, message: String, downloaded: u64) -> Self {
+        let kind = match error.is_timeout() || error.is_connect() || error.is_body() || error.is_request() {
+            true => FailureKind::Transient,
+            false => FailureKind::Source,
+        };
+        Self::new(kind, message, downloaded)
+    }
+
+    /// A response with an error status
+    pub fn status(response: &Response, downloaded: u64) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        Self {
+            retry_after,
+            ..Self::new(status_kind(status), format!("Server returned {}", status), downloaded)
+        }
+    }
+}
+
+/// Whether an error status is worth retrying at the same URL
+pub fn status_kind(status: StatusCode) -> FailureKind {
+    match status.as_u16() {
+        408 | 425 | 429 | 500 | 502 | 503 | 504 => FailureKind::Transient,
+        _ => FailureKind::Source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_honors_retry_after() {
+        let policy = RetryPolicy::default();
+        let secs = |retry, retry_after: Option<u64>| policy.backoff(retry, retry_after.map(Duration::from_secs)).as_secs();
+        assert_eq!([secs(1, None), secs(2, None), secs(3, None), secs(8, None)], [1, 2, 4, 60]);
+        assert_eq!(secs(1, Some(10)), 10);
+        assert_eq!(secs(3, Some(1)), 4);
+        assert_eq!(secs(1, Some(3600)), 60);
+
+        assert_eq!(status_kind(StatusCode::SERVICE_UNAVAILABLE), FailureKind::Transient);
+        assert_eq!(status_kind(StatusCode::TOO_MANY_REQUESTS), FailureKind::Transient);
+        assert_eq!(status_kind(StatusCode::NOT_FOUND), FailureKind::Source);
+        assert_eq!(status_kind(StatusCode::FORBIDDEN), FailureKind::Source);
+    }
+}
@@ -0,0 +1,165 @@
+// Dropped, Pasted and Page Download Sources
+use crate::error::WebxError;
+use base64::Engine;
+
+/// Largest file a page or a data: URL may hand over in memory
+pub const MAX_INLINE_DOWNLOAD_BYTES: usize = 100 * 1024 * 1024;
+
+/// A file carried inside a `data:` URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataUrl {
+    /// MIME type without parameters, `text/plain` when none is given
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+impl DataUrl {
+    /// Decode a `data:[<type>][;base64],<data>` URL
+    pub fn parse(url: &str) -> Result<Self, WebxError> {
+        let rest = url
+            .strip_prefix("data:")
+            .or_else(|| url.strip_prefix("DATA:"))
+            .ok_or_else(|| WebxError::Invalid("Not a data: URL".to_string()))?;
+        let (header, data) = rest
+            .split_once(',')
+            .ok_or_else(|| WebxError::Parse("data: URL has no data".to_string()))?;
+        let mut params = header.split(';').map(str::trim);
+        let mime = match params.next() {
+            Some(mime) if mime.contains('/') => mime.to_lowercase(),
+            _ => "text/plain".to_string(),
+        };
+        let base64 = params.any(|param| param.eq_ignore_ascii_case("base64"));
+
+        // The inline data is at least three quarters of its encoded size
+        if data.len() / 4 * 3 > MAX_INLINE_DOWNLOAD_BYTES {
+            return Err(WebxError::Invalid("data: URL is too large to save".to_string()));
+        }
+        let bytes = percent_decode(data);
+        let bytes = match base64 {
+            true => {
+                let encoded: Vec<u8> = bytes.into_iter().filter(|b| !b.is_ascii_whitespace()).collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(&encoded)
+                    .map_err(|e| WebxError::Parse(format!("Invalid base64 in data: URL: {}", e)))?
+            }
+            false => bytes,
+        };
+        Ok(Self { mime, bytes })
+    }
+
+    /// File name for saving, from a suggested name or the MIME type
+    pub fn filename(&self, suggested: Option<&str>) -> String {
+        match suggested.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => name.to_string(),
+            None => format!("download.{}", extension_for_mime(&self.mime)),
+        }
+    }
+}
+
+/// URLs worth downloading in dropped or pasted text: http(s) URLs among
+/// the words of each line, or a line holding a whole data: URL.
+/// `text/uri-list` comment lines are skipped.
+pub fn download_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        // data: URLs may contain spaces in their unencoded text
+        if line.starts_with("data:") {
+            if !urls.iter().any(|url| url == line) {
+                urls.push(line.to_string());
+            }
+            continue;
+        }
+        for word in line.split_whitespace() {
+            let word = word.trim_matches(|c| matches!(c, '<' | '>' | '"' | '\'' | '(' | ')' | ',' | ';'));
+            let usable =
+                url::Url::parse(word).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+            if usable && !urls.iter().any(|url| url == word) {
+                urls.push(word.to_string());
+            }
+        }
+    }
+    urls
+}
+
+/// Usual extension of a MIME type, `bin` for unknown types
+pub fn extension_for_mime(mime: &str) -> &'static str {
+    match mime.split(';').next().unwrap_or_default().trim() {
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/css" => "css",
+        "text/csv" => "csv",
+        "text/calendar" => "ics",
+        "text/markdown" => "md",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        "image/bmp" => "bmp",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        "audio/wav" => "wav",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "font/woff2" => "woff2",
+        _ => "bin",
+    }
+}
+
+// Private helper functions
+
+fn percent_decode(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_urls_and_dropped_text() {
+        let png = DataUrl::parse("data:image/png;base64,iVBO\nRw0K").unwrap();
+        assert_eq!(png.mime, "image/png");
+        assert_eq!(png.bytes, b"\x89PNG\r\n");
+        assert_eq!(png.filename(None), "download.png");
+        assert_eq!(png.filename(Some("chart.png")), "chart.png");
+
+        let text = DataUrl::parse("data:,Hello%2C%20World%21").unwrap();
+        assert_eq!((text.mime.as_str(), text.bytes.as_slice()), ("text/plain", &b"Hello, World!"[..]));
+        assert!(DataUrl::parse("data:text/plain;base64,@@@").is_err());
+        assert!(DataUrl::parse("https://example.com/").is_err());
+
+        let dropped = "# comment\nhttps://example.com/a.zip\r\nhttps://example.com/a.zip\nfile:///etc/passwd\n";
+        assert_eq!(download_urls(dropped), vec!["https://example.com/a.zip"]);
+        let pasted = "see <https://example.com/report.pdf> and data:text/plain,hi there";
+        assert_eq!(download_urls(pasted), vec!["https://example.com/report.pdf"]);
+        assert_eq!(download_urls("data:text/plain,hi there"), vec!["data:text/plain,hi there"]);
+        assert!(download_urls("just words").is_empty());
+    }
+}
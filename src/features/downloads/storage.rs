@@ -1,9 +1,53 @@
 // Download Storage Management
-use std::fs::File;
-use std::io::{Seek, SeekFrom, Write};
+use crate::error::WebxError;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
+/// Extension used for in-progress download data
+pub const PARTIAL_EXTENSION: &str = "part";
+
+/// Metadata kept next to a .part file so a download can be resumed after a restart
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResumeInfo {
+    pub url: String,
+    pub final_path: PathBuf,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub total_size: u64,
+}
+
+impl ResumeInfo {
+    /// Check the stored validators against the ones the server reports now.
+    ///
+    /// Returns None when no validators were stored, meaning the partial data
+    /// has to be compared by content instead.
+    pub fn validators_match(&self, etag: Option<&str>, last_modified: Option<&str>) -> Option<bool> {
+        if let Some(saved) = &self.etag {
+            // Weak ETags do not guarantee byte-identical content
+            if saved.starts_with("W/") {
+                return Some(false);
+            }
+            return Some(etag == Some(saved.as_str()));
+        }
+        if let Some(saved) = &self.last_modified {
+            return Some(last_modified == Some(saved.as_str()));
+        }
+        None
+    }
+
+    /// Value for the If-Range header of a resumed request
+    pub fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
 /// Download storage manager
+#[derive(Clone)]
 pub struct DownloadStorage {
     base_directory: PathBuf,
 }
@@ -16,11 +60,17 @@ impl DownloadStorage {
 
     /// Get unique filepath for a download
     pub fn get_unique_filepath(&self, filename: &str) -> PathBuf {
+        self.get_unique_filepath_avoiding(filename, &[])
+    }
+
+    /// Get unique filepath for a download, also avoiding paths downloads
+    /// still in progress will take
+    pub fn get_unique_filepath_avoiding(&self, filename: &str, taken: &[PathBuf]) -> PathBuf {
         let mut filepath = self.base_directory.join(filename);
         let original_path = filepath.clone();
         let mut counter = 1;
         
-        while filepath.exists() {
+        while filepath.exists() || taken.contains(&filepath) {
             let stem = original_path.file_stem().unwrap().to_string_lossy();
             let extension = original_path.extension().map(|ext| ext.to_string_lossy());
             
@@ -43,13 +93,76 @@ impl DownloadStorage {
         filepath: &Path,
         resume_from: Option<u64>,
     ) -> Result<File, std::io::Error> {
-        let mut file = File::create(filepath)?;
-        
-        if let Some(offset) = resume_from {
-            file.seek(SeekFrom::Start(offset))?;
+        match resume_from {
+            Some(offset) => {
+                // Keep existing data up to the resume offset
+                let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(filepath)?;
+                file.set_len(offset)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(file)
+            }
+            None => File::create(filepath),
         }
-        
-        Ok(file)
+    }
+
+    /// Path of the partial data file for a download
+    pub fn partial_path(final_path: &Path) -> PathBuf {
+        let mut name = final_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", PARTIAL_EXTENSION));
+        final_path.with_file_name(name)
+    }
+
+    /// Path of the resume metadata for a partial data file
+    fn resume_info_path(partial_path: &Path) -> PathBuf {
+        let mut name = partial_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".json");
+        partial_path.with_file_name(name)
+    }
+
+    /// Save resume metadata next to a partial download
+    pub fn save_resume_info(&self, partial_path: &Path, info: &ResumeInfo) -> Result<(), WebxError> {
+        let content = serde_json::to_string_pretty(info)?;
+        std::fs::write(Self::resume_info_path(partial_path), content)?;
+        Ok(())
+    }
+
+    /// Load resume metadata for a partial download
+    pub fn load_resume_info(&self, partial_path: &Path) -> Option<ResumeInfo> {
+        let content = std::fs::read_to_string(Self::resume_info_path(partial_path)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Move a finished partial download into place and drop its metadata
+    pub fn finalize_partial(&self, partial_path: &Path, final_path: &Path) -> Result<(), std::io::Error> {
+        std::fs::rename(partial_path, final_path)?;
+        let _ = std::fs::remove_file(Self::resume_info_path(partial_path));
+        Ok(())
+    }
+
+    /// Remove a partial download and its metadata
+    pub fn discard_partial(&self, partial_path: &Path) -> Result<(), std::io::Error> {
+        if partial_path.exists() {
+            std::fs::remove_file(partial_path)?;
+        }
+        let _ = std::fs::remove_file(Self::resume_info_path(partial_path));
+        Ok(())
+    }
+
+    /// List partial downloads in the base directory that can be resumed
+    pub fn list_resumable(&self) -> Vec<(PathBuf, ResumeInfo)> {
+        let mut resumable = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&self.base_directory) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_EXTENSION) {
+                    if let Some(info) = self.load_resume_info(&path) {
+                        resumable.push((path, info));
+                    }
+                }
+            }
+        }
+        resumable.sort_by(|a, b| a.0.cmp(&b.0));
+        resumable
     }
 
     /// Check if partial download exists
@@ -67,8 +180,17 @@ impl DownloadStorage {
 
     /// Clean up failed downloads
     pub fn cleanup_partial_downloads(&self) -> Result<(), std::io::Error> {
-        // Remove files that are likely incomplete downloads
-        // This is a simplified implementation
+        // Partial files without resume metadata can never be resumed
+        if let Ok(entries) = std::fs::read_dir(&self.base_directory) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_EXTENSION)
+                    && self.load_resume_info(&path).is_none()
+                {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -94,4 +216,71 @@ impl DownloadStorage {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resume_info_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = DownloadStorage::new(temp_dir.path().to_path_buf());
+        let final_path = temp_dir.path().join("archive.tar.gz");
+        let partial = DownloadStorage::partial_path(&final_path);
+        assert_eq!(partial.file_name().unwrap(), "archive.tar.gz.part");
+
+        let info = ResumeInfo {
+            url: "https://example.com/archive.tar.gz".to_string(),
+            final_path: final_path.clone(),
+            etag: Some("\"abc123\"".to_string()),
+            last_modified: None,
+            total_size: 1024,
+        };
+        std::fs::write(&partial, b"partial").unwrap();
+        storage.save_resume_info(&partial, &info).unwrap();
+
+        let resumable = storage.list_resumable();
+        assert_eq!(resumable.len(), 1);
+        assert_eq!(resumable[0].1, info);
+
+        storage.finalize_partial(&partial, &final_path).unwrap();
+        assert!(final_path.exists());
+        assert!(storage.list_resumable().is_empty());
+    }
+
+    #[test]
+    fn test_resume_keeps_existing_data() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = DownloadStorage::new(temp_dir.path().to_path_buf());
+        let path = temp_dir.path().join("file.bin.part");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let mut file = storage.create_download_file(&path, Some(4)).unwrap();
+        file.write_all(b"XY").unwrap();
+        drop(file);
+        assert_eq!(std::fs::read(&path).unwrap(), b"0123XY");
+    }
+
+    #[test]
+    fn test_validators() {
+        let mut info = ResumeInfo {
+            url: "https://example.com/f".to_string(),
+            final_path: PathBuf::from("f"),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Tue, 01 Oct 2024 10:00:00 GMT".to_string()),
+            total_size: 0,
+        };
+        assert_eq!(info.validators_match(Some("\"v1\""), None), Some(true));
+        assert_eq!(info.validators_match(Some("\"v2\""), None), Some(false));
+
+        info.etag = None;
+        assert_eq!(info.validators_match(None, Some("Tue, 01 Oct 2024 10:00:00 GMT")), Some(true));
+        assert_eq!(info.if_range(), Some("Tue, 01 Oct 2024 10:00:00 GMT"));
+
+        info.last_modified = None;
+        assert_eq!(info.validators_match(None, None), None);
+    }
+}
@@ -0,0 +1,42 @@
+// Download List Store
+use crate::config::storage::write_atomic;
+use crate::core::Download;
+use crate::error::WebxError;
+use std::path::PathBuf;
+
+/// The download list, saved so downloads are listed after a restart and
+/// ones a crash interrupted can be recovered
+#[derive(Debug, Clone)]
+pub struct DownloadStore {
+    path: PathBuf,
+}
+
+impl DownloadStore {
+    /// Store at `path`, or downloads.json in the data directory
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let path = path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("downloads.json");
+            path
+        });
+        Self { path }
+    }
+
+    /// Saved downloads, none when nothing was saved yet
+    pub fn load(&self) -> Result<Vec<Download>, WebxError> {
+        match std::fs::read(&self.path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, downloads: &[Download]) -> Result<(), WebxError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.path, &serde_json::to_vec_pretty(downloads)?)?;
+        Ok(())
+    }
+}
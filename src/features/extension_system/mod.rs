@@ -1,12 +1,38 @@
 // Extension System Module - Placeholder
-pub struct ExtensionSystem;
+use crate::config::ExtensionPolicy;
+use crate::error::WebxError;
+use std::path::Path;
+
+#[derive(Default)]
+pub struct ExtensionSystem {
+    policy: Option<ExtensionPolicy>,
+}
 
 impl ExtensionSystem {
     pub fn new() -> Self {
-        Self
+        Self { policy: None }
+    }
+
+    /// Restrict which extensions may be loaded to an administrator's lists
+    pub fn set_policy(&mut self, policy: Option<ExtensionPolicy>) {
+        self.policy = policy;
     }
-    
-    pub fn load_extension(&self, _path: &str) {
+
+    /// Whether an extension, by ID, may be loaded
+    pub fn is_allowed(&self, id: &str) -> bool {
+        self.policy.as_ref().is_none_or(|policy| policy.allows(id))
+    }
+
+    /// Load an extension from its directory, named after the extension's ID
+    pub fn load_extension(&self, path: &str) -> Result<(), WebxError> {
+        let id = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| WebxError::Invalid(format!("No extension at {}", path)))?;
+        if !self.is_allowed(id) {
+            return Err(WebxError::Invalid(format!("Extension {} is blocked by your administrator", id)));
+        }
         // Placeholder implementation
+        Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+// Feed Detection
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Reports pages that link feeds with a `type: 'feeds'` IPC message
+/// carrying the page's head for `detect_feeds`
+pub const FEED_DETECT_SCRIPT: &str = r#"
+(function() {
+    const report = () => {
+        const selector = 'link[rel~="alternate"][type*="rss"], link[rel~="alternate"][type*="atom"], ' +
+            'link[rel~="alternate"][type="application/feed+json"]';
+        if (!document.head || !document.head.querySelector(selector)) return;
+        window.ipc.send({ type: 'feeds', action: 'detected', url: location.href, head: document.head.outerHTML });
+    };
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', report);
+    } else {
+        report();
+    }
+})();
+"#;
+
+/// Format of a feed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FeedKind {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// A feed a page links to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedLink {
+    /// Absolute feed URL
+    pub url: String,
+    pub title: Option<String>,
+    pub kind: FeedKind,
+}
+
+/// Find the feeds a page advertises with `<link rel="alternate">`
+pub fn detect_feeds(html: &str, page_url: &str) -> Vec<FeedLink> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("link[rel][type][href]") else {
+        return Vec::new();
+    };
+    let base = url::Url::parse(page_url).ok();
+
+    let mut feeds: Vec<FeedLink> = Vec::new();
+    for link in document.select(&selector) {
+        let element = link.value();
+        let is_alternate = element
+            .attr("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("alternate")));
+        let kind = match element.attr("type").map(|t| t.trim().to_ascii_lowercase()).as_deref() {
+            Some("application/rss+xml") => FeedKind::Rss,
+            Some("application/atom+xml") => FeedKind::Atom,
+            Some("application/feed+json") | Some("application/json+feed") => FeedKind::Json,
+            _ => continue,
+        };
+        let href = element.attr("href").unwrap_or_default();
+        let url = match &base {
+            Some(base) => base.join(href).map(|url| url.to_string()).ok(),
+            None => url::Url::parse(href).map(|url| url.to_string()).ok(),
+        };
+        if let Some(url) = url.filter(|_| is_alternate) {
+            if !feeds.iter().any(|feed| feed.url == url) {
+                feeds.push(FeedLink {
+                    url,
+                    title: element.attr("title").map(str::trim).filter(|t| !t.is_empty()).map(str::to_string),
+                    kind,
+                });
+            }
+        }
+    }
+    feeds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_alternate_feed_links() {
+        let html = r#"<html><head>
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="/feed.xml">
+            <link rel="alternate" type="application/atom+xml" href="https://blog.example/atom">
+            <link rel="alternate" type="application/rss+xml" href="feed.xml">
+            <link rel="stylesheet" type="text/css" href="/style.css">
+            <link rel="alternate" hreflang="de" type="text/html" href="/de/">
+        </head></html>"#;
+
+        let feeds = detect_feeds(html, "https://blog.example/posts/");
+        assert_eq!(feeds.len(), 3);
+        assert_eq!(feeds[0].url, "https://blog.example/feed.xml");
+        assert_eq!(feeds[0].title.as_deref(), Some("Posts"));
+        assert_eq!(feeds[1].kind, FeedKind::Atom);
+        assert_eq!(feeds[2].url, "https://blog.example/posts/feed.xml");
+    }
+}
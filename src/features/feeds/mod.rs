@@ -0,0 +1,513 @@
+// Web Feeds Module
+pub mod detect;
+pub mod page;
+
+pub use detect::{detect_feeds, FeedKind, FeedLink, FEED_DETECT_SCRIPT};
+pub use page::{render_feeds_page, render_item_page, render_page, FeedsPage, FEEDS_PAGE_URL};
+
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sled::{Db, Tree};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// How often the poller looks for feeds that are due
+const POLL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Feed reader configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedConfig {
+    pub poll_interval_minutes: u64,
+    /// Older items beyond this many are dropped, read or not
+    pub max_items_per_feed: usize,
+    pub request_timeout_secs: u64,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_minutes: 60,
+            max_items_per_feed: 200,
+            request_timeout_secs: 30,
+        }
+    }
+}
+
+/// A subscribed feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Feed {
+    pub id: u64,
+    pub url: String,
+    pub title: String,
+    /// Site the feed belongs to
+    pub site_url: Option<String>,
+    pub subscribed_at: DateTime<Utc>,
+    pub last_polled: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Validators for conditional requests
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// An article from a feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedItem {
+    pub id: u64,
+    pub feed_id: u64,
+    pub title: String,
+    pub url: Option<String>,
+    /// Plain text summary
+    pub summary: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub fetched_at: DateTime<Utc>,
+    pub read: bool,
+}
+
+impl FeedItem {
+    /// When the item was published, or first seen if the feed does not say
+    pub fn date(&self) -> DateTime<Utc> {
+        self.published.unwrap_or(self.fetched_at)
+    }
+}
+
+/// Feed events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FeedEvent {
+    /// A poll found new items
+    NewItems { feed_id: u64, count: usize },
+    PollFailed { feed_id: u64, error: String },
+}
+
+/// Subscribes to RSS, Atom and JSON feeds, polls them and keeps their items
+/// with read state
+pub struct FeedManager {
+    config: FeedConfig,
+    db: Db,
+    feeds: Tree,
+    items: Tree,
+    client: reqwest::Client,
+    poll_task: Mutex<Option<JoinHandle<()>>>,
+    tx: mpsc::UnboundedSender<FeedEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<FeedEvent>>>,
+}
+
+impl FeedManager {
+    /// Open the feed store
+    pub fn new(config: Option<FeedConfig>, db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config = config.unwrap_or_default();
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("feeds.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .build()?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        Ok(Self {
+            config,
+            feeds: db.open_tree("feeds")?,
+            items: db.open_tree("items")?,
+            db,
+            client,
+            poll_task: Mutex::new(None),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        })
+    }
+
+    /// Subscribe to a feed and fetch its items. Subscribing again returns
+    /// the existing subscription.
+    pub async fn subscribe(&self, url: &str) -> Result<Feed, WebxError> {
+        if let Some(feed) = self.feeds().into_iter().find(|feed| feed.url == url) {
+            return Ok(feed);
+        }
+
+        let mut feed = Feed {
+            id: self.db.generate_id()?,
+            url: url.to_string(),
+            title: url.to_string(),
+            site_url: None,
+            subscribed_at: Utc::now(),
+            last_polled: None,
+            last_error: None,
+            etag: None,
+            last_modified: None,
+        };
+        let body = self.fetch(&mut feed).await?.ok_or("Feed sent no content")?;
+        self.import(&mut feed, &body)?;
+        tracing::info!("Subscribed to {} ({})", feed.title, feed.url);
+        Ok(feed)
+    }
+
+    /// Unsubscribe from a feed and drop its items
+    pub fn unsubscribe(&self, feed_id: u64) -> Result<bool, WebxError> {
+        let existed = self.feeds.remove(feed_id.to_be_bytes())?.is_some();
+        for item in self.items(Some(feed_id), false) {
+            self.items.remove(item.id.to_be_bytes())?;
+        }
+        Ok(existed)
+    }
+
+    /// Subscribed feeds by title
+    pub fn feeds(&self) -> Vec<Feed> {
+        let mut feeds: Vec<Feed> = self
+            .feeds
+            .iter()
+            .values()
+            .filter_map(|value| value.ok())
+            .filter_map(|value| serde_json::from_slice(&value).ok())
+            .collect();
+        feeds.sort_by_key(|feed| feed.title.to_lowercase());
+        feeds
+    }
+
+    /// Get a subscribed feed
+    pub fn get_feed(&self, feed_id: u64) -> Option<Feed> {
+        let value = self.feeds.get(feed_id.to_be_bytes()).ok()??;
+        serde_json::from_slice(&value).ok()
+    }
+
+    /// Check if a feed URL is subscribed
+    pub fn is_subscribed(&self, url: &str) -> bool {
+        self.feeds().iter().any(|feed| feed.url == url)
+    }
+
+    /// Items of one feed or all feeds, newest first
+    pub fn items(&self, feed_id: Option<u64>, unread_only: bool) -> Vec<FeedItem> {
+        let mut items: Vec<FeedItem> = self
+            .items
+            .iter()
+            .values()
+            .filter_map(|value| value.ok())
+            .filter_map(|value| serde_json::from_slice::<FeedItem>(&value).ok())
+            .filter(|item| feed_id.is_none_or(|id| item.feed_id == id) && !(unread_only && item.read))
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.date()));
+        items
+    }
+
+    /// Get an item
+    pub fn get_item(&self, item_id: u64) -> Option<FeedItem> {
+        let value = self.items.get(item_id.to_be_bytes()).ok()??;
+        serde_json::from_slice(&value).ok()
+    }
+
+    /// Number of unread items of one feed or all feeds
+    pub fn unread_count(&self, feed_id: Option<u64>) -> usize {
+        self.items(feed_id, true).len()
+    }
+
+    /// Mark an item read or unread
+    pub fn mark_read(&self, item_id: u64, read: bool) -> Result<bool, WebxError> {
+        let Some(mut item) = self.get_item(item_id) else {
+            return Ok(false);
+        };
+        item.read = read;
+        self.items.insert(item_id.to_be_bytes(), serde_json::to_vec(&item)?)?;
+        Ok(true)
+    }
+
+    /// Mark every item of one feed or all feeds read
+    pub fn mark_all_read(&self, feed_id: Option<u64>) -> Result<usize, WebxError> {
+        let unread = self.items(feed_id, true);
+        for item in &unread {
+            self.mark_read(item.id, true)?;
+        }
+        Ok(unread.len())
+    }
+
+    /// Fetch a feed and store its new items, returning how many were new
+    pub async fn poll_feed(&self, feed_id: u64) -> Result<usize, WebxError> {
+        let mut feed = self
+            .get_feed(feed_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Feed {}", feed_id)))?;
+
+        let result = match self.fetch(&mut feed).await {
+            Ok(Some(body)) => self.import(&mut feed, &body),
+            Ok(None) => Ok(0),
+            Err(e) => Err(e),
+        };
+        feed.last_polled = Some(Utc::now());
+        feed.last_error = result.as_ref().err().map(|e| e.to_string());
+        // The feed may have been unsubscribed while it was fetched
+        if self.feeds.contains_key(feed_id.to_be_bytes())? {
+            self.feeds.insert(feed_id.to_be_bytes(), serde_json::to_vec(&feed)?)?;
+        }
+
+        match &result {
+            Ok(0) => {}
+            Ok(count) => {
+                let _ = self.tx.send(FeedEvent::NewItems { feed_id, count: *count });
+            }
+            Err(e) => {
+                let _ = self.tx.send(FeedEvent::PollFailed {
+                    feed_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+        result
+    }
+
+    /// Poll the feeds whose interval has passed, returning how many new
+    /// items were found
+    pub async fn poll_due(&self) -> usize {
+        let interval = chrono::Duration::minutes(self.config.poll_interval_minutes as i64);
+        let now = Utc::now();
+        let mut new_items = 0;
+        for feed in self.feeds() {
+            if feed.last_polled.is_some_and(|polled| now - polled < interval) {
+                continue;
+            }
+            match self.poll_feed(feed.id).await {
+                Ok(count) => new_items += count,
+                Err(e) => tracing::debug!("Failed to poll {}: {}", feed.url, e),
+            }
+        }
+        new_items
+    }
+
+    /// Poll due feeds in the background. Must be called inside the runtime.
+    pub fn start_polling(manager: Arc<Self>) {
+        let weak = Arc::downgrade(&manager);
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(POLL_CHECK_INTERVAL);
+            loop {
+                timer.tick().await;
+                let Some(manager) = weak.upgrade() else {
+                    break;
+                };
+                manager.poll_due().await;
+            }
+        });
+        if let Some(previous) = manager.poll_task.lock_or_recover().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stop background polling
+    pub fn stop_polling(&self) {
+        if let Some(handle) = self.poll_task.lock_or_recover().take() {
+            handle.abort();
+        }
+    }
+
+    /// Fetch the page an item links to, for reading it in reader mode
+    pub async fn fetch_article(&self, item: &FeedItem) -> Result<String, WebxError> {
+        let url = item.url.as_deref().ok_or("Feed item has no link")?;
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(response.text().await?)
+    }
+
+    /// Subscribe to feed events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<FeedEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), WebxError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Private helper methods
+
+    /// Fetch a feed's document, or `None` if it did not change since the
+    /// last poll
+    async fn fetch(&self, feed: &mut Feed) -> Result<Option<Vec<u8>>, WebxError> {
+        let mut request = self.client.get(&feed.url);
+        if let Some(etag) = &feed.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &feed.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        feed.etag = header(ETAG);
+        feed.last_modified = header(LAST_MODIFIED);
+        Ok(Some(response.bytes().await?.to_vec()))
+    }
+
+    /// Parse a feed document, store the feed and its new items, and drop
+    /// the oldest items over the limit
+    fn import(&self, feed: &mut Feed, body: &[u8]) -> Result<usize, WebxError> {
+        let parsed = feed_rs::parser::Builder::new()
+            .base_uri(Some(&feed.url))
+            .build()
+            .parse(body)
+            .map_err(|e| WebxError::Parse(format!("Invalid feed {}: {}", feed.url, e)))?;
+        if let Some(title) = parsed.title.as_ref().map(|t| t.content.trim()).filter(|t| !t.is_empty()) {
+            feed.title = title.to_string();
+        }
+        feed.site_url = parsed
+            .links
+            .iter()
+            .find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+            .map(|link| link.href.clone());
+        self.feeds.insert(feed.id.to_be_bytes(), serde_json::to_vec(feed)?)?;
+
+        let now = Utc::now();
+        let mut new_items = 0;
+        for entry in parsed.entries {
+            let id = item_id(feed.id, &entry.id);
+            if self.items.contains_key(id.to_be_bytes())? {
+                continue;
+            }
+            let url = entry
+                .links
+                .iter()
+                .find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                .map(|link| link.href.clone());
+            let summary = entry
+                .summary
+                .as_ref()
+                .map(|text| plain_text(&text.content))
+                .filter(|text| !text.is_empty());
+            let item = FeedItem {
+                id,
+                feed_id: feed.id,
+                title: entry
+                    .title
+                    .map(|title| plain_text(&title.content))
+                    .filter(|title| !title.is_empty())
+                    .unwrap_or_else(|| "Untitled".to_string()),
+                url,
+                summary,
+                published: entry.published.or(entry.updated),
+                fetched_at: now,
+                read: false,
+            };
+            self.items.insert(id.to_be_bytes(), serde_json::to_vec(&item)?)?;
+            new_items += 1;
+        }
+
+        for item in self.items(Some(feed.id), false).into_iter().skip(self.config.max_items_per_feed) {
+            self.items.remove(item.id.to_be_bytes())?;
+        }
+        Ok(new_items)
+    }
+}
+
+impl Drop for FeedManager {
+    fn drop(&mut self) {
+        self.stop_polling();
+    }
+}
+
+// Private helper functions
+
+/// Stable ID of a feed entry, so an entry is stored once however often it
+/// is fetched
+fn item_id(feed_id: u64, entry_id: &str) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(feed_id.to_be_bytes())
+        .chain_update(entry_id.as_bytes())
+        .finalize();
+    // Leave the top bit clear so IDs stay exact in page scripts
+    u64::from_be_bytes(digest[..8].try_into().unwrap_or_default()) >> 11
+}
+
+/// Text of an HTML snippet, whitespace collapsed
+fn plain_text(html: &str) -> String {
+    let fragment = scraper::Html::parse_fragment(html);
+    let text: Vec<&str> = fragment.root_element().text().collect();
+    text.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn rss(items: &[(&str, &str)]) -> String {
+        let items: String = items
+            .iter()
+            .map(|(guid, title)| {
+                format!(
+                    "<item><guid>{guid}</guid><title>{title}</title><link>https://blog.example/{guid}</link>\
+                     <description>&lt;p&gt;About {title}&lt;/p&gt;</description></item>"
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Example Blog</title>
+            <link>https://blog.example/</link>{items}</channel></rss>"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_poll_and_read_state() {
+        // Serve one item first, then the same item and a new one
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let bodies = [rss(&[("a", "First")]), rss(&[("b", "Second"), ("a", "First")])];
+            for body in bodies.iter().cycle() {
+                let Ok((mut stream, _)) = listener.accept().await else { break };
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = FeedManager::new(None, Some(temp_dir.path().join("feeds.db"))).unwrap();
+        let mut events = manager.subscribe_events();
+        let url = format!("http://127.0.0.1:{}/feed.xml", port);
+
+        let feed = manager.subscribe(&url).await.unwrap();
+        assert_eq!(feed.title, "Example Blog");
+        assert_eq!(feed.site_url.as_deref(), Some("https://blog.example/"));
+        assert_eq!(manager.subscribe(&url).await.unwrap().id, feed.id);
+        let items = manager.items(None, false);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].summary.as_deref(), Some("About First"));
+
+        assert_eq!(manager.poll_feed(feed.id).await.unwrap(), 1);
+        assert!(matches!(events.recv().await, Some(FeedEvent::NewItems { count: 1, .. })));
+        assert_eq!(manager.unread_count(Some(feed.id)), 2);
+
+        let first = items[0].id;
+        assert!(manager.mark_read(first, true).unwrap());
+        assert_eq!(manager.items(None, true).len(), 1);
+        assert_eq!(manager.mark_all_read(None).unwrap(), 1);
+        assert_eq!(manager.unread_count(None), 0);
+
+        assert!(manager.unsubscribe(feed.id).unwrap());
+        assert!(manager.items(None, false).is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+// Feeds Page
+use super::{Feed, FeedItem, FeedManager};
+use crate::error::WebxError;
+use crate::features::ui::reader::{ArticleContent, ReadingMode};
+use crate::utils::escape_html;
+
+/// Internal page listing unread feed items
+pub const FEEDS_PAGE_URL: &str = "webx://feeds";
+
+/// A page under `webx://feeds`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedsPage {
+    /// The list of unread items
+    Index,
+    /// An item opened in reader mode
+    Read(u64),
+}
+
+impl FeedsPage {
+    /// Parse a `webx://feeds` URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(FEEDS_PAGE_URL)?;
+        let path = rest.split(['?', '#']).next().unwrap_or_default().trim_matches('/');
+        match path.split_once('/') {
+            None if path.is_empty() => Some(Self::Index),
+            Some(("read", id)) => id.parse().ok().map(Self::Read),
+            _ => None,
+        }
+    }
+
+    /// URL of the page
+    pub fn url(&self) -> String {
+        match self {
+            Self::Index => FEEDS_PAGE_URL.to_string(),
+            Self::Read(id) => format!("{}/read/{}", FEEDS_PAGE_URL, id),
+        }
+    }
+}
+
+/// Render a `webx://feeds` page. Opening an item marks it read.
+pub async fn render_page(manager: &FeedManager, reader: &ReadingMode, page: FeedsPage) -> Result<String, WebxError> {
+    match page {
+        FeedsPage::Index => Ok(render_feeds_page(&manager.feeds(), &manager.items(None, true))),
+        FeedsPage::Read(item_id) => {
+            let item = manager
+                .get_item(item_id)
+                .ok_or_else(|| WebxError::NotFound(format!("Feed item {}", item_id)))?;
+            // Offline or unreachable articles still show their summary
+            let article = match manager.fetch_article(&item).await {
+                Ok(html) => Some(html),
+                Err(e) => {
+                    tracing::debug!("Failed to fetch {:?}: {}", item.url, e);
+                    None
+                }
+            };
+            manager.mark_read(item_id, true)?;
+            Ok(render_item_page(reader, &item, article.as_deref()))
+        }
+    }
+}
+
+/// Render the unread items of the given feeds, grouped by feed. Buttons send
+/// `type: 'feeds'` IPC messages with an `action` of `markread`,
+/// `markallread`, `unsubscribe` or `refresh`.
+pub fn render_feeds_page(feeds: &[Feed], items: &[FeedItem]) -> String {
+    let mut sections = String::new();
+    for feed in feeds {
+        let unread: Vec<&FeedItem> = items.iter().filter(|item| item.feed_id == feed.id && !item.read).collect();
+        let error = feed
+            .last_error
+            .as_deref()
+            .map(|e| format!(r#"<p class="error">Last update failed: {}</p>"#, escape_html(e)))
+            .unwrap_or_default();
+        let list: String = unread
+            .iter()
+            .map(|item| {
+                let summary = item
+                    .summary
+                    .as_deref()
+                    .map(|s| format!("<p>{}</p>", escape_html(&truncate(s, 240))))
+                    .unwrap_or_default();
+                format!(
+                    r#"<li><a href="{href}">{title}</a> <time>{date}</time>
+<button data-action="markread" data-item="{id}">Mark read</button>{summary}</li>"#,
+                    href = FeedsPage::Read(item.id).url(),
+                    title = escape_html(&item.title),
+                    date = item.date().format("%Y-%m-%d"),
+                    id = item.id,
+                )
+            })
+            .collect();
+        let list = if list.is_empty() {
+            r#"<p class="empty">No unread articles</p>"#.to_string()
+        } else {
+            format!("<ul>{}</ul>", list)
+        };
+        sections.push_str(&format!(
+            r#"<section><h2>{title} <span class="count">{count}</span></h2>
+<button data-action="markallread" data-feed="{id}">Mark all read</button>
+<button data-action="unsubscribe" data-feed="{id}">Unsubscribe</button>{error}{list}</section>"#,
+            title = escape_html(&feed.title),
+            count = unread.len(),
+            id = feed.id,
+        ));
+    }
+    if feeds.is_empty() {
+        sections = r#"<p class="empty">No feeds yet. Use Subscribe to Feed on a page that has one.</p>"#.to_string();
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Feeds</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Feeds</h1> <button data-action="refresh">Refresh</button></header>
+    {sections}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        sections = sections,
+        script = PAGE_SCRIPT,
+    )
+}
+
+/// Render a feed item in reader mode from the fetched article, falling back
+/// to the item's summary when no article can be extracted
+pub fn render_item_page(reader: &ReadingMode, item: &FeedItem, article_html: Option<&str>) -> String {
+    let url = item.url.as_deref().unwrap_or_default();
+    let article = article_html
+        .and_then(|html| reader.extract_article(html, url))
+        .unwrap_or_else(|| ArticleContent {
+            title: escape_html(&item.title),
+            author: None,
+            publish_date: item.published.map(|date| date.format("%Y-%m-%d").to_string()),
+            content: format!(
+                "<p>{}</p><p><a href=\"{}\">Open the original article</a></p>",
+                escape_html(item.summary.as_deref().unwrap_or_default()),
+                escape_html(url)
+            ),
+            excerpt: item.summary.clone().unwrap_or_default(),
+            image: None,
+        });
+    reader.generate_reader_html(&article)
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+header { display: flex; align-items: center; gap: 1em; }
+section { border-top: 1px solid #ddd; padding: 0.5em 0 1em; }
+h2 { font-size: 1.2em; }
+.count { color: #666; font-weight: normal; }
+ul { list-style: none; padding: 0; }
+li { margin: 0.8em 0; }
+li p { margin: 0.3em 0; color: #555; }
+time { color: #888; font-size: 0.85em; margin: 0 0.5em; }
+button { font-size: 0.8em; }
+.error { color: #b00; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    const message = { type: 'feeds', action: button.dataset.action };
+    if (button.dataset.item) message.item = Number(button.dataset.item);
+    if (button.dataset.feed) message.feed = Number(button.dataset.feed);
+    window.ipc.send(message);
+});
+"#;
+
+// Private helper functions
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_feeds_page_lists_unread_items() {
+        let feed = Feed {
+            id: 1,
+            url: "https://blog.example/feed.xml".to_string(),
+            title: "Example <Blog>".to_string(),
+            site_url: None,
+            subscribed_at: Utc::now(),
+            last_polled: None,
+            last_error: None,
+            etag: None,
+            last_modified: None,
+        };
+        let item = |id, read| FeedItem {
+            id,
+            feed_id: 1,
+            title: format!("Post {}", id),
+            url: Some(format!("https://blog.example/{}", id)),
+            summary: None,
+            published: None,
+            fetched_at: Utc::now(),
+            read,
+        };
+
+        let html = render_feeds_page(&[feed], &[item(7, false), item(8, true)]);
+        assert!(html.contains("Example &lt;Blog&gt;"));
+        assert!(html.contains(r#"href="webx://feeds/read/7""#));
+        assert!(!html.contains("Post 8"));
+
+        assert_eq!(FeedsPage::parse("webx://feeds/"), Some(FeedsPage::Index));
+        assert_eq!(FeedsPage::parse("webx://feeds/read/7"), Some(FeedsPage::Read(7)));
+        assert_eq!(FeedsPage::parse("webx://feedsx"), None);
+    }
+}
@@ -0,0 +1,168 @@
+// Forget This Site
+use crate::error::WebxError;
+use super::{FormHistory, HistoryManager};
+use crate::features::caching::{FaviconCache, HTTPCache, SiteStorageManager};
+use crate::features::security::password_manager::PasswordManager;
+use crate::features::security::permissions::PermissionManager;
+use crate::features::tabs::{ContainerManager, ThumbnailService};
+use serde::{Deserialize, Serialize};
+
+/// Stores besides history holding data for sites. Stores left as `None`
+/// are not touched.
+#[derive(Default)]
+pub struct SiteDataStores<'a> {
+    pub containers: Option<&'a ContainerManager>,
+    pub http_cache: Option<&'a mut HTTPCache>,
+    pub favicons: Option<&'a FaviconCache>,
+    pub thumbnails: Option<&'a ThumbnailService>,
+    pub permissions: Option<&'a PermissionManager>,
+    pub passwords: Option<&'a PasswordManager>,
+    pub site_storage: Option<&'a SiteStorageManager>,
+    pub form_history: Option<&'a FormHistory>,
+}
+
+/// What forgetting a site removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ForgetSiteReport {
+    pub domain: String,
+    pub history_pages: usize,
+    pub cookies: usize,
+    pub cache_entries: usize,
+    pub favicons: usize,
+    pub thumbnails: usize,
+    pub permission_origins: usize,
+    pub passwords: usize,
+    /// Origins whose local storage and IndexedDB were cleared
+    pub site_storage_origins: usize,
+    /// Values remembered for the site's form fields
+    pub form_entries: usize,
+    /// Stores that failed to clear; the others are still cleared
+    pub errors: Vec<String>,
+}
+
+impl ForgetSiteReport {
+    /// Check if every store was cleared
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl HistoryManager {
+    /// Forget everything about a site and its subdomains: history, cookies,
+    /// cached responses, icons, thumbnails, permissions, saved passwords,
+    /// local storage and form history
+    pub fn forget_site(
+        &self,
+        site: &str,
+        stores: SiteDataStores<'_>,
+    ) -> Result<ForgetSiteReport, WebxError> {
+        let domain = crate::utils::site_domain(site).ok_or_else(|| format!("Invalid site {}", site))?;
+        let mut report = ForgetSiteReport {
+            domain: domain.clone(),
+            ..Default::default()
+        };
+
+        match self.delete_domain(&domain) {
+            Ok(pages) => report.history_pages = pages.len(),
+            Err(e) => report.errors.push(format!("history: {}", e)),
+        }
+        if let Some(containers) = stores.containers {
+            report.cookies = containers.clear_site_cookies(&domain);
+        }
+        if let Some(http_cache) = stores.http_cache {
+            report.cache_entries = http_cache.clear_site(&domain);
+        }
+        if let Some(favicons) = stores.favicons {
+            report.favicons = favicons.clear_site(&domain);
+        }
+        if let Some(thumbnails) = stores.thumbnails {
+            match thumbnails.clear_site(&domain) {
+                Ok(count) => report.thumbnails = count,
+                Err(e) => report.errors.push(format!("thumbnails: {}", e)),
+            }
+        }
+        if let Some(permissions) = stores.permissions {
+            match permissions.clear_domain(&domain) {
+                Ok(count) => report.permission_origins = count,
+                Err(e) => report.errors.push(format!("permissions: {}", e)),
+            }
+        }
+        if let Some(passwords) = stores.passwords {
+            match passwords.delete_site_passwords(&domain) {
+                Ok(count) => report.passwords = count,
+                Err(e) => report.errors.push(format!("passwords: {}", e)),
+            }
+        }
+        if let Some(site_storage) = stores.site_storage {
+            match site_storage.clear_site(&domain) {
+                Ok(count) => report.site_storage_origins = count,
+                Err(e) => report.errors.push(format!("site storage: {}", e)),
+            }
+        }
+        if let Some(form_history) = stores.form_history {
+            match form_history.clear_site(&domain) {
+                Ok(count) => report.form_entries = count,
+                Err(e) => report.errors.push(format!("form history: {}", e)),
+            }
+        }
+
+        tracing::info!("Forgot site {}: {:?}", domain, report);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::history_manager::VisitTransition;
+    use crate::features::security::permissions::{PermissionKind, PermissionState};
+    use reqwest::cookie::CookieStore;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_forget_site_clears_all_stores() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = HistoryManager::new(Some(temp_dir.path().join("history.db"))).unwrap();
+        let containers = ContainerManager::new(Some(temp_dir.path().join("containers"))).unwrap();
+        let permissions = PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let mut http_cache = HTTPCache::new(1, 60, false);
+
+        for url in ["https://www.example.com/", "https://mail.example.com/inbox", "https://other.org/"] {
+            history.add_visit(url, None, VisitTransition::Link).unwrap();
+            http_cache
+                .store_response(url.to_string(), 200, HashMap::new(), b"body".to_vec())
+                .unwrap();
+        }
+        permissions
+            .set_permission("https://mail.example.com", PermissionKind::Notifications, PermissionState::Granted)
+            .unwrap();
+
+        let url = url::Url::parse("https://example.com/").unwrap();
+        let jar = containers.cookie_jar(None);
+        let cookie = reqwest::header::HeaderValue::from_static("session=1; Path=/");
+        jar.set_cookies(&mut std::iter::once(&cookie), &url);
+
+        let report = history
+            .forget_site(
+                "https://www.example.com/some/page",
+                SiteDataStores {
+                    containers: Some(&containers),
+                    http_cache: Some(&mut http_cache),
+                    permissions: Some(&permissions),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert!(report.is_complete());
+        assert_eq!(report.domain, "example.com");
+        assert_eq!(report.history_pages, 2);
+        assert_eq!(report.cache_entries, 2);
+        assert_eq!(report.permission_origins, 1);
+        assert_eq!(report.cookies, 1);
+        assert!(jar.cookies(&url).is_none());
+        assert_eq!(history.item_count(), 1);
+        assert!(http_cache.get_response("https://other.org/").is_some());
+    }
+}
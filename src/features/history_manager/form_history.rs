@@ -0,0 +1,538 @@
+// Form History
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::{host_in_domain, site_domain, LockExt};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Entries kept before the least recently used are dropped
+const MAX_ENTRIES: usize = 5000;
+
+/// Longer values are not remembered
+const MAX_VALUE_CHARS: usize = 200;
+
+/// Suggestions shown under a field
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Input types whose values may be remembered
+const RECORDED_INPUT_TYPES: &[&str] = &["", "text", "search", "email", "tel", "url"];
+
+/// `autocomplete` tokens of fields never remembered
+const SENSITIVE_AUTOCOMPLETE: &[&str] = &["off", "new-password", "current-password", "one-time-code"];
+
+/// Field name words of fields never remembered
+const SENSITIVE_NAME_WORDS: &[&str] = &["cc", "csc", "cvc", "cvv", "iban", "otp", "pin", "ssn", "tan"];
+
+/// Field name parts of fields never remembered
+const SENSITIVE_NAME_PARTS: &[&str] = &["pass", "card", "cvc", "cvv", "expir", "secur"];
+
+/// Script recording values typed into search boxes and text fields when
+/// their form is submitted, or Enter is pressed in a field outside a form,
+/// and showing earlier values in a dropdown under the field.
+/// Shift+Delete removes the highlighted value.
+pub const FORM_HISTORY_SCRIPT: &str = r#"
+(function() {
+    if (window.formHistory || !/^https?:$/.test(location.protocol)) return;
+    const TYPES = ['', 'text', 'search', 'email', 'tel', 'url'];
+    const fieldName = (input) => input.name || input.id || '';
+    const eligible = (input) => input instanceof HTMLInputElement && !input.readOnly && !input.disabled
+        && TYPES.includes((input.getAttribute('type') || '').toLowerCase()) && fieldName(input) !== '';
+    const describe = (input) => ({
+        name: fieldName(input),
+        inputType: input.type,
+        autocomplete: input.getAttribute('autocomplete') || (input.form && input.form.getAttribute('autocomplete')) || '',
+        value: input.value,
+    });
+    const record = (inputs) => {
+        const fields = inputs.filter((input) => eligible(input) && input.value.trim()).map(describe);
+        if (fields.length) window.ipc.send({ type: 'formhistory', action: 'record', fields: fields });
+    };
+    let list = null, target = null, items = [], active = -1, filling = false;
+    const hide = () => { if (list) { list.remove(); list = null; } items = []; active = -1; };
+    const highlight = (index) => {
+        active = index;
+        items.forEach((item, i) => { item.style.background = i === index ? '#e8f0fe' : ''; });
+    };
+    const pick = (value) => {
+        hide();
+        filling = true;
+        target.value = value;
+        target.dispatchEvent(new Event('input', { bubbles: true }));
+        filling = false;
+    };
+    const show = (input, values) => {
+        hide();
+        if (!values.length || document.activeElement !== input) return;
+        target = input;
+        const rect = input.getBoundingClientRect();
+        list = document.createElement('div');
+        list.style.cssText = 'position:fixed;z-index:2147483647;background:#fff;color:#202124;border:1px solid #dadce0;border-radius:4px;box-shadow:0 2px 8px rgba(0,0,0,.2);font:13px system-ui,sans-serif;max-height:240px;overflow:auto;';
+        list.style.left = rect.left + 'px';
+        list.style.top = rect.bottom + 'px';
+        list.style.minWidth = rect.width + 'px';
+        items = values.map((value) => {
+            const item = document.createElement('div');
+            item.textContent = value;
+            item.style.cssText = 'padding:4px 8px;cursor:default;white-space:nowrap;';
+            item.addEventListener('mousedown', (e) => { e.preventDefault(); pick(value); });
+            list.append(item);
+            return item;
+        });
+        document.documentElement.append(list);
+    };
+    const suggest = (input) => {
+        if (!eligible(input)) return hide();
+        const prefix = input.value;
+        window.ipc.request({ type: 'formhistory', action: 'suggest', field: fieldName(input), prefix: prefix })
+            .then((values) => { if (input.value === prefix) show(input, values || []); })
+            .catch(hide);
+    };
+    document.addEventListener('input', (e) => { if (!filling) suggest(e.target); }, true);
+    document.addEventListener('submit', (e) => { hide(); record(Array.from(e.target.elements || [])); }, true);
+    document.addEventListener('focusout', (e) => { if (e.target === target) hide(); }, true);
+    document.addEventListener('keydown', (e) => {
+        const input = e.target;
+        if (list && input === target) {
+            if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+                e.preventDefault();
+                highlight(Math.max(-1, Math.min(items.length - 1, active + (e.key === 'ArrowDown' ? 1 : -1))));
+                return;
+            }
+            if (e.key === 'Enter' && active >= 0) {
+                e.preventDefault();
+                pick(items[active].textContent);
+                return;
+            }
+            if (e.key === 'Delete' && e.shiftKey && active >= 0) {
+                e.preventDefault();
+                window.ipc.send({ type: 'formhistory', action: 'remove', field: fieldName(input), value: items[active].textContent });
+                items.splice(active, 1)[0].remove();
+                if (items.length) highlight(Math.min(active, items.length - 1)); else hide();
+                return;
+            }
+            if (e.key === 'Escape') return hide();
+        } else if (e.key === 'ArrowDown' && eligible(input)) {
+            suggest(input);
+        }
+        if (e.key === 'Enter' && eligible(input) && !input.form) record([input]);
+    }, true);
+    window.formHistory = { hide: hide };
+})();
+"#;
+
+/// Whether form history is recorded, and where not
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FormHistorySettings {
+    /// Off until the user turns it on
+    pub enabled: bool,
+    /// Sites (and their subdomains) whose fields are neither remembered
+    /// nor suggested
+    pub excluded_sites: Vec<String>,
+}
+
+/// A value typed into a field on a site
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormHistoryEntry {
+    pub site: String,
+    pub field: String,
+    pub value: String,
+    pub use_count: u32,
+    pub first_used: chrono::DateTime<chrono::Utc>,
+    pub last_used: chrono::DateTime<chrono::Utc>,
+}
+
+/// A field of a submitted form, as the page script describes it
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    #[serde(default, rename = "inputType")]
+    pub input_type: String,
+    #[serde(default)]
+    pub autocomplete: String,
+    pub value: String,
+}
+
+impl FormField {
+    /// Check if the field may hold a password, card details or another
+    /// secret, so its value must not be remembered
+    pub fn is_sensitive(&self) -> bool {
+        let input_type = self.input_type.to_lowercase();
+        let autocomplete = self.autocomplete.to_lowercase();
+        let name = self.name.to_lowercase();
+        !RECORDED_INPUT_TYPES.contains(&input_type.as_str())
+            || autocomplete
+                .split_whitespace()
+                .any(|token| SENSITIVE_AUTOCOMPLETE.contains(&token) || token.starts_with("cc-"))
+            || name
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|word| SENSITIVE_NAME_WORDS.contains(&word))
+            || SENSITIVE_NAME_PARTS.iter().any(|part| name.contains(part))
+            || looks_like_card_number(&self.value)
+    }
+}
+
+/// What a page asks of form history
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum FormHistoryAction {
+    /// A form was submitted; answered with how many values were remembered
+    Record { fields: Vec<FormField> },
+    /// Answered with earlier values of the field starting with `prefix`
+    Suggest {
+        field: String,
+        #[serde(default)]
+        prefix: String,
+    },
+    /// The user removed a suggestion
+    Remove { field: String, value: String },
+}
+
+/// Opt-in store of values typed into search boxes and text fields, per
+/// site and field, for autocomplete dropdowns
+pub struct FormHistory {
+    db: Db,
+    entries: Tree,
+    meta: Tree,
+    settings: Mutex<FormHistorySettings>,
+}
+
+impl FormHistory {
+    /// Open the form history store
+    pub fn new(db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("form_history.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        let meta = db.open_tree("meta")?;
+        let settings = match meta.get("settings")? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => FormHistorySettings::default(),
+        };
+        Ok(Self {
+            entries: db.open_tree("entries")?,
+            meta,
+            db,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> FormHistorySettings {
+        self.settings.lock_or_recover().clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.lock_or_recover().enabled
+    }
+
+    /// Turn recording and suggestions on or off. Remembered values stay
+    /// until cleared.
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), WebxError> {
+        self.update_settings(|settings| settings.enabled = enabled)
+    }
+
+    /// Stop or resume remembering and suggesting values on a site and its
+    /// subdomains
+    pub fn set_site_excluded(&self, site: &str, excluded: bool) -> Result<(), WebxError> {
+        let domain = site_domain(site).ok_or_else(|| format!("Invalid site {}", site))?;
+        self.update_settings(|settings| {
+            settings.excluded_sites.retain(|excluded| excluded != &domain);
+            if excluded {
+                settings.excluded_sites.push(domain);
+            }
+        })
+    }
+
+    /// Check if a page's fields are remembered and suggested
+    pub fn is_active_for(&self, page_url: &str) -> bool {
+        self.site_of(page_url).is_some()
+    }
+
+    /// Remember the values of a submitted form's fields, skipping
+    /// sensitive ones; returns how many were remembered
+    pub fn record(&self, page_url: &str, fields: &[FormField]) -> Result<usize, WebxError> {
+        let Some(site) = self.site_of(page_url) else {
+            return Ok(0);
+        };
+        let now = chrono::Utc::now();
+        let mut recorded = 0;
+        for field in fields.iter().filter(|field| !field.is_sensitive()) {
+            let value = field.value.trim();
+            if value.is_empty() || value.chars().count() > MAX_VALUE_CHARS || field.name.is_empty() {
+                continue;
+            }
+            let key = entry_key(&site, &field.name, value);
+            let entry = match self.entries.get(&key)? {
+                Some(bytes) => {
+                    let mut entry: FormHistoryEntry = serde_json::from_slice(&bytes)?;
+                    entry.use_count += 1;
+                    entry.last_used = now;
+                    entry
+                }
+                None => FormHistoryEntry {
+                    site: site.clone(),
+                    field: field.name.clone(),
+                    value: value.to_string(),
+                    use_count: 1,
+                    first_used: now,
+                    last_used: now,
+                },
+            };
+            self.entries.insert(key, serde_json::to_vec(&entry)?)?;
+            recorded += 1;
+        }
+        if recorded > 0 {
+            self.prune()?;
+        }
+        Ok(recorded)
+    }
+
+    /// Earlier values of a field on a page's site starting with `prefix`,
+    /// most used first
+    pub fn suggestions(&self, page_url: &str, field: &str, prefix: &str) -> Result<Vec<String>, WebxError> {
+        let Some(site) = self.site_of(page_url) else {
+            return Ok(Vec::new());
+        };
+        let prefix = prefix.trim().to_lowercase();
+        let mut matches: Vec<FormHistoryEntry> = Vec::new();
+        for item in self.entries.scan_prefix(entry_key(&site, field, "")) {
+            let (_, bytes) = item?;
+            let entry: FormHistoryEntry = serde_json::from_slice(&bytes)?;
+            let lowercase = entry.value.to_lowercase();
+            if lowercase.starts_with(&prefix) && lowercase != prefix {
+                matches.push(entry);
+            }
+        }
+        matches.sort_by(|a, b| b.use_count.cmp(&a.use_count).then(b.last_used.cmp(&a.last_used)));
+        Ok(matches.into_iter().take(MAX_SUGGESTIONS).map(|entry| entry.value).collect())
+    }
+
+    /// Forget one value of a field on a page's site
+    pub fn remove(&self, page_url: &str, field: &str, value: &str) -> Result<bool, WebxError> {
+        let Some(site) = site_domain(page_url) else {
+            return Ok(false);
+        };
+        Ok(self.entries.remove(entry_key(&site, field, value.trim()))?.is_some())
+    }
+
+    /// All remembered values
+    pub fn entries(&self) -> Result<Vec<FormHistoryEntry>, WebxError> {
+        self.entries
+            .iter()
+            .values()
+            .map(|bytes| Ok(serde_json::from_slice(&bytes?)?))
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Forget every remembered value, returning how many there were
+    pub fn clear(&self) -> Result<usize, WebxError> {
+        let count = self.entries.len();
+        self.entries.clear()?;
+        self.db.flush()?;
+        Ok(count)
+    }
+
+    /// Forget the values remembered on a site and its subdomains
+    pub fn clear_site(&self, domain: &str) -> Result<usize, WebxError> {
+        let mut removed = 0;
+        for entry in self.entries()? {
+            if host_in_domain(&entry.site, domain) {
+                self.entries.remove(entry_key(&entry.site, &entry.field, &entry.value))?;
+                removed += 1;
+            }
+        }
+        self.db.flush()?;
+        Ok(removed)
+    }
+
+    // Private helper methods
+
+    /// Site a page's values are kept under, or `None` when form history
+    /// is off for it
+    fn site_of(&self, page_url: &str) -> Option<String> {
+        let url = url::Url::parse(page_url).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        let site = site_domain(page_url)?;
+        let settings = self.settings.lock_or_recover();
+        let excluded = settings.excluded_sites.iter().any(|excluded| host_in_domain(&site, excluded));
+        (settings.enabled && !excluded).then_some(site)
+    }
+
+    fn update_settings(&self, update: impl FnOnce(&mut FormHistorySettings)) -> Result<(), WebxError> {
+        let mut settings = self.settings.lock_or_recover();
+        update(&mut settings);
+        self.meta.insert("settings", serde_json::to_vec(&*settings)?)?;
+        Ok(())
+    }
+
+    /// Drop the least recently used values over the limit
+    fn prune(&self) -> Result<(), WebxError> {
+        if self.entries.len() <= MAX_ENTRIES {
+            return Ok(());
+        }
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.last_used);
+        let excess = entries.len() - MAX_ENTRIES;
+        for entry in entries.into_iter().take(excess) {
+            self.entries.remove(entry_key(&entry.site, &entry.field, &entry.value))?;
+        }
+        Ok(())
+    }
+}
+
+impl IpcHandler for FormHistory {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["formhistory"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::FormHistory(FormHistoryAction::Record { fields }) => {
+                Ok(self.record(&context.origin, &fields)?.into())
+            }
+            IpcMessage::FormHistory(FormHistoryAction::Suggest { field, prefix }) => {
+                Ok(serde_json::to_value(self.suggestions(&context.origin, &field, &prefix)?)?)
+            }
+            IpcMessage::FormHistory(FormHistoryAction::Remove { field, value }) => {
+                Ok(self.remove(&context.origin, &field, &value)?.into())
+            }
+            _ => Err(WebxError::Invalid("Not a form history message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for FormHistory {
+    fn module(&self) -> &str {
+        "forms"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("forms.history_enabled", "Remember search and form entries", false)
+                .with_description("Suggest what you typed into search boxes and text fields before; never passwords or card details")
+                .with_category(SettingCategory::Privacy),
+            SettingDefinition::text("forms.history_excluded_sites", "Sites without form history", "")
+                .with_description("Comma-separated sites whose entries are neither remembered nor suggested")
+                .with_category(SettingCategory::Privacy),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "forms.history_enabled" => self.set_enabled(value.as_bool().ok_or("Expected a toggle value")?),
+            "forms.history_excluded_sites" => {
+                let sites: Vec<String> = value
+                    .as_str()
+                    .ok_or("Expected a list of sites")?
+                    .split(',')
+                    .filter_map(site_domain)
+                    .collect();
+                self.update_settings(|settings| settings.excluded_sites = sites)
+            }
+            _ => Err(format!("Unknown forms setting {}", key).into()),
+        }
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        let settings = self.settings();
+        match key {
+            "forms.history_enabled" => Some(SettingValue::Bool(settings.enabled)),
+            "forms.history_excluded_sites" => Some(SettingValue::String(settings.excluded_sites.join(", "))),
+            _ => None,
+        }
+    }
+}
+
+// Private helper functions
+
+fn entry_key(site: &str, field: &str, value: &str) -> String {
+    format!("{}\0{}\0{}", site, field, value)
+}
+
+/// Check if a value is a payment card number: 13 to 19 digits, possibly
+/// grouped, passing the Luhn check
+fn looks_like_card_number(value: &str) -> bool {
+    let value = value.trim();
+    if !value.chars().all(|c| c.is_ascii_digit() || c == ' ' || c == '-') {
+        return false;
+    }
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            1 if digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn field(name: &str, input_type: &str, value: &str) -> FormField {
+        FormField {
+            name: name.to_string(),
+            input_type: input_type.to_string(),
+            autocomplete: String::new(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_form_history_is_opt_in_and_skips_sensitive_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = FormHistory::new(Some(temp_dir.path().join("form_history.db"))).unwrap();
+        let page = "https://www.example.com/search";
+        assert_eq!(history.record(page, &[field("q", "search", "rust sled")]).unwrap(), 0);
+
+        history.set_enabled(true).unwrap();
+        let mut card = field("number", "text", "4111 1111 1111 1111");
+        let submitted = [
+            field("q", "search", "rust sled"),
+            field("password", "password", "hunter2"),
+            field("card_cvc", "text", "123"),
+            card.clone(),
+        ];
+        assert_eq!(history.record(page, &submitted).unwrap(), 1);
+        card.autocomplete = "shipping cc-number".to_string();
+        card.value = "1234".to_string();
+        assert!(card.is_sensitive());
+        history.record("https://example.com/", &[field("q", "", "rust serde")]).unwrap();
+        history.record(page, &[field("q", "search", " rust sled ")]).unwrap();
+
+        assert_eq!(history.suggestions("https://example.com/", "q", "ru").unwrap(), ["rust sled", "rust serde"]);
+        assert!(history.suggestions(page, "email", "ru").unwrap().is_empty());
+        assert!(history.remove(page, "q", "rust serde").unwrap());
+
+        history.set_site_excluded("example.com", true).unwrap();
+        assert!(history.suggestions(page, "q", "").unwrap().is_empty());
+        assert_eq!(history.count(), 1);
+        assert_eq!(history.clear_site("example.com").unwrap(), 1);
+        assert_eq!(history.count(), 0);
+    }
+}
@@ -0,0 +1,120 @@
+// Frecency Scoring
+use serde::{Deserialize, Serialize};
+
+/// How a visit to a page came about
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum VisitTransition {
+    Link,
+    Typed,
+    Bookmark,
+    Reload,
+    Redirect,
+}
+
+/// Scores pages by how often and how recently they were visited.
+///
+/// A page's frecency is the sum over its visits of `weight × decay(age)`,
+/// where the weight depends on the transition (typed visits count most) and
+/// the decay halves every `half_life_days`. Because every visit decays at the
+/// same rate, the sum is kept as a time-independent log-scaled rank:
+/// `ln Σ weight × 2^(visit_day / half_life)`. Ranks order pages exactly like
+/// their frecency at any instant, so they only change when a page is visited
+/// and never need periodic recomputation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrecencyModel {
+    pub half_life_days: f64,
+    pub typed_multiplier: f64,
+    pub bookmark_multiplier: f64,
+    pub redirect_multiplier: f64,
+    pub reload_multiplier: f64,
+}
+
+impl Default for FrecencyModel {
+    fn default() -> Self {
+        Self {
+            half_life_days: 30.0,
+            typed_multiplier: 2.0,
+            bookmark_multiplier: 1.4,
+            redirect_multiplier: 0.25,
+            reload_multiplier: 0.1,
+        }
+    }
+}
+
+impl FrecencyModel {
+    /// Weight of a single visit before decay
+    pub fn visit_weight(&self, transition: VisitTransition) -> f64 {
+        match transition {
+            VisitTransition::Link => 1.0,
+            VisitTransition::Typed => self.typed_multiplier,
+            VisitTransition::Bookmark => self.bookmark_multiplier,
+            VisitTransition::Redirect => self.redirect_multiplier,
+            VisitTransition::Reload => self.reload_multiplier,
+        }
+    }
+
+    /// Rank contributed by one visit
+    pub fn visit_rank(&self, transition: VisitTransition, at: chrono::DateTime<chrono::Utc>) -> f64 {
+        self.visit_weight(transition).ln() + self.decay_rate() * days_since_epoch(at)
+    }
+
+    /// Fold a visit into a page's rank (`None` for a page never visited)
+    pub fn add_visit(
+        &self,
+        rank: Option<f64>,
+        transition: VisitTransition,
+        at: chrono::DateTime<chrono::Utc>,
+    ) -> f64 {
+        let visit = self.visit_rank(transition, at);
+        match rank {
+            Some(rank) => log_add_exp(rank, visit),
+            None => visit,
+        }
+    }
+
+    /// Current frecency of a page with the given rank
+    pub fn score(&self, rank: f64, now: chrono::DateTime<chrono::Utc>) -> f64 {
+        (rank - self.decay_rate() * days_since_epoch(now)).exp()
+    }
+
+    // Private helper methods
+
+    fn decay_rate(&self) -> f64 {
+        std::f64::consts::LN_2 / self.half_life_days.max(0.001)
+    }
+}
+
+// Private helper functions
+
+fn days_since_epoch(at: chrono::DateTime<chrono::Utc>) -> f64 {
+    at.timestamp_millis() as f64 / 86_400_000.0
+}
+
+fn log_add_exp(a: f64, b: f64) -> f64 {
+    let (high, low) = if a >= b { (a, b) } else { (b, a) };
+    high + (low - high).exp().ln_1p()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_combines_count_recency_and_typed() {
+        let model = FrecencyModel::default();
+        let now = chrono::Utc::now();
+
+        let once = model.add_visit(None, VisitTransition::Link, now);
+        let twice = model.add_visit(Some(once), VisitTransition::Link, now);
+        assert!((model.score(once, now) - 1.0).abs() < 1e-9);
+        assert!((model.score(twice, now) - 2.0).abs() < 1e-9);
+
+        let typed = model.add_visit(None, VisitTransition::Typed, now);
+        assert!((model.score(typed, now) - 2.0).abs() < 1e-9);
+
+        // A visit one half-life ago counts half
+        let old = model.add_visit(None, VisitTransition::Link, now - chrono::Duration::days(30));
+        assert!((model.score(old, now) - 0.5).abs() < 1e-9);
+        assert!(old < once);
+    }
+}
@@ -0,0 +1,201 @@
+// History Prefix Index
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+/// Prefix matches beyond this many terms are too common to collect; the
+/// query walks pages in rank order instead, which finds enough matches fast
+/// precisely because they are common
+const CANDIDATE_LIMIT: usize = 2000;
+/// Title words indexed per page
+const MAX_TITLE_TERMS: usize = 12;
+
+/// Frecency rank with a total order so it can key a `BTreeSet`
+#[derive(Debug, Clone, Copy)]
+struct Rank(f64);
+
+impl PartialEq for Rank {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Rank {}
+
+impl PartialOrd for Rank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// In-memory index answering autocomplete queries over history pages
+#[derive(Default)]
+pub struct PrefixIndex {
+    terms: BTreeSet<(String, u64)>,
+    ranked: BTreeSet<(Rank, u64)>,
+    pages: HashMap<u64, (Rank, Vec<String>)>,
+}
+
+impl PrefixIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index a page, replacing any previous entry for it
+    pub fn insert(&mut self, id: u64, url: &str, title: &str, rank: f64) {
+        self.remove(id);
+
+        let terms = page_terms(url, title);
+        for term in &terms {
+            self.terms.insert((term.clone(), id));
+        }
+        self.ranked.insert((Rank(rank), id));
+        self.pages.insert(id, (Rank(rank), terms));
+    }
+
+    /// Update the rank of an indexed page
+    pub fn update_rank(&mut self, id: u64, rank: f64) {
+        if let Some((old, _)) = self.pages.get_mut(&id) {
+            self.ranked.remove(&(*old, id));
+            *old = Rank(rank);
+            self.ranked.insert((Rank(rank), id));
+        }
+    }
+
+    /// Remove a page from the index
+    pub fn remove(&mut self, id: u64) {
+        if let Some((rank, terms)) = self.pages.remove(&id) {
+            self.ranked.remove(&(rank, id));
+            for term in terms {
+                self.terms.remove(&(term, id));
+            }
+        }
+    }
+
+    /// Number of indexed pages
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Find the highest ranked pages where every query word prefixes one of
+    /// the page's terms (its URL without scheme and `www.`, or a title word)
+    pub fn search(&self, query: &str, limit: usize) -> Vec<u64> {
+        let words: Vec<String> = query.split_whitespace().map(|word| word.to_lowercase()).collect();
+        if words.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        // The longest word has the fewest prefix matches
+        let probe = words.iter().max_by_key(|word| word.len()).unwrap();
+        let mut candidates: Vec<u64> = Vec::new();
+        let mut too_common = false;
+        for (term, id) in self.terms.range((probe.clone(), 0)..) {
+            if !term.starts_with(probe.as_str()) {
+                break;
+            }
+            if candidates.len() == CANDIDATE_LIMIT {
+                too_common = true;
+                break;
+            }
+            candidates.push(*id);
+        }
+
+        if too_common {
+            return self
+                .ranked
+                .iter()
+                .rev()
+                .map(|(_, id)| *id)
+                .filter(|id| self.matches(*id, &words))
+                .take(limit)
+                .collect();
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+        let mut matches: Vec<(Rank, u64)> = candidates
+            .into_iter()
+            .filter(|id| self.matches(*id, &words))
+            .filter_map(|id| self.pages.get(&id).map(|(rank, _)| (*rank, id)))
+            .collect();
+        matches.sort_unstable_by(|a, b| b.cmp(a));
+        matches.into_iter().take(limit).map(|(_, id)| id).collect()
+    }
+
+    // Private helper methods
+
+    fn matches(&self, id: u64, words: &[String]) -> bool {
+        self.pages.get(&id).is_some_and(|(_, terms)| {
+            words
+                .iter()
+                .all(|word| terms.iter().any(|term| term.starts_with(word.as_str())))
+        })
+    }
+}
+
+/// URL as typed into the address bar: no scheme, no `www.`, lowercase
+pub fn strip_url(url: &str) -> String {
+    let lower = url.to_lowercase();
+    let without_scheme = lower.split_once("://").map(|(_, rest)| rest).unwrap_or(&lower);
+    without_scheme
+        .strip_prefix("www.")
+        .unwrap_or(without_scheme)
+        .to_string()
+}
+
+// Private helper functions
+
+fn page_terms(url: &str, title: &str) -> Vec<String> {
+    let mut terms = vec![strip_url(url)];
+    for word in title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(MAX_TITLE_TERMS)
+    {
+        let word = word.to_lowercase();
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_large_index() {
+        let mut index = PrefixIndex::new();
+        for id in 0..100_000u64 {
+            let url = format!("https://www.site{}.example/page/{}", id % 5000, id);
+            index.insert(id, &url, &format!("Page number {}", id), id as f64);
+        }
+        index.insert(200_000, "https://rust-lang.org/", "Rust Programming Language", 50.0);
+
+        // A common prefix walks pages in rank order
+        let common = index.search("site", 3);
+        assert_eq!(common, vec![99_999, 99_998, 99_997]);
+
+        // A rare prefix is answered from the term index
+        assert_eq!(index.search("rust", 5), vec![200_000]);
+        assert_eq!(index.search("programming ru", 5), vec![200_000]);
+        assert_eq!(index.search("site4999.example/page/99", 2), vec![99_999, 9_999]);
+
+        index.update_rank(0, 1e9);
+        assert_eq!(index.search("page", 1), vec![0]);
+        index.remove(0);
+        assert_eq!(index.search("site0.example/page/0", 10), Vec::<u64>::new());
+    }
+}
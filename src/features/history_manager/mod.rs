@@ -1,12 +1,582 @@
-// History Manager Module - Placeholder
-pub struct HistoryManager;
+// History Manager Module
+pub mod forget;
+pub mod form_history;
+pub mod frecency;
+pub mod index;
+pub mod stats;
+
+pub use forget::{ForgetSiteReport, SiteDataStores};
+pub use form_history::{FormField, FormHistory, FormHistoryAction, FormHistoryEntry, FormHistorySettings, FORM_HISTORY_SCRIPT};
+pub use frecency::{FrecencyModel, VisitTransition};
+pub use index::PrefixIndex;
+pub use stats::{DailyActivity, DomainStats, TopSite};
+
+use crate::error::WebxError;
+use crate::core::{match_score, recency_factor, SearchQuery, SearchResult, SearchSource, SearchSourceKind};
+use crate::utils::{LockExt, url_in_domain};
+
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A visit the same way as a page's last one this soon after it repeats it
+const DUPLICATE_VISIT_SECS: i64 = 10;
+
+/// Reloads this soon after a page's last visit are part of that visit
+const RELOAD_COALESCE_MINUTES: i64 = 30;
+
+/// A single visit to a page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Visit {
+    pub visited_at: chrono::DateTime<chrono::Utc>,
+    pub transition: VisitTransition,
+}
+
+/// A page in history with its visits
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryItem {
+    pub id: u64,
+    pub url: String,
+    pub title: String,
+    pub visit_count: u32,
+    pub typed_count: u32,
+    pub first_visit: chrono::DateTime<chrono::Utc>,
+    pub last_visit: chrono::DateTime<chrono::Utc>,
+    /// Time-independent frecency rank, see `FrecencyModel`
+    pub frecency_rank: f64,
+    pub visits: Vec<Visit>,
+}
+
+/// An address bar suggestion from history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistorySuggestion {
+    pub url: String,
+    pub title: String,
+    pub frecency: f64,
+    /// Text to complete the typed input with, when it prefixes the URL
+    pub inline_completion: Option<String>,
+}
+
+struct HistoryState {
+    items: HashMap<String, HistoryItem>,
+    urls: HashMap<u64, String>,
+    index: PrefixIndex,
+}
+
+/// Browsing history store with frecency ranked autocomplete
+pub struct HistoryManager {
+    db: Db,
+    tree: Tree,
+    model: FrecencyModel,
+    state: Arc<Mutex<HistoryState>>,
+}
 
 impl HistoryManager {
-    pub fn new() -> Self {
-        Self
+    /// Open the history store
+    pub fn new(db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("history.db");
+            path
+        });
+
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        let manager = Self {
+            tree: db.open_tree("pages")?,
+            db,
+            model: FrecencyModel::default(),
+            state: Arc::new(Mutex::new(HistoryState {
+                items: HashMap::new(),
+                urls: HashMap::new(),
+                index: PrefixIndex::new(),
+            })),
+        };
+
+        manager.load_items()?;
+        Ok(manager)
+    }
+
+    /// Record a visit to a page
+    pub fn add_visit(
+        &self,
+        url: &str,
+        title: Option<&str>,
+        transition: VisitTransition,
+    ) -> Result<Option<HistoryItem>, WebxError> {
+        if !is_recordable(url) {
+            return Ok(None);
+        }
+
+        let now = chrono::Utc::now();
+        let mut state = self.state.lock_or_recover();
+
+        let mut item = match state.items.get(url) {
+            Some(item) => item.clone(),
+            None => HistoryItem {
+                id: self.db.generate_id()?,
+                url: url.to_string(),
+                title: String::new(),
+                visit_count: 0,
+                typed_count: 0,
+                first_visit: now,
+                last_visit: now,
+                frecency_rank: 0.0,
+                visits: Vec::new(),
+            },
+        };
+
+        let is_new = item.visit_count == 0;
+        let title_changed = title.is_some_and(|title| title != item.title);
+        if let Some(title) = title {
+            item.title = title.to_string();
+        }
+        // Reloads and repeated loads fold into the visit they repeat
+        if !item.visits.last().is_some_and(|previous| repeats(previous, transition, now)) {
+            item.visit_count += 1;
+            if transition == VisitTransition::Typed {
+                item.typed_count += 1;
+            }
+            item.frecency_rank = self.model.add_visit(
+                (!is_new).then_some(item.frecency_rank),
+                transition,
+                now,
+            );
+            item.visits.push(Visit {
+                visited_at: now,
+                transition,
+            });
+        }
+        item.last_visit = now;
+
+        self.tree.insert(url.as_bytes(), serde_json::to_vec(&item)?)?;
+
+        if is_new || title_changed {
+            state.index.insert(item.id, &item.url, &item.title, item.frecency_rank);
+        } else {
+            state.index.update_rank(item.id, item.frecency_rank);
+        }
+        state.urls.insert(item.id, item.url.clone());
+        state.items.insert(item.url.clone(), item.clone());
+
+        Ok(Some(item))
+    }
+
+    /// Update the title of a page once it is known
+    pub fn set_title(&self, url: &str, title: &str) -> Result<bool, WebxError> {
+        let mut state = self.state.lock_or_recover();
+        let item = match state.items.get_mut(url) {
+            Some(item) if item.title != title => {
+                item.title = title.to_string();
+                item.clone()
+            }
+            _ => return Ok(false),
+        };
+
+        self.tree.insert(url.as_bytes(), serde_json::to_vec(&item)?)?;
+        state.index.insert(item.id, &item.url, &item.title, item.frecency_rank);
+        Ok(true)
+    }
+
+    /// Get a page from history
+    pub fn get_item(&self, url: &str) -> Option<HistoryItem> {
+        self.state.lock_or_recover().items.get(url).cloned()
+    }
+
+    /// Get the most recently visited pages
+    pub fn get_recent(&self, limit: usize) -> Vec<HistoryItem> {
+        let mut items: Vec<HistoryItem> = self.state.lock_or_recover().items.values().cloned().collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.last_visit));
+        items.truncate(limit);
+        items
+    }
+
+    /// Number of pages in history
+    pub fn item_count(&self) -> usize {
+        self.state.lock_or_recover().items.len()
+    }
+
+    /// Current frecency of a page
+    pub fn frecency(&self, url: &str) -> Option<f64> {
+        let rank = self.state.lock_or_recover().items.get(url)?.frecency_rank;
+        Some(self.model.score(rank, chrono::Utc::now()))
+    }
+
+    /// Suggest pages for what has been typed into the address bar, best first
+    pub fn autocomplete(&self, input: &str, limit: usize) -> Vec<HistorySuggestion> {
+        let typed = input.trim().to_lowercase();
+        let now = chrono::Utc::now();
+        let state = self.state.lock_or_recover();
+
+        state
+            .index
+            .search(&typed, limit)
+            .into_iter()
+            .filter_map(|id| state.urls.get(&id).and_then(|url| state.items.get(url)))
+            .map(|item| HistorySuggestion {
+                url: item.url.clone(),
+                title: item.title.clone(),
+                frecency: self.model.score(item.frecency_rank, now),
+                inline_completion: inline_completion(&typed, &item.url),
+            })
+            .collect()
     }
-    
-    pub fn add_visit(&self, _url: &str) {
-        // Placeholder implementation
+
+    /// Delete the visits made between `from` (inclusive) and `to`
+    /// (exclusive), returning how many were deleted. Pages left without
+    /// visits are removed; the rest are re-ranked from their remaining visits.
+    pub fn delete_range(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<usize, WebxError> {
+        let in_range = |visit: &Visit| visit.visited_at >= from && visit.visited_at < to;
+        let mut state = self.state.lock_or_recover();
+
+        let affected: Vec<HistoryItem> = state
+            .items
+            .values()
+            .filter(|item| item.visits.iter().any(in_range))
+            .cloned()
+            .collect();
+
+        let mut deleted = 0;
+        for mut item in affected {
+            let before = item.visits.len();
+            item.visits.retain(|visit| !in_range(visit));
+            deleted += before - item.visits.len();
+
+            if item.visits.is_empty() {
+                self.tree.remove(item.url.as_bytes())?;
+                state.index.remove(item.id);
+                state.urls.remove(&item.id);
+                state.items.remove(&item.url);
+            } else {
+                self.recompute(&mut item);
+                self.tree.insert(item.url.as_bytes(), serde_json::to_vec(&item)?)?;
+                state.index.update_rank(item.id, item.frecency_rank);
+                state.items.insert(item.url.clone(), item);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Count the visits made between `from` (inclusive) and `to` (exclusive)
+    pub fn count_range(&self, from: chrono::DateTime<chrono::Utc>, to: chrono::DateTime<chrono::Utc>) -> usize {
+        self.state
+            .lock_or_recover()
+            .items
+            .values()
+            .flat_map(|item| item.visits.iter())
+            .filter(|visit| visit.visited_at >= from && visit.visited_at < to)
+            .count()
+    }
+
+    /// Delete every page of a domain and its subdomains, returning the
+    /// deleted pages
+    pub fn delete_domain(&self, domain: &str) -> Result<Vec<HistoryItem>, WebxError> {
+        let domain = crate::utils::site_domain(domain).ok_or("Invalid domain")?;
+        self.delete_where(|item| url_in_domain(&item.url, &domain))
+    }
+
+    /// Delete a single page
+    pub fn delete_url(&self, url: &str) -> Result<bool, WebxError> {
+        Ok(!self.delete_where(|item| item.url == url)?.is_empty())
+    }
+
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), WebxError> {
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    /// Get the frecency model
+    pub fn get_model(&self) -> &FrecencyModel {
+        &self.model
+    }
+
+    // Private helper methods
+
+    fn delete_where(
+        &self,
+        matches: impl Fn(&HistoryItem) -> bool,
+    ) -> Result<Vec<HistoryItem>, WebxError> {
+        let mut state = self.state.lock_or_recover();
+        let urls: Vec<String> = state
+            .items
+            .values()
+            .filter(|item| matches(item))
+            .map(|item| item.url.clone())
+            .collect();
+
+        let mut deleted = Vec::new();
+        for url in urls {
+            self.tree.remove(url.as_bytes())?;
+            if let Some(item) = state.items.remove(&url) {
+                state.index.remove(item.id);
+                state.urls.remove(&item.id);
+                deleted.push(item);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Fold the repeated visits of a page stored before they were folded
+    /// as they came. Returns whether any were.
+    fn coalesce(&self, item: &mut HistoryItem) -> bool {
+        let before = item.visits.len();
+        let mut visits: Vec<Visit> = Vec::with_capacity(before);
+        for visit in std::mem::take(&mut item.visits) {
+            if !visits.last().is_some_and(|previous| repeats(previous, visit.transition, visit.visited_at)) {
+                visits.push(visit);
+            }
+        }
+        item.visits = visits;
+        if item.visits.len() == before {
+            return false;
+        }
+        let last_visit = item.last_visit;
+        self.recompute(item);
+        item.last_visit = last_visit;
+        true
+    }
+
+    fn recompute(&self, item: &mut HistoryItem) {
+        item.visits.sort_by_key(|visit| visit.visited_at);
+        item.visit_count = item.visits.len() as u32;
+        item.typed_count = item
+            .visits
+            .iter()
+            .filter(|visit| visit.transition == VisitTransition::Typed)
+            .count() as u32;
+        if let (Some(first), Some(last)) = (item.visits.first(), item.visits.last()) {
+            item.first_visit = first.visited_at;
+            item.last_visit = last.visited_at;
+        }
+        item.frecency_rank = item.visits.iter().fold(None, |rank, visit| {
+            Some(self.model.add_visit(rank, visit.transition, visit.visited_at))
+        })
+        .unwrap_or(0.0);
+    }
+
+    fn load_items(&self) -> Result<(), WebxError> {
+        let mut state = self.state.lock_or_recover();
+        for entry in self.tree.iter() {
+            let (_, value) = entry?;
+            let mut item: HistoryItem = match serde_json::from_slice(&value) {
+                Ok(item) => item,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable history entry: {}", e);
+                    continue;
+                }
+            };
+            if self.coalesce(&mut item) {
+                self.tree.insert(item.url.as_bytes(), serde_json::to_vec(&item)?)?;
+            }
+            state.index.insert(item.id, &item.url, &item.title, item.frecency_rank);
+            state.urls.insert(item.id, item.url.clone());
+            state.items.insert(item.url.clone(), item);
+        }
+        Ok(())
+    }
+}
+
+impl SearchSource for HistoryManager {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::History
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = chrono::Utc::now();
+        self.state
+            .lock_or_recover()
+            .items
+            .values()
+            .filter_map(|item| {
+                let score = match_score(&words, &[(&item.title, 1.0), (&item.url, 0.8)])?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::History,
+                    id: item.id.to_string(),
+                    title: item.title.clone(),
+                    url: Some(item.url.clone()),
+                    detail: None,
+                    score: score * recency_factor(item.last_visit, now),
+                    timestamp: Some(item.last_visit),
+                })
+            })
+            .collect()
+    }
+}
+
+// Private helper functions
+
+/// Whether a visit repeats the page's previous one: a reload soon after it,
+/// or the same kind of visit again within seconds
+fn repeats(previous: &Visit, transition: VisitTransition, at: chrono::DateTime<chrono::Utc>) -> bool {
+    let elapsed = at - previous.visited_at;
+    match transition {
+        VisitTransition::Reload => elapsed < chrono::Duration::minutes(RELOAD_COALESCE_MINUTES),
+        _ => transition == previous.transition && elapsed < chrono::Duration::seconds(DUPLICATE_VISIT_SECS),
+    }
+}
+
+fn is_recordable(url: &str) -> bool {
+    !["about:", "data:", "javascript:", "blob:", "view-source:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+/// Complete typed input to the end of the URL segment it stops in, so
+/// "exa" becomes "example.com/" and "example.com/do" becomes
+/// "example.com/docs/"
+fn inline_completion(typed: &str, url: &str) -> Option<String> {
+    let stripped = index::strip_url(url);
+    if typed.is_empty() || typed.contains(char::is_whitespace) || !stripped.starts_with(typed) {
+        return None;
+    }
+
+    let end = stripped[typed.len()..]
+        .find(['/', '?', '#'])
+        .map(|pos| typed.len() + pos + 1)
+        .unwrap_or(stripped.len());
+    Some(stripped[..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_typed_visits_rank_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = HistoryManager::new(Some(temp_dir.path().join("history.db"))).unwrap();
+
+        history
+            .add_visit("https://example.com/docs/intro", Some("Intro"), VisitTransition::Link)
+            .unwrap();
+        history
+            .add_visit("https://www.example.org/", Some("Example Org"), VisitTransition::Link)
+            .unwrap();
+        history
+            .add_visit("https://www.example.org/", None, VisitTransition::Typed)
+            .unwrap();
+        assert!(history.add_visit("about:blank", None, VisitTransition::Typed).unwrap().is_none());
+
+        let suggestions = history.autocomplete("exa", 5);
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].url, "https://www.example.org/");
+        assert_eq!(suggestions[0].title, "Example Org");
+        assert_eq!(suggestions[0].inline_completion.as_deref(), Some("example.org/"));
+        assert!((suggestions[0].frecency - 3.0).abs() < 0.01);
+
+        let item = history.get_item("https://www.example.org/").unwrap();
+        assert_eq!((item.visit_count, item.typed_count), (2, 1));
+
+        // Title words match too
+        assert_eq!(history.autocomplete("intro", 5)[0].url, "https://example.com/docs/intro");
+    }
+
+    #[test]
+    fn test_delete_range_reranks_remaining_visits() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = HistoryManager::new(Some(temp_dir.path().join("history.db"))).unwrap();
+
+        let start = chrono::Utc::now();
+        history.add_visit("https://example.com/", None, VisitTransition::Typed).unwrap();
+        history.add_visit("https://example.org/", None, VisitTransition::Link).unwrap();
+        let middle = chrono::Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        history.add_visit("https://example.com/", None, VisitTransition::Link).unwrap();
+
+        assert_eq!(history.delete_range(start, middle).unwrap(), 2);
+        assert!(history.get_item("https://example.org/").is_none());
+        let item = history.get_item("https://example.com/").unwrap();
+        assert_eq!((item.visit_count, item.typed_count), (1, 0));
+        assert!((history.frecency("https://example.com/").unwrap() - 1.0).abs() < 0.01);
+
+        assert_eq!(history.delete_domain("www.example.com").unwrap().len(), 1);
+        assert!(history.autocomplete("example", 5).is_empty());
+    }
+
+    #[test]
+    fn test_reloads_and_repeats_fold_into_one_visit() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("history.db");
+        {
+            let history = HistoryManager::new(Some(path.clone())).unwrap();
+            history.add_visit("https://example.com/", None, VisitTransition::Link).unwrap();
+            for _ in 0..50 {
+                history.add_visit("https://example.com/", None, VisitTransition::Reload).unwrap();
+            }
+            let item = history.add_visit("https://example.com/", None, VisitTransition::Link).unwrap().unwrap();
+            assert_eq!((item.visits.len(), item.visit_count), (1, 1));
+            assert!((history.frecency("https://example.com/").unwrap() - 1.0).abs() < 0.01);
+
+            // Typing the address is a visit of its own
+            let item = history.add_visit("https://example.com/", None, VisitTransition::Typed).unwrap().unwrap();
+            assert_eq!((item.visit_count, item.typed_count), (2, 1));
+
+            // Rows stored before folding are folded when history opens
+            let now = chrono::Utc::now();
+            let mut stored = history.get_item("https://example.com/").unwrap();
+            stored.visits = (0..3)
+                .map(|seconds| Visit {
+                    visited_at: now - chrono::Duration::hours(2) + chrono::Duration::seconds(seconds),
+                    transition: VisitTransition::Link,
+                })
+                .chain(std::iter::once(Visit {
+                    visited_at: now,
+                    transition: VisitTransition::Link,
+                }))
+                .collect();
+            history.tree.insert(stored.url.as_bytes(), serde_json::to_vec(&stored).unwrap()).unwrap();
+            history.flush().unwrap();
+        }
+
+        let history = (0..50)
+            .find_map(|_| {
+                HistoryManager::new(Some(path.clone()))
+                    .map_err(|_| std::thread::sleep(std::time::Duration::from_millis(20)))
+                    .ok()
+            })
+            .unwrap();
+        let item = history.get_item("https://example.com/").unwrap();
+        assert_eq!((item.visits.len(), item.visit_count), (2, 2));
+    }
+
+    #[test]
+    fn test_history_persists() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("history.db");
+        {
+            let history = HistoryManager::new(Some(path.clone())).unwrap();
+            history
+                .add_visit("https://rust-lang.org/learn", Some("Learn"), VisitTransition::Typed)
+                .unwrap();
+            history.set_title("https://rust-lang.org/learn", "Learn Rust").unwrap();
+            history.flush().unwrap();
+        }
+
+        // sled releases its file lock once its flusher thread winds down
+        let history = (0..50)
+            .find_map(|_| {
+                HistoryManager::new(Some(path.clone()))
+                    .map_err(|_| std::thread::sleep(std::time::Duration::from_millis(20)))
+                    .ok()
+            })
+            .unwrap();
+        assert_eq!(history.item_count(), 1);
+        let suggestions = history.autocomplete("rust-lang.org/l", 5);
+        assert_eq!(suggestions[0].title, "Learn Rust");
+        assert_eq!(suggestions[0].inline_completion.as_deref(), Some("rust-lang.org/learn"));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,149 @@
+// History Statistics
+use super::{HistoryManager, VisitTransition};
+use crate::utils::{site_domain, LockExt};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Visits to one domain and its pages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DomainStats {
+    pub domain: String,
+    pub visit_count: u32,
+    pub page_count: usize,
+    pub last_visit: DateTime<Utc>,
+}
+
+/// Visits made on one day, in local time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyActivity {
+    pub date: NaiveDate,
+    pub visit_count: usize,
+}
+
+/// A page for the new tab page's most visited tiles
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopSite {
+    pub url: String,
+    pub title: String,
+    pub visit_count: u32,
+    pub frecency: f64,
+}
+
+impl HistoryManager {
+    /// Visits per domain, most visited first
+    pub fn visits_per_domain(&self, limit: usize) -> Vec<DomainStats> {
+        let mut domains: HashMap<String, DomainStats> = HashMap::new();
+        for item in self.state.lock_or_recover().items.values() {
+            let Some(domain) = site_domain(&item.url) else {
+                continue;
+            };
+            let stats = domains.entry(domain.clone()).or_insert_with(|| DomainStats {
+                domain,
+                visit_count: 0,
+                page_count: 0,
+                last_visit: item.last_visit,
+            });
+            stats.visit_count += item.visit_count;
+            stats.page_count += 1;
+            stats.last_visit = stats.last_visit.max(item.last_visit);
+        }
+
+        let mut domains: Vec<DomainStats> = domains.into_values().collect();
+        domains.sort_by(|a, b| b.visit_count.cmp(&a.visit_count).then_with(|| b.last_visit.cmp(&a.last_visit)));
+        domains.truncate(limit);
+        domains
+    }
+
+    /// Visits per day from `from` to `to`, both included, for a histogram;
+    /// days without visits are there with none
+    pub fn daily_activity(&self, from: NaiveDate, to: NaiveDate) -> Vec<DailyActivity> {
+        let mut counts: HashMap<NaiveDate, usize> = HashMap::new();
+        for visit in self.state.lock_or_recover().items.values().flat_map(|item| item.visits.iter()) {
+            let date = visit.visited_at.with_timezone(&Local).date_naive();
+            if date >= from && date <= to {
+                *counts.entry(date).or_default() += 1;
+            }
+        }
+
+        from.iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| DailyActivity {
+                date,
+                visit_count: counts.get(&date).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+
+    /// Most visited pages for the new tab page, by frecency, with the best
+    /// page of each domain only. Pages only ever reached through redirects
+    /// are left out.
+    pub fn most_visited(&self, limit: usize) -> Vec<TopSite> {
+        let now = Utc::now();
+        let state = self.state.lock_or_recover();
+        let mut best: HashMap<String, TopSite> = HashMap::new();
+        for item in state.items.values() {
+            if item.visits.iter().all(|visit| visit.transition == VisitTransition::Redirect) {
+                continue;
+            }
+            let site = TopSite {
+                url: item.url.clone(),
+                title: item.title.clone(),
+                visit_count: item.visit_count,
+                frecency: self.model.score(item.frecency_rank, now),
+            };
+            let domain = site_domain(&item.url).unwrap_or_else(|| item.url.clone());
+            match best.get(&domain) {
+                Some(current) if current.frecency >= site.frecency => {}
+                _ => {
+                    best.insert(domain, site);
+                }
+            }
+        }
+
+        let mut sites: Vec<TopSite> = best.into_values().collect();
+        sites.sort_by(|a, b| b.frecency.total_cmp(&a.frecency));
+        sites.truncate(limit);
+        sites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_domain_stats_activity_and_top_sites() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = HistoryManager::new(Some(temp_dir.path().join("history.db"))).unwrap();
+
+        for (url, transition) in [
+            ("https://www.example.com/", VisitTransition::Typed),
+            ("https://example.com/docs", VisitTransition::Link),
+            ("https://example.com/blog", VisitTransition::Link),
+            ("https://news.org/", VisitTransition::Link),
+            ("https://tracker.net/bounce", VisitTransition::Redirect),
+        ] {
+            history.add_visit(url, None, transition).unwrap();
+        }
+
+        let domains = history.visits_per_domain(10);
+        assert_eq!(domains[0].domain, "example.com");
+        assert_eq!((domains[0].visit_count, domains[0].page_count), (3, 3));
+        assert_eq!(domains.len(), 3);
+        assert_eq!(history.visits_per_domain(1).len(), 1);
+
+        let today = Local::now().date_naive();
+        let week_ago = today - chrono::Duration::days(6);
+        let activity = history.daily_activity(week_ago, today);
+        assert_eq!(activity.len(), 7);
+        assert_eq!(activity[6], DailyActivity { date: today, visit_count: 5 });
+        assert!(activity[..6].iter().all(|day| day.visit_count == 0));
+
+        // One tile per site, the typed home page first; redirects are not sites
+        let sites = history.most_visited(10);
+        let urls: Vec<&str> = sites.iter().map(|site| site.url.as_str()).collect();
+        assert_eq!(urls, ["https://www.example.com/", "https://news.org/"]);
+    }
+}
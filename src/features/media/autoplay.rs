@@ -0,0 +1,165 @@
+// Autoplay Blocking
+use crate::core::AutoplayPolicy;
+use crate::error::WebxError;
+use crate::features::security::permissions::{PermissionKind, PermissionManager, PermissionState};
+use std::sync::Arc;
+
+/// Decides which media may autoplay. The setting applies everywhere except
+/// sites with an autoplay decision in the permission store: granted sites
+/// may autoplay anything, denied ones nothing.
+pub struct AutoplayBlocker {
+    default_policy: AutoplayPolicy,
+    permissions: Option<Arc<PermissionManager>>,
+}
+
+impl AutoplayBlocker {
+    /// Create new autoplay blocker
+    pub fn new(default_policy: Option<AutoplayPolicy>) -> Self {
+        Self {
+            default_policy: default_policy.unwrap_or_default(),
+            permissions: None,
+        }
+    }
+
+    /// Use the permission store for per-site exceptions
+    pub fn set_permission_manager(&mut self, permissions: Arc<PermissionManager>) {
+        self.permissions = Some(permissions);
+    }
+
+    /// Policy for sites without an exception
+    pub fn default_policy(&self) -> AutoplayPolicy {
+        self.default_policy
+    }
+
+    /// Change the policy for sites without an exception
+    pub fn set_default_policy(&mut self, policy: AutoplayPolicy) {
+        self.default_policy = policy;
+    }
+
+    /// Policy that applies to a page
+    pub fn policy_for(&self, url: &str) -> AutoplayPolicy {
+        let decision = self
+            .permissions
+            .as_ref()
+            .map(|permissions| permissions.get_permission(url, PermissionKind::Autoplay))
+            .unwrap_or(PermissionState::Ask);
+        match decision {
+            PermissionState::Granted => AutoplayPolicy::Allow,
+            PermissionState::Denied => AutoplayPolicy::BlockAll,
+            PermissionState::Ask => self.default_policy,
+        }
+    }
+
+    /// Let a site autoplay, block it, or with `None` follow the setting again
+    pub fn set_site_policy(&self, url: &str, allow: Option<bool>) -> Result<(), WebxError> {
+        let permissions = self
+            .permissions
+            .as_ref()
+            .ok_or("Site autoplay exceptions need the permission store")?;
+        let state = match allow {
+            Some(true) => PermissionState::Granted,
+            Some(false) => PermissionState::Denied,
+            None => PermissionState::Ask,
+        };
+        permissions.set_permission(url, PermissionKind::Autoplay, state)
+    }
+
+    /// Script injected into every page that enforces the policy until the
+    /// user interacts with the page. Site exceptions are baked in, so the
+    /// script must be rebuilt when they change.
+    pub fn page_script(&self) -> String {
+        let mut exceptions = serde_json::Map::new();
+        if let Some(permissions) = &self.permissions {
+            for origin in permissions.list_origins() {
+                let policy = self.policy_for(&origin);
+                if policy != self.default_policy {
+                    exceptions.insert(origin, serde_json::Value::String(policy_name(policy).to_string()));
+                }
+            }
+        }
+
+        format!(
+            r#"
+(function() {{
+    if (window.__webxAutoplayGuard) return;
+    window.__webxAutoplayGuard = true;
+    const exceptions = {exceptions};
+    const policy = exceptions[location.origin] || '{default_policy}';
+    if (policy === 'allow') return;
+
+    const activated = () => navigator.userActivation ? navigator.userActivation.hasBeenActive : false;
+    const blocked = (el) => !activated() && (policy === 'block-all' || (!el.muted && el.volume > 0));
+
+    const play = HTMLMediaElement.prototype.play;
+    HTMLMediaElement.prototype.play = function() {{
+        if (blocked(this)) {{
+            return Promise.reject(new DOMException('Autoplay was blocked', 'NotAllowedError'));
+        }}
+        return play.apply(this, arguments);
+    }};
+
+    const stop = (root) => {{
+        if (!root.querySelectorAll) return;
+        root.querySelectorAll('audio[autoplay], video[autoplay]').forEach((el) => {{
+            if (blocked(el)) {{
+                el.autoplay = false;
+                el.pause();
+            }}
+        }});
+    }};
+    stop(document);
+    new MutationObserver((mutations) => {{
+        mutations.forEach((mutation) => mutation.addedNodes.forEach((node) => {{
+            if (node.nodeType === 1) stop(node.parentNode || node);
+        }}));
+    }}).observe(document.documentElement, {{ childList: true, subtree: true }});
+}})();
+"#,
+            exceptions = serde_json::Value::Object(exceptions),
+            default_policy = policy_name(self.default_policy),
+        )
+    }
+}
+
+impl Default for AutoplayBlocker {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+// Private helper functions
+
+fn policy_name(policy: AutoplayPolicy) -> &'static str {
+    match policy {
+        AutoplayPolicy::Allow => "allow",
+        AutoplayPolicy::BlockAudible => "block-audible",
+        AutoplayPolicy::BlockAll => "block-all",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_site_exceptions_override_setting() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut blocker = AutoplayBlocker::new(Some(AutoplayPolicy::BlockAudible));
+        blocker.set_permission_manager(Arc::new(PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap()));
+
+        blocker.set_site_policy("https://music.example/album", Some(true)).unwrap();
+        blocker.set_site_policy("https://ads.example", Some(false)).unwrap();
+        assert_eq!(blocker.policy_for("https://music.example/other"), AutoplayPolicy::Allow);
+        assert_eq!(blocker.policy_for("https://ads.example/banner"), AutoplayPolicy::BlockAll);
+        assert_eq!(blocker.policy_for("https://news.example"), AutoplayPolicy::BlockAudible);
+
+        let script = blocker.page_script();
+        assert!(script.contains(r#""https://music.example":"allow""#));
+        assert!(script.contains("|| 'block-audible'"));
+
+        blocker.set_site_policy("https://music.example", None).unwrap();
+        assert_eq!(blocker.policy_for("https://music.example"), AutoplayPolicy::BlockAudible);
+        assert!(AutoplayBlocker::default().set_site_policy("https://a.example", Some(true)).is_err());
+    }
+}
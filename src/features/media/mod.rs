@@ -0,0 +1,487 @@
+// Media Controls and Picture-in-Picture
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+pub mod autoplay;
+#[cfg(target_os = "linux")]
+mod mpris;
+pub mod read_aloud;
+#[cfg(target_os = "windows")]
+mod smtc;
+
+pub use autoplay::AutoplayBlocker;
+pub use read_aloud::{ReadAloudEvent, ReadAloudService, ReadAloudState, SpeechEngine, READ_PAGE_ALOUD_SCRIPT};
+
+/// Script that reports the page's media to the browser over IPC
+pub const MEDIA_OBSERVER_SCRIPT: &str = r#"
+(function() {
+    if (window.__webxMediaObserver) return;
+    window.__webxMediaObserver = true;
+    const report = (el) => {
+        const meta = (navigator.mediaSession && navigator.mediaSession.metadata) || {};
+        const artwork = (meta.artwork || []).map((a) => a.src).pop() || el.poster || null;
+        window.ipc.send({
+            type: 'media',
+            state: el.ended ? 'stopped' : (el.paused ? 'paused' : 'playing'),
+            title: meta.title || document.title,
+            artist: meta.artist || null,
+            album: meta.album || null,
+            artwork: artwork,
+            position: el.currentTime || 0,
+            duration: isFinite(el.duration) ? el.duration : null,
+            has_video: el.tagName === 'VIDEO',
+            picture_in_picture: document.pictureInPictureElement === el,
+        });
+    };
+    ['play', 'pause', 'ended', 'seeked', 'loadedmetadata', 'enterpictureinpicture', 'leavepictureinpicture']
+        .forEach((type) => document.addEventListener(type, (e) => {
+            if (e.target instanceof HTMLMediaElement) report(e.target);
+        }, true));
+})();
+"#;
+
+/// Playback state of a tab's media
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+/// What is playing, as far as the page tells
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct MediaMetadata {
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork_url: Option<String>,
+}
+
+/// Media playing in a tab
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MediaSession {
+    pub tab_id: usize,
+    pub metadata: MediaMetadata,
+    pub state: PlaybackState,
+    /// Playback position in seconds when the session was last updated
+    pub position: f64,
+    /// Length in seconds, `None` for live streams
+    pub duration: Option<f64>,
+    pub has_video: bool,
+    pub picture_in_picture: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MediaSession {
+    /// Current playback position in seconds, advanced while playing
+    pub fn current_position(&self) -> f64 {
+        let mut position = self.position;
+        if self.state == PlaybackState::Playing {
+            position += (Utc::now() - self.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+        }
+        match self.duration {
+            Some(duration) => position.min(duration),
+            None => position,
+        }
+    }
+}
+
+/// Media report sent by `MEDIA_OBSERVER_SCRIPT`
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaReport {
+    pub state: PlaybackState,
+    #[serde(default)]
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork: Option<String>,
+    #[serde(default)]
+    pub position: f64,
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub has_video: bool,
+    #[serde(default)]
+    pub picture_in_picture: bool,
+}
+
+/// Action to run on the media in a tab
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MediaCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    /// Seek by an offset in seconds
+    SeekBy(f64),
+    /// Seek to a position in seconds
+    SeekTo(f64),
+    SetMuted(bool),
+    EnterPictureInPicture,
+    ExitPictureInPicture,
+}
+
+impl MediaCommand {
+    /// JavaScript that applies the command to the page's media
+    pub fn script(&self) -> String {
+        let action = match self {
+            MediaCommand::Play => "el.play();".to_string(),
+            MediaCommand::Pause => "el.pause();".to_string(),
+            MediaCommand::PlayPause => "if (el.paused) { el.play(); } else { el.pause(); }".to_string(),
+            MediaCommand::Stop => "el.pause(); el.currentTime = 0;".to_string(),
+            MediaCommand::SeekBy(offset) => format!("el.currentTime = Math.max(0, el.currentTime + {});", offset),
+            MediaCommand::SeekTo(position) => format!("el.currentTime = Math.max(0, {});", position),
+            MediaCommand::SetMuted(muted) => {
+                return format!(
+                    "document.querySelectorAll('audio, video').forEach((el) => {{ el.muted = {}; }});",
+                    muted
+                );
+            }
+            MediaCommand::EnterPictureInPicture => {
+                "if (el.tagName === 'VIDEO' && document.pictureInPictureEnabled) { el.requestPictureInPicture(); }"
+                    .to_string()
+            }
+            MediaCommand::ExitPictureInPicture => {
+                return "if (document.pictureInPictureElement) { document.exitPictureInPicture(); }".to_string();
+            }
+        };
+        format!(
+            r#"
+(function() {{
+    const media = Array.from(document.querySelectorAll('audio, video'));
+    const el = media.find((m) => !m.paused) || media.find((m) => m.currentTime > 0) || media[0];
+    if (!el) return;
+    {}
+}})();
+"#,
+            action
+        )
+    }
+}
+
+/// Audio indicator shown on a tab
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TabAudioIndicator {
+    None,
+    Playing,
+    Muted,
+}
+
+/// Media events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MediaEvent {
+    /// A tab's media started, changed or stopped; refresh its indicator
+    SessionChanged { tab_id: usize },
+    SessionEnded { tab_id: usize },
+    /// Run `command.script()` in the tab
+    Command { tab_id: usize, command: MediaCommand },
+    MuteChanged { tab_id: usize, muted: bool },
+}
+
+/// Tracks media playing in tabs and controls it from the browser and the
+/// system's media keys and widgets (MPRIS on Linux, SMTC on Windows)
+pub struct MediaController {
+    sessions: Mutex<HashMap<usize, MediaSession>>,
+    muted_tabs: Mutex<HashSet<usize>>,
+    now_playing: watch::Sender<Option<MediaSession>>,
+    system_controls: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    tx: mpsc::UnboundedSender<MediaEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<MediaEvent>>>,
+}
+
+impl MediaController {
+    /// Create new media controller
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            muted_tabs: Mutex::new(HashSet::new()),
+            now_playing: watch::Sender::new(None),
+            system_controls: Mutex::new(None),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Record a media report from a tab's page
+    pub fn handle_report(&self, tab_id: usize, report: MediaReport) {
+        let session = MediaSession {
+            tab_id,
+            metadata: MediaMetadata {
+                title: report.title,
+                artist: report.artist.filter(|a| !a.is_empty()),
+                album: report.album.filter(|a| !a.is_empty()),
+                artwork_url: report.artwork.filter(|a| !a.is_empty()),
+            },
+            state: report.state,
+            position: report.position.max(0.0),
+            duration: report.duration.filter(|d| d.is_finite() && *d > 0.0),
+            has_video: report.has_video,
+            picture_in_picture: report.picture_in_picture,
+            updated_at: Utc::now(),
+        };
+        self.sessions.lock_or_recover().insert(tab_id, session);
+        let _ = self.tx.send(MediaEvent::SessionChanged { tab_id });
+        self.update_now_playing();
+    }
+
+    /// Record a media report sent as the page's IPC message
+    pub fn handle_message(&self, tab_id: usize, message: &str) -> Result<(), WebxError> {
+        let report = serde_json::from_str(message)?;
+        self.handle_report(tab_id, report);
+        Ok(())
+    }
+
+    /// Forget a tab's media, e.g. when the tab navigates or closes
+    pub fn remove_tab(&self, tab_id: usize) {
+        self.muted_tabs.lock_or_recover().remove(&tab_id);
+        if self.sessions.lock_or_recover().remove(&tab_id).is_some() {
+            let _ = self.tx.send(MediaEvent::SessionEnded { tab_id });
+            self.update_now_playing();
+        }
+    }
+
+    /// Get a tab's media session
+    pub fn get_session(&self, tab_id: usize) -> Option<MediaSession> {
+        self.sessions.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    /// Get all media sessions
+    pub fn get_sessions(&self) -> Vec<MediaSession> {
+        let mut sessions: Vec<MediaSession> = self.sessions.lock_or_recover().values().cloned().collect();
+        sessions.sort_by_key(|s| s.tab_id);
+        sessions
+    }
+
+    /// The session the system media controls act on: the most recently
+    /// updated playing session, else the most recently paused one
+    pub fn now_playing(&self) -> Option<MediaSession> {
+        let sessions = self.sessions.lock_or_recover();
+        let latest = |state| {
+            sessions
+                .values()
+                .filter(|s| s.state == state)
+                .max_by_key(|s| s.updated_at)
+                .cloned()
+        };
+        latest(PlaybackState::Playing).or_else(|| latest(PlaybackState::Paused))
+    }
+
+    /// Send a command to a tab's media
+    pub fn control(&self, tab_id: usize, command: MediaCommand) -> Result<(), WebxError> {
+        if !self.sessions.lock_or_recover().contains_key(&tab_id) {
+            return Err(WebxError::NotFound(format!("Media in tab {}", tab_id)));
+        }
+        let _ = self.tx.send(MediaEvent::Command { tab_id, command });
+        Ok(())
+    }
+
+    /// Send a command to the now playing session
+    pub fn control_now_playing(&self, command: MediaCommand) -> Result<(), WebxError> {
+        let session = self.now_playing().ok_or_else(|| WebxError::NotFound("Playing media".to_string()))?;
+        self.control(session.tab_id, command)
+    }
+
+    /// Play a tab's media
+    pub fn play(&self, tab_id: usize) -> Result<(), WebxError> {
+        self.control(tab_id, MediaCommand::Play)
+    }
+
+    /// Pause a tab's media
+    pub fn pause(&self, tab_id: usize) -> Result<(), WebxError> {
+        self.control(tab_id, MediaCommand::Pause)
+    }
+
+    /// Seek a tab's media to a position in seconds
+    pub fn seek(&self, tab_id: usize, position: f64) -> Result<(), WebxError> {
+        self.control(tab_id, MediaCommand::SeekTo(position))
+    }
+
+    /// Pop a tab's video out into a picture-in-picture window, or back in
+    pub fn set_picture_in_picture(&self, tab_id: usize, enabled: bool) -> Result<(), WebxError> {
+        let has_video = self.get_session(tab_id).is_some_and(|s| s.has_video);
+        if enabled && !has_video {
+            return Err(WebxError::NotFound(format!("Video in tab {}", tab_id)));
+        }
+        let command = if enabled {
+            MediaCommand::EnterPictureInPicture
+        } else {
+            MediaCommand::ExitPictureInPicture
+        };
+        self.control(tab_id, command)
+    }
+
+    /// Mute or unmute a tab
+    pub fn set_tab_muted(&self, tab_id: usize, muted: bool) {
+        let changed = {
+            let mut muted_tabs = self.muted_tabs.lock_or_recover();
+            if muted {
+                muted_tabs.insert(tab_id)
+            } else {
+                muted_tabs.remove(&tab_id)
+            }
+        };
+        if changed {
+            let _ = self.tx.send(MediaEvent::Command {
+                tab_id,
+                command: MediaCommand::SetMuted(muted),
+            });
+            let _ = self.tx.send(MediaEvent::MuteChanged { tab_id, muted });
+        }
+    }
+
+    /// Toggle a tab's mute, returning whether it is now muted
+    pub fn toggle_tab_mute(&self, tab_id: usize) -> bool {
+        let muted = !self.is_tab_muted(tab_id);
+        self.set_tab_muted(tab_id, muted);
+        muted
+    }
+
+    /// Check whether a tab is muted
+    pub fn is_tab_muted(&self, tab_id: usize) -> bool {
+        self.muted_tabs.lock_or_recover().contains(&tab_id)
+    }
+
+    /// Audio indicator for a tab
+    pub fn audio_indicator(&self, tab_id: usize) -> TabAudioIndicator {
+        if self.is_tab_muted(tab_id) {
+            return TabAudioIndicator::Muted;
+        }
+        match self.get_session(tab_id) {
+            Some(session) if session.state == PlaybackState::Playing => TabAudioIndicator::Playing,
+            _ => TabAudioIndicator::None,
+        }
+    }
+
+    /// Publish the now playing session to the system media controls. Must
+    /// be called from within the browser runtime.
+    pub fn start_system_controls(controller: Arc<Self>) {
+        let now_playing = controller.now_playing.subscribe();
+        let weak = Arc::downgrade(&controller);
+        let handle = tokio::spawn(async move {
+            #[cfg(target_os = "linux")]
+            let result = mpris::run(weak, now_playing).await;
+            #[cfg(target_os = "windows")]
+            let result = smtc::run(weak, now_playing).await;
+            #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+            let result: Result<(), WebxError> = {
+                let _ = (weak, now_playing);
+                Ok(())
+            };
+            if let Err(e) = result {
+                tracing::warn!("System media controls unavailable: {}", e);
+            }
+        });
+        if let Some(previous) = controller.system_controls.lock_or_recover().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stop publishing to the system media controls
+    pub fn stop_system_controls(&self) {
+        if let Some(handle) = self.system_controls.lock_or_recover().take() {
+            handle.abort();
+        }
+    }
+
+    /// Subscribe to media events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<MediaEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn update_now_playing(&self) {
+        let session = self.now_playing();
+        self.now_playing.send_if_modified(|current| {
+            if *current == session {
+                return false;
+            }
+            *current = session;
+            true
+        });
+    }
+}
+
+impl Default for MediaController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MediaController {
+    fn drop(&mut self) {
+        self.stop_system_controls();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(state: PlaybackState, title: &str) -> MediaReport {
+        MediaReport {
+            state,
+            title: title.to_string(),
+            artist: Some("Artist".to_string()),
+            album: None,
+            artwork: None,
+            position: 12.0,
+            duration: Some(180.0),
+            has_video: false,
+            picture_in_picture: false,
+        }
+    }
+
+    #[test]
+    fn test_now_playing_and_commands() {
+        let controller = MediaController::new();
+        let mut events = controller.subscribe_events();
+
+        controller.handle_report(1, report(PlaybackState::Paused, "Podcast"));
+        controller.handle_report(2, report(PlaybackState::Playing, "Song"));
+        assert_eq!(controller.now_playing().unwrap().tab_id, 2);
+        assert_eq!(controller.audio_indicator(2), TabAudioIndicator::Playing);
+        assert_eq!(controller.audio_indicator(1), TabAudioIndicator::None);
+
+        // Once the song stops, the paused podcast is the one to control
+        controller.remove_tab(2);
+        controller.control_now_playing(MediaCommand::PlayPause).unwrap();
+        while let Ok(event) = events.try_recv() {
+            if let MediaEvent::Command { tab_id, command } = event {
+                assert_eq!(tab_id, 1);
+                assert_eq!(command, MediaCommand::PlayPause);
+                assert!(command.script().contains("el.play()"));
+                return;
+            }
+        }
+        panic!("expected a command event");
+    }
+
+    #[test]
+    fn test_mute_and_picture_in_picture() {
+        let controller = MediaController::new();
+        controller
+            .handle_message(
+                3,
+                r#"{"type":"media","state":"playing","title":"Clip","position":1.5,"duration":null,"has_video":true}"#,
+            )
+            .unwrap();
+        assert!(controller.get_session(3).unwrap().duration.is_none());
+
+        assert!(controller.toggle_tab_mute(3));
+        assert_eq!(controller.audio_indicator(3), TabAudioIndicator::Muted);
+        assert!(!controller.toggle_tab_mute(3));
+
+        assert!(controller.set_picture_in_picture(3, true).is_ok());
+        controller.handle_report(4, report(PlaybackState::Playing, "Audio only"));
+        assert!(controller.set_picture_in_picture(4, true).is_err());
+        assert!(controller.play(5).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// MPRIS Media Controls (Linux)
+use super::{MediaCommand, MediaController, MediaSession, PlaybackState};
+use crate::error::WebxError;
+use std::collections::HashMap;
+use std::sync::Weak;
+use tokio::sync::watch;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{connection, interface};
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Publish the now playing session on the session bus until the controller
+/// goes away
+pub(super) async fn run(
+    controller: Weak<MediaController>,
+    mut now_playing: watch::Receiver<Option<MediaSession>>,
+) -> Result<(), WebxError> {
+    let player = PlayerInterface {
+        controller,
+        session: now_playing.borrow_and_update().clone(),
+    };
+    let connection = connection::Builder::session()
+        .and_then(|builder| builder.name(format!("org.mpris.MediaPlayer2.webx.instance{}", std::process::id())))
+        .and_then(|builder| builder.serve_at(MPRIS_PATH, RootInterface))
+        .and_then(|builder| builder.serve_at(MPRIS_PATH, player))
+        .map_err(bus_error)?
+        .build()
+        .await
+        .map_err(bus_error)?;
+    let player = connection
+        .object_server()
+        .interface::<_, PlayerInterface>(MPRIS_PATH)
+        .await
+        .map_err(bus_error)?;
+
+    while now_playing.changed().await.is_ok() {
+        let session = now_playing.borrow_and_update().clone();
+        let mut iface = player.get_mut().await;
+        iface.session = session;
+        let emitter = player.signal_emitter();
+        iface.playback_status_changed(emitter).await.map_err(bus_error)?;
+        iface.metadata_changed(emitter).await.map_err(bus_error)?;
+        iface.can_play_changed(emitter).await.map_err(bus_error)?;
+        iface.can_seek_changed(emitter).await.map_err(bus_error)?;
+    }
+    Ok(())
+}
+
+/// `org.mpris.MediaPlayer2`: identifies the browser
+struct RootInterface;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "WebX".to_string()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        "webx".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`: the now playing session and its controls
+struct PlayerInterface {
+    controller: Weak<MediaController>,
+    session: Option<MediaSession>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn play(&self) {
+        self.command(MediaCommand::Play);
+    }
+
+    fn pause(&self) {
+        self.command(MediaCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.command(MediaCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.command(MediaCommand::Stop);
+    }
+
+    fn next(&self) {}
+
+    fn previous(&self) {}
+
+    /// Seek by an offset in microseconds
+    fn seek(&self, offset: i64) {
+        self.command(MediaCommand::SeekBy(offset as f64 / 1_000_000.0));
+    }
+
+    /// Seek to a position in microseconds, if the track is still current
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if let Some(session) = &self.session {
+            if track_id.as_str() == track_path(session) {
+                self.command(MediaCommand::SeekTo(position as f64 / 1_000_000.0));
+            }
+        }
+    }
+
+    fn open_uri(&self, _uri: String) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        match self.session.as_ref().map(|s| s.state) {
+            Some(PlaybackState::Playing) => "Playing",
+            Some(PlaybackState::Paused) => "Paused",
+            _ => "Stopped",
+        }
+        .to_string()
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let Some(session) = &self.session else {
+            return HashMap::new();
+        };
+        let mut metadata = HashMap::new();
+        let mut insert = |key: &str, value: Value<'_>| {
+            if let Ok(value) = OwnedValue::try_from(value) {
+                metadata.insert(key.to_string(), value);
+            }
+        };
+        if let Ok(path) = ObjectPath::try_from(track_path(session)) {
+            insert("mpris:trackid", Value::from(path));
+        }
+        insert("xesam:title", Value::from(session.metadata.title.clone()));
+        if let Some(artist) = &session.metadata.artist {
+            insert("xesam:artist", Value::from(vec![artist.clone()]));
+        }
+        if let Some(album) = &session.metadata.album {
+            insert("xesam:album", Value::from(album.clone()));
+        }
+        if let Some(artwork) = &session.metadata.artwork_url {
+            insert("mpris:artUrl", Value::from(artwork.clone()));
+        }
+        if let Some(duration) = session.duration {
+            insert("mpris:length", Value::from((duration * 1_000_000.0) as i64));
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        self.session
+            .as_ref()
+            .map(|s| (s.current_position() * 1_000_000.0) as i64)
+            .unwrap_or(0)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.session.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.session.as_ref().is_some_and(|s| s.duration.is_some())
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+impl PlayerInterface {
+    fn command(&self, command: MediaCommand) {
+        if let Some(controller) = self.controller.upgrade() {
+            if let Err(e) = controller.control_now_playing(command) {
+                tracing::debug!("Ignoring media key: {}", e);
+            }
+        }
+    }
+}
+
+// Private helper functions
+
+fn track_path(session: &MediaSession) -> String {
+    format!("/org/webx/tab/{}", session.tab_id)
+}
+
+fn bus_error(error: zbus::Error) -> WebxError {
+    WebxError::Invalid(format!("D-Bus error: {}", error))
+}
@@ -0,0 +1,507 @@
+// Read Aloud
+use crate::core::ReadAloudSettings;
+use crate::error::WebxError;
+use crate::features::ui::reader::ReaderToken;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::{mpsc, watch};
+
+/// Slowest and fastest speaking rates accepted
+const RATE_RANGE: (f32, f32) = (0.25, 4.0);
+
+/// Script that has a page send itself to be read aloud, or pause and resume
+pub const READ_PAGE_ALOUD_SCRIPT: &str =
+    "window.ipc.send({ type: 'readaloud', action: 'toggle', html: document.documentElement.outerHTML, url: window.location.href });";
+
+/// How a speech engine takes its speaking rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateFormat {
+    /// Offset from normal speed in percent, -100 to 100 (speech-dispatcher)
+    Percent,
+    /// Words per minute, given the voice's normal words per minute
+    WordsPerMinute(u32),
+    /// Steps from -10 to 10 (SAPI)
+    Steps,
+}
+
+impl RateFormat {
+    /// Engine argument for a rate where 1.0 is normal speed
+    pub fn format(&self, rate: f32) -> String {
+        match self {
+            RateFormat::Percent => (((rate - 1.0) * 100.0).round() as i32).clamp(-100, 100).to_string(),
+            RateFormat::WordsPerMinute(normal) => ((*normal as f32 * rate).round() as u32).to_string(),
+            RateFormat::Steps => (((rate - 1.0) * 10.0).round() as i32).clamp(-10, 10).to_string(),
+        }
+    }
+}
+
+/// A program that speaks a sentence and exits once it is done. `{rate}`,
+/// `{voice}` and `{text}` in its arguments and input are replaced.
+#[derive(Debug, Clone)]
+pub struct SpeechEngine {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Added to the arguments when a voice is set
+    pub voice_args: Vec<String>,
+    /// Written to the program's standard input
+    pub input: Option<String>,
+    pub rate_format: RateFormat,
+    /// Lists the voices, one per line with the name first
+    pub list_voices_args: Option<Vec<String>>,
+    /// Silences speech the engine may still have queued after a cancel
+    pub stop_args: Option<Vec<String>>,
+}
+
+impl SpeechEngine {
+    /// speech-dispatcher through its `spd-say` client
+    pub fn speech_dispatcher() -> Self {
+        Self {
+            program: PathBuf::from("spd-say"),
+            args: args(&["--wait", "--rate", "{rate}", "--", "{text}"]),
+            voice_args: args(&["--synthesis-voice", "{voice}"]),
+            input: None,
+            rate_format: RateFormat::Percent,
+            list_voices_args: Some(args(&["--list-synthesis-voices"])),
+            stop_args: Some(args(&["--stop"])),
+        }
+    }
+
+    /// macOS speech synthesis through `say`
+    pub fn macos_say() -> Self {
+        Self {
+            program: PathBuf::from("say"),
+            args: args(&["-r", "{rate}", "--", "{text}"]),
+            voice_args: args(&["-v", "{voice}"]),
+            input: None,
+            rate_format: RateFormat::WordsPerMinute(175),
+            list_voices_args: Some(args(&["-v", "?"])),
+            stop_args: None,
+        }
+    }
+
+    /// Windows speech synthesis (SAPI) through PowerShell; the voice and
+    /// text go through standard input so they need no quoting
+    pub fn windows_sapi() -> Self {
+        let speak = "$voice = [Console]::In.ReadLine(); $text = [Console]::In.ReadToEnd(); \
+            Add-Type -AssemblyName System.Speech; \
+            $synth = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+            if ($voice) { $synth.SelectVoice($voice) }; $synth.Rate = {rate}; $synth.Speak($text)";
+        let list = "Add-Type -AssemblyName System.Speech; \
+            (New-Object System.Speech.Synthesis.SpeechSynthesizer).GetInstalledVoices() | \
+            ForEach-Object { $_.VoiceInfo.Name }";
+        Self {
+            program: PathBuf::from("powershell"),
+            args: args(&["-NoProfile", "-NonInteractive", "-Command", speak]),
+            voice_args: Vec::new(),
+            input: Some("{voice}\n{text}".to_string()),
+            rate_format: RateFormat::Steps,
+            list_voices_args: Some(args(&["-NoProfile", "-NonInteractive", "-Command", list])),
+            stop_args: None,
+        }
+    }
+
+    /// The engine of the operating system
+    pub fn system() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::windows_sapi()
+        } else if cfg!(target_os = "macos") {
+            Self::macos_say()
+        } else {
+            Self::speech_dispatcher()
+        }
+    }
+
+    /// Speak a text and wait until it was spoken. Dropping the future
+    /// cancels the speech.
+    pub async fn speak(&self, text: &str, settings: &ReadAloudSettings) -> Result<(), WebxError> {
+        let rate = self.rate_format.format(settings.rate);
+        let voice = settings.voice.clone().unwrap_or_default();
+        let fill = |template: &str| {
+            template
+                .replace("{rate}", &rate)
+                .replace("{voice}", &voice)
+                .replace("{text}", text)
+        };
+
+        let mut command = Command::new(&self.program);
+        command.args(self.args.iter().map(|arg| fill(arg)));
+        if settings.voice.is_some() {
+            command.args(self.voice_args.iter().map(|arg| fill(arg)));
+        }
+        let mut child = command
+            .stdin(if self.input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let (Some(input), Some(mut stdin)) = (&self.input, child.stdin.take()) {
+            stdin.write_all(fill(input).as_bytes()).await?;
+        }
+
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(format!("Speech engine failed with {}", status).into());
+        }
+        Ok(())
+    }
+
+    /// Names of the voices the engine offers
+    pub async fn voices(&self) -> Result<Vec<String>, WebxError> {
+        let Some(list_args) = &self.list_voices_args else {
+            return Ok(Vec::new());
+        };
+        let output = Command::new(&self.program).args(list_args).stderr(Stdio::null()).output().await?;
+        if !output.status.success() {
+            return Err(format!("Speech engine failed with {}", output.status).into());
+        }
+
+        // Columns are separated by runs of spaces; names may contain one
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().split("  ").next())
+            .filter(|name| !name.is_empty() && *name != "NAME")
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Silence speech left behind by a cancelled `speak`
+    pub async fn stop(&self) {
+        if let Some(stop_args) = &self.stop_args {
+            let _ = Command::new(&self.program)
+                .args(stop_args)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .await;
+        }
+    }
+}
+
+/// Whether an article is being read
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ReadAloudState {
+    Idle,
+    Playing,
+    Paused,
+}
+
+/// Read aloud events
+#[derive(Debug, Clone)]
+pub enum ReadAloudEvent {
+    /// A sentence started; highlight it in the tab
+    Sentence { tab_id: usize, index: usize, token: ReaderToken },
+    /// Reading started, paused, resumed or ended
+    StateChanged { tab_id: usize, state: ReadAloudState },
+    /// The speech engine failed and reading stopped
+    Error { tab_id: usize, message: String },
+}
+
+struct Session {
+    id: u64,
+    tab_id: usize,
+    tokens: Vec<ReaderToken>,
+    index: usize,
+    state: ReadAloudState,
+}
+
+/// Reads reader mode articles aloud sentence by sentence, so reading can
+/// pause, skip and follow along with a highlight
+pub struct ReadAloudService {
+    engine: SpeechEngine,
+    settings: Mutex<ReadAloudSettings>,
+    session: Mutex<Option<Session>>,
+    next_session_id: Mutex<u64>,
+    /// Bumped on every control so the reading task re-reads the session
+    wake: watch::Sender<u64>,
+    tx: mpsc::UnboundedSender<ReadAloudEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<ReadAloudEvent>>>,
+}
+
+impl ReadAloudService {
+    /// Create new read aloud service using the system's speech engine
+    pub fn new(settings: Option<ReadAloudSettings>) -> Self {
+        Self::with_engine(SpeechEngine::system(), settings)
+    }
+
+    /// Create new read aloud service using a speech engine
+    pub fn with_engine(engine: SpeechEngine, settings: Option<ReadAloudSettings>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            engine,
+            settings: Mutex::new(settings.unwrap_or_default()),
+            session: Mutex::new(None),
+            next_session_id: Mutex::new(0),
+            wake: watch::channel(0).0,
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Get the speed and voice settings
+    pub fn settings(&self) -> ReadAloudSettings {
+        self.settings.lock_or_recover().clone()
+    }
+
+    /// Change the speaking rate, from the next sentence on
+    pub fn set_rate(&self, rate: f32) {
+        self.settings.lock_or_recover().rate = rate.clamp(RATE_RANGE.0, RATE_RANGE.1);
+    }
+
+    /// Change the voice, from the next sentence on
+    pub fn set_voice(&self, voice: Option<String>) {
+        self.settings.lock_or_recover().voice = voice;
+    }
+
+    /// Names of the voices the speech engine offers
+    pub async fn available_voices(&self) -> Result<Vec<String>, WebxError> {
+        self.engine.voices().await
+    }
+
+    /// Read tokens of a tab aloud from the start, replacing what was being
+    /// read. Must be called inside the runtime.
+    pub fn start(self: &Arc<Self>, tab_id: usize, tokens: Vec<ReaderToken>) {
+        let id = {
+            let mut next_id = self.next_session_id.lock_or_recover();
+            *next_id += 1;
+            *next_id
+        };
+        if let Some(previous) = self.session.lock_or_recover().replace(Session {
+            id,
+            tab_id,
+            tokens,
+            index: 0,
+            state: ReadAloudState::Playing,
+        }) {
+            self.emit_state(previous.tab_id, ReadAloudState::Idle);
+        }
+        self.emit_state(tab_id, ReadAloudState::Playing);
+        self.wake();
+        tokio::spawn(Self::read(Arc::clone(self), id));
+    }
+
+    /// Pause after cancelling the current sentence; resuming repeats it
+    pub fn pause(&self) {
+        self.set_state(ReadAloudState::Paused);
+    }
+
+    /// Continue reading
+    pub fn resume(&self) {
+        self.set_state(ReadAloudState::Playing);
+    }
+
+    /// Pause or resume, returning the new state
+    pub fn toggle(&self) -> ReadAloudState {
+        match self.state() {
+            Some((_, ReadAloudState::Playing)) => self.pause(),
+            Some((_, ReadAloudState::Paused)) => self.resume(),
+            _ => {}
+        }
+        self.state().map(|(_, state)| state).unwrap_or(ReadAloudState::Idle)
+    }
+
+    /// Jump to the next sentence
+    pub fn skip_forward(&self) {
+        self.move_to(|index, len| (index + 1).min(len));
+    }
+
+    /// Jump back to the previous sentence
+    pub fn skip_back(&self) {
+        self.move_to(|index, _| index.saturating_sub(1));
+    }
+
+    /// Stop reading
+    pub fn stop(&self) {
+        if let Some(session) = self.session.lock_or_recover().take() {
+            self.emit_state(session.tab_id, ReadAloudState::Idle);
+        }
+        self.wake();
+    }
+
+    /// Stop reading if a closing tab is being read
+    pub fn stop_tab(&self, tab_id: usize) {
+        if self.state().is_some_and(|(reading, _)| reading == tab_id) {
+            self.stop();
+        }
+    }
+
+    /// The tab being read and whether reading is paused
+    pub fn state(&self) -> Option<(usize, ReadAloudState)> {
+        self.session.lock_or_recover().as_ref().map(|session| (session.tab_id, session.state))
+    }
+
+    /// Subscribe to read aloud events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<ReadAloudEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    async fn read(service: Arc<Self>, id: u64) {
+        let mut wake = service.wake.subscribe();
+        loop {
+            wake.borrow_and_update();
+            let next = {
+                let mut session = service.session.lock_or_recover();
+                let Some(current) = session.as_mut().filter(|session| session.id == id) else {
+                    break;
+                };
+                match current.tokens.get(current.index) {
+                    None => {
+                        let tab_id = current.tab_id;
+                        *session = None;
+                        service.emit_state(tab_id, ReadAloudState::Idle);
+                        break;
+                    }
+                    Some(_) if current.state == ReadAloudState::Paused => None,
+                    Some(token) => Some((current.tab_id, current.index, token.clone())),
+                }
+            };
+            let Some((tab_id, index, token)) = next else {
+                if wake.changed().await.is_err() {
+                    break;
+                }
+                continue;
+            };
+
+            let _ = service.tx.send(ReadAloudEvent::Sentence {
+                tab_id,
+                index,
+                token: token.clone(),
+            });
+            let settings = service.settings();
+            tokio::select! {
+                result = service.engine.speak(&token.text, &settings) => match result {
+                    Ok(()) => service.move_to_if(id, index, index + 1),
+                    Err(e) => {
+                        let _ = service.tx.send(ReadAloudEvent::Error { tab_id, message: e.to_string() });
+                        if service.session.lock_or_recover().as_ref().is_some_and(|session| session.id == id) {
+                            service.stop();
+                        }
+                        break;
+                    }
+                },
+                _ = wake.changed() => service.engine.stop().await,
+            }
+        }
+    }
+
+    fn set_state(&self, state: ReadAloudState) {
+        let changed = {
+            let mut session = self.session.lock_or_recover();
+            match session.as_mut() {
+                Some(session) if session.state != state => {
+                    session.state = state;
+                    Some(session.tab_id)
+                }
+                _ => None,
+            }
+        };
+        if let Some(tab_id) = changed {
+            self.emit_state(tab_id, state);
+            self.wake();
+        }
+    }
+
+    fn move_to(&self, target: impl FnOnce(usize, usize) -> usize) {
+        if let Some(session) = self.session.lock_or_recover().as_mut() {
+            session.index = target(session.index, session.tokens.len());
+        }
+        self.wake();
+    }
+
+    /// Advance a session that is still at `from` after its sentence ended
+    fn move_to_if(&self, id: u64, from: usize, to: usize) {
+        if let Some(session) = self.session.lock_or_recover().as_mut() {
+            if session.id == id && session.index == from {
+                session.index = to;
+            }
+        }
+    }
+
+    fn emit_state(&self, tab_id: usize, state: ReadAloudState) {
+        let _ = self.tx.send(ReadAloudEvent::StateChanged { tab_id, state });
+    }
+
+    fn wake(&self) {
+        self.wake.send_modify(|generation| *generation += 1);
+    }
+}
+
+// Private helper functions
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn tokens(count: usize) -> Vec<ReaderToken> {
+        (0..count)
+            .map(|i| ReaderToken {
+                block: Some(i),
+                text: format!("Sentence {}.", i),
+                start: 0,
+                end: 11,
+            })
+            .collect()
+    }
+
+    fn sleeping_engine(seconds: &str) -> SpeechEngine {
+        SpeechEngine {
+            program: PathBuf::from("sh"),
+            args: args(&["-c", &format!("sleep {}", seconds), "{text}"]),
+            voice_args: Vec::new(),
+            input: None,
+            rate_format: RateFormat::Percent,
+            list_voices_args: None,
+            stop_args: None,
+        }
+    }
+
+    async fn next(events: &mut mpsc::UnboundedReceiver<ReadAloudEvent>) -> ReadAloudEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reads_every_sentence_in_order() {
+        let service = Arc::new(ReadAloudService::with_engine(sleeping_engine("0"), None));
+        let mut events = service.subscribe_events();
+        service.start(7, tokens(3));
+
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::StateChanged { state: ReadAloudState::Playing, .. }));
+        for expected in 0..3 {
+            assert!(matches!(next(&mut events).await, ReadAloudEvent::Sentence { tab_id: 7, index, .. } if index == expected));
+        }
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::StateChanged { state: ReadAloudState::Idle, .. }));
+        assert_eq!(service.state(), None);
+        assert_eq!(RateFormat::WordsPerMinute(175).format(2.0), "350");
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_skip_interrupt_the_sentence() {
+        let service = Arc::new(ReadAloudService::with_engine(sleeping_engine("5"), None));
+        let mut events = service.subscribe_events();
+        service.start(1, tokens(3));
+        next(&mut events).await;
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::Sentence { index: 0, .. }));
+
+        service.skip_forward();
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::Sentence { index: 1, .. }));
+        assert_eq!(service.toggle(), ReadAloudState::Paused);
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::StateChanged { state: ReadAloudState::Paused, .. }));
+        assert_eq!(service.toggle(), ReadAloudState::Playing);
+        next(&mut events).await;
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::Sentence { index: 1, .. }));
+
+        service.stop_tab(1);
+        assert!(matches!(next(&mut events).await, ReadAloudEvent::StateChanged { state: ReadAloudState::Idle, .. }));
+    }
+}
@@ -0,0 +1,105 @@
+// System Media Transport Controls (Windows)
+use super::{MediaCommand, MediaController, MediaSession, PlaybackState};
+use crate::error::WebxError;
+use std::sync::Weak;
+use tokio::sync::watch;
+use windows::core::HSTRING;
+use windows::Foundation::{TypedEventHandler, Uri};
+use windows::Media::Playback::MediaPlayer;
+use windows::Media::{
+    MediaPlaybackStatus, MediaPlaybackType, SystemMediaTransportControls, SystemMediaTransportControlsButton,
+    SystemMediaTransportControlsButtonPressedEventArgs,
+};
+use windows::Storage::Streams::RandomAccessStreamReference;
+
+/// Publish the now playing session to the media overlay until the
+/// controller goes away
+pub(super) async fn run(
+    controller: Weak<MediaController>,
+    mut now_playing: watch::Receiver<Option<MediaSession>>,
+) -> Result<(), WebxError> {
+    // A player with its command manager off hands the controls to us
+    let player = MediaPlayer::new().map_err(smtc_error)?;
+    player.CommandManager().and_then(|m| m.SetIsEnabled(false)).map_err(smtc_error)?;
+    let controls = player.SystemMediaTransportControls().map_err(smtc_error)?;
+    controls.SetIsPlayEnabled(true).map_err(smtc_error)?;
+    controls.SetIsPauseEnabled(true).map_err(smtc_error)?;
+    controls.SetIsStopEnabled(true).map_err(smtc_error)?;
+    controls
+        .ButtonPressed(&TypedEventHandler::new(
+            move |_, args: &Option<SystemMediaTransportControlsButtonPressedEventArgs>| {
+                let command = match args.as_ref().map(|args| args.Button()).transpose()? {
+                    Some(SystemMediaTransportControlsButton::Play) => Some(MediaCommand::Play),
+                    Some(SystemMediaTransportControlsButton::Pause) => Some(MediaCommand::Pause),
+                    Some(SystemMediaTransportControlsButton::Stop) => Some(MediaCommand::Stop),
+                    _ => None,
+                };
+                if let (Some(command), Some(controller)) = (command, controller.upgrade()) {
+                    if let Err(e) = controller.control_now_playing(command) {
+                        tracing::debug!("Ignoring media key: {}", e);
+                    }
+                }
+                Ok(())
+            },
+        ))
+        .map_err(smtc_error)?;
+
+    let session = now_playing.borrow_and_update().clone();
+    show_session(&controls, session.as_ref())?;
+    while now_playing.changed().await.is_ok() {
+        let session = now_playing.borrow_and_update().clone();
+        show_session(&controls, session.as_ref())?;
+    }
+    Ok(())
+}
+
+// Private helper functions
+
+fn show_session(controls: &SystemMediaTransportControls, session: Option<&MediaSession>) -> Result<(), WebxError> {
+    let Some(session) = session else {
+        controls.SetIsEnabled(false).map_err(smtc_error)?;
+        return Ok(());
+    };
+
+    controls.SetIsEnabled(true).map_err(smtc_error)?;
+    let status = match session.state {
+        PlaybackState::Playing => MediaPlaybackStatus::Playing,
+        PlaybackState::Paused => MediaPlaybackStatus::Paused,
+        PlaybackState::Stopped => MediaPlaybackStatus::Stopped,
+    };
+    controls.SetPlaybackStatus(status).map_err(smtc_error)?;
+
+    let updater = controls.DisplayUpdater().map_err(smtc_error)?;
+    updater.ClearAll().map_err(smtc_error)?;
+    let metadata = &session.metadata;
+    if session.has_video {
+        updater.SetType(MediaPlaybackType::Video).map_err(smtc_error)?;
+        let properties = updater.VideoProperties().map_err(smtc_error)?;
+        properties.SetTitle(&HSTRING::from(metadata.title.as_str())).map_err(smtc_error)?;
+        if let Some(artist) = &metadata.artist {
+            properties.SetSubtitle(&HSTRING::from(artist.as_str())).map_err(smtc_error)?;
+        }
+    } else {
+        updater.SetType(MediaPlaybackType::Music).map_err(smtc_error)?;
+        let properties = updater.MusicProperties().map_err(smtc_error)?;
+        properties.SetTitle(&HSTRING::from(metadata.title.as_str())).map_err(smtc_error)?;
+        if let Some(artist) = &metadata.artist {
+            properties.SetArtist(&HSTRING::from(artist.as_str())).map_err(smtc_error)?;
+        }
+        if let Some(album) = &metadata.album {
+            properties.SetAlbumTitle(&HSTRING::from(album.as_str())).map_err(smtc_error)?;
+        }
+    }
+    if let Some(artwork) = &metadata.artwork_url {
+        let thumbnail = Uri::CreateUri(&HSTRING::from(artwork.as_str()))
+            .and_then(|uri| RandomAccessStreamReference::CreateFromUri(&uri))
+            .map_err(smtc_error)?;
+        updater.SetThumbnail(&thumbnail).map_err(smtc_error)?;
+    }
+    updater.Update().map_err(smtc_error)?;
+    Ok(())
+}
+
+fn smtc_error(error: windows::core::Error) -> WebxError {
+    WebxError::Invalid(format!("Media controls error: {}", error))
+}
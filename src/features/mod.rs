@@ -65,6 +65,8 @@ pub mod history_manager;
 pub mod bookmark_manager;
 pub mod sandbox;
 pub mod certificate_manager;
+pub mod media;
+pub mod feeds;
 
 pub use tabs::*;
 pub use downloads::*;
@@ -0,0 +1,233 @@
+// HTML to Markdown Conversion
+use scraper::{ElementRef, Html, Node};
+
+/// Convert an HTML fragment, e.g. a reader mode article, to Markdown.
+/// Scripts, styles and unknown elements are reduced to their text.
+pub fn html_to_markdown(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    write_children(fragment.root_element(), &mut out, &Context::default());
+
+    // Collapse the blank lines blocks leave behind
+    let mut markdown = String::new();
+    let mut blank = true;
+    for line in out.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            if !blank {
+                markdown.push('\n');
+            }
+            blank = true;
+        } else {
+            markdown.push_str(line);
+            markdown.push('\n');
+            blank = false;
+        }
+    }
+    markdown.trim().to_string()
+}
+
+/// Where in the document conversion is
+#[derive(Clone, Default)]
+struct Context {
+    /// Prefix of each line, for quotes and list items
+    indent: String,
+    preformatted: bool,
+}
+
+// Private helper functions
+
+fn write_children(element: ElementRef, out: &mut String, context: &Context) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) if context.preformatted => out.push_str(text),
+            Node::Text(text) => {
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if collapsed.is_empty() {
+                    continue;
+                }
+                // Keep the space between inline runs
+                if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '\n']) && !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push_str(&escape(&collapsed));
+                if text.ends_with(char::is_whitespace) {
+                    out.push(' ');
+                }
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, out, context);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_element(element: ElementRef, out: &mut String, context: &Context) {
+    let name = element.value().name();
+    match name {
+        "script" | "style" | "noscript" | "template" | "head" => {}
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            block(out, context);
+            out.push_str(&"#".repeat(level));
+            out.push(' ');
+            out.push_str(inline_text(element, context).trim());
+            block(out, context);
+        }
+        "p" | "div" | "section" | "article" | "header" | "footer" | "figure" | "table" | "tr" => {
+            block(out, context);
+            write_children(element, out, context);
+            block(out, context);
+        }
+        "br" => {
+            out.push('\n');
+            out.push_str(&context.indent);
+        }
+        "hr" => {
+            block(out, context);
+            out.push_str("---");
+            block(out, context);
+        }
+        "strong" | "b" => wrap_inline(element, out, context, "**"),
+        "em" | "i" => wrap_inline(element, out, context, "*"),
+        "code" if !context.preformatted => {
+            out.push('`');
+            out.push_str(&element.text().collect::<String>());
+            out.push('`');
+        }
+        "pre" => {
+            block(out, context);
+            out.push_str("```\n");
+            let code: String = element.text().collect();
+            for line in code.trim_end().lines() {
+                out.push_str(&context.indent);
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push_str(&context.indent);
+            out.push_str("```");
+            block(out, context);
+        }
+        "a" => {
+            let text = inline_text(element, context);
+            match element.value().attr("href").filter(|href| !href.starts_with("javascript:")) {
+                Some(href) if !text.trim().is_empty() => {
+                    out.push_str(&format!("[{}]({})", text.trim(), href));
+                }
+                _ => out.push_str(&text),
+            }
+        }
+        "img" => {
+            if let Some(src) = element.value().attr("src") {
+                let alt = element.value().attr("alt").unwrap_or_default();
+                out.push_str(&format!("![{}]({})", escape(alt), src));
+            }
+        }
+        "blockquote" => {
+            let quoted = Context {
+                indent: format!("{}> ", context.indent),
+                ..context.clone()
+            };
+            block(out, &quoted);
+            write_children(element, out, &quoted);
+            block(out, context);
+        }
+        "ul" | "ol" => {
+            block(out, context);
+            let ordered = name == "ol";
+            let items = element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "li");
+            for (index, item) in items.enumerate() {
+                let marker = if ordered { format!("{}. ", index + 1) } else { "- ".to_string() };
+                let nested = Context {
+                    indent: format!("{}{}", context.indent, " ".repeat(marker.len())),
+                    ..context.clone()
+                };
+                out.push('\n');
+                out.push_str(&context.indent);
+                out.push_str(&marker);
+                write_children(item, out, &nested);
+            }
+            block(out, context);
+        }
+        "td" | "th" => {
+            write_children(element, out, context);
+            out.push(' ');
+        }
+        _ => write_children(element, out, context),
+    }
+}
+
+/// Start a new block: a blank line, then the line prefix
+fn block(out: &mut String, context: &Context) {
+    // Drop trailing lines holding only a prefix
+    loop {
+        let trimmed = out.trim_end().len();
+        out.truncate(trimmed);
+        let line_start = out.rfind('\n').map_or(0, |i| i + 1);
+        if out.len() == line_start || !out[line_start..].trim_start_matches([' ', '>']).is_empty() {
+            break;
+        }
+        out.truncate(line_start);
+    }
+    out.push_str("\n\n");
+    out.push_str(&context.indent);
+}
+
+fn wrap_inline(element: ElementRef, out: &mut String, context: &Context, marker: &str) {
+    let text = inline_text(element, context);
+    if text.trim().is_empty() {
+        return;
+    }
+    out.push_str(marker);
+    out.push_str(text.trim());
+    out.push_str(marker);
+}
+
+fn inline_text(element: ElementRef, context: &Context) -> String {
+    let mut text = String::new();
+    write_children(element, &mut text, context);
+    text
+}
+
+/// Escape characters Markdown would read as formatting
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '`' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<h2>Title</h2>
+            <p>Some <strong>bold</strong> and <em>italic</em> text with a <a href="https://example.com">link</a>.</p>
+            <ul><li>One</li><li>Two <code>x*y</code></li></ul>
+            <blockquote><p>Quoted</p></blockquote>
+            <pre>let a = 1;
+let b = 2;</pre>
+            <script>alert(1)</script>"#;
+
+        assert_eq!(
+            html_to_markdown(html),
+            "## Title\n\n\
+             Some **bold** and *italic* text with a [link](https://example.com).\n\n\
+             - One\n\
+             - Two `x*y`\n\n\
+             > Quoted\n\n\
+             ```\nlet a = 1;\nlet b = 2;\n```"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+// Web Clipper
+pub mod markdown;
+pub mod page;
+
+pub use markdown::html_to_markdown;
+pub use page::{render_notes_page, NotesPage, NOTES_PAGE_URL};
+
+use crate::error::WebxError;
+use crate::core::{match_score, recency_factor, SearchQuery, SearchResult, SearchSource, SearchSourceKind};
+use crate::features::ui::reader::ReadingMode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Reports the selection, or the whole page when nothing is selected, with a
+/// `type: 'clip'` IPC message. Tags are asked for first.
+pub const CLIP_SCRIPT: &str = r#"
+(function() {
+    const selection = window.getSelection().toString().trim();
+    const tags = prompt(selection ? 'Clip selection with tags (comma separated):' : 'Clip article with tags (comma separated):', '');
+    if (tags === null) return;
+    window.ipc.send({
+        type: 'clip',
+        url: location.href,
+        title: document.title,
+        selection: selection || null,
+        html: selection ? null : document.documentElement.outerHTML,
+        tags: tags.split(',').map((tag) => tag.trim()).filter((tag) => tag),
+    });
+})();
+"#;
+
+/// What a note was clipped from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum NoteKind {
+    Selection,
+    /// A simplified article from reader mode
+    Article,
+}
+
+/// A clipped note
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Note {
+    pub id: u64,
+    pub title: String,
+    /// Markdown
+    pub content: String,
+    /// Lowercase, without duplicates
+    pub tags: Vec<String>,
+    pub source_url: String,
+    pub kind: NoteKind,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Local store of clipped notes
+pub struct Notebook {
+    db: Db,
+    notes: Tree,
+}
+
+impl Notebook {
+    /// Open the notebook
+    pub fn new(db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("notes.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        Ok(Self {
+            notes: db.open_tree("notes")?,
+            db,
+        })
+    }
+
+    /// Save selected text from a page
+    pub fn clip_selection(&self, url: &str, title: &str, text: &str, tags: &[String]) -> Result<Note, WebxError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Nothing is selected".into());
+        }
+        self.insert(title, text.to_string(), tags, url, NoteKind::Selection)
+    }
+
+    /// Save a page simplified by reader mode
+    pub fn clip_article(&self, reader: &ReadingMode, html: &str, url: &str, tags: &[String]) -> Result<Note, WebxError> {
+        let article = reader
+            .extract_article(html, url)
+            .ok_or_else(|| WebxError::Parse(format!("No article found on {}", url)))?;
+        self.insert(&article.title, html_to_markdown(&article.content), tags, url, NoteKind::Article)
+    }
+
+    /// Get a note
+    pub fn get(&self, id: u64) -> Option<Note> {
+        let value = self.notes.get(id.to_be_bytes()).ok()??;
+        serde_json::from_slice(&value).ok()
+    }
+
+    /// All notes, newest first
+    pub fn notes(&self) -> Vec<Note> {
+        let mut notes: Vec<Note> = self
+            .notes
+            .iter()
+            .values()
+            .filter_map(|value| value.ok())
+            .filter_map(|value| serde_json::from_slice(&value).ok())
+            .collect();
+        notes.sort_by_key(|note: &Note| std::cmp::Reverse((note.created_at, note.id)));
+        notes
+    }
+
+    /// Notes matching every word of a query, newest first. `tag:name` words
+    /// match tags; other words match the title, content, tags or source URL.
+    pub fn search(&self, query: &str) -> Vec<Note> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.notes()
+            .into_iter()
+            .filter(|note| {
+                let haystack = format!("{}\n{}\n{}", note.title, note.content, note.source_url).to_lowercase();
+                words.iter().all(|word| match word.strip_prefix("tag:") {
+                    Some(tag) => note.tags.iter().any(|t| t == tag),
+                    None => haystack.contains(word.as_str()) || note.tags.iter().any(|t| t.contains(word.as_str())),
+                })
+            })
+            .collect()
+    }
+
+    /// Every tag with the number of notes carrying it
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for note in self.notes() {
+            for tag in note.tags {
+                *tags.entry(tag).or_insert(0) += 1;
+            }
+        }
+        tags
+    }
+
+    /// Replace a note's tags
+    pub fn set_tags(&self, id: u64, tags: &[String]) -> Result<Note, WebxError> {
+        let mut note = self.get(id).ok_or_else(|| WebxError::NotFound(format!("Note {}", id)))?;
+        note.tags = normalize_tags(tags);
+        self.notes.insert(id.to_be_bytes(), serde_json::to_vec(&note)?)?;
+        Ok(note)
+    }
+
+    /// Delete a note
+    pub fn delete(&self, id: u64) -> Result<bool, WebxError> {
+        Ok(self.notes.remove(id.to_be_bytes())?.is_some())
+    }
+
+    /// Write one note to a Markdown file
+    pub fn export_note(&self, id: u64, path: &Path) -> Result<(), WebxError> {
+        let note = self.get(id).ok_or_else(|| WebxError::NotFound(format!("Note {}", id)))?;
+        std::fs::write(path, note_to_markdown(&note))?;
+        Ok(())
+    }
+
+    /// Write every note, oldest first, to one Markdown file, returning how
+    /// many were exported
+    pub fn export_notebook(&self, path: &Path) -> Result<usize, WebxError> {
+        let mut notes = self.notes();
+        notes.reverse();
+        let markdown = notes.iter().map(note_to_markdown).collect::<Vec<_>>().join("\n---\n\n");
+        std::fs::write(path, format!("# Notebook\n\n{}", markdown))?;
+        Ok(notes.len())
+    }
+
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), WebxError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Private helper methods
+
+    fn insert(&self, title: &str, content: String, tags: &[String], url: &str, kind: NoteKind) -> Result<Note, WebxError> {
+        let title = title.trim();
+        let note = Note {
+            id: self.db.generate_id()?,
+            title: if title.is_empty() { url.to_string() } else { title.to_string() },
+            content,
+            tags: normalize_tags(tags),
+            source_url: url.to_string(),
+            kind,
+            created_at: Utc::now(),
+        };
+        self.notes.insert(note.id.to_be_bytes(), serde_json::to_vec(&note)?)?;
+        Ok(note)
+    }
+}
+
+/// A note as Markdown, with its source, date and tags under the title
+pub fn note_to_markdown(note: &Note) -> String {
+    let mut details = vec![
+        format!("Source: <{}>", note.source_url),
+        format!("Clipped: {}", note.created_at.format("%Y-%m-%d %H:%M UTC")),
+    ];
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|tag| format!("#{}", tag.replace(' ', "-"))).collect();
+        details.push(format!("Tags: {}", tags.join(" ")));
+    }
+    // Trailing double spaces keep the details on separate lines
+    let mut markdown = format!("## {}\n\n{}\n\n", note.title, details.join("  \n"));
+    match note.kind {
+        NoteKind::Selection => {
+            for line in note.content.lines() {
+                markdown.push_str(&format!("> {}\n", line).replace("> \n", ">\n"));
+            }
+        }
+        NoteKind::Article => {
+            markdown.push_str(&note.content);
+            markdown.push('\n');
+        }
+    }
+    markdown
+}
+
+impl SearchSource for Notebook {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::Notes
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = Utc::now();
+        self.notes()
+            .into_iter()
+            .filter_map(|note| {
+                let tags = note.tags.join(" ");
+                let fields = [
+                    (note.title.as_str(), 1.0),
+                    (tags.as_str(), 0.9),
+                    (note.content.as_str(), 0.6),
+                    (note.source_url.as_str(), 0.5),
+                ];
+                let score = match_score(&words, &fields)?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::Notes,
+                    id: note.id.to_string(),
+                    score: score * recency_factor(note.created_at, now),
+                    timestamp: Some(note.created_at),
+                    url: (!note.source_url.is_empty()).then_some(note.source_url),
+                    detail: note.content.lines().find(|line| !line.trim().is_empty()).map(str::to_string),
+                    title: note.title,
+                })
+            })
+            .collect()
+    }
+}
+
+// Private helper functions
+
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_clip_search_and_export() {
+        let temp_dir = TempDir::new().unwrap();
+        let notebook = Notebook::new(Some(temp_dir.path().join("notes.db"))).unwrap();
+        let tags = vec!["Rust".to_string(), "#rust".to_string(), "async ".to_string()];
+
+        let note = notebook
+            .clip_selection("https://example.com/post", "A Post", "Futures are lazy.\n\nPoll them.", &tags)
+            .unwrap();
+        assert_eq!(note.tags, vec!["rust", "async"]);
+        notebook
+            .clip_selection("https://example.com/other", "", "Something else", &[])
+            .unwrap();
+        assert!(notebook.clip_selection("https://example.com", "Empty", "  ", &[]).is_err());
+
+        assert_eq!(notebook.search("lazy tag:rust").len(), 1);
+        assert_eq!(notebook.search("example.com").len(), 2);
+        assert!(notebook.search("lazy tag:python").is_empty());
+        assert_eq!(notebook.tags().get("rust"), Some(&1));
+
+        let markdown = note_to_markdown(&note);
+        assert!(markdown.starts_with("## A Post\n\nSource: <https://example.com/post>"));
+        assert!(markdown.contains("Tags: #rust #async\n"));
+        assert!(markdown.ends_with("> Futures are lazy.\n>\n> Poll them.\n"));
+
+        let path = temp_dir.path().join("notebook.md");
+        assert_eq!(notebook.export_notebook(&path).unwrap(), 2);
+        let exported = std::fs::read_to_string(&path).unwrap();
+        assert!(exported.find("## A Post").unwrap() < exported.find("## https://example.com/other").unwrap());
+
+        assert!(notebook.delete(note.id).unwrap());
+        assert_eq!(notebook.notes().len(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+// Notes Page
+use super::{Note, NoteKind, Notebook};
+use crate::error::WebxError;
+use crate::utils::escape_html;
+use std::collections::BTreeMap;
+
+/// Internal page for searching clipped notes
+pub const NOTES_PAGE_URL: &str = "webx://notes";
+
+/// A page under `webx://notes`
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotesPage {
+    /// Notes matching a search, or all notes
+    Index { query: Option<String> },
+    /// One note in full
+    Note(u64),
+}
+
+impl NotesPage {
+    /// Parse a `webx://notes` URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(NOTES_PAGE_URL)?;
+        if !rest.is_empty() && !rest.starts_with(['/', '?', '#']) {
+            return None;
+        }
+        let parsed = url::Url::parse(url).ok()?;
+        match parsed.path().trim_matches('/') {
+            "" => Some(Self::Index {
+                query: parsed
+                    .query_pairs()
+                    .find(|(key, _)| key == "q")
+                    .map(|(_, value)| value.trim().to_string())
+                    .filter(|query| !query.is_empty()),
+            }),
+            id => id.parse().ok().map(Self::Note),
+        }
+    }
+}
+
+/// Render a `webx://notes` page
+pub fn render_page(notebook: &Notebook, page: &NotesPage) -> Result<String, WebxError> {
+    match page {
+        NotesPage::Index { query } => {
+            let notes = match query {
+                Some(query) => notebook.search(query),
+                None => notebook.notes(),
+            };
+            Ok(render_notes_page(&notes, query.as_deref(), &notebook.tags()))
+        }
+        NotesPage::Note(id) => {
+            let note = notebook
+                .get(*id)
+                .ok_or_else(|| WebxError::NotFound(format!("Note {}", id)))?;
+            Ok(render_note_page(&note))
+        }
+    }
+}
+
+/// Render a search box, the tags and the given notes. Buttons send
+/// `type: 'notes'` IPC messages with an `action` of `delete`, `export` or
+/// `exportall`.
+pub fn render_notes_page(notes: &[Note], query: Option<&str>, tags: &BTreeMap<String, usize>) -> String {
+    let tag_links: String = tags
+        .iter()
+        .map(|(tag, count)| {
+            format!(
+                r#"<a class="tag" href="{}?q=tag:{}">#{} <span>{}</span></a> "#,
+                NOTES_PAGE_URL,
+                escape_html(&tag.replace(' ', "%20")),
+                escape_html(tag),
+                count
+            )
+        })
+        .collect();
+    let list: String = notes
+        .iter()
+        .map(|note| {
+            format!(
+                r#"<li><a href="{base}/{id}">{title}</a> <time>{date}</time>
+<button data-action="export" data-note="{id}">Export</button>
+<button data-action="delete" data-note="{id}">Delete</button>
+<p>{snippet}</p><p class="source">{tags}<a href="{url}">{url_text}</a></p></li>"#,
+                base = NOTES_PAGE_URL,
+                id = note.id,
+                title = escape_html(&note.title),
+                date = note.created_at.format("%Y-%m-%d"),
+                snippet = escape_html(&snippet(&note.content, 200)),
+                tags = note.tags.iter().map(|tag| format!("#{} ", escape_html(tag))).collect::<String>(),
+                url = escape_html(&note.source_url),
+                url_text = escape_html(&note.source_url),
+            )
+        })
+        .collect();
+    let list = match (list.is_empty(), query) {
+        (true, Some(_)) => r#"<p class="empty">No notes match</p>"#.to_string(),
+        (true, None) => r#"<p class="empty">No notes yet. Clip a selection or an article with Ctrl+Shift+K.</p>"#.to_string(),
+        (false, _) => format!("<ul>{}</ul>", list),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Notes</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header>
+        <h1>Notes</h1>
+        <form action="{base}"><input type="search" name="q" value="{query}" placeholder="Search notes, tag:name"></form>
+        <button data-action="exportall">Export All</button>
+    </header>
+    <nav>{tag_links}</nav>
+    {list}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        base = NOTES_PAGE_URL,
+        query = escape_html(query.unwrap_or_default()),
+        tag_links = tag_links,
+        list = list,
+        script = PAGE_SCRIPT,
+    )
+}
+
+/// Render one note with its Markdown source
+pub fn render_note_page(note: &Note) -> String {
+    let kind = match note.kind {
+        NoteKind::Selection => "Selection",
+        NoteKind::Article => "Article",
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><a href="{base}">Notes</a></header>
+    <h1>{title}</h1>
+    <p class="source">{kind} from <a href="{url}">{url}</a>, {date} {tags}</p>
+    <button data-action="export" data-note="{id}">Export</button>
+    <button data-action="delete" data-note="{id}">Delete</button>
+    <pre>{content}</pre>
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        base = NOTES_PAGE_URL,
+        title = escape_html(&note.title),
+        kind = kind,
+        url = escape_html(&note.source_url),
+        date = note.created_at.format("%Y-%m-%d %H:%M"),
+        tags = note.tags.iter().map(|tag| format!("#{} ", escape_html(tag))).collect::<String>(),
+        id = note.id,
+        content = escape_html(&note.content),
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+header { display: flex; align-items: center; gap: 1em; }
+header form { flex: 1; }
+input[type=search] { width: 100%; padding: 0.4em; }
+nav { margin: 1em 0; }
+.tag { margin-right: 0.5em; color: #2a6; text-decoration: none; }
+.tag span { color: #888; font-size: 0.85em; }
+ul { list-style: none; padding: 0; }
+li { margin: 1em 0; border-top: 1px solid #eee; padding-top: 0.5em; }
+li p { margin: 0.3em 0; color: #555; }
+.source { color: #888; font-size: 0.85em; }
+time { color: #888; font-size: 0.85em; margin: 0 0.5em; }
+button { font-size: 0.8em; }
+pre { white-space: pre-wrap; font-family: ui-monospace, monospace; background: #f7f7f7; padding: 1em; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    if (button.dataset.action === 'delete' && !confirm('Delete this note?')) return;
+    const message = { type: 'notes', action: button.dataset.action };
+    if (button.dataset.note) message.note = Number(button.dataset.note);
+    window.ipc.send(message);
+});
+"#;
+
+// Private helper functions
+
+/// The start of a note's text, whitespace collapsed
+fn snippet(markdown: &str, max_chars: usize) -> String {
+    let text = markdown.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notes_urls() {
+        assert_eq!(NotesPage::parse("webx://notes"), Some(NotesPage::Index { query: None }));
+        assert_eq!(
+            NotesPage::parse("webx://notes/?q=tag%3Arust+async"),
+            Some(NotesPage::Index { query: Some("tag:rust async".to_string()) })
+        );
+        assert_eq!(NotesPage::parse("webx://notes/42"), Some(NotesPage::Note(42)));
+        assert_eq!(NotesPage::parse("webx://notesx"), None);
+    }
+}
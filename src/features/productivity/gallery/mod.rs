@@ -0,0 +1,160 @@
+// Image Gallery Saver
+use serde::Deserialize;
+
+/// Images smaller than this many pixels on a side are left out, so icons
+/// and tracking pixels do not crowd the gallery
+pub const GALLERY_MIN_SIZE: u32 = 100;
+
+/// Folder images are saved to when the user names none
+const DEFAULT_FOLDER: &str = "Images";
+
+/// Script giving pages `window.imageGallery`, whose `open(minSize)` shows
+/// the page's images at least `minSize` pixels wide and high with their
+/// dimensions, and saves the ones the user keeps selected
+pub const GALLERY_SCRIPT: &str = r#"
+(function() {
+    if (window.imageGallery) return;
+    let panel = null;
+    const close = () => { if (panel) { panel.remove(); panel = null; } };
+    const collect = (minSize) => {
+        const seen = new Set();
+        const images = [];
+        for (const img of document.images) {
+            const url = img.currentSrc || img.src;
+            if (!/^https?:/.test(url) || seen.has(url)) continue;
+            if (img.naturalWidth < minSize || img.naturalHeight < minSize) continue;
+            seen.add(url);
+            images.push({ url: url, width: img.naturalWidth, height: img.naturalHeight, alt: img.alt || null });
+        }
+        return images;
+    };
+    const open = (minSize) => {
+        close();
+        const images = collect(minSize);
+        panel = document.createElement('div');
+        panel.style.cssText = 'position:fixed;inset:24px;z-index:2147483647;background:#fff;color:#202124;border:1px solid #dadce0;border-radius:8px;box-shadow:0 4px 24px rgba(0,0,0,.3);font:13px system-ui,sans-serif;display:flex;flex-direction:column;';
+        const bar = document.createElement('div');
+        bar.style.cssText = 'display:flex;gap:8px;align-items:center;padding:10px 14px;border-bottom:1px solid #dadce0;';
+        const folder = document.createElement('input');
+        folder.value = document.title.trim();
+        folder.placeholder = 'Folder';
+        folder.style.cssText = 'flex:1;padding:4px 6px;';
+        const toggle = document.createElement('button');
+        const save = document.createElement('button');
+        const cancel = document.createElement('button');
+        cancel.textContent = 'Cancel';
+        cancel.addEventListener('click', close);
+        bar.append(folder, toggle, save, cancel);
+        const grid = document.createElement('div');
+        grid.style.cssText = 'flex:1;overflow:auto;display:grid;grid-template-columns:repeat(auto-fill,minmax(160px,1fr));gap:10px;padding:14px;';
+        const boxes = images.map((image) => {
+            const cell = document.createElement('label');
+            cell.style.cssText = 'display:flex;flex-direction:column;gap:4px;align-items:center;cursor:default;';
+            const thumb = document.createElement('img');
+            thumb.src = image.url;
+            thumb.style.cssText = 'max-width:100%;height:120px;object-fit:contain;background:#f1f3f4;';
+            const caption = document.createElement('span');
+            const box = document.createElement('input');
+            box.type = 'checkbox';
+            box.checked = true;
+            box.addEventListener('change', update);
+            caption.append(box, ' ' + image.width + ' × ' + image.height);
+            cell.append(thumb, caption);
+            grid.append(cell);
+            return box;
+        });
+        function update() {
+            const count = boxes.filter((box) => box.checked).length;
+            save.textContent = 'Save ' + count + (count === 1 ? ' image' : ' images');
+            save.disabled = count === 0;
+            toggle.textContent = count === boxes.length ? 'Select none' : 'Select all';
+        }
+        toggle.addEventListener('click', () => {
+            const all = boxes.some((box) => !box.checked);
+            boxes.forEach((box) => { box.checked = all; });
+            update();
+        });
+        save.addEventListener('click', () => {
+            const picked = images.filter((_, index) => boxes[index].checked);
+            window.ipc.send({ type: 'saveimages', folder: folder.value, images: picked });
+            close();
+        });
+        if (!images.length) grid.textContent = 'No images of at least ' + minSize + ' × ' + minSize + ' pixels on this page';
+        update();
+        panel.append(bar, grid);
+        document.documentElement.append(panel);
+    };
+    window.imageGallery = { open: open };
+    document.addEventListener('keydown', (e) => { if (e.key === 'Escape') close(); }, true);
+})();
+"#;
+
+/// An image shown in the gallery
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GalleryImage {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub alt: Option<String>,
+}
+
+/// Images the user picked to save, and the folder to save them to
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GallerySelection {
+    #[serde(default)]
+    pub folder: String,
+    pub images: Vec<GalleryImage>,
+}
+
+impl GallerySelection {
+    /// Name of the download subfolder
+    pub fn folder_name(&self) -> String {
+        match self.folder.trim() {
+            "" => DEFAULT_FOLDER.to_string(),
+            folder => folder.to_string(),
+        }
+    }
+
+    /// URLs to download: web images of at least `min_size` pixels on a
+    /// side, each once
+    pub fn urls(&self, min_size: u32) -> Vec<String> {
+        let mut urls: Vec<String> = Vec::new();
+        for image in &self.images {
+            let web = url::Url::parse(&image.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            let large = image.width >= min_size && image.height >= min_size;
+            if web && large && !urls.contains(&image.url) {
+                urls.push(image.url.clone());
+            }
+        }
+        urls
+    }
+}
+
+/// Script opening the gallery in a page running `GALLERY_SCRIPT`
+pub fn gallery_script(min_size: u32) -> String {
+    format!("window.imageGallery && window.imageGallery.open({});", min_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_keeps_large_web_images_once() {
+        let selection: GallerySelection = serde_json::from_value(serde_json::json!({
+            "folder": "  ",
+            "images": [
+                { "url": "https://example.com/photo.jpg", "width": 1200, "height": 800, "alt": "Lake" },
+                { "url": "https://example.com/icon.png", "width": 32, "height": 32 },
+                { "url": "https://example.com/banner.png", "width": 970, "height": 90 },
+                { "url": "data:image/png;base64,AAAA", "width": 400, "height": 400 },
+                { "url": "https://example.com/photo.jpg", "width": 1200, "height": 800 },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(selection.folder_name(), "Images");
+        assert_eq!(selection.urls(GALLERY_MIN_SIZE), ["https://example.com/photo.jpg"]);
+        assert_eq!(selection.urls(80).len(), 2);
+    }
+}
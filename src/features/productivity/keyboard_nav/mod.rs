@@ -0,0 +1,360 @@
+// Keyboard Navigation
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Letters link hints are made of unless the user picks others
+pub const DEFAULT_HINT_CHARACTERS: &str = "asdfghjkl";
+
+/// Links labelled at most, so huge pages do not stall
+const MAX_HINTS: usize = 2000;
+
+/// Script giving pages caret browsing, a movable text cursor, and link
+/// hints, labels typed to follow links, reading the options from the
+/// browser as the page loads and when `window.webxKeyboardNav.refresh()` runs
+pub const KEYBOARD_NAV_SCRIPT: &str = r#"
+(function() {
+    if (window.webxKeyboardNav) return;
+    let options = { caretBrowsing: false, linkHints: false };
+    let caret = null, hints = null;
+    const editable = (element) => element && (element.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(element.tagName));
+    const visible = (rect) => rect.width > 0 && rect.height > 0 && rect.bottom > 0 && rect.right > 0 && rect.top < innerHeight && rect.left < innerWidth;
+
+    // Caret browsing: a blinking cursor moved through the text with the arrow keys
+    const placeCaret = () => {
+        const selection = getSelection();
+        if (selection.rangeCount) return;
+        const walker = document.createTreeWalker(document.body || document.documentElement, NodeFilter.SHOW_TEXT);
+        for (let node = walker.nextNode(); node; node = walker.nextNode()) {
+            if (!node.textContent.trim()) continue;
+            const range = document.createRange();
+            range.selectNodeContents(node);
+            if (visible(range.getBoundingClientRect())) {
+                selection.collapse(node, node.textContent.search(/\S/));
+                return;
+            }
+        }
+    };
+    const drawCaret = () => {
+        if (!caret) return;
+        const selection = getSelection();
+        if (!selection.rangeCount) { caret.style.display = 'none'; return; }
+        const range = selection.getRangeAt(0).cloneRange();
+        range.collapse(selection.focusNode === range.startContainer && selection.focusOffset === range.startOffset);
+        let rect = range.getClientRects()[0];
+        if (!rect && range.startContainer.parentElement) rect = range.startContainer.parentElement.getBoundingClientRect();
+        if (!rect) { caret.style.display = 'none'; return; }
+        caret.style.display = 'block';
+        caret.style.left = rect.left + 'px';
+        caret.style.top = rect.top + 'px';
+        caret.style.height = Math.max(rect.height, 12) + 'px';
+        if (rect.top < 0 || rect.bottom > innerHeight) scrollBy(0, rect.top < 0 ? rect.top - 20 : rect.bottom - innerHeight + 20);
+    };
+    const setCaret = (on) => {
+        if (on && !caret) {
+            caret = document.createElement('div');
+            caret.style.cssText = 'position:fixed;z-index:2147483647;width:2px;background:currentColor;color:#1a73e8;pointer-events:none;animation:webx-caret 1s steps(1) infinite;';
+            const style = document.createElement('style');
+            style.textContent = '@keyframes webx-caret { 50% { opacity: 0; } }';
+            caret.append(style);
+            document.documentElement.append(caret);
+            placeCaret();
+            drawCaret();
+        } else if (!on && caret) {
+            caret.remove();
+            caret = null;
+        }
+    };
+    const moves = {
+        ArrowLeft: ['backward', 'character'], ArrowRight: ['forward', 'character'],
+        ArrowUp: ['backward', 'line'], ArrowDown: ['forward', 'line'],
+        Home: ['backward', 'lineboundary'], End: ['forward', 'lineboundary']
+    };
+    document.addEventListener('selectionchange', drawCaret);
+    addEventListener('scroll', drawCaret, true);
+
+    // Link hints: label the links in view and follow the one whose label is typed
+    const targets = () => {
+        const found = [];
+        for (const element of document.querySelectorAll('a[href], button, input, select, textarea, summary, [onclick], [role="button"], [role="link"], [contenteditable="true"]')) {
+            if (element.disabled || (element.type === 'hidden')) continue;
+            const rect = element.getClientRects()[0];
+            if (rect && visible(rect) && getComputedStyle(element).visibility !== 'hidden') found.push({ element: element, rect: rect });
+        }
+        return found;
+    };
+    const closeHints = () => {
+        if (hints) { hints.layer.remove(); hints = null; }
+    };
+    const follow = (element, newTab) => {
+        closeHints();
+        if (editable(element)) { element.focus(); return; }
+        if (newTab && element.href && /^https?:/.test(element.href)) {
+            window.ipc.send({ type: 'openinnewtab', url: element.href });
+            return;
+        }
+        element.focus();
+        element.click();
+    };
+    const showHints = (newTab) => {
+        closeHints();
+        const found = targets().slice(0, 2000);
+        if (!found.length) return;
+        window.ipc.request({ type: 'keyboardnav', action: 'labels', count: found.length }).then((labels) => {
+            const layer = document.createElement('div');
+            layer.style.cssText = 'position:fixed;inset:0;z-index:2147483647;pointer-events:none;';
+            const marks = found.map((target, index) => {
+                const mark = document.createElement('span');
+                mark.textContent = labels[index];
+                mark.style.cssText = 'position:fixed;padding:0 3px;border:1px solid #c38a22;border-radius:3px;background:#ffd76e;color:#302505;font:bold 12px monospace;text-transform:uppercase;';
+                mark.style.left = Math.max(target.rect.left, 0) + 'px';
+                mark.style.top = Math.max(target.rect.top, 0) + 'px';
+                layer.append(mark);
+                return { label: labels[index], mark: mark, element: target.element };
+            });
+            document.documentElement.append(layer);
+            hints = { layer: layer, marks: marks, typed: '', newTab: newTab };
+        }).catch(() => {});
+    };
+    const typeHint = (key) => {
+        hints.typed += key;
+        const matching = hints.marks.filter((mark) => mark.label.startsWith(hints.typed));
+        if (!matching.length) { closeHints(); return; }
+        if (matching.length === 1 && matching[0].label === hints.typed) { follow(matching[0].element, hints.newTab); return; }
+        for (const mark of hints.marks) mark.mark.style.display = mark.label.startsWith(hints.typed) ? '' : 'none';
+    };
+
+    document.addEventListener('keydown', (e) => {
+        if (hints) {
+            e.preventDefault();
+            e.stopPropagation();
+            if (e.key === 'Escape') closeHints();
+            else if (e.key === 'Backspace') { hints.typed = ''; hints.marks.forEach((mark) => { mark.mark.style.display = ''; }); }
+            else if (e.key.length === 1) typeHint(e.key.toLowerCase());
+            return;
+        }
+        // F7: Caret browsing on or off
+        if (e.key === 'F7' && !e.ctrlKey && !e.altKey && !e.metaKey) {
+            e.preventDefault();
+            window.ipc.send({ type: 'togglecaret' });
+            return;
+        }
+        if (editable(e.target) || e.ctrlKey || e.altKey || e.metaKey) return;
+        // F, or Shift+F for new tabs: Link hints
+        if (options.linkHints && e.key.toLowerCase() === 'f') {
+            e.preventDefault();
+            showHints(e.shiftKey);
+            return;
+        }
+        if (!caret) return;
+        const move = moves[e.key];
+        if (move) {
+            e.preventDefault();
+            placeCaret();
+            getSelection().modify(e.shiftKey ? 'extend' : 'move', move[0], move[1]);
+        } else if (e.key === 'Enter') {
+            const node = getSelection().focusNode;
+            const link = node && (node.nodeType === 1 ? node : node.parentElement).closest('a[href]');
+            if (link) {
+                e.preventDefault();
+                link.click();
+            }
+        }
+    }, true);
+
+    const apply = (settings) => {
+        options = settings;
+        setCaret(settings.caretBrowsing);
+        if (!settings.linkHints) closeHints();
+    };
+    const refresh = () => {
+        window.ipc.request({ type: 'keyboardnav', action: 'state' }).then(apply).catch(() => {});
+    };
+    window.webxKeyboardNav = { refresh: refresh, showHints: showHints };
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', refresh);
+    } else {
+        refresh();
+    }
+})();
+"#;
+
+/// Script re-applying changed keyboard navigation options to an open page
+pub const KEYBOARD_NAV_REFRESH_SCRIPT: &str = "window.webxKeyboardNav && window.webxKeyboardNav.refresh();";
+
+/// Keyboard navigation options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardNavSettings {
+    /// Move a text cursor through pages with the arrow keys
+    pub caret_browsing: bool,
+    /// Label links to follow when F is pressed outside text fields
+    pub link_hints: bool,
+    /// Letters hint labels are made of
+    pub hint_characters: String,
+}
+
+impl Default for KeyboardNavSettings {
+    fn default() -> Self {
+        Self {
+            caret_browsing: false,
+            link_hints: false,
+            hint_characters: DEFAULT_HINT_CHARACTERS.to_string(),
+        }
+    }
+}
+
+/// What a page asks of keyboard navigation
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum KeyboardNavAction {
+    /// The options the page applies
+    State,
+    /// Labels for this many links
+    Labels { count: usize },
+}
+
+/// The options as the page script takes them
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PageKeyboardNav {
+    caret_browsing: bool,
+    link_hints: bool,
+}
+
+/// Caret browsing and link hints for every page
+#[derive(Default)]
+pub struct KeyboardNav {
+    settings: Mutex<KeyboardNavSettings>,
+}
+
+impl KeyboardNav {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn settings(&self) -> KeyboardNavSettings {
+        self.settings.lock_or_recover().clone()
+    }
+
+    /// Hint labels for `count` links: all the same length and as short as the
+    /// hint characters allow, so none is the start of another
+    pub fn hint_labels(&self, count: usize) -> Vec<String> {
+        let characters: Vec<char> = self.settings.lock_or_recover().hint_characters.chars().collect();
+        let count = count.min(MAX_HINTS);
+        let mut length = 1;
+        while characters.len().pow(length) < count {
+            length += 1;
+        }
+        (0..count)
+            .map(|mut index| {
+                let mut label = vec![characters[0]; length as usize];
+                for slot in label.iter_mut().rev() {
+                    *slot = characters[index % characters.len()];
+                    index /= characters.len();
+                }
+                label.into_iter().collect()
+            })
+            .collect()
+    }
+}
+
+impl IpcHandler for KeyboardNav {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["keyboardnav"]
+    }
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::KeyboardNav(KeyboardNavAction::State) => {
+                let settings = self.settings();
+                Ok(serde_json::to_value(PageKeyboardNav {
+                    caret_browsing: settings.caret_browsing,
+                    link_hints: settings.link_hints,
+                })?)
+            }
+            IpcMessage::KeyboardNav(KeyboardNavAction::Labels { count }) => Ok(serde_json::to_value(self.hint_labels(count))?),
+            _ => Err(WebxError::Invalid("Not a keyboard navigation message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for KeyboardNav {
+    fn module(&self) -> &str {
+        "keyboard"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("keyboard.caret_browsing", "Caret browsing", false)
+                .with_description("Move a text cursor through pages with the arrow keys; F7 turns it on or off")
+                .with_category(SettingCategory::General),
+            SettingDefinition::toggle("keyboard.link_hints", "Link hints", false)
+                .with_description("Press F outside text fields to label links, then type a label to follow it; Shift+F opens it in a new tab")
+                .with_category(SettingCategory::General),
+            SettingDefinition::text("keyboard.hint_characters", "Link hint letters", DEFAULT_HINT_CHARACTERS)
+                .with_description("At least two different letters, easiest to reach first")
+                .with_category(SettingCategory::General),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let mut settings = self.settings.lock_or_recover();
+        match key {
+            "keyboard.caret_browsing" => settings.caret_browsing = value.as_bool().ok_or("Expected a toggle value")?,
+            "keyboard.link_hints" => settings.link_hints = value.as_bool().ok_or("Expected a toggle value")?,
+            "keyboard.hint_characters" => {
+                settings.hint_characters = parse_hint_characters(value.as_str().ok_or("Expected text")?)?
+            }
+            _ => return Err(format!("Unknown keyboard setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+/// Hint letters, lowercased, each once
+fn parse_hint_characters(text: &str) -> Result<String, WebxError> {
+    let mut characters = String::new();
+    for character in text.trim().to_lowercase().chars() {
+        if !character.is_alphanumeric() {
+            return Err(WebxError::Invalid(format!("{:?} can not be typed as a link hint", character)));
+        }
+        if !characters.contains(character) {
+            characters.push(character);
+        }
+    }
+    if characters.chars().count() < 2 {
+        return Err(WebxError::Invalid("Link hints need at least two different letters".to_string()));
+    }
+    Ok(characters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_labels_are_short_and_prefix_free() {
+        let nav = KeyboardNav::new();
+        assert_eq!(nav.hint_labels(3), ["a", "s", "d"]);
+
+        let labels = nav.hint_labels(10);
+        assert_eq!(labels.len(), 10);
+        assert!(labels.iter().all(|label| label.len() == 2));
+        assert_eq!(labels[..2], ["aa", "as"]);
+        assert_eq!(labels[9], "sa");
+
+        nav.apply_setting("keyboard.hint_characters", &SettingValue::String(" JKjk ".to_string()))
+            .unwrap();
+        assert_eq!(nav.settings().hint_characters, "jk");
+        assert_eq!(nav.hint_labels(5), ["jjj", "jjk", "jkj", "jkk", "kjj"]);
+        assert!(nav
+            .apply_setting("keyboard.hint_characters", &SettingValue::String("a;".to_string()))
+            .is_err());
+        assert_eq!(nav.hint_labels(MAX_HINTS * 2).len(), MAX_HINTS);
+    }
+}
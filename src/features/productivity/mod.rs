@@ -1,9 +1,28 @@
 // Productivity Features Module
+pub mod clipper;
+pub mod gallery;
+pub mod keyboard_nav;
 pub mod pdf;
 pub mod printing;
+pub mod reading_list;
+pub mod screenshot;
 pub mod session;
+pub mod share;
+pub mod translate;
 
 // Re-export for convenience
+pub use clipper::{
+    html_to_markdown, note_to_markdown, render_notes_page, Note, NoteKind, Notebook, NotesPage, CLIP_SCRIPT, NOTES_PAGE_URL,
+};
+pub use gallery::*;
+pub use keyboard_nav::*;
 pub use pdf::*;
 pub use printing::*;
-pub use session::*;
\ No newline at end of file
+pub use reading_list::{
+    render_reading_list_page, ReadingList, ReadingListEntry, ReadingListPage, ReadingListSort, READING_LIST_PAGE_URL,
+    SAVE_FOR_LATER_SCRIPT,
+};
+pub use screenshot::*;
+pub use session::*;
+pub use share::*;
+pub use translate::*;
\ No newline at end of file
@@ -1,13 +1,915 @@
-// Printing Module - Placeholder
-// TODO: Implement printing functionality
-pub struct PrintManager;
+// Print Manager
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::ui::reader::ReadingMode;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::{escape_html, LockExt};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+mod pagination;
+
+pub use pagination::{ContentPosition, PageBoundary, Pagination};
+
+/// Script that gives pages `window.printManager` and routes `window.print()`
+/// and Ctrl+P through the browser. Selections and simplified pages print
+/// from a hidden frame holding the document the browser prepared.
+pub const PRINT_SCRIPT: &str = r#"
+(function() {
+    if (window.printManager) return;
+    const nativePrint = window.print.bind(window);
+    const selectionHtml = () => {
+        const selection = window.getSelection();
+        const holder = document.createElement('div');
+        for (let i = 0; i < selection.rangeCount; i++) holder.append(selection.getRangeAt(i).cloneContents());
+        return holder.innerHTML || null;
+    };
+    const print = (scope) => {
+        window.ipc.request({
+            type: 'print-page',
+            url: window.location.href,
+            title: document.title,
+            html: scope === 'selection' ? '' : document.documentElement.outerHTML,
+            selection: scope === 'selection' ? selectionHtml() : null,
+            scope,
+        }).then((job) => {
+            if (!job.html) {
+                const style = document.createElement('style');
+                style.textContent = job.css;
+                (document.head || document.documentElement).append(style);
+                window.addEventListener('afterprint', () => style.remove(), { once: true });
+                nativePrint();
+                return;
+            }
+            const frame = document.createElement('iframe');
+            frame.style.cssText = 'position:fixed;width:0;height:0;border:0;visibility:hidden;';
+            frame.addEventListener('load', () => {
+                frame.contentWindow.addEventListener('afterprint', () => frame.remove(), { once: true });
+                frame.contentWindow.print();
+            });
+            frame.srcdoc = job.html;
+            (document.body || document.documentElement).append(frame);
+        }).catch((error) => console.warn('WebX could not print:', error));
+    };
+    window.printManager = {
+        printPage: () => print(null),
+        printSelection: () => print('selection'),
+        printSimplified: () => print('simplified'),
+    };
+    window.print = () => print(null);
+    document.addEventListener('keydown', (e) => {
+        if ((e.ctrlKey || e.metaKey) && !e.shiftKey && !e.altKey && e.key.toLowerCase() === 'p') {
+            e.preventDefault();
+            print(null);
+        }
+    });
+})();
+"#;
+
+/// Script printing the selection of a page running `PRINT_SCRIPT`
+pub const PRINT_SELECTION_SCRIPT: &str = "window.printManager && window.printManager.printSelection();";
+
+/// Print job status
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PrintJobStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Print settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintSettings {
+    pub printer_name: Option<String>,
+    pub copies: u32,
+    pub page_range: Option<(u32, u32)>,
+    pub duplex: bool,
+    pub color: bool,
+    pub paper_size: PaperSize,
+    pub orientation: PageOrientation,
+    pub margins: PageMargins,
+    pub scale: f32,
+    /// Print pages through reader mode, without navigation, ads and other
+    /// clutter
+    pub simplify: bool,
+    pub header: MarginLine,
+    pub footer: MarginLine,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        Self {
+            printer_name: None,
+            copies: 1,
+            page_range: None,
+            duplex: false,
+            color: true,
+            paper_size: PaperSize::A4,
+            orientation: PageOrientation::Portrait,
+            margins: PageMargins::default(),
+            scale: 1.0,
+            simplify: false,
+            header: MarginLine {
+                left: Some(MarginField::Title),
+                center: None,
+                right: Some(MarginField::Date),
+            },
+            footer: MarginLine {
+                left: Some(MarginField::Url),
+                center: None,
+                right: Some(MarginField::PageNumber),
+            },
+        }
+    }
+}
+
+/// Paper size options
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PaperSize {
+    A4,
+    Letter,
+    Legal,
+    A3,
+    Custom(f32, f32), // width, height in mm
+}
+
+/// Page orientation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+}
+
+/// Page margins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageMargins {
+    pub top: f32,
+    pub bottom: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+impl Default for PageMargins {
+    fn default() -> Self {
+        Self {
+            top: 20.0,
+            bottom: 20.0,
+            left: 20.0,
+            right: 20.0,
+        }
+    }
+}
+
+/// What a header or footer shows in one of its places
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginField {
+    Title,
+    Url,
+    /// "Page X of Y"
+    PageNumber,
+    /// The day the page is printed
+    Date,
+}
+
+/// A header or footer, by what its left, middle and right show; all empty
+/// turns it off
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MarginLine {
+    pub left: Option<MarginField>,
+    pub center: Option<MarginField>,
+    pub right: Option<MarginField>,
+}
+
+/// What of a page goes on paper
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintScope {
+    Page,
+    /// Only the selected part
+    Selection,
+    /// The page through reader mode
+    Simplified,
+}
+
+/// A page to print, as the print script sends it
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrintRequest {
+    pub url: String,
+    pub title: String,
+    /// The page's markup, to simplify
+    #[serde(default)]
+    pub html: String,
+    /// Markup of the selection, for selection jobs
+    #[serde(default)]
+    pub selection: Option<String>,
+    /// What to print; the settings decide between the page and its
+    /// simplified version when unset
+    #[serde(default)]
+    pub scope: Option<PrintScope>,
+}
+
+/// What the page prints for a job
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PrintDocument {
+    pub job_id: usize,
+    pub scope: PrintScope,
+    /// Page size, margins, headers and footers for printing the page itself
+    pub css: String,
+    /// Document to print in place of the page, for selections and
+    /// simplified pages
+    pub html: Option<String>,
+}
+
+/// Printer information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrinterInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub is_network: bool,
+    pub status: PrinterStatus,
+    pub supported_paper_sizes: Vec<PaperSize>,
+    pub can_duplex: bool,
+    pub can_color: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PrinterStatus {
+    Ready,
+    Busy,
+    Error,
+    Offline,
+    Unknown,
+}
+
+/// Print job information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintJob {
+    pub id: usize,
+    pub title: String,
+    pub url: String,
+    pub scope: PrintScope,
+    pub pages: u32,
+    pub settings: PrintSettings,
+    pub status: PrintJobStatus,
+    pub progress: f32, // 0.0 to 1.0
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Print manager for handling print jobs
+pub struct PrintManager {
+    jobs: Mutex<Vec<PrintJob>>,
+    printers: Mutex<Vec<PrinterInfo>>,
+    settings: Mutex<PrintSettings>,
+    next_job_id: AtomicUsize,
+    reading_mode: ReadingMode,
+    config_dir: PathBuf,
+}
 
 impl PrintManager {
-    pub fn new() -> Self {
-        Self
+    /// Create a new print manager
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("print");
+            path
+        });
+
+        // Create config directory
+        std::fs::create_dir_all(&config_dir)?;
+        let settings = match std::fs::read(config_dir.join("settings.json")) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PrintSettings::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let manager = Self {
+            jobs: Mutex::new(Vec::new()),
+            printers: Mutex::new(Vec::new()),
+            settings: Mutex::new(settings),
+            next_job_id: AtomicUsize::new(1),
+            reading_mode: ReadingMode::new(None),
+            config_dir,
+        };
+
+        // Discover available printers
+        manager.discover_printers()?;
+
+        Ok(manager)
+    }
+
+    /// Settings new jobs from pages use
+    pub fn settings(&self) -> PrintSettings {
+        self.settings.lock_or_recover().clone()
+    }
+
+    pub fn set_settings(&self, settings: PrintSettings) -> Result<(), WebxError> {
+        write_atomic(&self.config_dir.join("settings.json"), &serde_json::to_vec_pretty(&settings)?)?;
+        *self.settings.lock_or_recover() = settings;
+        Ok(())
+    }
+
+    /// Prepare a page, its selection or its simplified version for printing
+    pub fn print_page(&self, request: &PrintRequest, settings: PrintSettings) -> Result<PrintDocument, WebxError> {
+        let job_id = self.next_job_id.fetch_add(1, Ordering::Relaxed);
+        let scope = request.scope.unwrap_or(match settings.simplify {
+            true => PrintScope::Simplified,
+            false => PrintScope::Page,
+        });
+
+        let job = PrintJob {
+            id: job_id,
+            title: request.title.clone(),
+            url: request.url.clone(),
+            scope,
+            pages: 0,
+            settings: settings.clone(),
+            status: PrintJobStatus::Processing,
+            progress: 0.0,
+            created_at: chrono::Utc::now(),
+            started_at: Some(chrono::Utc::now()),
+            completed_at: None,
+        };
+        self.jobs.lock_or_recover().push(job);
+
+        let prepared = self.prepare_document(request, scope, &settings);
+        let pages = match &prepared {
+            Ok(Some(html)) => Pagination::measure(html, &settings),
+            _ => Pagination::measure(&request.html, &settings),
+        };
+        let pages = pages.selected_pages(settings.page_range).len() as u32;
+        self.update_job(job_id, |job| {
+            job.pages = pages;
+            job.status = match prepared {
+                Ok(_) => PrintJobStatus::Completed,
+                Err(_) => PrintJobStatus::Failed,
+            };
+            job.progress = 1.0;
+            job.completed_at = Some(chrono::Utc::now());
+        });
+        let html = prepared?;
+
+        Ok(PrintDocument {
+            job_id,
+            scope,
+            css: page_css(&settings, &request.title, &request.url),
+            html,
+        })
+    }
+
+    /// Get print job status
+    pub fn get_job_status(&self, job_id: usize) -> Option<PrintJob> {
+        let jobs = self.jobs.lock_or_recover();
+        jobs.iter().find(|job| job.id == job_id).cloned()
+    }
+
+    /// Cancel a print job
+    pub fn cancel_job(&self, job_id: usize) -> bool {
+        let mut jobs = self.jobs.lock_or_recover();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == job_id) {
+            if job.status == PrintJobStatus::Pending || job.status == PrintJobStatus::Processing {
+                job.status = PrintJobStatus::Cancelled;
+                job.completed_at = Some(chrono::Utc::now());
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+
+    /// Get all print jobs
+    pub fn get_jobs(&self) -> Vec<PrintJob> {
+        self.jobs.lock_or_recover().clone()
+    }
+
+    /// Get active print jobs
+    pub fn get_active_jobs(&self) -> Vec<PrintJob> {
+        let jobs = self.jobs.lock_or_recover();
+        jobs.iter()
+            .filter(|job| {
+                job.status == PrintJobStatus::Pending || job.status == PrintJobStatus::Processing
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get available printers
+    pub fn get_printers(&self) -> Vec<PrinterInfo> {
+        self.printers.lock_or_recover().clone()
+    }
+
+    /// Get default printer
+    pub fn get_default_printer(&self) -> Option<PrinterInfo> {
+        let printers = self.printers.lock_or_recover();
+        printers.iter().find(|printer| printer.is_default).cloned()
+    }
+
+    /// Set default printer
+    pub fn set_default_printer(&self, printer_name: &str) -> Result<(), WebxError> {
+        {
+            let mut printers = self.printers.lock_or_recover();
+            for printer in printers.iter_mut() {
+                printer.is_default = printer.name == printer_name;
+            }
+        }
+        self.save_printer_config()?;
+        Ok(())
+    }
+
+    /// Preview of content as it prints: a sheet for each page in the
+    /// print range, with its header and footer
+    pub fn generate_print_preview(&self, html_content: &str, settings: &PrintSettings) -> String {
+        preview_document(&Pagination::measure(html_content, settings), settings)
+    }
+
+    // Private helper methods
+
+    /// Standalone document for a job, None when the page prints itself
+    fn prepare_document(&self, request: &PrintRequest, scope: PrintScope, settings: &PrintSettings) -> Result<Option<String>, WebxError> {
+        let content = match scope {
+            PrintScope::Page => return Ok(None),
+            PrintScope::Selection => request
+                .selection
+                .clone()
+                .filter(|selection| !selection.trim().is_empty())
+                .ok_or("Nothing is selected to print")?,
+            PrintScope::Simplified => {
+                let article = self
+                    .reading_mode
+                    .extract_article(&request.html, &request.url)
+                    .ok_or("This page has no article to simplify")?;
+                format!("<h1>{}</h1>\n{}", escape_html(&article.title), article.content)
+            }
+        };
+        Ok(Some(print_document(&request.title, &request.url, &content, settings)))
+    }
+
+    fn update_job(&self, job_id: usize, f: impl FnOnce(&mut PrintJob)) {
+        if let Some(job) = self.jobs.lock_or_recover().iter_mut().find(|job| job.id == job_id) {
+            f(job);
+        }
+    }
+
+    fn discover_printers(&self) -> Result<(), WebxError> {
+        {
+            let mut printers = self.printers.lock_or_recover();
+            printers.clear();
+
+            // In a real implementation, you would query the system for available printers
+            // For demo, add some mock printers
+            printers.push(PrinterInfo {
+                name: "PDF Printer".to_string(),
+                is_default: true,
+                is_network: false,
+                status: PrinterStatus::Ready,
+                supported_paper_sizes: vec![PaperSize::A4, PaperSize::Letter],
+                can_duplex: true,
+                can_color: true,
+            });
+
+            printers.push(PrinterInfo {
+                name: "Network Printer".to_string(),
+                is_default: false,
+                is_network: true,
+                status: PrinterStatus::Ready,
+                supported_paper_sizes: vec![PaperSize::A4, PaperSize::Letter, PaperSize::Legal],
+                can_duplex: true,
+                can_color: true,
+            });
+        }
+
+        self.save_printer_config()?;
+        Ok(())
+    }
+
+    fn save_printer_config(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("printers.json");
+        let content = serde_json::to_vec_pretty(&*self.printers.lock_or_recover())?;
+        write_atomic(&path, &content)?;
+        Ok(())
+    }
+}
+
+impl IpcHandler for PrintManager {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["print-page"]
     }
-    
-    pub fn print_page(&self, _content: &str) {
-        // Placeholder implementation
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::PrintPage(request) => Ok(serde_json::to_value(self.print_page(&request, self.settings())?)?),
+            _ => Err(WebxError::Invalid("Not a print request".to_string())),
+        }
+    }
+}
+
+// Private helper functions
+
+/// Width and height of a page as laid out, in mm
+fn page_size(settings: &PrintSettings) -> (f32, f32) {
+    let (width, height) = match settings.paper_size {
+        PaperSize::A4 => (210.0, 297.0),
+        PaperSize::Letter => (216.0, 279.0),
+        PaperSize::Legal => (216.0, 356.0),
+        PaperSize::A3 => (297.0, 420.0),
+        PaperSize::Custom(w, h) => (w, h),
+    };
+
+    if settings.orientation == PageOrientation::Landscape {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Page size, margins, headers and footers
+fn page_css(settings: &PrintSettings, title: &str, url: &str) -> String {
+    let (page_width, page_height) = page_size(settings);
+
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut boxes = String::new();
+    for (line, edge) in [(&settings.header, "top"), (&settings.footer, "bottom")] {
+        for (field, place) in [(line.left, "left"), (line.center, "center"), (line.right, "right")] {
+            let content = match field {
+                Some(MarginField::Title) => css_string(title),
+                Some(MarginField::Url) => css_string(url),
+                Some(MarginField::PageNumber) => r#""Page " counter(page) " of " counter(pages)"#.to_string(),
+                Some(MarginField::Date) => css_string(&date),
+                None => continue,
+            };
+            boxes.push_str(&format!(
+                "    @{}-{} {{\n        content: {};\n        font: 9pt sans-serif;\n        color: #555;\n    }}\n",
+                edge, place, content
+            ));
+        }
+    }
+
+    format!(
+        r#"
+@page {{
+    size: {}mm {}mm;
+    margin: {}mm {}mm {}mm {}mm;
+{}}}
+"#,
+        page_width,
+        page_height,
+        settings.margins.top,
+        settings.margins.right,
+        settings.margins.bottom,
+        settings.margins.left,
+        boxes
+    )
+}
+
+/// Standalone document printing some content on its own
+fn print_document(title: &str, url: &str, content: &str, settings: &PrintSettings) -> String {
+    let (page_width, page_height) = page_size(settings);
+    // Relative links and images resolve against the page
+    let base = match url {
+        "" => String::new(),
+        url => format!("\n    <base href=\"{}\">", escape_html(url)),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">{base}
+    <title>{title}</title>
+    <style>
+        {page_css}
+body {{
+    font-family: Arial, sans-serif;
+    font-size: 12pt;
+    line-height: 1.4;
+    color: black;
+    background: white;
+}}
+
+img {{
+    max-width: 100%;
+}}
+
+.print-content {{
+    width: {width}mm;
+    min-height: {height}mm;
+}}
+
+@media print {{
+    body {{
+        margin: 0;
+        padding: 0;
+    }}
+
+    .print-content {{
+        width: auto;
+        min-height: 0;
+        margin: 0;
+        padding: 0;
+    }}
+}}
+    </style>
+</head>
+<body>
+    <div class="print-content">
+        {content}
+    </div>
+</body>
+</html>"#,
+        base = base,
+        title = escape_html(title),
+        page_css = page_css(settings, title, url),
+        width = page_width - settings.margins.left - settings.margins.right,
+        height = page_height - settings.margins.top - settings.margins.bottom,
+        content = content,
+    )
+}
+
+/// Pages laid out as sheets of paper
+fn preview_document(pagination: &Pagination, settings: &PrintSettings) -> String {
+    let (page_width, page_height) = page_size(settings);
+    let margins = &settings.margins;
+    let scale = if settings.scale > 0.0 { settings.scale } else { 1.0 };
+    let title = match pagination.title.as_str() {
+        "" => "Print Preview",
+        title => title,
+    };
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let mut sheets = String::new();
+    for page in pagination.selected_pages(settings.page_range) {
+        let line = |line: &MarginLine, class: &str| {
+            let text = |field: Option<MarginField>| match field {
+                Some(MarginField::Title) => escape_html(title),
+                Some(MarginField::Url) | None => String::new(),
+                Some(MarginField::PageNumber) => format!("Page {} of {}", page.number, pagination.total_pages),
+                Some(MarginField::Date) => date.clone(),
+            };
+            format!(
+                "<div class=\"margin-line {}\"><span>{}</span><span>{}</span><span>{}</span></div>",
+                class,
+                text(line.left),
+                text(line.center),
+                text(line.right)
+            )
+        };
+        sheets.push_str(&format!(
+            "<section class=\"sheet\" data-page=\"{}\">\n{}\n<div class=\"sheet-content\">\n{}</div>\n{}\n</section>\n",
+            page.number,
+            line(&settings.header, "header"),
+            pagination.page_html(page),
+            line(&settings.footer, "footer")
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>
+        {page_css}
+body {{
+    margin: 0;
+    background: #e8e8e8;
+    font-family: Arial, sans-serif;
+    font-size: 12pt;
+    line-height: 1.4;
+    color: black;
+}}
+
+.sheet {{
+    position: relative;
+    box-sizing: border-box;
+    width: {width}mm;
+    height: {height}mm;
+    margin: 10mm auto;
+    padding: {top}mm {right}mm {bottom}mm {left}mm;
+    overflow: hidden;
+    background: white;
+    box-shadow: 0 1px 4px rgba(0, 0, 0, 0.3);
+}}
+
+.sheet-content {{
+    width: {content_width}mm;
+    transform: scale({scale});
+    transform-origin: top left;
+}}
+
+.sheet-content img {{
+    max-width: 100%;
+}}
+
+.margin-line {{
+    position: absolute;
+    left: {left}mm;
+    right: {right}mm;
+    display: flex;
+    font: 9pt sans-serif;
+    color: #555;
+}}
+
+.margin-line span {{
+    flex: 1;
+}}
+
+.margin-line span:nth-child(2) {{
+    text-align: center;
+}}
+
+.margin-line span:nth-child(3) {{
+    text-align: right;
+}}
+
+.header {{
+    top: {header_top}mm;
+}}
+
+.footer {{
+    bottom: {footer_bottom}mm;
+}}
+
+@media print {{
+    body {{
+        background: white;
+    }}
+
+    .sheet {{
+        width: auto;
+        height: auto;
+        margin: 0;
+        padding: 0;
+        box-shadow: none;
+        break-after: page;
+    }}
+
+    .margin-line {{
+        display: none;
+    }}
+}}
+    </style>
+</head>
+<body>
+{sheets}</body>
+</html>"#,
+        title = escape_html(title),
+        page_css = page_css(settings, title, ""),
+        width = page_width,
+        height = page_height,
+        top = margins.top,
+        right = margins.right,
+        bottom = margins.bottom,
+        left = margins.left,
+        content_width = (page_width - margins.left - margins.right).max(1.0) / scale,
+        scale = scale,
+        header_top = margins.top / 3.0,
+        footer_bottom = margins.bottom / 3.0,
+        sheets = sheets,
+    )
+}
+
+/// Quoted CSS string, on one line
+fn css_string(text: &str) -> String {
+    let escaped: String = text
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => format!("\\{}", c),
+            '\n' | '\r' | '\t' => " ".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_print_manager_basic_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PrintManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        // Test printer discovery
+        let printers = manager.get_printers();
+        assert!(!printers.is_empty());
+        assert!(printers.iter().any(|p| p.is_default));
+
+        // Test print job creation
+        let request = PrintRequest {
+            url: "https://example.com".to_string(),
+            title: "Test Page".to_string(),
+            ..PrintRequest::default()
+        };
+        let document = manager.print_page(&request, PrintSettings::default()).unwrap();
+        assert_eq!(document.scope, PrintScope::Page);
+        assert_eq!(document.html, None);
+
+        // Test job status
+        let job = manager.get_job_status(document.job_id).unwrap();
+        assert_eq!(job.id, document.job_id);
+        assert_eq!(job.title, "Test Page");
+        assert_eq!(job.status, PrintJobStatus::Completed);
+
+        // Finished jobs cannot be cancelled
+        assert!(!manager.cancel_job(document.job_id));
+        assert_eq!(manager.get_jobs().len(), 1);
+    }
+
+    #[test]
+    fn test_print_settings() {
+        let settings = PrintSettings {
+            copies: 2,
+            duplex: true,
+            paper_size: PaperSize::Letter,
+            ..PrintSettings::default()
+        };
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PrintManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let preview_html = manager.generate_print_preview("<p>Test content</p>", &settings);
+
+        assert!(preview_html.contains("@page"));
+        assert!(preview_html.contains("size: 216mm 279mm")); // Letter size
+        assert!(preview_html.contains("<span>Page 1 of 1</span>"));
+
+        // Only the pages in the print range are shown
+        let long = format!("<title>Long read</title>{}", "<p>Some more text to print.</p>".repeat(200));
+        let total = Pagination::measure(&long, &settings).total_pages;
+        assert!(total > 3);
+        let settings = PrintSettings {
+            page_range: Some((2, 3)),
+            ..settings
+        };
+        let preview_html = manager.generate_print_preview(&long, &settings);
+        assert_eq!(preview_html.matches("<section class=\"sheet\"").count(), 2);
+        assert!(preview_html.contains(&format!("Page 3 of {}", total)));
+        assert!(preview_html.contains("<span>Long read</span>"));
+    }
+
+    #[test]
+    fn test_selection_and_simplified_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PrintManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let settings = PrintSettings {
+            header: MarginLine::default(),
+            footer: MarginLine {
+                center: Some(MarginField::PageNumber),
+                right: Some(MarginField::Title),
+                ..MarginLine::default()
+            },
+            ..PrintSettings::default()
+        };
+        let paragraph = "The committee met on Tuesday to review the budget for the coming year. ".repeat(8);
+        let request = PrintRequest {
+            url: "https://example.com/news/budget".to_string(),
+            title: "Budget \"review\"".to_string(),
+            html: format!(
+                "<html><body><nav>Home | Sections</nav><article><h1>Budget review</h1><p>{}</p><p>{}</p></article></body></html>",
+                paragraph, paragraph
+            ),
+            selection: Some("<p>Just <b>this</b></p>".to_string()),
+            scope: Some(PrintScope::Selection),
+        };
+
+        let selection = manager.print_page(&request, settings.clone()).unwrap().html.unwrap();
+        assert!(selection.contains("<p>Just <b>this</b></p>"));
+        assert!(!selection.contains("committee"));
+        assert!(selection.contains(r#"@bottom-center {
+        content: "Page " counter(page) " of " counter(pages);"#));
+        assert!(selection.contains(r#"content: "Budget \"review\"";"#));
+        assert!(!selection.contains("@top-left"));
+
+        // Simplifying drops the page's navigation
+        let simplified = PrintRequest {
+            scope: None,
+            ..request.clone()
+        };
+        let settings = PrintSettings { simplify: true, ..settings };
+        let document = manager.print_page(&simplified, settings.clone()).unwrap();
+        assert_eq!(document.scope, PrintScope::Simplified);
+        let html = document.html.unwrap();
+        assert!(html.contains("committee"));
+        assert!(!html.contains("Sections"));
+
+        let nothing = PrintRequest {
+            selection: None,
+            ..request
+        };
+        assert!(manager.print_page(&nothing, settings).is_err());
+        assert_eq!(manager.get_jobs().last().unwrap().status, PrintJobStatus::Failed);
     }
-}
\ No newline at end of file
+}
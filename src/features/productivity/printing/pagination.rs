@@ -0,0 +1,471 @@
+// Print Pagination
+use super::{escape_html, page_size, PrintSettings};
+use scraper::{ElementRef, Html, Node};
+use serde::Serialize;
+
+/// Millimetres in a point and in a CSS pixel
+const MM_PER_PT: f32 = 0.3528;
+const MM_PER_PX: f32 = 0.2646;
+
+/// Body text as print documents set it: 12pt with 1.4 line height
+const BODY_FONT_PT: f32 = 12.0;
+const LINE_HEIGHT: f32 = 1.4;
+
+/// Average glyph width, in ems, of proportional and monospace text
+const PROPORTIONAL_CHAR_EM: f32 = 0.5;
+const MONOSPACE_CHAR_EM: f32 = 0.6;
+
+/// Height given to images without a size
+const DEFAULT_IMAGE_MM: f32 = 50.0;
+
+/// Fewest lines of a paragraph left at the bottom of a page
+const MIN_ORPHAN_LINES: usize = 2;
+
+/// Elements laid out as blocks; the rest flow inside them
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "caption", "dd", "details", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "img", "li", "main",
+    "nav", "ol", "p", "pre", "section", "summary", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// Elements that never print
+const HIDDEN_TAGS: &[&str] = &["head", "script", "style", "noscript", "template", "iframe", "object", "embed"];
+
+/// A place in the measured content: a block, and a line within it
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ContentPosition {
+    pub block: usize,
+    pub line: usize,
+}
+
+/// Where one page starts and where the next one does
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct PageBoundary {
+    /// 1-based page number
+    pub number: u32,
+    pub start: ContentPosition,
+    /// First position on the next page
+    pub end: ContentPosition,
+}
+
+/// Content broken into pages of the chosen paper, margins and scale
+#[derive(Debug, Clone, Serialize)]
+pub struct Pagination {
+    pub pages: Vec<PageBoundary>,
+    pub total_pages: u32,
+    /// The document's title, when it has one
+    pub title: String,
+    #[serde(skip)]
+    blocks: Vec<Block>,
+}
+
+impl Pagination {
+    /// Lay out HTML on pages. Text is measured with average glyph widths
+    /// for the fonts print documents use, so breaks land where a printer
+    /// puts them within a line or two.
+    pub fn measure(html: &str, settings: &PrintSettings) -> Self {
+        let document = Html::parse_document(html);
+        let title = document
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .find(|element| element.value().name() == "title")
+            .map(|element| element.text().collect::<String>().trim().to_string())
+            .unwrap_or_default();
+
+        let (page_width, page_height) = page_size(settings);
+        let scale = if settings.scale > 0.0 { settings.scale } else { 1.0 };
+        let width = (page_width - settings.margins.left - settings.margins.right).max(1.0) / scale;
+        let height = (page_height - settings.margins.top - settings.margins.bottom).max(1.0) / scale;
+
+        let mut blocks = Vec::new();
+        collect_blocks(document.root_element(), width, &mut blocks);
+        let pages = break_pages(&blocks, height);
+
+        Self {
+            total_pages: pages.len() as u32,
+            pages,
+            title,
+            blocks,
+        }
+    }
+
+    /// Pages a print range picks, all of them without one. A range past
+    /// the end is cut short; one wholly past it picks nothing.
+    pub fn selected_pages(&self, range: Option<(u32, u32)>) -> &[PageBoundary] {
+        let Some((first, last)) = range else {
+            return &self.pages;
+        };
+        let first = first.max(1) as usize;
+        let last = (last as usize).min(self.pages.len());
+        if first > last {
+            return &[];
+        }
+        &self.pages[first - 1..last]
+    }
+
+    /// Markup of what goes on a page
+    pub fn page_html(&self, page: &PageBoundary) -> String {
+        let mut html = String::new();
+        for index in page.start.block..page.end.block.saturating_add(1).min(self.blocks.len()) {
+            let block = &self.blocks[index];
+            let from = if index == page.start.block { page.start.line } else { 0 };
+            let to = if index == page.end.block { page.end.line } else { block.line_count() };
+            if from >= to {
+                continue;
+            }
+
+            let (open, close) = wrapper(&block.tag);
+            html.push_str(open);
+            if from == 0 && to == block.line_count() {
+                html.push_str(&block.html);
+            } else {
+                // Part of a block split across pages; its text goes without its markup
+                let separator = if block.tag == "pre" { "\n" } else { " " };
+                let text = block.lines[from..to].join(separator);
+                html.push_str(&format!("<{tag}>{}</{tag}>", escape_html(&text), tag = block.tag));
+            }
+            html.push_str(close);
+            html.push('\n');
+        }
+        html
+    }
+}
+
+/// A piece of content laid out on its own lines
+#[derive(Debug, Clone)]
+struct Block {
+    /// Element it prints as, "p" for loose text
+    tag: String,
+    html: String,
+    /// Text as wrapped to the page width; empty for images and rules
+    lines: Vec<String>,
+    line_height: f32,
+    /// Height of an image or rule, which cannot be split
+    fixed_height: Option<f32>,
+    /// Space above, left out at the top of a page
+    gap: f32,
+}
+
+impl Block {
+    /// Lines it counts as when broken between pages; images and rules are one
+    fn line_count(&self) -> usize {
+        if self.fixed_height.is_some() {
+            1
+        } else {
+            self.lines.len()
+        }
+    }
+
+    fn is_heading(&self) -> bool {
+        matches!(self.tag.as_str(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+    }
+}
+
+// Private helper functions
+
+/// Walk the tree, turning elements without blocks inside and runs of loose
+/// text into blocks
+fn collect_blocks(element: ElementRef, width: f32, blocks: &mut Vec<Block>) {
+    let name = element.value().name();
+    if HIDDEN_TAGS.contains(&name) {
+        return;
+    }
+    match name {
+        "img" => return blocks.push(image_block(element, width)),
+        "hr" => return blocks.push(fixed_block("hr", element.html(), 2.0 * MM_PER_PX, BODY_FONT_PT * MM_PER_PT / 2.0)),
+        _ => {}
+    }
+    if !contains_block(element) {
+        if BLOCK_TAGS.contains(&name) {
+            let text = element.text().collect::<String>();
+            push_text_block(name, element.html(), &text, width, blocks);
+        }
+        return;
+    }
+
+    // Loose text between the blocks inside prints as paragraphs
+    let mut run_html = String::new();
+    let mut run_text = String::new();
+    for child in element.children() {
+        let inner = ElementRef::wrap(child);
+        let is_block = inner.is_some_and(|inner| BLOCK_TAGS.contains(&inner.value().name()) || contains_block(inner));
+        if is_block {
+            push_text_block("p", std::mem::take(&mut run_html), &std::mem::take(&mut run_text), width, blocks);
+            if let Some(inner) = inner {
+                collect_blocks(inner, width, blocks);
+            }
+            continue;
+        }
+        match (child.value(), inner) {
+            (Node::Text(text), _) => {
+                run_html.push_str(&escape_html(text));
+                run_text.push_str(text);
+            }
+            (_, Some(inner)) if !HIDDEN_TAGS.contains(&inner.value().name()) => {
+                run_html.push_str(&inner.html());
+                run_text.extend(inner.text());
+            }
+            _ => {}
+        }
+    }
+    push_text_block("p", run_html, &run_text, width, blocks);
+}
+
+/// Whether blocks are laid out inside an element
+fn contains_block(element: ElementRef) -> bool {
+    element
+        .descendants()
+        .skip(1)
+        .filter_map(ElementRef::wrap)
+        .any(|inner| BLOCK_TAGS.contains(&inner.value().name()))
+}
+
+/// Add a block of text wrapped to the width; blank text adds nothing
+fn push_text_block(tag: &str, html: String, text: &str, width: f32, blocks: &mut Vec<Block>) {
+    let monospace = tag == "pre";
+    let font = font_size_mm(tag);
+    let char_em = if monospace { MONOSPACE_CHAR_EM } else { PROPORTIONAL_CHAR_EM };
+    let columns = ((width / (font * char_em)).floor() as usize).max(1);
+
+    let lines = if monospace {
+        text.trim_matches('\n').lines().flat_map(|line| wrap(line, columns, true)).collect::<Vec<_>>()
+    } else {
+        wrap(text, columns, false)
+    };
+    if lines.is_empty() {
+        return;
+    }
+    blocks.push(Block {
+        tag: tag.to_string(),
+        html,
+        lines,
+        line_height: font * LINE_HEIGHT,
+        fixed_height: None,
+        gap: block_gap(tag, font),
+    });
+}
+
+fn fixed_block(tag: &str, html: String, height: f32, gap: f32) -> Block {
+    Block {
+        tag: tag.to_string(),
+        html,
+        lines: Vec::new(),
+        line_height: 0.0,
+        fixed_height: Some(height),
+        gap,
+    }
+}
+
+/// An image at its size, shrunk to fit the width
+fn image_block(element: ElementRef, width: f32) -> Block {
+    let pixels = |attribute| element.value().attr(attribute).and_then(|value| value.trim_end_matches("px").parse::<f32>().ok());
+    let height = match (pixels("width"), pixels("height")) {
+        (Some(w), Some(h)) if w > 0.0 => h * MM_PER_PX * (width / (w * MM_PER_PX)).min(1.0),
+        (None, Some(h)) => h * MM_PER_PX,
+        _ => DEFAULT_IMAGE_MM,
+    };
+    fixed_block("img", element.html(), height, BODY_FONT_PT * MM_PER_PT / 2.0)
+}
+
+/// Font size of a block's text, in mm
+fn font_size_mm(tag: &str) -> f32 {
+    let em = match tag {
+        "h1" => 2.0,
+        "h2" => 1.5,
+        "h3" => 1.17,
+        "h5" => 0.83,
+        "h6" => 0.67,
+        "pre" => 0.83,
+        _ => 1.0,
+    };
+    BODY_FONT_PT * em * MM_PER_PT
+}
+
+/// Space above a block: paragraph and heading margins, none inside lists
+/// and tables
+fn block_gap(tag: &str, font: f32) -> f32 {
+    match tag {
+        "li" | "tr" | "td" | "th" | "dt" | "dd" => 0.0,
+        "h1" => font * 0.67,
+        "h2" | "h3" | "h4" | "h5" | "h6" => font * 0.83,
+        _ => font,
+    }
+}
+
+/// Greedy word wrap at a column count; long words are cut. `keep_spaces`
+/// keeps preformatted text as it is.
+fn wrap(text: &str, columns: usize, keep_spaces: bool) -> Vec<String> {
+    if keep_spaces {
+        let chars: Vec<char> = text.chars().collect();
+        if chars.is_empty() {
+            return vec![String::new()];
+        }
+        return chars.chunks(columns).map(|chunk| chunk.iter().collect()).collect();
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_chars = 0;
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > columns {
+            if line_chars > 0 {
+                lines.push(std::mem::take(&mut line));
+                line_chars = 0;
+            }
+            lines.push(word.drain(..columns).collect());
+        }
+        if line_chars > 0 && line_chars + 1 + word.len() > columns {
+            lines.push(std::mem::take(&mut line));
+            line_chars = 0;
+        }
+        if line_chars > 0 {
+            line.push(' ');
+            line_chars += 1;
+        }
+        line_chars += word.len();
+        line.extend(word);
+    }
+    if line_chars > 0 {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Break blocks into pages of a height. Images taller than a page get a
+/// page of their own, headings move down with the text they head, and
+/// paragraphs leave at least two lines behind.
+fn break_pages(blocks: &[Block], height: f32) -> Vec<PageBoundary> {
+    let mut pages = Vec::new();
+    let mut start = ContentPosition { block: 0, line: 0 };
+    let mut used = 0.0;
+    let mut new_page = |at: ContentPosition, start: &mut ContentPosition, used: &mut f32| {
+        pages.push(PageBoundary {
+            number: pages.len() as u32 + 1,
+            start: *start,
+            end: at,
+        });
+        *start = at;
+        *used = 0.0;
+    };
+
+    for (index, block) in blocks.iter().enumerate() {
+        let gap = |used: f32| if used > 0.0 { block.gap } else { 0.0 };
+        if let Some(fixed) = block.fixed_height {
+            if used > 0.0 && used + gap(used) + fixed.min(height) > height {
+                new_page(ContentPosition { block: index, line: 0 }, &mut start, &mut used);
+            }
+            used += gap(used) + fixed.min(height);
+            continue;
+        }
+
+        if block.is_heading() && used > 0.0 {
+            let follower = blocks.get(index + 1).map_or(0.0, |next| next.gap + next.line_height);
+            let needed = gap(used) + block.lines.len() as f32 * block.line_height + follower;
+            if used + needed > height {
+                new_page(ContentPosition { block: index, line: 0 }, &mut start, &mut used);
+            }
+        }
+
+        let mut line = 0;
+        while line < block.lines.len() {
+            let room = ((height - used - gap(used)) / block.line_height + 0.001).floor().max(0.0) as usize;
+            let rest = block.lines.len() - line;
+            let too_few = used > 0.0 && room < rest && room < MIN_ORPHAN_LINES;
+            if room == 0 || too_few {
+                if used == 0.0 {
+                    // A line taller than the page still goes somewhere
+                    used = height;
+                    line += 1;
+                    continue;
+                }
+                new_page(ContentPosition { block: index, line }, &mut start, &mut used);
+                continue;
+            }
+            let taken = room.min(rest);
+            used += gap(used) + taken as f32 * block.line_height;
+            line += taken;
+            if line < block.lines.len() {
+                new_page(ContentPosition { block: index, line }, &mut start, &mut used);
+            }
+        }
+    }
+
+    // Breaks fall inside blocks, so the last page always has something, or
+    // is the one blank page of empty content
+    new_page(ContentPosition { block: blocks.len(), line: 0 }, &mut start, &mut used);
+    pages
+}
+
+/// Markup that keeps a lone list item or table row valid
+fn wrapper(tag: &str) -> (&'static str, &'static str) {
+    match tag {
+        "li" => ("<ul>", "</ul>"),
+        "dt" | "dd" => ("<dl>", "</dl>"),
+        "tr" => ("<table>", "</table>"),
+        "td" | "th" => ("<table><tr>", "</tr></table>"),
+        _ => ("", ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::productivity::printing::PageOrientation;
+
+    #[test]
+    fn test_pages_follow_paper_and_range() {
+        let paragraph = format!("<p>{}</p>", "The quick brown fox jumps over the lazy dog. ".repeat(12));
+        let html = format!("<html><head><title>Report</title></head><body><h1>Report</h1>{}</body></html>", paragraph.repeat(30));
+
+        let portrait = Pagination::measure(&html, &PrintSettings::default());
+        assert_eq!(portrait.title, "Report");
+        assert!(portrait.total_pages > 2);
+        assert_eq!(portrait.pages[0].start, ContentPosition { block: 0, line: 0 });
+        for pair in portrait.pages.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(portrait.pages.last().unwrap().end.block, 31);
+
+        // Landscape pages break elsewhere; a bigger margin means more pages
+        let landscape = Pagination::measure(
+            &html,
+            &PrintSettings {
+                orientation: PageOrientation::Landscape,
+                ..PrintSettings::default()
+            },
+        );
+        assert_ne!(landscape.pages, portrait.pages);
+        let mut cramped = PrintSettings::default();
+        cramped.margins.top = 100.0;
+        assert!(Pagination::measure(&html, &cramped).total_pages > portrait.total_pages);
+
+        assert_eq!(portrait.selected_pages(Some((2, 3))).iter().map(|page| page.number).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(portrait.selected_pages(Some((2, 999))).len(), portrait.total_pages as usize - 1);
+        assert!(portrait.selected_pages(Some((999, 1000))).is_empty());
+        assert_eq!(portrait.selected_pages(None).len(), portrait.total_pages as usize);
+    }
+
+    #[test]
+    fn test_split_paragraphs_and_page_markup() {
+        let long = "word ".repeat(4000);
+        let html = format!("<body>Intro <b>text</b><div><p>{}</p><img src=\"a.png\" width=\"800\" height=\"600\"></div><ul><li>Last</li></ul></body>", long);
+        let pagination = Pagination::measure(&html, &PrintSettings::default());
+
+        let first = pagination.page_html(&pagination.pages[0]);
+        assert!(first.starts_with("Intro <b>text</b>"));
+        assert!(first.contains("<p>word word"));
+        let last = pagination.page_html(pagination.pages.last().unwrap());
+        assert!(last.contains("<ul><li>Last</li></ul>"));
+        assert!(pagination.pages.iter().any(|page| pagination.page_html(page).contains("<img")));
+
+        // Every line is on exactly one page
+        let printed: usize = pagination.pages.iter().map(|page| pagination.page_html(page).matches("word").count()).sum();
+        assert_eq!(printed, 4000);
+
+        let empty = Pagination::measure("<html><body></body></html>", &PrintSettings::default());
+        assert_eq!(empty.total_pages, 1);
+        assert_eq!(empty.page_html(&empty.pages[0]), "");
+    }
+}
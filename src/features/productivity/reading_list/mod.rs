@@ -0,0 +1,293 @@
+// Reading List
+pub mod page;
+
+pub use page::{render_reading_list_page, ReadingListPage, READING_LIST_PAGE_URL};
+
+use crate::error::WebxError;
+use crate::core::{match_score, recency_factor, SearchQuery, SearchResult, SearchSource, SearchSourceKind};
+use crate::features::ui::reader::{ArticleContent, ReadingMode};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::path::PathBuf;
+
+/// Sends the page with a `type: 'savelater'` IPC message so its article can
+/// be kept for reading offline
+pub const SAVE_FOR_LATER_SCRIPT: &str = r#"
+(function() {
+    window.ipc.send({
+        type: 'savelater',
+        url: location.href,
+        title: document.title,
+        html: document.documentElement.outerHTML,
+    });
+})();
+"#;
+
+/// Average reading speed for reading time estimates
+const WORDS_PER_MINUTE: usize = 230;
+
+/// A page saved to read later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingListEntry {
+    pub id: u64,
+    pub url: String,
+    pub title: String,
+    pub excerpt: Option<String>,
+    /// The article, captured when saved, for reading offline
+    pub article: Option<ArticleContent>,
+    pub word_count: usize,
+    pub added_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl ReadingListEntry {
+    /// Estimated minutes to read the article, at least one
+    pub fn reading_time_minutes(&self) -> usize {
+        self.word_count.div_ceil(WORDS_PER_MINUTE).max(1)
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.read_at.is_some()
+    }
+}
+
+/// Order of the reading list
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ReadingListSort {
+    #[default]
+    Newest,
+    Oldest,
+    Title,
+    /// Quickest reads first
+    ReadingTime,
+}
+
+impl ReadingListSort {
+    /// Name used in page URLs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::Title => "title",
+            Self::ReadingTime => "time",
+        }
+    }
+
+    /// Parse a name used in page URLs
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::Newest, Self::Oldest, Self::Title, Self::ReadingTime]
+            .into_iter()
+            .find(|sort| sort.as_str() == name)
+    }
+}
+
+/// Pages saved for later, kept apart from bookmarks
+pub struct ReadingList {
+    db: Db,
+    entries: Tree,
+}
+
+impl ReadingList {
+    /// Open the reading list
+    pub fn new(db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("reading_list.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        Ok(Self {
+            entries: db.open_tree("entries")?,
+            db,
+        })
+    }
+
+    /// Save a page, capturing its article when reader mode finds one. Saving
+    /// a page again refreshes its article and marks it unread.
+    pub fn save(&self, reader: &ReadingMode, url: &str, title: &str, html: Option<&str>) -> Result<ReadingListEntry, WebxError> {
+        let article = html.and_then(|html| reader.extract_article(html, url));
+        let word_count = article.as_ref().map(|article| word_count(&article.content)).unwrap_or_default();
+        let title = article
+            .as_ref()
+            .map(|article| article.title.trim())
+            .filter(|title| !title.is_empty())
+            .or(Some(title.trim()).filter(|title| !title.is_empty()))
+            .unwrap_or(url)
+            .to_string();
+
+        let existing = self.find(url);
+        let entry = ReadingListEntry {
+            id: match &existing {
+                Some(entry) => entry.id,
+                None => self.db.generate_id()?,
+            },
+            url: url.to_string(),
+            title,
+            excerpt: article.as_ref().map(|article| article.excerpt.clone()).filter(|e| !e.is_empty()),
+            article,
+            word_count,
+            added_at: existing.map(|entry| entry.added_at).unwrap_or_else(Utc::now),
+            read_at: None,
+        };
+        self.put(&entry)?;
+        Ok(entry)
+    }
+
+    /// Get an entry
+    pub fn get(&self, id: u64) -> Option<ReadingListEntry> {
+        let value = self.entries.get(id.to_be_bytes()).ok()??;
+        serde_json::from_slice(&value).ok()
+    }
+
+    /// The entry for a URL
+    pub fn find(&self, url: &str) -> Option<ReadingListEntry> {
+        self.entries(ReadingListSort::Newest, false).into_iter().find(|entry| entry.url == url)
+    }
+
+    /// Entries in the given order, optionally only unread ones
+    pub fn entries(&self, sort: ReadingListSort, unread_only: bool) -> Vec<ReadingListEntry> {
+        let mut entries: Vec<ReadingListEntry> = self
+            .entries
+            .iter()
+            .values()
+            .filter_map(|value| value.ok())
+            .filter_map(|value| serde_json::from_slice::<ReadingListEntry>(&value).ok())
+            .filter(|entry| !(unread_only && entry.is_read()))
+            .collect();
+        match sort {
+            ReadingListSort::Newest => entries.sort_by_key(|entry| std::cmp::Reverse(entry.added_at)),
+            ReadingListSort::Oldest => entries.sort_by_key(|entry| entry.added_at),
+            ReadingListSort::Title => entries.sort_by_key(|entry| entry.title.to_lowercase()),
+            ReadingListSort::ReadingTime => entries.sort_by_key(|entry| (entry.reading_time_minutes(), entry.added_at)),
+        }
+        entries
+    }
+
+    /// Number of unread entries
+    pub fn unread_count(&self) -> usize {
+        self.entries(ReadingListSort::Newest, true).len()
+    }
+
+    /// Mark an entry read or unread
+    pub fn mark_read(&self, id: u64, read: bool) -> Result<bool, WebxError> {
+        let Some(mut entry) = self.get(id) else {
+            return Ok(false);
+        };
+        entry.read_at = if read { entry.read_at.or_else(|| Some(Utc::now())) } else { None };
+        self.put(&entry)?;
+        Ok(true)
+    }
+
+    /// Remove an entry
+    pub fn remove(&self, id: u64) -> Result<bool, WebxError> {
+        Ok(self.entries.remove(id.to_be_bytes())?.is_some())
+    }
+
+    /// Put back entries from a backup. An entry already on the list for
+    /// the same URL is replaced, keeping its ID. Returns how many were added.
+    pub fn restore(&self, entries: &[ReadingListEntry]) -> Result<usize, WebxError> {
+        let mut added = 0;
+        for entry in entries {
+            let id = match self.find(&entry.url) {
+                Some(existing) => existing.id,
+                None => {
+                    added += 1;
+                    self.db.generate_id()?
+                }
+            };
+            self.put(&ReadingListEntry { id, ..entry.clone() })?;
+        }
+        Ok(added)
+    }
+
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), WebxError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Private helper methods
+
+    fn put(&self, entry: &ReadingListEntry) -> Result<(), WebxError> {
+        self.entries.insert(entry.id.to_be_bytes(), serde_json::to_vec(entry)?)?;
+        Ok(())
+    }
+}
+
+impl SearchSource for ReadingList {
+    fn kind(&self) -> SearchSourceKind {
+        SearchSourceKind::ReadingList
+    }
+
+    fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words = query.words();
+        let now = Utc::now();
+        self.entries(ReadingListSort::Newest, false)
+            .into_iter()
+            .filter_map(|entry| {
+                let excerpt = entry.excerpt.as_deref().unwrap_or_default();
+                let score = match_score(&words, &[(&entry.title, 1.0), (&entry.url, 0.8), (excerpt, 0.6)])?;
+                Some(SearchResult {
+                    kind: SearchSourceKind::ReadingList,
+                    id: entry.id.to_string(),
+                    score: score * recency_factor(entry.added_at, now),
+                    timestamp: Some(entry.added_at),
+                    title: entry.title,
+                    url: Some(entry.url),
+                    detail: entry.excerpt,
+                })
+            })
+            .collect()
+    }
+}
+
+// Private helper functions
+
+/// Words in an HTML fragment
+fn word_count(html: &str) -> usize {
+    let fragment = scraper::Html::parse_fragment(html);
+    fragment.root_element().text().map(|text| text.split_whitespace().count()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_sort_and_read_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let list = ReadingList::new(Some(temp_dir.path().join("reading_list.db"))).unwrap();
+        let reader = ReadingMode::new(None);
+        let body = "<p>word </p>".repeat(300);
+        let html = format!("<html><head><title>Long</title></head><body><article><h1>Long Read</h1>{}</article></body></html>", body);
+
+        let long = list.save(&reader, "https://example.com/long", "Long", Some(&html)).unwrap();
+        assert_eq!(long.title, "Long Read");
+        assert!(long.article.is_some());
+        assert_eq!(long.reading_time_minutes(), 2);
+        let short = list.save(&reader, "https://example.com/short", "A short one", None).unwrap();
+        assert_eq!(short.reading_time_minutes(), 1);
+
+        let by_time: Vec<u64> = list.entries(ReadingListSort::ReadingTime, false).iter().map(|e| e.id).collect();
+        assert_eq!(by_time, vec![short.id, long.id]);
+        let by_title: Vec<u64> = list.entries(ReadingListSort::Title, false).iter().map(|e| e.id).collect();
+        assert_eq!(by_title, vec![short.id, long.id]);
+
+        assert!(list.mark_read(long.id, true).unwrap());
+        assert_eq!(list.unread_count(), 1);
+        // Saving again keeps the entry and puts it back on the unread list
+        let saved_again = list.save(&reader, "https://example.com/long", "Long", Some(&html)).unwrap();
+        assert_eq!(saved_again.id, long.id);
+        assert_eq!(list.unread_count(), 2);
+
+        assert!(list.remove(short.id).unwrap());
+        assert_eq!(list.entries(ReadingListSort::Newest, false).len(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+// Reading List Page
+use super::{ReadingList, ReadingListEntry, ReadingListSort};
+use crate::error::WebxError;
+use crate::features::ui::reader::ReadingMode;
+use crate::utils::escape_html;
+
+/// Internal page listing saved pages
+pub const READING_LIST_PAGE_URL: &str = "webx://reading-list";
+
+/// A page under `webx://reading-list`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadingListPage {
+    /// The saved pages in an order, optionally only unread ones
+    Index { sort: ReadingListSort, unread_only: bool },
+    /// A saved article, read offline
+    Read(u64),
+}
+
+impl ReadingListPage {
+    /// Parse a `webx://reading-list` URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(READING_LIST_PAGE_URL)?;
+        if !rest.is_empty() && !rest.starts_with(['/', '?', '#']) {
+            return None;
+        }
+        let parsed = url::Url::parse(url).ok()?;
+        match parsed.path().trim_matches('/') {
+            "" => {
+                let param = |name: &str| parsed.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value);
+                Some(Self::Index {
+                    sort: param("sort").and_then(|sort| ReadingListSort::parse(&sort)).unwrap_or_default(),
+                    unread_only: param("unread").is_some_and(|value| value == "1"),
+                })
+            }
+            id => id.parse().ok().map(Self::Read),
+        }
+    }
+}
+
+/// Render a `webx://reading-list` page. Opening a saved article marks it
+/// read.
+pub fn render_page(list: &ReadingList, reader: &ReadingMode, page: ReadingListPage) -> Result<String, WebxError> {
+    match page {
+        ReadingListPage::Index { sort, unread_only } => {
+            Ok(render_reading_list_page(&list.entries(sort, unread_only), sort, unread_only))
+        }
+        ReadingListPage::Read(id) => {
+            let entry = list.get(id).ok_or_else(|| WebxError::NotFound(format!("Reading list entry {}", id)))?;
+            let article = entry
+                .article
+                .as_ref()
+                .ok_or_else(|| WebxError::NotFound(format!("No saved article for {}", entry.url)))?;
+            list.mark_read(id, true)?;
+            Ok(reader.generate_reader_html(article))
+        }
+    }
+}
+
+/// Render the saved pages with sort and filter links. Buttons send
+/// `type: 'readinglist'` IPC messages with an `action` of `markread`,
+/// `markunread` or `remove`.
+pub fn render_reading_list_page(entries: &[ReadingListEntry], sort: ReadingListSort, unread_only: bool) -> String {
+    let sorts: String = [
+        (ReadingListSort::Newest, "Newest"),
+        (ReadingListSort::Oldest, "Oldest"),
+        (ReadingListSort::Title, "Title"),
+        (ReadingListSort::ReadingTime, "Reading time"),
+    ]
+    .iter()
+    .map(|(option, label)| {
+        let class = if *option == sort { r#" class="current""# } else { "" };
+        format!(
+            r#"<a{} href="{}?sort={}&amp;unread={}">{}</a> "#,
+            class,
+            READING_LIST_PAGE_URL,
+            option.as_str(),
+            unread_only as u8,
+            label
+        )
+    })
+    .collect();
+    let filter = format!(
+        r#"<a href="{}?sort={}&amp;unread={}">{}</a>"#,
+        READING_LIST_PAGE_URL,
+        sort.as_str(),
+        !unread_only as u8,
+        if unread_only { "Show all" } else { "Show unread only" }
+    );
+
+    let list: String = entries
+        .iter()
+        .map(|entry| {
+            // Saved articles open offline; others open the live page
+            let href = match entry.article {
+                Some(_) => format!("{}/{}", READING_LIST_PAGE_URL, entry.id),
+                None => entry.url.clone(),
+            };
+            let host = url::Url::parse(&entry.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            let (action, label) = if entry.is_read() { ("markunread", "Mark unread") } else { ("markread", "Mark read") };
+            format!(
+                r#"<li class="{state}"><a href="{href}">{title}</a> <span class="meta">{minutes} min · {host}</span>
+<button data-action="{action}" data-entry="{id}">{label}</button>
+<button data-action="remove" data-entry="{id}">Remove</button>{excerpt}</li>"#,
+                state = if entry.is_read() { "read" } else { "unread" },
+                href = escape_html(&href),
+                title = escape_html(&entry.title),
+                minutes = entry.reading_time_minutes(),
+                host = escape_html(&host),
+                id = entry.id,
+                excerpt = entry
+                    .excerpt
+                    .as_deref()
+                    .map(|excerpt| format!("<p>{}</p>", escape_html(excerpt)))
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    let list = if list.is_empty() {
+        r#"<p class="empty">Nothing to read. Save a page for later with Ctrl+Shift+D.</p>"#.to_string()
+    } else {
+        format!("<ul>{}</ul>", list)
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Reading List</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Reading List</h1></header>
+    <nav>Sort: {sorts} · {filter}</nav>
+    {list}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        sorts = sorts,
+        filter = filter,
+        list = list,
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+nav { color: #666; font-size: 0.9em; }
+nav a { margin-right: 0.3em; }
+nav a.current { font-weight: bold; text-decoration: none; color: #222; }
+ul { list-style: none; padding: 0; }
+li { margin: 1em 0; border-top: 1px solid #eee; padding-top: 0.5em; }
+li.read a { color: #888; }
+li p { margin: 0.3em 0; color: #555; }
+.meta { color: #888; font-size: 0.85em; margin: 0 0.5em; }
+button { font-size: 0.8em; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    window.ipc.send({ type: 'readinglist', action: button.dataset.action, entry: Number(button.dataset.entry) });
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reading_list_urls() {
+        assert_eq!(
+            ReadingListPage::parse("webx://reading-list"),
+            Some(ReadingListPage::Index { sort: ReadingListSort::Newest, unread_only: false })
+        );
+        assert_eq!(
+            ReadingListPage::parse("webx://reading-list?sort=time&unread=1"),
+            Some(ReadingListPage::Index { sort: ReadingListSort::ReadingTime, unread_only: true })
+        );
+        assert_eq!(ReadingListPage::parse("webx://reading-list/5"), Some(ReadingListPage::Read(5)));
+        assert_eq!(ReadingListPage::parse("webx://reading-listing"), None);
+    }
+}
@@ -0,0 +1,406 @@
+// Page Screenshots
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::{ImageFormat, ImageReader, Limits, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long a page gets to answer one capture request
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest segment accepted from a page, in pixels
+const MAX_SEGMENT_DIMENSION: u32 = 8192;
+/// Full page captures stop at this height, in pixels
+const MAX_PAGE_HEIGHT: u32 = 32768;
+
+/// Page and viewport size at the time of a capture, in CSS pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PageMetrics {
+    pub scroll_y: f64,
+    pub viewport_width: f64,
+    pub viewport_height: f64,
+    pub page_height: f64,
+    pub device_pixel_ratio: f64,
+}
+
+/// Answer of the capture script, sent back over IPC
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaptureReply {
+    pub request_id: u64,
+    pub metrics: PageMetrics,
+    /// Rendered viewport as a PNG data URL
+    pub image: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A captured screenshot
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub png: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Capture events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CaptureEvent {
+    /// Evaluate the script in the tab; the page answers with a
+    /// `type: 'capture'` IPC message
+    RunScript { tab_id: usize, script: String },
+}
+
+/// Takes screenshots of the visible part of a page or of the whole page.
+/// The page renders itself through an SVG image, so cross-origin images
+/// and frames are left out.
+pub struct CaptureService {
+    next_request_id: AtomicU64,
+    pending: Mutex<HashMap<u64, (usize, oneshot::Sender<CaptureReply>)>>,
+    timeout: Duration,
+    tx: mpsc::UnboundedSender<CaptureEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<CaptureEvent>>>,
+}
+
+impl CaptureService {
+    /// Create new capture service
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            next_request_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+            timeout: CAPTURE_TIMEOUT,
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Capture what the tab shows
+    pub async fn capture_visible(&self, tab_id: usize) -> Result<Screenshot, WebxError> {
+        let reply = self.request(tab_id, None, true).await?;
+        encode_png(&decode_segment(&reply)?)
+    }
+
+    /// Capture the whole page by scrolling through it and stitching the
+    /// segments, then scroll back to where the user was
+    pub async fn capture_full_page(&self, tab_id: usize) -> Result<Screenshot, WebxError> {
+        let start = self.request(tab_id, None, false).await?.metrics;
+        let segments = self.capture_segments(tab_id, &start).await;
+        if let Err(e) = self.request(tab_id, Some(start.scroll_y), false).await {
+            tracing::debug!("Could not restore scroll position: {}", e);
+        }
+
+        let segments = segments?;
+        let page_height = segments
+            .iter()
+            .map(|(metrics, _)| metrics.page_height)
+            .fold(start.page_height, f64::max);
+        encode_png(&stitch_segments(&segments, page_height))
+    }
+
+    /// Hand a `type: 'capture'` IPC message from a page to its request
+    pub fn handle_reply(&self, message: &str) -> Result<(), WebxError> {
+        self.settle(serde_json::from_str(message)?)
+    }
+
+    /// Hand a page's capture reply to its request
+    pub fn settle(&self, reply: CaptureReply) -> Result<(), WebxError> {
+        let (_, sender) = self
+            .pending
+            .lock_or_recover()
+            .remove(&reply.request_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Capture request {}", reply.request_id)))?;
+        let _ = sender.send(reply);
+        Ok(())
+    }
+
+    /// Fail the requests waiting on a tab, e.g. when it closes
+    pub fn cancel_tab(&self, tab_id: usize) {
+        self.pending
+            .lock_or_recover()
+            .retain(|_, (pending_tab, _)| *pending_tab != tab_id);
+    }
+
+    /// Subscribe to capture events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<CaptureEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    async fn capture_segments(
+        &self,
+        tab_id: usize,
+        start: &PageMetrics,
+    ) -> Result<Vec<(PageMetrics, RgbaImage)>, WebxError> {
+        let viewport = start.viewport_height.max(1.0);
+        let max_height = MAX_PAGE_HEIGHT as f64 / start.device_pixel_ratio.max(1.0);
+
+        let mut segments = Vec::new();
+        let mut y = 0.0;
+        loop {
+            let reply = self.request(tab_id, Some(y), true).await?;
+            let image = decode_segment(&reply)?;
+            let metrics = reply.metrics;
+            segments.push((metrics, image));
+
+            // The page clamps the scroll position at its end
+            let bottom = metrics.scroll_y + viewport;
+            if bottom >= metrics.page_height.min(max_height) || metrics.scroll_y + 1.0 < y {
+                return Ok(segments);
+            }
+            y = bottom;
+        }
+    }
+
+    async fn request(&self, tab_id: usize, scroll_to: Option<f64>, capture: bool) -> Result<CaptureReply, WebxError> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock_or_recover().insert(request_id, (tab_id, sender));
+        let _ = self.tx.send(CaptureEvent::RunScript {
+            tab_id,
+            script: capture_script(request_id, scroll_to, capture),
+        });
+
+        let result = tokio::time::timeout(self.timeout, receiver).await;
+        self.pending.lock_or_recover().remove(&request_id);
+        let reply = match result {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => return Err(format!("Capture of tab {} was cancelled", tab_id).into()),
+            Err(_) => return Err(format!("Tab {} did not answer the capture request", tab_id).into()),
+        };
+        match &reply.error {
+            Some(error) => Err(format!("Tab {} could not be captured: {}", tab_id, error).into()),
+            None => Ok(reply),
+        }
+    }
+}
+
+impl Default for CaptureService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpcHandler for CaptureService {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["capture"]
+    }
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::Capture(reply) => self.settle(reply).map(|_| serde_json::Value::Null),
+            _ => Err(WebxError::Invalid("Not a capture reply".to_string())),
+        }
+    }
+}
+
+/// Save a screenshot into a directory, e.g. the downloads directory, under
+/// a timestamped name
+pub fn save_screenshot(screenshot: &Screenshot, dir: &Path) -> Result<PathBuf, WebxError> {
+    std::fs::create_dir_all(dir)?;
+    let stem = format!("Screenshot {}", chrono::Local::now().format("%Y-%m-%d %H-%M-%S"));
+    let mut path = dir.join(format!("{}.png", stem));
+    let mut counter = 1;
+    while path.exists() {
+        path = dir.join(format!("{} ({}).png", stem, counter));
+        counter += 1;
+    }
+    std::fs::write(&path, &screenshot.png)?;
+    Ok(path)
+}
+
+/// Place segments captured at their scroll positions onto one image of the
+/// page's height, in CSS pixels. Later segments cover earlier ones where
+/// they overlap.
+pub fn stitch_segments(segments: &[(PageMetrics, RgbaImage)], page_height: f64) -> RgbaImage {
+    let ratio = segments
+        .first()
+        .map(|(metrics, _)| metrics.device_pixel_ratio.max(0.1))
+        .unwrap_or(1.0);
+    let width = segments.iter().map(|(_, image)| image.width()).max().unwrap_or(0);
+    let height = ((page_height * ratio).round() as u32).clamp(1, MAX_PAGE_HEIGHT);
+
+    let mut page = RgbaImage::new(width.max(1), height);
+    for (metrics, image) in segments {
+        let top = (metrics.scroll_y * ratio).round() as i64;
+        image::imageops::replace(&mut page, image, 0, top);
+    }
+    page
+}
+
+// Private helper functions
+
+fn capture_script(request_id: u64, scroll_to: Option<f64>, capture: bool) -> String {
+    format!(
+        r#"
+(function() {{
+    const requestId = {request_id};
+    const scrollTo = {scroll_to};
+    const capture = {capture};
+    const root = document.documentElement;
+    const reply = (extra) => window.ipc.send(Object.assign({{
+        type: 'capture',
+        request_id: requestId,
+        metrics: {{
+            scroll_y: window.scrollY,
+            viewport_width: root.clientWidth,
+            viewport_height: window.innerHeight,
+            page_height: Math.max(root.scrollHeight, document.body ? document.body.scrollHeight : 0),
+            device_pixel_ratio: window.devicePixelRatio || 1,
+        }},
+    }}, extra));
+
+    const render = () => {{
+        const width = root.clientWidth;
+        const height = window.innerHeight;
+        const clone = root.cloneNode(true);
+        clone.querySelectorAll('script').forEach((el) => el.remove());
+        // Stylesheets the page may read are inlined; linked ones would not load
+        let css = '';
+        for (const sheet of document.styleSheets) {{
+            try {{ css += Array.from(sheet.cssRules).map((rule) => rule.cssText).join('\n'); }} catch (e) {{}}
+        }}
+        const style = document.createElement('style');
+        style.textContent = css;
+        (clone.querySelector('head') || clone).appendChild(style);
+
+        const html = new XMLSerializer().serializeToString(clone);
+        const svg = '<svg xmlns="http://www.w3.org/2000/svg" width="' + width + '" height="' + height + '">' +
+            '<foreignObject x="0" y="' + (-window.scrollY) + '" width="' + width + '" height="' + root.scrollHeight + '">' +
+            html + '</foreignObject></svg>';
+        const img = new Image();
+        img.onload = () => {{
+            const ratio = window.devicePixelRatio || 1;
+            const canvas = document.createElement('canvas');
+            canvas.width = Math.round(width * ratio);
+            canvas.height = Math.round(height * ratio);
+            const context = canvas.getContext('2d');
+            context.scale(ratio, ratio);
+            context.fillStyle = getComputedStyle(document.body || root).backgroundColor || '#fff';
+            context.fillRect(0, 0, width, height);
+            context.drawImage(img, 0, 0);
+            try {{
+                reply({{ image: canvas.toDataURL('image/png') }});
+            }} catch (e) {{
+                reply({{ error: String(e) }});
+            }}
+        }};
+        img.onerror = () => reply({{ error: 'The page could not be rendered' }});
+        img.src = 'data:image/svg+xml;charset=utf-8,' + encodeURIComponent(svg);
+    }};
+
+    if (scrollTo !== null) window.scrollTo(window.scrollX, scrollTo);
+    // Let the page paint after scrolling, lazy content included
+    requestAnimationFrame(() => requestAnimationFrame(() => capture ? render() : reply({{}})));
+}})();
+"#,
+        request_id = request_id,
+        scroll_to = scroll_to.map(|y| y.to_string()).unwrap_or_else(|| "null".to_string()),
+        capture = capture,
+    )
+}
+
+fn decode_segment(reply: &CaptureReply) -> Result<RgbaImage, WebxError> {
+    let data_url = reply.image.as_deref().ok_or("The page sent no image")?;
+    let (_, data) = data_url.split_once(";base64,").ok_or("The page sent an unknown image encoding")?;
+    let bytes = STANDARD
+        .decode(data)
+        .map_err(|e| WebxError::Parse(format!("Invalid screenshot data: {}", e)))?;
+
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SEGMENT_DIMENSION);
+    limits.max_image_height = Some(MAX_SEGMENT_DIMENSION);
+    reader.limits(limits);
+    Ok(reader.decode()?.to_rgba8())
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Screenshot, WebxError> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(Screenshot {
+        png,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn metrics(scroll_y: f64) -> PageMetrics {
+        PageMetrics {
+            scroll_y,
+            viewport_width: 4.0,
+            viewport_height: 3.0,
+            page_height: 5.0,
+            device_pixel_ratio: 2.0,
+        }
+    }
+
+    fn data_url(color: [u8; 4]) -> String {
+        let image = RgbaImage::from_pixel(8, 6, Rgba(color));
+        format!("data:image/png;base64,{}", STANDARD.encode(encode_png(&image).unwrap().png))
+    }
+
+    #[tokio::test]
+    async fn test_full_page_capture_stitches_segments() {
+        let service = std::sync::Arc::new(CaptureService::new());
+        let mut events = service.subscribe_events();
+
+        // Play the page: 5 CSS pixels tall, 3 visible, scroll clamped at 2
+        let page = std::sync::Arc::clone(&service);
+        tokio::spawn(async move {
+            let mut scroll_y = 1.0;
+            let mut id = 0;
+            while let Some(CaptureEvent::RunScript { script, .. }) = events.recv().await {
+                id += 1;
+                if let Some(target) = script.split("const scrollTo = ").nth(1).and_then(|s| s.split(';').next()) {
+                    if let Ok(target) = target.parse::<f64>() {
+                        scroll_y = target.min(2.0);
+                    }
+                }
+                let image = if script.contains("const capture = true") {
+                    format!(r#","image":"{}""#, data_url(if scroll_y == 0.0 { [255, 0, 0, 255] } else { [0, 0, 255, 255] }))
+                } else {
+                    String::new()
+                };
+                let reply = format!(
+                    r#"{{"type":"capture","request_id":{},"metrics":{}{}}}"#,
+                    id,
+                    serde_json::to_string(&metrics(scroll_y)).unwrap(),
+                    image
+                );
+                page.handle_reply(&reply).unwrap();
+            }
+        });
+
+        let screenshot = service.capture_full_page(1).await.unwrap();
+        assert_eq!((screenshot.width, screenshot.height), (8, 10));
+        let image = image::load_from_memory(&screenshot.png).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(0, 9), &Rgba([0, 0, 255, 255]));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_capture_fails() {
+        let service = std::sync::Arc::new(CaptureService::new());
+        let mut events = service.subscribe_events();
+        let waiting = std::sync::Arc::clone(&service);
+        let capture = tokio::spawn(async move { waiting.capture_visible(3).await });
+
+        events.recv().await.unwrap();
+        service.cancel_tab(3);
+        assert!(capture.await.unwrap().is_err());
+        assert!(service.handle_reply(r#"{"request_id":99,"metrics":{"scroll_y":0,"viewport_width":1,"viewport_height":1,"page_height":1,"device_pixel_ratio":1}}"#).is_err());
+    }
+}
@@ -1,17 +1,761 @@
-// Session Management Module - Placeholder
-// TODO: Implement session management functionality
-pub struct SessionManager;
+// Session Restore Functionality
+use crate::error::WebxError;
+use crate::config::storage::write_atomic;
+use crate::core::{Tab, BrowserState, NavigationHistory, SplitOrientation, SplitView, WindowGeometry};
+use crate::features::system::conditions::{BackgroundWork, ConditionsMonitor, TaskPriority};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-impl SessionManager {
-    pub fn new() -> Self {
-        Self
+/// Time between auto-saves while the conditions monitor holds optional work
+/// back, e.g. on battery
+const DEFERRED_SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Session data structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionData {
+    /// Tabs of the focused window, kept for sessions without `windows`
+    pub tabs: Vec<SessionTab>,
+    pub active_tab_index: Option<usize>,
+    pub window_position: Option<(i32, i32)>,
+    pub window_size: Option<(u32, u32)>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub session_name: Option<String>,
+    /// Every window with its own tabs and geometry
+    #[serde(default)]
+    pub windows: Vec<SessionWindow>,
+    #[serde(default)]
+    pub focused_window_index: Option<usize>,
+}
+
+/// Window data for session storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub tabs: Vec<SessionTab>,
+    pub active_tab_index: Option<usize>,
+    pub geometry: Option<WindowGeometry>,
+    #[serde(default)]
+    pub split: Option<SessionSplit>,
+}
+
+/// Split layout of a window for session storage
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SessionSplit {
+    /// Index of the tab shown beside the active one
+    pub secondary_tab_index: usize,
+    pub ratio: f64,
+    pub orientation: SplitOrientation,
+}
+
+/// Tab data for session storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub url: String,
+    pub title: String,
+    pub scroll_position: Option<(f64, f64)>,
+    pub form_data: Option<String>, // Serialized form data
+    #[serde(default)]
+    pub container_id: Option<String>,
+    #[serde(default)]
+    pub muted: bool,
+    /// When the tab was last shown; `None` in sessions saved before this was kept
+    #[serde(default)]
+    pub last_accessed: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Back and forward pages; empty in sessions saved before this was kept
+    #[serde(default)]
+    pub history: NavigationHistory,
+}
+
+/// Session restore configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionConfig {
+    pub auto_save_interval: Duration,
+    pub max_sessions: usize,
+    pub save_on_exit: bool,
+    pub restore_on_start: bool,
+    pub backup_sessions: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            auto_save_interval: Duration::from_secs(30),
+            max_sessions: 10,
+            save_on_exit: true,
+            restore_on_start: true,
+            backup_sessions: true,
+        }
+    }
+}
+
+/// Session manager for saving and restoring browsing sessions
+pub struct SessionRestore {
+    config: SessionConfig,
+    sessions_dir: PathBuf,
+    backup_dir: PathBuf,
+    current_session: Arc<Mutex<Option<SessionData>>>,
+    save_timer: Option<tokio::task::JoinHandle<()>>,
+    conditions: Option<Arc<ConditionsMonitor>>,
+}
+
+impl SessionRestore {
+    /// Create a new session restore manager
+    pub fn new(
+        config: Option<SessionConfig>,
+        data_dir: Option<PathBuf>,
+    ) -> Result<Self, WebxError> {
+        let config = config.unwrap_or_default();
+        let data_dir = data_dir.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("sessions");
+            path
+        });
+        
+        let sessions_dir = data_dir.clone();
+        let backup_dir = data_dir.join("backup");
+        
+        // Create directories
+        fs::create_dir_all(&sessions_dir)?;
+        fs::create_dir_all(&backup_dir)?;
+        
+        let manager = Self {
+            config,
+            sessions_dir,
+            backup_dir,
+            current_session: Arc::new(Mutex::new(None)),
+            save_timer: None,
+            conditions: None,
+        };
+        
+        Ok(manager)
+    }
+
+    /// Capture current browser state as a session
+    pub fn capture_session(
+        &self,
+        browser_state: &BrowserState,
+        window_position: Option<(i32, i32)>,
+        window_size: Option<(u32, u32)>,
+    ) -> SessionData {
+        Self::capture_session_static(browser_state, window_position, window_size)
+    }
+
+    /// Save session to disk
+    pub fn save_session(
+        &self,
+        mut session: SessionData,
+        session_name: Option<String>,
+    ) -> Result<String, WebxError> {
+        let session_id = format!("session_{}", uuid::Uuid::new_v4());
+        session.session_name = session_name.or_else(|| {
+            Some(format!(
+                "Session {}",
+                session.timestamp.format("%Y-%m-%d %H:%M")
+            ))
+        });
+        
+        let filename = format!("{}.json", session_id);
+        let path = self.sessions_dir.join(&filename);
+        
+        let content = serde_json::to_string_pretty(&session)?;
+        fs::write(&path, content)?;
+        
+        // Backup the session
+        if self.config.backup_sessions {
+            let backup_path = self.backup_dir.join(&filename);
+            fs::copy(&path, &backup_path)?;
+        }
+        
+        // Update current session
+        *self.current_session.lock_or_recover() = Some(session);
+        
+        // Clean up old sessions
+        self.cleanup_old_sessions()?;
+        
+        Ok(session_id)
+    }
+
+    /// Restore session from disk
+    pub fn restore_session(
+        &self,
+        session_id: &str,
+    ) -> Result<SessionData, WebxError> {
+        let filename = format!("{}.json", session_id);
+        let path = self.sessions_dir.join(&filename);
+        
+        if !path.exists() {
+            return Err(WebxError::NotFound(format!("Session {}", session_id)));
+        }
+        
+        let content = fs::read_to_string(&path)?;
+        let session: SessionData = serde_json::from_str(&content)?;
+        
+        Ok(session)
+    }
+
+    /// Get list of available sessions
+    pub fn list_sessions(&self) -> Result<Vec<(String, SessionData)>, WebxError> {
+        let mut sessions = Vec::new();
+        
+        for entry in fs::read_dir(&self.sessions_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                if let Some(filename) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    let content = fs::read_to_string(&path)?;
+                    if let Ok(session) = serde_json::from_str::<SessionData>(&content) {
+                        sessions.push((filename.to_string(), session));
+                    }
+                }
+            }
+        }
+        
+        // Sort by timestamp (newest first)
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.1.timestamp));
+        
+        Ok(sessions)
+    }
+
+    /// Delete a session
+    pub fn delete_session(&self, session_id: &str) -> Result<(), WebxError> {
+        let filename = format!("{}.json", session_id);
+        let path = self.sessions_dir.join(&filename);
+        
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        
+        // Also remove backup
+        let backup_path = self.backup_dir.join(&filename);
+        if backup_path.exists() {
+            fs::remove_file(&backup_path)?;
+        }
+        
+        Ok(())
+    }
+
+    /// Restore browser state from session data
+    pub fn apply_session_to_browser(
+        &self,
+        session: &SessionData,
+        browser_state: &mut BrowserState,
+    ) -> Result<(), WebxError> {
+        // Clear existing windows and tabs
+        browser_state.tabs.clear();
+        browser_state.windows.clear();
+        browser_state.active_tab_id = None;
+        browser_state.focused_window_id = None;
+
+        // Sessions saved before multi-window support hold a single window
+        let legacy_window;
+        let windows = if session.windows.is_empty() {
+            let geometry = match (session.window_position, session.window_size) {
+                (Some((x, y)), Some((width, height))) => Some(WindowGeometry {
+                    x,
+                    y,
+                    width,
+                    height,
+                    maximized: false,
+                }),
+                _ => None,
+            };
+            legacy_window = [SessionWindow {
+                tabs: session.tabs.clone(),
+                active_tab_index: session.active_tab_index,
+                geometry,
+                split: None,
+            }];
+            &legacy_window[..]
+        } else {
+            &session.windows[..]
+        };
+
+        let mut window_ids = Vec::new();
+        for session_window in windows {
+            let window_id = browser_state.open_window();
+            window_ids.push(window_id);
+            if let Some(geometry) = session_window.geometry {
+                browser_state.set_window_geometry(window_id, geometry);
+            }
+
+            // Restore tabs
+            for (index, session_tab) in session_window.tabs.iter().enumerate() {
+                let tab_id = browser_state.next_tab_id;
+                browser_state.next_tab_id += 1;
+                
+                let history = if session_tab.history.entries().is_empty() {
+                    NavigationHistory::starting_at(&session_tab.url, &session_tab.title)
+                } else {
+                    session_tab.history.clone()
+                };
+                let mut tab = Tab {
+                    id: tab_id,
+                    title: session_tab.title.clone(),
+                    url: session_tab.url.clone(),
+                    favicon: None,
+                    is_loading: false,
+                    can_go_back: false,
+                    can_go_forward: false,
+                    container_id: session_tab.container_id.clone(),
+                    is_audible: false,
+                    muted: session_tab.muted,
+                    last_accessed: session_tab.last_accessed.unwrap_or_else(chrono::Utc::now),
+                    group: session_tab.group.clone(),
+                    history,
+                };
+                tab.sync_history_flags();
+                
+                browser_state.tabs.insert(tab_id, tab);
+                if let Some(window) = browser_state.windows.get_mut(&window_id) {
+                    window.tab_ids.push(tab_id);
+                }
+                
+                // Set active tab
+                if session_window.active_tab_index == Some(index) {
+                    browser_state.active_tab_id = Some(tab_id);
+                }
+            }
+            
+            // If no active tab was set, activate the first one
+            if browser_state.active_tab_id.is_none() {
+                browser_state.active_tab_id = browser_state.windows[&window_id].tab_ids.first().copied();
+            }
+
+            if let Some(split) = session_window.split {
+                let secondary = browser_state.windows[&window_id].tab_ids.get(split.secondary_tab_index).copied();
+                if let Some(secondary) = secondary.filter(|&id| browser_state.split_window(window_id, id)) {
+                    browser_state.set_window_split(
+                        window_id,
+                        SplitView {
+                            secondary_tab_id: secondary,
+                            ratio: split.ratio,
+                            orientation: split.orientation,
+                        },
+                    );
+                }
+            }
+        }
+
+        let focused = session
+            .focused_window_index
+            .and_then(|index| window_ids.get(index))
+            .or(window_ids.first());
+        if let Some(&window_id) = focused {
+            browser_state.focus_window(window_id);
+        }
+        
+        Ok(())
+    }
+
+    /// Auto-save less often while the monitor holds optional work back
+    pub fn set_conditions_monitor(&mut self, conditions: Arc<ConditionsMonitor>) {
+        self.conditions = Some(conditions);
+    }
+
+    /// Start auto-save timer. Window geometry comes from the browser state.
+    pub fn start_auto_save(
+        &mut self,
+        browser_state: Arc<Mutex<BrowserState>>,
+    ) -> Result<(), WebxError> {
+        if self.save_timer.is_some() {
+            self.stop_auto_save();
+        }
+        
+        let interval = self.config.auto_save_interval;
+        let sessions_dir = self.sessions_dir.clone();
+        let current_session = self.current_session.clone();
+        let conditions = self.conditions.clone();
+        
+        let handle = tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            let mut last_save: Option<Instant> = None;
+            
+            loop {
+                interval_timer.tick().await;
+
+                // Save less often while optional work waits; exit saves anyway
+                let deferred = conditions
+                    .as_ref()
+                    .is_some_and(|conditions| conditions.should_defer(BackgroundWork::new(TaskPriority::Low)));
+                if deferred && last_save.is_some_and(|saved| saved.elapsed() < DEFERRED_SAVE_INTERVAL) {
+                    continue;
+                }
+                last_save = Some(Instant::now());
+                
+                // Capture the session, then save it without holding the state lock
+                let session = {
+                    let state = browser_state.lock_or_recover();
+                    SessionRestore::capture_session_static(&state, None, None)
+                };
+                if let Ok(content) = serde_json::to_vec(&session) {
+                    let path = sessions_dir.join("autosave.json");
+                    let written = tokio::task::spawn_blocking(move || write_atomic(&path, &content)).await;
+                    if let Err(e) = written.map_err(std::io::Error::other).and_then(|result| result) {
+                        tracing::warn!("Failed to auto-save session: {}", e);
+                    }
+                }
+                *current_session.lock_or_recover() = Some(session);
+            }
+        });
+        
+        self.save_timer = Some(handle);
+        Ok(())
+    }
+
+    /// Stop auto-save timer
+    pub fn stop_auto_save(&mut self) {
+        if let Some(handle) = self.save_timer.take() {
+            handle.abort();
+        }
+    }
+
+    /// Write the auto-save session now, e.g. when the browser exits
+    pub fn save_autosave(&self, browser_state: &BrowserState) -> Result<(), WebxError> {
+        let session = Self::capture_session_static(browser_state, None, None);
+        write_atomic(&self.sessions_dir.join("autosave.json"), &serde_json::to_vec(&session)?)?;
+        *self.current_session.lock_or_recover() = Some(session);
+        Ok(())
+    }
+
+    /// Get last auto-saved session
+    pub fn get_last_autosave(&self) -> Option<SessionData> {
+        let path = self.sessions_dir.join("autosave.json");
+        if path.exists() {
+            if let Ok(content) = fs::read_to_string(&path) {
+                if let Ok(session) = serde_json::from_str(&content) {
+                    return Some(session);
+                }
+            }
+        }
+        None
+    }
+
+    /// Set configuration
+    pub fn set_config(&mut self, config: SessionConfig) {
+        self.config = config;
+    }
+
+    /// Get current configuration
+    pub fn get_config(&self) -> &SessionConfig {
+        &self.config
     }
+
+    // Private helper methods
     
-    pub fn save_session(&self) {
-        // Placeholder implementation
+    fn cleanup_old_sessions(&self) -> Result<(), WebxError> {
+        let mut sessions = self.list_sessions()?;
+        
+        if sessions.len() > self.config.max_sessions {
+            // Sort by timestamp (oldest first)
+            sessions.sort_by_key(|session| session.1.timestamp);
+            
+            // Remove excess sessions
+            let excess_count = sessions.len() - self.config.max_sessions;
+            for (session_id, _) in sessions.iter().take(excess_count) {
+                self.delete_session(session_id)?;
+            }
+        }
+        
+        Ok(())
     }
     
-    pub fn restore_session(&self) {
-        // Placeholder implementation
+    fn capture_session_static(
+        browser_state: &BrowserState,
+        window_position: Option<(i32, i32)>,
+        window_size: Option<(u32, u32)>,
+    ) -> SessionData {
+        let mut window_ids: Vec<usize> = browser_state.windows.keys().copied().collect();
+        window_ids.sort_unstable();
+
+        let windows: Vec<SessionWindow> = window_ids
+            .iter()
+            .map(|&window_id| {
+                let tabs = browser_state.window_tabs(window_id);
+                let active = browser_state.window_active_tab(window_id);
+                let window = &browser_state.windows[&window_id];
+                SessionWindow {
+                    tabs: tabs.iter().map(|tab| session_tab(tab)).collect(),
+                    active_tab_index: active.and_then(|id| tabs.iter().position(|tab| tab.id == id)),
+                    geometry: window.geometry,
+                    split: window.split.and_then(|split| {
+                        Some(SessionSplit {
+                            secondary_tab_index: tabs.iter().position(|tab| tab.id == split.secondary_tab_id)?,
+                            ratio: split.ratio,
+                            orientation: split.orientation,
+                        })
+                    }),
+                }
+            })
+            .collect();
+        let focused_window_index = browser_state
+            .focused_window_id
+            .and_then(|id| window_ids.iter().position(|&window_id| window_id == id));
+
+        // Older readers only know the focused window
+        let (tabs, active_tab_index, geometry) = match focused_window_index.and_then(|index| windows.get(index)) {
+            Some(window) => (window.tabs.clone(), window.active_tab_index, window.geometry),
+            None => (Vec::new(), None, None),
+        };
+        
+        SessionData {
+            tabs,
+            active_tab_index,
+            window_position: window_position.or(geometry.map(|g| (g.x, g.y))),
+            window_size: window_size.or(geometry.map(|g| (g.width, g.height))),
+            timestamp: chrono::Utc::now(),
+            session_name: None,
+            windows,
+            focused_window_index,
+        }
+    }
+}
+
+// Private helper functions
+
+fn session_tab(tab: &Tab) -> SessionTab {
+    SessionTab {
+        url: tab.url.clone(),
+        title: tab.title.clone(),
+        scroll_position: tab.history.current().and_then(|entry| entry.scroll_position),
+        form_data: None,       // Would capture form data
+        container_id: tab.container_id.clone(),
+        muted: tab.muted,
+        last_accessed: Some(tab.last_accessed),
+        group: tab.group.clone(),
+        history: tab.history.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::BrowserSettings;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_session_capture_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Create test browser state
+        let mut browser_state = BrowserState::new();
+        browser_state.settings = BrowserSettings::default();
+        
+        browser_state.add_tab("https://example.com".to_string());
+        let tab2_id = browser_state.add_tab("https://google.com".to_string());
+        browser_state.active_tab_id = Some(tab2_id);
+        
+        // Capture session
+        let session = session_manager.capture_session(
+            &browser_state,
+            Some((100, 100)),
+            Some((1280, 800)),
+        );
+        
+        assert_eq!(session.tabs.len(), 2);
+        assert_eq!(session.active_tab_index, Some(1)); // Second tab
+        assert_eq!(session.window_position, Some((100, 100)));
+        
+        // Save session
+        let session_id = session_manager.save_session(session.clone(), None).unwrap();
+        assert!(!session_id.is_empty());
+        
+        // List sessions
+        let sessions = session_manager.list_sessions().unwrap();
+        assert!(!sessions.is_empty());
+        assert_eq!(sessions[0].0, session_id);
+        
+        // Restore session
+        let restored_session = session_manager.restore_session(&session_id).unwrap();
+        assert_eq!(restored_session.tabs.len(), 2);
+        assert_eq!(restored_session.active_tab_index, Some(1));
+    }
+
+    #[test]
+    fn test_session_apply_to_browser() {
+        let session_manager = SessionRestore::new(None, None).unwrap();
+        
+        // Create session data
+        let session = SessionData {
+            tabs: vec![
+                SessionTab {
+                    url: "https://example.com".to_string(),
+                    title: "Example".to_string(),
+                    scroll_position: None,
+                    form_data: None,
+                    container_id: None,
+                    muted: false,
+                    last_accessed: None,
+                    group: None,
+                    history: NavigationHistory::default(),
+                },
+                SessionTab {
+                    url: "https://google.com".to_string(),
+                    title: "Google".to_string(),
+                    scroll_position: None,
+                    form_data: None,
+                    container_id: None,
+                    muted: false,
+                    last_accessed: None,
+                    group: None,
+                    history: NavigationHistory::default(),
+                },
+            ],
+            active_tab_index: Some(1),
+            window_position: Some((100, 100)),
+            window_size: Some((1280, 800)),
+            timestamp: chrono::Utc::now(),
+            session_name: Some("Test Session".to_string()),
+            windows: Vec::new(),
+            focused_window_index: None,
+        };
+        
+        // Apply to browser state
+        let mut browser_state = BrowserState::new();
+        browser_state.settings = BrowserSettings::default();
+        
+        session_manager
+            .apply_session_to_browser(&session, &mut browser_state)
+            .unwrap();
+        
+        assert_eq!(browser_state.tabs.len(), 2);
+        assert!(browser_state.active_tab_id.is_some());
+        
+        let active_tab = browser_state.active_tab().unwrap();
+        assert_eq!(active_tab.url, "https://google.com");
+    }
+
+    #[test]
+    fn test_session_deletion() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Create and save a session
+        let browser_state = BrowserState::new();
+        let session = session_manager.capture_session(&browser_state, None, None);
+        let session_id = session_manager.save_session(session, Some("Test".to_string())).unwrap();
+        
+        // Verify session exists
+        let sessions_before = session_manager.list_sessions().unwrap();
+        assert!(sessions_before.iter().any(|(id, _)| id == &session_id));
+        
+        // Delete session
+        session_manager.delete_session(&session_id).unwrap();
+        
+        // Verify session is gone
+        let sessions_after = session_manager.list_sessions().unwrap();
+        assert!(!sessions_after.iter().any(|(id, _)| id == &session_id));
+    }
+
+    #[test]
+    fn test_session_preserves_containers() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let mut browser_state = BrowserState::new();
+        let tab_id = browser_state.add_tab("https://mail.example.com".to_string());
+        browser_state.tabs.get_mut(&tab_id).unwrap().container_id = Some("work".to_string());
+        browser_state.add_tab("https://example.com".to_string());
+
+        let session = session_manager.capture_session(&browser_state, None, None);
+        let session_id = session_manager.save_session(session, None).unwrap();
+        let restored = session_manager.restore_session(&session_id).unwrap();
+
+        let mut new_state = BrowserState::new();
+        session_manager
+            .apply_session_to_browser(&restored, &mut new_state)
+            .unwrap();
+
+        let mut tabs: Vec<_> = new_state.tabs.values().collect();
+        tabs.sort_by_key(|tab| tab.id);
+        assert_eq!(tabs[0].container_id.as_deref(), Some("work"));
+        assert_eq!(tabs[1].container_id, None);
+    }
+
+    #[test]
+    fn test_session_restores_each_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let mut browser_state = BrowserState::new();
+        browser_state.add_tab("https://a.example".to_string());
+        let moved = browser_state.add_tab("https://b.example".to_string());
+        let second_window = browser_state.move_tab_to_new_window(moved).unwrap();
+        let geometry = WindowGeometry { x: 40, y: 60, width: 900, height: 700, maximized: false };
+        browser_state.set_window_geometry(second_window, geometry);
+
+        session_manager.save_autosave(&browser_state).unwrap();
+        let restored = session_manager.get_last_autosave().unwrap();
+        assert_eq!(restored.windows.len(), 2);
+
+        let mut new_state = BrowserState::new();
+        session_manager
+            .apply_session_to_browser(&restored, &mut new_state)
+            .unwrap();
+
+        let focused = new_state.focused_window_id.unwrap();
+        assert_eq!(new_state.windows[&focused].geometry, Some(geometry));
+        assert_eq!(new_state.active_tab().unwrap().url, "https://b.example");
+        assert_eq!(new_state.windows.len(), 2);
+        assert!(new_state.windows.values().all(|window| window.tab_ids.len() == 1));
+    }
+
+    #[test]
+    fn test_session_keeps_split_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let mut browser_state = BrowserState::new();
+        let left = browser_state.add_tab("https://a.example".to_string());
+        let right = browser_state.add_tab("https://b.example".to_string());
+        browser_state.activate_tab(left);
+        let window_id = browser_state.window_of_tab(left).unwrap();
+        assert!(browser_state.split_window(window_id, right));
+        let split = SplitView { ratio: 0.3, orientation: SplitOrientation::Stacked, ..SplitView::new(right) };
+        browser_state.set_window_split(window_id, split);
+
+        session_manager.save_autosave(&browser_state).unwrap();
+        let restored = session_manager.get_last_autosave().unwrap();
+        let mut new_state = BrowserState::new();
+        session_manager.apply_session_to_browser(&restored, &mut new_state).unwrap();
+
+        let window = &new_state.windows[&new_state.focused_window_id.unwrap()];
+        let split = window.split.unwrap();
+        assert_eq!(new_state.tabs[&split.secondary_tab_id].url, "https://b.example");
+        assert_eq!((split.ratio, split.orientation), (0.3, SplitOrientation::Stacked));
+        assert_eq!(new_state.active_tab().unwrap().url, "https://a.example");
+    }
+
+    #[test]
+    fn test_session_keeps_tab_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let session_manager = SessionRestore::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let mut browser_state = BrowserState::new();
+        let tab_id = browser_state.add_tab("https://a.example".to_string());
+        let tab = browser_state.tabs.get_mut(&tab_id).unwrap();
+        tab.record_navigation("https://a.example", "A");
+        tab.record_navigation("https://b.example", "B");
+        tab.history.current_mut().unwrap().scroll_position = Some((0.0, 420.0));
+        tab.record_navigation("https://c.example", "C");
+        tab.go_to_offset(-1);
+
+        session_manager.save_autosave(&browser_state).unwrap();
+        let restored = session_manager.get_last_autosave().unwrap();
+        let mut new_state = BrowserState::new();
+        session_manager.apply_session_to_browser(&restored, &mut new_state).unwrap();
+
+        let tab = new_state.active_tab().unwrap();
+        assert_eq!(tab.url, "https://b.example");
+        assert!(tab.can_go_back && tab.can_go_forward);
+        assert_eq!(tab.history.current().unwrap().scroll_position, Some((0.0, 420.0)));
+        assert_eq!(tab.history.entries().len(), 3);
     }
-}
\ No newline at end of file
+}
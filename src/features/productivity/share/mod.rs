@@ -0,0 +1,527 @@
+// Share
+use crate::error::WebxError;
+use crate::features::productivity::reading_list::ReadingList;
+use crate::features::system::protocol_handlers::ProtocolHandlers;
+use crate::features::ui::reader::ReadingMode;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Script giving pages `window.shareMenu`, whose `open(link)` shows the
+/// share targets for the page, or a link on it, and runs the one picked
+pub const SHARE_SCRIPT: &str = r#"
+(function() {
+    if (window.shareMenu) return;
+    let panel = null;
+    const close = () => { if (panel) { panel.remove(); panel = null; } };
+    const show = (build) => {
+        close();
+        panel = document.createElement('div');
+        panel.style.cssText = 'position:fixed;top:12px;right:12px;z-index:2147483647;background:#fff;color:#202124;border:1px solid #dadce0;border-radius:8px;box-shadow:0 4px 16px rgba(0,0,0,.2);padding:6px 0;font:13px system-ui,sans-serif;min-width:200px;';
+        build(panel);
+        document.documentElement.append(panel);
+    };
+    const notice = (text) => show((box) => {
+        box.style.padding = '10px 16px';
+        box.textContent = text;
+        setTimeout(close, 2500);
+    });
+    const run = (entry, item) => {
+        const message = Object.assign({ type: 'share', target: entry.target, destination: entry.destination }, item);
+        if (entry.wants_html) message.html = document.documentElement.outerHTML;
+        window.ipc.request(message).then((outcome) => {
+            if (outcome.kind === 'open') {
+                close();
+                window.location.href = outcome.url;
+            } else if (outcome.kind === 'show') {
+                show((box) => {
+                    box.style.padding = '12px';
+                    box.style.textAlign = 'center';
+                    box.innerHTML = outcome.svg;
+                    const caption = document.createElement('div');
+                    caption.textContent = outcome.caption;
+                    caption.style.cssText = 'margin-top:6px;max-width:220px;overflow:hidden;text-overflow:ellipsis;white-space:nowrap;';
+                    box.append(caption);
+                    box.addEventListener('click', close);
+                });
+            } else {
+                notice(outcome.message);
+            }
+        }).catch((error) => notice(String(error)));
+    };
+    window.shareMenu = {
+        open: (link) => {
+            const selection = String(window.getSelection() || '').trim();
+            const item = {
+                url: link || window.location.href,
+                title: link ? '' : document.title,
+                text: selection || null,
+            };
+            window.ipc.request({ type: 'share-targets' }).then((entries) => {
+                if (!entries.length) return notice('Nothing to share with');
+                show((box) => {
+                    for (const entry of entries) {
+                        const row = document.createElement('div');
+                        row.textContent = entry.label;
+                        row.style.cssText = 'padding:6px 16px;cursor:default;white-space:nowrap;';
+                        row.addEventListener('mouseenter', () => { row.style.background = '#e3ecfd'; });
+                        row.addEventListener('mouseleave', () => { row.style.background = ''; });
+                        row.addEventListener('mousedown', (e) => e.preventDefault());
+                        row.addEventListener('click', () => run(entry, item));
+                        box.append(row);
+                    }
+                });
+            });
+        },
+    };
+    document.addEventListener('keydown', (e) => { if (e.key === 'Escape') close(); }, true);
+    document.addEventListener('mousedown', (e) => { if (panel && !panel.contains(e.target)) close(); }, true);
+})();
+"#;
+
+/// A page, or a link on it, to share
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct ShareItem {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    /// Text selected on the page
+    #[serde(default)]
+    pub text: Option<String>,
+    /// The page's markup, for targets that keep its content
+    #[serde(default)]
+    pub html: Option<String>,
+}
+
+/// An entry of the share menu
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ShareEntry {
+    /// ID of the target the entry runs
+    pub target: String,
+    /// Where within the target it goes, such as a device
+    pub destination: Option<String>,
+    pub label: String,
+    /// Whether the page sends its markup along
+    pub wants_html: bool,
+}
+
+/// What sharing left for the page to do
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ShareOutcome {
+    /// Shared; tell the user
+    Done { message: String },
+    /// Open a link that leaves the browser, such as a mailto: link
+    Open { url: String },
+    /// Show an image, such as a QR code, with a caption
+    Show { svg: String, caption: String },
+}
+
+/// Somewhere pages can be shared to
+pub trait ShareTarget: Send + Sync {
+    /// Stable ID, such as "copy-link"
+    fn id(&self) -> &'static str;
+
+    /// Entries for the share menu; none while what the target needs is not
+    /// set up
+    fn entries(&self) -> Vec<ShareEntry>;
+
+    fn share(&self, item: &ShareItem, destination: Option<&str>) -> Result<ShareOutcome, WebxError>;
+}
+
+/// A device signed in to the user's sync account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SyncDevice {
+    pub id: String,
+    pub name: String,
+}
+
+/// A sync backend that hands pages to the user's other devices
+pub trait DeviceSync: Send + Sync {
+    /// Devices pages can be sent to; none until the user signs in
+    fn devices(&self) -> Vec<SyncDevice>;
+
+    fn send_tab(&self, device_id: &str, item: &ShareItem) -> Result<(), WebxError>;
+}
+
+/// Copies the link to the system clipboard
+pub struct CopyLinkTarget {
+    /// Kept open, as some systems empty the clipboard once it is closed
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl CopyLinkTarget {
+    pub fn new() -> Self {
+        Self {
+            clipboard: Mutex::new(None),
+        }
+    }
+
+    // Private helper methods
+
+    fn with_clipboard<T>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> T) -> Option<T> {
+        let mut clipboard = self.clipboard.lock_or_recover();
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().ok();
+        }
+        clipboard.as_mut().map(f)
+    }
+}
+
+impl Default for CopyLinkTarget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShareTarget for CopyLinkTarget {
+    fn id(&self) -> &'static str {
+        "copy-link"
+    }
+
+    fn entries(&self) -> Vec<ShareEntry> {
+        match self.with_clipboard(|_| ()) {
+            Some(()) => vec![entry(self.id(), None, "Copy Link", false)],
+            None => Vec::new(),
+        }
+    }
+
+    fn share(&self, item: &ShareItem, _destination: Option<&str>) -> Result<ShareOutcome, WebxError> {
+        self.with_clipboard(|clipboard| clipboard.set_text(item.url.clone()))
+            .ok_or("No clipboard is available")?
+            .map_err(|e| WebxError::Invalid(format!("Could not copy the link: {}", e)))?;
+        Ok(ShareOutcome::Done {
+            message: "Link copied".to_string(),
+        })
+    }
+}
+
+/// Starts an email with the link, in the application handling mailto: links
+pub struct EmailTarget {
+    protocol_handlers: Arc<ProtocolHandlers>,
+}
+
+impl EmailTarget {
+    pub fn new(protocol_handlers: Arc<ProtocolHandlers>) -> Self {
+        Self { protocol_handlers }
+    }
+}
+
+impl ShareTarget for EmailTarget {
+    fn id(&self) -> &'static str {
+        "email"
+    }
+
+    fn entries(&self) -> Vec<ShareEntry> {
+        match self.protocol_handlers.claims_url("mailto:") {
+            true => vec![entry(self.id(), None, "Email Link", false)],
+            false => Vec::new(),
+        }
+    }
+
+    fn share(&self, item: &ShareItem, _destination: Option<&str>) -> Result<ShareOutcome, WebxError> {
+        let subject = match item.title.trim() {
+            "" => item.url.as_str(),
+            title => title,
+        };
+        let body = match item.text.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+            Some(text) => format!("“{}”\n\n{}", text, item.url),
+            None => item.url.clone(),
+        };
+        Ok(ShareOutcome::Open {
+            url: format!("mailto:?subject={}&body={}", encode_component(subject), encode_component(&body)),
+        })
+    }
+}
+
+/// Shows the link as a QR code, for a phone to scan
+pub struct QrCodeTarget;
+
+impl ShareTarget for QrCodeTarget {
+    fn id(&self) -> &'static str {
+        "qr-code"
+    }
+
+    fn entries(&self) -> Vec<ShareEntry> {
+        vec![entry(self.id(), None, "Show QR Code", false)]
+    }
+
+    fn share(&self, item: &ShareItem, _destination: Option<&str>) -> Result<ShareOutcome, WebxError> {
+        let code = qrcode::QrCode::new(item.url.as_bytes())
+            .map_err(|e| WebxError::Invalid(format!("This link cannot be a QR code: {}", e)))?;
+        let svg = code
+            .render::<qrcode::render::svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+        Ok(ShareOutcome::Show {
+            svg,
+            caption: item.url.clone(),
+        })
+    }
+}
+
+/// Saves the page to the reading list
+pub struct ReadingListTarget {
+    reading_list: Arc<ReadingList>,
+    reading_mode: ReadingMode,
+}
+
+impl ReadingListTarget {
+    pub fn new(reading_list: Arc<ReadingList>) -> Self {
+        Self {
+            reading_list,
+            reading_mode: ReadingMode::new(None),
+        }
+    }
+}
+
+impl ShareTarget for ReadingListTarget {
+    fn id(&self) -> &'static str {
+        "reading-list"
+    }
+
+    fn entries(&self) -> Vec<ShareEntry> {
+        vec![entry(self.id(), None, "Save to Reading List", true)]
+    }
+
+    fn share(&self, item: &ShareItem, _destination: Option<&str>) -> Result<ShareOutcome, WebxError> {
+        let saved = self
+            .reading_list
+            .save(&self.reading_mode, &item.url, &item.title, item.html.as_deref())?;
+        Ok(ShareOutcome::Done {
+            message: format!("Saved “{}” to the reading list", saved.title),
+        })
+    }
+}
+
+/// Sends the page to one of the user's other devices
+pub struct SendToDeviceTarget {
+    sync: Arc<dyn DeviceSync>,
+}
+
+impl SendToDeviceTarget {
+    pub fn new(sync: Arc<dyn DeviceSync>) -> Self {
+        Self { sync }
+    }
+}
+
+impl ShareTarget for SendToDeviceTarget {
+    fn id(&self) -> &'static str {
+        "send-to-device"
+    }
+
+    fn entries(&self) -> Vec<ShareEntry> {
+        self.sync
+            .devices()
+            .into_iter()
+            .map(|device| entry(self.id(), Some(device.id), &format!("Send to {}", device.name), false))
+            .collect()
+    }
+
+    fn share(&self, item: &ShareItem, destination: Option<&str>) -> Result<ShareOutcome, WebxError> {
+        let device_id = destination.ok_or("No device was picked")?;
+        let device = self
+            .sync
+            .devices()
+            .into_iter()
+            .find(|device| device.id == device_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Device {}", device_id)))?;
+        self.sync.send_tab(&device.id, item)?;
+        Ok(ShareOutcome::Done {
+            message: format!("Sent to {}", device.name),
+        })
+    }
+}
+
+/// The share menu's targets
+#[derive(Default)]
+pub struct ShareService {
+    targets: Mutex<Vec<Arc<dyn ShareTarget>>>,
+}
+
+impl ShareService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Service with the built-in targets: copying the link, email, QR code
+    /// and the reading list
+    pub fn with_builtin_targets(reading_list: Arc<ReadingList>, protocol_handlers: Arc<ProtocolHandlers>) -> Self {
+        let service = Self::new();
+        let targets: [Arc<dyn ShareTarget>; 4] = [
+            Arc::new(CopyLinkTarget::new()),
+            Arc::new(EmailTarget::new(protocol_handlers)),
+            Arc::new(QrCodeTarget),
+            Arc::new(ReadingListTarget::new(reading_list)),
+        ];
+        for target in targets {
+            service.targets.lock_or_recover().push(target);
+        }
+        service
+    }
+
+    /// Add a target, such as sending to devices once sync is set up
+    pub fn register(&self, target: Arc<dyn ShareTarget>) -> Result<(), WebxError> {
+        let mut targets = self.targets.lock_or_recover();
+        if targets.iter().any(|existing| existing.id() == target.id()) {
+            return Err(WebxError::Invalid(format!("A share target {} is already registered", target.id())));
+        }
+        targets.push(target);
+        Ok(())
+    }
+
+    /// Remove a target, returning whether there was one
+    pub fn unregister(&self, target_id: &str) -> bool {
+        let mut targets = self.targets.lock_or_recover();
+        let before = targets.len();
+        targets.retain(|target| target.id() != target_id);
+        targets.len() < before
+    }
+
+    /// Entries of the share menu, in target order
+    pub fn entries(&self) -> Vec<ShareEntry> {
+        self.targets().iter().flat_map(|target| target.entries()).collect()
+    }
+
+    /// Share an item with a target
+    pub fn share(&self, target_id: &str, destination: Option<&str>, item: &ShareItem) -> Result<ShareOutcome, WebxError> {
+        if item.url.trim().is_empty() {
+            return Err("Nothing to share".into());
+        }
+        let target = self
+            .targets()
+            .into_iter()
+            .find(|target| target.id() == target_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Share target {}", target_id)))?;
+        let outcome = target.share(item, destination)?;
+        tracing::info!("Shared {} with {}", item.url, target_id);
+        Ok(outcome)
+    }
+
+    // Private helper methods
+
+    /// Targets, outside the lock, as sharing may take a while
+    fn targets(&self) -> Vec<Arc<dyn ShareTarget>> {
+        self.targets.lock_or_recover().clone()
+    }
+}
+
+impl IpcHandler for ShareService {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["share-targets", "share"]
+    }
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::ShareTargets => Ok(serde_json::to_value(self.entries())?),
+            IpcMessage::Share { target, destination, item } => {
+                Ok(serde_json::to_value(self.share(&target, destination.as_deref(), &item)?)?)
+            }
+            _ => Err(WebxError::Invalid("Not a share message".to_string())),
+        }
+    }
+}
+
+/// Script opening the share menu in a page running `SHARE_SCRIPT`, for the
+/// page or a link on it
+pub fn share_menu_script(link: Option<&str>) -> String {
+    format!(
+        "window.shareMenu && window.shareMenu.open({});",
+        serde_json::to_string(&link).unwrap_or_else(|_| "null".to_string())
+    )
+}
+
+// Private helper functions
+
+fn entry(target: &str, destination: Option<String>, label: &str, wants_html: bool) -> ShareEntry {
+    ShareEntry {
+        target: target.to_string(),
+        destination,
+        label: label.to_string(),
+        wants_html,
+    }
+}
+
+/// Percent-encode all but unreserved characters, as mailto: fields need
+fn encode_component(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    struct Devices(Mutex<Vec<String>>);
+
+    impl DeviceSync for Devices {
+        fn devices(&self) -> Vec<SyncDevice> {
+            vec![SyncDevice {
+                id: "laptop-1".to_string(),
+                name: "Laptop".to_string(),
+            }]
+        }
+
+        fn send_tab(&self, device_id: &str, item: &ShareItem) -> Result<(), WebxError> {
+            self.0.lock_or_recover().push(format!("{} {}", device_id, item.url));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_targets_follow_integrations_and_share() {
+        let temp_dir = TempDir::new().unwrap();
+        let reading_list = Arc::new(ReadingList::new(Some(temp_dir.path().join("reading_list.db"))).unwrap());
+        let handlers = Arc::new(ProtocolHandlers::new(None));
+        let service = ShareService::with_builtin_targets(reading_list.clone(), handlers.clone());
+        let targets = |service: &ShareService| service.entries().into_iter().map(|entry| entry.target).collect::<Vec<_>>();
+        assert!(targets(&service).ends_with(&["email".to_string(), "qr-code".to_string(), "reading-list".to_string()]));
+
+        let item = ShareItem {
+            url: "https://example.com/a?b=c".to_string(),
+            title: "Fish & chips".to_string(),
+            text: Some("Best in town".to_string()),
+            html: None,
+        };
+        assert_eq!(
+            service.share("email", None, &item).unwrap(),
+            ShareOutcome::Open {
+                url: "mailto:?subject=Fish%20%26%20chips&body=%E2%80%9CBest%20in%20town%E2%80%9D%0A%0Ahttps%3A%2F%2Fexample.com%2Fa%3Fb%3Dc"
+                    .to_string()
+            }
+        );
+        match service.share("qr-code", None, &item).unwrap() {
+            ShareOutcome::Show { svg, caption } => {
+                assert!(svg.contains("<svg"));
+                assert_eq!(caption, item.url);
+            }
+            other => panic!("Unexpected outcome {:?}", other),
+        }
+        service.share("reading-list", None, &item).unwrap();
+        assert_eq!(reading_list.find(&item.url).unwrap().title, "Fish & chips");
+        assert!(service.share("fax", None, &item).is_err());
+        let message = r#"{"type":"share","target":"qr-code","url":"https://example.com/","title":"Example","id":4}"#;
+        assert!(matches!(
+            serde_json::from_str(message).unwrap(),
+            IpcMessage::Share { target, destination: None, item } if target == "qr-code" && item.title == "Example"
+        ));
+
+        // Email goes once nothing handles mailto: links, devices come with sync
+        handlers.unregister_scheme("mailto").unwrap();
+        let devices = Arc::new(Devices(Mutex::new(Vec::new())));
+        service.register(Arc::new(SendToDeviceTarget::new(devices.clone()))).unwrap();
+        assert!(service.register(Arc::new(QrCodeTarget)).is_err());
+        let entries = service.entries();
+        assert!(!entries.iter().any(|entry| entry.target == "email"));
+        let device = entries.iter().find(|entry| entry.target == "send-to-device").unwrap();
+        assert_eq!((device.label.as_str(), device.destination.as_deref()), ("Send to Laptop", Some("laptop-1")));
+        service.share("send-to-device", Some("laptop-1"), &item).unwrap();
+        assert_eq!(devices.0.lock_or_recover().as_slice(), ["laptop-1 https://example.com/a?b=c"]);
+    }
+}
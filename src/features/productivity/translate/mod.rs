@@ -0,0 +1,551 @@
+// Page Translation
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use whatlang::Lang;
+
+/// Collects the page's text nodes and reports them with a `type: 'pagetext'`
+/// IPC message. The nodes stay on `window.__webxTranslate` so translations
+/// can be put back in place.
+pub const COLLECT_TEXT_SCRIPT: &str = r#"
+(function() {
+    const skip = new Set(['SCRIPT', 'STYLE', 'NOSCRIPT', 'CODE', 'PRE', 'TEXTAREA', 'SVG']);
+    const nodes = [];
+    const walker = document.createTreeWalker(document.body || document.documentElement, NodeFilter.SHOW_TEXT, {
+        acceptNode: (node) => {
+            for (let el = node.parentElement; el; el = el.parentElement) {
+                if (skip.has(el.tagName.toUpperCase()) || el.isContentEditable || el.id === '__webx-translate-bar') {
+                    return NodeFilter.FILTER_REJECT;
+                }
+            }
+            return /\p{L}{2}/u.test(node.nodeValue) ? NodeFilter.FILTER_ACCEPT : NodeFilter.FILTER_SKIP;
+        }
+    });
+    while (walker.nextNode() && nodes.length < 5000) nodes.push(walker.currentNode);
+    window.__webxTranslate = { nodes: nodes, originals: nodes.map((node) => node.nodeValue) };
+    window.ipc.send({
+        type: 'pagetext',
+        url: location.href,
+        lang: document.documentElement.lang || null,
+        texts: nodes.map((node) => node.nodeValue.trim()),
+    });
+})();
+"#;
+
+/// Puts the original text back after a translation
+pub const RESTORE_TEXT_SCRIPT: &str = r#"
+(function() {
+    const state = window.__webxTranslate;
+    if (!state) return;
+    state.nodes.forEach((node, i) => { node.nodeValue = state.originals[i]; });
+})();
+"#;
+
+/// Where translations come from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TranslationBackend {
+    /// A LibreTranslate server, self-hosted or public
+    LibreTranslate { endpoint: String, api_key: Option<String> },
+    /// A local translation program that reads one text per line on stdin
+    /// and writes one translation per line. `{source}` and `{target}` in
+    /// the arguments are replaced by language codes.
+    LocalModel { command: PathBuf, args: Vec<String> },
+}
+
+/// Translation configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslateConfig {
+    pub enabled: bool,
+    pub backend: TranslationBackend,
+    /// ISO 639-1 code pages are translated into
+    pub target_language: String,
+    /// Detections below this confidence (0-1) are not acted on
+    pub min_confidence: f64,
+    /// Texts are sent in batches of at most this many characters
+    pub max_batch_chars: usize,
+    pub timeout_secs: u64,
+}
+
+impl Default for TranslateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend: TranslationBackend::LibreTranslate {
+                endpoint: "http://localhost:5000".to_string(),
+                api_key: None,
+            },
+            target_language: "en".to_string(),
+            min_confidence: 0.5,
+            max_batch_chars: 5000,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// What a site remembers about translation
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SitePreference {
+    /// Translate the site's pages without asking
+    AlwaysTranslate,
+    /// Never offer translation on the site
+    NeverTranslate,
+}
+
+/// Language detected for a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// ISO 639-1 code
+    pub code: String,
+    /// English name of the language
+    pub name: String,
+    pub confidence: f64,
+}
+
+/// What to do with a loaded page
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranslateDecision {
+    /// The page is in the target language, or translation is off
+    Nothing,
+    /// Ask the user whether to translate from this language
+    Offer(DetectedLanguage),
+    /// Translate from this language right away
+    Translate(DetectedLanguage),
+}
+
+/// Text nodes a page reported with a `type: 'pagetext'` IPC message
+#[derive(Debug, Clone, Deserialize)]
+pub struct PageText {
+    pub url: String,
+    /// The page's `lang` attribute
+    pub lang: Option<String>,
+    pub texts: Vec<String>,
+}
+
+/// Detects page languages and translates pages through the configured backend
+pub struct Translator {
+    config: TranslateConfig,
+    sites: Mutex<HashMap<String, SitePreference>>,
+    sites_path: PathBuf,
+    client: reqwest::Client,
+}
+
+impl Translator {
+    /// Create new translator
+    pub fn new(config: Option<TranslateConfig>, config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        fs::create_dir_all(&config_dir)?;
+
+        let sites_path = config_dir.join("translate_sites.json");
+        let sites = if sites_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&sites_path)?)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            config: config.unwrap_or_default(),
+            sites: Mutex::new(sites),
+            sites_path,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Get translation configuration
+    pub fn config(&self) -> &TranslateConfig {
+        &self.config
+    }
+
+    /// Change the language pages are translated into
+    pub fn set_target_language(&mut self, code: &str) {
+        self.config.target_language = code.to_lowercase();
+    }
+
+    /// Detect the language of a text, if it is confident enough
+    pub fn detect_language(&self, text: &str) -> Option<DetectedLanguage> {
+        let info = whatlang::detect(text)?;
+        let code = iso_639_1(info.lang());
+        (info.confidence() >= self.config.min_confidence).then(|| DetectedLanguage {
+            code: code.to_string(),
+            name: info.lang().eng_name().to_string(),
+            confidence: info.confidence(),
+        })
+    }
+
+    /// Detect the language of a page from its text, falling back to the
+    /// language it declares when the text is too short to tell
+    pub fn detect_page_language(&self, page: &PageText) -> Option<DetectedLanguage> {
+        let sample: String = page.texts.iter().take(200).map(|t| format!("{} ", t)).collect();
+        self.detect_language(&sample).or_else(|| {
+            let code = page.lang.as_deref()?.split(['-', '_']).next()?.to_lowercase();
+            let lang = Lang::all().iter().find(|lang| iso_639_1(**lang) == code)?;
+            Some(DetectedLanguage {
+                code,
+                name: lang.eng_name().to_string(),
+                confidence: 0.0,
+            })
+        })
+    }
+
+    /// Decide whether to translate a page, offer it, or leave it alone
+    pub fn decide(&self, page: &PageText) -> TranslateDecision {
+        if !self.config.enabled {
+            return TranslateDecision::Nothing;
+        }
+
+        match (self.detect_page_language(page), self.site_preference(&page.url)) {
+            (Some(detected), _) if detected.code == self.config.target_language => TranslateDecision::Nothing,
+            (_, Some(SitePreference::NeverTranslate)) | (None, _) => TranslateDecision::Nothing,
+            (Some(detected), Some(SitePreference::AlwaysTranslate)) => TranslateDecision::Translate(detected),
+            (Some(detected), None) => TranslateDecision::Offer(detected),
+        }
+    }
+
+    /// Translation preference of the site a page belongs to
+    pub fn site_preference(&self, url: &str) -> Option<SitePreference> {
+        let host = site_key(url)?;
+        self.sites.lock_or_recover().get(&host).copied()
+    }
+
+    /// Remember a site's preference, or forget it with `None`
+    pub fn set_site_preference(&self, url: &str, preference: Option<SitePreference>) -> Result<(), WebxError> {
+        let host = site_key(url).ok_or("URL has no host")?;
+        let mut sites = self.sites.lock_or_recover();
+        match preference {
+            Some(preference) => sites.insert(host, preference),
+            None => sites.remove(&host),
+        };
+        fs::write(&self.sites_path, serde_json::to_string_pretty(&*sites)?)?;
+        Ok(())
+    }
+
+    /// Translate texts into the target language, keeping their order
+    pub async fn translate(&self, texts: &[String], source: &str) -> Result<Vec<String>, WebxError> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let mut translated = Vec::with_capacity(texts.len());
+        for batch in batches(texts, self.config.max_batch_chars) {
+            let result = match &self.config.backend {
+                TranslationBackend::LibreTranslate { endpoint, api_key } => {
+                    tokio::time::timeout(timeout, self.translate_remote(endpoint, api_key.as_deref(), batch, source))
+                        .await
+                }
+                TranslationBackend::LocalModel { command, args } => {
+                    tokio::time::timeout(timeout, self.translate_local(command, args, batch, source)).await
+                }
+            };
+            let batch_result =
+                result.map_err(|_| WebxError::Network("Translation service did not answer in time".to_string()))??;
+            if batch_result.len() != batch.len() {
+                return Err(WebxError::Parse(format!(
+                    "Translation service returned {} texts for {}",
+                    batch_result.len(),
+                    batch.len()
+                )));
+            }
+            translated.extend(batch_result);
+        }
+        Ok(translated)
+    }
+
+    /// Script replacing the text nodes collected by `COLLECT_TEXT_SCRIPT`
+    /// with their translations, keeping surrounding whitespace
+    pub fn replacement_script(&self, translations: &[String]) -> Result<String, WebxError> {
+        Ok(format!(
+            r#"
+(function() {{
+    const state = window.__webxTranslate;
+    if (!state) return;
+    const translations = {translations};
+    state.nodes.forEach((node, i) => {{
+        if (translations[i] === undefined) return;
+        const original = state.originals[i];
+        node.nodeValue = original.match(/^\s*/)[0] + translations[i] + original.match(/\s*$/)[0];
+    }});
+    const bar = document.getElementById('__webx-translate-bar');
+    if (bar) bar.remove();
+}})();
+"#,
+            translations = serde_json::to_string(translations)?,
+        ))
+    }
+
+    /// Script showing a bar that offers to translate from a language. Its
+    /// buttons answer with `type: 'translate'` IPC messages.
+    pub fn offer_script(&self, language: &DetectedLanguage) -> Result<String, WebxError> {
+        Ok(format!(
+            r#"
+(function() {{
+    if (document.getElementById('__webx-translate-bar')) return;
+    const bar = document.createElement('div');
+    bar.id = '__webx-translate-bar';
+    bar.style.cssText = 'position:fixed;top:0;left:0;right:0;z-index:2147483647;display:flex;gap:8px;' +
+        'align-items:center;padding:6px 12px;font:13px system-ui,sans-serif;background:#f1f3f4;color:#202124;' +
+        'border-bottom:1px solid #dadce0;';
+    const label = document.createElement('span');
+    label.textContent = 'This page is in ' + {language} + '. Translate it?';
+    label.style.flex = '1';
+    bar.appendChild(label);
+    const button = (text, action) => {{
+        const el = document.createElement('button');
+        el.textContent = text;
+        el.onclick = () => {{
+            bar.remove();
+            if (action) window.ipc.send({{ type: 'translate', action: action }});
+        }};
+        bar.appendChild(el);
+    }};
+    button('Translate', 'once');
+    button('Always translate this site', 'always');
+    button('Never translate this site', 'never');
+    button('✕', null);
+    document.documentElement.appendChild(bar);
+}})();
+"#,
+            language = serde_json::to_string(&language.name)?,
+        ))
+    }
+
+    // Private helper methods
+
+    async fn translate_remote(
+        &self,
+        endpoint: &str,
+        api_key: Option<&str>,
+        texts: &[String],
+        source: &str,
+    ) -> Result<Vec<String>, WebxError> {
+        let mut body = serde_json::json!({
+            "q": texts,
+            "source": source,
+            "target": self.config.target_language,
+            "format": "text",
+        });
+        if let Some(api_key) = api_key {
+            body["api_key"] = serde_json::Value::String(api_key.to_string());
+        }
+
+        let response: LibreTranslateResponse = self
+            .client
+            .post(format!("{}/translate", endpoint.trim_end_matches('/')))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.translated_text)
+    }
+
+    async fn translate_local(
+        &self,
+        command: &Path,
+        args: &[String],
+        texts: &[String],
+        source: &str,
+    ) -> Result<Vec<String>, WebxError> {
+        let args = args.iter().map(|arg| {
+            arg.replace("{source}", source)
+                .replace("{target}", &self.config.target_language)
+        });
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // One text per line, so line breaks inside a text become spaces
+        let input: String = texts.iter().map(|text| format!("{}\n", text.replace(['\r', '\n'], " "))).collect();
+        let mut stdin = child.stdin.take().ok_or("Translation program has no input")?;
+        stdin.write_all(input.as_bytes()).await?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(format!("Translation program failed with {}", output.status).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+    }
+}
+
+/// Response of LibreTranslate's `/translate` for a list of texts
+#[derive(Deserialize)]
+struct LibreTranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: Vec<String>,
+}
+
+// Private helper functions
+
+fn site_key(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    Some(url.host_str()?.trim_start_matches("www.").to_lowercase())
+}
+
+/// Split texts into runs of at most `max_chars` characters; a longer text
+/// gets a batch of its own
+fn batches(texts: &[String], max_chars: usize) -> Vec<&[String]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (i, text) in texts.iter().enumerate() {
+        let len = text.chars().count();
+        if i > start && chars + len > max_chars {
+            batches.push(&texts[start..i]);
+            start = i;
+            chars = 0;
+        }
+        chars += len;
+    }
+    if start < texts.len() {
+        batches.push(&texts[start..]);
+    }
+    batches
+}
+
+/// ISO 639-1 code of a detected language
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn page(url: &str, text: &str) -> PageText {
+        PageText {
+            url: url.to_string(),
+            lang: None,
+            texts: vec![text.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_decision_follows_site_preference() {
+        let temp_dir = TempDir::new().unwrap();
+        let translator = Translator::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        let german = "Der schnelle braune Fuchs springt über den faulen Hund und läuft dann nach Hause zurück.";
+        let english = "The quick brown fox jumps over the lazy dog and then runs back home again.";
+
+        assert_eq!(translator.decide(&page("https://news.example/a", english)), TranslateDecision::Nothing);
+        assert!(matches!(
+            translator.decide(&page("https://zeitung.example/a", german)),
+            TranslateDecision::Offer(ref lang) if lang.code == "de"
+        ));
+
+        translator
+            .set_site_preference("https://www.zeitung.example/", Some(SitePreference::AlwaysTranslate))
+            .unwrap();
+        let reopened = Translator::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(matches!(
+            reopened.decide(&page("https://zeitung.example/b", german)),
+            TranslateDecision::Translate(_)
+        ));
+        reopened
+            .set_site_preference("https://zeitung.example/", Some(SitePreference::NeverTranslate))
+            .unwrap();
+        assert_eq!(reopened.decide(&page("https://zeitung.example/c", german)), TranslateDecision::Nothing);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_local_model_translates_in_batches() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = TranslateConfig {
+            backend: TranslationBackend::LocalModel {
+                command: PathBuf::from("tr"),
+                args: vec!["a-z".to_string(), "A-Z".to_string()],
+            },
+            max_batch_chars: 8,
+            ..TranslateConfig::default()
+        };
+        let translator = Translator::new(Some(config), Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let texts = vec!["hello".to_string(), "multi\nline".to_string(), "world".to_string()];
+        assert_eq!(batches(&texts, 8).len(), 3);
+        let translated = translator.translate(&texts, "en").await.unwrap();
+        assert_eq!(translated, vec!["HELLO", "MULTI LINE", "WORLD"]);
+    }
+}
@@ -1,4 +1,5 @@
 // Bandwidth Usage Monitoring
+use crate::utils::LockExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -39,6 +40,7 @@ pub struct BandwidthMonitor {
     last_sample: Arc<Mutex<Option<BandwidthSample>>>,
     total_received: Arc<Mutex<u64>>,
     total_sent: Arc<Mutex<u64>>,
+    total_saved: Arc<Mutex<u64>>,
     start_time: Instant,
 }
 
@@ -51,6 +53,7 @@ impl BandwidthMonitor {
             last_sample: Arc::new(Mutex::new(None)),
             total_received: Arc::new(Mutex::new(0)),
             total_sent: Arc::new(Mutex::new(0)),
+            total_saved: Arc::new(Mutex::new(0)),
             start_time: Instant::now(),
         }
     }
@@ -64,8 +67,8 @@ impl BandwidthMonitor {
         let timestamp = chrono::Utc::now();
         
         // Update totals
-        *self.total_received.lock().unwrap() += bytes_received;
-        *self.total_sent.lock().unwrap() += bytes_sent;
+        *self.total_received.lock_or_recover() += bytes_received;
+        *self.total_sent.lock_or_recover() += bytes_sent;
 
         // Create new sample
         let sample = BandwidthSample {
@@ -75,11 +78,11 @@ impl BandwidthMonitor {
         };
 
         // Update last sample
-        *self.last_sample.lock().unwrap() = Some(sample.clone());
+        *self.last_sample.lock_or_recover() = Some(sample.clone());
 
         // Add to samples collection
         {
-            let mut samples = self.samples.lock().unwrap();
+            let mut samples = self.samples.lock_or_recover();
             samples.push(sample);
             
             // Trim old samples
@@ -87,9 +90,18 @@ impl BandwidthMonitor {
         }
     }
 
+    /// Record bytes that did not have to be transferred thanks to
+    /// optimizations such as image transcoding
+    pub fn record_savings(&self, bytes_saved: u64) {
+        if !self.config.enable_monitoring {
+            return;
+        }
+        *self.total_saved.lock_or_recover() += bytes_saved;
+    }
+
     /// Get current bandwidth usage (last second)
     pub fn get_current_bandwidth(&self) -> BandwidthUsage {
-        let samples = self.samples.lock().unwrap();
+        let samples = self.samples.lock_or_recover();
         
         if samples.is_empty() {
             return BandwidthUsage::default();
@@ -118,7 +130,7 @@ impl BandwidthMonitor {
 
     /// Get average bandwidth over time period
     pub fn get_average_bandwidth(&self, duration_minutes: u64) -> BandwidthUsage {
-        let samples = self.samples.lock().unwrap();
+        let samples = self.samples.lock_or_recover();
         
         if samples.is_empty() {
             return BandwidthUsage::default();
@@ -147,14 +159,16 @@ impl BandwidthMonitor {
 
     /// Get total data transferred
     pub fn get_total_transferred(&self) -> DataTransferStats {
-        let received = *self.total_received.lock().unwrap();
-        let sent = *self.total_sent.lock().unwrap();
+        let received = *self.total_received.lock_or_recover();
+        let sent = *self.total_sent.lock_or_recover();
+        let saved = *self.total_saved.lock_or_recover();
         let elapsed = self.start_time.elapsed().as_secs();
 
         DataTransferStats {
             total_received_bytes: received,
             total_sent_bytes: sent,
             total_bytes: received + sent,
+            total_saved_bytes: saved,
             session_duration_seconds: elapsed,
             average_download_kbps: if elapsed > 0 {
                 (received as f64 * 8.0 / 1000.0 / elapsed as f64) as u64
@@ -181,7 +195,7 @@ impl BandwidthMonitor {
 
     /// Get bandwidth history for charting
     pub fn get_bandwidth_history(&self, minutes: u64) -> Vec<BandwidthHistoryPoint> {
-        let samples = self.samples.lock().unwrap();
+        let samples = self.samples.lock_or_recover();
         let cutoff = chrono::Utc::now() - chrono::Duration::minutes(minutes as i64);
         
         samples
@@ -197,10 +211,11 @@ impl BandwidthMonitor {
 
     /// Reset counters
     pub fn reset(&self) {
-        *self.total_received.lock().unwrap() = 0;
-        *self.total_sent.lock().unwrap() = 0;
-        self.samples.lock().unwrap().clear();
-        *self.last_sample.lock().unwrap() = None;
+        *self.total_received.lock_or_recover() = 0;
+        *self.total_sent.lock_or_recover() = 0;
+        *self.total_saved.lock_or_recover() = 0;
+        self.samples.lock_or_recover().clear();
+        *self.last_sample.lock_or_recover() = None;
         // Note: start_time is not reset to maintain session continuity
     }
 
@@ -236,6 +251,8 @@ pub struct DataTransferStats {
     pub total_received_bytes: u64,
     pub total_sent_bytes: u64,
     pub total_bytes: u64,
+    #[serde(default)]
+    pub total_saved_bytes: u64,
     pub session_duration_seconds: u64,
     pub average_download_kbps: u64,
     pub average_upload_kbps: u64,
@@ -1,4 +1,5 @@
 // CSS Minification
+use crate::error::WebxError;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -35,7 +36,7 @@ pub struct CSSMinifier {
 
 impl CSSMinifier {
     /// Create new CSS minifier
-    pub fn new(config: Option<CSSMinificationConfig>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config: Option<CSSMinificationConfig>) -> Result<Self, WebxError> {
         let config = config.unwrap_or_default();
         
         Ok(Self {
@@ -43,12 +44,12 @@ impl CSSMinifier {
             comment_regex: Regex::new(r"/\*[\s\S]*?\*/")?,
             whitespace_regex: Regex::new(r"\s+")?,
             zero_value_regex: Regex::new(r"(?i)(\d+)px")?,
-            hex_color_regex: Regex::new(r"#([a-f0-9])\1([a-f0-9])\2([a-f0-9])\3")?,
+            hex_color_regex: Regex::new(r"(?i)#([a-f0-9]{6})\b")?,
         })
     }
 
     /// Minify CSS code
-    pub fn minify(&self, css_code: &str) -> Result<MinifiedCSS, Box<dyn std::error::Error>> {
+    pub fn minify(&self, css_code: &str) -> Result<MinifiedCSS, WebxError> {
         let original_size = css_code.len();
         let mut minified = css_code.to_string();
         
@@ -99,17 +100,17 @@ impl CSSMinifier {
     // Private helper methods
     
     fn optimize_hex_colors(&self, css: &str) -> String {
-        // Convert #ffffff to #fff, #000000 to #000, etc.
-        let mut result = css.to_string();
-        
-        // This is a simplified implementation
-        result = result.replace("#ffffff", "#fff");
-        result = result.replace("#000000", "#000");
-        result = result.replace("#ff0000", "#f00");
-        result = result.replace("#00ff00", "#0f0");
-        result = result.replace("#0000ff", "#00f");
-        
-        result
+        // Convert #ffffff to #fff, #aabbcc to #abc, etc.
+        self.hex_color_regex
+            .replace_all(css, |caps: &regex::Captures| {
+                let hex = caps[1].as_bytes();
+                if hex[0] == hex[1] && hex[2] == hex[3] && hex[4] == hex[5] {
+                    format!("#{}{}{}", hex[0] as char, hex[2] as char, hex[4] as char).to_lowercase()
+                } else {
+                    caps[0].to_string()
+                }
+            })
+            .to_string()
     }
     
     fn optimize_named_colors(&self, css: &str) -> String {
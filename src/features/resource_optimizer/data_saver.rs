@@ -0,0 +1,518 @@
+// Data Saver Mode
+use crate::error::WebxError;
+use super::bandwidth_monitor::BandwidthMonitor;
+use super::css_minifier::{CSSMinificationConfig, CSSMinifier};
+use super::image_optimizer::{ImageOptimizationConfig, ImageOptimizer, ImageOutputFormat};
+use super::javascript_minifier::{JSMinificationConfig, JavaScriptMinifier};
+use crate::core::{BrowserSettings, DataSaverProfile};
+use crate::features::caching::http_cache::{HTTPCache, HTTPCacheEntry};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Scripts and stylesheets smaller than this are not worth minifying
+const MIN_MINIFY_SIZE: usize = 1024;
+
+/// Average line length above which a resource is assumed to be minified
+const MINIFIED_LINE_LENGTH: usize = 250;
+
+/// Where responses come from when the network and the cache both have them
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CachePreference {
+    /// Revalidate with the network as usual
+    Network,
+    /// Serve cached copies, even ones that expired within `max_stale_secs`
+    PreferCache,
+}
+
+/// What a data saver profile turns on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataSaverPolicy {
+    pub optimize_images: bool,
+    pub image_quality: u8,
+    pub image_max_width: Option<u32>,
+    pub image_max_height: Option<u32>,
+    pub image_format: ImageOutputFormat,
+    pub minify_styles: bool,
+    /// Whitespace-only script minification; scripts whose regex literals
+    /// depend on repeated spaces can change behaviour
+    pub minify_scripts: bool,
+    pub lazy_load_images: bool,
+    pub lazy_load_iframes: bool,
+    pub block_autoplay: bool,
+    pub disable_prefetch: bool,
+    pub cache_preference: CachePreference,
+    pub max_stale_secs: u64,
+}
+
+impl DataSaverPolicy {
+    /// Policy for a profile
+    pub fn for_profile(profile: DataSaverProfile) -> Self {
+        match profile {
+            DataSaverProfile::Off => Self {
+                optimize_images: false,
+                image_quality: 100,
+                image_max_width: None,
+                image_max_height: None,
+                image_format: ImageOutputFormat::WebP,
+                minify_styles: false,
+                minify_scripts: false,
+                lazy_load_images: false,
+                lazy_load_iframes: false,
+                block_autoplay: false,
+                disable_prefetch: false,
+                cache_preference: CachePreference::Network,
+                max_stale_secs: 0,
+            },
+            DataSaverProfile::Moderate => Self {
+                optimize_images: true,
+                image_quality: 75,
+                image_max_width: Some(1920),
+                image_max_height: Some(1080),
+                image_format: ImageOutputFormat::WebP,
+                minify_styles: true,
+                minify_scripts: false,
+                lazy_load_images: true,
+                lazy_load_iframes: false,
+                block_autoplay: false,
+                disable_prefetch: true,
+                cache_preference: CachePreference::PreferCache,
+                max_stale_secs: 10 * 60,
+            },
+            DataSaverProfile::Aggressive => Self {
+                optimize_images: true,
+                image_quality: 50,
+                image_max_width: Some(1280),
+                image_max_height: Some(720),
+                image_format: ImageOutputFormat::Avif,
+                minify_styles: true,
+                minify_scripts: true,
+                lazy_load_images: true,
+                lazy_load_iframes: true,
+                block_autoplay: true,
+                disable_prefetch: true,
+                cache_preference: CachePreference::PreferCache,
+                max_stale_secs: 24 * 60 * 60,
+            },
+        }
+    }
+}
+
+/// A response body after data saver processing
+#[derive(Debug, Clone)]
+pub struct OptimizedResource {
+    pub data: Vec<u8>,
+    pub content_type: String,
+    pub original_size: usize,
+    pub optimized_size: usize,
+}
+
+/// Savings reported for the data saver
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DataSaverStats {
+    pub profile: DataSaverProfile,
+    pub images_optimized: u64,
+    pub scripts_minified: u64,
+    pub stylesheets_minified: u64,
+    pub cache_hits: u64,
+    pub original_bytes: u64,
+    pub delivered_bytes: u64,
+    pub image_bytes_saved: u64,
+    pub script_bytes_saved: u64,
+    pub style_bytes_saved: u64,
+    pub cache_bytes_saved: u64,
+}
+
+impl DataSaverStats {
+    /// Bytes saved across all optimizations
+    pub fn total_saved(&self) -> u64 {
+        self.image_bytes_saved + self.script_bytes_saved + self.style_bytes_saved + self.cache_bytes_saved
+    }
+
+    /// Share of the original traffic that was saved, in percent
+    pub fn savings_percent(&self) -> f64 {
+        let original = self.original_bytes + self.cache_bytes_saved;
+        if original == 0 {
+            0.0
+        } else {
+            self.total_saved() as f64 * 100.0 / original as f64
+        }
+    }
+}
+
+/// Single switch driving image optimization, minification, lazy loading
+/// and cache preference
+pub struct DataSaverMode {
+    profile: DataSaverProfile,
+    policy: DataSaverPolicy,
+    images: ImageOptimizer,
+    scripts: JavaScriptMinifier,
+    styles: CSSMinifier,
+    bandwidth: Option<Arc<BandwidthMonitor>>,
+    stats: Arc<Mutex<DataSaverStats>>,
+}
+
+impl DataSaverMode {
+    /// Create a data saver running the given profile
+    pub fn new(
+        profile: DataSaverProfile,
+        bandwidth: Option<Arc<BandwidthMonitor>>,
+    ) -> Result<Self, WebxError> {
+        let policy = DataSaverPolicy::for_profile(profile);
+        Ok(Self {
+            profile,
+            images: Self::build_image_optimizer(&policy, bandwidth.clone()),
+            scripts: Self::build_script_minifier()?,
+            styles: Self::build_style_minifier()?,
+            policy,
+            bandwidth,
+            stats: Arc::new(Mutex::new(DataSaverStats {
+                profile,
+                ..Default::default()
+            })),
+        })
+    }
+
+    /// Create a data saver for the profile chosen in the browser settings
+    pub fn from_settings(
+        settings: &BrowserSettings,
+        bandwidth: Option<Arc<BandwidthMonitor>>,
+    ) -> Result<Self, WebxError> {
+        Self::new(settings.data_saver, bandwidth)
+    }
+
+    /// Switch to another profile
+    pub fn set_profile(&mut self, profile: DataSaverProfile) {
+        self.apply_profile(profile);
+        self.stats.lock_or_recover().profile = profile;
+    }
+
+    /// Get the active profile
+    pub fn profile(&self) -> DataSaverProfile {
+        self.profile
+    }
+
+    /// Get the policy of the active profile
+    pub fn policy(&self) -> &DataSaverPolicy {
+        &self.policy
+    }
+
+    /// Check if the data saver is on
+    pub fn is_enabled(&self) -> bool {
+        self.profile != DataSaverProfile::Off
+    }
+
+    /// Headers added to every request; `Save-Data` lets servers send
+    /// lighter pages themselves
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        if self.is_enabled() {
+            vec![("Save-Data".to_string(), "on".to_string())]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Run a response body through the optimizations the profile enables
+    pub fn optimize_response(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<OptimizedResource, WebxError> {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        let original_size = body.len();
+        let unchanged = || OptimizedResource {
+            data: body.to_vec(),
+            content_type: content_type.to_string(),
+            original_size,
+            optimized_size: original_size,
+        };
+
+        let result = if mime.starts_with("image/") && self.policy.optimize_images {
+            let optimized = self.images.optimize_image(body, content_type)?;
+            let saved = (optimized.original_size - optimized.optimized_size) as u64;
+            if saved > 0 {
+                let mut stats = self.stats.lock_or_recover();
+                stats.images_optimized += 1;
+                stats.image_bytes_saved += saved;
+            }
+            OptimizedResource {
+                data: optimized.data,
+                content_type: optimized.content_type,
+                original_size,
+                optimized_size: optimized.optimized_size,
+            }
+        } else if is_script(&mime) && self.policy.minify_scripts && self.should_minify(url, body) {
+            let code = String::from_utf8_lossy(body);
+            let minified = self.scripts.minify(&code)?;
+            self.record_minification(minified.savings_bytes as u64, true);
+            OptimizedResource {
+                optimized_size: minified.code.len(),
+                data: minified.code.into_bytes(),
+                content_type: content_type.to_string(),
+                original_size,
+            }
+        } else if mime == "text/css" && self.policy.minify_styles && self.should_minify(url, body) {
+            let code = String::from_utf8_lossy(body);
+            let minified = self.styles.minify(&code)?;
+            self.record_minification(minified.savings_bytes as u64, false);
+            OptimizedResource {
+                optimized_size: minified.code.len(),
+                data: minified.code.into_bytes(),
+                content_type: content_type.to_string(),
+                original_size,
+            }
+        } else {
+            unchanged()
+        };
+
+        let mut stats = self.stats.lock_or_recover();
+        stats.original_bytes += original_size as u64;
+        stats.delivered_bytes += result.optimized_size as u64;
+        Ok(result)
+    }
+
+    /// Look up a response in the cache according to the cache preference,
+    /// counting served entries as saved traffic
+    pub fn cached_response(&self, cache: &mut HTTPCache, url: &str) -> Option<HTTPCacheEntry> {
+        let entry = match self.policy.cache_preference {
+            CachePreference::Network => cache.get_response(url),
+            CachePreference::PreferCache => {
+                cache.get_response_allowing_stale(url, Duration::from_secs(self.policy.max_stale_secs))
+            }
+        }?;
+
+        if self.is_enabled() {
+            let saved = entry.content_length as u64;
+            {
+                let mut stats = self.stats.lock_or_recover();
+                stats.cache_hits += 1;
+                stats.cache_bytes_saved += saved;
+            }
+            if let Some(monitor) = &self.bandwidth {
+                monitor.record_savings(saved);
+            }
+        }
+        Some(entry)
+    }
+
+    /// Script injected into pages for lazy loading, autoplay blocking and
+    /// prefetch removal
+    pub fn get_page_script(&self) -> String {
+        if !self.is_enabled() {
+            return String::new();
+        }
+
+        format!(
+            r#"
+(function() {{
+    const lazyImages = {lazy_images};
+    const lazyIframes = {lazy_iframes};
+    const blockAutoplay = {block_autoplay};
+    const disablePrefetch = {disable_prefetch};
+
+    function apply(root) {{
+        if (!root.querySelectorAll) return;
+        if (lazyImages) {{
+            root.querySelectorAll('img:not([loading])').forEach(img => img.loading = 'lazy');
+        }}
+        if (lazyIframes) {{
+            root.querySelectorAll('iframe:not([loading])').forEach(frame => frame.loading = 'lazy');
+        }}
+        if (blockAutoplay) {{
+            root.querySelectorAll('video, audio').forEach(media => {{
+                media.autoplay = false;
+                media.preload = 'none';
+                if (!media.paused) media.pause();
+            }});
+        }}
+        if (disablePrefetch) {{
+            root.querySelectorAll('link[rel~="prefetch"], link[rel~="prerender"], link[rel~="dns-prefetch"]')
+                .forEach(link => link.remove());
+        }}
+    }}
+
+    apply(document);
+    new MutationObserver(mutations => {{
+        mutations.forEach(mutation => {{
+            mutation.addedNodes.forEach(node => {{
+                if (node.nodeType === 1) apply(node.parentNode || node);
+            }});
+        }});
+    }}).observe(document.documentElement, {{ childList: true, subtree: true }});
+}})();
+"#,
+            lazy_images = self.policy.lazy_load_images,
+            lazy_iframes = self.policy.lazy_load_iframes,
+            block_autoplay = self.policy.block_autoplay,
+            disable_prefetch = self.policy.disable_prefetch,
+        )
+    }
+
+    /// Get savings statistics
+    pub fn get_stats(&self) -> DataSaverStats {
+        self.stats.lock_or_recover().clone()
+    }
+
+    /// Reset savings statistics
+    pub fn reset_stats(&self) {
+        *self.stats.lock_or_recover() = DataSaverStats {
+            profile: self.profile,
+            ..Default::default()
+        };
+    }
+
+    // Private helper methods
+
+    fn apply_profile(&mut self, profile: DataSaverProfile) {
+        self.profile = profile;
+        self.policy = DataSaverPolicy::for_profile(profile);
+        self.images = Self::build_image_optimizer(&self.policy, self.bandwidth.clone());
+    }
+
+    fn build_image_optimizer(policy: &DataSaverPolicy, bandwidth: Option<Arc<BandwidthMonitor>>) -> ImageOptimizer {
+        let mut optimizer = ImageOptimizer::new(Some(ImageOptimizationConfig {
+            enable_compression: policy.optimize_images,
+            quality: policy.image_quality,
+            enable_lazy_loading: policy.lazy_load_images,
+            enable_webp_conversion: true,
+            output_format: policy.image_format,
+            max_width: policy.image_max_width,
+            max_height: policy.image_max_height,
+            ..Default::default()
+        }));
+        if let Some(monitor) = bandwidth {
+            optimizer.set_bandwidth_monitor(monitor);
+        }
+        optimizer
+    }
+
+    fn build_script_minifier() -> Result<JavaScriptMinifier, WebxError> {
+        // Comment and console stripping are regex based and can corrupt
+        // string literals, so only whitespace is touched
+        JavaScriptMinifier::new(Some(JSMinificationConfig {
+            remove_whitespace: true,
+            remove_comments: false,
+            shorten_variable_names: false,
+            remove_console_logs: false,
+            optimize_booleans: false,
+        }))
+    }
+
+    fn build_style_minifier() -> Result<CSSMinifier, WebxError> {
+        // Named color and unit rewriting is not context aware
+        CSSMinifier::new(Some(CSSMinificationConfig {
+            remove_whitespace: true,
+            remove_comments: true,
+            remove_unused_selectors: false,
+            optimize_colors: false,
+            shorten_zero_values: false,
+        }))
+    }
+
+    fn should_minify(&self, url: &str, body: &[u8]) -> bool {
+        if body.len() < MIN_MINIFY_SIZE || url.contains(".min.") {
+            return false;
+        }
+
+        // Already minified resources are mostly very long lines
+        let lines = body.iter().filter(|&&b| b == b'\n').count() + 1;
+        body.len() / lines < MINIFIED_LINE_LENGTH
+    }
+
+    fn record_minification(&self, saved: u64, script: bool) {
+        {
+            let mut stats = self.stats.lock_or_recover();
+            if script {
+                stats.scripts_minified += 1;
+                stats.script_bytes_saved += saved;
+            } else {
+                stats.stylesheets_minified += 1;
+                stats.style_bytes_saved += saved;
+            }
+        }
+        if let Some(monitor) = &self.bandwidth {
+            monitor.record_savings(saved);
+        }
+    }
+}
+
+fn is_script(mime: &str) -> bool {
+    matches!(
+        mime,
+        "application/javascript" | "text/javascript" | "application/x-javascript" | "application/ecmascript"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stylesheet() -> String {
+        (0..80)
+            .map(|i| format!("/* rule {} */\n.item-{} {{\n    margin: 4px;\n    color: #336699;\n}}\n", i, i))
+            .collect()
+    }
+
+    #[test]
+    fn test_profiles_drive_minification() {
+        let monitor = Arc::new(BandwidthMonitor::new(None));
+        let mut saver = DataSaverMode::new(DataSaverProfile::Off, Some(monitor.clone())).unwrap();
+        let css = stylesheet();
+
+        let result = saver.optimize_response("https://example.com/site.css", "text/css", css.as_bytes()).unwrap();
+        assert_eq!(result.optimized_size, css.len());
+        assert!(saver.request_headers().is_empty());
+        assert!(saver.get_page_script().is_empty());
+
+        saver.set_profile(DataSaverProfile::Moderate);
+        let result = saver.optimize_response("https://example.com/site.css", "text/css; charset=utf-8", css.as_bytes()).unwrap();
+        assert!(result.optimized_size < css.len());
+        assert!(!String::from_utf8(result.data).unwrap().contains("/* rule"));
+
+        // Already minified files are left alone
+        let result = saver.optimize_response("https://example.com/site.min.css", "text/css", css.as_bytes()).unwrap();
+        assert_eq!(result.optimized_size, css.len());
+
+        let stats = saver.get_stats();
+        assert_eq!(stats.profile, DataSaverProfile::Moderate);
+        assert_eq!(stats.stylesheets_minified, 1);
+        assert!(stats.savings_percent() > 0.0);
+        assert_eq!(monitor.get_total_transferred().total_saved_bytes, stats.style_bytes_saved);
+        assert_eq!(saver.request_headers()[0].0, "Save-Data");
+    }
+
+    #[test]
+    fn test_prefer_cache_serves_stale_entries() {
+        let saver = DataSaverMode::new(DataSaverProfile::Aggressive, None).unwrap();
+        let mut cache = HTTPCache::new(1, 60, false);
+        let mut headers = std::collections::HashMap::new();
+        headers.insert("cache-control".to_string(), "max-age=0".to_string());
+        cache
+            .store_response("https://example.com/".to_string(), 200, headers, vec![0; 2048])
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(saver.cached_response(&mut cache, "https://example.com/").is_some());
+        assert_eq!(saver.get_stats().cache_bytes_saved, 2048);
+
+        let off = DataSaverMode::new(DataSaverProfile::Off, None).unwrap();
+        assert!(off.cached_response(&mut cache, "https://example.com/").is_none());
+    }
+
+    #[test]
+    fn test_script_minification_keeps_code_valid() {
+        let saver = DataSaverMode::new(DataSaverProfile::Aggressive, None).unwrap();
+        let script: String = (0..60)
+            .map(|i| format!("let value{} = typeof  window   === 'object'\nreturnValue(value{})\n", i, i))
+            .collect();
+
+        let result = saver
+            .optimize_response("https://example.com/app.js", "application/javascript", script.as_bytes())
+            .unwrap();
+        let code = String::from_utf8(result.data).unwrap();
+        assert!(result.optimized_size < script.len());
+        assert!(code.contains("let value0=typeof window==='object'\nreturnValue(value0)"));
+    }
+}
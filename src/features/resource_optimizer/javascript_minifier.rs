@@ -1,4 +1,5 @@
 // JavaScript Minification
+use crate::error::WebxError;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -34,7 +35,7 @@ pub struct JavaScriptMinifier {
 
 impl JavaScriptMinifier {
     /// Create new JavaScript minifier
-    pub fn new(config: Option<JSMinificationConfig>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config: Option<JSMinificationConfig>) -> Result<Self, WebxError> {
         let config = config.unwrap_or_default();
         
         Ok(Self {
@@ -46,7 +47,7 @@ impl JavaScriptMinifier {
     }
 
     /// Minify JavaScript code
-    pub fn minify(&self, js_code: &str) -> Result<MinifiedJS, Box<dyn std::error::Error>> {
+    pub fn minify(&self, js_code: &str) -> Result<MinifiedJS, WebxError> {
         let original_size = js_code.len();
         let mut minified = js_code.to_string();
         
@@ -99,38 +100,49 @@ impl JavaScriptMinifier {
         let mut result = String::new();
         let mut in_string = false;
         let mut string_char = '\0';
+        let mut escaped = false;
         let mut chars = code.chars().peekable();
         
         while let Some(ch) = chars.next() {
+            if in_string {
+                result.push(ch);
+                if escaped {
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == string_char {
+                    in_string = false;
+                }
+                continue;
+            }
+
             match ch {
-                '"' | '\'' => {
-                    if !in_string {
-                        in_string = true;
-                        string_char = ch;
-                        result.push(ch);
-                    } else if ch == string_char {
-                        // Check for escaped quote
-                        let prev_is_escape = result.chars().last() == Some('\\');
-                        if !prev_is_escape {
-                            in_string = false;
-                        }
-                        result.push(ch);
-                    } else {
-                        result.push(ch);
-                    }
+                '"' | '\'' | '`' => {
+                    in_string = true;
+                    string_char = ch;
+                    result.push(ch);
                 }
                 ' ' | '\t' | '\n' | '\r' => {
-                    if in_string {
-                        result.push(ch);
-                    } else {
-                        // Only preserve necessary whitespace
-                        let next_ch = chars.peek();
-                        if let Some(&next) = next_ch {
-                            // Preserve space between certain tokens
-                            if self.needs_space_preserved(result.chars().last(), Some(next)) {
-                                result.push(' ');
-                            }
+                    // Collapse the whole run of whitespace
+                    let mut has_newline = ch == '\n' || ch == '\r';
+                    while let Some(&next) = chars.peek() {
+                        if !next.is_whitespace() {
+                            break;
                         }
+                        has_newline |= next == '\n' || next == '\r';
+                        chars.next();
+                    }
+
+                    let prev = result.chars().last();
+                    let next = chars.peek().copied();
+                    if prev.is_none() || next.is_none() {
+                        continue;
+                    }
+                    if has_newline {
+                        // Line breaks are kept for automatic semicolon insertion
+                        result.push('\n');
+                    } else if self.needs_space_preserved(prev, next) {
+                        result.push(' ');
                     }
                 }
                 _ => {
@@ -143,13 +155,12 @@ impl JavaScriptMinifier {
     }
     
     fn needs_space_preserved(&self, prev: Option<char>, next: Option<char>) -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
         match (prev, next) {
-            (Some('r'), Some(_)) => true, // return
-            (Some(_), Some('f')) => true, // function
-            (Some('i'), Some('(')) => true, // if(
-            (Some('f'), Some('(')) => true, // for(
-            (Some('w'), Some('(')) => true, // while(
-            (Some('c'), Some('(')) => true, // catch(
+            // Keywords and identifiers: `return x`, `typeof y`, `var z`
+            (Some(a), Some(b)) if is_word(a) && is_word(b) => true,
+            // `a + +b` and `a - -b` must not become increments
+            (Some('+'), Some('+')) | (Some('-'), Some('-')) => true,
             _ => false,
         }
     }
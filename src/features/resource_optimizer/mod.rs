@@ -3,8 +3,10 @@ pub mod image_optimizer;
 pub mod javascript_minifier;
 pub mod css_minifier;
 pub mod bandwidth_monitor;
+pub mod data_saver;
 
 pub use image_optimizer::ImageOptimizer;
 pub use javascript_minifier::JavaScriptMinifier;
 pub use css_minifier::CSSMinifier;
-pub use bandwidth_monitor::BandwidthMonitor;
\ No newline at end of file
+pub use bandwidth_monitor::BandwidthMonitor;
+pub use data_saver::{DataSaverMode, DataSaverPolicy, DataSaverStats};
\ No newline at end of file
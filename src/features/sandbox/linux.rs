@@ -0,0 +1,239 @@
+// Linux Sandbox Hooks (AppArmor and seccomp)
+use crate::error::WebxError;
+use super::policy::{IsolationLevel, SandboxPolicy};
+use super::SandboxHook;
+use std::path::Path;
+
+const AUDIT_ARCH_X86_64: u32 = 0xC000_003E;
+const AUDIT_ARCH_AARCH64: u32 = 0xC000_00B7;
+
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7FFF_0000;
+
+// Classic BPF opcodes
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_RET_K: u16 = 0x06;
+
+// Offsets into struct seccomp_data
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+
+/// Syscalls no content process needs
+const STANDARD_DENIED: &[(&str, libc::c_long)] = &[
+    ("ptrace", libc::SYS_ptrace),
+    ("process_vm_readv", libc::SYS_process_vm_readv),
+    ("process_vm_writev", libc::SYS_process_vm_writev),
+    ("kexec_load", libc::SYS_kexec_load),
+    ("init_module", libc::SYS_init_module),
+    ("finit_module", libc::SYS_finit_module),
+    ("delete_module", libc::SYS_delete_module),
+    ("mount", libc::SYS_mount),
+    ("umount2", libc::SYS_umount2),
+    ("pivot_root", libc::SYS_pivot_root),
+    ("swapon", libc::SYS_swapon),
+    ("swapoff", libc::SYS_swapoff),
+    ("reboot", libc::SYS_reboot),
+    ("bpf", libc::SYS_bpf),
+    ("perf_event_open", libc::SYS_perf_event_open),
+    ("keyctl", libc::SYS_keyctl),
+    ("add_key", libc::SYS_add_key),
+    ("request_key", libc::SYS_request_key),
+];
+
+/// Additional syscalls denied under strict isolation
+const STRICT_DENIED: &[(&str, libc::c_long)] = &[
+    ("chroot", libc::SYS_chroot),
+    ("setns", libc::SYS_setns),
+    ("unshare", libc::SYS_unshare),
+    ("personality", libc::SYS_personality),
+    ("userfaultfd", libc::SYS_userfaultfd),
+    ("io_uring_setup", libc::SYS_io_uring_setup),
+];
+
+/// A seccomp deny-list filter for a content process
+#[derive(Debug, Clone)]
+pub struct SeccompFilter {
+    denied: Vec<(&'static str, libc::c_long)>,
+}
+
+impl SeccompFilter {
+    /// Build the filter for an isolation level
+    pub fn for_level(level: IsolationLevel) -> Self {
+        let mut denied = Vec::new();
+        if level >= IsolationLevel::Standard {
+            denied.extend_from_slice(STANDARD_DENIED);
+        }
+        if level >= IsolationLevel::Strict {
+            denied.extend_from_slice(STRICT_DENIED);
+        }
+        Self { denied }
+    }
+
+    /// Names of the syscalls the filter rejects
+    pub fn denied_syscalls(&self) -> Vec<&'static str> {
+        self.denied.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Compile the filter to classic BPF: kill on foreign arch, EPERM for denied calls
+    pub fn to_bpf(&self) -> Vec<libc::sock_filter> {
+        let mut program = vec![
+            bpf_stmt(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+            bpf_jump(BPF_JMP_JEQ_K, native_audit_arch(), 1, 0),
+            bpf_stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            bpf_stmt(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+        ];
+        for (_, nr) in &self.denied {
+            program.push(bpf_jump(BPF_JMP_JEQ_K, *nr as u32, 0, 1));
+            program.push(bpf_stmt(BPF_RET_K, SECCOMP_RET_ERRNO | libc::EPERM as u32));
+        }
+        program.push(bpf_stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+        program
+    }
+
+    /// Install the filter on the calling thread and its future children.
+    /// Irreversible: only call this inside a tab's content process.
+    pub fn install(&self) -> Result<(), WebxError> {
+        let program = self.to_bpf();
+        let prog = libc::sock_fprog {
+            len: program.len() as libc::c_ushort,
+            filter: program.as_ptr() as *mut libc::sock_filter,
+        };
+
+        // SAFETY: `prog` points at `program`, which outlives both calls
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            if libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &prog as *const libc::sock_fprog,
+            ) != 0
+            {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Installs the level's seccomp filter
+pub struct SeccompHook;
+
+impl SandboxHook for SeccompHook {
+    fn name(&self) -> &str {
+        "seccomp"
+    }
+
+    fn is_available(&self) -> bool {
+        cfg!(any(target_arch = "x86_64", target_arch = "aarch64"))
+            && Path::new("/proc/sys/kernel/seccomp/actions_avail").exists()
+    }
+
+    fn apply(&self, policy: &SandboxPolicy) -> Result<(), WebxError> {
+        if !policy.seccomp {
+            return Ok(());
+        }
+        SeccompFilter::for_level(policy.level).install()
+    }
+}
+
+/// Switches the process to the policy's AppArmor profile
+pub struct AppArmorHook;
+
+impl AppArmorHook {
+    /// Command written to the process's AppArmor attribute to change profile
+    pub fn change_profile_command(profile: &str) -> String {
+        format!("changeprofile {}", profile)
+    }
+
+    /// Check if a profile is loaded in the kernel
+    pub fn is_profile_loaded(profile: &str) -> bool {
+        std::fs::read_to_string("/sys/kernel/security/apparmor/profiles")
+            .map(|profiles| {
+                profiles
+                    .lines()
+                    .any(|line| line.split(" (").next() == Some(profile))
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl SandboxHook for AppArmorHook {
+    fn name(&self) -> &str {
+        "apparmor"
+    }
+
+    fn is_available(&self) -> bool {
+        std::fs::read_to_string("/sys/module/apparmor/parameters/enabled")
+            .map(|enabled| enabled.trim() == "Y")
+            .unwrap_or(false)
+    }
+
+    fn apply(&self, policy: &SandboxPolicy) -> Result<(), WebxError> {
+        let Some(profile) = &policy.apparmor_profile else {
+            return Ok(());
+        };
+        if !Self::is_profile_loaded(profile) {
+            return Err(format!("AppArmor profile {} is not loaded", profile).into());
+        }
+
+        // Newer kernels expose a per-LSM attribute directory
+        let attr = ["/proc/thread-self/attr/apparmor/current", "/proc/thread-self/attr/current"]
+            .into_iter()
+            .find(|path| Path::new(path).exists())
+            .ok_or("AppArmor process attributes are not available")?;
+        std::fs::write(attr, Self::change_profile_command(profile))?;
+        Ok(())
+    }
+}
+
+/// Hooks available on Linux
+pub fn platform_hooks() -> Vec<Box<dyn SandboxHook>> {
+    vec![Box::new(AppArmorHook), Box::new(SeccompHook)]
+}
+
+fn native_audit_arch() -> u32 {
+    if cfg!(target_arch = "aarch64") {
+        AUDIT_ARCH_AARCH64
+    } else {
+        AUDIT_ARCH_X86_64
+    }
+}
+
+fn bpf_stmt(code: u16, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code, jt: 0, jf: 0, k }
+}
+
+fn bpf_jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code, jt, jf, k }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seccomp_filter_program() {
+        assert!(SeccompFilter::for_level(IsolationLevel::Trusted).denied_syscalls().is_empty());
+
+        let standard = SeccompFilter::for_level(IsolationLevel::Standard);
+        let strict = SeccompFilter::for_level(IsolationLevel::Strict);
+        assert!(standard.denied_syscalls().contains(&"ptrace"));
+        assert!(!standard.denied_syscalls().contains(&"unshare"));
+        assert!(strict.denied_syscalls().contains(&"unshare"));
+
+        let program = strict.to_bpf();
+        // Arch check (3) + load nr (1) + two per denied call + final allow
+        assert_eq!(program.len(), 4 + 2 * strict.denied_syscalls().len() + 1);
+        assert_eq!(program[1].k, native_audit_arch());
+        assert_eq!(program.last().unwrap().k, SECCOMP_RET_ALLOW);
+        assert_eq!(program[5].k, SECCOMP_RET_ERRNO | libc::EPERM as u32);
+        assert_eq!(
+            AppArmorHook::change_profile_command("webx-content-strict"),
+            "changeprofile webx-content-strict"
+        );
+    }
+}
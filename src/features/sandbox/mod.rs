@@ -1,12 +1,334 @@
-// Sandbox Module - Placeholder
-pub struct Sandbox;
+// Sandbox Module
+pub mod policy;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+pub use policy::{IpcSurface, IsolationLevel, SandboxPolicy};
+
+use crate::error::WebxError;
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Platform mechanism that enforces a policy inside a tab's content process
+pub trait SandboxHook: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Check if the mechanism is supported on this system
+    fn is_available(&self) -> bool;
+
+    /// Restrict the calling process; irreversible, so only call it in the content process
+    fn apply(&self, policy: &SandboxPolicy) -> Result<(), WebxError>;
+}
+
+/// Sandbox configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Level for web origins without a rule
+    pub default_level: IsolationLevel,
+    /// Host rules; a rule for "example.com" also covers its subdomains
+    pub origin_rules: HashMap<String, IsolationLevel>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            default_level: IsolationLevel::Standard,
+            origin_rules: HashMap::new(),
+        }
+    }
+}
+
+/// Per-tab process isolation policy engine
+pub struct Sandbox {
+    config: Arc<Mutex<SandboxConfig>>,
+    config_path: PathBuf,
+    tabs: Arc<Mutex<HashMap<usize, SandboxPolicy>>>,
+    hooks: Vec<Box<dyn SandboxHook>>,
+}
 
 impl Sandbox {
-    pub fn new() -> Self {
-        Self
+    /// Create new sandbox
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        #[cfg(target_os = "linux")]
+        let hooks = linux::platform_hooks();
+        #[cfg(not(target_os = "linux"))]
+        let hooks: Vec<Box<dyn SandboxHook>> = Vec::new();
+
+        let sandbox = Self {
+            config: Arc::new(Mutex::new(SandboxConfig::default())),
+            config_path: config_dir.join("sandbox.json"),
+            tabs: Arc::new(Mutex::new(HashMap::new())),
+            hooks,
+        };
+
+        sandbox.load_config()?;
+
+        Ok(sandbox)
+    }
+
+    /// Policy for content from an origin (a URL or bare host)
+    pub fn policy_for(&self, origin: &str) -> SandboxPolicy {
+        SandboxPolicy::for_level(self.level_for(origin))
+    }
+
+    /// Isolation level for content from an origin
+    pub fn level_for(&self, origin: &str) -> IsolationLevel {
+        let parsed = url::Url::parse(origin)
+            .or_else(|_| url::Url::parse(&format!("https://{}", origin)));
+        let Ok(url) = parsed else {
+            return IsolationLevel::Strict;
+        };
+
+        match url.scheme() {
+            "webx" | "about" => IsolationLevel::Trusted,
+            "http" | "https" => {
+                let config = self.config.lock_or_recover();
+                url.host_str()
+                    .and_then(|host| Self::matching_rule(&config.origin_rules, host))
+                    .unwrap_or(config.default_level)
+            }
+            // file://, data:, blob: and unknown schemes get opaque, untrusted origins
+            _ => IsolationLevel::Strict,
+        }
+    }
+
+    /// Set the level for origins without a rule
+    pub fn set_default_level(&self, level: IsolationLevel) -> Result<(), WebxError> {
+        if level == IsolationLevel::Trusted {
+            return Err("Web content cannot be trusted by default".into());
+        }
+        self.config.lock_or_recover().default_level = level;
+        self.save_config()
+    }
+
+    /// Get the level for origins without a rule
+    pub fn get_default_level(&self) -> IsolationLevel {
+        self.config.lock_or_recover().default_level
+    }
+
+    /// Set the isolation level for a site and its subdomains
+    pub fn set_origin_level(&self, origin: &str, level: IsolationLevel) -> Result<(), WebxError> {
+        let host = Self::normalize_host(origin).ok_or("Invalid origin")?;
+        if level == IsolationLevel::Trusted {
+            return Err("Only built-in pages can be trusted".into());
+        }
+        self.config.lock_or_recover().origin_rules.insert(host, level);
+        self.save_config()
+    }
+
+    /// Remove a site rule
+    pub fn remove_origin_level(&self, origin: &str) -> Result<bool, WebxError> {
+        let Some(host) = Self::normalize_host(origin) else {
+            return Ok(false);
+        };
+        let removed = self.config.lock_or_recover().origin_rules.remove(&host).is_some();
+        if removed {
+            self.save_config()?;
+        }
+        Ok(removed)
+    }
+
+    /// List site rules
+    pub fn list_origin_rules(&self) -> Vec<(String, IsolationLevel)> {
+        let mut rules: Vec<(String, IsolationLevel)> = self
+            .config
+            .lock_or_recover()
+            .origin_rules
+            .iter()
+            .map(|(host, level)| (host.clone(), *level))
+            .collect();
+        rules.sort();
+        rules
+    }
+
+    /// Compute and remember the policy for a tab about to load an origin
+    pub fn assign_tab(&self, tab_id: usize, origin: &str) -> SandboxPolicy {
+        let policy = self.policy_for(origin);
+        self.tabs.lock_or_recover().insert(tab_id, policy.clone());
+        policy
+    }
+
+    /// Get the policy a tab is running under
+    pub fn tab_policy(&self, tab_id: usize) -> Option<SandboxPolicy> {
+        self.tabs.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    /// Forget a closed tab
+    pub fn release_tab(&self, tab_id: usize) {
+        self.tabs.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Check if a tab may send an IPC message; unknown tabs may not
+    pub fn check_ipc(&self, tab_id: usize, channel: &str) -> bool {
+        self.tab_policy(tab_id)
+            .map(|policy| policy.ipc.allows(channel))
+            .unwrap_or(false)
+    }
+
+    /// Check if a tab may navigate to or load a URL
+    pub fn check_navigation(&self, tab_id: usize, url: &str) -> bool {
+        self.tab_policy(tab_id)
+            .map(|policy| policy.allows_url(url))
+            .unwrap_or(false)
+    }
+
+    /// Names of the platform hooks supported on this system
+    pub fn available_hooks(&self) -> Vec<String> {
+        self.hooks
+            .iter()
+            .filter(|hook| hook.is_available())
+            .map(|hook| hook.name().to_string())
+            .collect()
+    }
+
+    /// Apply every available platform hook; call from the content process before loading the page
+    pub fn apply_hooks(&self, policy: &SandboxPolicy) -> Vec<(String, Result<(), String>)> {
+        self.hooks
+            .iter()
+            .filter(|hook| hook.is_available())
+            .map(|hook| {
+                let result = hook.apply(policy).map_err(|e| e.to_string());
+                if let Err(e) = &result {
+                    tracing::warn!("Sandbox hook {} failed: {}", hook.name(), e);
+                }
+                (hook.name().to_string(), result)
+            })
+            .collect()
+    }
+
+    // Private helper methods
+
+    fn matching_rule(rules: &HashMap<String, IsolationLevel>, host: &str) -> Option<IsolationLevel> {
+        let host = host.trim_start_matches("www.");
+        let mut candidate = host;
+        loop {
+            if let Some(level) = rules.get(candidate) {
+                return Some(*level);
+            }
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+
+    fn normalize_host(origin: &str) -> Option<String> {
+        let url = url::Url::parse(origin)
+            .or_else(|_| url::Url::parse(&format!("https://{}", origin)))
+            .ok()?;
+        url.host_str()
+            .map(|host| host.trim_start_matches("www.").to_lowercase())
+    }
+
+    fn save_config(&self) -> Result<(), WebxError> {
+        let config = self.config.lock_or_recover();
+        let content = serde_json::to_string_pretty(&*config)?;
+        std::fs::write(&self.config_path, content)?;
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<(), WebxError> {
+        if self.config_path.exists() {
+            let content = std::fs::read_to_string(&self.config_path)?;
+            *self.config.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
     }
-    
-    pub fn isolate_content(&self, _content: &str) {
-        // Placeholder implementation
+}
+
+impl SettingsProvider for Sandbox {
+    fn module(&self) -> &str {
+        "sandbox"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::choice(
+            "sandbox.default_level",
+            "Default site isolation",
+            IsolationLevel::Standard.name(),
+            &[
+                ("standard", "Standard"),
+                ("strict", "Strict (minimal browser access)"),
+                ("locked", "Locked (JavaScript disabled)"),
+            ],
+        )
+        .with_description("How tightly web pages are isolated from the browser and the system")
+        .with_category(SettingCategory::Security)
+        .with_restart_required()]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "sandbox.default_level" => {
+                let name = value.as_str().ok_or("Expected a choice value")?;
+                let level = IsolationLevel::from_name(name).ok_or("Unknown isolation level")?;
+                self.set_default_level(level)
+            }
+            _ => Err(format!("Unknown sandbox setting {}", key).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_policy_for_origin() {
+        let temp_dir = TempDir::new().unwrap();
+        let sandbox = Sandbox::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        assert_eq!(sandbox.level_for("webx://settings"), IsolationLevel::Trusted);
+        assert_eq!(sandbox.level_for("https://example.com"), IsolationLevel::Standard);
+        assert_eq!(sandbox.level_for("file:///tmp/page.html"), IsolationLevel::Strict);
+        assert_eq!(sandbox.level_for("data:text/html,hi"), IsolationLevel::Strict);
+
+        sandbox.set_origin_level("https://www.shady.example", IsolationLevel::Locked).unwrap();
+        assert_eq!(sandbox.level_for("https://cdn.shady.example/x.js"), IsolationLevel::Locked);
+        assert!(!sandbox.policy_for("shady.example").javascript_enabled);
+        assert!(sandbox.set_origin_level("example.com", IsolationLevel::Trusted).is_err());
+
+        // Rules persist
+        let sandbox = Sandbox::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(sandbox.list_origin_rules(), vec![("shady.example".to_string(), IsolationLevel::Locked)]);
+        assert!(sandbox.remove_origin_level("shady.example").unwrap());
+        assert_eq!(sandbox.level_for("https://shady.example"), IsolationLevel::Standard);
+    }
+
+    #[test]
+    fn test_tab_enforcement() {
+        let temp_dir = TempDir::new().unwrap();
+        let sandbox = Sandbox::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        sandbox.assign_tab(1, "https://example.com");
+        assert!(sandbox.check_ipc(1, "find-start"));
+        assert!(sandbox.check_ipc(1, "print-page"));
+        assert!(!sandbox.check_ipc(1, "get-printers"));
+        assert!(!sandbox.check_navigation(1, "file:///etc/passwd"));
+        assert!(!sandbox.check_navigation(1, "webx://settings"));
+        assert!(sandbox.check_navigation(1, "https://other.example"));
+
+        let internal = sandbox.assign_tab(2, "webx://settings");
+        assert_eq!(internal.ipc, IpcSurface::Full);
+        assert!(internal.allows_url("file:///home"));
+
+        sandbox.apply_setting("sandbox.default_level", &SettingValue::String("strict".to_string())).unwrap();
+        let policy = sandbox.assign_tab(1, "https://example.com");
+        assert!(policy.dedicated_process);
+        assert!(!sandbox.check_ipc(1, "print-page"));
+
+        sandbox.release_tab(1);
+        assert!(!sandbox.check_ipc(1, "find-start"));
     }
-}
\ No newline at end of file
+}
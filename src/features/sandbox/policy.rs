@@ -0,0 +1,179 @@
+// Sandbox Policies
+use serde::{Deserialize, Serialize};
+
+/// IPC messages page-facing features may send to the browser
+pub const PAGE_IPC_CHANNELS: &[&str] = &[
+    "find-start",
+    "find-next",
+    "find-previous",
+    "find-end",
+    "shortcut-triggered",
+    "spellcheck-add-word",
+    "print-page",
+    "print-preview",
+    "csp-violation",
+    "csp-script-usage",
+    "csp-meta-policy",
+];
+
+/// IPC messages still allowed under strict isolation
+pub const MINIMAL_IPC_CHANNELS: &[&str] = &[
+    "find-start",
+    "find-next",
+    "find-previous",
+    "find-end",
+    "shortcut-triggered",
+    "csp-violation",
+    "csp-script-usage",
+    "csp-meta-policy",
+];
+
+/// How tightly a tab is isolated, from least to most restricted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IsolationLevel {
+    /// Built-in browser pages
+    Trusted,
+    /// Regular web content
+    Standard,
+    /// Untrusted content: minimal IPC and a tighter syscall filter
+    Strict,
+    /// Strict plus JavaScript disabled
+    Locked,
+}
+
+impl IsolationLevel {
+    /// All levels in order
+    pub fn all() -> &'static [IsolationLevel] {
+        &[
+            IsolationLevel::Trusted,
+            IsolationLevel::Standard,
+            IsolationLevel::Strict,
+            IsolationLevel::Locked,
+        ]
+    }
+
+    /// Short name used for profile names and settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            IsolationLevel::Trusted => "trusted",
+            IsolationLevel::Standard => "standard",
+            IsolationLevel::Strict => "strict",
+            IsolationLevel::Locked => "locked",
+        }
+    }
+
+    /// Parse a level from its short name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|level| level.name() == name)
+    }
+}
+
+/// IPC messages a tab may send to the browser process
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum IpcSurface {
+    Full,
+    Restricted(Vec<String>),
+    None,
+}
+
+impl IpcSurface {
+    /// Check if a message type is allowed
+    pub fn allows(&self, channel: &str) -> bool {
+        match self {
+            IpcSurface::Full => true,
+            IpcSurface::Restricted(channels) => channels.iter().any(|c| c == channel),
+            IpcSurface::None => false,
+        }
+    }
+}
+
+/// Isolation settings applied to a tab's content process
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SandboxPolicy {
+    pub level: IsolationLevel,
+    pub javascript_enabled: bool,
+    /// Whether the page may load or navigate to file:// URLs
+    pub allow_file_access: bool,
+    pub allow_popups: bool,
+    pub allow_downloads: bool,
+    pub ipc: IpcSurface,
+    /// Give the tab its own content process instead of sharing one per site
+    pub dedicated_process: bool,
+    /// AppArmor profile the content process switches to (Linux)
+    pub apparmor_profile: Option<String>,
+    /// Apply the level's seccomp syscall filter (Linux)
+    pub seccomp: bool,
+}
+
+impl SandboxPolicy {
+    /// Preset policy for an isolation level
+    pub fn for_level(level: IsolationLevel) -> Self {
+        let apparmor_profile = match level {
+            IsolationLevel::Trusted => None,
+            _ => Some(format!("webx-content-{}", level.name())),
+        };
+
+        match level {
+            IsolationLevel::Trusted => Self {
+                level,
+                javascript_enabled: true,
+                allow_file_access: true,
+                allow_popups: true,
+                allow_downloads: true,
+                ipc: IpcSurface::Full,
+                dedicated_process: false,
+                apparmor_profile,
+                seccomp: false,
+            },
+            IsolationLevel::Standard => Self {
+                level,
+                javascript_enabled: true,
+                allow_file_access: false,
+                allow_popups: true,
+                allow_downloads: true,
+                ipc: IpcSurface::Restricted(to_owned(PAGE_IPC_CHANNELS)),
+                dedicated_process: false,
+                apparmor_profile,
+                seccomp: true,
+            },
+            IsolationLevel::Strict => Self {
+                level,
+                javascript_enabled: true,
+                allow_file_access: false,
+                allow_popups: false,
+                allow_downloads: false,
+                ipc: IpcSurface::Restricted(to_owned(MINIMAL_IPC_CHANNELS)),
+                dedicated_process: true,
+                apparmor_profile,
+                seccomp: true,
+            },
+            IsolationLevel::Locked => Self {
+                level,
+                javascript_enabled: false,
+                allow_file_access: false,
+                allow_popups: false,
+                allow_downloads: false,
+                ipc: IpcSurface::None,
+                dedicated_process: true,
+                apparmor_profile,
+                seccomp: true,
+            },
+        }
+    }
+
+    /// Check if the page may navigate to or load a URL
+    pub fn allows_url(&self, url: &str) -> bool {
+        let scheme = url.split(':').next().unwrap_or("").to_lowercase();
+        match scheme.as_str() {
+            "file" => self.allow_file_access,
+            "javascript" => self.javascript_enabled,
+            // Internal pages are only reachable from other internal pages
+            "webx" => self.level == IsolationLevel::Trusted,
+            _ => true,
+        }
+    }
+}
+
+fn to_owned(channels: &[&str]) -> Vec<String> {
+    channels.iter().map(|c| c.to_string()).collect()
+}
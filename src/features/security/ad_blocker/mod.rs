@@ -1,13 +1,397 @@
-// Ad Blocker Module - Placeholder
-// TODO: Implement ad blocking functionality
-pub struct AdBlocker;
+// Ad Blocker with Customizable Filter Lists
+use crate::error::WebxError;
+use crate::utils::{LockExt, Snapshot};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Ad blocker rule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct AdBlockRule {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Predefined filter lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FilterList {
+    EasyList,
+    EasyPrivacy,
+    FanboyAnnoyances,
+    Custom,
+}
+
+/// Ad blocker for blocking advertisements and trackers
+pub struct AdBlocker {
+    rules: Arc<Mutex<HashSet<AdBlockRule>>>,
+    /// Recompiled in the background when rules change; URLs are matched
+    /// against the previous set until the new one is in
+    compiled_regexes: Arc<Snapshot<Vec<Regex>>>,
+    config_dir: PathBuf,
+}
 
 impl AdBlocker {
-    pub fn new() -> Self {
-        Self
+    /// Create a new ad blocker
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("adblock");
+            path
+        });
+        
+        // Create config directory
+        fs::create_dir_all(&config_dir)?;
+        
+        let blocker = Self {
+            rules: Arc::new(Mutex::new(HashSet::new())),
+            compiled_regexes: Arc::new(Snapshot::new(Vec::new())),
+            config_dir,
+        };
+        
+        // Load existing rules
+        blocker.load_rules()?;
+        
+        // Add default rules if none exist
+        if blocker.rules.lock_or_recover().is_empty() {
+            blocker.add_default_rules()?;
+        }
+
+        // Nothing can match before the first rule set is in, so compile it
+        // right away
+        let regexes = compile_rule_set(&blocker.rules.lock_or_recover());
+        blocker.compiled_regexes.store(regexes);
+        
+        Ok(blocker)
+    }
+
+    /// Check if a URL should be blocked
+    pub fn should_block(&self, url: &str) -> bool {
+        let regexes = self.compiled_regexes.load();
+        
+        for regex in regexes.iter() {
+            if regex.is_match(url) {
+                return true;
+            }
+        }
+        
+        false
+    }
+
+    /// Add a new blocking rule. It applies once the rules are recompiled in
+    /// the background.
+    pub fn add_rule(&self, pattern: String, is_regex: bool) -> Result<(), WebxError> {
+        let rule = AdBlockRule {
+            pattern: pattern.clone(),
+            is_regex,
+            enabled: true,
+            created_at: chrono::Utc::now(),
+        };
+        
+        {
+            let mut rules = self.rules.lock_or_recover();
+            rules.insert(rule);
+        }
+        
+        self.compile_rules();
+        self.save_rules()?;
+        
+        Ok(())
+    }
+
+    /// Remove a rule
+    pub fn remove_rule(&self, pattern: &str) -> bool {
+        let removed = {
+            let mut rules = self.rules.lock_or_recover();
+            let initial_len = rules.len();
+            rules.retain(|rule| rule.pattern != pattern);
+            rules.len() != initial_len
+        };
+        
+        if removed {
+            self.compile_rules();
+            let _ = self.save_rules();
+        }
+        
+        removed
+    }
+
+    /// Enable/disable a rule
+    pub fn set_rule_enabled(&self, pattern: &str, enabled: bool) -> bool {
+        let updated = {
+            let mut rules = self.rules.lock_or_recover();
+            match rules.iter().find(|rule| rule.pattern == pattern && rule.enabled != enabled).cloned() {
+                Some(rule) => {
+                    rules.remove(&rule);
+                    rules.insert(AdBlockRule { enabled, ..rule });
+                    true
+                }
+                None => false,
+            }
+        };
+        
+        if updated {
+            self.compile_rules();
+            let _ = self.save_rules();
+        }
+        
+        updated
+    }
+
+    /// Get all rules
+    pub fn get_rules(&self) -> Vec<AdBlockRule> {
+        let rules = self.rules.lock_or_recover();
+        rules.iter().cloned().collect()
+    }
+
+    /// Load predefined filter list. Its rules apply once the rules are
+    /// recompiled in the background.
+    pub fn load_filter_list(&self, filter_list: FilterList) -> Result<(), WebxError> {
+        let rules = match filter_list {
+            FilterList::EasyList => self.get_easylist_rules(),
+            FilterList::EasyPrivacy => self.get_easyprivacy_rules(),
+            FilterList::FanboyAnnoyances => self.get_fanboy_annoyances_rules(),
+            FilterList::Custom => vec![], // Custom rules are user-defined
+        };
+        
+        {
+            let mut existing_rules = self.rules.lock_or_recover();
+            for rule in rules {
+                existing_rules.insert(rule);
+            }
+        }
+        
+        self.compile_rules();
+        self.save_rules()?;
+        
+        Ok(())
+    }
+
+    /// Clear all rules
+    pub fn clear_rules(&self) {
+        self.rules.lock_or_recover().clear();
+        self.compiled_regexes.store(Vec::new());
+        let _ = self.save_rules();
+    }
+
+    /// Get statistics
+    pub fn get_stats(&self) -> (usize, usize) {
+        let rules = self.rules.lock_or_recover();
+        let active_count = rules.iter().filter(|r| r.enabled).count();
+        (rules.len(), active_count)
+    }
+
+    /// Whether rules are being recompiled in the background
+    pub fn is_compiling(&self) -> bool {
+        self.compiled_regexes.is_rebuilding()
+    }
+
+    /// Wait for rule changes made so far to take effect, returning false if
+    /// `timeout` passed first
+    pub fn wait_for_rules(&self, timeout: Duration) -> bool {
+        self.compiled_regexes.wait_for_rebuilds(timeout)
+    }
+
+    // Private helper methods
+    
+    /// Recompile the rules off the calling thread
+    fn compile_rules(&self) {
+        let rules = self.rules.lock_or_recover().clone();
+        Snapshot::rebuild(&self.compiled_regexes, move || compile_rule_set(&rules));
+    }
+    
+    fn save_rules(&self) -> Result<(), WebxError> {
+        let rules = self.rules.lock_or_recover();
+        let path = self.config_dir.join("rules.json");
+        let content = serde_json::to_string_pretty(&*rules)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+    
+    fn load_rules(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("rules.json");
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let rules: HashSet<AdBlockRule> = serde_json::from_str(&content)?;
+            
+            *self.rules.lock_or_recover() = rules;
+        }
+        Ok(())
+    }
+    
+    fn add_default_rules(&self) -> Result<(), WebxError> {
+        let default_rules = vec![
+            // Common ad domains
+            ".*\\.doubleclick\\.net.*",
+            ".*\\.googlesyndication\\.com.*",
+            ".*\\.googleadservices\\.com.*",
+            ".*\\.facebook\\.com/tr.*",
+            ".*\\.facebook\\.com/impression\\.php.*",
+            ".*\\.adservice\\.google\\.com.*",
+            
+            // Analytics and tracking
+            ".*\\.google-analytics\\.com.*",
+            ".*\\.analytics\\.google\\.com.*",
+            ".*\\.facebook\\.com/tr.*",
+            ".*\\.facebook\\.com/pixel.*",
+            
+            // Popup/popunder ads
+            ".*popup.*",
+            ".*popunder.*",
+            
+            // Common ad paths
+            ".*/ads/.*",
+            ".*/ad/.*",
+            ".*/banner/.*",
+        ];
+        
+        {
+            let mut rules = self.rules.lock_or_recover();
+            for pattern in default_rules {
+                rules.insert(AdBlockRule {
+                    pattern: pattern.to_string(),
+                    is_regex: true,
+                    enabled: true,
+                    created_at: chrono::Utc::now(),
+                });
+            }
+        }
+        
+        self.save_rules()?;
+        
+        Ok(())
     }
     
-    pub fn should_block(&self, _url: &str) -> bool {
-        false // Placeholder implementation
+    fn get_easylist_rules(&self) -> Vec<AdBlockRule> {
+        // These would typically be loaded from the actual EasyList
+        // For demo purposes, including some common patterns
+        vec![
+            AdBlockRule {
+                pattern: ".*\\.2mdn\\.net.*".to_string(),
+                is_regex: true,
+                enabled: true,
+                created_at: chrono::Utc::now(),
+            },
+            AdBlockRule {
+                pattern: ".*\\.adnxs\\.com.*".to_string(),
+                is_regex: true,
+                enabled: true,
+                created_at: chrono::Utc::now(),
+            },
+        ]
+    }
+    
+    fn get_easyprivacy_rules(&self) -> Vec<AdBlockRule> {
+        vec![
+            AdBlockRule {
+                pattern: ".*\\.scorecardresearch\\.com.*".to_string(),
+                is_regex: true,
+                enabled: true,
+                created_at: chrono::Utc::now(),
+            },
+        ]
+    }
+    
+    fn get_fanboy_annoyances_rules(&self) -> Vec<AdBlockRule> {
+        vec![
+            AdBlockRule {
+                pattern: ".*newsletter.*".to_string(),
+                is_regex: true,
+                enabled: true,
+                created_at: chrono::Utc::now(),
+            },
+        ]
+    }
+}
+
+// Private helper functions
+
+/// Compile enabled rules, skipping those that do not compile
+fn compile_rule_set(rules: &HashSet<AdBlockRule>) -> Vec<Regex> {
+    rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| {
+            let pattern = if rule.is_regex {
+                rule.pattern.clone()
+            } else {
+                // Convert simple pattern to regex
+                pattern_to_regex(&rule.pattern)
+            };
+            Regex::new(&pattern).ok()
+        })
+        .collect()
+}
+
+fn pattern_to_regex(pattern: &str) -> String {
+    // Escape special regex characters and convert wildcards
+    let escaped = regex::escape(pattern);
+    escaped.replace(r"\*", ".*").replace(r"\?", ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ad_blocker_basic_functionality() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocker = AdBlocker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Test default rules are loaded
+        let rules = blocker.get_rules();
+        assert!(!rules.is_empty());
+        
+        // Test blocking functionality
+        assert!(blocker.should_block("https://pagead2.googlesyndication.com/pagead/js/adsbygoogle.js"));
+        assert!(blocker.should_block("https://www.google-analytics.com/analytics.js"));
+        
+        // Test non-blocking URLs
+        assert!(!blocker.should_block("https://example.com"));
+        assert!(!blocker.should_block("https://github.com"));
+    }
+
+    #[test]
+    fn test_add_remove_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocker = AdBlocker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Add a custom rule
+        blocker.add_rule(".*test-ad\\.com.*".to_string(), true).unwrap();
+        
+        // Test the new rule works once compiled
+        assert!(blocker.wait_for_rules(Duration::from_secs(5)));
+        assert!(blocker.should_block("https://test-ad.com/banner.jpg"));
+        
+        // Remove the rule
+        assert!(blocker.remove_rule(".*test-ad\\.com.*"));
+        assert!(blocker.wait_for_rules(Duration::from_secs(5)));
+        assert!(!blocker.should_block("https://test-ad.com/banner.jpg"));
+    }
+
+    #[test]
+    fn test_rule_enabling() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocker = AdBlocker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Add a rule
+        blocker.add_rule(".*temp-block\\.com.*".to_string(), true).unwrap();
+        
+        // Disable it
+        assert!(blocker.set_rule_enabled(".*temp-block\\.com.*", false));
+        assert!(blocker.wait_for_rules(Duration::from_secs(5)));
+        assert!(!blocker.should_block("https://temp-block.com/ad.js"));
+        
+        // Re-enable it
+        assert!(blocker.set_rule_enabled(".*temp-block\\.com.*", true));
+        assert!(blocker.wait_for_rules(Duration::from_secs(5)));
+        assert!(blocker.should_block("https://temp-block.com/ad.js"));
     }
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+// Tracking Parameter Cleaner
+
+/// Query parameters removed from copied links unless configured otherwise.
+/// A trailing `*` matches any name starting with what comes before it.
+pub const DEFAULT_TRACKING_PARAMETERS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "ttclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok", "oly_anon_id", "oly_enc_id", "vero_id",
+];
+
+/// Removes tracking parameters from links
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlCleaner {
+    patterns: Vec<String>,
+}
+
+impl UrlCleaner {
+    /// Cleaner removing parameters matching any pattern, ignoring case
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| pattern.as_ref().trim().to_lowercase())
+                .filter(|pattern| !pattern.is_empty() && pattern != "*")
+                .collect(),
+        }
+    }
+
+    /// Cleaner from a list of patterns separated by commas or whitespace
+    pub fn parse(list: &str) -> Self {
+        let patterns: Vec<&str> = list.split(|c: char| c == ',' || c.is_whitespace()).collect();
+        Self::new(&patterns)
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether a query parameter is a tracking one
+    pub fn is_tracking(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *pattern,
+        })
+    }
+
+    /// A web link without its tracking parameters, `None` if it has none.
+    /// The other parameters are kept exactly as they were written.
+    pub fn clean_url(&self, url: &str) -> Option<String> {
+        let mut parsed = url::Url::parse(url).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return None;
+        }
+        let query = parsed.query()?;
+        let pairs: Vec<&str> = query.split('&').collect();
+        let kept: Vec<&str> = pairs
+            .iter()
+            .copied()
+            .filter(|pair| {
+                let name = url::form_urlencoded::parse(pair.as_bytes()).next().map(|(name, _)| name);
+                !name.is_some_and(|name| self.is_tracking(&name))
+            })
+            .collect();
+        if kept.len() == pairs.len() {
+            return None;
+        }
+        let kept = kept.join("&");
+        parsed.set_query(Some(&kept).filter(|query| !query.is_empty()).map(String::as_str));
+        Some(parsed.to_string())
+    }
+
+    /// Text with every web link in it cleaned
+    pub fn clean_text(&self, text: &str) -> String {
+        text.split_inclusive(char::is_whitespace)
+            .map(|word| {
+                let link = word.trim_end();
+                match self.clean_url(link) {
+                    Some(cleaned) => format!("{}{}", cleaned, &word[link.len()..]),
+                    None => word.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for UrlCleaner {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACKING_PARAMETERS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracking_parameters_are_removed() {
+        let cleaner = UrlCleaner::default();
+        assert_eq!(
+            cleaner.clean_url("https://shop.example/item?id=7&utm_source=news&UTM_Medium=mail&fbclid=abc#reviews"),
+            Some("https://shop.example/item?id=7#reviews".to_string())
+        );
+        assert_eq!(
+            cleaner.clean_url("https://example.com/?gclid=1"),
+            Some("https://example.com/".to_string())
+        );
+        assert_eq!(cleaner.clean_url("https://example.com/?q=a+b%20c"), None);
+        assert_eq!(cleaner.clean_url("mailto:a@example.com?utm_source=x"), None);
+        assert_eq!(
+            cleaner.clean_text("See https://example.com/a?utm_campaign=x and\nhttps://example.com/b?ref=1"),
+            "See https://example.com/a and\nhttps://example.com/b?ref=1"
+        );
+
+        let custom = UrlCleaner::parse("ref, si ,*");
+        assert_eq!(custom.patterns(), ["ref", "si"]);
+        assert_eq!(
+            custom.clean_url("https://example.com/b?ref=1&utm_source=x"),
+            Some("https://example.com/b?utm_source=x".to_string())
+        );
+    }
+}
@@ -0,0 +1,431 @@
+// Clipboard Access Broker
+pub mod cleaner;
+
+pub use cleaner::{UrlCleaner, DEFAULT_TRACKING_PARAMETERS};
+
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::security::permissions::{PermissionKind, PermissionManager, PermissionState};
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Page clipboard writes a site may make per minute unless configured
+pub const DEFAULT_WRITES_PER_MINUTE: usize = 10;
+
+/// Clipboard accesses kept in the log
+const MAX_LOG_ENTRIES: usize = 200;
+
+/// Script routing a page's clipboard reads and writes through the broker.
+/// Rich writes keep only their text, copy commands need a user gesture,
+/// and links the user copies are cleaned before reaching the clipboard.
+pub const CLIPBOARD_SCRIPT: &str = r#"
+(function() {
+    if (window.webxClipboard) return;
+    window.webxClipboard = true;
+    const broker = (message) => window.ipc.request(Object.assign({ type: 'clipboard' }, message)).then((answer) => {
+        if (!answer.allowed) throw new DOMException(answer.reason, 'NotAllowedError');
+        return answer;
+    });
+    const clipboard = navigator.clipboard;
+    if (clipboard) {
+        clipboard.readText = () => broker({ action: 'read' }).then((answer) => answer.text);
+        clipboard.writeText = (text) => broker({ action: 'write', text: String(text) }).then(() => undefined);
+        clipboard.read = () => broker({ action: 'read' }).then((answer) => [
+            new ClipboardItem({ 'text/plain': new Blob([answer.text], { type: 'text/plain' }) }),
+        ]);
+        clipboard.write = (items) => Promise.all(Array.from(items, (item) =>
+            item.types.includes('text/plain') ? item.getType('text/plain').then((blob) => blob.text()) : ''
+        )).then((texts) => clipboard.writeText(texts.join('')));
+    }
+    const execCommand = document.execCommand.bind(document);
+    document.execCommand = function(command, ...rest) {
+        const name = String(command).toLowerCase();
+        const gesture = !navigator.userActivation || navigator.userActivation.isActive;
+        if ((name === 'copy' || name === 'cut') && !gesture) return false;
+        return execCommand(command, ...rest);
+    };
+    document.addEventListener('copy', (event) => {
+        const text = String(window.getSelection()).trim();
+        if (!/^https?:\/\/\S+$/.test(text)) return;
+        event.preventDefault();
+        window.ipc.send({ type: 'clipboard', action: 'copy', text: text });
+    }, true);
+})();
+"#;
+
+/// What a page does with the clipboard
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ClipboardAction {
+    /// The page reads the clipboard's text
+    Read,
+    /// The page writes text to the clipboard
+    Write { text: String },
+    /// The user copied a link on the page
+    Copy { text: String },
+}
+
+/// Kind of clipboard access
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClipboardAccessKind {
+    Read,
+    Write,
+    /// Copied by the user rather than the page
+    Copy,
+}
+
+/// A clipboard access in the log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClipboardAccess {
+    pub origin: String,
+    pub kind: ClipboardAccessKind,
+    pub allowed: bool,
+    /// Why the access was refused
+    pub reason: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Reads and writes the clipboard's text
+pub trait ClipboardBackend: Send + Sync {
+    fn read_text(&self) -> Result<String, WebxError>;
+
+    fn write_text(&self, text: &str) -> Result<(), WebxError>;
+}
+
+/// The system clipboard
+pub struct SystemClipboard {
+    /// Kept open, as some systems empty the clipboard once it is closed
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl SystemClipboard {
+    pub fn new() -> Self {
+        Self {
+            clipboard: Mutex::new(None),
+        }
+    }
+
+    // Private helper methods
+
+    fn with_clipboard<T>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, WebxError> {
+        let mut clipboard = self.clipboard.lock_or_recover();
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().ok();
+        }
+        let clipboard = clipboard.as_mut().ok_or("No clipboard is available")?;
+        f(clipboard).map_err(|e| WebxError::Invalid(format!("Clipboard unavailable: {}", e)))
+    }
+}
+
+impl Default for SystemClipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClipboardBackend for SystemClipboard {
+    fn read_text(&self) -> Result<String, WebxError> {
+        self.with_clipboard(|clipboard| clipboard.get_text())
+    }
+
+    fn write_text(&self, text: &str) -> Result<(), WebxError> {
+        self.with_clipboard(|clipboard| clipboard.set_text(text.to_string()))
+    }
+}
+
+/// Stands between pages and the clipboard: reads need the site's clipboard
+/// permission, writes are limited per site, every access is logged, and
+/// links are cleaned of tracking parameters on the way in
+pub struct ClipboardBroker {
+    backend: Arc<dyn ClipboardBackend>,
+    permission_manager: Option<Arc<PermissionManager>>,
+    cleaner: Mutex<UrlCleaner>,
+    clean_urls: Mutex<bool>,
+    writes_per_minute: Mutex<usize>,
+    /// Recent page writes by origin
+    writes: Mutex<HashMap<String, VecDeque<Instant>>>,
+    log: Mutex<VecDeque<ClipboardAccess>>,
+}
+
+impl ClipboardBroker {
+    /// Create new broker for the system clipboard
+    pub fn new() -> Self {
+        Self {
+            backend: Arc::new(SystemClipboard::new()),
+            permission_manager: None,
+            cleaner: Mutex::new(UrlCleaner::default()),
+            clean_urls: Mutex::new(true),
+            writes_per_minute: Mutex::new(DEFAULT_WRITES_PER_MINUTE),
+            writes: Mutex::new(HashMap::new()),
+            log: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Use another clipboard
+    pub fn set_backend(&mut self, backend: Arc<dyn ClipboardBackend>) {
+        self.backend = backend;
+    }
+
+    /// Check page reads against the permission store; without one, pages
+    /// can not read the clipboard
+    pub fn set_permission_manager(&mut self, permission_manager: Arc<PermissionManager>) {
+        self.permission_manager = Some(permission_manager);
+    }
+
+    /// Remove tracking parameters from links written to the clipboard
+    pub fn set_clean_urls(&self, enabled: bool) {
+        *self.clean_urls.lock_or_recover() = enabled;
+    }
+
+    pub fn set_cleaner(&self, cleaner: UrlCleaner) {
+        *self.cleaner.lock_or_recover() = cleaner;
+    }
+
+    pub fn set_writes_per_minute(&self, writes: usize) {
+        *self.writes_per_minute.lock_or_recover() = writes;
+    }
+
+    /// Text as it goes onto the clipboard
+    pub fn clean(&self, text: &str) -> String {
+        if *self.clean_urls.lock_or_recover() {
+            self.cleaner.lock_or_recover().clean_text(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The clipboard's text, for a page whose site may read it
+    pub fn read(&self, origin: &str) -> Result<String, WebxError> {
+        let permitted = self
+            .permission_manager
+            .as_ref()
+            .is_some_and(|permissions| permissions.get_permission(origin, PermissionKind::ClipboardRead) == PermissionState::Granted);
+        let result = if permitted {
+            self.backend.read_text()
+        } else {
+            Err(WebxError::Invalid("This site may not read the clipboard".to_string()))
+        };
+        self.record(origin, ClipboardAccessKind::Read, &result);
+        result
+    }
+
+    /// Write a page's text to the clipboard, unless its site wrote too often
+    pub fn write(&self, origin: &str, text: &str) -> Result<(), WebxError> {
+        let result = if self.take_write(origin) {
+            self.backend.write_text(&self.clean(text))
+        } else {
+            Err(WebxError::Invalid("This site is writing to the clipboard too often".to_string()))
+        };
+        self.record(origin, ClipboardAccessKind::Write, &result);
+        result
+    }
+
+    /// Put a link the user copied on the clipboard, cleaned. Any page
+    /// script can claim a copy, so only a single link is taken, and copies
+    /// count against the site's write limit.
+    pub fn copy(&self, origin: &str, text: &str) -> Result<(), WebxError> {
+        let is_link = url::Url::parse(text).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+            && !text.contains(char::is_whitespace);
+        let result = if !is_link {
+            Err(WebxError::Invalid("Only copied links go through the browser".to_string()))
+        } else if self.take_write(origin) {
+            self.backend.write_text(&self.clean(text))
+        } else {
+            Err(WebxError::Invalid("This site is writing to the clipboard too often".to_string()))
+        };
+        self.record(origin, ClipboardAccessKind::Copy, &result);
+        result
+    }
+
+    /// Clipboard accesses, oldest first
+    pub fn access_log(&self) -> Vec<ClipboardAccess> {
+        self.log.lock_or_recover().iter().cloned().collect()
+    }
+
+    pub fn clear_log(&self) {
+        self.log.lock_or_recover().clear();
+    }
+
+    // Private helper methods
+
+    /// Count a page write against its site's limit, `false` when over it
+    fn take_write(&self, origin: &str) -> bool {
+        let limit = *self.writes_per_minute.lock_or_recover();
+        let now = Instant::now();
+        let mut writes = self.writes.lock_or_recover();
+        writes.retain(|_, times| {
+            while times.front().is_some_and(|time| now.duration_since(*time) >= Duration::from_secs(60)) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = writes.entry(PermissionManager::normalize_origin(origin)).or_default();
+        if times.len() >= limit {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+
+    fn record<T>(&self, origin: &str, kind: ClipboardAccessKind, result: &Result<T, WebxError>) {
+        let reason = result.as_ref().err().map(|e| e.to_string());
+        match &reason {
+            Some(reason) => tracing::info!("Clipboard {:?} by {} refused: {}", kind, origin, reason),
+            None => tracing::info!("Clipboard {:?} by {}", kind, origin),
+        }
+        let mut log = self.log.lock_or_recover();
+        if log.len() == MAX_LOG_ENTRIES {
+            log.pop_front();
+        }
+        log.push_back(ClipboardAccess {
+            origin: PermissionManager::normalize_origin(origin),
+            kind,
+            allowed: reason.is_none(),
+            reason,
+            at: Utc::now(),
+        });
+    }
+}
+
+impl Default for ClipboardBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpcHandler for ClipboardBroker {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["clipboard"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        let IpcMessage::Clipboard(action) = message else {
+            return Err(WebxError::Invalid("Not a clipboard message".to_string()));
+        };
+        let result = match action {
+            ClipboardAction::Read => self.read(&context.origin).map(|text| serde_json::json!({ "text": text })),
+            ClipboardAction::Write { text } => self.write(&context.origin, &text).map(|()| serde_json::json!({})),
+            ClipboardAction::Copy { text } => self.copy(&context.origin, &text).map(|()| serde_json::json!({})),
+        };
+        // Refusals are answers the page script turns into errors
+        Ok(match result {
+            Ok(mut answer) => {
+                answer["allowed"] = serde_json::Value::Bool(true);
+                answer
+            }
+            Err(e) => serde_json::json!({ "allowed": false, "reason": e.to_string() }),
+        })
+    }
+}
+
+impl SettingsProvider for ClipboardBroker {
+    fn module(&self) -> &str {
+        "clipboard"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("clipboard.clean_urls", "Clean copied links", true)
+                .with_description("Remove tracking parameters such as utm_source and fbclid from links put on the clipboard")
+                .with_category(SettingCategory::Privacy),
+            SettingDefinition::text(
+                "clipboard.tracking_parameters",
+                "Tracking parameters",
+                &DEFAULT_TRACKING_PARAMETERS.join(", "),
+            )
+            .with_description("Parameters removed from copied links; a trailing * matches any ending")
+            .with_category(SettingCategory::Privacy),
+            SettingDefinition::integer(
+                "clipboard.writes_per_minute",
+                "Clipboard writes per minute",
+                DEFAULT_WRITES_PER_MINUTE as i64,
+                1,
+                100,
+            )
+            .with_description("How often a site may write to the clipboard before further writes are refused")
+            .with_category(SettingCategory::Advanced),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "clipboard.clean_urls" => self.set_clean_urls(value.as_bool().ok_or("Expected a toggle value")?),
+            "clipboard.tracking_parameters" => {
+                self.set_cleaner(UrlCleaner::parse(value.as_str().ok_or("Expected text")?))
+            }
+            "clipboard.writes_per_minute" => {
+                let writes = value.as_i64().filter(|writes| *writes > 0).ok_or("Expected a positive number")?;
+                self.set_writes_per_minute(writes as usize);
+            }
+            _ => return Err(format!("Unknown clipboard setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct MemoryClipboard {
+        text: Mutex<String>,
+    }
+
+    impl ClipboardBackend for MemoryClipboard {
+        fn read_text(&self) -> Result<String, WebxError> {
+            Ok(self.text.lock_or_recover().clone())
+        }
+
+        fn write_text(&self, text: &str) -> Result<(), WebxError> {
+            *self.text.lock_or_recover() = text.to_string();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_broker_permissions_limits_and_cleaning() {
+        let temp_dir = TempDir::new().unwrap();
+        let permissions = Arc::new(PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap());
+        permissions
+            .set_permission("https://docs.example", PermissionKind::ClipboardRead, PermissionState::Granted)
+            .unwrap();
+        let clipboard = Arc::new(MemoryClipboard::default());
+        let mut broker = ClipboardBroker::new();
+        broker.set_backend(clipboard.clone());
+        broker.set_permission_manager(permissions);
+
+        // Only permitted sites read, and the refusal is logged
+        broker.write("https://docs.example/a", "secret").unwrap();
+        assert_eq!(broker.read("https://docs.example/b").unwrap(), "secret");
+        assert!(broker.read("https://other.example").is_err());
+        let log = broker.access_log();
+        assert_eq!(log.len(), 3);
+        assert!(!log[2].allowed && log[2].kind == ClipboardAccessKind::Read);
+
+        broker.copy("https://news.example", "https://news.example/story?id=1&utm_source=feed").unwrap();
+        assert_eq!(*clipboard.text.lock_or_recover(), "https://news.example/story?id=1");
+
+        // Writes over the limit are refused per site
+        broker.apply_setting("clipboard.writes_per_minute", &SettingValue::Integer(2)).unwrap();
+        broker.write("https://ads.example", "1").unwrap();
+        broker.write("https://ads.example", "2").unwrap();
+        assert!(broker.write("https://ads.example", "3").is_err());
+        assert!(broker.copy("https://ads.example", "https://ads.example/win").is_err());
+        assert_eq!(*clipboard.text.lock_or_recover(), "2");
+        assert!(broker.copy("https://news.example", "not a link").is_err());
+
+        broker.apply_setting("clipboard.clean_urls", &SettingValue::Bool(false)).unwrap();
+        broker.copy("https://news.example", "https://news.example/?fbclid=x").unwrap();
+        assert_eq!(*clipboard.text.lock_or_recover(), "https://news.example/?fbclid=x");
+    }
+}
@@ -0,0 +1,466 @@
+// Content Security Policy Auditing
+pub mod policy;
+
+pub use policy::{ContentSecurityPolicy, CspFinding, FindingSeverity};
+
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::{LockExt, extract_domain};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Violations kept per tab for the report
+const MAX_TAB_VIOLATIONS: usize = 100;
+/// Distinct blocked URIs remembered per site
+const MAX_SITE_BLOCKED_URIS: usize = 20;
+
+/// Script execution the injected hook reports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptUsageKind {
+    InlineScript,
+    InlineHandler,
+    JavascriptUrl,
+    /// new Function() or string setTimeout/setInterval
+    Eval,
+}
+
+/// A violation as reported by the page's securitypolicyviolation event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CspViolation {
+    #[serde(default)]
+    pub document_uri: String,
+    #[serde(default)]
+    pub blocked_uri: String,
+    #[serde(default)]
+    pub violated_directive: String,
+    #[serde(default)]
+    pub effective_directive: String,
+    /// "enforce" or "report"
+    #[serde(default)]
+    pub disposition: String,
+    #[serde(default)]
+    pub sample: String,
+    #[serde(default)]
+    pub source_file: String,
+    #[serde(default)]
+    pub line_number: u32,
+    #[serde(default = "chrono::Utc::now")]
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Violations aggregated for a site across tabs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteCspStats {
+    pub site: String,
+    pub violation_count: u64,
+    pub by_directive: BTreeMap<String, u64>,
+    pub blocked_uris: Vec<String>,
+    pub inline_script_count: u64,
+    pub eval_count: u64,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+/// Security report for the page loaded in a tab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabSecurityReport {
+    pub tab_id: usize,
+    pub url: String,
+    pub has_policy: bool,
+    /// At least one policy is enforced (not report-only)
+    pub enforced: bool,
+    pub policies: Vec<ContentSecurityPolicy>,
+    pub findings: Vec<CspFinding>,
+    pub violations: Vec<CspViolation>,
+    pub script_usage: BTreeMap<String, u64>,
+    /// Inline scripts or eval that ran with no policy restricting them
+    pub unprotected_executions: u64,
+}
+
+#[derive(Debug, Clone)]
+struct TabCspState {
+    url: String,
+    policies: Vec<ContentSecurityPolicy>,
+    violations: Vec<CspViolation>,
+    script_usage: HashMap<ScriptUsageKind, u64>,
+    unprotected_executions: u64,
+}
+
+/// Collects CSP headers, violations and script usage per tab and per site
+pub struct CspMonitor {
+    tabs: Arc<Mutex<HashMap<usize, TabCspState>>>,
+    sites: Arc<Mutex<HashMap<String, SiteCspStats>>>,
+}
+
+impl CspMonitor {
+    /// Create new CSP monitor
+    pub fn new() -> Self {
+        Self {
+            tabs: Arc::new(Mutex::new(HashMap::new())),
+            sites: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Record a document response; resets the tab's state for the new page
+    pub fn record_document(&self, tab_id: usize, url: &str, headers: &[(String, String)]) {
+        let mut policies = Vec::new();
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("content-security-policy") {
+                policies.extend(ContentSecurityPolicy::parse_header(value, false));
+            } else if name.eq_ignore_ascii_case("content-security-policy-report-only") {
+                policies.extend(ContentSecurityPolicy::parse_header(value, true));
+            }
+        }
+
+        self.tabs.lock_or_recover().insert(
+            tab_id,
+            TabCspState {
+                url: url.to_string(),
+                policies,
+                violations: Vec::new(),
+                script_usage: HashMap::new(),
+                unprotected_executions: 0,
+            },
+        );
+    }
+
+    /// Record a policy delivered in a <meta http-equiv> tag (always enforced)
+    pub fn record_meta_policy(&self, tab_id: usize, content: &str) {
+        if let Some(state) = self.tabs.lock_or_recover().get_mut(&tab_id) {
+            state.policies.extend(ContentSecurityPolicy::parse_header(content, false));
+        }
+    }
+
+    /// Record a violation reported by the page
+    pub fn record_violation(&self, tab_id: usize, violation: CspViolation) {
+        let site = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let Some(state) = tabs.get_mut(&tab_id) else {
+                return;
+            };
+            if state.violations.len() >= MAX_TAB_VIOLATIONS {
+                state.violations.remove(0);
+            }
+            state.violations.push(violation.clone());
+            extract_domain(&state.url)
+        };
+
+        let directive = if violation.effective_directive.is_empty() {
+            violation.violated_directive.split_whitespace().next().unwrap_or("").to_string()
+        } else {
+            violation.effective_directive.clone()
+        };
+
+        self.update_site(&site, |stats| {
+            stats.violation_count += 1;
+            *stats.by_directive.entry(directive).or_insert(0) += 1;
+            if !violation.blocked_uri.is_empty()
+                && !stats.blocked_uris.contains(&violation.blocked_uri)
+                && stats.blocked_uris.len() < MAX_SITE_BLOCKED_URIS
+            {
+                stats.blocked_uris.push(violation.blocked_uri.clone());
+            }
+        });
+    }
+
+    /// Record script execution seen by the hook; returns whether the tab's policies allow it
+    pub fn record_script_usage(&self, tab_id: usize, kind: ScriptUsageKind) -> bool {
+        let (allowed, site) = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let Some(state) = tabs.get_mut(&tab_id) else {
+                return true;
+            };
+
+            let enforced: Vec<&ContentSecurityPolicy> = state.policies.iter().filter(|p| !p.report_only).collect();
+            let allowed = enforced.iter().all(|policy| Self::policy_allows(policy, kind));
+            // Allowed because no policy restricts it at all
+            let restricted = enforced.iter().any(|policy| policy.effective_sources("script-src").is_some());
+
+            *state.script_usage.entry(kind).or_insert(0) += 1;
+            if allowed && !restricted {
+                state.unprotected_executions += 1;
+            }
+            (allowed, extract_domain(&state.url))
+        };
+
+        self.update_site(&site, |stats| match kind {
+            ScriptUsageKind::Eval => stats.eval_count += 1,
+            _ => stats.inline_script_count += 1,
+        });
+        allowed
+    }
+
+    /// Handle a message sent by the monitor script; returns false for unrelated messages
+    pub fn handle_ipc_message(&self, tab_id: usize, body: &str) -> Result<bool, WebxError> {
+        let message: serde_json::Value = serde_json::from_str(body)?;
+        let ours = message["type"]
+            .as_str()
+            .is_some_and(|kind| self.message_types().contains(&kind));
+        if ours {
+            self.record_message(tab_id, serde_json::from_value(message)?);
+        }
+        Ok(ours)
+    }
+
+    /// Record one of the monitor script's messages
+    pub fn record_message(&self, tab_id: usize, message: IpcMessage) {
+        match message {
+            IpcMessage::CspViolation { violation } => self.record_violation(tab_id, violation),
+            IpcMessage::CspScriptUsage { kind, count } => {
+                // Counts come from the page, so cap them
+                for _ in 0..count.min(1000) {
+                    self.record_script_usage(tab_id, kind);
+                }
+            }
+            IpcMessage::CspMetaPolicy { content } => self.record_meta_policy(tab_id, &content),
+            _ => {}
+        }
+    }
+
+    /// Get the security report for a tab
+    pub fn get_tab_report(&self, tab_id: usize) -> Option<TabSecurityReport> {
+        let tabs = self.tabs.lock_or_recover();
+        let state = tabs.get(&tab_id)?;
+
+        let mut findings: Vec<CspFinding> = if state.policies.is_empty() {
+            vec![CspFinding {
+                severity: FindingSeverity::High,
+                directive: String::new(),
+                message: "Page has no Content Security Policy".to_string(),
+            }]
+        } else {
+            state.policies.iter().flat_map(|policy| policy.audit()).collect()
+        };
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        findings.dedup();
+
+        Some(TabSecurityReport {
+            tab_id,
+            url: state.url.clone(),
+            has_policy: !state.policies.is_empty(),
+            enforced: state.policies.iter().any(|p| !p.report_only),
+            policies: state.policies.clone(),
+            findings,
+            violations: state.violations.clone(),
+            script_usage: state
+                .script_usage
+                .iter()
+                .map(|(kind, count)| (format!("{:?}", kind), *count))
+                .collect(),
+            unprotected_executions: state.unprotected_executions,
+        })
+    }
+
+    /// Get aggregated stats for a site
+    pub fn get_site_stats(&self, url: &str) -> Option<SiteCspStats> {
+        self.sites.lock_or_recover().get(&extract_domain(url)).cloned()
+    }
+
+    /// Sites with the most violations first
+    pub fn list_sites(&self) -> Vec<SiteCspStats> {
+        let mut sites: Vec<SiteCspStats> = self.sites.lock_or_recover().values().cloned().collect();
+        sites.sort_by(|a, b| b.violation_count.cmp(&a.violation_count).then(a.site.cmp(&b.site)));
+        sites
+    }
+
+    /// Clear aggregated site stats
+    pub fn clear_site_stats(&self) {
+        self.sites.lock_or_recover().clear();
+    }
+
+    /// Forget a closed tab
+    pub fn remove_tab(&self, tab_id: usize) {
+        self.tabs.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Script injected into pages to report violations and script usage
+    pub fn get_monitor_script(&self) -> String {
+        r#"
+(function() {
+    if (window.__webxCspMonitor) return;
+    window.__webxCspMonitor = true;
+
+    function send(message) {
+        try { window.ipc.send(message); } catch (e) {}
+    }
+
+    document.addEventListener('securitypolicyviolation', function(e) {
+        send({
+            type: 'csp-violation',
+            violation: {
+                documentUri: e.documentURI,
+                blockedUri: e.blockedURI,
+                violatedDirective: e.violatedDirective,
+                effectiveDirective: e.effectiveDirective,
+                disposition: e.disposition,
+                sample: e.sample,
+                sourceFile: e.sourceFile,
+                lineNumber: e.lineNumber
+            }
+        });
+    });
+
+    // new Function() and string timers; direct eval cannot be wrapped without
+    // changing its scoping, so blocked eval shows up as a violation instead
+    let evalReports = 0;
+    function reportEval() {
+        if (evalReports++ < 20) send({ type: 'csp-script-usage', kind: 'eval', count: 1 });
+    }
+    window.Function = new Proxy(window.Function, {
+        apply(target, thisArg, args) { reportEval(); return Reflect.apply(target, thisArg, args); },
+        construct(target, args, newTarget) { reportEval(); return Reflect.construct(target, args, newTarget); }
+    });
+    ['setTimeout', 'setInterval'].forEach(function(name) {
+        window[name] = new Proxy(window[name], {
+            apply(target, thisArg, args) {
+                if (typeof args[0] === 'string') reportEval();
+                return Reflect.apply(target, thisArg, args);
+            }
+        });
+    });
+
+    document.addEventListener('DOMContentLoaded', function() {
+        document.querySelectorAll('meta[http-equiv="Content-Security-Policy" i]').forEach(function(meta) {
+            send({ type: 'csp-meta-policy', content: meta.content });
+        });
+
+        const inlineScripts = document.querySelectorAll('script:not([src])').length;
+        if (inlineScripts) send({ type: 'csp-script-usage', kind: 'inline-script', count: inlineScripts });
+
+        let handlers = 0;
+        let jsUrls = 0;
+        document.querySelectorAll('*').forEach(function(el) {
+            for (const attr of el.attributes) {
+                if (attr.name.startsWith('on')) handlers++;
+            }
+            const href = el.getAttribute('href');
+            if (href && href.trim().toLowerCase().startsWith('javascript:')) jsUrls++;
+        });
+        if (handlers) send({ type: 'csp-script-usage', kind: 'inline-handler', count: handlers });
+        if (jsUrls) send({ type: 'csp-script-usage', kind: 'javascript-url', count: jsUrls });
+    });
+})();
+"#
+        .to_string()
+    }
+
+    // Private helper methods
+
+    fn policy_allows(policy: &ContentSecurityPolicy, kind: ScriptUsageKind) -> bool {
+        match kind {
+            ScriptUsageKind::InlineScript => policy.allows_inline_script(),
+            ScriptUsageKind::InlineHandler | ScriptUsageKind::JavascriptUrl => policy.allows_inline_handlers(),
+            ScriptUsageKind::Eval => policy.allows_eval(),
+        }
+    }
+
+    fn update_site(&self, site: &str, apply: impl FnOnce(&mut SiteCspStats)) {
+        let mut sites = self.sites.lock_or_recover();
+        let stats = sites.entry(site.to_string()).or_insert_with(|| SiteCspStats {
+            site: site.to_string(),
+            violation_count: 0,
+            by_directive: BTreeMap::new(),
+            blocked_uris: Vec::new(),
+            inline_script_count: 0,
+            eval_count: 0,
+            last_seen: chrono::Utc::now(),
+        });
+        apply(stats);
+        stats.last_seen = chrono::Utc::now();
+    }
+}
+
+impl Default for CspMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpcHandler for CspMonitor {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["csp-violation", "csp-script-usage", "csp-meta-policy"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        let tab_id = context.tab_id.ok_or("CSP report from a pane without a tab")?;
+        self.record_message(tab_id, message);
+        Ok(serde_json::Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_report() {
+        let monitor = CspMonitor::new();
+        monitor.record_document(
+            1,
+            "https://www.example.com/app",
+            &[
+                ("Content-Security-Policy".to_string(), "script-src 'self'; object-src 'none'".to_string()),
+                ("Content-Security-Policy-Report-Only".to_string(), "default-src 'self'".to_string()),
+            ],
+        );
+
+        assert!(!monitor.record_script_usage(1, ScriptUsageKind::InlineScript));
+        assert!(!monitor.record_script_usage(1, ScriptUsageKind::Eval));
+
+        let handled = monitor
+            .handle_ipc_message(
+                1,
+                r#"{"type":"csp-violation","violation":{"blockedUri":"https://evil.example/x.js",
+                    "violatedDirective":"script-src 'self'","effectiveDirective":"script-src-elem","disposition":"enforce"}}"#,
+            )
+            .unwrap();
+        assert!(handled);
+        assert!(!monitor.handle_ipc_message(1, r#"{"type":"find-start"}"#).unwrap());
+
+        let report = monitor.get_tab_report(1).unwrap();
+        assert!(report.has_policy && report.enforced);
+        assert_eq!(report.policies.len(), 2);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.unprotected_executions, 0);
+        assert!(report.findings.iter().any(|f| f.directive == "base-uri"));
+
+        // A new document resets the tab but site stats accumulate
+        monitor.record_document(1, "https://example.com/other", &[]);
+        assert!(monitor.record_script_usage(1, ScriptUsageKind::InlineScript));
+        let report = monitor.get_tab_report(1).unwrap();
+        assert!(!report.has_policy);
+        assert_eq!(report.unprotected_executions, 1);
+        assert_eq!(report.findings[0].severity, FindingSeverity::High);
+
+        let stats = monitor.get_site_stats("https://example.com").unwrap();
+        assert_eq!(stats.violation_count, 1);
+        assert_eq!(stats.by_directive["script-src-elem"], 1);
+        assert_eq!(stats.blocked_uris, vec!["https://evil.example/x.js".to_string()]);
+        assert_eq!(stats.inline_script_count, 2);
+        assert_eq!(stats.eval_count, 1);
+    }
+
+    #[test]
+    fn test_meta_policy_and_usage_messages() {
+        let monitor = CspMonitor::new();
+        monitor.record_document(4, "https://blog.example.org/", &[]);
+        monitor
+            .handle_ipc_message(4, r#"{"type":"csp-meta-policy","content":"script-src 'self' 'unsafe-eval'"}"#)
+            .unwrap();
+        monitor
+            .handle_ipc_message(4, r#"{"type":"csp-script-usage","kind":"inline-handler","count":3}"#)
+            .unwrap();
+        assert!(monitor.record_script_usage(4, ScriptUsageKind::Eval));
+
+        let report = monitor.get_tab_report(4).unwrap();
+        assert!(report.enforced);
+        assert_eq!(report.script_usage["InlineHandler"], 3);
+        assert!(monitor.handle_ipc_message(4, r#"{"type":"csp-script-usage","kind":"bogus"}"#).is_err());
+
+        monitor.remove_tab(4);
+        assert!(monitor.get_tab_report(4).is_none());
+        assert_eq!(monitor.list_sites()[0].site, "blog.example.org");
+    }
+}
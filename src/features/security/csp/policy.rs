@@ -0,0 +1,214 @@
+// Content Security Policy Parsing
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How serious an audit finding is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FindingSeverity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+/// A weakness found in a site's policy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CspFinding {
+    pub severity: FindingSeverity,
+    pub directive: String,
+    pub message: String,
+}
+
+/// A single parsed policy (one header value)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContentSecurityPolicy {
+    /// Directive name (lowercase) to source list
+    pub directives: BTreeMap<String, Vec<String>>,
+    /// Delivered via Content-Security-Policy-Report-Only
+    pub report_only: bool,
+}
+
+impl ContentSecurityPolicy {
+    /// Parse one serialized policy; later duplicates of a directive are ignored per spec
+    pub fn parse(value: &str, report_only: bool) -> Self {
+        let mut directives = BTreeMap::new();
+        for directive in value.split(';') {
+            let mut tokens = directive.split_ascii_whitespace();
+            let Some(name) = tokens.next() else {
+                continue;
+            };
+            directives
+                .entry(name.to_lowercase())
+                .or_insert_with(|| tokens.map(|t| t.to_string()).collect());
+        }
+        Self { directives, report_only }
+    }
+
+    /// Parse a header value that may hold several comma-separated policies
+    pub fn parse_header(value: &str, report_only: bool) -> Vec<Self> {
+        value
+            .split(',')
+            .map(|policy| Self::parse(policy, report_only))
+            .filter(|policy| !policy.directives.is_empty())
+            .collect()
+    }
+
+    /// Sources for a directive, following the fallback chain to default-src
+    pub fn effective_sources(&self, directive: &str) -> Option<&Vec<String>> {
+        let chain: &[&str] = match directive {
+            "script-src-elem" | "script-src-attr" => &[directive, "script-src", "default-src"],
+            "style-src-elem" | "style-src-attr" => &[directive, "style-src", "default-src"],
+            "worker-src" => &["worker-src", "child-src", "script-src", "default-src"],
+            "frame-src" => &["frame-src", "child-src", "default-src"],
+            // Navigation and document directives do not fall back
+            "base-uri" | "form-action" | "frame-ancestors" | "sandbox" => &[directive],
+            _ => &[directive, "default-src"],
+        };
+        chain.iter().find_map(|name| self.directives.get(*name))
+    }
+
+    /// Whether inline `<script>` blocks may run under this policy
+    pub fn allows_inline_script(&self) -> bool {
+        self.allows_inline("script-src-elem")
+    }
+
+    /// Whether inline event handlers (onclick=...) and javascript: URLs may run
+    pub fn allows_inline_handlers(&self) -> bool {
+        self.allows_inline("script-src-attr")
+    }
+
+    /// Whether eval(), new Function() and string timers may run
+    pub fn allows_eval(&self) -> bool {
+        match self.effective_sources("script-src") {
+            Some(sources) => has_keyword(sources, "'unsafe-eval'"),
+            None => true,
+        }
+    }
+
+    /// Endpoints violations should be reported to
+    pub fn report_endpoints(&self) -> Vec<String> {
+        self.directives
+            .get("report-uri")
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Check the policy for common weaknesses
+    pub fn audit(&self) -> Vec<CspFinding> {
+        let mut findings = Vec::new();
+        let mut add = |severity, directive: &str, message: &str| {
+            findings.push(CspFinding {
+                severity,
+                directive: directive.to_string(),
+                message: message.to_string(),
+            })
+        };
+
+        match self.effective_sources("script-src") {
+            None => add(
+                FindingSeverity::High,
+                "script-src",
+                "No script-src or default-src: scripts can load from anywhere",
+            ),
+            Some(sources) => {
+                if self.allows_inline_script() {
+                    add(FindingSeverity::High, "script-src", "'unsafe-inline' allows injected inline scripts");
+                }
+                if has_keyword(sources, "'unsafe-eval'") {
+                    add(FindingSeverity::Medium, "script-src", "'unsafe-eval' allows eval() and new Function()");
+                }
+                if sources.iter().any(|s| is_wildcard(s)) {
+                    add(FindingSeverity::High, "script-src", "Wildcard source allows scripts from any host");
+                }
+                if sources.iter().any(|s| s.eq_ignore_ascii_case("http:") || s.starts_with("http://")) {
+                    add(FindingSeverity::Medium, "script-src", "Scripts may load over insecure HTTP");
+                }
+                if sources.iter().any(|s| s.eq_ignore_ascii_case("data:")) {
+                    add(FindingSeverity::Medium, "script-src", "data: URLs can be used to inject scripts");
+                }
+            }
+        }
+
+        if !self
+            .effective_sources("object-src")
+            .is_some_and(|sources| has_keyword(sources, "'none'"))
+        {
+            add(FindingSeverity::Medium, "object-src", "Plugins are not blocked; set object-src 'none'");
+        }
+        if !self.directives.contains_key("base-uri") {
+            add(FindingSeverity::Low, "base-uri", "Missing base-uri allows <base> tag injection");
+        }
+        if !self.directives.contains_key("frame-ancestors") {
+            add(FindingSeverity::Info, "frame-ancestors", "Page may be framed by other sites");
+        }
+        if self.report_only {
+            add(FindingSeverity::Info, "", "Policy is report-only and is not enforced");
+        }
+
+        findings
+    }
+
+    // Private helper methods
+
+    fn allows_inline(&self, directive: &str) -> bool {
+        let Some(sources) = self.effective_sources(directive) else {
+            return true;
+        };
+        // Nonces, hashes and strict-dynamic make browsers ignore 'unsafe-inline'
+        let has_nonce_or_hash = sources.iter().any(|s| {
+            let s = s.to_lowercase();
+            s.starts_with("'nonce-") || s.starts_with("'sha256-") || s.starts_with("'sha384-")
+                || s.starts_with("'sha512-") || s == "'strict-dynamic'"
+        });
+        has_keyword(sources, "'unsafe-inline'") && !has_nonce_or_hash
+    }
+}
+
+fn has_keyword(sources: &[String], keyword: &str) -> bool {
+    sources.iter().any(|s| s.eq_ignore_ascii_case(keyword))
+}
+
+fn is_wildcard(source: &str) -> bool {
+    source == "*" || source.eq_ignore_ascii_case("https:") || source.ends_with("://*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_evaluate() {
+        let policies = ContentSecurityPolicy::parse_header(
+            "default-src 'self'; script-src 'self' 'unsafe-inline' 'nonce-abc'; SCRIPT-SRC *, object-src 'none'",
+            false,
+        );
+        assert_eq!(policies.len(), 2);
+
+        let first = &policies[0];
+        // The duplicate (uppercase) script-src is ignored
+        assert_eq!(first.directives["script-src"].len(), 3);
+        assert!(!first.allows_inline_script(), "nonce disables unsafe-inline");
+        assert!(!first.allows_eval());
+        assert_eq!(first.effective_sources("img-src").unwrap(), &vec!["'self'".to_string()]);
+        assert!(first.effective_sources("frame-ancestors").is_none());
+
+        let second = &policies[1];
+        assert!(second.allows_inline_script());
+        assert!(second.allows_eval());
+    }
+
+    #[test]
+    fn test_audit_findings() {
+        let weak = ContentSecurityPolicy::parse("script-src * 'unsafe-inline' 'unsafe-eval'", false);
+        let findings = weak.audit();
+        let high: Vec<_> = findings.iter().filter(|f| f.severity == FindingSeverity::High).collect();
+        assert_eq!(high.len(), 2);
+        assert!(findings.iter().any(|f| f.directive == "object-src"));
+
+        let strong = ContentSecurityPolicy::parse(
+            "default-src 'none'; script-src 'self'; object-src 'none'; base-uri 'none'; frame-ancestors 'self'",
+            false,
+        );
+        assert!(strong.audit().is_empty());
+    }
+}
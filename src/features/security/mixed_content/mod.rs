@@ -0,0 +1,394 @@
+// Mixed Content Policy
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::web_inspector::ResourceType;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+/// Insecure URLs remembered per page
+const MAX_PAGE_URLS: usize = 20;
+
+/// Script reporting the subresources an HTTPS page loaded over plain HTTP,
+/// whatever got past the interception layer
+pub const MIXED_CONTENT_SCRIPT: &str = r#"
+(function() {
+    if (location.protocol !== 'https:' || !window.PerformanceObserver || window.__webxMixedContent) return;
+    window.__webxMixedContent = true;
+    const resources = {
+        img: 'Image', image: 'Image', audio: 'Media', video: 'Media', track: 'Media',
+        script: 'Script', link: 'Stylesheet', iframe: 'Document', frame: 'Document',
+        xmlhttprequest: 'Xhr', fetch: 'Fetch', beacon: 'Fetch'
+    };
+    const seen = new Set();
+    new PerformanceObserver((list) => {
+        for (const entry of list.getEntries()) {
+            if (!entry.name.startsWith('http:') || seen.has(entry.name)) continue;
+            seen.add(entry.name);
+            window.ipc.send({ type: 'mixedcontent', url: entry.name, resource: resources[entry.initiatorType] || 'Other' });
+        }
+    }).observe({ type: 'resource', buffered: true });
+})();
+"#;
+
+/// How much harm an insecure subresource can do to an HTTPS page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MixedContentKind {
+    /// Images and media: they can be seen or swapped, but not run, so they
+    /// are tried over HTTPS
+    Upgradeable,
+    /// Scripts, styles, frames, requests and the rest, which could take over
+    /// the page
+    Blockable,
+}
+
+impl MixedContentKind {
+    pub fn classify(resource_type: ResourceType) -> Self {
+        match resource_type {
+            ResourceType::Image | ResourceType::Media => MixedContentKind::Upgradeable,
+            _ => MixedContentKind::Blockable,
+        }
+    }
+}
+
+/// What happens to upgradeable mixed content
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PassiveMixedContent {
+    /// Load it over HTTPS instead
+    Upgrade,
+    /// Load it over HTTP, downgrading the page's indicator
+    Allow,
+    Block,
+}
+
+/// What to do with a subresource request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixedContentDecision {
+    Allow,
+    /// Load the HTTPS URL instead
+    Upgrade(String),
+    Block,
+}
+
+/// Security indicator of a tab's page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SecurityIndicator {
+    Secure,
+    /// Images or media loaded over HTTP
+    PassiveMixedContent,
+    /// Scripts, styles or frames loaded over HTTP
+    ActiveMixedContent,
+}
+
+/// Mixed content seen on the page a tab shows
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PageMixedContent {
+    pub page_url: String,
+    pub upgraded: u64,
+    pub blocked: u64,
+    /// Upgradeable subresources loaded over HTTP
+    pub passive_loaded: u64,
+    /// Blockable subresources loaded over HTTP
+    pub active_loaded: u64,
+    /// The first insecure URLs, for the page's report
+    pub urls: Vec<String>,
+}
+
+impl PageMixedContent {
+    pub fn indicator(&self) -> SecurityIndicator {
+        if self.active_loaded > 0 {
+            SecurityIndicator::ActiveMixedContent
+        } else if self.passive_loaded > 0 {
+            SecurityIndicator::PassiveMixedContent
+        } else {
+            SecurityIndicator::Secure
+        }
+    }
+}
+
+/// Mixed content events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MixedContentEvent {
+    /// A tab's security indicator changed; refresh it
+    IndicatorChanged { tab_id: usize, indicator: SecurityIndicator },
+}
+
+/// What a subresource request came to
+#[derive(Clone, Copy)]
+enum Outcome {
+    Upgraded,
+    Blocked,
+    Loaded(MixedContentKind),
+}
+
+#[derive(Clone, Copy)]
+struct MixedContentSettings {
+    passive: PassiveMixedContent,
+    /// Try blockable content over HTTPS before blocking it
+    upgrade_blockable: bool,
+}
+
+/// Decides whether HTTPS pages may load subresources over HTTP, counts
+/// what each page tried, and downgrades a page's security indicator once
+/// insecure content loads on it
+pub struct MixedContentPolicy {
+    settings: Mutex<MixedContentSettings>,
+    pages: Mutex<HashMap<usize, PageMixedContent>>,
+    tx: mpsc::UnboundedSender<MixedContentEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<MixedContentEvent>>>,
+}
+
+impl MixedContentPolicy {
+    /// Create a policy upgrading passive content and blocking the rest
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            settings: Mutex::new(MixedContentSettings {
+                passive: PassiveMixedContent::Upgrade,
+                upgrade_blockable: false,
+            }),
+            pages: Mutex::new(HashMap::new()),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Whether loading `url` from `page_url` is mixed content
+    pub fn is_mixed(page_url: &str, url: &str) -> bool {
+        let scheme = |url: &str| url::Url::parse(url).map(|url| url.scheme().to_string()).unwrap_or_default();
+        scheme(page_url) == "https" && matches!(scheme(url).as_str(), "http" | "ws")
+    }
+
+    /// Decide a subresource request of the page a tab shows, counting it
+    /// when it is mixed content
+    pub fn check_request(&self, tab_id: usize, page_url: &str, url: &str, resource_type: ResourceType) -> MixedContentDecision {
+        if !Self::is_mixed(page_url, url) {
+            return MixedContentDecision::Allow;
+        }
+        let kind = MixedContentKind::classify(resource_type);
+        let settings = *self.settings.lock_or_recover();
+        let upgrade = match kind {
+            MixedContentKind::Upgradeable => match settings.passive {
+                PassiveMixedContent::Upgrade => true,
+                PassiveMixedContent::Allow => {
+                    self.record(tab_id, page_url, url, Outcome::Loaded(kind));
+                    return MixedContentDecision::Allow;
+                }
+                PassiveMixedContent::Block => false,
+            },
+            MixedContentKind::Blockable => settings.upgrade_blockable,
+        };
+        match upgrade.then(|| secure_url(url)).flatten() {
+            Some(secure) => {
+                self.record(tab_id, page_url, url, Outcome::Upgraded);
+                MixedContentDecision::Upgrade(secure)
+            }
+            None => {
+                self.record(tab_id, page_url, url, Outcome::Blocked);
+                MixedContentDecision::Block
+            }
+        }
+    }
+
+    /// Record a subresource the page reports it loaded over HTTP
+    pub fn record_loaded(&self, tab_id: usize, page_url: &str, url: &str, resource_type: ResourceType) {
+        if Self::is_mixed(page_url, url) {
+            self.record(tab_id, page_url, url, Outcome::Loaded(MixedContentKind::classify(resource_type)));
+        }
+    }
+
+    /// Mixed content seen on a tab's page
+    pub fn page(&self, tab_id: usize) -> Option<PageMixedContent> {
+        self.pages.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    pub fn indicator(&self, tab_id: usize) -> SecurityIndicator {
+        self.page(tab_id).map_or(SecurityIndicator::Secure, |page| page.indicator())
+    }
+
+    /// Forget a tab's page, e.g. when the tab navigates or closes
+    pub fn remove_tab(&self, tab_id: usize) {
+        let removed = self.pages.lock_or_recover().remove(&tab_id);
+        if removed.is_some_and(|page| page.indicator() != SecurityIndicator::Secure) {
+            let _ = self.tx.send(MixedContentEvent::IndicatorChanged {
+                tab_id,
+                indicator: SecurityIndicator::Secure,
+            });
+        }
+    }
+
+    pub fn set_passive(&self, passive: PassiveMixedContent) {
+        self.settings.lock_or_recover().passive = passive;
+    }
+
+    pub fn set_upgrade_blockable(&self, upgrade: bool) {
+        self.settings.lock_or_recover().upgrade_blockable = upgrade;
+    }
+
+    /// Subscribe to mixed content events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<MixedContentEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn record(&self, tab_id: usize, page_url: &str, url: &str, outcome: Outcome) {
+        let changed = {
+            let mut pages = self.pages.lock_or_recover();
+            let page = pages.entry(tab_id).or_default();
+            if page.page_url != page_url {
+                *page = PageMixedContent {
+                    page_url: page_url.to_string(),
+                    ..Default::default()
+                };
+            }
+            let before = page.indicator();
+            match outcome {
+                Outcome::Upgraded => page.upgraded += 1,
+                Outcome::Blocked => page.blocked += 1,
+                Outcome::Loaded(MixedContentKind::Upgradeable) => page.passive_loaded += 1,
+                Outcome::Loaded(MixedContentKind::Blockable) => page.active_loaded += 1,
+            }
+            if page.urls.len() < MAX_PAGE_URLS && !page.urls.iter().any(|seen| seen == url) {
+                page.urls.push(url.to_string());
+            }
+            Some(page.indicator()).filter(|after| *after != before)
+        };
+        if let Some(indicator) = changed {
+            tracing::info!("Insecure content loaded on {}", page_url);
+            let _ = self.tx.send(MixedContentEvent::IndicatorChanged { tab_id, indicator });
+        }
+    }
+}
+
+impl Default for MixedContentPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpcHandler for MixedContentPolicy {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["mixedcontent"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::MixedContent { url, resource } => {
+                let tab_id = context.tab_id.ok_or("Mixed content reported outside a tab")?;
+                self.record_loaded(tab_id, &context.origin, &url, resource);
+                Ok(serde_json::Value::Null)
+            }
+            _ => Err(WebxError::Invalid("Not a mixed content message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for MixedContentPolicy {
+    fn module(&self) -> &str {
+        "mixed_content"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::choice(
+                "mixed_content.passive",
+                "Insecure images and media on secure pages",
+                "upgrade",
+                &[("upgrade", "Load over HTTPS"), ("allow", "Load over HTTP"), ("block", "Block")],
+            )
+            .with_description("Pages loading them over HTTP are no longer shown as secure")
+            .with_category(SettingCategory::Security),
+            SettingDefinition::toggle("mixed_content.upgrade_blockable", "Try insecure scripts over HTTPS", false)
+                .with_description("Scripts, styles and frames a secure page asks for over HTTP are tried over HTTPS instead of blocked")
+                .with_category(SettingCategory::Security),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "mixed_content.passive" => {
+                let passive = match value.as_str() {
+                    Some("upgrade") => PassiveMixedContent::Upgrade,
+                    Some("allow") => PassiveMixedContent::Allow,
+                    Some("block") => PassiveMixedContent::Block,
+                    _ => return Err("Expected upgrade, allow or block".into()),
+                };
+                self.set_passive(passive);
+            }
+            "mixed_content.upgrade_blockable" => {
+                self.set_upgrade_blockable(value.as_bool().ok_or("Expected a toggle value")?);
+            }
+            _ => return Err(format!("Unknown mixed content setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+/// The HTTPS or WSS address of an HTTP or WS URL
+fn secure_url(url: &str) -> Option<String> {
+    let mut url = url::Url::parse(url).ok()?;
+    let scheme = if url.scheme() == "ws" { "wss" } else { "https" };
+    url.set_scheme(scheme).ok()?;
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_content_decisions_and_indicator() {
+        let policy = MixedContentPolicy::new();
+        let mut events = policy.subscribe_events();
+        let page = "https://news.example/story";
+
+        assert_eq!(
+            policy.check_request(1, "http://news.example/", "http://cdn.example/a.js", ResourceType::Script),
+            MixedContentDecision::Allow
+        );
+        assert_eq!(
+            policy.check_request(1, page, "http://cdn.example:80/photo.jpg", ResourceType::Image),
+            MixedContentDecision::Upgrade("https://cdn.example/photo.jpg".to_string())
+        );
+        assert_eq!(
+            policy.check_request(1, page, "http://cdn.example/app.js", ResourceType::Script),
+            MixedContentDecision::Block
+        );
+        policy.apply_setting("mixed_content.upgrade_blockable", &SettingValue::Bool(true)).unwrap();
+        assert_eq!(
+            policy.check_request(1, page, "ws://live.example/feed", ResourceType::WebSocket),
+            MixedContentDecision::Upgrade("wss://live.example/feed".to_string())
+        );
+        assert_eq!(policy.indicator(1), SecurityIndicator::Secure);
+        assert!(events.try_recv().is_err());
+
+        // Passive content loaded over HTTP downgrades the indicator once
+        policy.apply_setting("mixed_content.passive", &SettingValue::String("allow".to_string())).unwrap();
+        assert_eq!(
+            policy.check_request(1, page, "http://cdn.example/clip.mp4", ResourceType::Media),
+            MixedContentDecision::Allow
+        );
+        policy.record_loaded(1, page, "http://cdn.example/other.png", ResourceType::Image);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(MixedContentEvent::IndicatorChanged { tab_id: 1, indicator: SecurityIndicator::PassiveMixedContent })
+        ));
+        assert!(events.try_recv().is_err());
+        let counts = policy.page(1).unwrap();
+        assert_eq!((counts.upgraded, counts.blocked, counts.passive_loaded), (2, 1, 2));
+        assert_eq!(counts.urls.len(), 5);
+
+        // A new page starts over
+        policy.record_loaded(1, "https://news.example/next", "http://cdn.example/app.js", ResourceType::Script);
+        assert_eq!(policy.indicator(1), SecurityIndicator::ActiveMixedContent);
+        assert_eq!(policy.page(1).unwrap().passive_loaded, 0);
+        policy.remove_tab(1);
+        assert_eq!(policy.indicator(1), SecurityIndicator::Secure);
+    }
+}
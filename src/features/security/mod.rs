@@ -2,7 +2,23 @@
 pub mod password_manager;
 pub mod ad_blocker;
 pub mod privacy;
+pub mod permissions;
+pub mod webauthn;
+pub mod csp;
+pub mod safe_browsing;
+pub mod secrets;
+pub mod clipboard;
+pub mod mixed_content;
+pub mod sri;
 
 pub use password_manager::PasswordManager;
 pub use ad_blocker::AdBlocker;
-pub use privacy::PrivacyProtection;
\ No newline at end of file
+pub use privacy::PrivacyProtection;
+pub use permissions::PermissionManager;
+pub use webauthn::WebAuthnBridge;
+pub use csp::CspMonitor;
+pub use safe_browsing::SafeBrowsing;
+pub use secrets::SecretStore;
+pub use clipboard::ClipboardBroker;
+pub use mixed_content::MixedContentPolicy;
+pub use sri::SriChecker;
\ No newline at end of file
@@ -1,4 +1,5 @@
 // Password Encryption Utilities
+use crate::error::WebxError;
 use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
@@ -11,34 +12,50 @@ use sha2::Sha256;
 /// Password encryption handler
 pub struct PasswordEncryption {
     master_key: [u8; 32],
-    salt: [u8; 32],
 }
 
 impl PasswordEncryption {
     /// Create new password encryption instance
-    pub fn new(master_password: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let salt = Self::generate_salt();
-        let master_key = Self::derive_key(master_password, &salt)?;
-        
-        Ok(Self {
-            master_key,
-            salt,
-        })
+    pub fn new(master_password: &str) -> Result<Self, WebxError> {
+        Self::with_salt(master_password, &Self::generate_salt())
+    }
+
+    /// Derive the key from a master password and a salt kept from earlier runs
+    pub fn with_salt(master_password: &str, salt: &[u8]) -> Result<Self, WebxError> {
+        let master_key = Self::derive_key(master_password, salt)?;
+        Ok(Self { master_key })
+    }
+
+    /// Use a random key held elsewhere, such as the OS keyring
+    pub fn from_key(master_key: [u8; 32]) -> Self {
+        Self { master_key }
     }
     
     /// Encrypt a password
-    pub fn encrypt(&self, password: &str) -> Result<(Vec<u8>, [u8; 12]), Box<dyn std::error::Error>> {
+    pub fn encrypt(&self, password: &str) -> Result<(Vec<u8>, [u8; 12]), WebxError> {
         let iv = Self::generate_iv();
         let encrypted = Self::encrypt_password(password, &self.master_key, &iv)?;
         Ok((encrypted, iv))
     }
     
     /// Decrypt a password
-    pub fn decrypt(&self, encrypted_data: &(Vec<u8>, [u8; 12])) -> Result<String, Box<dyn std::error::Error>> {
+    pub fn decrypt(&self, encrypted_data: &(Vec<u8>, [u8; 12])) -> Result<String, WebxError> {
         let (ref encrypted, ref iv) = encrypted_data;
         Self::decrypt_password(encrypted, &self.master_key, iv)
     }
-    pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+
+    /// Check, in constant time, whether this is the key derived from a
+    /// master password
+    pub fn matches_password(&self, password: &str, salt: &[u8]) -> Result<bool, WebxError> {
+        let key = Self::derive_key(password, salt)?;
+        let difference = key
+            .iter()
+            .zip(self.master_key.iter())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b));
+        Ok(difference == 0)
+    }
+
+    pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], WebxError> {
         let mut key = [0u8; 32];
         pbkdf2::<hmac::Hmac<sha2::Sha256>>(
             password.as_bytes(),
@@ -46,7 +63,7 @@ impl PasswordEncryption {
             100_000,
             &mut key,
         )
-        .map_err(|_| "Key derivation failed")?;
+        .map_err(|_| WebxError::Crypto("Key derivation failed".to_string()))?;
         Ok(key)
     }
 
@@ -69,12 +86,12 @@ impl PasswordEncryption {
         password: &str,
         key: &[u8; 32],
         iv: &[u8; 12],
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| "Invalid key")?;
+    ) -> Result<Vec<u8>, WebxError> {
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| WebxError::Crypto("Invalid key".to_string()))?;
         let nonce = Nonce::from_slice(iv);
         let encrypted = cipher
             .encrypt(nonce, password.as_bytes())
-            .map_err(|_| "Encryption failed")?;
+            .map_err(|_| WebxError::Crypto("Encryption failed".to_string()))?;
         Ok(encrypted)
     }
 
@@ -83,14 +100,14 @@ impl PasswordEncryption {
         encrypted: &[u8],
         key: &[u8; 32],
         iv: &[u8; 12],
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| "Invalid key")?;
+    ) -> Result<String, WebxError> {
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| WebxError::Crypto("Invalid key".to_string()))?;
         let nonce = Nonce::from_slice(iv);
         let decrypted = cipher
             .decrypt(nonce, encrypted)
-            .map_err(|_| "Decryption failed")?;
+            .map_err(|_| WebxError::Crypto("Decryption failed".to_string()))?;
         let password = String::from_utf8(decrypted)
-            .map_err(|_| "Invalid UTF-8 in decrypted password")?;
+            .map_err(|_| WebxError::Crypto("Invalid UTF-8 in decrypted password".to_string()))?;
         Ok(password)
     }
 
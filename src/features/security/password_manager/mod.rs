@@ -5,10 +5,43 @@ pub mod ui;
 
 pub use encryption::PasswordEncryption;
 pub use storage::PasswordStorage;
-pub use ui::PasswordUI;
+pub use ui::{PasskeyPreview, PasswordUI};
 
+use crate::error::WebxError;
+use crate::features::security::secrets::{SecretStore, VAULT_KEY_SECRET};
+use crate::utils::LockExt;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+/// How the password vault's key is obtained
+pub enum VaultUnlock<'a> {
+    /// Derived from a password the user types, with a salt kept in the vault
+    MasterPassword(&'a str),
+    /// A random key kept in the OS keyring or its encrypted-file fallback
+    Secrets(&'a SecretStore),
+}
+
+/// A passkey of the platform authenticator, kept encrypted in the vault
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoredPasskey {
+    pub credential_id: Vec<u8>,
+    pub rp_id: String,
+    pub user_id: Vec<u8>,
+    pub user_name: String,
+    pub user_display_name: String,
+    /// P-256 private key
+    pub private_key: Vec<u8>,
+    pub sign_count: u32,
+    /// Offered when the site does not name the credentials it accepts
+    pub discoverable: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
 /// Main Password Manager that coordinates all password functionality
 pub struct PasswordManager {
     storage: Arc<Mutex<PasswordStorage>>,
@@ -17,16 +50,37 @@ pub struct PasswordManager {
 }
 
 impl PasswordManager {
-    /// Create new password manager
-    pub fn new(master_password: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
-        let storage = Arc::new(Mutex::new(PasswordStorage::new(None)?));
-        let encryption = PasswordEncryption::new(master_password.unwrap_or("default"))?;
-        let ui = PasswordUI::new();
-        
+    /// Create new password manager. Without a master password the vault is
+    /// unlocked with a key from the OS keyring.
+    pub fn new(master_password: Option<&str>) -> Result<Self, WebxError> {
+        match master_password {
+            Some(password) => Self::open(None, VaultUnlock::MasterPassword(password)),
+            None => Self::open(None, VaultUnlock::Secrets(&SecretStore::new(None)?)),
+        }
+    }
+
+    /// Open the vault at `db_path` (default: the data directory)
+    pub fn open(db_path: Option<PathBuf>, unlock: VaultUnlock) -> Result<Self, WebxError> {
+        let storage = PasswordStorage::new(db_path)?;
+        let encryption = match unlock {
+            VaultUnlock::MasterPassword(password) => {
+                let salt = match storage.get_salt()? {
+                    Some(salt) => salt,
+                    None => {
+                        let salt = PasswordEncryption::generate_salt().to_vec();
+                        storage.store_salt(&salt)?;
+                        salt
+                    }
+                };
+                PasswordEncryption::with_salt(password, &salt)?
+            }
+            VaultUnlock::Secrets(secrets) => PasswordEncryption::from_key(secrets.get_or_create_key(VAULT_KEY_SECRET)?),
+        };
+
         Ok(Self {
-            storage,
+            storage: Arc::new(Mutex::new(storage)),
             encryption,
-            ui,
+            ui: PasswordUI::new(),
         })
     }
     
@@ -36,9 +90,9 @@ impl PasswordManager {
         url: &str,
         username: &str,
         password: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), WebxError> {
         let encrypted_password = self.encryption.encrypt(password)?;
-        let storage = self.storage.lock().unwrap();
+        let storage = self.storage.lock_or_recover();
         storage.save_password(url, username, &encrypted_password)
     }
     
@@ -47,8 +101,8 @@ impl PasswordManager {
         &self,
         url: &str,
         username: &str,
-    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        let storage = self.storage.lock().unwrap();
+    ) -> Result<Option<String>, WebxError> {
+        let storage = self.storage.lock_or_recover();
         if let Some(encrypted_password) = storage.get_password(url, username)? {
             let decrypted = self.encryption.decrypt(&encrypted_password)?;
             Ok(Some(decrypted))
@@ -57,8 +111,78 @@ impl PasswordManager {
         }
     }
     
+    /// Delete every password saved for a domain and its subdomains
+    pub fn delete_site_passwords(&self, domain: &str) -> Result<usize, WebxError> {
+        self.storage.lock_or_recover().delete_site_passwords(domain)
+    }
+    
+    /// Save a passkey, replacing one with the same credential ID
+    pub fn save_passkey(&self, passkey: &StoredPasskey) -> Result<(), WebxError> {
+        let sealed = self.encryption.encrypt(&serde_json::to_string(passkey)?)?;
+        self.storage
+            .lock_or_recover()
+            .store_passkey(&URL_SAFE_NO_PAD.encode(&passkey.credential_id), &sealed)
+    }
+
+    /// Passkeys for a relying party ID
+    pub fn passkeys(&self, rp_id: &str) -> Result<Vec<StoredPasskey>, WebxError> {
+        let sealed = self.storage.lock_or_recover().list_passkeys()?;
+        let mut passkeys = Vec::new();
+        for entry in sealed {
+            let passkey: StoredPasskey = serde_json::from_str(&self.encryption.decrypt(&entry)?)?;
+            if passkey.rp_id.eq_ignore_ascii_case(rp_id) {
+                passkeys.push(passkey);
+            }
+        }
+        Ok(passkeys)
+    }
+
+    /// Delete a passkey
+    pub fn delete_passkey(&self, credential_id: &[u8]) -> Result<bool, WebxError> {
+        self.storage
+            .lock_or_recover()
+            .delete_passkey(&URL_SAFE_NO_PAD.encode(credential_id))
+    }
+
+    /// Check a master password against the one the vault was opened with.
+    /// Always false for a vault unlocked from the OS keyring.
+    pub fn verify_master_password(&self, password: &str) -> Result<bool, WebxError> {
+        match self.storage.lock_or_recover().get_salt()? {
+            Some(salt) => self.encryption.matches_password(password, &salt),
+            None => Ok(false),
+        }
+    }
+
+    /// Show passkeys from the WebAuthn bridge in the entry view
+    pub fn set_passkeys(&mut self, passkeys: Vec<PasskeyPreview>) {
+        self.ui.set_passkeys(passkeys);
+    }
+    
     /// Show password manager UI
     pub fn show_ui(&self) {
         self.ui.show();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::security::secrets::SecretBackend;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vault_reopens_with_stored_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("passwords.db");
+        let secrets = SecretStore::with_backend(Some(temp_dir.path().to_path_buf()), SecretBackend::EncryptedFile).unwrap();
+
+        {
+            let manager = PasswordManager::open(Some(db_path.clone()), VaultUnlock::Secrets(&secrets)).unwrap();
+            manager.save_password("https://example.com", "alice", "s3cret").unwrap();
+        }
+        let manager = PasswordManager::open(Some(db_path), VaultUnlock::Secrets(&secrets)).unwrap();
+        assert_eq!(
+            manager.get_password("https://example.com", "alice").unwrap().as_deref(),
+            Some("s3cret")
+        );
+    }
+}
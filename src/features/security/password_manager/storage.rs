@@ -1,4 +1,7 @@
 // Password Storage Backend
+use crate::error::WebxError;
+use crate::utils::{LockExt, url_in_domain};
+use serde::{Deserialize, Serialize};
 use sled::Db;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -11,7 +14,7 @@ pub struct PasswordStorage {
 
 impl PasswordStorage {
     /// Create new password storage
-    pub fn new(db_path: Option<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(db_path: Option<PathBuf>) -> Result<Self, WebxError> {
         let db_path = db_path.unwrap_or_else(|| {
             let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
             path.push("webx");
@@ -38,7 +41,7 @@ impl PasswordStorage {
         url: &str,
         username: &str,
         encrypted_password: &(Vec<u8>, [u8; 12]),
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), WebxError> {
         let id = self.generate_id(url, username);
         let (ref encrypted_data, ref iv) = encrypted_password;
         
@@ -56,7 +59,7 @@ impl PasswordStorage {
         &self,
         url: &str,
         username: &str,
-    ) -> Result<Option<(Vec<u8>, [u8; 12])>, Box<dyn std::error::Error>> {
+    ) -> Result<Option<(Vec<u8>, [u8; 12])>, WebxError> {
         let id = self.generate_id(url, username);
         
         if let Some(encrypted_data) = self.get_password_entry(id)? {
@@ -99,8 +102,8 @@ impl PasswordStorage {
     }
     
     /// Get password by ID (internal helper)
-    fn get_password_by_id(&self, id: usize) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    fn get_password_by_id(&self, id: usize) -> Result<Option<Vec<u8>>, WebxError> {
+        let db = self.db.lock_or_recover();
         let key = format!("password_{}", id);
         
         if let Some(value) = db.get(key)? {
@@ -118,8 +121,8 @@ impl PasswordStorage {
         username: &str,
         encrypted_password: &[u8],
         iv: &[u8; 12],
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    ) -> Result<(), WebxError> {
+        let db = self.db.lock_or_recover();
         let key = format!("password_{}", id);
         
         let entry = serde_json::json!({
@@ -137,8 +140,8 @@ impl PasswordStorage {
         Ok(())
     }
     /// Get password by ID (internal helper)
-    fn get_password_entry(&self, id: usize) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    fn get_password_entry(&self, id: usize) -> Result<Option<Vec<u8>>, WebxError> {
+        let db = self.db.lock_or_recover();
         let key = format!("password_{}", id);
         
         if let Some(value) = db.get(key)? {
@@ -149,8 +152,8 @@ impl PasswordStorage {
     }
 
     /// List all password entries (metadata only)
-    pub fn list_passwords(&self) -> Result<Vec<(usize, String, String)>, Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    pub fn list_passwords(&self) -> Result<Vec<(usize, String, String)>, WebxError> {
+        let db = self.db.lock_or_recover();
         let mut entries = Vec::new();
         
         for result in db.iter() {
@@ -174,8 +177,8 @@ impl PasswordStorage {
     }
 
     /// Delete password entry
-    pub fn delete_password(&self, id: usize) -> Result<bool, Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    pub fn delete_password(&self, id: usize) -> Result<bool, WebxError> {
+        let db = self.db.lock_or_recover();
         let key = format!("password_{}", id);
         
         if db.contains_key(&key)? {
@@ -186,16 +189,56 @@ impl PasswordStorage {
         }
     }
 
+    /// Delete every password saved for a domain and its subdomains
+    pub fn delete_site_passwords(&self, domain: &str) -> Result<usize, WebxError> {
+        let mut deleted = 0;
+        for (id, website, _) in self.list_passwords()? {
+            if url_in_domain(&website, domain) && self.delete_password(id)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Store an encrypted passkey under its credential ID
+    pub fn store_passkey(&self, credential_id: &str, sealed: &(Vec<u8>, [u8; 12])) -> Result<(), WebxError> {
+        let entry = SealedPasskey {
+            encrypted_passkey: sealed.0.clone(),
+            iv: sealed.1,
+        };
+        let db = self.db.lock_or_recover();
+        db.insert(format!("passkey_{}", credential_id), serde_json::to_vec(&entry)?)?;
+        Ok(())
+    }
+
+    /// All encrypted passkeys
+    pub fn list_passkeys(&self) -> Result<Vec<(Vec<u8>, [u8; 12])>, WebxError> {
+        let db = self.db.lock_or_recover();
+        let mut passkeys = Vec::new();
+        for result in db.scan_prefix("passkey_") {
+            let (_, value) = result?;
+            let entry: SealedPasskey = serde_json::from_slice(&value)?;
+            passkeys.push((entry.encrypted_passkey, entry.iv));
+        }
+        Ok(passkeys)
+    }
+
+    /// Delete a passkey by credential ID
+    pub fn delete_passkey(&self, credential_id: &str) -> Result<bool, WebxError> {
+        let db = self.db.lock_or_recover();
+        Ok(db.remove(format!("passkey_{}", credential_id))?.is_some())
+    }
+
     /// Store master password salt
-    pub fn store_salt(&self, salt: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    pub fn store_salt(&self, salt: &[u8]) -> Result<(), WebxError> {
+        let db = self.db.lock_or_recover();
         db.insert("master_salt", salt)?;
         Ok(())
     }
 
     /// Retrieve master password salt
-    pub fn get_salt(&self) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
-        let db = self.db.lock().unwrap();
+    pub fn get_salt(&self) -> Result<Option<Vec<u8>>, WebxError> {
+        let db = self.db.lock_or_recover();
         if let Some(salt) = db.get("master_salt")? {
             Ok(Some(salt.to_vec()))
         } else {
@@ -207,4 +250,10 @@ impl PasswordStorage {
     pub fn db_path(&self) -> &PathBuf {
         &self.db_path
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedPasskey {
+    encrypted_passkey: Vec<u8>,
+    iv: [u8; 12],
+}
@@ -8,6 +8,7 @@ pub struct PasswordUI {
     pub master_password_strength: PasswordStrength,
     pub entries_count: usize,
     pub recent_entries: Vec<PasswordEntryPreview>,
+    pub passkeys: Vec<PasskeyPreview>,
 }
 
 /// Password strength indicator
@@ -29,6 +30,16 @@ pub struct PasswordEntryPreview {
     pub last_used: Option<String>,
 }
 
+/// Preview of a passkey (WebAuthn credential) for UI display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasskeyPreview {
+    pub credential_id: String,
+    pub website: String,
+    pub username: String,
+    pub authenticator: String,
+    pub last_used: Option<String>,
+}
+
 impl PasswordUI {
     /// Create new password UI state
     pub fn new() -> Self {
@@ -37,6 +48,7 @@ impl PasswordUI {
             master_password_strength: PasswordStrength::VeryWeak,
             entries_count: 0,
             recent_entries: Vec::new(),
+            passkeys: Vec::new(),
         }
     }
 
@@ -60,6 +72,16 @@ impl PasswordUI {
         self.recent_entries = entries;
     }
 
+    /// Update passkeys shown alongside password entries
+    pub fn set_passkeys(&mut self, passkeys: Vec<PasskeyPreview>) {
+        self.passkeys = passkeys;
+    }
+
+    /// Get passkeys registered for a website
+    pub fn passkeys_for_site(&self, website: &str) -> Vec<&PasskeyPreview> {
+        self.passkeys.iter().filter(|p| p.website == website).collect()
+    }
+
     /// Calculate password strength
     pub fn calculate_strength(password: &str) -> PasswordStrength {
         let length = password.len();
@@ -123,5 +145,6 @@ impl PasswordUI {
         println!("  Master Password Strength: {:?}", self.master_password_strength);
         println!("  Entries Count: {}", self.entries_count);
         println!("  Recent Entries: {}", self.recent_entries.len());
+        println!("  Passkeys: {}", self.passkeys.len());
     }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+// Media Device Selection Memory
+use serde::{Deserialize, Serialize};
+
+/// Kind of capture device
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum MediaDeviceKind {
+    VideoInput,
+    AudioInput,
+}
+
+/// A capture device as reported by navigator.mediaDevices.enumerateDevices()
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MediaDevice {
+    pub device_id: String,
+    pub label: String,
+    pub kind: MediaDeviceKind,
+}
+
+/// Device the user chose for a site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceBinding {
+    pub origin: String,
+    pub device: MediaDevice,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeviceBinding {
+    /// Create a binding for a device chosen now
+    pub fn new(origin: &str, device: MediaDevice) -> Self {
+        Self {
+            origin: origin.to_string(),
+            device,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Find the bound device among the currently available ones.
+    ///
+    /// Device IDs are matched first; labels are used as a fallback since
+    /// some platforms regenerate IDs when a device is reconnected.
+    pub fn resolve<'a>(&self, available: &'a [MediaDevice]) -> Option<&'a MediaDevice> {
+        let candidates = || available.iter().filter(|d| d.kind == self.device.kind);
+
+        candidates()
+            .find(|d| d.device_id == self.device.device_id)
+            .or_else(|| {
+                if self.device.label.is_empty() {
+                    None
+                } else {
+                    candidates().find(|d| d.label == self.device.label)
+                }
+            })
+    }
+}
+
+/// Generate JavaScript that pre-selects remembered devices in getUserMedia calls
+/// that do not request a specific device
+pub fn device_preselection_script(video: Option<&MediaDevice>, audio: Option<&MediaDevice>) -> String {
+    let quote = |device: Option<&MediaDevice>| {
+        device
+            .map(|d| serde_json::to_string(&d.device_id).unwrap_or_else(|_| "null".to_string()))
+            .unwrap_or_else(|| "null".to_string())
+    };
+
+    format!(
+        r#"
+(function() {{
+    if (!navigator.mediaDevices || window.__webxDevicePreselect) return;
+    window.__webxDevicePreselect = true;
+
+    const preferred = {{ video: {}, audio: {} }};
+    const original = navigator.mediaDevices.getUserMedia.bind(navigator.mediaDevices);
+
+    const withDevice = (constraint, deviceId) => {{
+        if (!constraint || !deviceId) return constraint;
+        if (constraint === true) return {{ deviceId: {{ ideal: deviceId }} }};
+        if (constraint.deviceId) return constraint;
+        return {{ ...constraint, deviceId: {{ ideal: deviceId }} }};
+    }};
+
+    navigator.mediaDevices.getUserMedia = function(constraints = {{}}) {{
+        return original({{
+            ...constraints,
+            video: withDevice(constraints.video, preferred.video),
+            audio: withDevice(constraints.audio, preferred.audio)
+        }});
+    }};
+}})();
+"#,
+        quote(video),
+        quote(audio)
+    )
+}
@@ -0,0 +1,367 @@
+// Site Permissions Module
+pub mod devices;
+pub mod recording;
+
+pub use devices::{DeviceBinding, MediaDevice, MediaDeviceKind};
+pub use recording::{RecordingAction, RecordingEvent, RecordingIndicator, RecordingTracker, TabRecording};
+
+use crate::config::storage::{save_json, DEFAULT_BACKUP_COUNT};
+use crate::error::WebxError;
+use crate::utils::{LockExt, url_in_domain};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Capabilities a site can request
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PermissionKind {
+    Camera,
+    Microphone,
+    Notifications,
+    Geolocation,
+    ClipboardRead,
+    Autoplay,
+}
+
+impl PermissionKind {
+    /// Capture device kind governed by this permission, if any
+    pub fn device_kind(&self) -> Option<MediaDeviceKind> {
+        match self {
+            PermissionKind::Camera => Some(MediaDeviceKind::VideoInput),
+            PermissionKind::Microphone => Some(MediaDeviceKind::AudioInput),
+            _ => None,
+        }
+    }
+}
+
+/// Decision for a permission
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    Ask,
+}
+
+/// A stored permission decision for an origin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionGrant {
+    pub origin: String,
+    pub kind: PermissionKind,
+    pub state: PermissionState,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Everything the site info panel shows for one origin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SitePermissions {
+    pub origin: String,
+    pub permissions: Vec<(PermissionKind, PermissionState)>,
+    pub devices: Vec<DeviceBinding>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PermissionStore {
+    grants: Vec<PermissionGrant>,
+    device_bindings: Vec<DeviceBinding>,
+}
+
+/// Per-origin permission store
+pub struct PermissionManager {
+    store: Arc<Mutex<PermissionStore>>,
+    store_path: PathBuf,
+}
+
+impl PermissionManager {
+    /// Create new permission manager
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        let manager = Self {
+            store: Arc::new(Mutex::new(PermissionStore::default())),
+            store_path: config_dir.join("permissions.json"),
+        };
+
+        manager.load_store()?;
+
+        Ok(manager)
+    }
+
+    /// Normalize a URL or origin to its serialized origin
+    pub fn normalize_origin(url: &str) -> String {
+        match url::Url::parse(url) {
+            Ok(parsed) => parsed.origin().ascii_serialization(),
+            Err(_) => url.to_string(),
+        }
+    }
+
+    /// Get the decision for a permission (Ask if never decided)
+    pub fn get_permission(&self, origin: &str, kind: PermissionKind) -> PermissionState {
+        let origin = Self::normalize_origin(origin);
+        self.store
+            .lock_or_recover()
+            .grants
+            .iter()
+            .find(|g| g.origin == origin && g.kind == kind)
+            .map(|g| g.state)
+            .unwrap_or(PermissionState::Ask)
+    }
+
+    /// Record a decision for a permission
+    pub fn set_permission(
+        &self,
+        origin: &str,
+        kind: PermissionKind,
+        state: PermissionState,
+    ) -> Result<(), WebxError> {
+        let origin = Self::normalize_origin(origin);
+        {
+            let mut store = self.store.lock_or_recover();
+            store.grants.retain(|g| !(g.origin == origin && g.kind == kind));
+            if state != PermissionState::Ask {
+                store.grants.push(PermissionGrant {
+                    origin: origin.clone(),
+                    kind,
+                    state,
+                    updated_at: chrono::Utc::now(),
+                });
+            }
+
+            // A device choice only makes sense while access is granted
+            if state != PermissionState::Granted {
+                if let Some(device_kind) = kind.device_kind() {
+                    store
+                        .device_bindings
+                        .retain(|b| !(b.origin == origin && b.device.kind == device_kind));
+                }
+            }
+        }
+        self.save_store()
+    }
+
+    /// Grant camera or microphone access and remember the chosen device
+    pub fn grant_media_access(
+        &self,
+        origin: &str,
+        kind: PermissionKind,
+        device: MediaDevice,
+    ) -> Result<(), WebxError> {
+        self.set_permission(origin, kind, PermissionState::Granted)?;
+        self.set_device_binding(origin, kind, device)
+    }
+
+    /// Change the remembered device for a granted camera/microphone permission
+    pub fn set_device_binding(
+        &self,
+        origin: &str,
+        kind: PermissionKind,
+        device: MediaDevice,
+    ) -> Result<(), WebxError> {
+        let device_kind = kind.device_kind().ok_or("Permission does not use a capture device")?;
+        if device.kind != device_kind {
+            return Err("Device kind does not match permission".into());
+        }
+        if self.get_permission(origin, kind) != PermissionState::Granted {
+            return Err("Permission is not granted for this site".into());
+        }
+
+        let origin = Self::normalize_origin(origin);
+        {
+            let mut store = self.store.lock_or_recover();
+            store
+                .device_bindings
+                .retain(|b| !(b.origin == origin && b.device.kind == device_kind));
+            store.device_bindings.push(DeviceBinding::new(&origin, device));
+        }
+        self.save_store()
+    }
+
+    /// Forget the remembered device without revoking the permission
+    pub fn clear_device_binding(&self, origin: &str, kind: PermissionKind) -> Result<bool, WebxError> {
+        let Some(device_kind) = kind.device_kind() else {
+            return Ok(false);
+        };
+        let origin = Self::normalize_origin(origin);
+        let removed = {
+            let mut store = self.store.lock_or_recover();
+            let len_before = store.device_bindings.len();
+            store
+                .device_bindings
+                .retain(|b| !(b.origin == origin && b.device.kind == device_kind));
+            store.device_bindings.len() != len_before
+        };
+        if removed {
+            self.save_store()?;
+        }
+        Ok(removed)
+    }
+
+    /// Device to pre-select for a site among the currently available devices
+    pub fn preferred_device(
+        &self,
+        origin: &str,
+        kind: PermissionKind,
+        available: &[MediaDevice],
+    ) -> Option<MediaDevice> {
+        let device_kind = kind.device_kind()?;
+        if self.get_permission(origin, kind) != PermissionState::Granted {
+            return None;
+        }
+
+        let origin = Self::normalize_origin(origin);
+        let store = self.store.lock_or_recover();
+        store
+            .device_bindings
+            .iter()
+            .find(|b| b.origin == origin && b.device.kind == device_kind)
+            .and_then(|b| b.resolve(available))
+            .cloned()
+    }
+
+    /// Script that makes the page's getUserMedia calls use the remembered devices
+    pub fn get_device_preselection_script(&self, origin: &str, available: &[MediaDevice]) -> String {
+        let video = self.preferred_device(origin, PermissionKind::Camera, available);
+        let audio = self.preferred_device(origin, PermissionKind::Microphone, available);
+        devices::device_preselection_script(video.as_ref(), audio.as_ref())
+    }
+
+    /// Permissions and device bindings for the site info panel
+    pub fn site_permissions(&self, origin: &str) -> SitePermissions {
+        let origin = Self::normalize_origin(origin);
+        let store = self.store.lock_or_recover();
+
+        SitePermissions {
+            origin: origin.clone(),
+            permissions: store
+                .grants
+                .iter()
+                .filter(|g| g.origin == origin)
+                .map(|g| (g.kind, g.state))
+                .collect(),
+            devices: store
+                .device_bindings
+                .iter()
+                .filter(|b| b.origin == origin)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// List all origins with stored decisions
+    pub fn list_origins(&self) -> Vec<String> {
+        let store = self.store.lock_or_recover();
+        let mut origins: Vec<String> = store.grants.iter().map(|g| g.origin.clone()).collect();
+        origins.sort();
+        origins.dedup();
+        origins
+    }
+
+    /// Remove every decision and device binding for an origin
+    pub fn clear_site(&self, origin: &str) -> Result<(), WebxError> {
+        let origin = Self::normalize_origin(origin);
+        {
+            let mut store = self.store.lock_or_recover();
+            store.grants.retain(|g| g.origin != origin);
+            store.device_bindings.retain(|b| b.origin != origin);
+        }
+        self.save_store()
+    }
+
+    /// Remove every decision for the origins of a domain and its subdomains
+    pub fn clear_domain(&self, domain: &str) -> Result<usize, WebxError> {
+        let origins: Vec<String> = self
+            .list_origins()
+            .into_iter()
+            .filter(|origin| url_in_domain(origin, domain))
+            .collect();
+        for origin in &origins {
+            self.clear_site(origin)?;
+        }
+        Ok(origins.len())
+    }
+
+    // Private helper methods
+
+    fn save_store(&self) -> Result<(), WebxError> {
+        let store = self.store.lock_or_recover();
+        save_json(&self.store_path, &*store, DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+
+    fn load_store(&self) -> Result<(), WebxError> {
+        if self.store_path.exists() {
+            let content = std::fs::read_to_string(&self.store_path)?;
+            *self.store.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn camera(id: &str, label: &str) -> MediaDevice {
+        MediaDevice {
+            device_id: id.to_string(),
+            label: label.to_string(),
+            kind: MediaDeviceKind::VideoInput,
+        }
+    }
+
+    #[test]
+    fn test_device_memory_per_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let origin = "https://meet.example.com/room/42";
+
+        manager
+            .grant_media_access(origin, PermissionKind::Camera, camera("cam-2", "USB Webcam"))
+            .unwrap();
+
+        // Bindings are stored per origin and persisted
+        let manager = PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let available = vec![camera("cam-1", "Integrated Camera"), camera("cam-2", "USB Webcam")];
+        let preferred = manager.preferred_device(origin, PermissionKind::Camera, &available);
+        assert_eq!(preferred.unwrap().device_id, "cam-2");
+
+        // Falls back to label when the device ID changed after reconnecting
+        let reconnected = vec![camera("cam-9", "USB Webcam")];
+        let preferred = manager.preferred_device("https://meet.example.com", PermissionKind::Camera, &reconnected);
+        assert_eq!(preferred.unwrap().device_id, "cam-9");
+
+        let script = manager.get_device_preselection_script(origin, &available);
+        assert!(script.contains(r#"video: "cam-2""#));
+        assert!(script.contains("audio: null"));
+    }
+
+    #[test]
+    fn test_revoking_permission_clears_binding() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let origin = "https://meet.example.com";
+
+        // Binding a device requires the permission to be granted
+        assert!(manager
+            .set_device_binding(origin, PermissionKind::Camera, camera("cam-1", "Integrated Camera"))
+            .is_err());
+
+        manager
+            .grant_media_access(origin, PermissionKind::Camera, camera("cam-1", "Integrated Camera"))
+            .unwrap();
+        assert_eq!(manager.site_permissions(origin).devices.len(), 1);
+
+        manager
+            .set_permission(origin, PermissionKind::Camera, PermissionState::Denied)
+            .unwrap();
+        let site = manager.site_permissions(origin);
+        assert!(site.devices.is_empty());
+        assert_eq!(site.permissions, vec![(PermissionKind::Camera, PermissionState::Denied)]);
+    }
+}
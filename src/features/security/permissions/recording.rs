@@ -0,0 +1,387 @@
+// Camera and Microphone Use
+use super::{PermissionKind, PermissionManager, PermissionState};
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Script asking the browser before a page opens the camera or microphone
+/// and reporting which of them the page's live streams use, so the browser
+/// can show that the tab records and stop it with `window.webxRecording.stop()`
+pub const RECORDING_SCRIPT: &str = r#"
+(function() {
+    if (!navigator.mediaDevices || !navigator.mediaDevices.getUserMedia || window.webxRecording) return;
+    const devices = navigator.mediaDevices;
+    const getUserMedia = devices.getUserMedia.bind(devices);
+    const live = new Set();
+    const report = () => {
+        let camera = false, microphone = false;
+        for (const track of live) {
+            if (track.readyState !== 'live') { live.delete(track); continue; }
+            if (track.kind === 'video') camera = true;
+            if (track.kind === 'audio') microphone = true;
+        }
+        window.ipc.send({ type: 'recording', action: 'state', camera: camera, microphone: microphone });
+    };
+    const watch = (stream) => {
+        for (const track of stream.getTracks()) {
+            live.add(track);
+            // Tracks the page stops itself fire no 'ended' event
+            const stopTrack = track.stop.bind(track);
+            track.stop = () => { stopTrack(); report(); };
+            track.addEventListener('ended', report);
+        }
+        report();
+        return stream;
+    };
+    devices.getUserMedia = function(constraints = {}) {
+        const wanted = { type: 'recording', action: 'request', camera: !!constraints.video, microphone: !!constraints.audio };
+        return window.ipc.request(wanted).then((answer) => {
+            if (!answer.allowed) throw new DOMException(answer.reason, 'NotAllowedError');
+            return getUserMedia(constraints).then(watch);
+        });
+    };
+    const stop = (kinds) => {
+        for (const track of live) {
+            if ((track.kind === 'video' && kinds.camera) || (track.kind === 'audio' && kinds.microphone)) track.stop();
+        }
+        report();
+    };
+    window.webxRecording = { stop: stop };
+})();
+"#;
+
+/// What a page says about its camera and microphone use
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum RecordingAction {
+    /// The page is about to open these devices
+    Request {
+        #[serde(default)]
+        camera: bool,
+        #[serde(default)]
+        microphone: bool,
+    },
+    /// The devices the page's live streams use now
+    State {
+        #[serde(default)]
+        camera: bool,
+        #[serde(default)]
+        microphone: bool,
+    },
+}
+
+/// Devices a tab has open
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TabRecording {
+    pub tab_id: usize,
+    /// URL of the page recording
+    pub origin: String,
+    pub camera: bool,
+    pub microphone: bool,
+    /// When the tab started recording
+    pub since: DateTime<Utc>,
+}
+
+/// Recording indicator shown on a tab
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RecordingIndicator {
+    None,
+    Microphone,
+    Camera,
+    CameraAndMicrophone,
+}
+
+/// Recording events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordingEvent {
+    /// A tab opened or closed a device; refresh its indicator
+    Changed { tab_id: usize },
+    /// Run `stop_script(camera, microphone)` in the tab
+    Revoke { tab_id: usize, camera: bool, microphone: bool },
+}
+
+/// Script closing a page's camera or microphone streams
+pub fn stop_script(camera: bool, microphone: bool) -> String {
+    format!(
+        "window.webxRecording && window.webxRecording.stop({{ camera: {}, microphone: {} }});",
+        camera, microphone
+    )
+}
+
+/// Devices turned off for all sites
+#[derive(Default)]
+struct BlockedDevices {
+    camera: bool,
+    microphone: bool,
+}
+
+/// Tracks which tabs use the camera and microphone, and keeps pages from
+/// opening them while the user turned them off for every site or the site
+pub struct RecordingTracker {
+    tabs: Mutex<HashMap<usize, TabRecording>>,
+    blocked: Mutex<BlockedDevices>,
+    permission_manager: Option<Arc<PermissionManager>>,
+    tx: mpsc::UnboundedSender<RecordingEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<RecordingEvent>>>,
+}
+
+impl RecordingTracker {
+    /// Create new recording tracker
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tabs: Mutex::new(HashMap::new()),
+            blocked: Mutex::new(BlockedDevices::default()),
+            permission_manager: None,
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Refuse devices the site's permissions deny
+    pub fn set_permission_manager(&mut self, permission_manager: Arc<PermissionManager>) {
+        self.permission_manager = Some(permission_manager);
+    }
+
+    /// Whether a page may open the devices, and why not if it may not
+    pub fn check_request(&self, origin: &str, camera: bool, microphone: bool) -> Result<(), String> {
+        {
+            let blocked = self.blocked.lock_or_recover();
+            if camera && blocked.camera {
+                return Err("The camera is turned off for all sites".to_string());
+            }
+            if microphone && blocked.microphone {
+                return Err("The microphone is turned off for all sites".to_string());
+            }
+        }
+        let denied = |kind| {
+            self.permission_manager
+                .as_ref()
+                .is_some_and(|permissions| permissions.get_permission(origin, kind) == PermissionState::Denied)
+        };
+        if camera && denied(PermissionKind::Camera) {
+            return Err("The camera is blocked for this site".to_string());
+        }
+        if microphone && denied(PermissionKind::Microphone) {
+            return Err("The microphone is blocked for this site".to_string());
+        }
+        Ok(())
+    }
+
+    /// Record the devices a tab's page has open
+    pub fn update(&self, tab_id: usize, origin: &str, camera: bool, microphone: bool) {
+        let changed = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let before = tabs.get(&tab_id).map(|tab| (tab.camera, tab.microphone));
+            let after = Some((camera, microphone)).filter(|_| camera || microphone);
+            if camera || microphone {
+                let tab = tabs.entry(tab_id).or_insert_with(|| TabRecording {
+                    tab_id,
+                    origin: origin.to_string(),
+                    camera,
+                    microphone,
+                    since: Utc::now(),
+                });
+                tab.origin = origin.to_string();
+                tab.camera = camera;
+                tab.microphone = microphone;
+            } else {
+                tabs.remove(&tab_id);
+            }
+            before != after
+        };
+        if changed {
+            let _ = self.tx.send(RecordingEvent::Changed { tab_id });
+        }
+
+        // Streams opened before a device was turned off are closed too
+        let blocked = self.blocked.lock_or_recover();
+        if (camera && blocked.camera) || (microphone && blocked.microphone) {
+            let _ = self.tx.send(RecordingEvent::Revoke {
+                tab_id,
+                camera: blocked.camera,
+                microphone: blocked.microphone,
+            });
+        }
+    }
+
+    /// Forget a tab's devices, e.g. when the tab navigates or closes
+    pub fn remove_tab(&self, tab_id: usize) {
+        if self.tabs.lock_or_recover().remove(&tab_id).is_some() {
+            let _ = self.tx.send(RecordingEvent::Changed { tab_id });
+        }
+    }
+
+    /// Devices a tab has open
+    pub fn tab_recording(&self, tab_id: usize) -> Option<TabRecording> {
+        self.tabs.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    /// Every tab with a device open, by tab ID
+    pub fn recordings(&self) -> Vec<TabRecording> {
+        let mut recordings: Vec<TabRecording> = self.tabs.lock_or_recover().values().cloned().collect();
+        recordings.sort_by_key(|recording| recording.tab_id);
+        recordings
+    }
+
+    pub fn indicator(&self, tab_id: usize) -> RecordingIndicator {
+        match self.tab_recording(tab_id).map(|tab| (tab.camera, tab.microphone)) {
+            Some((true, true)) => RecordingIndicator::CameraAndMicrophone,
+            Some((true, false)) => RecordingIndicator::Camera,
+            Some((false, true)) => RecordingIndicator::Microphone,
+            _ => RecordingIndicator::None,
+        }
+    }
+
+    /// Close a tab's camera, microphone or both
+    pub fn revoke_tab(&self, tab_id: usize, camera: bool, microphone: bool) -> Result<(), WebxError> {
+        if self.tab_recording(tab_id).is_none() {
+            return Err(WebxError::NotFound(format!("Recording in tab {}", tab_id)));
+        }
+        let _ = self.tx.send(RecordingEvent::Revoke {
+            tab_id,
+            camera,
+            microphone,
+        });
+        Ok(())
+    }
+
+    /// Close every tab's camera and microphone
+    pub fn revoke_all(&self) {
+        for recording in self.recordings() {
+            let _ = self.revoke_tab(recording.tab_id, true, true);
+        }
+    }
+
+    /// Turn the camera off for all sites, closing it where it is open
+    pub fn set_camera_blocked(&self, blocked: bool) {
+        self.blocked.lock_or_recover().camera = blocked;
+        if blocked {
+            for recording in self.recordings().into_iter().filter(|recording| recording.camera) {
+                let _ = self.revoke_tab(recording.tab_id, true, false);
+            }
+        }
+    }
+
+    /// Turn the microphone off for all sites, closing it where it is open
+    pub fn set_microphone_blocked(&self, blocked: bool) {
+        self.blocked.lock_or_recover().microphone = blocked;
+        if blocked {
+            for recording in self.recordings().into_iter().filter(|recording| recording.microphone) {
+                let _ = self.revoke_tab(recording.tab_id, false, true);
+            }
+        }
+    }
+
+    /// Subscribe to recording events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<RecordingEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+}
+
+impl Default for RecordingTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpcHandler for RecordingTracker {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["recording"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::Recording(RecordingAction::Request { camera, microphone }) => {
+                Ok(match self.check_request(&context.origin, camera, microphone) {
+                    Ok(()) => serde_json::json!({ "allowed": true }),
+                    Err(reason) => serde_json::json!({ "allowed": false, "reason": reason }),
+                })
+            }
+            IpcMessage::Recording(RecordingAction::State { camera, microphone }) => {
+                let tab_id = context.tab_id.ok_or("Recording reported outside a tab")?;
+                self.update(tab_id, &context.origin, camera, microphone);
+                Ok(serde_json::Value::Null)
+            }
+            _ => Err(WebxError::Invalid("Not a recording message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for RecordingTracker {
+    fn module(&self) -> &str {
+        "recording"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("recording.block_camera", "Turn off the camera for all sites", false)
+                .with_description("Sites can not use the camera, whatever their permissions; open cameras are closed")
+                .with_category(SettingCategory::Privacy),
+            SettingDefinition::toggle("recording.block_microphone", "Turn off the microphone for all sites", false)
+                .with_description("Sites can not use the microphone, whatever their permissions; open microphones are closed")
+                .with_category(SettingCategory::Privacy),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let blocked = value.as_bool().ok_or("Expected a toggle value")?;
+        match key {
+            "recording.block_camera" => self.set_camera_blocked(blocked),
+            "recording.block_microphone" => self.set_microphone_blocked(blocked),
+            _ => return Err(format!("Unknown recording setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recording_indicators_and_kill_switch() {
+        let temp_dir = TempDir::new().unwrap();
+        let permissions = Arc::new(PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap());
+        permissions
+            .set_permission("https://spy.example", PermissionKind::Microphone, PermissionState::Denied)
+            .unwrap();
+        let mut tracker = RecordingTracker::new();
+        tracker.set_permission_manager(permissions);
+        let mut events = tracker.subscribe_events();
+
+        assert!(tracker.check_request("https://meet.example/room", true, true).is_ok());
+        assert!(tracker.check_request("https://spy.example/page", false, true).is_err());
+        assert!(tracker.check_request("https://spy.example/page", true, false).is_ok());
+
+        tracker.update(1, "https://meet.example/room", true, true);
+        tracker.update(1, "https://meet.example/room", true, true);
+        assert!(matches!(events.try_recv(), Ok(RecordingEvent::Changed { tab_id: 1 })));
+        assert!(events.try_recv().is_err());
+        assert_eq!(tracker.indicator(1), RecordingIndicator::CameraAndMicrophone);
+
+        // The kill switch refuses new requests and closes the open camera
+        tracker
+            .apply_setting("recording.block_camera", &SettingValue::Bool(true))
+            .unwrap();
+        assert!(tracker.check_request("https://meet.example/room", true, false).is_err());
+        assert!(matches!(
+            events.try_recv(),
+            Ok(RecordingEvent::Revoke { tab_id: 1, camera: true, microphone: false })
+        ));
+        tracker.update(1, "https://meet.example/room", false, true);
+        assert_eq!(tracker.indicator(1), RecordingIndicator::Microphone);
+
+        assert!(tracker.revoke_tab(2, true, true).is_err());
+        tracker.remove_tab(1);
+        assert!(tracker.recordings().is_empty());
+        assert_eq!(tracker.indicator(1), RecordingIndicator::None);
+    }
+}
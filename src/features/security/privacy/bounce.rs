@@ -0,0 +1,329 @@
+// Bounce Tracking Protection
+use super::{PrivacyProtection, TrackerCategory};
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Hops followed when unwrapping a chain before giving up
+const MAX_HOPS: usize = 10;
+
+/// Navigations closer together than this after an intermediary belong to
+/// the same chain
+const CHAIN_WINDOW: Duration = Duration::from_secs(10);
+
+/// Chains kept in the audit log
+const MAX_CHAINS: usize = 200;
+
+/// A redirect service tracking the links it forwards
+struct Redirector {
+    /// Host, subdomains included
+    host: &'static str,
+    path_prefix: &'static str,
+    /// Query parameters carrying the destination; empty when the service
+    /// keeps it to itself, so the chain is only recorded
+    parameters: &'static [&'static str],
+}
+
+const KNOWN_REDIRECTORS: &[Redirector] = &[
+    Redirector { host: "l.facebook.com", path_prefix: "/l.php", parameters: &["u"] },
+    Redirector { host: "lm.facebook.com", path_prefix: "/l.php", parameters: &["u"] },
+    Redirector { host: "l.messenger.com", path_prefix: "/l.php", parameters: &["u"] },
+    Redirector { host: "l.instagram.com", path_prefix: "/", parameters: &["u"] },
+    Redirector { host: "google.com", path_prefix: "/url", parameters: &["q", "url"] },
+    Redirector { host: "youtube.com", path_prefix: "/redirect", parameters: &["q"] },
+    Redirector { host: "out.reddit.com", path_prefix: "/", parameters: &["url"] },
+    Redirector { host: "linkedin.com", path_prefix: "/redir/redirect", parameters: &["url"] },
+    Redirector { host: "steamcommunity.com", path_prefix: "/linkfilter/", parameters: &["url", "u"] },
+    Redirector { host: "slack-redir.net", path_prefix: "/link", parameters: &["url"] },
+    Redirector { host: "vk.com", path_prefix: "/away.php", parameters: &["to"] },
+    Redirector { host: "disq.us", path_prefix: "/url", parameters: &["url"] },
+    Redirector { host: "click.linksynergy.com", path_prefix: "/", parameters: &["murl"] },
+    Redirector { host: "go.redirectingat.com", path_prefix: "/", parameters: &["url"] },
+    Redirector { host: "t.co", path_prefix: "/", parameters: &[] },
+    Redirector { host: "lnkd.in", path_prefix: "/", parameters: &[] },
+    Redirector { host: "list-manage.com", path_prefix: "/track/click", parameters: &[] },
+    Redirector { host: "sendgrid.net", path_prefix: "/ls/click", parameters: &[] },
+];
+
+/// Destination parameters of URLs the tracker rules match
+const TRACKER_DESTINATION_PARAMETERS: &[&str] = &["adurl", "murl", "dest", "destination", "url", "u"];
+
+/// A redirect chain through tracking intermediaries
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedirectChain {
+    pub tab_id: usize,
+    /// Intermediaries, in the order the chain went through them
+    pub hops: Vec<String>,
+    pub destination: String,
+    /// Whether the tab went straight to the destination
+    pub skipped: bool,
+    pub at: DateTime<Utc>,
+}
+
+/// Bounce tracking statistics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BounceStats {
+    pub chains_detected: u64,
+    pub chains_skipped: u64,
+    pub hops_skipped: u64,
+    pub start_time: DateTime<Utc>,
+}
+
+impl Default for BounceStats {
+    fn default() -> Self {
+        Self {
+            chains_detected: 0,
+            chains_skipped: 0,
+            hops_skipped: 0,
+            start_time: Utc::now(),
+        }
+    }
+}
+
+/// Spots navigations bouncing through tracking redirectors and sends the
+/// tab straight to where the chain leads when the destination is known
+pub struct BounceProtection {
+    privacy: Option<Arc<PrivacyProtection>>,
+    enabled: Mutex<bool>,
+    /// Intermediaries a tab went through without a known destination,
+    /// with when it last went through one
+    pending: Mutex<HashMap<usize, (Instant, Vec<String>)>>,
+    chains: Mutex<VecDeque<RedirectChain>>,
+    stats: Mutex<BounceStats>,
+}
+
+impl BounceProtection {
+    /// Create new bounce protection, skipping chains until turned off
+    pub fn new() -> Self {
+        Self {
+            privacy: None,
+            enabled: Mutex::new(true),
+            pending: Mutex::new(HashMap::new()),
+            chains: Mutex::new(VecDeque::new()),
+            stats: Mutex::new(BounceStats::default()),
+        }
+    }
+
+    /// Treat URLs the tracking rules match as intermediaries too
+    pub fn set_privacy_protection(&mut self, privacy: Arc<PrivacyProtection>) {
+        self.privacy = Some(privacy);
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        *self.enabled.lock_or_recover() = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self.enabled.lock_or_recover()
+    }
+
+    /// Whether a URL is a tracking intermediary
+    pub fn is_intermediary(&self, url: &str) -> bool {
+        known_redirector(url).is_some() || self.tracker_parameters(url).is_some()
+    }
+
+    /// Where an intermediary forwards to, one hop on
+    pub fn next_hop(&self, url: &str) -> Option<String> {
+        let parameters = match known_redirector(url) {
+            Some(redirector) => redirector.parameters,
+            None => self.tracker_parameters(url)?,
+        };
+        let parsed = url::Url::parse(url).ok()?;
+        parameters.iter().find_map(|parameter| {
+            let (_, value) = parsed.query_pairs().find(|(name, _)| name == parameter)?;
+            let destination = url::Url::parse(&value).ok()?;
+            let leaves = matches!(destination.scheme(), "http" | "https") && destination.host_str() != parsed.host_str();
+            leaves.then(|| destination.to_string())
+        })
+    }
+
+    /// The intermediaries a URL bounces through and the destination they
+    /// lead to, `None` if it is not an intermediary with a known destination
+    pub fn resolve(&self, url: &str) -> Option<(Vec<String>, String)> {
+        let mut hops = Vec::new();
+        let mut current = url.to_string();
+        while let Some(next) = self.next_hop(&current) {
+            if hops.len() == MAX_HOPS {
+                return None;
+            }
+            hops.push(std::mem::replace(&mut current, next));
+        }
+        (!hops.is_empty()).then_some((hops, current))
+    }
+
+    /// Look at a tab navigating, and return where to go instead when the
+    /// navigation bounces through intermediaries to a known destination.
+    /// Chains whose destination only shows on arrival are recorded then.
+    pub fn check_navigation(&self, tab_id: usize, url: &str) -> Option<String> {
+        let now = Instant::now();
+        let mut pending = self.pending.lock_or_recover();
+        let mut hops = match pending.remove(&tab_id) {
+            Some((at, hops)) if now.duration_since(at) < CHAIN_WINDOW => hops,
+            _ => Vec::new(),
+        };
+        let skip = if self.is_enabled() { self.resolve(url) } else { None };
+        match skip {
+            Some((skipped, destination)) => {
+                drop(pending);
+                hops.extend(skipped);
+                self.record(tab_id, hops, &destination, true);
+                Some(destination)
+            }
+            None if self.is_intermediary(url) => {
+                hops.push(url.to_string());
+                pending.insert(tab_id, (now, hops));
+                None
+            }
+            None => {
+                drop(pending);
+                if !hops.is_empty() {
+                    self.record(tab_id, hops, url, false);
+                }
+                None
+            }
+        }
+    }
+
+    /// Forget a closed tab's chain in progress
+    pub fn forget_tab(&self, tab_id: usize) {
+        self.pending.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Recorded chains, oldest first
+    pub fn chains(&self) -> Vec<RedirectChain> {
+        self.chains.lock_or_recover().iter().cloned().collect()
+    }
+
+    pub fn get_statistics(&self) -> BounceStats {
+        self.stats.lock_or_recover().clone()
+    }
+
+    pub fn reset_statistics(&self) {
+        *self.stats.lock_or_recover() = BounceStats::default();
+        self.chains.lock_or_recover().clear();
+    }
+
+    // Private helper methods
+
+    /// Destination parameters of a URL the tracking rules match
+    fn tracker_parameters(&self, url: &str) -> Option<&'static [&'static str]> {
+        let category = self.privacy.as_ref()?.tracker_category(url)?;
+        // Sharing and login links of social sites carry URLs that are no redirect
+        let tracking = matches!(
+            category,
+            TrackerCategory::Advertising
+                | TrackerCategory::Analytics
+                | TrackerCategory::Affiliate
+                | TrackerCategory::EmailTracking
+        );
+        tracking.then_some(TRACKER_DESTINATION_PARAMETERS)
+    }
+
+    fn record(&self, tab_id: usize, hops: Vec<String>, destination: &str, skipped: bool) {
+        tracing::debug!(
+            "Redirect chain in tab {} through {} intermediaries to {}{}",
+            tab_id,
+            hops.len(),
+            destination,
+            if skipped { ", skipped" } else { "" }
+        );
+        {
+            let mut stats = self.stats.lock_or_recover();
+            stats.chains_detected += 1;
+            if skipped {
+                stats.chains_skipped += 1;
+                stats.hops_skipped += hops.len() as u64;
+            }
+        }
+        let mut chains = self.chains.lock_or_recover();
+        if chains.len() == MAX_CHAINS {
+            chains.pop_front();
+        }
+        chains.push_back(RedirectChain {
+            tab_id,
+            hops,
+            destination: destination.to_string(),
+            skipped,
+            at: Utc::now(),
+        });
+    }
+}
+
+impl Default for BounceProtection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsProvider for BounceProtection {
+    fn module(&self) -> &str {
+        "redirects"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::toggle("redirects.skip_tracking", "Skip tracking redirects", true)
+            .with_description("Links bouncing through tracking redirect services go straight to where they lead")
+            .with_category(SettingCategory::Privacy)]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "redirects.skip_tracking" => {
+                self.set_enabled(value.as_bool().ok_or("Expected a toggle value")?);
+                Ok(())
+            }
+            _ => Err(format!("Unknown redirect setting {}", key).into()),
+        }
+    }
+}
+
+// Private helper functions
+
+fn known_redirector(url: &str) -> Option<&'static Redirector> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    KNOWN_REDIRECTORS.iter().find(|redirector| {
+        let host_matches = host == redirector.host || host.ends_with(&format!(".{}", redirector.host));
+        host_matches && parsed.path().starts_with(redirector.path_prefix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redirect_chains_are_skipped_and_recorded() {
+        let temp_dir = TempDir::new().unwrap();
+        let privacy = Arc::new(PrivacyProtection::new(None, Some(temp_dir.path().to_path_buf())).unwrap());
+        let mut bounces = BounceProtection::new();
+        bounces.set_privacy_protection(privacy);
+
+        // A Facebook link wrapping an ad click wrapping the shop
+        let ad = "https://adclick.g.doubleclick.net/pcs/click?adurl=https%3A%2F%2Fshop.example%2Fitem";
+        let link = format!("https://l.facebook.com/l.php?u={}", url::form_urlencoded::byte_serialize(ad.as_bytes()).collect::<String>());
+        assert_eq!(bounces.check_navigation(1, &link).as_deref(), Some("https://shop.example/item"));
+        assert_eq!(bounces.chains()[0].hops, vec![link.clone(), ad.to_string()]);
+
+        // Sharing links of social sites are left alone
+        assert_eq!(bounces.check_navigation(1, "https://www.facebook.com/sharer.php?u=https://a.example"), None);
+        assert_eq!(bounces.check_navigation(1, "https://news.example/"), None);
+
+        // Opaque redirects are recorded once the destination loads
+        assert_eq!(bounces.check_navigation(2, "https://t.co/abc123"), None);
+        assert_eq!(bounces.check_navigation(2, "https://blog.example/post"), None);
+        let chain = bounces.chains().pop().unwrap();
+        assert_eq!((chain.destination.as_str(), chain.skipped), ("https://blog.example/post", false));
+
+        bounces.apply_setting("redirects.skip_tracking", &SettingValue::Bool(false)).unwrap();
+        assert_eq!(bounces.check_navigation(3, &link), None);
+        let stats = bounces.get_statistics();
+        assert_eq!((stats.chains_detected, stats.chains_skipped, stats.hops_skipped), (2, 1, 2));
+    }
+}
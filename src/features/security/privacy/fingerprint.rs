@@ -0,0 +1,484 @@
+// Anti-Fingerprinting Protections
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::utils::{host_in_domain, site_domain, LockExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A protection against one fingerprinting technique
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintDefense {
+    /// Noise in pixels read back from canvases
+    CanvasNoise,
+    /// A common GPU vendor and renderer reported to WebGL
+    WebGlVendor,
+    /// Noise in audio samples and frequency data read back
+    AudioNoise,
+    /// CPU core count clamped to a common value
+    HardwareConcurrency,
+    /// A fixed time zone instead of the system's
+    Timezone,
+}
+
+impl FingerprintDefense {
+    pub const ALL: [FingerprintDefense; 5] = [
+        FingerprintDefense::CanvasNoise,
+        FingerprintDefense::WebGlVendor,
+        FingerprintDefense::AudioNoise,
+        FingerprintDefense::HardwareConcurrency,
+        FingerprintDefense::Timezone,
+    ];
+
+    /// Name of the defense in settings and page scripts
+    pub fn key(&self) -> &'static str {
+        match self {
+            FingerprintDefense::CanvasNoise => "canvas_noise",
+            FingerprintDefense::WebGlVendor => "webgl_vendor",
+            FingerprintDefense::AudioNoise => "audio_noise",
+            FingerprintDefense::HardwareConcurrency => "hardware_concurrency",
+            FingerprintDefense::Timezone => "timezone",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FingerprintDefense::CanvasNoise => "Add noise to canvas reads",
+            FingerprintDefense::WebGlVendor => "Hide the graphics card from WebGL",
+            FingerprintDefense::AudioNoise => "Add noise to audio reads",
+            FingerprintDefense::HardwareConcurrency => "Report fewer processor cores",
+            FingerprintDefense::Timezone => "Report a fixed time zone",
+        }
+    }
+
+    fn setting_key(&self) -> String {
+        format!("fingerprinting.{}", self.key())
+    }
+
+    fn from_setting_key(key: &str) -> Option<Self> {
+        let key = key.strip_prefix("fingerprinting.")?;
+        Self::ALL.into_iter().find(|defense| defense.key() == key)
+    }
+}
+
+/// Which defenses run, and what they report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FingerprintConfig {
+    /// Defenses on for sites without an exception
+    pub enabled: BTreeSet<FingerprintDefense>,
+    /// Defenses turned on or off for a site and its subdomains
+    pub sites: BTreeMap<String, BTreeMap<FingerprintDefense, bool>>,
+    pub webgl_vendor: String,
+    pub webgl_renderer: String,
+    /// Most processor cores reported
+    pub hardware_concurrency: u32,
+    /// IANA time zone reported
+    pub timezone: String,
+}
+
+impl Default for FingerprintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: FingerprintDefense::ALL.into_iter().filter(|defense| *defense != FingerprintDefense::Timezone).collect(),
+            sites: BTreeMap::new(),
+            webgl_vendor: "Google Inc.".to_string(),
+            webgl_renderer: "ANGLE (Intel, Intel(R) UHD Graphics, OpenGL 4.6)".to_string(),
+            hardware_concurrency: 4,
+            timezone: "UTC".to_string(),
+        }
+    }
+}
+
+impl FingerprintConfig {
+    /// Defenses that apply to a page
+    pub fn defenses_for(&self, url: &str) -> Vec<FingerprintDefense> {
+        let exceptions = site_domain(url).and_then(|host| {
+            self.sites
+                .iter()
+                .filter(|(site, _)| host_in_domain(&host, site))
+                .max_by_key(|(site, _)| site.len())
+                .map(|(_, exceptions)| exceptions)
+        });
+        FingerprintDefense::ALL
+            .into_iter()
+            .filter(|defense| match exceptions.and_then(|exceptions| exceptions.get(defense)) {
+                Some(enabled) => *enabled,
+                None => self.enabled.contains(defense),
+            })
+            .collect()
+    }
+
+    /// Script running the given defenses in a page
+    pub fn script(&self, defenses: &[FingerprintDefense]) -> String {
+        if defenses.is_empty() {
+            return String::new();
+        }
+        let modules: String = defenses.iter().map(|defense| self.module(*defense)).collect();
+        format!("(function() {{\n{}\n{}}})();\n", NOISE, modules)
+    }
+
+    /// Script injected into every page, running each defense that is on for
+    /// the page's site. Site exceptions are baked in, so the script must be
+    /// rebuilt when they change.
+    pub fn page_script(&self) -> String {
+        // Defenses off everywhere are left out altogether
+        let used: Vec<FingerprintDefense> = FingerprintDefense::ALL
+            .into_iter()
+            .filter(|defense| {
+                self.enabled.contains(defense) || self.sites.values().any(|exceptions| exceptions.get(defense) == Some(&true))
+            })
+            .collect();
+        if used.is_empty() {
+            return String::new();
+        }
+        let enabled: Vec<&str> = self.enabled.iter().map(|defense| defense.key()).collect();
+        let sites: BTreeMap<&str, BTreeMap<&str, bool>> = self
+            .sites
+            .iter()
+            .map(|(site, exceptions)| {
+                let exceptions = exceptions.iter().map(|(defense, on)| (defense.key(), *on)).collect();
+                (site.as_str(), exceptions)
+            })
+            .collect();
+        let modules: String = used
+            .iter()
+            .map(|defense| format!("    if (on('{}')) {{\n{}    }}\n", defense.key(), self.module(*defense)))
+            .collect();
+        format!(
+            r#"
+(function() {{
+    if (window.__webxFingerprintGuard) return;
+    window.__webxFingerprintGuard = true;
+    const enabled = {enabled};
+    const sites = {sites};
+    // The exceptions of the closest site the page's host belongs to
+    const labels = location.hostname.toLowerCase().replace(/^www\./, '').split('.');
+    let exceptions = {{}};
+    for (let i = 0; i < labels.length; i++) {{
+        const site = labels.slice(i).join('.');
+        if (sites[site]) {{
+            exceptions = sites[site];
+            break;
+        }}
+    }}
+    const on = (key) => key in exceptions ? exceptions[key] : enabled.includes(key);
+{noise}
+{modules}}})();
+"#,
+            enabled = serde_json::Value::from(enabled),
+            sites = serde_json::to_string(&sites).unwrap_or_else(|_| "{}".to_string()),
+            noise = NOISE,
+            modules = modules,
+        )
+    }
+
+    // Private helper methods
+
+    /// Script of one defense, using the page's `noise`
+    fn module(&self, defense: FingerprintDefense) -> String {
+        match defense {
+            FingerprintDefense::CanvasNoise => CANVAS_NOISE_MODULE.to_string(),
+            FingerprintDefense::WebGlVendor => WEBGL_VENDOR_MODULE
+                .replace("__VENDOR__", &serde_json::Value::from(self.webgl_vendor.as_str()).to_string())
+                .replace("__RENDERER__", &serde_json::Value::from(self.webgl_renderer.as_str()).to_string()),
+            FingerprintDefense::AudioNoise => AUDIO_NOISE_MODULE.to_string(),
+            FingerprintDefense::HardwareConcurrency => {
+                HARDWARE_CONCURRENCY_MODULE.replace("__CORES__", &self.hardware_concurrency.max(1).to_string())
+            }
+            FingerprintDefense::Timezone => {
+                TIMEZONE_MODULE.replace("__ZONE__", &serde_json::Value::from(self.timezone.as_str()).to_string())
+            }
+        }
+    }
+}
+
+/// Noise in [0, 1) for a position, the same for the whole page so repeated
+/// reads agree
+const NOISE: &str = "    const seed = Math.random() * 1000;
+    const noise = (i) => {
+        const x = Math.sin(i * 12.9898 + seed) * 43758.5453;
+        return x - Math.floor(x);
+    };";
+
+/// Pixels read back from a canvas are shifted slightly
+const CANVAS_NOISE_MODULE: &str = r#"
+    // canvas_noise
+    (function() {
+        const addNoise = (image) => {
+            for (let i = 0; i < image.data.length; i += 4) {
+                if (noise(i) < 0.01) image.data[i] ^= 1;
+            }
+            return image;
+        };
+        const getImageData = CanvasRenderingContext2D.prototype.getImageData;
+        CanvasRenderingContext2D.prototype.getImageData = function() {
+            return addNoise(getImageData.apply(this, arguments));
+        };
+        const withNoise = (read) => function() {
+            const context = this.width && this.height && this.getContext('2d');
+            if (!context) return read.apply(this, arguments);
+            const copy = document.createElement('canvas');
+            copy.width = this.width;
+            copy.height = this.height;
+            copy.getContext('2d').putImageData(addNoise(getImageData.call(context, 0, 0, this.width, this.height)), 0, 0);
+            return read.apply(copy, arguments);
+        };
+        HTMLCanvasElement.prototype.toDataURL = withNoise(HTMLCanvasElement.prototype.toDataURL);
+        HTMLCanvasElement.prototype.toBlob = withNoise(HTMLCanvasElement.prototype.toBlob);
+    })();
+"#;
+
+/// The unmasked vendor and renderer name a common graphics card
+const WEBGL_VENDOR_MODULE: &str = r#"
+    // webgl_vendor
+    (function() {
+        const UNMASKED_VENDOR = 0x9245;
+        const UNMASKED_RENDERER = 0x9246;
+        const contexts = [window.WebGLRenderingContext, window.WebGL2RenderingContext].filter(Boolean);
+        contexts.forEach((type) => {
+            const getParameter = type.prototype.getParameter;
+            type.prototype.getParameter = function(parameter) {
+                if (parameter === UNMASKED_VENDOR) return __VENDOR__;
+                if (parameter === UNMASKED_RENDERER) return __RENDERER__;
+                return getParameter.apply(this, arguments);
+            };
+        });
+    })();
+"#;
+
+/// Audio samples and frequency data read back are shifted below what can
+/// be heard
+const AUDIO_NOISE_MODULE: &str = r#"
+    // audio_noise
+    (function() {
+        if (!window.AudioBuffer) return;
+        const shift = (array, scale) => {
+            for (let i = 0; i < array.length; i += 100) array[i] += (noise(i) - 0.5) * scale;
+        };
+        const getChannelData = AudioBuffer.prototype.getChannelData;
+        AudioBuffer.prototype.getChannelData = function() {
+            const data = getChannelData.apply(this, arguments);
+            if (!data.__webxNoise) {
+                shift(data, 1e-7);
+                data.__webxNoise = true;
+            }
+            return data;
+        };
+        if (!window.AnalyserNode) return;
+        const getFloatFrequencyData = AnalyserNode.prototype.getFloatFrequencyData;
+        AnalyserNode.prototype.getFloatFrequencyData = function(array) {
+            getFloatFrequencyData.apply(this, arguments);
+            shift(array, 0.1);
+        };
+    })();
+"#;
+
+/// `navigator.hardwareConcurrency` never reports more than the configured
+/// number of cores
+const HARDWARE_CONCURRENCY_MODULE: &str = r#"
+    // hardware_concurrency
+    (function() {
+        const cores = Math.min(navigator.hardwareConcurrency || __CORES__, __CORES__);
+        Object.defineProperty(Navigator.prototype, 'hardwareConcurrency', { get: () => cores, configurable: true });
+    })();
+"#;
+
+/// Dates and `Intl` report the configured time zone instead of the system's
+const TIMEZONE_MODULE: &str = r#"
+    // timezone
+    (function() {
+        const zone = __ZONE__;
+        const DateTimeFormat = Intl.DateTimeFormat;
+        const inZone = (locales, options) => new DateTimeFormat(locales, Object.assign({ timeZone: zone }, options));
+        Intl.DateTimeFormat = function(locales, options) {
+            return inZone(locales, options);
+        };
+        Intl.DateTimeFormat.prototype = DateTimeFormat.prototype;
+        Intl.DateTimeFormat.supportedLocalesOf = DateTimeFormat.supportedLocalesOf;
+        const minutes = (date, timeZone) => {
+            const parts = new DateTimeFormat('en-US', { timeZone: timeZone, hourCycle: 'h23',
+                year: 'numeric', month: 'numeric', day: 'numeric', hour: 'numeric', minute: 'numeric' }).formatToParts(date);
+            const part = (type) => Number(parts.find((p) => p.type === type).value);
+            return Date.UTC(part('year'), part('month') - 1, part('day'), part('hour'), part('minute')) / 60000;
+        };
+        Date.prototype.getTimezoneOffset = function() {
+            return isNaN(this) ? NaN : minutes(this, 'UTC') - minutes(this, zone);
+        };
+        const toString = Date.prototype.toString;
+        Date.prototype.toString = function() {
+            return isNaN(this) ? toString.call(this) : inZone('en-US', { dateStyle: 'full', timeStyle: 'long' }).format(this);
+        };
+    })();
+"#;
+
+/// Keeps the anti-fingerprinting configuration, on globally and per site
+pub struct FingerprintShield {
+    config: Mutex<FingerprintConfig>,
+    config_path: PathBuf,
+}
+
+impl FingerprintShield {
+    /// Load the configuration kept in `config_dir`
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("privacy");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let config_path = config_dir.join("fingerprinting.json");
+        let config = match std::fs::read_to_string(&config_path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => FingerprintConfig::default(),
+        };
+        Ok(Self {
+            config: Mutex::new(config),
+            config_path,
+        })
+    }
+
+    pub fn config(&self) -> FingerprintConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    /// Defenses that apply to a page
+    pub fn defenses_for(&self, url: &str) -> Vec<FingerprintDefense> {
+        self.config.lock_or_recover().defenses_for(url)
+    }
+
+    /// Script running the defenses that apply to a page
+    pub fn script_for(&self, url: &str) -> String {
+        let config = self.config.lock_or_recover();
+        config.script(&config.defenses_for(url))
+    }
+
+    /// Script injected into every page
+    pub fn page_script(&self) -> String {
+        self.config.lock_or_recover().page_script()
+    }
+
+    /// Turn a defense on or off for sites without an exception
+    pub fn set_enabled(&self, defense: FingerprintDefense, enabled: bool) -> Result<(), WebxError> {
+        self.update(|config| {
+            if enabled {
+                config.enabled.insert(defense);
+            } else {
+                config.enabled.remove(&defense);
+            }
+        })
+    }
+
+    /// Turn a defense on or off for a page's site, or with `None` follow
+    /// the global setting again
+    pub fn set_site_enabled(&self, url: &str, defense: FingerprintDefense, enabled: Option<bool>) -> Result<(), WebxError> {
+        let site = site_domain(url).ok_or("Not a site")?;
+        self.update(|config| {
+            let exceptions = config.sites.entry(site.clone()).or_default();
+            match enabled {
+                Some(enabled) => exceptions.insert(defense, enabled),
+                None => exceptions.remove(&defense),
+            };
+            if exceptions.is_empty() {
+                config.sites.remove(&site);
+            }
+        })
+    }
+
+    /// Sites with exceptions, and what they turn on or off
+    pub fn site_exceptions(&self) -> Vec<(String, BTreeMap<FingerprintDefense, bool>)> {
+        let config = self.config.lock_or_recover();
+        config.sites.iter().map(|(site, exceptions)| (site.clone(), exceptions.clone())).collect()
+    }
+
+    // Private helper methods
+
+    fn update(&self, update: impl FnOnce(&mut FingerprintConfig)) -> Result<(), WebxError> {
+        let mut config = self.config.lock_or_recover();
+        update(&mut config);
+        write_atomic(&self.config_path, &serde_json::to_vec_pretty(&*config)?)?;
+        Ok(())
+    }
+}
+
+impl SettingsProvider for FingerprintShield {
+    fn module(&self) -> &str {
+        "fingerprinting"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let defaults = FingerprintConfig::default();
+        FingerprintDefense::ALL
+            .iter()
+            .map(|defense| {
+                SettingDefinition::toggle(&defense.setting_key(), defense.label(), defaults.enabled.contains(defense))
+                    .with_description("Applies to pages opened afterwards; sites can be excepted one by one")
+                    .with_category(SettingCategory::Privacy)
+            })
+            .collect()
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let defense =
+            FingerprintDefense::from_setting_key(key).ok_or_else(|| format!("Unknown fingerprinting setting {}", key))?;
+        self.set_enabled(defense, value.as_bool().ok_or("Expected a toggle value")?)
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        let defense = FingerprintDefense::from_setting_key(key)?;
+        Some(SettingValue::Bool(self.config.lock_or_recover().enabled.contains(&defense)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_defenses_compose_per_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let shield = FingerprintShield::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        // Everything but the time zone is on by default
+        let script = shield.script_for("https://example.com");
+        for key in ["canvas_noise", "webgl_vendor", "audio_noise", "hardware_concurrency"] {
+            assert!(script.contains(&format!("// {}", key)));
+        }
+        assert!(!script.contains("// timezone"));
+        assert!(script.contains("\"Google Inc.\""));
+
+        // A site may drop a defense, and its subdomains with it
+        shield.set_site_enabled("https://maps.example.com", FingerprintDefense::WebGlVendor, Some(false)).unwrap();
+        assert!(!shield.script_for("https://tiles.maps.example.com").contains("// webgl_vendor"));
+        assert!(shield.script_for("https://example.com").contains("// webgl_vendor"));
+
+        // Defenses off everywhere are left out of the page script
+        shield.apply_setting("fingerprinting.audio_noise", &SettingValue::Bool(false)).unwrap();
+        shield.set_site_enabled("https://bank.test", FingerprintDefense::Timezone, Some(true)).unwrap();
+        let page_script = shield.page_script();
+        assert!(!page_script.contains("// audio_noise"));
+        assert!(page_script.contains("if (on('timezone'))"));
+        assert!(page_script.contains("\"maps.example.com\":{\"webgl_vendor\":false}"));
+
+        // Exceptions survive a restart, and are forgotten once cleared
+        let shield = FingerprintShield::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(
+            shield.defenses_for("https://bank.test/login"),
+            vec![
+                FingerprintDefense::CanvasNoise,
+                FingerprintDefense::WebGlVendor,
+                FingerprintDefense::HardwareConcurrency,
+                FingerprintDefense::Timezone,
+            ]
+        );
+        shield.set_site_enabled("https://bank.test", FingerprintDefense::Timezone, None).unwrap();
+        assert_eq!(shield.site_exceptions().len(), 1);
+        assert!(shield.apply_setting("fingerprinting.fonts", &SettingValue::Bool(true)).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+// New Identity
+use super::{BounceProtection, PrivacyProtection};
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::caching::HTTPCache;
+use crate::features::system::proxy::TorController;
+use crate::features::system::user_agent::UserAgentSwitcher;
+use crate::features::tabs::ContainerManager;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Session state a new identity leaves behind. Stores left as `None` are
+/// skipped.
+#[derive(Clone, Default)]
+pub struct IdentityTargets {
+    pub containers: Option<Arc<ContainerManager>>,
+    pub http_cache: Option<Arc<Mutex<HTTPCache>>>,
+    pub user_agents: Option<Arc<Mutex<UserAgentSwitcher>>>,
+    pub tor: Option<Arc<tokio::sync::Mutex<TorController>>>,
+    pub privacy: Option<Arc<PrivacyProtection>>,
+    pub bounces: Option<Arc<BounceProtection>>,
+}
+
+/// What starting over with a new identity cleared
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdentityReport {
+    pub cookies: usize,
+    pub cache_entries: usize,
+    /// Whether tor was asked for fresh circuits
+    pub new_circuits: bool,
+    /// Whether tabs should be closed rather than reloaded
+    pub close_tabs: bool,
+    pub ran_at: chrono::DateTime<chrono::Utc>,
+    /// Stores that failed; the others are still cleared
+    pub errors: Vec<String>,
+}
+
+/// Starts the browser over as someone new to the sites it visits, like
+/// Tor Browser's "New Identity": cookies, cache, session user agents,
+/// circuits and session privacy state are dropped. Tabs are left to the
+/// caller, which closes or reloads them as the report says.
+pub struct NewIdentity {
+    targets: IdentityTargets,
+    close_tabs: Mutex<bool>,
+}
+
+impl NewIdentity {
+    /// Create a new identity action, reloading tabs until set otherwise
+    pub fn new(targets: IdentityTargets) -> Self {
+        Self {
+            targets,
+            close_tabs: Mutex::new(false),
+        }
+    }
+
+    /// Close tabs instead of reloading them
+    pub fn set_close_tabs(&self, close_tabs: bool) {
+        *self.close_tabs.lock_or_recover() = close_tabs;
+    }
+
+    pub fn close_tabs(&self) -> bool {
+        *self.close_tabs.lock_or_recover()
+    }
+
+    /// Drop the session's identity
+    pub async fn run(&self) -> IdentityReport {
+        let mut report = IdentityReport {
+            cookies: 0,
+            cache_entries: 0,
+            new_circuits: false,
+            close_tabs: self.close_tabs(),
+            ran_at: chrono::Utc::now(),
+            errors: Vec::new(),
+        };
+
+        if let Some(containers) = &self.targets.containers {
+            report.cookies = containers.clear_cookies_except(&[]);
+        }
+        if let Some(http_cache) = &self.targets.http_cache {
+            report.cache_entries = http_cache.lock_or_recover().trim_to_size(0);
+        }
+        if let Some(user_agents) = &self.targets.user_agents {
+            user_agents.lock_or_recover().clear_session_agents();
+        }
+        if let Some(privacy) = &self.targets.privacy {
+            privacy.reset_statistics();
+        }
+        if let Some(bounces) = &self.targets.bounces {
+            bounces.reset_statistics();
+        }
+        if let Some(tor) = &self.targets.tor {
+            let mut tor = tor.lock().await;
+            if tor.is_running() {
+                match tor.new_circuits().await {
+                    Ok(()) => report.new_circuits = true,
+                    Err(e) => report.errors.push(format!("tor: {}", e)),
+                }
+            }
+        }
+
+        tracing::info!(
+            "New identity: cleared {} cookies and {} cache entries{}",
+            report.cookies,
+            report.cache_entries,
+            if report.new_circuits { ", new tor circuits" } else { "" }
+        );
+        report
+    }
+}
+
+impl SettingsProvider for NewIdentity {
+    fn module(&self) -> &str {
+        "identity"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::toggle("identity.close_tabs", "Close tabs for a new identity", false)
+            .with_description("Starting over with a new identity closes every tab instead of reloading it")
+            .with_category(SettingCategory::Privacy)]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "identity.close_tabs" => {
+                self.set_close_tabs(value.as_bool().ok_or("Expected a toggle value")?);
+                Ok(())
+            }
+            _ => Err(format!("Unknown identity setting {}", key).into()),
+        }
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        match key {
+            "identity.close_tabs" => Some(SettingValue::Bool(self.close_tabs())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_new_identity_clears_session_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let containers = Arc::new(ContainerManager::new(Some(temp_dir.path().join("containers"))).unwrap());
+        let http_cache = Arc::new(Mutex::new(HTTPCache::new(1, 60, false)));
+        let privacy = Arc::new(PrivacyProtection::new(None, Some(temp_dir.path().join("privacy"))).unwrap());
+
+        let url = url::Url::parse("https://example.com/").unwrap();
+        let header = reqwest::header::HeaderValue::from_static("session=1");
+        containers.cookie_jar(None).set_cookies(&mut std::iter::once(&header), &url);
+        http_cache
+            .lock_or_recover()
+            .store_response(url.to_string(), 200, HashMap::new(), vec![0; 100])
+            .unwrap();
+        privacy.upgrade_to_https("http://example.com/");
+
+        let identity = NewIdentity::new(IdentityTargets {
+            containers: Some(containers.clone()),
+            http_cache: Some(http_cache.clone()),
+            privacy: Some(privacy.clone()),
+            ..Default::default()
+        });
+        identity.apply_setting("identity.close_tabs", &SettingValue::Bool(true)).unwrap();
+        let report = identity.run().await;
+        assert_eq!((report.cookies, report.cache_entries, report.new_circuits), (1, 1, false));
+        assert!(report.close_tabs && report.errors.is_empty());
+        assert!(containers.cookie_jar(None).cookies(&url).is_none());
+        assert_eq!(privacy.get_statistics().https_upgrades, 0);
+    }
+}
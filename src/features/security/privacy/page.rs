@@ -0,0 +1,161 @@
+// Privacy Report Page
+use super::report::PrivacySummary;
+use super::TrackerCategory;
+use crate::utils::{escape_html, script_literal};
+
+/// Address of the privacy report page
+pub const PRIVACY_PAGE_URL: &str = "webx://privacy";
+
+/// Sites and trackers listed on the page
+const TOP_ENTRIES: usize = 10;
+
+/// Whether a URL is the privacy report page
+pub fn is_privacy_page(url: &str) -> bool {
+    url.strip_prefix(PRIVACY_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Render what tracking protection did over the summary's period: totals,
+/// a bar per week, and the sites and trackers blocked most. The export
+/// button saves `export_json`; the clear button sends a `type:
+/// 'privacyreport'` IPC request with an `action` of `clear`, and the new
+/// identity button a `type: 'newidentity'` message.
+pub fn render_privacy_page(summary: &PrivacySummary, export_json: &str) -> String {
+    let body = if summary.sites.is_empty() {
+        r#"<p class="empty">Nothing blocked or upgraded yet.</p>"#.to_string()
+    } else {
+        let cards = [
+            ("Trackers blocked", summary.totals.blocked_total().to_string()),
+            ("Sites with trackers", summary.sites.iter().filter(|(_, site)| site.blocked_total() > 0).count().to_string()),
+            ("HTTPS upgrades", summary.totals.https_upgrades.to_string()),
+        ];
+        let cards: String = cards
+            .iter()
+            .map(|(label, value)| format!(r#"<div class="card"><span class="value">{}</span>{}</div>"#, value, label))
+            .collect();
+
+        let highest = summary.weeks.iter().map(|week| week.blocked).max().unwrap_or(0).max(1);
+        let weeks: String = summary
+            .weeks
+            .iter()
+            .map(|week| {
+                format!(
+                    r#"<div class="week" title="{} blocked, {} upgraded"><div class="bar" style="height: {}%"></div><span>{}</span></div>"#,
+                    week.blocked,
+                    week.https_upgrades,
+                    week.blocked * 100 / highest,
+                    week.week_of.format("%b %d"),
+                )
+            })
+            .collect();
+
+        let categories: String = summary
+            .totals
+            .blocked
+            .iter()
+            .map(|(category, count)| format!("<tr><td>{}</td><td>{}</td></tr>", category_name(category), count))
+            .collect();
+        let sites: String = summary
+            .sites
+            .iter()
+            .take(TOP_ENTRIES)
+            .map(|(site, activity)| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape_html(site),
+                    activity.blocked_total(),
+                    activity.https_upgrades
+                )
+            })
+            .collect();
+        let trackers: String = summary
+            .top_trackers
+            .iter()
+            .take(TOP_ENTRIES)
+            .map(|(tracker, count)| format!("<tr><td>{}</td><td>{}</td></tr>", escape_html(tracker), count))
+            .collect();
+        format!(
+            r#"<div class="cards">{}</div>
+    <h2>By Week</h2>
+    <div class="weeks">{}</div>
+    <h2>By Category</h2>
+    <table><thead><tr><th>Category</th><th>Blocked</th></tr></thead><tbody>{}</tbody></table>
+    <h2>Sites</h2>
+    <table><thead><tr><th>Site</th><th>Blocked</th><th>HTTPS upgrades</th></tr></thead><tbody>{}</tbody></table>
+    <h2>Top Trackers</h2>
+    <table><thead><tr><th>Tracker</th><th>Blocked</th></tr></thead><tbody>{}</tbody></table>"#,
+            cards, weeks, categories, sites, trackers
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Privacy Report</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Privacy Report</h1></header>
+    <p>Since {since}. Recorded on this device only.</p>
+    <p><button data-action="export">Export JSON</button> <button data-action="clear">Clear Report</button> <button data-action="newidentity">New Identity</button></p>
+    {body}
+    <script>const report = {export};{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        since = summary.since.format("%Y-%m-%d"),
+        body = body,
+        export = script_literal(export_json),
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+h2 { font-size: 1.1em; margin-top: 1.8em; }
+.cards { display: grid; grid-template-columns: repeat(3, 1fr); gap: 0.8em; margin: 1.5em 0; }
+.card { border: 1px solid #eee; border-radius: 4px; padding: 0.8em; color: #666; font-size: 0.85em; }
+.card .value { display: block; font-size: 1.6em; color: #222; }
+.weeks { display: flex; align-items: flex-end; gap: 0.4em; height: 140px; }
+.week { flex: 1; display: flex; flex-direction: column; justify-content: flex-end; height: 100%; font-size: 0.7em; color: #888; text-align: center; }
+.week .bar { background: #4a7fd4; border-radius: 2px 2px 0 0; min-height: 1px; }
+table { width: 100%; border-collapse: collapse; font-size: 0.9em; }
+th, td { text-align: right; padding: 0.3em 0.5em; border-bottom: 1px solid #eee; }
+th:first-child, td:first-child { text-align: left; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    if (button.dataset.action === 'export') {
+        const link = document.createElement('a');
+        link.href = URL.createObjectURL(new Blob([report], { type: 'application/json' }));
+        link.download = 'webx-privacy-report.json';
+        link.click();
+        URL.revokeObjectURL(link.href);
+    } else if (button.dataset.action === 'newidentity') {
+        window.ipc.send({ type: 'newidentity' });
+    } else {
+        window.ipc.request({ type: 'privacyreport', action: button.dataset.action }).then(() => location.reload());
+    }
+});
+"#;
+
+// Private helper functions
+
+fn category_name(category: &TrackerCategory) -> &'static str {
+    match category {
+        TrackerCategory::Advertising => "Advertising",
+        TrackerCategory::Analytics => "Analytics",
+        TrackerCategory::SocialMedia => "Social media",
+        TrackerCategory::Cryptomining => "Cryptomining",
+        TrackerCategory::Fingerprinting => "Fingerprinting",
+        TrackerCategory::EmailTracking => "Email tracking",
+        TrackerCategory::Affiliate => "Affiliate",
+        TrackerCategory::CDN => "CDN",
+    }
+}
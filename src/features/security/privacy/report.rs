@@ -0,0 +1,260 @@
+// Privacy Report
+use super::TrackerCategory;
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::system::scheduler::{TaskScheduler, TaskSpec};
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage, PrivacyReportAction};
+use crate::utils::{site_domain, LockExt};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often the report is written to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Days of activity kept
+const RETENTION_DAYS: i64 = 180;
+
+/// Site recorded for requests of pages without one
+const UNKNOWN_SITE: &str = "(unknown)";
+
+/// What protection did on a site over a day
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SiteActivity {
+    /// Trackers blocked, by category
+    pub blocked: BTreeMap<TrackerCategory, u64>,
+    /// Trackers blocked, by the tracker's domain
+    pub trackers: BTreeMap<String, u64>,
+    pub https_upgrades: u64,
+}
+
+impl SiteActivity {
+    pub fn blocked_total(&self) -> u64 {
+        self.blocked.values().sum()
+    }
+
+    fn merge(&mut self, other: &SiteActivity) {
+        for (category, count) in &other.blocked {
+            *self.blocked.entry(category.clone()).or_default() += count;
+        }
+        for (tracker, count) in &other.trackers {
+            *self.trackers.entry(tracker.clone()).or_default() += count;
+        }
+        self.https_upgrades += other.https_upgrades;
+    }
+}
+
+/// Protection over a week, starting on its Monday
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WeekActivity {
+    pub week_of: NaiveDate,
+    pub blocked: u64,
+    pub https_upgrades: u64,
+}
+
+/// Protection over a period, as the privacy page shows it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrivacySummary {
+    pub since: NaiveDate,
+    /// Everything, summed over sites
+    pub totals: SiteActivity,
+    /// Sites by trackers blocked on them, most first
+    pub sites: Vec<(String, SiteActivity)>,
+    /// Tracker domains by times blocked, most first
+    pub top_trackers: Vec<(String, u64)>,
+    /// Every week of the period, oldest first
+    pub weeks: Vec<WeekActivity>,
+}
+
+/// Stored activity, by day and site
+type Days = BTreeMap<NaiveDate, BTreeMap<String, SiteActivity>>;
+
+/// Tracking protection activity per site and category over time, kept on
+/// disk for the privacy page
+pub struct PrivacyReport {
+    days: Mutex<Days>,
+    /// Whether `days` changed since the last flush
+    dirty: Mutex<bool>,
+    path: PathBuf,
+}
+
+impl PrivacyReport {
+    /// Load the report kept in `config_dir`
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("privacy");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let path = config_dir.join("report.json");
+        let days = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => Days::new(),
+        };
+        Ok(Self {
+            days: Mutex::new(days),
+            dirty: Mutex::new(false),
+            path,
+        })
+    }
+
+    /// Count a tracker blocked on a page
+    pub fn record_blocked(&self, page_url: &str, tracker_url: &str, category: &TrackerCategory) {
+        let tracker = site_domain(tracker_url).unwrap_or_else(|| UNKNOWN_SITE.to_string());
+        self.update(page_url, |activity| {
+            *activity.blocked.entry(category.clone()).or_default() += 1;
+            *activity.trackers.entry(tracker).or_default() += 1;
+        });
+    }
+
+    /// Count a page loaded over HTTPS instead of HTTP
+    pub fn record_https_upgrade(&self, page_url: &str) {
+        self.update(page_url, |activity| activity.https_upgrades += 1);
+    }
+
+    /// Activity of the last `days` days, today included
+    pub fn summary(&self, days: u32) -> PrivacySummary {
+        let today = Utc::now().date_naive();
+        let since = today - ChronoDuration::days(i64::from(days.saturating_sub(1)));
+        let mut totals = SiteActivity::default();
+        let mut sites: BTreeMap<String, SiteActivity> = BTreeMap::new();
+        let mut weeks: BTreeMap<NaiveDate, WeekActivity> = BTreeMap::new();
+        let mut week_of = monday(since);
+        while week_of <= today {
+            weeks.insert(week_of, WeekActivity { week_of, blocked: 0, https_upgrades: 0 });
+            week_of += ChronoDuration::days(7);
+        }
+
+        for (date, day) in self.days.lock_or_recover().range(since..) {
+            for (site, activity) in day {
+                totals.merge(activity);
+                sites.entry(site.clone()).or_default().merge(activity);
+                if let Some(week) = weeks.get_mut(&monday(*date)) {
+                    week.blocked += activity.blocked_total();
+                    week.https_upgrades += activity.https_upgrades;
+                }
+            }
+        }
+
+        let mut sites: Vec<(String, SiteActivity)> = sites.into_iter().collect();
+        sites.sort_by(|a, b| b.1.blocked_total().cmp(&a.1.blocked_total()).then_with(|| a.0.cmp(&b.0)));
+        let mut top_trackers: Vec<(String, u64)> = totals.trackers.iter().map(|(tracker, count)| (tracker.clone(), *count)).collect();
+        top_trackers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        PrivacySummary {
+            since,
+            totals,
+            sites,
+            top_trackers,
+            weeks: weeks.into_values().collect(),
+        }
+    }
+
+    /// The summary of the last `days` days as pretty-printed JSON
+    pub fn export(&self, days: u32) -> Result<String, WebxError> {
+        Ok(serde_json::to_string_pretty(&self.summary(days))?)
+    }
+
+    /// Write changes to disk, dropping days past retention
+    pub fn flush(&self) -> Result<(), WebxError> {
+        let mut dirty = self.dirty.lock_or_recover();
+        let mut days = self.days.lock_or_recover();
+        let cutoff = Utc::now().date_naive() - ChronoDuration::days(RETENTION_DAYS);
+        let kept = days.split_off(&cutoff);
+        *dirty |= !days.is_empty();
+        *days = kept;
+        if *dirty {
+            write_atomic(&self.path, &serde_json::to_vec(&*days)?)?;
+            *dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Delete all recorded activity
+    pub fn clear(&self) -> Result<(), WebxError> {
+        self.days.lock_or_recover().clear();
+        *self.dirty.lock_or_recover() = true;
+        self.flush()
+    }
+
+    /// Flush regularly
+    pub fn schedule(report: Arc<Self>, scheduler: &TaskScheduler) {
+        scheduler.register(TaskSpec::new("privacy-report-flush", FLUSH_INTERVAL), move || {
+            let report = Arc::clone(&report);
+            async move { report.flush() }
+        });
+    }
+
+    // Private helper methods
+
+    fn update(&self, page_url: &str, update: impl FnOnce(&mut SiteActivity)) {
+        let site = site_domain(page_url).unwrap_or_else(|| UNKNOWN_SITE.to_string());
+        let today = Utc::now().date_naive();
+        update(self.days.lock_or_recover().entry(today).or_default().entry(site).or_default());
+        *self.dirty.lock_or_recover() = true;
+    }
+}
+
+impl IpcHandler for PrivacyReport {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["privacyreport"]
+    }
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::PrivacyReport { action: PrivacyReportAction::Clear } => {
+                self.clear()?;
+                Ok(serde_json::Value::Null)
+            }
+            _ => Err(WebxError::Invalid("Not a privacy report message".to_string())),
+        }
+    }
+}
+
+// Private helper functions
+
+/// Monday of the week a day falls in
+fn monday(date: NaiveDate) -> NaiveDate {
+    date - ChronoDuration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_aggregates_and_persists() {
+        let temp_dir = TempDir::new().unwrap();
+        let report = PrivacyReport::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        report.record_blocked("https://news.example/a", "https://ads.doubleclick.net/ad", &TrackerCategory::Advertising);
+        report.record_blocked("https://www.news.example/b", "https://ssl.google-analytics.com/ga.js", &TrackerCategory::Analytics);
+        report.record_blocked("https://shop.example", "https://ads.doubleclick.net/px", &TrackerCategory::Advertising);
+        report.record_https_upgrade("http://shop.example");
+        report.flush().unwrap();
+
+        // Sites and trackers come most blocked first, and survive a restart
+        let report = PrivacyReport::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let summary = report.summary(28);
+        assert_eq!(summary.sites[0].0, "news.example");
+        assert_eq!(summary.sites[0].1.blocked_total(), 2);
+        assert_eq!(summary.sites[1].1.https_upgrades, 1);
+        assert_eq!(summary.top_trackers[0], ("ads.doubleclick.net".to_string(), 2));
+        assert_eq!(summary.totals.blocked[&TrackerCategory::Advertising], 2);
+        assert!(summary.weeks.len() >= 4);
+        assert_eq!(summary.weeks.last().unwrap().blocked, 3);
+
+        let exported: serde_json::Value = serde_json::from_str(&report.export(28).unwrap()).unwrap();
+        assert_eq!(exported["totals"]["https_upgrades"], 1);
+
+        report.clear().unwrap();
+        let report = PrivacyReport::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(report.summary(28).sites.is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+// Data Retention Policies
+use crate::core::HistoryEntry;
+use crate::features::caching::{HTTPCache, SiteStorageManager};
+use crate::features::history_manager::{FormHistory, HistoryManager};
+use crate::features::tabs::ContainerManager;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often scheduled retention runs
+const RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What browsing data to keep and for how long
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RetentionPolicy {
+    /// Delete history visits older than this many days
+    pub keep_history_days: Option<u32>,
+    pub clear_history_on_exit: bool,
+    pub clear_cookies_on_exit: bool,
+    /// Sites (and their subdomains) whose cookies and site storage
+    /// survive clearing
+    pub cookie_allowlist: Vec<String>,
+    /// Clear local storage and IndexedDB of sites not allowed above and
+    /// without persistent storage
+    #[serde(default)]
+    pub clear_site_storage_on_exit: bool,
+    pub clear_cache_on_exit: bool,
+    /// Forget values remembered for search boxes and form fields
+    #[serde(default)]
+    pub clear_form_history_on_exit: bool,
+    /// Trim the HTTP cache to this size
+    pub max_cache_mb: Option<u64>,
+}
+
+/// When retention runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RetentionTrigger {
+    /// The daily timer: age and size limits only
+    Scheduled,
+    /// Browser shutdown: age and size limits plus clear-on-exit rules
+    Exit,
+}
+
+/// What a retention run removed, or would remove for a dry run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetentionReport {
+    pub trigger: RetentionTrigger,
+    pub dry_run: bool,
+    pub history_visits: usize,
+    pub cookies: usize,
+    pub cache_entries: usize,
+    /// Origins whose local storage and IndexedDB were cleared
+    pub site_storage_origins: usize,
+    /// Values remembered for form fields that were forgotten
+    pub form_entries: usize,
+    pub ran_at: chrono::DateTime<chrono::Utc>,
+    /// Stores that failed; the others are still processed
+    pub errors: Vec<String>,
+}
+
+/// Stores retention applies to. Stores left as `None` are skipped.
+#[derive(Clone, Default)]
+pub struct RetentionTargets {
+    pub history: Option<Arc<HistoryManager>>,
+    pub containers: Option<Arc<ContainerManager>>,
+    pub http_cache: Option<Arc<Mutex<HTTPCache>>>,
+    pub site_storage: Option<Arc<SiteStorageManager>>,
+    pub form_history: Option<Arc<FormHistory>>,
+}
+
+/// Applies a retention policy at shutdown and on a daily timer
+pub struct RetentionEngine {
+    policy: RetentionPolicy,
+    targets: RetentionTargets,
+    last_report: Arc<Mutex<Option<RetentionReport>>>,
+    timer: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl RetentionEngine {
+    /// Create a new retention engine
+    pub fn new(policy: RetentionPolicy, targets: RetentionTargets) -> Self {
+        Self {
+            policy,
+            targets,
+            last_report: Arc::new(Mutex::new(None)),
+            timer: None,
+        }
+    }
+
+    /// Report what a run would remove without removing anything
+    pub fn dry_run(&self, trigger: RetentionTrigger) -> RetentionReport {
+        self.execute(trigger, true)
+    }
+
+    /// Apply the policy
+    pub fn run(&self, trigger: RetentionTrigger) -> RetentionReport {
+        let report = self.execute(trigger, false);
+        tracing::info!(
+            "Retention ({:?}) removed {} visits, {} cookies, {} cache entries, storage of {} sites, {} form entries",
+            trigger,
+            report.history_visits,
+            report.cookies,
+            report.cache_entries,
+            report.site_storage_origins,
+            report.form_entries
+        );
+        *self.last_report.lock_or_recover() = Some(report.clone());
+        report
+    }
+
+    /// Apply the history rules to the browser state's history list,
+    /// returning how many entries were removed
+    pub fn prune_history_entries(&self, entries: &mut Vec<HistoryEntry>, trigger: RetentionTrigger) -> usize {
+        let before = entries.len();
+        if trigger == RetentionTrigger::Exit && self.policy.clear_history_on_exit {
+            entries.clear();
+        } else if let Some(cutoff) = self.history_cutoff() {
+            entries.retain(|entry| entry.visited_at >= cutoff);
+        }
+        before - entries.len()
+    }
+
+    /// Run scheduled retention once a day in the background
+    pub fn start_daily_timer(engine: Arc<Mutex<RetentionEngine>>) {
+        let task_engine = engine.clone();
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(RETENTION_INTERVAL);
+            loop {
+                timer.tick().await;
+                // Pruning hits the disk, so keep it off the async workers
+                let engine = task_engine.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    engine.lock_or_recover().run(RetentionTrigger::Scheduled);
+                })
+                .await;
+            }
+        });
+
+        let mut engine = engine.lock_or_recover();
+        engine.stop_daily_timer();
+        engine.timer = Some(handle);
+    }
+
+    /// Stop the daily timer
+    pub fn stop_daily_timer(&mut self) {
+        if let Some(handle) = self.timer.take() {
+            handle.abort();
+        }
+    }
+
+    /// Check if the daily timer is running
+    pub fn is_scheduled(&self) -> bool {
+        self.timer.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Get the report of the last run
+    pub fn last_report(&self) -> Option<RetentionReport> {
+        self.last_report.lock_or_recover().clone()
+    }
+
+    /// Set the policy
+    pub fn set_policy(&mut self, policy: RetentionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Get the policy
+    pub fn get_policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    // Private helper methods
+
+    fn history_cutoff(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.policy
+            .keep_history_days
+            .map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64))
+    }
+
+    fn execute(&self, trigger: RetentionTrigger, dry_run: bool) -> RetentionReport {
+        let on_exit = trigger == RetentionTrigger::Exit;
+        let mut report = RetentionReport {
+            trigger,
+            dry_run,
+            history_visits: 0,
+            cookies: 0,
+            cache_entries: 0,
+            site_storage_origins: 0,
+            form_entries: 0,
+            ran_at: chrono::Utc::now(),
+            errors: Vec::new(),
+        };
+
+        if let Some(history) = &self.targets.history {
+            let cutoff = if on_exit && self.policy.clear_history_on_exit {
+                Some(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+            } else {
+                self.history_cutoff()
+            };
+            if let Some(cutoff) = cutoff {
+                let from = chrono::DateTime::<chrono::Utc>::MIN_UTC;
+                if dry_run {
+                    report.history_visits = history.count_range(from, cutoff);
+                } else {
+                    match history.delete_range(from, cutoff) {
+                        Ok(count) => report.history_visits = count,
+                        Err(e) => report.errors.push(format!("history: {}", e)),
+                    }
+                }
+            }
+        }
+
+        if let Some(containers) = &self.targets.containers {
+            if on_exit && self.policy.clear_cookies_on_exit {
+                report.cookies = if dry_run {
+                    containers.count_cookies_except(&self.policy.cookie_allowlist)
+                } else {
+                    containers.clear_cookies_except(&self.policy.cookie_allowlist)
+                };
+            }
+        }
+
+        if let Some(http_cache) = &self.targets.http_cache {
+            let max_bytes = if on_exit && self.policy.clear_cache_on_exit {
+                Some(0)
+            } else {
+                self.policy.max_cache_mb.map(|mb| (mb * 1024 * 1024) as usize)
+            };
+            if let Some(max_bytes) = max_bytes {
+                let mut cache = http_cache.lock_or_recover();
+                report.cache_entries = if dry_run {
+                    cache.entries_over_size(max_bytes).len()
+                } else {
+                    cache.trim_to_size(max_bytes)
+                };
+            }
+        }
+
+        // The web view holds its storage open while it runs, so site
+        // storage is only touched at exit: cleared, or trimmed to quota
+        if let Some(site_storage) = self.targets.site_storage.as_ref().filter(|_| on_exit) {
+            let allowlist = &self.policy.cookie_allowlist;
+            report.site_storage_origins = match (self.policy.clear_site_storage_on_exit, dry_run) {
+                (true, true) => site_storage.count_all_except(allowlist),
+                (true, false) => site_storage.clear_all_except(allowlist).unwrap_or_else(|e| {
+                    report.errors.push(format!("site storage: {}", e));
+                    0
+                }),
+                (false, true) => site_storage.quota_evictions().len(),
+                (false, false) => site_storage.enforce_quotas().map(|evicted| evicted.len()).unwrap_or_else(|e| {
+                    report.errors.push(format!("site storage: {}", e));
+                    0
+                }),
+            };
+        }
+
+        if let Some(form_history) = &self.targets.form_history {
+            if on_exit && self.policy.clear_form_history_on_exit {
+                report.form_entries = if dry_run {
+                    form_history.count()
+                } else {
+                    form_history.clear().unwrap_or_else(|e| {
+                        report.errors.push(format!("form history: {}", e));
+                        0
+                    })
+                };
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::history_manager::VisitTransition;
+    use reqwest::cookie::CookieStore;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_exit_policy_dry_run_then_apply() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = Arc::new(HistoryManager::new(Some(temp_dir.path().join("history.db"))).unwrap());
+        let containers = Arc::new(ContainerManager::new(Some(temp_dir.path().join("containers"))).unwrap());
+        let http_cache = Arc::new(Mutex::new(HTTPCache::new(1, 60, false)));
+
+        history
+            .add_visit("https://example.com/", None, VisitTransition::Link)
+            .unwrap();
+        http_cache
+            .lock_or_recover()
+            .store_response("https://example.com/".to_string(), 200, HashMap::new(), vec![0; 100])
+            .unwrap();
+        let jar = containers.cookie_jar(None);
+        for (site, cookie) in [("https://bank.example/", "auth=1"), ("https://tracker.test/", "id=2")] {
+            let url = url::Url::parse(site).unwrap();
+            let header = reqwest::header::HeaderValue::from_static(cookie);
+            jar.set_cookies(&mut std::iter::once(&header), &url);
+        }
+
+        let policy = RetentionPolicy {
+            clear_history_on_exit: true,
+            clear_cookies_on_exit: true,
+            cookie_allowlist: vec!["bank.example".to_string()],
+            clear_cache_on_exit: true,
+            ..Default::default()
+        };
+        let engine = RetentionEngine::new(
+            policy,
+            RetentionTargets {
+                history: Some(history.clone()),
+                containers: Some(containers.clone()),
+                http_cache: Some(http_cache.clone()),
+                ..Default::default()
+            },
+        );
+
+        // Scheduled runs leave clear-on-exit data alone
+        let scheduled = engine.dry_run(RetentionTrigger::Scheduled);
+        assert_eq!((scheduled.history_visits, scheduled.cookies, scheduled.cache_entries), (0, 0, 0));
+
+        let preview = engine.dry_run(RetentionTrigger::Exit);
+        assert_eq!((preview.history_visits, preview.cookies, preview.cache_entries), (1, 1, 1));
+        assert_eq!(history.item_count(), 1);
+
+        let report = engine.run(RetentionTrigger::Exit);
+        assert_eq!((report.history_visits, report.cookies, report.cache_entries), (1, 1, 1));
+        assert!(report.errors.is_empty());
+        assert_eq!(history.item_count(), 0);
+        assert!(jar.cookies(&url::Url::parse("https://bank.example/").unwrap()).is_some());
+        assert!(jar.cookies(&url::Url::parse("https://tracker.test/").unwrap()).is_none());
+        assert_eq!(engine.last_report(), Some(report));
+    }
+
+    #[test]
+    fn test_history_age_limit() {
+        let engine = RetentionEngine::new(
+            RetentionPolicy {
+                keep_history_days: Some(30),
+                ..Default::default()
+            },
+            RetentionTargets::default(),
+        );
+
+        let now = chrono::Utc::now();
+        let mut entries: Vec<HistoryEntry> = [0, 10, 45]
+            .iter()
+            .enumerate()
+            .map(|(id, days)| HistoryEntry {
+                id,
+                title: String::new(),
+                url: format!("https://example.com/{}", id),
+                visited_at: now - chrono::Duration::days(*days),
+            })
+            .collect();
+
+        assert_eq!(engine.prune_history_entries(&mut entries, RetentionTrigger::Scheduled), 1);
+        assert_eq!(entries.len(), 2);
+    }
+}
@@ -0,0 +1,361 @@
+// Local Hash-Prefix Database
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// Categories of unsafe sites
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ThreatType {
+    Malware,
+    SocialEngineering,
+    UnwantedSoftware,
+    PotentiallyHarmfulApplication,
+}
+
+impl ThreatType {
+    /// Lists fetched from the Update API
+    pub fn all() -> &'static [ThreatType] {
+        &[
+            ThreatType::Malware,
+            ThreatType::SocialEngineering,
+            ThreatType::UnwantedSoftware,
+            ThreatType::PotentiallyHarmfulApplication,
+        ]
+    }
+
+    /// Name used by the Safe Browsing API and bundled list files
+    pub fn api_name(&self) -> &'static str {
+        match self {
+            ThreatType::Malware => "MALWARE",
+            ThreatType::SocialEngineering => "SOCIAL_ENGINEERING",
+            ThreatType::UnwantedSoftware => "UNWANTED_SOFTWARE",
+            ThreatType::PotentiallyHarmfulApplication => "POTENTIALLY_HARMFUL_APPLICATION",
+        }
+    }
+
+    /// Parse an API name
+    pub fn from_api_name(name: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|t| t.api_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Heading shown on the warning page
+    pub fn title(&self) -> &'static str {
+        match self {
+            ThreatType::Malware => "This site may harm your computer",
+            ThreatType::SocialEngineering => "Deceptive site ahead",
+            ThreatType::UnwantedSoftware => "This site may install unwanted software",
+            ThreatType::PotentiallyHarmfulApplication => "This site may offer harmful apps",
+        }
+    }
+}
+
+/// Hash prefixes and confirmed full hashes for one threat type
+#[derive(Debug, Clone, Default)]
+pub struct ThreatList {
+    /// Sorted lexicographically, as required for removals and checksums
+    prefixes: Vec<Vec<u8>>,
+    prefix_lengths: HashSet<usize>,
+    full_hashes: HashSet<[u8; 32]>,
+    pub client_state: String,
+}
+
+impl ThreatList {
+    /// Number of stored prefixes
+    pub fn prefix_count(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    /// Find the stored prefix of a full hash, if any
+    pub fn matching_prefix(&self, hash: &[u8; 32]) -> Option<Vec<u8>> {
+        self.prefix_lengths
+            .iter()
+            .map(|len| hash[..*len].to_vec())
+            .find(|prefix| self.prefixes.binary_search(prefix).is_ok())
+    }
+
+    /// Check if a full hash is known to be unsafe
+    pub fn contains_full_hash(&self, hash: &[u8; 32]) -> bool {
+        self.full_hashes.contains(hash)
+    }
+
+    /// Add a confirmed full hash (also adds its 4-byte prefix)
+    pub fn add_full_hash(&mut self, hash: [u8; 32]) {
+        self.full_hashes.insert(hash);
+        self.add_prefixes(vec![hash[..4].to_vec()]);
+    }
+
+    /// SHA-256 over the sorted, concatenated prefixes
+    pub fn checksum(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for prefix in &self.prefixes {
+            hasher.update(prefix);
+        }
+        hasher.finalize().into()
+    }
+
+    // Private helper methods
+
+    fn add_prefixes(&mut self, prefixes: Vec<Vec<u8>>) {
+        // Sorting once keeps large list updates linearithmic
+        self.prefix_lengths.extend(prefixes.iter().map(Vec::len));
+        self.prefixes.extend(prefixes);
+        self.prefixes.sort_unstable();
+        self.prefixes.dedup();
+    }
+
+    fn remove_indices(&mut self, indices: &[usize]) -> Result<(), String> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.last().is_some_and(|&i| i >= self.prefixes.len()) {
+            return Err("Removal index out of range".to_string());
+        }
+        for index in indices.into_iter().rev() {
+            self.prefixes.remove(index);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        *self = ThreatList::default();
+    }
+}
+
+/// On-disk form: prefixes grouped by length, each group base64 of the concatenation
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredList {
+    client_state: String,
+    prefixes: BTreeMap<usize, String>,
+    full_hashes: Vec<String>,
+}
+
+/// All threat lists
+#[derive(Debug, Default)]
+pub struct HashPrefixDatabase {
+    lists: HashMap<ThreatType, ThreatList>,
+}
+
+impl HashPrefixDatabase {
+    /// Create an empty database
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a threat list
+    pub fn list(&self, threat: ThreatType) -> Option<&ThreatList> {
+        self.lists.get(&threat)
+    }
+
+    /// Threat types whose full hash is confirmed unsafe
+    pub fn full_hash_matches(&self, hash: &[u8; 32]) -> Vec<ThreatType> {
+        let mut threats: Vec<ThreatType> = self
+            .lists
+            .iter()
+            .filter(|(_, list)| list.contains_full_hash(hash))
+            .map(|(threat, _)| *threat)
+            .collect();
+        threats.sort();
+        threats
+    }
+
+    /// Lists with a prefix matching the hash, with the matched prefix
+    pub fn prefix_matches(&self, hash: &[u8; 32]) -> Vec<(ThreatType, Vec<u8>)> {
+        let mut matches: Vec<(ThreatType, Vec<u8>)> = self
+            .lists
+            .iter()
+            .filter_map(|(threat, list)| list.matching_prefix(hash).map(|prefix| (*threat, prefix)))
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    /// Add a known-bad full hash (bundled lists and confirmed lookups)
+    pub fn add_full_hash(&mut self, threat: ThreatType, hash: [u8; 32]) {
+        self.lists.entry(threat).or_default().add_full_hash(hash);
+    }
+
+    /// Client states to send with the next update request
+    pub fn client_states(&self) -> Vec<(ThreatType, String)> {
+        ThreatType::all()
+            .iter()
+            .map(|threat| {
+                let state = self.lists.get(threat).map(|l| l.client_state.clone()).unwrap_or_default();
+                (*threat, state)
+            })
+            .collect()
+    }
+
+    /// Apply a threatListUpdates:fetch response
+    pub fn apply_update(&mut self, response: &serde_json::Value) -> Result<(), WebxError> {
+        let updates = response["listUpdateResponses"].as_array().cloned().unwrap_or_default();
+
+        for update in updates {
+            let Some(threat) = update["threatType"].as_str().and_then(ThreatType::from_api_name) else {
+                continue;
+            };
+            let list = self.lists.entry(threat).or_default();
+
+            if update["responseType"].as_str() == Some("FULL_UPDATE") {
+                list.clear();
+            }
+
+            // Removal indices refer to the list before this update's additions
+            for removal in update["removals"].as_array().into_iter().flatten() {
+                let indices: Vec<usize> = removal["rawIndices"]["indices"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| i.as_u64().map(|i| i as usize))
+                    .collect();
+                if let Err(e) = list.remove_indices(&indices) {
+                    list.clear();
+                    return Err(format!("{} list: {}", threat.api_name(), e).into());
+                }
+            }
+
+            for addition in update["additions"].as_array().into_iter().flatten() {
+                let raw = &addition["rawHashes"];
+                let size = raw["prefixSize"].as_u64().unwrap_or(4) as usize;
+                if !(4..=32).contains(&size) {
+                    return Err(format!("Invalid prefix size {}", size).into());
+                }
+                let bytes = STANDARD.decode(raw["rawHashes"].as_str().unwrap_or(""))?;
+                list.add_prefixes(bytes.chunks_exact(size).map(|c| c.to_vec()).collect());
+            }
+
+            if let Some(expected) = update["checksum"]["sha256"].as_str() {
+                if STANDARD.decode(expected)? != list.checksum() {
+                    // Start over with a full update next time
+                    list.clear();
+                    return Err(WebxError::Parse(format!("{} list checksum mismatch", threat.api_name())));
+                }
+            }
+
+            list.client_state = update["newClientState"].as_str().unwrap_or_default().to_string();
+        }
+
+        Ok(())
+    }
+
+    /// Save the database
+    pub fn save(&self, path: &Path) -> Result<(), WebxError> {
+        let stored: BTreeMap<ThreatType, StoredList> = self
+            .lists
+            .iter()
+            .map(|(threat, list)| {
+                let mut groups: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+                for prefix in &list.prefixes {
+                    groups.entry(prefix.len()).or_default().extend_from_slice(prefix);
+                }
+                let stored = StoredList {
+                    client_state: list.client_state.clone(),
+                    prefixes: groups.into_iter().map(|(len, bytes)| (len, STANDARD.encode(bytes))).collect(),
+                    full_hashes: list.full_hashes.iter().map(|h| STANDARD.encode(h)).collect(),
+                };
+                (*threat, stored)
+            })
+            .collect();
+        write_atomic(path, &serde_json::to_vec(&stored)?)?;
+        Ok(())
+    }
+
+    /// Load a saved database
+    pub fn load(path: &Path) -> Result<Self, WebxError> {
+        let stored: BTreeMap<ThreatType, StoredList> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let mut database = Self::new();
+
+        for (threat, stored) in stored {
+            let list = database.lists.entry(threat).or_default();
+            list.client_state = stored.client_state;
+            for (len, encoded) in stored.prefixes {
+                let bytes = STANDARD.decode(encoded)?;
+                list.add_prefixes(bytes.chunks_exact(len.max(1)).map(|c| c.to_vec()).collect());
+            }
+            for encoded in stored.full_hashes {
+                let hash: [u8; 32] = STANDARD
+                    .decode(encoded)?
+                    .try_into()
+                    .map_err(|_| "Invalid full hash length")?;
+                list.full_hashes.insert(hash);
+            }
+        }
+
+        Ok(database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(response_type: &str, prefixes: &[&[u8]], removals: &[usize], checksum: Option<[u8; 32]>) -> serde_json::Value {
+        let raw: Vec<u8> = prefixes.concat();
+        let mut update = serde_json::json!({
+            "threatType": "MALWARE",
+            "responseType": response_type,
+            "additions": [{ "rawHashes": { "prefixSize": 4, "rawHashes": STANDARD.encode(raw) } }],
+            "removals": [{ "rawIndices": { "indices": removals } }],
+            "newClientState": format!("state-{}", response_type),
+        });
+        if let Some(checksum) = checksum {
+            update["checksum"] = serde_json::json!({ "sha256": STANDARD.encode(checksum) });
+        }
+        serde_json::json!({ "listUpdateResponses": [update] })
+    }
+
+    #[test]
+    fn test_apply_updates() {
+        let mut database = HashPrefixDatabase::new();
+        database
+            .apply_update(&update("FULL_UPDATE", &[b"cccc", b"aaaa", b"bbbb"], &[], None))
+            .unwrap();
+        let list = database.list(ThreatType::Malware).unwrap();
+        assert_eq!(list.prefix_count(), 3);
+        assert_eq!(list.client_state, "state-FULL_UPDATE");
+
+        // Remove "bbbb" (index 1 of the sorted list) and add "dddd"
+        let mut expected = Sha256::new();
+        for prefix in [b"aaaa", b"cccc", b"dddd"] {
+            expected.update(prefix);
+        }
+        database
+            .apply_update(&update("PARTIAL_UPDATE", &[b"dddd"], &[1], Some(expected.finalize().into())))
+            .unwrap();
+        let list = database.list(ThreatType::Malware).unwrap();
+        let mut hash = [0u8; 32];
+        hash[..4].copy_from_slice(b"dddd");
+        assert_eq!(list.matching_prefix(&hash), Some(b"dddd".to_vec()));
+        hash[..4].copy_from_slice(b"bbbb");
+        assert!(list.matching_prefix(&hash).is_none());
+
+        // A bad checksum resets the list
+        assert!(database
+            .apply_update(&update("PARTIAL_UPDATE", &[b"eeee"], &[], Some([0; 32])))
+            .is_err());
+        assert_eq!(database.list(ThreatType::Malware).unwrap().prefix_count(), 0);
+        assert!(database.apply_update(&update("PARTIAL_UPDATE", &[], &[7], None)).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("db.json");
+
+        let mut database = HashPrefixDatabase::new();
+        database.apply_update(&update("FULL_UPDATE", &[b"abcd"], &[], None)).unwrap();
+        let full = Sha256::digest(b"evil.example/").into();
+        database.add_full_hash(ThreatType::SocialEngineering, full);
+        database.save(&path).unwrap();
+
+        let loaded = HashPrefixDatabase::load(&path).unwrap();
+        assert_eq!(loaded.full_hash_matches(&full), vec![ThreatType::SocialEngineering]);
+        assert_eq!(loaded.list(ThreatType::Malware).unwrap().checksum(), database.list(ThreatType::Malware).unwrap().checksum());
+        assert_eq!(loaded.client_states()[0], (ThreatType::Malware, "state-FULL_UPDATE".to_string()));
+    }
+}
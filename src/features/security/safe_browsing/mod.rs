@@ -0,0 +1,710 @@
+// Safe Browsing Module
+pub mod database;
+pub mod url_hash;
+
+pub use database::{HashPrefixDatabase, ThreatType};
+
+use crate::error::WebxError;
+use crate::config::storage::{save_json, DEFAULT_BACKUP_COUNT};
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::features::system::scheduler::{TaskScheduler, TaskSpec};
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::{escape_html, LockExt};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Internal page the warning is shown at; only it may ask to proceed
+pub const SAFE_BROWSING_PAGE_URL: &str = "webx://blocked";
+
+const API_BASE: &str = "https://safebrowsing.googleapis.com/v4";
+/// Used when the API does not say how long to wait
+const DEFAULT_UPDATE_INTERVAL_SECS: i64 = 30 * 60;
+/// How often the scheduler looks for due list updates
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(DEFAULT_UPDATE_INTERVAL_SECS as u64);
+/// How long a URL loads unchecked after the API could not be reached
+const FAIL_OPEN_SECS: i64 = 60;
+
+/// Safe browsing configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafeBrowsingConfig {
+    pub enabled: bool,
+    /// Google Safe Browsing API key; without one only bundled lists are used
+    pub api_key: Option<String>,
+    pub next_update_at: Option<DateTime<Utc>>,
+}
+
+impl Default for SafeBrowsingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            api_key: None,
+            next_update_at: None,
+        }
+    }
+}
+
+/// A site the user chose to trust despite a warning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowOverride {
+    pub host: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Result of checking a navigation
+#[derive(Debug, Clone, PartialEq)]
+pub enum NavigationDecision {
+    Allow,
+    Block { threat: ThreatType },
+}
+
+/// Result of a local lookup
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalVerdict {
+    Safe,
+    Unsafe(ThreatType),
+    /// A hash prefix matched; the full hash must be confirmed online
+    Unconfirmed(Vec<(ThreatType, Vec<u8>)>),
+}
+
+/// Safe browsing events
+#[derive(Debug, Clone)]
+pub enum SafeBrowsingEvent {
+    /// Show the warning interstitial instead of the page
+    NavigationBlocked { tab_id: usize, url: String, threat: ThreatType },
+    ListsUpdated { prefix_count: usize },
+    OverrideAdded { host: String },
+}
+
+/// Checks navigations against locally cached threat lists
+pub struct SafeBrowsing {
+    config: Arc<Mutex<SafeBrowsingConfig>>,
+    database: Arc<Mutex<HashPrefixDatabase>>,
+    /// Prefixes confirmed safe by the API, until the given time
+    negative_cache: Arc<Mutex<HashMap<Vec<u8>, DateTime<Utc>>>>,
+    overrides: Arc<Mutex<Vec<AllowOverride>>>,
+    data_dir: PathBuf,
+    client: reqwest::Client,
+    tx: mpsc::UnboundedSender<SafeBrowsingEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<SafeBrowsingEvent>>>>,
+}
+
+impl SafeBrowsing {
+    /// Create new safe browsing checker
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        let data_dir = config_dir.join("safe_browsing");
+        std::fs::create_dir_all(&data_dir)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let safe_browsing = Self {
+            config: Arc::new(Mutex::new(SafeBrowsingConfig::default())),
+            database: Arc::new(Mutex::new(HashPrefixDatabase::new())),
+            negative_cache: Arc::new(Mutex::new(HashMap::new())),
+            overrides: Arc::new(Mutex::new(Vec::new())),
+            data_dir,
+            client: reqwest::Client::new(),
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        };
+
+        safe_browsing.load_data()?;
+
+        Ok(safe_browsing)
+    }
+
+    /// Enable or disable checks
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), WebxError> {
+        self.config.lock_or_recover().enabled = enabled;
+        self.save_config()
+    }
+
+    /// Set the API key used for list updates and full-hash lookups
+    pub fn set_api_key(&self, api_key: Option<String>) -> Result<(), WebxError> {
+        self.config.lock_or_recover().api_key = api_key.filter(|k| !k.trim().is_empty());
+        self.save_config()
+    }
+
+    /// Get configuration
+    pub fn get_config(&self) -> SafeBrowsingConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    /// Load a bundled list: one URL per line, optionally prefixed by a threat type
+    pub fn load_bundled_list(&self, path: &Path) -> Result<usize, WebxError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut count = 0;
+        {
+            let mut database = self.database.lock_or_recover();
+            for line in content.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (threat, entry) = match line.split_once(char::is_whitespace) {
+                    Some((kind, rest)) => match ThreatType::from_api_name(kind) {
+                        Some(threat) => (threat, rest.trim()),
+                        None => (ThreatType::Malware, line),
+                    },
+                    None => (ThreatType::Malware, line),
+                };
+                if let Some(expression) = url_hash::canonicalize(entry) {
+                    database.add_full_hash(threat, url_hash::hash_expression(&expression));
+                    count += 1;
+                }
+            }
+        }
+        self.save_database()?;
+        Ok(count)
+    }
+
+    /// Check a URL against the local lists only
+    pub fn check_url_local(&self, url: &str) -> LocalVerdict {
+        let hashes = url_hash::url_hashes(url);
+        let database = self.database.lock_or_recover();
+
+        for hash in &hashes {
+            if let Some(threat) = database.full_hash_matches(hash).first() {
+                return LocalVerdict::Unsafe(*threat);
+            }
+        }
+
+        let now = Utc::now();
+        let negative_cache = self.negative_cache.lock_or_recover();
+        let unconfirmed: Vec<(ThreatType, Vec<u8>)> = hashes
+            .iter()
+            .flat_map(|hash| database.prefix_matches(hash))
+            .filter(|(_, prefix)| negative_cache.get(prefix).is_none_or(|until| *until <= now))
+            .collect();
+
+        if unconfirmed.is_empty() {
+            LocalVerdict::Safe
+        } else {
+            LocalVerdict::Unconfirmed(unconfirmed)
+        }
+    }
+
+    /// Decide whether a tab may navigate to a URL without waiting on the
+    /// network, before the page loads; None when a listed prefix matched
+    /// and `check_navigation` must confirm it online. Emits an event when
+    /// blocked.
+    pub fn check_navigation_now(&self, tab_id: usize, url: &str) -> Option<NavigationDecision> {
+        let is_web = url::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !is_web || !self.config.lock_or_recover().enabled || self.is_allowed(url) {
+            return Some(NavigationDecision::Allow);
+        }
+        match self.check_url_local(url) {
+            LocalVerdict::Safe => Some(NavigationDecision::Allow),
+            LocalVerdict::Unsafe(threat) => Some(self.block(tab_id, url, threat)),
+            // Without an API key a bare prefix match cannot be confirmed
+            LocalVerdict::Unconfirmed(_) if self.config.lock_or_recover().api_key.is_none() => {
+                Some(NavigationDecision::Allow)
+            }
+            LocalVerdict::Unconfirmed(_) => None,
+        }
+    }
+
+    /// Decide whether a tab may navigate to a URL, confirming listed
+    /// prefixes online; emits an event when blocked
+    pub async fn check_navigation(&self, tab_id: usize, url: &str) -> NavigationDecision {
+        if let Some(decision) = self.check_navigation_now(tab_id, url) {
+            return decision;
+        }
+
+        if let LocalVerdict::Unconfirmed(prefixes) = self.check_url_local(url) {
+            let prefixes: Vec<Vec<u8>> = prefixes.into_iter().map(|(_, p)| p).collect();
+            if let Err(e) = self.confirm_full_hashes(&prefixes).await {
+                // Fail open rather than block the web when the API is
+                // unreachable, letting the held navigation go ahead
+                tracing::warn!("Safe Browsing lookup failed: {}", e);
+                let until = Utc::now() + chrono::Duration::seconds(FAIL_OPEN_SECS);
+                let mut negative_cache = self.negative_cache.lock_or_recover();
+                for prefix in prefixes {
+                    negative_cache.insert(prefix, until);
+                }
+                return NavigationDecision::Allow;
+            }
+        }
+
+        match self.check_url_local(url) {
+            LocalVerdict::Unsafe(threat) => self.block(tab_id, url, threat),
+            _ => NavigationDecision::Allow,
+        }
+    }
+
+    /// Fetch list updates if the API's minimum wait has passed
+    pub async fn update_if_due(&self) -> Result<bool, WebxError> {
+        let due = self
+            .config
+            .lock_or_recover()
+            .next_update_at
+            .is_none_or(|at| at <= Utc::now());
+        if !due {
+            return Ok(false);
+        }
+        self.update_lists().await?;
+        Ok(true)
+    }
+
+    /// Fetch threat list updates from the Update API
+    pub async fn update_lists(&self) -> Result<usize, WebxError> {
+        let api_key = self.config.lock_or_recover().api_key.clone().ok_or("No Safe Browsing API key configured")?;
+
+        let requests: Vec<serde_json::Value> = self
+            .database
+            .lock_or_recover()
+            .client_states()
+            .into_iter()
+            .map(|(threat, state)| {
+                serde_json::json!({
+                    "threatType": threat.api_name(),
+                    "platformType": "ANY_PLATFORM",
+                    "threatEntryType": "URL",
+                    "state": state,
+                    "constraints": { "supportedCompressions": ["RAW"] },
+                })
+            })
+            .collect();
+        let body = serde_json::json!({
+            "client": Self::client_info(),
+            "listUpdateRequests": requests,
+        });
+
+        let response: serde_json::Value = self
+            .client
+            .post(format!("{}/threatListUpdates:fetch?key={}", API_BASE, api_key))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let prefix_count = {
+            let mut database = self.database.lock_or_recover();
+            database.apply_update(&response)?;
+            ThreatType::all()
+                .iter()
+                .filter_map(|t| database.list(*t))
+                .map(|l| l.prefix_count())
+                .sum()
+        };
+
+        let wait = response["minimumWaitDuration"]
+            .as_str()
+            .and_then(parse_duration_secs)
+            .unwrap_or(DEFAULT_UPDATE_INTERVAL_SECS);
+        self.config.lock_or_recover().next_update_at = Some(Utc::now() + chrono::Duration::seconds(wait));
+
+        self.save_config()?;
+        self.save_database()?;
+        let _ = self.tx.send(SafeBrowsingEvent::ListsUpdated { prefix_count });
+        Ok(prefix_count)
+    }
+
+    /// Trust a site despite warnings (user-reported false positive)
+    pub fn add_allow_override(&self, url: &str, reason: Option<&str>) -> Result<(), WebxError> {
+        let host = site_host(url);
+        {
+            let mut overrides = self.overrides.lock_or_recover();
+            overrides.retain(|o| o.host != host);
+            overrides.push(AllowOverride {
+                host: host.clone(),
+                reason: reason.map(|r| r.to_string()),
+                created_at: Utc::now(),
+            });
+        }
+        self.save_overrides()?;
+        let _ = self.tx.send(SafeBrowsingEvent::OverrideAdded { host });
+        Ok(())
+    }
+
+    /// Remove an allow override
+    pub fn remove_allow_override(&self, url: &str) -> Result<bool, WebxError> {
+        let host = site_host(url);
+        let removed = {
+            let mut overrides = self.overrides.lock_or_recover();
+            let before = overrides.len();
+            overrides.retain(|o| o.host != host);
+            overrides.len() != before
+        };
+        if removed {
+            self.save_overrides()?;
+        }
+        Ok(removed)
+    }
+
+    /// List allow overrides
+    pub fn list_allow_overrides(&self) -> Vec<AllowOverride> {
+        self.overrides.lock_or_recover().clone()
+    }
+
+    /// Check if the user trusted a URL's site
+    pub fn is_allowed(&self, url: &str) -> bool {
+        let host = site_host(url);
+        self.overrides.lock_or_recover().iter().any(|o| o.host == host)
+    }
+
+    /// The warning page at a `blocked_page_url`; only URLs the lists block
+    /// have one
+    pub fn render_blocked_page(&self, page_url: &str) -> Result<String, WebxError> {
+        let url = url::Url::parse(page_url)
+            .ok()
+            .and_then(|parsed| parsed.query_pairs().find(|(key, _)| key == "url").map(|(_, url)| url.to_string()))
+            .ok_or_else(|| WebxError::NotFound(format!("No blocked site in {}", page_url)))?;
+        match self.check_url_local(&url) {
+            LocalVerdict::Unsafe(threat) => Ok(self.get_interstitial_html(&url, threat)),
+            _ => Err(WebxError::NotFound(format!("{} is not blocked", url))),
+        }
+    }
+
+    /// Warning page shown in place of a blocked site, served at
+    /// `SAFE_BROWSING_PAGE_URL` so it may answer with `safe-browsing-proceed`
+    pub fn get_interstitial_html(&self, url: &str, threat: ThreatType) -> String {
+        format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>Security warning</title>
+    <style>
+        body {{ font-family: system-ui, sans-serif; background: #b71c1c; color: #fff; display: flex; justify-content: center; padding-top: 15vh; }}
+        main {{ max-width: 600px; }}
+        code {{ word-break: break-all; }}
+        button {{ padding: 10px 20px; border: none; border-radius: 4px; font-size: 15px; cursor: pointer; }}
+        .details {{ margin-top: 40px; font-size: 13px; opacity: 0.85; }}
+        .details a {{ color: #fff; }}
+    </style>
+</head>
+<body>
+    <main>
+        <h1>{title}</h1>
+        <p>WebX blocked <code>{url}</code> because it is listed as {kind}.</p>
+        <button onclick="history.length > 1 ? history.back() : window.close()">Back to safety</button>
+        <div class="details">
+            If you understand the risks, you can
+            <a href="#" onclick="window.ipc.request({{ type: 'safe-browsing-proceed', url: {url_json} }}).then(() => location.replace({url_json})); return false;">visit this site anyway</a>.
+        </div>
+    </main>
+</body>
+</html>"##,
+            title = threat.title(),
+            url = escape_html(url),
+            kind = threat.api_name().to_lowercase().replace('_', " "),
+            url_json = serde_json::to_string(url).unwrap_or_default().replace('"', "&quot;"),
+        )
+    }
+
+    /// Subscribe to safe browsing events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<SafeBrowsingEvent> {
+        self.rx.lock_or_recover().take().expect("Events already subscribed")
+    }
+
+    /// Fetch list updates as the API allows, once a key is configured
+    pub fn schedule(safe_browsing: Arc<Self>, scheduler: &TaskScheduler) {
+        let spec = TaskSpec::new("safe-browsing-update", UPDATE_CHECK_INTERVAL).uses_network();
+        scheduler.register(spec, move || {
+            let safe_browsing = Arc::clone(&safe_browsing);
+            async move {
+                if safe_browsing.get_config().api_key.is_none() {
+                    return Ok(());
+                }
+                safe_browsing.update_if_due().await.map(|_| ())
+            }
+        });
+    }
+
+    // Private helper methods
+
+    fn block(&self, tab_id: usize, url: &str, threat: ThreatType) -> NavigationDecision {
+        let _ = self.tx.send(SafeBrowsingEvent::NavigationBlocked {
+            tab_id,
+            url: url.to_string(),
+            threat,
+        });
+        NavigationDecision::Block { threat }
+    }
+
+    async fn confirm_full_hashes(&self, prefixes: &[Vec<u8>]) -> Result<(), WebxError> {
+        let api_key = self.config.lock_or_recover().api_key.clone().ok_or("No Safe Browsing API key configured")?;
+        let (threat_types, client_states): (Vec<&str>, Vec<String>) = self
+            .database
+            .lock_or_recover()
+            .client_states()
+            .into_iter()
+            .map(|(threat, state)| (threat.api_name(), state))
+            .unzip();
+
+        let body = serde_json::json!({
+            "client": Self::client_info(),
+            "clientStates": client_states,
+            "threatInfo": {
+                "threatTypes": threat_types,
+                "platformTypes": ["ANY_PLATFORM"],
+                "threatEntryTypes": ["URL"],
+                "threatEntries": prefixes.iter().map(|p| serde_json::json!({ "hash": STANDARD.encode(p) })).collect::<Vec<_>>(),
+            },
+        });
+
+        let response: serde_json::Value = self
+            .client
+            .post(format!("{}/fullHashes:find?key={}", API_BASE, api_key))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.apply_full_hash_response(prefixes, &response);
+        self.save_database()
+    }
+
+    fn apply_full_hash_response(&self, prefixes: &[Vec<u8>], response: &serde_json::Value) {
+        {
+            let mut database = self.database.lock_or_recover();
+            for found in response["matches"].as_array().into_iter().flatten() {
+                let threat = found["threatType"].as_str().and_then(ThreatType::from_api_name);
+                let hash = found["threat"]["hash"]
+                    .as_str()
+                    .and_then(|h| STANDARD.decode(h).ok())
+                    .and_then(|h| <[u8; 32]>::try_from(h).ok());
+                if let (Some(threat), Some(hash)) = (threat, hash) {
+                    database.add_full_hash(threat, hash);
+                }
+            }
+        }
+
+        // Prefixes are safe for any URL whose full hash was not returned
+        let negative_secs = response["negativeCacheDuration"]
+            .as_str()
+            .and_then(parse_duration_secs)
+            .unwrap_or(300);
+        let until = Utc::now() + chrono::Duration::seconds(negative_secs);
+        let mut negative_cache = self.negative_cache.lock_or_recover();
+        for prefix in prefixes {
+            negative_cache.insert(prefix.clone(), until);
+        }
+    }
+
+    fn client_info() -> serde_json::Value {
+        serde_json::json!({
+            "clientId": "webx",
+            "clientVersion": env!("CARGO_PKG_VERSION"),
+        })
+    }
+
+    fn save_config(&self) -> Result<(), WebxError> {
+        save_json(&self.data_dir.join("config.json"), &*self.config.lock_or_recover(), DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+
+    fn save_database(&self) -> Result<(), WebxError> {
+        self.database.lock_or_recover().save(&self.data_dir.join("lists.json"))
+    }
+
+    fn save_overrides(&self) -> Result<(), WebxError> {
+        save_json(&self.data_dir.join("overrides.json"), &*self.overrides.lock_or_recover(), DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+
+    fn load_data(&self) -> Result<(), WebxError> {
+        let config_path = self.data_dir.join("config.json");
+        if config_path.exists() {
+            *self.config.lock_or_recover() = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+        }
+
+        let lists_path = self.data_dir.join("lists.json");
+        if lists_path.exists() {
+            match HashPrefixDatabase::load(&lists_path) {
+                Ok(database) => *self.database.lock_or_recover() = database,
+                // A corrupt cache is rebuilt by the next full update
+                Err(e) => tracing::warn!("Discarding Safe Browsing lists: {}", e),
+            }
+        }
+
+        let overrides_path = self.data_dir.join("overrides.json");
+        if overrides_path.exists() {
+            *self.overrides.lock_or_recover() = serde_json::from_str(&std::fs::read_to_string(overrides_path)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl IpcHandler for SafeBrowsing {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["safe-browsing-proceed"]
+    }
+
+    /// Trusts the site the warning page blocked, so the page can go on to it
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::SafeBrowsingProceed { url } => {
+                // Only a site the warning was shown for can be trusted from it
+                if !matches!(self.check_url_local(&url), LocalVerdict::Unsafe(_)) {
+                    return Err(WebxError::Invalid(format!("{} is not blocked", url)));
+                }
+                self.add_allow_override(&url, None)?;
+                Ok(serde_json::Value::Null)
+            }
+            _ => Err(WebxError::Invalid("Not a safe browsing message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for SafeBrowsing {
+    fn module(&self) -> &str {
+        "safebrowsing"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("safebrowsing.enabled", "Warn about dangerous sites", true)
+                .with_description("Check pages against lists of known malware and phishing sites")
+                .with_category(SettingCategory::Security),
+            SettingDefinition::text("safebrowsing.api_key", "Safe Browsing API key", "")
+                .with_description("Google Safe Browsing key for automatic list updates")
+                .with_category(SettingCategory::Security),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "safebrowsing.enabled" => self.set_enabled(value.as_bool().ok_or("Expected a toggle value")?),
+            "safebrowsing.api_key" => self.set_api_key(value.as_str().map(|s| s.to_string())),
+            _ => Err(format!("Unknown safe browsing setting {}", key).into()),
+        }
+    }
+}
+
+/// Address of the warning page for a blocked URL
+pub fn blocked_page_url(url: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+    format!("{}?url={}", SAFE_BROWSING_PAGE_URL, encoded)
+}
+
+/// Whether a URL is the warning page
+pub fn is_blocked_page(url: &str) -> bool {
+    url.strip_prefix(SAFE_BROWSING_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Host an override applies to; accepts URLs or bare hosts
+fn site_host(url: &str) -> String {
+    let parsed = url::Url::parse(url).or_else(|_| url::Url::parse(&format!("https://{}", url)));
+    parsed
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_lowercase()))
+        .unwrap_or_else(|| url.to_lowercase())
+}
+
+/// Parse an API duration like "593.440s"
+fn parse_duration_secs(value: &str) -> Option<i64> {
+    value.strip_suffix('s')?.parse::<f64>().ok().map(|secs| secs.ceil() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_bundled_list_blocking() {
+        let temp_dir = TempDir::new().unwrap();
+        let list_path = temp_dir.path().join("bundled.txt");
+        std::fs::write(
+            &list_path,
+            "# test list\nSOCIAL_ENGINEERING phish.example/login\nmalware.example\n",
+        )
+        .unwrap();
+
+        let safe_browsing = SafeBrowsing::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let mut events = safe_browsing.subscribe_events();
+        assert_eq!(safe_browsing.load_bundled_list(&list_path).unwrap(), 2);
+
+        assert_eq!(
+            safe_browsing.check_navigation(3, "https://phish.example/login?next=/").await,
+            NavigationDecision::Block { threat: ThreatType::SocialEngineering }
+        );
+        match events.try_recv() {
+            Ok(SafeBrowsingEvent::NavigationBlocked { tab_id, threat, .. }) => {
+                assert_eq!(tab_id, 3);
+                assert_eq!(threat, ThreatType::SocialEngineering);
+            }
+            other => panic!("Expected blocked event, got {:?}", other),
+        }
+
+        // Any page on a listed host is covered by the host expression
+        assert_eq!(
+            safe_browsing.check_url_local("http://cdn.malware.example/payload.exe"),
+            LocalVerdict::Unsafe(ThreatType::Malware)
+        );
+        assert_eq!(safe_browsing.check_navigation(1, "https://phish.example/about").await, NavigationDecision::Allow);
+        assert_eq!(safe_browsing.check_navigation(1, "https://example.com/").await, NavigationDecision::Allow);
+
+        let html = safe_browsing.get_interstitial_html("https://phish.example/<x>", ThreatType::SocialEngineering);
+        assert!(html.contains("Deceptive site ahead") && html.contains("&lt;x&gt;"));
+
+        // Navigations are decided before they load, and the warning is only
+        // served for listed sites
+        assert_eq!(
+            safe_browsing.check_navigation_now(2, "http://malware.example/"),
+            Some(NavigationDecision::Block { threat: ThreatType::Malware })
+        );
+        assert_eq!(safe_browsing.check_navigation_now(2, "webx://settings"), Some(NavigationDecision::Allow));
+        let page = blocked_page_url("http://malware.example/?a=1&b=2");
+        assert!(is_blocked_page(&page));
+        assert!(safe_browsing.render_blocked_page(&page).unwrap().contains("malware.example/?a=1&amp;b=2"));
+        assert!(safe_browsing.render_blocked_page(&blocked_page_url("https://example.com/")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allow_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let list_path = temp_dir.path().join("bundled.txt");
+        std::fs::write(&list_path, "MALWARE tools.example/\n").unwrap();
+
+        let safe_browsing = SafeBrowsing::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        safe_browsing.load_bundled_list(&list_path).unwrap();
+        assert!(matches!(
+            safe_browsing.check_navigation(1, "https://tools.example/").await,
+            NavigationDecision::Block { .. }
+        ));
+
+        safe_browsing
+            .add_allow_override("https://www.tools.example/download", Some("Internal tool"))
+            .unwrap();
+        assert_eq!(safe_browsing.check_navigation(1, "https://tools.example/").await, NavigationDecision::Allow);
+
+        // Lists and overrides persist
+        let safe_browsing = SafeBrowsing::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(safe_browsing.list_allow_overrides()[0].host, "tools.example");
+        assert!(safe_browsing.remove_allow_override("tools.example").unwrap());
+        assert!(matches!(
+            safe_browsing.check_navigation(1, "https://tools.example/").await,
+            NavigationDecision::Block { .. }
+        ));
+
+        // Proceeding from the warning page trusts the site; web pages may not ask
+        let proceed = IpcMessage::SafeBrowsingProceed { url: "https://tools.example/".to_string() };
+        assert!(!proceed.scope().allows("https://tools.example/"));
+        let context = IpcContext { window_id: 1, tab_id: Some(1), origin: SAFE_BROWSING_PAGE_URL.to_string() };
+        assert!(proceed.scope().allows(&context.origin));
+        safe_browsing.handle(&context, proceed).unwrap();
+        assert!(safe_browsing.is_allowed("https://tools.example/"));
+        let unlisted = IpcMessage::SafeBrowsingProceed { url: "https://example.com/".to_string() };
+        assert!(safe_browsing.handle(&context, unlisted).is_err());
+
+        assert_eq!(parse_duration_secs("593.440s"), Some(594));
+    }
+}
@@ -0,0 +1,149 @@
+// URL Canonicalization and Hashing (Safe Browsing v4 rules)
+use sha2::{Digest, Sha256};
+
+/// Hosts are checked with at most this many suffixes besides the exact host
+const MAX_HOST_SUFFIXES: usize = 4;
+/// Paths are checked with at most this many prefixes besides the exact path
+const MAX_PATH_PREFIXES: usize = 4;
+
+/// Canonical "host/path?query" form of a URL, or None if it is not an http(s) URL
+pub fn canonicalize(url: &str) -> Option<String> {
+    let trimmed: String = url.trim().chars().filter(|c| !matches!(c, '\t' | '\r' | '\n')).collect();
+    let with_scheme = if trimmed.contains("://") {
+        trimmed
+    } else {
+        format!("http://{}", trimmed)
+    };
+
+    let parsed = url::Url::parse(&with_scheme).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = parsed.host_str()?.trim_matches('.').to_lowercase();
+    let mut collapsed = String::with_capacity(host.len());
+    for ch in host.chars() {
+        if !(ch == '.' && collapsed.ends_with('.')) {
+            collapsed.push(ch);
+        }
+    }
+
+    // The url crate already resolved "." and ".." segments
+    let mut path = parsed.path().to_string();
+    while path.contains("//") {
+        path = path.replace("//", "/");
+    }
+
+    let mut canonical = format!("{}{}", collapsed, path);
+    if let Some(query) = parsed.query() {
+        canonical.push('?');
+        canonical.push_str(query);
+    }
+    Some(canonical)
+}
+
+/// All host-suffix/path-prefix combinations that are looked up for a URL
+pub fn url_expressions(url: &str) -> Vec<String> {
+    let Some(canonical) = canonicalize(url) else {
+        return Vec::new();
+    };
+
+    let (host, path_and_query) = match canonical.find('/') {
+        Some(index) => canonical.split_at(index),
+        None => (canonical.as_str(), "/"),
+    };
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path_and_query, None),
+    };
+
+    let mut hosts = vec![host.to_string()];
+    let is_ip = host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[');
+    if !is_ip {
+        let components: Vec<&str> = host.split('.').collect();
+        // Start from the last five components and drop leading ones
+        let start = components.len().saturating_sub(MAX_HOST_SUFFIXES + 1).max(1);
+        for i in start..components.len().saturating_sub(1) {
+            hosts.push(components[i..].join("."));
+        }
+    }
+
+    let mut paths = Vec::new();
+    if let Some(query) = query {
+        paths.push(format!("{}?{}", path, query));
+    }
+    paths.push(path.to_string());
+    paths.push("/".to_string());
+    // Only directories: the final component is covered by the exact path
+    let directories = &path[..path.rfind('/').unwrap_or(0)];
+    let segments: Vec<&str> = directories.split('/').filter(|s| !s.is_empty()).collect();
+    let mut prefix = String::from("/");
+    for segment in segments.iter().take(MAX_PATH_PREFIXES - 1) {
+        prefix.push_str(segment);
+        prefix.push('/');
+        paths.push(prefix.clone());
+    }
+
+    let mut expressions = Vec::new();
+    for host in &hosts {
+        for path in &paths {
+            let expression = format!("{}{}", host, path);
+            if !expressions.contains(&expression) {
+                expressions.push(expression);
+            }
+        }
+    }
+    expressions
+}
+
+/// SHA-256 of an expression
+pub fn hash_expression(expression: &str) -> [u8; 32] {
+    Sha256::digest(expression.as_bytes()).into()
+}
+
+/// Full hashes for every lookup expression of a URL
+pub fn url_hashes(url: &str) -> Vec<[u8; 32]> {
+    url_expressions(url).iter().map(|e| hash_expression(e)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_expressions() {
+        assert_eq!(
+            canonicalize("HTTP://www.GOOgle.com/a/../b//c?x=1#frag").as_deref(),
+            Some("www.google.com/b/c?x=1")
+        );
+        assert_eq!(canonicalize("example.com").as_deref(), Some("example.com/"));
+        assert!(canonicalize("ftp://example.com/").is_none());
+
+        // Example from the Safe Browsing documentation
+        let expressions = url_expressions("http://a.b.c/1/2.html?param=1");
+        assert_eq!(
+            expressions,
+            vec![
+                "a.b.c/1/2.html?param=1",
+                "a.b.c/1/2.html",
+                "a.b.c/",
+                "a.b.c/1/",
+                "b.c/1/2.html?param=1",
+                "b.c/1/2.html",
+                "b.c/",
+                "b.c/1/",
+            ]
+        );
+
+        // IP hosts are not split into suffixes
+        assert!(url_expressions("http://1.2.3.4/").iter().all(|e| e.starts_with("1.2.3.4/")));
+
+        // At most five host variants are checked
+        let hosts: std::collections::HashSet<String> = url_expressions("http://a.b.c.d.e.f.g/")
+            .iter()
+            .map(|e| e.trim_end_matches('/').to_string())
+            .collect();
+        assert_eq!(hosts.len(), 5);
+        assert!(hosts.contains("f.g") && !hosts.contains("b.c.d.e.f.g"));
+    }
+}
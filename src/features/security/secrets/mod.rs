@@ -0,0 +1,258 @@
+// Secret Storage
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::security::password_manager::PasswordEncryption;
+use crate::utils::LockExt;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Service name secrets are filed under in the OS keyring
+pub const SECRET_SERVICE: &str = "webx";
+
+/// Random key the password vault is encrypted with, unless the user sets a
+/// master password
+pub const VAULT_KEY_SECRET: &str = "vault-key";
+
+/// WebDAV account password for backups
+pub const WEBDAV_PASSWORD_SECRET: &str = "webdav-password";
+
+/// Passphrase backup archives are encrypted with
+pub const BACKUP_PASSPHRASE_SECRET: &str = "backup-passphrase";
+
+/// Where secrets are kept
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SecretBackend {
+    /// Secret Service or the kernel keyring on Linux, Keychain on macOS,
+    /// Credential Manager on Windows
+    Keyring,
+    /// AES-256-GCM encrypted file in the config directory, for systems
+    /// without a usable keyring. Its key sits beside it, readable only by
+    /// the user, so it keeps secrets out of backups and casual view but not
+    /// from someone who can read the profile.
+    EncryptedFile,
+}
+
+/// Stores passwords, keys and tokens in the OS keyring, falling back to an
+/// encrypted file
+pub struct SecretStore {
+    backend: SecretBackend,
+    service: String,
+    file_path: PathBuf,
+    key_path: PathBuf,
+    /// Secrets in the encrypted file, read on first use
+    file_cache: Mutex<Option<BTreeMap<String, String>>>,
+}
+
+impl SecretStore {
+    /// Use the OS keyring if it answers, the encrypted file in `config_dir`
+    /// (default: the config directory) if not
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let backend = if keyring_available(SECRET_SERVICE) {
+            SecretBackend::Keyring
+        } else {
+            tracing::warn!("No usable OS keyring, keeping secrets in an encrypted file");
+            SecretBackend::EncryptedFile
+        };
+        Self::with_backend(config_dir, backend)
+    }
+
+    /// Use a given backend
+    pub fn with_backend(config_dir: Option<PathBuf>, backend: SecretBackend) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        Ok(Self {
+            backend,
+            service: SECRET_SERVICE.to_string(),
+            file_path: config_dir.join("secrets.json"),
+            key_path: config_dir.join("secrets.key"),
+            file_cache: Mutex::new(None),
+        })
+    }
+
+    pub fn backend(&self) -> SecretBackend {
+        self.backend
+    }
+
+    /// A secret by name, None if it was never stored
+    pub fn get(&self, name: &str) -> Result<Option<String>, WebxError> {
+        match self.backend {
+            SecretBackend::Keyring => match keyring::Entry::new(&self.service, name)?.get_password() {
+                Ok(secret) => Ok(Some(secret)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+            SecretBackend::EncryptedFile => Ok(self.with_file(|secrets| Ok(secrets.get(name).cloned()))?),
+        }
+    }
+
+    /// Store a secret, replacing any with the same name
+    pub fn set(&self, name: &str, secret: &str) -> Result<(), WebxError> {
+        match self.backend {
+            SecretBackend::Keyring => Ok(keyring::Entry::new(&self.service, name)?.set_password(secret)?),
+            SecretBackend::EncryptedFile => self.with_file(|secrets| {
+                secrets.insert(name.to_string(), secret.to_string());
+                Ok(())
+            }),
+        }
+    }
+
+    /// Remove a secret; whether there was one
+    pub fn delete(&self, name: &str) -> Result<bool, WebxError> {
+        match self.backend {
+            SecretBackend::Keyring => match keyring::Entry::new(&self.service, name)?.delete_credential() {
+                Ok(()) => Ok(true),
+                Err(keyring::Error::NoEntry) => Ok(false),
+                Err(e) => Err(e.into()),
+            },
+            SecretBackend::EncryptedFile => self.with_file(|secrets| Ok(secrets.remove(name).is_some())),
+        }
+    }
+
+    /// A random 32-byte key by name, created and stored on first use
+    pub fn get_or_create_key(&self, name: &str) -> Result<[u8; 32], WebxError> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        if let Some(encoded) = self.get(name)? {
+            return engine
+                .decode(encoded)?
+                .try_into()
+                .map_err(|_| WebxError::Crypto(format!("Stored key {} has the wrong length", name)));
+        }
+        let key = PasswordEncryption::generate_salt();
+        self.set(name, &engine.encode(key))?;
+        Ok(key)
+    }
+
+    // Private helper methods
+
+    /// Run a change on the encrypted file's secrets, writing them back if
+    /// the change succeeds
+    fn with_file<T>(&self, change: impl FnOnce(&mut BTreeMap<String, String>) -> Result<T, WebxError>) -> Result<T, WebxError> {
+        let mut cache = self.file_cache.lock_or_recover();
+        let key = self.file_key()?;
+        if cache.is_none() {
+            *cache = Some(read_secrets_file(&self.file_path, &key)?);
+        }
+        let secrets = cache.as_mut().expect("loaded above");
+        let before = secrets.clone();
+        let result = change(secrets)?;
+        if *secrets != before {
+            write_secrets_file(&self.file_path, &key, secrets)?;
+        }
+        Ok(result)
+    }
+
+    fn file_key(&self) -> Result<[u8; 32], WebxError> {
+        match std::fs::read(&self.key_path) {
+            Ok(bytes) => bytes
+                .try_into()
+                .map_err(|_| WebxError::Crypto("The secrets key file is damaged".to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = PasswordEncryption::generate_salt();
+                write_atomic(&self.key_path, &key)?;
+                restrict_to_owner(&self.key_path)?;
+                Ok(key)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl From<keyring::Error> for WebxError {
+    fn from(e: keyring::Error) -> Self {
+        WebxError::Storage(format!("Keyring: {}", e))
+    }
+}
+
+// Private helper functions
+
+/// Whether the keyring answers a lookup; a missing entry counts as an answer
+fn keyring_available(service: &str) -> bool {
+    match keyring::Entry::new(service, "availability-check").and_then(|entry| entry.get_password()) {
+        Ok(_) | Err(keyring::Error::NoEntry) => true,
+        Err(e) => {
+            tracing::debug!("Keyring unavailable: {}", e);
+            false
+        }
+    }
+}
+
+/// Secrets file: name to base64 of nonce followed by ciphertext
+fn read_secrets_file(path: &Path, key: &[u8; 32]) -> Result<BTreeMap<String, String>, WebxError> {
+    let encoded: BTreeMap<String, String> = match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let engine = base64::engine::general_purpose::STANDARD;
+    encoded
+        .into_iter()
+        .map(|(name, value)| {
+            let sealed = engine.decode(value)?;
+            if sealed.len() < 12 {
+                return Err(WebxError::Crypto(format!("Secret {} is damaged", name)));
+            }
+            let (nonce, ciphertext) = sealed.split_at(12);
+            let nonce: [u8; 12] = nonce.try_into().expect("split at 12");
+            let secret = PasswordEncryption::decrypt_password(ciphertext, key, &nonce)?;
+            Ok((name, secret))
+        })
+        .collect()
+}
+
+fn write_secrets_file(path: &Path, key: &[u8; 32], secrets: &BTreeMap<String, String>) -> Result<(), WebxError> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut encoded = BTreeMap::new();
+    for (name, secret) in secrets {
+        let nonce = PasswordEncryption::generate_iv();
+        let mut sealed = nonce.to_vec();
+        sealed.extend(PasswordEncryption::encrypt_password(secret, key, &nonce)?);
+        encoded.insert(name.clone(), engine.encode(sealed));
+    }
+    write_atomic(path, &serde_json::to_vec_pretty(&encoded)?)?;
+    restrict_to_owner(path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SecretStore::with_backend(Some(temp_dir.path().to_path_buf()), SecretBackend::EncryptedFile).unwrap();
+        store.set("proxy/custom/office", "hunter2").unwrap();
+        let key = store.get_or_create_key(VAULT_KEY_SECRET).unwrap();
+
+        // Nothing is readable on disk
+        let on_disk = std::fs::read_to_string(temp_dir.path().join("secrets.json")).unwrap();
+        assert!(!on_disk.contains("hunter2"));
+
+        let reopened = SecretStore::with_backend(Some(temp_dir.path().to_path_buf()), SecretBackend::EncryptedFile).unwrap();
+        assert_eq!(reopened.get("proxy/custom/office").unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(reopened.get_or_create_key(VAULT_KEY_SECRET).unwrap(), key);
+        assert!(reopened.delete("proxy/custom/office").unwrap());
+        assert!(!reopened.delete("proxy/custom/office").unwrap());
+        assert_eq!(reopened.get("proxy/custom/office").unwrap(), None);
+    }
+}
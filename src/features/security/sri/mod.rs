@@ -0,0 +1,336 @@
+// Subresource Integrity
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::web_inspector::ResourceType;
+use crate::utils::{host_in_domain, site_domain, LockExt};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+/// Failed URLs remembered per site
+const MAX_SITE_FAILED_URLS: usize = 20;
+
+/// Hash functions an integrity attribute may name, weakest first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntegrityAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl IntegrityAlgorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(IntegrityAlgorithm::Sha256),
+            "sha384" => Some(IntegrityAlgorithm::Sha384),
+            "sha512" => Some(IntegrityAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Base64 digest of a body, as integrity attributes write it
+    pub fn digest(&self, body: &[u8]) -> String {
+        match self {
+            IntegrityAlgorithm::Sha256 => STANDARD.encode(Sha256::digest(body)),
+            IntegrityAlgorithm::Sha384 => STANDARD.encode(Sha384::digest(body)),
+            IntegrityAlgorithm::Sha512 => STANDARD.encode(Sha512::digest(body)),
+        }
+    }
+}
+
+/// The hashes of an `integrity` attribute a body may match. Only the
+/// strongest algorithm listed counts, and unknown ones are ignored, as the
+/// specification asks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityMetadata {
+    pub algorithm: IntegrityAlgorithm,
+    pub digests: Vec<String>,
+}
+
+impl IntegrityMetadata {
+    /// Parse an `integrity` attribute; `None` when it names no known hash
+    pub fn parse(attribute: &str) -> Option<Self> {
+        let hashes: Vec<(IntegrityAlgorithm, String)> = attribute
+            .split_whitespace()
+            .filter_map(|token| {
+                let (algorithm, rest) = token.split_once('-')?;
+                // Anything after '?' is an option, which no hash uses yet
+                let digest = rest.split('?').next().unwrap_or(rest);
+                Some((IntegrityAlgorithm::parse(&algorithm.to_ascii_lowercase())?, digest.to_string()))
+            })
+            .collect();
+        let algorithm = hashes.iter().map(|(algorithm, _)| *algorithm).max()?;
+        Some(Self {
+            algorithm,
+            digests: hashes
+                .into_iter()
+                .filter(|(other, _)| *other == algorithm)
+                .map(|(_, digest)| digest)
+                .collect(),
+        })
+    }
+
+    pub fn matches(&self, body: &[u8]) -> bool {
+        let digest = self.algorithm.digest(body);
+        self.digests.contains(&digest)
+    }
+}
+
+/// Why a subresource failed its integrity check
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SriFailureReason {
+    /// The body hashes to none of the listed digests
+    Mismatch,
+    /// A third-party script without an integrity attribute, in strict mode
+    MissingIntegrity,
+}
+
+/// A script or stylesheet that was not run for failing its check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SriFailure {
+    pub tab_id: usize,
+    pub page_url: String,
+    pub url: String,
+    pub resource_type: ResourceType,
+    pub reason: SriFailureReason,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What to do with a fetched subresource
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SriVerdict {
+    Allow,
+    Block(SriFailureReason),
+}
+
+/// Integrity checks aggregated for a site across tabs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteSriStats {
+    pub site: String,
+    pub verified: u64,
+    pub mismatched: u64,
+    pub missing: u64,
+    pub failed_urls: Vec<String>,
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+/// Integrity events
+#[derive(Debug, Clone)]
+pub enum SriEvent {
+    /// A subresource was blocked for failing its check
+    Failed(SriFailure),
+}
+
+/// Checks scripts and stylesheets against their `integrity` attributes
+/// before they run, and in strict mode refuses third-party scripts that
+/// come without one
+pub struct SriChecker {
+    strict: Mutex<bool>,
+    sites: Mutex<HashMap<String, SiteSriStats>>,
+    tx: mpsc::UnboundedSender<SriEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<SriEvent>>>,
+}
+
+impl SriChecker {
+    /// Create new integrity checker, checking attributes where pages give them
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            strict: Mutex::new(false),
+            sites: Mutex::new(HashMap::new()),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Check a fetched subresource of a tab's page against the `integrity`
+    /// attribute of the element that asked for it
+    pub fn check(
+        &self,
+        tab_id: usize,
+        page_url: &str,
+        url: &str,
+        resource_type: ResourceType,
+        integrity: Option<&str>,
+        body: &[u8],
+    ) -> SriVerdict {
+        if !matches!(resource_type, ResourceType::Script | ResourceType::Stylesheet) {
+            return SriVerdict::Allow;
+        }
+        let site = site_domain(page_url).unwrap_or_default();
+        let reason = match integrity.and_then(IntegrityMetadata::parse) {
+            Some(metadata) if metadata.matches(body) => {
+                self.update_site(&site, |stats| stats.verified += 1);
+                return SriVerdict::Allow;
+            }
+            Some(_) => SriFailureReason::Mismatch,
+            None if resource_type == ResourceType::Script && self.is_strict() && is_third_party(&site, url) => {
+                SriFailureReason::MissingIntegrity
+            }
+            None => return SriVerdict::Allow,
+        };
+
+        let failure = SriFailure {
+            tab_id,
+            page_url: page_url.to_string(),
+            url: url.to_string(),
+            resource_type,
+            reason,
+            timestamp: Utc::now(),
+        };
+        self.update_site(&site, |stats| {
+            match reason {
+                SriFailureReason::Mismatch => stats.mismatched += 1,
+                SriFailureReason::MissingIntegrity => stats.missing += 1,
+            }
+            if stats.failed_urls.len() < MAX_SITE_FAILED_URLS && !stats.failed_urls.contains(&failure.url) {
+                stats.failed_urls.push(failure.url.clone());
+            }
+            stats.last_failure = Some(failure.timestamp);
+        });
+        tracing::warn!("Blocked {} on {}: {:?}", url, page_url, reason);
+        let _ = self.tx.send(SriEvent::Failed(failure));
+        SriVerdict::Block(reason)
+    }
+
+    /// Whether third-party scripts need an integrity attribute
+    pub fn is_strict(&self) -> bool {
+        *self.strict.lock_or_recover()
+    }
+
+    pub fn set_strict(&self, strict: bool) {
+        *self.strict.lock_or_recover() = strict;
+    }
+
+    /// Get aggregated stats for a site
+    pub fn get_site_stats(&self, url: &str) -> Option<SiteSriStats> {
+        self.sites.lock_or_recover().get(&site_domain(url)?).cloned()
+    }
+
+    /// Sites with the most failures first
+    pub fn list_sites(&self) -> Vec<SiteSriStats> {
+        let mut sites: Vec<SiteSriStats> = self.sites.lock_or_recover().values().cloned().collect();
+        sites.sort_by(|a, b| {
+            (b.mismatched + b.missing)
+                .cmp(&(a.mismatched + a.missing))
+                .then(a.site.cmp(&b.site))
+        });
+        sites
+    }
+
+    /// Clear aggregated site stats
+    pub fn clear_site_stats(&self) {
+        self.sites.lock_or_recover().clear();
+    }
+
+    /// Subscribe to integrity failures
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<SriEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn update_site(&self, site: &str, apply: impl FnOnce(&mut SiteSriStats)) {
+        let mut sites = self.sites.lock_or_recover();
+        let stats = sites.entry(site.to_string()).or_insert_with(|| SiteSriStats {
+            site: site.to_string(),
+            verified: 0,
+            mismatched: 0,
+            missing: 0,
+            failed_urls: Vec::new(),
+            last_failure: None,
+        });
+        apply(stats);
+    }
+}
+
+impl Default for SriChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsProvider for SriChecker {
+    fn module(&self) -> &str {
+        "sri"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::toggle("sri.strict", "Require integrity for third-party scripts", false)
+            .with_description("Scripts from other sites only run when the page pins them with an integrity hash")
+            .with_category(SettingCategory::Security)]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "sri.strict" => {
+                self.set_strict(value.as_bool().ok_or("Expected a toggle value")?);
+                Ok(())
+            }
+            _ => Err(format!("Unknown integrity setting {}", key).into()),
+        }
+    }
+}
+
+// Private helper functions
+
+/// Whether a subresource comes from outside the page's site
+fn is_third_party(page_site: &str, url: &str) -> bool {
+    let host = url::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string));
+    !host.is_some_and(|host| host_in_domain(&host, page_site))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_checks_and_site_stats() {
+        let checker = SriChecker::new();
+        let mut events = checker.subscribe_events();
+        let page = "https://www.shop.example/cart";
+        let body = b"alert(1)";
+        let integrity = format!(
+            "sha256-{} sha384-{}?ct=application/javascript md5-bogus",
+            IntegrityAlgorithm::Sha256.digest(b"other"),
+            IntegrityAlgorithm::Sha384.digest(body)
+        );
+
+        // Only the strongest listed algorithm counts
+        let metadata = IntegrityMetadata::parse(&integrity).unwrap();
+        assert_eq!(metadata.algorithm, IntegrityAlgorithm::Sha384);
+        assert!(IntegrityMetadata::parse("md5-abc").is_none());
+
+        let cdn = "https://cdn.other.example/lib.js";
+        assert_eq!(checker.check(1, page, cdn, ResourceType::Script, Some(&integrity), body), SriVerdict::Allow);
+        assert_eq!(
+            checker.check(1, page, cdn, ResourceType::Script, Some(&integrity), b"tampered"),
+            SriVerdict::Block(SriFailureReason::Mismatch)
+        );
+        assert!(matches!(events.try_recv(), Ok(SriEvent::Failed(failure)) if failure.url == cdn));
+        assert_eq!(checker.check(1, page, cdn, ResourceType::Image, Some("sha256-x"), body), SriVerdict::Allow);
+
+        // Strict mode wants integrity on third-party scripts only
+        assert_eq!(checker.check(1, page, cdn, ResourceType::Script, None, body), SriVerdict::Allow);
+        checker.apply_setting("sri.strict", &SettingValue::Bool(true)).unwrap();
+        assert_eq!(
+            checker.check(1, page, cdn, ResourceType::Script, None, body),
+            SriVerdict::Block(SriFailureReason::MissingIntegrity)
+        );
+        let own = "https://static.shop.example/app.js";
+        assert_eq!(checker.check(1, page, own, ResourceType::Script, None, body), SriVerdict::Allow);
+        assert_eq!(checker.check(1, page, cdn, ResourceType::Stylesheet, None, body), SriVerdict::Allow);
+
+        let stats = checker.get_site_stats("https://shop.example/").unwrap();
+        assert_eq!((stats.verified, stats.mismatched, stats.missing), (1, 1, 1));
+        assert_eq!(stats.failed_urls, vec![cdn.to_string()]);
+        assert_eq!(checker.list_sites()[0].site, "shop.example");
+        checker.clear_site_stats();
+        assert!(checker.list_sites().is_empty());
+    }
+}
@@ -0,0 +1,535 @@
+// CTAP2 Protocol: CBOR encoding and CTAPHID transport for USB security keys
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use super::{
+    Authenticator, AuthenticatorAttachment, AuthenticatorInfo, AuthenticatorTransport,
+    CredentialAssertionRequest, CredentialCreationRequest, RawAssertion, RawCredential,
+};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const HID_REPORT_SIZE: usize = 64;
+const BROADCAST_CHANNEL: u32 = 0xffff_ffff;
+
+const CTAPHID_INIT: u8 = 0x86;
+const CTAPHID_CBOR: u8 = 0x90;
+const CTAPHID_KEEPALIVE: u8 = 0xbb;
+const CTAPHID_ERROR: u8 = 0xbf;
+
+const CTAP2_MAKE_CREDENTIAL: u8 = 0x01;
+const CTAP2_GET_ASSERTION: u8 = 0x02;
+const CTAP2_GET_INFO: u8 = 0x04;
+
+/// FIDO usage page as it appears in a HID report descriptor (0x06 0xD0 0xF1)
+const FIDO_USAGE_PAGE: [u8; 3] = [0x06, 0xd0, 0xf1];
+
+/// Minimal CBOR value model covering what CTAP2 uses
+#[derive(Debug, Clone, PartialEq)]
+pub enum CborValue {
+    Unsigned(u64),
+    Negative(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CborValue>),
+    Map(Vec<(CborValue, CborValue)>),
+    Bool(bool),
+    Null,
+}
+
+impl CborValue {
+    /// Create an integer value
+    pub fn int(value: i64) -> Self {
+        if value < 0 {
+            CborValue::Negative(value)
+        } else {
+            CborValue::Unsigned(value as u64)
+        }
+    }
+
+    /// Create a text value
+    pub fn text(value: &str) -> Self {
+        CborValue::Text(value.to_string())
+    }
+
+    /// Look up a map entry by integer key
+    pub fn get_int(&self, key: i64) -> Option<&CborValue> {
+        self.get(&CborValue::int(key))
+    }
+
+    /// Look up a map entry by text key
+    pub fn get_text(&self, key: &str) -> Option<&CborValue> {
+        self.get(&CborValue::text(key))
+    }
+
+    /// Get the byte string contents
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            CborValue::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Get the text string contents
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            CborValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Encode to CBOR bytes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    /// Decode one CBOR value from the start of the input
+    pub fn decode(input: &[u8]) -> Result<(CborValue, usize), WebxError> {
+        let mut pos = 0;
+        let value = Self::decode_at(input, &mut pos, 0)?;
+        Ok((value, pos))
+    }
+
+    // Private helper methods
+
+    fn get(&self, key: &CborValue) -> Option<&CborValue> {
+        match self {
+            CborValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            CborValue::Unsigned(n) => Self::encode_head(out, 0, *n),
+            CborValue::Negative(n) => Self::encode_head(out, 1, (-1 - *n) as u64),
+            CborValue::Bytes(bytes) => {
+                Self::encode_head(out, 2, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            CborValue::Text(text) => {
+                Self::encode_head(out, 3, text.len() as u64);
+                out.extend_from_slice(text.as_bytes());
+            }
+            CborValue::Array(items) => {
+                Self::encode_head(out, 4, items.len() as u64);
+                items.iter().for_each(|item| item.encode_into(out));
+            }
+            CborValue::Map(entries) => {
+                Self::encode_head(out, 5, entries.len() as u64);
+                for (key, value) in entries {
+                    key.encode_into(out);
+                    value.encode_into(out);
+                }
+            }
+            CborValue::Bool(false) => out.push(0xf4),
+            CborValue::Bool(true) => out.push(0xf5),
+            CborValue::Null => out.push(0xf6),
+        }
+    }
+
+    fn encode_head(out: &mut Vec<u8>, major: u8, value: u64) {
+        let major = major << 5;
+        if value < 24 {
+            out.push(major | value as u8);
+        } else if value <= u8::MAX as u64 {
+            out.push(major | 24);
+            out.push(value as u8);
+        } else if value <= u16::MAX as u64 {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        } else if value <= u32::MAX as u64 {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        } else {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    fn decode_at(input: &[u8], pos: &mut usize, depth: usize) -> Result<CborValue, WebxError> {
+        if depth > 16 {
+            return Err(WebxError::Parse("CBOR nesting too deep".to_string()));
+        }
+
+        let initial = *input.get(*pos).ok_or("Truncated CBOR")?;
+        *pos += 1;
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+
+        if major == 7 {
+            return match info {
+                20 => Ok(CborValue::Bool(false)),
+                21 => Ok(CborValue::Bool(true)),
+                22 | 23 => Ok(CborValue::Null),
+                _ => Err("Unsupported CBOR simple value".into()),
+            };
+        }
+
+        let argument = match info {
+            0..=23 => info as u64,
+            24..=27 => {
+                let size = 1usize << (info - 24);
+                let bytes = input.get(*pos..*pos + size).ok_or("Truncated CBOR")?;
+                *pos += size;
+                bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+            }
+            _ => return Err("Indefinite-length CBOR is not supported".into()),
+        };
+
+        match major {
+            0 => Ok(CborValue::Unsigned(argument)),
+            1 => Ok(CborValue::Negative(-1 - argument as i64)),
+            2 | 3 => {
+                let len = argument as usize;
+                let bytes = input.get(*pos..*pos + len).ok_or("Truncated CBOR")?.to_vec();
+                *pos += len;
+                if major == 2 {
+                    Ok(CborValue::Bytes(bytes))
+                } else {
+                    Ok(CborValue::Text(String::from_utf8(bytes)?))
+                }
+            }
+            4 => {
+                let mut items = Vec::new();
+                for _ in 0..argument {
+                    items.push(Self::decode_at(input, pos, depth + 1)?);
+                }
+                Ok(CborValue::Array(items))
+            }
+            5 => {
+                let mut entries = Vec::new();
+                for _ in 0..argument {
+                    let key = Self::decode_at(input, pos, depth + 1)?;
+                    let value = Self::decode_at(input, pos, depth + 1)?;
+                    entries.push((key, value));
+                }
+                Ok(CborValue::Map(entries))
+            }
+            _ => Err("Unsupported CBOR major type".into()),
+        }
+    }
+}
+
+/// Split a CTAPHID message into 64-byte packets
+pub fn frame_message(channel: u32, command: u8, payload: &[u8]) -> Vec<[u8; HID_REPORT_SIZE]> {
+    let mut packets = Vec::new();
+
+    let mut init = [0u8; HID_REPORT_SIZE];
+    init[..4].copy_from_slice(&channel.to_be_bytes());
+    init[4] = command;
+    init[5..7].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+    let first = payload.len().min(HID_REPORT_SIZE - 7);
+    init[7..7 + first].copy_from_slice(&payload[..first]);
+    packets.push(init);
+
+    for (seq, chunk) in payload[first..].chunks(HID_REPORT_SIZE - 5).enumerate() {
+        let mut cont = [0u8; HID_REPORT_SIZE];
+        cont[..4].copy_from_slice(&channel.to_be_bytes());
+        cont[4] = seq as u8;
+        cont[5..5 + chunk.len()].copy_from_slice(chunk);
+        packets.push(cont);
+    }
+
+    packets
+}
+
+/// Find FIDO HID devices by inspecting hidraw report descriptors (Linux)
+pub fn discover_hid_devices() -> Vec<PathBuf> {
+    let mut devices = Vec::new();
+    let Ok(entries) = std::fs::read_dir("/sys/class/hidraw") else {
+        return devices;
+    };
+
+    for entry in entries.flatten() {
+        let descriptor_path = entry.path().join("device").join("report_descriptor");
+        if let Ok(descriptor) = std::fs::read(&descriptor_path) {
+            if descriptor.windows(3).any(|w| w == FIDO_USAGE_PAGE) {
+                devices.push(Path::new("/dev").join(entry.file_name()));
+            }
+        }
+    }
+
+    devices.sort();
+    devices
+}
+
+/// A USB security key speaking CTAP2 over HID
+pub struct HidAuthenticator {
+    path: PathBuf,
+    device: Mutex<File>,
+    channel: u32,
+}
+
+impl HidAuthenticator {
+    /// Open a hidraw device and allocate a CTAPHID channel
+    pub fn open(path: &Path) -> Result<Self, WebxError> {
+        let device = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut authenticator = Self {
+            path: path.to_path_buf(),
+            device: Mutex::new(device),
+            channel: BROADCAST_CHANNEL,
+        };
+
+        let nonce: [u8; 8] = rand::random();
+        let response = authenticator.transact(CTAPHID_INIT, &nonce)?;
+        if response.len() < 12 || response[..8] != nonce {
+            return Err("CTAPHID_INIT nonce mismatch".into());
+        }
+        authenticator.channel = u32::from_be_bytes([response[8], response[9], response[10], response[11]]);
+
+        Ok(authenticator)
+    }
+
+    /// Device node of this authenticator
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Send a CTAP2 command and return the decoded response body
+    pub fn ctap2_command(&self, command: u8, params: Option<CborValue>) -> Result<CborValue, WebxError> {
+        let mut payload = vec![command];
+        if let Some(params) = params {
+            payload.extend(params.encode());
+        }
+
+        let response = self.transact(CTAPHID_CBOR, &payload)?;
+        let status = *response.first().ok_or("Empty CTAP2 response")?;
+        if status != 0 {
+            return Err(format!("CTAP2 error 0x{:02x}", status).into());
+        }
+        if response.len() == 1 {
+            return Ok(CborValue::Map(Vec::new()));
+        }
+
+        Ok(CborValue::decode(&response[1..])?.0)
+    }
+
+    // Private helper methods
+
+    fn transact(&self, command: u8, payload: &[u8]) -> Result<Vec<u8>, WebxError> {
+        let mut device = self.device.lock_or_recover();
+
+        for packet in frame_message(self.channel, command, payload) {
+            // hidraw expects a leading report ID byte
+            let mut report = vec![0u8];
+            report.extend_from_slice(&packet);
+            device.write_all(&report)?;
+        }
+
+        loop {
+            let mut packet = [0u8; HID_REPORT_SIZE];
+            device.read_exact(&mut packet)?;
+
+            let channel = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+            if channel != self.channel {
+                continue;
+            }
+
+            match packet[4] {
+                CTAPHID_KEEPALIVE => continue,
+                CTAPHID_ERROR => return Err(format!("CTAPHID error 0x{:02x}", packet[7]).into()),
+                cmd if cmd == command => {
+                    let len = u16::from_be_bytes([packet[5], packet[6]]) as usize;
+                    let mut data = packet[7..].to_vec();
+                    let mut expected_seq = 0u8;
+                    while data.len() < len {
+                        device.read_exact(&mut packet)?;
+                        if packet[4] != expected_seq {
+                            return Err("CTAPHID continuation out of sequence".into());
+                        }
+                        data.extend_from_slice(&packet[5..]);
+                        expected_seq = expected_seq.wrapping_add(1);
+                    }
+                    data.truncate(len);
+                    return Ok(data);
+                }
+                _ => return Err("Unexpected CTAPHID response".into()),
+            }
+        }
+    }
+}
+
+impl Authenticator for HidAuthenticator {
+    fn info(&self) -> AuthenticatorInfo {
+        let aaguid = self
+            .ctap2_command(CTAP2_GET_INFO, None)
+            .ok()
+            .and_then(|info| info.get_int(3).and_then(|v| v.as_bytes()).map(|b| b.to_vec()))
+            .unwrap_or_default();
+
+        AuthenticatorInfo {
+            name: format!("Security key ({})", self.path.display()),
+            aaguid,
+            attachment: AuthenticatorAttachment::CrossPlatform,
+            transport: AuthenticatorTransport::Usb,
+        }
+    }
+
+    fn make_credential(
+        &self,
+        client_data_hash: &[u8; 32],
+        request: &CredentialCreationRequest,
+    ) -> Result<RawCredential, WebxError> {
+        let algorithms = request
+            .algorithms
+            .iter()
+            .map(|alg| {
+                CborValue::Map(vec![
+                    (CborValue::text("alg"), CborValue::int(*alg)),
+                    (CborValue::text("type"), CborValue::text("public-key")),
+                ])
+            })
+            .collect();
+
+        let params = CborValue::Map(vec![
+            (CborValue::int(1), CborValue::Bytes(client_data_hash.to_vec())),
+            (
+                CborValue::int(2),
+                CborValue::Map(vec![
+                    (CborValue::text("id"), CborValue::text(&request.rp_id)),
+                    (CborValue::text("name"), CborValue::text(&request.rp_name)),
+                ]),
+            ),
+            (
+                CborValue::int(3),
+                CborValue::Map(vec![
+                    (CborValue::text("id"), CborValue::Bytes(request.user_id.clone())),
+                    (CborValue::text("name"), CborValue::text(&request.user_name)),
+                    (CborValue::text("displayName"), CborValue::text(&request.user_display_name)),
+                ]),
+            ),
+            (CborValue::int(4), CborValue::Array(algorithms)),
+            (
+                CborValue::int(7),
+                CborValue::Map(vec![(CborValue::text("rk"), CborValue::Bool(request.resident_key))]),
+            ),
+        ]);
+
+        let response = self.ctap2_command(CTAP2_MAKE_CREDENTIAL, Some(params))?;
+        let fmt = response.get_int(1).cloned().ok_or("Missing attestation format")?;
+        let auth_data = response.get_int(2).cloned().ok_or("Missing authenticator data")?;
+        let att_stmt = response.get_int(3).cloned().unwrap_or(CborValue::Map(Vec::new()));
+
+        let credential_id = credential_id_from_auth_data(auth_data.as_bytes().unwrap_or_default())
+            .ok_or("Authenticator data has no attested credential")?;
+
+        // Re-key the CTAP response into a WebAuthn attestation object
+        let attestation_object = CborValue::Map(vec![
+            (CborValue::text("fmt"), fmt),
+            (CborValue::text("attStmt"), att_stmt),
+            (CborValue::text("authData"), auth_data),
+        ])
+        .encode();
+
+        Ok(RawCredential {
+            credential_id,
+            attestation_object,
+        })
+    }
+
+    fn get_assertion(
+        &self,
+        client_data_hash: &[u8; 32],
+        request: &CredentialAssertionRequest,
+    ) -> Result<RawAssertion, WebxError> {
+        let mut params = vec![
+            (CborValue::int(1), CborValue::text(&request.rp_id)),
+            (CborValue::int(2), CborValue::Bytes(client_data_hash.to_vec())),
+        ];
+        if !request.allow_credentials.is_empty() {
+            let allow_list = request
+                .allow_credentials
+                .iter()
+                .map(|id| {
+                    CborValue::Map(vec![
+                        (CborValue::text("id"), CborValue::Bytes(id.clone())),
+                        (CborValue::text("type"), CborValue::text("public-key")),
+                    ])
+                })
+                .collect();
+            params.push((CborValue::int(3), CborValue::Array(allow_list)));
+        }
+
+        let response = self.ctap2_command(CTAP2_GET_ASSERTION, Some(CborValue::Map(params)))?;
+        let credential_id = response
+            .get_int(1)
+            .and_then(|c| c.get_text("id"))
+            .and_then(|id| id.as_bytes())
+            .map(|id| id.to_vec())
+            .or_else(|| request.allow_credentials.first().cloned())
+            .ok_or("Missing credential in assertion")?;
+
+        Ok(RawAssertion {
+            credential_id,
+            authenticator_data: response
+                .get_int(2)
+                .and_then(|v| v.as_bytes())
+                .ok_or("Missing authenticator data")?
+                .to_vec(),
+            signature: response
+                .get_int(3)
+                .and_then(|v| v.as_bytes())
+                .ok_or("Missing signature")?
+                .to_vec(),
+            user_handle: response
+                .get_int(4)
+                .and_then(|u| u.get_text("id"))
+                .and_then(|id| id.as_bytes())
+                .map(|id| id.to_vec()),
+        })
+    }
+}
+
+/// Extract the credential ID from attested credential data in authenticator data
+pub fn credential_id_from_auth_data(auth_data: &[u8]) -> Option<Vec<u8>> {
+    // rpIdHash (32) | flags (1) | signCount (4) | aaguid (16) | idLen (2) | id
+    const ATTESTED_FLAG: u8 = 0x40;
+    let flags = *auth_data.get(32)?;
+    if flags & ATTESTED_FLAG == 0 {
+        return None;
+    }
+    let len = u16::from_be_bytes([*auth_data.get(53)?, *auth_data.get(54)?]) as usize;
+    auth_data.get(55..55 + len).map(|id| id.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let value = CborValue::Map(vec![
+            (CborValue::int(1), CborValue::Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            (CborValue::int(2), CborValue::text("example.com")),
+            (CborValue::int(3), CborValue::Array(vec![CborValue::int(-7), CborValue::int(-257)])),
+            (CborValue::int(4), CborValue::Bool(true)),
+            (CborValue::int(5), CborValue::Unsigned(70_000)),
+        ]);
+
+        let encoded = value.encode();
+        assert_eq!(encoded[0], 0xa5); // map with 5 entries
+        let (decoded, consumed) = CborValue::decode(&encoded).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(decoded.get_int(2).and_then(|v| v.as_text()), Some("example.com"));
+    }
+
+    #[test]
+    fn test_ctaphid_framing() {
+        let payload: Vec<u8> = (0..150u8).collect();
+        let packets = frame_message(0x0102_0304, CTAPHID_CBOR, &payload);
+
+        // 57 bytes in the init packet, then 59 per continuation packet
+        assert_eq!(packets.len(), 3);
+        assert_eq!(&packets[0][..7], &[1, 2, 3, 4, CTAPHID_CBOR, 0, 150]);
+        assert_eq!(packets[0][7], 0);
+        assert_eq!(packets[1][4], 0);
+        assert_eq!(packets[1][5], 57);
+        assert_eq!(packets[2][4], 1);
+        assert_eq!(packets[2][5], 116);
+    }
+}
@@ -0,0 +1,624 @@
+// WebAuthn / FIDO2 Bridge
+pub mod ctap2;
+pub mod platform;
+
+pub use ctap2::{CborValue, HidAuthenticator};
+pub use platform::{PlatformAuthenticator, UserPresence};
+
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::security::password_manager::ui::PasskeyPreview;
+use crate::utils::LockExt;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// COSE algorithm identifier for ES256
+pub const COSE_ALG_ES256: i64 = -7;
+
+/// Page script routing navigator.credentials.create() and get() for
+/// public key credentials to the bridge, as "webauthn-create" and
+/// "webauthn-get" messages settled through `window.webxWebAuthn.settle`.
+/// Other credential types go to the engine.
+pub const WEBAUTHN_SCRIPT: &str = r#"
+(function () {
+    if (!window.ipc || window.webxWebAuthn || !navigator.credentials) return;
+    var pending = {};
+    var next = 1;
+    var engineCreate = navigator.credentials.create.bind(navigator.credentials);
+    var engineGet = navigator.credentials.get.bind(navigator.credentials);
+
+    function call(message) {
+        return new Promise(function (resolve, reject) {
+            var token = next++;
+            pending[token] = { resolve: resolve, reject: reject };
+            message.token = token;
+            window.ipc.send(message);
+        });
+    }
+
+    function bytes(source) {
+        if (typeof source === 'string') return Array.from(new TextEncoder().encode(source));
+        if (source instanceof ArrayBuffer) return Array.from(new Uint8Array(source));
+        return Array.from(new Uint8Array(source.buffer, source.byteOffset, source.byteLength));
+    }
+
+    function buffer(base64url) {
+        var binary = atob(base64url.replace(/-/g, '+').replace(/_/g, '/'));
+        var view = new Uint8Array(binary.length);
+        for (var i = 0; i < binary.length; i++) view[i] = binary.charCodeAt(i);
+        return view.buffer;
+    }
+
+    function credential(id, transport, response) {
+        return {
+            id: id,
+            rawId: buffer(id),
+            type: 'public-key',
+            authenticatorAttachment: transport === 'Internal' ? 'platform' : 'cross-platform',
+            response: response,
+            getClientExtensionResults: function () { return {}; }
+        };
+    }
+
+    navigator.credentials.create = function (options) {
+        if (!options || !options.publicKey) return engineCreate(options);
+        var o = options.publicKey;
+        var selection = o.authenticatorSelection || {};
+        var attachment = { 'platform': 'Platform', 'cross-platform': 'CrossPlatform' }[selection.authenticatorAttachment];
+        return call({
+            type: 'webauthn-create',
+            origin: location.origin,
+            rp_id: (o.rp && o.rp.id) || location.hostname,
+            rp_name: (o.rp && o.rp.name) || location.hostname,
+            user_id: bytes(o.user.id),
+            user_name: o.user.name,
+            user_display_name: o.user.displayName || o.user.name,
+            challenge: bytes(o.challenge),
+            algorithms: (o.pubKeyCredParams || []).map(function (param) { return param.alg; }),
+            resident_key: selection.residentKey === 'required' || selection.residentKey === 'preferred' || !!selection.requireResidentKey,
+            attachment: attachment || null,
+            user_verification: selection.userVerification || 'preferred'
+        }).then(function (created) {
+            return credential(created.credential_id, created.transport, {
+                clientDataJSON: buffer(created.client_data_json),
+                attestationObject: buffer(created.attestation_object),
+                getTransports: function () { return [created.transport.toLowerCase()]; }
+            });
+        });
+    };
+
+    navigator.credentials.get = function (options) {
+        if (!options || !options.publicKey) return engineGet(options);
+        var o = options.publicKey;
+        return call({
+            type: 'webauthn-get',
+            origin: location.origin,
+            rp_id: o.rpId || location.hostname,
+            challenge: bytes(o.challenge),
+            allow_credentials: (o.allowCredentials || []).map(function (allowed) { return bytes(allowed.id); }),
+            user_verification: o.userVerification || 'preferred'
+        }).then(function (assertion) {
+            return credential(assertion.credential_id, assertion.transport, {
+                clientDataJSON: buffer(assertion.client_data_json),
+                authenticatorData: buffer(assertion.authenticator_data),
+                signature: buffer(assertion.signature),
+                userHandle: assertion.user_handle ? buffer(assertion.user_handle) : null
+            });
+        });
+    };
+
+    window.webxWebAuthn = {
+        settle: function (token, error, value) {
+            var request = pending[token];
+            delete pending[token];
+            if (!request) return;
+            if (error) request.reject(new DOMException(error, 'NotAllowedError'));
+            else request.resolve(value);
+        }
+    };
+})();
+"#;
+
+/// How an authenticator is reached
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AuthenticatorTransport {
+    Usb,
+    Nfc,
+    Ble,
+    Internal,
+}
+
+/// Whether an authenticator is built into the device or roaming
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AuthenticatorAttachment {
+    Platform,
+    CrossPlatform,
+}
+
+/// Whether a page wants the user verified, as its options say
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UserVerification {
+    Required,
+    #[default]
+    Preferred,
+    Discouraged,
+}
+
+/// Description of an available authenticator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatorInfo {
+    pub name: String,
+    pub aaguid: Vec<u8>,
+    pub attachment: AuthenticatorAttachment,
+    pub transport: AuthenticatorTransport,
+}
+
+/// navigator.credentials.create() request from a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialCreationRequest {
+    pub origin: String,
+    pub rp_id: String,
+    pub rp_name: String,
+    pub user_id: Vec<u8>,
+    pub user_name: String,
+    pub user_display_name: String,
+    pub challenge: Vec<u8>,
+    pub algorithms: Vec<i64>,
+    pub resident_key: bool,
+    pub attachment: Option<AuthenticatorAttachment>,
+    #[serde(default)]
+    pub user_verification: UserVerification,
+}
+
+/// navigator.credentials.get() request from a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialAssertionRequest {
+    pub origin: String,
+    pub rp_id: String,
+    pub challenge: Vec<u8>,
+    pub allow_credentials: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub user_verification: UserVerification,
+}
+
+/// Credential produced by an authenticator
+#[derive(Debug, Clone)]
+pub struct RawCredential {
+    pub credential_id: Vec<u8>,
+    pub attestation_object: Vec<u8>,
+}
+
+/// Assertion produced by an authenticator
+#[derive(Debug, Clone)]
+pub struct RawAssertion {
+    pub credential_id: Vec<u8>,
+    pub authenticator_data: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub user_handle: Option<Vec<u8>>,
+}
+
+/// Result returned to the page for navigator.credentials.create()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialCreationResponse {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+    pub transport: AuthenticatorTransport,
+}
+
+/// Result returned to the page for navigator.credentials.get()
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionResponse {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    pub user_handle: Option<String>,
+    pub transport: AuthenticatorTransport,
+}
+
+/// A credential registered for a site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteCredential {
+    pub credential_id: String,
+    pub rp_id: String,
+    pub user_name: String,
+    pub user_display_name: String,
+    pub authenticator: String,
+    pub transport: AuthenticatorTransport,
+    pub created_at: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// Authenticator capable of creating credentials and signing assertions
+pub trait Authenticator: Send + Sync {
+    /// Describe this authenticator
+    fn info(&self) -> AuthenticatorInfo;
+
+    /// Create a new credential
+    fn make_credential(
+        &self,
+        client_data_hash: &[u8; 32],
+        request: &CredentialCreationRequest,
+    ) -> Result<RawCredential, WebxError>;
+
+    /// Sign an assertion with an existing credential
+    fn get_assertion(
+        &self,
+        client_data_hash: &[u8; 32],
+        request: &CredentialAssertionRequest,
+    ) -> Result<RawAssertion, WebxError>;
+}
+
+/// Bridges page WebAuthn requests to platform and USB authenticators
+pub struct WebAuthnBridge {
+    authenticators: Arc<Mutex<Vec<Arc<dyn Authenticator>>>>,
+    credentials: Arc<Mutex<Vec<SiteCredential>>>,
+    store_path: PathBuf,
+}
+
+impl WebAuthnBridge {
+    /// Create new WebAuthn bridge
+    pub fn new(data_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let data_dir = data_dir.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&data_dir)?;
+
+        let bridge = Self {
+            authenticators: Arc::new(Mutex::new(Vec::new())),
+            credentials: Arc::new(Mutex::new(Vec::new())),
+            store_path: data_dir.join("webauthn_credentials.json"),
+        };
+
+        bridge.load_credentials()?;
+
+        Ok(bridge)
+    }
+
+    /// Register an authenticator (e.g. a platform authenticator)
+    pub fn register_authenticator(&self, authenticator: Arc<dyn Authenticator>) {
+        self.authenticators.lock_or_recover().push(authenticator);
+    }
+
+    /// Detect and register connected USB security keys
+    pub fn discover_usb_authenticators(&self) -> usize {
+        let mut found = 0;
+        for path in ctap2::discover_hid_devices() {
+            match HidAuthenticator::open(&path) {
+                Ok(authenticator) => {
+                    self.register_authenticator(Arc::new(authenticator));
+                    found += 1;
+                }
+                Err(e) => tracing::warn!("Failed to open security key {}: {}", path.display(), e),
+            }
+        }
+        found
+    }
+
+    /// List available authenticators
+    pub fn list_authenticators(&self) -> Vec<AuthenticatorInfo> {
+        self.authenticators
+            .lock_or_recover()
+            .iter()
+            .map(|a| a.info())
+            .collect()
+    }
+
+    /// Check that a page origin may act for the given relying party ID
+    pub fn validate_origin(origin: &str, rp_id: &str) -> Result<(), WebxError> {
+        let url = url::Url::parse(origin)?;
+        let host = url.host_str().ok_or("Origin has no host")?.to_lowercase();
+        let rp_id = rp_id.to_lowercase();
+
+        let is_localhost = host == "localhost" || host.ends_with(".localhost");
+        if url.scheme() != "https" && !(url.scheme() == "http" && is_localhost) {
+            return Err("WebAuthn requires a secure origin".into());
+        }
+
+        if rp_id.is_empty() || (!rp_id.contains('.') && rp_id != "localhost") {
+            return Err(format!("Invalid relying party ID: {}", rp_id).into());
+        }
+
+        if host != rp_id && !host.ends_with(&format!(".{}", rp_id)) {
+            return Err(format!("Origin {} is not allowed to use RP ID {}", origin, rp_id).into());
+        }
+
+        Ok(())
+    }
+
+    /// Handle navigator.credentials.create()
+    pub fn create_credential(
+        &self,
+        request: &CredentialCreationRequest,
+    ) -> Result<CredentialCreationResponse, WebxError> {
+        Self::validate_origin(&request.origin, &request.rp_id)?;
+        if !request.algorithms.contains(&COSE_ALG_ES256) {
+            return Err("No supported public key algorithm requested".into());
+        }
+
+        let authenticator = self
+            .select_authenticator(request.attachment)
+            .ok_or("No suitable authenticator available")?;
+        let info = authenticator.info();
+
+        let client_data_json = Self::client_data_json("webauthn.create", &request.challenge, &request.origin);
+        let client_data_hash: [u8; 32] = Sha256::digest(client_data_json.as_bytes()).into();
+
+        let credential = authenticator.make_credential(&client_data_hash, request)?;
+        let credential_id = URL_SAFE_NO_PAD.encode(&credential.credential_id);
+
+        self.credentials.lock_or_recover().push(SiteCredential {
+            credential_id: credential_id.clone(),
+            rp_id: request.rp_id.to_lowercase(),
+            user_name: request.user_name.clone(),
+            user_display_name: request.user_display_name.clone(),
+            authenticator: info.name,
+            transport: info.transport,
+            created_at: Utc::now(),
+            last_used: None,
+        });
+        self.save_credentials()?;
+
+        Ok(CredentialCreationResponse {
+            credential_id,
+            client_data_json: URL_SAFE_NO_PAD.encode(client_data_json.as_bytes()),
+            attestation_object: URL_SAFE_NO_PAD.encode(&credential.attestation_object),
+            transport: info.transport,
+        })
+    }
+
+    /// Handle navigator.credentials.get()
+    pub fn get_assertion(
+        &self,
+        request: &CredentialAssertionRequest,
+    ) -> Result<AssertionResponse, WebxError> {
+        Self::validate_origin(&request.origin, &request.rp_id)?;
+
+        let client_data_json = Self::client_data_json("webauthn.get", &request.challenge, &request.origin);
+        let client_data_hash: [u8; 32] = Sha256::digest(client_data_json.as_bytes()).into();
+
+        let authenticators: Vec<Arc<dyn Authenticator>> = self.authenticators.lock_or_recover().clone();
+        if authenticators.is_empty() {
+            return Err("No authenticator available".into());
+        }
+
+        let mut last_error: WebxError = "No authenticator produced an assertion".into();
+        for authenticator in authenticators {
+            let transport = authenticator.info().transport;
+            match authenticator.get_assertion(&client_data_hash, request) {
+                Ok(assertion) => {
+                    let credential_id = URL_SAFE_NO_PAD.encode(&assertion.credential_id);
+                    self.mark_used(&credential_id)?;
+
+                    return Ok(AssertionResponse {
+                        credential_id,
+                        client_data_json: URL_SAFE_NO_PAD.encode(client_data_json.as_bytes()),
+                        authenticator_data: URL_SAFE_NO_PAD.encode(&assertion.authenticator_data),
+                        signature: URL_SAFE_NO_PAD.encode(&assertion.signature),
+                        user_handle: assertion.user_handle.map(|h| URL_SAFE_NO_PAD.encode(h)),
+                        transport,
+                    });
+                }
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// List credentials registered for a site
+    pub fn credentials_for_site(&self, rp_id: &str) -> Vec<SiteCredential> {
+        let rp_id = rp_id.to_lowercase();
+        self.credentials
+            .lock_or_recover()
+            .iter()
+            .filter(|c| c.rp_id == rp_id)
+            .cloned()
+            .collect()
+    }
+
+    /// List all registered credentials
+    pub fn list_credentials(&self) -> Vec<SiteCredential> {
+        self.credentials.lock_or_recover().clone()
+    }
+
+    /// Forget a credential
+    pub fn remove_credential(&self, credential_id: &str) -> Result<bool, WebxError> {
+        let removed = {
+            let mut credentials = self.credentials.lock_or_recover();
+            let len_before = credentials.len();
+            credentials.retain(|c| c.credential_id != credential_id);
+            credentials.len() != len_before
+        };
+        if removed {
+            self.save_credentials()?;
+        }
+        Ok(removed)
+    }
+
+    /// Passkey entries for the password manager's entry view
+    pub fn passkey_previews(&self) -> Vec<PasskeyPreview> {
+        self.credentials
+            .lock_or_recover()
+            .iter()
+            .map(|c| PasskeyPreview {
+                credential_id: c.credential_id.clone(),
+                website: c.rp_id.clone(),
+                username: c.user_name.clone(),
+                authenticator: c.authenticator.clone(),
+                last_used: c.last_used.map(|t| t.to_rfc3339()),
+            })
+            .collect()
+    }
+
+    // Private helper methods
+
+    fn select_authenticator(&self, attachment: Option<AuthenticatorAttachment>) -> Option<Arc<dyn Authenticator>> {
+        let authenticators = self.authenticators.lock_or_recover();
+        authenticators
+            .iter()
+            .find(|a| attachment.is_none_or(|wanted| a.info().attachment == wanted))
+            .cloned()
+    }
+
+    fn client_data_json(kind: &str, challenge: &[u8], origin: &str) -> String {
+        serde_json::json!({
+            "type": kind,
+            "challenge": URL_SAFE_NO_PAD.encode(challenge),
+            "origin": origin,
+            "crossOrigin": false,
+        })
+        .to_string()
+    }
+
+    fn mark_used(&self, credential_id: &str) -> Result<(), WebxError> {
+        {
+            let mut credentials = self.credentials.lock_or_recover();
+            if let Some(credential) = credentials.iter_mut().find(|c| c.credential_id == credential_id) {
+                credential.last_used = Some(Utc::now());
+            }
+        }
+        self.save_credentials()
+    }
+
+    fn save_credentials(&self) -> Result<(), WebxError> {
+        let credentials = self.credentials.lock_or_recover();
+        // No backups: old copies of the keys should not pile up
+        write_atomic(&self.store_path, &serde_json::to_vec_pretty(&*credentials)?)?;
+        Ok(())
+    }
+
+    fn load_credentials(&self) -> Result<(), WebxError> {
+        if self.store_path.exists() {
+            let content = std::fs::read_to_string(&self.store_path)?;
+            *self.credentials.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Script settling the page's promise for a `WEBAUTHN_SCRIPT` request
+pub fn webauthn_settle_script(token: u64, result: Result<serde_json::Value, WebxError>) -> String {
+    match result {
+        Ok(value) => format!("window.webxWebAuthn && window.webxWebAuthn.settle({}, null, {});", token, value),
+        Err(e) => format!(
+            "window.webxWebAuthn && window.webxWebAuthn.settle({}, {}, null);",
+            token,
+            serde_json::Value::String(e.to_string())
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    struct FakeAuthenticator;
+
+    impl Authenticator for FakeAuthenticator {
+        fn info(&self) -> AuthenticatorInfo {
+            AuthenticatorInfo {
+                name: "Fake key".to_string(),
+                aaguid: vec![0; 16],
+                attachment: AuthenticatorAttachment::CrossPlatform,
+                transport: AuthenticatorTransport::Usb,
+            }
+        }
+
+        fn make_credential(
+            &self,
+            _client_data_hash: &[u8; 32],
+            _request: &CredentialCreationRequest,
+        ) -> Result<RawCredential, WebxError> {
+            Ok(RawCredential {
+                credential_id: vec![1, 2, 3, 4],
+                attestation_object: vec![0xa0],
+            })
+        }
+
+        fn get_assertion(
+            &self,
+            _client_data_hash: &[u8; 32],
+            _request: &CredentialAssertionRequest,
+        ) -> Result<RawAssertion, WebxError> {
+            Ok(RawAssertion {
+                credential_id: vec![1, 2, 3, 4],
+                authenticator_data: vec![0; 37],
+                signature: vec![9; 8],
+                user_handle: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_origin_validation() {
+        assert!(WebAuthnBridge::validate_origin("https://login.example.com", "example.com").is_ok());
+        assert!(WebAuthnBridge::validate_origin("https://example.com", "example.com").is_ok());
+        assert!(WebAuthnBridge::validate_origin("http://localhost:8080", "localhost").is_ok());
+
+        assert!(WebAuthnBridge::validate_origin("http://example.com", "example.com").is_err());
+        assert!(WebAuthnBridge::validate_origin("https://evil-example.com", "example.com").is_err());
+        assert!(WebAuthnBridge::validate_origin("https://example.com", "com").is_err());
+        assert!(WebAuthnBridge::validate_origin("https://example.com", "login.example.com").is_err());
+    }
+
+    #[test]
+    fn test_credential_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let bridge = WebAuthnBridge::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        bridge.register_authenticator(Arc::new(FakeAuthenticator));
+
+        let request = CredentialCreationRequest {
+            origin: "https://accounts.example.com".to_string(),
+            rp_id: "example.com".to_string(),
+            rp_name: "Example".to_string(),
+            user_id: vec![42],
+            user_name: "alice".to_string(),
+            user_display_name: "Alice".to_string(),
+            challenge: vec![7; 16],
+            algorithms: vec![COSE_ALG_ES256],
+            resident_key: true,
+            attachment: None,
+            user_verification: UserVerification::Preferred,
+        };
+        let created = bridge.create_credential(&request).unwrap();
+        assert_eq!(created.credential_id, "AQIDBA");
+
+        let client_data = URL_SAFE_NO_PAD.decode(&created.client_data_json).unwrap();
+        let client_data: serde_json::Value = serde_json::from_slice(&client_data).unwrap();
+        assert_eq!(client_data["type"], "webauthn.create");
+        assert_eq!(client_data["origin"], "https://accounts.example.com");
+
+        let assertion = bridge
+            .get_assertion(&CredentialAssertionRequest {
+                origin: "https://example.com".to_string(),
+                rp_id: "example.com".to_string(),
+                challenge: vec![8; 16],
+                allow_credentials: vec![vec![1, 2, 3, 4]],
+                user_verification: UserVerification::Discouraged,
+            })
+            .unwrap();
+        assert_eq!(assertion.credential_id, created.credential_id);
+
+        // Credentials persist and show up in the password manager view
+        let bridge = WebAuthnBridge::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let credentials = bridge.credentials_for_site("example.com");
+        assert_eq!(credentials.len(), 1);
+        assert!(credentials[0].last_used.is_some());
+        assert_eq!(bridge.passkey_previews()[0].username, "alice");
+
+        assert!(bridge.remove_credential("AQIDBA").unwrap());
+        assert!(bridge.list_credentials().is_empty());
+    }
+}
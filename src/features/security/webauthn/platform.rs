@@ -0,0 +1,340 @@
+// Platform Authenticator
+use super::{
+    Authenticator, AuthenticatorAttachment, AuthenticatorInfo, AuthenticatorTransport, CborValue,
+    CredentialAssertionRequest, CredentialCreationRequest, RawAssertion, RawCredential, UserVerification,
+    COSE_ALG_ES256,
+};
+use crate::error::WebxError;
+use crate::features::security::password_manager::{PasswordManager, StoredPasskey};
+use crate::utils::LockExt;
+use chrono::Utc;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::SecretKey;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// AAGUID identifying WebX's software authenticator
+pub const PLATFORM_AAGUID: [u8; 16] = [
+    0x9c, 0x3e, 0x5a, 0x0d, 0x41, 0x7b, 0x4f, 0x2e, 0xa6, 0x18, 0x53, 0xd1, 0x0e, 0x7f, 0xb2, 0x64,
+];
+
+/// How long a master password check counts as user verification
+const VERIFICATION_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+// Authenticator data flags
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED: u8 = 0x40;
+
+/// Asks the user, outside any page, before a passkey is created or used.
+/// Calls block until the user answers.
+pub trait UserPresence: Send + Sync {
+    /// Whether the user agrees
+    fn confirm(&self, message: &str) -> bool;
+    /// The master password the user entered, or None if they declined
+    fn master_password(&self, message: &str) -> Option<String>;
+}
+
+/// Software authenticator keeping passkeys in the encrypted password
+/// vault. Every credential is stored, so all of them can be discoverable;
+/// the user confirms each use, and is verified by entering the vault's
+/// master password.
+pub struct PlatformAuthenticator {
+    vault: Arc<PasswordManager>,
+    presence: Arc<dyn UserPresence>,
+    verified_until: Mutex<Option<Instant>>,
+}
+
+impl PlatformAuthenticator {
+    pub fn new(vault: Arc<PasswordManager>, presence: Arc<dyn UserPresence>) -> Self {
+        Self {
+            vault,
+            presence,
+            verified_until: Mutex::new(None),
+        }
+    }
+
+    /// Verify the user with the vault's master password. Passkeys are then
+    /// used with user verification for a few minutes.
+    pub fn verify_user(&self, master_password: &str) -> Result<bool, WebxError> {
+        let verified = self.vault.verify_master_password(master_password)?;
+        if verified {
+            *self.verified_until.lock_or_recover() = Some(Instant::now() + VERIFICATION_WINDOW);
+        }
+        Ok(verified)
+    }
+
+    /// End user verification before its time runs out
+    pub fn forget_verification(&self) {
+        *self.verified_until.lock_or_recover() = None;
+    }
+
+    /// Check if the user was verified recently
+    pub fn is_user_verified(&self) -> bool {
+        self.verified_until
+            .lock_or_recover()
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    // Private helper methods
+
+    /// Ask the user before a passkey is created or used, and return the
+    /// authenticator data flags their answer earns. Verification the site
+    /// requires is asked for as the master password, which also shows the
+    /// user is present.
+    fn ask_user(&self, message: &str, requirement: UserVerification) -> Result<u8, WebxError> {
+        if requirement == UserVerification::Required && !self.is_user_verified() {
+            let password = self
+                .presence
+                .master_password(message)
+                .ok_or_else(|| WebxError::Invalid("The passkey request was declined".to_string()))?;
+            if !self.verify_user(&password)? {
+                return Err(WebxError::Invalid("Wrong master password".to_string()));
+            }
+        } else if !self.presence.confirm(message) {
+            return Err(WebxError::Invalid("The passkey request was declined".to_string()));
+        }
+        match (requirement, self.is_user_verified()) {
+            (UserVerification::Discouraged, _) | (_, false) => Ok(FLAG_USER_PRESENT),
+            (_, true) => Ok(FLAG_USER_PRESENT | FLAG_USER_VERIFIED),
+        }
+    }
+}
+
+impl Authenticator for PlatformAuthenticator {
+    fn info(&self) -> AuthenticatorInfo {
+        AuthenticatorInfo {
+            name: "WebX passkeys".to_string(),
+            aaguid: PLATFORM_AAGUID.to_vec(),
+            attachment: AuthenticatorAttachment::Platform,
+            transport: AuthenticatorTransport::Internal,
+        }
+    }
+
+    fn make_credential(
+        &self,
+        _client_data_hash: &[u8; 32],
+        request: &CredentialCreationRequest,
+    ) -> Result<RawCredential, WebxError> {
+        if !request.algorithms.contains(&COSE_ALG_ES256) {
+            return Err("The passkey authenticator only supports ES256".into());
+        }
+        let message = format!("Create a passkey for {} on {}?", request.user_name, request.rp_id);
+        let flags = self.ask_user(&message, request.user_verification)? | FLAG_ATTESTED;
+
+        let secret_key = SecretKey::random(&mut OsRng);
+        let mut credential_id = vec![0u8; 16];
+        OsRng.fill_bytes(&mut credential_id);
+
+        // Attested credential data: AAGUID, ID length, ID, COSE public key
+        let point = secret_key.public_key().to_encoded_point(false);
+        let cose_key = CborValue::Map(vec![
+            (CborValue::int(1), CborValue::int(2)),
+            (CborValue::int(3), CborValue::int(COSE_ALG_ES256)),
+            (CborValue::int(-1), CborValue::int(1)),
+            (CborValue::int(-2), CborValue::Bytes(point.x().ok_or("Invalid public key")?.to_vec())),
+            (CborValue::int(-3), CborValue::Bytes(point.y().ok_or("Invalid public key")?.to_vec())),
+        ]);
+        let mut auth_data = auth_data(&request.rp_id, flags, 0);
+        auth_data.extend_from_slice(&PLATFORM_AAGUID);
+        auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&credential_id);
+        auth_data.extend_from_slice(&cose_key.encode());
+
+        self.vault.save_passkey(&StoredPasskey {
+            credential_id: credential_id.clone(),
+            rp_id: request.rp_id.to_lowercase(),
+            user_id: request.user_id.clone(),
+            user_name: request.user_name.clone(),
+            user_display_name: request.user_display_name.clone(),
+            private_key: secret_key.to_bytes().to_vec(),
+            sign_count: 0,
+            discoverable: request.resident_key,
+            created_at: Utc::now(),
+            last_used: None,
+        })?;
+
+        let attestation_object = CborValue::Map(vec![
+            (CborValue::text("fmt"), CborValue::text("none")),
+            (CborValue::text("attStmt"), CborValue::Map(Vec::new())),
+            (CborValue::text("authData"), CborValue::Bytes(auth_data)),
+        ])
+        .encode();
+
+        Ok(RawCredential {
+            credential_id,
+            attestation_object,
+        })
+    }
+
+    fn get_assertion(
+        &self,
+        client_data_hash: &[u8; 32],
+        request: &CredentialAssertionRequest,
+    ) -> Result<RawAssertion, WebxError> {
+        // Without an allow list the site lets the user pick a discoverable
+        // passkey; the one used last is taken
+        let mut passkey = self
+            .vault
+            .passkeys(&request.rp_id)?
+            .into_iter()
+            .filter(|passkey| match request.allow_credentials.is_empty() {
+                true => passkey.discoverable,
+                false => request.allow_credentials.contains(&passkey.credential_id),
+            })
+            .max_by_key(|passkey| passkey.last_used.unwrap_or(passkey.created_at))
+            .ok_or_else(|| WebxError::NotFound(format!("Passkey for {}", request.rp_id)))?;
+        let message = format!("Sign in to {} as {} with your passkey?", request.rp_id, passkey.user_name);
+        let flags = self.ask_user(&message, request.user_verification)?;
+
+        passkey.sign_count += 1;
+        passkey.last_used = Some(Utc::now());
+        let authenticator_data = auth_data(&request.rp_id, flags, passkey.sign_count);
+        let secret_key = SecretKey::from_slice(&passkey.private_key)
+            .map_err(|_| WebxError::Crypto("Invalid passkey".to_string()))?;
+        let signature: Signature =
+            SigningKey::from(secret_key).sign(&[authenticator_data.as_slice(), client_data_hash].concat());
+        self.vault.save_passkey(&passkey)?;
+
+        Ok(RawAssertion {
+            credential_id: passkey.credential_id,
+            authenticator_data,
+            signature: signature.to_der().as_bytes().to_vec(),
+            user_handle: Some(passkey.user_id),
+        })
+    }
+}
+
+// Private helper functions
+
+/// Authenticator data up to the signature counter
+fn auth_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+    let mut data = Sha256::digest(rp_id.to_lowercase().as_bytes()).to_vec();
+    data.push(flags);
+    data.extend_from_slice(&sign_count.to_be_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::security::password_manager::VaultUnlock;
+    use crate::features::security::webauthn::WebAuthnBridge;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::VerifyingKey;
+    use p256::EncodedPoint;
+    use tempfile::TempDir;
+
+    /// Answers prompts as a test sets it to
+    #[derive(Default)]
+    struct FakePresence {
+        confirm: Mutex<bool>,
+        password: Mutex<Option<String>>,
+    }
+
+    impl UserPresence for FakePresence {
+        fn confirm(&self, _message: &str) -> bool {
+            *self.confirm.lock_or_recover()
+        }
+
+        fn master_password(&self, _message: &str) -> Option<String> {
+            self.password.lock_or_recover().clone()
+        }
+    }
+
+    #[test]
+    fn test_passkeys_live_in_the_vault_and_sign_assertions() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = Arc::new(
+            PasswordManager::open(
+                Some(temp_dir.path().join("passwords.db")),
+                VaultUnlock::MasterPassword("correct horse"),
+            )
+            .unwrap(),
+        );
+        let presence = Arc::new(FakePresence::default());
+        let platform = Arc::new(PlatformAuthenticator::new(vault.clone(), presence.clone()));
+        let bridge = WebAuthnBridge::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        bridge.register_authenticator(platform.clone());
+
+        let mut request = CredentialCreationRequest {
+            origin: "https://example.com".to_string(),
+            rp_id: "example.com".to_string(),
+            rp_name: "Example".to_string(),
+            user_id: vec![7, 7],
+            user_name: "alice".to_string(),
+            user_display_name: "Alice".to_string(),
+            challenge: vec![1; 16],
+            algorithms: vec![COSE_ALG_ES256],
+            resident_key: true,
+            attachment: Some(AuthenticatorAttachment::Platform),
+            user_verification: UserVerification::Required,
+        };
+        // Nothing is created without the user's answer
+        request.user_verification = UserVerification::Preferred;
+        assert!(bridge.create_credential(&request).is_err());
+        request.user_verification = UserVerification::Required;
+        assert!(bridge.create_credential(&request).is_err());
+        *presence.password.lock_or_recover() = Some("wrong".to_string());
+        assert!(bridge.create_credential(&request).is_err());
+        assert!(!platform.is_user_verified());
+        *presence.password.lock_or_recover() = Some("correct horse".to_string());
+        let created = bridge.create_credential(&request).unwrap();
+        assert!(platform.is_user_verified());
+
+        // Public key from the attestation object's authenticator data
+        let attestation = URL_SAFE_NO_PAD.decode(&created.attestation_object).unwrap();
+        let (attestation, _) = CborValue::decode(&attestation).unwrap();
+        let auth_data = attestation.get_text("authData").and_then(|data| data.as_bytes()).unwrap();
+        assert_eq!(auth_data[32], FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED);
+        let (cose_key, _) = CborValue::decode(&auth_data[55 + 16..]).unwrap();
+        let point = EncodedPoint::from_affine_coordinates(
+            cose_key.get_int(-2).and_then(|x| x.as_bytes()).unwrap().into(),
+            cose_key.get_int(-3).and_then(|y| y.as_bytes()).unwrap().into(),
+            false,
+        );
+        let verifying_key = VerifyingKey::from_encoded_point(&point).unwrap();
+
+        // A discoverable passkey signs in without an allow list, once the
+        // user agrees
+        let sign_in = CredentialAssertionRequest {
+            origin: "https://example.com".to_string(),
+            rp_id: "example.com".to_string(),
+            challenge: vec![2; 16],
+            allow_credentials: Vec::new(),
+            user_verification: UserVerification::Preferred,
+        };
+        assert!(bridge.get_assertion(&sign_in).is_err());
+        *presence.confirm.lock_or_recover() = true;
+        let assertion = bridge.get_assertion(&sign_in).unwrap();
+        assert_eq!(assertion.credential_id, created.credential_id);
+        assert_eq!(assertion.user_handle.as_deref(), Some("Bwc"));
+        let authenticator_data = URL_SAFE_NO_PAD.decode(&assertion.authenticator_data).unwrap();
+        assert_eq!(authenticator_data[33..37], 1u32.to_be_bytes());
+        let client_data = URL_SAFE_NO_PAD.decode(&assertion.client_data_json).unwrap();
+        let signed = [authenticator_data, Sha256::digest(&client_data).to_vec()].concat();
+        let signature = Signature::from_der(&URL_SAFE_NO_PAD.decode(&assertion.signature).unwrap()).unwrap();
+        assert!(verifying_key.verify(&signed, &signature).is_ok());
+
+        // Keys are encrypted with the master password and survive a restart
+        drop(bridge);
+        drop(platform);
+        drop(vault);
+        let vault = PasswordManager::open(
+            Some(temp_dir.path().join("passwords.db")),
+            VaultUnlock::MasterPassword("correct horse"),
+        )
+        .unwrap();
+        let passkeys = vault.passkeys("EXAMPLE.com").unwrap();
+        assert_eq!((passkeys.len(), passkeys[0].sign_count), (1, 1));
+        assert!(vault.delete_passkey(&passkeys[0].credential_id).unwrap());
+        assert!(vault.passkeys("example.com").unwrap().is_empty());
+    }
+}
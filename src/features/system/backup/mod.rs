@@ -0,0 +1,309 @@
+// Profile Backups
+mod profile;
+mod webdav;
+
+pub use profile::{
+    ProfileBackup, ProfileFile, ProfileLocations, ProfileManifest, ProfileRestoreOptions, ProfileRoot, ProfileSection,
+    PRE_RESTORE_SUFFIX, PROFILE_BACKUP_VERSION,
+};
+pub use webdav::{WebDavBackup, WebDavConfig, WebDavSnapshot, WEBDAV_BACKUP_TASK};
+
+use crate::config::storage::write_atomic;
+use crate::config::ConfigManager;
+use crate::core::{Bookmark, BrowserSettings, BrowserState};
+use crate::error::WebxError;
+use crate::features::productivity::reading_list::{ReadingList, ReadingListEntry, ReadingListSort};
+use crate::features::security::password_manager::PasswordEncryption;
+use crate::utils::LockExt;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Version of the archive layout, bumped when older builds could not read it
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Starts every encrypted settings backup, ahead of its salt and nonce
+const BACKUP_MAGIC: &[u8] = b"WEBXBAK1";
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Part of the profile a backup can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupItem {
+    Bookmarks,
+    Settings,
+    Shortcuts,
+    ReadingList,
+}
+
+impl BackupItem {
+    pub const ALL: [BackupItem; 4] = [
+        BackupItem::Bookmarks,
+        BackupItem::Settings,
+        BackupItem::Shortcuts,
+        BackupItem::ReadingList,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupItem::Bookmarks => "bookmarks",
+            BackupItem::Settings => "settings",
+            BackupItem::Shortcuts => "shortcuts",
+            BackupItem::ReadingList => "reading_list",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|item| item.as_str() == name)
+    }
+}
+
+/// What a backup holds, before it is compressed and encrypted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupArchive {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub bookmarks: Option<Vec<Bookmark>>,
+    pub settings: Option<BrowserSettings>,
+    /// The keyboard shortcuts file as it was stored
+    pub shortcuts: Option<serde_json::Value>,
+    pub reading_list: Option<Vec<ReadingListEntry>>,
+}
+
+impl BackupArchive {
+    /// Items the archive holds
+    pub fn items(&self) -> Vec<BackupItem> {
+        BackupItem::ALL
+            .into_iter()
+            .filter(|item| match item {
+                BackupItem::Bookmarks => self.bookmarks.is_some(),
+                BackupItem::Settings => self.settings.is_some(),
+                BackupItem::Shortcuts => self.shortcuts.is_some(),
+                BackupItem::ReadingList => self.reading_list.is_some(),
+            })
+            .collect()
+    }
+}
+
+/// Where backed-up data is read from and restored to
+#[derive(Clone)]
+pub struct BackupSources {
+    state: Arc<Mutex<BrowserState>>,
+    config: Arc<ConfigManager>,
+    reading_list: Option<Arc<ReadingList>>,
+    shortcuts_path: PathBuf,
+}
+
+impl BackupSources {
+    /// Bookmarks and settings come from the live browser state, which is
+    /// saved through `config` on restore
+    pub fn new(state: Arc<Mutex<BrowserState>>, config: Arc<ConfigManager>) -> Self {
+        let mut shortcuts_path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+        shortcuts_path.push("webx");
+        shortcuts_path.push("shortcuts");
+        shortcuts_path.push("shortcuts.json");
+        Self {
+            state,
+            config,
+            reading_list: None,
+            shortcuts_path,
+        }
+    }
+
+    pub fn with_reading_list(mut self, reading_list: Arc<ReadingList>) -> Self {
+        self.reading_list = Some(reading_list);
+        self
+    }
+
+    pub fn with_shortcuts_path(mut self, path: PathBuf) -> Self {
+        self.shortcuts_path = path;
+        self
+    }
+
+    /// Gather the given items. Shortcuts never customized, or a missing
+    /// reading list, are left out.
+    pub fn collect(&self, items: &[BackupItem]) -> Result<BackupArchive, WebxError> {
+        let mut archive = BackupArchive {
+            version: BACKUP_FORMAT_VERSION,
+            created_at: Utc::now(),
+            ..Default::default()
+        };
+        for item in items {
+            match item {
+                BackupItem::Bookmarks => archive.bookmarks = Some(self.state.lock_or_recover().bookmarks.clone()),
+                BackupItem::Settings => archive.settings = Some(self.state.lock_or_recover().settings.clone()),
+                BackupItem::Shortcuts => {
+                    archive.shortcuts = match std::fs::read(&self.shortcuts_path) {
+                        Ok(bytes) => Some(serde_json::from_slice(&bytes)?),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                BackupItem::ReadingList => {
+                    archive.reading_list = self
+                        .reading_list
+                        .as_ref()
+                        .map(|reading_list| reading_list.entries(ReadingListSort::Oldest, false));
+                }
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Put back the given items from an archive, replacing current
+    /// bookmarks, settings and shortcuts. Returns the items restored.
+    /// Managers pick up restored settings and shortcuts on the next start.
+    pub fn restore(&self, archive: &BackupArchive, items: &[BackupItem]) -> Result<Vec<BackupItem>, WebxError> {
+        if archive.version > BACKUP_FORMAT_VERSION {
+            return Err(WebxError::Invalid(format!(
+                "Backup was made by a newer WebX (format {})",
+                archive.version
+            )));
+        }
+        let mut restored = Vec::new();
+        for item in items {
+            let done = match item {
+                BackupItem::Bookmarks => match &archive.bookmarks {
+                    Some(bookmarks) => {
+                        self.config.save_bookmarks(bookmarks)?;
+                        self.state.lock_or_recover().bookmarks = bookmarks.clone();
+                        true
+                    }
+                    None => false,
+                },
+                BackupItem::Settings => match &archive.settings {
+                    Some(settings) => {
+                        self.config.save_settings(settings)?;
+                        self.state.lock_or_recover().settings = settings.clone();
+                        true
+                    }
+                    None => false,
+                },
+                BackupItem::Shortcuts => match &archive.shortcuts {
+                    Some(shortcuts) => {
+                        if let Some(parent) = self.shortcuts_path.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        write_atomic(&self.shortcuts_path, &serde_json::to_vec_pretty(shortcuts)?)?;
+                        true
+                    }
+                    None => false,
+                },
+                BackupItem::ReadingList => match (&archive.reading_list, &self.reading_list) {
+                    (Some(entries), Some(reading_list)) => {
+                        reading_list.restore(entries)?;
+                        true
+                    }
+                    _ => false,
+                },
+            };
+            if done {
+                restored.push(*item);
+            }
+        }
+        Ok(restored)
+    }
+}
+
+/// Compress and encrypt an archive with a key derived from `passphrase`
+pub fn seal_backup(archive: &BackupArchive, passphrase: &str) -> Result<Vec<u8>, WebxError> {
+    seal(BACKUP_MAGIC, &serde_json::to_vec(archive)?, passphrase)
+}
+
+/// Decrypt and read a backup made by `seal_backup`
+pub fn open_backup(sealed: &[u8], passphrase: &str) -> Result<BackupArchive, WebxError> {
+    Ok(serde_json::from_slice(&unseal(BACKUP_MAGIC, sealed, passphrase)?)?)
+}
+
+// Private helper functions
+
+/// Gzip `plain` and encrypt it with AES-256-GCM, behind `magic`, the salt
+/// of the passphrase's key and the nonce
+fn seal(magic: &[u8], plain: &[u8], passphrase: &str) -> Result<Vec<u8>, WebxError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(plain)?;
+    let compressed = encoder.finish()?;
+
+    let salt = PasswordEncryption::generate_salt();
+    let nonce = PasswordEncryption::generate_iv();
+    let key = PasswordEncryption::derive_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| WebxError::Crypto("Invalid key".to_string()))?;
+    let encrypted = cipher
+        .encrypt(Nonce::from_slice(&nonce), compressed.as_slice())
+        .map_err(|_| WebxError::Crypto("Encrypting the backup failed".to_string()))?;
+
+    let mut sealed = Vec::with_capacity(magic.len() + SALT_LEN + NONCE_LEN + encrypted.len());
+    sealed.extend_from_slice(magic);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&encrypted);
+    Ok(sealed)
+}
+
+/// Reverse `seal`. The GCM tag fails on a wrong passphrase or any change
+/// to the sealed bytes.
+fn unseal(magic: &[u8], sealed: &[u8], passphrase: &str) -> Result<Vec<u8>, WebxError> {
+    let rest = sealed
+        .strip_prefix(magic)
+        .filter(|rest| rest.len() > SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| WebxError::Parse("Not a WebX backup".to_string()))?;
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+    let key = PasswordEncryption::derive_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| WebxError::Crypto("Invalid key".to_string()))?;
+    let compressed = cipher
+        .decrypt(Nonce::from_slice(nonce), encrypted)
+        .map_err(|_| WebxError::Crypto("Wrong passphrase or damaged backup".to_string()))?;
+
+    let mut plain = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut plain)?;
+    Ok(plain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backup_round_trip_and_partial_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Arc::new(ConfigManager::with_config_dir(temp_dir.path().join("config")).unwrap());
+        let state = Arc::new(Mutex::new(BrowserState::new()));
+        state.lock_or_recover().add_bookmark("Example".to_string(), "https://example.com/".to_string());
+        state.lock_or_recover().settings.home_page = "https://start.example/".to_string();
+        let shortcuts_path = temp_dir.path().join("shortcuts.json");
+        std::fs::write(&shortcuts_path, r#"[{"keys": "Ctrl+T"}]"#).unwrap();
+        let sources = BackupSources::new(Arc::clone(&state), Arc::clone(&config)).with_shortcuts_path(shortcuts_path.clone());
+
+        let archive = sources.collect(&BackupItem::ALL).unwrap();
+        assert_eq!(archive.items(), vec![BackupItem::Bookmarks, BackupItem::Settings, BackupItem::Shortcuts]);
+        let sealed = seal_backup(&archive, "correct horse").unwrap();
+        assert!(open_backup(&sealed, "wrong").is_err());
+        assert!(open_backup(b"WEBXBAK1short", "correct horse").is_err());
+        let opened = open_backup(&sealed, "correct horse").unwrap();
+
+        // Restoring only bookmarks leaves changed settings and shortcuts alone
+        state.lock_or_recover().bookmarks.clear();
+        state.lock_or_recover().settings.home_page = "https://changed.example/".to_string();
+        std::fs::write(&shortcuts_path, "[]").unwrap();
+        let restored = sources.restore(&opened, &[BackupItem::Bookmarks, BackupItem::ReadingList]).unwrap();
+        assert_eq!(restored, vec![BackupItem::Bookmarks]);
+        assert_eq!(state.lock_or_recover().bookmarks.len(), 1);
+        assert_eq!(config.load_bookmarks().len(), 1);
+        assert_eq!(state.lock_or_recover().settings.home_page, "https://changed.example/");
+
+        sources.restore(&opened, &BackupItem::ALL).unwrap();
+        assert_eq!(state.lock_or_recover().settings.home_page, "https://start.example/");
+        assert!(std::fs::read_to_string(&shortcuts_path).unwrap().contains("Ctrl+T"));
+    }
+}
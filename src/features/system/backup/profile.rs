@@ -0,0 +1,497 @@
+// Whole Profile Backups
+use super::{seal, unseal};
+use crate::config::storage::write_atomic;
+use crate::config::ConfigManager;
+use crate::error::WebxError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Version of the profile archive layout
+pub const PROFILE_BACKUP_VERSION: u32 = 1;
+
+/// Suffix of what a restore replaced, kept beside it until the next restore
+pub const PRE_RESTORE_SUFFIX: &str = ".before-restore";
+
+/// Starts every encrypted profile backup
+const PROFILE_MAGIC: &[u8] = b"WEBXPRF1";
+
+/// Part of the profile that can be backed up and restored on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileSection {
+    /// Browser settings and every feature's config file
+    Config,
+    Bookmarks,
+    /// Session history and the history database
+    History,
+    /// Saved passwords and passkeys, still encrypted with the master password
+    Vault,
+    /// Saved and auto-saved sessions
+    Sessions,
+}
+
+impl ProfileSection {
+    pub const ALL: [ProfileSection; 5] = [
+        ProfileSection::Config,
+        ProfileSection::Bookmarks,
+        ProfileSection::History,
+        ProfileSection::Vault,
+        ProfileSection::Sessions,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileSection::Config => "config",
+            ProfileSection::Bookmarks => "bookmarks",
+            ProfileSection::History => "history",
+            ProfileSection::Vault => "vault",
+            ProfileSection::Sessions => "sessions",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|section| section.as_str() == name)
+    }
+}
+
+/// Directory a backed-up file is relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileRoot {
+    /// Where `ConfigManager` keeps settings, bookmarks and history
+    Profile,
+    /// Where features keep their config files
+    Config,
+    /// Where databases and sessions live
+    Data,
+}
+
+/// Directories a profile lives in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileLocations {
+    pub profile_dir: PathBuf,
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+impl ProfileLocations {
+    /// The default profile's directories, with `profile` in place of
+    /// `ConfigManager`'s directory if given
+    pub fn new(profile: Option<PathBuf>) -> Result<Self, WebxError> {
+        let profile_dir = match profile {
+            Some(profile) => profile,
+            None => ConfigManager::new()?.config_dir().clone(),
+        };
+        let config_dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from(".")).join("webx");
+        let data_dir = dirs::data_dir().unwrap_or_else(|| PathBuf::from(".")).join("webx");
+        Ok(Self {
+            profile_dir,
+            config_dir,
+            data_dir,
+        })
+    }
+
+    pub fn root(&self, root: ProfileRoot) -> &Path {
+        match root {
+            ProfileRoot::Profile => &self.profile_dir,
+            ProfileRoot::Config => &self.config_dir,
+            ProfileRoot::Data => &self.data_dir,
+        }
+    }
+
+    /// Files and directories a section is made of, relative to their root.
+    /// Some may not exist.
+    pub fn section_entries(&self, section: ProfileSection) -> Result<Vec<(ProfileRoot, String)>, WebxError> {
+        let entry = |root, name: &str| (root, name.to_string());
+        Ok(match section {
+            ProfileSection::Bookmarks => vec![entry(ProfileRoot::Profile, "bookmarks.json")],
+            ProfileSection::History => vec![
+                entry(ProfileRoot::Profile, "history.json"),
+                entry(ProfileRoot::Data, "history.db"),
+            ],
+            ProfileSection::Vault => vec![entry(ProfileRoot::Data, "passwords.db")],
+            ProfileSection::Sessions => vec![entry(ProfileRoot::Data, "sessions")],
+            ProfileSection::Config => {
+                // The feature config directory may be the profile directory itself
+                let shared = self.config_dir == self.profile_dir;
+                let mut entries = vec![entry(ProfileRoot::Profile, "settings.json")];
+                let dir = match fs::read_dir(&self.config_dir) {
+                    Ok(dir) => dir,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+                    Err(e) => return Err(e.into()),
+                };
+                for item in dir {
+                    let name = item?.file_name().to_string_lossy().to_string();
+                    let owned = ["settings.json", "bookmarks.json", "history.json"]
+                        .iter()
+                        .any(|owned| name.starts_with(owned));
+                    if (!shared || !owned) && !name.ends_with(PRE_RESTORE_SUFFIX) && !name.ends_with(".tmp") {
+                        entries.push(entry(ProfileRoot::Config, &name));
+                    }
+                }
+                entries.sort_by(|a, b| a.1.cmp(&b.1));
+                entries
+            }
+        })
+    }
+}
+
+/// A file in a profile backup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileFile {
+    pub section: ProfileSection,
+    pub root: ProfileRoot,
+    /// Path below the root, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Hex SHA-256 of the contents, checked before anything is restored
+    pub sha256: String,
+}
+
+/// What a profile backup holds
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileManifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// WebX version that made the backup
+    pub app_version: String,
+    /// Sections asked for, including ones that had no files
+    pub sections: Vec<ProfileSection>,
+    pub files: Vec<ProfileFile>,
+}
+
+impl ProfileManifest {
+    /// Sections with at least one file
+    pub fn stored_sections(&self) -> Vec<ProfileSection> {
+        let mut sections: Vec<ProfileSection> = self.files.iter().map(|file| file.section).collect();
+        sections.sort();
+        sections.dedup();
+        sections
+    }
+}
+
+/// Which parts of a backup to restore
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileRestoreOptions {
+    pub sections: Vec<ProfileSection>,
+    /// Move what a restore replaces aside to `<name>.before-restore`
+    /// instead of deleting it
+    pub keep_previous: bool,
+}
+
+impl Default for ProfileRestoreOptions {
+    fn default() -> Self {
+        Self {
+            sections: ProfileSection::ALL.to_vec(),
+            keep_previous: true,
+        }
+    }
+}
+
+impl ProfileRestoreOptions {
+    /// Restore only the given sections, e.g. bookmarks alone
+    pub fn only(sections: &[ProfileSection]) -> Self {
+        Self {
+            sections: sections.to_vec(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A single encrypted archive of the whole profile: config, bookmarks,
+/// history, the password vault and sessions. Databases are copied as they
+/// are on disk, so back up and restore with the browser closed.
+#[derive(Debug, Clone)]
+pub struct ProfileBackup {
+    manifest: ProfileManifest,
+    /// Contents of each manifest file, in the same order
+    contents: Vec<Vec<u8>>,
+}
+
+impl ProfileBackup {
+    /// Back up the whole default profile to `path`
+    pub fn create(path: &Path, passphrase: &str) -> Result<ProfileManifest, WebxError> {
+        Self::create_from(&ProfileLocations::new(None)?, &ProfileSection::ALL, path, passphrase)
+    }
+
+    /// Back up sections of the profile in `locations` to `path`
+    pub fn create_from(
+        locations: &ProfileLocations,
+        sections: &[ProfileSection],
+        path: &Path,
+        passphrase: &str,
+    ) -> Result<ProfileManifest, WebxError> {
+        if passphrase.is_empty() {
+            return Err(WebxError::Invalid("Backups need a passphrase".to_string()));
+        }
+        let mut backup = Self {
+            manifest: ProfileManifest {
+                version: PROFILE_BACKUP_VERSION,
+                created_at: Utc::now(),
+                app_version: env!("CARGO_PKG_VERSION").to_string(),
+                sections: sections.to_vec(),
+                files: Vec::new(),
+            },
+            contents: Vec::new(),
+        };
+        for &section in sections {
+            for (root, name) in locations.section_entries(section)? {
+                let base = locations.root(root);
+                for file in files_below(&base.join(&name))? {
+                    let contents = fs::read(&file)?;
+                    let relative = file.strip_prefix(base).unwrap_or(&file);
+                    backup.manifest.files.push(ProfileFile {
+                        section,
+                        root,
+                        path: relative_path(relative),
+                        size: contents.len() as u64,
+                        sha256: sha256_hex(&contents),
+                    });
+                    backup.contents.push(contents);
+                }
+            }
+        }
+
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, &seal(PROFILE_MAGIC, &backup.encode()?, passphrase)?)?;
+        tracing::info!("Backed up {} profile files to {}", backup.manifest.files.len(), path.display());
+        Ok(backup.manifest)
+    }
+
+    /// Decrypt a backup and check every file against its checksum
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self, WebxError> {
+        Self::decode(&unseal(PROFILE_MAGIC, &fs::read(path)?, passphrase)?)
+    }
+
+    pub fn manifest(&self) -> &ProfileManifest {
+        &self.manifest
+    }
+
+    /// Restore sections into `locations`, replacing what is there. Returns
+    /// the sections restored; ones the backup has no files for are skipped.
+    pub fn restore(&self, locations: &ProfileLocations, options: &ProfileRestoreOptions) -> Result<Vec<ProfileSection>, WebxError> {
+        if self.manifest.version > PROFILE_BACKUP_VERSION {
+            return Err(WebxError::Invalid(format!(
+                "Backup was made by a newer WebX ({})",
+                self.manifest.app_version
+            )));
+        }
+        let mut restored = Vec::new();
+        for section in self.manifest.stored_sections() {
+            if !options.sections.contains(&section) {
+                continue;
+            }
+            let files: Vec<(&ProfileFile, &Vec<u8>)> = self
+                .manifest
+                .files
+                .iter()
+                .zip(&self.contents)
+                .filter(|(file, _)| file.section == section)
+                .collect();
+
+            // Replace whole top-level entries, so a database never mixes old and restored files
+            let mut replaced: Vec<PathBuf> = Vec::new();
+            for (file, _) in &files {
+                let top = file.path.split('/').next().unwrap_or_default();
+                let target = locations.root(file.root).join(top);
+                if !replaced.contains(&target) {
+                    set_aside(&target, options.keep_previous)?;
+                    replaced.push(target);
+                }
+            }
+            for (file, contents) in files {
+                let target = locations.root(file.root).join(&file.path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                write_atomic(&target, contents)?;
+            }
+            restored.push(section);
+        }
+        tracing::info!("Restored {:?} from the profile backup", restored);
+        Ok(restored)
+    }
+
+    // Private helper methods
+
+    /// Manifest length, manifest JSON, then each file's contents in order
+    fn encode(&self) -> Result<Vec<u8>, WebxError> {
+        let manifest = serde_json::to_vec(&self.manifest)?;
+        let size: usize = self.contents.iter().map(Vec::len).sum();
+        let mut bytes = Vec::with_capacity(8 + manifest.len() + size);
+        bytes.extend_from_slice(&(manifest.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&manifest);
+        for contents in &self.contents {
+            bytes.extend_from_slice(contents);
+        }
+        Ok(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, WebxError> {
+        let damaged = || WebxError::Parse("Damaged profile backup".to_string());
+        let (length, rest) = bytes.split_first_chunk::<8>().ok_or_else(damaged)?;
+        let length = usize::try_from(u64::from_be_bytes(*length)).map_err(|_| damaged())?;
+        if length > rest.len() {
+            return Err(damaged());
+        }
+        let (manifest, mut rest) = rest.split_at(length);
+        let manifest: ProfileManifest = serde_json::from_slice(manifest)?;
+
+        let mut contents = Vec::with_capacity(manifest.files.len());
+        for file in &manifest.files {
+            if !is_safe_path(&file.path) {
+                return Err(WebxError::Invalid(format!("Unsafe path in profile backup: {}", file.path)));
+            }
+            let size = usize::try_from(file.size).map_err(|_| damaged())?;
+            if size > rest.len() {
+                return Err(damaged());
+            }
+            let (data, remaining) = rest.split_at(size);
+            if sha256_hex(data) != file.sha256 {
+                return Err(WebxError::Crypto(format!("Checksum mismatch for {}", file.path)));
+            }
+            contents.push(data.to_vec());
+            rest = remaining;
+        }
+        if !rest.is_empty() {
+            return Err(damaged());
+        }
+        Ok(Self { manifest, contents })
+    }
+}
+
+// Private helper functions
+
+/// Every file at or below `path`, in a stable order; none if it is missing
+fn files_below(path: &Path) -> Result<Vec<PathBuf>, WebxError> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    if metadata.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !metadata.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut children: Vec<PathBuf> = fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<_, _>>()?;
+    children.sort();
+    let mut files = Vec::new();
+    for child in children {
+        files.extend(files_below(&child)?);
+    }
+    Ok(files)
+}
+
+fn relative_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether a stored path stays below its root
+fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.contains('\\')
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Move `target` to `<target>.before-restore`, or delete it
+fn set_aside(target: &Path, keep: bool) -> Result<(), WebxError> {
+    if fs::symlink_metadata(target).is_err() {
+        return Ok(());
+    }
+    let remove = |path: &Path| match path.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    };
+    if !keep {
+        remove(target)?;
+        return Ok(());
+    }
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(PRE_RESTORE_SUFFIX);
+    let aside = target.with_file_name(name);
+    if fs::symlink_metadata(&aside).is_ok() {
+        remove(&aside)?;
+    }
+    fs::rename(target, &aside)?;
+    Ok(())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_profile_backup_verifies_and_restores_sections() {
+        let temp_dir = TempDir::new().unwrap();
+        let locations = ProfileLocations {
+            profile_dir: temp_dir.path().join("profile"),
+            config_dir: temp_dir.path().join("profile"),
+            data_dir: temp_dir.path().join("data"),
+        };
+        let history_db = locations.data_dir.join("history.db");
+        fs::create_dir_all(history_db.join("blobs")).unwrap();
+        fs::create_dir_all(&locations.profile_dir).unwrap();
+        fs::write(locations.profile_dir.join("bookmarks.json"), "[1]").unwrap();
+        fs::write(locations.profile_dir.join("bookmarks.json.1"), "[0]").unwrap();
+        fs::write(locations.profile_dir.join("settings.json"), "{}").unwrap();
+        fs::write(locations.profile_dir.join("proxy.json"), "{}").unwrap();
+        fs::write(history_db.join("db"), "visits").unwrap();
+        fs::write(history_db.join("blobs").join("1"), "blob").unwrap();
+
+        let path = temp_dir.path().join("backup.webxprofile");
+        let manifest = ProfileBackup::create_from(&locations, &ProfileSection::ALL, &path, "secret").unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["proxy.json", "settings.json", "bookmarks.json", "history.db/blobs/1", "history.db/db"]);
+        assert_eq!(
+            manifest.stored_sections(),
+            vec![ProfileSection::Config, ProfileSection::Bookmarks, ProfileSection::History]
+        );
+
+        // A wrong passphrase, a flipped byte or a file not matching its checksum is refused
+        assert!(ProfileBackup::open(&path, "wrong").is_err());
+        let mut sealed = fs::read(&path).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        fs::write(temp_dir.path().join("flipped"), &sealed).unwrap();
+        assert!(ProfileBackup::open(&temp_dir.path().join("flipped"), "secret").is_err());
+        let mut tampered = ProfileBackup::open(&path, "secret").unwrap();
+        tampered.contents[2] = b"[2]".to_vec();
+        assert!(ProfileBackup::decode(&tampered.encode().unwrap()).is_err());
+
+        // Restore bookmarks only; the history database keeps its new contents
+        fs::write(locations.profile_dir.join("bookmarks.json"), "[]").unwrap();
+        fs::write(history_db.join("db"), "newer visits").unwrap();
+        let backup = ProfileBackup::open(&path, "secret").unwrap();
+        let restored = backup
+            .restore(&locations, &ProfileRestoreOptions::only(&[ProfileSection::Bookmarks]))
+            .unwrap();
+        assert_eq!(restored, vec![ProfileSection::Bookmarks]);
+        assert_eq!(fs::read_to_string(locations.profile_dir.join("bookmarks.json")).unwrap(), "[1]");
+        assert_eq!(fs::read_to_string(locations.profile_dir.join("bookmarks.json.before-restore")).unwrap(), "[]");
+        assert_eq!(fs::read_to_string(history_db.join("db")).unwrap(), "newer visits");
+
+        // Restoring history replaces the whole database directory
+        fs::write(history_db.join("snap.1"), "stale").unwrap();
+        backup.restore(&locations, &ProfileRestoreOptions::only(&[ProfileSection::History])).unwrap();
+        assert_eq!(fs::read_to_string(history_db.join("db")).unwrap(), "visits");
+        assert!(!history_db.join("snap.1").exists());
+        assert!(locations.data_dir.join("history.db.before-restore").join("snap.1").exists());
+    }
+}
@@ -0,0 +1,458 @@
+// WebDAV Backup Target
+use super::{open_backup, seal_backup, BackupArchive, BackupItem, BackupSources};
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::security::secrets::{SecretStore, BACKUP_PASSPHRASE_SECRET, WEBDAV_PASSWORD_SECRET};
+use crate::features::system::proxy::ProxyManager;
+use crate::features::system::scheduler::{TaskPriority, TaskScheduler, TaskSpec};
+use crate::utils::LockExt;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDateTime, Utc};
+use regex::Regex;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Name of the scheduled backup task
+pub const WEBDAV_BACKUP_TASK: &str = "webdav-backup";
+
+/// How often the scheduled task checks whether a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const SNAPSHOT_PREFIX: &str = "webx-backup-";
+const SNAPSHOT_SUFFIX: &str = ".webxbak";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getcontentlength/></d:prop></d:propfind>"#;
+
+/// Where and how often backups go to a WebDAV server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WebDavConfig {
+    /// Back up on a schedule
+    pub enabled: bool,
+    /// Collection the snapshots go in, e.g.
+    /// https://cloud.example.com/remote.php/dav/files/alice/WebX
+    pub url: String,
+    pub username: String,
+    pub interval_hours: u64,
+    /// Snapshots kept on the server; older ones are deleted after a backup
+    pub keep_snapshots: usize,
+    pub items: Vec<BackupItem>,
+}
+
+impl Default for WebDavConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            username: String::new(),
+            interval_hours: 24,
+            keep_snapshots: 10,
+            items: BackupItem::ALL.to_vec(),
+        }
+    }
+}
+
+/// A backup stored on the server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebDavSnapshot {
+    /// File name in the backup collection
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub size: Option<u64>,
+}
+
+/// Account password and archive passphrase
+struct WebDavSecrets {
+    password: String,
+    passphrase: String,
+}
+
+/// Pushes encrypted snapshots of bookmarks, settings, shortcuts and the
+/// reading list to a WebDAV server such as Nextcloud, and restores them
+pub struct WebDavBackup {
+    config: Mutex<WebDavConfig>,
+    config_path: Option<PathBuf>,
+    secrets: Mutex<Option<WebDavSecrets>>,
+    /// Keeps the credentials across restarts when set
+    secret_store: Option<Arc<SecretStore>>,
+    sources: BackupSources,
+    client: Client,
+    proxy: Option<Arc<Mutex<ProxyManager>>>,
+    last_backup: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl WebDavBackup {
+    /// Backups with the given config, kept in memory only
+    pub fn new(sources: BackupSources, config: Option<WebDavConfig>) -> Self {
+        Self {
+            config: Mutex::new(config.unwrap_or_default()),
+            config_path: None,
+            secrets: Mutex::new(None),
+            secret_store: None,
+            sources,
+            client: Client::builder().timeout(Duration::from_secs(120)).build().unwrap_or_default(),
+            proxy: None,
+            last_backup: Mutex::new(None),
+        }
+    }
+
+    /// Load the config from `config_path` (default: the config directory)
+    pub fn load(sources: BackupSources, config_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_path = config_path.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("webdav_backup.json");
+            path
+        });
+        let config = match std::fs::read(&config_path) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            config_path: Some(config_path),
+            ..Self::new(sources, config)
+        })
+    }
+
+    pub fn config(&self) -> WebDavConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    pub fn set_config(&self, config: WebDavConfig) -> Result<(), WebxError> {
+        if !config.url.is_empty() {
+            check_url(&config.url)?;
+        }
+        *self.config.lock_or_recover() = config;
+        self.save()
+    }
+
+    /// Keep the credentials in a secret store, and use any saved there
+    pub fn set_secret_store(&mut self, store: Arc<SecretStore>) -> Result<(), WebxError> {
+        if let Some(passphrase) = store.get(BACKUP_PASSPHRASE_SECRET)? {
+            let password = store.get(WEBDAV_PASSWORD_SECRET)?.unwrap_or_default();
+            *self.secrets.lock_or_recover() = Some(WebDavSecrets { password, passphrase });
+        }
+        self.secret_store = Some(store);
+        Ok(())
+    }
+
+    /// Set the account password and the passphrase archives are encrypted
+    /// with. Without a secret store neither is written anywhere, so
+    /// scheduled backups wait until they are set again.
+    pub fn set_credentials(&self, password: &str, passphrase: &str) -> Result<(), WebxError> {
+        if passphrase.is_empty() {
+            return Err(WebxError::Invalid("Backups need a passphrase".to_string()));
+        }
+        if let Some(store) = &self.secret_store {
+            store.set(WEBDAV_PASSWORD_SECRET, password)?;
+            store.set(BACKUP_PASSPHRASE_SECRET, passphrase)?;
+        }
+        *self.secrets.lock_or_recover() = Some(WebDavSecrets {
+            password: password.to_string(),
+            passphrase: passphrase.to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn clear_credentials(&self) {
+        *self.secrets.lock_or_recover() = None;
+        if let Some(store) = &self.secret_store {
+            for name in [WEBDAV_PASSWORD_SECRET, BACKUP_PASSPHRASE_SECRET] {
+                if let Err(e) = store.delete(name) {
+                    tracing::warn!("Failed to delete saved backup credentials: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.secrets.lock_or_recover().is_some()
+    }
+
+    /// Route backups through the user's proxy rules
+    pub fn set_proxy_manager(&mut self, proxy: Arc<Mutex<ProxyManager>>) {
+        self.proxy = Some(proxy);
+    }
+
+    /// Upload a snapshot of the configured items, then delete snapshots
+    /// past the number kept
+    pub async fn push(&self) -> Result<WebDavSnapshot, WebxError> {
+        let config = self.config();
+        let archive = self.sources.collect(&config.items)?;
+        let sealed = seal_backup(&archive, &self.passphrase()?)?;
+        let snapshot = WebDavSnapshot {
+            name: snapshot_name(archive.created_at),
+            created_at: archive.created_at,
+            size: Some(sealed.len() as u64),
+        };
+
+        // The collection may exist already, which servers answer with 405
+        let response = self.request(dav_method("MKCOL")?, "")?.send().await?;
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            return Err(WebxError::Network(format!("Creating the backup folder failed: {}", response.status())));
+        }
+        self.request(Method::PUT, &snapshot.name)?
+            .header("Content-Type", "application/octet-stream")
+            .body(sealed)
+            .send()
+            .await?
+            .error_for_status()?;
+        *self.last_backup.lock_or_recover() = Some(snapshot.created_at);
+        tracing::info!("Backed up {} to {}", snapshot.name, config.url);
+
+        let snapshots = self.snapshots().await?;
+        for old in snapshots.iter().skip(config.keep_snapshots.max(1)) {
+            if let Err(e) = self.delete_snapshot(&old.name).await {
+                tracing::warn!("Failed to delete old backup {}: {}", old.name, e);
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Snapshots on the server, newest first
+    pub async fn snapshots(&self) -> Result<Vec<WebDavSnapshot>, WebxError> {
+        let response = self
+            .request(dav_method("PROPFIND")?, "")?
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let body = response.error_for_status()?.text().await?;
+        let mut snapshots = parse_snapshots(&body)?;
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created_at));
+        Ok(snapshots)
+    }
+
+    /// Download and decrypt a snapshot, the newest one when `name` is `None`
+    pub async fn pull(&self, name: Option<&str>) -> Result<BackupArchive, WebxError> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => match self.snapshots().await?.into_iter().next() {
+                Some(snapshot) => snapshot.name,
+                None => return Err(WebxError::NotFound("No backups on the server".to_string())),
+            },
+        };
+        if parse_snapshot_name(&name).is_none() {
+            return Err(WebxError::Invalid(format!("Not a WebX backup: {}", name)));
+        }
+        let response = self.request(Method::GET, &name)?.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(WebxError::NotFound(format!("No backup {}", name)));
+        }
+        let sealed = response.error_for_status()?.bytes().await?;
+        open_backup(&sealed, &self.passphrase()?)
+    }
+
+    /// Restore items from a snapshot, the newest one when `name` is `None`.
+    /// Returns the items restored.
+    pub async fn restore(&self, name: Option<&str>, items: &[BackupItem]) -> Result<Vec<BackupItem>, WebxError> {
+        let archive = self.pull(name).await?;
+        self.sources.restore(&archive, items)
+    }
+
+    pub async fn delete_snapshot(&self, name: &str) -> Result<(), WebxError> {
+        if parse_snapshot_name(name).is_none() {
+            return Err(WebxError::Invalid(format!("Not a WebX backup: {}", name)));
+        }
+        self.request(Method::DELETE, name)?.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Whether a scheduled backup should run now
+    pub async fn backup_due(&self) -> Result<bool, WebxError> {
+        let config = self.config();
+        if !config.enabled || config.url.is_empty() || !self.has_credentials() {
+            return Ok(false);
+        }
+        let known = *self.last_backup.lock_or_recover();
+        let last = match known {
+            Some(last) => Some(last),
+            None => self.snapshots().await?.first().map(|snapshot| snapshot.created_at),
+        };
+        let interval = ChronoDuration::hours(config.interval_hours.max(1) as i64);
+        Ok(last.is_none_or(|last| Utc::now() - last >= interval))
+    }
+
+    /// Check hourly, as low-priority network work, whether a backup is due
+    pub fn schedule(backup: Arc<Self>, scheduler: &TaskScheduler) {
+        let spec = TaskSpec::new(WEBDAV_BACKUP_TASK, CHECK_INTERVAL)
+            .with_priority(TaskPriority::Low)
+            .uses_network();
+        scheduler.register(spec, move || {
+            let backup = Arc::clone(&backup);
+            async move {
+                if backup.backup_due().await? {
+                    backup.push().await?;
+                }
+                Ok(())
+            }
+        });
+    }
+
+    // Private helper methods
+
+    fn passphrase(&self) -> Result<String, WebxError> {
+        self.secrets
+            .lock_or_recover()
+            .as_ref()
+            .map(|secrets| secrets.passphrase.clone())
+            .ok_or_else(|| WebxError::Locked("Enter the backup passphrase first".to_string()))
+    }
+
+    /// Authenticated request for a file in the backup collection, or the
+    /// collection itself when `name` is empty
+    fn request(&self, method: Method, name: &str) -> Result<RequestBuilder, WebxError> {
+        let config = self.config();
+        if config.url.is_empty() {
+            return Err(WebxError::Invalid("No WebDAV server set for backups".to_string()));
+        }
+        let url = format!("{}/{}", config.url.trim_end_matches('/'), name);
+        let client = match &self.proxy {
+            Some(proxy) => proxy.lock_or_recover().client_for_url(&url)?.0,
+            None => self.client.clone(),
+        };
+        let password = self.secrets.lock_or_recover().as_ref().map(|secrets| secrets.password.clone());
+        let mut request = client.request(method, &url);
+        if !config.username.is_empty() {
+            request = request.basic_auth(config.username, password);
+        }
+        Ok(request)
+    }
+
+    fn save(&self) -> Result<(), WebxError> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let config = self.config.lock_or_recover().clone();
+        write_atomic(path, &serde_json::to_vec_pretty(&config)?)?;
+        Ok(())
+    }
+}
+
+impl SettingsProvider for WebDavBackup {
+    fn module(&self) -> &str {
+        "backup"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("backup.webdav_enabled", "Back up to a WebDAV server", false)
+                .with_description("Upload encrypted snapshots of bookmarks, settings, shortcuts and the reading list")
+                .with_category(SettingCategory::General),
+            SettingDefinition::text("backup.webdav_url", "WebDAV folder", "")
+                .with_description("e.g. https://cloud.example.com/remote.php/dav/files/you/WebX")
+                .with_category(SettingCategory::General),
+            SettingDefinition::text("backup.webdav_username", "WebDAV user name", "")
+                .with_category(SettingCategory::General),
+            SettingDefinition::integer("backup.interval_hours", "Hours between backups", 24, 1, 24 * 30)
+                .with_category(SettingCategory::General),
+            SettingDefinition::integer("backup.keep_snapshots", "Backups to keep", 10, 1, 365)
+                .with_category(SettingCategory::General),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let mut config = self.config();
+        match key {
+            "backup.webdav_enabled" => config.enabled = value.as_bool().ok_or("Expected a toggle value")?,
+            "backup.webdav_url" => config.url = value.as_str().ok_or("Expected a text value")?.trim().to_string(),
+            "backup.webdav_username" => {
+                config.username = value.as_str().ok_or("Expected a text value")?.trim().to_string()
+            }
+            "backup.interval_hours" => config.interval_hours = value.as_i64().ok_or("Expected an integer value")? as u64,
+            "backup.keep_snapshots" => config.keep_snapshots = value.as_i64().ok_or("Expected an integer value")? as usize,
+            _ => return Err(format!("Unknown backup setting {}", key).into()),
+        }
+        self.set_config(config)
+    }
+}
+
+// Private helper functions
+
+fn dav_method(name: &str) -> Result<Method, WebxError> {
+    Method::from_bytes(name.as_bytes()).map_err(|e| WebxError::Invalid(e.to_string()))
+}
+
+fn check_url(url: &str) -> Result<(), WebxError> {
+    let parsed = url::Url::parse(url).map_err(|e| WebxError::Invalid(format!("Bad WebDAV URL: {}", e)))?;
+    match parsed.scheme() {
+        "https" => Ok(()),
+        // Plain HTTP only to this machine, where nothing crosses the network
+        "http" if matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")) => Ok(()),
+        _ => Err(WebxError::Invalid(format!("WebDAV backups need an https URL: {}", url))),
+    }
+}
+
+fn snapshot_name(created_at: DateTime<Utc>) -> String {
+    format!("{}{}{}", SNAPSHOT_PREFIX, created_at.format(SNAPSHOT_TIME_FORMAT), SNAPSHOT_SUFFIX)
+}
+
+fn parse_snapshot_name(name: &str) -> Option<DateTime<Utc>> {
+    let time = name.strip_prefix(SNAPSHOT_PREFIX)?.strip_suffix(SNAPSHOT_SUFFIX)?;
+    NaiveDateTime::parse_from_str(time, SNAPSHOT_TIME_FORMAT).ok().map(|time| time.and_utc())
+}
+
+/// Snapshots listed in a PROPFIND multistatus response, whatever prefix
+/// the server gives the DAV: namespace
+fn parse_snapshots(xml: &str) -> Result<Vec<WebDavSnapshot>, WebxError> {
+    let response = Regex::new(r"(?s)<(?:[\w-]+:)?response\b.*?</(?:[\w-]+:)?response>")?;
+    let href = Regex::new(r"<(?:[\w-]+:)?href>\s*([^<]*?)\s*</")?;
+    let length = Regex::new(r"<(?:[\w-]+:)?getcontentlength>\s*(\d+)\s*</")?;
+    let snapshots = response
+        .find_iter(xml)
+        .filter_map(|block| {
+            let block = block.as_str();
+            let href = href.captures(block)?.get(1)?.as_str();
+            let name = href.trim_end_matches('/').rsplit('/').next()?.to_string();
+            let created_at = parse_snapshot_name(&name)?;
+            let size = length.captures(block).and_then(|size| size[1].parse().ok());
+            Some(WebDavSnapshot { name, created_at, size })
+        })
+        .collect();
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_listing() {
+        let created_at = DateTime::parse_from_rfc3339("2026-10-16T08:30:05Z").unwrap().with_timezone(&Utc);
+        let name = snapshot_name(created_at);
+        assert_eq!(name, "webx-backup-20261016T083005Z.webxbak");
+        assert_eq!(parse_snapshot_name(&name), Some(created_at));
+        assert_eq!(parse_snapshot_name("notes.txt"), None);
+
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/remote.php/dav/files/alice/WebX/</d:href></d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/WebX/webx-backup-20261016T083005Z.webxbak</d:href>
+    <d:propstat><d:prop><d:getcontentlength>2048</d:getcontentlength></d:prop></d:propstat>
+  </d:response>
+  <d:response><d:href>/remote.php/dav/files/alice/WebX/other.txt</d:href></d:response>
+</d:multistatus>"#;
+        let snapshots = parse_snapshots(xml).unwrap();
+        assert_eq!(snapshots, vec![WebDavSnapshot { name, created_at, size: Some(2048) }]);
+        let apache = "<D:multistatus xmlns:D=\"DAV:\"><D:response><D:href>/dav/webx-backup-20260101T000000Z.webxbak</D:href></D:response></D:multistatus>";
+        assert_eq!(parse_snapshots(apache).unwrap().len(), 1);
+
+        assert!(check_url("https://cloud.example.com/remote.php/dav/files/alice/WebX").is_ok());
+        assert!(check_url("http://cloud.example.com/dav").is_err());
+        assert!(check_url("http://localhost:8080/dav").is_ok());
+    }
+}
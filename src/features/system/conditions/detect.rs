@@ -0,0 +1,170 @@
+// Power and Network Conditions
+use serde::{Deserialize, Serialize};
+
+/// Power and network state that background work adapts to
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SystemConditions {
+    pub on_battery: bool,
+    /// Remaining charge, when known
+    pub battery_percent: Option<u8>,
+    /// The active connection is billed by use, e.g. a phone hotspot
+    pub metered_network: bool,
+}
+
+impl SystemConditions {
+    /// Read the current state from the system. Whatever cannot be read
+    /// counts as mains power on an unmetered network. Runs commands, so
+    /// call it off the UI thread.
+    pub fn detect() -> Self {
+        let (on_battery, battery_percent) = detect_power();
+        Self {
+            on_battery,
+            battery_percent,
+            metered_network: detect_metered(),
+        }
+    }
+
+    /// Whether the battery is at or below a charge level
+    pub fn battery_below(&self, percent: u8) -> bool {
+        self.on_battery && self.battery_percent.is_some_and(|charge| charge <= percent)
+    }
+}
+
+// Private helper functions
+
+#[cfg(target_os = "linux")]
+fn detect_power() -> (bool, Option<u8>) {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return (false, None);
+    };
+    let supplies: Vec<PowerSupply> = entries
+        .flatten()
+        .map(|entry| {
+            let read = |name: &str| {
+                std::fs::read_to_string(entry.path().join(name))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            PowerSupply {
+                kind: read("type"),
+                online: read("online") == "1",
+                status: read("status"),
+                capacity: read("capacity").parse().ok(),
+            }
+        })
+        .collect();
+    power_state(&supplies)
+}
+
+#[cfg(target_os = "macos")]
+fn detect_power() -> (bool, Option<u8>) {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_pmset(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or((false, None))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect_power() -> (bool, Option<u8>) {
+    (false, None)
+}
+
+#[cfg(target_os = "linux")]
+fn detect_metered() -> bool {
+    // NetworkManager's overall verdict for the primary connection
+    std::process::Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_some_and(|output| parse_nm_metered(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_metered() -> bool {
+    false
+}
+
+/// A `/sys/class/power_supply` entry
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+struct PowerSupply {
+    /// `Mains`, `Battery`, `USB`, ...
+    kind: String,
+    online: bool,
+    /// `Charging`, `Discharging`, `Full`, ...
+    status: String,
+    capacity: Option<u8>,
+}
+
+/// On battery when a battery discharges, or when there are batteries and
+/// no online mains supply
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn power_state(supplies: &[PowerSupply]) -> (bool, Option<u8>) {
+    let batteries: Vec<&PowerSupply> = supplies.iter().filter(|supply| supply.kind == "Battery").collect();
+    if batteries.is_empty() {
+        return (false, None);
+    }
+    let mains_online = supplies.iter().any(|supply| supply.kind != "Battery" && supply.online);
+    let discharging = batteries.iter().any(|battery| battery.status == "Discharging");
+    let charge = batteries.iter().filter_map(|battery| battery.capacity).min();
+    (discharging || !mains_online, charge)
+}
+
+/// `pmset -g batt` prints e.g. `Now drawing from 'Battery Power'` and
+/// `-InternalBattery-0 (id=1234) 76%; discharging; ...`
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+fn parse_pmset(output: &str) -> (bool, Option<u8>) {
+    let on_battery = output.contains("'Battery Power'");
+    let charge = output
+        .split_whitespace()
+        .find_map(|word| word.trim_end_matches(';').strip_suffix('%')?.parse().ok());
+    (on_battery, charge)
+}
+
+/// `busctl` prints `u <n>`, where 1 is metered and 3 guessed metered
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_nm_metered(output: &str) -> bool {
+    matches!(output.trim().strip_prefix("u "), Some("1" | "3"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_and_network_parsing() {
+        let supply = |kind: &str, online: bool, status: &str, capacity: Option<u8>| PowerSupply {
+            kind: kind.to_string(),
+            online,
+            status: status.to_string(),
+            capacity,
+        };
+        assert_eq!(power_state(&[supply("Mains", true, "", None)]), (false, None));
+        assert_eq!(
+            power_state(&[supply("Mains", true, "", None), supply("Battery", false, "Charging", Some(40))]),
+            (false, Some(40))
+        );
+        assert_eq!(
+            power_state(&[supply("Mains", false, "", None), supply("Battery", false, "Discharging", Some(15))]),
+            (true, Some(15))
+        );
+
+        let pmset = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=4653155)\t76%; discharging; 5:12 remaining present: true\n";
+        assert_eq!(parse_pmset(pmset), (true, Some(76)));
+        assert_eq!(parse_pmset("Now drawing from 'AC Power'\n"), (false, None));
+
+        assert!(parse_nm_metered("u 1\n"));
+        assert!(parse_nm_metered("u 3\n"));
+        assert!(!parse_nm_metered("u 4\n"));
+        assert!(!parse_nm_metered(""));
+    }
+}
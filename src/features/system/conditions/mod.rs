@@ -0,0 +1,323 @@
+// System Conditions Monitor
+mod detect;
+
+pub use detect::SystemConditions;
+
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How often power and network state is read again
+const DETECT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How much background work matters when the system is constrained
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Nice to have; held back on battery
+    Low,
+    /// Held back only when the battery runs low
+    Normal,
+    /// Always runs, e.g. saving the session
+    High,
+}
+
+/// Work a subsystem is about to do in the background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundWork {
+    pub priority: TaskPriority,
+    /// Uses the network, so is held back on metered connections
+    pub uses_network: bool,
+    /// Wait until the user has been inactive for a while
+    pub idle_only: bool,
+}
+
+impl BackgroundWork {
+    pub fn new(priority: TaskPriority) -> Self {
+        Self {
+            priority,
+            uses_network: false,
+            idle_only: false,
+        }
+    }
+
+    pub fn uses_network(mut self) -> Self {
+        self.uses_network = true;
+        self
+    }
+
+    pub fn idle_only(mut self) -> Self {
+        self.idle_only = true;
+        self
+    }
+}
+
+/// Whether background work follows the power and network state
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundWorkMode {
+    /// Hold work back on battery and metered connections, as configured
+    #[default]
+    Automatic,
+    /// Never hold work back for power or network
+    AlwaysRun,
+    /// Hold back all but essential work, whatever the state
+    Conserve,
+}
+
+impl BackgroundWorkMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Automatic => "automatic",
+            Self::AlwaysRun => "always_run",
+            Self::Conserve => "conserve",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "automatic" => Some(Self::Automatic),
+            "always_run" => Some(Self::AlwaysRun),
+            "conserve" => Some(Self::Conserve),
+            _ => None,
+        }
+    }
+}
+
+/// Conditions monitor configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ConditionsConfig {
+    pub mode: BackgroundWorkMode,
+    /// Inactivity after which the browser counts as idle
+    pub idle_after_secs: u64,
+    /// Hold back low-priority work on battery power
+    pub defer_on_battery: bool,
+    /// Charge at or below which normal-priority work is held back too
+    pub low_battery_percent: u8,
+    /// Hold back network work on metered connections
+    pub respect_metered: bool,
+}
+
+impl Default for ConditionsConfig {
+    fn default() -> Self {
+        Self {
+            mode: BackgroundWorkMode::Automatic,
+            idle_after_secs: 120,
+            defer_on_battery: true,
+            low_battery_percent: 20,
+            respect_metered: true,
+        }
+    }
+}
+
+/// Keeps track of battery, metered network and user activity, so
+/// subsystems can ask before doing expensive work whether it should wait
+pub struct ConditionsMonitor {
+    config: Mutex<ConditionsConfig>,
+    conditions: Mutex<SystemConditions>,
+    last_activity: Mutex<Instant>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ConditionsMonitor {
+    /// Create new monitor
+    pub fn new(config: Option<ConditionsConfig>) -> Self {
+        Self {
+            config: Mutex::new(config.unwrap_or_default()),
+            conditions: Mutex::new(SystemConditions::default()),
+            last_activity: Mutex::new(Instant::now()),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> ConditionsConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    pub fn set_config(&self, config: ConditionsConfig) {
+        *self.config.lock_or_recover() = config;
+    }
+
+    pub fn conditions(&self) -> SystemConditions {
+        *self.conditions.lock_or_recover()
+    }
+
+    /// Replace the power and network state, which the background loop
+    /// otherwise reads from the system every minute
+    pub fn set_conditions(&self, conditions: SystemConditions) {
+        *self.conditions.lock_or_recover() = conditions;
+    }
+
+    /// Count user activity, which holds back idle-only work
+    pub fn note_activity(&self) {
+        *self.last_activity.lock_or_recover() = Instant::now();
+    }
+
+    /// Whether the user has been inactive long enough for idle work
+    pub fn is_idle(&self) -> bool {
+        let idle_after = Duration::from_secs(self.config.lock_or_recover().idle_after_secs);
+        self.last_activity.lock_or_recover().elapsed() >= idle_after
+    }
+
+    /// Why work should wait for now, `None` when it may run
+    pub fn defer_reason(&self, work: BackgroundWork) -> Option<&'static str> {
+        if work.idle_only && !self.is_idle() {
+            return Some("waiting for the browser to be idle");
+        }
+        if work.priority == TaskPriority::High {
+            return None;
+        }
+        let config = self.config();
+        let conditions = self.conditions();
+        match config.mode {
+            BackgroundWorkMode::AlwaysRun => None,
+            BackgroundWorkMode::Conserve => Some("saving battery and data"),
+            BackgroundWorkMode::Automatic => {
+                if conditions.battery_below(config.low_battery_percent) {
+                    Some("battery low")
+                } else if work.priority == TaskPriority::Low && config.defer_on_battery && conditions.on_battery {
+                    Some("on battery power")
+                } else if work.uses_network && config.respect_metered && conditions.metered_network {
+                    Some("metered network")
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Whether work should wait for now
+    pub fn should_defer(&self, work: BackgroundWork) -> bool {
+        self.defer_reason(work).is_some()
+    }
+
+    /// Start reading power and network state in the background
+    pub fn start(monitor: Arc<Self>) {
+        let weak = Arc::downgrade(&monitor);
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(DETECT_INTERVAL);
+            loop {
+                timer.tick().await;
+                let Ok(conditions) = tokio::task::spawn_blocking(SystemConditions::detect).await else {
+                    continue;
+                };
+                let Some(monitor) = weak.upgrade() else {
+                    break;
+                };
+                if monitor.conditions() != conditions {
+                    tracing::debug!("System conditions changed: {:?}", conditions);
+                }
+                monitor.set_conditions(conditions);
+            }
+        });
+        if let Some(previous) = monitor.task.lock_or_recover().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stop reading power and network state
+    pub fn stop(&self) {
+        if let Some(handle) = self.task.lock_or_recover().take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Default for ConditionsMonitor {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl SettingsProvider for ConditionsMonitor {
+    fn module(&self) -> &str {
+        "background"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::choice(
+                "background.mode",
+                "Background work",
+                BackgroundWorkMode::Automatic.name(),
+                &[
+                    ("automatic", "Adapt to battery and network"),
+                    ("always_run", "Always run"),
+                    ("conserve", "Save battery and data"),
+                ],
+            )
+            .with_description("Sync, prefetching, statistics uploads and session saving can wait while on battery or a metered connection")
+            .with_category(SettingCategory::General),
+            SettingDefinition::toggle("background.defer_on_battery", "Save battery", true)
+                .with_description("Hold back optional background work while running on battery")
+                .with_category(SettingCategory::Advanced),
+            SettingDefinition::toggle("background.respect_metered", "Save data on metered connections", true)
+                .with_description("Hold back background downloads and uploads on connections billed by use")
+                .with_category(SettingCategory::Network),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let mut config = self.config.lock_or_recover();
+        match key {
+            "background.mode" => {
+                let name = value.as_str().ok_or("Expected a choice")?;
+                config.mode = BackgroundWorkMode::from_name(name).ok_or("Unknown background work mode")?;
+            }
+            "background.defer_on_battery" => config.defer_on_battery = value.as_bool().ok_or("Expected a toggle value")?,
+            "background.respect_metered" => config.respect_metered = value.as_bool().ok_or("Expected a toggle value")?,
+            _ => return Err(format!("Unknown background setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_work_defers_by_mode_and_conditions() {
+        let monitor = ConditionsMonitor::new(None);
+        let prefetch = BackgroundWork::new(TaskPriority::Low).uses_network();
+        let sync = BackgroundWork::new(TaskPriority::Normal).uses_network();
+        let autosave = BackgroundWork::new(TaskPriority::High);
+        assert!(!monitor.should_defer(prefetch));
+
+        monitor.set_conditions(SystemConditions {
+            on_battery: true,
+            battery_percent: Some(60),
+            metered_network: false,
+        });
+        assert_eq!(monitor.defer_reason(prefetch), Some("on battery power"));
+        assert_eq!(monitor.defer_reason(sync), None);
+
+        monitor.set_conditions(SystemConditions {
+            on_battery: true,
+            battery_percent: Some(10),
+            metered_network: true,
+        });
+        assert_eq!(monitor.defer_reason(sync), Some("battery low"));
+        assert_eq!(monitor.defer_reason(autosave), None);
+
+        // The user's override wins over the detected state
+        monitor
+            .apply_setting("background.mode", &SettingValue::String("always_run".to_string()))
+            .unwrap();
+        assert!(!monitor.should_defer(prefetch));
+        monitor.set_conditions(SystemConditions::default());
+        monitor
+            .apply_setting("background.mode", &SettingValue::String("conserve".to_string()))
+            .unwrap();
+        assert_eq!(monitor.defer_reason(sync), Some("saving battery and data"));
+        assert!(!monitor.should_defer(autosave));
+        assert!(monitor
+            .apply_setting("background.mode", &SettingValue::String("turbo".to_string()))
+            .is_err());
+        assert!(monitor.should_defer(autosave.idle_only()));
+    }
+}
@@ -0,0 +1,355 @@
+// Default Browser Registration
+use crate::error::WebxError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// URL schemes WebX registers itself for
+pub const HANDLED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Default browser configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefaultBrowserConfig {
+    /// Desktop entry file name on Linux
+    pub desktop_id: String,
+    pub display_name: String,
+    /// Program the OS launches; defaults to the running executable
+    pub executable: Option<PathBuf>,
+    /// Web mail page for `mailto:` links, with `%s` replaced by the
+    /// encoded link, e.g. `https://mail.example.com/compose?to=%s`
+    pub mailto_handler: Option<String>,
+}
+
+impl Default for DefaultBrowserConfig {
+    fn default() -> Self {
+        Self {
+            desktop_id: "webx.desktop".to_string(),
+            display_name: "WebX".to_string(),
+            executable: None,
+            mailto_handler: None,
+        }
+    }
+}
+
+/// Whether the OS knows about WebX and uses it for web links
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RegistrationStatus {
+    NotRegistered,
+    /// Registered as a browser, but another browser is the default
+    Registered,
+    Default,
+}
+
+/// Registers WebX as the system browser and maps the URLs the OS hands it
+/// to pages it can load
+pub struct DefaultBrowserManager {
+    config: DefaultBrowserConfig,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    applications_dir: PathBuf,
+}
+
+impl DefaultBrowserManager {
+    /// Create new default browser manager. `applications_dir` is where the
+    /// desktop entry goes on Linux.
+    pub fn new(config: Option<DefaultBrowserConfig>, applications_dir: Option<PathBuf>) -> Self {
+        let applications_dir = applications_dir.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("applications");
+            path
+        });
+
+        Self {
+            config: config.unwrap_or_default(),
+            applications_dir,
+        }
+    }
+
+    /// Register WebX with the OS and make it the default browser. On
+    /// Windows the user has to confirm the choice in the settings app,
+    /// which this opens.
+    pub fn register(&self) -> Result<(), WebxError> {
+        let executable = self.executable()?;
+
+        #[cfg(target_os = "linux")]
+        {
+            self.register_linux(&executable)
+        }
+        #[cfg(target_os = "windows")]
+        {
+            self.register_windows(&executable)
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            let _ = executable;
+            Err(WebxError::Invalid(
+                "Default browser registration is not supported on this platform".to_string(),
+            ))
+        }
+    }
+
+    /// Remove WebX's registration. The OS picks another default browser.
+    pub fn unregister(&self) -> Result<(), WebxError> {
+        #[cfg(target_os = "linux")]
+        {
+            let path = self.desktop_entry_path();
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            let _ = run_tool("update-desktop-database", &[&self.applications_dir.to_string_lossy()]);
+            Ok(())
+        }
+        #[cfg(target_os = "windows")]
+        {
+            self.unregister_windows()
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            Ok(())
+        }
+    }
+
+    /// Check whether WebX is registered and the default browser
+    pub fn status(&self) -> RegistrationStatus {
+        #[cfg(target_os = "linux")]
+        {
+            if !self.desktop_entry_path().exists() {
+                return RegistrationStatus::NotRegistered;
+            }
+            match run_tool("xdg-settings", &["check", "default-web-browser", &self.config.desktop_id]) {
+                Ok(answer) if answer.trim() == "yes" => RegistrationStatus::Default,
+                _ => RegistrationStatus::Registered,
+            }
+        }
+        #[cfg(target_os = "windows")]
+        {
+            self.status_windows()
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            RegistrationStatus::NotRegistered
+        }
+    }
+
+    /// Desktop entry that announces WebX as a browser and URL handler
+    pub fn desktop_entry(&self, executable: &Path) -> String {
+        let mime_types: Vec<String> = HANDLED_SCHEMES
+            .iter()
+            .map(|scheme| format!("x-scheme-handler/{};", scheme))
+            .chain(["text/html;".to_string(), "application/xhtml+xml;".to_string()])
+            .collect();
+
+        format!(
+            "[Desktop Entry]\n\
+             Version=1.0\n\
+             Type=Application\n\
+             Name={name}\n\
+             GenericName=Web Browser\n\
+             Comment=Official system browser for Ledokoz OS\n\
+             Exec=\"{exec}\" %U\n\
+             Icon=webx\n\
+             Terminal=false\n\
+             Categories=Network;WebBrowser;\n\
+             MimeType={mime}\n\
+             StartupNotify=true\n",
+            name = self.config.display_name,
+            exec = executable.display().to_string().replace('"', "\\\""),
+            mime = mime_types.concat(),
+        )
+    }
+
+    /// Turn a URL the OS passed on the command line into one to open, or
+    /// None if WebX can't handle it
+    pub fn resolve_url(&self, url: &str) -> Option<String> {
+        let parsed = match url::Url::parse(url) {
+            Ok(parsed) => parsed,
+            // Not a URL: a path or search the address bar deals with
+            Err(_) => return Some(url.to_string()),
+        };
+
+        match parsed.scheme() {
+            "http" | "https" | "file" | "about" => Some(url.to_string()),
+            "mailto" => match &self.config.mailto_handler {
+                Some(handler) => {
+                    let encoded: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+                    Some(handler.replace("%s", &encoded))
+                }
+                None => {
+                    tracing::warn!("No web mail handler configured for {}", url);
+                    None
+                }
+            },
+            // A one letter scheme is a Windows drive letter
+            scheme if scheme.len() == 1 => Some(url.to_string()),
+            scheme => {
+                tracing::warn!("Refusing to open unsupported scheme {}:", scheme);
+                None
+            }
+        }
+    }
+
+    /// Set the web mail page used for `mailto:` links
+    pub fn set_mailto_handler(&mut self, handler: Option<String>) {
+        self.config.mailto_handler = handler;
+    }
+
+    // Private helper methods
+
+    fn executable(&self) -> Result<PathBuf, WebxError> {
+        match &self.config.executable {
+            Some(executable) => Ok(executable.clone()),
+            None => Ok(std::env::current_exe()?),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn desktop_entry_path(&self) -> PathBuf {
+        self.applications_dir.join(&self.config.desktop_id)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn register_linux(&self, executable: &Path) -> Result<(), WebxError> {
+        std::fs::create_dir_all(&self.applications_dir)?;
+        std::fs::write(self.desktop_entry_path(), self.desktop_entry(executable))?;
+        let _ = run_tool("update-desktop-database", &[&self.applications_dir.to_string_lossy()]);
+
+        let desktop_id = self.config.desktop_id.as_str();
+        run_tool("xdg-settings", &["set", "default-web-browser", desktop_id])?;
+        for scheme in HANDLED_SCHEMES {
+            run_tool("xdg-mime", &["default", desktop_id, &format!("x-scheme-handler/{}", scheme)])?;
+        }
+        run_tool("xdg-mime", &["default", desktop_id, "text/html"])?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn register_windows(&self, executable: &Path) -> Result<(), WebxError> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        use winreg::RegKey;
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let command = format!("\"{}\" \"%1\"", executable.display());
+        let name = &self.config.display_name;
+
+        // URL class the associations point at
+        let (class, _) = hkcu.create_subkey(format!(r"Software\Classes\{}", WINDOWS_PROG_ID))?;
+        class.set_value("", &format!("{} URL", name))?;
+        class.set_value("URL Protocol", &"")?;
+        let (open, _) = class.create_subkey(r"shell\open\command")?;
+        open.set_value("", &command)?;
+
+        // Browser entry with its capabilities
+        let client_path = format!(r"Software\Clients\StartMenuInternet\{}", name);
+        let (client, _) = hkcu.create_subkey(&client_path)?;
+        client.set_value("", name)?;
+        let (open, _) = client.create_subkey(r"shell\open\command")?;
+        open.set_value("", &format!("\"{}\"", executable.display()))?;
+        let (capabilities, _) = client.create_subkey("Capabilities")?;
+        capabilities.set_value("ApplicationName", name)?;
+        capabilities.set_value("ApplicationDescription", &"Official system browser for Ledokoz OS")?;
+        let (associations, _) = capabilities.create_subkey("URLAssociations")?;
+        for scheme in HANDLED_SCHEMES {
+            associations.set_value(scheme, &WINDOWS_PROG_ID)?;
+        }
+
+        let (registered, _) = hkcu.create_subkey(r"Software\RegisteredApplications")?;
+        registered.set_value(name, &format!(r"{}\Capabilities", client_path))?;
+
+        // Windows only lets the user pick the default browser
+        run_tool("cmd", &["/C", "start", "", "ms-settings:defaultapps"])?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn unregister_windows(&self) -> Result<(), WebxError> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        use winreg::RegKey;
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let name = &self.config.display_name;
+        let _ = hkcu.delete_subkey_all(format!(r"Software\Classes\{}", WINDOWS_PROG_ID));
+        let _ = hkcu.delete_subkey_all(format!(r"Software\Clients\StartMenuInternet\{}", name));
+        if let Ok(registered) = hkcu.open_subkey_with_flags(r"Software\RegisteredApplications", winreg::enums::KEY_WRITE) {
+            let _ = registered.delete_value(name);
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn status_windows(&self) -> RegistrationStatus {
+        use winreg::enums::HKEY_CURRENT_USER;
+        use winreg::RegKey;
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let prog_id: Option<String> = hkcu
+            .open_subkey(r"Software\Microsoft\Windows\Shell\Associations\UrlAssociations\https\UserChoice")
+            .and_then(|key| key.get_value("ProgId"))
+            .ok();
+        if prog_id.as_deref() == Some(WINDOWS_PROG_ID) {
+            return RegistrationStatus::Default;
+        }
+
+        let client_path = format!(r"Software\Clients\StartMenuInternet\{}", self.config.display_name);
+        if hkcu.open_subkey(client_path).is_ok() {
+            RegistrationStatus::Registered
+        } else {
+            RegistrationStatus::NotRegistered
+        }
+    }
+}
+
+impl Default for DefaultBrowserManager {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+/// Registry class that URL associations point at on Windows
+#[cfg(target_os = "windows")]
+const WINDOWS_PROG_ID: &str = "WebXURL";
+
+// Private helper functions
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn run_tool(program: &str, args: &[&str]) -> Result<String, WebxError> {
+    let output = std::process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(WebxError::Invalid(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_entry_handles_web_schemes() {
+        let manager = DefaultBrowserManager::new(None, None);
+        let entry = manager.desktop_entry(Path::new("/usr/bin/webx"));
+
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/usr/bin/webx\" %U\n"));
+        assert!(entry.contains(
+            "MimeType=x-scheme-handler/http;x-scheme-handler/https;x-scheme-handler/mailto;text/html;application/xhtml+xml;\n"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_external_urls() {
+        let mut manager = DefaultBrowserManager::new(None, None);
+        assert_eq!(manager.resolve_url("https://example.com/"), Some("https://example.com/".to_string()));
+        assert_eq!(manager.resolve_url("example.com"), Some("example.com".to_string()));
+        assert_eq!(manager.resolve_url("mailto:a@example.com"), None);
+        assert_eq!(manager.resolve_url("javascript:alert(1)"), None);
+
+        manager.set_mailto_handler(Some("https://mail.example.com/compose?to=%s".to_string()));
+        assert_eq!(
+            manager.resolve_url("mailto:a@example.com"),
+            Some("https://mail.example.com/compose?to=mailto%3Aa%40example.com".to_string())
+        );
+    }
+}
@@ -0,0 +1,240 @@
+// JSON Log Files
+use crate::config::storage::backup_path;
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Feature groups whose modules are components of their own
+const FEATURE_GROUPS: &[&str] = &["productivity", "security", "system", "ui"];
+
+/// A line of a log file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub component: String,
+    pub target: String,
+    pub message: String,
+    /// Fields recorded with the event besides the message
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Component a log target belongs to, which names its log file: the feature
+/// module for features, e.g. `downloads` or `proxy`, else the top-level
+/// module. Other crates log to `external`.
+pub fn component_of(target: &str) -> String {
+    let Some(path) = target.strip_prefix("webx").filter(|rest| rest.is_empty() || rest.starts_with("::")) else {
+        return "external".to_string();
+    };
+    let mut modules = path.split("::").filter(|module| !module.is_empty());
+    match modules.next() {
+        None => "browser".to_string(),
+        Some("features") => {
+            let module = modules.next().unwrap_or("features");
+            if FEATURE_GROUPS.contains(&module) {
+                modules.next().unwrap_or(module).to_string()
+            } else {
+                module.to_string()
+            }
+        }
+        Some(module) => module.to_string(),
+    }
+}
+
+/// Log file that moves to numbered backups as it fills, e.g. `ui.log`,
+/// `ui.log.1`, `ui.log.2`. Lines go straight to the file, so a crash loses
+/// nothing written before it.
+pub struct RotatingLogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_backups: usize,
+}
+
+impl RotatingLogFile {
+    /// Open a log file for appending
+    pub fn open(path: &Path, max_bytes: u64, max_backups: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: file.metadata()?.len(),
+            file,
+            max_bytes,
+            max_backups,
+        })
+    }
+
+    /// Append a line, rotating first if it would overfill the file
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let length = line.len() as u64 + 1;
+        if self.size > 0 && self.size + length > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.size += length;
+        Ok(())
+    }
+
+    // Private helper methods
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_backups > 0 {
+            for index in (1..self.max_backups).rev() {
+                let backup = backup_path(&self.path, index);
+                if backup.exists() {
+                    fs::rename(&backup, backup_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, backup_path(&self.path, 1))?;
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Tracing layer writing events as JSON lines to a rotating log file per
+/// component
+pub struct JsonFileLayer {
+    log_dir: PathBuf,
+    max_bytes: u64,
+    max_backups: usize,
+    files: Mutex<HashMap<String, RotatingLogFile>>,
+}
+
+impl JsonFileLayer {
+    /// Layer writing to `<component>.log` files in `log_dir`
+    pub fn new(log_dir: &Path, max_bytes: u64, max_backups: usize) -> io::Result<Self> {
+        fs::create_dir_all(log_dir)?;
+        Ok(Self {
+            log_dir: log_dir.to_path_buf(),
+            max_bytes,
+            max_backups,
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Write an entry to its component's file
+    pub fn write(&self, entry: &LogEntry) -> io::Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let mut files = self.files.lock_or_recover();
+        let file = match files.get_mut(&entry.component) {
+            Some(file) => file,
+            None => {
+                let path = self.log_dir.join(format!("{}.log", entry.component));
+                let file = RotatingLogFile::open(&path, self.max_bytes, self.max_backups)?;
+                files.entry(entry.component.clone()).or_insert(file)
+            }
+        };
+        file.write_line(&line)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for JsonFileLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        let entry = LogEntry {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            component: component_of(metadata.target()),
+            target: metadata.target().to_string(),
+            message: fields.message,
+            fields: fields.fields,
+        };
+        // Logging the failure would come straight back here
+        if let Err(e) = self.write(&entry) {
+            eprintln!("Failed to write log file: {}", e);
+        }
+    }
+}
+
+/// Collects an event's message and fields as JSON
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: serde_json::Value) {
+        match (field.name(), value) {
+            ("message", serde_json::Value::String(message)) => self.message = message,
+            (name, value) => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_events_go_to_rotating_component_files() {
+        assert_eq!(component_of("webx::features::system::proxy::client"), "proxy");
+        assert_eq!(component_of("webx::features::downloads::manager"), "downloads");
+        assert_eq!(component_of("webx::ui::window"), "ui");
+        assert_eq!(component_of("webx"), "browser");
+        assert_eq!(component_of("webxtra::thing"), "external");
+
+        let temp_dir = TempDir::new().unwrap();
+        let layer = JsonFileLayer::new(temp_dir.path(), 300, 2).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..10 {
+                tracing::info!(target: "webx::features::downloads::manager", attempt = i, "Download started");
+            }
+        });
+
+        let log = temp_dir.path().join("downloads.log");
+        let last = fs::read_to_string(&log).unwrap();
+        let entry: LogEntry = serde_json::from_str(last.lines().last().unwrap()).unwrap();
+        assert_eq!(entry.message, "Download started");
+        assert_eq!(entry.fields["attempt"], 9);
+        assert_eq!(entry.level, "INFO");
+        // Old lines move to backups, and only as many backups as allowed are kept
+        assert!(backup_path(&log, 2).exists());
+        assert!(!backup_path(&log, 3).exists());
+    }
+}
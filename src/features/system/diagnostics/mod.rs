@@ -0,0 +1,122 @@
+// Logging and Diagnostics
+pub mod bundle;
+pub mod layer;
+mod zip;
+
+pub use bundle::{bundle_file_name, export_diagnostics, scrub_settings, DiagnosticsBundle, VersionInfo};
+pub use layer::{component_of, JsonFileLayer, LogEntry, RotatingLogFile};
+
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Where logs and crash reports go and how much of them is kept
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub log_dir: PathBuf,
+    pub level: LevelFilter,
+    /// Size at which a component's log file moves to a backup
+    pub max_file_bytes: u64,
+    /// Backups kept of each component's log file
+    pub max_backups: usize,
+    /// Also print log lines to stderr
+    pub stderr: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_dir: default_log_dir(),
+            level: LevelFilter::INFO,
+            max_file_bytes: 5 * 1024 * 1024,
+            max_backups: 3,
+            stderr: true,
+        }
+    }
+}
+
+/// What a panic left behind, written next to the logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub timestamp: DateTime<Utc>,
+    pub version: String,
+    pub thread: Option<String>,
+    pub message: String,
+    /// `file:line:column` the panic came from
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+/// Directory logs and crash reports go to by default
+pub fn default_log_dir() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("webx");
+    path.push("logs");
+    path
+}
+
+/// Send log events to stderr and to JSON log files per component, and write
+/// a crash report with a backtrace when the browser panics
+pub fn init_logging(config: &LoggingConfig) -> Result<(), WebxError> {
+    let files = JsonFileLayer::new(&config.log_dir, config.max_file_bytes, config.max_backups)?;
+    let stderr = config.stderr.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_target(false)
+            .with_writer(std::io::stderr)
+    });
+    tracing_subscriber::registry()
+        .with(config.level)
+        .with(stderr)
+        .with(files)
+        .try_init()
+        .map_err(|e| WebxError::Invalid(format!("Logging is already set up: {}", e)))?;
+    install_panic_hook(&config.log_dir);
+    Ok(())
+}
+
+/// Write a crash report to `log_dir` and log the panic before the default
+/// panic handling runs
+pub fn install_panic_hook(log_dir: &Path) {
+    let log_dir = log_dir.to_path_buf();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = match (info.payload().downcast_ref::<&str>(), info.payload().downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "Box<dyn Any>".to_string(),
+        };
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            thread: std::thread::current().name().map(str::to_string),
+            message,
+            location: info.location().map(|location| location.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+        tracing::error!(
+            location = report.location.as_deref().unwrap_or("unknown"),
+            "Panicked: {}",
+            report.message
+        );
+        match write_crash_report(&log_dir, &report) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write crash report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+// Private helper functions
+
+fn write_crash_report(log_dir: &Path, report: &CrashReport) -> Result<PathBuf, WebxError> {
+    std::fs::create_dir_all(log_dir)?;
+    let path = log_dir.join(format!("crash-{}.json", report.timestamp.format("%Y%m%d-%H%M%S%.3f")));
+    write_atomic(&path, &serde_json::to_vec_pretty(report)?)?;
+    Ok(path)
+}
@@ -0,0 +1,128 @@
+// Zip Archive Writing
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{self, Write};
+
+/// Writes a zip archive of deflated files. Enough for diagnostics bundles:
+/// no directories, encryption or archives over 4 GiB.
+pub struct ZipWriter<W: Write> {
+    out: W,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+    modified: (u16, u16),
+}
+
+/// What the central directory records about a file
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Start an archive whose files are all dated `modified`
+    pub fn new(out: W, modified: NaiveDateTime) -> Self {
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+            modified: dos_date_time(modified),
+        }
+    }
+
+    /// Add a file
+    pub fn add_file(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+
+        let entry = CentralEntry {
+            name: name.to_string(),
+            crc: crc.sum(),
+            compressed_size: to_u32(compressed.len() as u64)?,
+            size: to_u32(contents.len() as u64)?,
+            offset: to_u32(self.offset)?,
+        };
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.common_fields(&mut header, &entry);
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name.as_bytes());
+
+        self.out.write_all(&header)?;
+        self.out.write_all(&compressed)?;
+        self.offset += (header.len() + compressed.len()) as u64;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory, returning the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_offset = to_u32(self.offset)?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            self.common_fields(&mut directory, entry);
+            directory.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many files for a zip archive"))?;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // this disk
+        end.extend_from_slice(&0u16.to_le_bytes()); // disk with the directory
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&to_u32(directory.len() as u64)?.to_le_bytes());
+        end.extend_from_slice(&directory_offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+        self.out.write_all(&directory)?;
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    // Private helper methods
+
+    /// Fields local headers and central directory entries share, from the
+    /// version needed to extract to the file name length
+    fn common_fields(&self, buffer: &mut Vec<u8>, entry: &CentralEntry) {
+        buffer.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        buffer.extend_from_slice(&0x0800u16.to_le_bytes()); // UTF-8 names
+        buffer.extend_from_slice(&8u16.to_le_bytes()); // deflate
+        buffer.extend_from_slice(&self.modified.1.to_le_bytes());
+        buffer.extend_from_slice(&self.modified.0.to_le_bytes());
+        buffer.extend_from_slice(&entry.crc.to_le_bytes());
+        buffer.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        buffer.extend_from_slice(&entry.size.to_le_bytes());
+        buffer.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    }
+}
+
+// Private helper functions
+
+/// MS-DOS `(date, time)`, which zip files date entries with
+fn dos_date_time(time: NaiveDateTime) -> (u16, u16) {
+    let year = time.year().clamp(1980, 2107) as u16;
+    let date = ((year - 1980) << 9) | ((time.month() as u16) << 5) | time.day() as u16;
+    let time = ((time.hour() as u16) << 11) | ((time.minute() as u16) << 5) | (time.second() as u16 / 2);
+    (date, time)
+}
+
+fn to_u32(size: u64) -> io::Result<u32> {
+    u32::try_from(size).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large for a zip archive"))
+}
@@ -0,0 +1,412 @@
+// Single Instance Lock and URL Handoff
+use crate::error::WebxError;
+use crate::runtime::BrowserRuntime;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// How long a second instance waits for the running one to accept its URLs
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Largest handoff message the running instance reads
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// Message a second invocation sends to the running instance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InstanceMessage {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<CaptureRequest>,
+}
+
+/// Screenshot of a page the running instance should take and save
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureRequest {
+    pub url: String,
+    /// Absolute path of the PNG file to write
+    pub path: PathBuf,
+    pub full_page: bool,
+}
+
+/// Events from other invocations of the browser
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstanceEvent {
+    /// Open these URLs as new tabs; an empty list just asks for focus
+    OpenUrls(Vec<String>),
+    /// Open the page in a new tab and save a screenshot once it loaded
+    Capture(CaptureRequest),
+}
+
+/// Makes sure only one browser process runs per profile. The first process
+/// holds a lock file and listens on a local socket (a named pipe on
+/// Windows); later invocations forward their URL arguments to it and exit.
+pub struct SingleInstance {
+    runtime_dir: PathBuf,
+    lock_file: Option<File>,
+    listener_task: Option<JoinHandle<()>>,
+    tx: mpsc::UnboundedSender<InstanceEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<InstanceEvent>>>,
+}
+
+impl SingleInstance {
+    /// Create new single instance guard for the lock and socket in
+    /// `runtime_dir`
+    pub fn new(runtime_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let runtime_dir = runtime_dir.unwrap_or_else(|| {
+            let mut path = dirs::runtime_dir()
+                .or_else(dirs::cache_dir)
+                .unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        fs::create_dir_all(&runtime_dir)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        Ok(Self {
+            runtime_dir,
+            lock_file: None,
+            listener_task: None,
+            tx,
+            rx: Mutex::new(Some(rx)),
+        })
+    }
+
+    /// Take the instance lock. Returns false if another process holds it,
+    /// in which case URLs should be forwarded with `forward_urls`.
+    pub fn try_acquire(&mut self) -> Result<bool, WebxError> {
+        if self.lock_file.is_some() {
+            return Ok(true);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(self.lock_path())?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0)?;
+                writeln!(file, "{}", std::process::id())?;
+                self.lock_file = Some(file);
+                Ok(true)
+            }
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Whether this process holds the instance lock
+    pub fn is_primary(&self) -> bool {
+        self.lock_file.is_some()
+    }
+
+    /// Send URLs to the running instance, waiting until it acknowledges
+    /// them. Relative file paths are resolved against this process's
+    /// working directory first.
+    pub fn forward_urls(&self, urls: &[String]) -> Result<(), WebxError> {
+        let cwd = std::env::current_dir()?;
+        self.forward_message(&InstanceMessage {
+            urls: urls.iter().map(|url| launch_url(url, &cwd)).collect(),
+            capture: None,
+        })
+    }
+
+    /// Ask the running instance to screenshot a page. Returns once the
+    /// request was accepted; the running instance writes the file later.
+    pub fn forward_capture(&self, url: &str, path: &Path, full_page: bool) -> Result<(), WebxError> {
+        let cwd = std::env::current_dir()?;
+        self.forward_message(&InstanceMessage {
+            urls: Vec::new(),
+            capture: Some(CaptureRequest {
+                url: launch_url(url, &cwd),
+                path: cwd.join(path),
+                full_page,
+            }),
+        })
+    }
+
+    /// Listen for URLs from later invocations. Must be called inside the
+    /// browser runtime after the lock was acquired.
+    pub fn start_listening(&mut self) -> Result<(), WebxError> {
+        if !self.is_primary() {
+            return Err(WebxError::Locked(
+                "Another browser instance holds the instance lock".to_string(),
+            ));
+        }
+        if self.listener_task.is_some() {
+            return Ok(());
+        }
+
+        let handle = BrowserRuntime::current()?;
+        let task = self.spawn_listener(&handle)?;
+        self.listener_task = Some(task);
+        Ok(())
+    }
+
+    /// Stop listening for other invocations
+    pub fn stop_listening(&mut self) {
+        if let Some(task) = self.listener_task.take() {
+            task.abort();
+            #[cfg(unix)]
+            let _ = fs::remove_file(self.socket_path());
+        }
+    }
+
+    /// Whether the listener is running
+    pub fn is_listening(&self) -> bool {
+        self.listener_task.is_some()
+    }
+
+    /// Subscribe to events from other invocations
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<InstanceEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn forward_message(&self, message: &InstanceMessage) -> Result<(), WebxError> {
+        let mut payload = serde_json::to_string(message)?;
+        payload.push('\n');
+
+        // The running instance may hold the lock but not be listening yet
+        let deadline = Instant::now() + FORWARD_TIMEOUT;
+        loop {
+            match self.send_message(payload.as_bytes()) {
+                Ok(()) => return Ok(()),
+                Err(e) if Instant::now() < deadline => {
+                    tracing::debug!("Running instance not reachable yet: {}", e);
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    return Err(WebxError::Network(format!(
+                        "Could not reach the running browser: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.runtime_dir.join("instance.lock")
+    }
+
+    #[cfg(unix)]
+    fn socket_path(&self) -> PathBuf {
+        self.runtime_dir.join("instance.sock")
+    }
+
+    #[cfg(windows)]
+    fn pipe_name(&self) -> String {
+        // One pipe per profile directory, which is already per user
+        let digest = md5::compute(self.runtime_dir.to_string_lossy().as_bytes());
+        format!(r"\\.\pipe\webx-{:x}", digest)
+    }
+
+    #[cfg(unix)]
+    fn send_message(&self, payload: &[u8]) -> std::io::Result<()> {
+        let stream = std::os::unix::net::UnixStream::connect(self.socket_path())?;
+        stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+        exchange_message(stream, payload)
+    }
+
+    #[cfg(windows)]
+    fn send_message(&self, payload: &[u8]) -> std::io::Result<()> {
+        let pipe = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(self.pipe_name())?;
+        exchange_message(pipe, payload)
+    }
+
+    #[cfg(unix)]
+    fn spawn_listener(&self, handle: &tokio::runtime::Handle) -> Result<JoinHandle<()>, WebxError> {
+        // Holding the lock means any socket left behind belongs to a dead process
+        let socket_path = self.socket_path();
+        let _ = fs::remove_file(&socket_path);
+        let listener = tokio::net::UnixListener::bind(&socket_path)?;
+
+        let tx = self.tx.clone();
+        Ok(handle.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve_connection(stream, tx.clone()));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Instance socket stopped accepting: {}", e);
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    #[cfg(windows)]
+    fn spawn_listener(&self, handle: &tokio::runtime::Handle) -> Result<JoinHandle<()>, WebxError> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let pipe_name = self.pipe_name();
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&pipe_name)?;
+
+        let tx = self.tx.clone();
+        Ok(handle.spawn(async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    tracing::warn!("Instance pipe stopped accepting: {}", e);
+                    break;
+                }
+                // Open the next pipe instance before serving this client
+                let connected = server;
+                server = match ServerOptions::new().create(&pipe_name) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        tracing::warn!("Could not reopen instance pipe: {}", e);
+                        break;
+                    }
+                };
+                tokio::spawn(serve_connection(connected, tx.clone()));
+            }
+        }))
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        self.stop_listening();
+    }
+}
+
+/// Turn a command line argument into something the address bar can load:
+/// URLs pass through, existing paths become absolute `file://` URLs
+pub fn launch_url(arg: &str, cwd: &Path) -> String {
+    // A one letter scheme is a Windows drive letter, not a URL
+    if let Ok(url) = url::Url::parse(arg) {
+        if url.scheme().len() > 1 {
+            return arg.to_string();
+        }
+    }
+
+    let path = cwd.join(arg);
+    if path.exists() {
+        let path = path.canonicalize().unwrap_or(path);
+        if let Ok(url) = url::Url::from_file_path(&path) {
+            return url.to_string();
+        }
+    }
+    arg.to_string()
+}
+
+// Private helper functions
+
+fn exchange_message<S: Read + Write>(mut stream: S, payload: &[u8]) -> std::io::Result<()> {
+    stream.write_all(payload)?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Unexpected reply from running instance: {:?}", reply.trim()),
+        ))
+    }
+}
+
+async fn serve_connection<S>(stream: S, tx: mpsc::UnboundedSender<InstanceEvent>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Err(e) = handle_message(stream, &tx).await {
+        tracing::warn!("Ignoring message from another instance: {}", e);
+    }
+}
+
+async fn handle_message<S>(stream: S, tx: &mpsc::UnboundedSender<InstanceEvent>) -> Result<(), WebxError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader.take(MAX_MESSAGE_BYTES))
+        .read_line(&mut line)
+        .await?;
+
+    let message: InstanceMessage = serde_json::from_str(&line)?;
+    let event = match message.capture {
+        Some(capture) => InstanceEvent::Capture(capture),
+        None => InstanceEvent::OpenUrls(message.urls),
+    };
+    let _ = tx.send(event);
+    writer.write_all(b"ok\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_second_instance_forwards_urls() {
+        let temp_dir = TempDir::new().unwrap();
+        let runtime = BrowserRuntime::new(Some(1)).unwrap();
+
+        let mut primary = SingleInstance::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let mut secondary = SingleInstance::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(primary.try_acquire().unwrap());
+        assert!(!secondary.try_acquire().unwrap());
+        assert!(secondary.start_listening().is_err());
+
+        let mut events = primary.subscribe_events();
+        {
+            let _guard = runtime.enter();
+            primary.start_listening().unwrap();
+        }
+
+        secondary
+            .forward_urls(&["https://example.com/".to_string()])
+            .unwrap();
+        assert_eq!(
+            runtime.block_on(events.recv()),
+            Some(InstanceEvent::OpenUrls(vec!["https://example.com/".to_string()]))
+        );
+
+        let out = temp_dir.path().join("shot.png");
+        secondary.forward_capture("https://example.com/", &out, true).unwrap();
+        assert_eq!(
+            runtime.block_on(events.recv()),
+            Some(InstanceEvent::Capture(CaptureRequest {
+                url: "https://example.com/".to_string(),
+                path: out,
+                full_page: true,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_launch_url_resolves_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("page.html"), "<p>hi</p>").unwrap();
+
+        assert_eq!(launch_url("https://example.com", temp_dir.path()), "https://example.com");
+        assert_eq!(launch_url("example.com", temp_dir.path()), "example.com");
+        let file_url = launch_url("page.html", temp_dir.path());
+        assert!(file_url.starts_with("file://"));
+        assert!(file_url.ends_with("/page.html"));
+    }
+}
@@ -0,0 +1,452 @@
+// Local Usage Metrics
+pub mod histogram;
+mod page;
+
+pub use histogram::{Histogram, BUCKET_BOUNDS};
+pub use page::{is_stats_page, render_stats_page, STATS_PAGE_URL};
+
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::system::scheduler::{TaskPriority, TaskScheduler, TaskSpec};
+use crate::utils::LockExt;
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Pages that finished loading
+pub const PAGE_LOADS: &str = "page_loads";
+/// Milliseconds from a page starting to load to its webview finishing
+pub const PAGE_LOAD_TIME: &str = "page_load_ms";
+/// Requests the tracker blocker stopped
+pub const BLOCKER_HITS: &str = "blocker_hits";
+/// HTTP cache lookups that found a fresh response
+pub const CACHE_HITS: &str = "cache_hits";
+/// HTTP cache lookups that found nothing usable
+pub const CACHE_MISSES: &str = "cache_misses";
+/// Milliseconds from launch to the first window showing
+pub const STARTUP_TIME: &str = "startup_ms";
+
+/// How often recorded metrics are written to the store
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often complete days are uploaded, when an endpoint is set
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Metrics configuration. Nothing is recorded until the user turns metrics
+/// on, and nothing leaves the machine unless they also set an endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Where complete days are posted as JSON; `None` keeps them local
+    pub upload_endpoint: Option<String>,
+    /// Days of metrics kept in the store
+    pub retention_days: u32,
+    pub request_timeout_secs: u64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            upload_endpoint: None,
+            retention_days: 90,
+            request_timeout_secs: 30,
+        }
+    }
+}
+
+/// A metric's value over a day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MetricValue {
+    Counter { value: u64 },
+    Histogram(Histogram),
+}
+
+impl MetricValue {
+    /// Add another value of the same metric; values of a different kind
+    /// replace this one
+    pub fn merge(&mut self, other: &MetricValue) {
+        match (self, other) {
+            (MetricValue::Counter { value }, MetricValue::Counter { value: other }) => *value += other,
+            (MetricValue::Histogram(histogram), MetricValue::Histogram(other)) => histogram.merge(other),
+            (this, other) => *this = other.clone(),
+        }
+    }
+}
+
+/// Metrics recorded on one day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyMetrics {
+    pub date: NaiveDate,
+    pub metrics: BTreeMap<String, MetricValue>,
+}
+
+/// Metrics over a range of days, for the stats page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsSummary {
+    /// Days with metrics, oldest first
+    pub days: Vec<DailyMetrics>,
+    /// Every day's metrics merged
+    pub totals: BTreeMap<String, MetricValue>,
+}
+
+impl MetricsSummary {
+    /// Total of a counter, 0 if nothing was counted
+    pub fn counter(&self, name: &str) -> u64 {
+        match self.totals.get(name) {
+            Some(MetricValue::Counter { value }) => *value,
+            _ => 0,
+        }
+    }
+
+    pub fn histogram(&self, name: &str) -> Option<&Histogram> {
+        match self.totals.get(name) {
+            Some(MetricValue::Histogram(histogram)) if histogram.count > 0 => Some(histogram),
+            _ => None,
+        }
+    }
+
+    /// Fraction of HTTP cache lookups that hit
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let hits = self.counter(CACHE_HITS);
+        let lookups = hits + self.counter(CACHE_MISSES);
+        (lookups > 0).then(|| hits as f64 / lookups as f64)
+    }
+}
+
+/// What is posted to the upload endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsUpload {
+    pub version: String,
+    pub days: Vec<DailyMetrics>,
+}
+
+/// Opt-in counters and histograms of browser use, kept per day in a local
+/// store and shown on the stats page
+pub struct Metrics {
+    config: Mutex<MetricsConfig>,
+    db: Db,
+    days: Tree,
+    meta: Tree,
+    /// Values recorded since the last flush, by day and metric
+    pending: Mutex<BTreeMap<(NaiveDate, String), MetricValue>>,
+    client: reqwest::Client,
+}
+
+impl Metrics {
+    /// Open the metrics store. A given config replaces the stored one.
+    pub fn new(config: Option<MetricsConfig>, db_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let db_path = db_path.unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("metrics.db");
+            path
+        });
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = sled::open(&db_path)?;
+        let meta = db.open_tree("meta")?;
+        let stored = match meta.get("config")? {
+            Some(bytes) => Some(serde_json::from_slice(&bytes)?),
+            None => None,
+        };
+        let config = config.or(stored).unwrap_or_default();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .build()?;
+        Ok(Self {
+            config: Mutex::new(config),
+            days: db.open_tree("days")?,
+            meta,
+            db,
+            pending: Mutex::new(BTreeMap::new()),
+            client,
+        })
+    }
+
+    pub fn config(&self) -> MetricsConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.lock_or_recover().enabled
+    }
+
+    /// Turn recording on or off. Turning it off drops what was not yet
+    /// flushed; stored days stay until cleared.
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), WebxError> {
+        if !enabled {
+            self.pending.lock_or_recover().clear();
+        }
+        self.update_config(|config| config.enabled = enabled)
+    }
+
+    /// Set where complete days are uploaded, or `None` to keep them local
+    pub fn set_upload_endpoint(&self, endpoint: Option<String>) -> Result<(), WebxError> {
+        let endpoint = endpoint.filter(|endpoint| !endpoint.trim().is_empty());
+        if let Some(endpoint) = &endpoint {
+            let url = url::Url::parse(endpoint).map_err(|e| WebxError::Invalid(format!("Bad upload endpoint: {}", e)))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(WebxError::Invalid(format!("Upload endpoint must be http or https: {}", endpoint)));
+            }
+        }
+        self.update_config(|config| config.upload_endpoint = endpoint)
+    }
+
+    /// Add to a counter
+    pub fn increment(&self, name: &str, by: u64) {
+        self.add(name, MetricValue::Counter { value: by });
+    }
+
+    /// Record a value in a histogram
+    pub fn record(&self, name: &str, value: f64) {
+        let mut histogram = Histogram::default();
+        histogram.record(value);
+        self.add(name, MetricValue::Histogram(histogram));
+    }
+
+    /// Record a duration in milliseconds in a histogram
+    pub fn record_duration(&self, name: &str, duration: Duration) {
+        self.record(name, duration.as_secs_f64() * 1000.0);
+    }
+
+    /// Count an HTTP cache lookup
+    pub fn record_cache_lookup(&self, hit: bool) {
+        self.increment(if hit { CACHE_HITS } else { CACHE_MISSES }, 1);
+    }
+
+    /// Write recorded values to the store and drop days past retention
+    pub fn flush(&self) -> Result<(), WebxError> {
+        let pending = std::mem::take(&mut *self.pending.lock_or_recover());
+        for ((date, name), value) in pending {
+            let key = day_key(date, &name);
+            let value = match self.days.get(&key)? {
+                Some(bytes) => {
+                    let mut stored: MetricValue = serde_json::from_slice(&bytes)?;
+                    stored.merge(&value);
+                    stored
+                }
+                None => value,
+            };
+            self.days.insert(key, serde_json::to_vec(&value)?)?;
+        }
+
+        let retention_days = self.config.lock_or_recover().retention_days;
+        let cutoff = Utc::now().date_naive() - ChronoDuration::days(i64::from(retention_days));
+        for key in self.days.range(..day_key(cutoff, "")).keys() {
+            self.days.remove(key?)?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Metrics of the last `days` days, today included
+    pub fn summary(&self, days: u32) -> Result<MetricsSummary, WebxError> {
+        self.flush()?;
+        let since = Utc::now().date_naive() - ChronoDuration::days(i64::from(days.saturating_sub(1)));
+        let days = self.days_since(since)?;
+        let mut totals: BTreeMap<String, MetricValue> = BTreeMap::new();
+        for day in &days {
+            for (name, value) in &day.metrics {
+                match totals.get_mut(name) {
+                    Some(total) => total.merge(value),
+                    None => {
+                        totals.insert(name.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        Ok(MetricsSummary { days, totals })
+    }
+
+    /// Delete every recorded metric
+    pub fn clear(&self) -> Result<(), WebxError> {
+        self.pending.lock_or_recover().clear();
+        self.days.clear()?;
+        self.meta.remove("uploaded_through")?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Post complete days not uploaded yet to the configured endpoint,
+    /// returning how many were sent. Does nothing while metrics are off or
+    /// no endpoint is set.
+    pub async fn upload(&self) -> Result<usize, WebxError> {
+        let config = self.config();
+        let Some(endpoint) = config.upload_endpoint.filter(|_| config.enabled) else {
+            return Ok(0);
+        };
+        self.flush()?;
+
+        let today = Utc::now().date_naive();
+        let uploaded_through: Option<NaiveDate> = match self.meta.get("uploaded_through")? {
+            Some(bytes) => Some(serde_json::from_slice(&bytes)?),
+            None => None,
+        };
+        let since = uploaded_through
+            .map(|date| date + ChronoDuration::days(1))
+            .unwrap_or(NaiveDate::MIN);
+        let days: Vec<DailyMetrics> = self.days_since(since)?.into_iter().filter(|day| day.date < today).collect();
+        let Some(last) = days.last().map(|day| day.date) else {
+            return Ok(0);
+        };
+
+        let upload = MetricsUpload {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            days,
+        };
+        self.client.post(&endpoint).json(&upload).send().await?.error_for_status()?;
+        self.meta.insert("uploaded_through", serde_json::to_vec(&last)?)?;
+        tracing::info!("Uploaded {} days of metrics to {}", upload.days.len(), endpoint);
+        Ok(upload.days.len())
+    }
+
+    /// Flush regularly, and upload as low-priority network work
+    pub fn schedule(metrics: Arc<Self>, scheduler: &TaskScheduler) {
+        let flushing = Arc::clone(&metrics);
+        scheduler.register(TaskSpec::new("metrics-flush", FLUSH_INTERVAL), move || {
+            let metrics = Arc::clone(&flushing);
+            async move { metrics.flush() }
+        });
+        let spec = TaskSpec::new("metrics-upload", UPLOAD_INTERVAL)
+            .with_priority(TaskPriority::Low)
+            .uses_network();
+        scheduler.register(spec, move || {
+            let metrics = Arc::clone(&metrics);
+            async move { metrics.upload().await.map(|_| ()) }
+        });
+    }
+
+    // Private helper methods
+
+    fn add(&self, name: &str, value: MetricValue) {
+        if !self.is_enabled() {
+            return;
+        }
+        let key = (Utc::now().date_naive(), name.to_string());
+        let mut pending = self.pending.lock_or_recover();
+        match pending.get_mut(&key) {
+            Some(existing) => existing.merge(&value),
+            None => {
+                pending.insert(key, value);
+            }
+        }
+    }
+
+    fn update_config(&self, update: impl FnOnce(&mut MetricsConfig)) -> Result<(), WebxError> {
+        let mut config = self.config.lock_or_recover();
+        update(&mut config);
+        self.meta.insert("config", serde_json::to_vec(&*config)?)?;
+        Ok(())
+    }
+
+    /// Stored days from `since` on, oldest first
+    fn days_since(&self, since: NaiveDate) -> Result<Vec<DailyMetrics>, WebxError> {
+        let mut days: Vec<DailyMetrics> = Vec::new();
+        for entry in self.days.range(day_key(since, "")..) {
+            let (key, bytes) = entry?;
+            let key = String::from_utf8_lossy(&key).to_string();
+            let Some((date, name)) = key.split_once('/') else {
+                continue;
+            };
+            let Ok(date) = date.parse::<NaiveDate>() else {
+                continue;
+            };
+            let value: MetricValue = serde_json::from_slice(&bytes)?;
+            match days.last_mut() {
+                Some(day) if day.date == date => {
+                    day.metrics.insert(name.to_string(), value);
+                }
+                _ => days.push(DailyMetrics {
+                    date,
+                    metrics: BTreeMap::from([(name.to_string(), value)]),
+                }),
+            }
+        }
+        Ok(days)
+    }
+}
+
+impl SettingsProvider for Metrics {
+    fn module(&self) -> &str {
+        "metrics"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("metrics.enabled", "Record usage statistics", false)
+                .with_description("Count page loads, blocked requests, cache hits and load times on this device")
+                .with_category(SettingCategory::Privacy),
+            SettingDefinition::text("metrics.upload_endpoint", "Statistics upload address", "")
+                .with_description("Send each day's statistics here; leave empty to keep them on this device")
+                .with_category(SettingCategory::Privacy),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "metrics.enabled" => self.set_enabled(value.as_bool().ok_or("Expected a toggle value")?),
+            "metrics.upload_endpoint" => self.set_upload_endpoint(value.as_str().map(|s| s.to_string())),
+            _ => Err(format!("Unknown metrics setting {}", key).into()),
+        }
+    }
+}
+
+// Private helper functions
+
+/// Store key of a metric on a day; ISO dates keep keys in date order
+fn day_key(date: NaiveDate, name: &str) -> String {
+    format!("{}/{}", date.format("%Y-%m-%d"), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_metrics_are_opt_in_and_kept_per_day() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("metrics.db");
+        let metrics = Metrics::new(None, Some(db_path.clone())).unwrap();
+
+        metrics.increment(PAGE_LOADS, 1);
+        assert!(metrics.summary(7).unwrap().days.is_empty());
+
+        metrics.set_enabled(true).unwrap();
+        metrics.increment(PAGE_LOADS, 2);
+        metrics.increment(PAGE_LOADS, 1);
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(false);
+        for ms in [40.0, 80.0, 150.0, 900.0] {
+            metrics.record(PAGE_LOAD_TIME, ms);
+        }
+        metrics.flush().unwrap();
+        metrics.increment(PAGE_LOADS, 1);
+
+        let summary = metrics.summary(7).unwrap();
+        assert_eq!(summary.days.len(), 1);
+        assert_eq!(summary.counter(PAGE_LOADS), 4);
+        assert_eq!(summary.cache_hit_rate(), Some(2.0 / 3.0));
+        let load_times = summary.histogram(PAGE_LOAD_TIME).unwrap();
+        assert_eq!(load_times.count, 4);
+        assert_eq!(load_times.mean(), Some(292.5));
+        assert_eq!(load_times.quantile(0.5), Some(100.0));
+        assert_eq!(load_times.quantile(1.0), Some(900.0));
+        assert!(metrics.set_upload_endpoint(Some("ftp://stats.example".to_string())).is_err());
+
+        // The opt-in outlives the process; clearing forgets the data only
+        drop(metrics);
+        let metrics = Metrics::new(None, Some(db_path)).unwrap();
+        assert!(metrics.is_enabled());
+        metrics.clear().unwrap();
+        assert_eq!(metrics.summary(7).unwrap().counter(PAGE_LOADS), 0);
+    }
+}
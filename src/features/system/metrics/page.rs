@@ -0,0 +1,134 @@
+// Usage Statistics Page
+use super::{
+    MetricsConfig, MetricsSummary, BLOCKER_HITS, CACHE_HITS, CACHE_MISSES, PAGE_LOADS, PAGE_LOAD_TIME, STARTUP_TIME,
+};
+use crate::utils::escape_html;
+
+/// Address of the statistics page
+pub const STATS_PAGE_URL: &str = "webx://stats";
+
+/// Whether a URL is the statistics page
+pub fn is_stats_page(url: &str) -> bool {
+    url.strip_prefix(STATS_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Render recorded metrics with totals and a row per day. Buttons send
+/// `type: 'stats'` IPC messages with an `action` of `enable`, `disable` or
+/// `clear`.
+pub fn render_stats_page(summary: &MetricsSummary, config: &MetricsConfig) -> String {
+    let status = match (&config.enabled, &config.upload_endpoint) {
+        (false, _) => "Statistics are off. Nothing about your browsing is recorded.".to_string(),
+        (true, None) => "Statistics are recorded on this device only.".to_string(),
+        (true, Some(endpoint)) => format!(
+            "Statistics are recorded on this device, and each day's totals are sent to {}.",
+            escape_html(endpoint)
+        ),
+    };
+    let toggle = if config.enabled {
+        r#"<button data-action="disable">Turn Off</button>"#
+    } else {
+        r#"<button data-action="enable">Turn On</button>"#
+    };
+
+    let body = if summary.days.is_empty() {
+        r#"<p class="empty">No statistics recorded yet.</p>"#.to_string()
+    } else {
+        let cards = [
+            ("Pages loaded", summary.counter(PAGE_LOADS).to_string()),
+            ("Average load time", milliseconds(summary.histogram(PAGE_LOAD_TIME).and_then(|h| h.mean()))),
+            ("95% of loads within", milliseconds(summary.histogram(PAGE_LOAD_TIME).and_then(|h| h.quantile(0.95)))),
+            ("Requests blocked", summary.counter(BLOCKER_HITS).to_string()),
+            (
+                "Cache hit rate",
+                summary
+                    .cache_hit_rate()
+                    .map(|rate| format!("{:.0}%", rate * 100.0))
+                    .unwrap_or_else(|| "–".to_string()),
+            ),
+            ("Average startup", milliseconds(summary.histogram(STARTUP_TIME).and_then(|h| h.mean()))),
+        ];
+        let cards: String = cards
+            .iter()
+            .map(|(label, value)| format!(r#"<div class="card"><span class="value">{}</span>{}</div>"#, value, label))
+            .collect();
+
+        let rows: String = summary
+            .days
+            .iter()
+            .rev()
+            .map(|day| {
+                let day_summary = MetricsSummary {
+                    days: Vec::new(),
+                    totals: day.metrics.clone(),
+                };
+                let lookups = day_summary.counter(CACHE_HITS) + day_summary.counter(CACHE_MISSES);
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    day.date.format("%Y-%m-%d"),
+                    day_summary.counter(PAGE_LOADS),
+                    milliseconds(day_summary.histogram(PAGE_LOAD_TIME).and_then(|h| h.mean())),
+                    day_summary.counter(BLOCKER_HITS),
+                    lookups,
+                )
+            })
+            .collect();
+        format!(
+            r#"<div class="cards">{}</div>
+    <table><thead><tr><th>Day</th><th>Pages</th><th>Avg. load</th><th>Blocked</th><th>Cache lookups</th></tr></thead>
+    <tbody>{}</tbody></table>"#,
+            cards, rows
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Statistics</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Statistics</h1></header>
+    <p>{status}</p>
+    <p>{toggle} <button data-action="clear">Clear Statistics</button></p>
+    {body}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        status = status,
+        toggle = toggle,
+        body = body,
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+.cards { display: grid; grid-template-columns: repeat(3, 1fr); gap: 0.8em; margin: 1.5em 0; }
+.card { border: 1px solid #eee; border-radius: 4px; padding: 0.8em; color: #666; font-size: 0.85em; }
+.card .value { display: block; font-size: 1.6em; color: #222; }
+table { width: 100%; border-collapse: collapse; font-size: 0.9em; }
+th, td { text-align: right; padding: 0.3em 0.5em; border-bottom: 1px solid #eee; }
+th:first-child, td:first-child { text-align: left; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (button) window.ipc.send({ type: 'stats', action: button.dataset.action });
+});
+"#;
+
+// Private helper functions
+
+fn milliseconds(value: Option<f64>) -> String {
+    match value {
+        Some(ms) if ms >= 1000.0 => format!("{:.1} s", ms / 1000.0),
+        Some(ms) => format!("{:.0} ms", ms),
+        None => "–".to_string(),
+    }
+}
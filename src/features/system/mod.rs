@@ -2,8 +2,30 @@
 pub mod shortcuts;
 pub mod proxy;
 pub mod user_agent;
+pub mod resources;
+pub mod instance;
+pub mod default_browser;
+pub mod notifications;
+pub mod tray;
+pub mod diagnostics;
+pub mod metrics;
+pub mod conditions;
+pub mod scheduler;
+pub mod protocol_handlers;
+pub mod backup;
 
 // Re-export for convenience
 pub use shortcuts::*;
 pub use proxy::*;
-pub use user_agent::*;
\ No newline at end of file
+pub use user_agent::*;
+pub use resources::*;
+pub use instance::*;
+pub use default_browser::*;
+pub use notifications::*;
+pub use tray::*;
+pub use diagnostics::*;
+pub use metrics::*;
+pub use conditions::*;
+pub use scheduler::*;
+pub use protocol_handlers::*;
+pub use backup::*;
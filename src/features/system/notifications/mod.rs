@@ -0,0 +1,472 @@
+// Desktop Notifications Module
+pub mod push;
+
+pub use push::{
+    push_settle_script, HttpPushTransport, PushChannel, PushDelivery, PushFuture, PushManager, PushSubscription,
+    PushSubscriptionKeys, PushTransport, PUSH_FETCH_TASK, PUSH_SCRIPT,
+};
+
+use crate::error::WebxError;
+use crate::features::downloads::{DownloadEvent, DownloadManager};
+use crate::features::security::permissions::{PermissionKind, PermissionManager, PermissionState};
+use crate::utils::LockExt;
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// How urgent a notification is; the OS may style or order them by this
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum NotificationUrgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// A notification to show on the desktop
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    /// Site that sent the notification; None for the browser's own
+    pub origin: Option<String>,
+    pub urgency: NotificationUrgency,
+}
+
+impl Notification {
+    /// Create a browser notification
+    pub fn new(title: &str, body: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            body: body.to_string(),
+            origin: None,
+            urgency: NotificationUrgency::Normal,
+        }
+    }
+}
+
+/// Daily window during which a site's notifications are held back. The
+/// window may wrap past midnight, e.g. 22:00 to 07:00.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Check if a time of day falls in the window
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What happened to a notification
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NotificationOutcome {
+    Shown,
+    /// Held back by do-not-disturb or the site's quiet hours
+    Suppressed,
+    /// The site has no notification permission
+    NotPermitted,
+}
+
+/// Shows notifications through the operating system
+pub trait NotificationBackend: Send + Sync {
+    fn show(&self, notification: &Notification) -> Result<(), WebxError>;
+}
+
+/// Notification backend using the platform's notification service:
+/// libnotify on Linux, Notification Center on macOS and toasts on Windows
+pub struct SystemNotificationBackend;
+
+impl NotificationBackend for SystemNotificationBackend {
+    fn show(&self, notification: &Notification) -> Result<(), WebxError> {
+        #[cfg(target_os = "linux")]
+        {
+            let urgency = match notification.urgency {
+                NotificationUrgency::Low => "low",
+                NotificationUrgency::Normal => "normal",
+                NotificationUrgency::Critical => "critical",
+            };
+            run_notifier(
+                "notify-send",
+                &[
+                    "--app-name=WebX",
+                    &format!("--urgency={}", urgency),
+                    &notification.title,
+                    &notification.body,
+                ],
+            )
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(&notification.body),
+                applescript_string(&notification.title)
+            );
+            run_notifier("osascript", &["-e", &script])
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let script = format!(
+                "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null; \
+                 $template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+                 $text = $template.GetElementsByTagName('text'); \
+                 $text.Item(0).AppendChild($template.CreateTextNode({})) | Out-Null; \
+                 $text.Item(1).AppendChild($template.CreateTextNode({})) | Out-Null; \
+                 [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('WebX').Show([Windows.UI.Notifications.ToastNotification]::new($template))",
+                powershell_string(&notification.title),
+                powershell_string(&notification.body)
+            );
+            run_notifier("powershell", &["-NoProfile", "-NonInteractive", "-Command", &script])
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        {
+            let _ = notification;
+            Err(WebxError::Invalid(
+                "Desktop notifications are not supported on this platform".to_string(),
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct NotificationSettings {
+    do_not_disturb: bool,
+    quiet_hours: HashMap<String, QuietHours>,
+}
+
+/// Shows download and site notifications, honoring site permissions,
+/// per-origin quiet hours and global do-not-disturb
+pub struct NotificationManager {
+    settings: Arc<Mutex<NotificationSettings>>,
+    settings_path: PathBuf,
+    backend: Arc<dyn NotificationBackend>,
+    permissions: Option<Arc<PermissionManager>>,
+    download_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl NotificationManager {
+    /// Create new notification manager
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        let manager = Self {
+            settings: Arc::new(Mutex::new(NotificationSettings::default())),
+            settings_path: config_dir.join("notifications.json"),
+            backend: Arc::new(SystemNotificationBackend),
+            permissions: None,
+            download_task: Mutex::new(None),
+        };
+
+        manager.load_settings()?;
+
+        Ok(manager)
+    }
+
+    /// Show notifications through another backend
+    pub fn set_backend(&mut self, backend: Arc<dyn NotificationBackend>) {
+        self.backend = backend;
+    }
+
+    /// Check site notifications against the permission store
+    pub fn set_permission_manager(&mut self, permissions: Arc<PermissionManager>) {
+        self.permissions = Some(permissions);
+    }
+
+    /// Show a notification from the browser itself. Do-not-disturb holds
+    /// back everything except critical notifications.
+    pub async fn notify(&self, notification: Notification) -> Result<NotificationOutcome, WebxError> {
+        if self.is_do_not_disturb() && notification.urgency != NotificationUrgency::Critical {
+            return Ok(NotificationOutcome::Suppressed);
+        }
+        self.show(notification).await
+    }
+
+    /// Show a Web Notification from a site, if the site may send one now
+    pub async fn notify_site(&self, origin: &str, title: &str, body: &str) -> Result<NotificationOutcome, WebxError> {
+        let origin = PermissionManager::normalize_origin(origin);
+        let permitted = self
+            .permissions
+            .as_ref()
+            .is_some_and(|permissions| {
+                permissions.get_permission(&origin, PermissionKind::Notifications) == PermissionState::Granted
+            });
+        if !permitted {
+            return Ok(NotificationOutcome::NotPermitted);
+        }
+
+        if self.is_do_not_disturb() || self.is_quiet(&origin, current_time()) {
+            return Ok(NotificationOutcome::Suppressed);
+        }
+
+        self.show(Notification {
+            title: title.to_string(),
+            body: body.to_string(),
+            origin: Some(origin),
+            urgency: NotificationUrgency::Normal,
+        })
+        .await
+    }
+
+    /// Turn global do-not-disturb on or off
+    pub fn set_do_not_disturb(&self, enabled: bool) -> Result<(), WebxError> {
+        self.settings.lock_or_recover().do_not_disturb = enabled;
+        self.save_settings()
+    }
+
+    /// Check if do-not-disturb is on
+    pub fn is_do_not_disturb(&self) -> bool {
+        self.settings.lock_or_recover().do_not_disturb
+    }
+
+    /// Set or clear the quiet hours for a site
+    pub fn set_quiet_hours(&self, origin: &str, quiet_hours: Option<QuietHours>) -> Result<(), WebxError> {
+        let origin = PermissionManager::normalize_origin(origin);
+        {
+            let mut settings = self.settings.lock_or_recover();
+            match quiet_hours {
+                Some(quiet_hours) => {
+                    settings.quiet_hours.insert(origin, quiet_hours);
+                }
+                None => {
+                    settings.quiet_hours.remove(&origin);
+                }
+            }
+        }
+        self.save_settings()
+    }
+
+    /// Get the quiet hours for a site
+    pub fn get_quiet_hours(&self, origin: &str) -> Option<QuietHours> {
+        let origin = PermissionManager::normalize_origin(origin);
+        self.settings.lock_or_recover().quiet_hours.get(&origin).copied()
+    }
+
+    /// Check if a site is in its quiet hours at a time of day
+    pub fn is_quiet(&self, origin: &str, time: NaiveTime) -> bool {
+        self.get_quiet_hours(origin)
+            .is_some_and(|quiet_hours| quiet_hours.contains(time))
+    }
+
+    /// Show a toast when a download completes, fails, is blocked or waits
+    /// for confirmation. Must be called inside the browser runtime; takes
+    /// the download manager's events.
+    pub fn start_download_notifications(manager: Arc<NotificationManager>, downloads: Arc<DownloadManager>) {
+        let mut events = downloads.subscribe_events();
+        let task_manager = manager.clone();
+        let handle = tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                let notification = match event {
+                    // Batches are told about once, when all their files are done
+                    DownloadEvent::Completed(id) | DownloadEvent::Failed(id, _) if downloads.batch_of(id).is_some() => None,
+                    DownloadEvent::BatchCompleted(batch_id, completed, failed) => downloads.get_batch(batch_id).map(|batch| {
+                        let folder = download_name(&batch.folder.to_string_lossy());
+                        let body = match failed {
+                            0 => format!("Saved {} files to {}", completed, folder),
+                            _ => format!("Saved {} files to {}; {} failed", completed, folder, failed),
+                        };
+                        Notification::new("Downloads complete", &body)
+                    }),
+                    DownloadEvent::Completed(id) => downloads.get_download(id).map(|download| {
+                        Notification::new("Download complete", &download_name(&download.path))
+                    }),
+                    DownloadEvent::Failed(id, error) => downloads.get_download(id).map(|download| Notification {
+                        urgency: NotificationUrgency::Critical,
+                        ..Notification::new(
+                            "Download failed",
+                            &format!("{}: {}", download_name(&download.path), error),
+                        )
+                    }),
+                    DownloadEvent::ConfirmationRequired(id, decision) => downloads.get_download(id).map(|download| {
+                        Notification::new(
+                            "Download needs confirmation",
+                            &format!("{}: {}", download_name(&download.path), decision.reason),
+                        )
+                    }),
+                    DownloadEvent::HandoffReady(_, handoff) => handoff.prompt.map(|prompt| {
+                        Notification::new("Download ready", &format!("{} Open it from the downloads list.", prompt))
+                    }),
+                    DownloadEvent::Blocked(id, reason) => downloads.get_download(id).map(|download| Notification {
+                        urgency: NotificationUrgency::Critical,
+                        ..Notification::new(
+                            "Download blocked",
+                            &format!("{}: {}", download_name(&download.path), reason),
+                        )
+                    }),
+                    _ => None,
+                };
+
+                if let Some(notification) = notification {
+                    if let Err(e) = task_manager.notify(notification).await {
+                        tracing::warn!("Failed to show download notification: {}", e);
+                    }
+                }
+            }
+        });
+
+        manager.stop_download_notifications();
+        *manager.download_task.lock_or_recover() = Some(handle);
+    }
+
+    /// Stop showing download notifications
+    pub fn stop_download_notifications(&self) {
+        if let Some(handle) = self.download_task.lock_or_recover().take() {
+            handle.abort();
+        }
+    }
+
+    // Private helper methods
+
+    async fn show(&self, notification: Notification) -> Result<NotificationOutcome, WebxError> {
+        // Backends talk to the OS, which may block
+        let backend = self.backend.clone();
+        tokio::task::spawn_blocking(move || backend.show(&notification)).await??;
+        Ok(NotificationOutcome::Shown)
+    }
+
+    fn save_settings(&self) -> Result<(), WebxError> {
+        let settings = self.settings.lock_or_recover();
+        let content = serde_json::to_string_pretty(&*settings)?;
+        std::fs::write(&self.settings_path, content)?;
+        Ok(())
+    }
+
+    fn load_settings(&self) -> Result<(), WebxError> {
+        if self.settings_path.exists() {
+            let content = std::fs::read_to_string(&self.settings_path)?;
+            *self.settings.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+fn current_time() -> NaiveTime {
+    chrono::Local::now().time()
+}
+
+fn download_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run_notifier(program: &str, args: &[&str]) -> Result<(), WebxError> {
+    let output = std::process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(WebxError::Invalid(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        shown: Mutex<Vec<Notification>>,
+    }
+
+    impl NotificationBackend for RecordingBackend {
+        fn show(&self, notification: &Notification) -> Result<(), WebxError> {
+            self.shown.lock_or_recover().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_site_notifications_need_permission_and_respect_dnd() {
+        let temp_dir = TempDir::new().unwrap();
+        let permissions = Arc::new(PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap());
+        let backend = Arc::new(RecordingBackend::default());
+        let mut manager = NotificationManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        manager.set_backend(backend.clone());
+        manager.set_permission_manager(permissions.clone());
+
+        let origin = "https://chat.example.com/room/1";
+        assert_eq!(
+            manager.notify_site(origin, "Hi", "New message").await.unwrap(),
+            NotificationOutcome::NotPermitted
+        );
+
+        permissions
+            .set_permission(origin, PermissionKind::Notifications, PermissionState::Granted)
+            .unwrap();
+        assert_eq!(
+            manager.notify_site(origin, "Hi", "New message").await.unwrap(),
+            NotificationOutcome::Shown
+        );
+        assert_eq!(
+            backend.shown.lock_or_recover()[0].origin.as_deref(),
+            Some("https://chat.example.com")
+        );
+
+        manager.set_do_not_disturb(true).unwrap();
+        assert_eq!(
+            manager.notify_site(origin, "Hi", "Again").await.unwrap(),
+            NotificationOutcome::Suppressed
+        );
+        let critical = Notification {
+            urgency: NotificationUrgency::Critical,
+            ..Notification::new("Download failed", "file.zip")
+        };
+        assert_eq!(manager.notify(critical).await.unwrap(), NotificationOutcome::Shown);
+
+        // Do-not-disturb is remembered
+        let reloaded = NotificationManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(reloaded.is_do_not_disturb());
+    }
+
+    #[test]
+    fn test_quiet_hours_wrap_midnight() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = NotificationManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+
+        manager
+            .set_quiet_hours(
+                "https://news.example.com/",
+                Some(QuietHours { start: at(22), end: at(7) }),
+            )
+            .unwrap();
+        assert!(manager.is_quiet("https://news.example.com", at(23)));
+        assert!(manager.is_quiet("https://news.example.com", at(3)));
+        assert!(!manager.is_quiet("https://news.example.com", at(12)));
+        assert!(!manager.is_quiet("https://other.example.com", at(23)));
+    }
+}
@@ -0,0 +1,739 @@
+// Web Push Client
+use super::{NotificationManager, NotificationOutcome};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::features::security::permissions::{PermissionKind, PermissionManager, PermissionState};
+use crate::features::system::scheduler::{TaskScheduler, TaskSpec};
+use crate::ipc::PushAction;
+use crate::utils::LockExt;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hkdf::Hkdf;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Name of the scheduled task fetching push messages
+pub const PUSH_FETCH_TASK: &str = "push-fetch";
+
+/// How often the push service is asked for new messages
+const FETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Page script standing in for the engine's PushManager, which has no push
+/// service behind it in a webview. Requests go out as "push" messages and
+/// are settled through `window.webxPush.settle`.
+pub const PUSH_SCRIPT: &str = r#"
+(function () {
+    if (!window.ipc || window.webxPush || !window.ServiceWorkerRegistration) return;
+    var pending = {};
+    var next = 1;
+
+    function call(message) {
+        return new Promise(function (resolve, reject) {
+            var token = next++;
+            pending[token] = { resolve: resolve, reject: reject };
+            message.type = 'push';
+            message.token = token;
+            window.ipc.send(message);
+        });
+    }
+
+    function toBase64Url(key) {
+        if (typeof key === 'string') return key;
+        var bytes = new Uint8Array(key.buffer || key, key.byteOffset || 0, key.byteLength);
+        var text = '';
+        for (var i = 0; i < bytes.length; i++) text += String.fromCharCode(bytes[i]);
+        return btoa(text).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '');
+    }
+
+    function fromBase64Url(text) {
+        var binary = atob(text.replace(/-/g, '+').replace(/_/g, '/'));
+        var bytes = new Uint8Array(binary.length);
+        for (var i = 0; i < binary.length; i++) bytes[i] = binary.charCodeAt(i);
+        return bytes.buffer;
+    }
+
+    function subscription(scope, json, key) {
+        if (!json) return null;
+        return {
+            endpoint: json.endpoint,
+            expirationTime: null,
+            options: { userVisibleOnly: true, applicationServerKey: key ? fromBase64Url(key) : null },
+            getKey: function (name) { return json.keys[name] ? fromBase64Url(json.keys[name]) : null; },
+            toJSON: function () { return json; },
+            unsubscribe: function () { return call({ action: 'unsubscribe', scope: scope }); }
+        };
+    }
+
+    function pushManager(scope) {
+        return {
+            subscribe: function (options) {
+                options = options || {};
+                if (!options.userVisibleOnly) {
+                    return Promise.reject(new DOMException('Push messages must be shown to the user', 'NotAllowedError'));
+                }
+                var key = options.applicationServerKey ? toBase64Url(options.applicationServerKey) : null;
+                return call({ action: 'subscribe', scope: scope, application_server_key: key }).then(function (json) {
+                    return subscription(scope, json, key);
+                });
+            },
+            getSubscription: function () {
+                return call({ action: 'get', scope: scope }).then(function (json) {
+                    return subscription(scope, json, json && json.application_server_key);
+                });
+            },
+            permissionState: function () {
+                return Promise.resolve(Notification.permission === 'default' ? 'prompt' : Notification.permission);
+            }
+        };
+    }
+
+    window.webxPush = {
+        settle: function (token, error, value) {
+            var request = pending[token];
+            delete pending[token];
+            if (!request) return;
+            if (error) request.reject(new DOMException(error, 'AbortError'));
+            else request.resolve(value);
+        }
+    };
+    Object.defineProperty(ServiceWorkerRegistration.prototype, 'pushManager', {
+        configurable: true,
+        get: function () { return pushManager(this.scope); }
+    });
+})();
+"#;
+
+/// A site's push subscription as `PushSubscription.toJSON()` gives it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub expiration_time: Option<i64>,
+    pub keys: PushSubscriptionKeys,
+    /// VAPID key the subscription was made with, base64url
+    #[serde(rename = "application_server_key")]
+    pub application_server_key: Option<String>,
+}
+
+/// Public half of a subscription's keys, base64url
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PushSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// A channel on the push service; its endpoint is what sites send to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PushChannel {
+    pub id: String,
+    pub endpoint: String,
+    /// Secret for reading the channel's messages
+    pub token: String,
+}
+
+/// An encrypted message waiting on the push service
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushDelivery {
+    pub id: String,
+    /// `aes128gcm` encoded body, as the application server sent it
+    pub body: Vec<u8>,
+}
+
+pub type PushFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, WebxError>> + Send + 'a>>;
+
+/// Connection to a push service holding messages for the browser's channels
+pub trait PushTransport: Send + Sync {
+    /// Open a channel, restricted to senders holding the VAPID key if given
+    fn register<'a>(&'a self, application_server_key: Option<&'a str>) -> PushFuture<'a, PushChannel>;
+    fn unregister<'a>(&'a self, channel: &'a PushChannel) -> PushFuture<'a, ()>;
+    /// Messages waiting on a channel
+    fn fetch<'a>(&'a self, channel: &'a PushChannel) -> PushFuture<'a, Vec<PushDelivery>>;
+    /// Drop a message from the service once handled
+    fn acknowledge<'a>(&'a self, channel: &'a PushChannel, message_id: &'a str) -> PushFuture<'a, ()>;
+}
+
+/// Push service reached over HTTPS and polled for messages:
+/// `POST /channels` opens a channel, `GET /channels/{id}/messages` lists
+/// waiting messages as `{id, body}` with base64url bodies, and
+/// `DELETE /channels/{id}/messages/{message}` acknowledges one. Channel
+/// requests carry the channel's token as a bearer token.
+pub struct HttpPushTransport {
+    server: url::Url,
+    client: Client,
+}
+
+impl HttpPushTransport {
+    pub fn new(server: &str) -> Result<Self, WebxError> {
+        let mut server = url::Url::parse(server)?;
+        if server.scheme() != "https" {
+            return Err(WebxError::Invalid("The push service must use https".to_string()));
+        }
+        if !server.path().ends_with('/') {
+            server.set_path(&format!("{}/", server.path()));
+        }
+        Ok(Self {
+            server,
+            client: Client::builder().timeout(Duration::from_secs(30)).build().unwrap_or_default(),
+        })
+    }
+
+    // Private helper methods
+
+    fn channel_url(&self, channel: &PushChannel, rest: &str) -> Result<url::Url, WebxError> {
+        Ok(self.server.join(&format!("channels/{}{}", channel.id, rest))?)
+    }
+}
+
+impl PushTransport for HttpPushTransport {
+    fn register<'a>(&'a self, application_server_key: Option<&'a str>) -> PushFuture<'a, PushChannel> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(self.server.join("channels")?)
+                .json(&serde_json::json!({ "application_server_key": application_server_key }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(response.json().await?)
+        })
+    }
+
+    fn unregister<'a>(&'a self, channel: &'a PushChannel) -> PushFuture<'a, ()> {
+        Box::pin(async move {
+            self.client
+                .delete(self.channel_url(channel, "")?)
+                .bearer_auth(&channel.token)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+
+    fn fetch<'a>(&'a self, channel: &'a PushChannel) -> PushFuture<'a, Vec<PushDelivery>> {
+        Box::pin(async move {
+            #[derive(Deserialize)]
+            struct WireDelivery {
+                id: String,
+                body: String,
+            }
+
+            let deliveries: Vec<WireDelivery> = self
+                .client
+                .get(self.channel_url(channel, "/messages")?)
+                .bearer_auth(&channel.token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            deliveries
+                .into_iter()
+                .map(|delivery| {
+                    Ok(PushDelivery {
+                        id: delivery.id,
+                        body: URL_SAFE_NO_PAD.decode(delivery.body.trim_end_matches('='))?,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn acknowledge<'a>(&'a self, channel: &'a PushChannel, message_id: &'a str) -> PushFuture<'a, ()> {
+        Box::pin(async move {
+            self.client
+                .delete(self.channel_url(channel, &format!("/messages/{}", message_id))?)
+                .bearer_auth(&channel.token)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// A subscription with its private key. Kept in the profile like the rest
+/// of the site data; the key only opens messages for this subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PushRegistration {
+    origin: String,
+    scope: String,
+    channel: PushChannel,
+    application_server_key: Option<String>,
+    private_key: String,
+    auth: String,
+    created_at: DateTime<Utc>,
+}
+
+impl PushRegistration {
+    fn secret_key(&self) -> Result<SecretKey, WebxError> {
+        SecretKey::from_slice(&URL_SAFE_NO_PAD.decode(&self.private_key)?)
+            .map_err(|_| WebxError::Crypto("Invalid push subscription key".to_string()))
+    }
+
+    fn subscription(&self) -> Result<PushSubscription, WebxError> {
+        let public_key = self.secret_key()?.public_key().to_encoded_point(false);
+        Ok(PushSubscription {
+            endpoint: self.channel.endpoint.clone(),
+            expiration_time: None,
+            keys: PushSubscriptionKeys {
+                p256dh: URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+                auth: self.auth.clone(),
+            },
+            application_server_key: self.application_server_key.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PushState {
+    /// Push service address; push is off without one
+    server: Option<String>,
+    subscriptions: Vec<PushRegistration>,
+}
+
+/// Keeps sites' push subscriptions and shows their messages as desktop
+/// notifications, whether or not a tab of the site is open. Only sites
+/// allowed to notify may subscribe, and taking the permission away ends
+/// their subscriptions.
+pub struct PushManager {
+    state: Mutex<PushState>,
+    state_path: PathBuf,
+    transport: Mutex<Option<Arc<dyn PushTransport>>>,
+    notifications: Arc<NotificationManager>,
+    permissions: Arc<PermissionManager>,
+}
+
+impl PushManager {
+    /// Create new push manager
+    pub fn new(
+        config_dir: Option<PathBuf>,
+        notifications: Arc<NotificationManager>,
+        permissions: Arc<PermissionManager>,
+    ) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let state_path = config_dir.join("push.json");
+        let state: PushState = match std::fs::read(&state_path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PushState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let transport = match &state.server {
+            Some(server) => Some(Arc::new(HttpPushTransport::new(server)?) as Arc<dyn PushTransport>),
+            None => None,
+        };
+
+        Ok(Self {
+            state: Mutex::new(state),
+            state_path,
+            transport: Mutex::new(transport),
+            notifications,
+            permissions,
+        })
+    }
+
+    /// Push service address, if push is set up
+    pub fn server(&self) -> Option<String> {
+        self.state.lock_or_recover().server.clone()
+    }
+
+    /// Use a push service, or turn push off with None. Subscriptions belong
+    /// to the service they were made on, so changing it drops them; sites
+    /// subscribe again on their next visit.
+    pub fn set_server(&self, server: Option<&str>) -> Result<(), WebxError> {
+        let transport = match server {
+            Some(server) => Some(Arc::new(HttpPushTransport::new(server)?) as Arc<dyn PushTransport>),
+            None => None,
+        };
+        {
+            let mut state = self.state.lock_or_recover();
+            if state.server.as_deref() == server {
+                return Ok(());
+            }
+            state.server = server.map(str::to_string);
+            state.subscriptions.clear();
+        }
+        *self.transport.lock_or_recover() = transport;
+        self.save()
+    }
+
+    /// Reach the push service through another transport
+    pub fn set_transport(&self, transport: Arc<dyn PushTransport>) {
+        *self.transport.lock_or_recover() = Some(transport);
+    }
+
+    /// Subscribe a service worker scope of a site to push messages, or give
+    /// back its subscription if it has one with the same VAPID key
+    pub async fn subscribe(
+        &self,
+        origin: &str,
+        scope: &str,
+        application_server_key: Option<&str>,
+    ) -> Result<PushSubscription, WebxError> {
+        let origin = PermissionManager::normalize_origin(origin);
+        if PermissionManager::normalize_origin(scope) != origin {
+            return Err(WebxError::Invalid(format!("{} cannot subscribe {}", origin, scope)));
+        }
+        if !self.permitted(&origin) {
+            return Err(WebxError::Invalid(format!("{} may not show notifications", origin)));
+        }
+        let application_server_key = application_server_key.map(vapid_key).transpose()?;
+
+        if let Some(existing) = self.find(&origin, scope) {
+            if existing.application_server_key == application_server_key {
+                return existing.subscription();
+            }
+            return Err(WebxError::Invalid(
+                "The scope is already subscribed with a different application server key".to_string(),
+            ));
+        }
+
+        let transport = self.transport().ok_or("No push service is set up")?;
+        let channel = transport.register(application_server_key.as_deref()).await?;
+        let mut auth = [0u8; 16];
+        OsRng.fill_bytes(&mut auth);
+        let registration = PushRegistration {
+            origin,
+            scope: scope.to_string(),
+            channel,
+            application_server_key,
+            private_key: URL_SAFE_NO_PAD.encode(SecretKey::random(&mut OsRng).to_bytes()),
+            auth: URL_SAFE_NO_PAD.encode(auth),
+            created_at: Utc::now(),
+        };
+        let subscription = registration.subscription()?;
+        self.state.lock_or_recover().subscriptions.push(registration);
+        self.save()?;
+        Ok(subscription)
+    }
+
+    /// Subscription of a service worker scope of a site
+    pub fn get_subscription(&self, origin: &str, scope: &str) -> Result<Option<PushSubscription>, WebxError> {
+        self.find(origin, scope).map(|registration| registration.subscription()).transpose()
+    }
+
+    /// End a scope's subscription; false if it had none
+    pub async fn unsubscribe(&self, origin: &str, scope: &str) -> Result<bool, WebxError> {
+        let Some(registration) = self.find(origin, scope) else {
+            return Ok(false);
+        };
+        if let Some(transport) = self.transport() {
+            if let Err(e) = transport.unregister(&registration.channel).await {
+                tracing::warn!("Failed to close push channel for {}: {}", scope, e);
+            }
+        }
+        self.state
+            .lock_or_recover()
+            .subscriptions
+            .retain(|other| other.origin != registration.origin || other.scope != scope);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Fetch waiting messages and show them. Messages for sites that lost
+    /// the notification permission end their subscriptions instead.
+    /// Returns the number of notifications shown.
+    pub async fn poll(&self) -> Result<usize, WebxError> {
+        let Some(transport) = self.transport() else {
+            return Ok(0);
+        };
+        let registrations = self.state.lock_or_recover().subscriptions.clone();
+        let mut shown = 0;
+        for registration in registrations {
+            if !self.permitted(&registration.origin) {
+                self.unsubscribe(&registration.origin, &registration.scope).await?;
+                continue;
+            }
+
+            for delivery in transport.fetch(&registration.channel).await? {
+                match decrypt_message(&registration, &delivery.body) {
+                    Ok(payload) => {
+                        let (title, body) = notification_text(&registration.origin, &payload);
+                        let outcome = self.notifications.notify_site(&registration.origin, &title, &body).await?;
+                        if outcome == NotificationOutcome::Shown {
+                            shown += 1;
+                        }
+                    }
+                    Err(e) => tracing::warn!("Dropped a push message for {}: {}", registration.origin, e),
+                }
+                transport.acknowledge(&registration.channel, &delivery.id).await?;
+            }
+        }
+        Ok(shown)
+    }
+
+    /// Carry out a request of `PUSH_SCRIPT` from a page showing `origin`
+    pub async fn page_request(&self, origin: &str, scope: &str, action: PushAction) -> Result<serde_json::Value, WebxError> {
+        match action {
+            PushAction::Subscribe { application_server_key } => Ok(serde_json::to_value(
+                self.subscribe(origin, scope, application_server_key.as_deref()).await?,
+            )?),
+            PushAction::Get => Ok(serde_json::to_value(self.get_subscription(origin, scope)?)?),
+            PushAction::Unsubscribe => Ok(serde_json::Value::Bool(self.unsubscribe(origin, scope).await?)),
+        }
+    }
+
+    /// Fetch push messages on the browser's scheduler
+    pub fn schedule(manager: Arc<Self>, scheduler: &TaskScheduler) {
+        let spec = TaskSpec::new(PUSH_FETCH_TASK, FETCH_INTERVAL).uses_network();
+        scheduler.register(spec, move || {
+            let manager = Arc::clone(&manager);
+            async move {
+                manager.poll().await?;
+                Ok(())
+            }
+        });
+    }
+
+    // Private helper methods
+
+    fn permitted(&self, origin: &str) -> bool {
+        self.permissions.get_permission(origin, PermissionKind::Notifications) == PermissionState::Granted
+    }
+
+    fn transport(&self) -> Option<Arc<dyn PushTransport>> {
+        self.transport.lock_or_recover().clone()
+    }
+
+    fn find(&self, origin: &str, scope: &str) -> Option<PushRegistration> {
+        let origin = PermissionManager::normalize_origin(origin);
+        self.state
+            .lock_or_recover()
+            .subscriptions
+            .iter()
+            .find(|registration| registration.origin == origin && registration.scope == scope)
+            .cloned()
+    }
+
+    fn save(&self) -> Result<(), WebxError> {
+        let content = serde_json::to_vec_pretty(&*self.state.lock_or_recover())?;
+        write_atomic(&self.state_path, &content)?;
+        Ok(())
+    }
+}
+
+/// Script settling the page's promise for a `PUSH_SCRIPT` request
+pub fn push_settle_script(token: u64, result: Result<serde_json::Value, WebxError>) -> String {
+    match result {
+        Ok(value) => format!("window.webxPush && window.webxPush.settle({}, null, {});", token, value),
+        Err(e) => format!(
+            "window.webxPush && window.webxPush.settle({}, {}, null);",
+            token,
+            serde_json::Value::String(e.to_string())
+        ),
+    }
+}
+
+// Private helper functions
+
+/// Check a VAPID public key is an uncompressed P-256 point, as base64url
+fn vapid_key(key: &str) -> Result<String, WebxError> {
+    let bytes = URL_SAFE_NO_PAD.decode(key.trim_end_matches('='))?;
+    if bytes.len() != 65 || PublicKey::from_sec1_bytes(&bytes).is_err() {
+        return Err(WebxError::Invalid("The application server key is not a P-256 public key".to_string()));
+    }
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Open an `aes128gcm` message (RFC 8188) encrypted for a subscription as
+/// Web Push (RFC 8291) does it
+fn decrypt_message(registration: &PushRegistration, body: &[u8]) -> Result<Vec<u8>, WebxError> {
+    let invalid = |what: &str| WebxError::Crypto(format!("Invalid push message: {}", what));
+
+    // Header: salt, record size, and the sender's public key as key ID
+    let (salt, rest) = body.split_at_checked(16).ok_or_else(|| invalid("too short"))?;
+    let (record_size, rest) = rest.split_at_checked(4).ok_or_else(|| invalid("too short"))?;
+    let (key_length, rest) = rest.split_first().ok_or_else(|| invalid("too short"))?;
+    let (sender_key, records) = rest.split_at_checked(*key_length as usize).ok_or_else(|| invalid("too short"))?;
+    let record_size = u32::from_be_bytes([record_size[0], record_size[1], record_size[2], record_size[3]]) as usize;
+    if record_size < 18 || records.is_empty() {
+        return Err(invalid("bad record size"));
+    }
+
+    let secret_key = registration.secret_key()?;
+    let sender = PublicKey::from_sec1_bytes(sender_key).map_err(|_| invalid("bad sender key"))?;
+    let shared = p256::ecdh::diffie_hellman(secret_key.to_nonzero_scalar(), sender.as_affine());
+    let auth = URL_SAFE_NO_PAD.decode(&registration.auth)?;
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(secret_key.public_key().to_encoded_point(false).as_bytes());
+    key_info.extend_from_slice(sender_key);
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&auth), shared.raw_secret_bytes())
+        .expand(&key_info, &mut ikm)
+        .map_err(|_| invalid("key derivation failed"))?;
+
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), &ikm);
+    let mut content_key = [0u8; 16];
+    let mut base_nonce = [0u8; 12];
+    hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut content_key)
+        .and_then(|_| hkdf.expand(b"Content-Encoding: nonce\0", &mut base_nonce))
+        .map_err(|_| invalid("key derivation failed"))?;
+    let cipher = Aes128Gcm::new_from_slice(&content_key).map_err(|_| invalid("bad content key"))?;
+
+    let record_count = records.len().div_ceil(record_size);
+    let mut plaintext = Vec::new();
+    for (sequence, record) in records.chunks(record_size).enumerate() {
+        let mut nonce = base_nonce;
+        for (byte, sequence_byte) in nonce[4..].iter_mut().zip((sequence as u64).to_be_bytes()) {
+            *byte ^= sequence_byte;
+        }
+        let mut data = cipher.decrypt(Nonce::from_slice(&nonce), record)?;
+
+        // Each record ends in a delimiter and zero padding; 2 marks the last
+        let delimiter = data.iter().rposition(|byte| *byte != 0).ok_or_else(|| invalid("no padding delimiter"))?;
+        let expected = if sequence + 1 == record_count { 2 } else { 1 };
+        if data[delimiter] != expected {
+            return Err(invalid("bad padding delimiter"));
+        }
+        data.truncate(delimiter);
+        plaintext.extend_from_slice(&data);
+    }
+    Ok(plaintext)
+}
+
+/// Notification title and body of a push payload: `{"title", "body"}` as
+/// JSON, or plain text shown under the site's name
+fn notification_text(origin: &str, payload: &[u8]) -> (String, String) {
+    let text = String::from_utf8_lossy(payload);
+    if let Ok(message) = serde_json::from_str::<serde_json::Value>(&text) {
+        if let Some(title) = message["title"].as_str() {
+            return (title.to_string(), message["body"].as_str().unwrap_or_default().to_string());
+        }
+    }
+    let site = url::Url::parse(origin)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| origin.to_string());
+    (site, text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::system::notifications::{Notification, NotificationBackend};
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct FakeService {
+        waiting: Mutex<Vec<PushDelivery>>,
+        acknowledged: Mutex<Vec<String>>,
+    }
+
+    impl PushTransport for FakeService {
+        fn register<'a>(&'a self, _application_server_key: Option<&'a str>) -> PushFuture<'a, PushChannel> {
+            Box::pin(async {
+                Ok(PushChannel {
+                    id: "c1".to_string(),
+                    endpoint: "https://push.example.net/send/c1".to_string(),
+                    token: "secret".to_string(),
+                })
+            })
+        }
+
+        fn unregister<'a>(&'a self, _channel: &'a PushChannel) -> PushFuture<'a, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn fetch<'a>(&'a self, _channel: &'a PushChannel) -> PushFuture<'a, Vec<PushDelivery>> {
+            Box::pin(async { Ok(std::mem::take(&mut *self.waiting.lock_or_recover())) })
+        }
+
+        fn acknowledge<'a>(&'a self, _channel: &'a PushChannel, message_id: &'a str) -> PushFuture<'a, ()> {
+            Box::pin(async move {
+                self.acknowledged.lock_or_recover().push(message_id.to_string());
+                Ok(())
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingBackend(Mutex<Vec<Notification>>);
+
+    impl NotificationBackend for RecordingBackend {
+        fn show(&self, notification: &Notification) -> Result<(), WebxError> {
+            self.0.lock_or_recover().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_messages_become_site_notifications() {
+        let temp_dir = TempDir::new().unwrap();
+        let permissions = Arc::new(PermissionManager::new(Some(temp_dir.path().to_path_buf())).unwrap());
+        let backend = Arc::new(RecordingBackend::default());
+        let mut notifications = NotificationManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        notifications.set_backend(backend.clone());
+        notifications.set_permission_manager(permissions.clone());
+        let push = PushManager::new(Some(temp_dir.path().to_path_buf()), Arc::new(notifications), permissions.clone())
+            .unwrap();
+        let service = Arc::new(FakeService::default());
+        push.set_transport(service.clone());
+
+        // Test vector of RFC 8291, appendix A
+        let vapid = "BP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A8";
+        let scope = "https://chat.example.com/app/";
+        assert!(push.subscribe("https://chat.example.com", scope, Some(vapid)).await.is_err());
+        permissions
+            .set_permission("https://chat.example.com", PermissionKind::Notifications, PermissionState::Granted)
+            .unwrap();
+        assert!(push.subscribe("https://evil.example", scope, Some(vapid)).await.is_err());
+        let subscription = push.subscribe("https://chat.example.com", scope, Some(vapid)).await.unwrap();
+        assert_eq!(subscription.endpoint, "https://push.example.net/send/c1");
+        assert_eq!(push.get_subscription("https://chat.example.com", scope).unwrap(), Some(subscription));
+        assert_eq!(push.get_subscription("https://evil.example", scope).unwrap(), None);
+        assert!(push.subscribe("https://chat.example.com", scope, None).await.is_err());
+
+        {
+            let mut state = push.state.lock_or_recover();
+            state.subscriptions[0].private_key = "q1dXpw3UpT5VOmu_cf_v6ih07Aems3njxI-JWgLcM94".to_string();
+            state.subscriptions[0].auth = "BTBZMqHH6r4Tts7J_aSIgg".to_string();
+        }
+        let body = URL_SAFE_NO_PAD
+            .decode(
+                "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8w\
+                 EqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN",
+            )
+            .unwrap();
+        *service.waiting.lock_or_recover() = vec![
+            PushDelivery { id: "m1".to_string(), body: body.clone() },
+            PushDelivery { id: "m2".to_string(), body: body[..body.len() - 1].to_vec() },
+        ];
+
+        assert_eq!(push.poll().await.unwrap(), 1);
+        let shown = backend.0.lock_or_recover().clone();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].title, "chat.example.com");
+        assert_eq!(shown[0].body, "When I grow up, I want to be a watermelon");
+        // The garbled message is dropped, not fetched again
+        assert_eq!(*service.acknowledged.lock_or_recover(), ["m1", "m2"]);
+
+        // Blocking notifications ends the subscription
+        permissions
+            .set_permission("https://chat.example.com", PermissionKind::Notifications, PermissionState::Denied)
+            .unwrap();
+        *service.waiting.lock_or_recover() = vec![PushDelivery { id: "m3".to_string(), body }];
+        assert_eq!(push.poll().await.unwrap(), 0);
+        assert_eq!(push.get_subscription("https://chat.example.com", scope).unwrap(), None);
+    }
+}
@@ -0,0 +1,599 @@
+// External Protocol Handlers
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::utils::{open_with_system, LockExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+#[cfg(feature = "torrent-engine")]
+use std::sync::Arc;
+
+/// Scheme of BitTorrent magnet links
+pub const MAGNET_SCHEME: &str = "magnet";
+/// Extension of BitTorrent metadata files
+pub const TORRENT_EXTENSION: &str = "torrent";
+
+/// Schemes handed to the system's applications out of the box: mail, phone
+/// calls and video meetings
+const DEFAULT_SYSTEM_SCHEMES: &[&str] = &["mailto", "tel", "zoommtg"];
+/// File types opened with the system's applications out of the box, e.g.
+/// OpenDocument files going to the office suite
+const DEFAULT_SYSTEM_MIME_TYPES: &[&str] = &["application/vnd.oasis.opendocument.*"];
+
+/// What a link or downloaded file is handed to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum HandlerTarget {
+    /// The application the system associates with it
+    #[default]
+    System,
+    /// A program and its arguments, e.g. `transmission-gtk %u`; `%u` is
+    /// replaced by the link or file, which is appended when absent
+    Command(String),
+    /// The built-in torrent engine, in builds with the `torrent-engine`
+    /// feature
+    BuiltIn,
+    /// Nothing; links are ignored and files stay in the download folder
+    Browser,
+    /// A feature of the browser itself, for links only
+    Feature(BrowserFeature),
+}
+
+/// Browser features links can be handed to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BrowserFeature {
+    /// Subscribe to the feed a `feed:` link points at
+    Feeds,
+}
+
+/// How links of a scheme, or files of a type, leave the browser
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HandlerRule {
+    pub target: HandlerTarget,
+    /// Ask the user before launching anything
+    pub ask_first: bool,
+}
+
+impl HandlerRule {
+    pub fn new(target: HandlerTarget) -> Self {
+        Self { target, ask_first: true }
+    }
+}
+
+/// Protocol handler configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProtocolHandlerConfig {
+    /// Rules by lowercase URL scheme
+    pub schemes: BTreeMap<String, HandlerRule>,
+    /// Rules by lowercase file extension, for finished downloads
+    pub file_types: BTreeMap<String, HandlerRule>,
+    /// Rules by lowercase MIME type, such as `text/calendar` or
+    /// `application/vnd.oasis.opendocument.*`, for finished downloads whose
+    /// extension has no rule
+    pub mime_types: BTreeMap<String, HandlerRule>,
+}
+
+impl Default for ProtocolHandlerConfig {
+    fn default() -> Self {
+        let system = |key: &str| (key.to_string(), HandlerRule::new(HandlerTarget::System));
+        let mut schemes: BTreeMap<_, _> = DEFAULT_SYSTEM_SCHEMES.iter().map(|scheme| system(scheme)).collect();
+        schemes.insert(MAGNET_SCHEME.to_string(), HandlerRule::new(HandlerTarget::System));
+        schemes.insert("feed".to_string(), HandlerRule::new(HandlerTarget::Feature(BrowserFeature::Feeds)));
+        Self {
+            schemes,
+            file_types: BTreeMap::from([system(TORRENT_EXTENSION)]),
+            mime_types: DEFAULT_SYSTEM_MIME_TYPES.iter().map(|mime| system(mime)).collect(),
+        }
+    }
+}
+
+/// Which rule a link or file matched
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RuleKey {
+    Scheme(String),
+    FileType(String),
+    MimeType(String),
+}
+
+/// A link or file about to leave the browser
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Handoff {
+    /// The link, or the path of a downloaded file
+    pub target: String,
+    pub handler: HandlerTarget,
+    pub rule: RuleKey,
+    /// Question to put to the user first, if the rule asks for it
+    pub prompt: Option<String>,
+}
+
+/// What the built-in torrent engine is given
+#[cfg(feature = "torrent-engine")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TorrentSource {
+    Magnet(String),
+    File(PathBuf),
+}
+
+/// An in-process torrent client
+#[cfg(feature = "torrent-engine")]
+pub trait TorrentEngine: Send + Sync {
+    /// Start fetching a torrent into the engine's download folder
+    fn add(&self, source: TorrentSource) -> Result<(), WebxError>;
+}
+
+/// Registry of schemes and file types WebX hands to other applications,
+/// such as magnet links and .torrent files going to a torrent client
+pub struct ProtocolHandlers {
+    config: Mutex<ProtocolHandlerConfig>,
+    config_path: Option<PathBuf>,
+    #[cfg(feature = "torrent-engine")]
+    engine: Mutex<Option<Arc<dyn TorrentEngine>>>,
+}
+
+impl ProtocolHandlers {
+    /// Registry with the given rules, kept in memory only
+    pub fn new(config: Option<ProtocolHandlerConfig>) -> Self {
+        Self {
+            config: Mutex::new(config.unwrap_or_default()),
+            config_path: None,
+            #[cfg(feature = "torrent-engine")]
+            engine: Mutex::new(None),
+        }
+    }
+
+    /// Load rules from `config_path` (default: the config directory)
+    pub fn load(config_path: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_path = config_path.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("protocol_handlers.json");
+            path
+        });
+        let config = match std::fs::read(&config_path) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            config_path: Some(config_path),
+            ..Self::new(config)
+        })
+    }
+
+    pub fn config(&self) -> ProtocolHandlerConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    /// Hand a scheme to a handler. Web schemes stay with the browser.
+    pub fn register_scheme(&self, scheme: &str, rule: HandlerRule) -> Result<(), WebxError> {
+        let scheme = scheme.trim().trim_end_matches(':').to_lowercase();
+        if matches!(scheme.as_str(), "http" | "https" | "webx" | "about" | "data" | "blob" | "file" | "javascript") {
+            return Err(WebxError::Invalid(format!("{}: links are handled by the browser", scheme)));
+        }
+        check_target(&rule.target)?;
+        self.config.lock_or_recover().schemes.insert(scheme, rule);
+        self.save()
+    }
+
+    /// Hand downloaded files of an extension to a handler
+    pub fn register_file_type(&self, extension: &str, rule: HandlerRule) -> Result<(), WebxError> {
+        check_file_target(&rule.target)?;
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        self.config.lock_or_recover().file_types.insert(extension, rule);
+        self.save()
+    }
+
+    /// Hand downloaded files of a MIME type, or of a `type/*` family, to a
+    /// handler
+    pub fn register_mime_type(&self, mime: &str, rule: HandlerRule) -> Result<(), WebxError> {
+        check_file_target(&rule.target)?;
+        let mime = mime.trim().to_lowercase();
+        if !mime.contains('/') {
+            return Err(WebxError::Invalid(format!("{} is not a MIME type", mime)));
+        }
+        self.config.lock_or_recover().mime_types.insert(mime, rule);
+        self.save()
+    }
+
+    pub fn unregister_scheme(&self, scheme: &str) -> Result<bool, WebxError> {
+        let removed = self.config.lock_or_recover().schemes.remove(&scheme.to_lowercase()).is_some();
+        self.save()?;
+        Ok(removed)
+    }
+
+    pub fn unregister_file_type(&self, extension: &str) -> Result<bool, WebxError> {
+        let removed = self.config.lock_or_recover().file_types.remove(&extension.to_lowercase()).is_some();
+        self.save()?;
+        Ok(removed)
+    }
+
+    pub fn unregister_mime_type(&self, mime: &str) -> Result<bool, WebxError> {
+        let removed = self.config.lock_or_recover().mime_types.remove(&mime.trim().to_lowercase()).is_some();
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// Whether navigating to a URL must leave the browser
+    pub fn claims_url(&self, url: &str) -> bool {
+        let scheme = url_scheme(url);
+        self.config
+            .lock_or_recover()
+            .schemes
+            .get(&scheme)
+            .is_some_and(|rule| rule.target != HandlerTarget::Browser)
+    }
+
+    /// What to do with a link of a registered scheme
+    pub fn resolve_url(&self, url: &str) -> Option<Handoff> {
+        let scheme = url_scheme(url);
+        let rule = self.config.lock_or_recover().schemes.get(&scheme)?.clone();
+        let prompt = format!("Open this {} link with {}?", scheme, handler_name(&rule.target));
+        Some(handoff(url.to_string(), RuleKey::Scheme(scheme), rule, prompt))
+    }
+
+    /// What to do with a finished download of a registered file type
+    pub fn resolve_file(&self, path: &Path) -> Option<Handoff> {
+        self.resolve_download(path, None)
+    }
+
+    /// What to do with a finished download, by its extension or else the
+    /// MIME type the server gave
+    pub fn resolve_download(&self, path: &Path, mime: Option<&str>) -> Option<Handoff> {
+        let (key, rule) = {
+            let config = self.config.lock_or_recover();
+            let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+            match extension.and_then(|extension| Some((config.file_types.get(&extension)?.clone(), extension))) {
+                Some((rule, extension)) => (RuleKey::FileType(extension), rule),
+                None => {
+                    let (mime, rule) = mime_rule(&config.mime_types, mime?)?;
+                    (RuleKey::MimeType(mime), rule)
+                }
+            }
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let prompt = format!("Open {} with {}?", name, handler_name(&rule.target));
+        Some(handoff(path.to_string_lossy().to_string(), key, rule, prompt))
+    }
+
+    /// Stop asking before handing links or files like a handoff's to its
+    /// handler, the "always use this application" choice
+    pub fn always_use(&self, handoff: &Handoff) -> Result<(), WebxError> {
+        {
+            let mut config = self.config.lock_or_recover();
+            let rules = match &handoff.rule {
+                RuleKey::Scheme(key) => config.schemes.get_mut(key),
+                RuleKey::FileType(key) => config.file_types.get_mut(key),
+                RuleKey::MimeType(key) => config.mime_types.get_mut(key),
+            };
+            let rule = rules.ok_or_else(|| WebxError::NotFound(format!("Handler rule {:?}", handoff.rule)))?;
+            rule.target = handoff.handler.clone();
+            rule.ask_first = false;
+        }
+        self.save()
+    }
+
+    /// Launch a handoff's handler. Callers show the prompt first when there
+    /// is one.
+    pub fn launch(&self, handoff: &Handoff) -> Result<(), WebxError> {
+        match &handoff.handler {
+            HandlerTarget::System => open_with_system(&handoff.target),
+            HandlerTarget::Command(command) => {
+                let (program, args) = command_line(command, &handoff.target)?;
+                std::process::Command::new(program).args(args).spawn()?;
+                Ok(())
+            }
+            HandlerTarget::BuiltIn => self.launch_built_in(&handoff.target),
+            HandlerTarget::Browser => Ok(()),
+            HandlerTarget::Feature(feature) => Err(WebxError::Invalid(format!(
+                "{:?} links are opened by the browser, not launched",
+                feature
+            ))),
+        }
+    }
+
+    /// Use an in-process engine for torrents handed to `BuiltIn`
+    #[cfg(feature = "torrent-engine")]
+    pub fn set_torrent_engine(&self, engine: Arc<dyn TorrentEngine>) {
+        *self.engine.lock_or_recover() = Some(engine);
+    }
+
+    // Private helper methods
+
+    #[cfg(feature = "torrent-engine")]
+    fn launch_built_in(&self, target: &str) -> Result<(), WebxError> {
+        let engine = self
+            .engine
+            .lock_or_recover()
+            .clone()
+            .ok_or_else(|| WebxError::Invalid("The built-in torrent engine is not running".to_string()))?;
+        match url_scheme(target) == MAGNET_SCHEME {
+            true => engine.add(TorrentSource::Magnet(target.to_string())),
+            false => engine.add(TorrentSource::File(PathBuf::from(target))),
+        }
+    }
+
+    #[cfg(not(feature = "torrent-engine"))]
+    fn launch_built_in(&self, _target: &str) -> Result<(), WebxError> {
+        Err(WebxError::Invalid("This build has no built-in torrent engine".to_string()))
+    }
+
+    /// Point both torrent rules at one target
+    fn set_torrent_target(&self, target: HandlerTarget) {
+        let mut config = self.config.lock_or_recover();
+        let ProtocolHandlerConfig { schemes, file_types, .. } = &mut *config;
+        for entry in [
+            schemes.entry(MAGNET_SCHEME.to_string()),
+            file_types.entry(TORRENT_EXTENSION.to_string()),
+        ] {
+            entry.or_insert_with(|| HandlerRule::new(HandlerTarget::System)).target = target.clone();
+        }
+    }
+
+    fn save(&self) -> Result<(), WebxError> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let config = self.config.lock_or_recover().clone();
+        write_atomic(path, &serde_json::to_vec_pretty(&config)?)?;
+        Ok(())
+    }
+}
+
+impl Default for ProtocolHandlers {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl SettingsProvider for ProtocolHandlers {
+    fn module(&self) -> &str {
+        "protocols"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let mut handlers = vec![
+            ("system", "System default application"),
+            ("command", "Custom program"),
+            ("browser", "Do nothing; keep .torrent files"),
+        ];
+        if cfg!(feature = "torrent-engine") {
+            handlers.insert(1, ("builtin", "Built-in torrent engine"));
+        }
+        vec![
+            SettingDefinition::choice("protocols.torrent_handler", "Open torrents with", "system", &handlers)
+                .with_description("Where magnet links and downloaded .torrent files go")
+                .with_category(SettingCategory::Downloads),
+            SettingDefinition::text("protocols.torrent_command", "Torrent program", "")
+                .with_description("Program for the custom choice, e.g. \"qbittorrent %u\"")
+                .with_category(SettingCategory::Downloads),
+            SettingDefinition::toggle("protocols.ask_before_launch", "Ask before opening other applications", true)
+                .with_description("Confirm before a link or download launches another program")
+                .with_category(SettingCategory::Security),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "protocols.torrent_handler" => {
+                let target = match value.as_str().ok_or("Expected a choice value")? {
+                    "system" => HandlerTarget::System,
+                    "builtin" => HandlerTarget::BuiltIn,
+                    "browser" => HandlerTarget::Browser,
+                    // Keep a program already set; the command setting fills it in
+                    "command" => match self.config.lock_or_recover().schemes.get(MAGNET_SCHEME) {
+                        Some(HandlerRule { target: HandlerTarget::Command(command), .. }) => {
+                            HandlerTarget::Command(command.clone())
+                        }
+                        _ => HandlerTarget::Command(String::new()),
+                    },
+                    other => return Err(format!("Unknown torrent handler {}", other).into()),
+                };
+                if target == HandlerTarget::BuiltIn {
+                    check_target(&target)?;
+                }
+                self.set_torrent_target(target);
+            }
+            "protocols.torrent_command" => {
+                let command = value.as_str().ok_or("Expected a text value")?.trim().to_string();
+                if !command.is_empty() {
+                    self.set_torrent_target(HandlerTarget::Command(command));
+                }
+            }
+            "protocols.ask_before_launch" => {
+                let ask = value.as_bool().ok_or("Expected a toggle value")?;
+                let mut config = self.config.lock_or_recover();
+                let ProtocolHandlerConfig { schemes, file_types, mime_types } = &mut *config;
+                for rule in schemes.values_mut().chain(file_types.values_mut()).chain(mime_types.values_mut()) {
+                    rule.ask_first = ask;
+                }
+            }
+            _ => return Err(format!("Unknown protocol handler setting {}", key).into()),
+        }
+        self.save()
+    }
+}
+
+/// Address a `feed:` link points at: `feed:https://…` wraps it, while
+/// `feed://…` stands for http
+pub fn feed_scheme_url(link: &str) -> Option<String> {
+    let rest = link.trim().strip_prefix("feed:")?;
+    let url = match rest.strip_prefix("//") {
+        Some(rest) => format!("http://{}", rest),
+        None => rest.to_string(),
+    };
+    url::Url::parse(&url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|url| url.to_string())
+}
+
+/// Label of the choice to stop asking before handing links or files like
+/// a handoff's to its handler
+pub fn always_label(handoff: &Handoff) -> String {
+    let kind = match &handoff.rule {
+        RuleKey::Scheme(scheme) => format!("{} links", scheme),
+        RuleKey::FileType(extension) => format!(".{} files", extension),
+        RuleKey::MimeType(mime) => format!("{} files", mime),
+    };
+    format!("Always open {} with {}", kind, handler_name(&handoff.handler))
+}
+
+// Private helper functions
+
+fn url_scheme(url: &str) -> String {
+    url.split_once(':').map(|(scheme, _)| scheme.trim().to_lowercase()).unwrap_or_default()
+}
+
+fn handoff(target: String, key: RuleKey, rule: HandlerRule, prompt: String) -> Handoff {
+    // Nothing is launched for the browser itself, so there is nothing to ask
+    let ask = rule.ask_first && rule.target != HandlerTarget::Browser;
+    Handoff {
+        target,
+        handler: rule.target,
+        rule: key,
+        prompt: ask.then_some(prompt),
+    }
+}
+
+/// Rule for a MIME type, exact before `type/*` families, e.g.
+/// `application/vnd.oasis.opendocument.*`
+fn mime_rule(rules: &BTreeMap<String, HandlerRule>, mime: &str) -> Option<(String, HandlerRule)> {
+    let mime = mime.split(';').next().unwrap_or_default().trim().to_lowercase();
+    if let Some(rule) = rules.get(&mime) {
+        return Some((mime, rule.clone()));
+    }
+    rules
+        .iter()
+        .filter_map(|(pattern, rule)| Some((pattern, rule, pattern.strip_suffix('*')?)))
+        .filter(|(_, _, prefix)| mime.starts_with(prefix))
+        .max_by_key(|(_, _, prefix)| prefix.len())
+        .map(|(pattern, rule, _)| (pattern.clone(), rule.clone()))
+}
+
+fn handler_name(target: &HandlerTarget) -> String {
+    match target {
+        HandlerTarget::System => "the default application".to_string(),
+        HandlerTarget::Command(command) => command
+            .split_whitespace()
+            .next()
+            .and_then(|program| Path::new(program).file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "a custom program".to_string()),
+        HandlerTarget::BuiltIn => "the built-in torrent engine".to_string(),
+        HandlerTarget::Browser => "WebX".to_string(),
+        HandlerTarget::Feature(BrowserFeature::Feeds) => "WebX's feed reader".to_string(),
+    }
+}
+
+fn check_target(target: &HandlerTarget) -> Result<(), WebxError> {
+    if *target == HandlerTarget::BuiltIn && !cfg!(feature = "torrent-engine") {
+        return Err(WebxError::Invalid("This build has no built-in torrent engine".to_string()));
+    }
+    Ok(())
+}
+
+/// Files go to applications, not to browser features
+fn check_file_target(target: &HandlerTarget) -> Result<(), WebxError> {
+    if matches!(target, HandlerTarget::Feature(_)) {
+        return Err(WebxError::Invalid("Browser features only take links".to_string()));
+    }
+    check_target(target)
+}
+
+/// Program and arguments of a handler command for a link or file. The
+/// target is passed as one argument, never through a shell.
+fn command_line(command: &str, target: &str) -> Result<(String, Vec<String>), WebxError> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| WebxError::Invalid("No program set for this handler".to_string()))?
+        .to_string();
+    let mut args: Vec<String> = words.map(|word| word.replace("%u", target)).collect();
+    if !command.contains("%u") {
+        args.push(target.to_string());
+    }
+    Ok((program, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_torrent_handoffs() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("handlers.json");
+        let handlers = ProtocolHandlers::load(Some(config_path.clone())).unwrap();
+        let magnet = "magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+
+        assert!(handlers.claims_url(magnet));
+        assert!(!handlers.claims_url("https://example.com/file.torrent"));
+        let handoff = handlers.resolve_url(magnet).unwrap();
+        assert_eq!(handoff.handler, HandlerTarget::System);
+        assert_eq!(handoff.prompt.as_deref(), Some("Open this magnet link with the default application?"));
+        assert!(handlers.resolve_file(Path::new("/tmp/report.pdf")).is_none());
+
+        handlers
+            .apply_setting("protocols.torrent_command", &SettingValue::String("/usr/bin/qbittorrent %u".to_string()))
+            .unwrap();
+        handlers.apply_setting("protocols.ask_before_launch", &SettingValue::Bool(false)).unwrap();
+        let handoff = handlers.resolve_file(Path::new("/tmp/Ubuntu.TORRENT")).unwrap();
+        assert_eq!(handoff.handler, HandlerTarget::Command("/usr/bin/qbittorrent %u".to_string()));
+        assert_eq!(handoff.prompt, None);
+        assert_eq!(
+            command_line("qbittorrent --skip-dialog=true %u", magnet).unwrap(),
+            ("qbittorrent".to_string(), vec!["--skip-dialog=true".to_string(), magnet.to_string()])
+        );
+        assert_eq!(command_line("transmission-gtk", magnet).unwrap().1, vec![magnet.to_string()]);
+
+        assert!(handlers.register_scheme("https", HandlerRule::new(HandlerTarget::System)).is_err());
+        assert_eq!(
+            handlers.register_scheme("builtin", HandlerRule::new(HandlerTarget::BuiltIn)).is_ok(),
+            cfg!(feature = "torrent-engine")
+        );
+
+        // Rules are saved
+        let reloaded = ProtocolHandlers::load(Some(config_path)).unwrap();
+        assert_eq!(reloaded.resolve_url(magnet).unwrap().handler, handoff.handler);
+    }
+
+    #[test]
+    fn test_mime_rules_and_always_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("handlers.json");
+        let handlers = ProtocolHandlers::load(Some(config_path.clone())).unwrap();
+
+        for url in ["mailto:someone@example.com", "tel:+15550100", "zoommtg://zoom.us/join?confno=1"] {
+            assert!(handlers.claims_url(url), "{}", url);
+        }
+        let feed = handlers.resolve_url("feed:https://example.com/rss").unwrap();
+        assert_eq!(feed.handler, HandlerTarget::Feature(BrowserFeature::Feeds));
+        assert!(handlers.launch(&feed).is_err());
+
+        // Files without an extension rule fall back to their MIME type
+        let odt = Path::new("/tmp/minutes.odt");
+        assert!(handlers.resolve_file(odt).is_none());
+        let handoff = handlers
+            .resolve_download(odt, Some("application/vnd.oasis.opendocument.text; charset=binary"))
+            .unwrap();
+        assert_eq!(handoff.rule, RuleKey::MimeType("application/vnd.oasis.opendocument.*".to_string()));
+        assert!(handoff.prompt.is_some());
+        assert!(always_label(&handoff).starts_with("Always open application/vnd.oasis.opendocument.* files"));
+        assert!(handlers
+            .register_mime_type("application/rss+xml", HandlerRule::new(HandlerTarget::Feature(BrowserFeature::Feeds)))
+            .is_err());
+
+        // "Always" stops the asking, and is remembered
+        handlers.always_use(&handoff).unwrap();
+        let reloaded = ProtocolHandlers::load(Some(config_path)).unwrap();
+        let handoff = reloaded.resolve_download(odt, Some("application/vnd.oasis.opendocument.text")).unwrap();
+        assert_eq!(handoff.prompt, None);
+        assert!(reloaded.resolve_url("mailto:someone@example.com").unwrap().prompt.is_some());
+    }
+}
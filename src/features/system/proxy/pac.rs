@@ -0,0 +1,422 @@
+// PAC (Proxy Auto-Configuration) Files
+use crate::error::WebxError;
+use super::{ProxyConfig, ProxyType};
+use boa_engine::{js_string, Context, JsArgs, JsResult, JsString, JsValue, NativeFunction, Source};
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// How long a FindProxyForURL result is reused for the same host
+const PAC_CACHE_TTL: Duration = Duration::from_secs(300);
+/// Loop and recursion limits so a broken PAC file cannot hang the browser
+const LOOP_ITERATION_LIMIT: u64 = 1_000_000;
+const RECURSION_LIMIT: usize = 256;
+
+/// Helper functions every PAC file may call; dnsResolve is provided natively
+const PAC_UTILS: &str = r#"
+function dnsDomainIs(host, domain) {
+  return host.length >= domain.length &&
+    host.substring(host.length - domain.length) == domain;
+}
+
+function dnsDomainLevels(host) {
+  return host.split('.').length - 1;
+}
+
+function isPlainHostName(host) {
+  return host.indexOf('.') == -1;
+}
+
+function localHostOrDomainIs(host, hostdom) {
+  return host == hostdom || hostdom.lastIndexOf(host + '.', 0) == 0;
+}
+
+function isResolvable(host) {
+  return dnsResolve(host) != null;
+}
+
+function myIpAddress() {
+  return __webxMyIpAddress;
+}
+
+function convert_addr(ipchars) {
+  var bytes = ipchars.split('.');
+  return (((bytes[0] & 0xff) << 24) | ((bytes[1] & 0xff) << 16) |
+    ((bytes[2] & 0xff) << 8) | (bytes[3] & 0xff)) >>> 0;
+}
+
+function isInNet(ipaddr, pattern, maskstr) {
+  if (!/^\d+\.\d+\.\d+\.\d+$/.test(ipaddr)) {
+    ipaddr = dnsResolve(ipaddr);
+    if (ipaddr == null) return false;
+  }
+  var mask = convert_addr(maskstr);
+  return ((convert_addr(ipaddr) & mask) >>> 0) == ((convert_addr(pattern) & mask) >>> 0);
+}
+
+function shExpMatch(str, pattern) {
+  pattern = pattern.replace(/[.+^${}()|[\]\\]/g, '\\$&')
+    .replace(/\*/g, '.*').replace(/\?/g, '.');
+  return new RegExp('^' + pattern + '$').test(str);
+}
+
+function __webxArgs(args) {
+  var list = Array.prototype.slice.call(args);
+  var gmt = list[list.length - 1] === 'GMT';
+  if (gmt) list.pop();
+  return { list: list, now: new Date(), gmt: gmt };
+}
+
+function __webxInRange(start, value, end) {
+  return start <= end ? (value >= start && value <= end) : (value >= start || value <= end);
+}
+
+var __webxDays = ['SUN', 'MON', 'TUE', 'WED', 'THU', 'FRI', 'SAT'];
+var __webxMonths = ['JAN', 'FEB', 'MAR', 'APR', 'MAY', 'JUN',
+  'JUL', 'AUG', 'SEP', 'OCT', 'NOV', 'DEC'];
+
+function weekdayRange() {
+  var a = __webxArgs(arguments);
+  var today = a.gmt ? a.now.getUTCDay() : a.now.getDay();
+  var start = __webxDays.indexOf(a.list[0]);
+  var end = a.list.length > 1 ? __webxDays.indexOf(a.list[1]) : start;
+  if (start < 0 || end < 0) return false;
+  return __webxInRange(start, today, end);
+}
+
+function dateRange() {
+  var a = __webxArgs(arguments);
+  var half = Math.max(1, a.list.length / 2);
+  var first = a.list.slice(0, half);
+  var second = a.list.length > 1 ? a.list.slice(half) : first;
+  // Each bound is a mix of day (1-31), month name and four-digit year
+  var fields = { day: false, month: false, year: false };
+  var key = function (parts, date) {
+    var day = date ? (a.gmt ? date.getUTCDate() : date.getDate()) : 0;
+    var month = date ? (a.gmt ? date.getUTCMonth() : date.getMonth()) : 0;
+    var year = date ? (a.gmt ? date.getUTCFullYear() : date.getFullYear()) : 0;
+    for (var i = 0; i < parts.length; i++) {
+      var part = parts[i];
+      if (typeof part === 'string') { month = __webxMonths.indexOf(part); fields.month = true; }
+      else if (part > 31) { year = part; fields.year = true; }
+      else { day = part; fields.day = true; }
+    }
+    return (fields.year ? year : 0) * 10000 + (fields.month ? month : 0) * 100 + (fields.day ? day : 0);
+  };
+  var start = key(first);
+  var end = key(second);
+  return __webxInRange(start, key([], a.now), end);
+}
+
+function timeRange() {
+  var a = __webxArgs(arguments);
+  var l = a.list;
+  var h = a.gmt ? a.now.getUTCHours() : a.now.getHours();
+  var m = a.gmt ? a.now.getUTCMinutes() : a.now.getMinutes();
+  var s = a.gmt ? a.now.getUTCSeconds() : a.now.getSeconds();
+  var now = h * 3600 + m * 60 + s;
+  if (l.length == 1) return h == l[0];
+  if (l.length == 2) return __webxInRange(l[0] * 3600, now, l[1] * 3600 - 1);
+  if (l.length == 4) return __webxInRange(l[0] * 3600 + l[1] * 60, now, l[2] * 3600 + l[3] * 60 - 1);
+  if (l.length == 6) return __webxInRange(l[0] * 3600 + l[1] * 60 + l[2], now, l[3] * 3600 + l[4] * 60 + l[5]);
+  return false;
+}
+"#;
+
+/// One candidate from a FindProxyForURL result, in order of preference
+#[derive(Debug, Clone, PartialEq)]
+pub enum PacEntry {
+    Direct,
+    Proxy(ProxyConfig),
+}
+
+/// A loaded PAC file
+#[derive(Debug, Clone)]
+pub struct PacScript {
+    source: String,
+}
+
+impl PacScript {
+    /// Load a PAC file, checking that it defines FindProxyForURL
+    pub fn parse(source: &str) -> Result<Self, WebxError> {
+        let script = Self {
+            source: source.to_string(),
+        };
+
+        let mut context = script.context()?;
+        let defined = context
+            .eval(Source::from_bytes("typeof FindProxyForURL === 'function'"))
+            .map_err(|e| e.to_string())?;
+        if !defined.to_boolean() {
+            return Err(WebxError::Parse("PAC file does not define FindProxyForURL".to_string()));
+        }
+
+        Ok(script)
+    }
+
+    /// Fetch a PAC file from an http(s) or file URL. The request never goes
+    /// through a proxy, since the PAC file is what decides the proxy.
+    pub async fn fetch(url: &str, timeout: Duration) -> Result<Self, WebxError> {
+        let parsed = url::Url::parse(url)?;
+        let source = match parsed.scheme() {
+            "file" => {
+                let path = parsed.to_file_path().map_err(|_| "Invalid PAC file path")?;
+                tokio::fs::read_to_string(path).await?
+            }
+            "http" | "https" => {
+                let client = reqwest::Client::builder().no_proxy().timeout(timeout).build()?;
+                let response = client.get(url).send().await?;
+                if !response.status().is_success() {
+                    return Err(WebxError::Network(format!("PAC server returned {}", response.status())));
+                }
+                response.text().await?
+            }
+            scheme => return Err(format!("Unsupported PAC URL scheme: {}", scheme).into()),
+        };
+
+        Self::parse(&source)
+    }
+
+    /// Run FindProxyForURL and return its raw result string
+    pub fn find_proxy_for_url(&self, url: &str) -> Result<String, WebxError> {
+        let parsed = url::Url::parse(url)?;
+        let host = parsed.host_str().ok_or("URL has no host")?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        // Like other browsers, hide the path and query of secure URLs from the script
+        let script_url = if parsed.scheme() == "https" {
+            format!("{}/", parsed.origin().ascii_serialization())
+        } else {
+            url.to_string()
+        };
+
+        let call = format!(
+            "FindProxyForURL({}, {})",
+            serde_json::to_string(&script_url)?,
+            serde_json::to_string(host)?
+        );
+        let mut context = self.context()?;
+        let result = context.eval(Source::from_bytes(&call)).map_err(|e| e.to_string())?;
+        if !result.is_string() {
+            return Err("FindProxyForURL did not return a string".into());
+        }
+        let result = result.to_string(&mut context).map_err(|e| e.to_string())?;
+        Ok(result.to_std_string_escaped())
+    }
+
+    /// Run FindProxyForURL and parse its result
+    pub fn evaluate(&self, url: &str) -> Result<Vec<PacEntry>, WebxError> {
+        let entries = parse_pac_result(&self.find_proxy_for_url(url)?);
+        if entries.is_empty() {
+            return Err("FindProxyForURL returned no usable entries".into());
+        }
+        Ok(entries)
+    }
+
+    // Private helper methods
+
+    fn context(&self) -> Result<Context, WebxError> {
+        let mut context = Context::default();
+        context.runtime_limits_mut().set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+        context.runtime_limits_mut().set_recursion_limit(RECURSION_LIMIT);
+
+        context
+            .register_global_builtin_callable(js_string!("dnsResolve"), 1, NativeFunction::from_fn_ptr(dns_resolve))
+            .map_err(|e| e.to_string())?;
+
+        let my_ip = format!("var __webxMyIpAddress = {};", serde_json::to_string(&local_ip_address())?);
+        context.eval(Source::from_bytes(&my_ip)).map_err(|e| e.to_string())?;
+        context.eval(Source::from_bytes(PAC_UTILS)).map_err(|e| e.to_string())?;
+        context
+            .eval(Source::from_bytes(&self.source))
+            .map_err(|e| format!("PAC file failed to load: {}", e))?;
+
+        Ok(context)
+    }
+}
+
+/// Evaluates the active PAC file, caching results per host
+pub struct PacResolver {
+    script: Option<PacScript>,
+    cache: HashMap<String, (Vec<PacEntry>, Instant)>,
+}
+
+impl PacResolver {
+    /// Create a resolver with no PAC file loaded
+    pub fn new() -> Self {
+        Self {
+            script: None,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Replace the PAC file, dropping results of the previous one
+    pub fn set_script(&mut self, script: Option<PacScript>) {
+        self.script = script;
+        self.cache.clear();
+    }
+
+    /// Check if a PAC file is loaded
+    pub fn has_script(&self) -> bool {
+        self.script.is_some()
+    }
+
+    /// Proxies to try for a URL, in order
+    pub fn resolve(&mut self, url: &str) -> Result<Vec<PacEntry>, WebxError> {
+        let script = self.script.as_ref().ok_or("No PAC file loaded")?;
+        let host = url::Url::parse(url)?
+            .host_str()
+            .ok_or("URL has no host")?
+            .to_lowercase();
+
+        if let Some((entries, at)) = self.cache.get(&host) {
+            if at.elapsed() < PAC_CACHE_TTL {
+                return Ok(entries.clone());
+            }
+        }
+
+        let entries = script.evaluate(url)?;
+        self.cache.insert(host, (entries.clone(), Instant::now()));
+        Ok(entries)
+    }
+
+    /// Forget cached results
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl Default for PacResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse a FindProxyForURL result such as "PROXY a:8080; SOCKS5 b:1080; DIRECT".
+/// Unknown or malformed entries are skipped.
+pub fn parse_pac_result(result: &str) -> Vec<PacEntry> {
+    result
+        .split(';')
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let keyword = parts.next()?.to_uppercase();
+            if keyword == "DIRECT" {
+                return Some(PacEntry::Direct);
+            }
+
+            let (proxy_type, default_port) = match keyword.as_str() {
+                "PROXY" | "HTTP" => (ProxyType::Http, 80),
+                "HTTPS" => (ProxyType::Https, 443),
+                "SOCKS" | "SOCKS5" => (ProxyType::Socks5, 1080),
+                "SOCKS4" => (ProxyType::Socks4, 1080),
+                _ => return None,
+            };
+            let address = parts.next()?;
+            let (host, port) = match address.rsplit_once(':') {
+                // A bare IPv6 address has colons but no port
+                Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                    (host, port.parse().ok()?)
+                }
+                _ => (address, default_port),
+            };
+
+            Some(PacEntry::Proxy(ProxyConfig {
+                proxy_type,
+                host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+                port,
+                auth: None,
+                enabled: true,
+                bypass_domains: vec![],
+            }))
+        })
+        .collect()
+}
+
+fn dns_resolve(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let host = args.get_or_undefined(0).to_string(context)?.to_std_string_escaped();
+    let address = (host.as_str(), 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.find(|a| a.is_ipv4()));
+
+    Ok(match address {
+        Some(address) => JsValue::from(JsString::from(address.ip().to_string().as_str())),
+        None => JsValue::null(),
+    })
+}
+
+fn local_ip_address() -> String {
+    // Connecting a UDP socket sends nothing but picks the outgoing interface
+    std::net::UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("192.0.2.1:80")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pac_result() {
+        let entries = parse_pac_result("PROXY proxy.corp:3128; SOCKS5 [::1]:1080;HTTPS secure.corp; BOGUS x; DIRECT");
+        assert_eq!(entries.len(), 4);
+        match &entries[0] {
+            PacEntry::Proxy(config) => {
+                assert_eq!(config.proxy_type, ProxyType::Http);
+                assert_eq!((config.host.as_str(), config.port), ("proxy.corp", 3128));
+            }
+            PacEntry::Direct => panic!("expected proxy"),
+        }
+        match &entries[1] {
+            PacEntry::Proxy(config) => assert_eq!((config.host.as_str(), config.port), ("::1", 1080)),
+            PacEntry::Direct => panic!("expected proxy"),
+        }
+        match &entries[2] {
+            PacEntry::Proxy(config) => assert_eq!(config.port, 443),
+            PacEntry::Direct => panic!("expected proxy"),
+        }
+        assert_eq!(entries[3], PacEntry::Direct);
+        assert!(parse_pac_result("").is_empty());
+    }
+
+    #[test]
+    fn test_pac_evaluation() {
+        let script = PacScript::parse(
+            r#"
+            function FindProxyForURL(url, host) {
+              if (isPlainHostName(host) || dnsDomainIs(host, ".intranet.corp")) return "DIRECT";
+              if (isInNet(host, "10.0.0.0", "255.0.0.0")) return "SOCKS5 10.0.0.1:1080";
+              if (shExpMatch(url, "http://*.example.com/*")) return "PROXY proxy.corp:3128; DIRECT";
+              if (url.indexOf("secret") != -1) return "PROXY leak:1";
+              return "PROXY fallback.corp:8080";
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(script.evaluate("http://printer/").unwrap(), vec![PacEntry::Direct]);
+        assert_eq!(script.evaluate("https://wiki.intranet.corp/").unwrap(), vec![PacEntry::Direct]);
+        assert_eq!(script.find_proxy_for_url("http://10.1.2.3/").unwrap(), "SOCKS5 10.0.0.1:1080");
+        assert_eq!(script.evaluate("http://www.example.com/page").unwrap().len(), 2);
+        // Paths of https URLs are not shown to the script
+        assert_eq!(script.find_proxy_for_url("https://site.org/secret").unwrap(), "PROXY fallback.corp:8080");
+
+        assert!(PacScript::parse("var x = 1;").is_err());
+        assert!(PacScript::parse("function FindProxyForURL(").is_err());
+
+        // Runaway scripts hit the loop limit instead of hanging
+        let looping = PacScript::parse("function FindProxyForURL(u, h) { while (true) {} }").unwrap();
+        assert!(looping.evaluate("http://a.example/").is_err());
+
+        let mut resolver = PacResolver::new();
+        assert!(resolver.resolve("http://a.example/").is_err());
+        resolver.set_script(Some(script));
+        assert_eq!(resolver.resolve("http://printer/").unwrap(), vec![PacEntry::Direct]);
+        assert!(resolver.cache.contains_key("printer"));
+    }
+}
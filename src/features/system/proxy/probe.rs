@@ -0,0 +1,303 @@
+// Proxy Connectivity Testing
+use super::{ProxyAuth, ProxyConfig, ProxyErrorKind, ProxyRequestError, ProxyRoute, ProxyType};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Probe fetched through the proxy unless the settings name another one
+pub const DEFAULT_PROBE_URL: &str = "http://example.com/";
+
+/// Why a proxy test failed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ProxyTestFailure {
+    InvalidConfig(String),
+    /// No TCP connection to the proxy
+    Unreachable(String),
+    Timeout,
+    /// The proxy speaks a different protocol than configured
+    HandshakeFailed(String),
+    AuthenticationFailed(String),
+    /// Connected to the proxy, but the probe request through it failed
+    ProbeFailed(String),
+}
+
+impl std::fmt::Display for ProxyTestFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProxyTestFailure::InvalidConfig(reason) => write!(f, "Invalid proxy configuration: {}", reason),
+            ProxyTestFailure::Unreachable(reason) => write!(f, "Proxy unreachable: {}", reason),
+            ProxyTestFailure::Timeout => write!(f, "Proxy test timed out"),
+            ProxyTestFailure::HandshakeFailed(reason) => write!(f, "Proxy handshake failed: {}", reason),
+            ProxyTestFailure::AuthenticationFailed(reason) => write!(f, "Proxy authentication failed: {}", reason),
+            ProxyTestFailure::ProbeFailed(reason) => write!(f, "Request through proxy failed: {}", reason),
+        }
+    }
+}
+
+/// Outcome of testing a proxy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyTestResult {
+    /// Proxy description, e.g. "SOCKS5 127.0.0.1:9050"
+    pub proxy: String,
+    pub probe_url: String,
+    pub success: bool,
+    /// Time to open the TCP connection to the proxy
+    pub connect_ms: Option<f64>,
+    /// Time from sending the probe request to receiving response headers
+    pub first_byte_ms: Option<f64>,
+    /// HTTP status of the probe response
+    pub status: Option<u16>,
+    pub failure: Option<ProxyTestFailure>,
+    pub tested_at: DateTime<Utc>,
+}
+
+impl ProxyTestResult {
+    fn new(config: &ProxyConfig, probe_url: &str) -> Self {
+        Self {
+            proxy: config.describe(),
+            probe_url: probe_url.to_string(),
+            success: false,
+            connect_ms: None,
+            first_byte_ms: None,
+            status: None,
+            failure: None,
+            tested_at: Utc::now(),
+        }
+    }
+
+    fn fail(mut self, failure: ProxyTestFailure) -> Self {
+        self.success = false;
+        self.failure = Some(failure);
+        self
+    }
+}
+
+/// Connect to a proxy, check its handshake and credentials, and fetch the
+/// probe URL through it. Each step is limited by `timeout`.
+pub async fn test_proxy(config: &ProxyConfig, probe_url: &str, timeout: Duration) -> ProxyTestResult {
+    let result = ProxyTestResult::new(config, probe_url);
+    if let Err(e) = config.to_url(true) {
+        return result.fail(ProxyTestFailure::InvalidConfig(e.to_string()));
+    }
+
+    // Plain TCP connection, timed on its own
+    let started = Instant::now();
+    let mut stream = match tokio::time::timeout(timeout, TcpStream::connect((config.host.as_str(), config.port))).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return result.fail(ProxyTestFailure::Unreachable(e.to_string())),
+        Err(_) => return result.fail(ProxyTestFailure::Timeout),
+    };
+    let mut result = ProxyTestResult {
+        connect_ms: Some(elapsed_ms(started)),
+        ..result
+    };
+
+    // SOCKS5 reports authentication problems precisely during the handshake
+    if config.proxy_type == ProxyType::Socks5 {
+        match tokio::time::timeout(timeout, socks5_handshake(&mut stream, config.auth.as_ref())).await {
+            Ok(Ok(())) => {}
+            Ok(Err(failure)) => return result.fail(failure),
+            Err(_) => return result.fail(ProxyTestFailure::Timeout),
+        }
+    }
+    drop(stream);
+
+    let route = ProxyRoute::Proxy {
+        config: config.clone(),
+        remote_dns: true,
+    };
+    let client = match route.build_client(timeout) {
+        Ok(client) => client,
+        Err(e) => return result.fail(ProxyTestFailure::InvalidConfig(e.to_string())),
+    };
+
+    let started = Instant::now();
+    let response = match tokio::time::timeout(timeout, client.get(probe_url).send()).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            let error = ProxyRequestError::from_reqwest(probe_url, &route, &e);
+            return result.fail(match error.kind {
+                ProxyErrorKind::Authentication => ProxyTestFailure::AuthenticationFailed(error.message),
+                ProxyErrorKind::Timeout => ProxyTestFailure::Timeout,
+                _ => ProxyTestFailure::ProbeFailed(error.message),
+            });
+        }
+        Err(_) => return result.fail(ProxyTestFailure::Timeout),
+    };
+    result.first_byte_ms = Some(elapsed_ms(started));
+    result.status = Some(response.status().as_u16());
+
+    match response.status() {
+        StatusCode::PROXY_AUTHENTICATION_REQUIRED => result.fail(ProxyTestFailure::AuthenticationFailed(
+            "Proxy rejected the credentials".to_string(),
+        )),
+        // Gateway errors come from the proxy failing to reach the probe
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => result.fail(
+            ProxyTestFailure::ProbeFailed(format!("Proxy returned {}", response.status())),
+        ),
+        _ => {
+            result.success = true;
+            result
+        }
+    }
+}
+
+/// SOCKS5 method negotiation and username/password authentication (RFC 1928/1929)
+async fn socks5_handshake(stream: &mut TcpStream, auth: Option<&ProxyAuth>) -> Result<(), ProxyTestFailure> {
+    let handshake_error = |e: std::io::Error| ProxyTestFailure::HandshakeFailed(e.to_string());
+
+    let greeting: &[u8] = if auth.is_some() { &[5, 2, 0, 2] } else { &[5, 1, 0] };
+    stream.write_all(greeting).await.map_err(handshake_error)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await.map_err(handshake_error)?;
+    if reply[0] != 5 {
+        return Err(ProxyTestFailure::HandshakeFailed("Not a SOCKS5 proxy".to_string()));
+    }
+
+    match (reply[1], auth) {
+        (0, _) => Ok(()),
+        (2, Some(auth)) => {
+            let (username, password) = (auth.username.as_bytes(), auth.password.as_bytes());
+            if username.len() > 255 || password.len() > 255 {
+                return Err(ProxyTestFailure::InvalidConfig(
+                    "SOCKS5 usernames and passwords are limited to 255 bytes".to_string(),
+                ));
+            }
+
+            let mut request = vec![1, username.len() as u8];
+            request.extend_from_slice(username);
+            request.push(password.len() as u8);
+            request.extend_from_slice(password);
+            stream.write_all(&request).await.map_err(handshake_error)?;
+
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await.map_err(handshake_error)?;
+            if status[1] != 0 {
+                return Err(ProxyTestFailure::AuthenticationFailed(
+                    "Proxy rejected the username or password".to_string(),
+                ));
+            }
+            Ok(())
+        }
+        (2, None) | (0xFF, None) => Err(ProxyTestFailure::AuthenticationFailed(
+            "Proxy requires a username and password".to_string(),
+        )),
+        (0xFF, Some(_)) => Err(ProxyTestFailure::AuthenticationFailed(
+            "Proxy accepts none of the offered authentication methods".to_string(),
+        )),
+        (method, _) => Err(ProxyTestFailure::HandshakeFailed(format!(
+            "Proxy chose unsupported method {}",
+            method
+        ))),
+    }
+}
+
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Minimal SOCKS5 proxy accepting user/secret that answers every tunneled request with 204
+    async fn fake_socks5_proxy() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut greeting = [0u8; 2];
+                    stream.read_exact(&mut greeting).await?;
+                    let mut methods = vec![0u8; greeting[1] as usize];
+                    stream.read_exact(&mut methods).await?;
+                    stream.write_all(&[5, 2]).await?;
+
+                    let mut buf = [0u8; 512];
+                    let n = stream.read(&mut buf).await?;
+                    let ulen = buf[1] as usize;
+                    let user = &buf[2..2 + ulen];
+                    let pass = &buf[3 + ulen..n];
+                    let ok = user == b"user" && pass == b"secret";
+                    stream.write_all(&[1, if ok { 0 } else { 1 }]).await?;
+                    if !ok {
+                        return Ok::<(), std::io::Error>(());
+                    }
+
+                    // CONNECT request, then a canned HTTP response
+                    let _ = stream.read(&mut buf).await?;
+                    stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 80]).await?;
+                    let _ = stream.read(&mut buf).await?;
+                    stream
+                        .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                        .await?;
+                    Ok(())
+                });
+            }
+        });
+
+        port
+    }
+
+    fn config(proxy_type: ProxyType, port: u16, password: &str) -> ProxyConfig {
+        ProxyConfig {
+            proxy_type,
+            host: "127.0.0.1".to_string(),
+            port,
+            auth: Some(ProxyAuth {
+                username: "user".to_string(),
+                password: password.to_string(),
+            }),
+            enabled: true,
+            bypass_domains: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks5_probe() {
+        let port = fake_socks5_proxy().await;
+        let timeout = Duration::from_secs(5);
+
+        let result = test_proxy(&config(ProxyType::Socks5, port, "secret"), "http://probe.test/", timeout).await;
+        assert!(result.success, "{:?}", result.failure);
+        assert_eq!(result.status, Some(204));
+        assert!(result.connect_ms.is_some() && result.first_byte_ms.is_some());
+
+        let result = test_proxy(&config(ProxyType::Socks5, port, "wrong"), "http://probe.test/", timeout).await;
+        assert!(!result.success);
+        assert!(matches!(result.failure, Some(ProxyTestFailure::AuthenticationFailed(_))));
+        assert!(result.first_byte_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_failures() {
+        // HTTP proxy that demands credentials from everyone
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let timeout = Duration::from_secs(5);
+        let result = test_proxy(&config(ProxyType::Http, port, "secret"), "http://probe.test/", timeout).await;
+        assert_eq!(result.status, Some(407));
+        assert!(matches!(result.failure, Some(ProxyTestFailure::AuthenticationFailed(_))));
+
+        // Nothing listens on a port that was just released
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let result = test_proxy(&config(ProxyType::Http, closed, "secret"), "http://probe.test/", timeout).await;
+        assert!(matches!(result.failure, Some(ProxyTestFailure::Unreachable(_))));
+        assert!(result.connect_ms.is_none());
+    }
+}
@@ -0,0 +1,478 @@
+// Tor Controller
+use crate::error::WebxError;
+use super::{ProxyAuth, ProxyConfig, ProxyType};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+
+/// How often bootstrap progress is polled while waiting
+const BOOTSTRAP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Whether WebX runs tor itself or uses one that is already running
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TorMode {
+    /// Launch a tor process owned by the browser
+    Managed,
+    /// Connect to a system tor (or Tor Browser) on the configured ports
+    External,
+}
+
+/// Tor settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorConfig {
+    pub mode: TorMode,
+    /// tor executable; defaults to one bundled next to WebX, then PATH
+    pub tor_path: Option<PathBuf>,
+    pub socks_port: u16,
+    pub control_port: u16,
+    /// Password for external instances using HashedControlPassword
+    pub control_password: Option<String>,
+    pub bootstrap_timeout_secs: u64,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        Self {
+            mode: TorMode::Managed,
+            tor_path: None,
+            socks_port: 9050,
+            control_port: 9051,
+            control_password: None,
+            bootstrap_timeout_secs: 120,
+        }
+    }
+}
+
+/// Bootstrap progress reported by the control port
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootstrapStatus {
+    /// 0-100
+    pub progress: u8,
+    pub tag: String,
+    pub summary: String,
+}
+
+impl BootstrapStatus {
+    /// Check if tor can build circuits
+    pub fn is_done(&self) -> bool {
+        self.progress >= 100
+    }
+}
+
+/// Launches or attaches to tor and manages circuit isolation
+pub struct TorController {
+    config: TorConfig,
+    data_dir: PathBuf,
+    process: Option<Child>,
+    /// SOCKS credentials per identity; tor isolates circuits by SOCKS auth
+    identities: HashMap<String, String>,
+}
+
+impl TorController {
+    /// Create a new Tor controller
+    pub fn new(config: Option<TorConfig>, data_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let data_dir = data_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("tor");
+            path
+        });
+        std::fs::create_dir_all(&data_dir)?;
+
+        Ok(Self {
+            config: config.unwrap_or_default(),
+            data_dir,
+            process: None,
+            identities: HashMap::new(),
+        })
+    }
+
+    /// Get Tor settings
+    pub fn config(&self) -> &TorConfig {
+        &self.config
+    }
+
+    /// Start tor (managed mode) and wait until it has bootstrapped
+    pub async fn start(&mut self) -> Result<(), WebxError> {
+        if self.config.mode == TorMode::Managed && !self.is_running() {
+            let tor_path = self.find_tor_binary().ok_or_else(|| WebxError::NotFound("tor executable".to_string()))?;
+            let torrc = self.data_dir.join("torrc");
+            // Empty torrc so a system-wide configuration does not leak in
+            std::fs::write(&torrc, "")?;
+
+            let child = Command::new(tor_path)
+                .arg("-f")
+                .arg(&torrc)
+                .arg("--SocksPort")
+                .arg(format!("127.0.0.1:{} IsolateSOCKSAuth", self.config.socks_port))
+                .arg("--ControlPort")
+                .arg(format!("127.0.0.1:{}", self.config.control_port))
+                .arg("--CookieAuthentication")
+                .arg("1")
+                .arg("--DataDirectory")
+                .arg(self.data_dir.join("data"))
+                // tor exits on its own if the browser goes away
+                .arg("--__OwningControllerProcess")
+                .arg(std::process::id().to_string())
+                .kill_on_drop(true)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn()?;
+            self.process = Some(child);
+        }
+
+        let timeout = Duration::from_secs(self.config.bootstrap_timeout_secs);
+        match tokio::time::timeout(timeout, self.wait_for_bootstrap()).await {
+            Ok(result) => result,
+            Err(_) => Err(WebxError::Network("Tor did not finish bootstrapping in time".to_string())),
+        }
+    }
+
+    /// Stop a managed tor process
+    pub async fn stop(&mut self) -> Result<(), WebxError> {
+        if let Some(mut child) = self.process.take() {
+            if let Ok(mut control) = self.connect_control().await {
+                let _ = control.command("SIGNAL SHUTDOWN").await;
+            }
+            if tokio::time::timeout(Duration::from_secs(5), child.wait()).await.is_err() {
+                child.kill().await?;
+            }
+        }
+        self.identities.clear();
+        Ok(())
+    }
+
+    /// Check if the managed tor process is alive
+    pub fn is_running(&mut self) -> bool {
+        self.process
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    /// Open an authenticated control port connection
+    pub async fn connect_control(&self) -> Result<TorControl, WebxError> {
+        let mut control = TorControl::connect(self.config.control_port).await?;
+        control.authenticate(self.config.control_password.as_deref()).await?;
+        Ok(control)
+    }
+
+    /// Current bootstrap progress
+    pub async fn bootstrap_status(&self) -> Result<BootstrapStatus, WebxError> {
+        let mut control = self.connect_control().await?;
+        let reply = control.command("GETINFO status/bootstrap-phase").await?;
+        reply
+            .iter()
+            .find_map(|line| parse_bootstrap_phase(line))
+            .ok_or_else(|| "Missing bootstrap status in reply".into())
+    }
+
+    /// Ask tor for fresh circuits for every identity
+    pub async fn new_circuits(&self) -> Result<(), WebxError> {
+        let mut control = self.connect_control().await?;
+        control.command("SIGNAL NEWNYM").await?;
+        Ok(())
+    }
+
+    /// SOCKS proxy for an identity (e.g. a container or private window).
+    /// Identities never share circuits with each other.
+    pub fn proxy_for_identity(&mut self, identity: &str) -> ProxyConfig {
+        let password = self
+            .identities
+            .entry(identity.to_string())
+            .or_insert_with(random_token)
+            .clone();
+
+        ProxyConfig {
+            auth: Some(ProxyAuth {
+                username: identity.to_string(),
+                password,
+            }),
+            ..self.socks_proxy()
+        }
+    }
+
+    /// Move an identity onto new circuits, leaving other identities alone
+    pub fn renew_identity(&mut self, identity: &str) {
+        self.identities.insert(identity.to_string(), random_token());
+    }
+
+    /// The SOCKS proxy tor listens on, without isolation credentials
+    pub fn socks_proxy(&self) -> ProxyConfig {
+        ProxyConfig {
+            proxy_type: ProxyType::Socks5,
+            host: "127.0.0.1".to_string(),
+            port: self.config.socks_port,
+            auth: None,
+            enabled: true,
+            bypass_domains: vec![],
+        }
+    }
+
+    // Private helper methods
+
+    async fn wait_for_bootstrap(&mut self) -> Result<(), WebxError> {
+        loop {
+            if self.config.mode == TorMode::Managed && !self.is_running() {
+                return Err("tor exited during startup".into());
+            }
+            // The control port may not be listening yet right after launch
+            if let Ok(status) = self.bootstrap_status().await {
+                if status.is_done() {
+                    return Ok(());
+                }
+                tracing::debug!("Tor bootstrap {}%: {}", status.progress, status.summary);
+            }
+            tokio::time::sleep(BOOTSTRAP_POLL_INTERVAL).await;
+        }
+    }
+
+    fn find_tor_binary(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.config.tor_path {
+            return Some(path.clone());
+        }
+
+        let name = if cfg!(windows) { "tor.exe" } else { "tor" };
+        let bundled = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("tor").join(name)));
+        if let Some(bundled) = bundled.filter(|path| path.exists()) {
+            return Some(bundled);
+        }
+
+        std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(name))
+                .find(|path| path.exists())
+        })
+    }
+}
+
+/// Connection to tor's control port
+pub struct TorControl {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl TorControl {
+    /// Connect to a control port on localhost
+    pub async fn connect(port: u16) -> Result<Self, WebxError> {
+        let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    /// Authenticate with whatever method tor offers
+    pub async fn authenticate(&mut self, password: Option<&str>) -> Result<(), WebxError> {
+        let info = self.command("PROTOCOLINFO 1").await?;
+        let (methods, cookie_file) = parse_protocol_info(&info);
+
+        let command = if methods.iter().any(|m| m == "NULL") {
+            "AUTHENTICATE".to_string()
+        } else if let (true, Some(cookie_file)) = (methods.iter().any(|m| m == "COOKIE"), cookie_file) {
+            let cookie = std::fs::read(Path::new(&cookie_file))?;
+            let hex: String = cookie.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("AUTHENTICATE {}", hex)
+        } else if let (true, Some(password)) = (methods.iter().any(|m| m == "HASHEDPASSWORD"), password) {
+            format!("AUTHENTICATE {}", serde_json::to_string(password)?)
+        } else {
+            return Err(format!("No usable Tor control authentication method ({})", methods.join(", ")).into());
+        };
+
+        self.command(&command).await?;
+        Ok(())
+    }
+
+    /// Send a command and return the reply lines, failing on non-250 replies
+    pub async fn command(&mut self, command: &str) -> Result<Vec<String>, WebxError> {
+        self.writer.write_all(format!("{}\r\n", command).as_bytes()).await?;
+
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            if line.len() < 4 {
+                return Err(format!("Malformed control reply: {}", line).into());
+            }
+            let (code, separator, text) = (&line[..3], &line[3..4], &line[4..]);
+            if code != "250" {
+                return Err(format!("Tor control error {}: {}", code, text).into());
+            }
+            lines.push(text.to_string());
+
+            match separator {
+                " " => return Ok(lines),
+                // Data follows until a lone "."
+                "+" => loop {
+                    let data = self.read_line().await?;
+                    if data == "." {
+                        break;
+                    }
+                    lines.push(data);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String, WebxError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(WebxError::Network("Tor control connection closed".to_string()));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Authentication methods and cookie file from a PROTOCOLINFO reply
+pub fn parse_protocol_info(lines: &[String]) -> (Vec<String>, Option<String>) {
+    let mut methods = Vec::new();
+    let mut cookie_file = None;
+
+    for line in lines {
+        let Some(rest) = line.strip_prefix("AUTH ") else {
+            continue;
+        };
+        for part in rest.split(' ') {
+            if let Some(list) = part.strip_prefix("METHODS=") {
+                methods = list.split(',').map(str::to_string).collect();
+            }
+        }
+        if let Some(start) = rest.find("COOKIEFILE=\"") {
+            let value = &rest[start + "COOKIEFILE=\"".len()..];
+            cookie_file = value.rfind('"').map(|end| value[..end].replace("\\\"", "\"").replace("\\\\", "\\"));
+        }
+    }
+
+    (methods, cookie_file)
+}
+
+/// Parse "status/bootstrap-phase=NOTICE BOOTSTRAP PROGRESS=100 TAG=done SUMMARY="Done""
+pub fn parse_bootstrap_phase(line: &str) -> Option<BootstrapStatus> {
+    let status = line.strip_prefix("status/bootstrap-phase=")?;
+    let field = |name: &str| -> Option<String> {
+        let start = status.find(&format!("{}=", name))? + name.len() + 1;
+        let value = &status[start..];
+        Some(match value.strip_prefix('"') {
+            Some(quoted) => quoted[..quoted.find('"').unwrap_or(quoted.len())].to_string(),
+            None => value.split(' ').next().unwrap_or("").to_string(),
+        })
+    };
+
+    Some(BootstrapStatus {
+        progress: field("PROGRESS")?.parse().ok()?,
+        tag: field("TAG").unwrap_or_default(),
+        summary: field("SUMMARY").unwrap_or_default(),
+    })
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::system::proxy::{ProxyManager, ProxyProfile, ProxyRoute};
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_control_replies() {
+        let info = vec![
+            "PROTOCOLINFO 1".to_string(),
+            r#"AUTH METHODS=COOKIE,SAFECOOKIE COOKIEFILE="/run/tor/control.authcookie""#.to_string(),
+            r#"VERSION Tor="0.4.8.10""#.to_string(),
+            "OK".to_string(),
+        ];
+        let (methods, cookie) = parse_protocol_info(&info);
+        assert_eq!(methods, vec!["COOKIE", "SAFECOOKIE"]);
+        assert_eq!(cookie.as_deref(), Some("/run/tor/control.authcookie"));
+
+        let status = parse_bootstrap_phase(
+            r#"status/bootstrap-phase=NOTICE BOOTSTRAP PROGRESS=75 TAG=enough_dirinfo SUMMARY="Loaded enough directory info""#,
+        )
+        .unwrap();
+        assert_eq!(status.progress, 75);
+        assert_eq!(status.tag, "enough_dirinfo");
+        assert_eq!(status.summary, "Loaded enough directory info");
+        assert!(!status.is_done());
+        assert!(parse_bootstrap_phase("version=0.4.8").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_external_tor_and_isolation() {
+        // Control port stub of an already bootstrapped tor without authentication
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let control_port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 256];
+                    while let Ok(n) = stream.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                        let command = String::from_utf8_lossy(&buf[..n]).to_string();
+                        let reply = if command.starts_with("PROTOCOLINFO") {
+                            "250-PROTOCOLINFO 1\r\n250-AUTH METHODS=NULL\r\n250 OK\r\n".to_string()
+                        } else if command.starts_with("GETINFO status/bootstrap-phase") {
+                            "250-status/bootstrap-phase=NOTICE BOOTSTRAP PROGRESS=100 TAG=done SUMMARY=\"Done\"\r\n250 OK\r\n"
+                                .to_string()
+                        } else if command.starts_with("AUTHENTICATE") || command.starts_with("SIGNAL NEWNYM") {
+                            "250 OK\r\n".to_string()
+                        } else {
+                            "510 Unrecognized command\r\n".to_string()
+                        };
+                        let _ = stream.write_all(reply.as_bytes()).await;
+                    }
+                });
+            }
+        });
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = TorConfig {
+            mode: TorMode::External,
+            control_port,
+            bootstrap_timeout_secs: 5,
+            ..Default::default()
+        };
+        let mut tor = TorController::new(Some(config), Some(temp_dir.path().to_path_buf())).unwrap();
+        tor.start().await.unwrap();
+        assert!(tor.bootstrap_status().await.unwrap().is_done());
+        tor.new_circuits().await.unwrap();
+        assert!(tor.connect_control().await.unwrap().command("BOGUS").await.is_err());
+
+        // Identities get distinct, stable SOCKS credentials until renewed
+        let work = tor.proxy_for_identity("work");
+        let personal = tor.proxy_for_identity("personal");
+        assert_ne!(work.auth, personal.auth);
+        assert_eq!(tor.proxy_for_identity("work").auth, work.auth);
+        tor.renew_identity("work");
+        assert_ne!(tor.proxy_for_identity("work").auth, work.auth);
+        assert_eq!(work.port, 9050);
+
+        // The Tor profile always resolves names through tor
+        let mut manager = ProxyManager::new(None, Some(temp_dir.path().join("proxies"))).unwrap();
+        manager.use_tor(&tor).unwrap();
+        manager.set_active_profile(ProxyProfile::Tor).unwrap();
+        assert!(!manager.get_settings().dns_over_proxy);
+        match manager.route_for_url("https://example.com/") {
+            ProxyRoute::Proxy { config, remote_dns } => {
+                assert!(remote_dns);
+                assert_eq!(config.to_url(remote_dns).unwrap().scheme(), "socks5h");
+            }
+            ProxyRoute::Direct => panic!("expected Tor route"),
+        }
+    }
+}
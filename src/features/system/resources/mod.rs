@@ -0,0 +1,403 @@
+// Resource Usage Monitoring
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::mpsc;
+
+/// Resource monitor configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceMonitorConfig {
+    pub sample_interval_ms: u64,
+    /// Memory a single tab process may use before it is reported as runaway
+    pub max_tab_memory_mb: Option<u64>,
+    /// CPU (percent of one core) a tab process may use before it is
+    /// reported as runaway
+    pub max_tab_cpu_percent: Option<f32>,
+    /// Consecutive samples over a limit before a tab is reported
+    pub sustained_samples: u32,
+}
+
+impl Default for ResourceMonitorConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_ms: 2000,
+            max_tab_memory_mb: Some(2048),
+            max_tab_cpu_percent: Some(90.0),
+            sustained_samples: 5,
+        }
+    }
+}
+
+/// Memory and CPU use of one tab
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TabResourceUsage {
+    pub tab_id: usize,
+    pub pid: u32,
+    pub memory_bytes: u64,
+    /// Percent of one core; may exceed 100 on multi-core machines
+    pub cpu_percent: f32,
+    /// Number of tabs sharing this tab's process, including itself
+    pub process_tab_count: usize,
+    pub sampled_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Aggregated usage of the browser and all of its processes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BrowserResourceUsage {
+    pub browser_memory_bytes: u64,
+    pub browser_cpu_percent: f32,
+    /// Helper processes spawned by the browser (web content, GPU, network)
+    pub helper_memory_bytes: u64,
+    pub helper_cpu_percent: f32,
+    pub helper_process_count: usize,
+    pub total_memory_bytes: u64,
+    pub total_cpu_percent: f32,
+    pub system_memory_bytes: u64,
+    pub tracked_tabs: usize,
+    pub sampled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Resource events for the task manager
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ResourceEvent {
+    Sampled(BrowserResourceUsage),
+    TabOverLimit { tab_id: usize, memory_bytes: u64, cpu_percent: f32 },
+    TabKilled { tab_id: usize, pid: u32 },
+    TabProcessExited { tab_id: usize, pid: u32 },
+}
+
+/// Samples per-tab and browser-wide memory and CPU use
+pub struct ResourceMonitor {
+    config: ResourceMonitorConfig,
+    system: Arc<Mutex<System>>,
+    browser_pid: Pid,
+    tab_processes: Arc<Mutex<HashMap<usize, u32>>>,
+    tab_usage: Arc<Mutex<HashMap<usize, TabResourceUsage>>>,
+    totals: Arc<Mutex<BrowserResourceUsage>>,
+    over_limit: Arc<Mutex<HashMap<usize, u32>>>,
+    sampler: Option<tokio::task::JoinHandle<()>>,
+    tx: mpsc::UnboundedSender<ResourceEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ResourceEvent>>>>,
+}
+
+impl ResourceMonitor {
+    /// Create a new resource monitor
+    pub fn new(config: Option<ResourceMonitorConfig>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            config: config.unwrap_or_default(),
+            system: Arc::new(Mutex::new(System::new())),
+            browser_pid: Pid::from_u32(std::process::id()),
+            tab_processes: Arc::new(Mutex::new(HashMap::new())),
+            tab_usage: Arc::new(Mutex::new(HashMap::new())),
+            totals: Arc::new(Mutex::new(BrowserResourceUsage::default())),
+            over_limit: Arc::new(Mutex::new(HashMap::new())),
+            sampler: None,
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        }
+    }
+
+    /// Record which process renders a tab, as reported by the web view
+    pub fn register_tab_process(&self, tab_id: usize, pid: u32) {
+        self.tab_processes.lock_or_recover().insert(tab_id, pid);
+    }
+
+    /// Stop tracking a tab
+    pub fn unregister_tab(&self, tab_id: usize) {
+        self.tab_processes.lock_or_recover().remove(&tab_id);
+        self.tab_usage.lock_or_recover().remove(&tab_id);
+        self.over_limit.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Take a sample of every browser process now
+    pub fn sample(&self) -> BrowserResourceUsage {
+        let now = chrono::Utc::now();
+        let tab_processes = self.tab_processes.lock_or_recover().clone();
+
+        let (totals, tab_usage, exited) = {
+            let mut system = self.system.lock_or_recover();
+            system.refresh_memory();
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::All,
+                true,
+                ProcessRefreshKind::nothing().with_memory().with_cpu(),
+            );
+
+            let helpers = self.descendants(&system);
+            let mut totals = BrowserResourceUsage {
+                system_memory_bytes: system.total_memory(),
+                tracked_tabs: tab_processes.len(),
+                sampled_at: Some(now),
+                ..Default::default()
+            };
+            if let Some(browser) = system.process(self.browser_pid) {
+                totals.browser_memory_bytes = browser.memory();
+                totals.browser_cpu_percent = browser.cpu_usage();
+            }
+            for pid in &helpers {
+                if let Some(process) = system.process(*pid) {
+                    totals.helper_memory_bytes += process.memory();
+                    totals.helper_cpu_percent += process.cpu_usage();
+                }
+            }
+            totals.helper_process_count = helpers.len();
+            totals.total_memory_bytes = totals.browser_memory_bytes + totals.helper_memory_bytes;
+            totals.total_cpu_percent = totals.browser_cpu_percent + totals.helper_cpu_percent;
+
+            let mut per_process: HashMap<u32, usize> = HashMap::new();
+            for pid in tab_processes.values() {
+                *per_process.entry(*pid).or_default() += 1;
+            }
+
+            let mut tab_usage = HashMap::new();
+            let mut exited = Vec::new();
+            for (&tab_id, &pid) in &tab_processes {
+                match system.process(Pid::from_u32(pid)) {
+                    Some(process) => {
+                        tab_usage.insert(
+                            tab_id,
+                            TabResourceUsage {
+                                tab_id,
+                                pid,
+                                memory_bytes: process.memory(),
+                                cpu_percent: process.cpu_usage(),
+                                process_tab_count: per_process[&pid],
+                                sampled_at: now,
+                            },
+                        );
+                    }
+                    None => exited.push((tab_id, pid)),
+                }
+            }
+            (totals, tab_usage, exited)
+        };
+
+        for (tab_id, pid) in exited {
+            self.tab_processes.lock_or_recover().remove(&tab_id);
+            let _ = self.tx.send(ResourceEvent::TabProcessExited { tab_id, pid });
+        }
+
+        self.check_limits(&tab_usage);
+        *self.tab_usage.lock_or_recover() = tab_usage;
+        *self.totals.lock_or_recover() = totals.clone();
+
+        let _ = self.tx.send(ResourceEvent::Sampled(totals.clone()));
+        totals
+    }
+
+    /// Get the latest sample for a tab
+    pub fn get_tab_usage(&self, tab_id: usize) -> Option<TabResourceUsage> {
+        self.tab_usage.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    /// Get the latest sample of every tab, heaviest first
+    pub fn get_all_tab_usage(&self) -> Vec<TabResourceUsage> {
+        let mut usage: Vec<TabResourceUsage> = self.tab_usage.lock_or_recover().values().cloned().collect();
+        usage.sort_by(|a, b| b.memory_bytes.cmp(&a.memory_bytes).then(a.tab_id.cmp(&b.tab_id)));
+        usage
+    }
+
+    /// Get the latest browser-wide totals
+    pub fn get_totals(&self) -> BrowserResourceUsage {
+        self.totals.lock_or_recover().clone()
+    }
+
+    /// Kill the process rendering a tab. Every tab sharing that process is
+    /// affected and returned; the browser's own process is never killed.
+    pub fn kill_tab(&self, tab_id: usize) -> Result<Vec<usize>, WebxError> {
+        let pid = *self
+            .tab_processes
+            .lock_or_recover()
+            .get(&tab_id)
+            .ok_or_else(|| WebxError::NotFound(format!("Process of tab {}", tab_id)))?;
+
+        if Pid::from_u32(pid) == self.browser_pid {
+            return Err("Tab is rendered in the browser process and cannot be killed".into());
+        }
+
+        {
+            let mut system = self.system.lock_or_recover();
+            system.refresh_processes(ProcessesToUpdate::Some(&[Pid::from_u32(pid)]), true);
+            let process = system
+                .process(Pid::from_u32(pid))
+                .ok_or_else(|| WebxError::NotFound(format!("Process {}", pid)))?;
+            if !process.kill() {
+                return Err(format!("Failed to kill process {}", pid).into());
+            }
+        }
+
+        let affected: Vec<usize> = {
+            let mut tab_processes = self.tab_processes.lock_or_recover();
+            let affected: Vec<usize> = tab_processes
+                .iter()
+                .filter(|(_, &tab_pid)| tab_pid == pid)
+                .map(|(&id, _)| id)
+                .collect();
+            tab_processes.retain(|_, tab_pid| *tab_pid != pid);
+            affected
+        };
+
+        for id in &affected {
+            self.tab_usage.lock_or_recover().remove(id);
+            self.over_limit.lock_or_recover().remove(id);
+            let _ = self.tx.send(ResourceEvent::TabKilled { tab_id: *id, pid });
+        }
+
+        tracing::info!("Killed process {} for tabs {:?}", pid, affected);
+        Ok(affected)
+    }
+
+    /// Sample periodically in the background
+    pub fn start_sampling(monitor: Arc<Mutex<ResourceMonitor>>) {
+        let task_monitor = monitor.clone();
+        let interval = Duration::from_millis(monitor.lock_or_recover().config.sample_interval_ms.max(250));
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            loop {
+                timer.tick().await;
+                task_monitor.lock_or_recover().sample();
+            }
+        });
+
+        let mut monitor = monitor.lock_or_recover();
+        monitor.stop_sampling();
+        monitor.sampler = Some(handle);
+    }
+
+    /// Stop background sampling
+    pub fn stop_sampling(&mut self) {
+        if let Some(handle) = self.sampler.take() {
+            handle.abort();
+        }
+    }
+
+    /// Check if background sampling is running
+    pub fn is_sampling(&self) -> bool {
+        self.sampler.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Subscribe to resource events (can only be called once)
+    pub fn subscribe_events(&self) -> Option<mpsc::UnboundedReceiver<ResourceEvent>> {
+        self.rx.lock_or_recover().take()
+    }
+
+    /// Set configuration
+    pub fn set_config(&mut self, config: ResourceMonitorConfig) {
+        self.config = config;
+    }
+
+    /// Get configuration
+    pub fn get_config(&self) -> &ResourceMonitorConfig {
+        &self.config
+    }
+
+    // Private helper methods
+
+    fn descendants(&self, system: &System) -> HashSet<Pid> {
+        let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        for (pid, process) in system.processes() {
+            if let Some(parent) = process.parent() {
+                children.entry(parent).or_default().push(*pid);
+            }
+        }
+
+        let mut found = HashSet::new();
+        let mut pending = vec![self.browser_pid];
+        while let Some(pid) = pending.pop() {
+            for child in children.get(&pid).into_iter().flatten() {
+                if found.insert(*child) {
+                    pending.push(*child);
+                }
+            }
+        }
+        found
+    }
+
+    fn check_limits(&self, tab_usage: &HashMap<usize, TabResourceUsage>) {
+        let memory_limit = self.config.max_tab_memory_mb.map(|mb| mb * 1024 * 1024);
+        let mut over_limit = self.over_limit.lock_or_recover();
+
+        for usage in tab_usage.values() {
+            let exceeded = memory_limit.is_some_and(|limit| usage.memory_bytes > limit)
+                || self
+                    .config
+                    .max_tab_cpu_percent
+                    .is_some_and(|limit| usage.cpu_percent > limit);
+
+            if !exceeded {
+                over_limit.remove(&usage.tab_id);
+                continue;
+            }
+
+            let count = over_limit.entry(usage.tab_id).or_default();
+            *count += 1;
+            // Report once when the limit has been exceeded long enough
+            if *count == self.config.sustained_samples.max(1) {
+                let _ = self.tx.send(ResourceEvent::TabOverLimit {
+                    tab_id: usage.tab_id,
+                    memory_bytes: usage.memory_bytes,
+                    cpu_percent: usage.cpu_percent,
+                });
+            }
+        }
+
+        over_limit.retain(|tab_id, _| tab_usage.contains_key(tab_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_reports_browser_and_tabs() {
+        let monitor = ResourceMonitor::new(Some(ResourceMonitorConfig {
+            max_tab_memory_mb: Some(0),
+            sustained_samples: 1,
+            ..Default::default()
+        }));
+        let mut events = monitor.subscribe_events().unwrap();
+        monitor.register_tab_process(1, std::process::id());
+        monitor.register_tab_process(2, std::process::id());
+
+        let totals = monitor.sample();
+        assert!(totals.browser_memory_bytes > 0);
+        assert!(totals.total_memory_bytes >= totals.browser_memory_bytes);
+        assert_eq!(totals.tracked_tabs, 2);
+
+        let usage = monitor.get_all_tab_usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].process_tab_count, 2);
+
+        // In-process tabs must never take the browser down
+        assert!(monitor.kill_tab(1).is_err());
+
+        let mut over_limit = 0;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, ResourceEvent::TabOverLimit { .. }) {
+                over_limit += 1;
+            }
+        }
+        assert_eq!(over_limit, 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_tab_process() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let monitor = ResourceMonitor::new(None);
+        monitor.register_tab_process(7, child.id());
+
+        monitor.sample();
+        assert!(monitor.get_tab_usage(7).is_some());
+
+        assert_eq!(monitor.kill_tab(7).unwrap(), vec![7]);
+        assert!(!child.wait().unwrap().success());
+        assert!(monitor.get_tab_usage(7).is_none());
+    }
+}
@@ -0,0 +1,426 @@
+// Background Task Scheduler
+pub use crate::features::system::conditions::{SystemConditions, TaskPriority};
+
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::features::system::conditions::{BackgroundWork, ConditionsMonitor};
+use crate::utils::LockExt;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How often the scheduler looks for due tasks
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// When and under which conditions a task runs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskSpec {
+    pub name: String,
+    pub interval: Duration,
+    pub priority: TaskPriority,
+    /// Fraction of the interval each run moves by at random, so tasks
+    /// sharing an interval spread out
+    pub jitter: f64,
+    /// Wait until the user has been inactive for a while
+    pub idle_only: bool,
+    /// Uses the network, so is held back on metered connections
+    pub uses_network: bool,
+    /// First run soon after registering instead of one interval later
+    pub run_at_start: bool,
+}
+
+impl TaskSpec {
+    pub fn new(name: &str, interval: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            interval,
+            priority: TaskPriority::Normal,
+            jitter: 0.1,
+            idle_only: false,
+            uses_network: false,
+            run_at_start: false,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: TaskPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn idle_only(mut self) -> Self {
+        self.idle_only = true;
+        self
+    }
+
+    pub fn uses_network(mut self) -> Self {
+        self.uses_network = true;
+        self
+    }
+
+    pub fn run_at_start(mut self) -> Self {
+        self.run_at_start = true;
+        self
+    }
+
+    /// The work each run does, as the conditions monitor judges it
+    pub fn work(&self) -> BackgroundWork {
+        BackgroundWork {
+            priority: self.priority,
+            uses_network: self.uses_network,
+            idle_only: self.idle_only,
+        }
+    }
+}
+
+/// Scheduler configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Tasks started per tick; the rest wait for later ticks
+    pub max_tasks_per_tick: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self { max_tasks_per_tick: 1 }
+    }
+}
+
+/// A task's state, for showing what runs in the background
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskStatus {
+    pub spec: TaskSpec,
+    pub paused: bool,
+    pub running: bool,
+    /// Why a due task is not running, if it is held back
+    pub held_back: Option<String>,
+    /// Time until the next run; zero when due
+    pub due_in: Duration,
+    pub runs: u64,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+type TaskFuture = Pin<Box<dyn Future<Output = Result<(), WebxError>> + Send>>;
+type TaskJob = Arc<dyn Fn() -> TaskFuture + Send + Sync>;
+
+struct ScheduledTask {
+    spec: TaskSpec,
+    job: TaskJob,
+    next_run: Instant,
+    paused: bool,
+    running: bool,
+    runs: u64,
+    last_run: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// Runs the browser's periodic and idle-time work. Tasks run one after
+/// another in priority order with jittered intervals, so they never fire
+/// all at once; the conditions monitor holds tasks back while on battery
+/// or a metered connection, and everything can be paused.
+pub struct TaskScheduler {
+    config: Mutex<SchedulerConfig>,
+    tasks: Mutex<BTreeMap<String, ScheduledTask>>,
+    monitor: Arc<ConditionsMonitor>,
+    paused: Mutex<bool>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl TaskScheduler {
+    /// Create new scheduler whose tasks wait as `monitor` says
+    pub fn new(config: Option<SchedulerConfig>, monitor: Arc<ConditionsMonitor>) -> Self {
+        Self {
+            config: Mutex::new(config.unwrap_or_default()),
+            tasks: Mutex::new(BTreeMap::new()),
+            monitor,
+            paused: Mutex::new(false),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> SchedulerConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    pub fn set_config(&self, config: SchedulerConfig) {
+        *self.config.lock_or_recover() = config;
+    }
+
+    /// Add a task, replacing one with the same name
+    pub fn register<F, Fut>(&self, spec: TaskSpec, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), WebxError>> + Send + 'static,
+    {
+        let next_run = match spec.run_at_start {
+            true => Instant::now(),
+            false => Instant::now() + jittered(spec.interval, spec.jitter),
+        };
+        let job: TaskJob = Arc::new(move || Box::pin(job()) as TaskFuture);
+        self.tasks.lock_or_recover().insert(
+            spec.name.clone(),
+            ScheduledTask {
+                spec,
+                job,
+                next_run,
+                paused: false,
+                running: false,
+                runs: 0,
+                last_run: None,
+                last_error: None,
+            },
+        );
+    }
+
+    /// Remove a task; a run in progress finishes
+    pub fn unregister(&self, name: &str) -> bool {
+        self.tasks.lock_or_recover().remove(name).is_some()
+    }
+
+    /// Stop running a task until it is resumed
+    pub fn pause_task(&self, name: &str) -> Result<(), WebxError> {
+        self.with_task(name, |task| task.paused = true)
+    }
+
+    pub fn resume_task(&self, name: &str) -> Result<(), WebxError> {
+        self.with_task(name, |task| task.paused = false)
+    }
+
+    /// Run a task at the next tick, whatever its interval
+    pub fn run_soon(&self, name: &str) -> Result<(), WebxError> {
+        self.with_task(name, |task| task.next_run = Instant::now())
+    }
+
+    /// Pause or resume every task
+    pub fn set_paused(&self, paused: bool) {
+        *self.paused.lock_or_recover() = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock_or_recover()
+    }
+
+    /// The monitor deciding when tasks wait
+    pub fn conditions_monitor(&self) -> &Arc<ConditionsMonitor> {
+        &self.monitor
+    }
+
+    /// Every task, by name
+    pub fn tasks(&self) -> Vec<TaskStatus> {
+        let now = Instant::now();
+        self.tasks
+            .lock_or_recover()
+            .values()
+            .map(|task| TaskStatus {
+                spec: task.spec.clone(),
+                paused: task.paused,
+                running: task.running,
+                held_back: self.monitor.defer_reason(task.spec.work()).map(str::to_string),
+                due_in: task.next_run.saturating_duration_since(now),
+                runs: task.runs,
+                last_run: task.last_run,
+                last_error: task.last_error.clone(),
+            })
+            .collect()
+    }
+
+    /// Run the due tasks that conditions allow, highest priority and most
+    /// overdue first, up to the per-tick limit. Returns the tasks run.
+    pub async fn run_due(&self) -> Vec<String> {
+        if self.is_paused() {
+            return Vec::new();
+        }
+        let now = Instant::now();
+        let limit = self.config.lock_or_recover().max_tasks_per_tick.max(1);
+        let picked: Vec<(String, TaskJob)> = {
+            let mut tasks = self.tasks.lock_or_recover();
+            let mut due: Vec<&mut ScheduledTask> = tasks
+                .values_mut()
+                .filter(|task| !task.paused && !task.running && task.next_run <= now)
+                .filter(|task| !self.monitor.should_defer(task.spec.work()))
+                .collect();
+            due.sort_by(|a, b| b.spec.priority.cmp(&a.spec.priority).then(a.next_run.cmp(&b.next_run)));
+            due.into_iter()
+                .take(limit)
+                .map(|task| {
+                    task.running = true;
+                    (task.spec.name.clone(), Arc::clone(&task.job))
+                })
+                .collect()
+        };
+
+        let mut ran = Vec::new();
+        for (name, job) in picked {
+            let result = job().await;
+            if let Err(e) = &result {
+                tracing::warn!("Background task {} failed: {}", name, e);
+            }
+            if let Some(task) = self.tasks.lock_or_recover().get_mut(&name) {
+                task.running = false;
+                task.runs += 1;
+                task.last_run = Some(Utc::now());
+                task.last_error = result.err().map(|e| e.to_string());
+                task.next_run = Instant::now() + jittered(task.spec.interval, task.spec.jitter);
+            }
+            ran.push(name);
+        }
+        ran
+    }
+
+    /// Start running tasks in the background
+    pub fn start(scheduler: Arc<Self>) {
+        let weak = Arc::downgrade(&scheduler);
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(TICK_INTERVAL);
+            loop {
+                timer.tick().await;
+                let Some(scheduler) = weak.upgrade() else {
+                    break;
+                };
+                scheduler.run_due().await;
+            }
+        });
+        if let Some(previous) = scheduler.task.lock_or_recover().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stop running tasks; a run in progress is cancelled
+    pub fn stop(&self) {
+        if let Some(handle) = self.task.lock_or_recover().take() {
+            handle.abort();
+        }
+    }
+
+    // Private helper methods
+
+    fn with_task(&self, name: &str, update: impl FnOnce(&mut ScheduledTask)) -> Result<(), WebxError> {
+        let mut tasks = self.tasks.lock_or_recover();
+        let task = tasks
+            .get_mut(name)
+            .ok_or_else(|| WebxError::NotFound(format!("No background task {}", name)))?;
+        update(task);
+        Ok(())
+    }
+}
+
+impl Default for TaskScheduler {
+    fn default() -> Self {
+        Self::new(None, Arc::new(ConditionsMonitor::default()))
+    }
+}
+
+impl SettingsProvider for TaskScheduler {
+    fn module(&self) -> &str {
+        "scheduler"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![
+            SettingDefinition::toggle("scheduler.paused", "Pause background tasks", false)
+                .with_description("Stop cache cleanup, prefetching, statistics and other periodic work")
+                .with_category(SettingCategory::Advanced),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let enabled = value.as_bool().ok_or("Expected a toggle value")?;
+        match key {
+            "scheduler.paused" => self.set_paused(enabled),
+            _ => return Err(format!("Unknown scheduler setting {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+/// An interval moved by up to `jitter` of itself either way
+fn jittered(interval: Duration, jitter: f64) -> Duration {
+    if jitter <= 0.0 {
+        return interval;
+    }
+    let factor = 1.0 + rand::thread_rng().gen_range(-jitter..=jitter);
+    interval.mul_f64(factor.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::system::conditions::ConditionsConfig;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_task(scheduler: &TaskScheduler, spec: TaskSpec) -> Arc<AtomicUsize> {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = Arc::clone(&runs);
+        scheduler.register(spec, move || {
+            let runs = Arc::clone(&task_runs);
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        runs
+    }
+
+    #[tokio::test]
+    async fn test_tasks_run_by_priority_and_conditions() {
+        let monitor = Arc::new(ConditionsMonitor::new(Some(ConditionsConfig {
+            idle_after_secs: 3600,
+            ..Default::default()
+        })));
+        let scheduler = TaskScheduler::new(None, Arc::clone(&monitor));
+        let hour = Duration::from_secs(3600);
+        let save = counting_task(&scheduler, TaskSpec::new("save", hour).with_priority(TaskPriority::High).run_at_start());
+        let gc = counting_task(&scheduler, TaskSpec::new("gc", hour).with_priority(TaskPriority::Low).run_at_start());
+        let upload = counting_task(&scheduler, TaskSpec::new("upload", hour).uses_network().run_at_start());
+        let warm = counting_task(&scheduler, TaskSpec::new("warm", hour).idle_only().run_at_start());
+
+        // One task per tick, most important first
+        assert_eq!(scheduler.run_due().await, vec!["save"]);
+
+        monitor.set_conditions(SystemConditions {
+            on_battery: true,
+            battery_percent: Some(80),
+            metered_network: true,
+        });
+        assert!(scheduler.run_due().await.is_empty());
+        let held: Vec<Option<String>> = scheduler.tasks().into_iter().map(|task| task.held_back).collect();
+        assert_eq!(
+            held,
+            vec![
+                Some("on battery power".to_string()),
+                None,
+                Some("metered network".to_string()),
+                Some("waiting for the browser to be idle".to_string()),
+            ]
+        );
+
+        monitor.set_conditions(SystemConditions::default());
+        scheduler.set_paused(true);
+        assert!(scheduler.run_due().await.is_empty());
+        scheduler.set_paused(false);
+        scheduler.pause_task("upload").unwrap();
+        assert_eq!(scheduler.run_due().await, vec!["gc"]);
+        assert!(scheduler.run_due().await.is_empty());
+        assert!(scheduler.pause_task("missing").is_err());
+
+        assert_eq!(save.load(Ordering::SeqCst), 1);
+        assert_eq!(gc.load(Ordering::SeqCst), 1);
+        assert_eq!(upload.load(Ordering::SeqCst), 0);
+        assert_eq!(warm.load(Ordering::SeqCst), 0);
+        assert!(scheduler.tasks().iter().all(|task| !task.running));
+    }
+}
@@ -0,0 +1,295 @@
+// System Tray Module
+use crate::core::{Download, DownloadStatus, TraySettings};
+use crate::i18n::{tr, tr_args};
+use serde::{Deserialize, Serialize};
+
+/// Size of the rendered tray icon in pixels
+pub const TRAY_ICON_SIZE: u32 = 32;
+
+/// Quick actions offered by the tray menu
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrayAction {
+    ToggleWindow,
+    NewTab,
+    NewPrivateWindow,
+    /// Start over with a new identity, once the user confirms
+    NewIdentity,
+    /// Open a finished download, by download ID
+    OpenDownload(usize),
+    /// Try a failed download again, by download ID
+    RetryDownload(usize),
+    Quit,
+}
+
+impl TrayAction {
+    /// Stable menu item ID for the action
+    pub fn id(&self) -> String {
+        match self {
+            TrayAction::ToggleWindow => "toggle-window".to_string(),
+            TrayAction::NewTab => "new-tab".to_string(),
+            TrayAction::NewPrivateWindow => "new-private-window".to_string(),
+            TrayAction::NewIdentity => "new-identity".to_string(),
+            TrayAction::OpenDownload(id) => format!("download-{}", id),
+            TrayAction::RetryDownload(id) => format!("retry-download-{}", id),
+            TrayAction::Quit => "quit".to_string(),
+        }
+    }
+
+    /// Parse a menu item ID back into its action
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "toggle-window" => Some(TrayAction::ToggleWindow),
+            "new-tab" => Some(TrayAction::NewTab),
+            "new-private-window" => Some(TrayAction::NewPrivateWindow),
+            "new-identity" => Some(TrayAction::NewIdentity),
+            "quit" => Some(TrayAction::Quit),
+            _ => match id.strip_prefix("retry-download-") {
+                Some(id) => id.parse().ok().map(TrayAction::RetryDownload),
+                None => id
+                    .strip_prefix("download-")
+                    .and_then(|id| id.parse().ok())
+                    .map(TrayAction::OpenDownload),
+            },
+        }
+    }
+}
+
+/// One entry of the tray menu
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrayMenuEntry {
+    Item {
+        action: TrayAction,
+        label: String,
+    },
+    /// Disabled text, e.g. for an empty list
+    Label(String),
+    Submenu {
+        label: String,
+        entries: Vec<TrayMenuEntry>,
+    },
+    Separator,
+}
+
+/// RGBA image for the tray icon
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayIconImage {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What the tray shows: menu, tooltip and icon for the browser's state
+#[derive(Debug, Clone, Default)]
+pub struct TrayModel {
+    settings: TraySettings,
+}
+
+impl TrayModel {
+    /// Create new tray model
+    pub fn new(settings: Option<TraySettings>) -> Self {
+        Self {
+            settings: settings.unwrap_or_default(),
+        }
+    }
+
+    /// Build the tray menu
+    pub fn menu(&self, downloads: &[Download], window_visible: bool) -> Vec<TrayMenuEntry> {
+        let item = |action, label: String| TrayMenuEntry::Item { action, label };
+
+        let mut recent: Vec<&Download> = downloads
+            .iter()
+            .filter(|d| matches!(d.status, DownloadStatus::Completed | DownloadStatus::Failed))
+            .collect();
+        recent.sort_by_key(|d| std::cmp::Reverse(d.started_at));
+        let mut recent_entries: Vec<TrayMenuEntry> = recent
+            .into_iter()
+            .take(self.settings.recent_downloads)
+            .map(|d| match d.status {
+                DownloadStatus::Failed => item(TrayAction::RetryDownload(d.id), tr_args("tray-retry-download", &[("filename", &d.filename)])),
+                _ => item(TrayAction::OpenDownload(d.id), d.filename.clone()),
+            })
+            .collect();
+        if recent_entries.is_empty() {
+            recent_entries.push(TrayMenuEntry::Label(tr("tray-no-downloads")));
+        }
+
+        vec![
+            item(
+                TrayAction::ToggleWindow,
+                tr(if window_visible { "tray-hide" } else { "tray-show" }),
+            ),
+            TrayMenuEntry::Separator,
+            item(TrayAction::NewTab, tr("menu-new-tab")),
+            item(TrayAction::NewPrivateWindow, tr("tray-new-private-window")),
+            item(TrayAction::NewIdentity, tr("menu-new-identity")),
+            TrayMenuEntry::Separator,
+            TrayMenuEntry::Submenu {
+                label: tr("tray-recent-downloads"),
+                entries: recent_entries,
+            },
+            TrayMenuEntry::Separator,
+            item(TrayAction::Quit, tr("tray-quit")),
+        ]
+    }
+
+    /// Tooltip text for the icon
+    pub fn tooltip(&self, downloads: &[Download]) -> String {
+        tr_args("tray-tooltip", &[("count", &active_downloads(downloads).to_string())])
+    }
+
+    /// Render the icon, with a badge counting active downloads
+    pub fn icon(&self, downloads: &[Download]) -> TrayIconImage {
+        let size = TRAY_ICON_SIZE as i32;
+        let mut image = TrayIconImage {
+            rgba: vec![0; (size * size * 4) as usize],
+            width: TRAY_ICON_SIZE,
+            height: TRAY_ICON_SIZE,
+        };
+
+        // Globe
+        fill_circle(&mut image, size / 2, size / 2, size / 2 - 1, [0x25, 0x63, 0xeb, 0xff]);
+
+        let active = active_downloads(downloads);
+        if self.settings.show_download_badge && active > 0 {
+            let radius = size / 4;
+            let (cx, cy) = (size - radius - 1, size - radius - 1);
+            fill_circle(&mut image, cx, cy, radius, [0xdc, 0x26, 0x26, 0xff]);
+            draw_digit(&mut image, cx - 1, cy - 2, active.min(9), [0xff, 0xff, 0xff, 0xff]);
+        }
+
+        image
+    }
+
+    /// Tray settings
+    pub fn settings(&self) -> &TraySettings {
+        &self.settings
+    }
+
+    /// Change tray settings
+    pub fn set_settings(&mut self, settings: TraySettings) {
+        self.settings = settings;
+    }
+}
+
+/// Number of downloads that are pending or transferring
+pub fn active_downloads(downloads: &[Download]) -> usize {
+    downloads
+        .iter()
+        .filter(|d| matches!(d.status, DownloadStatus::Pending | DownloadStatus::Downloading))
+        .count()
+}
+
+// Private helper functions
+
+/// 3x5 pixel digits, one row per entry, bits from left to right
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+fn set_pixel(image: &mut TrayIconImage, x: i32, y: i32, color: [u8; 4]) {
+    if x < 0 || y < 0 || x >= image.width as i32 || y >= image.height as i32 {
+        return;
+    }
+    let offset = ((y as u32 * image.width + x as u32) * 4) as usize;
+    image.rgba[offset..offset + 4].copy_from_slice(&color);
+}
+
+fn fill_circle(image: &mut TrayIconImage, cx: i32, cy: i32, radius: i32, color: [u8; 4]) {
+    for y in cy - radius..=cy + radius {
+        for x in cx - radius..=cx + radius {
+            let (dx, dy) = (x - cx, y - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                set_pixel(image, x, y, color);
+            }
+        }
+    }
+}
+
+fn draw_digit(image: &mut TrayIconImage, left: i32, top: i32, digit: usize, color: [u8; 4]) {
+    for (row, bits) in DIGITS[digit].iter().enumerate() {
+        for column in 0..3 {
+            if bits & (0b100 >> column) != 0 {
+                set_pixel(image, left + column, top + row as i32, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(id: usize, status: DownloadStatus) -> Download {
+        Download {
+            id,
+            url: format!("https://example.com/file{}.zip", id),
+            filename: format!("file{}.zip", id),
+            path: format!("/tmp/file{}.zip", id),
+            size: 100,
+            downloaded: 0,
+            status,
+            started_at: chrono::Utc::now() + chrono::Duration::seconds(id as i64),
+            mirrors: Vec::new(),
+            attempts: Vec::new(),
+            mime: None,
+        }
+    }
+
+    #[test]
+    fn test_menu_lists_recent_downloads() {
+        let model = TrayModel::new(Some(TraySettings {
+            recent_downloads: 2,
+            ..TraySettings::default()
+        }));
+        let downloads = vec![
+            download(1, DownloadStatus::Completed),
+            download(2, DownloadStatus::Downloading),
+            download(3, DownloadStatus::Completed),
+            download(4, DownloadStatus::Completed),
+            download(5, DownloadStatus::Failed),
+        ];
+
+        let menu = model.menu(&downloads, true);
+        let TrayMenuEntry::Submenu { entries, .. } = &menu[6] else {
+            panic!("expected downloads submenu");
+        };
+        let actions: Vec<TrayAction> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                TrayMenuEntry::Item { action, .. } => Some(*action),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(actions, vec![TrayAction::RetryDownload(5), TrayAction::OpenDownload(4)]);
+        assert_eq!(TrayAction::from_id(&TrayAction::OpenDownload(4).id()), Some(TrayAction::OpenDownload(4)));
+        assert_eq!(TrayAction::from_id(&TrayAction::RetryDownload(5).id()), Some(TrayAction::RetryDownload(5)));
+        assert_eq!(TrayAction::from_id(&TrayAction::NewIdentity.id()), Some(TrayAction::NewIdentity));
+        assert_eq!(model.tooltip(&downloads), "WebX - 1 download in progress");
+    }
+
+    #[test]
+    fn test_icon_badge_follows_active_downloads() {
+        let model = TrayModel::new(None);
+        let idle = model.icon(&[]);
+        let busy = model.icon(&[download(1, DownloadStatus::Downloading)]);
+
+        assert_eq!(idle.rgba.len(), (TRAY_ICON_SIZE * TRAY_ICON_SIZE * 4) as usize);
+        assert_ne!(idle, busy);
+
+        let mut hidden_badge = model.clone();
+        hidden_badge.set_settings(TraySettings {
+            show_download_badge: false,
+            ..TraySettings::default()
+        });
+        assert_eq!(hidden_badge.icon(&[download(1, DownloadStatus::Downloading)]), idle);
+    }
+}
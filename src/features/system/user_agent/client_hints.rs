@@ -0,0 +1,406 @@
+// User Agent Client Hints
+use serde::{Deserialize, Serialize};
+
+/// Every Sec-CH-UA request header a Chromium browser may send. Callers strip
+/// these when the spoofed user agent is not Chromium-based, since Firefox and
+/// Safari never send them.
+pub const CLIENT_HINT_HEADERS: &[&str] = &[
+    "Sec-CH-UA",
+    "Sec-CH-UA-Mobile",
+    "Sec-CH-UA-Platform",
+    "Sec-CH-UA-Platform-Version",
+    "Sec-CH-UA-Arch",
+    "Sec-CH-UA-Bitness",
+    "Sec-CH-UA-Model",
+    "Sec-CH-UA-Full-Version",
+    "Sec-CH-UA-Full-Version-List",
+    "Sec-CH-UA-WoW64",
+];
+
+/// A brand/version pair as exposed by Sec-CH-UA and navigator.userAgentData
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BrandVersion {
+    pub brand: String,
+    pub version: String,
+}
+
+/// Client Hints derived from a user agent string
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientHints {
+    pub brands: Vec<BrandVersion>,
+    pub full_version_list: Vec<BrandVersion>,
+    pub full_version: String,
+    pub mobile: bool,
+    pub platform: String,
+    pub platform_version: String,
+    pub architecture: String,
+    pub bitness: String,
+    pub model: String,
+    pub wow64: bool,
+}
+
+impl ClientHints {
+    /// Derive Client Hints from a user agent string. Returns `None` for
+    /// browsers that do not implement User-Agent Client Hints (Firefox,
+    /// Safari, Chrome on iOS), so no hints are sent for them at all.
+    pub fn from_user_agent(user_agent: &str) -> Option<Self> {
+        if user_agent.contains("Firefox/")
+            || user_agent.contains("FxiOS/")
+            || user_agent.contains("CriOS/")
+            || user_agent.contains("EdgiOS/")
+        {
+            return None;
+        }
+
+        let chrome_version = token_version(user_agent, "Chrome/")?;
+        let major = major_version(&chrome_version)?;
+
+        let (brand, brand_version) = if let Some(version) = token_version(user_agent, "Edg/")
+            .or_else(|| token_version(user_agent, "EdgA/"))
+        {
+            ("Microsoft Edge", version)
+        } else if let Some(version) = token_version(user_agent, "OPR/") {
+            ("Opera", version)
+        } else {
+            ("Google Chrome", chrome_version.clone())
+        };
+        let brand_major = major_version(&brand_version).unwrap_or(major);
+
+        let brands = greased_brand_list(
+            major,
+            ("Chromium", major.to_string()),
+            (brand, brand_major.to_string()),
+        );
+        let full_version_list = greased_brand_list(
+            major,
+            ("Chromium", chrome_version.clone()),
+            (brand, brand_version.clone()),
+        )
+        .into_iter()
+        .map(|mut entry| {
+            // The GREASE brand carries a full four-part version in the full list
+            if entry.brand.starts_with("Not") {
+                entry.version = format!("{}.0.0.0", entry.version);
+            }
+            entry
+        })
+        .collect();
+
+        let platform_info = platform_from_user_agent(user_agent);
+
+        Some(Self {
+            brands,
+            full_version_list,
+            full_version: brand_version,
+            mobile: user_agent.contains("Mobile"),
+            platform: platform_info.platform.to_string(),
+            platform_version: platform_info.version,
+            architecture: platform_info.architecture.to_string(),
+            bitness: platform_info.bitness.to_string(),
+            model: platform_info.model,
+            wow64: user_agent.contains("WOW64"),
+        })
+    }
+
+    /// Low-entropy hints sent on every request
+    pub fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("Sec-CH-UA".to_string(), format_brand_list(&self.brands)),
+            ("Sec-CH-UA-Mobile".to_string(), format_boolean(self.mobile)),
+            ("Sec-CH-UA-Platform".to_string(), quote(&self.platform)),
+        ]
+    }
+
+    /// Value for a single hint header, including high-entropy hints a site
+    /// requested through Accept-CH
+    pub fn header_value(&self, name: &str) -> Option<String> {
+        let value = match name.to_ascii_lowercase().as_str() {
+            "sec-ch-ua" => format_brand_list(&self.brands),
+            "sec-ch-ua-mobile" => format_boolean(self.mobile),
+            "sec-ch-ua-platform" => quote(&self.platform),
+            "sec-ch-ua-platform-version" => quote(&self.platform_version),
+            "sec-ch-ua-arch" => quote(&self.architecture),
+            "sec-ch-ua-bitness" => quote(&self.bitness),
+            "sec-ch-ua-model" => quote(&self.model),
+            "sec-ch-ua-full-version" => quote(&self.full_version),
+            "sec-ch-ua-full-version-list" => format_brand_list(&self.full_version_list),
+            "sec-ch-ua-wow64" => format_boolean(self.wow64),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Headers for a request, adding the high-entropy hints listed in a
+    /// site's Accept-CH response header
+    pub fn headers_for_accept_ch(&self, accept_ch: &str) -> Vec<(String, String)> {
+        let mut headers = self.headers();
+        for requested in accept_ch.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            if headers.iter().any(|(name, _)| name.eq_ignore_ascii_case(requested)) {
+                continue;
+            }
+            if let Some(value) = self.header_value(requested) {
+                let name = CLIENT_HINT_HEADERS
+                    .iter()
+                    .find(|known| known.eq_ignore_ascii_case(requested))
+                    .map(|known| known.to_string())
+                    .unwrap_or_else(|| requested.to_string());
+                headers.push((name, value));
+            }
+        }
+        headers
+    }
+
+    /// JSON object mirroring the values navigator.userAgentData exposes,
+    /// including the getHighEntropyValues() fields
+    pub fn to_user_agent_data(&self) -> serde_json::Value {
+        serde_json::json!({
+            "brands": self.brands,
+            "mobile": self.mobile,
+            "platform": self.platform,
+            "highEntropy": {
+                "architecture": self.architecture,
+                "bitness": self.bitness,
+                "model": self.model,
+                "platformVersion": self.platform_version,
+                "uaFullVersion": self.full_version,
+                "fullVersionList": self.full_version_list,
+                "wow64": self.wow64,
+            },
+        })
+    }
+}
+
+struct PlatformInfo {
+    platform: &'static str,
+    version: String,
+    architecture: &'static str,
+    bitness: &'static str,
+    model: String,
+}
+
+// Private helper functions
+
+fn token_version(user_agent: &str, token: &str) -> Option<String> {
+    let start = user_agent.find(token)? + token.len();
+    let version: String = user_agent[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+fn major_version(version: &str) -> Option<u32> {
+    version.split('.').next()?.parse().ok()
+}
+
+/// Chromium's GREASE algorithm: the fake brand and the brand order are both
+/// seeded by the major version, so the list matches what that release sends
+fn greased_brand_list(
+    seed: u32,
+    chromium: (&str, String),
+    brand: (&str, String),
+) -> Vec<BrandVersion> {
+    const GREASEY_CHARS: [&str; 11] = [" ", "(", ":", "-", ".", "/", ")", ";", "=", "?", "_"];
+    const GREASED_VERSIONS: [&str; 3] = ["8", "99", "24"];
+    const ORDERS: [[usize; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+
+    let seed = seed as usize;
+    let grease = BrandVersion {
+        brand: format!(
+            "Not{}A{}Brand",
+            GREASEY_CHARS[seed % GREASEY_CHARS.len()],
+            GREASEY_CHARS[(seed + 1) % GREASEY_CHARS.len()]
+        ),
+        version: GREASED_VERSIONS[seed % GREASED_VERSIONS.len()].to_string(),
+    };
+    let chromium = BrandVersion {
+        brand: chromium.0.to_string(),
+        version: chromium.1,
+    };
+    let brand = BrandVersion {
+        brand: brand.0.to_string(),
+        version: brand.1,
+    };
+
+    let order = ORDERS[seed % ORDERS.len()];
+    let mut slots: [Option<BrandVersion>; 3] = [None, None, None];
+    slots[order[0]] = Some(grease);
+    slots[order[1]] = Some(chromium);
+    slots[order[2]] = Some(brand);
+    slots.into_iter().flatten().collect()
+}
+
+fn platform_from_user_agent(user_agent: &str) -> PlatformInfo {
+    let (architecture, bitness) = if user_agent.contains("aarch64") || user_agent.contains("ARM64") {
+        ("arm", "64")
+    } else if user_agent.contains("x86_64")
+        || user_agent.contains("Win64")
+        || user_agent.contains("x64")
+        || user_agent.contains("Intel Mac")
+    {
+        ("x86", "64")
+    } else {
+        ("x86", "32")
+    };
+
+    if let Some(android) = user_agent.find("Android") {
+        // "Android 14; SM-S918U)" -> version 14.0.0, model SM-S918U
+        let rest = &user_agent[android + "Android".len()..];
+        let section = rest.split(')').next().unwrap_or("");
+        let mut parts = section.split(';').map(str::trim);
+        let version = parts.next().unwrap_or("");
+        let model = parts.find(|part| !part.is_empty() && *part != "K").unwrap_or("");
+        return PlatformInfo {
+            platform: "Android",
+            version: pad_version(version),
+            architecture: "",
+            bitness: "",
+            model: model.to_string(),
+        };
+    }
+
+    if user_agent.contains("Windows") {
+        // The Windows version in the UA string is frozen at 10.0
+        return PlatformInfo {
+            platform: "Windows",
+            version: "10.0.0".to_string(),
+            architecture,
+            bitness,
+            model: String::new(),
+        };
+    }
+
+    if user_agent.contains("Macintosh") || user_agent.contains("Mac OS X") {
+        let version = user_agent
+            .find("Mac OS X ")
+            .map(|start| {
+                user_agent[start + "Mac OS X ".len()..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit() || *c == '_' || *c == '.')
+                    .collect::<String>()
+                    .replace('_', ".")
+            })
+            .unwrap_or_default();
+        return PlatformInfo {
+            platform: "macOS",
+            version: pad_version(&version),
+            architecture,
+            bitness,
+            model: String::new(),
+        };
+    }
+
+    if user_agent.contains("CrOS") {
+        return PlatformInfo {
+            platform: "Chrome OS",
+            version: String::new(),
+            architecture,
+            bitness,
+            model: String::new(),
+        };
+    }
+
+    if user_agent.contains("Linux") || user_agent.contains("X11") {
+        return PlatformInfo {
+            platform: "Linux",
+            version: String::new(),
+            architecture,
+            bitness,
+            model: String::new(),
+        };
+    }
+
+    PlatformInfo {
+        platform: "Unknown",
+        version: String::new(),
+        architecture: "",
+        bitness: "",
+        model: String::new(),
+    }
+}
+
+fn pad_version(version: &str) -> String {
+    if version.is_empty() {
+        return String::new();
+    }
+    let mut parts: Vec<&str> = version.split('.').collect();
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    parts.join(".")
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn format_boolean(value: bool) -> String {
+    if value { "?1" } else { "?0" }.to_string()
+}
+
+fn format_brand_list(brands: &[BrandVersion]) -> String {
+    brands
+        .iter()
+        .map(|entry| format!("{};v={}", quote(&entry.brand), quote(&entry.version)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    #[test]
+    fn test_chrome_hints_match_real_browser() {
+        let hints = ClientHints::from_user_agent(CHROME_WINDOWS).unwrap();
+        assert_eq!(
+            hints.header_value("Sec-CH-UA").unwrap(),
+            r#""Not_A Brand";v="8", "Chromium";v="120", "Google Chrome";v="120""#
+        );
+        assert_eq!(hints.header_value("Sec-CH-UA-Platform").unwrap(), "\"Windows\"");
+        assert_eq!(hints.header_value("Sec-CH-UA-Mobile").unwrap(), "?0");
+        assert_eq!(hints.architecture, "x86");
+
+        let headers = hints.headers_for_accept_ch("sec-ch-ua-platform-version, Sec-CH-UA-Bitness");
+        assert_eq!(headers.len(), 5);
+        assert!(headers.contains(&("Sec-CH-UA-Platform-Version".to_string(), "\"10.0.0\"".to_string())));
+    }
+
+    #[test]
+    fn test_mobile_and_edge_hints() {
+        let android = "Mozilla/5.0 (Linux; Android 14; SM-S918U) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36";
+        let hints = ClientHints::from_user_agent(android).unwrap();
+        assert!(hints.mobile);
+        assert_eq!(hints.platform, "Android");
+        assert_eq!(hints.platform_version, "14.0.0");
+        assert_eq!(hints.model, "SM-S918U");
+
+        let edge = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.2210.91";
+        let hints = ClientHints::from_user_agent(edge).unwrap();
+        assert_eq!(hints.platform, "macOS");
+        assert_eq!(hints.platform_version, "10.15.7");
+        assert_eq!(hints.full_version, "120.0.2210.91");
+        assert!(hints.brands.iter().any(|entry| entry.brand == "Microsoft Edge"));
+    }
+
+    #[test]
+    fn test_non_chromium_has_no_hints() {
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+        let safari = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15";
+        assert!(ClientHints::from_user_agent(firefox).is_none());
+        assert!(ClientHints::from_user_agent(safari).is_none());
+    }
+}
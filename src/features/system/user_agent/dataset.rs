@@ -0,0 +1,156 @@
+// User Agent Profile Dataset
+use crate::error::WebxError;
+use super::UserAgentProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Longest user agent string accepted from a dataset
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// One profile entry in a dataset
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatasetEntry {
+    pub profile: UserAgentProfile,
+    pub user_agent: String,
+}
+
+/// A downloaded set of up-to-date user agent strings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserAgentDataset {
+    #[serde(default)]
+    pub generated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub profiles: Vec<DatasetEntry>,
+    #[serde(default)]
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl UserAgentDataset {
+    /// Parse and validate a dataset. Entries that fail validation are
+    /// dropped; a dataset without a single valid entry is rejected.
+    pub fn parse(json: &str) -> Result<Self, WebxError> {
+        let mut dataset: UserAgentDataset = serde_json::from_str(json)?;
+
+        let total = dataset.profiles.len();
+        dataset.profiles.retain(|entry| match validate_entry(entry) {
+            Ok(()) => true,
+            Err(reason) => {
+                tracing::warn!("Skipping user agent for {:?}: {}", entry.profile, reason);
+                false
+            }
+        });
+
+        if dataset.profiles.is_empty() {
+            return Err(format!("User agent dataset has no valid profiles ({} rejected)", total).into());
+        }
+
+        Ok(dataset)
+    }
+
+    /// Download and validate a dataset
+    pub async fn fetch(url: &str, timeout: Duration) -> Result<Self, WebxError> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        let response = client.get(url).send().await?.error_for_status()?;
+        let body = response.text().await?;
+
+        let mut dataset = Self::parse(&body)?;
+        dataset.fetched_at = Some(chrono::Utc::now());
+        Ok(dataset)
+    }
+
+    /// Profiles keyed for merging over the bundled strings
+    pub fn to_profiles(&self) -> HashMap<UserAgentProfile, String> {
+        self.profiles
+            .iter()
+            .map(|entry| (entry.profile.clone(), entry.user_agent.clone()))
+            .collect()
+    }
+
+    /// Check if the dataset should be downloaded again
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        match self.fetched_at {
+            Some(fetched_at) => {
+                let age = chrono::Utc::now().signed_duration_since(fetched_at);
+                age.to_std().map(|age| age >= max_age).unwrap_or(false)
+            }
+            None => true,
+        }
+    }
+}
+
+// Private helper functions
+
+fn validate_entry(entry: &DatasetEntry) -> Result<(), String> {
+    let ua = entry.user_agent.as_str();
+
+    if ua.len() > MAX_USER_AGENT_LENGTH {
+        return Err("user agent is too long".to_string());
+    }
+    if !ua.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return Err("user agent contains non-printable characters".to_string());
+    }
+    if !ua.starts_with("Mozilla/5.0 (") {
+        return Err("user agent does not start with Mozilla/5.0".to_string());
+    }
+
+    // The string must still describe the browser and platform the profile names
+    let required: &[&str] = match entry.profile {
+        UserAgentProfile::ChromeWindows => &["Chrome/", "Windows NT"],
+        UserAgentProfile::ChromeMac => &["Chrome/", "Macintosh"],
+        UserAgentProfile::ChromeLinux => &["Chrome/", "Linux"],
+        UserAgentProfile::FirefoxWindows => &["Firefox/", "Windows NT"],
+        UserAgentProfile::FirefoxMac => &["Firefox/", "Macintosh"],
+        UserAgentProfile::FirefoxLinux => &["Firefox/", "Linux"],
+        UserAgentProfile::SafariMac => &["Version/", "Safari/", "Macintosh"],
+        UserAgentProfile::SafariIOS => &["Version/", "Safari/", "iPhone"],
+        UserAgentProfile::EdgeWindows => &["Edg/", "Windows NT"],
+        UserAgentProfile::EdgeMac => &["Edg/", "Macintosh"],
+        UserAgentProfile::MobileAndroid => &["Android", "Mobile"],
+        UserAgentProfile::MobileIOS => &["iPhone", "Mobile"],
+        UserAgentProfile::Custom(_) => &[],
+    };
+    if let Some(missing) = required.iter().find(|token| !ua.contains(*token)) {
+        return Err(format!("user agent does not contain {}", missing));
+    }
+
+    let excluded: &[&str] = match entry.profile {
+        UserAgentProfile::ChromeWindows | UserAgentProfile::ChromeMac | UserAgentProfile::ChromeLinux => {
+            &["Edg/", "OPR/", "Firefox/"]
+        }
+        UserAgentProfile::SafariMac | UserAgentProfile::SafariIOS => &["Chrome/", "Firefox/"],
+        _ => &[],
+    };
+    if let Some(found) = excluded.iter().find(|token| ua.contains(*token)) {
+        return Err(format!("user agent unexpectedly contains {}", found));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_drops_invalid_entries() {
+        let json = r#"{
+            "generated_at": "2026-10-01T00:00:00Z",
+            "profiles": [
+                {"profile": "ChromeWindows", "user_agent": "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36"},
+                {"profile": "FirefoxLinux", "user_agent": "Mozilla/5.0 (Windows NT 10.0; rv:143.0) Gecko/20100101 Firefox/143.0"},
+                {"profile": "SafariMac", "user_agent": "curl/8.0"}
+            ]
+        }"#;
+
+        let dataset = UserAgentDataset::parse(json).unwrap();
+        assert_eq!(dataset.profiles.len(), 1);
+        assert!(dataset.to_profiles()[&UserAgentProfile::ChromeWindows].contains("Chrome/141"));
+        assert!(dataset.is_stale(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_dataset() {
+        assert!(UserAgentDataset::parse(r#"{"profiles": []}"#).is_err());
+        assert!(UserAgentDataset::parse("not json").is_err());
+    }
+}
@@ -1,13 +1,867 @@
-// User Agent Manager Module - Placeholder
-// TODO: Implement user agent switching functionality
-pub struct UserAgentManager;
+// User Agent Switcher
+pub mod client_hints;
+pub mod dataset;
 
-impl UserAgentManager {
-    pub fn new() -> Self {
-        Self
+pub use client_hints::{BrandVersion, ClientHints, CLIENT_HINT_HEADERS};
+pub use dataset::{DatasetEntry, UserAgentDataset};
+
+use crate::config::storage::{save_json, write_atomic, DEFAULT_BACKUP_COUNT};
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a dataset download may take
+const DATASET_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before retrying a failed dataset download
+const DATASET_RETRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Predefined user agents
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum UserAgentProfile {
+    ChromeWindows,
+    ChromeMac,
+    ChromeLinux,
+    FirefoxWindows,
+    FirefoxMac,
+    FirefoxLinux,
+    SafariMac,
+    SafariIOS,
+    EdgeWindows,
+    EdgeMac,
+    MobileAndroid,
+    MobileIOS,
+    Custom(String),
+}
+
+/// User agent configuration for a site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteUserAgent {
+    pub domain_pattern: String,
+    pub user_agent: String,
+    pub enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Global user agent settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAgentConfig {
+    pub global_user_agent: Option<String>,
+    pub per_site_enabled: bool,
+    pub randomize_user_agent: bool,
+    pub randomization_frequency: RandomizationFrequency,
+    /// URL of a JSON dataset with up-to-date user agent strings
+    #[serde(default)]
+    pub dataset_url: Option<String>,
+    #[serde(default = "default_dataset_update_hours")]
+    pub dataset_update_hours: u32,
+}
+
+fn default_dataset_update_hours() -> u32 {
+    24
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RandomizationFrequency {
+    PerRequest,
+    PerSession,
+    PerHour,
+    PerDay,
+}
+
+impl Default for UserAgentConfig {
+    fn default() -> Self {
+        Self {
+            global_user_agent: None,
+            per_site_enabled: true,
+            randomize_user_agent: false,
+            randomization_frequency: RandomizationFrequency::PerSession,
+            dataset_url: None,
+            dataset_update_hours: default_dataset_update_hours(),
+        }
+    }
+}
+
+/// User agent switcher manager
+pub struct UserAgentSwitcher {
+    config: UserAgentConfig,
+    site_agents: Arc<Mutex<HashMap<String, SiteUserAgent>>>,
+    current_session_agents: Arc<Mutex<HashMap<String, String>>>,
+    dataset: Arc<Mutex<Option<UserAgentDataset>>>,
+    dataset_updater: Option<tokio::task::JoinHandle<()>>,
+    config_path: PathBuf,
+}
+
+impl UserAgentSwitcher {
+    /// Create a new user agent switcher
+    pub fn new(
+        config: Option<UserAgentConfig>,
+        config_dir: Option<PathBuf>,
+    ) -> Result<Self, WebxError> {
+        let config = config.unwrap_or_default();
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("user-agents");
+            path
+        });
+        
+        // Create config directory
+        fs::create_dir_all(&config_dir)?;
+        
+        let mut switcher = Self {
+            config,
+            site_agents: Arc::new(Mutex::new(HashMap::new())),
+            current_session_agents: Arc::new(Mutex::new(HashMap::new())),
+            dataset: Arc::new(Mutex::new(None)),
+            dataset_updater: None,
+            config_path: config_dir.join("config.json"),
+        };
+        
+        // Load existing configuration
+        switcher.load_config()?;
+        
+        Ok(switcher)
+    }
+
+    /// Get user agent for a URL
+    pub fn get_user_agent(&self, url: &str) -> String {
+        // Check for site-specific user agent first
+        if self.config.per_site_enabled {
+            if let Some(domain) = self.extract_domain(url) {
+                let site_agents = self.site_agents.lock_or_recover();
+                if let Some(site_agent) = site_agents.get(&domain) {
+                    if site_agent.enabled {
+                        return site_agent.user_agent.clone();
+                    }
+                }
+            }
+        }
+        
+        // Check for session-specific user agent
+        if self.config.randomize_user_agent {
+            let mut session_agents = self.current_session_agents.lock_or_recover();
+            let domain = self.extract_domain(url).unwrap_or_else(|| "default".to_string());
+            
+            if !session_agents.contains_key(&domain) {
+                let random_ua = self.generate_random_user_agent();
+                session_agents.insert(domain.clone(), random_ua);
+            }
+            
+            return session_agents.get(&domain).unwrap().clone();
+        }
+        
+        // Return global user agent or default
+        self.config
+            .global_user_agent
+            .clone()
+            .unwrap_or_else(|| self.get_default_user_agent())
+    }
+
+    /// Set global user agent
+    pub fn set_global_user_agent(&mut self, user_agent: Option<String>) -> Result<(), WebxError> {
+        self.config.global_user_agent = user_agent;
+        self.save_config()?;
+        Ok(())
+    }
+
+    /// Set user agent for a specific site
+    pub fn set_site_user_agent(
+        &self,
+        domain_pattern: String,
+        user_agent: String,
+    ) -> Result<(), WebxError> {
+        let site_agent = SiteUserAgent {
+            domain_pattern: domain_pattern.clone(),
+            user_agent,
+            enabled: true,
+            created_at: chrono::Utc::now(),
+        };
+        
+        {
+            let mut site_agents = self.site_agents.lock_or_recover();
+            site_agents.insert(domain_pattern, site_agent);
+        }
+        
+        self.save_site_agents()?;
+        Ok(())
+    }
+
+    /// Remove site-specific user agent
+    pub fn remove_site_user_agent(&self, domain_pattern: &str) -> bool {
+        let removed = self.site_agents.lock_or_recover().remove(domain_pattern).is_some();
+
+
+        if removed {
+            let _ = self.save_site_agents();
+        }
+        
+        removed
+    }
+
+    /// Enable/disable site-specific user agent
+    pub fn set_site_agent_enabled(&self, domain_pattern: &str, enabled: bool) -> bool {
+        let found = match self.site_agents.lock_or_recover().get_mut(domain_pattern) {
+            Some(agent) => {
+                agent.enabled = enabled;
+                true
+            }
+            None => false,
+        };
+
+        if found {
+            let _ = self.save_site_agents();
+        }
+
+        found
+    }
+
+    /// Get all site-specific user agents
+    pub fn get_site_agents(&self) -> Vec<SiteUserAgent> {
+        let site_agents = self.site_agents.lock_or_recover();
+        site_agents.values().cloned().collect()
+    }
+
+    /// Set randomization configuration
+    pub fn set_randomization(
+        &mut self,
+        enabled: bool,
+        frequency: RandomizationFrequency,
+    ) -> Result<(), WebxError> {
+        self.config.randomize_user_agent = enabled;
+        self.config.randomization_frequency = frequency;
+        self.save_config()?;
+        Ok(())
+    }
+
+    /// Clear session user agents
+    pub fn clear_session_agents(&self) {
+        self.current_session_agents.lock_or_recover().clear();
+    }
+
+    /// Reset to default configuration
+    pub fn reset_to_defaults(&mut self) -> Result<(), WebxError> {
+        self.config = UserAgentConfig::default();
+        self.site_agents.lock_or_recover().clear();
+        self.current_session_agents.lock_or_recover().clear();
+        self.save_config()?;
+        self.save_site_agents()?;
+        Ok(())
+    }
+
+    /// Get available profiles: the bundled strings, overridden by the
+    /// downloaded dataset when one is available
+    pub fn get_profiles(&self) -> HashMap<UserAgentProfile, String> {
+        let mut profiles = Self::get_predefined_profiles();
+        if let Some(dataset) = self.dataset.lock_or_recover().as_ref() {
+            profiles.extend(dataset.to_profiles());
+        }
+        profiles
+    }
+
+    /// Get the user agent string for a profile
+    pub fn get_profile_user_agent(&self, profile: &UserAgentProfile) -> Option<String> {
+        if let UserAgentProfile::Custom(user_agent) = profile {
+            return Some(user_agent.clone());
+        }
+        self.get_profiles().get(profile).cloned()
+    }
+
+    /// Set the URL of the user agent dataset
+    pub fn set_dataset_url(&mut self, url: Option<String>, update_hours: u32) -> Result<(), WebxError> {
+        self.config.dataset_url = url;
+        self.config.dataset_update_hours = update_hours.max(1);
+        self.save_config()?;
+        Ok(())
+    }
+
+    /// Get the active dataset, if one has been downloaded
+    pub fn get_dataset(&self) -> Option<UserAgentDataset> {
+        self.dataset.lock_or_recover().clone()
+    }
+
+    /// Replace the active dataset and cache it for offline use
+    pub fn apply_dataset(&self, dataset: UserAgentDataset) -> Result<(), WebxError> {
+        write_atomic(&self.dataset_path(), &serde_json::to_vec_pretty(&dataset)?)?;
+        *self.dataset.lock_or_recover() = Some(dataset);
+        Ok(())
+    }
+
+    /// Download the dataset now, keeping the current profiles on failure
+    pub async fn update_dataset(switcher: Arc<Mutex<UserAgentSwitcher>>) -> Result<(), WebxError> {
+        let url = switcher.lock_or_recover().config.dataset_url.clone();
+        let url = url.ok_or("No user agent dataset URL configured")?;
+
+        let dataset = UserAgentDataset::fetch(&url, DATASET_FETCH_TIMEOUT).await?;
+        tracing::info!("Updated user agent dataset with {} profiles", dataset.profiles.len());
+        switcher.lock_or_recover().apply_dataset(dataset)
+    }
+
+    /// Keep the dataset up to date in the background. Downloads happen when
+    /// the cached copy is older than the configured interval; failures are
+    /// retried later while the cached or bundled strings stay in use.
+    pub fn start_dataset_updates(switcher: Arc<Mutex<UserAgentSwitcher>>) {
+        let task_switcher = switcher.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let (url, max_age, stale) = {
+                    let switcher = task_switcher.lock_or_recover();
+                    let max_age = Duration::from_secs(switcher.config.dataset_update_hours.max(1) as u64 * 3600);
+                    let stale = switcher
+                        .dataset
+                        .lock_or_recover()
+                        .as_ref()
+                        .map(|dataset| dataset.is_stale(max_age))
+                        .unwrap_or(true);
+                    (switcher.config.dataset_url.clone(), max_age, stale)
+                };
+
+                let wait = match url {
+                    Some(_) if stale => match Self::update_dataset(task_switcher.clone()).await {
+                        Ok(()) => max_age,
+                        Err(e) => {
+                            tracing::warn!("Failed to update user agent dataset: {}", e);
+                            DATASET_RETRY_INTERVAL
+                        }
+                    },
+                    _ => DATASET_RETRY_INTERVAL.min(max_age),
+                };
+
+                tokio::time::sleep(wait).await;
+            }
+        });
+
+        let mut switcher = switcher.lock_or_recover();
+        switcher.stop_dataset_updates();
+        switcher.dataset_updater = Some(handle);
+    }
+
+    /// Stop updating the dataset in the background
+    pub fn stop_dataset_updates(&mut self) {
+        if let Some(handle) = self.dataset_updater.take() {
+            handle.abort();
+        }
+    }
+
+    /// Check if background dataset updates are running
+    pub fn is_updating_dataset(&self) -> bool {
+        self.dataset_updater.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Get bundled user agent profiles, used when no dataset is available
+    pub fn get_predefined_profiles() -> HashMap<UserAgentProfile, String> {
+        let mut profiles = HashMap::new();
+        
+        // Chrome Windows
+        profiles.insert(
+            UserAgentProfile::ChromeWindows,
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
+        );
+        
+        // Chrome Mac
+        profiles.insert(
+            UserAgentProfile::ChromeMac,
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
+        );
+        
+        // Chrome Linux
+        profiles.insert(
+            UserAgentProfile::ChromeLinux,
+            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
+        );
+        
+        // Firefox Windows
+        profiles.insert(
+            UserAgentProfile::FirefoxWindows,
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:121.0) Gecko/20100101 Firefox/121.0".to_string()
+        );
+        
+        // Firefox Mac
+        profiles.insert(
+            UserAgentProfile::FirefoxMac,
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:121.0) Gecko/20100101 Firefox/121.0".to_string()
+        );
+        
+        // Firefox Linux
+        profiles.insert(
+            UserAgentProfile::FirefoxLinux,
+            "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0".to_string()
+        );
+        
+        // Safari Mac
+        profiles.insert(
+            UserAgentProfile::SafariMac,
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15".to_string()
+        );
+        
+        // Safari iOS
+        profiles.insert(
+            UserAgentProfile::SafariIOS,
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1".to_string()
+        );
+        
+        // Edge Windows
+        profiles.insert(
+            UserAgentProfile::EdgeWindows,
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0".to_string()
+        );
+        
+        // Edge Mac
+        profiles.insert(
+            UserAgentProfile::EdgeMac,
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0".to_string()
+        );
+        
+        // Mobile Android
+        profiles.insert(
+            UserAgentProfile::MobileAndroid,
+            "Mozilla/5.0 (Linux; Android 14; SM-S918U) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36".to_string()
+        );
+        
+        // Mobile iOS
+        profiles.insert(
+            UserAgentProfile::MobileIOS,
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1".to_string()
+        );
+        
+        profiles
+    }
+
+    /// Get JavaScript for user agent spoofing
+    pub fn get_user_agent_script(&self) -> String {
+        r#"
+(function() {
+    // Override navigator.userAgent
+    const originalUserAgent = navigator.userAgent;
+    let currentUserAgent = originalUserAgent;
+    
+    Object.defineProperty(navigator, 'userAgent', {
+        get: function() {
+            return currentUserAgent;
+        },
+        configurable: false
+    });
+    
+    // Override navigator.appVersion
+    Object.defineProperty(navigator, 'appVersion', {
+        get: function() {
+            return currentUserAgent.substring(currentUserAgent.indexOf('/') + 1);
+        },
+        configurable: false
+    });
+    
+    // Override navigator.platform
+    Object.defineProperty(navigator, 'platform', {
+        get: function() {
+            if (currentUserAgent.includes('Win')) return 'Win32';
+            if (currentUserAgent.includes('Mac')) return 'MacIntel';
+            if (currentUserAgent.includes('Linux')) return 'Linux x86_64';
+            return 'unknown';
+        },
+        configurable: false
+    });
+    
+    // Listen for user agent updates from the browser
+    window.addEventListener('webx-user-agent-change', function(e) {
+        currentUserAgent = e.detail.userAgent;
+    });
+    
+    console.log('User agent spoofing enabled');
+})();
+"#
+        .to_string()
+    }
+
+    /// Get the Client Hints matching the user agent sent to a URL. `None`
+    /// means the selected agent is not Chromium-based and must not send any.
+    pub fn get_client_hints(&self, url: &str) -> Option<ClientHints> {
+        ClientHints::from_user_agent(&self.get_user_agent(url))
+    }
+
+    /// Get the identity headers for a request: User-Agent plus the
+    /// low-entropy Client Hints, or no hints at all for non-Chromium agents
+    pub fn get_request_headers(&self, url: &str) -> Vec<(String, String)> {
+        let user_agent = self.get_user_agent(url);
+        let mut headers = vec![("User-Agent".to_string(), user_agent.clone())];
+        if let Some(hints) = ClientHints::from_user_agent(&user_agent) {
+            headers.extend(hints.headers());
+        }
+        headers
+    }
+
+    /// Get the identity headers for a request to a site that sent an
+    /// Accept-CH header, including the high-entropy hints it asked for
+    pub fn get_request_headers_with_accept_ch(&self, url: &str, accept_ch: &str) -> Vec<(String, String)> {
+        let user_agent = self.get_user_agent(url);
+        let mut headers = vec![("User-Agent".to_string(), user_agent.clone())];
+        if let Some(hints) = ClientHints::from_user_agent(&user_agent) {
+            headers.extend(hints.headers_for_accept_ch(accept_ch));
+        }
+        headers
+    }
+
+    /// Get JavaScript that pins navigator.userAgent, navigator.platform and
+    /// navigator.userAgentData to the same profile used for request headers
+    pub fn get_user_agent_script_for(&self, url: &str) -> String {
+        let user_agent = self.get_user_agent(url);
+        let hints = ClientHints::from_user_agent(&user_agent);
+        let platform = match hints.as_ref().map(|hints| hints.platform.as_str()) {
+            Some("Windows") => "Win32",
+            Some("macOS") => "MacIntel",
+            Some("Android") => "Linux armv81",
+            Some(_) => "Linux x86_64",
+            None => navigator_platform(&user_agent),
+        };
+        let user_agent_data = hints
+            .map(|hints| hints.to_user_agent_data())
+            .unwrap_or(serde_json::Value::Null);
+
+        format!(
+            r#"
+(function() {{
+    const userAgent = {user_agent};
+    const platform = {platform};
+    const uaData = {user_agent_data};
+
+    function define(target, name, value) {{
+        Object.defineProperty(target, name, {{
+            get: function() {{ return value; }},
+            configurable: false
+        }});
+    }}
+
+    define(navigator, 'userAgent', userAgent);
+    define(navigator, 'appVersion', userAgent.substring(userAgent.indexOf('/') + 1));
+    define(navigator, 'platform', platform);
+
+    if (uaData === null) {{
+        // Non-Chromium profiles do not expose User-Agent Client Hints
+        try {{ delete Navigator.prototype.userAgentData; }} catch (e) {{}}
+        define(navigator, 'userAgentData', undefined);
+        return;
+    }}
+
+    const brands = Object.freeze(uaData.brands.map(function(b) {{ return Object.freeze(b); }}));
+    const lowEntropy = {{ brands: brands, mobile: uaData.mobile, platform: uaData.platform }};
+    const userAgentData = {{
+        get brands() {{ return brands; }},
+        get mobile() {{ return uaData.mobile; }},
+        get platform() {{ return uaData.platform; }},
+        getHighEntropyValues: function(hints) {{
+            const result = Object.assign({{}}, lowEntropy);
+            (hints || []).forEach(function(hint) {{
+                if (Object.prototype.hasOwnProperty.call(uaData.highEntropy, hint)) {{
+                    result[hint] = uaData.highEntropy[hint];
+                }}
+            }});
+            return Promise.resolve(result);
+        }},
+        toJSON: function() {{ return Object.assign({{}}, lowEntropy); }}
+    }};
+    if (typeof NavigatorUAData !== 'undefined') {{
+        Object.setPrototypeOf(userAgentData, NavigatorUAData.prototype);
+    }}
+    define(navigator, 'userAgentData', userAgentData);
+}})();
+"#,
+            user_agent = serde_json::Value::String(user_agent.clone()),
+            platform = serde_json::Value::String(platform.to_string()),
+            user_agent_data = user_agent_data,
+        )
+    }
+
+    /// Set configuration
+    pub fn set_config(&mut self, config: UserAgentConfig) {
+        self.config = config;
+    }
+
+    /// Get current configuration
+    pub fn get_config(&self) -> &UserAgentConfig {
+        &self.config
+    }
+
+    // Private helper methods
+
+
+    fn extract_domain(&self, url: &str) -> Option<String> {
+        if let Ok(parsed) = url::Url::parse(url) {
+            if let Some(host) = parsed.host_str() {
+                // Remove www. prefix
+                return Some(host.replace("www.", ""));
+            }
+        }
+        None
+    }
+    
+    fn get_default_user_agent(&self) -> String {
+        // WebX default user agent
+        format!(
+            "Mozilla/5.0 ({}; {}) AppleWebKit/537.36 (KHTML, like Gecko) WebX/{} Safari/537.36",
+            self.get_platform(),
+            self.get_platform_details(),
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+    
+    fn get_platform(&self) -> &'static str {
+        if cfg!(target_os = "windows") {
+            "Windows NT 10.0; Win64; x64"
+        } else if cfg!(target_os = "macos") {
+            "Macintosh; Intel Mac OS X 10_15_7"
+        } else {
+            "X11; Linux x86_64"
+        }
+    }
+    
+    fn get_platform_details(&self) -> &'static str {
+        if cfg!(target_os = "windows") {
+            "Win64"
+        } else if cfg!(target_os = "macos") {
+            "Mac OS X"
+        } else {
+            "Linux"
+        }
+    }
+    
+    fn generate_random_user_agent(&self) -> String {
+        let profiles = self.get_profiles();
+        let profiles_vec: Vec<&String> = profiles.values().collect();
+        
+        use rand::seq::SliceRandom;
+        let mut rng = rand::thread_rng();
+        
+        profiles_vec
+            .choose(&mut rng)
+            .cloned()
+            .unwrap_or(&self.get_default_user_agent())
+            .clone()
+    }
+    
+    fn dataset_path(&self) -> PathBuf {
+        self.config_path.parent().unwrap().join("dataset.json")
+    }
+
+    fn save_config(&self) -> Result<(), WebxError> {
+        save_json(&self.config_path, &self.config, DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+    
+    fn save_site_agents(&self) -> Result<(), WebxError> {
+        let path = self.config_path.parent().unwrap().join("site_agents.json");
+        let site_agents = self.site_agents.lock_or_recover();
+        save_json(&path, &*site_agents, DEFAULT_BACKUP_COUNT)?;
+        Ok(())
     }
     
-    pub fn set_user_agent(&self, _user_agent: &str) {
-        // Placeholder implementation
+    fn load_config(&mut self) -> Result<(), WebxError> {
+        if self.config_path.exists() {
+            let content = fs::read_to_string(&self.config_path)?;
+            self.config = serde_json::from_str(&content)?;
+        }
+        
+        let site_agents_path = self.config_path.parent().unwrap().join("site_agents.json");
+        if site_agents_path.exists() {
+            let content = fs::read_to_string(&site_agents_path)?;
+            let site_agents: HashMap<String, SiteUserAgent> = serde_json::from_str(&content)?;
+            *self.site_agents.lock_or_recover() = site_agents;
+        }
+
+        // A cached dataset that no longer validates is ignored in favour of
+        // the bundled strings
+        let dataset_path = self.dataset_path();
+        if dataset_path.exists() {
+            let content = fs::read_to_string(&dataset_path)?;
+            match UserAgentDataset::parse(&content) {
+                Ok(dataset) => *self.dataset.lock_or_recover() = Some(dataset),
+                Err(e) => tracing::warn!("Ignoring cached user agent dataset: {}", e),
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+fn navigator_platform(user_agent: &str) -> &'static str {
+    if user_agent.contains("iPhone") {
+        "iPhone"
+    } else if user_agent.contains("iPad") {
+        "iPad"
+    } else if user_agent.contains("Win") {
+        "Win32"
+    } else if user_agent.contains("Mac") {
+        "MacIntel"
+    } else if user_agent.contains("Linux") {
+        "Linux x86_64"
+    } else {
+        "unknown"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_user_agent_switching() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Test default user agent
+        let default_ua = switcher.get_user_agent("https://example.com");
+        assert!(!default_ua.is_empty());
+        assert!(default_ua.contains("WebX"));
+        
+        // Test global user agent
+        switcher.set_global_user_agent(Some("CustomAgent/1.0".to_string())).unwrap();
+        let global_ua = switcher.get_user_agent("https://example.com");
+        assert_eq!(global_ua, "CustomAgent/1.0");
+    }
+
+    #[test]
+    fn test_site_specific_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Set site-specific user agent
+        switcher
+            .set_site_user_agent("example.com".to_string(), "SiteSpecificAgent/1.0".to_string())
+            .unwrap();
+        
+        // Test site-specific agent
+        let site_ua = switcher.get_user_agent("https://example.com/page");
+        assert_eq!(site_ua, "SiteSpecificAgent/1.0");
+        
+        // Test that other sites still use global/default
+        switcher.set_global_user_agent(Some("GlobalAgent/1.0".to_string())).unwrap();
+        let other_ua = switcher.get_user_agent("https://google.com");
+        assert_eq!(other_ua, "GlobalAgent/1.0");
+    }
+
+    #[test]
+    fn test_predefined_profiles() {
+        let profiles = UserAgentSwitcher::get_predefined_profiles();
+        
+        // Test that all profiles are present
+        assert!(profiles.contains_key(&UserAgentProfile::ChromeWindows));
+        assert!(profiles.contains_key(&UserAgentProfile::FirefoxMac));
+        assert!(profiles.contains_key(&UserAgentProfile::SafariIOS));
+        
+        // Test that profiles have reasonable content
+        let chrome_ua = profiles.get(&UserAgentProfile::ChromeWindows).unwrap();
+        assert!(chrome_ua.contains("Chrome"));
+        assert!(chrome_ua.contains("Windows"));
+        
+        let safari_ua = profiles.get(&UserAgentProfile::SafariIOS).unwrap();
+        assert!(safari_ua.contains("Safari"));
+        assert!(safari_ua.contains("iPhone"));
+    }
+
+    #[test]
+    fn test_randomization() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Enable randomization
+        switcher
+            .set_randomization(true, RandomizationFrequency::PerSession)
+            .unwrap();
+        
+        // Get user agents for same domain - should be consistent
+        let ua1 = switcher.get_user_agent("https://example.com/page1");
+        let ua2 = switcher.get_user_agent("https://example.com/page2");
+        assert_eq!(ua1, ua2);
+        
+        // Different domain should get different agent
+        let ua3 = switcher.get_user_agent("https://google.com");
+        assert_ne!(ua1, ua3);
+    }
+
+    #[test]
+    fn test_configuration_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Set some configuration
+        switcher.set_global_user_agent(Some("PersistentAgent/1.0".to_string())).unwrap();
+        switcher
+            .set_site_user_agent("test.com".to_string(), "TestAgent/1.0".to_string())
+            .unwrap();
+        
+        // Create new instance to test loading
+        let switcher2 = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Test that configuration was loaded
+        let loaded_ua = switcher2.get_user_agent("https://nonexistent.com");
+        assert_eq!(loaded_ua, "PersistentAgent/1.0");
+        
+        let site_agents = switcher2.get_site_agents();
+        assert!(!site_agents.is_empty());
+        assert_eq!(site_agents[0].domain_pattern, "test.com");
+    }
+
+    #[test]
+    fn test_client_hints_follow_selected_agent() {
+        let temp_dir = TempDir::new().unwrap();
+        let switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        let profiles = UserAgentSwitcher::get_predefined_profiles();
+
+        switcher
+            .set_site_user_agent(
+                "chrome.test".to_string(),
+                profiles[&UserAgentProfile::MobileAndroid].clone(),
+            )
+            .unwrap();
+        switcher
+            .set_site_user_agent(
+                "firefox.test".to_string(),
+                profiles[&UserAgentProfile::FirefoxLinux].clone(),
+            )
+            .unwrap();
+
+        let headers = switcher.get_request_headers("https://chrome.test/");
+        assert!(headers.contains(&("Sec-CH-UA-Mobile".to_string(), "?1".to_string())));
+        assert!(headers.contains(&("Sec-CH-UA-Platform".to_string(), "\"Android\"".to_string())));
+        let script = switcher.get_user_agent_script_for("https://chrome.test/");
+        assert!(script.contains("\"platform\":\"Android\""));
+        assert!(script.contains("Linux armv81"));
+
+        let headers = switcher.get_request_headers("https://firefox.test/");
+        assert_eq!(headers.len(), 1);
+        assert!(switcher.get_client_hints("https://firefox.test/").is_none());
+        let script = switcher.get_user_agent_script_for("https://firefox.test/");
+        assert!(script.contains("const uaData = null;"));
+    }
+
+    #[test]
+    fn test_dataset_overrides_bundled_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let switcher = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        let bundled = UserAgentSwitcher::get_predefined_profiles();
+        assert_eq!(switcher.get_profiles(), bundled);
+
+        let chrome = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36";
+        let dataset = UserAgentDataset::parse(&format!(
+            r#"{{"profiles": [{{"profile": "ChromeLinux", "user_agent": "{}"}}]}}"#,
+            chrome
+        ))
+        .unwrap();
+        switcher.apply_dataset(dataset).unwrap();
+
+        let profiles = switcher.get_profiles();
+        assert_eq!(profiles[&UserAgentProfile::ChromeLinux], chrome);
+        assert_eq!(profiles[&UserAgentProfile::FirefoxMac], bundled[&UserAgentProfile::FirefoxMac]);
+
+        // The cached dataset survives a restart
+        let switcher2 = UserAgentSwitcher::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(
+            switcher2.get_profile_user_agent(&UserAgentProfile::ChromeLinux).unwrap(),
+            chrome
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,385 @@
+// Per-Tab Audio Volume and Output Routing
+use crate::error::WebxError;
+use super::events::TabEvent;
+use crate::utils::{LockExt, extract_domain};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// An audio output device (sink) known to the platform sound server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AudioOutputDevice {
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// Audio settings remembered for a site
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SiteAudioPreference {
+    pub volume: f32,
+    pub output_device: Option<String>,
+}
+
+impl Default for SiteAudioPreference {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            output_device: None,
+        }
+    }
+}
+
+/// Current audio state of a tab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabAudioState {
+    pub tab_id: usize,
+    pub site: String,
+    pub volume: f32,
+    pub output_device: Option<String>,
+    pub stream_id: Option<u32>,
+}
+
+/// Manages per-tab volume and output device routing
+pub struct TabAudioManager {
+    tabs: Arc<Mutex<HashMap<usize, TabAudioState>>>,
+    site_preferences: Arc<Mutex<HashMap<String, SiteAudioPreference>>>,
+    config_path: PathBuf,
+    tx: mpsc::UnboundedSender<TabEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<TabEvent>>>>,
+}
+
+impl TabAudioManager {
+    /// Create new tab audio manager
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let manager = Self {
+            tabs: Arc::new(Mutex::new(HashMap::new())),
+            site_preferences: Arc::new(Mutex::new(HashMap::new())),
+            config_path: config_dir.join("tab_audio.json"),
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        };
+
+        manager.load_preferences()?;
+
+        Ok(manager)
+    }
+
+    /// Start tracking a tab, applying the remembered settings for its site
+    pub fn attach_tab(&self, tab_id: usize, url: &str) -> TabAudioState {
+        let site = extract_domain(url);
+        let preference = self
+            .site_preferences
+            .lock_or_recover()
+            .get(&site)
+            .cloned()
+            .unwrap_or_default();
+
+        let state = TabAudioState {
+            tab_id,
+            site,
+            volume: preference.volume,
+            output_device: preference.output_device,
+            stream_id: None,
+        };
+
+        self.tabs.lock_or_recover().insert(tab_id, state.clone());
+        state
+    }
+
+    /// Stop tracking a tab
+    pub fn detach_tab(&self, tab_id: usize) -> bool {
+        self.tabs.lock_or_recover().remove(&tab_id).is_some()
+    }
+
+    /// Associate a sound server stream with a tab and apply its settings to it
+    pub fn bind_stream(&self, tab_id: usize, stream_id: u32) -> Result<(), WebxError> {
+        let state = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let state = tabs.get_mut(&tab_id).ok_or_else(|| WebxError::NotFound(format!("Tab {}", tab_id)))?;
+            state.stream_id = Some(stream_id);
+            state.clone()
+        };
+
+        if Self::supports_routing() {
+            Self::apply_stream_volume(stream_id, state.volume)?;
+            if let Some(device) = &state.output_device {
+                Self::apply_stream_device(stream_id, device)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set a tab's volume (0.0 to 1.0) and remember it for the tab's site
+    pub fn set_volume(&self, tab_id: usize, volume: f32) -> Result<(), WebxError> {
+        let volume = volume.clamp(0.0, 1.0);
+        let state = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let state = tabs.get_mut(&tab_id).ok_or_else(|| WebxError::NotFound(format!("Tab {}", tab_id)))?;
+            state.volume = volume;
+            state.clone()
+        };
+
+        if let (Some(stream_id), true) = (state.stream_id, Self::supports_routing()) {
+            Self::apply_stream_volume(stream_id, volume)?;
+        }
+
+        self.site_preferences
+            .lock_or_recover()
+            .entry(state.site)
+            .or_default()
+            .volume = volume;
+        self.save_preferences()?;
+
+        let _ = self.tx.send(TabEvent::VolumeChanged { tab_id, volume });
+        Ok(())
+    }
+
+    /// Get a tab's volume
+    pub fn get_volume(&self, tab_id: usize) -> Option<f32> {
+        self.tabs.lock_or_recover().get(&tab_id).map(|state| state.volume)
+    }
+
+    /// Route a tab to an output device (None for the system default)
+    pub fn set_output_device(
+        &self,
+        tab_id: usize,
+        device_id: Option<String>,
+    ) -> Result<(), WebxError> {
+        if let Some(device) = &device_id {
+            if !self.list_output_devices().iter().any(|d| &d.id == device) {
+                return Err(WebxError::NotFound(format!("Output device {}", device)));
+            }
+        }
+
+        let state = {
+            let mut tabs = self.tabs.lock_or_recover();
+            let state = tabs.get_mut(&tab_id).ok_or_else(|| WebxError::NotFound(format!("Tab {}", tab_id)))?;
+            state.output_device = device_id.clone();
+            state.clone()
+        };
+
+        if let Some(stream_id) = state.stream_id {
+            let target = match &device_id {
+                Some(device) => Some(device.clone()),
+                None => self.default_output_device().map(|d| d.id),
+            };
+            if let Some(target) = target {
+                Self::apply_stream_device(stream_id, &target)?;
+            }
+        }
+
+        self.site_preferences
+            .lock_or_recover()
+            .entry(state.site)
+            .or_default()
+            .output_device = device_id.clone();
+        self.save_preferences()?;
+
+        let _ = self.tx.send(TabEvent::OutputDeviceChanged { tab_id, device_id });
+        Ok(())
+    }
+
+    /// Get the output device a tab is routed to
+    pub fn get_output_device(&self, tab_id: usize) -> Option<String> {
+        self.tabs
+            .lock_or_recover()
+            .get(&tab_id)
+            .and_then(|state| state.output_device.clone())
+    }
+
+    /// Get a tab's audio state
+    pub fn get_tab_state(&self, tab_id: usize) -> Option<TabAudioState> {
+        self.tabs.lock_or_recover().get(&tab_id).cloned()
+    }
+
+    /// Get remembered settings for a site
+    pub fn get_site_preference(&self, site: &str) -> Option<SiteAudioPreference> {
+        self.site_preferences.lock_or_recover().get(site).cloned()
+    }
+
+    /// Forget remembered settings for a site
+    pub fn clear_site_preference(&self, site: &str) -> Result<bool, WebxError> {
+        let removed = self.site_preferences.lock_or_recover().remove(site).is_some();
+        if removed {
+            self.save_preferences()?;
+        }
+        Ok(removed)
+    }
+
+    /// List output devices reported by the sound server
+    pub fn list_output_devices(&self) -> Vec<AudioOutputDevice> {
+        if !Self::supports_routing() {
+            return Vec::new();
+        }
+
+        let default_sink = Self::run_pactl(&["get-default-sink"])
+            .map(|output| output.trim().to_string())
+            .unwrap_or_default();
+
+        Self::run_pactl(&["list", "short", "sinks"])
+            .map(|output| Self::parse_sinks(&output, &default_sink))
+            .unwrap_or_default()
+    }
+
+    /// Get the system default output device
+    pub fn default_output_device(&self) -> Option<AudioOutputDevice> {
+        self.list_output_devices().into_iter().find(|d| d.is_default)
+    }
+
+    /// Check whether the platform supports per-stream routing
+    pub fn supports_routing() -> bool {
+        cfg!(target_os = "linux") && Self::run_pactl(&["info"]).is_some()
+    }
+
+    /// JavaScript that scales media element volume in the page, used when
+    /// the sound server cannot control the tab's stream directly
+    pub fn get_volume_script(volume: f32) -> String {
+        format!(
+            r#"
+(function() {{
+    const volume = {};
+    const apply = (el) => {{ el.volume = volume; }};
+    document.querySelectorAll('audio, video').forEach(apply);
+    if (!window.__webxVolumeObserver) {{
+        window.__webxVolumeObserver = new MutationObserver(() => {{
+            document.querySelectorAll('audio, video').forEach((el) => {{ el.volume = window.__webxVolume; }});
+        }});
+        window.__webxVolumeObserver.observe(document.documentElement, {{ childList: true, subtree: true }});
+    }}
+    window.__webxVolume = volume;
+}})();
+"#,
+            volume.clamp(0.0, 1.0)
+        )
+    }
+
+    /// Subscribe to volume and routing events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<TabEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn parse_sinks(output: &str, default_sink: &str) -> Vec<AudioOutputDevice> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let _index = fields.next()?;
+                let name = fields.next()?.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                Some(AudioOutputDevice {
+                    id: name.to_string(),
+                    name: name.to_string(),
+                    is_default: name == default_sink,
+                })
+            })
+            .collect()
+    }
+
+    fn apply_stream_volume(stream_id: u32, volume: f32) -> Result<(), WebxError> {
+        let percent = format!("{}%", (volume * 100.0).round() as u32);
+        Self::run_pactl(&["set-sink-input-volume", &stream_id.to_string(), &percent])
+            .ok_or("Failed to set stream volume")?;
+        Ok(())
+    }
+
+    fn apply_stream_device(stream_id: u32, device_id: &str) -> Result<(), WebxError> {
+        Self::run_pactl(&["move-sink-input", &stream_id.to_string(), device_id])
+            .ok_or("Failed to move stream to output device")?;
+        Ok(())
+    }
+
+    fn run_pactl(args: &[&str]) -> Option<String> {
+        let output = Command::new("pactl").args(args).output().ok()?;
+        if output.status.success() {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            None
+        }
+    }
+
+    fn save_preferences(&self) -> Result<(), WebxError> {
+        let preferences = self.site_preferences.lock_or_recover();
+        let content = serde_json::to_string_pretty(&*preferences)?;
+        std::fs::write(&self.config_path, content)?;
+        Ok(())
+    }
+
+    fn load_preferences(&self) -> Result<(), WebxError> {
+        if self.config_path.exists() {
+            let content = std::fs::read_to_string(&self.config_path)?;
+            *self.site_preferences.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_volume_persists_per_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TabAudioManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        manager.attach_tab(1, "https://music.example.com/playlist");
+        manager.set_volume(1, 0.5).unwrap();
+        assert_eq!(manager.get_volume(1), Some(0.5));
+
+        // Volume is clamped
+        manager.set_volume(1, 3.0).unwrap();
+        assert_eq!(manager.get_volume(1), Some(1.0));
+        manager.set_volume(1, 0.5).unwrap();
+
+        // A new manager restores the site preference for new tabs
+        let manager = TabAudioManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let state = manager.attach_tab(7, "https://music.example.com/other");
+        assert_eq!(state.volume, 0.5);
+    }
+
+    #[test]
+    fn test_volume_events_and_sink_parsing() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = TabAudioManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let mut events = manager.subscribe_events();
+
+        manager.attach_tab(2, "https://calls.example.org");
+        manager.set_volume(2, 0.25).unwrap();
+        match events.try_recv().unwrap() {
+            TabEvent::VolumeChanged { tab_id, volume } => {
+                assert_eq!(tab_id, 2);
+                assert_eq!(volume, 0.25);
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+
+        let sinks = TabAudioManager::parse_sinks(
+            "0\talsa_output.speakers\tmodule-alsa-card.c\ts16le 2ch 44100Hz\tRUNNING\n\
+             1\tbluez_output.headphones\tmodule-bluez5-device.c\ts16le 2ch 48000Hz\tIDLE\n",
+            "alsa_output.speakers",
+        );
+        assert_eq!(sinks.len(), 2);
+        assert!(sinks[0].is_default);
+        assert_eq!(sinks[1].id, "bluez_output.headphones");
+        assert!(!sinks[1].is_default);
+    }
+}
@@ -0,0 +1,422 @@
+// Tab Containers
+use crate::config::storage::{save_json, DEFAULT_BACKUP_COUNT};
+use crate::error::WebxError;
+use crate::features::system::proxy::{ProxyManager, ProxyProfile, ProxyRequestError, ProxyRoute};
+use crate::features::system::user_agent::UserAgentSwitcher;
+use crate::utils::{LockExt, host_in_domain};
+use reqwest::Client;
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Partition key used by tabs outside any container
+pub const DEFAULT_PARTITION: &str = "default";
+
+/// Label colors a container can use
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ContainerColor {
+    Blue,
+    Turquoise,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Pink,
+    Purple,
+    Gray,
+}
+
+impl ContainerColor {
+    /// CSS color used for the tab strip label
+    pub fn hex(&self) -> &'static str {
+        match self {
+            ContainerColor::Blue => "#37adff",
+            ContainerColor::Turquoise => "#00c79a",
+            ContainerColor::Green => "#51cd00",
+            ContainerColor::Yellow => "#ffcb00",
+            ContainerColor::Orange => "#ff9f00",
+            ContainerColor::Red => "#ff613d",
+            ContainerColor::Pink => "#ff4bda",
+            ContainerColor::Purple => "#af51f5",
+            ContainerColor::Gray => "#8f8f9d",
+        }
+    }
+}
+
+/// An identity context with its own cookies, storage, user agent and proxy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub color: ContainerColor,
+    /// User agent override, `None` to use the user agent switcher's choice
+    pub user_agent: Option<String>,
+    /// Proxy profile override, `None` to use the proxy manager's routing
+    pub proxy_profile: Option<ProxyProfile>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Container {
+    /// Key partitioning localStorage, IndexedDB and cache for this container
+    pub fn storage_partition(&self) -> String {
+        format!("container-{}", self.id)
+    }
+}
+
+/// Manages containers and the data kept separate for each of them
+pub struct ContainerManager {
+    containers: Arc<Mutex<Vec<Container>>>,
+    cookie_jars: Arc<Mutex<HashMap<String, Arc<CookieStoreMutex>>>>,
+    data_dir: PathBuf,
+}
+
+impl ContainerManager {
+    /// Create a new container manager
+    pub fn new(data_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let data_dir = data_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("containers");
+            path
+        });
+
+        fs::create_dir_all(&data_dir)?;
+
+        let manager = Self {
+            containers: Arc::new(Mutex::new(Vec::new())),
+            cookie_jars: Arc::new(Mutex::new(HashMap::new())),
+            data_dir,
+        };
+
+        if manager.containers_path().exists() {
+            manager.load_containers()?;
+        } else {
+            manager.initialize_default_containers()?;
+        }
+
+        Ok(manager)
+    }
+
+    /// Create a container
+    pub fn create_container(
+        &self,
+        name: String,
+        color: ContainerColor,
+    ) -> Result<Container, WebxError> {
+        let container = Container {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            color,
+            user_agent: None,
+            proxy_profile: None,
+            created_at: chrono::Utc::now(),
+        };
+
+        self.containers.lock_or_recover().push(container.clone());
+        self.save_containers()?;
+        Ok(container)
+    }
+
+    /// Remove a container and delete everything stored for it. Tabs still
+    /// assigned to it should be moved with `TabManager::clear_container`.
+    pub fn remove_container(&self, container_id: &str) -> Result<bool, WebxError> {
+        let removed = {
+            let mut containers = self.containers.lock_or_recover();
+            let before = containers.len();
+            containers.retain(|container| container.id != container_id);
+            containers.len() != before
+        };
+
+        if removed {
+            self.clear_container_data(container_id)?;
+            self.save_containers()?;
+        }
+        Ok(removed)
+    }
+
+    /// Rename a container or change its color
+    pub fn update_container(
+        &self,
+        container_id: &str,
+        name: String,
+        color: ContainerColor,
+    ) -> Result<bool, WebxError> {
+        self.modify_container(container_id, |container| {
+            container.name = name;
+            container.color = color;
+        })
+    }
+
+    /// Set the user agent tabs in a container present
+    pub fn set_container_user_agent(
+        &self,
+        container_id: &str,
+        user_agent: Option<String>,
+    ) -> Result<bool, WebxError> {
+        self.modify_container(container_id, |container| container.user_agent = user_agent)
+    }
+
+    /// Set the proxy profile tabs in a container connect through
+    pub fn set_container_proxy(
+        &self,
+        container_id: &str,
+        proxy_profile: Option<ProxyProfile>,
+    ) -> Result<bool, WebxError> {
+        self.modify_container(container_id, |container| container.proxy_profile = proxy_profile)
+    }
+
+    /// Get a container
+    pub fn get_container(&self, container_id: &str) -> Option<Container> {
+        self.containers
+            .lock_or_recover()
+            .iter()
+            .find(|container| container.id == container_id)
+            .cloned()
+    }
+
+    /// Get all containers in display order
+    pub fn get_containers(&self) -> Vec<Container> {
+        self.containers.lock_or_recover().clone()
+    }
+
+    /// Storage partition key for a tab's container
+    pub fn storage_partition(&self, container_id: Option<&str>) -> String {
+        container_id
+            .and_then(|id| self.get_container(id))
+            .map(|container| container.storage_partition())
+            .unwrap_or_else(|| DEFAULT_PARTITION.to_string())
+    }
+
+    /// Directory holding the web view's cookies and storage for a container,
+    /// so each container's web context is isolated on disk
+    pub fn data_directory(&self, container_id: Option<&str>) -> PathBuf {
+        self.data_dir
+            .join("data")
+            .join(self.storage_partition(container_id))
+    }
+
+    /// Cookie jar for requests the browser makes on behalf of a container
+    pub fn cookie_jar(&self, container_id: Option<&str>) -> Arc<CookieStoreMutex> {
+        let partition = self.storage_partition(container_id);
+        self.cookie_jars
+            .lock_or_recover()
+            .entry(partition)
+            .or_insert_with(|| Arc::new(CookieStoreMutex::default()))
+            .clone()
+    }
+
+    /// User agent for a tab in a container
+    pub fn user_agent_for(&self, container_id: Option<&str>, url: &str, switcher: &UserAgentSwitcher) -> String {
+        container_id
+            .and_then(|id| self.get_container(id))
+            .and_then(|container| container.user_agent)
+            .unwrap_or_else(|| switcher.get_user_agent(url))
+    }
+
+    /// Route requests from a container to a URL take
+    pub fn route_for(&self, container_id: Option<&str>, url: &str, proxy: &ProxyManager) -> ProxyRoute {
+        match container_id
+            .and_then(|id| self.get_container(id))
+            .and_then(|container| container.proxy_profile)
+        {
+            Some(profile) => proxy.route_for_profile(&profile, url),
+            None => proxy.route_for_url(url),
+        }
+    }
+
+    /// HTTP client carrying a container's cookies over its proxy route
+    pub fn client_for(
+        &self,
+        container_id: Option<&str>,
+        url: &str,
+        proxy: &ProxyManager,
+    ) -> Result<(Client, ProxyRoute), ProxyRequestError> {
+        let route = self.route_for(container_id, url, proxy);
+        let timeout = Duration::from_secs(proxy.get_settings().timeout_seconds as u64);
+        let client = route
+            .client_builder(timeout)
+            .and_then(|builder| {
+                Ok(builder
+                    .cookie_provider(self.cookie_jar(container_id))
+                    .build()?)
+            })
+            .map_err(|e| ProxyRequestError::invalid_config(url, &route, e.to_string()))?;
+        Ok((client, route))
+    }
+
+    /// Delete the cookies and storage of a container
+    pub fn clear_container_data(&self, container_id: &str) -> Result<(), WebxError> {
+        let partition = format!("container-{}", container_id);
+        // Clients built earlier share the jar, so empty it as well
+        if let Some(jar) = self.cookie_jars.lock_or_recover().remove(&partition) {
+            jar.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+
+        let dir = self.data_dir.join("data").join(&partition);
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the cookies of a domain and its subdomains from every
+    /// container's jar, returning how many were deleted. Web view cookies
+    /// live in each container's data directory and are cleared by the web view.
+    pub fn clear_site_cookies(&self, domain: &str) -> usize {
+        self.remove_cookies(|host| host_in_domain(host, domain), false)
+    }
+
+    /// Delete every cookie outside the allowlisted domains (and their
+    /// subdomains), returning how many were deleted
+    pub fn clear_cookies_except(&self, allowlist: &[String]) -> usize {
+        self.remove_cookies(|host| !in_allowlist(host, allowlist), false)
+    }
+
+    /// Count the cookies `clear_cookies_except` would delete
+    pub fn count_cookies_except(&self, allowlist: &[String]) -> usize {
+        self.remove_cookies(|host| !in_allowlist(host, allowlist), true)
+    }
+
+    // Private helper methods
+
+    fn remove_cookies(&self, matches: impl Fn(&str) -> bool, dry_run: bool) -> usize {
+        let jars: Vec<Arc<CookieStoreMutex>> = self.cookie_jars.lock_or_recover().values().cloned().collect();
+        let mut removed = 0;
+
+        for jar in jars {
+            let mut store = jar.lock().unwrap_or_else(PoisonError::into_inner);
+            let doomed: Vec<(String, String, String)> = store
+                .iter_any()
+                .filter_map(|cookie| {
+                    let domain = cookie.domain.as_cow()?.into_owned();
+                    matches(&domain).then(|| (domain, String::from(&cookie.path), cookie.name().to_string()))
+                })
+                .collect();
+
+            removed += doomed.len();
+            if !dry_run {
+                for (domain, path, name) in doomed {
+                    store.remove(&domain, &path, &name);
+                }
+            }
+        }
+        removed
+    }
+
+    fn modify_container(
+        &self,
+        container_id: &str,
+        update: impl FnOnce(&mut Container),
+    ) -> Result<bool, WebxError> {
+        let found = match self
+            .containers
+            .lock_or_recover()
+            .iter_mut()
+            .find(|container| container.id == container_id)
+        {
+            Some(container) => {
+                update(container);
+                true
+            }
+            None => false,
+        };
+
+        if found {
+            self.save_containers()?;
+        }
+        Ok(found)
+    }
+
+    fn initialize_default_containers(&self) -> Result<(), WebxError> {
+        for (name, color) in [
+            ("Personal", ContainerColor::Blue),
+            ("Work", ContainerColor::Orange),
+            ("Banking", ContainerColor::Green),
+            ("Shopping", ContainerColor::Pink),
+        ] {
+            self.create_container(name.to_string(), color)?;
+        }
+        Ok(())
+    }
+
+    fn containers_path(&self) -> PathBuf {
+        self.data_dir.join("containers.json")
+    }
+
+    fn save_containers(&self) -> Result<(), WebxError> {
+        save_json(&self.containers_path(), &*self.containers.lock_or_recover(), DEFAULT_BACKUP_COUNT)?;
+        Ok(())
+    }
+
+    fn load_containers(&self) -> Result<(), WebxError> {
+        let content = fs::read_to_string(self.containers_path())?;
+        *self.containers.lock_or_recover() = serde_json::from_str(&content)?;
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+fn in_allowlist(host: &str, allowlist: &[String]) -> bool {
+    allowlist
+        .iter()
+        .filter_map(|site| crate::utils::site_domain(site))
+        .any(|domain| host_in_domain(host, &domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_containers_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ContainerManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(manager.get_containers().len(), 4);
+
+        let work = manager.get_containers()[1].clone();
+        assert_eq!(work.name, "Work");
+        manager
+            .set_container_user_agent(&work.id, Some("WorkAgent/1.0".to_string()))
+            .unwrap();
+        manager
+            .set_container_proxy(&work.id, Some(ProxyProfile::Tor))
+            .unwrap();
+
+        let reloaded = ContainerManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let work = reloaded.get_container(&work.id).unwrap();
+        assert_eq!(work.user_agent.as_deref(), Some("WorkAgent/1.0"));
+        assert_eq!(work.proxy_profile, Some(ProxyProfile::Tor));
+
+        assert!(reloaded.remove_container(&work.id).unwrap());
+        assert_eq!(reloaded.storage_partition(Some(&work.id)), DEFAULT_PARTITION);
+    }
+
+    #[test]
+    fn test_cookies_isolated_per_container() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ContainerManager::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let containers = manager.get_containers();
+        let (personal, work) = (&containers[0].id, &containers[1].id);
+
+        let url = url::Url::parse("https://example.com/").unwrap();
+        let header = reqwest::header::HeaderValue::from_static("session=personal");
+        manager
+            .cookie_jar(Some(personal))
+            .set_cookies(&mut std::iter::once(&header), &url);
+
+        assert!(manager.cookie_jar(Some(personal)).cookies(&url).is_some());
+        assert!(manager.cookie_jar(Some(work)).cookies(&url).is_none());
+        assert!(manager.cookie_jar(None).cookies(&url).is_none());
+        assert_ne!(manager.data_directory(Some(personal)), manager.data_directory(Some(work)));
+
+        manager.clear_container_data(personal).unwrap();
+        assert!(manager.cookie_jar(Some(personal)).cookies(&url).is_none());
+    }
+}
@@ -14,6 +14,15 @@ pub enum TabEvent {
     DuplicateRequested { source_tab_id: usize },
     PinChanged { tab_id: usize, pinned: bool },
     MuteChanged { tab_id: usize, muted: bool },
+    VolumeChanged { tab_id: usize, volume: f32 },
+    OutputDeviceChanged { tab_id: usize, device_id: Option<String> },
+    ContainerChanged { tab_id: usize, container_id: Option<String> },
+    /// A tab moved within its window's strip
+    Moved { tab_id: usize, window_id: usize, from_index: usize, to_index: usize },
+    /// A tab left its window to be adopted by another
+    Detached { tab_id: usize, window_id: usize },
+    /// A window adopted a tab at a position in its strip
+    Attached { tab_id: usize, window_id: usize, index: usize },
 }
 
 impl TabEvent {
@@ -1,28 +1,64 @@
 // Tab Manager Core Logic
-use crate::core::{Tab, BrowserState};
+use super::containers::ContainerManager;
+use super::events::TabEvent;
+use super::policies::{find_stale_tabs, group_by_domain, StaleTab};
+use super::switcher::{search_tabs, TabMatch};
+use super::transfer::TabTransfer;
+use crate::core::{BrowserState, NavigationEntry, NavigationHistory, SplitOrientation, SplitView, Tab};
+use crate::utils::{LockExt, StateWatchdog};
+use chrono::Utc;
 use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
 
 /// Tab manager for handling multiple tabs
 pub struct TabManager {
     state: Arc<Mutex<BrowserState>>,
+    tx: mpsc::UnboundedSender<TabEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<TabEvent>>>,
 }
 
 impl TabManager {
     /// Create a new tab manager
     pub fn new(state: Arc<Mutex<BrowserState>>) -> Self {
-        Self { state }
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            state,
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Subscribe to tabs moving within and between windows
+    pub fn subscribe_events(&self) -> Option<mpsc::UnboundedReceiver<TabEvent>> {
+        self.rx.lock_or_recover().take()
+    }
+
+    /// Let the watchdog repair the browser state after a panic
+    pub fn watch_state(&self, watchdog: &StateWatchdog) {
+        watchdog.watch_with_reset("browser state", &self.state, BrowserState::repair);
     }
 
     /// Create a new tab
     pub fn create_tab(&self, url: Option<String>) -> usize {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock_or_recover();
         let tab_url = url.unwrap_or_else(|| state.settings.home_page.clone());
         state.add_tab(tab_url)
     }
 
+    /// Create a new tab inside a container
+    pub fn create_tab_in_container(&self, url: Option<String>, container_id: Option<String>) -> usize {
+        let mut state = self.state.lock_or_recover();
+        let tab_url = url.unwrap_or_else(|| state.settings.home_page.clone());
+        let tab_id = state.add_tab(tab_url);
+        if let Some(tab) = state.tabs.get_mut(&tab_id) {
+            tab.container_id = container_id;
+        }
+        tab_id
+    }
+
     /// Close a tab
     pub fn close_tab(&self, tab_id: usize) -> bool {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock_or_recover();
         
         if state.tabs.contains_key(&tab_id) {
             state.remove_tab(tab_id);
@@ -34,51 +70,706 @@ impl TabManager {
 
     /// Switch to a specific tab
     pub fn switch_to_tab(&self, tab_id: usize) -> bool {
-        let mut state = self.state.lock().unwrap();
-        
-        if state.tabs.contains_key(&tab_id) {
-            state.active_tab_id = Some(tab_id);
-            true
-        } else {
-            false
+        let mut state = self.state.lock_or_recover();
+        state.activate_tab(tab_id)
+    }
+
+    /// Open a new window with one tab, returning the window and tab IDs
+    pub fn create_window(&self, url: Option<String>) -> (usize, usize) {
+        let mut state = self.state.lock_or_recover();
+        let tab_url = url.unwrap_or_else(|| state.settings.home_page.clone());
+        let window_id = state.open_window();
+        let tab_id = state.add_tab_to_window(window_id, tab_url).unwrap_or_default();
+        (window_id, tab_id)
+    }
+
+    /// Close a window and all of its tabs
+    pub fn close_window(&self, window_id: usize) -> Vec<usize> {
+        self.state.lock_or_recover().close_window(window_id)
+    }
+
+    /// Close a window's tabs, leaving it a single new tab on the home page,
+    /// and return the closed tabs
+    pub fn reset_window(&self, window_id: usize) -> Vec<usize> {
+        let mut state = self.state.lock_or_recover();
+        let home_page = state.settings.home_page.clone();
+        let closed = state.windows.get(&window_id).map(|window| window.tab_ids.clone()).unwrap_or_default();
+        if state.add_tab_to_window(window_id, home_page).is_none() {
+            return Vec::new();
+        }
+        for &tab_id in &closed {
+            state.remove_tab(tab_id);
+        }
+        closed
+    }
+
+    /// Move a tab into a new window of its own
+    pub fn move_tab_to_new_window(&self, tab_id: usize) -> Option<usize> {
+        self.state.lock_or_recover().move_tab_to_new_window(tab_id)
+    }
+
+    /// Move a tab to another window
+    pub fn move_tab_to_window(&self, tab_id: usize, window_id: usize) -> bool {
+        self.state.lock_or_recover().move_tab_to_window(tab_id, window_id)
+    }
+
+    /// Move a tab to a position in its window's strip, e.g. when it is
+    /// dropped there
+    pub fn move_tab(&self, tab_id: usize, index: usize) -> bool {
+        let moved = {
+            let mut state = self.state.lock_or_recover();
+            let window_id = state.window_of_tab(tab_id);
+            let from_index = state.reorder_tab(tab_id, index);
+            let to_index = window_id
+                .and_then(|id| state.windows.get(&id))
+                .and_then(|window| window.tab_ids.iter().position(|&id| id == tab_id));
+            window_id.zip(from_index).zip(to_index)
+        };
+        match moved {
+            Some(((window_id, from_index), to_index)) => {
+                if from_index != to_index {
+                    let _ = self.tx.send(TabEvent::Moved { tab_id, window_id, from_index, to_index });
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take a tab out of its window, with its page and state, for another
+    /// window to adopt
+    pub fn detach_tab(&self, tab_id: usize) -> Option<TabTransfer> {
+        let transfer = {
+            let mut state = self.state.lock_or_recover();
+            let source_window_id = state.window_of_tab(tab_id);
+            let source_index = source_window_id
+                .and_then(|id| state.windows.get(&id))
+                .and_then(|window| window.tab_ids.iter().position(|&id| id == tab_id));
+            let tab = state.detach_tab(tab_id)?;
+            TabTransfer { tab, source_window_id, source_index }
+        };
+        if let Some(window_id) = transfer.source_window_id {
+            let _ = self.tx.send(TabEvent::Detached { tab_id, window_id });
+        }
+        Some(transfer)
+    }
+
+    /// Adopt a detached tab into a window at a position in its strip, or at
+    /// the end, making it the window's active tab. A window that is gone is
+    /// replaced by the focused one. Returns the tab's ID, which changes only
+    /// if another tab took it meanwhile.
+    pub fn adopt_tab(&self, window_id: usize, transfer: TabTransfer, index: Option<usize>) -> Option<usize> {
+        let (tab_id, window_id, index) = {
+            let mut state = self.state.lock_or_recover();
+            let tab_id = state.attach_tab(window_id, transfer.tab, index)?;
+            let window_id = state.window_of_tab(tab_id)?;
+            let index = state.windows[&window_id].tab_ids.iter().position(|&id| id == tab_id)?;
+            (tab_id, window_id, index)
+        };
+        let _ = self.tx.send(TabEvent::Attached { tab_id, window_id, index });
+        Some(tab_id)
+    }
+
+    /// Move a tab into another window at a position in its strip, or within
+    /// its own window, e.g. when it is dropped there
+    pub fn transfer_tab(&self, tab_id: usize, window_id: usize, index: Option<usize>) -> Option<usize> {
+        let source = {
+            let state = self.state.lock_or_recover();
+            if !state.windows.contains_key(&window_id) {
+                return None;
+            }
+            state.window_of_tab(tab_id)
+        };
+        if source == Some(window_id) {
+            let index = index.unwrap_or(usize::MAX);
+            return self.move_tab(tab_id, index).then_some(tab_id);
         }
+        let transfer = self.detach_tab(tab_id)?;
+        self.adopt_tab(window_id, transfer, index)
+    }
+
+    /// Split a window, showing its most recently used other tab beside the
+    /// active one, or a new tab with the home page if it has no other tab.
+    /// Returns the tab shown beside the active one.
+    pub fn enter_split_view(&self, window_id: usize) -> Option<usize> {
+        let mut state = self.state.lock_or_recover();
+        let active = state.window_active_tab(window_id)?;
+        let other = state
+            .window_tabs(window_id)
+            .into_iter()
+            .filter(|tab| tab.id != active)
+            .max_by_key(|tab| tab.last_accessed)
+            .map(|tab| tab.id);
+        let other = match other {
+            Some(other) => other,
+            None => {
+                let home_page = state.settings.home_page.clone();
+                let new_tab = state.add_tab_to_window(window_id, home_page)?;
+                // Adding a tab activates it; keep the current one in front
+                state.activate_tab(active);
+                new_tab
+            }
+        };
+        state.split_window(window_id, other).then_some(other)
+    }
+
+    /// Show a tab beside the active tab of its window
+    pub fn split_with_tab(&self, tab_id: usize) -> bool {
+        let mut state = self.state.lock_or_recover();
+        match state.window_of_tab(tab_id) {
+            Some(window_id) => state.split_window(window_id, tab_id),
+            None => false,
+        }
+    }
+
+    /// Show only a window's active tab, returning the tab that was beside it
+    pub fn exit_split_view(&self, window_id: usize) -> Option<usize> {
+        self.state.lock_or_recover().unsplit_window(window_id)
+    }
+
+    /// A window's split layout, if it is split
+    pub fn get_split_view(&self, window_id: usize) -> Option<SplitView> {
+        let state = self.state.lock_or_recover();
+        state.windows.get(&window_id).and_then(|window| window.split)
+    }
+
+    /// Turn a split window's panes between side by side and stacked
+    pub fn rotate_split_view(&self, window_id: usize) -> Option<SplitView> {
+        let mut split = self.get_split_view(window_id)?;
+        split.orientation = match split.orientation {
+            SplitOrientation::SideBySide => SplitOrientation::Stacked,
+            SplitOrientation::Stacked => SplitOrientation::SideBySide,
+        };
+        self.state.lock_or_recover().set_window_split(window_id, split);
+        self.get_split_view(window_id)
+    }
+
+    /// Grow, or with a negative change shrink, the active tab's pane of a
+    /// split window
+    pub fn resize_split_view(&self, window_id: usize, change: f64) -> Option<SplitView> {
+        let mut split = self.get_split_view(window_id)?;
+        split.ratio += change;
+        self.state.lock_or_recover().set_window_split(window_id, split);
+        self.get_split_view(window_id)
+    }
+
+    /// Get the tabs of a window in strip order
+    pub fn get_window_tabs(&self, window_id: usize) -> Vec<Tab> {
+        let state = self.state.lock_or_recover();
+        state.window_tabs(window_id).into_iter().cloned().collect()
     }
 
     /// Get all tabs
     pub fn get_tabs(&self) -> Vec<Tab> {
-        let state = self.state.lock().unwrap();
+        let state = self.state.lock_or_recover();
         state.tabs.values().cloned().collect()
     }
 
+    /// Get all tabs, most recently used first
+    pub fn get_tabs_by_recent_use(&self) -> Vec<Tab> {
+        let state = self.state.lock_or_recover();
+        let mut tabs: Vec<Tab> = state.tabs.values().cloned().collect();
+        // The active tab may share its access time with the one before it
+        tabs.sort_by_key(|tab| (std::cmp::Reverse(tab.last_accessed), Some(tab.id) != state.active_tab_id));
+        tabs
+    }
+
+    /// Fuzzy search tab titles and URLs, best matches first and most
+    /// recently used first among equals
+    pub fn search(&self, query: &str) -> Vec<TabMatch> {
+        search_tabs(&self.get_tabs_by_recent_use(), query)
+    }
+
     /// Get active tab
     pub fn get_active_tab(&self) -> Option<Tab> {
-        let state = self.state.lock().unwrap();
+        let state = self.state.lock_or_recover();
         state.active_tab().cloned()
     }
 
-    /// Duplicate current tab
+    /// Duplicate current tab, keeping it in the same container
     pub fn duplicate_tab(&self) -> Option<usize> {
-        let active_url = {
-            let state = self.state.lock().unwrap();
-            state.active_tab().map(|tab| tab.url.clone())
+        let active = {
+            let state = self.state.lock_or_recover();
+            state
+                .active_tab()
+                .map(|tab| (tab.url.clone(), tab.container_id.clone()))
         };
-        
-        if let Some(url) = active_url {
-            Some(self.create_tab(Some(url)))
-        } else {
-            None
+
+        active.map(|(url, container_id)| self.create_tab_in_container(Some(url), container_id))
+    }
+
+    /// Move a tab into a container, or back to the default context with `None`
+    pub fn set_tab_container(&self, tab_id: usize, container_id: Option<String>) -> bool {
+        let mut state = self.state.lock_or_recover();
+        match state.tabs.get_mut(&tab_id) {
+            Some(tab) => {
+                tab.container_id = container_id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the container a tab belongs to
+    pub fn get_tab_container(&self, tab_id: usize) -> Option<String> {
+        let state = self.state.lock_or_recover();
+        state.tabs.get(&tab_id).and_then(|tab| tab.container_id.clone())
+    }
+
+    /// Get all tabs in a container
+    pub fn get_tabs_in_container(&self, container_id: &str) -> Vec<Tab> {
+        let state = self.state.lock_or_recover();
+        state
+            .tabs
+            .values()
+            .filter(|tab| tab.container_id.as_deref() == Some(container_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Move every tab of a container back to the default context, returning
+    /// how many tabs were affected
+    pub fn clear_container(&self, container_id: &str) -> usize {
+        let mut state = self.state.lock_or_recover();
+        let mut moved = 0;
+        for tab in state.tabs.values_mut() {
+            if tab.container_id.as_deref() == Some(container_id) {
+                tab.container_id = None;
+                moved += 1;
+            }
+        }
+        moved
+    }
+
+    /// Drop assignments to containers that no longer exist, e.g. after
+    /// restoring a session saved before a container was removed
+    pub fn reconcile_containers(&self, containers: &ContainerManager) -> usize {
+        let mut state = self.state.lock_or_recover();
+        let mut moved = 0;
+        for tab in state.tabs.values_mut() {
+            let missing = tab
+                .container_id
+                .as_deref()
+                .is_some_and(|id| containers.get_container(id).is_none());
+            if missing {
+                tab.container_id = None;
+                moved += 1;
+            }
         }
+        moved
     }
 
     /// Get tab count
     pub fn tab_count(&self) -> usize {
-        let state = self.state.lock().unwrap();
+        let state = self.state.lock_or_recover();
         state.tabs.len()
     }
 
+    /// Record whether a tab's page is playing sound
+    pub fn set_tab_audible(&self, tab_id: usize, audible: bool) -> bool {
+        let mut state = self.state.lock_or_recover();
+        match state.tabs.get_mut(&tab_id) {
+            Some(tab) => {
+                tab.is_audible = audible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mute or unmute a tab
+    pub fn set_tab_muted(&self, tab_id: usize, muted: bool) -> bool {
+        let mut state = self.state.lock_or_recover();
+        match state.tabs.get_mut(&tab_id) {
+            Some(tab) => {
+                tab.muted = muted;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Toggle a tab's mute, returning whether it is now muted
+    pub fn toggle_tab_mute(&self, tab_id: usize) -> Option<bool> {
+        let mut state = self.state.lock_or_recover();
+        let tab = state.tabs.get_mut(&tab_id)?;
+        tab.muted = !tab.muted;
+        Some(tab.muted)
+    }
+
+    /// Mute every tab no window is showing, returning the newly muted tabs
+    pub fn mute_background_tabs(&self) -> Vec<usize> {
+        let mut state = self.state.lock_or_recover();
+        let visible: Vec<usize> = state
+            .windows
+            .keys()
+            .filter_map(|&window_id| state.window_active_tab(window_id))
+            .collect();
+
+        let mut muted: Vec<usize> = state
+            .tabs
+            .values_mut()
+            .filter(|tab| !tab.muted && !visible.contains(&tab.id))
+            .map(|tab| {
+                tab.muted = true;
+                tab.id
+            })
+            .collect();
+        muted.sort_unstable();
+        muted
+    }
+
+    /// Unmute every tab, returning the tabs that were muted
+    pub fn unmute_all_tabs(&self) -> Vec<usize> {
+        let mut state = self.state.lock_or_recover();
+        let mut unmuted: Vec<usize> = state
+            .tabs
+            .values_mut()
+            .filter(|tab| tab.muted)
+            .map(|tab| {
+                tab.muted = false;
+                tab.id
+            })
+            .collect();
+        unmuted.sort_unstable();
+        unmuted
+    }
+
+    /// Get the tabs playing sound
+    pub fn get_audible_tabs(&self) -> Vec<Tab> {
+        let state = self.state.lock_or_recover();
+        state.tabs.values().filter(|tab| tab.is_audible).cloned().collect()
+    }
+
+    /// Record that a tab loaded a page, adding it to the tab's history
+    pub fn record_navigation(&self, tab_id: usize, url: &str, title: &str) -> bool {
+        let mut state = self.state.lock_or_recover();
+        match state.tabs.get_mut(&tab_id) {
+            Some(tab) => {
+                tab.record_navigation(url, title);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set a tab's title, e.g. when its page changes it after loading
+    pub fn set_tab_title(&self, tab_id: usize, title: &str) -> bool {
+        let mut state = self.state.lock_or_recover();
+        let Some(tab) = state.tabs.get_mut(&tab_id) else {
+            return false;
+        };
+        tab.title = title.to_string();
+        if let Some(entry) = tab.history.current_mut() {
+            entry.title = title.to_string();
+        }
+        true
+    }
+
+    /// Remember where a page was scrolled as the tab leaves it, so going
+    /// back to it can scroll there again
+    pub fn save_scroll_position(&self, tab_id: usize, url: &str, position: (f64, f64)) -> bool {
+        let mut state = self.state.lock_or_recover();
+        let entry = state
+            .tabs
+            .get_mut(&tab_id)
+            .and_then(|tab| tab.history.current_mut())
+            .filter(|entry| entry.url == url);
+        match entry {
+            Some(entry) => {
+                entry.scroll_position = Some(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go back a page in a tab, returning the page to load
+    pub fn go_back(&self, tab_id: usize) -> Option<NavigationEntry> {
+        self.go_to_offset(tab_id, -1)
+    }
+
+    /// Go forward a page in a tab, returning the page to load
+    pub fn go_forward(&self, tab_id: usize) -> Option<NavigationEntry> {
+        self.go_to_offset(tab_id, 1)
+    }
+
+    /// Move through a tab's history by a number of pages, negative going
+    /// back, returning the page to load
+    pub fn go_to_offset(&self, tab_id: usize, offset: isize) -> Option<NavigationEntry> {
+        let mut state = self.state.lock_or_recover();
+        state.tabs.get_mut(&tab_id)?.go_to_offset(offset)
+    }
+
+    /// A tab's back and forward stack, e.g. for a long-press back menu
+    pub fn get_navigation_history(&self, tab_id: usize) -> Option<NavigationHistory> {
+        let state = self.state.lock_or_recover();
+        state.tabs.get(&tab_id).map(|tab| tab.history.clone())
+    }
+
+    /// Group the tabs of every window that share a domain, gathering each
+    /// group in the tab strip, returning how many tabs are grouped
+    pub fn group_tabs_by_domain(&self) -> usize {
+        let mut state = self.state.lock_or_recover();
+        let window_ids: Vec<usize> = state.windows.keys().copied().collect();
+        window_ids
+            .into_iter()
+            .map(|window_id| group_window_by_domain(&mut state, window_id))
+            .sum()
+    }
+
+    /// Regroup a tab's window after its page changed, if tabs are grouped by
+    /// domain automatically, returning the tab's group
+    pub fn apply_group_policy(&self, tab_id: usize) -> Option<String> {
+        let mut state = self.state.lock_or_recover();
+        if !state.settings.tab_policy.auto_group_by_domain {
+            return None;
+        }
+        let window_id = state.window_of_tab(tab_id)?;
+        group_window_by_domain(&mut state, window_id);
+        state.tabs.get(&tab_id)?.group.clone()
+    }
+
+    /// Take every tab out of its group, returning how many were grouped
+    pub fn ungroup_tabs(&self) -> usize {
+        let mut state = self.state.lock_or_recover();
+        state.tabs.values_mut().filter_map(|tab| tab.group.take()).count()
+    }
+
+    /// Tabs unused for the configured number of days, least recently used
+    /// first. Tabs a window is showing and tabs playing sound are never
+    /// stale.
+    pub fn stale_tabs(&self) -> Vec<StaleTab> {
+        let state = self.state.lock_or_recover();
+        let tabs: Vec<Tab> = state.tabs.values().cloned().collect();
+        find_stale_tabs(
+            &tabs,
+            &protected_tabs(&state),
+            state.settings.tab_policy.stale_after_days,
+            Utc::now(),
+        )
+    }
+
+    /// Close tabs picked from the stale tab review that are still stale,
+    /// returning the closed tabs
+    pub fn close_stale_tabs(&self, tab_ids: &[usize]) -> Vec<usize> {
+        let stale: Vec<usize> = self
+            .stale_tabs()
+            .into_iter()
+            .map(|tab| tab.tab_id)
+            .filter(|tab_id| tab_ids.contains(tab_id))
+            .collect();
+        let mut state = self.state.lock_or_recover();
+        for &tab_id in &stale {
+            state.remove_tab(tab_id);
+        }
+        stale
+    }
+
+    /// Keep tabs from the stale tab review, counting them as used now,
+    /// returning how many were found
+    pub fn keep_tabs(&self, tab_ids: &[usize]) -> usize {
+        let mut state = self.state.lock_or_recover();
+        let now = Utc::now();
+        let mut kept = 0;
+        for tab_id in tab_ids {
+            if let Some(tab) = state.tabs.get_mut(tab_id) {
+                tab.last_accessed = now;
+                kept += 1;
+            }
+        }
+        kept
+    }
+
     /// Check if tab exists
     pub fn tab_exists(&self, tab_id: usize) -> bool {
-        let state = self.state.lock().unwrap();
+        let state = self.state.lock_or_recover();
         state.tabs.contains_key(&tab_id)
     }
-}
\ No newline at end of file
+}
+
+// Private helper functions
+
+/// Group a window's tabs by domain, returning how many tabs are grouped
+fn group_window_by_domain(state: &mut BrowserState, window_id: usize) -> usize {
+    let grouped = group_by_domain(&state.window_tabs(window_id));
+    let mut count = 0;
+    for (tab_id, group) in &grouped {
+        if let Some(tab) = state.tabs.get_mut(tab_id) {
+            count += group.is_some() as usize;
+            tab.group = group.clone();
+        }
+    }
+    if let Some(window) = state.windows.get_mut(&window_id) {
+        window.tab_ids = grouped.into_iter().map(|(tab_id, _)| tab_id).collect();
+    }
+    count
+}
+
+/// Tabs that are never stale: those windows show, beside each other in
+/// split view too, and those playing sound
+fn protected_tabs(state: &BrowserState) -> Vec<usize> {
+    state
+        .windows
+        .values()
+        .flat_map(|window| [state.window_active_tab(window.id), window.split.map(|split| split.secondary_tab_id)])
+        .flatten()
+        .chain(state.tabs.values().filter(|tab| tab.is_audible).map(|tab| tab.id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_keep_separate_tab_strips() {
+        let manager = TabManager::new(Arc::new(Mutex::new(BrowserState::new())));
+        let first = manager.create_tab(Some("https://a.example".to_string()));
+        let second = manager.create_tab(Some("https://b.example".to_string()));
+        let (window, third) = manager.create_window(Some("https://c.example".to_string()));
+
+        assert_eq!(manager.get_active_tab().unwrap().id, third);
+        assert_eq!(manager.get_window_tabs(window).len(), 1);
+
+        // Switching to a tab focuses its window and keeps the other's active tab
+        assert!(manager.switch_to_tab(first));
+        assert_eq!(manager.get_active_tab().unwrap().id, first);
+        let moved_to = manager.move_tab_to_new_window(second).unwrap();
+        assert_eq!(manager.get_active_tab().unwrap().id, second);
+        assert_eq!(
+            manager.get_window_tabs(moved_to).iter().map(|tab| tab.id).collect::<Vec<_>>(),
+            vec![second]
+        );
+
+        assert_eq!(manager.close_window(window), vec![third]);
+        assert_eq!(manager.tab_count(), 2);
+        assert!(!manager.tab_exists(third));
+
+        // Resetting a window leaves it one tab, on the home page
+        assert_eq!(manager.reset_window(moved_to), vec![second]);
+        let home = manager.get_window_tabs(moved_to);
+        assert_eq!(home.len(), 1);
+        assert_eq!(home[0].url, manager.state.lock_or_recover().settings.home_page);
+    }
+
+    #[test]
+    fn test_search_tabs_by_recent_use() {
+        let manager = TabManager::new(Arc::new(Mutex::new(BrowserState::new())));
+        let docs = manager.create_tab(Some("https://docs.rs/serde".to_string()));
+        let mail = manager.create_tab(Some("https://mail.example.com".to_string()));
+        let news = manager.create_tab(Some("https://news.example.com".to_string()));
+        manager.switch_to_tab(docs);
+
+        let recent: Vec<usize> = manager.get_tabs_by_recent_use().iter().map(|tab| tab.id).collect();
+        assert_eq!(recent, vec![docs, news, mail]);
+        let found: Vec<usize> = manager.search("example").iter().map(|m| m.tab_id).collect();
+        assert_eq!(found, vec![news, mail]);
+        assert_eq!(manager.search("srde")[0].tab_id, docs);
+    }
+
+    #[test]
+    fn test_group_by_domain_and_close_stale_tabs() {
+        let state = Arc::new(Mutex::new(BrowserState::new()));
+        let manager = TabManager::new(Arc::clone(&state));
+        let first = manager.create_tab(Some("https://example.com/a".to_string()));
+        let other = manager.create_tab(Some("https://docs.rs".to_string()));
+        let second = manager.create_tab(Some("https://www.example.com/b".to_string()));
+
+        // Grouping only happens when the policy asks for it
+        assert_eq!(manager.apply_group_policy(second), None);
+        state.lock_or_recover().settings.tab_policy.auto_group_by_domain = true;
+        assert_eq!(manager.apply_group_policy(second).as_deref(), Some("example.com"));
+        let window = state.lock_or_recover().window_of_tab(first).unwrap();
+        let strip: Vec<usize> = manager.get_window_tabs(window).iter().map(|tab| tab.id).collect();
+        assert_eq!(strip, vec![first, second, other]);
+        assert_eq!(manager.ungroup_tabs(), 2);
+
+        // The shown tab is never stale, however old
+        let month_ago = Utc::now() - chrono::Duration::days(30);
+        for tab in state.lock_or_recover().tabs.values_mut() {
+            tab.last_accessed = month_ago;
+        }
+        let stale: Vec<usize> = manager.stale_tabs().iter().map(|tab| tab.tab_id).collect();
+        assert_eq!(stale, vec![first, other]);
+        assert_eq!(manager.keep_tabs(&[other]), 1);
+        assert_eq!(manager.close_stale_tabs(&[first, other, second]), vec![first]);
+        assert_eq!(manager.tab_count(), 2);
+    }
+
+    #[test]
+    fn test_split_view_follows_tabs() {
+        let state = Arc::new(Mutex::new(BrowserState::new()));
+        let manager = TabManager::new(Arc::clone(&state));
+        let first = manager.create_tab(Some("https://a.example".to_string()));
+        let window = state.lock_or_recover().window_of_tab(first).unwrap();
+
+        // A lone tab gets a new tab beside it
+        let beside = manager.enter_split_view(window).unwrap();
+        assert_ne!(beside, first);
+        assert_eq!(manager.get_active_tab().unwrap().id, first);
+        assert_eq!(manager.rotate_split_view(window).unwrap().orientation, SplitOrientation::Stacked);
+        assert_eq!(manager.resize_split_view(window, 0.9).unwrap().ratio, 0.8);
+
+        // Switching to the other pane swaps the panes
+        assert!(manager.switch_to_tab(beside));
+        assert_eq!(manager.get_split_view(window).unwrap().secondary_tab_id, first);
+
+        // Closing either tab ends the split
+        manager.close_tab(first);
+        assert_eq!(manager.get_split_view(window), None);
+        let third = manager.create_tab(None);
+        assert!(manager.split_with_tab(beside));
+        assert_eq!(manager.exit_split_view(window), Some(beside));
+        assert_eq!(manager.get_active_tab().unwrap().id, third);
+    }
+
+    #[test]
+    fn test_reorder_and_transfer_tabs() {
+        let manager = TabManager::new(Arc::new(Mutex::new(BrowserState::new())));
+        let mut events = manager.subscribe_events().unwrap();
+        let first = manager.create_tab(Some("https://a.example".to_string()));
+        let second = manager.create_tab(Some("https://b.example".to_string()));
+        let (window, third) = manager.create_window(Some("https://c.example".to_string()));
+
+        assert!(manager.move_tab(second, 0));
+        assert!(matches!(events.try_recv(), Ok(TabEvent::Moved { from_index: 1, to_index: 0, .. })));
+
+        // Dropping a tab in another window moves it there, keeping its state
+        manager.set_tab_muted(first, true);
+        assert_eq!(manager.transfer_tab(first, window, Some(0)), Some(first));
+        assert!(matches!(events.try_recv(), Ok(TabEvent::Detached { tab_id, .. }) if tab_id == first));
+        assert!(matches!(events.try_recv(), Ok(TabEvent::Attached { index: 0, .. })));
+        let strip: Vec<(usize, bool)> = manager.get_window_tabs(window).iter().map(|tab| (tab.id, tab.muted)).collect();
+        assert_eq!(strip, vec![(first, true), (third, false)]);
+        assert_eq!(manager.get_active_tab().unwrap().id, first);
+
+        // A payload survives being dragged as JSON, even if its ID was taken
+        let transfer = manager.detach_tab(third).unwrap();
+        let json = transfer.to_json().unwrap();
+        let taken = manager.create_tab(None);
+        let mut transfer = TabTransfer::from_json(&json).unwrap();
+        transfer.tab.id = taken;
+        let adopted = manager.adopt_tab(window, transfer, None).unwrap();
+        assert_ne!(adopted, taken);
+        assert_eq!(manager.get_active_tab().unwrap().url, "https://c.example");
+    }
+
+    #[test]
+    fn test_mute_background_tabs() {
+        let manager = TabManager::new(Arc::new(Mutex::new(BrowserState::new())));
+        let background = manager.create_tab(Some("https://music.example".to_string()));
+        let visible = manager.create_tab(Some("https://video.example".to_string()));
+        let (_, other_window) = manager.create_window(None);
+
+        assert!(manager.set_tab_audible(background, true));
+        assert_eq!(manager.get_audible_tabs().len(), 1);
+
+        // Active tabs of every window stay audible
+        assert_eq!(manager.mute_background_tabs(), vec![background]);
+        assert_eq!(manager.toggle_tab_mute(visible), Some(true));
+        assert_eq!(manager.toggle_tab_mute(other_window), Some(true));
+        assert_eq!(manager.unmute_all_tabs(), vec![background, visible, other_window]);
+        assert_eq!(manager.toggle_tab_mute(99), None);
+    }
+}
@@ -2,10 +2,22 @@
 pub mod manager;
 pub mod ui;
 pub mod events;
+pub mod audio;
+pub mod containers;
+pub mod thumbnails;
+pub mod switcher;
+pub mod policies;
+pub mod transfer;
 
 pub use manager::TabManager;
 pub use ui::TabUI;
 pub use events::TabEvent;
+pub use audio::TabAudioManager;
+pub use containers::{Container, ContainerColor, ContainerManager};
+pub use thumbnails::{Thumbnail, ThumbnailConfig, ThumbnailEvent, ThumbnailService};
+pub use policies::{find_stale_tabs, group_by_domain, is_stale_tabs_page, render_stale_tabs_page, tab_domain, StaleTab, STALE_TABS_PAGE_URL};
+pub use transfer::{TabTransfer, TAB_DRAG_MIME_TYPE};
+pub use switcher::{fuzzy_match, search_tabs, switcher_script, FuzzyMatch, TabMatch, TAB_SWITCHER_SCRIPT};
 
 use crate::core::{Tab, BrowserState};
 use std::sync::{Arc, Mutex};
\ No newline at end of file
@@ -0,0 +1,186 @@
+// Tab Policies
+use crate::core::Tab;
+use crate::utils::escape_html;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Internal page listing stale tabs for review before they are closed
+pub const STALE_TABS_PAGE_URL: &str = "webx://stale-tabs";
+
+/// A tab left unused long enough to be suggested for closing
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StaleTab {
+    pub tab_id: usize,
+    pub title: String,
+    pub url: String,
+    pub last_accessed: DateTime<Utc>,
+    /// Whole days since the tab was last shown
+    pub idle_days: i64,
+}
+
+/// Domain a tab is grouped under: the URL's host without `www.`. Internal
+/// and local pages have none.
+pub fn tab_domain(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_lowercase())
+}
+
+/// Group tabs in strip order by domain. Tabs sharing a domain with another
+/// tab are gathered where the first of them is and get the domain as their
+/// group; the rest are ungrouped. Returns the new strip order with each
+/// tab's group.
+pub fn group_by_domain(tabs: &[&Tab]) -> Vec<(usize, Option<String>)> {
+    let domains: Vec<Option<String>> = tabs.iter().map(|tab| tab_domain(&tab.url)).collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for domain in domains.iter().flatten() {
+        *counts.entry(domain).or_insert(0) += 1;
+    }
+
+    let mut order: Vec<(usize, Option<String>)> = Vec::with_capacity(tabs.len());
+    for (i, (tab, domain)) in tabs.iter().zip(&domains).enumerate() {
+        match domain.as_deref().filter(|domain| counts[domain] > 1) {
+            Some(domain) if order.iter().any(|(_, group)| group.as_deref() == Some(domain)) => {}
+            Some(domain) => {
+                // The first tab of a group brings the others along
+                for (member, _) in tabs[i..].iter().zip(&domains[i..]).filter(|(_, d)| d.as_deref() == Some(domain)) {
+                    order.push((member.id, Some(domain.to_string())));
+                }
+            }
+            None => order.push((tab.id, None)),
+        }
+    }
+    order
+}
+
+/// Tabs not shown for at least `stale_after_days`, least recently used
+/// first. Tabs in `protected`, e.g. those windows are showing, never are.
+pub fn find_stale_tabs(tabs: &[Tab], protected: &[usize], stale_after_days: u32, now: DateTime<Utc>) -> Vec<StaleTab> {
+    if stale_after_days == 0 {
+        return Vec::new();
+    }
+    let mut stale: Vec<StaleTab> = tabs
+        .iter()
+        .filter(|tab| !protected.contains(&tab.id))
+        .map(|tab| StaleTab {
+            tab_id: tab.id,
+            title: tab.title.clone(),
+            url: tab.url.clone(),
+            last_accessed: tab.last_accessed,
+            idle_days: (now - tab.last_accessed).num_days(),
+        })
+        .filter(|tab| tab.idle_days >= i64::from(stale_after_days))
+        .collect();
+    stale.sort_by_key(|tab| (tab.last_accessed, tab.tab_id));
+    stale
+}
+
+/// Whether a URL is the stale tabs page
+pub fn is_stale_tabs_page(url: &str) -> bool {
+    url.strip_prefix(STALE_TABS_PAGE_URL)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+}
+
+/// Render the stale tabs for review, all selected. Buttons send `type:
+/// 'staletabs'` IPC messages with an `action` of `close` or `keep` and the
+/// selected `tabs`.
+pub fn render_stale_tabs_page(stale: &[StaleTab], stale_after_days: u32) -> String {
+    let list: String = stale
+        .iter()
+        .map(|tab| {
+            format!(
+                r#"<li><label><input type="checkbox" data-tab="{id}" checked> {title}</label>
+<span class="meta">{idle} · {url}</span></li>"#,
+                id = tab.tab_id,
+                title = escape_html(&tab.title),
+                idle = match tab.idle_days {
+                    1 => "1 day".to_string(),
+                    days => format!("{} days", days),
+                },
+                url = escape_html(&tab.url),
+            )
+        })
+        .collect();
+    let body = if list.is_empty() {
+        r#"<p class="empty">No tabs have gone unused for that long.</p>"#.to_string()
+    } else {
+        format!(
+            r#"<ul>{}</ul>
+    <button data-action="close">Close Selected</button>
+    <button data-action="keep">Keep Selected</button>"#,
+            list
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Stale Tabs</title>
+    <style>{css}</style>
+</head>
+<body>
+    <header><h1>Stale Tabs</h1></header>
+    <p>Tabs not used for {days} days or more. Closing them frees memory; kept tabs count as used today.</p>
+    {body}
+    <script>{script}</script>
+</body>
+</html>"#,
+        css = PAGE_CSS,
+        days = stale_after_days,
+        body = body,
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 760px; margin: 2em auto; padding: 0 1em; color: #222; }
+ul { list-style: none; padding: 0; }
+li { margin: 0.6em 0; border-top: 1px solid #eee; padding-top: 0.5em; }
+.meta { display: block; color: #888; font-size: 0.85em; margin-left: 1.6em; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.empty { color: #888; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    const tabs = Array.from(document.querySelectorAll('input[data-tab]:checked')).map((input) => Number(input.dataset.tab));
+    if (tabs.length) window.ipc.send({ type: 'staletabs', action: button.dataset.action, tabs });
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_domain_gathers_tabs() {
+        let tabs: Vec<Tab> = [
+            "https://www.example.com/a",
+            "https://docs.rs/serde",
+            "https://news.example.org",
+            "https://example.com/b",
+            "webx://feeds",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, url)| Tab::new(i + 1, url.to_string()))
+        .collect();
+        let tabs: Vec<&Tab> = tabs.iter().collect();
+        let example = Some("example.com".to_string());
+
+        assert_eq!(
+            group_by_domain(&tabs),
+            vec![(1, example.clone()), (4, example), (2, None), (3, None), (5, None)]
+        );
+        assert_eq!(tab_domain("webx://feeds"), None);
+        assert!(is_stale_tabs_page("webx://stale-tabs?x=1"));
+        assert!(!is_stale_tabs_page("webx://stale-tabsx"));
+    }
+}
@@ -0,0 +1,276 @@
+// Tab Search
+use crate::core::Tab;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Overlay for switching tabs. Opened with Ctrl+Tab or Ctrl+Shift+A; typing
+/// sends `type: 'tabsearch'` IPC messages, choosing a tab sends `type:
+/// 'switchtab'`. Results are shown with `switcher_script`.
+pub const TAB_SWITCHER_SCRIPT: &str = r#"
+(function() {
+    let overlay = null, input = null, list = null, results = [], selected = 0, holdingCtrl = false;
+    const close = () => { if (overlay) overlay.remove(); overlay = null; holdingCtrl = false; };
+    const choose = () => {
+        const result = results[selected];
+        close();
+        if (result) window.ipc.send({ type: 'switchtab', tab_id: result.tab_id });
+    };
+    const highlight = (text, ranges) => {
+        const chars = Array.from(text);
+        const fragment = document.createDocumentFragment();
+        let last = 0;
+        for (const [start, end] of ranges) {
+            fragment.append(chars.slice(last, start).join(''));
+            const mark = document.createElement('mark');
+            mark.textContent = chars.slice(start, end).join('');
+            fragment.append(mark);
+            last = end;
+        }
+        fragment.append(chars.slice(last).join(''));
+        return fragment;
+    };
+    const render = () => {
+        list.replaceChildren(...results.map((result, i) => {
+            const row = document.createElement('div');
+            row.style.cssText = 'padding:6px 10px;border-radius:4px;cursor:pointer;' + (i === selected ? 'background:#e3ecfd;' : '');
+            const title = document.createElement('div');
+            title.append(highlight(result.title, result.title_ranges));
+            const url = document.createElement('div');
+            url.style.cssText = 'font-size:11px;color:#777;overflow:hidden;text-overflow:ellipsis;white-space:nowrap;';
+            url.append(highlight(result.url, result.url_ranges));
+            row.append(title, url);
+            row.addEventListener('click', () => { selected = i; choose(); });
+            return row;
+        }));
+    };
+    const open = () => {
+        overlay = document.createElement('div');
+        overlay.style.cssText = 'position:fixed;top:12%;left:50%;transform:translateX(-50%);width:min(560px,90vw);' +
+            'max-height:60vh;overflow:auto;z-index:2147483647;background:#fff;color:#222;font:14px system-ui,sans-serif;' +
+            'border-radius:8px;box-shadow:0 8px 32px rgba(0,0,0,.3);padding:8px;';
+        input = document.createElement('input');
+        input.placeholder = 'Search tabs';
+        input.style.cssText = 'width:100%;box-sizing:border-box;padding:8px;font-size:15px;border:1px solid #ccc;border-radius:4px;';
+        input.addEventListener('input', () => window.ipc.send({ type: 'tabsearch', query: input.value }));
+        list = document.createElement('div');
+        overlay.append(input, list);
+        (document.body || document.documentElement).append(overlay);
+        input.focus();
+    };
+    window.__webxTabSwitcher = {
+        show: (matches, initial) => {
+            if (!overlay) open();
+            results = matches;
+            selected = Math.min(initial, Math.max(results.length - 1, 0));
+            render();
+        },
+    };
+    document.addEventListener('keydown', (e) => {
+        if (e.ctrlKey && (e.key === 'Tab' || (e.shiftKey && e.key.toLowerCase() === 'a'))) {
+            e.preventDefault();
+            if (overlay && e.key === 'Tab') {
+                selected = (selected + (e.shiftKey ? results.length - 1 : 1)) % Math.max(results.length, 1);
+                render();
+            } else if (!overlay) {
+                holdingCtrl = e.key === 'Tab';
+                window.ipc.send({ type: 'tabsearch', query: '', cycle: holdingCtrl });
+            }
+            return;
+        }
+        if (!overlay) return;
+        if (e.key === 'Escape') { e.preventDefault(); close(); }
+        else if (e.key === 'Enter') { e.preventDefault(); choose(); }
+        else if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+            e.preventDefault();
+            selected = (selected + (e.key === 'ArrowDown' ? 1 : results.length - 1)) % Math.max(results.length, 1);
+            render();
+        }
+    }, true);
+    document.addEventListener('keyup', (e) => {
+        // Releasing Ctrl after Ctrl+Tab switches, like other browsers
+        if (overlay && holdingCtrl && e.key === 'Control') choose();
+    }, true);
+})();
+"#;
+
+/// A tab matching a search, with the characters that matched for
+/// highlighting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TabMatch {
+    pub tab_id: usize,
+    pub title: String,
+    pub url: String,
+    /// Higher is better
+    pub score: i64,
+    /// Matched character ranges of the title, in characters
+    pub title_ranges: Vec<Range<usize>>,
+    /// Matched character ranges of the URL, in characters
+    pub url_ranges: Vec<Range<usize>>,
+}
+
+/// A fuzzy match of a query in a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Character indexes of the text that matched the query
+    pub positions: Vec<usize>,
+}
+
+impl FuzzyMatch {
+    /// Matched positions as ranges of consecutive characters
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for &position in &self.positions {
+            match ranges.last_mut() {
+                Some(range) if range.end == position => range.end += 1,
+                _ => ranges.push(position..position + 1),
+            }
+        }
+        ranges
+    }
+}
+
+/// Find the query's characters in order in the text, ignoring case and
+/// spaces in the query. Consecutive characters and characters at the start
+/// of words score higher; gaps score lower.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+
+    // Prefer a contiguous match when there is one
+    if let Some(start) = lower.windows(query.len()).position(|window| window == query.as_slice()) {
+        let positions: Vec<usize> = (start..start + query.len()).collect();
+        let score = match_score(&text, &positions);
+        return Some(FuzzyMatch { score, positions });
+    }
+
+    // Jumping to word starts can skip characters a later one needs
+    let positions = subsequence(&text, &lower, &query, true).or_else(|| subsequence(&text, &lower, &query, false))?;
+    let score = match_score(&text, &positions);
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Match a query against tabs in most recently used order, best matches
+/// first. Title matches count more than URL matches. An empty query keeps
+/// every tab in order.
+pub fn search_tabs(tabs: &[Tab], query: &str) -> Vec<TabMatch> {
+    let mut matches: Vec<TabMatch> = tabs
+        .iter()
+        .filter_map(|tab| {
+            let title = fuzzy_match(query, &tab.title);
+            let url = fuzzy_match(query, display_url(&tab.url));
+            let score = match (&title, &url) {
+                (None, None) => return None,
+                (title, url) => title
+                    .as_ref()
+                    .map(|m| m.score * 2)
+                    .max(url.as_ref().map(|m| m.score)),
+            }
+            .unwrap_or_default();
+            // URL ranges are relative to the displayed URL; shift them to the full one
+            let offset = tab.url.chars().count() - display_url(&tab.url).chars().count();
+            Some(TabMatch {
+                tab_id: tab.id,
+                title: tab.title.clone(),
+                url: tab.url.clone(),
+                score,
+                title_ranges: title.map(|m| m.ranges()).unwrap_or_default(),
+                url_ranges: url
+                    .map(|m| m.ranges().into_iter().map(|r| r.start + offset..r.end + offset).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    // Stable, so equal scores stay in most recently used order
+    matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+    matches
+}
+
+/// Script that shows search results in the tab switcher, selecting the
+/// result at `selected`
+pub fn switcher_script(matches: &[TabMatch], selected: usize) -> Result<String, serde_json::Error> {
+    Ok(format!(
+        "window.__webxTabSwitcher && window.__webxTabSwitcher.show({}, {});",
+        serde_json::to_string(matches)?,
+        selected
+    ))
+}
+
+// Private helper functions
+
+fn is_word_start(text: &[char], index: usize) -> bool {
+    index == 0
+        || !text[index - 1].is_alphanumeric()
+        || (text[index - 1].is_lowercase() && text[index].is_uppercase())
+}
+
+/// Positions of the query's characters in order, taking the next word
+/// start holding each character if `word_starts`, else its next occurrence
+fn subsequence(text: &[char], lower: &[char], query: &[char], word_starts: bool) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut next = 0;
+    for q in query {
+        let word_start = (next..lower.len())
+            .find(|&i| lower[i] == *q && is_word_start(text, i))
+            .filter(|_| word_starts);
+        let found = word_start.or_else(|| (next..lower.len()).find(|&i| lower[i] == *q))?;
+        positions.push(found);
+        next = found + 1;
+    }
+    Some(positions)
+}
+
+fn match_score(text: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    for (i, &position) in positions.iter().enumerate() {
+        score += 10;
+        if is_word_start(text, position) {
+            score += 8;
+        }
+        match i.checked_sub(1).map(|previous| positions[previous]) {
+            Some(previous) if previous + 1 == position => score += 6,
+            Some(previous) => score -= (position - previous - 1).min(10) as i64,
+            None => score -= position.min(15) as i64 / 3,
+        }
+    }
+    score
+}
+
+/// The URL without its scheme and `www.`, which queries rarely mean
+fn display_url(url: &str) -> &str {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.strip_prefix("www.").unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match_ranks_and_highlights() {
+        let found = fuzzy_match("gh iss", "GitHub Issues").unwrap();
+        assert_eq!(found.ranges(), vec![0..1, 3..4, 7..10]);
+        assert!(fuzzy_match("xyz", "GitHub Issues").is_none());
+        assert_eq!(fuzzy_match("ib", "libs in").unwrap().positions, vec![1, 2]);
+        // Contiguous and word-start matches beat scattered ones
+        assert!(fuzzy_match("doc", "Rust docs").unwrap().score > fuzzy_match("doc", "dashboard of cats").unwrap().score);
+
+        let mut docs = Tab::new(1, "https://docs.rs/tokio".to_string());
+        docs.title = "tokio - Rust".to_string();
+        let mut mail = Tab::new(2, "https://mail.example.com/".to_string());
+        mail.title = "Inbox".to_string();
+
+        let matches = search_tabs(&[mail.clone(), docs.clone()], "tokio");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].tab_id, 1);
+        assert_eq!(matches[0].title_ranges, vec![0..5]);
+        assert_eq!(matches[0].url_ranges, vec![16..21]);
+        // An empty query keeps the given order
+        let all: Vec<usize> = search_tabs(&[mail, docs], "").iter().map(|m| m.tab_id).collect();
+        assert_eq!(all, vec![2, 1]);
+    }
+}
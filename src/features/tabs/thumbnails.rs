@@ -0,0 +1,479 @@
+// Page Thumbnails
+use crate::error::WebxError;
+use crate::utils::{LockExt, url_in_domain};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{ImageReader, Limits};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Largest screenshot accepted for downsizing
+const MAX_CAPTURE_DIMENSION: u32 = 8192;
+/// How often the idle watcher checks for pages ready to capture
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Thumbnail configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailConfig {
+    pub width: u32,
+    pub height: u32,
+    pub quality: u8,
+    /// Quiet time after a page finishes loading before it is captured
+    pub idle_delay_ms: u64,
+    /// Pages captured more recently than this are not captured again
+    pub recapture_after_minutes: u64,
+    pub max_cache_mb: u64,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            width: 320,
+            height: 200,
+            quality: 75,
+            idle_delay_ms: 1500,
+            recapture_after_minutes: 60,
+            max_cache_mb: 64,
+        }
+    }
+}
+
+/// A stored page thumbnail
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Thumbnail {
+    pub url: String,
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+    pub last_accessed: chrono::DateTime<chrono::Utc>,
+}
+
+/// Thumbnail events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ThumbnailEvent {
+    /// The page in a tab has been idle long enough; the window should take
+    /// a screenshot of the tab's web view and pass it to `store_capture`
+    CaptureRequested { tab_id: usize, url: String },
+    Updated { url: String },
+    Evicted { url: String },
+}
+
+/// Captures, downsizes and caches page thumbnails on disk
+pub struct ThumbnailService {
+    config: ThumbnailConfig,
+    cache_dir: PathBuf,
+    index: Arc<Mutex<HashMap<String, Thumbnail>>>,
+    pending: Arc<Mutex<HashMap<usize, (String, Instant)>>>,
+    idle_watcher: Option<tokio::task::JoinHandle<()>>,
+    tx: mpsc::UnboundedSender<ThumbnailEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ThumbnailEvent>>>>,
+}
+
+impl ThumbnailService {
+    /// Create a new thumbnail service
+    pub fn new(
+        config: Option<ThumbnailConfig>,
+        cache_dir: Option<PathBuf>,
+    ) -> Result<Self, WebxError> {
+        let cache_dir = cache_dir.unwrap_or_else(|| {
+            let mut path = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("thumbnails");
+            path
+        });
+
+        fs::create_dir_all(&cache_dir)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let service = Self {
+            config: config.unwrap_or_default(),
+            cache_dir,
+            index: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            idle_watcher: None,
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        };
+
+        service.load_index()?;
+        Ok(service)
+    }
+
+    /// A tab started navigating; any capture waiting for it is cancelled
+    pub fn on_navigation_started(&self, tab_id: usize) {
+        self.pending.lock_or_recover().remove(&tab_id);
+    }
+
+    /// A tab finished loading; it is captured once it stays idle
+    pub fn on_load_finished(&self, tab_id: usize, url: &str) {
+        if !is_capturable(url) || self.is_fresh(url) {
+            return;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(self.config.idle_delay_ms);
+        self.pending
+            .lock_or_recover()
+            .insert(tab_id, (url.to_string(), deadline));
+    }
+
+    /// A tab was closed
+    pub fn on_tab_closed(&self, tab_id: usize) {
+        self.pending.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Take the tabs whose pages have been idle long enough to capture,
+    /// emitting a capture request for each
+    pub fn take_due_captures(&self) -> Vec<(usize, String)> {
+        let now = Instant::now();
+        let mut due: Vec<(usize, String)> = {
+            let mut pending = self.pending.lock_or_recover();
+            let ready: Vec<usize> = pending
+                .iter()
+                .filter(|(_, (_, deadline))| *deadline <= now)
+                .map(|(tab_id, _)| *tab_id)
+                .collect();
+            ready
+                .into_iter()
+                .filter_map(|tab_id| pending.remove(&tab_id).map(|(url, _)| (tab_id, url)))
+                .collect()
+        };
+        due.sort();
+
+        for (tab_id, url) in &due {
+            let _ = self.tx.send(ThumbnailEvent::CaptureRequested {
+                tab_id: *tab_id,
+                url: url.clone(),
+            });
+        }
+        due
+    }
+
+    /// Downsize a screenshot of a page (PNG, JPEG or WebP) and store it
+    pub fn store_capture(&self, url: &str, screenshot: &[u8]) -> Result<Thumbnail, WebxError> {
+        if !is_capturable(url) {
+            return Err(format!("Pages like {} are not thumbnailed", url).into());
+        }
+
+        let mut reader = ImageReader::new(Cursor::new(screenshot)).with_guessed_format()?;
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_CAPTURE_DIMENSION);
+        limits.max_image_height = Some(MAX_CAPTURE_DIMENSION);
+        reader.limits(limits);
+        let image = reader.decode()?;
+
+        // Keep the top of the page, cropped to the thumbnail's aspect ratio
+        let (width, height) = (self.config.width.max(1), self.config.height.max(1));
+        let crop_height = ((image.width() as u64 * height as u64) / width as u64).clamp(1, image.height() as u64) as u32;
+        let thumbnail = image
+            .crop_imm(0, 0, image.width(), crop_height)
+            .resize(width, height, FilterType::Triangle)
+            .to_rgb8();
+
+        let encoded = webp::Encoder::from_rgb(&thumbnail, thumbnail.width(), thumbnail.height())
+            .encode(self.config.quality.min(100) as f32)
+            .to_vec();
+
+        let key = normalize_url(url);
+        let file_name = format!("{}.webp", hex_digest(&key));
+        fs::write(self.cache_dir.join(&file_name), &encoded)?;
+
+        let now = chrono::Utc::now();
+        let entry = Thumbnail {
+            url: key.clone(),
+            file_name,
+            width: thumbnail.width(),
+            height: thumbnail.height(),
+            size_bytes: encoded.len() as u64,
+            captured_at: now,
+            last_accessed: now,
+        };
+        self.index.lock_or_recover().insert(key.clone(), entry.clone());
+
+        self.enforce_cache_limit()?;
+        self.save_index()?;
+
+        if self.index.lock_or_recover().contains_key(&key) {
+            let _ = self.tx.send(ThumbnailEvent::Updated { url: key });
+        }
+        Ok(entry)
+    }
+
+    /// Get the thumbnail of a page
+    pub fn get_thumbnail(&self, url: &str) -> Option<Thumbnail> {
+        let mut index = self.index.lock_or_recover();
+        let entry = index.get_mut(&normalize_url(url))?;
+        entry.last_accessed = chrono::Utc::now();
+        Some(entry.clone())
+    }
+
+    /// Get the path of a page's thumbnail image
+    pub fn get_thumbnail_path(&self, url: &str) -> Option<PathBuf> {
+        self.get_thumbnail(url)
+            .map(|thumbnail| self.cache_dir.join(thumbnail.file_name))
+            .filter(|path| path.exists())
+    }
+
+    /// Get a page's thumbnail as a data URL for the new tab page and tab
+    /// hover previews
+    pub fn get_thumbnail_data_url(&self, url: &str) -> Option<String> {
+        let data = fs::read(self.get_thumbnail_path(url)?).ok()?;
+        Some(format!("data:image/webp;base64,{}", STANDARD.encode(data)))
+    }
+
+    /// Get thumbnails for several pages, skipping pages without one
+    pub fn get_thumbnails(&self, urls: &[String]) -> HashMap<String, Thumbnail> {
+        urls.iter()
+            .filter_map(|url| self.get_thumbnail(url).map(|thumbnail| (url.clone(), thumbnail)))
+            .collect()
+    }
+
+    /// Remove a page's thumbnail
+    pub fn remove_thumbnail(&self, url: &str) -> Result<bool, WebxError> {
+        let removed = self.index.lock_or_recover().remove(&normalize_url(url));
+        match removed {
+            Some(thumbnail) => {
+                let _ = fs::remove_file(self.cache_dir.join(thumbnail.file_name));
+                self.save_index()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove the thumbnails of a domain and its subdomains
+    pub fn clear_site(&self, domain: &str) -> Result<usize, WebxError> {
+        let removed: Vec<Thumbnail> = {
+            let mut index = self.index.lock_or_recover();
+            let urls: Vec<String> = index.keys().filter(|url| url_in_domain(url, domain)).cloned().collect();
+            urls.iter().filter_map(|url| index.remove(url)).collect()
+        };
+        for thumbnail in &removed {
+            let _ = fs::remove_file(self.cache_dir.join(&thumbnail.file_name));
+        }
+        if !removed.is_empty() {
+            self.save_index()?;
+        }
+        Ok(removed.len())
+    }
+
+    /// Delete all thumbnails
+    pub fn clear(&self) -> Result<(), WebxError> {
+        let thumbnails: Vec<Thumbnail> = self.index.lock_or_recover().drain().map(|(_, t)| t).collect();
+        for thumbnail in thumbnails {
+            let _ = fs::remove_file(self.cache_dir.join(thumbnail.file_name));
+        }
+        self.save_index()
+    }
+
+    /// Total size of the thumbnails on disk
+    pub fn total_size(&self) -> u64 {
+        self.index.lock_or_recover().values().map(|t| t.size_bytes).sum()
+    }
+
+    /// Poll for idle pages in the background, emitting capture requests
+    pub fn start_idle_watcher(service: Arc<Mutex<ThumbnailService>>) {
+        let task_service = service.clone();
+        let handle = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(IDLE_POLL_INTERVAL);
+            loop {
+                timer.tick().await;
+                task_service.lock_or_recover().take_due_captures();
+            }
+        });
+
+        let mut service = service.lock_or_recover();
+        service.stop_idle_watcher();
+        service.idle_watcher = Some(handle);
+    }
+
+    /// Stop the background idle watcher
+    pub fn stop_idle_watcher(&mut self) {
+        if let Some(handle) = self.idle_watcher.take() {
+            handle.abort();
+        }
+    }
+
+    /// Check if the idle watcher is running
+    pub fn is_watching(&self) -> bool {
+        self.idle_watcher.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Subscribe to thumbnail events (can only be called once)
+    pub fn subscribe_events(&self) -> Option<mpsc::UnboundedReceiver<ThumbnailEvent>> {
+        self.rx.lock_or_recover().take()
+    }
+
+    /// Get configuration
+    pub fn get_config(&self) -> &ThumbnailConfig {
+        &self.config
+    }
+
+    // Private helper methods
+
+    fn is_fresh(&self, url: &str) -> bool {
+        let max_age = chrono::Duration::minutes(self.config.recapture_after_minutes as i64);
+        self.index
+            .lock_or_recover()
+            .get(&normalize_url(url))
+            .is_some_and(|thumbnail| chrono::Utc::now() - thumbnail.captured_at < max_age)
+    }
+
+    fn enforce_cache_limit(&self) -> Result<(), WebxError> {
+        let max_bytes = self.config.max_cache_mb * 1024 * 1024;
+        let evicted: Vec<Thumbnail> = {
+            let mut index = self.index.lock_or_recover();
+            let mut total: u64 = index.values().map(|t| t.size_bytes).sum();
+            let mut by_access: Vec<(chrono::DateTime<chrono::Utc>, String)> = index
+                .values()
+                .map(|t| (t.last_accessed, t.url.clone()))
+                .collect();
+            by_access.sort();
+
+            let mut evicted = Vec::new();
+            for (_, url) in by_access {
+                if total <= max_bytes {
+                    break;
+                }
+                if let Some(thumbnail) = index.remove(&url) {
+                    total -= thumbnail.size_bytes;
+                    evicted.push(thumbnail);
+                }
+            }
+            evicted
+        };
+
+        for thumbnail in evicted {
+            fs::remove_file(self.cache_dir.join(&thumbnail.file_name))?;
+            let _ = self.tx.send(ThumbnailEvent::Evicted { url: thumbnail.url });
+        }
+        Ok(())
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.cache_dir.join("thumbnails.json")
+    }
+
+    fn save_index(&self) -> Result<(), WebxError> {
+        let content = serde_json::to_string_pretty(&*self.index.lock_or_recover())?;
+        fs::write(self.index_path(), content)?;
+        Ok(())
+    }
+
+    fn load_index(&self) -> Result<(), WebxError> {
+        if !self.index_path().exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(self.index_path())?;
+        let mut index: HashMap<String, Thumbnail> = serde_json::from_str(&content)?;
+        // Drop entries whose image was deleted behind our back
+        index.retain(|_, thumbnail| self.cache_dir.join(&thumbnail.file_name).exists());
+        *self.index.lock_or_recover() = index;
+        Ok(())
+    }
+}
+
+// Private helper functions
+
+fn is_capturable(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn normalize_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+fn hex_digest(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn screenshot(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([30, 120, 200]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_capture_is_downsized_and_queryable() {
+        let temp_dir = TempDir::new().unwrap();
+        let service = ThumbnailService::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let thumbnail = service
+            .store_capture("https://example.com/page#section", &screenshot(1280, 2400))
+            .unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (320, 200));
+
+        assert!(service.get_thumbnail("https://example.com/page").is_some());
+        assert!(service
+            .get_thumbnail_data_url("https://example.com/page")
+            .unwrap()
+            .starts_with("data:image/webp;base64,"));
+        assert!(service.store_capture("about:blank", &screenshot(10, 10)).is_err());
+
+        // The index survives restarts
+        let reloaded = ThumbnailService::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(reloaded.total_size(), thumbnail.size_bytes);
+        assert!(reloaded.remove_thumbnail("https://example.com/page").unwrap());
+        assert!(reloaded.get_thumbnail_path("https://example.com/page").is_none());
+    }
+
+    #[test]
+    fn test_captures_wait_for_idle_and_respect_cap() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ThumbnailConfig {
+            idle_delay_ms: 0,
+            max_cache_mb: 0,
+            ..Default::default()
+        };
+        let service = ThumbnailService::new(Some(config), Some(temp_dir.path().to_path_buf())).unwrap();
+        let mut events = service.subscribe_events().unwrap();
+
+        service.on_load_finished(1, "https://example.com/");
+        service.on_load_finished(2, "https://example.org/");
+        service.on_navigation_started(2);
+        service.on_load_finished(3, "webx://settings");
+        assert_eq!(service.take_due_captures(), vec![(1, "https://example.com/".to_string())]);
+        assert!(service.take_due_captures().is_empty());
+
+        // A zero-sized cache keeps nothing on disk
+        service.store_capture("https://example.com/", &screenshot(64, 64)).unwrap();
+        assert_eq!(service.total_size(), 0);
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            kinds.push(match event {
+                ThumbnailEvent::CaptureRequested { .. } => "requested",
+                ThumbnailEvent::Updated { .. } => "updated",
+                ThumbnailEvent::Evicted { .. } => "evicted",
+            });
+        }
+        assert_eq!(kinds, vec!["requested", "evicted"]);
+    }
+}
@@ -0,0 +1,30 @@
+// Tab Transfer
+use crate::core::Tab;
+use crate::error::WebxError;
+use serde::{Deserialize, Serialize};
+
+/// Type of the drag data carrying a tab between windows
+pub const TAB_DRAG_MIME_TYPE: &str = "application/x-webx-tab";
+
+/// A tab detached from its window, with its page and state, for another
+/// window to adopt. As JSON it is the data dragged between windows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabTransfer {
+    pub tab: Tab,
+    /// Window the tab was detached from
+    pub source_window_id: Option<usize>,
+    /// Position the tab had in its window's strip
+    pub source_index: Option<usize>,
+}
+
+impl TabTransfer {
+    /// The transfer as drag data
+    pub fn to_json(&self) -> Result<String, WebxError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Read a transfer from drag data
+    pub fn from_json(json: &str) -> Result<Self, WebxError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
@@ -0,0 +1,442 @@
+// Context Menus
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+/// Longest selection shown in an item's label before it is cut short
+const LABEL_SELECTION_CHARS: usize = 32;
+
+/// ID of the built-in item opening the devtools, withdrawn when policy
+/// disables them
+pub const INSPECT_ITEM: &str = "inspect";
+
+/// Script that replaces the webview's context menu with the browser's. The
+/// page asks for the items fitting what was clicked and shows them; Shift
+/// keeps the webview's own menu.
+pub const CONTEXT_MENU_SCRIPT: &str = r#"
+(function() {
+    if (window.__webxContextMenu) return;
+    window.__webxContextMenu = true;
+    let menu = null;
+    const close = () => { if (menu) menu.remove(); menu = null; };
+    const show = (items, x, y) => {
+        close();
+        if (!items.length) return;
+        menu = document.createElement('div');
+        menu.style.cssText = 'position:fixed;z-index:2147483647;min-width:200px;padding:4px 0;background:#fff;color:#222;' +
+            'font:13px system-ui,sans-serif;border-radius:6px;box-shadow:0 4px 16px rgba(0,0,0,.3);';
+        let group = items[0].extension;
+        for (const item of items) {
+            if (item.extension !== group) {
+                const separator = document.createElement('div');
+                separator.style.cssText = 'height:1px;margin:4px 0;background:#ddd;';
+                menu.append(separator);
+                group = item.extension;
+            }
+            const row = document.createElement('div');
+            row.textContent = item.label;
+            row.style.cssText = 'padding:6px 16px;cursor:default;white-space:nowrap;';
+            row.addEventListener('mouseenter', () => { row.style.background = '#e3ecfd'; });
+            row.addEventListener('mouseleave', () => { row.style.background = ''; });
+            // Keep the page's selection for the item to act on
+            row.addEventListener('mousedown', (e) => e.preventDefault());
+            row.addEventListener('click', () => {
+                close();
+                window.ipc.send({ type: 'contextmenu-activate', item: item.id });
+            });
+            menu.append(row);
+        }
+        (document.body || document.documentElement).append(menu);
+        // Keep the menu inside the viewport
+        menu.style.left = Math.max(0, Math.min(x, innerWidth - menu.offsetWidth)) + 'px';
+        menu.style.top = Math.max(0, Math.min(y, innerHeight - menu.offsetHeight)) + 'px';
+    };
+    document.addEventListener('contextmenu', (e) => {
+        if (e.shiftKey) return;
+        e.preventDefault();
+        const target = e.target instanceof Element ? e.target : e.target.parentElement;
+        const link = target && target.closest('a[href]');
+        const image = target && target.closest('img');
+        const x = e.clientX, y = e.clientY;
+        window.ipc.request({
+            type: 'contextmenu',
+            link: link ? link.href : null,
+            image: image ? (image.currentSrc || image.src) : null,
+            selection: window.getSelection().toString() || null,
+        })
+            .then((items) => show(items, x, y))
+            .catch((error) => console.warn('WebX could not show the context menu:', error));
+    }, true);
+    document.addEventListener('mousedown', (e) => { if (menu && !menu.contains(e.target)) close(); }, true);
+    document.addEventListener('keydown', (e) => { if (e.key === 'Escape') close(); }, true);
+    window.addEventListener('scroll', close, true);
+    window.addEventListener('blur', close);
+})();
+"#;
+
+/// What a menu was opened on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MenuContext {
+    /// The page itself, with nothing more specific clicked
+    Page,
+    Link,
+    Image,
+    Selection,
+}
+
+/// Element and selection a menu was opened on, as the page reports them
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MenuTarget {
+    /// Filled in from the page's address, not taken from the page
+    #[serde(default, skip_deserializing)]
+    pub page_url: String,
+    #[serde(default)]
+    pub link: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub selection: Option<String>,
+}
+
+impl MenuTarget {
+    /// Contexts the target is in; `Page` when none of the others
+    pub fn contexts(&self) -> Vec<MenuContext> {
+        let present = |value: &Option<String>| value.as_deref().is_some_and(|value| !value.trim().is_empty());
+        let mut contexts = Vec::new();
+        if present(&self.link) {
+            contexts.push(MenuContext::Link);
+        }
+        if present(&self.image) {
+            contexts.push(MenuContext::Image);
+        }
+        if present(&self.selection) {
+            contexts.push(MenuContext::Selection);
+        }
+        if contexts.is_empty() {
+            contexts.push(MenuContext::Page);
+        }
+        contexts
+    }
+}
+
+/// What a built-in item does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinCommand {
+    OpenLinkInNewTab,
+    SaveImage,
+    SearchSelection,
+    /// Read the selection aloud, or the page when nothing is selected
+    ReadAloud,
+    PrintSelection,
+    /// Share the link clicked, or the page
+    Share,
+    /// Pick images on the page to save together
+    SaveImages,
+    Inspect,
+}
+
+/// Who added an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuItemSource {
+    Builtin(BuiltinCommand),
+    /// An extension, by ID
+    Extension(String),
+}
+
+/// An item the menu shows in some contexts
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub id: String,
+    /// Shown text; `%s` stands for the selected text
+    pub label: String,
+    pub contexts: Vec<MenuContext>,
+    pub source: MenuItemSource,
+}
+
+/// An item as the page shows it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MenuEntry {
+    pub id: String,
+    pub label: String,
+    /// Extension that added the item; the page groups items by it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+}
+
+/// What the user picked
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+    OpenInNewTab(String),
+    SaveImage(String),
+    /// Search the web for the text
+    SearchSelection(String),
+    /// Read the text aloud, or the page with `None`
+    ReadAloud(Option<String>),
+    /// Print what is selected in the tab
+    PrintSelection,
+    /// Share the link, or the page with `None`
+    Share(Option<String>),
+    SaveImages,
+    Inspect,
+    /// An extension's item, for the extension to act on
+    Extension { extension_id: String, item_id: String, target: MenuTarget },
+}
+
+/// An item picked in a tab's menu
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextMenuEvent {
+    pub tab_id: usize,
+    pub action: MenuAction,
+}
+
+/// The browser's context menu, with the items of core features and those
+/// extensions contribute
+pub struct ContextMenu {
+    items: Mutex<Vec<MenuItem>>,
+    /// Target of the menu each tab has open, by tab ID
+    open_menus: Mutex<HashMap<usize, MenuTarget>>,
+    tx: mpsc::UnboundedSender<ContextMenuEvent>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<ContextMenuEvent>>>,
+}
+
+impl Default for ContextMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContextMenu {
+    /// A menu with the built-in items
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let builtin = |id: &str, label: &str, contexts: &[MenuContext], command| MenuItem {
+            id: id.to_string(),
+            label: label.to_string(),
+            contexts: contexts.to_vec(),
+            source: MenuItemSource::Builtin(command),
+        };
+        let everywhere = [MenuContext::Page, MenuContext::Link, MenuContext::Image, MenuContext::Selection];
+        let items = vec![
+            builtin("open-link-in-new-tab", "Open Link in New Tab", &[MenuContext::Link], BuiltinCommand::OpenLinkInNewTab),
+            builtin("save-image", "Save Image", &[MenuContext::Image], BuiltinCommand::SaveImage),
+            builtin("search-selection", "Search the Web for “%s”", &[MenuContext::Selection], BuiltinCommand::SearchSelection),
+            builtin("read-aloud", "Read Aloud", &[MenuContext::Page, MenuContext::Selection], BuiltinCommand::ReadAloud),
+            builtin("print-selection", "Print Selection", &[MenuContext::Selection], BuiltinCommand::PrintSelection),
+            builtin("share", "Share…", &[MenuContext::Page, MenuContext::Link], BuiltinCommand::Share),
+            builtin("save-images", "Save Images…", &[MenuContext::Page], BuiltinCommand::SaveImages),
+            builtin(INSPECT_ITEM, "Inspect", &everywhere, BuiltinCommand::Inspect),
+        ];
+
+        Self {
+            items: Mutex::new(items),
+            open_menus: Mutex::new(HashMap::new()),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Add an extension's item, shown after the built-in ones. Returns the
+    /// item's menu-wide ID.
+    pub fn contribute(&self, extension_id: &str, item_id: &str, label: &str, contexts: &[MenuContext]) -> Result<String, WebxError> {
+        if label.trim().is_empty() || contexts.is_empty() {
+            return Err(WebxError::Invalid(format!("Menu item {} of extension {} needs a label and contexts", item_id, extension_id)));
+        }
+        let id = format!("{}/{}", extension_id, item_id);
+        let mut items = self.items.lock_or_recover();
+        if items.iter().any(|item| item.id == id) {
+            return Err(WebxError::Invalid(format!("Extension {} already has a menu item {}", extension_id, item_id)));
+        }
+        items.push(MenuItem {
+            id: id.clone(),
+            label: label.to_string(),
+            contexts: contexts.to_vec(),
+            source: MenuItemSource::Extension(extension_id.to_string()),
+        });
+        Ok(id)
+    }
+
+    /// Remove every item of an extension, e.g. when it is unloaded; how many
+    /// there were
+    pub fn withdraw(&self, extension_id: &str) -> usize {
+        let mut items = self.items.lock_or_recover();
+        let before = items.len();
+        items.retain(|item| item.source != MenuItemSource::Extension(extension_id.to_string()));
+        before - items.len()
+    }
+
+    /// Remove an item by ID; whether there was one
+    pub fn remove_item(&self, id: &str) -> bool {
+        let mut items = self.items.lock_or_recover();
+        let before = items.len();
+        items.retain(|item| item.id != id);
+        items.len() != before
+    }
+
+    /// Items fitting a target
+    pub fn items_for(&self, target: &MenuTarget) -> Vec<MenuEntry> {
+        let contexts = target.contexts();
+        let selection = target.selection.as_deref().map(shorten).unwrap_or_default();
+        self.items
+            .lock_or_recover()
+            .iter()
+            .filter(|item| item.contexts.iter().any(|context| contexts.contains(context)))
+            .map(|item| MenuEntry {
+                id: item.id.clone(),
+                label: item.label.replace("%s", &selection),
+                extension: match &item.source {
+                    MenuItemSource::Extension(extension_id) => Some(extension_id.clone()),
+                    MenuItemSource::Builtin(_) => None,
+                },
+            })
+            .collect()
+    }
+
+    /// Open a tab's menu on a target, replacing any it had open; the items
+    /// to show
+    pub fn open(&self, tab_id: usize, target: MenuTarget) -> Vec<MenuEntry> {
+        let entries = self.items_for(&target);
+        self.open_menus.lock_or_recover().insert(tab_id, target);
+        entries
+    }
+
+    /// Pick an item of a tab's open menu, closing the menu
+    pub fn activate(&self, tab_id: usize, item_id: &str) -> Result<MenuAction, WebxError> {
+        let target = self
+            .open_menus
+            .lock_or_recover()
+            .remove(&tab_id)
+            .ok_or_else(|| WebxError::NotFound(format!("No context menu open in tab {}", tab_id)))?;
+        let item = self
+            .items
+            .lock_or_recover()
+            .iter()
+            .find(|item| item.id == item_id)
+            .cloned()
+            .ok_or_else(|| WebxError::NotFound(format!("Menu item {}", item_id)))?;
+        let contexts = target.contexts();
+        if !item.contexts.iter().any(|context| contexts.contains(context)) {
+            return Err(WebxError::Invalid(format!("Menu item {} does not apply here", item_id)));
+        }
+
+        let action = match item.source {
+            MenuItemSource::Builtin(command) => builtin_action(command, target)?,
+            MenuItemSource::Extension(extension_id) => MenuAction::Extension {
+                item_id: item.id[extension_id.len() + 1..].to_string(),
+                extension_id,
+                target,
+            },
+        };
+        let _ = self.tx.send(ContextMenuEvent { tab_id, action: action.clone() });
+        Ok(action)
+    }
+
+    /// Drop a closed tab's open menu
+    pub fn forget_tab(&self, tab_id: usize) {
+        self.open_menus.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Subscribe to picked items
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<ContextMenuEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+}
+
+impl IpcHandler for ContextMenu {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["contextmenu", "contextmenu-activate"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        let tab_id = context.tab_id.ok_or("The page's tab is gone")?;
+        match message {
+            IpcMessage::ContextMenu(target) => {
+                let target = MenuTarget {
+                    page_url: context.origin.clone(),
+                    ..target
+                };
+                Ok(serde_json::to_value(self.open(tab_id, target))?)
+            }
+            IpcMessage::ContextMenuActivate { item } => self.activate(tab_id, &item).map(|_| serde_json::Value::Null),
+            _ => Err(WebxError::Invalid("Not a context menu message".to_string())),
+        }
+    }
+}
+
+// Private helper functions
+
+fn builtin_action(command: BuiltinCommand, target: MenuTarget) -> Result<MenuAction, WebxError> {
+    let action = match command {
+        BuiltinCommand::OpenLinkInNewTab => MenuAction::OpenInNewTab(target.link.ok_or("No link was clicked")?),
+        BuiltinCommand::SaveImage => MenuAction::SaveImage(target.image.ok_or("No image was clicked")?),
+        BuiltinCommand::SearchSelection => MenuAction::SearchSelection(target.selection.ok_or("Nothing is selected")?),
+        BuiltinCommand::ReadAloud => MenuAction::ReadAloud(target.selection.filter(|text| !text.trim().is_empty())),
+        BuiltinCommand::PrintSelection => MenuAction::PrintSelection,
+        BuiltinCommand::Share => MenuAction::Share(target.link),
+        BuiltinCommand::SaveImages => MenuAction::SaveImages,
+        BuiltinCommand::Inspect => MenuAction::Inspect,
+    };
+    Ok(action)
+}
+
+/// Selected text for a label, on one line and cut short
+fn shorten(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= LABEL_SELECTION_CHARS {
+        return text;
+    }
+    let mut short: String = text.chars().take(LABEL_SELECTION_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_follow_target_and_extensions_get_their_clicks() {
+        let menu = ContextMenu::new();
+        let mut events = menu.subscribe_events();
+        let ids = |entries: Vec<MenuEntry>| entries.into_iter().map(|entry| entry.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(menu.items_for(&MenuTarget::default())), ["read-aloud", "share", "save-images", "inspect"]);
+        let link = MenuTarget {
+            link: Some("https://example.com/a".to_string()),
+            ..MenuTarget::default()
+        };
+        assert_eq!(ids(menu.items_for(&link)), ["open-link-in-new-tab", "share", "inspect"]);
+
+        let id = menu.contribute("translator", "translate", "Translate “%s”", &[MenuContext::Selection]).unwrap();
+        assert!(menu.contribute("translator", "translate", "Again", &[MenuContext::Page]).is_err());
+        let selection = MenuTarget {
+            selection: Some("  bonjour\n le monde ".to_string()),
+            ..MenuTarget::default()
+        };
+        let entries = menu.open(4, selection.clone());
+        assert_eq!(entries[0].label, "Search the Web for “bonjour le monde”");
+        assert_eq!(entries.last().unwrap().extension.as_deref(), Some("translator"));
+
+        // An item not shown for the target is refused
+        assert!(menu.activate(4, "save-image").is_err());
+        menu.open(4, selection.clone());
+        menu.activate(4, &id).unwrap();
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ContextMenuEvent {
+                tab_id: 4,
+                action: MenuAction::Extension {
+                    extension_id: "translator".to_string(),
+                    item_id: "translate".to_string(),
+                    target: selection,
+                },
+            }
+        );
+        assert!(menu.activate(4, &id).is_err());
+        assert_eq!(menu.withdraw("translator"), 1);
+    }
+}
@@ -0,0 +1,207 @@
+// Navigation Error Pages
+pub mod page;
+
+pub use page::{render_error_page, ErrorPageOptions};
+
+use crate::features::system::proxy::{ProxyErrorKind, ProxyRequestError, ProxyRoute};
+use crate::i18n::{tr, tr_args};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+
+/// How long a failed page gets to answer when diagnosing it
+pub const DIAGNOSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long each well-known host gets to accept a connection when checking
+/// whether the network is up
+pub const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Public resolvers that accept connections from any working network
+const CONNECTIVITY_PROBES: &[&str] = &["1.1.1.1:443", "8.8.8.8:53", "9.9.9.9:443"];
+
+/// Why a page could not be loaded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NavigationErrorKind {
+    /// The host name does not resolve
+    DnsFailure,
+    /// The secure connection could not be set up, e.g. a bad certificate
+    Tls,
+    ConnectionRefused,
+    Timeout,
+    /// The network itself is down
+    Offline,
+    Other,
+}
+
+impl NavigationErrorKind {
+    /// Classify an error message, e.g. a client error with its causes
+    pub fn from_message(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        if has(&["dns error", "failed to lookup address", "name or service not known", "no such host", "name resolution"]) {
+            Self::DnsFailure
+        } else if has(&["certificate", "tls", "ssl", "handshake"]) {
+            Self::Tls
+        } else if has(&["connection refused", "actively refused"]) {
+            Self::ConnectionRefused
+        } else if has(&["timed out", "timeout"]) {
+            Self::Timeout
+        } else if has(&["network is unreachable", "no route to host"]) {
+            Self::Offline
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Error code shown on the page, named like other browsers' codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DnsFailure => "ERR_NAME_NOT_RESOLVED",
+            Self::Tls => "ERR_SSL_PROTOCOL_ERROR",
+            Self::ConnectionRefused => "ERR_CONNECTION_REFUSED",
+            Self::Timeout => "ERR_TIMED_OUT",
+            Self::Offline => "ERR_INTERNET_DISCONNECTED",
+            Self::Other => "ERR_FAILED",
+        }
+    }
+
+    pub fn title(&self) -> String {
+        tr(&format!("error-{}-title", self.message_id()))
+    }
+
+    /// What went wrong, in plain words, for a host
+    pub fn explanation(&self, host: &str) -> String {
+        tr_args(&format!("error-{}-explanation", self.message_id()), &[("host", host)])
+    }
+
+    // Private helper methods
+
+    /// Middle of the IDs of the kind's messages
+    fn message_id(&self) -> &'static str {
+        match self {
+            Self::DnsFailure => "dns",
+            Self::Tls => "tls",
+            Self::ConnectionRefused => "refused",
+            Self::Timeout => "timeout",
+            Self::Offline => "offline",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// A page that failed to load, with what is known about why
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NavigationError {
+    pub url: String,
+    pub kind: NavigationErrorKind,
+    /// Technical detail, e.g. the client error with its causes
+    pub detail: String,
+    /// Proxy the page was loaded through, if any
+    pub proxy: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl NavigationError {
+    /// Error for a request that failed over a route
+    pub fn from_request_error(error: &ProxyRequestError) -> Self {
+        let kind = match error.kind {
+            ProxyErrorKind::Timeout => NavigationErrorKind::Timeout,
+            _ => NavigationErrorKind::from_message(&error.message),
+        };
+        Self {
+            url: error.url.clone(),
+            kind,
+            detail: error.message.clone(),
+            proxy: error.proxy.clone(),
+            occurred_at: Utc::now(),
+        }
+    }
+
+    /// Host of the page, or its URL if it has none
+    pub fn host(&self) -> String {
+        url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| self.url.clone())
+    }
+
+    /// The page over plain HTTP, for HTTPS pages that failed before a
+    /// response came back
+    pub fn http_fallback(&self) -> Option<String> {
+        let mut url = url::Url::parse(&self.url).ok()?;
+        if url.scheme() != "https" || self.kind == NavigationErrorKind::Offline {
+            return None;
+        }
+        url.set_scheme("http").ok()?;
+        Some(url.to_string())
+    }
+}
+
+/// Request a page to find out why it failed to load. Returns `None` if the
+/// server answers at all; an HTTP error status is a page the server sent.
+/// Connection failures on a direct route are reported as offline when no
+/// other host can be reached either.
+pub async fn diagnose(client: &Client, route: &ProxyRoute, url: &str) -> Option<NavigationError> {
+    let error = match client.head(url).timeout(DIAGNOSE_TIMEOUT).send().await {
+        Ok(_) => return None,
+        Err(e) => ProxyRequestError::from_reqwest(url, route, &e),
+    };
+    let mut error = NavigationError::from_request_error(&error);
+    let network_failure = matches!(
+        error.kind,
+        NavigationErrorKind::DnsFailure | NavigationErrorKind::ConnectionRefused | NavigationErrorKind::Timeout
+    );
+    if network_failure && route.proxy().is_none() && !is_online(CONNECTIVITY_TIMEOUT).await {
+        error.kind = NavigationErrorKind::Offline;
+    }
+    Some(error)
+}
+
+/// Whether the network is up: any well-known host accepts a connection
+pub async fn is_online(timeout: Duration) -> bool {
+    let mut probes = JoinSet::new();
+    for address in CONNECTIVITY_PROBES {
+        probes.spawn(tokio::time::timeout(timeout, TcpStream::connect(*address)));
+    }
+    while let Some(result) = probes.join_next().await {
+        if matches!(result, Ok(Ok(Ok(_)))) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_navigation_errors() {
+        let cases = [
+            ("error sending request: dns error: failed to lookup address information", NavigationErrorKind::DnsFailure),
+            ("invalid peer certificate: Expired", NavigationErrorKind::Tls),
+            ("tcp connect error: Connection refused (os error 111)", NavigationErrorKind::ConnectionRefused),
+            ("operation timed out", NavigationErrorKind::Timeout),
+            ("Network is unreachable (os error 101)", NavigationErrorKind::Offline),
+            ("unexpected end of file", NavigationErrorKind::Other),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(NavigationErrorKind::from_message(message), kind, "{}", message);
+        }
+
+        let error = NavigationError {
+            url: "https://example.com:443/a?b=1".to_string(),
+            kind: NavigationErrorKind::Tls,
+            detail: String::new(),
+            proxy: None,
+            occurred_at: Utc::now(),
+        };
+        assert_eq!(error.host(), "example.com");
+        assert_eq!(error.http_fallback().as_deref(), Some("http://example.com/a?b=1"));
+        let offline = NavigationError { kind: NavigationErrorKind::Offline, ..error };
+        assert_eq!(offline.http_fallback(), None);
+    }
+}
@@ -0,0 +1,163 @@
+// Navigation Error Page
+use super::{NavigationError, NavigationErrorKind};
+use crate::features::caching::offline_storage::OfflinePageInfo;
+use crate::i18n::{localizer, tr, tr_args};
+use crate::utils::escape_html;
+use chrono::{DateTime, Utc};
+
+/// Most saved pages suggested while offline
+const MAX_OFFLINE_SUGGESTIONS: usize = 5;
+
+/// What an error page can offer besides retrying
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPageOptions {
+    /// When the failed page was saved for offline reading, if it was
+    pub cached_copy: Option<DateTime<Utc>>,
+    /// Saved pages to suggest instead, most recent first; only shown offline
+    pub offline_pages: Vec<OfflinePageInfo>,
+}
+
+/// Render the page shown in place of one that failed to load, with the
+/// error code, an explanation and diagnostics. Retrying reloads the page;
+/// the cached copy and saved pages send `type: 'errorpage'` IPC messages
+/// with an `action` of `cached` and the page's `url`.
+pub fn render_error_page(error: &NavigationError, options: &ErrorPageOptions) -> String {
+    let mut actions = vec![format!(r#"<button id="retry">{}</button>"#, escape_html(&tr("error-try-again")))];
+    if let Some(http) = error.http_fallback() {
+        actions.push(format!(
+            r#"<a class="button" href="{}">{}</a>"#,
+            escape_html(&http),
+            escape_html(&tr("error-try-http"))
+        ));
+    }
+    if let Some(saved_at) = options.cached_copy {
+        actions.push(format!(
+            r#"<button data-cached="{}">{}</button> <span class="meta">{}</span>"#,
+            escape_html(&error.url),
+            escape_html(&tr("error-open-cached")),
+            escape_html(&tr_args("error-saved-at", &[("time", &saved_at.format("%Y-%m-%d %H:%M").to_string())]))
+        ));
+    }
+
+    let suggestions = if error.kind == NavigationErrorKind::Offline && !options.offline_pages.is_empty() {
+        let mut pages = options.offline_pages.clone();
+        pages.sort_by_key(|page| std::cmp::Reverse(page.saved_at));
+        let list: String = pages
+            .iter()
+            .take(MAX_OFFLINE_SUGGESTIONS)
+            .map(|page| {
+                format!(
+                    r##"<li><a href="#" data-cached="{url}">{title}</a> <span class="meta">{url}</span></li>"##,
+                    url = escape_html(&page.url),
+                    title = escape_html(&page.title),
+                )
+            })
+            .collect();
+        format!("<h2>{}</h2><ul>{}</ul>", escape_html(&tr("error-saved-pages")), list)
+    } else {
+        String::new()
+    };
+
+    let mut diagnostics = vec![
+        ("error-code", error.kind.code().to_string()),
+        ("error-address", error.url.clone()),
+        ("error-connection", error.proxy.clone().unwrap_or_else(|| tr("error-direct"))),
+        ("error-time", error.occurred_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+    ];
+    if !error.detail.is_empty() {
+        diagnostics.push(("error-details", error.detail.clone()));
+    }
+    let diagnostics: String = diagnostics
+        .iter()
+        .map(|(name, value)| format!("<dt>{}</dt><dd>{}</dd>", escape_html(&tr(name)), escape_html(value)))
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>{css}</style>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>{explanation}</p>
+    <p class="code">{code}</p>
+    <p class="actions">{actions}</p>
+    {suggestions}
+    <details><summary>{diagnostics_title}</summary><dl>{diagnostics}</dl></details>
+    <script>{script}</script>
+</body>
+</html>"#,
+        lang = localizer().locale(),
+        title = escape_html(&error.kind.title()),
+        css = PAGE_CSS,
+        explanation = escape_html(&error.kind.explanation(&error.host())),
+        code = error.kind.code(),
+        actions = actions.join(" "),
+        suggestions = suggestions,
+        diagnostics_title = escape_html(&tr("error-diagnostics")),
+        diagnostics = diagnostics,
+        script = PAGE_SCRIPT,
+    )
+}
+
+const PAGE_CSS: &str = r#"
+body { font-family: system-ui, sans-serif; max-width: 640px; margin: 12vh auto 2em; padding: 0 1em; color: #222; }
+h1 { font-weight: 500; }
+h2 { font-size: 1em; margin-top: 2em; }
+.code { color: #888; font-family: monospace; }
+.actions button, .actions .button { font: inherit; padding: 0.4em 1em; margin-right: 0.4em; }
+.actions .button { border: 1px solid #aaa; border-radius: 3px; color: inherit; text-decoration: none; }
+.meta { color: #888; font-size: 0.85em; }
+ul { padding-left: 1.2em; }
+details { margin-top: 2em; color: #555; }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.3em 1em; font-size: 0.9em; }
+dt { font-weight: 600; }
+dd { margin: 0; word-break: break-all; }
+"#;
+
+const PAGE_SCRIPT: &str = r#"
+document.getElementById('retry').addEventListener('click', () => location.reload());
+document.addEventListener('click', (e) => {
+    const cached = e.target.closest('[data-cached]');
+    if (!cached) return;
+    e.preventDefault();
+    window.ipc.send({ type: 'errorpage', action: 'cached', url: cached.dataset.cached });
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_page_offers_fallbacks() {
+        let error = NavigationError {
+            url: "https://example.com/".to_string(),
+            kind: NavigationErrorKind::ConnectionRefused,
+            detail: "tcp connect error: Connection refused".to_string(),
+            proxy: None,
+            occurred_at: Utc::now(),
+        };
+        let page = render_error_page(&error, &ErrorPageOptions::default());
+        assert!(page.contains("ERR_CONNECTION_REFUSED"));
+        assert!(page.contains(r#"href="http://example.com/""#));
+        assert!(!page.contains("Open Cached Copy"));
+
+        let saved = OfflinePageInfo {
+            url: "https://docs.rs/".to_string(),
+            title: "Docs".to_string(),
+            saved_at: Utc::now(),
+            resource_count: 0,
+            total_size: 0,
+        };
+        let options = ErrorPageOptions { cached_copy: Some(Utc::now()), offline_pages: vec![saved] };
+        let offline = NavigationError { kind: NavigationErrorKind::Offline, ..error };
+        let page = render_error_page(&offline, &options);
+        assert!(page.contains("Open Cached Copy"));
+        assert!(page.contains(r#"data-cached="https://docs.rs/""#));
+        assert!(!page.contains("Try HTTP"));
+    }
+}
@@ -3,8 +3,14 @@ pub mod themes;
 pub mod reader;
 pub mod search;
 pub mod spell_checker;
+pub mod error_pages;
+pub mod context_menu;
+pub mod zoom;
 
 pub use themes::ThemeManager;
-pub use reader::ReaderMode;
-pub use search::SearchEngine;
-pub use spell_checker::SpellChecker;
\ No newline at end of file
+pub use reader::ReadingMode;
+pub use search::FindInPage;
+pub use spell_checker::SpellChecker;
+pub use error_pages::{NavigationError, NavigationErrorKind};
+pub use context_menu::{ContextMenu, ContextMenuEvent, MenuAction};
+pub use zoom::{PageZoom, SiteZoom, ZoomAction, ZoomMode};
\ No newline at end of file
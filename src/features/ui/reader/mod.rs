@@ -1,13 +1,750 @@
-// Reader Mode Module - Placeholder
-// TODO: Implement reading mode functionality
-pub struct ReaderMode;
+// Reading Mode for Articles
+use crate::utils::escape_html;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
 
-impl ReaderMode {
-    pub fn new() -> Self {
-        Self
+/// Elements read as one block, e.g. one paragraph, in reading order
+const BLOCK_SELECTOR: &str = "h1, h2, h3, h4, h5, h6, p, li, blockquote, pre, figcaption, dt, dd";
+
+/// Elements an article keeps. Others are unwrapped to their content, or
+/// dropped with it when listed in `DROPPED_TAGS`.
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "caption", "cite", "code", "dd", "del", "dfn", "div", "dl", "dt", "em",
+    "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li", "mark", "ol", "p",
+    "pre", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th", "thead",
+    "time", "tr", "u", "ul",
+];
+/// Elements dropped along with everything inside them
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "frame", "frameset", "object", "embed", "svg", "math", "form",
+    "button", "input", "select", "textarea", "link", "meta", "base", "head", "title",
+];
+/// Elements without content or end tag
+const VOID_TAGS: &[&str] = &["br", "hr", "img"];
+/// Attributes an article keeps; event handlers and styles never are
+const ALLOWED_ATTRIBUTES: &[&str] = &["href", "src", "alt", "title", "datetime", "colspan", "rowspan", "cite"];
+
+/// Reading mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingModeConfig {
+    pub font_family: String,
+    pub font_size: u32,
+    pub line_height: f32,
+    pub text_color: String,
+    pub background_color: String,
+    pub width: u32,
+    pub margin: u32,
+}
+
+impl Default for ReadingModeConfig {
+    fn default() -> Self {
+        Self {
+            font_family: "Georgia, serif".to_string(),
+            font_size: 18,
+            line_height: 1.6,
+            text_color: "#333333".to_string(),
+            background_color: "#ffffff".to_string(),
+            width: 800,
+            margin: 40,
+        }
+    }
+}
+
+/// Article content extracted by reading mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArticleContent {
+    pub title: String,
+    pub author: Option<String>,
+    pub publish_date: Option<String>,
+    pub content: String,
+    pub excerpt: String,
+    pub image: Option<String>,
+}
+
+/// A sentence or heading of an article, in reading order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReaderToken {
+    /// Block of the reader body the text is in, `None` for the title
+    pub block: Option<usize>,
+    pub text: String,
+    /// Where the text starts and ends in the block's text, in UTF-16 units
+    /// like the page counts them
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Reading mode extractor for web pages
+pub struct ReadingMode {
+    config: ReadingModeConfig,
+}
+
+impl ReadingMode {
+    /// Create a new reading mode extractor
+    pub fn new(config: Option<ReadingModeConfig>) -> Self {
+        Self {
+            config: config.unwrap_or_default(),
+        }
+    }
+
+    /// Extract article content from HTML
+    pub fn extract_article(&self, html: &str, url: &str) -> Option<ArticleContent> {
+        let document = Html::parse_document(html);
+        
+        // Try to find the main content
+        let content = self.find_main_content(&document)?;
+        
+        // Extract metadata
+        let title = self.extract_title(&document, url);
+        let author = self.extract_author(&document);
+        let publish_date = self.extract_publish_date(&document);
+        let image = self.extract_image(&document, url);
+        let excerpt = self.generate_excerpt(&content);
+        
+        Some(ArticleContent {
+            title,
+            author,
+            publish_date,
+            content,
+            excerpt,
+            image,
+        })
+    }
+
+    /// Generate reading-friendly HTML. The article is sanitized again here,
+    /// as it may have been saved before it would have been on extraction.
+    pub fn generate_reader_html(&self, article: &ArticleContent) -> String {
+        let css = self.generate_css();
+        
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title}</title>
+    <style>{css}</style>
+</head>
+<body>
+    <article class="reader-content">
+        <header class="reader-header">
+            <h1>{title}</h1>
+            {metadata}
+        </header>
+        <div class="reader-body">
+            {content}
+        </div>
+    </article>
+</body>
+</html>"#,
+            title = escape_html(&article.title),
+            metadata = self.format_metadata(article),
+            content = sanitize_html(&article.content),
+            css = css
+        )
+    }
+
+    /// Split an article into the sentences and headings of the reader view,
+    /// in the order they are read aloud
+    pub fn tokens(&self, article: &ArticleContent) -> Vec<ReaderToken> {
+        let mut tokens = vec![ReaderToken {
+            block: None,
+            text: article.title.clone(),
+            start: 0,
+            end: article.title.encode_utf16().count(),
+        }];
+
+        let fragment = Html::parse_fragment(&article.content);
+        for (block, text) in text_blocks(&fragment).into_iter().enumerate() {
+            for (start, end) in split_sentences(&text) {
+                tokens.push(ReaderToken {
+                    block: Some(block),
+                    text: text[start..end].to_string(),
+                    start: text[..start].encode_utf16().count(),
+                    end: text[..end].encode_utf16().count(),
+                });
+            }
+        }
+        tokens
+    }
+
+    /// Split plain text, such as a selection, into sentences to read aloud.
+    /// They belong to no block of the reader view.
+    pub fn text_tokens(&self, text: &str) -> Vec<ReaderToken> {
+        split_sentences(text)
+            .into_iter()
+            .map(|(start, end)| ReaderToken {
+                block: None,
+                text: text[start..end].to_string(),
+                start: text[..start].encode_utf16().count(),
+                end: text[..end].encode_utf16().count(),
+            })
+            .collect()
+    }
+
+    /// Script for the reader view that highlights a token and scrolls it
+    /// into view, or clears the highlight with `None`
+    pub fn highlight_script(&self, token: Option<&ReaderToken>) -> String {
+        let token = serde_json::to_string(&token).unwrap_or_else(|_| "null".to_string());
+        format!(
+            r#"
+(function() {{
+    const token = {token};
+    if (!window.CSS || !CSS.highlights) return;
+    CSS.highlights.delete('webx-read-aloud');
+    if (!token) return;
+
+    const blocks = Array.from(document.querySelectorAll('.reader-body :is({blocks})'))
+        .filter((el) => !el.parentElement.closest('.reader-body :is({blocks})'));
+    const block = token.block === null ? document.querySelector('.reader-header h1') : blocks[token.block];
+    if (!block) return;
+
+    const range = document.createRange();
+    const walker = document.createTreeWalker(block, NodeFilter.SHOW_TEXT);
+    let offset = 0;
+    let started = false;
+    while (walker.nextNode()) {{
+        const node = walker.currentNode;
+        const length = node.nodeValue.length;
+        if (!started && token.start <= offset + length) {{
+            range.setStart(node, token.start - offset);
+            started = true;
+        }}
+        if (started && token.end <= offset + length) {{
+            range.setEnd(node, token.end - offset);
+            break;
+        }}
+        offset += length;
+    }}
+    CSS.highlights.set('webx-read-aloud', new Highlight(range));
+    block.scrollIntoView({{ block: 'center', behavior: 'smooth' }});
+}})();
+"#,
+            token = token,
+            blocks = BLOCK_SELECTOR,
+        )
+    }
+
+    /// Update configuration
+    pub fn set_config(&mut self, config: ReadingModeConfig) {
+        self.config = config;
+    }
+
+    /// Get current configuration
+    pub fn get_config(&self) -> &ReadingModeConfig {
+        &self.config
+    }
+
+    // Private helper methods
+    
+    fn find_main_content(&self, document: &Html) -> Option<String> {
+        // Try common selectors for article content
+        let selectors = vec![
+            "article",
+            ".article-content",
+            ".post-content",
+            ".entry-content",
+            ".content",
+            "[role='main']",
+            ".main-content",
+            "#main-content",
+            ".story-body",
+            ".article-body",
+        ];
+        
+        for selector_str in selectors {
+            let selector = Selector::parse(selector_str).ok()?;
+            if let Some(element) = document.select(&selector).next() {
+                let content = element.inner_html();
+                if self.is_content_sufficient(&content) {
+                    return Some(self.clean_content(&content));
+                }
+            }
+        }
+        
+        // Fallback: look for paragraphs, then take a short article as it is
+        self.extract_from_paragraphs(document).or_else(|| {
+            let selector = Selector::parse("article").ok()?;
+            let content = document.select(&selector).next()?.inner_html();
+            (!content.trim().is_empty()).then(|| self.clean_content(&content))
+        })
+    }
+    
+    fn extract_title(&self, document: &Html, url: &str) -> String {
+        // Try various title selectors
+        // Headings first; the document title often carries the site name
+        let title_selectors = vec![
+            ".entry-title",
+            ".post-title",
+            ".article-title",
+            "h1",
+            "title",
+        ];
+        
+        for selector_str in title_selectors {
+            let selector = match Selector::parse(selector_str) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            
+            if let Some(element) = document.select(&selector).next() {
+                let title = element.text().collect::<Vec<_>>().join(" ").trim().to_string();
+                if !title.is_empty() && title.len() < 200 {
+                    return title;
+                }
+            }
+        }
+        
+        // Fallback to URL
+        self.title_from_url(url)
+    }
+    
+    fn extract_author(&self, document: &Html) -> Option<String> {
+        let author_selectors = vec![
+            "[rel='author']",
+            ".author",
+            ".byline",
+            ".post-author",
+            "[class*='author']",
+        ];
+        
+        for selector_str in author_selectors {
+            let selector = Selector::parse(selector_str).ok()?;
+            if let Some(element) = document.select(&selector).next() {
+                let author = element.text().collect::<Vec<_>>().join(" ").trim().to_string();
+                if !author.is_empty() && author.len() < 100 {
+                    return Some(author);
+                }
+            }
+        }
+        
+        None
+    }
+    
+    fn extract_publish_date(&self, document: &Html) -> Option<String> {
+        let date_selectors = vec![
+            "time[datetime]",
+            ".publish-date",
+            ".post-date",
+            "[class*='date']",
+        ];
+        
+        for selector_str in date_selectors {
+            let selector = Selector::parse(selector_str).ok()?;
+            if let Some(element) = document.select(&selector).next() {
+                if let Some(datetime) = element.value().attr("datetime") {
+                    return Some(datetime.to_string());
+                }
+                
+                let date_text = element.text().collect::<Vec<_>>().join(" ").trim().to_string();
+                if !date_text.is_empty() {
+                    return Some(date_text);
+                }
+            }
+        }
+        
+        None
+    }
+    
+    fn extract_image(&self, document: &Html, base_url: &str) -> Option<String> {
+        // Look for featured/open graph images
+        let img_selectors = vec![
+            "meta[property='og:image']",
+            "meta[name='twitter:image']",
+            ".featured-image img",
+            "article img:first-of-type",
+        ];
+        
+        for selector_str in img_selectors {
+            let selector = Selector::parse(selector_str).ok()?;
+            if let Some(element) = document.select(&selector).next() {
+                if let Some(img_url) = element.value().attr("content").or(element.value().attr("src")) {
+                    return Some(self.resolve_url(img_url, base_url));
+                }
+            }
+        }
+        
+        None
+    }
+    
+    fn extract_from_paragraphs(&self, document: &Html) -> Option<String> {
+        let p_selector = Selector::parse("p").ok()?;
+        let paragraphs: Vec<String> = document
+            .select(&p_selector)
+            .map(|p| p.inner_html())
+            .filter(|content| content.len() > 50)
+            .take(20)
+            .collect();
+        
+        if paragraphs.len() >= 3 {
+            Some(paragraphs.join("\n\n"))
+        } else {
+            None
+        }
+    }
+    
+    fn is_content_sufficient(&self, content: &str) -> bool {
+        // Basic heuristics to determine if content is substantial
+        let text_length = content.chars().count();
+        let paragraph_count = content.matches("<p").count();
+        let word_count = content.split_whitespace().count();
+        
+        text_length > 500 && paragraph_count >= 2 && word_count > 100
+    }
+    
+    fn clean_content(&self, content: &str) -> String {
+        // Remove common unwanted elements
+        let unwanted_selectors = vec![
+            "script",
+            "style",
+            "noscript",
+            ".ad",
+            "[class*='advertisement']",
+            "[id*='ad-']",
+            ".social-share",
+            ".comments",
+        ];
+        
+        let mut cleaned = content.to_string();
+        
+        // This is a simplified cleaning - in practice you'd use a proper HTML parser
+        for selector in unwanted_selectors {
+            // Remove elements matching selector (simplified)
+            let pattern = format!(r"<{}[^>]*>.*?</{}>", selector, selector);
+            if let Ok(re) = regex::Regex::new(&pattern) {
+                cleaned = re.replace_all(&cleaned, "").to_string();
+            }
+        }
+        
+        sanitize_html(&cleaned)
     }
     
-    pub fn extract_content(&self, _html: &str) -> String {
-        String::new() // Placeholder implementation
+    fn generate_excerpt(&self, content: &str) -> String {
+        // Extract first few sentences as excerpt
+        let text_only = scraper::Html::parse_fragment(content)
+            .root_element()
+            .text()
+            .collect::<Vec<_>>()
+            .join(" ");
+        
+        let sentences: Vec<&str> = text_only.split(['.', '!', '?'])
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .take(2)
+            .collect();
+        
+        if sentences.is_empty() {
+            text_only.chars().take(200).collect()
+        } else {
+            sentences.join(". ") + "."
+        }
+    }
+    
+    fn title_from_url(&self, url: &str) -> String {
+        // Extract domain and path to create a title
+        if let Ok(parsed) = url::Url::parse(url) {
+            if let Some(host) = parsed.host_str() {
+                let domain = host.replace("www.", "");
+                return format!("Article from {}", domain);
+            }
+        }
+        "Web Article".to_string()
+    }
+    
+    fn format_metadata(&self, article: &ArticleContent) -> String {
+        let mut metadata = String::new();
+        
+        if let Some(author) = &article.author {
+            metadata.push_str(&format!("<p class=\"reader-author\">By {}</p>", escape_html(author)));
+        }
+        
+        if let Some(date) = &article.publish_date {
+            metadata.push_str(&format!("<p class=\"reader-date\">Published: {}</p>", escape_html(date)));
+        }
+        
+        metadata
+    }
+    
+    fn generate_css(&self) -> String {
+        format!(
+            r#"
+body {{
+    font-family: {};
+    font-size: {}px;
+    line-height: {};
+    color: {};
+    background-color: {};
+    margin: 0;
+    padding: 20px;
+}}
+
+.reader-content {{
+    max-width: {}px;
+    margin: 0 auto;
+    padding: {}px;
+}}
+
+.reader-header h1 {{
+    font-size: 2em;
+    margin-bottom: 20px;
+    line-height: 1.2;
+}}
+
+.reader-author, .reader-date {{
+    color: #666;
+    font-size: 0.9em;
+    margin: 5px 0;
+}}
+
+.reader-body {{
+    margin-top: 30px;
+}}
+
+::highlight(webx-read-aloud) {{
+    background-color: #fde68a;
+    color: #111111;
+}}
+
+.reader-body p {{
+    margin-bottom: 1.2em;
+    text-align: justify;
+}}
+
+img {{
+    max-width: 100%;
+    height: auto;
+    display: block;
+    margin: 20px auto;
+}}
+
+@media (max-width: 768px) {{
+    .reader-content {{
+        padding: 10px;
+    }}
+    
+    body {{
+        font-size: {}px;
+    }}
+}}
+"#,
+            self.config.font_family,
+            self.config.font_size,
+            self.config.line_height,
+            self.config.text_color,
+            self.config.background_color,
+            self.config.width,
+            self.config.margin,
+            (self.config.font_size as f32 * 0.9) as u32
+        )
+    }
+    
+    fn resolve_url(&self, url: &str, base_url: &str) -> String {
+        if url.starts_with("http") {
+            url.to_string()
+        } else if let Ok(base) = url::Url::parse(base_url) {
+            if let Ok(resolved) = base.join(url) {
+                resolved.to_string()
+            } else {
+                url.to_string()
+            }
+        } else {
+            url.to_string()
+        }
+    }
+}
+
+/// Rebuild article HTML from allowed elements and attributes only, so a
+/// saved page cannot run script wherever the browser shows it
+pub fn sanitize_html(content: &str) -> String {
+    let fragment = Html::parse_fragment(content);
+    let mut sanitized = String::new();
+    write_sanitized(fragment.root_element(), false, &mut sanitized);
+    sanitized
+}
+
+// Private helper functions
+
+/// Write an element's children, and the element itself when `keep` is set
+/// and it is allowed
+fn write_sanitized(element: ElementRef, keep: bool, out: &mut String) {
+    let name = element.value().name();
+    let allowed = keep && ALLOWED_TAGS.contains(&name);
+    if allowed {
+        out.push('<');
+        out.push_str(name);
+        for (attribute, value) in element.value().attrs() {
+            if allowed_attribute(attribute, value) {
+                out.push_str(&format!(r#" {}="{}""#, attribute, escape_html(value)));
+            }
+        }
+        out.push('>');
+        if VOID_TAGS.contains(&name) {
+            return;
+        }
+    }
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            out.push_str(&escape_html(text));
+        } else if let Some(child) = ElementRef::wrap(child) {
+            if !DROPPED_TAGS.contains(&child.value().name()) {
+                write_sanitized(child, true, out);
+            }
+        }
+    }
+    if allowed {
+        out.push_str(&format!("</{}>", name));
+    }
+}
+
+/// Whether an article may keep an attribute; links and images only lead
+/// to web pages, or relative to the article
+fn allowed_attribute(attribute: &str, value: &str) -> bool {
+    if !ALLOWED_ATTRIBUTES.contains(&attribute) {
+        return false;
+    }
+    if !matches!(attribute, "href" | "src" | "cite") {
+        return true;
+    }
+    match url::Url::parse(value.trim()) {
+        Ok(url) => matches!(url.scheme(), "http" | "https"),
+        Err(_) => !value.trim_start().starts_with("//") && !value.contains(':'),
+    }
+}
+
+/// Text of the outermost blocks of an article, in document order
+fn text_blocks(fragment: &Html) -> Vec<String> {
+    let Ok(selector) = Selector::parse(BLOCK_SELECTOR) else {
+        return Vec::new();
+    };
+    let matched: Vec<ElementRef> = fragment.select(&selector).collect();
+    matched
+        .iter()
+        .filter(|element| {
+            !element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| matched.contains(&ancestor))
+        })
+        .map(|element| element.text().collect::<String>())
+        .collect()
+}
+
+/// Byte ranges of the sentences in a text, without surrounding whitespace
+fn split_sentences(text: &str) -> Vec<(usize, usize)> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends_sentence = matches!(c, '.' | '!' | '?' | '…' | '。')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if ends_sentence || chars.peek().is_none() {
+            let end = i + c.len_utf8();
+            let sentence = &text[start..end];
+            let trimmed = sentence.trim_start();
+            let sentence_start = start + sentence.len() - trimmed.len();
+            let sentence_end = sentence_start + trimmed.trim_end().len();
+            if sentence_end > sentence_start {
+                sentences.push((sentence_start, sentence_end));
+            }
+            start = end;
+        }
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_mode_extraction() {
+        let html = r#"
+        <!DOCTYPE html>
+        <html>
+        <head><title>Test Article</title></head>
+        <body>
+            <article>
+                <h1>Test Article Title</h1>
+                <p class="author">John Doe</p>
+                <p>First paragraph of content...</p>
+                <p>Second paragraph with more content...</p>
+                <p>Third paragraph concluding the article.</p>
+            </article>
+        </body>
+        </html>
+        "#;
+        
+        let reading_mode = ReadingMode::new(None);
+        let article = reading_mode.extract_article(html, "https://example.com/article");
+        
+        assert!(article.is_some());
+        let article = article.unwrap();
+        assert_eq!(article.title, "Test Article Title");
+        assert_eq!(article.author, Some("John Doe".to_string()));
+        assert!(!article.content.is_empty());
+    }
+
+    #[test]
+    fn test_tokens_split_blocks_into_sentences() {
+        let reading_mode = ReadingMode::new(None);
+        let article = ArticleContent {
+            title: "Título".to_string(),
+            author: None,
+            publish_date: None,
+            content: "<h2>Intro</h2><p>Héllo there. How are you?</p><ul><li><p>One item.</p></li></ul>".to_string(),
+            excerpt: String::new(),
+            image: None,
+        };
+
+        let tokens = reading_mode.tokens(&article);
+        let texts: Vec<_> = tokens.iter().map(|t| (t.block, t.text.as_str())).collect();
+        assert_eq!(
+            texts,
+            vec![
+                (None, "Título"),
+                (Some(0), "Intro"),
+                (Some(1), "Héllo there."),
+                (Some(1), "How are you?"),
+                (Some(2), "One item."),
+            ]
+        );
+        assert_eq!((tokens[3].start, tokens[3].end), (13, 25));
+    }
+
+    #[test]
+    fn test_sanitize_html_keeps_text_and_drops_script() {
+        let content = "<p onclick=\"steal()\">Hello <b>world</b>\n<script>\nwindow.ipc.send({type: 'readinglist'})\n</script></p>\
+            <img src=\"x\" onerror=\"steal()\"><a href=\"javascript:steal()\">link</a>\
+            <a href=\"https://example.com/?a=1&b=2\">ok</a><iframe src=\"https://evil.example\"></iframe>";
+        assert_eq!(
+            sanitize_html(content),
+            "<p>Hello <b>world</b>\n</p><img src=\"x\"><a>link</a><a href=\"https://example.com/?a=1&amp;b=2\">ok</a>"
+        );
+
+        let reading_mode = ReadingMode::new(None);
+        let article = ArticleContent {
+            title: "</title><script>steal()</script>".to_string(),
+            author: Some("<img src=x onerror=steal()>".to_string()),
+            publish_date: None,
+            content: content.to_string(),
+            excerpt: String::new(),
+            image: None,
+        };
+        let html = reading_mode.generate_reader_html(&article);
+        assert!(!html.contains("<script") && !html.contains("onerror") && !html.contains("onclick"));
+    }
+
+    #[test]
+    fn test_reading_mode_config() {
+        let config = ReadingModeConfig {
+            font_size: 20,
+            background_color: "#f5f5f5".to_string(),
+            ..ReadingModeConfig::default()
+        };
+
+        let reading_mode = ReadingMode::new(Some(config));
+        let current_config = reading_mode.get_config();
+        
+        assert_eq!(current_config.font_size, 20);
+        assert_eq!(current_config.background_color, "#f5f5f5");
     }
 }
\ No newline at end of file
@@ -1,13 +1,790 @@
-// Search Module - Placeholder
-// TODO: Implement search functionality
-pub struct SearchEngine;
+// Find in Page Feature
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-impl SearchEngine {
-    pub fn new() -> Self {
-        Self
+/// Find sessions a tab keeps; starting another ends its oldest
+const MAX_TAB_SESSIONS: usize = 8;
+
+/// Find options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    pub whole_words: bool,
+    pub regex: bool,
+    pub highlight_all: bool,
+}
+
+impl Default for FindOptions {
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            whole_words: false,
+            regex: false,
+            highlight_all: true,
+        }
+    }
+}
+
+/// Find result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindResult {
+    pub index: usize,
+    pub text: String,
+    pub position: (usize, usize), // (start, end) character positions
+    pub dom_position: (usize, usize), // (start, end) UTF-16 offsets into the page's text nodes
+    pub page_number: Option<u32>,
+}
+
+/// Find session
+#[derive(Debug, Clone)]
+pub struct FindSession {
+    pub query: String,
+    pub options: FindOptions,
+    pub results: Vec<FindResult>,
+    pub current_index: usize,
+    pub content_hash: u64, // To detect content changes
+    pub highlighted: bool, // Whether highlight marks were injected into the page
+    pub started: Instant,
+}
+
+/// Find in page manager. Sessions are named by the page, so they are kept
+/// per tab.
+pub struct FindInPage {
+    sessions: Arc<Mutex<HashMap<usize, HashMap<String, FindSession>>>>,
+    max_results: usize,
+}
+
+impl FindInPage {
+    /// Create a new find in page manager
+    pub fn new(max_results: Option<usize>) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            max_results: max_results.unwrap_or(1000),
+        }
+    }
+
+    /// Start a new find session
+    pub fn start_find(
+        &self,
+        tab_id: usize,
+        session_id: &str,
+        content: &str,
+        query: &str,
+        options: FindOptions,
+    ) -> Vec<FindResult> {
+        let results = self.perform_find(content, query, &options);
+        let content_hash = self.hash_content(content);
+        
+        let session = FindSession {
+            query: query.to_string(),
+            options,
+            results: results.clone(),
+            current_index: 0,
+            content_hash,
+            highlighted: false,
+            started: Instant::now(),
+        };
+        
+        {
+            let mut sessions = self.sessions.lock_or_recover();
+            let tab = sessions.entry(tab_id).or_default();
+            // The page names its sessions, so it may not keep opening new ones
+            if tab.len() >= MAX_TAB_SESSIONS && !tab.contains_key(session_id) {
+                let oldest = tab.iter().min_by_key(|(_, session)| session.started).map(|(id, _)| id.clone());
+                if let Some(oldest) = oldest {
+                    tab.remove(&oldest);
+                }
+            }
+            tab.insert(session_id.to_string(), session);
+        }
+        
+        results
+    }
+
+    /// Find next occurrence
+    pub fn find_next(&self, tab_id: usize, session_id: &str) -> Option<FindResult> {
+        let mut sessions = self.sessions.lock_or_recover();
+        if let Some(session) = session_mut(&mut sessions, tab_id, session_id) {
+            if !session.results.is_empty() {
+                session.current_index = (session.current_index + 1) % session.results.len();
+                Some(session.results[session.current_index].clone())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Find previous occurrence
+    pub fn find_previous(&self, tab_id: usize, session_id: &str) -> Option<FindResult> {
+        let mut sessions = self.sessions.lock_or_recover();
+        if let Some(session) = session_mut(&mut sessions, tab_id, session_id) {
+            if !session.results.is_empty() {
+                if session.current_index == 0 {
+                    session.current_index = session.results.len() - 1;
+                } else {
+                    session.current_index -= 1;
+                }
+                Some(session.results[session.current_index].clone())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Jump to specific result
+    pub fn jump_to_result(&self, tab_id: usize, session_id: &str, index: usize) -> Option<FindResult> {
+        let mut sessions = self.sessions.lock_or_recover();
+        if let Some(session) = session_mut(&mut sessions, tab_id, session_id) {
+            if index < session.results.len() {
+                session.current_index = index;
+                Some(session.results[index].clone())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Update find session with new content
+    pub fn update_content(&self, tab_id: usize, session_id: &str, new_content: &str) -> Option<Vec<FindResult>> {
+        let mut sessions = self.sessions.lock_or_recover();
+        if let Some(session) = session_mut(&mut sessions, tab_id, session_id) {
+            let new_hash = self.hash_content(new_content);
+            
+            // Only re-find if content actually changed
+            if new_hash != session.content_hash {
+                let results = self.perform_find(new_content, &session.query, &session.options);
+                session.results = results.clone();
+                session.content_hash = new_hash;
+                session.current_index = 0;
+                Some(results)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// End find session
+    pub fn end_find(&self, tab_id: usize, session_id: &str) -> bool {
+        let mut sessions = self.sessions.lock_or_recover();
+        sessions.get_mut(&tab_id).and_then(|tab| tab.remove(session_id)).is_some()
+    }
+
+    /// End find session, returning the script that removes its highlights from the page
+    pub fn end_find_with_cleanup(&self, tab_id: usize, session_id: &str) -> Option<String> {
+        let mut sessions = self.sessions.lock_or_recover();
+        match sessions.get_mut(&tab_id).and_then(|tab| tab.remove(session_id)) {
+            Some(session) if session.highlighted => Some(self.get_cleanup_script()),
+            _ => None,
+        }
+    }
+
+    /// Generate JavaScript that marks the session's matches in the page and
+    /// scrolls the current match into view
+    pub fn get_highlight_script(&self, tab_id: usize, session_id: &str) -> Option<String> {
+        let mut sessions = self.sessions.lock_or_recover();
+        let session = session_mut(&mut sessions, tab_id, session_id)?;
+        session.highlighted = true;
+
+        let ranges: Vec<String> = session
+            .results
+            .iter()
+            .map(|r| format!("[{},{}]", r.dom_position.0, r.dom_position.1))
+            .collect();
+
+        Some(format!(
+            "{}\nwindow.webxFindMarks.apply([{}], {}, {});\n",
+            self.get_marks_runtime(),
+            ranges.join(","),
+            session.current_index,
+            session.options.highlight_all
+        ))
+    }
+
+    /// Generate JavaScript that moves the current-match highlight after
+    /// find_next, find_previous or jump_to_result
+    pub fn get_current_match_script(&self, tab_id: usize, session_id: &str) -> Option<String> {
+        let highlight_all = {
+            let sessions = self.sessions.lock_or_recover();
+            let session = sessions.get(&tab_id).and_then(|tab| tab.get(session_id))?;
+            if !session.highlighted {
+                return None;
+            }
+            session.options.highlight_all
+        };
+
+        if highlight_all {
+            let sessions = self.sessions.lock_or_recover();
+            let session = sessions.get(&tab_id).and_then(|tab| tab.get(session_id))?;
+            Some(format!(
+                "{}\nwindow.webxFindMarks.setCurrent({});\n",
+                self.get_marks_runtime(),
+                session.current_index
+            ))
+        } else {
+            // Only the current match is marked, so the marks must be rebuilt
+            self.get_highlight_script(tab_id, session_id)
+        }
+    }
+
+    /// Generate JavaScript that removes all find highlights from the page
+    pub fn get_cleanup_script(&self) -> String {
+        format!("{}\nwindow.webxFindMarks.clear();\n", self.get_marks_runtime())
+    }
+
+    /// Get current session info
+    pub fn get_session_info(&self, tab_id: usize, session_id: &str) -> Option<(usize, usize)> {
+        let sessions = self.sessions.lock_or_recover();
+        sessions
+            .get(&tab_id)
+            .and_then(|tab| tab.get(session_id))
+            .map(|session| (session.current_index, session.results.len()))
+    }
+
+    /// Forget a tab's sessions, when it closes or leaves its page
+    pub fn close_tab(&self, tab_id: usize) {
+        self.sessions.lock_or_recover().remove(&tab_id);
+    }
+
+    /// Get all results for a session
+    pub fn get_all_results(&self, tab_id: usize, session_id: &str) -> Option<Vec<FindResult>> {
+        let sessions = self.sessions.lock_or_recover();
+        sessions.get(&tab_id).and_then(|tab| tab.get(session_id)).map(|s| s.results.clone())
     }
+
+    /// Generate JavaScript for find in page integration
+    pub fn get_find_script(&self) -> String {
+        format!("{}\n{}", self.get_marks_runtime(), r#"
+(function() {
+    class WebXFindInPage {
+        constructor() {
+            this.sessionId = null;
+            this.isActive = false;
+            this.results = [];
+            this.currentIndex = 0;
+        }
+        
+        startFind(query, options = {}) {
+            this.cleanupHighlights();
+            
+            const defaultOptions = {
+                case_sensitive: false,
+                whole_words: false,
+                regex: false,
+                highlight_all: true
+            };
+            
+            const findOptions = { ...defaultOptions, ...options };
+            this.sessionId = this.generateSessionId();
+            
+            const sessionId = this.sessionId;
+            window.ipc.request({
+                type: 'find-start',
+                sessionId: sessionId,
+                query: query,
+                options: findOptions,
+                content: window.webxFindMarks.collectText()
+            }).then((reply) => {
+                if (this.sessionId === sessionId) this.updateResults(reply.results, 0);
+            });
+            
+            this.isActive = true;
+        }
+        
+        findNext() {
+            if (!this.isActive) return;
+            
+            window.ipc.request({
+                type: 'find-next',
+                sessionId: this.sessionId
+            }).then((result) => this.moveTo(result));
+        }
+        
+        findPrevious() {
+            if (!this.isActive) return;
+            
+            window.ipc.request({
+                type: 'find-previous',
+                sessionId: this.sessionId
+            }).then((result) => this.moveTo(result));
+        }
+        
+        moveTo(result) {
+            if (!result || !this.isActive) return;
+            this.currentIndex = result.index;
+            window.webxFindMarks.setCurrent(result.index);
+        }
+        
+        updateResults(results, currentIndex) {
+            this.results = results;
+            this.currentIndex = currentIndex;
+            this.highlightResults(results);
+        }
+        
+        highlightResults(results) {
+            const ranges = results.map(result => result.dom_position);
+            window.webxFindMarks.apply(ranges, this.currentIndex, true);
+        }
+        
+        cleanupHighlights() {
+            window.webxFindMarks.clear();
+        }
+        
+        endFind() {
+            this.cleanupHighlights();
+            this.isActive = false;
+            this.results = [];
+            this.currentIndex = 0;
+            
+            if (this.sessionId) {
+                window.ipc.send({
+                    type: 'find-end',
+                    sessionId: this.sessionId
+                });
+                this.sessionId = null;
+            }
+        }
+        
+        generateSessionId() {
+            return 'find_' + Date.now() + '_' + Math.random().toString(36).substr(2, 9);
+        }
+        
+        getStatus() {
+            return {
+                isActive: this.isActive,
+                resultCount: this.results.length,
+                currentIndex: this.currentIndex
+            };
+        }
+    }
+    
+    // Create global instance
+    window.webxFind = new WebXFindInPage();
     
-    pub fn search(&self, _query: &str) -> Vec<String> {
-        Vec::new() // Placeholder implementation
+    // Keyboard shortcuts
+    document.addEventListener('keydown', function(e) {
+        // Ctrl+F or Cmd+F to start find
+        if ((e.ctrlKey || e.metaKey) && e.key === 'f') {
+            e.preventDefault();
+            // Would show find UI
+            window.webxFind.startFind('');
+        }
+        
+        // ESC to close find
+        if (e.key === 'Escape' && window.webxFind.isActive) {
+            e.preventDefault();
+            window.webxFind.endFind();
+        }
+        
+        // Enter/Shift+Enter for next/previous
+        if (window.webxFind.isActive) {
+            if (e.key === 'Enter' && !e.shiftKey) {
+                e.preventDefault();
+                window.webxFind.findNext();
+            } else if (e.key === 'Enter' && e.shiftKey) {
+                e.preventDefault();
+                window.webxFind.findPrevious();
+            }
+        }
+    });
+})();
+"#)
+    }
+
+    /// Generate CSS for highlighting
+    pub fn get_highlight_css(&self) -> String {
+        r#"
+.webx-find-highlight {
+    background-color: #ffeb3b;
+    color: #000;
+    padding: 1px 2px;
+    border-radius: 2px;
+    box-shadow: 0 0 0 1px rgba(0,0,0,0.1);
+}
+
+.webx-find-highlight-current {
+    background-color: #ff9800;
+    color: #fff;
+    font-weight: bold;
+}
+
+.webx-find-highlight:focus {
+    outline: 2px solid #2196f3;
+    outline-offset: 1px;
+}
+"#
+        .to_string()
+    }
+
+    // Private helper methods
+
+    /// JavaScript runtime shared by the highlight scripts. It maps UTF-16
+    /// offsets over the page's text nodes (the same text sent as `content`
+    /// with find-start) onto DOM ranges and wraps them in <mark> elements.
+    fn get_marks_runtime(&self) -> String {
+        format!(
+            r#"
+(function() {{
+    if (window.webxFindMarks) return;
+
+    const style = document.createElement('style');
+    style.textContent = `{}`;
+    (document.head || document.documentElement).appendChild(style);
+
+    window.webxFindMarks = {{
+        marks: [],
+
+        textNodes() {{
+            const walker = document.createTreeWalker(document.body, NodeFilter.SHOW_TEXT, {{
+                acceptNode(node) {{
+                    const parent = node.parentNode && node.parentNode.nodeName;
+                    return (parent === 'SCRIPT' || parent === 'STYLE' || parent === 'NOSCRIPT')
+                        ? NodeFilter.FILTER_REJECT
+                        : NodeFilter.FILTER_ACCEPT;
+                }}
+            }});
+            const nodes = [];
+            while (walker.nextNode()) nodes.push(walker.currentNode);
+            return nodes;
+        }},
+
+        collectText() {{
+            return this.textNodes().map(node => node.nodeValue).join('');
+        }},
+
+        apply(ranges, current, highlightAll) {{
+            this.clear();
+            const nodes = this.textNodes();
+            const offsets = [];
+            let position = 0;
+            nodes.forEach(node => {{
+                offsets.push(position);
+                position += node.nodeValue.length;
+            }});
+
+            // Wrap from the last match backwards so splitting text nodes
+            // never shifts the offsets of matches still to be wrapped
+            for (let i = ranges.length - 1; i >= 0; i--) {{
+                if (!highlightAll && i !== current) continue;
+                const [start, end] = ranges[i];
+                for (let j = nodes.length - 1; j >= 0; j--) {{
+                    const nodeStart = offsets[j];
+                    const nodeEnd = nodeStart + nodes[j].nodeValue.length;
+                    if (nodeEnd <= start || nodeStart >= end) continue;
+
+                    const range = document.createRange();
+                    range.setStart(nodes[j], Math.max(start, nodeStart) - nodeStart);
+                    range.setEnd(nodes[j], Math.min(end, nodeEnd) - nodeStart);
+                    const mark = document.createElement('mark');
+                    mark.className = 'webx-find-highlight';
+                    mark.dataset.webxFindIndex = i;
+                    range.surroundContents(mark);
+                    this.marks.unshift(mark);
+                }}
+            }}
+
+            this.setCurrent(current);
+        }},
+
+        setCurrent(index) {{
+            let first = null;
+            this.marks.forEach(mark => {{
+                const isCurrent = Number(mark.dataset.webxFindIndex) === index;
+                mark.classList.toggle('webx-find-highlight-current', isCurrent);
+                if (isCurrent && !first) first = mark;
+            }});
+            if (first) first.scrollIntoView({{ block: 'center', behavior: 'smooth' }});
+        }},
+
+        clear() {{
+            this.marks.forEach(mark => {{
+                const parent = mark.parentNode;
+                if (!parent) return;
+                while (mark.firstChild) parent.insertBefore(mark.firstChild, mark);
+                parent.removeChild(mark);
+                parent.normalize();
+            }});
+            this.marks = [];
+        }}
+    }};
+}})();
+"#,
+            self.get_highlight_css()
+        )
+    }
+    
+    fn perform_find(&self, content: &str, query: &str, options: &FindOptions) -> Vec<FindResult> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        
+        // Matching runs over the original text, so offsets never drift the
+        // way they would after lowercasing it
+        let Some(matcher) = self.build_matcher(query, options) else {
+            return Vec::new();
+        };
+        
+        let mut results = Vec::new();
+        let mut start_pos = 0;
+        // Running (byte, UTF-16) offset pair so DOM positions are computed incrementally
+        let mut utf16_cursor = (0, 0);
+        
+        while results.len() < self.max_results && start_pos <= content.len() {
+            let Some(mat) = matcher.find_at(content, start_pos) else {
+                break;
+            };
+            let (start, end) = (mat.start(), mat.end());
+            
+            if options.whole_words && !options.regex && !self.is_whole_word(content, start, end) {
+                start_pos = start + next_char_len(content, start);
+                continue;
+            }
+            
+            let original_text = content[start..end].to_string();
+            
+            let dom_start = utf16_cursor.1 + content[utf16_cursor.0..start].encode_utf16().count();
+            let dom_end = dom_start + original_text.encode_utf16().count();
+            utf16_cursor = (end, dom_end);
+            
+            results.push(FindResult {
+                index: results.len(),
+                text: original_text,
+                position: (start, end),
+                dom_position: (dom_start, dom_end),
+                page_number: None, // Would be set for paginated content
+            });
+            
+            // Step past empty regex matches so the scan always advances
+            start_pos = if end > start { end } else { end + next_char_len(content, end) };
+        }
+        
+        results
+    }
+    
+    /// Compile the query into a matcher; literal queries, and regex ones that
+    /// fail to parse, are escaped and matched as plain text
+    fn build_matcher(&self, query: &str, options: &FindOptions) -> Option<Regex> {
+        let pattern = if options.regex && Regex::new(query).is_ok() {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .ok()
+    }
+    
+    fn is_whole_word(&self, text: &str, start: usize, end: usize) -> bool {
+        let start_boundary = !text[..start].chars().next_back().is_some_and(char::is_alphanumeric);
+        let end_boundary = !text[end..].chars().next().is_some_and(char::is_alphanumeric);
+        start_boundary && end_boundary
+    }
+    
+    fn hash_content(&self, content: &str) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl IpcHandler for FindInPage {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["find-start", "find-next", "find-previous", "find-end"]
+    }
+
+    /// Answers find-start with `{ results }`, find-next and find-previous
+    /// with the new current result or null, and find-end with whether the
+    /// session existed
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        let tab_id = context.tab_id.ok_or("Find in page outside a tab")?;
+        let reply = match message {
+            IpcMessage::FindStart { session_id, query, options, content } => {
+                serde_json::json!({ "results": self.start_find(tab_id, &session_id, &content, &query, options) })
+            }
+            IpcMessage::FindNext { session_id } => serde_json::to_value(self.find_next(tab_id, &session_id))?,
+            IpcMessage::FindPrevious { session_id } => serde_json::to_value(self.find_previous(tab_id, &session_id))?,
+            IpcMessage::FindEnd { session_id } => serde_json::Value::Bool(self.end_find(tab_id, &session_id)),
+            _ => return Err(WebxError::Invalid("Not a find in page message".to_string())),
+        };
+        Ok(reply)
+    }
+}
+
+// Private helper functions
+
+/// A tab's session, if it has one by that name
+fn session_mut<'a>(
+    sessions: &'a mut HashMap<usize, HashMap<String, FindSession>>,
+    tab_id: usize,
+    session_id: &str,
+) -> Option<&'a mut FindSession> {
+    sessions.get_mut(&tab_id)?.get_mut(session_id)
+}
+
+/// Byte length of the character at `pos`, or 1 at the end of the text
+fn next_char_len(text: &str, pos: usize) -> usize {
+    text[pos..].chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_literal_search() {
+        let finder = FindInPage::new(None);
+        let content = "The quick brown fox jumps over the lazy dog. The fox is quick.";
+        
+        let options = FindOptions::default();
+        let results = finder.start_find(1, "session1", content, "fox", options);
+        
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].text, "fox");
+        assert_eq!(results[1].text, "fox");
+        
+        // Test case sensitivity
+        let mut case_options = FindOptions::default();
+        case_options.case_sensitive = true;
+        let case_results = finder.start_find(1, "session2", content, "Fox", case_options);
+        assert_eq!(case_results.len(), 0); // No matches with exact case
+        
+        // Test whole words
+        let mut word_options = FindOptions::default();
+        word_options.whole_words = true;
+        let word_results = finder.start_find(1, "session3", content, "the", word_options);
+        assert_eq!(word_results.len(), 3); // Only whole word matches (The, the, The)
+    }
+
+    #[test]
+    fn test_find_navigation() {
+        let finder = FindInPage::new(None);
+        let content = "test test test test";
+        
+        let options = FindOptions::default();
+        finder.start_find(1, "session1", content, "test", options);
+        
+        // Test navigation
+        let first = finder.find_next(1, "session1").unwrap();
+        assert_eq!(first.index, 1); // Second occurrence (0-indexed)
+        
+        let second = finder.find_next(1, "session1").unwrap();
+        assert_eq!(second.index, 2);
+        
+        let prev = finder.find_previous(1, "session1").unwrap();
+        assert_eq!(prev.index, 1);
+        
+        // Test jumping to specific result
+        let jump_result = finder.jump_to_result(1, "session1", 3).unwrap();
+        assert_eq!(jump_result.index, 3);
+    }
+
+    #[test]
+    fn test_find_with_updates() {
+        let finder = FindInPage::new(None);
+        let content1 = "original content with test word";
+        let content2 = "updated content with test word and another test";
+        
+        let options = FindOptions::default();
+        finder.start_find(1, "session1", content1, "test", options.clone());
+        
+        // Update with new content
+        let new_results = finder.update_content(1, "session1", content2).unwrap();
+        assert_eq!(new_results.len(), 2); // Now has 2 matches
+        
+        // Session info should reflect new count
+        let info = finder.get_session_info(1, "session1").unwrap();
+        assert_eq!(info.1, 2); // 2 total results
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let finder = FindInPage::new(None);
+        let content = "Phone: 123-456-7890, Another: 987-654-3210";
+        
+        let mut options = FindOptions::default();
+        options.regex = true;
+        let results = finder.start_find(1, "session1", content, r"\d{3}-\d{3}-\d{4}", options);
+        
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].text, "123-456-7890");
+        assert_eq!(results[1].text, "987-654-3210");
+    }
+
+    #[test]
+    fn test_case_insensitive_offsets() {
+        let finder = FindInPage::new(None);
+        // 'İ' lowercases to two characters, which used to shift every later offset
+        let content = "İSTANBUL and Istanbul";
+
+        let results = finder.start_find(1, "session1", content, "istanbul", FindOptions::default());
+        assert_eq!(results.len(), 1);
+        assert_eq!(&content[results[0].position.0..results[0].position.1], "Istanbul");
+
+        let options = FindOptions { whole_words: true, ..Default::default() };
+        let results = finder.start_find(1, "session2", "Catalog CAT cat", "cat", options);
+        let texts: Vec<&str> = results.iter().map(|result| result.text.as_str()).collect();
+        assert_eq!(texts, vec!["CAT", "cat"]);
+    }
+
+    #[test]
+    fn test_highlight_scripts() {
+        let finder = FindInPage::new(None);
+        let content = "café au lait, café noir";
+
+        let results = finder.start_find(1, "session1", content, "café", FindOptions::default());
+        assert_eq!(results.len(), 2);
+        // DOM positions are UTF-16 offsets, not byte offsets
+        assert_eq!(results[0].dom_position, (0, 4));
+        assert_eq!(results[1].dom_position, (14, 18));
+
+        // Nothing to clean up until highlights were injected
+        assert!(finder.get_current_match_script(1, "session1").is_none());
+
+        let script = finder.get_highlight_script(1, "session1").unwrap();
+        assert!(script.contains("window.webxFindMarks.apply([[0,4],[14,18]], 0, true);"));
+
+        finder.find_next(1, "session1");
+        let script = finder.get_current_match_script(1, "session1").unwrap();
+        assert!(script.contains("window.webxFindMarks.setCurrent(1);"));
+
+        let cleanup = finder.end_find_with_cleanup(1, "session1").unwrap();
+        assert!(cleanup.contains("window.webxFindMarks.clear();"));
+        assert!(finder.get_session_info(1, "session1").is_none());
+    }
+
+    #[test]
+    fn test_sessions_are_kept_per_tab() {
+        let finder = FindInPage::new(None);
+        finder.start_find(1, "find", "one two", "one", FindOptions::default());
+        finder.start_find(2, "find", "one one", "one", FindOptions::default());
+        assert_eq!(finder.get_session_info(1, "find"), Some((0, 1)));
+        assert_eq!(finder.get_session_info(2, "find"), Some((0, 2)));
+
+        for i in 0..MAX_TAB_SESSIONS {
+            finder.start_find(1, &format!("more{}", i), "one", "one", FindOptions::default());
+        }
+        assert!(finder.get_session_info(1, "find").is_none());
+        assert_eq!(finder.sessions.lock_or_recover()[&1].len(), MAX_TAB_SESSIONS);
+
+        finder.close_tab(2);
+        assert!(finder.get_session_info(2, "find").is_none());
     }
-}
\ No newline at end of file
+}
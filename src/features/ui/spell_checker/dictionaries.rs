@@ -0,0 +1,256 @@
+// Spell Check Dictionary Installation
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use super::hunspell::HunspellDictionary;
+use super::suggestions::parse_frequency_list;
+use super::SpellLanguage;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Default source for Hunspell dictionaries (LibreOffice dictionaries repository)
+pub const DEFAULT_DICTIONARY_SOURCE: &str = "https://raw.githubusercontent.com/LibreOffice/dictionaries/master";
+
+/// Locations where distributions install Hunspell dictionaries
+const SYSTEM_DICTIONARY_DIRS: &[&str] = &["/usr/share/hunspell", "/usr/share/myspell/dicts", "/usr/share/myspell"];
+
+/// Where a dictionary was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum DictionarySource {
+    /// Installed into the browser's own dictionary directory
+    Installed,
+    /// Provided by the operating system
+    System,
+}
+
+/// Paths of a .aff/.dic pair for one language
+#[derive(Debug, Clone)]
+pub struct DictionaryFiles {
+    pub aff_path: PathBuf,
+    pub dic_path: PathBuf,
+    pub source: DictionarySource,
+}
+
+/// Downloads, installs and locates Hunspell dictionaries per language
+pub struct DictionaryManager {
+    dictionary_dir: PathBuf,
+    source_url: Arc<Mutex<String>>,
+    system_dirs: Vec<PathBuf>,
+}
+
+impl DictionaryManager {
+    /// Create a dictionary manager storing dictionaries in `dictionary_dir`
+    pub fn new(dictionary_dir: PathBuf) -> Result<Self, WebxError> {
+        fs::create_dir_all(&dictionary_dir)?;
+
+        Ok(Self {
+            dictionary_dir,
+            source_url: Arc::new(Mutex::new(DEFAULT_DICTIONARY_SOURCE.to_string())),
+            system_dirs: SYSTEM_DICTIONARY_DIRS.iter().map(PathBuf::from).collect(),
+        })
+    }
+
+    /// Set the base URL dictionaries are downloaded from
+    pub fn set_source_url(&self, url: &str) {
+        *self.source_url.lock_or_recover() = url.trim_end_matches('/').to_string();
+    }
+
+    /// Get the base URL dictionaries are downloaded from
+    pub fn get_source_url(&self) -> String {
+        self.source_url.lock_or_recover().clone()
+    }
+
+    /// URLs of the .aff and .dic files for a language
+    pub fn download_urls(&self, language: &SpellLanguage) -> (String, String) {
+        let base = format!("{}/{}", self.get_source_url(), language.repository_path());
+        (format!("{}.aff", base), format!("{}.dic", base))
+    }
+
+    /// Find the dictionary for a language, preferring installed over system copies
+    pub fn locate(&self, language: &SpellLanguage) -> Option<DictionaryFiles> {
+        let name = language.hunspell_name();
+
+        let (aff_path, dic_path) = self.installed_paths(language);
+        if aff_path.exists() && dic_path.exists() {
+            return Some(DictionaryFiles {
+                aff_path,
+                dic_path,
+                source: DictionarySource::Installed,
+            });
+        }
+
+        self.system_dirs.iter().find_map(|dir| {
+            let aff_path = dir.join(format!("{}.aff", name));
+            let dic_path = dir.join(format!("{}.dic", name));
+            (aff_path.exists() && dic_path.exists()).then_some(DictionaryFiles {
+                aff_path,
+                dic_path,
+                source: DictionarySource::System,
+            })
+        })
+    }
+
+    /// Check if a dictionary is available for a language
+    pub fn is_available(&self, language: &SpellLanguage) -> bool {
+        self.locate(language).is_some()
+    }
+
+    /// Load and parse the dictionary for a language, if one is available
+    pub fn load(&self, language: &SpellLanguage) -> Result<Option<HunspellDictionary>, WebxError> {
+        match self.locate(language) {
+            Some(files) => Ok(Some(HunspellDictionary::from_files(&files.aff_path, &files.dic_path)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the word frequency list for a language (empty if none is installed)
+    pub fn load_frequencies(&self, language: &SpellLanguage) -> Result<HashMap<String, u64>, WebxError> {
+        let path = self.frequency_path(language);
+        if path.exists() {
+            Ok(parse_frequency_list(&fs::read_to_string(path)?))
+        } else {
+            Ok(HashMap::new())
+        }
+    }
+
+    /// Install a word frequency list ("word count" per line) used to rank suggestions
+    pub fn install_frequency_list(&self, language: &SpellLanguage, path: &Path) -> Result<(), WebxError> {
+        let content = fs::read_to_string(path)?;
+        if parse_frequency_list(&content).is_empty() {
+            return Err("Frequency list contains no words".into());
+        }
+        fs::write(self.frequency_path(language), content)?;
+        Ok(())
+    }
+
+    /// Download and install the dictionary for a language
+    pub async fn install(&self, language: &SpellLanguage) -> Result<DictionaryFiles, WebxError> {
+        let (aff_url, dic_url) = self.download_urls(language);
+        let client = reqwest::Client::new();
+
+        let aff = client.get(&aff_url).send().await?.error_for_status()?.bytes().await?;
+        let dic = client.get(&dic_url).send().await?.error_for_status()?.bytes().await?;
+
+        tracing::info!("Downloaded {} dictionary from {}", language.name(), aff_url);
+        self.install_bytes(language, &aff, &dic)
+    }
+
+    /// Install a dictionary from local .aff/.dic files
+    pub fn install_from_files(
+        &self,
+        language: &SpellLanguage,
+        aff_path: &Path,
+        dic_path: &Path,
+    ) -> Result<DictionaryFiles, WebxError> {
+        let aff = fs::read(aff_path)?;
+        let dic = fs::read(dic_path)?;
+        self.install_bytes(language, &aff, &dic)
+    }
+
+    /// Remove an installed dictionary (system dictionaries are left alone)
+    pub fn uninstall(&self, language: &SpellLanguage) -> Result<bool, WebxError> {
+        let (aff_path, dic_path) = self.installed_paths(language);
+        let existed = aff_path.exists() || dic_path.exists();
+
+        for path in [aff_path, dic_path, self.frequency_path(language)] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(existed)
+    }
+
+    /// Languages with a dictionary available
+    pub fn available_languages(&self) -> Vec<SpellLanguage> {
+        SpellLanguage::all()
+            .into_iter()
+            .filter(|lang| self.is_available(lang))
+            .collect()
+    }
+
+    // Private helper methods
+
+    fn installed_paths(&self, language: &SpellLanguage) -> (PathBuf, PathBuf) {
+        let name = language.hunspell_name();
+        (
+            self.dictionary_dir.join(format!("{}.aff", name)),
+            self.dictionary_dir.join(format!("{}.dic", name)),
+        )
+    }
+
+    fn frequency_path(&self, language: &SpellLanguage) -> PathBuf {
+        self.dictionary_dir.join(format!("{}.freq", language.hunspell_name()))
+    }
+
+    fn install_bytes(
+        &self,
+        language: &SpellLanguage,
+        aff: &[u8],
+        dic: &[u8],
+    ) -> Result<DictionaryFiles, WebxError> {
+        // Refuse to replace a working dictionary with one that does not parse
+        HunspellDictionary::parse(aff, dic)?;
+
+        let (aff_path, dic_path) = self.installed_paths(language);
+        for (path, content) in [(&aff_path, aff), (&dic_path, dic)] {
+            let temp_path = path.with_extension("tmp");
+            fs::write(&temp_path, content)?;
+            fs::rename(&temp_path, path)?;
+        }
+
+        Ok(DictionaryFiles {
+            aff_path,
+            dic_path,
+            source: DictionarySource::Installed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_install_from_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DictionaryManager::new(temp_dir.path().join("dictionaries")).unwrap();
+        let language = SpellLanguage::German;
+
+        let aff_path = temp_dir.path().join("source.aff");
+        let dic_path = temp_dir.path().join("source.dic");
+        fs::write(&aff_path, "SET UTF-8\nSFX N Y 1\nSFX N 0 n e\n").unwrap();
+        fs::write(&dic_path, "1\nStraße/N\n").unwrap();
+
+        let files = manager.install_from_files(&language, &aff_path, &dic_path).unwrap();
+        assert_eq!(files.source, DictionarySource::Installed);
+        assert!(files.dic_path.ends_with("de_DE.dic"));
+
+        let dict = manager.load(&language).unwrap().unwrap();
+        assert!(dict.check("Straßen"));
+
+        // Invalid dictionaries are rejected
+        fs::write(&dic_path, "0\n").unwrap();
+        assert!(manager.install_from_files(&language, &aff_path, &dic_path).is_err());
+        assert!(manager.load(&language).unwrap().is_some());
+
+        assert!(manager.uninstall(&language).unwrap());
+        assert!(!manager.installed_paths(&language).0.exists());
+    }
+
+    #[test]
+    fn test_download_urls() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DictionaryManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let (aff, dic) = manager.download_urls(&SpellLanguage::EnglishUS);
+        assert_eq!(aff, format!("{}/en/en_US.aff", DEFAULT_DICTIONARY_SOURCE));
+        assert_eq!(dic, format!("{}/en/en_US.dic", DEFAULT_DICTIONARY_SOURCE));
+
+        manager.set_source_url("https://mirror.example.com/dicts/");
+        let (aff, _) = manager.download_urls(&SpellLanguage::French);
+        assert_eq!(aff, "https://mirror.example.com/dicts/fr_FR/fr.aff");
+    }
+}
@@ -0,0 +1,410 @@
+// Grammar and Style Checking
+use crate::error::WebxError;
+use super::{SpellChecker, SpellLanguage};
+use crate::config::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::utils::LockExt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Kind of problem found in a text
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GrammarIssueKind {
+    Spelling,
+    DoubledWord,
+    Article,
+    Capitalization,
+    Confusion,
+}
+
+/// A problem with its location (byte offset and length) and possible fixes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrammarIssue {
+    pub kind: GrammarIssueKind,
+    pub offset: usize,
+    pub length: usize,
+    pub text: String,
+    pub message: String,
+    pub replacements: Vec<String>,
+}
+
+/// Commonly confused phrases: pattern, replacement template, explanation
+const CONFUSIONS: &[(&str, &str, &str)] = &[
+    (
+        r"(?i)\b(could|should|would|must|might) of\b",
+        "$1 have",
+        "Use \"have\" after modal verbs",
+    ),
+    (r"(?i)\balot\b", "a lot", "\"A lot\" is written as two words"),
+    (
+        r"(?i)\b(more|less|better|worse|rather|other) then\b",
+        "$1 than",
+        "Use \"than\" for comparisons",
+    ),
+    (r"(?i)\byour welcome\b", "you're welcome", "Did you mean \"you're\" (you are)?"),
+    (r"(?i)\bits self\b", "itself", "\"Itself\" is written as one word"),
+    (r"(?i)\bshould(?:n't)? of\b", "should have", "Use \"have\" after modal verbs"),
+];
+
+/// Words that are correct when repeated ("I had had enough")
+const ALLOWED_DOUBLES: &[&str] = &["had", "that"];
+
+/// Abbreviations whose trailing period does not end a sentence (single
+/// letters such as the parts of "e.g." are handled separately)
+const ABBREVIATIONS: &[&str] = &["etc", "vs", "mr", "mrs", "ms", "dr", "st", "no", "approx"];
+
+struct Token<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+/// Rule-based grammar checker layered on top of the spell checker
+pub struct GrammarChecker {
+    spell_checker: Arc<SpellChecker>,
+    enabled_languages: Arc<Mutex<HashSet<SpellLanguage>>>,
+    confusions: Vec<(Regex, &'static str, &'static str)>,
+}
+
+impl GrammarChecker {
+    /// Create a grammar checker; grammar rules start enabled for English
+    pub fn new(spell_checker: Arc<SpellChecker>) -> Self {
+        let confusions = CONFUSIONS
+            .iter()
+            .map(|(pattern, replacement, message)| {
+                (Regex::new(pattern).expect("invalid confusion pattern"), *replacement, *message)
+            })
+            .collect();
+
+        Self {
+            spell_checker,
+            enabled_languages: Arc::new(Mutex::new(
+                [SpellLanguage::EnglishUS, SpellLanguage::EnglishUK].into_iter().collect(),
+            )),
+            confusions,
+        }
+    }
+
+    /// Enable or disable grammar checking for a language
+    pub fn set_language_enabled(&self, language: SpellLanguage, enabled: bool) {
+        let mut languages = self.enabled_languages.lock_or_recover();
+        if enabled {
+            languages.insert(language);
+        } else {
+            languages.remove(&language);
+        }
+    }
+
+    /// Check if grammar checking is enabled for a language
+    pub fn is_language_enabled(&self, language: &SpellLanguage) -> bool {
+        self.enabled_languages.lock_or_recover().contains(language)
+    }
+
+    /// Check spelling and grammar, returning issues ordered by offset
+    pub fn check(&self, text: &str) -> Vec<GrammarIssue> {
+        let mut issues: Vec<GrammarIssue> = self
+            .spell_checker
+            .check_text(text)
+            .into_iter()
+            .map(|result| GrammarIssue {
+                kind: GrammarIssueKind::Spelling,
+                offset: result.offset,
+                length: result.word.len(),
+                message: format!("\"{}\" may be misspelled", result.word),
+                text: result.word,
+                replacements: result.suggestions,
+            })
+            .collect();
+
+        let active: Vec<SpellLanguage> = self
+            .spell_checker
+            .get_active_languages()
+            .into_iter()
+            .filter(|lang| self.is_language_enabled(lang))
+            .collect();
+
+        if !active.is_empty() {
+            let tokens = tokenize(text);
+            issues.extend(self.check_doubled_words(text, &tokens));
+            issues.extend(self.check_capitalization(text, &tokens));
+
+            let english = active
+                .iter()
+                .any(|lang| matches!(lang, SpellLanguage::EnglishUS | SpellLanguage::EnglishUK));
+            if english {
+                issues.extend(self.check_articles(&tokens));
+                issues.extend(self.check_confusions(text));
+            }
+        }
+
+        issues.sort_by_key(|issue| (issue.offset, issue.length));
+        issues
+    }
+
+    // Private helper methods
+
+    fn check_doubled_words(&self, text: &str, tokens: &[Token]) -> Vec<GrammarIssue> {
+        tokens
+            .windows(2)
+            .filter(|pair| {
+                let (first, second) = (&pair[0], &pair[1]);
+                let between = &text[first.offset + first.text.len()..second.offset];
+                first.text.eq_ignore_ascii_case(second.text)
+                    && between.chars().all(char::is_whitespace)
+                    && !ALLOWED_DOUBLES.contains(&first.text.to_lowercase().as_str())
+            })
+            .map(|pair| {
+                let end = pair[1].offset + pair[1].text.len();
+                GrammarIssue {
+                    kind: GrammarIssueKind::DoubledWord,
+                    offset: pair[0].offset,
+                    length: end - pair[0].offset,
+                    text: text[pair[0].offset..end].to_string(),
+                    message: format!("\"{}\" is repeated", pair[0].text),
+                    replacements: vec![pair[0].text.to_string()],
+                }
+            })
+            .collect()
+    }
+
+    fn check_capitalization(&self, text: &str, tokens: &[Token]) -> Vec<GrammarIssue> {
+        tokens
+            .windows(2)
+            .filter(|pair| {
+                let (previous, word) = (&pair[0], &pair[1]);
+                let between = &text[previous.offset + previous.text.len()..word.offset];
+                let ends_sentence = between.trim_start().starts_with(['.', '!', '?'])
+                    && between.ends_with(char::is_whitespace)
+                    && !(between.starts_with('.')
+                        && (ABBREVIATIONS.contains(&previous.text.to_lowercase().as_str())
+                            || previous.text.chars().count() == 1));
+                ends_sentence && word.text.starts_with(char::is_lowercase)
+            })
+            .map(|pair| {
+                let word = &pair[1];
+                GrammarIssue {
+                    kind: GrammarIssueKind::Capitalization,
+                    offset: word.offset,
+                    length: word.text.len(),
+                    text: word.text.to_string(),
+                    message: "Sentences should start with a capital letter".to_string(),
+                    replacements: vec![capitalize(word.text)],
+                }
+            })
+            .collect()
+    }
+
+    fn check_articles(&self, tokens: &[Token]) -> Vec<GrammarIssue> {
+        tokens
+            .windows(2)
+            .filter_map(|pair| {
+                let (article, word) = (&pair[0], &pair[1]);
+                let lower = article.text.to_lowercase();
+                let needs_an = starts_with_vowel_sound(word.text);
+
+                let replacement = match (lower.as_str(), needs_an) {
+                    ("a", true) => "an",
+                    ("an", false) => "a",
+                    _ => return None,
+                };
+                let replacement = if article.text.starts_with(char::is_uppercase) {
+                    capitalize(replacement)
+                } else {
+                    replacement.to_string()
+                };
+
+                Some(GrammarIssue {
+                    kind: GrammarIssueKind::Article,
+                    offset: article.offset,
+                    length: article.text.len(),
+                    text: article.text.to_string(),
+                    message: format!("Use \"{}\" before \"{}\"", replacement.to_lowercase(), word.text),
+                    replacements: vec![replacement],
+                })
+            })
+            .collect()
+    }
+
+    fn check_confusions(&self, text: &str) -> Vec<GrammarIssue> {
+        let mut issues = Vec::new();
+
+        for (regex, replacement, message) in &self.confusions {
+            for captures in regex.captures_iter(text) {
+                let found = captures.get(0).unwrap();
+                let mut fixed = String::new();
+                captures.expand(replacement, &mut fixed);
+
+                issues.push(GrammarIssue {
+                    kind: GrammarIssueKind::Confusion,
+                    offset: found.start(),
+                    length: found.len(),
+                    text: found.as_str().to_string(),
+                    message: message.to_string(),
+                    replacements: vec![fixed],
+                });
+            }
+        }
+
+        // Lowercase "i" as a pronoun
+        for token in tokenize(text).iter().filter(|t| t.text == "i") {
+            issues.push(GrammarIssue {
+                kind: GrammarIssueKind::Capitalization,
+                offset: token.offset,
+                length: 1,
+                text: "i".to_string(),
+                message: "The pronoun \"I\" is always capitalized".to_string(),
+                replacements: vec!["I".to_string()],
+            });
+        }
+
+        // Overlapping patterns (e.g. "should of") are reported once
+        issues.sort_by_key(|issue| issue.offset);
+        issues.dedup_by_key(|issue| issue.offset);
+        issues
+    }
+}
+
+impl SettingsProvider for GrammarChecker {
+    fn module(&self) -> &str {
+        "grammar"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        SpellLanguage::all()
+            .iter()
+            .map(|lang| {
+                SettingDefinition::toggle(
+                    &format!("grammar.{}", lang.code()),
+                    &format!("Check grammar in {}", lang.name()),
+                    matches!(lang, SpellLanguage::EnglishUS | SpellLanguage::EnglishUK),
+                )
+                .with_description("Flag repeated words, capitalization and common word confusions")
+                .with_category(SettingCategory::Languages)
+            })
+            .collect()
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        let code = key.strip_prefix("grammar.").ok_or("Unknown grammar setting")?;
+        let language = SpellLanguage::from_code(code).ok_or("Unknown grammar language")?;
+        let enabled = value.as_bool().ok_or("Expected a toggle value")?;
+        self.set_language_enabled(language, enabled);
+        Ok(())
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, ch) in text.char_indices() {
+        let is_word_char = ch.is_alphanumeric() || (ch == '\'' && start.is_some());
+        match (is_word_char, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(Token { text: &text[s..i], offset: s });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(Token { text: &text[s..], offset: s });
+    }
+
+    tokens
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Approximate whether a word is pronounced with a leading vowel sound
+fn starts_with_vowel_sound(word: &str) -> bool {
+    let lower = word.to_lowercase();
+
+    // Silent "h"
+    const VOWEL_SOUND: &[&str] = &["hour", "honest", "honor", "honour", "heir"];
+    // Vowel letters pronounced "yoo" or "wun"
+    const CONSONANT_SOUND: &[&str] = &["uni", "use", "usu", "uti", "eu", "ewe", "one", "once", "ubiq", "uran"];
+
+    if VOWEL_SOUND.iter().any(|p| lower.starts_with(p)) {
+        return true;
+    }
+    if CONSONANT_SOUND.iter().any(|p| lower.starts_with(p)) {
+        return false;
+    }
+
+    // Acronyms read letter by letter ("an FAQ", "a UFO" is an exception we accept)
+    if word.len() > 1 && word.chars().all(|c| c.is_ascii_uppercase()) {
+        return word.starts_with(['A', 'E', 'F', 'H', 'I', 'L', 'M', 'N', 'O', 'R', 'S', 'X']);
+    }
+
+    lower.starts_with(['a', 'e', 'i', 'o', 'u'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn checker(temp_dir: &TempDir) -> GrammarChecker {
+        let spell_checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        GrammarChecker::new(Arc::new(spell_checker))
+    }
+
+    fn kinds(issues: &[GrammarIssue]) -> Vec<GrammarIssueKind> {
+        issues
+            .iter()
+            .filter(|i| i.kind != GrammarIssueKind::Spelling)
+            .map(|i| i.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_grammar_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = checker(&temp_dir);
+
+        let text = "I would of gone to the the park. then a hour later i left.";
+        let issues = checker.check(text);
+        let grammar: Vec<&GrammarIssue> = issues.iter().filter(|i| i.kind != GrammarIssueKind::Spelling).collect();
+
+        assert_eq!(
+            kinds(&issues),
+            vec![
+                GrammarIssueKind::Confusion,
+                GrammarIssueKind::DoubledWord,
+                GrammarIssueKind::Capitalization,
+                GrammarIssueKind::Article,
+                GrammarIssueKind::Capitalization,
+            ]
+        );
+        assert_eq!(grammar[0].replacements, vec!["would have"]);
+        assert_eq!(&text[grammar[1].offset..grammar[1].offset + grammar[1].length], "the the");
+        assert_eq!(grammar[2].replacements, vec!["Then"]);
+        assert_eq!(grammar[3].replacements, vec!["an"]);
+        assert_eq!(grammar[4].replacements, vec!["I"]);
+
+        // Common exceptions are not flagged
+        let issues = checker.check("He had had a useful talk with Dr. smith, e.g. about an honest idea.");
+        assert!(kinds(&issues).is_empty());
+    }
+
+    #[test]
+    fn test_toggle_per_language() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = checker(&temp_dir);
+        assert!(!kinds(&checker.check("This is is wrong")).is_empty());
+
+        checker.set_language_enabled(SpellLanguage::EnglishUS, false);
+        assert!(kinds(&checker.check("This is is wrong")).is_empty());
+
+        // Spelling is still reported when grammar checking is off
+        assert!(checker.check("Thsi").iter().any(|i| i.kind == GrammarIssueKind::Spelling));
+    }
+}
@@ -0,0 +1,560 @@
+// Hunspell Dictionary Parsing
+use crate::error::WebxError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Affix flag, normalized from any of the Hunspell flag encodings
+pub type Flag = u32;
+
+/// AF alias table; index 0 is unused since aliases are 1-based
+type FlagAliases = Vec<Vec<Flag>>;
+
+/// How flags are written in the .aff and .dic files (the FLAG directive)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlagMode {
+    Char,
+    Long,
+    Num,
+}
+
+impl FlagMode {
+    fn parse_flags(&self, text: &str) -> Vec<Flag> {
+        match self {
+            FlagMode::Char => text.chars().map(|c| c as Flag).collect(),
+            FlagMode::Long => {
+                let chars: Vec<char> = text.chars().collect();
+                chars
+                    .chunks(2)
+                    .map(|pair| {
+                        let high = pair[0] as Flag;
+                        let low = pair.get(1).map(|c| *c as Flag).unwrap_or(0);
+                        (high << 16) | low
+                    })
+                    .collect()
+            }
+            FlagMode::Num => text
+                .split(',')
+                .filter_map(|n| n.trim().parse::<Flag>().ok())
+                .collect(),
+        }
+    }
+}
+
+/// One element of an affix condition pattern
+#[derive(Debug, Clone)]
+enum ConditionToken {
+    Any,
+    Char(char),
+    Set { negated: bool, chars: Vec<char> },
+}
+
+impl ConditionToken {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            ConditionToken::Any => true,
+            ConditionToken::Char(c) => *c == ch,
+            ConditionToken::Set { negated, chars } => chars.contains(&ch) != *negated,
+        }
+    }
+}
+
+/// Simplified regular expression limiting which stems an affix applies to
+#[derive(Debug, Clone)]
+struct Condition {
+    tokens: Vec<ConditionToken>,
+}
+
+impl Condition {
+    fn parse(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '.' => tokens.push(ConditionToken::Any),
+                '[' => {
+                    let mut set = Vec::new();
+                    let mut negated = false;
+                    for (i, c) in chars.by_ref().enumerate() {
+                        match c {
+                            '^' if i == 0 => negated = true,
+                            ']' => break,
+                            _ => set.push(c),
+                        }
+                    }
+                    tokens.push(ConditionToken::Set { negated, chars: set });
+                }
+                _ => tokens.push(ConditionToken::Char(ch)),
+            }
+        }
+
+        // "." alone is the conventional way of writing "no condition"
+        if tokens.len() == 1 && matches!(tokens[0], ConditionToken::Any) {
+            tokens.clear();
+        }
+
+        Self { tokens }
+    }
+
+    fn matches_start(&self, word: &[char]) -> bool {
+        word.len() >= self.tokens.len() && self.tokens.iter().zip(word).all(|(t, c)| t.matches(*c))
+    }
+
+    fn matches_end(&self, word: &[char]) -> bool {
+        word.len() >= self.tokens.len()
+            && self
+                .tokens
+                .iter()
+                .rev()
+                .zip(word.iter().rev())
+                .all(|(t, c)| t.matches(*c))
+    }
+}
+
+/// A single PFX or SFX rule
+#[derive(Debug, Clone)]
+struct AffixEntry {
+    flag: Flag,
+    strip: String,
+    add: String,
+    condition: Condition,
+    cross_product: bool,
+    continuation: Vec<Flag>,
+}
+
+/// Hunspell dictionary: stems from the .dic file and affix rules from the .aff file
+pub struct HunspellDictionary {
+    stems: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<AffixEntry>,
+    suffixes: Vec<AffixEntry>,
+    try_chars: String,
+    forbidden_flag: Option<Flag>,
+    need_affix_flag: Option<Flag>,
+    no_suggest_flag: Option<Flag>,
+}
+
+impl HunspellDictionary {
+    /// Load a dictionary from a .aff/.dic pair
+    pub fn from_files(aff_path: &Path, dic_path: &Path) -> Result<Self, WebxError> {
+        let aff = fs::read(aff_path)?;
+        let dic = fs::read(dic_path)?;
+        Self::parse(&aff, &dic)
+    }
+
+    /// Parse raw .aff and .dic contents, honouring the SET encoding
+    pub fn parse(aff: &[u8], dic: &[u8]) -> Result<Self, WebxError> {
+        let encoding = detect_encoding(aff);
+        let aff = decode(aff, &encoding);
+        let dic = decode(dic, &encoding);
+
+        let mut dictionary = Self {
+            stems: HashMap::new(),
+            prefixes: Vec::new(),
+            suffixes: Vec::new(),
+            try_chars: String::new(),
+            forbidden_flag: None,
+            need_affix_flag: None,
+            no_suggest_flag: None,
+        };
+
+        let (flag_mode, aliases) = dictionary.parse_affixes(&aff)?;
+        dictionary.parse_stems(&dic, flag_mode, &aliases);
+
+        if dictionary.stems.is_empty() {
+            return Err("Dictionary contains no words".into());
+        }
+
+        Ok(dictionary)
+    }
+
+    /// Check whether a word is spelled correctly, allowing for capitalization
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.trim_matches('\'');
+        if word.is_empty() {
+            return false;
+        }
+
+        if self.check_exact(word) {
+            return true;
+        }
+
+        // "Hello" and "HELLO" are accepted when "hello" is
+        let lower = word.to_lowercase();
+        if lower != word && self.check_exact(&lower) {
+            return true;
+        }
+
+        // "Paris" is stored capitalized, so "PARIS" needs title case
+        let mut chars = lower.chars();
+        if let Some(first) = chars.next() {
+            let title: String = first.to_uppercase().chain(chars).collect();
+            if title != word && self.check_exact(&title) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Stems that may be offered as suggestions
+    pub fn stems(&self) -> impl Iterator<Item = &str> {
+        self.stems
+            .iter()
+            .filter(|(_, flags)| {
+                !self.has_flag(flags, self.forbidden_flag)
+                    && !self.has_flag(flags, self.no_suggest_flag)
+                    && !self.has_flag(flags, self.need_affix_flag)
+            })
+            .map(|(stem, _)| stem.as_str())
+    }
+
+    /// Number of stems in the .dic file
+    pub fn stem_count(&self) -> usize {
+        self.stems.len()
+    }
+
+    /// Characters the dictionary recommends trying when building suggestions (TRY)
+    pub fn try_chars(&self) -> &str {
+        &self.try_chars
+    }
+
+    // Private helper methods
+
+    fn parse_affixes(&mut self, aff: &str) -> Result<(FlagMode, FlagAliases), WebxError> {
+        let mut flag_mode = FlagMode::Char;
+        let mut aliases: FlagAliases = Vec::new();
+        let mut raw_flags: Vec<(&str, Vec<&str>)> = Vec::new();
+
+        // Flag directives may appear after the FLAG line, so collect first
+        for line in aff.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens[0] {
+                "FLAG" => {
+                    flag_mode = match tokens.get(1).copied() {
+                        Some("long") => FlagMode::Long,
+                        Some("num") => FlagMode::Num,
+                        _ => FlagMode::Char,
+                    }
+                }
+                _ => raw_flags.push((tokens[0], tokens)),
+            }
+        }
+
+        let mut pending: HashMap<(bool, Flag), (bool, usize)> = HashMap::new();
+
+        for (directive, tokens) in raw_flags {
+            match directive {
+                "TRY" => self.try_chars = tokens.get(1).unwrap_or(&"").to_string(),
+                "FORBIDDENWORD" => self.forbidden_flag = single_flag(flag_mode, tokens.get(1)),
+                "NEEDAFFIX" | "PSEUDOROOT" => self.need_affix_flag = single_flag(flag_mode, tokens.get(1)),
+                "NOSUGGEST" => self.no_suggest_flag = single_flag(flag_mode, tokens.get(1)),
+                "AF" if tokens.len() >= 2 => {
+                    // The first AF line only announces the number of aliases
+                    if aliases.is_empty() && tokens[1].parse::<usize>().is_ok() && tokens.len() == 2 {
+                        aliases.push(Vec::new());
+                    } else {
+                        aliases.push(flag_mode.parse_flags(tokens[1]));
+                    }
+                }
+                "PFX" | "SFX" if tokens.len() >= 4 => {
+                    let is_prefix = directive == "PFX";
+                    let flag = single_flag(flag_mode, tokens.get(1)).ok_or("Invalid affix flag")?;
+                    let key = (is_prefix, flag);
+
+                    match pending.get_mut(&key) {
+                        Some((cross_product, remaining)) if *remaining > 0 => {
+                            *remaining -= 1;
+                            let cross_product = *cross_product;
+                            let entry = parse_affix_entry(&tokens, flag, cross_product, flag_mode, &aliases);
+                            if is_prefix {
+                                self.prefixes.push(entry);
+                            } else {
+                                self.suffixes.push(entry);
+                            }
+                        }
+                        _ => {
+                            let cross_product = tokens[2] == "Y";
+                            let count = tokens[3].parse::<usize>()?;
+                            pending.insert(key, (cross_product, count));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok((flag_mode, aliases))
+    }
+
+    fn parse_stems(&mut self, dic: &str, flag_mode: FlagMode, aliases: &[Vec<Flag>]) {
+        let mut lines = dic.lines();
+
+        // The first line is the approximate word count
+        lines.next();
+
+        for line in lines {
+            // Morphological fields follow a tab or space
+            let entry = line.split(['\t', ' ']).next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let (word, flags) = split_entry(entry);
+            let flags = match flags {
+                Some(flags) => resolve_flags(flags, flag_mode, aliases),
+                None => Vec::new(),
+            };
+
+            self.stems.entry(word).or_default().extend(flags);
+        }
+    }
+
+    fn has_flag(&self, flags: &[Flag], flag: Option<Flag>) -> bool {
+        flag.is_some_and(|f| flags.contains(&f))
+    }
+
+    fn check_exact(&self, word: &str) -> bool {
+        if let Some(flags) = self.stems.get(word) {
+            if self.has_flag(flags, self.forbidden_flag) {
+                return false;
+            }
+            if !self.has_flag(flags, self.need_affix_flag) {
+                return true;
+            }
+        }
+
+        self.check_suffixed(word, None, None) || self.check_prefixed(word)
+    }
+
+    fn stem_has_flags(&self, stem: &str, required: &[Flag]) -> bool {
+        self.stems.get(stem).is_some_and(|flags| {
+            !self.has_flag(flags, self.forbidden_flag) && required.iter().all(|f| flags.contains(f))
+        })
+    }
+
+    /// Strip a suffix and look the remainder up. `prefix` is set when a
+    /// prefix has already been removed; `outer` is set when checking the
+    /// inner suffix of a twofold suffix.
+    fn check_suffixed(&self, word: &str, prefix: Option<&AffixEntry>, outer: Option<Flag>) -> bool {
+        for suffix in &self.suffixes {
+            if let Some(prefix) = prefix {
+                if !(prefix.cross_product && suffix.cross_product) {
+                    continue;
+                }
+            }
+            if let Some(outer) = outer {
+                if !suffix.continuation.contains(&outer) {
+                    continue;
+                }
+            }
+
+            let Some(base) = word.strip_suffix(suffix.add.as_str()) else {
+                continue;
+            };
+            if base.is_empty() {
+                continue;
+            }
+
+            let stem = format!("{}{}", base, suffix.strip);
+            let stem_chars: Vec<char> = stem.chars().collect();
+            if !suffix.condition.matches_end(&stem_chars) {
+                continue;
+            }
+
+            let mut required = vec![suffix.flag];
+            if let Some(prefix) = prefix {
+                // The suffix may itself allow the prefix through its continuation flags
+                if !suffix.continuation.contains(&prefix.flag) {
+                    required.push(prefix.flag);
+                }
+            }
+
+            if self.stem_has_flags(&stem, &required) {
+                return true;
+            }
+
+            // Twofold suffixes: the stem + inner suffix may be a valid word
+            if prefix.is_none() && outer.is_none() && self.check_suffixed(&stem, None, Some(suffix.flag)) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn check_prefixed(&self, word: &str) -> bool {
+        for prefix in &self.prefixes {
+            let Some(rest) = word.strip_prefix(prefix.add.as_str()) else {
+                continue;
+            };
+            if rest.is_empty() {
+                continue;
+            }
+
+            let stem = format!("{}{}", prefix.strip, rest);
+            let stem_chars: Vec<char> = stem.chars().collect();
+
+            if prefix.condition.matches_start(&stem_chars) && self.stem_has_flags(&stem, &[prefix.flag]) {
+                return true;
+            }
+
+            if prefix.cross_product && self.check_suffixed(&stem, Some(prefix), None) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+fn single_flag(mode: FlagMode, token: Option<&&str>) -> Option<Flag> {
+    token.and_then(|t| mode.parse_flags(t).first().copied())
+}
+
+fn resolve_flags(flags: &str, mode: FlagMode, aliases: &[Vec<Flag>]) -> Vec<Flag> {
+    // With AF aliases, flag fields are 1-based indexes into the alias table
+    if !aliases.is_empty() {
+        if let Ok(index) = flags.parse::<usize>() {
+            return aliases.get(index).cloned().unwrap_or_default();
+        }
+    }
+    mode.parse_flags(flags)
+}
+
+fn parse_affix_entry(
+    tokens: &[&str],
+    flag: Flag,
+    cross_product: bool,
+    mode: FlagMode,
+    aliases: &[Vec<Flag>],
+) -> AffixEntry {
+    let zero_is_empty = |s: &str| if s == "0" { String::new() } else { s.to_string() };
+
+    let strip = zero_is_empty(tokens[2]);
+    let (add, continuation) = match tokens[3].split_once('/') {
+        Some((add, flags)) => (zero_is_empty(add), resolve_flags(flags, mode, aliases)),
+        None => (zero_is_empty(tokens[3]), Vec::new()),
+    };
+    let condition = Condition::parse(tokens.get(4).copied().unwrap_or("."));
+
+    AffixEntry {
+        flag,
+        strip,
+        add,
+        condition,
+        cross_product,
+        continuation,
+    }
+}
+
+/// Split "word/FLAGS", honouring "\/" escapes inside the word
+fn split_entry(entry: &str) -> (String, Option<&str>) {
+    let bytes = entry.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'/' && (i == 0 || bytes[i - 1] != b'\\') {
+            return (entry[..i].replace("\\/", "/"), Some(&entry[i + 1..]));
+        }
+    }
+    (entry.replace("\\/", "/"), None)
+}
+
+fn detect_encoding(aff: &[u8]) -> String {
+    // The SET line is plain ASCII whatever the file encoding is
+    aff.split(|b| *b == b'\n')
+        .map(|line| String::from_utf8_lossy(line).trim().to_string())
+        .find_map(|line| line.strip_prefix("SET ").map(|e| e.trim().to_uppercase()))
+        .unwrap_or_else(|| "ISO8859-1".to_string())
+}
+
+fn decode(bytes: &[u8], encoding: &str) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match encoding {
+        "UTF-8" | "UTF8" => String::from_utf8_lossy(bytes).into_owned(),
+        "ISO8859-1" | "ISO-8859-1" | "ISO8859-15" | "ISO-8859-15" => {
+            bytes.iter().map(|b| *b as char).collect()
+        }
+        other => {
+            tracing::warn!("Unsupported dictionary encoding {}, decoding as UTF-8", other);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'
+NOSUGGEST !
+
+PFX U Y 1
+PFX U   0     un         .
+
+SFX S Y 4
+SFX S   y     ies        [^aeiou]y
+SFX S   0     s          [aeiou]y
+SFX S   0     es         [sxzh]
+SFX S   0     s          [^sxzhy]
+
+SFX D Y 2
+SFX D   0     d          e
+SFX D   0     ed/L       [^e]
+
+SFX L Y 1
+SFX L   0     ly         .
+";
+
+    const DIC: &str = "5
+try/S
+box/S
+lock/UDS
+pure
+shit/!
+";
+
+    #[test]
+    fn test_affix_expansion() {
+        let dict = HunspellDictionary::parse(AFF.as_bytes(), DIC.as_bytes()).unwrap();
+        assert_eq!(dict.stem_count(), 5);
+
+        assert!(dict.check("try"));
+        assert!(dict.check("tries"));
+        assert!(dict.check("boxes"));
+        assert!(dict.check("locks"));
+        assert!(dict.check("unlock"));
+        assert!(dict.check("unlocked"));
+        assert!(dict.check("unlocks"));
+        assert!(!dict.check("trys"));
+        assert!(!dict.check("boxs"));
+        assert!(!dict.check("pures"));
+        assert!(!dict.check("untry"));
+
+        // Twofold suffix: "ed" carries the continuation flag L
+        assert!(dict.check("lockedly"));
+
+        // Capitalization variants
+        assert!(dict.check("Unlocked"));
+        assert!(dict.check("BOXES"));
+
+        // NOSUGGEST words are accepted but never suggested
+        assert!(dict.check("shit"));
+        assert!(!dict.stems().any(|s| s == "shit"));
+    }
+
+    #[test]
+    fn test_latin1_and_long_flags() {
+        let aff = b"SET ISO8859-1\nFLAG long\nSFX Aa Y 1\nSFX Aa 0 n .\n";
+        let dic = b"1\nk\xF6rpe/Aa\n";
+        let dict = HunspellDictionary::parse(aff, dic).unwrap();
+
+        assert!(dict.check("körpe"));
+        assert!(dict.check("körpen"));
+        assert!(!dict.check("körpes"));
+    }
+}
@@ -1,13 +1,742 @@
-// Spell Checker Module - Placeholder
-// TODO: Implement spell checking functionality
-pub struct SpellChecker;
+// Spell Checker for Text Inputs
+pub mod dictionaries;
+pub mod grammar;
+pub mod hunspell;
+pub mod suggestions;
+
+pub use dictionaries::{DictionaryFiles, DictionaryManager, DictionarySource};
+pub use grammar::{GrammarChecker, GrammarIssue, GrammarIssueKind};
+pub use hunspell::HunspellDictionary;
+pub use suggestions::{RankedSuggestion, SuggestionIndex};
+
+use crate::error::WebxError;
+use crate::config::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Supported languages for spell checking
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SpellLanguage {
+    EnglishUS,
+    EnglishUK,
+    Spanish,
+    French,
+    German,
+    Italian,
+    Portuguese,
+    Russian,
+}
+
+impl SpellLanguage {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpellLanguage::EnglishUS => "en-US",
+            SpellLanguage::EnglishUK => "en-GB",
+            SpellLanguage::Spanish => "es",
+            SpellLanguage::French => "fr",
+            SpellLanguage::German => "de",
+            SpellLanguage::Italian => "it",
+            SpellLanguage::Portuguese => "pt",
+            SpellLanguage::Russian => "ru",
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpellLanguage::EnglishUS => "English (US)",
+            SpellLanguage::EnglishUK => "English (UK)",
+            SpellLanguage::Spanish => "Spanish",
+            SpellLanguage::French => "French",
+            SpellLanguage::German => "German",
+            SpellLanguage::Italian => "Italian",
+            SpellLanguage::Portuguese => "Portuguese",
+            SpellLanguage::Russian => "Russian",
+        }
+    }
+
+    /// Locale name used for Hunspell dictionary files
+    pub fn hunspell_name(&self) -> &'static str {
+        match self {
+            SpellLanguage::EnglishUS => "en_US",
+            SpellLanguage::EnglishUK => "en_GB",
+            SpellLanguage::Spanish => "es_ES",
+            SpellLanguage::French => "fr_FR",
+            SpellLanguage::German => "de_DE",
+            SpellLanguage::Italian => "it_IT",
+            SpellLanguage::Portuguese => "pt_PT",
+            SpellLanguage::Russian => "ru_RU",
+        }
+    }
+
+    /// Path of the dictionary (without extension) in the dictionary source
+    pub fn repository_path(&self) -> &'static str {
+        match self {
+            SpellLanguage::EnglishUS => "en/en_US",
+            SpellLanguage::EnglishUK => "en/en_GB",
+            SpellLanguage::Spanish => "es/es_ES",
+            SpellLanguage::French => "fr_FR/fr",
+            SpellLanguage::German => "de/de_DE_frami",
+            SpellLanguage::Italian => "it_IT/it_IT",
+            SpellLanguage::Portuguese => "pt_PT/pt_PT",
+            SpellLanguage::Russian => "ru_RU/ru_RU",
+        }
+    }
+
+    /// Look up a language by its code
+    pub fn from_code(code: &str) -> Option<SpellLanguage> {
+        Self::all().into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// All supported languages
+    pub fn all() -> Vec<SpellLanguage> {
+        vec![
+            SpellLanguage::EnglishUS,
+            SpellLanguage::EnglishUK,
+            SpellLanguage::Spanish,
+            SpellLanguage::French,
+            SpellLanguage::German,
+            SpellLanguage::Italian,
+            SpellLanguage::Portuguese,
+            SpellLanguage::Russian,
+        ]
+    }
+}
+
+/// Spell checker dictionary
+pub struct SpellDictionary {
+    words: HashSet<String>,
+    hunspell: Option<HunspellDictionary>,
+    index: SuggestionIndex,
+    language: SpellLanguage,
+}
+
+impl SpellDictionary {
+    pub fn new(language: SpellLanguage) -> Self {
+        Self {
+            words: HashSet::new(),
+            hunspell: None,
+            index: SuggestionIndex::new(),
+            language,
+        }
+    }
+
+    /// Create a dictionary backed by a Hunspell .aff/.dic pair
+    pub fn from_hunspell(language: SpellLanguage, hunspell: HunspellDictionary) -> Self {
+        let mut index = SuggestionIndex::new();
+        for stem in hunspell.stems() {
+            index.insert(stem);
+        }
+
+        Self {
+            words: HashSet::new(),
+            hunspell: Some(hunspell),
+            index,
+            language,
+        }
+    }
+    
+    pub fn add_word(&mut self, word: &str) {
+        let word = word.to_lowercase();
+        self.index.insert(&word);
+        self.words.insert(word);
+    }
+    
+    pub fn remove_word(&mut self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.index.remove(&word);
+        self.words.remove(&word)
+    }
+
+    /// Set word frequencies used to rank suggestions
+    pub fn set_frequencies(&mut self, frequencies: HashMap<String, u64>) {
+        self.index.extend_frequencies(frequencies);
+    }
+    
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+            || self.hunspell.as_ref().is_some_and(|h| h.check(word))
+    }
+
+    /// Whether affix-aware Hunspell data backs this dictionary
+    pub fn is_hunspell(&self) -> bool {
+        self.hunspell.is_some()
+    }
+
+    /// Ranked suggestions for a misspelled word, best first
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<RankedSuggestion> {
+        self.index.suggest(word, limit)
+    }
+    
+    pub fn get_language(&self) -> &SpellLanguage {
+        &self.language
+    }
+    
+    pub fn word_count(&self) -> usize {
+        self.words.len() + self.hunspell.as_ref().map(|h| h.stem_count()).unwrap_or(0)
+    }
+}
+
+/// Number of suggestions offered for a misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Spell checker service
+pub struct SpellChecker {
+    dictionaries: Arc<Mutex<HashMap<SpellLanguage, SpellDictionary>>>,
+    active_languages: Arc<Mutex<Vec<SpellLanguage>>>,
+    user_dictionary: Arc<Mutex<HashSet<String>>>,
+    dictionary_manager: DictionaryManager,
+    config_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellCheckResult {
+    pub word: String,
+    pub offset: usize,
+    pub suggestions: Vec<String>,
+    pub is_misspelled: bool,
+}
 
 impl SpellChecker {
-    pub fn new() -> Self {
-        Self
+    /// Create a new spell checker
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("spellcheck");
+            path
+        });
+        
+        // Create config directory
+        fs::create_dir_all(&config_dir)?;
+        
+        let spell_checker = Self {
+            dictionaries: Arc::new(Mutex::new(HashMap::new())),
+            active_languages: Arc::new(Mutex::new(vec![SpellLanguage::EnglishUS])),
+            user_dictionary: Arc::new(Mutex::new(HashSet::new())),
+            dictionary_manager: DictionaryManager::new(config_dir.join("dictionaries"))?,
+            config_dir,
+        };
+        
+        // Load user dictionary and active languages
+        spell_checker.load_user_dictionary()?;
+        spell_checker.load_config()?;
+        
+        // Load default dictionaries
+        spell_checker.load_default_dictionaries()?;
+        
+        Ok(spell_checker)
+    }
+
+    /// Check spelling of text
+    pub fn check_text(&self, text: &str) -> Vec<SpellCheckResult> {
+        let mut results = Vec::new();
+        let words = self.extract_words(text);
+        
+        let dictionaries = self.dictionaries.lock_or_recover();
+        let user_dict = self.user_dictionary.lock_or_recover();
+        let active_langs = self.active_languages.lock_or_recover();
+        
+        for (word, offset) in words {
+            if word.len() < 2 || word.chars().all(|c| c.is_ascii_digit()) {
+                continue; // Skip very short words and numbers
+            }
+            
+            let is_correct = user_dict.contains(&word.to_lowercase())
+                || active_langs.iter().any(|lang| {
+                    dictionaries
+                        .get(lang)
+                        .map(|dict| dict.contains(&word))
+                        .unwrap_or(false)
+                });
+            
+            if !is_correct {
+                let suggestions = self.get_suggestions(&word, &dictionaries, &active_langs);
+                results.push(SpellCheckResult {
+                    word: word.clone(),
+                    offset,
+                    suggestions,
+                    is_misspelled: true,
+                });
+            }
+        }
+        
+        results
+    }
+
+    /// Add word to user dictionary
+    pub fn add_to_user_dictionary(&self, word: &str) -> Result<(), WebxError> {
+        {
+            let mut user_dict = self.user_dictionary.lock_or_recover();
+            user_dict.insert(word.to_lowercase());
+        }
+        
+        self.save_user_dictionary()?;
+        Ok(())
+    }
+
+    /// Remove word from user dictionary
+    pub fn remove_from_user_dictionary(&self, word: &str) -> Result<(), WebxError> {
+        {
+            let mut user_dict = self.user_dictionary.lock_or_recover();
+            user_dict.remove(&word.to_lowercase());
+        }
+        
+        self.save_user_dictionary()?;
+        Ok(())
+    }
+
+    /// Set active languages
+    pub fn set_active_languages(&self, languages: Vec<SpellLanguage>) -> Result<(), WebxError> {
+        for language in &languages {
+            self.load_language(language)?;
+        }
+        *self.active_languages.lock_or_recover() = languages;
+        self.save_config()?;
+        Ok(())
+    }
+
+    /// Get active languages
+    pub fn get_active_languages(&self) -> Vec<SpellLanguage> {
+        self.active_languages.lock_or_recover().clone()
+    }
+
+    /// Get available languages
+    pub fn get_available_languages(&self) -> Vec<SpellLanguage> {
+        SpellLanguage::all()
+    }
+
+    /// Get languages with an installed or system Hunspell dictionary
+    pub fn get_installed_languages(&self) -> Vec<SpellLanguage> {
+        self.dictionary_manager.available_languages()
+    }
+
+    /// Get the dictionary manager
+    pub fn dictionary_manager(&self) -> &DictionaryManager {
+        &self.dictionary_manager
     }
+
+    /// Download and load the Hunspell dictionary for a language
+    pub async fn install_dictionary(&self, language: &SpellLanguage) -> Result<(), WebxError> {
+        self.dictionary_manager.install(language).await?;
+        self.load_language(language)?;
+        Ok(())
+    }
+
+    /// Install and load a Hunspell dictionary from local .aff/.dic files
+    pub fn import_dictionary(
+        &self,
+        language: &SpellLanguage,
+        aff_path: &Path,
+        dic_path: &Path,
+    ) -> Result<(), WebxError> {
+        self.dictionary_manager.install_from_files(language, aff_path, dic_path)?;
+        self.load_language(language)?;
+        Ok(())
+    }
+
+    /// Remove an installed dictionary, falling back to a system copy if present
+    pub fn remove_dictionary(&self, language: &SpellLanguage) -> Result<bool, WebxError> {
+        let removed = self.dictionary_manager.uninstall(language)?;
+        self.dictionaries.lock_or_recover().remove(language);
+        self.load_language(language)?;
+        Ok(removed)
+    }
+
+    /// (Re)load the dictionary for a language. Returns false if none is available.
+    pub fn load_language(&self, language: &SpellLanguage) -> Result<bool, WebxError> {
+        match self.dictionary_manager.load(language)? {
+            Some(hunspell) => {
+                tracing::info!(
+                    "Loaded {} dictionary with {} stems",
+                    language.name(),
+                    hunspell.stem_count()
+                );
+                let mut dictionary = SpellDictionary::from_hunspell(language.clone(), hunspell);
+                dictionary.set_frequencies(self.dictionary_manager.load_frequencies(language)?);
+                self.dictionaries.lock_or_recover().insert(language.clone(), dictionary);
+                Ok(true)
+            }
+            None => {
+                if *language == SpellLanguage::EnglishUS {
+                    self.load_fallback_dictionary();
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Get user dictionary words
+    pub fn get_user_words(&self) -> Vec<String> {
+        let user_dict = self.user_dictionary.lock_or_recover();
+        user_dict.iter().cloned().collect()
+    }
+
+    /// Clear user dictionary
+    pub fn clear_user_dictionary(&self) -> Result<(), WebxError> {
+        self.user_dictionary.lock_or_recover().clear();
+        self.save_user_dictionary()?;
+        Ok(())
+    }
+
+    /// Get JavaScript for spell checking integration
+    pub fn get_spell_check_script(&self) -> String {
+        r#"
+(function() {
+    const spellChecker = {
+        checkText: function(text) {
+            // This would communicate with the Rust backend
+            // For now, we'll simulate the response
+            return [];
+        },
+        
+        addToDictionary: function(word) {
+            window.ipc.send({
+                type: 'spellcheck-add-word',
+                word: word
+            });
+        },
+        
+        getSuggestions: function(word) {
+            // Simulated suggestions
+            return ['suggestion1', 'suggestion2'];
+        }
+    };
+    
+    // Expose to global scope
+    window.spellChecker = spellChecker;
     
-    pub fn check_spelling(&self, _text: &str) -> Vec<String> {
-        Vec::new() // Placeholder implementation
+    // Monitor text inputs for spell checking
+    document.addEventListener('input', function(e) {
+        if (e.target.tagName === 'INPUT' || e.target.tagName === 'TEXTAREA') {
+            // Debounced spell checking would go here
+        }
+    });
+})();
+"#
+        .to_string()
+    }
+
+    // Private helper methods
+    
+    fn extract_words(&self, text: &str) -> Vec<(String, usize)> {
+        let mut words = Vec::new();
+        let mut current_word = String::new();
+        let mut start_pos = 0;
+        
+        for (i, ch) in text.char_indices() {
+            if ch.is_alphabetic() || ch == '\'' {
+                if current_word.is_empty() {
+                    start_pos = i;
+                }
+                current_word.push(ch);
+            } else if !current_word.is_empty() {
+                words.push((current_word.clone(), start_pos));
+                current_word.clear();
+            }
+        }
+        
+        // Don't forget the last word
+        if !current_word.is_empty() {
+            words.push((current_word, start_pos));
+        }
+        
+        words
+    }
+    
+    fn get_suggestions(
+        &self,
+        word: &str,
+        dictionaries: &HashMap<SpellLanguage, SpellDictionary>,
+        active_langs: &[SpellLanguage],
+    ) -> Vec<String> {
+        let mut ranked: Vec<RankedSuggestion> = active_langs
+            .iter()
+            .filter_map(|lang| dictionaries.get(lang))
+            .flat_map(|dict| dict.suggest(word, MAX_SUGGESTIONS))
+            .collect();
+        suggestions::sort_suggestions(&mut ranked);
+        
+        let mut suggestions: Vec<String> = Vec::new();
+        for suggestion in ranked {
+            let cased = match_case(word, &suggestion.word);
+            if !suggestions.contains(&cased) {
+                suggestions.push(cased);
+            }
+        }
+        
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+    
+    fn load_user_dictionary(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("user_dictionary.txt");
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let mut user_dict = self.user_dictionary.lock_or_recover();
+            for word in content.lines() {
+                let trimmed = word.trim();
+                if !trimmed.is_empty() {
+                    user_dict.insert(trimmed.to_lowercase());
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn save_user_dictionary(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("user_dictionary.txt");
+        let user_dict = self.user_dictionary.lock_or_recover();
+        let content = user_dict.iter().cloned().collect::<Vec<_>>().join("\n");
+        fs::write(path, content)?;
+        Ok(())
+    }
+    
+    fn save_config(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("config.json");
+        let active_langs = self.active_languages.lock_or_recover();
+        let config = serde_json::json!({
+            "active_languages": *active_langs
+        });
+        let content = serde_json::to_string_pretty(&config)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+    
+    fn load_config(&self) -> Result<(), WebxError> {
+        let path = self.config_dir.join("config.json");
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let config: serde_json::Value = serde_json::from_str(&content)?;
+            if let Some(languages) = config.get("active_languages") {
+                *self.active_languages.lock_or_recover() = serde_json::from_value(languages.clone())?;
+            }
+        }
+        Ok(())
+    }
+    
+    fn load_default_dictionaries(&self) -> Result<(), WebxError> {
+        let active_langs = self.get_active_languages();
+        
+        for language in &active_langs {
+            // A broken dictionary should not take spell checking down for other languages
+            if let Err(e) = self.load_language(language) {
+                tracing::warn!("Failed to load {} dictionary: {}", language.name(), e);
+            }
+        }
+        
+        if !self.dictionaries.lock_or_recover().contains_key(&SpellLanguage::EnglishUS) {
+            self.load_fallback_dictionary();
+        }
+        
+        Ok(())
+    }
+    
+    /// Small built-in English list used until a real dictionary is installed
+    fn load_fallback_dictionary(&self) {
+        let mut english_dict = SpellDictionary::new(SpellLanguage::EnglishUS);
+        let common_words = vec![
+            "the", "be", "to", "of", "and", "a", "in", "that", "have", "i",
+            "it", "for", "not", "on", "with", "he", "as", "you", "do", "at",
+            "this", "but", "his", "by", "from", "they", "we", "say", "her", "she",
+            "or", "an", "will", "my", "one", "all", "would", "there", "their",
+            "what", "so", "up", "out", "if", "about", "who", "get", "which", "go",
+            "me", "when", "make", "can", "like", "time", "no", "just", "him", "know",
+            "take", "people", "into", "year", "your", "good", "some", "could", "them",
+            "see", "other", "than", "then", "now", "look", "only", "come", "its",
+            "over", "think", "also", "back", "after", "use", "two", "how", "our",
+            "work", "first", "well", "way", "even", "new", "want", "because", "any",
+            "these", "give", "day", "most", "us", "is", "was", "are", "were", "been",
+            "being", "have", "has", "had", "having", "do", "does", "did", "doing",
+            "will", "would", "shall", "should", "may", "might", "must", "can", "could",
+        ];
+        
+        // The list is in descending frequency order
+        let total = common_words.len() as u64;
+        let mut frequencies = HashMap::new();
+        for (rank, word) in common_words.into_iter().enumerate() {
+            english_dict.add_word(word);
+            frequencies.entry(word.to_string()).or_insert(total - rank as u64);
+        }
+        english_dict.set_frequencies(frequencies);
+        
+        self.dictionaries
+            .lock_or_recover()
+            .insert(SpellLanguage::EnglishUS, english_dict);
+    }
+}
+
+impl SettingsProvider for SpellChecker {
+    fn module(&self) -> &str {
+        "spellcheck"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let languages: Vec<(&str, &str)> = SpellLanguage::all()
+            .iter()
+            .map(|lang| (lang.code(), lang.name()))
+            .collect();
+
+        vec![
+            SettingDefinition::choice(
+                "spellcheck.language",
+                "Spell check language",
+                SpellLanguage::EnglishUS.code(),
+                &languages,
+            )
+            .with_description("Language used to check spelling in text fields")
+            .with_category(SettingCategory::Languages),
+            SettingDefinition::text(
+                "spellcheck.dictionary_source",
+                "Dictionary download source",
+                dictionaries::DEFAULT_DICTIONARY_SOURCE,
+            )
+            .with_description("Base URL Hunspell dictionaries are downloaded from")
+            .with_category(SettingCategory::Advanced),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "spellcheck.language" => {
+                let code = value.as_str().ok_or("Expected a language code")?;
+                let language = SpellLanguage::from_code(code).ok_or("Unknown spell check language")?;
+                self.set_active_languages(vec![language])
+            }
+            "spellcheck.dictionary_source" => {
+                let url = value.as_str().ok_or("Expected a URL")?;
+                url::Url::parse(url)?;
+                self.dictionary_manager.set_source_url(url);
+                Ok(())
+            }
+            _ => Err(format!("Unknown setting {}", key).into()),
+        }
+    }
+}
+
+/// Apply the capitalization of the typed word to a lowercase suggestion
+fn match_case(typed: &str, suggestion: &str) -> String {
+    if suggestion.chars().any(|c| c.is_uppercase()) {
+        return suggestion.to_string();
+    }
+    
+    let letters: Vec<char> = typed.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        return suggestion.to_uppercase();
+    }
+    
+    let mut chars = suggestion.chars();
+    match (typed.chars().next(), chars.next()) {
+        (Some(first), Some(s)) if first.is_uppercase() => s.to_uppercase().chain(chars).collect(),
+        _ => suggestion.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_spell_checker_basic_functionality() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Test checking text with misspelled words
+        let results = checker.check_text("This is a tst with mispelled wrds.");
+        assert!(!results.is_empty());
+        
+        // Test user dictionary
+        checker.add_to_user_dictionary("tst").unwrap();
+        let results = checker.check_text("This is a tst.");
+        assert!(results.is_empty()); // Should be no errors now
+        
+        // Test removing from user dictionary
+        checker.remove_from_user_dictionary("tst").unwrap();
+        let results = checker.check_text("This is a tst.");
+        assert!(!results.is_empty()); // Should show error again
+    }
+
+    #[test]
+    fn test_language_management() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Test available languages
+        let languages = checker.get_available_languages();
+        assert!(!languages.is_empty());
+        assert!(languages.contains(&SpellLanguage::EnglishUS));
+        
+        // Test setting active languages
+        checker.set_active_languages(vec![SpellLanguage::Spanish]).unwrap();
+        let active = checker.get_active_languages();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0], SpellLanguage::Spanish);
+        
+        // Active languages survive a restart
+        let checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(checker.get_active_languages(), vec![SpellLanguage::Spanish]);
+    }
+
+    #[test]
+    fn test_imported_hunspell_dictionary() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        let aff_path = temp_dir.path().join("es.aff");
+        let dic_path = temp_dir.path().join("es.dic");
+        fs::write(&aff_path, "SET UTF-8\nSFX S Y 2\nSFX S 0 s [aeiou]\nSFX S ón ones ón\n").unwrap();
+        fs::write(&dic_path, "3\ncasa/S\ncanción/S\nuna\n").unwrap();
+        
+        checker.import_dictionary(&SpellLanguage::Spanish, &aff_path, &dic_path).unwrap();
+        checker.set_active_languages(vec![SpellLanguage::Spanish]).unwrap();
+        
+        // Inflected forms are recognized through the affix rules
+        assert!(checker.check_text("Una casa, casas y canciones").iter().all(|r| r.word == "y"));
+        
+        let results = checker.check_text("cassa");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].suggestions.contains(&"casa".to_string()));
+    }
+
+    #[test]
+    fn test_registered_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = Arc::new(SpellChecker::new(Some(temp_dir.path().join("spellcheck"))).unwrap());
+        let registry = crate::config::SettingsRegistry::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        registry.register(checker.clone()).unwrap();
+        
+        registry
+            .set("spellcheck.language", SettingValue::String("fr".to_string()))
+            .unwrap();
+        assert_eq!(checker.get_active_languages(), vec![SpellLanguage::French]);
+        
+        // Values the module rejects are not stored
+        assert!(registry
+            .set("spellcheck.dictionary_source", SettingValue::String("not a url".to_string()))
+            .is_err());
+        assert_eq!(
+            registry.get_string("spellcheck.dictionary_source").unwrap(),
+            dictionaries::DEFAULT_DICTIONARY_SOURCE
+        );
+    }
+
+    #[test]
+    fn test_suggestion_ranking() {
+        let temp_dir = TempDir::new().unwrap();
+        let checker = SpellChecker::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        
+        // Swapped letters rank the intended word first, ties follow frequency
+        let results = checker.check_text("teh");
+        assert_eq!(results[0].suggestions[0], "the");
+        assert_eq!(results[0].suggestions, checker.check_text("teh")[0].suggestions);
+        
+        // Suggestions follow the capitalization of the typed word
+        let results = checker.check_text("Becuase WTIH");
+        assert_eq!(results[0].suggestions[0], "Because");
+        assert_eq!(results[1].suggestions[0], "WITH");
     }
 }
\ No newline at end of file
@@ -0,0 +1,361 @@
+// Spell Check Suggestion Ranking
+use std::collections::HashMap;
+
+/// Largest edit distance considered when looking for suggestions
+pub const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// How much a word's frequency can lower its score; kept below half an edit
+/// so a common word never outranks a strictly closer one
+const MAX_FREQUENCY_BONUS: f64 = 0.45;
+const FREQUENCY_WEIGHT: f64 = 0.05;
+
+/// Cost of hitting a neighbouring key or swapping two letters
+const TYPO_COST: f64 = 0.5;
+
+/// A ranked suggestion (lower score is better)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedSuggestion {
+    pub word: String,
+    pub score: f64,
+}
+
+struct BkNode {
+    word: String,
+    key: Vec<char>,
+    removed: bool,
+    children: HashMap<usize, usize>,
+}
+
+/// BK-tree over Levenshtein distance for fast near-match lookup
+#[derive(Default)]
+pub struct BkTree {
+    nodes: Vec<BkNode>,
+    lookup: HashMap<String, usize>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a word (duplicates are ignored)
+    pub fn insert(&mut self, word: &str) {
+        if let Some(&index) = self.lookup.get(word) {
+            self.nodes[index].removed = false;
+            return;
+        }
+
+        let key: Vec<char> = word.to_lowercase().chars().collect();
+        let new_index = self.nodes.len();
+        let mut current = 0;
+
+        if !self.nodes.is_empty() {
+            loop {
+                let distance = levenshtein(&self.nodes[current].key, &key);
+                match self.nodes[current].children.get(&distance) {
+                    Some(&child) => current = child,
+                    None => {
+                        self.nodes[current].children.insert(distance, new_index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.nodes.push(BkNode {
+            word: word.to_string(),
+            key,
+            removed: false,
+            children: HashMap::new(),
+        });
+        self.lookup.insert(word.to_string(), new_index);
+    }
+
+    /// Remove a word. Nodes stay in place to keep the tree structure valid.
+    pub fn remove(&mut self, word: &str) -> bool {
+        match self.lookup.get(word) {
+            Some(&index) if !self.nodes[index].removed => {
+                self.nodes[index].removed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of words in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.iter().filter(|n| !n.removed).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All words within `max_distance` of `query`, with their distance
+    pub fn find(&self, query: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let mut matches = Vec::new();
+        if self.nodes.is_empty() {
+            return matches;
+        }
+
+        let query: Vec<char> = query.to_lowercase().chars().collect();
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = levenshtein(&node.key, &query);
+
+            if distance <= max_distance && !node.removed {
+                matches.push((node.word.as_str(), distance));
+            }
+
+            // Triangle inequality: only children in [d - max, d + max] can match
+            let low = distance.saturating_sub(max_distance);
+            let high = distance + max_distance;
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| **d >= low && **d <= high)
+                    .map(|(_, child)| *child),
+            );
+        }
+
+        matches
+    }
+}
+
+/// Candidate index plus word frequencies for one dictionary
+#[derive(Default)]
+pub struct SuggestionIndex {
+    tree: BkTree,
+    frequencies: HashMap<String, u64>,
+}
+
+impl SuggestionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.tree.insert(word);
+    }
+
+    pub fn remove(&mut self, word: &str) -> bool {
+        self.tree.remove(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Set how often a word occurs in typical text
+    pub fn set_frequency(&mut self, word: &str, count: u64) {
+        self.frequencies.insert(word.to_lowercase(), count);
+    }
+
+    /// Merge a frequency table
+    pub fn extend_frequencies(&mut self, frequencies: HashMap<String, u64>) {
+        for (word, count) in frequencies {
+            self.set_frequency(&word, count);
+        }
+    }
+
+    pub fn frequency(&self, word: &str) -> u64 {
+        self.frequencies.get(&word.to_lowercase()).copied().unwrap_or(0)
+    }
+
+    /// Suggestions for a misspelled word, best first
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<RankedSuggestion> {
+        let typed: Vec<char> = word.to_lowercase().chars().collect();
+
+        let mut ranked: Vec<RankedSuggestion> = self
+            .tree
+            .find(word, MAX_SUGGESTION_DISTANCE)
+            .into_iter()
+            .filter(|(_, distance)| *distance > 0)
+            .map(|(candidate, _)| {
+                let key: Vec<char> = candidate.to_lowercase().chars().collect();
+                let frequency_bonus =
+                    (FREQUENCY_WEIGHT * (1.0 + self.frequency(candidate) as f64).log10()).min(MAX_FREQUENCY_BONUS);
+                RankedSuggestion {
+                    word: candidate.to_string(),
+                    score: typo_distance(&typed, &key) - frequency_bonus,
+                }
+            })
+            .collect();
+
+        sort_suggestions(&mut ranked);
+        ranked.truncate(limit);
+        ranked
+    }
+}
+
+/// Order suggestions by score, then alphabetically so results are deterministic
+pub fn sort_suggestions(suggestions: &mut [RankedSuggestion]) {
+    suggestions.sort_by(|a, b| a.score.total_cmp(&b.score).then_with(|| a.word.cmp(&b.word)));
+}
+
+/// Parse a frequency list: "word count" per line, or bare words in
+/// descending frequency order
+pub fn parse_frequency_list(content: &str) -> HashMap<String, u64> {
+    let lines: Vec<&str> = content.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let total = lines.len() as u64;
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, line)| {
+            let mut parts = line.split_whitespace();
+            let word = parts.next()?.to_lowercase();
+            let count = match parts.next() {
+                Some(count) => count.parse().ok()?,
+                None => total - rank as u64,
+            };
+            Some((word, count))
+        })
+        .collect()
+}
+
+/// Plain Levenshtein distance
+pub fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j + 1] + 1).min(current[j] + 1).min(previous[j] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Edit distance where neighbouring-key substitutions and swapped letters
+/// cost less than other edits, matching how people actually mistype
+pub fn typo_distance(typed: &[char], candidate: &[char]) -> f64 {
+    let rows = typed.len() + 1;
+    let cols = candidate.len() + 1;
+    let mut matrix = vec![vec![0.0; cols]; rows];
+
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i as f64;
+    }
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j as f64;
+    }
+
+    for i in 1..rows {
+        for j in 1..cols {
+            let (a, b) = (typed[i - 1], candidate[j - 1]);
+            let substitution = if a == b {
+                0.0
+            } else if keys_adjacent(a, b) {
+                TYPO_COST
+            } else {
+                1.0
+            };
+
+            let mut best = (matrix[i - 1][j] + 1.0)
+                .min(matrix[i][j - 1] + 1.0)
+                .min(matrix[i - 1][j - 1] + substitution);
+
+            if i > 1 && j > 1 && a == candidate[j - 2] && typed[i - 2] == b {
+                best = best.min(matrix[i - 2][j - 2] + TYPO_COST);
+            }
+
+            matrix[i][j] = best;
+        }
+    }
+
+    matrix[rows - 1][cols - 1]
+}
+
+/// Whether two letters are next to each other on a QWERTY keyboard
+pub fn keys_adjacent(a: char, b: char) -> bool {
+    match (key_position(a), key_position(b)) {
+        (Some((ra, ca)), Some((rb, cb))) => {
+            let dr = ra - rb;
+            let dc = ca - cb;
+            (dr * dr + dc * dc).sqrt() <= 1.2
+        }
+        _ => false,
+    }
+}
+
+fn key_position(ch: char) -> Option<(f64, f64)> {
+    // Each row is staggered half a key to the right of the one above
+    const ROWS: [(&str, f64); 3] = [("qwertyuiop", 0.0), ("asdfghjkl", 0.5), ("zxcvbnm", 1.0)];
+
+    let ch = ch.to_ascii_lowercase();
+    ROWS.iter().enumerate().find_map(|(row, (keys, offset))| {
+        keys.find(ch).map(|col| (row as f64, col as f64 + offset))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_bk_tree_lookup() {
+        let mut tree = BkTree::new();
+        for word in ["book", "books", "cake", "boo", "boon", "cook", "cape", "cart"] {
+            tree.insert(word);
+        }
+
+        let mut found: Vec<&str> = tree.find("bo", 1).into_iter().map(|(w, _)| w).collect();
+        found.sort();
+        assert_eq!(found, vec!["boo"]);
+
+        let mut found: Vec<&str> = tree.find("boko", 2).into_iter().map(|(w, _)| w).collect();
+        found.sort();
+        assert_eq!(found, vec!["boo", "book", "books", "boon"]);
+
+        assert!(tree.remove("boo"));
+        assert!(!tree.find("bo", 1).iter().any(|(w, _)| *w == "boo"));
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn test_typo_distance() {
+        assert!(keys_adjacent('h', 'n'));
+        assert!(keys_adjacent('h', 'u'));
+        assert!(!keys_adjacent('h', 't'));
+        assert!(keys_adjacent('s', 'd'));
+        assert!(!keys_adjacent('a', 'p'));
+
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(typo_distance(&chars("teh"), &chars("the")), TYPO_COST);
+        assert_eq!(typo_distance(&chars("ten"), &chars("teh")), TYPO_COST);
+        assert_eq!(typo_distance(&chars("tex"), &chars("teh")), 1.0);
+    }
+
+    #[test]
+    fn test_ranking_by_frequency_and_keyboard() {
+        let mut index = SuggestionIndex::new();
+        for word in ["the", "ten", "tea", "then", "thee"] {
+            index.insert(word);
+        }
+        index.extend_frequencies(parse_frequency_list("the 5000000\nten 20000\nthen 900000\ntea 15000"));
+
+        // Swapped letters and neighbouring keys beat arbitrary edits,
+        // frequency breaks near-ties
+        let words: Vec<String> = index.suggest("teh", 5).into_iter().map(|s| s.word).collect();
+        assert_eq!(words, vec!["the", "ten", "tea", "then", "thee"]);
+
+        // Results are stable across calls
+        let again: Vec<String> = index.suggest("teh", 5).into_iter().map(|s| s.word).collect();
+        assert_eq!(words, again);
+    }
+}
@@ -0,0 +1,428 @@
+// Accessibility Settings
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::LockExt;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Largest minimum font size the user may set, in CSS pixels
+pub const MAX_MINIMUM_FONT_PX: u32 = 48;
+
+/// Script that applies the accessibility CSS and minimum font size to each
+/// page, asking the browser for them as the page loads and again when
+/// `window.webxAccessibility.refresh()` runs
+pub const ACCESSIBILITY_SCRIPT: &str = r#"
+(function() {
+    if (window.webxAccessibility) return;
+    let style = null, observer = null, minimum = 0;
+    const enlarge = (root) => {
+        if (!minimum || !root.querySelectorAll) return;
+        for (const element of [root, ...root.querySelectorAll('*')]) {
+            if (!element.childElementCount && !element.textContent.trim()) continue;
+            if (parseFloat(getComputedStyle(element).fontSize) < minimum) {
+                element.style.setProperty('font-size', minimum + 'px', 'important');
+                element.dataset.webxMinFont = '';
+            }
+        }
+    };
+    const apply = (settings) => {
+        if (!style) {
+            style = document.createElement('style');
+            style.id = 'webx-accessibility';
+        }
+        style.textContent = settings.css || '';
+        if (!style.isConnected) (document.head || document.documentElement).append(style);
+        for (const element of document.querySelectorAll('[data-webx-min-font]')) {
+            element.style.removeProperty('font-size');
+            delete element.dataset.webxMinFont;
+        }
+        minimum = settings.minimumFontPx || 0;
+        if (observer) observer.disconnect();
+        observer = null;
+        if (!minimum) return;
+        enlarge(document.documentElement);
+        observer = new MutationObserver((mutations) => {
+            for (const mutation of mutations) mutation.addedNodes.forEach(enlarge);
+        });
+        observer.observe(document.documentElement, { childList: true, subtree: true });
+    };
+    const refresh = () => {
+        window.ipc.request({ type: 'accessibility' }).then(apply).catch(() => {});
+    };
+    window.webxAccessibility = { refresh: refresh };
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', refresh);
+    } else {
+        refresh();
+    }
+})();
+"#;
+
+/// Script re-applying changed accessibility settings to an open page
+pub const ACCESSIBILITY_REFRESH_SCRIPT: &str = "window.webxAccessibility && window.webxAccessibility.refresh();";
+
+/// High-contrast color scheme forced on the browser and pages
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContrastTheme {
+    #[default]
+    Off,
+    BlackOnWhite,
+    WhiteOnBlack,
+    YellowOnBlack,
+}
+
+/// Colors of a high-contrast theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContrastPalette {
+    pub background: &'static str,
+    pub text: &'static str,
+    pub link: &'static str,
+    pub visited: &'static str,
+    /// Focus outlines, selections and the accent of the browser chrome
+    pub accent: &'static str,
+}
+
+impl ContrastTheme {
+    pub const ALL: [ContrastTheme; 4] = [
+        ContrastTheme::Off,
+        ContrastTheme::BlackOnWhite,
+        ContrastTheme::WhiteOnBlack,
+        ContrastTheme::YellowOnBlack,
+    ];
+
+    /// Name used in settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContrastTheme::Off => "off",
+            ContrastTheme::BlackOnWhite => "black_on_white",
+            ContrastTheme::WhiteOnBlack => "white_on_black",
+            ContrastTheme::YellowOnBlack => "yellow_on_black",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContrastTheme::Off => "Off",
+            ContrastTheme::BlackOnWhite => "Black on white",
+            ContrastTheme::WhiteOnBlack => "White on black",
+            ContrastTheme::YellowOnBlack => "Yellow on black",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    /// Colors of the theme, `None` when it is off
+    pub fn palette(&self) -> Option<ContrastPalette> {
+        match self {
+            ContrastTheme::Off => None,
+            ContrastTheme::BlackOnWhite => Some(ContrastPalette {
+                background: "#ffffff",
+                text: "#000000",
+                link: "#0000c0",
+                visited: "#5a0080",
+                accent: "#0000c0",
+            }),
+            ContrastTheme::WhiteOnBlack => Some(ContrastPalette {
+                background: "#000000",
+                text: "#ffffff",
+                link: "#7fd4ff",
+                visited: "#d8a6ff",
+                accent: "#00e5ff",
+            }),
+            ContrastTheme::YellowOnBlack => Some(ContrastPalette {
+                background: "#000000",
+                text: "#ffff00",
+                link: "#00ffff",
+                visited: "#ff9cff",
+                accent: "#00ff00",
+            }),
+        }
+    }
+}
+
+/// Accessibility options applied to the browser and every page
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    /// Text smaller than this many CSS pixels is enlarged; 0 leaves sizes alone
+    pub minimum_font_px: u32,
+    pub contrast: ContrastTheme,
+    /// Stop animations, transitions and smooth scrolling
+    pub reduce_motion: bool,
+    /// Outline the focused element even where a page hides outlines
+    pub always_show_focus: bool,
+}
+
+/// What the page script is told to apply
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageAccessibility {
+    pub css: String,
+    pub minimum_font_px: u32,
+}
+
+/// Keeps the accessibility options and turns them into page CSS
+pub struct Accessibility {
+    config: Mutex<AccessibilityConfig>,
+    config_path: PathBuf,
+}
+
+impl Accessibility {
+    /// Load the options kept in `config_dir`, the themes folder by default
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("themes");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let config_path = config_dir.join("accessibility.json");
+        let config = match std::fs::read_to_string(&config_path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => AccessibilityConfig::default(),
+        };
+        Ok(Self {
+            config: Mutex::new(config),
+            config_path,
+        })
+    }
+
+    pub fn config(&self) -> AccessibilityConfig {
+        self.config.lock_or_recover().clone()
+    }
+
+    /// Change the options and save them
+    pub fn set_config(&self, config: AccessibilityConfig) -> Result<(), WebxError> {
+        self.update(|current| *current = config)
+    }
+
+    /// CSS forcing the options onto a page; empty when all are off
+    pub fn page_css(&self) -> String {
+        let config = self.config();
+        let mut css = String::new();
+        if let Some(palette) = config.contrast.palette() {
+            css.push_str(&format!(
+                r#"
+*, *::before, *::after {{
+    background-color: {background} !important;
+    background-image: none !important;
+    color: {text} !important;
+    border-color: {text} !important;
+    text-shadow: none !important;
+    box-shadow: none !important;
+}}
+a:link, a:link * {{ color: {link} !important; text-decoration: underline !important; }}
+a:visited, a:visited * {{ color: {visited} !important; }}
+::selection {{ background-color: {accent} !important; color: {background} !important; }}
+img, video, canvas, svg {{ filter: none !important; opacity: 1 !important; }}
+"#,
+                background = palette.background,
+                text = palette.text,
+                link = palette.link,
+                visited = palette.visited,
+                accent = palette.accent,
+            ));
+        }
+        if config.reduce_motion {
+            css.push_str(
+                r#"
+*, *::before, *::after {
+    animation-duration: 0.01ms !important;
+    animation-iteration-count: 1 !important;
+    animation-delay: 0s !important;
+    transition-duration: 0.01ms !important;
+    transition-delay: 0s !important;
+    scroll-behavior: auto !important;
+}
+"#,
+            );
+        }
+        if config.always_show_focus {
+            let accent = config.contrast.palette().map_or("#1a73e8", |palette| palette.accent);
+            css.push_str(&format!(
+                r#"
+:focus {{ outline: 3px solid {accent} !important; outline-offset: 2px !important; }}
+"#
+            ));
+        }
+        css
+    }
+
+    /// What the page script applies
+    pub fn page_settings(&self) -> PageAccessibility {
+        PageAccessibility {
+            css: self.page_css(),
+            minimum_font_px: self.config().minimum_font_px,
+        }
+    }
+
+    /// Theme variables of the browser chrome for the high-contrast theme,
+    /// `None` when it is off
+    pub fn chrome_css_variables(&self) -> Option<String> {
+        let palette = self.config().contrast.palette()?;
+        Some(format!(
+            r#"
+:root {{
+    /* High-contrast theme colors */
+    --bg-primary: {background};
+    --bg-secondary: {background};
+    --bg-tertiary: {background};
+    --text-primary: {text};
+    --text-secondary: {text};
+    --accent-primary: {accent};
+    --accent-hover: {link};
+    --border-primary: {text};
+    --success: {text};
+    --warning: {text};
+    --error: {text};
+    --shadow: none;
+}}
+"#,
+            background = palette.background,
+            text = palette.text,
+            accent = palette.accent,
+            link = palette.link,
+        ))
+    }
+
+    // Private helper methods
+
+    fn update(&self, update: impl FnOnce(&mut AccessibilityConfig)) -> Result<(), WebxError> {
+        let mut config = self.config.lock_or_recover();
+        let mut changed = config.clone();
+        update(&mut changed);
+        if changed.minimum_font_px > MAX_MINIMUM_FONT_PX {
+            return Err(WebxError::Invalid(format!(
+                "Minimum font size must be at most {} pixels",
+                MAX_MINIMUM_FONT_PX
+            )));
+        }
+        write_atomic(&self.config_path, &serde_json::to_vec_pretty(&changed)?)?;
+        *config = changed;
+        Ok(())
+    }
+}
+
+impl IpcHandler for Accessibility {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["accessibility"]
+    }
+
+    fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::Accessibility => Ok(serde_json::to_value(self.page_settings())?),
+            _ => Err(WebxError::Invalid("Not an accessibility message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for Accessibility {
+    fn module(&self) -> &str {
+        "accessibility"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let themes: Vec<(&str, &str)> = ContrastTheme::ALL.iter().map(|theme| (theme.name(), theme.label())).collect();
+        vec![
+            SettingDefinition::integer(
+                "accessibility.minimum_font_size",
+                "Minimum font size",
+                0,
+                0,
+                MAX_MINIMUM_FONT_PX as i64,
+            )
+            .with_description("Enlarge page text smaller than this many pixels; 0 leaves text as pages set it")
+            .with_category(SettingCategory::Appearance),
+            SettingDefinition::choice("accessibility.contrast", "High contrast", ContrastTheme::Off.name(), &themes)
+                .with_description("Force high-contrast colors on the browser and every page")
+                .with_category(SettingCategory::Appearance),
+            SettingDefinition::toggle("accessibility.reduce_motion", "Reduce motion", false)
+                .with_description("Stop animations, transitions and smooth scrolling on pages")
+                .with_category(SettingCategory::Appearance),
+            SettingDefinition::toggle("accessibility.focus_outlines", "Always show focus outlines", false)
+                .with_description("Outline the focused element even on pages that hide outlines")
+                .with_category(SettingCategory::Appearance),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "accessibility.minimum_font_size" => {
+                let size = value.as_i64().ok_or("Expected a number")?;
+                self.update(|config| config.minimum_font_px = size.max(0) as u32)
+            }
+            "accessibility.contrast" => {
+                let name = value.as_str().ok_or("Expected a contrast theme")?;
+                let contrast = ContrastTheme::from_name(name).ok_or("Unknown contrast theme")?;
+                self.update(|config| config.contrast = contrast)
+            }
+            "accessibility.reduce_motion" => {
+                let reduce = value.as_bool().ok_or("Expected a toggle value")?;
+                self.update(|config| config.reduce_motion = reduce)
+            }
+            "accessibility.focus_outlines" => {
+                let always = value.as_bool().ok_or("Expected a toggle value")?;
+                self.update(|config| config.always_show_focus = always)
+            }
+            _ => Err(format!("Unknown accessibility setting {}", key).into()),
+        }
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        let config = self.config();
+        match key {
+            "accessibility.minimum_font_size" => Some(SettingValue::Integer(config.minimum_font_px as i64)),
+            "accessibility.contrast" => Some(SettingValue::String(config.contrast.name().to_string())),
+            "accessibility.reduce_motion" => Some(SettingValue::Bool(config.reduce_motion)),
+            "accessibility.focus_outlines" => Some(SettingValue::Bool(config.always_show_focus)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_options_generate_page_css_and_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let accessibility = Accessibility::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(accessibility.page_css().is_empty());
+        assert!(accessibility.chrome_css_variables().is_none());
+
+        accessibility
+            .apply_setting("accessibility.contrast", &SettingValue::String("yellow_on_black".to_string()))
+            .unwrap();
+        accessibility.apply_setting("accessibility.reduce_motion", &SettingValue::Bool(true)).unwrap();
+        accessibility.apply_setting("accessibility.focus_outlines", &SettingValue::Bool(true)).unwrap();
+        accessibility.apply_setting("accessibility.minimum_font_size", &SettingValue::Integer(14)).unwrap();
+        assert!(accessibility
+            .apply_setting("accessibility.minimum_font_size", &SettingValue::Integer(200))
+            .is_err());
+
+        let page = accessibility.page_settings();
+        assert_eq!(page.minimum_font_px, 14);
+        assert!(page.css.contains("color: #ffff00 !important"));
+        assert!(page.css.contains("animation-duration: 0.01ms"));
+        assert!(page.css.contains(":focus { outline: 3px solid #00ff00"));
+        assert!(accessibility.chrome_css_variables().unwrap().contains("--bg-primary: #000000;"));
+
+        // The options survive a restart
+        let accessibility = Accessibility::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(accessibility.config().contrast, ContrastTheme::YellowOnBlack);
+        assert_eq!(
+            accessibility.current_value("accessibility.minimum_font_size"),
+            Some(SettingValue::Integer(14))
+        );
+    }
+}
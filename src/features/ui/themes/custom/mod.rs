@@ -1,13 +1,357 @@
-// Custom Themes Module - Placeholder
-// TODO: Implement custom theme functionality
-pub struct CustomThemeManager;
+// Custom Themes Module
+pub mod theme;
+
+pub use theme::{AccentColors, CustomTheme, Severity, ThemeBase, ThemeDiagnostic, ThemeValidationError};
+
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Theme changes to broadcast to open tabs and the browser chrome
+#[derive(Debug, Clone)]
+pub enum ThemeEvent {
+    /// A theme file was added or edited and loaded successfully
+    ThemeLoaded(String),
+    /// A theme file was deleted
+    ThemeRemoved(String),
+    /// A theme file failed to load; the previous version stays in use
+    ThemeInvalid { id: String, diagnostics: Vec<ThemeDiagnostic> },
+    /// The active theme changed or was edited; `css` should be applied everywhere
+    ActiveThemeChanged { id: Option<String>, css: Option<String> },
+}
+
+/// Loads user themes from a directory and hot-reloads them on change
+pub struct CustomThemeManager {
+    themes_dir: PathBuf,
+    themes: Arc<Mutex<HashMap<String, CustomTheme>>>,
+    active_theme: Arc<Mutex<Option<String>>>,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    tx: mpsc::UnboundedSender<ThemeEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ThemeEvent>>>>,
+}
 
 impl CustomThemeManager {
-    pub fn new() -> Self {
-        Self
+    /// Create new custom theme manager and load every theme in `themes_dir`
+    pub fn new(themes_dir: PathBuf) -> Result<Self, WebxError> {
+        std::fs::create_dir_all(&themes_dir)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let manager = Self {
+            themes_dir,
+            themes: Arc::new(Mutex::new(HashMap::new())),
+            active_theme: Arc::new(Mutex::new(None)),
+            watcher: Mutex::new(None),
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        };
+
+        manager.reload_all();
+
+        Ok(manager)
+    }
+
+    /// Directory themes are loaded from
+    pub fn themes_dir(&self) -> &PathBuf {
+        &self.themes_dir
     }
-    
-    pub fn load_custom_theme(&self, _theme_name: &str) {
-        // Placeholder implementation
+
+    /// Load a theme file, returning every problem found if it cannot be used
+    pub fn load_theme_file(path: &Path) -> Result<CustomTheme, ThemeValidationError> {
+        let invalid = |diagnostics| ThemeValidationError {
+            path: path.to_path_buf(),
+            diagnostics,
+        };
+
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            invalid(vec![ThemeDiagnostic {
+                severity: Severity::Error,
+                field: "file".to_string(),
+                message: e.to_string(),
+            }])
+        })?;
+
+        let theme = CustomTheme::parse(&content, path).map_err(|d| invalid(vec![d]))?;
+        let diagnostics = theme.validate();
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(invalid(diagnostics));
+        }
+
+        for warning in &diagnostics {
+            tracing::warn!("Theme {}: {}", path.display(), warning);
+        }
+        Ok(theme)
+    }
+
+    /// Validate a theme file without installing it (errors and warnings)
+    pub fn validate_theme_file(path: &Path) -> Vec<ThemeDiagnostic> {
+        match Self::load_theme_file(path) {
+            Ok(theme) => theme.validate(),
+            Err(e) => e.diagnostics,
+        }
+    }
+
+    /// Copy a theme file into the themes directory and load it
+    pub fn install_theme(&self, path: &Path) -> Result<String, WebxError> {
+        Self::load_theme_file(path)?;
+
+        let file_name = path.file_name().ok_or("Theme path has no file name")?;
+        let target = self.themes_dir.join(file_name);
+        std::fs::copy(path, &target)?;
+
+        Self::reload_path(&self.themes, &self.active_theme, &self.tx, &target);
+        theme_id(&target).ok_or_else(|| "Invalid theme file name".into())
+    }
+
+    /// Load a theme by ID (the file name without extension)
+    pub fn load_custom_theme(&self, theme_id: &str) -> Option<CustomTheme> {
+        self.themes.lock_or_recover().get(theme_id).cloned()
+    }
+
+    /// IDs and display names of all loaded themes
+    pub fn list_themes(&self) -> Vec<(String, String)> {
+        let mut themes: Vec<(String, String)> = self
+            .themes
+            .lock_or_recover()
+            .iter()
+            .map(|(id, theme)| (id.clone(), theme.name.clone()))
+            .collect();
+        themes.sort();
+        themes
+    }
+
+    /// CSS for a loaded theme
+    pub fn get_css(&self, theme_id: &str) -> Option<String> {
+        self.load_custom_theme(theme_id).map(|t| t.to_css())
+    }
+
+    /// Make a theme the active one and broadcast it
+    pub fn activate(&self, theme_id: &str) -> Result<(), WebxError> {
+        let css = self.get_css(theme_id).ok_or_else(|| WebxError::NotFound(format!("Theme {}", theme_id)))?;
+        *self.active_theme.lock_or_recover() = Some(theme_id.to_string());
+        let _ = self.tx.send(ThemeEvent::ActiveThemeChanged {
+            id: Some(theme_id.to_string()),
+            css: Some(css),
+        });
+        Ok(())
+    }
+
+    /// Stop using a custom theme
+    pub fn deactivate(&self) {
+        if self.active_theme.lock_or_recover().take().is_some() {
+            let _ = self.tx.send(ThemeEvent::ActiveThemeChanged { id: None, css: None });
+        }
+    }
+
+    /// Currently active custom theme ID
+    pub fn get_active_theme(&self) -> Option<String> {
+        self.active_theme.lock_or_recover().clone()
+    }
+
+    /// Re-read every theme file in the themes directory
+    pub fn reload_all(&self) {
+        self.themes.lock_or_recover().clear();
+
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&self.themes_dir) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => return,
+        };
+        paths.sort();
+
+        for path in paths {
+            Self::reload_path(&self.themes, &self.active_theme, &self.tx, &path);
+        }
+    }
+
+    /// Watch the themes directory and hot-reload themes as they are edited
+    pub fn start_watching(&self) -> Result<(), WebxError> {
+        let themes = self.themes.clone();
+        let active_theme = self.active_theme.clone();
+        let tx = self.tx.clone();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if !event.kind.is_access() => {
+                    for path in &event.paths {
+                        Self::reload_path(&themes, &active_theme, &tx, path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Theme watcher error: {}", e),
+            }
+        })?;
+        watcher.watch(&self.themes_dir, RecursiveMode::NonRecursive)?;
+
+        *self.watcher.lock_or_recover() = Some(watcher);
+        Ok(())
+    }
+
+    /// Stop hot-reloading themes
+    pub fn stop_watching(&self) {
+        self.watcher.lock_or_recover().take();
+    }
+
+    /// Check if the themes directory is being watched
+    pub fn is_watching(&self) -> bool {
+        self.watcher.lock_or_recover().is_some()
+    }
+
+    /// Script that swaps the theme stylesheet in a page and notifies listeners
+    pub fn get_apply_script(css: Option<&str>) -> String {
+        let css = serde_json::to_string(&css).unwrap_or_else(|_| "null".to_string());
+        format!(
+            r#"
+(function() {{
+    const css = {};
+    let style = document.getElementById('webx-custom-theme');
+    if (css === null) {{
+        if (style) style.remove();
+    }} else {{
+        if (!style) {{
+            style = document.createElement('style');
+            style.id = 'webx-custom-theme';
+            (document.head || document.documentElement).appendChild(style);
+        }}
+        style.textContent = css;
+    }}
+    window.dispatchEvent(new CustomEvent('webx-theme-change', {{ detail: {{ active: css !== null }} }}));
+}})();
+"#,
+            css
+        )
+    }
+
+    /// Subscribe to theme events
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<ThemeEvent> {
+        self.rx.lock_or_recover().take().unwrap()
+    }
+
+    // Private helper methods
+
+    fn reload_path(
+        themes: &Mutex<HashMap<String, CustomTheme>>,
+        active_theme: &Mutex<Option<String>>,
+        tx: &mpsc::UnboundedSender<ThemeEvent>,
+        path: &Path,
+    ) {
+        let Some(id) = theme_id(path) else {
+            return;
+        };
+
+        let event = if path.exists() {
+            match Self::load_theme_file(path) {
+                Ok(theme) => {
+                    themes.lock_or_recover().insert(id.clone(), theme);
+                    ThemeEvent::ThemeLoaded(id.clone())
+                }
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    let _ = tx.send(ThemeEvent::ThemeInvalid {
+                        id,
+                        diagnostics: e.diagnostics,
+                    });
+                    return;
+                }
+            }
+        } else if themes.lock_or_recover().remove(&id).is_some() {
+            ThemeEvent::ThemeRemoved(id.clone())
+        } else {
+            return;
+        };
+        let _ = tx.send(event);
+
+        // Edits to the active theme are pushed to open tabs right away
+        let mut active = active_theme.lock_or_recover();
+        if active.as_deref() == Some(id.as_str()) {
+            let css = themes.lock_or_recover().get(&id).map(|t| t.to_css());
+            if css.is_none() {
+                *active = None;
+            }
+            let _ = tx.send(ThemeEvent::ActiveThemeChanged { id: active.clone(), css });
+        }
+    }
+}
+
+/// Theme ID for a theme file path, or None if the file is not a theme
+fn theme_id(path: &Path) -> Option<String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") | Some("toml") => path.file_stem().and_then(|s| s.to_str()).map(str::to_string),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const THEME: &str = r##"{ "name": "Midnight", "colors": { "bg-primary": "#000010" } }"##;
+
+    #[test]
+    fn test_load_and_activate() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("midnight.json"), THEME).unwrap();
+        std::fs::write(temp_dir.path().join("broken.json"), r#"{ "name": "" }"#).unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "not a theme").unwrap();
+
+        let manager = CustomThemeManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut events = manager.subscribe_events();
+        assert_eq!(manager.list_themes(), vec![("midnight".to_string(), "Midnight".to_string())]);
+
+        let diagnostics = CustomThemeManager::validate_theme_file(&temp_dir.path().join("broken.json"));
+        assert_eq!(diagnostics[0].field, "name");
+
+        // Loading the directory reported both files
+        assert!(matches!(events.try_recv().unwrap(), ThemeEvent::ThemeInvalid { .. }));
+        assert!(matches!(events.try_recv().unwrap(), ThemeEvent::ThemeLoaded(_)));
+
+        manager.activate("midnight").unwrap();
+        match events.try_recv().unwrap() {
+            ThemeEvent::ActiveThemeChanged { id, css } => {
+                assert_eq!(id.as_deref(), Some("midnight"));
+                assert!(css.unwrap().contains("--bg-primary: #000010;"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(manager.activate("missing").is_err());
+
+        let script = CustomThemeManager::get_apply_script(Some(":root { --bg-primary: #000010; }"));
+        assert!(script.contains("webx-theme-change"));
+    }
+
+    #[test]
+    fn test_hot_reload_active_theme() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("midnight.json");
+        std::fs::write(&path, THEME).unwrap();
+
+        let manager = CustomThemeManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut events = manager.subscribe_events();
+        manager.activate("midnight").unwrap();
+        manager.start_watching().unwrap();
+        assert!(manager.is_watching());
+        while events.try_recv().is_ok() {}
+
+        std::fs::write(&path, THEME.replace("#000010", "#101020")).unwrap();
+
+        // Wait for the watcher to pick up the edit
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut reloaded_css = None;
+        while reloaded_css.is_none() && std::time::Instant::now() < deadline {
+            match events.try_recv() {
+                Ok(ThemeEvent::ActiveThemeChanged { css, .. }) => reloaded_css = css,
+                Ok(_) => {}
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(20)),
+            }
+        }
+        assert!(reloaded_css.unwrap().contains("--bg-primary: #101020;"));
+
+        // A broken edit keeps the last good version
+        std::fs::write(&path, "{ not json").unwrap();
+        CustomThemeManager::reload_path(&manager.themes, &manager.active_theme, &manager.tx, &path);
+        assert!(manager.get_css("midnight").unwrap().contains("#101020"));
     }
-}
\ No newline at end of file
+}
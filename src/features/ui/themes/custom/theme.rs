@@ -0,0 +1,450 @@
+// Custom Theme Definition and Validation
+use crate::error::WebxError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Built-in palette a custom theme starts from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeBase {
+    Light,
+    #[default]
+    Dark,
+}
+
+/// Accent colors used for highlights, focus rings and active tabs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccentColors {
+    pub primary: String,
+    #[serde(default)]
+    pub hover: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// A complete user-defined theme, loaded from JSON or TOML
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomTheme {
+    pub name: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub base: ThemeBase,
+    /// CSS variables without the leading "--" (e.g. "bg-primary")
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    #[serde(default)]
+    pub accent: Option<AccentColors>,
+    /// Toolbar icon name to image path (relative to the theme file) or data: URI
+    #[serde(default)]
+    pub toolbar_icons: BTreeMap<String, String>,
+    /// Directory of the theme file, used to resolve icon paths
+    #[serde(skip)]
+    pub source_dir: Option<PathBuf>,
+}
+
+/// CSS variables every theme defines, with light and dark defaults
+pub const THEME_VARIABLES: &[(&str, &str, &str)] = &[
+    ("bg-primary", "#ffffff", "#121212"),
+    ("bg-secondary", "#f8f9fa", "#1e1e1e"),
+    ("bg-tertiary", "#e9ecef", "#2d2d2d"),
+    ("text-primary", "#212529", "#e0e0e0"),
+    ("text-secondary", "#6c757d", "#a0a0a0"),
+    ("accent-primary", "#0d6efd", "#4d90fe"),
+    ("accent-hover", "#0b5ed7", "#5d9bff"),
+    ("accent-text", "#ffffff", "#ffffff"),
+    ("border-primary", "#dee2e6", "#333333"),
+    ("toolbar-bg", "#f8f9fa", "#1e1e1e"),
+    ("toolbar-text", "#212529", "#e0e0e0"),
+    ("tab-active-bg", "#ffffff", "#2d2d2d"),
+    ("tab-inactive-bg", "#e9ecef", "#1e1e1e"),
+    ("success", "#198754", "#4caf50"),
+    ("warning", "#ffc107", "#ff9800"),
+    ("error", "#dc3545", "#f44336"),
+    ("shadow", "0 1px 3px 0 rgba(0, 0, 0, 0.1)", "0 1px 3px 0 rgba(0, 0, 0, 0.3)"),
+];
+
+/// Variables holding something other than a color
+const NON_COLOR_VARIABLES: &[&str] = &["shadow"];
+
+/// Toolbar icons a theme can replace
+pub const TOOLBAR_ICONS: &[&str] = &[
+    "back", "forward", "reload", "stop", "home", "new-tab", "close-tab", "bookmarks", "downloads", "history",
+    "menu", "settings", "extensions",
+];
+
+const ICON_EXTENSIONS: &[&str] = &["svg", "png"];
+
+const NAMED_COLORS: &[&str] = &[
+    "transparent", "currentcolor", "black", "white", "red", "green", "blue", "yellow", "orange", "purple",
+    "gray", "grey", "silver", "navy", "teal", "maroon", "olive", "lime", "aqua", "fuchsia",
+];
+
+/// How serious a validation finding is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A validation finding pointing at the offending field
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThemeDiagnostic {
+    pub severity: Severity,
+    /// Dotted field path such as "colors.bg-primary", or "file" for parse errors
+    pub field: String,
+    pub message: String,
+}
+
+impl ThemeDiagnostic {
+    fn error(field: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            field: field.to_string(),
+            message,
+        }
+    }
+
+    fn warning(field: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ThemeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{} in {}: {}", level, self.field, self.message)
+    }
+}
+
+/// Error returned when a theme file cannot be used
+#[derive(Debug, Clone)]
+pub struct ThemeValidationError {
+    pub path: PathBuf,
+    pub diagnostics: Vec<ThemeDiagnostic>,
+}
+
+impl fmt::Display for ThemeValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid theme {}:", self.path.display())?;
+        for diagnostic in self.diagnostics.iter().filter(|d| d.severity == Severity::Error) {
+            write!(f, "\n  {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ThemeValidationError {}
+
+impl From<ThemeValidationError> for WebxError {
+    fn from(e: ThemeValidationError) -> Self {
+        WebxError::Parse(e.to_string())
+    }
+}
+
+impl CustomTheme {
+    /// Parse a theme file, choosing the format from its extension
+    pub fn parse(content: &str, path: &Path) -> Result<Self, ThemeDiagnostic> {
+        let mut theme: CustomTheme = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(content).map_err(|e| {
+                let location = e
+                    .span()
+                    .map(|span| {
+                        let line = content[..span.start].matches('\n').count() + 1;
+                        format!(" (line {})", line)
+                    })
+                    .unwrap_or_default();
+                ThemeDiagnostic::error("file", format!("{}{}", e.message(), location))
+            })?,
+            Some("json") => serde_json::from_str(content).map_err(|e| {
+                ThemeDiagnostic::error("file", format!("{} (line {}, column {})", e, e.line(), e.column()))
+            })?,
+            _ => {
+                return Err(ThemeDiagnostic::error(
+                    "file",
+                    "Theme files must have a .json or .toml extension".to_string(),
+                ))
+            }
+        };
+
+        theme.source_dir = path.parent().map(Path::to_path_buf);
+        Ok(theme)
+    }
+
+    /// Check the theme for mistakes; errors make the theme unusable, warnings do not
+    pub fn validate(&self) -> Vec<ThemeDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if self.name.trim().is_empty() {
+            diagnostics.push(ThemeDiagnostic::error("name", "Theme name must not be empty".to_string()));
+        }
+
+        for (variable, value) in &self.colors {
+            let field = format!("colors.{}", variable);
+            let variable = variable.trim_start_matches("--");
+
+            if let Some(problem) = unsafe_css_value(value) {
+                diagnostics.push(ThemeDiagnostic::error(&field, problem));
+                continue;
+            }
+
+            match THEME_VARIABLES.iter().find(|(name, _, _)| *name == variable) {
+                Some(_) if NON_COLOR_VARIABLES.contains(&variable) => {}
+                Some(_) => {
+                    if !is_css_color(value) {
+                        diagnostics.push(ThemeDiagnostic::error(
+                            &field,
+                            format!("\"{}\" is not a valid color (use #rrggbb, rgb(), hsl() or a color name)", value),
+                        ));
+                    }
+                }
+                None => {
+                    let hint = closest_name(variable, THEME_VARIABLES.iter().map(|(name, _, _)| *name))
+                        .map(|name| format!("; did you mean \"{}\"?", name))
+                        .unwrap_or_default();
+                    diagnostics.push(ThemeDiagnostic::warning(
+                        &field,
+                        format!("Unknown variable \"{}\" will be passed through{}", variable, hint),
+                    ));
+                }
+            }
+        }
+
+        if let Some(accent) = &self.accent {
+            let fields = [
+                ("accent.primary", Some(&accent.primary)),
+                ("accent.hover", accent.hover.as_ref()),
+                ("accent.text", accent.text.as_ref()),
+            ];
+            for (field, value) in fields {
+                if let Some(value) = value {
+                    if unsafe_css_value(value).is_some() || !is_css_color(value) {
+                        diagnostics.push(ThemeDiagnostic::error(field, format!("\"{}\" is not a valid color", value)));
+                    }
+                }
+            }
+        }
+
+        for (icon, source) in &self.toolbar_icons {
+            let field = format!("toolbar_icons.{}", icon);
+
+            if !TOOLBAR_ICONS.contains(&icon.as_str()) {
+                let hint = closest_name(icon, TOOLBAR_ICONS.iter().copied())
+                    .map(|name| format!("; did you mean \"{}\"?", name))
+                    .unwrap_or_default();
+                diagnostics.push(ThemeDiagnostic::warning(
+                    &field,
+                    format!("Unknown toolbar icon \"{}\"{}", icon, hint),
+                ));
+            }
+
+            if source.starts_with("data:image/") {
+                if source.contains(['"', '\'', ')']) {
+                    diagnostics.push(ThemeDiagnostic::error(&field, "Icon data URI contains quotes".to_string()));
+                }
+                continue;
+            }
+
+            let extension = Path::new(source).extension().and_then(|e| e.to_str()).unwrap_or("");
+            if !ICON_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+                diagnostics.push(ThemeDiagnostic::error(&field, "Icons must be .svg or .png files".to_string()));
+            } else if Path::new(source).is_absolute() || source.contains("..") {
+                diagnostics.push(ThemeDiagnostic::error(
+                    &field,
+                    "Icon paths must be relative to the theme directory".to_string(),
+                ));
+            } else if let Some(path) = self.icon_path(icon) {
+                if !path.exists() {
+                    diagnostics.push(ThemeDiagnostic::error(
+                        &field,
+                        format!("Icon file {} does not exist", path.display()),
+                    ));
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Whether validation found no errors
+    pub fn is_valid(&self) -> bool {
+        self.validate().iter().all(|d| d.severity != Severity::Error)
+    }
+
+    /// Absolute path of a file-based toolbar icon
+    pub fn icon_path(&self, icon: &str) -> Option<PathBuf> {
+        let source = self.toolbar_icons.get(icon)?;
+        if source.starts_with("data:") {
+            return None;
+        }
+        Some(match &self.source_dir {
+            Some(dir) => dir.join(source),
+            None => PathBuf::from(source),
+        })
+    }
+
+    /// Resolved value of every CSS variable, base palette first
+    pub fn variables(&self) -> BTreeMap<String, String> {
+        let mut variables: BTreeMap<String, String> = THEME_VARIABLES
+            .iter()
+            .map(|(name, light, dark)| {
+                let value = match self.base {
+                    ThemeBase::Light => light,
+                    ThemeBase::Dark => dark,
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+
+        if let Some(accent) = &self.accent {
+            variables.insert("accent-primary".to_string(), accent.primary.clone());
+            if let Some(hover) = &accent.hover {
+                variables.insert("accent-hover".to_string(), hover.clone());
+            }
+            if let Some(text) = &accent.text {
+                variables.insert("accent-text".to_string(), text.clone());
+            }
+        }
+
+        // Explicit colors win over accent shorthands
+        for (name, value) in &self.colors {
+            variables.insert(name.trim_start_matches("--").to_string(), value.clone());
+        }
+
+        for icon in self.toolbar_icons.keys() {
+            let url = match self.icon_path(icon) {
+                Some(path) => format!("file://{}", path.display()),
+                None => self.toolbar_icons[icon].clone(),
+            };
+            variables.insert(format!("icon-{}", icon), format!("url(\"{}\")", url));
+        }
+
+        variables
+    }
+
+    /// CSS for the theme as a :root block
+    pub fn to_css(&self) -> String {
+        let mut css = format!(":root {{\n    /* {} */\n", self.name.replace("*/", ""));
+        for (name, value) in self.variables() {
+            css.push_str(&format!("    --{}: {};\n", name, value));
+        }
+        css.push_str("}\n");
+        css
+    }
+}
+
+/// Reject values that could break out of the declaration they are placed in
+fn unsafe_css_value(value: &str) -> Option<String> {
+    if value.trim().is_empty() {
+        return Some("Value must not be empty".to_string());
+    }
+    if value.contains([';', '{', '}', '<']) || value.contains("/*") {
+        return Some(format!("\"{}\" contains characters not allowed in a CSS value", value));
+    }
+    None
+}
+
+fn is_css_color(value: &str) -> bool {
+    let value = value.trim().to_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    for function in ["rgb(", "rgba(", "hsl(", "hsla(", "var(--"] {
+        if value.starts_with(function) && value.ends_with(')') {
+            return true;
+        }
+    }
+
+    NAMED_COLORS.contains(&value.as_str())
+}
+
+/// Closest known name within two edits, for "did you mean" hints
+fn closest_name<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let name: Vec<char> = name.chars().collect();
+    known
+        .map(|candidate| {
+            let chars: Vec<char> = candidate.chars().collect();
+            (crate::features::ui::spell_checker::suggestions::levenshtein(&name, &chars), candidate)
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_and_css() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("back.svg"), "<svg/>").unwrap();
+        let path = temp_dir.path().join("ocean.toml");
+
+        let content = r##"
+name = "Ocean"
+base = "light"
+
+[colors]
+bg-primary = "#e0f7fa"
+
+[accent]
+primary = "#00838f"
+
+[toolbar_icons]
+back = "back.svg"
+"##;
+        let theme = CustomTheme::parse(content, &path).unwrap();
+        assert!(theme.validate().is_empty());
+
+        let css = theme.to_css();
+        assert!(css.contains("--bg-primary: #e0f7fa;"));
+        assert!(css.contains("--accent-primary: #00838f;"));
+        // Unset variables come from the light base palette
+        assert!(css.contains("--text-primary: #212529;"));
+        assert!(css.contains(&format!("--icon-back: url(\"file://{}\")", temp_dir.path().join("back.svg").display())));
+    }
+
+    #[test]
+    fn test_validation_messages() {
+        let path = PathBuf::from("/themes/broken.json");
+
+        let err = CustomTheme::parse("{\"name\": \"Broken\",\n \"colors\": [}", &path).unwrap_err();
+        assert_eq!(err.field, "file");
+        assert!(err.message.contains("line 2"));
+
+        let theme = CustomTheme::parse(
+            r##"{
+                "name": "Broken",
+                "colors": { "bg-primry": "#000", "text-primary": "not-a-color", "error": "red; background: url(x)" },
+                "toolbar_icons": { "reload": "../../etc/icon.svg" }
+            }"##,
+            &path,
+        )
+        .unwrap();
+
+        let diagnostics = theme.validate();
+        let find = |field: &str| diagnostics.iter().find(|d| d.field == field).unwrap();
+
+        assert_eq!(find("colors.bg-primry").severity, Severity::Warning);
+        assert!(find("colors.bg-primry").message.contains("did you mean \"bg-primary\""));
+        assert_eq!(find("colors.text-primary").severity, Severity::Error);
+        assert!(find("colors.error").message.contains("not allowed"));
+        assert!(find("toolbar_icons.reload").message.contains("relative"));
+        assert!(!theme.is_valid());
+    }
+}
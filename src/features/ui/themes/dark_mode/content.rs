@@ -0,0 +1,425 @@
+// Forced Dark Mode for Web Content
+use crate::error::WebxError;
+use crate::utils::{LockExt, extract_domain};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Pages whose background luminance is below this are treated as already dark
+pub const DARK_LUMINANCE_THRESHOLD: f64 = 0.35;
+
+/// Per-site override of the global setting
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SiteDarkening {
+    Always,
+    Never,
+}
+
+/// Overall brightness of a page as determined by color analysis
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PageTone {
+    Light,
+    Dark,
+    Unknown,
+}
+
+/// Content dark mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentDarkModeConfig {
+    pub enabled: bool,
+    /// Strength of the inversion (0.0 - 1.0); slightly below 1 avoids pure black
+    pub intensity: f32,
+    pub brightness: f32,
+    pub contrast: f32,
+    pub sepia: f32,
+    pub site_overrides: HashMap<String, SiteDarkening>,
+}
+
+impl Default for ContentDarkModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 0.9,
+            brightness: 1.0,
+            contrast: 1.0,
+            sepia: 0.0,
+            site_overrides: HashMap::new(),
+        }
+    }
+}
+
+/// Darkens bright pages when the browser theme is dark
+pub struct ContentDarkMode {
+    config: Arc<Mutex<ContentDarkModeConfig>>,
+    config_path: PathBuf,
+}
+
+impl ContentDarkMode {
+    /// Create new content dark mode engine
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path.push("themes");
+            path
+        });
+
+        std::fs::create_dir_all(&config_dir)?;
+
+        let engine = Self {
+            config: Arc::new(Mutex::new(ContentDarkModeConfig::default())),
+            config_path: config_dir.join("content_dark_mode.json"),
+        };
+
+        engine.load_config()?;
+
+        Ok(engine)
+    }
+
+    /// Enable or disable forced dark mode globally
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), WebxError> {
+        self.config.lock_or_recover().enabled = enabled;
+        self.save_config()
+    }
+
+    /// Check if forced dark mode is enabled globally
+    pub fn is_enabled(&self) -> bool {
+        self.config.lock_or_recover().enabled
+    }
+
+    /// Adjust the darkening filter
+    pub fn set_filter(&self, intensity: f32, brightness: f32, contrast: f32, sepia: f32) -> Result<(), WebxError> {
+        {
+            let mut config = self.config.lock_or_recover();
+            config.intensity = intensity.clamp(0.0, 1.0);
+            config.brightness = brightness.clamp(0.5, 1.5);
+            config.contrast = contrast.clamp(0.5, 1.5);
+            config.sepia = sepia.clamp(0.0, 1.0);
+        }
+        self.save_config()
+    }
+
+    /// Always or never darken a site, regardless of the global setting
+    pub fn set_site_override(&self, url: &str, rule: Option<SiteDarkening>) -> Result<(), WebxError> {
+        let site = extract_domain(url);
+        {
+            let mut config = self.config.lock_or_recover();
+            match rule {
+                Some(rule) => config.site_overrides.insert(site, rule),
+                None => config.site_overrides.remove(&site),
+            };
+        }
+        self.save_config()
+    }
+
+    /// Override for a site, if any
+    pub fn get_site_override(&self, url: &str) -> Option<SiteDarkening> {
+        self.config
+            .lock_or_recover()
+            .site_overrides
+            .get(&extract_domain(url))
+            .copied()
+    }
+
+    /// Sites with an override
+    pub fn list_site_overrides(&self) -> Vec<(String, SiteDarkening)> {
+        let mut sites: Vec<(String, SiteDarkening)> = self
+            .config
+            .lock_or_recover()
+            .site_overrides
+            .iter()
+            .map(|(site, rule)| (site.clone(), *rule))
+            .collect();
+        sites.sort_by(|a, b| a.0.cmp(&b.0));
+        sites
+    }
+
+    /// Whether a page should be darkened given the current browser theme
+    pub fn should_darken(&self, url: &str, browser_is_dark: bool) -> bool {
+        if !browser_is_dark || !url.starts_with("http") {
+            return false;
+        }
+        match self.get_site_override(url) {
+            Some(SiteDarkening::Always) => true,
+            Some(SiteDarkening::Never) => false,
+            None => self.is_enabled(),
+        }
+    }
+
+    /// Script to inject into a page, or None if it should not be darkened
+    pub fn get_injection_script(&self, url: &str, browser_is_dark: bool) -> Option<String> {
+        if !self.should_darken(url, browser_is_dark) {
+            return None;
+        }
+        let force = self.get_site_override(url) == Some(SiteDarkening::Always);
+        Some(self.build_script(force))
+    }
+
+    /// Script that removes darkening from a page (when the theme turns light)
+    pub fn get_removal_script(&self) -> String {
+        r#"
+(function() {
+    if (window.__webxDarkMode) window.__webxDarkMode.disable();
+})();
+"#
+        .to_string()
+    }
+
+    /// CSS applied to pages that need darkening
+    pub fn get_darkening_css(&self) -> String {
+        let config = self.config.lock_or_recover();
+        let filter = format!(
+            "invert({}) hue-rotate(180deg) brightness({}) contrast({}) sepia({})",
+            config.intensity, config.brightness, config.contrast, config.sepia
+        );
+
+        format!(
+            r#"
+html {{
+    filter: {filter} !important;
+    background-color: #fff !important;
+}}
+/* Re-invert media so photos and videos keep their real colors */
+img, picture, video, canvas, iframe, embed, object, svg image,
+[style*="background-image"], [data-webx-dark-preserve] {{
+    filter: invert(1) hue-rotate(180deg) !important;
+}}
+/* Nested media inside an already re-inverted element must not flip back */
+[style*="background-image"] img, picture img, [data-webx-dark-preserve] img {{
+    filter: none !important;
+}}
+"#
+        )
+    }
+
+    // Private helper methods
+
+    fn build_script(&self, force: bool) -> String {
+        let css = serde_json::to_string(&self.get_darkening_css()).unwrap_or_default();
+
+        format!(
+            r#"
+(function() {{
+    if (window.__webxDarkMode) {{ window.__webxDarkMode.enable(); return; }}
+
+    const CSS = {css};
+    const FORCE = {force};
+    const THRESHOLD = {threshold};
+
+    function parseColor(value) {{
+        const m = value && value.match(/rgba?\(([\d.]+),\s*([\d.]+),\s*([\d.]+)(?:,\s*([\d.]+))?/);
+        if (!m) return null;
+        const alpha = m[4] === undefined ? 1 : parseFloat(m[4]);
+        if (alpha < 0.1) return null;
+        return [parseFloat(m[1]), parseFloat(m[2]), parseFloat(m[3])];
+    }}
+
+    function luminance(rgb) {{
+        const c = rgb.map(v => {{
+            v /= 255;
+            return v <= 0.03928 ? v / 12.92 : Math.pow((v + 0.055) / 1.055, 2.4);
+        }});
+        return 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+    }}
+
+    // Sites that declare dark support or already render dark are left alone
+    function alreadyDark() {{
+        const meta = document.querySelector('meta[name="color-scheme"]');
+        if (meta && /^\s*dark/.test(meta.content)) return true;
+
+        const candidates = [document.body, document.documentElement, document.querySelector('main')];
+        for (const el of candidates) {{
+            if (!el) continue;
+            const bg = parseColor(getComputedStyle(el).backgroundColor);
+            if (bg) return luminance(bg) < THRESHOLD;
+        }}
+        const text = document.body && parseColor(getComputedStyle(document.body).color);
+        return text ? luminance(text) > 0.5 : false;
+    }}
+
+    let style = null;
+    let observer = null;
+
+    function preserveBackgroundImages(root) {{
+        root.querySelectorAll('*').forEach(el => {{
+            const bg = getComputedStyle(el).backgroundImage;
+            if (bg && bg.startsWith('url(') && !el.closest('[data-webx-dark-preserve]')) {{
+                el.setAttribute('data-webx-dark-preserve', '');
+            }}
+        }});
+    }}
+
+    function enable() {{
+        if (style) return;
+        if (!FORCE && alreadyDark()) return;
+        style = document.createElement('style');
+        style.id = 'webx-content-dark-mode';
+        style.textContent = CSS;
+        (document.head || document.documentElement).appendChild(style);
+        document.documentElement.style.colorScheme = 'dark';
+        if (document.body) preserveBackgroundImages(document.body);
+
+        observer = new MutationObserver(mutations => {{
+            for (const m of mutations) {{
+                m.addedNodes.forEach(node => {{
+                    if (node.nodeType === 1) preserveBackgroundImages(node);
+                }});
+            }}
+        }});
+        observer.observe(document.documentElement, {{ childList: true, subtree: true }});
+    }}
+
+    function disable() {{
+        if (style) style.remove();
+        style = null;
+        if (observer) observer.disconnect();
+        observer = null;
+        document.documentElement.style.colorScheme = '';
+    }}
+
+    window.__webxDarkMode = {{ enable, disable }};
+
+    if (document.readyState === 'loading') {{
+        document.addEventListener('DOMContentLoaded', enable);
+    }} else {{
+        enable();
+    }}
+}})();
+"#,
+            css = css,
+            force = force,
+            threshold = DARK_LUMINANCE_THRESHOLD
+        )
+    }
+
+    fn save_config(&self) -> Result<(), WebxError> {
+        let config = self.config.lock_or_recover();
+        let content = serde_json::to_string_pretty(&*config)?;
+        std::fs::write(&self.config_path, content)?;
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<(), WebxError> {
+        if self.config_path.exists() {
+            let content = std::fs::read_to_string(&self.config_path)?;
+            *self.config.lock_or_recover() = serde_json::from_str(&content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Classify a page from its computed background and text colors
+pub fn analyze_page_tone(background: Option<&str>, text: Option<&str>) -> PageTone {
+    if let Some(bg) = background.and_then(parse_rgb) {
+        return if relative_luminance(bg) < DARK_LUMINANCE_THRESHOLD {
+            PageTone::Dark
+        } else {
+            PageTone::Light
+        };
+    }
+    // Transparent background: light text implies a dark page
+    match text.and_then(parse_rgb) {
+        Some(fg) if relative_luminance(fg) > 0.5 => PageTone::Dark,
+        Some(_) => PageTone::Light,
+        None => PageTone::Unknown,
+    }
+}
+
+/// WCAG relative luminance of an sRGB color
+pub fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let channel = |v: u8| {
+        let v = v as f64 / 255.0;
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// Parse "#rgb", "#rrggbb", "rgb(r, g, b)" or "rgba(r, g, b, a)"; fully transparent is None
+pub fn parse_rgb(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim().to_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let expand = |s: &str| u8::from_str_radix(&s.repeat(2), 16).ok();
+        return match hex.len() {
+            3 => Some((expand(&hex[0..1])?, expand(&hex[1..2])?, expand(&hex[2..3])?)),
+            6 => Some((
+                u8::from_str_radix(&hex[0..2], 16).ok()?,
+                u8::from_str_radix(&hex[2..4], 16).ok()?,
+                u8::from_str_radix(&hex[4..6], 16).ok()?,
+            )),
+            _ => None,
+        };
+    }
+
+    let inner = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let parts: Vec<&str> = inner.split([',', ' ', '/']).filter(|p| !p.is_empty()).collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    if let Some(alpha) = parts.get(3).and_then(|a| a.parse::<f64>().ok()) {
+        if alpha < 0.1 {
+            return None;
+        }
+    }
+    let channel = |s: &str| s.parse::<f64>().ok().map(|v| v.clamp(0.0, 255.0) as u8);
+    Some((channel(parts[0])?, channel(parts[1])?, channel(parts[2])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_color_analysis() {
+        assert_eq!(parse_rgb("#fff"), Some((255, 255, 255)));
+        assert_eq!(parse_rgb("rgba(18, 18, 18, 1)"), Some((18, 18, 18)));
+        assert_eq!(parse_rgb("rgba(0, 0, 0, 0)"), None);
+
+        assert_eq!(analyze_page_tone(Some("rgb(255, 255, 255)"), None), PageTone::Light);
+        assert_eq!(analyze_page_tone(Some("#1e1e1e"), None), PageTone::Dark);
+        // Transparent background falls back to the text color
+        assert_eq!(
+            analyze_page_tone(Some("rgba(0, 0, 0, 0)"), Some("rgb(230, 230, 230)")),
+            PageTone::Dark
+        );
+        assert_eq!(analyze_page_tone(None, None), PageTone::Unknown);
+    }
+
+    #[test]
+    fn test_site_rules_and_theme_gate() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = ContentDarkMode::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        // Only active while the browser theme is dark
+        assert!(engine.get_injection_script("https://example.com", false).is_none());
+        assert!(engine.get_injection_script("https://example.com", true).is_some());
+        assert!(engine.get_injection_script("webx://settings", true).is_none());
+
+        engine
+            .set_site_override("https://www.maps.example.com/place", Some(SiteDarkening::Never))
+            .unwrap();
+        assert!(!engine.should_darken("https://maps.example.com", true));
+
+        engine.set_enabled(false).unwrap();
+        engine
+            .set_site_override("https://docs.example.com", Some(SiteDarkening::Always))
+            .unwrap();
+        let script = engine.get_injection_script("https://docs.example.com/a", true).unwrap();
+        assert!(script.contains("const FORCE = true;"));
+        assert!(!engine.should_darken("https://example.com", true));
+
+        // Settings persist
+        let engine = ContentDarkMode::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(!engine.is_enabled());
+        assert_eq!(engine.list_site_overrides().len(), 2);
+    }
+}
@@ -1,7 +1,18 @@
 // Unified Theme Manager
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use super::accessibility::Accessibility;
+use super::custom::{CustomThemeManager, ThemeBase};
+use super::dark_mode::ContentDarkMode;
+use super::schedule::ThemeSchedule;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bound between schedule checks so clock and config changes are picked up
+const SCHEDULE_RECHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
 
 /// Theme preference options
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -18,6 +29,8 @@ pub struct ThemeConfig {
     pub current_theme: ThemePreference,
     pub auto_detect_system: bool,
     pub custom_themes_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub schedule: ThemeSchedule,
 }
 
 impl Default for ThemeConfig {
@@ -26,6 +39,7 @@ impl Default for ThemeConfig {
             current_theme: ThemePreference::System,
             auto_detect_system: true,
             custom_themes_dir: None,
+            schedule: ThemeSchedule::Off,
         }
     }
 }
@@ -34,6 +48,11 @@ impl Default for ThemeConfig {
 pub struct ThemeManager {
     config: ThemeConfig,
     config_path: PathBuf,
+    custom_themes: CustomThemeManager,
+    content_dark_mode: ContentDarkMode,
+    accessibility: Arc<Accessibility>,
+    system_prefers_dark: bool,
+    scheduler: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl ThemeManager {
@@ -41,7 +60,7 @@ impl ThemeManager {
     pub fn new(
         config: Option<ThemeConfig>,
         config_dir: Option<PathBuf>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    ) -> Result<Self, WebxError> {
         let config = config.unwrap_or_default();
         let config_dir = config_dir.unwrap_or_else(|| {
             let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -53,19 +72,41 @@ impl ThemeManager {
         // Create config directory
         std::fs::create_dir_all(&config_dir)?;
 
+        let config_path = config_dir.join("theme_config.json");
+        let custom_themes_dir = config
+            .custom_themes_dir
+            .clone()
+            .unwrap_or_else(|| config_dir.join("custom"));
+
         let mut manager = Self {
             config,
-            config_path: config_dir.join("theme_config.json"),
+            config_path,
+            custom_themes: CustomThemeManager::new(custom_themes_dir)?,
+            content_dark_mode: ContentDarkMode::new(Some(config_dir.clone()))?,
+            accessibility: Arc::new(Accessibility::new(Some(config_dir))?),
+            system_prefers_dark: true,
+            scheduler: None,
         };
 
         // Load existing configuration
         manager.load_config()?;
 
+        // Restore the active custom theme; missing themes fall back to dark CSS
+        if let ThemePreference::Custom(name) = &manager.config.current_theme {
+            if manager.custom_themes.activate(name).is_err() {
+                tracing::warn!("Custom theme {} is no longer available", name);
+            }
+        }
+
         Ok(manager)
     }
 
     /// Set current theme
-    pub fn set_theme(&mut self, theme: ThemePreference) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn set_theme(&mut self, theme: ThemePreference) -> Result<(), WebxError> {
+        match &theme {
+            ThemePreference::Custom(name) => self.custom_themes.activate(name)?,
+            _ => self.custom_themes.deactivate(),
+        }
         self.config.current_theme = theme;
         self.save_config()?;
         Ok(())
@@ -78,17 +119,131 @@ impl ThemeManager {
 
     /// Get CSS variables for current theme
     pub fn get_css_variables(&self) -> String {
+        if let Some(css) = self.accessibility.chrome_css_variables() {
+            return css;
+        }
         match &self.config.current_theme {
             ThemePreference::Light => self.get_light_theme_css(),
             ThemePreference::Dark => self.get_dark_theme_css(),
             ThemePreference::System => {
-                // In a real implementation, this would detect system theme
-                self.get_dark_theme_css() // Default to dark
+                if self.system_prefers_dark {
+                    self.get_dark_theme_css()
+                } else {
+                    self.get_light_theme_css()
+                }
             }
             ThemePreference::Custom(name) => self.get_custom_theme_css(name),
         }
     }
 
+    /// Update the detected system color scheme (System preference defaults to dark)
+    pub fn update_system_theme(&mut self, prefers_dark: bool) {
+        self.system_prefers_dark = prefers_dark;
+    }
+
+    /// Whether the browser chrome is currently dark
+    pub fn is_dark_theme(&self) -> bool {
+        match &self.config.current_theme {
+            ThemePreference::Light => false,
+            ThemePreference::Dark => true,
+            ThemePreference::System => self.system_prefers_dark,
+            ThemePreference::Custom(name) => self
+                .custom_themes
+                .load_custom_theme(name)
+                .map(|theme| theme.base == ThemeBase::Dark)
+                .unwrap_or(true),
+        }
+    }
+
+    /// Get the forced dark mode engine for web content
+    pub fn content_dark_mode(&self) -> &ContentDarkMode {
+        &self.content_dark_mode
+    }
+
+    /// Get the accessibility options applied to the browser and pages
+    pub fn accessibility(&self) -> &Arc<Accessibility> {
+        &self.accessibility
+    }
+
+    /// Script that darkens a page, only when the browser theme is dark and
+    /// no high-contrast theme already recolors pages
+    pub fn get_content_dark_mode_script(&self, url: &str) -> Option<String> {
+        if self.accessibility.config().contrast.palette().is_some() {
+            return None;
+        }
+        self.content_dark_mode
+            .get_injection_script(url, self.is_dark_theme())
+    }
+
+    /// Set the automatic light/dark schedule and apply it right away
+    pub fn set_schedule(&mut self, schedule: ThemeSchedule) -> Result<(), WebxError> {
+        schedule.validate()?;
+        self.config.schedule = schedule;
+        self.save_config()?;
+        self.apply_schedule(Utc::now())?;
+        Ok(())
+    }
+
+    /// Get the automatic light/dark schedule
+    pub fn get_schedule(&self) -> &ThemeSchedule {
+        &self.config.schedule
+    }
+
+    /// Switch to the theme the schedule calls for at `now`; returns true if it changed
+    pub fn apply_schedule(&mut self, now: DateTime<Utc>) -> Result<bool, WebxError> {
+        let target = match self.config.schedule.is_dark_at(now) {
+            Some(true) => ThemePreference::Dark,
+            Some(false) => ThemePreference::Light,
+            None => return Ok(false),
+        };
+
+        if self.config.current_theme == target {
+            return Ok(false);
+        }
+        self.set_theme(target)?;
+        Ok(true)
+    }
+
+    /// Start switching themes on the configured schedule
+    pub fn start_scheduler(manager: Arc<Mutex<ThemeManager>>) {
+        let task_manager = manager.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let wait = {
+                    let mut manager = task_manager.lock_or_recover();
+                    let now = Utc::now();
+                    if let Err(e) = manager.apply_schedule(now) {
+                        tracing::warn!("Failed to apply theme schedule: {}", e);
+                    }
+                    manager
+                        .config
+                        .schedule
+                        .next_transition(now)
+                        .and_then(|(at, _)| (at - now).to_std().ok())
+                        .map_or(SCHEDULE_RECHECK_INTERVAL, |wait| wait.min(SCHEDULE_RECHECK_INTERVAL))
+                };
+                // Wake just after the switch time so it is already in the past
+                tokio::time::sleep(wait + Duration::from_secs(1)).await;
+            }
+        });
+
+        let mut manager = manager.lock_or_recover();
+        manager.stop_scheduler();
+        manager.scheduler = Some(handle);
+    }
+
+    /// Stop the theme scheduler
+    pub fn stop_scheduler(&mut self) {
+        if let Some(handle) = self.scheduler.take() {
+            handle.abort();
+        }
+    }
+
+    /// Check if the theme scheduler is running
+    pub fn is_scheduler_running(&self) -> bool {
+        self.scheduler.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
     /// Get theme configuration
     pub fn get_config(&self) -> &ThemeConfig {
         &self.config
@@ -99,25 +254,15 @@ impl ThemeManager {
         self.config = config;
     }
 
+    /// Get the custom theme manager
+    pub fn custom_themes(&self) -> &CustomThemeManager {
+        &self.custom_themes
+    }
+
     /// List available themes
     pub fn list_available_themes(&self) -> Vec<String> {
         let mut themes = vec!["Light".to_string(), "Dark".to_string()];
-        
-        // Add custom themes if directory exists
-        if let Some(custom_dir) = &self.config.custom_themes_dir {
-            if custom_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(custom_dir) {
-                    for entry in entries.flatten() {
-                        if let Some(name) = entry.file_name().to_str() {
-                            if name.ends_with(".json") {
-                                themes.push(name.trim_end_matches(".json").to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
+        themes.extend(self.custom_themes.list_themes().into_iter().map(|(id, _)| id));
         themes
     }
 
@@ -166,32 +311,18 @@ impl ThemeManager {
     }
 
     fn get_custom_theme_css(&self, theme_name: &str) -> String {
-        if let Some(custom_dir) = &self.config.custom_themes_dir {
-            let theme_path = custom_dir.join(format!("{}.json", theme_name));
-            if theme_path.exists() {
-                if let Ok(content) = std::fs::read_to_string(&theme_path) {
-                    if let Ok(theme_data) = serde_json::from_str::<serde_json::Value>(&content) {
-                        return self.generate_css_from_theme_data(&theme_data);
-                    }
-                }
-            }
-        }
-        self.get_dark_theme_css() // Fallback
-    }
-
-    fn generate_css_from_theme_data(&self, _theme_data: &serde_json::Value) -> String {
-        // This would convert theme JSON to CSS variables
-        // Simplified implementation for now
-        self.get_dark_theme_css()
+        self.custom_themes
+            .get_css(theme_name)
+            .unwrap_or_else(|| self.get_dark_theme_css()) // Fallback
     }
 
-    fn save_config(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn save_config(&self) -> Result<(), WebxError> {
         let content = serde_json::to_string_pretty(&self.config)?;
         std::fs::write(&self.config_path, content)?;
         Ok(())
     }
 
-    fn load_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn load_config(&mut self) -> Result<(), WebxError> {
         if self.config_path.exists() {
             let content = std::fs::read_to_string(&self.config_path)?;
             self.config = serde_json::from_str(&content)?;
@@ -218,8 +349,8 @@ mod tests {
         assert_eq!(manager.get_current_theme(), &ThemePreference::Light);
 
         // Test CSS generation
-        let dark_css = manager.get_css_variables();
-        assert!(dark_css.contains("--bg-primary: #121212"));
+        let light_css = manager.get_css_variables();
+        assert!(light_css.contains("--bg-primary: #ffffff"));
 
         // Test available themes
         let themes = manager.list_available_themes();
@@ -240,4 +371,75 @@ mod tests {
             _ => panic!("Expected custom theme"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_custom_theme_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let custom_dir = temp_dir.path().join("custom");
+        std::fs::create_dir_all(&custom_dir).unwrap();
+        std::fs::write(
+            custom_dir.join("solarized.toml"),
+            "name = \"Solarized\"\n[colors]\nbg-primary = \"#002b36\"\n",
+        )
+        .unwrap();
+
+        let mut manager = ThemeManager::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert!(manager.list_available_themes().contains(&"solarized".to_string()));
+        assert!(manager.set_theme(ThemePreference::Custom("missing".to_string())).is_err());
+
+        manager.set_theme(ThemePreference::Custom("solarized".to_string())).unwrap();
+        assert!(manager.get_css_variables().contains("--bg-primary: #002b36;"));
+        assert_eq!(manager.custom_themes().get_active_theme().as_deref(), Some("solarized"));
+
+        // The selection survives a restart
+        let manager = ThemeManager::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(manager.custom_themes().get_active_theme().as_deref(), Some("solarized"));
+    }
+
+    #[test]
+    fn test_content_dark_mode_follows_theme() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ThemeManager::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+
+        manager.set_theme(ThemePreference::Light).unwrap();
+        assert!(!manager.is_dark_theme());
+        assert!(manager.get_content_dark_mode_script("https://example.com").is_none());
+
+        manager.set_theme(ThemePreference::System).unwrap();
+        manager.update_system_theme(true);
+        assert!(manager.get_content_dark_mode_script("https://example.com").is_some());
+        manager.update_system_theme(false);
+        assert!(manager.get_css_variables().contains("--bg-primary: #ffffff"));
+        assert!(manager.get_content_dark_mode_script("https://example.com").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_switching() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = ThemeManager::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        manager.set_theme(ThemePreference::Light).unwrap();
+
+        // Tromsø in December never sees the sun
+        let schedule = ThemeSchedule::SunsetToSunrise { latitude: 69.65, longitude: 18.96 };
+        manager.config.schedule = schedule.clone();
+        let winter_noon = "2024-12-21T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(manager.apply_schedule(winter_noon).unwrap());
+        assert_eq!(manager.get_current_theme(), &ThemePreference::Dark);
+        assert!(!manager.apply_schedule(winter_noon).unwrap());
+
+        assert!(manager
+            .set_schedule(ThemeSchedule::SunsetToSunrise { latitude: 0.0, longitude: 200.0 })
+            .is_err());
+        manager.set_schedule(schedule.clone()).unwrap();
+
+        // The schedule is persisted with the theme config
+        let manager = ThemeManager::new(None, Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(manager.get_schedule(), &schedule);
+
+        let manager = Arc::new(Mutex::new(manager));
+        ThemeManager::start_scheduler(manager.clone());
+        assert!(manager.lock_or_recover().is_scheduler_running());
+        manager.lock_or_recover().stop_scheduler();
+        assert!(!manager.lock_or_recover().is_scheduler_running());
+    }
+}
@@ -1,10 +1,14 @@
 // Theme Management Module
+pub mod accessibility;
 pub mod dark_mode;
 pub mod light_mode;
 pub mod custom;
 pub mod manager;
+pub mod schedule;
 
-pub use dark_mode::DarkModeManager;
+pub use accessibility::{Accessibility, AccessibilityConfig, ContrastTheme, ACCESSIBILITY_REFRESH_SCRIPT, ACCESSIBILITY_SCRIPT};
+pub use dark_mode::{ContentDarkMode, DarkModeManager};
 pub use light_mode::LightModeManager;
 pub use custom::CustomThemeManager;
-pub use manager::ThemeManager;
\ No newline at end of file
+pub use manager::ThemeManager;
+pub use schedule::ThemeSchedule;
\ No newline at end of file
@@ -0,0 +1,223 @@
+// Scheduled Theme Switching
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Sun altitude at sunrise/sunset, accounting for refraction and the solar disc
+const SUN_ALTITUDE_DEG: f64 = -0.833;
+const EARTH_OBLIQUITY_DEG: f64 = 23.44;
+const J2000: f64 = 2451545.0;
+const UNIX_EPOCH_JULIAN: f64 = 2440587.5;
+
+/// When the browser should switch between light and dark themes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ThemeSchedule {
+    #[default]
+    Off,
+    /// Fixed local times of day
+    Fixed { light_at: NaiveTime, dark_at: NaiveTime },
+    /// Dark from sunset to sunrise at the given location (degrees, east positive)
+    SunsetToSunrise { latitude: f64, longitude: f64 },
+}
+
+/// Sunrise and sunset for a single day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolarDay {
+    Normal { sunrise: DateTime<Utc>, sunset: DateTime<Utc> },
+    /// The sun never sets (midnight sun)
+    PolarDay,
+    /// The sun never rises
+    PolarNight,
+}
+
+impl ThemeSchedule {
+    /// Check if a schedule is configured
+    pub fn is_enabled(&self) -> bool {
+        *self != ThemeSchedule::Off
+    }
+
+    /// Validate the schedule settings
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ThemeSchedule::Off => Ok(()),
+            ThemeSchedule::Fixed { light_at, dark_at } => {
+                if light_at == dark_at {
+                    Err("Light and dark switch times must differ".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            ThemeSchedule::SunsetToSunrise { latitude, longitude } => {
+                if !(-90.0..=90.0).contains(latitude) {
+                    Err(format!("Latitude {} is out of range (-90 to 90)", latitude))
+                } else if !(-180.0..=180.0).contains(longitude) {
+                    Err(format!("Longitude {} is out of range (-180 to 180)", longitude))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Whether the theme should be dark at the given instant, None if no schedule is set
+    pub fn is_dark_at(&self, now: DateTime<Utc>) -> Option<bool> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let transitions = self.transitions_around(now);
+        if let Some((_, dark)) = transitions.iter().rev().find(|(at, _)| *at <= now) {
+            return Some(*dark);
+        }
+
+        // No switch in the window: the sun stays up or down all day
+        match self {
+            ThemeSchedule::SunsetToSunrise { latitude, longitude } => {
+                match solar_day(now.date_naive(), *latitude, *longitude) {
+                    SolarDay::PolarNight => Some(true),
+                    _ => Some(false),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Next switch after the given instant and whether it turns the theme dark
+    pub fn next_transition(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, bool)> {
+        self.transitions_around(now)
+            .into_iter()
+            .find(|(at, _)| *at > now)
+    }
+
+    // Private helper methods
+
+    /// All switches from the day before to the day after `now`, in order
+    fn transitions_around(&self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, bool)> {
+        let mut transitions = Vec::new();
+
+        match self {
+            ThemeSchedule::Off => {}
+            ThemeSchedule::Fixed { light_at, dark_at } => {
+                let today = now.with_timezone(&Local).date_naive();
+                for offset in -1..=1 {
+                    let date = today + Duration::days(offset);
+                    for (time, dark) in [(light_at, false), (dark_at, true)] {
+                        // Times skipped by a DST change have no local instant
+                        if let Some(at) = Local.from_local_datetime(&date.and_time(*time)).earliest() {
+                            transitions.push((at.with_timezone(&Utc), dark));
+                        }
+                    }
+                }
+            }
+            ThemeSchedule::SunsetToSunrise { latitude, longitude } => {
+                let today = now.date_naive();
+                for offset in -1..=1 {
+                    let date = today + Duration::days(offset);
+                    if let SolarDay::Normal { sunrise, sunset } = solar_day(date, *latitude, *longitude) {
+                        transitions.push((sunrise, false));
+                        transitions.push((sunset, true));
+                    }
+                }
+            }
+        }
+
+        transitions.sort_by_key(|(at, _)| *at);
+        transitions
+    }
+}
+
+/// Compute sunrise and sunset for a date using the NOAA sunrise equation
+pub fn solar_day(date: NaiveDate, latitude: f64, longitude: f64) -> SolarDay {
+    let noon = Utc.from_utc_datetime(&date.and_hms_opt(12, 0, 0).unwrap());
+    let julian_date = noon.timestamp() as f64 / 86400.0 + UNIX_EPOCH_JULIAN;
+
+    let day = (julian_date - J2000 + 0.0008).round();
+    let mean_solar_noon = day - longitude / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * mean_solar_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = J2000 + mean_solar_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * EARTH_OBLIQUITY_DEG.to_radians().sin()).asin();
+    let phi = latitude.to_radians();
+    let cos_hour_angle = (SUN_ALTITUDE_DEG.to_radians().sin() - phi.sin() * declination.sin())
+        / (phi.cos() * declination.cos());
+
+    if cos_hour_angle < -1.0 {
+        return SolarDay::PolarDay;
+    }
+    if cos_hour_angle > 1.0 {
+        return SolarDay::PolarNight;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    SolarDay::Normal {
+        sunrise: julian_to_utc(transit - hour_angle / 360.0),
+        sunset: julian_to_utc(transit + hour_angle / 360.0),
+    }
+}
+
+fn julian_to_utc(julian: f64) -> DateTime<Utc> {
+    let seconds = ((julian - UNIX_EPOCH_JULIAN) * 86400.0).round() as i64;
+    Utc.timestamp_opt(seconds, 0).single().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes_between(a: DateTime<Utc>, b: DateTime<Utc>) -> i64 {
+        (a - b).num_minutes().abs()
+    }
+
+    #[test]
+    fn test_solar_day() {
+        // London around the summer solstice: sunrise ~03:43, sunset ~20:21 UTC
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        match solar_day(date, 51.5074, -0.1278) {
+            SolarDay::Normal { sunrise, sunset } => {
+                let expected_rise = Utc.with_ymd_and_hms(2024, 6, 21, 3, 43, 0).unwrap();
+                let expected_set = Utc.with_ymd_and_hms(2024, 6, 21, 20, 21, 0).unwrap();
+                assert!(minutes_between(sunrise, expected_rise) <= 3, "sunrise {}", sunrise);
+                assert!(minutes_between(sunset, expected_set) <= 3, "sunset {}", sunset);
+            }
+            other => panic!("Expected normal day, got {:?}", other),
+        }
+
+        // Tromsø has midnight sun in June and polar night in December
+        assert_eq!(solar_day(date, 69.65, 18.96), SolarDay::PolarDay);
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(solar_day(winter, 69.65, 18.96), SolarDay::PolarNight);
+
+        let schedule = ThemeSchedule::SunsetToSunrise { latitude: 69.65, longitude: 18.96 };
+        let noon = Utc.with_ymd_and_hms(2024, 12, 21, 12, 0, 0).unwrap();
+        assert_eq!(schedule.is_dark_at(noon), Some(true));
+    }
+
+    #[test]
+    fn test_schedule_transitions() {
+        let schedule = ThemeSchedule::Fixed {
+            light_at: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            dark_at: NaiveTime::from_hms_opt(19, 30, 0).unwrap(),
+        };
+        assert!(schedule.validate().is_ok());
+
+        let local = |h, m| {
+            Local
+                .with_ymd_and_hms(2024, 3, 12, h, m, 0)
+                .earliest()
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        assert_eq!(schedule.is_dark_at(local(12, 0)), Some(false));
+        assert_eq!(schedule.is_dark_at(local(22, 0)), Some(true));
+        assert_eq!(schedule.is_dark_at(local(3, 0)), Some(true));
+        assert_eq!(schedule.next_transition(local(12, 0)), Some((local(19, 30), true)));
+
+        assert_eq!(ThemeSchedule::Off.is_dark_at(local(12, 0)), None);
+        assert!(ThemeSchedule::SunsetToSunrise { latitude: 95.0, longitude: 0.0 }
+            .validate()
+            .is_err());
+    }
+}
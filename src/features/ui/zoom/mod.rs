@@ -0,0 +1,324 @@
+// Page Zoom
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::config::storage::write_atomic;
+use crate::error::WebxError;
+use crate::ipc::{IpcContext, IpcHandler, IpcMessage};
+use crate::utils::{site_domain, LockExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Zoom steps Ctrl+Plus and Ctrl+Minus move through
+pub const ZOOM_LEVELS: [f64; 17] = [
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
+
+/// Script scaling a page's text, but not its layout or images, by the
+/// `--webx-text-zoom` custom property. Each element's font size becomes
+/// `calc(<size as the page set it> * var(--webx-text-zoom))`, so changing
+/// the zoom only changes the property.
+pub const TEXT_ZOOM_SCRIPT: &str = r#"
+(function() {
+    if (window.webxZoom) return;
+    let observer = null;
+    const root = document.documentElement;
+    const scaleTree = (node) => {
+        if (node.nodeType !== 1) return;
+        const elements = [node, ...node.querySelectorAll('*')];
+        // Sizes are read before any is changed, so inherited sizes are not scaled twice
+        const sizes = elements.map((element) => parseFloat(getComputedStyle(element).fontSize));
+        elements.forEach((element, index) => {
+            if (element.dataset.webxTextZoom !== undefined || !sizes[index]) return;
+            element.dataset.webxTextZoom = sizes[index];
+            element.style.setProperty('font-size', 'calc(' + sizes[index] + 'px * var(--webx-text-zoom, 1))', 'important');
+        });
+    };
+    const setText = (factor) => {
+        root.style.setProperty('--webx-text-zoom', String(factor));
+        if (factor === 1 || observer) return;
+        scaleTree(root);
+        observer = new MutationObserver((mutations) => {
+            for (const mutation of mutations) mutation.addedNodes.forEach(scaleTree);
+        });
+        observer.observe(root, { childList: true, subtree: true });
+    };
+    const refresh = () => {
+        window.ipc.request({ type: 'zoomlevel' }).then((zoom) => setText(zoom.textScale)).catch(() => {});
+    };
+    window.webxZoom = { setText: setText, refresh: refresh };
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', refresh);
+    } else {
+        refresh();
+    }
+})();
+"#;
+
+/// How a page is zoomed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoomMode {
+    /// Scale the whole page, layout and images included
+    #[default]
+    FullPage,
+    /// Scale only the text, leaving layout and images as they are
+    TextOnly,
+}
+
+/// Zoom of a page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PageZoom {
+    pub level: f64,
+    pub mode: ZoomMode,
+}
+
+impl Default for PageZoom {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            mode: ZoomMode::FullPage,
+        }
+    }
+}
+
+impl PageZoom {
+    /// Zoom factor for the web view
+    pub fn webview_scale(&self) -> f64 {
+        match self.mode {
+            ZoomMode::FullPage => self.level,
+            ZoomMode::TextOnly => 1.0,
+        }
+    }
+
+    /// Factor the page's text is scaled by on top of the web view zoom
+    pub fn text_scale(&self) -> f64 {
+        match self.mode {
+            ZoomMode::FullPage => 1.0,
+            ZoomMode::TextOnly => self.level,
+        }
+    }
+
+    /// Script applying the text scale to a page running `TEXT_ZOOM_SCRIPT`
+    pub fn text_script(&self) -> String {
+        format!("window.webxZoom && window.webxZoom.setText({});", self.text_scale())
+    }
+}
+
+/// A change to a site's zoom
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoomAction {
+    In,
+    Out,
+    Reset,
+    /// Switch the site between full page and text-only zoom
+    TextOnly,
+}
+
+/// The zoom every site gets unless the user zoomed it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+struct ZoomConfig {
+    default: PageZoom,
+    sites: BTreeMap<String, PageZoom>,
+}
+
+/// Remembers how each site is zoomed
+pub struct SiteZoom {
+    config: Mutex<ZoomConfig>,
+    config_path: PathBuf,
+}
+
+impl SiteZoom {
+    /// Load the site zooms kept in `config_dir`
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let config_dir = config_dir.unwrap_or_else(|| {
+            let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push("webx");
+            path
+        });
+        std::fs::create_dir_all(&config_dir)?;
+
+        let config_path = config_dir.join("site_zoom.json");
+        let config = match std::fs::read_to_string(&config_path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => ZoomConfig::default(),
+        };
+        Ok(Self {
+            config: Mutex::new(config),
+            config_path,
+        })
+    }
+
+    /// Zoom of the site a page belongs to
+    pub fn zoom_for(&self, url: &str) -> PageZoom {
+        let config = self.config.lock_or_recover();
+        site_domain(url)
+            .and_then(|site| config.sites.get(&site).copied())
+            .unwrap_or(config.default)
+    }
+
+    /// Change the zoom of a page's site and return its new zoom
+    pub fn apply(&self, url: &str, action: ZoomAction) -> Result<PageZoom, WebxError> {
+        let site = web_site(url).ok_or("Zoom is remembered per site, and this page has none")?;
+        let mut zoom = self.zoom_for(url);
+        match action {
+            ZoomAction::In => zoom.level = next_level(zoom.level, true),
+            ZoomAction::Out => zoom.level = next_level(zoom.level, false),
+            ZoomAction::Reset => zoom.level = self.config.lock_or_recover().default.level,
+            ZoomAction::TextOnly => {
+                zoom.mode = match zoom.mode {
+                    ZoomMode::FullPage => ZoomMode::TextOnly,
+                    ZoomMode::TextOnly => ZoomMode::FullPage,
+                }
+            }
+        }
+        self.update(|config| {
+            if zoom == config.default {
+                config.sites.remove(&site);
+            } else {
+                config.sites.insert(site, zoom);
+            }
+        })?;
+        Ok(zoom)
+    }
+
+    /// Zoom level sites get unless zoomed
+    pub fn set_default_level(&self, level: f64) -> Result<(), WebxError> {
+        self.update(|config| config.default.level = level)
+    }
+
+    /// Zoom mode sites get unless switched
+    pub fn set_default_mode(&self, mode: ZoomMode) -> Result<(), WebxError> {
+        self.update(|config| config.default.mode = mode)
+    }
+
+    /// Sites zoomed differently from the default
+    pub fn sites(&self) -> Vec<(String, PageZoom)> {
+        let config = self.config.lock_or_recover();
+        config.sites.iter().map(|(site, zoom)| (site.clone(), *zoom)).collect()
+    }
+
+    /// Forget a site's zoom
+    pub fn clear_site(&self, site: &str) -> Result<(), WebxError> {
+        let site = site_domain(site).ok_or("Not a site")?;
+        self.update(|config| {
+            config.sites.remove(&site);
+        })
+    }
+
+    // Private helper methods
+
+    fn update(&self, update: impl FnOnce(&mut ZoomConfig)) -> Result<(), WebxError> {
+        let mut config = self.config.lock_or_recover();
+        update(&mut config);
+        // Sites left at the default need no entry
+        let default = config.default;
+        config.sites.retain(|_, zoom| *zoom != default);
+        write_atomic(&self.config_path, &serde_json::to_vec_pretty(&*config)?)?;
+        Ok(())
+    }
+}
+
+impl IpcHandler for SiteZoom {
+    fn message_types(&self) -> &'static [&'static str] {
+        &["zoomlevel"]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        match message {
+            IpcMessage::ZoomLevel => {
+                let zoom = self.zoom_for(&context.origin);
+                Ok(serde_json::json!({ "textScale": zoom.text_scale() }))
+            }
+            _ => Err(WebxError::Invalid("Not a zoom message".to_string())),
+        }
+    }
+}
+
+impl SettingsProvider for SiteZoom {
+    fn module(&self) -> &str {
+        "zoom"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        vec![SettingDefinition::toggle("zoom.text_only", "Zoom text only", false)
+            .with_description("Zooming scales text without scaling page layout or images; sites can be switched one by one")
+            .with_category(SettingCategory::Appearance)]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "zoom.text_only" => {
+                let text_only = value.as_bool().ok_or("Expected a toggle value")?;
+                self.set_default_mode(if text_only { ZoomMode::TextOnly } else { ZoomMode::FullPage })
+            }
+            _ => Err(format!("Unknown zoom setting {}", key).into()),
+        }
+    }
+
+    fn current_value(&self, key: &str) -> Option<SettingValue> {
+        match key {
+            "zoom.text_only" => Some(SettingValue::Bool(
+                self.config.lock_or_recover().default.mode == ZoomMode::TextOnly,
+            )),
+            _ => None,
+        }
+    }
+}
+
+// Private helper functions
+
+/// Site of a web page; other pages are not zoomed per site
+fn web_site(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    site_domain(url)
+}
+
+/// The zoom step after `level`, up or down
+fn next_level(level: f64, up: bool) -> f64 {
+    const EPSILON: f64 = 0.001;
+    if up {
+        ZOOM_LEVELS.iter().copied().find(|step| *step > level + EPSILON).unwrap_or(ZOOM_LEVELS[ZOOM_LEVELS.len() - 1])
+    } else {
+        ZOOM_LEVELS.iter().rev().copied().find(|step| *step < level - EPSILON).unwrap_or(ZOOM_LEVELS[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_site_zoom_modes_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let zoom = SiteZoom::new(Some(temp_dir.path().to_path_buf())).unwrap();
+
+        let page = zoom.apply("https://www.example.com/a", ZoomAction::In).unwrap();
+        assert_eq!(page, PageZoom { level: 1.1, mode: ZoomMode::FullPage });
+        assert_eq!(page.webview_scale(), 1.1);
+
+        let page = zoom.apply("https://example.com/b", ZoomAction::TextOnly).unwrap();
+        assert_eq!(page.webview_scale(), 1.0);
+        assert_eq!(page.text_scale(), 1.1);
+        assert!(zoom.apply("file:///tmp/page.html", ZoomAction::In).is_err());
+
+        // Other sites keep the default, and the zoom survives a restart
+        zoom.set_default_level(1.25).unwrap();
+        assert_eq!(zoom.zoom_for("https://other.org").level, 1.25);
+        let zoom = SiteZoom::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        assert_eq!(zoom.zoom_for("https://example.com").mode, ZoomMode::TextOnly);
+
+        // Back at the default, the site is forgotten
+        zoom.apply("https://example.com", ZoomAction::TextOnly).unwrap();
+        zoom.apply("https://example.com", ZoomAction::Reset).unwrap();
+        assert!(zoom.sites().is_empty());
+        assert_eq!(next_level(5.0, true), 5.0);
+        assert_eq!(next_level(1.0, false), 0.9);
+    }
+}
@@ -0,0 +1,527 @@
+// HAR (HTTP Archive) Serialization
+use crate::error::WebxError;
+use super::network::{NetworkRequest, NetworkTimings, RequestState, ResourceType};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const HAR_VERSION: &str = "1.2";
+
+/// Root of a HAR document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: String,
+    /// Total elapsed time in milliseconds
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    pub timings: HarTimings,
+    #[serde(rename = "_resourceType", default, skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+    #[serde(rename = "_initiator", default, skip_serializing_if = "Option::is_none")]
+    pub initiator: Option<String>,
+    #[serde(rename = "_error", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    #[serde(default)]
+    pub headers: Vec<HarNameValue>,
+    #[serde(default)]
+    pub query_string: Vec<HarNameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub params: Vec<HarNameValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    #[serde(default)]
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// "base64" for binary bodies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+/// Phase timings in milliseconds; -1 means not applicable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarTimings {
+    #[serde(default = "not_applicable")]
+    pub blocked: f64,
+    #[serde(default = "not_applicable")]
+    pub dns: f64,
+    #[serde(default = "not_applicable")]
+    pub connect: f64,
+    #[serde(default = "not_applicable")]
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Har {
+    /// Build a HAR log from recorded requests
+    pub fn from_requests(requests: &[NetworkRequest]) -> Self {
+        Self {
+            log: HarLog {
+                version: HAR_VERSION.to_string(),
+                creator: HarCreator {
+                    name: "WebX".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries: requests.iter().map(HarEntry::from_request).collect(),
+            },
+        }
+    }
+
+    /// Parse a HAR document
+    pub fn parse(content: &str) -> Result<Self, WebxError> {
+        let har: Har = serde_json::from_str(content)?;
+        if !har.log.version.starts_with("1.") {
+            return Err(format!("Unsupported HAR version {}", har.log.version).into());
+        }
+        Ok(har)
+    }
+
+    /// Load a HAR file
+    pub fn from_file(path: &Path) -> Result<Self, WebxError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, WebxError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write the HAR document to a file
+    pub fn save(&self, path: &Path) -> Result<(), WebxError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Convert entries back into requests for the given tab
+    pub fn to_requests(&self, tab_id: usize, mut next_id: impl FnMut() -> u64) -> Vec<NetworkRequest> {
+        self.log
+            .entries
+            .iter()
+            .map(|entry| entry.to_request(next_id(), tab_id))
+            .collect()
+    }
+
+    /// First recorded entry for a request, for replaying a capture
+    pub fn find_entry(&self, method: &str, url: &str) -> Option<&HarEntry> {
+        self.log
+            .entries
+            .iter()
+            .find(|entry| entry.request.method.eq_ignore_ascii_case(method) && entry.request.url == url)
+    }
+}
+
+impl HarEntry {
+    /// Convert a recorded request to a HAR entry
+    pub fn from_request(request: &NetworkRequest) -> Self {
+        let time = request.duration_ms().unwrap_or(0.0);
+        let timings = request.timings.clone().unwrap_or_else(|| {
+            let wait = request.time_to_first_byte_ms().unwrap_or(time);
+            NetworkTimings {
+                wait,
+                receive: (time - wait).max(0.0),
+                ..Default::default()
+            }
+        });
+        let http_version = request.http_version.clone().unwrap_or_else(|| "HTTP/1.1".to_string());
+
+        Self {
+            started_date_time: request.started_at.to_rfc3339(),
+            time,
+            request: HarRequest {
+                method: request.method.clone(),
+                url: request.url.clone(),
+                http_version: http_version.clone(),
+                cookies: request.request_header("cookie").map(parse_cookie_header).unwrap_or_default(),
+                headers: to_name_values(&request.request_headers),
+                query_string: query_string(&request.url),
+                post_data: request.request_body.as_ref().map(|body| HarPostData {
+                    mime_type: request.request_header("content-type").unwrap_or_default().to_string(),
+                    text: String::from_utf8_lossy(body).into_owned(),
+                    params: Vec::new(),
+                }),
+                headers_size: -1,
+                body_size: request.request_size as i64,
+            },
+            response: HarResponse {
+                // HAR uses status 0 for requests that never got a response
+                status: request.status.unwrap_or(0),
+                status_text: request.status_text.clone().unwrap_or_default(),
+                http_version,
+                cookies: request
+                    .response_headers
+                    .iter()
+                    .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+                    .filter_map(|(_, value)| parse_set_cookie(value))
+                    .collect(),
+                headers: to_name_values(&request.response_headers),
+                content: content_from_body(request),
+                redirect_url: request.response_header("location").unwrap_or_default().to_string(),
+                headers_size: -1,
+                body_size: if request.from_cache { 0 } else { request.response_size as i64 },
+            },
+            cache: serde_json::json!({}),
+            timings: HarTimings {
+                blocked: timings.blocked.unwrap_or(-1.0),
+                dns: timings.dns.unwrap_or(-1.0),
+                connect: timings.connect.unwrap_or(-1.0),
+                ssl: timings.ssl.unwrap_or(-1.0),
+                send: timings.send,
+                wait: timings.wait,
+                receive: timings.receive,
+            },
+            resource_type: Some(format!("{:?}", request.resource_type).to_lowercase()),
+            initiator: request.initiator.clone(),
+            error: match &request.state {
+                RequestState::Failed(error) => Some(error.clone()),
+                RequestState::Blocked => Some("blocked".to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Convert the entry back into a recorded request
+    pub fn to_request(&self, id: u64, tab_id: usize) -> NetworkRequest {
+        let started_at = DateTime::parse_from_rfc3339(&self.started_date_time)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        let at_offset = |ms: f64| started_at + Duration::microseconds((ms * 1000.0) as i64);
+
+        let timings = NetworkTimings {
+            blocked: applicable(self.timings.blocked),
+            dns: applicable(self.timings.dns),
+            connect: applicable(self.timings.connect),
+            ssl: applicable(self.timings.ssl),
+            send: self.timings.send.max(0.0),
+            wait: self.timings.wait.max(0.0),
+            receive: self.timings.receive.max(0.0),
+        };
+        // ssl is already counted in connect
+        let until_response = [timings.blocked, timings.dns, timings.connect]
+            .iter()
+            .flatten()
+            .sum::<f64>()
+            + timings.send
+            + timings.wait;
+
+        let responded = self.response.status != 0;
+        let state = match (&self.error, responded) {
+            (Some(error), _) if error == "blocked" => RequestState::Blocked,
+            (Some(error), _) => RequestState::Failed(error.clone()),
+            (None, true) => RequestState::Complete,
+            (None, false) => RequestState::Failed("No response".to_string()),
+        };
+        let response_body = self.response_body();
+
+        NetworkRequest {
+            id,
+            tab_id,
+            url: self.request.url.clone(),
+            method: self.request.method.to_uppercase(),
+            resource_type: self
+                .resource_type
+                .as_deref()
+                .map(parse_resource_type)
+                .unwrap_or_else(|| ResourceType::from_mime(&self.response.content.mime_type)),
+            initiator: self.initiator.clone(),
+            state,
+            status: responded.then_some(self.response.status),
+            status_text: responded.then(|| self.response.status_text.clone()),
+            mime_type: Some(self.response.content.mime_type.clone()).filter(|m| !m.is_empty()),
+            request_size: self.request.body_size.max(0) as u64,
+            response_size: self.response.content.size.max(0) as u64,
+            from_cache: responded && self.response.body_size == 0 && self.response.content.size > 0,
+            started_at,
+            response_at: responded.then(|| at_offset(until_response)),
+            finished_at: Some(at_offset(self.time.max(0.0))),
+            http_version: Some(self.response.http_version.clone()).filter(|v| !v.is_empty()),
+            request_headers: from_name_values(&self.request.headers),
+            response_headers: from_name_values(&self.response.headers),
+            request_body: self.request.post_data.as_ref().map(|data| data.text.clone().into_bytes()),
+            body_truncated: self.response.content.comment.as_deref() == Some(TRUNCATED_COMMENT),
+            response_body,
+            timings: Some(timings),
+        }
+    }
+
+    /// Decoded response body, if the capture included one
+    pub fn response_body(&self) -> Option<Vec<u8>> {
+        let text = self.response.content.text.as_ref()?;
+        match self.response.content.encoding.as_deref() {
+            Some("base64") => STANDARD.decode(text).ok(),
+            _ => Some(text.clone().into_bytes()),
+        }
+    }
+}
+
+const TRUNCATED_COMMENT: &str = "Body truncated at capture size limit";
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+
+fn applicable(value: f64) -> Option<f64> {
+    (value >= 0.0).then_some(value)
+}
+
+fn content_from_body(request: &NetworkRequest) -> HarContent {
+    let mime_type = request.mime_type.clone().unwrap_or_default();
+    let (text, encoding) = match &request.response_body {
+        Some(body) => match std::str::from_utf8(body) {
+            Ok(text) if is_textual(&mime_type) => (Some(text.to_string()), None),
+            _ => (Some(STANDARD.encode(body)), Some("base64".to_string())),
+        },
+        None => (None, None),
+    };
+
+    HarContent {
+        size: request.response_size as i64,
+        mime_type,
+        text,
+        encoding,
+        comment: (request.body_truncated && request.response_body.is_some()).then(|| TRUNCATED_COMMENT.to_string()),
+    }
+}
+
+fn is_textual(mime_type: &str) -> bool {
+    let mime = mime_type.to_lowercase();
+    mime.is_empty()
+        || mime.starts_with("text/")
+        || ["json", "javascript", "xml", "x-www-form-urlencoded", "svg"]
+            .iter()
+            .any(|kind| mime.contains(kind))
+}
+
+fn parse_resource_type(name: &str) -> ResourceType {
+    match name.to_lowercase().as_str() {
+        "document" => ResourceType::Document,
+        "stylesheet" => ResourceType::Stylesheet,
+        "script" => ResourceType::Script,
+        "image" => ResourceType::Image,
+        "font" => ResourceType::Font,
+        "media" => ResourceType::Media,
+        "xhr" => ResourceType::Xhr,
+        "fetch" => ResourceType::Fetch,
+        "websocket" => ResourceType::WebSocket,
+        _ => ResourceType::Other,
+    }
+}
+
+fn to_name_values(headers: &[(String, String)]) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| HarNameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+fn from_name_values(values: &[HarNameValue]) -> Vec<(String, String)> {
+    values.iter().map(|v| (v.name.clone(), v.value.clone())).collect()
+}
+
+fn parse_cookie_header(header: &str) -> Vec<HarNameValue> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some(HarNameValue {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_set_cookie(header: &str) -> Option<HarNameValue> {
+    parse_cookie_header(header.split(';').next()?).into_iter().next()
+}
+
+fn query_string(url: &str) -> Vec<HarNameValue> {
+    url::Url::parse(url)
+        .map(|parsed| {
+            parsed
+                .query_pairs()
+                .map(|(name, value)| HarNameValue {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::web_inspector::network::{NetworkLog, NetworkLogConfig, RequestFilter};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_har_export() {
+        let log = NetworkLog::default();
+        let id = log
+            .record_request(3, "GET", "https://example.com/search?q=rust&page=2", ResourceType::Document, None)
+            .unwrap();
+        log.record_response(id, 200, "OK", Some("text/html"), false);
+        log.record_complete(id, 0, 4096);
+        let failed = log
+            .record_request(3, "GET", "https://down.example.com/", ResourceType::Xhr, None)
+            .unwrap();
+        log.record_failure(failed, "net::ERR_CONNECTION_REFUSED");
+
+        let json = log.export_har(3, &RequestFilter::default()).to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["log"]["version"], "1.2");
+
+        let entries = value["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["request"]["queryString"][0]["name"], "q");
+        assert_eq!(entries[0]["response"]["content"]["mimeType"], "text/html");
+        assert_eq!(entries[0]["response"]["content"]["size"], 4096);
+        assert!(entries[0]["startedDateTime"].as_str().unwrap().contains('T'));
+        assert_eq!(entries[1]["response"]["status"], 0);
+        assert_eq!(entries[1]["_error"], "net::ERR_CONNECTION_REFUSED");
+        assert_eq!(entries[1]["timings"]["dns"], -1.0);
+    }
+
+    #[test]
+    fn test_har_round_trip() {
+        let log = NetworkLog::new(Some(NetworkLogConfig {
+            max_body_size: 8,
+            ..Default::default()
+        }));
+        let id = log
+            .record_request(1, "POST", "https://api.example.com/login", ResourceType::Fetch, None)
+            .unwrap();
+        let request_headers = vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Cookie".to_string(), "session=abc; theme=dark".to_string()),
+        ];
+        log.record_request_details(id, &request_headers, Some(b"{\"user\":1}"));
+        log.record_response(id, 302, "Found", Some("image/png"), false);
+        log.record_response_headers(id, "HTTP/2", &[
+            ("Location".to_string(), "/home".to_string()),
+            ("Set-Cookie".to_string(), "token=xyz; Path=/; HttpOnly".to_string()),
+        ]);
+        log.record_response_body(id, &[0x89, b'P', b'N', b'G', 0, 1, 2, 3, 4, 5]);
+        log.record_timings(id, NetworkTimings {
+            dns: Some(4.0),
+            connect: Some(20.0),
+            ssl: Some(12.0),
+            send: 1.0,
+            wait: 30.0,
+            receive: 5.0,
+            ..Default::default()
+        });
+        log.record_complete(id, 10, 10);
+
+        let har = log.export_har(1, &RequestFilter::default());
+        let entry = &har.log.entries[0];
+        assert_eq!(entry.request.cookies.len(), 2);
+        assert_eq!(entry.request.post_data.as_ref().unwrap().mime_type, "application/json");
+        assert_eq!(entry.response.redirect_url, "/home");
+        assert_eq!(entry.response.cookies[0].name, "token");
+        assert_eq!(entry.response.content.encoding.as_deref(), Some("base64"));
+        assert!(entry.response.content.comment.is_some());
+        assert_eq!(entry.timings.ssl, 12.0);
+
+        // Save, load into another tab and replay
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("capture.har");
+        har.save(&path).unwrap();
+        let loaded = Har::from_file(&path).unwrap();
+        assert_eq!(log.import_har(2, &loaded), 1);
+
+        let imported = &log.get_requests(2, &RequestFilter::default())[0];
+        assert_eq!(imported.method, "POST");
+        assert_eq!(imported.status, Some(302));
+        assert_eq!(imported.response_header("location"), Some("/home"));
+        assert_eq!(imported.response_body.as_deref(), Some(&[0x89, b'P', b'N', b'G', 0, 1, 2, 3][..]));
+        assert!(imported.body_truncated);
+        assert_eq!(imported.timings.as_ref().unwrap().dns, Some(4.0));
+
+        let replay = loaded.find_entry("post", "https://api.example.com/login").unwrap();
+        assert_eq!(replay.response.status, 302);
+        assert!(Har::parse(r#"{"log":{"version":"2.0","creator":{"name":"x","version":"1"}}}"#).is_err());
+    }
+}
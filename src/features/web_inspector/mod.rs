@@ -1,13 +1,60 @@
-// Web Inspector Module - Placeholder
-// TODO: Implement developer tools
-pub struct WebInspector;
+// Web Inspector Module
+use crate::error::WebxError;
+use std::path::Path;
+
+pub mod har;
+pub mod network;
+
+pub use har::{Har, HarEntry};
+pub use network::{
+    NetworkEvent, NetworkLog, NetworkLogConfig, NetworkRequest, NetworkSummary, NetworkTimings,
+    RequestFilter, RequestState, ResourceType,
+};
+
+/// Developer tools backend
+pub struct WebInspector {
+    network: NetworkLog,
+}
 
 impl WebInspector {
     pub fn new() -> Self {
-        Self
+        Self {
+            network: NetworkLog::default(),
+        }
+    }
+
+    /// Create a web inspector with a custom network log configuration
+    pub fn with_network_config(config: NetworkLogConfig) -> Self {
+        Self {
+            network: NetworkLog::new(Some(config)),
+        }
+    }
+
+    /// Request log fed by the interception layer
+    pub fn network(&self) -> &NetworkLog {
+        &self.network
     }
-    
+
+    /// Save a tab's network log as a HAR file; returns the number of entries written
+    pub fn export_har(&self, tab_id: usize, path: &Path) -> Result<usize, WebxError> {
+        let har = self.network.export_har(tab_id, &RequestFilter::default());
+        har.save(path)?;
+        Ok(har.log.entries.len())
+    }
+
+    /// Load a HAR file into a tab's network log; returns the number of entries imported
+    pub fn import_har(&self, tab_id: usize, path: &Path) -> Result<usize, WebxError> {
+        let har = Har::from_file(path)?;
+        Ok(self.network.import_har(tab_id, &har))
+    }
+
     pub fn open_dev_tools(&self) {
         // Placeholder implementation
     }
-}
\ No newline at end of file
+}
+
+impl Default for WebInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
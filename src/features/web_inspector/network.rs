@@ -0,0 +1,623 @@
+// Network Request Log
+use crate::utils::LockExt;
+use super::har::Har;
+use crate::features::downloads::MediaSniffer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Kind of resource a request loads
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ResourceType {
+    Document,
+    Stylesheet,
+    Script,
+    Image,
+    Font,
+    Media,
+    Xhr,
+    Fetch,
+    WebSocket,
+    Other,
+}
+
+impl ResourceType {
+    /// Guess the resource type from a MIME type
+    pub fn from_mime(mime_type: &str) -> Self {
+        let mime = mime_type.split(';').next().unwrap_or("").trim().to_lowercase();
+        match mime.as_str() {
+            "text/html" | "application/xhtml+xml" => ResourceType::Document,
+            "text/css" => ResourceType::Stylesheet,
+            "text/javascript" | "application/javascript" | "application/x-javascript" => ResourceType::Script,
+            m if m.starts_with("image/") => ResourceType::Image,
+            m if m.starts_with("font/") || m.contains("font") => ResourceType::Font,
+            m if m.starts_with("audio/") || m.starts_with("video/") => ResourceType::Media,
+            _ => ResourceType::Other,
+        }
+    }
+}
+
+/// Lifecycle state of a request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RequestState {
+    Pending,
+    Complete,
+    Failed(String),
+    Blocked,
+}
+
+/// Phase timings reported by the network stack, in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NetworkTimings {
+    /// Time queued before a connection was available
+    pub blocked: Option<f64>,
+    pub dns: Option<f64>,
+    /// Includes `ssl` when the connection is secure
+    pub connect: Option<f64>,
+    pub ssl: Option<f64>,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+/// A request seen by the interception layer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkRequest {
+    pub id: u64,
+    pub tab_id: usize,
+    pub url: String,
+    pub method: String,
+    pub resource_type: ResourceType,
+    /// URL of the document or script that issued the request
+    pub initiator: Option<String>,
+    pub state: RequestState,
+    pub status: Option<u16>,
+    pub status_text: Option<String>,
+    pub mime_type: Option<String>,
+    pub request_size: u64,
+    pub response_size: u64,
+    pub from_cache: bool,
+    pub started_at: DateTime<Utc>,
+    pub response_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub http_version: Option<String>,
+    #[serde(default)]
+    pub request_headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub response_headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub request_body: Option<Vec<u8>>,
+    #[serde(default)]
+    pub response_body: Option<Vec<u8>>,
+    /// A captured body was cut off at the configured size cap
+    #[serde(default)]
+    pub body_truncated: bool,
+    #[serde(default)]
+    pub timings: Option<NetworkTimings>,
+}
+
+impl NetworkRequest {
+    /// Total time from start to finish in milliseconds
+    pub fn duration_ms(&self) -> Option<f64> {
+        self.finished_at.map(|end| millis_between(self.started_at, end))
+    }
+
+    /// Time until the response headers arrived in milliseconds
+    pub fn time_to_first_byte_ms(&self) -> Option<f64> {
+        self.response_at.map(|at| millis_between(self.started_at, at))
+    }
+
+    /// Look up a request header (case-insensitive)
+    pub fn request_header(&self, name: &str) -> Option<&str> {
+        find_header(&self.request_headers, name)
+    }
+
+    /// Look up a response header (case-insensitive)
+    pub fn response_header(&self, name: &str) -> Option<&str> {
+        find_header(&self.response_headers, name)
+    }
+
+    /// Check if the request failed or returned an error status
+    pub fn is_error(&self) -> bool {
+        matches!(self.state, RequestState::Failed(_) | RequestState::Blocked)
+            || self.status.is_some_and(|status| status >= 400)
+    }
+}
+
+/// Filter for the request log panel
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestFilter {
+    /// Case-insensitive substring of the URL
+    pub text: Option<String>,
+    pub method: Option<String>,
+    /// Empty means all types
+    pub resource_types: Vec<ResourceType>,
+    /// Inclusive status code range
+    pub status_range: Option<(u16, u16)>,
+    pub errors_only: bool,
+}
+
+impl RequestFilter {
+    /// Check if a request passes the filter
+    pub fn matches(&self, request: &NetworkRequest) -> bool {
+        if let Some(text) = &self.text {
+            if !request.url.to_lowercase().contains(&text.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(method) = &self.method {
+            if !request.method.eq_ignore_ascii_case(method) {
+                return false;
+            }
+        }
+        if !self.resource_types.is_empty() && !self.resource_types.contains(&request.resource_type) {
+            return false;
+        }
+        if let Some((min, max)) = self.status_range {
+            match request.status {
+                Some(status) if (min..=max).contains(&status) => {}
+                _ => return false,
+            }
+        }
+        !self.errors_only || request.is_error()
+    }
+}
+
+/// Totals for a tab's request log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkSummary {
+    pub request_count: usize,
+    pub failed_count: usize,
+    pub transferred_bytes: u64,
+    pub cached_count: usize,
+    /// Time from the first request start to the last finish
+    pub load_time_ms: f64,
+}
+
+/// Live updates for the network panel
+#[derive(Debug, Clone)]
+pub enum NetworkEvent {
+    RequestStarted(NetworkRequest),
+    RequestUpdated(NetworkRequest),
+    TabCleared(usize),
+}
+
+/// Network log configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkLogConfig {
+    pub enabled: bool,
+    /// Requests kept per tab; the oldest are dropped first
+    pub max_requests_per_tab: usize,
+    /// Keep request and response bodies for HAR export
+    pub capture_bodies: bool,
+    /// Bodies larger than this are truncated
+    pub max_body_size: usize,
+}
+
+impl Default for NetworkLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_requests_per_tab: 1000,
+            capture_bodies: true,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Collects requests per tab in fixed-size ring buffers
+pub struct NetworkLog {
+    config: NetworkLogConfig,
+    tabs: Arc<Mutex<HashMap<usize, VecDeque<NetworkRequest>>>>,
+    /// Request id to tab id for requests still in flight
+    pending: Arc<Mutex<HashMap<u64, usize>>>,
+    next_id: AtomicU64,
+    /// Told about responses, to find media the page loads
+    media_sniffer: Option<Arc<MediaSniffer>>,
+    tx: mpsc::UnboundedSender<NetworkEvent>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<NetworkEvent>>>>,
+}
+
+impl NetworkLog {
+    /// Create new network log
+    pub fn new(config: Option<NetworkLogConfig>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            config: config.unwrap_or_default(),
+            tabs: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(1),
+            media_sniffer: None,
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+        }
+    }
+
+    /// Record a request as it leaves the browser; returns its id, or None when logging is off
+    pub fn record_request(
+        &self,
+        tab_id: usize,
+        method: &str,
+        url: &str,
+        resource_type: ResourceType,
+        initiator: Option<&str>,
+    ) -> Option<u64> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = NetworkRequest {
+            id,
+            tab_id,
+            url: url.to_string(),
+            method: method.to_uppercase(),
+            resource_type,
+            initiator: initiator.map(|s| s.to_string()),
+            state: RequestState::Pending,
+            status: None,
+            status_text: None,
+            mime_type: None,
+            request_size: 0,
+            response_size: 0,
+            from_cache: false,
+            started_at: Utc::now(),
+            response_at: None,
+            finished_at: None,
+            http_version: None,
+            request_headers: Vec::new(),
+            response_headers: Vec::new(),
+            request_body: None,
+            response_body: None,
+            body_truncated: false,
+            timings: None,
+        };
+
+        {
+            let mut tabs = self.tabs.lock_or_recover();
+            let log = tabs.entry(tab_id).or_default();
+            while log.len() >= self.config.max_requests_per_tab.max(1) {
+                if let Some(evicted) = log.pop_front() {
+                    self.pending.lock_or_recover().remove(&evicted.id);
+                }
+            }
+            log.push_back(request.clone());
+        }
+        self.pending.lock_or_recover().insert(id, tab_id);
+
+        let _ = self.tx.send(NetworkEvent::RequestStarted(request));
+        Some(id)
+    }
+
+    /// Record the headers and body sent with a request
+    pub fn record_request_details(&self, request_id: u64, headers: &[(String, String)], body: Option<&[u8]>) -> bool {
+        let body = body.and_then(|body| self.cap_body(body));
+        self.update(request_id, |request| {
+            request.request_headers = headers.to_vec();
+            if let Some((body, truncated)) = body {
+                request.request_size = request.request_size.max(body.len() as u64);
+                request.request_body = Some(body);
+                request.body_truncated |= truncated;
+            }
+        })
+    }
+
+    /// Record response headers and protocol version
+    pub fn record_response_headers(&self, request_id: u64, http_version: &str, headers: &[(String, String)]) -> bool {
+        self.update(request_id, |request| {
+            request.http_version = Some(http_version.to_string());
+            request.response_headers = headers.to_vec();
+        })
+    }
+
+    /// Record the decoded response body; must be called before `record_complete`
+    pub fn record_response_body(&self, request_id: u64, body: &[u8]) -> bool {
+        let Some((body, truncated)) = self.cap_body(body) else {
+            return false;
+        };
+        self.update(request_id, |request| {
+            request.response_body = Some(body);
+            request.body_truncated |= truncated;
+        })
+    }
+
+    /// Record phase timings measured by the network stack
+    pub fn record_timings(&self, request_id: u64, timings: NetworkTimings) -> bool {
+        self.update(request_id, |request| request.timings = Some(timings))
+    }
+
+    /// Record response headers
+    pub fn record_response(
+        &self,
+        request_id: u64,
+        status: u16,
+        status_text: &str,
+        mime_type: Option<&str>,
+        from_cache: bool,
+    ) -> bool {
+        self.update(request_id, |request| {
+            request.status = Some(status);
+            request.status_text = Some(status_text.to_string());
+            request.mime_type = mime_type.map(|s| s.to_string());
+            request.from_cache = from_cache;
+            request.response_at = Some(Utc::now());
+            if request.resource_type == ResourceType::Other {
+                if let Some(mime) = mime_type {
+                    request.resource_type = ResourceType::from_mime(mime);
+                }
+            }
+            if let Some(sniffer) = &self.media_sniffer {
+                sniffer.observe_request(request);
+            }
+        })
+    }
+
+    /// Record that the response body finished loading
+    pub fn record_complete(&self, request_id: u64, request_size: u64, response_size: u64) -> bool {
+        let updated = self.update(request_id, |request| {
+            request.state = RequestState::Complete;
+            request.request_size = request_size;
+            request.response_size = response_size;
+            request.finished_at = Some(Utc::now());
+        });
+        self.pending.lock_or_recover().remove(&request_id);
+        updated
+    }
+
+    /// Record a network error
+    pub fn record_failure(&self, request_id: u64, error: &str) -> bool {
+        let updated = self.update(request_id, |request| {
+            request.state = RequestState::Failed(error.to_string());
+            request.finished_at = Some(Utc::now());
+        });
+        self.pending.lock_or_recover().remove(&request_id);
+        updated
+    }
+
+    /// Record that a request was blocked (ad blocker, CSP, etc.)
+    pub fn record_blocked(&self, request_id: u64) -> bool {
+        let updated = self.update(request_id, |request| {
+            request.state = RequestState::Blocked;
+            request.finished_at = Some(Utc::now());
+        });
+        self.pending.lock_or_recover().remove(&request_id);
+        updated
+    }
+
+    /// Get a single request
+    pub fn get_request(&self, request_id: u64) -> Option<NetworkRequest> {
+        let tabs = self.tabs.lock_or_recover();
+        tabs.values()
+            .flat_map(|log| log.iter())
+            .find(|request| request.id == request_id)
+            .cloned()
+    }
+
+    /// Get a tab's requests in the order they started
+    pub fn get_requests(&self, tab_id: usize, filter: &RequestFilter) -> Vec<NetworkRequest> {
+        let tabs = self.tabs.lock_or_recover();
+        tabs.get(&tab_id)
+            .map(|log| log.iter().filter(|r| filter.matches(r)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get totals for a tab
+    pub fn get_summary(&self, tab_id: usize) -> NetworkSummary {
+        let tabs = self.tabs.lock_or_recover();
+        let Some(log) = tabs.get(&tab_id) else {
+            return NetworkSummary::default();
+        };
+
+        let first_start = log.iter().map(|r| r.started_at).min();
+        let last_finish = log.iter().filter_map(|r| r.finished_at).max();
+
+        NetworkSummary {
+            request_count: log.len(),
+            failed_count: log.iter().filter(|r| r.is_error()).count(),
+            transferred_bytes: log.iter().filter(|r| !r.from_cache).map(|r| r.response_size).sum(),
+            cached_count: log.iter().filter(|r| r.from_cache).count(),
+            load_time_ms: match (first_start, last_finish) {
+                (Some(start), Some(end)) => millis_between(start, end),
+                _ => 0.0,
+            },
+        }
+    }
+
+    /// Export a tab's requests as a HAR log
+    pub fn export_har(&self, tab_id: usize, filter: &RequestFilter) -> Har {
+        Har::from_requests(&self.get_requests(tab_id, filter))
+    }
+
+    /// Load a HAR capture into a tab's log; returns the number of requests added
+    pub fn import_har(&self, tab_id: usize, har: &Har) -> usize {
+        let requests = har.to_requests(tab_id, || self.next_id.fetch_add(1, Ordering::Relaxed));
+        let count = requests.len();
+
+        let mut tabs = self.tabs.lock_or_recover();
+        let log = tabs.entry(tab_id).or_default();
+        for request in requests {
+            if log.len() >= self.config.max_requests_per_tab.max(1) {
+                log.pop_front();
+            }
+            log.push_back(request);
+        }
+        count
+    }
+
+    /// Clear a tab's log (e.g. on navigation)
+    pub fn clear_tab(&self, tab_id: usize) {
+        if let Some(log) = self.tabs.lock_or_recover().get_mut(&tab_id) {
+            let mut pending = self.pending.lock_or_recover();
+            for request in log.drain(..) {
+                pending.remove(&request.id);
+            }
+        }
+        let _ = self.tx.send(NetworkEvent::TabCleared(tab_id));
+    }
+
+    /// Drop a closed tab's log
+    pub fn remove_tab(&self, tab_id: usize) {
+        if let Some(log) = self.tabs.lock_or_recover().remove(&tab_id) {
+            let mut pending = self.pending.lock_or_recover();
+            for request in log {
+                pending.remove(&request.id);
+            }
+        }
+    }
+
+    /// Offer media found in responses to a media sniffer
+    pub fn set_media_sniffer(&mut self, sniffer: Arc<MediaSniffer>) {
+        self.media_sniffer = Some(sniffer);
+    }
+
+    /// Get configuration
+    pub fn get_config(&self) -> &NetworkLogConfig {
+        &self.config
+    }
+
+    /// Subscribe to live request updates
+    pub fn subscribe_events(&self) -> mpsc::UnboundedReceiver<NetworkEvent> {
+        self.rx.lock_or_recover().take().expect("Events already subscribed")
+    }
+
+    // Private helper methods
+
+    fn cap_body(&self, body: &[u8]) -> Option<(Vec<u8>, bool)> {
+        if !self.config.capture_bodies {
+            return None;
+        }
+        let truncated = body.len() > self.config.max_body_size;
+        Some((body[..body.len().min(self.config.max_body_size)].to_vec(), truncated))
+    }
+
+    fn update(&self, request_id: u64, apply: impl FnOnce(&mut NetworkRequest)) -> bool {
+        let Some(tab_id) = self.pending.lock_or_recover().get(&request_id).copied() else {
+            return false;
+        };
+
+        let mut tabs = self.tabs.lock_or_recover();
+        let Some(request) = tabs
+            .get_mut(&tab_id)
+            .and_then(|log| log.iter_mut().rev().find(|r| r.id == request_id))
+        else {
+            return false;
+        };
+
+        apply(request);
+        let _ = self.tx.send(NetworkEvent::RequestUpdated(request.clone()));
+        true
+    }
+}
+
+impl Default for NetworkLog {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn millis_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_microseconds().unwrap_or(0).max(0) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_lifecycle_and_ring_buffer() {
+        let log = NetworkLog::new(Some(NetworkLogConfig {
+            enabled: true,
+            max_requests_per_tab: 3,
+            ..Default::default()
+        }));
+        let mut events = log.subscribe_events();
+
+        let doc = log
+            .record_request(1, "get", "https://example.com/", ResourceType::Document, None)
+            .unwrap();
+        assert!(log.record_response(doc, 200, "OK", Some("text/html"), false));
+        assert!(log.record_complete(doc, 120, 5120));
+
+        let request = log.get_request(doc).unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.state, RequestState::Complete);
+        assert!(request.duration_ms().unwrap() >= request.time_to_first_byte_ms().unwrap());
+        assert!(matches!(events.try_recv(), Ok(NetworkEvent::RequestStarted(_))));
+
+        // The oldest requests are dropped once the tab's buffer is full
+        for i in 0..3 {
+            log.record_request(1, "GET", &format!("https://example.com/{}.png", i), ResourceType::Image, None);
+        }
+        let requests = log.get_requests(1, &RequestFilter::default());
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|r| r.id != doc));
+        assert!(!log.record_complete(doc, 0, 0));
+
+        // Other tabs are unaffected
+        assert!(log.get_requests(2, &RequestFilter::default()).is_empty());
+        log.remove_tab(1);
+        assert_eq!(log.get_summary(1).request_count, 0);
+    }
+
+    #[test]
+    fn test_filtering_and_summary() {
+        let log = NetworkLog::default();
+        let page = "https://shop.example.com/";
+
+        let doc = log.record_request(7, "GET", page, ResourceType::Document, None).unwrap();
+        log.record_response(doc, 200, "OK", Some("text/html"), false);
+        log.record_complete(doc, 100, 2000);
+
+        let api = log
+            .record_request(7, "POST", "https://api.example.com/cart", ResourceType::Fetch, Some(page))
+            .unwrap();
+        log.record_response(api, 500, "Internal Server Error", Some("application/json"), false);
+        log.record_complete(api, 300, 50);
+
+        let logo = log
+            .record_request(7, "GET", "https://cdn.example.com/logo", ResourceType::Other, Some(page))
+            .unwrap();
+        log.record_response(logo, 200, "OK", Some("image/svg+xml"), true);
+        log.record_complete(logo, 0, 800);
+
+        let tracker = log
+            .record_request(7, "GET", "https://tracker.example.net/p", ResourceType::Script, Some(page))
+            .unwrap();
+        log.record_blocked(tracker);
+
+        let errors = log.get_requests(7, &RequestFilter { errors_only: true, ..Default::default() });
+        assert_eq!(errors.len(), 2);
+
+        let images = log.get_requests(7, &RequestFilter {
+            resource_types: vec![ResourceType::Image],
+            ..Default::default()
+        });
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].initiator.as_deref(), Some(page));
+
+        let posts = log.get_requests(7, &RequestFilter {
+            text: Some("CART".to_string()),
+            method: Some("post".to_string()),
+            status_range: Some((500, 599)),
+            ..Default::default()
+        });
+        assert_eq!(posts.len(), 1);
+
+        let summary = log.get_summary(7);
+        assert_eq!(summary.request_count, 4);
+        assert_eq!(summary.failed_count, 2);
+        assert_eq!(summary.cached_count, 1);
+        assert_eq!(summary.transferred_bytes, 2050);
+    }
+}
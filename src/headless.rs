@@ -0,0 +1,209 @@
+// Headless browser core for the command line and scripting
+use crate::config::ConfigManager;
+use crate::core::{BrowserState, DownloadStatus};
+use crate::error::WebxError;
+use crate::features::bookmark_manager::export_bookmarks_html;
+use crate::features::downloads::{DownloadEvent, DownloadManager, DownloadPolicy};
+use crate::features::system::proxy::ProxyManager;
+use crate::features::{PrivacyProtection, RetentionEngine, RetentionTargets, RetentionTrigger};
+use crate::runtime::BrowserRuntime;
+use crate::utils::{LockExt, StateWatchdog};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A page fetched without rendering it
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// URL after following redirects
+    pub url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// The browser's feature managers running without a window
+pub struct HeadlessBrowser {
+    runtime: BrowserRuntime,
+    config: Arc<ConfigManager>,
+    state: Arc<Mutex<BrowserState>>,
+    proxy_manager: Arc<Mutex<ProxyManager>>,
+    privacy_protection: Arc<PrivacyProtection>,
+    watchdog: Arc<StateWatchdog>,
+}
+
+impl HeadlessBrowser {
+    /// Create headless browser using the profile in `config_dir`, or the
+    /// default profile
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, WebxError> {
+        let runtime = BrowserRuntime::new(None)?;
+        let config = Arc::new(match config_dir {
+            Some(config_dir) => ConfigManager::with_config_dir(config_dir)?,
+            None => ConfigManager::new()?,
+        });
+
+        let mut state = BrowserState::new();
+        state.settings = config.load_settings();
+        state.bookmarks = config.load_bookmarks();
+        state.history = config.load_history();
+        for failure in config.load_failures() {
+            tracing::warn!("Failed to load {}", failure);
+        }
+
+        let state = Arc::new(Mutex::new(state));
+        let proxy_manager = Arc::new(Mutex::new(ProxyManager::new(None, None)?));
+        let privacy_protection = Arc::new(PrivacyProtection::new(None, None)?);
+
+        let watchdog = Arc::new(StateWatchdog::new());
+        watchdog.watch_with_reset("browser state", &state, BrowserState::repair);
+        watchdog.watch("proxy", &proxy_manager);
+
+        Ok(Self {
+            runtime,
+            config,
+            state,
+            proxy_manager,
+            privacy_protection,
+            watchdog,
+        })
+    }
+
+    /// Fetch a URL through the configured proxies
+    pub fn fetch(&self, url: &str) -> Result<FetchedPage, WebxError> {
+        let (client, _route) = self.proxy_manager.lock_or_recover().client_for_url(url)?;
+        let url = url.to_string();
+
+        self.runtime.block_on(async move {
+            let response = client.get(&url).send().await?;
+            let final_url = response.url().to_string();
+            let status = response.status().as_u16();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let body = response.bytes().await?.to_vec();
+
+            Ok(FetchedPage {
+                url: final_url,
+                status,
+                content_type,
+                body,
+            })
+        })
+    }
+
+    /// Fetch a URL and save the response body to a file
+    pub fn save_page(&self, url: &str, path: &Path) -> Result<FetchedPage, WebxError> {
+        let page = self.fetch(url)?;
+        std::fs::write(path, &page.body)?;
+        Ok(page)
+    }
+
+    /// Download a file with the download manager and wait for it to finish
+    pub fn download(&self, url: &str, download_dir: Option<PathBuf>) -> Result<PathBuf, WebxError> {
+        let mut manager = DownloadManager::new(download_dir)?;
+        manager.set_proxy_manager(Arc::clone(&self.proxy_manager));
+        manager.set_runtime(self.runtime.handle().clone());
+        manager.set_policy(DownloadPolicy::load(None, None)?);
+        let mut events = manager.subscribe_events();
+
+        self.runtime.block_on(async {
+            let download_id = manager.start_download(url).await?;
+            while let Some(event) = events.recv().await {
+                match event {
+                    DownloadEvent::Completed(id) if id == download_id => break,
+                    DownloadEvent::Failed(id, message) if id == download_id => {
+                        return Err(WebxError::Network(message));
+                    }
+                    // Nobody is there to confirm a held file type
+                    DownloadEvent::ConfirmationRequired(id, decision) if id == download_id => {
+                        return Err(WebxError::Invalid(decision.reason));
+                    }
+                    DownloadEvent::Blocked(id, reason) if id == download_id => {
+                        return Err(WebxError::Invalid(reason));
+                    }
+                    _ => {}
+                }
+            }
+
+            let download = manager
+                .get_download(download_id)
+                .filter(|download| download.status == DownloadStatus::Completed)
+                .ok_or_else(|| WebxError::Invalid(format!("Download of {} did not finish", url)))?;
+            Ok(PathBuf::from(download.path))
+        })
+    }
+
+    /// Capture a screenshot of a page. Rendering needs a webview, which the
+    /// headless core doesn't have yet.
+    pub fn screenshot(&self, url: &str, _out: &Path) -> Result<(), WebxError> {
+        Err(WebxError::Invalid(format!(
+            "Cannot screenshot {}: headless mode has no rendering backend",
+            url
+        )))
+    }
+
+    /// Export the profile's bookmarks as an HTML file other browsers import
+    pub fn export_bookmarks(&self, path: &Path) -> Result<usize, WebxError> {
+        export_bookmarks_html(&self.state.lock_or_recover().bookmarks, path)
+    }
+
+    /// Run the background services (retention, watchdog) until Ctrl+C,
+    /// then apply exit retention and save the profile
+    pub fn run_until_interrupted(&self) -> Result<(), WebxError> {
+        let _runtime_guard = self.runtime.enter();
+        let retention_engine = Arc::new(Mutex::new(RetentionEngine::new(
+            self.privacy_protection.retention_policy(),
+            RetentionTargets::default(),
+        )));
+        RetentionEngine::start_daily_timer(Arc::clone(&retention_engine));
+        StateWatchdog::start_watching(Arc::clone(&self.watchdog), Duration::from_secs(5));
+
+        tracing::info!("Running headless, press Ctrl+C to stop");
+        self.runtime.block_on(tokio::signal::ctrl_c())?;
+
+        self.watchdog.stop_watching();
+        let mut retention_engine = retention_engine.lock_or_recover();
+        retention_engine.stop_daily_timer();
+        retention_engine.run(RetentionTrigger::Exit);
+        let mut state = self.state.lock_or_recover();
+        retention_engine.prune_history_entries(&mut state.history, RetentionTrigger::Exit);
+        self.save_state(&state)
+    }
+
+    /// Shared browser state
+    pub fn state(&self) -> Arc<Mutex<BrowserState>> {
+        Arc::clone(&self.state)
+    }
+
+    // Private helper methods
+
+    fn save_state(&self, state: &BrowserState) -> Result<(), WebxError> {
+        self.config.save_settings(&state.settings)?;
+        self.config.save_bookmarks(&state.bookmarks)?;
+        self.config.save_history(&state.history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_bookmarks_from_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let profile = ConfigManager::with_config_dir(temp_dir.path().to_path_buf()).unwrap();
+        let mut state = BrowserState::new();
+        state.add_bookmark("Example".to_string(), "https://example.com/".to_string());
+        profile.save_bookmarks(&state.bookmarks).unwrap();
+
+        let browser = HeadlessBrowser::new(Some(temp_dir.path().to_path_buf())).unwrap();
+        let out = temp_dir.path().join("bookmarks.html");
+        assert_eq!(browser.export_bookmarks(&out).unwrap(), 1);
+        assert!(std::fs::read_to_string(&out)
+            .unwrap()
+            .contains(">Example</A>"));
+    }
+}
@@ -0,0 +1,226 @@
+// Message Bundles
+use crate::error::WebxError;
+use std::collections::HashMap;
+
+/// A message's value: text, or one of several texts picked by an argument
+#[derive(Debug, Clone, PartialEq)]
+enum Message {
+    Text(String),
+    /// `{ $variable -> [key] text *[other] text }`, picked by the argument's
+    /// exact value, then by its plural category, then the `*` default
+    Select {
+        variable: String,
+        variants: Vec<(String, String)>,
+        default: usize,
+    },
+}
+
+/// The messages of one locale, in the Fluent syntax subset WebX uses:
+/// `id = text` lines, `{ $name }` arguments, `#` comments, indented
+/// continuation lines and selects over a single argument
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    locale: String,
+    messages: HashMap<String, Message>,
+}
+
+impl Bundle {
+    /// Parse a locale's messages
+    pub fn parse(locale: &str, source: &str) -> Result<Self, WebxError> {
+        // (id, value, line) with continuation lines joined by newlines
+        let mut entries: Vec<(String, String, usize)> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) || line == "}" {
+                let (_, value, _) = entries
+                    .last_mut()
+                    .ok_or_else(|| parse_error(locale, number, "indented line outside a message"))?;
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+                continue;
+            }
+            let (id, value) = line
+                .split_once('=')
+                .ok_or_else(|| parse_error(locale, number, "expected `id = text`"))?;
+            let id = id.trim();
+            if !is_valid_id(id) {
+                return Err(parse_error(locale, number, &format!("{:?} is not a message ID", id)));
+            }
+            entries.push((id.to_string(), value.trim().to_string(), number));
+        }
+
+        let mut messages = HashMap::new();
+        for (id, value, line) in entries {
+            let message = parse_value(&value).map_err(|e| parse_error(locale, line, e))?;
+            if messages.insert(id.clone(), message).is_some() {
+                return Err(parse_error(locale, line, &format!("{} is defined twice", id)));
+            }
+        }
+        Ok(Self {
+            locale: locale.to_string(),
+            messages,
+        })
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn has_message(&self, id: &str) -> bool {
+        self.messages.contains_key(id)
+    }
+
+    /// IDs of every message, in no particular order
+    pub fn message_ids(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(String::as_str)
+    }
+
+    /// A message with its arguments filled in, `None` if the bundle lacks it.
+    /// Arguments the message uses but was not given are left as `{$name}`.
+    pub fn format(&self, id: &str, args: &[(&str, &str)]) -> Option<String> {
+        let pattern = match self.messages.get(id)? {
+            Message::Text(text) => text.as_str(),
+            Message::Select {
+                variable,
+                variants,
+                default,
+            } => {
+                let value = args.iter().find(|(name, _)| name == variable).map(|(_, value)| *value);
+                let category = value
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .map(|number| plural_category(&self.locale, number));
+                variants
+                    .iter()
+                    .find(|(key, _)| Some(key.as_str()) == value)
+                    .or_else(|| variants.iter().find(|(key, _)| Some(key.as_str()) == category))
+                    .unwrap_or(&variants[*default])
+                    .1
+                    .as_str()
+            }
+        };
+        Some(fill_arguments(pattern, args))
+    }
+}
+
+// Private helper functions
+
+fn parse_error(locale: &str, line: usize, message: &str) -> WebxError {
+    WebxError::Parse(format!("{} messages, line {}: {}", locale, line, message))
+}
+
+fn is_valid_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn parse_value(value: &str) -> Result<Message, &'static str> {
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or_default();
+    let Some(selector) = first.strip_prefix('{').and_then(|rest| rest.trim().strip_suffix("->")) else {
+        return Ok(Message::Text(value.to_string()));
+    };
+    let variable = selector
+        .trim()
+        .strip_prefix('$')
+        .ok_or("only arguments can be selected on")?
+        .to_string();
+
+    let mut variants = Vec::new();
+    let mut default = None;
+    for line in lines {
+        if line == "}" {
+            let default = default.ok_or("select has no `*` default variant")?;
+            return Ok(Message::Select {
+                variable,
+                variants,
+                default,
+            });
+        }
+        let (is_default, variant) = match line.strip_prefix('*') {
+            Some(variant) => (true, variant),
+            None => (false, line),
+        };
+        let (key, text) = variant
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .ok_or("expected a `[key] text` variant")?;
+        if is_default && default.replace(variants.len()).is_some() {
+            return Err("select has two default variants");
+        }
+        variants.push((key.trim().to_string(), text.trim().to_string()));
+    }
+    Err("select is missing its closing `}`")
+}
+
+/// Replace `{ $name }` with arguments
+fn fill_arguments(pattern: &str, args: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        let placeable = rest[start + 1..start + length].trim();
+        if let Some(name) = placeable.strip_prefix('$') {
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => text.push_str(value),
+                None => text.push_str(&format!("{{${}}}", name)),
+            }
+        } else {
+            text.push_str(&rest[start..=start + length]);
+        }
+        rest = &rest[start + length + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+/// CLDR cardinal plural category of a number for the locales WebX ships
+fn plural_category(locale: &str, number: f64) -> &'static str {
+    let language = locale.split('-').next().unwrap_or_default();
+    let one = match language {
+        // French counts 0 and 1.x as singular
+        "fr" => (0.0..2.0).contains(&number),
+        _ => number == 1.0,
+    };
+    if one {
+        "one"
+    } else {
+        "other"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_messages() {
+        let source = "# Comment\n\
+            greeting = Hello, { $name }!\n\
+            long = First line\n    second line\n\
+            files = { $count ->\n    [0] No files\n    [one] One file\n   *[other] { $count } files\n}\n";
+        let bundle = Bundle::parse("en-US", source).unwrap();
+        assert_eq!(bundle.format("greeting", &[("name", "Ada")]).unwrap(), "Hello, Ada!");
+        assert_eq!(bundle.format("greeting", &[]).unwrap(), "Hello, {$name}!");
+        assert_eq!(bundle.format("long", &[]).unwrap(), "First line\nsecond line");
+        assert_eq!(bundle.format("files", &[("count", "0")]).unwrap(), "No files");
+        assert_eq!(bundle.format("files", &[("count", "1")]).unwrap(), "One file");
+        assert_eq!(bundle.format("files", &[("count", "7")]).unwrap(), "7 files");
+        assert_eq!(bundle.format("missing", &[]), None);
+
+        let french = Bundle::parse("fr", "files = { $count ->\n    [one] { $count } fichier\n   *[other] { $count } fichiers\n}").unwrap();
+        assert_eq!(french.format("files", &[("count", "0")]).unwrap(), "0 fichier");
+
+        assert!(Bundle::parse("en-US", "no equals sign").is_err());
+        assert!(Bundle::parse("en-US", "a = 1\na = 2").is_err());
+        assert!(Bundle::parse("en-US", "a = { $n ->\n    [one] x\n}").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+# WebX browser UI strings, German
+
+## Tabs
+
+tab-new = Neuer Tab
+
+## Menu bar
+
+menu-file = Datei
+menu-edit = Bearbeiten
+menu-view = Ansicht
+menu-history = Chronik
+menu-bookmarks = Lesezeichen
+menu-help = Hilfe
+
+menu-new-tab = Neuer Tab
+menu-new-window = Neues Fenster
+menu-move-tab-to-new-window = Tab in neues Fenster verschieben
+menu-search-tabs = Tabs durchsuchen
+menu-group-tabs = Tabs nach Domain gruppieren
+menu-review-stale-tabs = Alte Tabs prüfen
+menu-new-identity = Neue Identität
+menu-close-tab = Tab schließen
+menu-close-window = Fenster schließen
+menu-exit = Beenden
+
+menu-undo = Rückgängig
+menu-redo = Wiederholen
+menu-cut = Ausschneiden
+menu-copy = Kopieren
+menu-paste = Einfügen
+menu-select-all = Alles auswählen
+
+menu-reload = Neu laden
+menu-force-reload = Ohne Cache neu laden
+menu-zoom-in = Vergrößern
+menu-zoom-out = Verkleinern
+menu-reset-zoom = Originalgröße
+menu-zoom-text-only = Nur Text zoomen
+menu-mute-tab = Tab stummschalten
+menu-mute-background-tabs = Hintergrund-Tabs stummschalten
+menu-picture-in-picture = Bild-im-Bild
+menu-stop-recording = Kamera und Mikrofon beenden
+menu-split-view = Geteilte Ansicht
+menu-rotate-split = Teilung drehen
+menu-translate-page = Seite übersetzen
+menu-show-original = Original anzeigen
+menu-read-aloud = Vorlesen
+menu-stop-read-aloud = Vorlesen beenden
+menu-screenshot = Bildschirmfoto aufnehmen
+menu-screenshot-full-page = Bildschirmfoto der ganzen Seite
+menu-caret-browsing = Textcursor-Navigation
+menu-link-hints = Link-Kürzel
+menu-toggle-devtools = Entwicklerwerkzeuge ein/aus
+
+menu-go-back = Zurück
+menu-go-forward = Vor
+menu-go-home = Startseite
+menu-show-history = Chronik anzeigen
+menu-clear-history = Chronik löschen
+
+menu-bookmark-page = Lesezeichen für diese Seite
+menu-show-bookmarks = Lesezeichen anzeigen
+menu-bookmark-manager = Lesezeichen verwalten
+menu-save-for-later = Für später speichern
+menu-show-reading-list = Leseliste anzeigen
+menu-subscribe-feed = Feed abonnieren
+menu-show-feeds = Feeds anzeigen
+menu-clip-to-notes = In Notizen übernehmen
+menu-show-notes = Notizen anzeigen
+
+menu-about = Über WebX
+menu-check-updates = Nach Updates suchen
+menu-report-issue = Problem melden
+menu-export-diagnostics = Diagnosedaten exportieren
+menu-show-stats = Nutzungsstatistik
+menu-documentation = Dokumentation
+
+## System tray
+
+tray-show = WebX anzeigen
+tray-hide = WebX ausblenden
+tray-new-private-window = Neues privates Fenster
+tray-recent-downloads = Letzte Downloads
+tray-no-downloads = Keine Downloads
+tray-retry-download = { $filename } erneut versuchen
+tray-quit = WebX beenden
+tray-tooltip = { $count ->
+    [0] WebX
+    [one] WebX - 1 Download läuft
+   *[other] WebX - { $count } Downloads laufen
+}
+
+## Error pages
+
+error-dns-title = Server nicht gefunden
+error-dns-explanation = WebX konnte { $host } nicht finden. Prüfen Sie die Adresse auf Tippfehler; die Website existiert möglicherweise nicht mehr.
+error-tls-title = Sichere Verbindung fehlgeschlagen
+error-tls-explanation = { $host } hat keine sichere Verbindung aufgebaut. Das Zertifikat ist möglicherweise ungültig oder abgelaufen, oder etwas im Netzwerk stört die Verbindung.
+error-refused-title = Verbindung abgelehnt
+error-refused-explanation = { $host } hat die Verbindung abgelehnt. Die Website ist möglicherweise nicht erreichbar, oder eine Firewall blockiert sie.
+error-timeout-title = Zeitüberschreitung der Verbindung
+error-timeout-explanation = { $host } hat zu lange nicht geantwortet. Der Server ist möglicherweise überlastet oder gerade nicht erreichbar.
+error-offline-title = Sie sind offline
+error-offline-explanation = Ihr Gerät ist nicht mit dem Internet verbunden. Prüfen Sie Ihre Netzwerkverbindung.
+error-other-title = Diese Seite konnte nicht geladen werden
+error-other-explanation = Beim Laden von { $host } ist ein Fehler aufgetreten.
+
+error-try-again = Erneut versuchen
+error-try-http = Über HTTP versuchen
+error-open-cached = Gespeicherte Kopie öffnen
+error-saved-at = gespeichert { $time }
+error-saved-pages = Zum Offline-Lesen gespeichert
+error-diagnostics = Diagnose
+error-code = Fehlercode
+error-address = Adresse
+error-connection = Verbindung
+error-direct = Direkt
+error-time = Zeit
+error-details = Details
+
+## Settings
+
+language-ui-locale = Sprache des Browsers
+language-ui-locale-description = Sprache der Menüs, des Infobereichs und der Browserseiten
+language-system = Systemsprache
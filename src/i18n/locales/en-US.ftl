@@ -0,0 +1,127 @@
+# WebX browser UI strings, English (United States)
+# Every other locale falls back to these for messages it lacks.
+
+## Tabs
+
+tab-new = New Tab
+
+## Menu bar
+
+menu-file = File
+menu-edit = Edit
+menu-view = View
+menu-history = History
+menu-bookmarks = Bookmarks
+menu-help = Help
+
+menu-new-tab = New Tab
+menu-new-window = New Window
+menu-move-tab-to-new-window = Move Tab to New Window
+menu-search-tabs = Search Tabs
+menu-group-tabs = Group Tabs by Domain
+menu-review-stale-tabs = Review Stale Tabs
+menu-new-identity = New Identity
+menu-close-tab = Close Tab
+menu-close-window = Close Window
+menu-exit = Exit
+
+menu-undo = Undo
+menu-redo = Redo
+menu-cut = Cut
+menu-copy = Copy
+menu-paste = Paste
+menu-select-all = Select All
+
+menu-reload = Reload
+menu-force-reload = Force Reload
+menu-zoom-in = Zoom In
+menu-zoom-out = Zoom Out
+menu-reset-zoom = Reset Zoom
+menu-zoom-text-only = Zoom Text Only
+menu-mute-tab = Mute Tab
+menu-mute-background-tabs = Mute Background Tabs
+menu-picture-in-picture = Picture in Picture
+menu-stop-recording = Stop Camera and Microphone
+menu-split-view = Split View
+menu-rotate-split = Rotate Split
+menu-translate-page = Translate Page
+menu-show-original = Show Original
+menu-read-aloud = Read Aloud
+menu-stop-read-aloud = Stop Reading Aloud
+menu-screenshot = Take Screenshot
+menu-screenshot-full-page = Take Full Page Screenshot
+menu-caret-browsing = Caret Browsing
+menu-link-hints = Link Hints
+menu-toggle-devtools = Toggle Developer Tools
+
+menu-go-back = Back
+menu-go-forward = Forward
+menu-go-home = Home
+menu-show-history = Show History
+menu-clear-history = Clear History
+
+menu-bookmark-page = Bookmark This Page
+menu-show-bookmarks = Show Bookmarks
+menu-bookmark-manager = Bookmark Manager
+menu-save-for-later = Save for Later
+menu-show-reading-list = Show Reading List
+menu-subscribe-feed = Subscribe to Feed
+menu-show-feeds = Show Feeds
+menu-clip-to-notes = Clip to Notes
+menu-show-notes = Show Notes
+
+menu-about = About WebX
+menu-check-updates = Check for Updates
+menu-report-issue = Report Issue
+menu-export-diagnostics = Export Diagnostics
+menu-show-stats = Usage Statistics
+menu-documentation = Documentation
+
+## System tray
+
+tray-show = Show WebX
+tray-hide = Hide WebX
+tray-new-private-window = New Private Window
+tray-recent-downloads = Recent Downloads
+tray-no-downloads = No downloads
+tray-retry-download = Retry { $filename }
+tray-quit = Quit WebX
+tray-tooltip = { $count ->
+    [0] WebX
+    [one] WebX - 1 download in progress
+   *[other] WebX - { $count } downloads in progress
+}
+
+## Error pages
+
+error-dns-title = Server not found
+error-dns-explanation = WebX could not find { $host }. Check the address for typos; the site may also no longer exist.
+error-tls-title = Secure connection failed
+error-tls-explanation = { $host } did not set up a secure connection. Its certificate may be invalid or expired, or something on the network is interfering.
+error-refused-title = Connection refused
+error-refused-explanation = { $host } refused the connection. The site may be down, or a firewall may be blocking it.
+error-timeout-title = The connection timed out
+error-timeout-explanation = { $host } took too long to respond. It may be overloaded or unreachable right now.
+error-offline-title = You are offline
+error-offline-explanation = Your device is not connected to the internet. Check your network connection.
+error-other-title = This page could not be loaded
+error-other-explanation = Something went wrong while loading { $host }.
+
+error-try-again = Try Again
+error-try-http = Try HTTP
+error-open-cached = Open Cached Copy
+error-saved-at = saved { $time }
+error-saved-pages = Saved for offline reading
+error-diagnostics = Diagnostics
+error-code = Error code
+error-address = Address
+error-connection = Connection
+error-direct = Direct
+error-time = Time
+error-details = Details
+
+## Settings
+
+language-ui-locale = Browser language
+language-ui-locale-description = Language of menus, the tray and browser pages
+language-system = System language
@@ -0,0 +1,126 @@
+# WebX browser UI strings, Spanish
+
+## Tabs
+
+tab-new = Nueva pestaña
+
+## Menu bar
+
+menu-file = Archivo
+menu-edit = Editar
+menu-view = Ver
+menu-history = Historial
+menu-bookmarks = Marcadores
+menu-help = Ayuda
+
+menu-new-tab = Nueva pestaña
+menu-new-window = Nueva ventana
+menu-move-tab-to-new-window = Mover pestaña a una ventana nueva
+menu-search-tabs = Buscar pestañas
+menu-group-tabs = Agrupar pestañas por dominio
+menu-review-stale-tabs = Revisar pestañas inactivas
+menu-new-identity = Nueva identidad
+menu-close-tab = Cerrar pestaña
+menu-close-window = Cerrar ventana
+menu-exit = Salir
+
+menu-undo = Deshacer
+menu-redo = Rehacer
+menu-cut = Cortar
+menu-copy = Copiar
+menu-paste = Pegar
+menu-select-all = Seleccionar todo
+
+menu-reload = Recargar
+menu-force-reload = Recargar sin caché
+menu-zoom-in = Ampliar
+menu-zoom-out = Reducir
+menu-reset-zoom = Tamaño real
+menu-zoom-text-only = Ampliar solo el texto
+menu-mute-tab = Silenciar pestaña
+menu-mute-background-tabs = Silenciar pestañas en segundo plano
+menu-picture-in-picture = Imagen en imagen
+menu-stop-recording = Detener cámara y micrófono
+menu-split-view = Vista dividida
+menu-rotate-split = Girar vista dividida
+menu-translate-page = Traducir página
+menu-show-original = Mostrar original
+menu-read-aloud = Leer en voz alta
+menu-stop-read-aloud = Dejar de leer
+menu-screenshot = Hacer captura de pantalla
+menu-screenshot-full-page = Capturar la página completa
+menu-caret-browsing = Navegación con cursor
+menu-link-hints = Atajos de enlaces
+menu-toggle-devtools = Herramientas para desarrolladores
+
+menu-go-back = Atrás
+menu-go-forward = Adelante
+menu-go-home = Inicio
+menu-show-history = Mostrar historial
+menu-clear-history = Borrar historial
+
+menu-bookmark-page = Añadir página a marcadores
+menu-show-bookmarks = Mostrar marcadores
+menu-bookmark-manager = Administrar marcadores
+menu-save-for-later = Guardar para más tarde
+menu-show-reading-list = Mostrar lista de lectura
+menu-subscribe-feed = Suscribirse al feed
+menu-show-feeds = Mostrar feeds
+menu-clip-to-notes = Recortar en notas
+menu-show-notes = Mostrar notas
+
+menu-about = Acerca de WebX
+menu-check-updates = Buscar actualizaciones
+menu-report-issue = Informar de un problema
+menu-export-diagnostics = Exportar diagnósticos
+menu-show-stats = Estadísticas de uso
+menu-documentation = Documentación
+
+## System tray
+
+tray-show = Mostrar WebX
+tray-hide = Ocultar WebX
+tray-new-private-window = Nueva ventana privada
+tray-recent-downloads = Descargas recientes
+tray-no-downloads = No hay descargas
+tray-retry-download = Reintentar { $filename }
+tray-quit = Salir de WebX
+tray-tooltip = { $count ->
+    [0] WebX
+    [one] WebX - 1 descarga en curso
+   *[other] WebX - { $count } descargas en curso
+}
+
+## Error pages
+
+error-dns-title = No se encontró el servidor
+error-dns-explanation = WebX no pudo encontrar { $host }. Compruebe que la dirección no tenga errores; es posible que el sitio ya no exista.
+error-tls-title = Falló la conexión segura
+error-tls-explanation = { $host } no estableció una conexión segura. Puede que su certificado no sea válido o haya caducado, o que algo en la red esté interfiriendo.
+error-refused-title = Conexión rechazada
+error-refused-explanation = { $host } rechazó la conexión. Puede que el sitio no esté disponible o que un cortafuegos lo esté bloqueando.
+error-timeout-title = Se agotó el tiempo de conexión
+error-timeout-explanation = { $host } tardó demasiado en responder. Puede que esté sobrecargado o que no se pueda acceder a él ahora.
+error-offline-title = No tiene conexión
+error-offline-explanation = Su dispositivo no está conectado a Internet. Compruebe su conexión de red.
+error-other-title = No se pudo cargar esta página
+error-other-explanation = Se produjo un error al cargar { $host }.
+
+error-try-again = Reintentar
+error-try-http = Probar con HTTP
+error-open-cached = Abrir copia guardada
+error-saved-at = guardada el { $time }
+error-saved-pages = Guardadas para leer sin conexión
+error-diagnostics = Diagnóstico
+error-code = Código de error
+error-address = Dirección
+error-connection = Conexión
+error-direct = Directa
+error-time = Hora
+error-details = Detalles
+
+## Settings
+
+language-ui-locale = Idioma del navegador
+language-ui-locale-description = Idioma de los menús, la bandeja del sistema y las páginas del navegador
+language-system = Idioma del sistema
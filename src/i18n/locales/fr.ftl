@@ -0,0 +1,126 @@
+# WebX browser UI strings, French
+
+## Tabs
+
+tab-new = Nouvel onglet
+
+## Menu bar
+
+menu-file = Fichier
+menu-edit = Édition
+menu-view = Affichage
+menu-history = Historique
+menu-bookmarks = Marque-pages
+menu-help = Aide
+
+menu-new-tab = Nouvel onglet
+menu-new-window = Nouvelle fenêtre
+menu-move-tab-to-new-window = Déplacer l’onglet vers une nouvelle fenêtre
+menu-search-tabs = Rechercher dans les onglets
+menu-group-tabs = Grouper les onglets par domaine
+menu-review-stale-tabs = Revoir les onglets inactifs
+menu-new-identity = Nouvelle identité
+menu-close-tab = Fermer l’onglet
+menu-close-window = Fermer la fenêtre
+menu-exit = Quitter
+
+menu-undo = Annuler
+menu-redo = Rétablir
+menu-cut = Couper
+menu-copy = Copier
+menu-paste = Coller
+menu-select-all = Tout sélectionner
+
+menu-reload = Actualiser
+menu-force-reload = Actualiser sans le cache
+menu-zoom-in = Zoom avant
+menu-zoom-out = Zoom arrière
+menu-reset-zoom = Taille réelle
+menu-zoom-text-only = Zoomer le texte seulement
+menu-mute-tab = Couper le son de l’onglet
+menu-mute-background-tabs = Couper le son des onglets en arrière-plan
+menu-picture-in-picture = Incrustation vidéo
+menu-stop-recording = Arrêter la caméra et le micro
+menu-split-view = Vue partagée
+menu-rotate-split = Pivoter la vue partagée
+menu-translate-page = Traduire la page
+menu-show-original = Afficher l’original
+menu-read-aloud = Lire à voix haute
+menu-stop-read-aloud = Arrêter la lecture
+menu-screenshot = Capture d’écran
+menu-screenshot-full-page = Capture de la page entière
+menu-caret-browsing = Navigation au curseur
+menu-link-hints = Raccourcis de liens
+menu-toggle-devtools = Outils de développement
+
+menu-go-back = Précédent
+menu-go-forward = Suivant
+menu-go-home = Accueil
+menu-show-history = Afficher l’historique
+menu-clear-history = Effacer l’historique
+
+menu-bookmark-page = Marquer cette page
+menu-show-bookmarks = Afficher les marque-pages
+menu-bookmark-manager = Gérer les marque-pages
+menu-save-for-later = Lire plus tard
+menu-show-reading-list = Afficher la liste de lecture
+menu-subscribe-feed = S’abonner au flux
+menu-show-feeds = Afficher les flux
+menu-clip-to-notes = Ajouter aux notes
+menu-show-notes = Afficher les notes
+
+menu-about = À propos de WebX
+menu-check-updates = Rechercher des mises à jour
+menu-report-issue = Signaler un problème
+menu-export-diagnostics = Exporter les diagnostics
+menu-show-stats = Statistiques d’utilisation
+menu-documentation = Documentation
+
+## System tray
+
+tray-show = Afficher WebX
+tray-hide = Masquer WebX
+tray-new-private-window = Nouvelle fenêtre privée
+tray-recent-downloads = Téléchargements récents
+tray-no-downloads = Aucun téléchargement
+tray-retry-download = Réessayer { $filename }
+tray-quit = Quitter WebX
+tray-tooltip = { $count ->
+    [0] WebX
+    [one] WebX - { $count } téléchargement en cours
+   *[other] WebX - { $count } téléchargements en cours
+}
+
+## Error pages
+
+error-dns-title = Serveur introuvable
+error-dns-explanation = WebX n’a pas trouvé { $host }. Vérifiez que l’adresse ne contient pas de faute de frappe ; le site n’existe peut-être plus.
+error-tls-title = Échec de la connexion sécurisée
+error-tls-explanation = { $host } n’a pas établi de connexion sécurisée. Son certificat est peut-être invalide ou expiré, ou un élément du réseau interfère.
+error-refused-title = Connexion refusée
+error-refused-explanation = { $host } a refusé la connexion. Le site est peut-être hors service, ou un pare-feu le bloque.
+error-timeout-title = Le délai de connexion a expiré
+error-timeout-explanation = { $host } a mis trop de temps à répondre. Il est peut-être surchargé ou injoignable pour le moment.
+error-offline-title = Vous êtes hors ligne
+error-offline-explanation = Votre appareil n’est pas connecté à Internet. Vérifiez votre connexion réseau.
+error-other-title = Impossible de charger cette page
+error-other-explanation = Une erreur s’est produite lors du chargement de { $host }.
+
+error-try-again = Réessayer
+error-try-http = Essayer en HTTP
+error-open-cached = Ouvrir la copie enregistrée
+error-saved-at = enregistrée le { $time }
+error-saved-pages = Enregistrées pour la lecture hors ligne
+error-diagnostics = Diagnostics
+error-code = Code d’erreur
+error-address = Adresse
+error-connection = Connexion
+error-direct = Directe
+error-time = Heure
+error-details = Détails
+
+## Settings
+
+language-ui-locale = Langue du navigateur
+language-ui-locale-description = Langue des menus, de la zone de notification et des pages du navigateur
+language-system = Langue du système
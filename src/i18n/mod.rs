@@ -0,0 +1,226 @@
+// Localization of Browser UI Strings
+pub mod bundle;
+
+pub use bundle::Bundle;
+
+use crate::config::registry::{SettingCategory, SettingDefinition, SettingValue, SettingsProvider};
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Locale every message exists in, used for messages a locale lacks
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Setting value that follows the operating system's language
+pub const SYSTEM_LOCALE: &str = "system";
+
+/// Locales shipped with the browser: tag, name in that language, messages
+const BUNDLED_LOCALES: &[(&str, &str, &str)] = &[
+    ("en-US", "English (US)", include_str!("locales/en-US.ftl")),
+    ("de", "Deutsch", include_str!("locales/de.ftl")),
+    ("es", "Español", include_str!("locales/es.ftl")),
+    ("fr", "Français", include_str!("locales/fr.ftl")),
+];
+
+/// Locales the UI can be shown in, as (tag, name in that language)
+pub fn available_locales() -> Vec<(&'static str, &'static str)> {
+    BUNDLED_LOCALES.iter().map(|(tag, name, _)| (*tag, *name)).collect()
+}
+
+/// The shipped locale closest to a requested one: the same tag, then the
+/// same language, then the default
+pub fn negotiate_locale(requested: &str) -> &'static str {
+    let requested = normalize_locale(requested);
+    let language = requested.split('-').next().unwrap_or_default();
+    BUNDLED_LOCALES
+        .iter()
+        .find(|(tag, _, _)| tag.eq_ignore_ascii_case(&requested))
+        .or_else(|| {
+            BUNDLED_LOCALES
+                .iter()
+                .find(|(tag, _, _)| tag.split('-').next().is_some_and(|l| l.eq_ignore_ascii_case(language)))
+        })
+        .map(|(tag, _, _)| *tag)
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// The user's language from the environment (`LC_ALL`, `LC_MESSAGES`,
+/// `LANG`), as a BCP 47 tag
+pub fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value != "C" && value != "POSIX" && !value.starts_with("C."))
+        .map(|value| normalize_locale(&value))
+}
+
+/// `de_DE.UTF-8@euro` style POSIX locales as BCP 47 tags, e.g. `de-DE`
+pub fn normalize_locale(locale: &str) -> String {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default().trim();
+    let mut parts = locale.split(['_', '-']).filter(|part| !part.is_empty());
+    let mut tag = parts.next().unwrap_or_default().to_lowercase();
+    for part in parts {
+        tag.push('-');
+        if part.len() == 2 {
+            tag.push_str(&part.to_uppercase());
+        } else {
+            tag.push_str(part);
+        }
+    }
+    tag
+}
+
+/// The UI language and its messages, switchable while the browser runs
+pub struct Localizer {
+    fallback: Arc<Bundle>,
+    current: Mutex<Arc<Bundle>>,
+}
+
+impl Localizer {
+    /// Localizer showing the shipped locale closest to `locale`
+    pub fn new(locale: &str) -> Self {
+        let fallback = Arc::new(load_bundle(DEFAULT_LOCALE).expect("the default locale's messages parse"));
+        let localizer = Self {
+            current: Mutex::new(Arc::clone(&fallback)),
+            fallback,
+        };
+        localizer.set_locale(locale);
+        localizer
+    }
+
+    /// Tag of the locale shown
+    pub fn locale(&self) -> String {
+        self.current.lock_or_recover().locale().to_string()
+    }
+
+    /// Switch to the shipped locale closest to `locale`, or the system
+    /// language for `SYSTEM_LOCALE`, and return the locale switched to
+    pub fn set_locale(&self, locale: &str) -> String {
+        let requested = if locale == SYSTEM_LOCALE {
+            system_locale().unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+        } else {
+            locale.to_string()
+        };
+        let tag = negotiate_locale(&requested);
+        let bundle = match load_bundle(tag) {
+            Ok(bundle) => Arc::new(bundle),
+            Err(e) => {
+                tracing::warn!("Showing {} instead: {}", DEFAULT_LOCALE, e);
+                Arc::clone(&self.fallback)
+            }
+        };
+        let shown = bundle.locale().to_string();
+        *self.current.lock_or_recover() = bundle;
+        shown
+    }
+
+    /// A message in the current locale
+    pub fn tr(&self, id: &str) -> String {
+        self.tr_args(id, &[])
+    }
+
+    /// A message in the current locale with its `{ $name }` arguments filled
+    /// in. Messages the locale lacks come from the default locale, and ones
+    /// missing there too show their ID.
+    pub fn tr_args(&self, id: &str, args: &[(&str, &str)]) -> String {
+        let bundle = Arc::clone(&self.current.lock_or_recover());
+        bundle
+            .format(id, args)
+            .or_else(|| self.fallback.format(id, args))
+            .unwrap_or_else(|| {
+                tracing::warn!("No message {}", id);
+                id.to_string()
+            })
+    }
+}
+
+impl SettingsProvider for Localizer {
+    fn module(&self) -> &str {
+        "language"
+    }
+
+    fn settings(&self) -> Vec<SettingDefinition> {
+        let system = self.tr("language-system");
+        let mut options = vec![(SYSTEM_LOCALE, system.as_str())];
+        options.extend(available_locales());
+        vec![
+            SettingDefinition::choice("language.ui_locale", &self.tr("language-ui-locale"), SYSTEM_LOCALE, &options)
+                .with_description(&self.tr("language-ui-locale-description"))
+                .with_category(SettingCategory::Languages),
+        ]
+    }
+
+    fn apply_setting(&self, key: &str, value: &SettingValue) -> Result<(), WebxError> {
+        match key {
+            "language.ui_locale" => {
+                let locale = value.as_str().ok_or("Expected a locale")?;
+                let shown = self.set_locale(locale);
+                tracing::info!("Showing the browser in {}", shown);
+                Ok(())
+            }
+            _ => Err(format!("Unknown language setting {}", key).into()),
+        }
+    }
+}
+
+/// The browser's localizer. It shows the default locale until the UI
+/// switches it to the system language or the user's choice.
+pub fn localizer() -> &'static Arc<Localizer> {
+    static LOCALIZER: OnceLock<Arc<Localizer>> = OnceLock::new();
+    LOCALIZER.get_or_init(|| Arc::new(Localizer::new(DEFAULT_LOCALE)))
+}
+
+/// A message in the browser's current locale
+pub fn tr(id: &str) -> String {
+    localizer().tr(id)
+}
+
+/// A message in the browser's current locale, with arguments
+pub fn tr_args(id: &str, args: &[(&str, &str)]) -> String {
+    localizer().tr_args(id, args)
+}
+
+// Private helper functions
+
+fn load_bundle(tag: &str) -> Result<Bundle, WebxError> {
+    let (_, _, source) = BUNDLED_LOCALES
+        .iter()
+        .find(|(bundled, _, _)| *bundled == tag)
+        .ok_or_else(|| WebxError::NotFound(format!("Locale {}", tag)))?;
+    Bundle::parse(tag, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locales_negotiate_and_fall_back() {
+        assert_eq!(normalize_locale("de_DE.UTF-8@euro"), "de-DE");
+        assert_eq!(normalize_locale("pt-br"), "pt-BR");
+        assert_eq!(negotiate_locale("en_US.UTF-8"), "en-US");
+        assert_eq!(negotiate_locale("de_AT.UTF-8"), "de");
+        assert_eq!(negotiate_locale("en-GB"), "en-US");
+        assert_eq!(negotiate_locale("ja-JP"), DEFAULT_LOCALE);
+
+        // Every shipped locale parses and only has messages the default has
+        let default = load_bundle(DEFAULT_LOCALE).unwrap();
+        for (tag, _) in available_locales() {
+            let bundle = load_bundle(tag).unwrap();
+            let unknown: Vec<&str> = bundle.message_ids().filter(|id| !default.has_message(id)).collect();
+            assert!(unknown.is_empty(), "{} has unknown messages {:?}", tag, unknown);
+        }
+
+        let localizer = Localizer::new("fr-CA");
+        assert_eq!(localizer.locale(), "fr");
+        assert_eq!(localizer.tr("menu-new-tab"), "Nouvel onglet");
+        assert_eq!(localizer.tr_args("tray-tooltip", &[("count", "2")]), "WebX - 2 téléchargements en cours");
+        assert_eq!(localizer.tr("no-such-message"), "no-such-message");
+
+        localizer.set_locale("de");
+        assert_eq!(localizer.tr("menu-new-tab"), "Neuer Tab");
+        localizer.apply_setting("language.ui_locale", &SettingValue::String("en-US".to_string())).unwrap();
+        assert_eq!(localizer.tr_args("tray-tooltip", &[("count", "1")]), "WebX - 1 download in progress");
+    }
+}
@@ -0,0 +1,476 @@
+// IPC Messages
+use super::IpcScope;
+use crate::config::registry::SettingValue;
+use crate::config::SETTINGS_PAGE_URL;
+use crate::features::feeds::FEEDS_PAGE_URL;
+use crate::features::history_manager::FormHistoryAction;
+use crate::features::downloads::SniffedResource;
+use crate::features::media::MediaReport;
+use crate::features::productivity::clipper::NOTES_PAGE_URL;
+use crate::features::productivity::gallery::GallerySelection;
+use crate::features::productivity::keyboard_nav::KeyboardNavAction;
+use crate::features::productivity::printing::PrintRequest;
+use crate::features::productivity::reading_list::READING_LIST_PAGE_URL;
+use crate::features::productivity::screenshot::CaptureReply;
+use crate::features::productivity::share::ShareItem;
+use crate::features::productivity::translate::PageText;
+use crate::features::security::clipboard::ClipboardAction;
+use crate::features::security::csp::{CspViolation, ScriptUsageKind};
+use crate::features::security::permissions::RecordingAction;
+use crate::features::security::privacy::PRIVACY_PAGE_URL;
+use crate::features::security::safe_browsing::SAFE_BROWSING_PAGE_URL;
+use crate::features::security::webauthn::{CredentialAssertionRequest, CredentialCreationRequest};
+use crate::features::system::metrics::STATS_PAGE_URL;
+use crate::features::tabs::STALE_TABS_PAGE_URL;
+use crate::features::ui::context_menu::MenuTarget;
+use crate::features::ui::search::FindOptions;
+use crate::features::ui::zoom::ZoomAction;
+use crate::features::web_inspector::ResourceType;
+use serde::Deserialize;
+
+/// A message a page sends with `window.ipc.send` or `window.ipc.request`,
+/// told apart by its `type`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum IpcMessage {
+    #[serde(rename = "pageload")]
+    PageLoad {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        title: String,
+    },
+    #[serde(rename = "titlechange")]
+    TitleChange { title: String },
+    #[serde(rename = "beforeunload")]
+    BeforeUnload {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        scroll_x: f64,
+        #[serde(default)]
+        scroll_y: f64,
+    },
+    /// Go back, negative, or forward through the tab's history
+    #[serde(rename = "traverse")]
+    Traverse { offset: isize },
+
+    #[serde(rename = "newwindow")]
+    NewWindow,
+    #[serde(rename = "movetabtonewwindow")]
+    MoveTabToNewWindow,
+    #[serde(rename = "switchtab")]
+    SwitchTab { tab_id: usize },
+    /// A tab was dropped at a position, or the end, of the window's strip
+    #[serde(rename = "movetab")]
+    MoveTab {
+        tab_id: usize,
+        #[serde(default)]
+        index: Option<usize>,
+    },
+    #[serde(rename = "tabsearch")]
+    TabSearch {
+        #[serde(default)]
+        query: String,
+        #[serde(default)]
+        cycle: bool,
+    },
+    #[serde(rename = "grouptabs")]
+    GroupTabs,
+    #[serde(rename = "reviewstaletabs")]
+    ReviewStaleTabs,
+    #[serde(rename = "staletabs")]
+    StaleTabs { action: StaleTabsAction, tabs: Vec<usize> },
+    /// Drop cookies, cache and site data once the user confirms; only the
+    /// privacy page may ask
+    #[serde(rename = "newidentity")]
+    NewIdentity,
+    #[serde(rename = "split")]
+    Split { action: SplitAction },
+
+    #[serde(rename = "mutetab")]
+    MuteTab,
+    #[serde(rename = "mutebackgroundtabs")]
+    MuteBackgroundTabs,
+    #[serde(rename = "pictureinpicture")]
+    PictureInPicture,
+    /// Close the tab's camera and microphone
+    #[serde(rename = "stoprecording")]
+    StopRecording,
+    #[serde(rename = "media")]
+    Media(MediaReport),
+    #[serde(rename = "readaloud")]
+    ReadAloud(ReadAloudAction),
+
+    #[serde(rename = "screenshot")]
+    Screenshot {
+        #[serde(default)]
+        full_page: bool,
+    },
+    /// Answer of a capture script
+    #[serde(rename = "capture")]
+    Capture(CaptureReply),
+
+    #[serde(rename = "pagetext")]
+    PageText(PageText),
+    #[serde(rename = "translate")]
+    Translate {
+        #[serde(default)]
+        action: Option<TranslateAction>,
+    },
+    #[serde(rename = "showoriginal")]
+    ShowOriginal,
+
+    #[serde(rename = "feeds")]
+    Feeds(FeedsAction),
+    #[serde(rename = "subscribefeed")]
+    SubscribeFeed,
+    #[serde(rename = "showfeeds")]
+    ShowFeeds,
+
+    #[serde(rename = "clippage")]
+    ClipPage,
+    #[serde(rename = "clip")]
+    Clip {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        selection: Option<String>,
+        #[serde(default)]
+        html: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    #[serde(rename = "notes")]
+    Notes(NotesAction),
+    #[serde(rename = "shownotes")]
+    ShowNotes,
+
+    #[serde(rename = "savepage")]
+    SavePage,
+    #[serde(rename = "savelater")]
+    SaveLater {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        html: String,
+    },
+    #[serde(rename = "readinglist")]
+    ReadingList(ReadingListAction),
+    #[serde(rename = "showreadinglist")]
+    ShowReadingList,
+
+    /// Links and images dropped onto the page, as text or a URI list;
+    /// downloaded once the user confirms, as pages can claim any drop
+    #[serde(rename = "droplinks")]
+    DropLinks { text: String },
+    /// A file the page made itself, read into a data: URL
+    #[serde(rename = "savedata")]
+    SaveData {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        filename: Option<String>,
+        data: String,
+    },
+    /// Images picked in the page's gallery, to save into one folder
+    #[serde(rename = "saveimages")]
+    SaveImages(GallerySelection),
+    /// Audio, video and stream playlists the page loaded
+    #[serde(rename = "mediafound")]
+    MediaFound { resources: Vec<SniffedResource> },
+    /// Save media found on the page, its main video or audio without a URL
+    #[serde(rename = "downloadmedia")]
+    DownloadMedia {
+        #[serde(default)]
+        url: Option<String>,
+    },
+
+    #[serde(rename = "errorpage")]
+    ErrorPage(ErrorPageAction),
+    #[serde(rename = "stats")]
+    Stats { action: StatsAction },
+    #[serde(rename = "privacyreport")]
+    PrivacyReport { action: PrivacyReportAction },
+    #[serde(rename = "settings")]
+    Settings(SettingsAction),
+    #[serde(rename = "showsettings")]
+    ShowSettings,
+
+    /// The user right-clicked; answered with the menu's items
+    #[serde(rename = "contextmenu")]
+    ContextMenu(MenuTarget),
+    /// The user picked an item of the tab's open menu
+    #[serde(rename = "contextmenu-activate")]
+    ContextMenuActivate { item: String },
+
+    /// Print the page, its selection or its simplified version; answered
+    /// with what to print
+    #[serde(rename = "print-page")]
+    PrintPage(PrintRequest),
+
+    /// Answered with the share menu's entries
+    #[serde(rename = "share-targets")]
+    ShareTargets,
+    /// Share a page or link with a target; answered with what the page does next
+    #[serde(rename = "share")]
+    Share {
+        target: String,
+        #[serde(default)]
+        destination: Option<String>,
+        #[serde(flatten)]
+        item: ShareItem,
+    },
+
+    /// Form history of the page's site: record a submitted form, or
+    /// answer with suggestions for a field
+    #[serde(rename = "formhistory")]
+    FormHistory(FormHistoryAction),
+
+    /// A page asking for the accessibility CSS and minimum font size
+    #[serde(rename = "accessibility")]
+    Accessibility,
+
+    /// Caret browsing and link hint state, or labels for link hints
+    #[serde(rename = "keyboardnav")]
+    KeyboardNav(KeyboardNavAction),
+    /// F7 in a page: caret browsing on or off everywhere
+    #[serde(rename = "togglecaret")]
+    ToggleCaretBrowsing,
+    /// A link followed from the keyboard into a new tab; any page can send
+    /// it, so it goes through the pop-up blocker
+    #[serde(rename = "openinnewtab")]
+    OpenInNewTab { url: String },
+
+    /// Zoom the page's site in, out, back, or between full page and text only
+    #[serde(rename = "zoom")]
+    Zoom { action: ZoomAction },
+    /// A page asking how much to scale its text
+    #[serde(rename = "zoomlevel")]
+    ZoomLevel,
+
+    /// A page reading or writing the clipboard, or the user copying a link
+    #[serde(rename = "clipboard")]
+    Clipboard(ClipboardAction),
+
+    /// A page about to open the camera or microphone, or reporting which
+    /// of them it has open
+    #[serde(rename = "recording")]
+    Recording(RecordingAction),
+
+    /// The user chose to visit a site the Safe Browsing warning blocked
+    #[serde(rename = "safe-browsing-proceed")]
+    SafeBrowsingProceed { url: String },
+
+    /// A secure page loaded a subresource over HTTP
+    #[serde(rename = "mixedcontent")]
+    MixedContent { url: String, resource: ResourceType },
+
+    /// navigator.credentials.create() from a page, settled later through
+    /// `window.webxWebAuthn.settle(token, ...)`
+    #[serde(rename = "webauthn-create")]
+    WebAuthnCreate {
+        token: u64,
+        #[serde(flatten)]
+        request: CredentialCreationRequest,
+    },
+    /// navigator.credentials.get() from a page, settled the same way
+    #[serde(rename = "webauthn-get")]
+    WebAuthnGet {
+        token: u64,
+        #[serde(flatten)]
+        request: CredentialAssertionRequest,
+    },
+
+    /// A page's push subscription request, settled later through
+    /// `window.webxPush.settle(token, ...)`
+    #[serde(rename = "push")]
+    Push {
+        token: u64,
+        scope: String,
+        #[serde(flatten)]
+        action: PushAction,
+    },
+
+    #[serde(rename = "find-start")]
+    FindStart {
+        #[serde(rename = "sessionId")]
+        session_id: String,
+        query: String,
+        #[serde(default)]
+        options: FindOptions,
+        /// The page's text, as the highlight runtime walks it
+        #[serde(default)]
+        content: String,
+    },
+    #[serde(rename = "find-next")]
+    FindNext {
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+    #[serde(rename = "find-previous")]
+    FindPrevious {
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+    #[serde(rename = "find-end")]
+    FindEnd {
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+
+    #[serde(rename = "csp-violation")]
+    CspViolation { violation: CspViolation },
+    #[serde(rename = "csp-script-usage")]
+    CspScriptUsage {
+        kind: ScriptUsageKind,
+        #[serde(default = "default_count")]
+        count: u64,
+    },
+    #[serde(rename = "csp-meta-policy")]
+    CspMetaPolicy { content: String },
+}
+
+impl IpcMessage {
+    /// Pages the message is accepted from. Actions of internal pages are only
+    /// taken from those pages, so a web page cannot, say, change settings.
+    pub fn scope(&self) -> IpcScope {
+        match self {
+            IpcMessage::Settings(_) => IpcScope::InternalPage(SETTINGS_PAGE_URL),
+            IpcMessage::Stats { .. } => IpcScope::InternalPage(STATS_PAGE_URL),
+            IpcMessage::PrivacyReport { .. } | IpcMessage::NewIdentity => IpcScope::InternalPage(PRIVACY_PAGE_URL),
+            IpcMessage::SafeBrowsingProceed { .. } => IpcScope::InternalPage(SAFE_BROWSING_PAGE_URL),
+            IpcMessage::StaleTabs { .. } => IpcScope::InternalPage(STALE_TABS_PAGE_URL),
+            IpcMessage::Notes(_) => IpcScope::InternalPage(NOTES_PAGE_URL),
+            IpcMessage::ReadingList(_) => IpcScope::InternalPage(READING_LIST_PAGE_URL),
+            IpcMessage::Feeds(FeedsAction::Detected { .. }) => IpcScope::AnyPage,
+            IpcMessage::Feeds(_) => IpcScope::InternalPage(FEEDS_PAGE_URL),
+            _ => IpcScope::AnyPage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StaleTabsAction {
+    Close,
+    /// Keep the tabs, counting them as used now
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitAction {
+    Toggle,
+    Rotate,
+    Grow,
+    Shrink,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReadAloudAction {
+    /// Start reading the page, or pause and resume
+    Toggle {
+        #[serde(default)]
+        html: String,
+        #[serde(default)]
+        url: String,
+    },
+    Next,
+    Previous,
+    Stop,
+    Faster,
+    Slower,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslateAction {
+    Once,
+    Always,
+    /// Only remember not to offer translating the site
+    Never,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum FeedsAction {
+    /// The page's head, sent by the feed detection script
+    Detected { url: String, head: String },
+    MarkRead { item: u64 },
+    /// Mark one feed's items read, or all items
+    MarkAllRead {
+        #[serde(default)]
+        feed: Option<u64>,
+    },
+    Unsubscribe { feed: u64 },
+    Refresh,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum NotesAction {
+    Delete { note: u64 },
+    Export { note: u64 },
+    ExportAll,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReadingListAction {
+    MarkRead { entry: u64 },
+    MarkUnread { entry: u64 },
+    Remove { entry: u64 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ErrorPageAction {
+    /// Open the copy of `url` saved for offline reading
+    Cached { url: String },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsAction {
+    Enable,
+    Disable,
+    Clear,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyReportAction {
+    Clear,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum SettingsAction {
+    Set { key: String, value: SettingValue },
+    Reset { key: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum PushAction {
+    Subscribe {
+        /// VAPID public key, base64url
+        #[serde(default)]
+        application_server_key: Option<String>,
+    },
+    Get,
+    Unsubscribe,
+}
+
+// Private helper functions
+
+fn default_count() -> u64 {
+    1
+}
+
@@ -0,0 +1,197 @@
+// Webview IPC
+//
+// Pages talk to the browser through `window.ipc`, set up by the init script:
+// `send(message)` for notifications and `request(message)`, which resolves
+// with the handler's answer. Messages are JSON objects told apart by `type`;
+// a request also carries an `id`, and is answered by running
+// `window.ipc.resolve(id, value)` or `window.ipc.reject(id, error)` in the
+// page.
+mod message;
+
+pub use message::*;
+
+use crate::error::WebxError;
+use crate::utils::LockExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Message types whose bodies hold captures or page contents, too big to
+/// log, or what the user typed into forms
+const UNLOGGED_TYPES: &[&str] = &["capture", "pagetext", "readaloud", "feeds", "clip", "savelater", "savedata", "find-start", "print-page", "share", "formhistory"];
+
+/// Pages a message is accepted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcScope {
+    /// Any page, for scripts injected into every page
+    AnyPage,
+    /// Only the internal page at an address such as "webx://settings"
+    InternalPage(&'static str),
+}
+
+impl IpcScope {
+    /// Whether a page, by URL, may send the message
+    pub fn allows(&self, origin: &str) -> bool {
+        match self {
+            IpcScope::AnyPage => true,
+            IpcScope::InternalPage(page) => origin
+                .strip_prefix(page)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#'])),
+        }
+    }
+}
+
+/// Where a message came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpcContext {
+    pub window_id: usize,
+    /// Tab the sending pane shows
+    pub tab_id: Option<usize>,
+    /// URL of the sending page
+    pub origin: String,
+}
+
+/// Takes the messages of one feature
+pub trait IpcHandler: Send + Sync {
+    /// `type`s of the messages it takes
+    fn message_types(&self) -> &'static [&'static str];
+
+    /// Act on a message. The value answers a `window.ipc.request`; it is
+    /// dropped for messages sent with `window.ipc.send`.
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError>;
+}
+
+/// Hands each message from a page to the handler registered for its type
+#[derive(Default)]
+pub struct IpcRouter {
+    handlers: Mutex<HashMap<&'static str, Arc<dyn IpcHandler>>>,
+}
+
+impl IpcRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a handler for its message types; a type has one handler
+    pub fn register(&self, handler: Arc<dyn IpcHandler>) -> Result<(), WebxError> {
+        let mut handlers = self.handlers.lock_or_recover();
+        if let Some(taken) = handler.message_types().iter().find(|kind| handlers.contains_key(*kind)) {
+            return Err(WebxError::Invalid(format!("IPC message type {} already has a handler", taken)));
+        }
+        for kind in handler.message_types() {
+            handlers.insert(kind, Arc::clone(&handler));
+        }
+        Ok(())
+    }
+
+    /// Whether a message type has a handler
+    pub fn handles(&self, message_type: &str) -> bool {
+        self.handlers.lock_or_recover().contains_key(message_type)
+    }
+
+    /// Route a message body from a page. Returns the script answering it
+    /// when it was a request, to run in the sending page.
+    pub fn dispatch(&self, context: &IpcContext, body: &str) -> Option<String> {
+        let envelope: serde_json::Value = match serde_json::from_str(body) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::warn!("Unreadable IPC message from {}: {}", context.origin, e);
+                return None;
+            }
+        };
+        let id = envelope["id"].as_u64();
+        let kind = envelope["type"].as_str().unwrap_or_default().to_string();
+        if !UNLOGGED_TYPES.contains(&kind.as_str()) {
+            tracing::info!("IPC message: {}", body);
+        }
+
+        let result = self.route(context, &kind, envelope);
+        if let Err(e) = &result {
+            tracing::warn!("IPC message {} from {} failed: {}", kind, context.origin, e);
+        }
+        id.map(|id| reply_script(id, result))
+    }
+
+    // Private helper methods
+
+    fn route(&self, context: &IpcContext, kind: &str, envelope: serde_json::Value) -> Result<serde_json::Value, WebxError> {
+        let handler = self
+            .handlers
+            .lock_or_recover()
+            .get(kind)
+            .cloned()
+            .ok_or_else(|| WebxError::NotFound(format!("No handler for IPC message type {:?}", kind)))?;
+        let message: IpcMessage = serde_json::from_value(envelope)?;
+        if !message.scope().allows(&context.origin) {
+            return Err(WebxError::Invalid(format!("{} may not send {} messages", context.origin, kind)));
+        }
+        handler.handle(context, message)
+    }
+}
+
+// Private helper functions
+
+/// Script settling the page's promise for request `id`
+fn reply_script(id: u64, result: Result<serde_json::Value, WebxError>) -> String {
+    match result {
+        Ok(value) => format!("window.ipc.resolve({}, {});", id, value),
+        Err(e) => format!("window.ipc.reject({}, {});", id, serde_json::Value::String(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl IpcHandler for Recorder {
+        fn message_types(&self) -> &'static [&'static str] {
+            &["traverse", "settings"]
+        }
+
+        fn handle(&self, _context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+            self.0.lock_or_recover().push(format!("{:?}", message));
+            match message {
+                IpcMessage::Traverse { offset } => Ok(serde_json::json!({ "went": offset })),
+                _ => Ok(serde_json::Value::Null),
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch_answers_requests_and_checks_origin() {
+        let router = IpcRouter::new();
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        router.register(recorder.clone()).unwrap();
+        assert!(router.register(recorder.clone()).is_err());
+
+        let page = IpcContext {
+            window_id: 1,
+            tab_id: Some(3),
+            origin: "https://example.com/".to_string(),
+        };
+        assert_eq!(
+            router.dispatch(&page, r#"{"type":"traverse","offset":-1,"id":7}"#).as_deref(),
+            Some(r#"window.ipc.resolve(7, {"went":-1});"#)
+        );
+        assert_eq!(router.dispatch(&page, r#"{"type":"traverse","offset":1}"#), None);
+
+        // Only the settings page may change settings
+        let change = r#"{"type":"settings","action":"reset","key":"general.home_page","id":8}"#;
+        assert!(router.dispatch(&page, change).unwrap().starts_with("window.ipc.reject(8,"));
+        let settings = IpcContext {
+            origin: "webx://settings".to_string(),
+            ..page.clone()
+        };
+        assert!(router.dispatch(&settings, change).unwrap().starts_with("window.ipc.resolve(8,"));
+
+        // Pages cannot start over with a new identity; the privacy page asks the browser to
+        assert!(!IpcMessage::NewIdentity.scope().allows(&page.origin));
+        assert!(IpcMessage::NewIdentity.scope().allows("webx://privacy"));
+
+        // Unknown types and malformed messages are refused
+        assert!(router.dispatch(&page, r#"{"type":"print-page","id":9}"#).unwrap().contains("No handler"));
+        assert!(router.dispatch(&page, r#"{"type":"traverse","id":10}"#).unwrap().starts_with("window.ipc.reject(10,"));
+        assert_eq!(recorder.0.lock_or_recover().len(), 3);
+    }
+}
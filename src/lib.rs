@@ -5,9 +5,17 @@ pub mod ui;
 pub mod core;
 pub mod utils;
 pub mod config;
+pub mod error;
 pub mod features;
+pub mod ipc;
+pub mod headless;
+pub mod i18n;
+pub mod runtime;
 
 pub use ui::*;
 pub use core::*;
 pub use utils::*;
 pub use config::*;
+pub use error::{ErrorEvent, ErrorKind, ErrorReporter, WebxError};
+pub use headless::{FetchedPage, HeadlessBrowser};
+pub use runtime::BrowserRuntime;
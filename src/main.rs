@@ -1,22 +1,299 @@
-use tracing_subscriber;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use webx::config::ConfigManager;
+use webx::features::system::backup::{ProfileBackup, ProfileLocations, ProfileRestoreOptions, ProfileSection};
+use webx::features::system::default_browser::{DefaultBrowserManager, RegistrationStatus};
+use webx::features::system::diagnostics::{self, LoggingConfig};
+use webx::features::system::instance::{launch_url, SingleInstance};
+use webx::headless::HeadlessBrowser;
 use webx::ui::BrowserApp;
 
+/// WebX Browser - Official system browser for Ledokoz OS
+#[derive(Parser)]
+#[command(name = "webx", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// Run the browser core without a window
+    #[arg(long, global = true)]
+    headless: bool,
+
+    /// Use this profile directory instead of the default one
+    #[arg(long, global = true)]
+    profile: Option<PathBuf>,
+
+    /// URLs or files to open as tabs, in the running browser if there is one
+    urls: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch a page and print it or save it to a file
+    Fetch {
+        url: String,
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+    /// Capture a screenshot of a page
+    Screenshot {
+        url: String,
+        out: PathBuf,
+        /// Capture the whole page instead of the visible part
+        #[arg(long)]
+        full_page: bool,
+    },
+    /// Download a file with the download manager
+    Download {
+        url: String,
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Manage bookmarks
+    Bookmarks {
+        #[command(subcommand)]
+        command: BookmarksCommand,
+    },
+    /// Make WebX the system's default browser
+    DefaultBrowser {
+        #[command(subcommand)]
+        command: DefaultBrowserCommand,
+    },
+    /// Back up or restore the whole profile as one encrypted file
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Zip recent logs, scrubbed settings and version info for a bug report
+    ExportDiagnostics {
+        /// Where to write the bundle; a dated file in the current directory by default
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BookmarksCommand {
+    /// Export bookmarks as an HTML file other browsers import
+    Export { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Save settings, bookmarks, history, passwords and sessions to a file
+    Create { file: PathBuf },
+    /// Restore a backup made with `backup create`
+    Restore {
+        file: PathBuf,
+        /// Restore only these parts: config, bookmarks, history, vault, sessions
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DefaultBrowserCommand {
+    /// Register WebX for web links and make it the default browser
+    Register,
+    /// Remove WebX's registration
+    Unregister,
+    /// Show whether WebX is the default browser
+    Status,
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .init();
+    let cli = Cli::parse();
+
+    // Log to stderr and per-component files, keeping crash reports next to them
+    let logging = LoggingConfig::default();
+    if let Err(e) = diagnostics::init_logging(&logging) {
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(false)
+            .with_writer(std::io::stderr)
+            .init();
+        diagnostics::install_panic_hook(&logging.log_dir);
+        tracing::warn!("Log files unavailable: {}", e);
+    }
+
+    if let Some(command) = cli.command {
+        return run_command(command, cli.profile);
+    }
 
     tracing::info!("🌐 WebX Browser - Official system browser for Ledokoz OS");
-    tracing::info!("Version 0.1.0");
+    tracing::info!("Version {}", env!("CARGO_PKG_VERSION"));
     tracing::info!("Built with Rust ❤️");
+
+    if cli.headless {
+        tracing::info!("Starting headless browser...");
+        HeadlessBrowser::new(cli.profile)?.run_until_interrupted()?;
+        tracing::info!("Headless browser stopped");
+        return Ok(());
+    }
+
+    // The OS passes links (including mailto:) as arguments
+    let default_browser = DefaultBrowserManager::default();
+    let urls: Vec<String> = cli.urls.iter().filter_map(|url| default_browser.resolve_url(url)).collect();
+
+    // Hand the URLs to an already running browser instead of starting another
+    let mut single_instance = SingleInstance::new(None)?;
+    if !single_instance.try_acquire()? {
+        single_instance.forward_urls(&urls)?;
+        tracing::info!("Opened in the running browser");
+        return Ok(());
+    }
+
     tracing::info!("Starting browser...");
 
     // Create and run the browser
-    let app = BrowserApp::new()?;
+    let mut app = BrowserApp::new()?;
+    app.set_single_instance(single_instance);
+    let cwd = std::env::current_dir()?;
+    app.open_on_start(urls.iter().map(|url| launch_url(url, &cwd)).collect());
     app.run()?;
 
     tracing::info!("Browser closed");
     Ok(())
 }
+
+fn run_command(command: Command, profile: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let command = match command {
+        Command::DefaultBrowser { command } => return run_default_browser_command(command),
+        Command::ExportDiagnostics { out } => return run_export_diagnostics_command(out, profile),
+        Command::Backup { command } => return run_backup_command(command, profile),
+        Command::Screenshot { url, out, full_page } => return run_screenshot_command(&url, &out, full_page, profile),
+        command => command,
+    };
+
+    let browser = HeadlessBrowser::new(profile)?;
+    match command {
+        Command::Fetch { url, save: Some(path) } => {
+            let page = browser.save_page(&url, &path)?;
+            tracing::info!("Saved {} ({}) to {}", page.url, page.status, path.display());
+        }
+        Command::Fetch { url, save: None } => {
+            let page = browser.fetch(&url)?;
+            std::io::Write::write_all(&mut std::io::stdout(), &page.body)?;
+        }
+        Command::Download { url, dir } => {
+            let path = browser.download(&url, dir)?;
+            tracing::info!("Downloaded {} to {}", url, path.display());
+        }
+        Command::Bookmarks {
+            command: BookmarksCommand::Export { file },
+        } => {
+            let count = browser.export_bookmarks(&file)?;
+            tracing::info!("Exported {} bookmarks to {}", count, file.display());
+        }
+        Command::DefaultBrowser { .. }
+        | Command::Screenshot { .. }
+        | Command::ExportDiagnostics { .. }
+        | Command::Backup { .. } => {
+            unreachable!("handled above")
+        }
+    }
+    Ok(())
+}
+
+fn run_screenshot_command(
+    url: &str,
+    out: &Path,
+    full_page: bool,
+    profile: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Only a window can render pages, so the running browser takes the shot
+    let mut single_instance = SingleInstance::new(None)?;
+    if !single_instance.try_acquire()? {
+        single_instance.forward_capture(url, out, full_page)?;
+        tracing::info!("The running browser will save the screenshot of {} to {}", url, out.display());
+        return Ok(());
+    }
+    drop(single_instance);
+
+    HeadlessBrowser::new(profile)?.screenshot(url, out)?;
+    tracing::info!("Saved screenshot of {} to {}", url, out.display());
+    Ok(())
+}
+
+fn run_export_diagnostics_command(out: Option<PathBuf>, profile: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = match profile {
+        Some(profile) => profile,
+        None => ConfigManager::new()?.config_dir().clone(),
+    };
+    let out = out.unwrap_or_else(|| PathBuf::from(diagnostics::bundle_file_name(chrono::Utc::now())));
+    let bundle = diagnostics::export_diagnostics(&LoggingConfig::default().log_dir, &config_dir, &out)?;
+    tracing::info!("Saved {} files to {}", bundle.files.len(), bundle.path.display());
+    Ok(())
+}
+
+fn run_backup_command(command: BackupCommand, profile: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    // Databases are copied as files, so the browser must not be using them
+    let mut single_instance = SingleInstance::new(None)?;
+    if !single_instance.try_acquire()? {
+        return Err("Close WebX before backing up or restoring the profile".into());
+    }
+
+    let locations = ProfileLocations::new(profile)?;
+    match command {
+        BackupCommand::Create { file } => {
+            let passphrase = read_passphrase(true)?;
+            let manifest = ProfileBackup::create_from(&locations, &ProfileSection::ALL, &file, &passphrase)?;
+            tracing::info!("Backed up {} files to {}", manifest.files.len(), file.display());
+        }
+        BackupCommand::Restore { file, only } => {
+            let mut options = ProfileRestoreOptions::default();
+            if !only.is_empty() {
+                let sections = only
+                    .iter()
+                    .map(|name| ProfileSection::parse(name.trim()).ok_or_else(|| format!("Unknown backup part {}", name)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                options = ProfileRestoreOptions::only(&sections);
+            }
+            let backup = ProfileBackup::open(&file, &read_passphrase(false)?)?;
+            let restored = backup.restore(&locations, &options)?;
+            let names: Vec<&str> = restored.iter().map(|section| section.as_str()).collect();
+            tracing::info!("Restored {} from {}", names.join(", "), file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Passphrase from WEBX_BACKUP_PASSPHRASE, or asked for on the terminal
+fn read_passphrase(confirm: bool) -> Result<String, Box<dyn std::error::Error>> {
+    if let Ok(passphrase) = std::env::var("WEBX_BACKUP_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let ask = |prompt: &str| -> std::io::Result<String> {
+        eprint!("{}", prompt);
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+    let passphrase = ask("Backup passphrase: ")?;
+    if confirm && ask("Repeat the passphrase: ")? != passphrase {
+        return Err("The passphrases do not match".into());
+    }
+    Ok(passphrase)
+}
+
+fn run_default_browser_command(command: DefaultBrowserCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let manager = DefaultBrowserManager::default();
+    match command {
+        DefaultBrowserCommand::Register => {
+            manager.register()?;
+            tracing::info!("Registered WebX as the default browser");
+        }
+        DefaultBrowserCommand::Unregister => {
+            manager.unregister()?;
+            tracing::info!("Removed WebX's default browser registration");
+        }
+        DefaultBrowserCommand::Status => {
+            let status = match manager.status() {
+                RegistrationStatus::Default => "WebX is the default browser",
+                RegistrationStatus::Registered => "WebX is registered but not the default browser",
+                RegistrationStatus::NotRegistered => "WebX is not registered",
+            };
+            println!("{}", status);
+        }
+    }
+    Ok(())
+}
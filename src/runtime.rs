@@ -0,0 +1,125 @@
+// Shared async runtime
+//
+// The window event loop is synchronous, so background work runs on one
+// runtime owned by `BrowserApp`. Feature APIs follow these rules:
+//
+// - `async fn` methods do network or disk IO and must be awaited on the
+//   browser runtime (from a task, or via `BrowserRuntime::block_on` from the
+//   UI thread). They never block a worker thread: file IO goes through
+//   `tokio::fs` or `spawn_blocking`.
+// - Plain methods only touch in-memory state or small config files and are
+//   safe to call from any thread, including the UI thread.
+// - `start_*` methods spawn background tasks and must be called inside the
+//   runtime, i.e. from a task or while a `BrowserRuntime::enter` guard is
+//   alive. Their `stop_*` counterparts can be called from anywhere.
+use crate::error::WebxError;
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::{EnterGuard, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+/// How long shutdown waits for background tasks to finish
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The browser's tokio runtime
+pub struct BrowserRuntime {
+    runtime: Option<Runtime>,
+}
+
+impl BrowserRuntime {
+    /// Create new runtime, defaulting to one worker per CPU core
+    pub fn new(worker_threads: Option<usize>) -> Result<Self, WebxError> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all().thread_name("webx-worker");
+        if let Some(worker_threads) = worker_threads {
+            builder.worker_threads(worker_threads.max(1));
+        }
+
+        Ok(Self {
+            runtime: Some(builder.build()?),
+        })
+    }
+
+    /// Handle for spawning onto the runtime from other threads
+    pub fn handle(&self) -> &Handle {
+        self.runtime().handle()
+    }
+
+    /// Make the runtime current on this thread while the guard is alive, so
+    /// `start_*` methods can spawn their tasks
+    pub fn enter(&self) -> EnterGuard<'_> {
+        self.runtime().enter()
+    }
+
+    /// Run a future to completion, blocking the calling thread. Must not be
+    /// called from inside a runtime task.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime().block_on(future)
+    }
+
+    /// Spawn a task onto the runtime
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.runtime().spawn(future)
+    }
+
+    /// Run blocking work on the runtime's blocking thread pool
+    pub fn spawn_blocking<F, R>(&self, f: F) -> JoinHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.runtime().spawn_blocking(f)
+    }
+
+    /// Stop the runtime, giving running tasks a moment to finish
+    pub fn shutdown(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+
+    /// Handle of the runtime the caller is running in, with an error that
+    /// says what went wrong instead of tokio's panic
+    pub fn current() -> Result<Handle, WebxError> {
+        Handle::try_current()
+            .map_err(|_| WebxError::Invalid("Must be called inside the browser runtime".to_string()))
+    }
+
+    // Private helper methods
+
+    fn runtime(&self) -> &Runtime {
+        self.runtime.as_ref().expect("Browser runtime was shut down")
+    }
+}
+
+impl Drop for BrowserRuntime {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_runs_tasks_from_sync_code() {
+        let mut runtime = BrowserRuntime::new(Some(2)).unwrap();
+        assert!(BrowserRuntime::current().is_err());
+
+        let task = runtime.spawn(async { tokio::fs::read_dir(".").await.is_ok() });
+        let blocking = runtime.spawn_blocking(|| 21 * 2);
+        assert!(runtime.block_on(task).unwrap());
+        assert_eq!(runtime.block_on(blocking).unwrap(), 42);
+
+        {
+            let _guard = runtime.enter();
+            assert!(BrowserRuntime::current().is_ok());
+        }
+        runtime.shutdown();
+    }
+}
@@ -0,0 +1,228 @@
+// Browser UI IPC Handler
+use crate::error::WebxError;
+use crate::features::feeds;
+use crate::features::productivity::clipper;
+use crate::features::productivity::gallery::GALLERY_MIN_SIZE;
+use crate::features::productivity::reading_list;
+use crate::features::productivity::translate::SitePreference;
+use crate::features::tabs;
+use crate::config::SETTINGS_PAGE_URL;
+use crate::ipc::{
+    ErrorPageAction, FeedsAction, IpcContext, IpcHandler, IpcMessage, NotesAction, ReadAloudAction, ReadingListAction,
+    SettingsAction, SplitAction, StaleTabsAction, StatsAction, TranslateAction,
+};
+use crate::ui::{
+    DownloadRequest, FeedsRequest, NotesRequest, ReadAloudRequest, ReadingListRequest, SettingsRequest, SplitRequest,
+    StaleTabsRequest, StatsRequest, UiEvent, WebAuthnRequest, SPLIT_RESIZE_STEP,
+};
+use crate::utils::LockExt;
+use std::sync::Mutex;
+use tao::event_loop::EventLoopProxy;
+
+/// Turns page messages about tabs, windows and the built-in features into
+/// events for the event loop
+pub struct UiIpcHandler {
+    /// Locked because the proxy is not Sync on every platform
+    proxy: Mutex<EventLoopProxy<UiEvent>>,
+}
+
+impl UiIpcHandler {
+    pub fn new(proxy: EventLoopProxy<UiEvent>) -> Self {
+        Self { proxy: Mutex::new(proxy) }
+    }
+}
+
+impl IpcHandler for UiIpcHandler {
+    fn message_types(&self) -> &'static [&'static str] {
+        &[
+            "pageload", "titlechange", "beforeunload", "traverse", "newwindow", "movetabtonewwindow", "switchtab",
+            "movetab", "tabsearch", "grouptabs", "reviewstaletabs", "staletabs", "newidentity", "split", "mutetab",
+            "mutebackgroundtabs", "pictureinpicture", "stoprecording", "media", "readaloud", "screenshot", "pagetext", "translate",
+            "showoriginal", "feeds", "subscribefeed", "showfeeds", "clippage", "clip", "notes", "shownotes",
+            "savepage", "savelater", "readinglist", "showreadinglist", "droplinks", "savedata",
+            "saveimages", "mediafound", "downloadmedia", "push", "webauthn-create", "webauthn-get", "errorpage", "stats", "settings", "showsettings",
+            "togglecaret", "openinnewtab", "zoom",
+        ]
+    }
+
+    fn handle(&self, context: &IpcContext, message: IpcMessage) -> Result<serde_json::Value, WebxError> {
+        let event = match context.tab_id {
+            Some(tab_id) => tab_event(tab_id, message),
+            None => Err(Box::new(message)),
+        };
+        let event = match event {
+            Ok(event) => Some(event),
+            Err(message) => window_event(context.window_id, *message),
+        }
+        .ok_or("The page's tab is gone")?;
+        self.proxy
+            .lock_or_recover()
+            .send_event(event)
+            .map_err(|_| WebxError::Invalid("The browser is shutting down".to_string()))?;
+        Ok(serde_json::Value::Null)
+    }
+}
+
+// Private helper functions
+
+/// Event for a message about the sending tab, or the message back if it is
+/// not about a tab; boxed, as messages are much larger than events
+fn tab_event(tab_id: usize, message: IpcMessage) -> Result<UiEvent, Box<IpcMessage>> {
+    let event = match message {
+        IpcMessage::PageLoad { url, title } => UiEvent::PageLoaded { tab_id, url, title },
+        IpcMessage::TitleChange { title } => UiEvent::TitleChanged(tab_id, title),
+        IpcMessage::BeforeUnload { url, scroll_x, scroll_y } => UiEvent::LeavingPage {
+            tab_id,
+            url,
+            scroll: (scroll_x, scroll_y),
+        },
+        IpcMessage::Traverse { offset } => UiEvent::Traverse(tab_id, offset),
+        IpcMessage::MoveTabToNewWindow => UiEvent::MoveTabToNewWindow(tab_id),
+        IpcMessage::TabSearch { query, cycle } => UiEvent::TabSearch { tab_id, query, cycle },
+        IpcMessage::StaleTabs { action, tabs } => UiEvent::StaleTabs(
+            tab_id,
+            match action {
+                StaleTabsAction::Close => StaleTabsRequest::Close(tabs),
+                StaleTabsAction::Keep => StaleTabsRequest::Keep(tabs),
+            },
+        ),
+        IpcMessage::MuteTab => UiEvent::ToggleMute(tab_id),
+        IpcMessage::Zoom { action } => UiEvent::Zoom(tab_id, action),
+        IpcMessage::PictureInPicture => UiEvent::TogglePictureInPicture(tab_id),
+        IpcMessage::StopRecording => UiEvent::StopRecording(tab_id),
+        IpcMessage::Media(report) => UiEvent::MediaReport(tab_id, report),
+        IpcMessage::MediaFound { resources } => UiEvent::MediaFound(tab_id, resources),
+        IpcMessage::DownloadMedia { url } => UiEvent::Download(DownloadRequest::Media { tab_id, url }),
+        IpcMessage::ReadAloud(action) => UiEvent::ReadAloud(
+            tab_id,
+            match action {
+                ReadAloudAction::Toggle { html, url } => ReadAloudRequest::Toggle { html, url },
+                ReadAloudAction::Next => ReadAloudRequest::Next,
+                ReadAloudAction::Previous => ReadAloudRequest::Previous,
+                ReadAloudAction::Stop => ReadAloudRequest::Stop,
+                ReadAloudAction::Faster => ReadAloudRequest::Faster,
+                ReadAloudAction::Slower => ReadAloudRequest::Slower,
+            },
+        ),
+        IpcMessage::Screenshot { full_page } => UiEvent::Screenshot { tab_id, full_page },
+        IpcMessage::PageText(page) => UiEvent::PageText(tab_id, page),
+        IpcMessage::Translate { action } => UiEvent::TranslatePage {
+            tab_id,
+            remember: match action {
+                Some(TranslateAction::Always) => Some(SitePreference::AlwaysTranslate),
+                Some(TranslateAction::Never) => Some(SitePreference::NeverTranslate),
+                Some(TranslateAction::Once) | None => None,
+            },
+        },
+        IpcMessage::ShowOriginal => UiEvent::ShowOriginal(tab_id),
+        IpcMessage::Feeds(action) => UiEvent::Feeds(
+            tab_id,
+            match action {
+                FeedsAction::Detected { url, head } => FeedsRequest::Detected { url, head },
+                FeedsAction::MarkRead { item } => FeedsRequest::MarkRead(item),
+                FeedsAction::MarkAllRead { feed } => FeedsRequest::MarkAllRead(feed),
+                FeedsAction::Unsubscribe { feed } => FeedsRequest::Unsubscribe(feed),
+                FeedsAction::Refresh => FeedsRequest::Refresh,
+            },
+        ),
+        IpcMessage::SubscribeFeed => UiEvent::Feeds(tab_id, FeedsRequest::Subscribe),
+        IpcMessage::ClipPage => UiEvent::EvalInTab {
+            tab_id,
+            script: clipper::CLIP_SCRIPT.to_string(),
+        },
+        IpcMessage::Clip { url, title, selection, html, tags } => UiEvent::Notes(
+            tab_id,
+            NotesRequest::Clip { url, title, selection, html, tags },
+        ),
+        IpcMessage::Notes(action) => UiEvent::Notes(
+            tab_id,
+            match action {
+                NotesAction::Delete { note } => NotesRequest::Delete(note),
+                NotesAction::Export { note } => NotesRequest::Export(Some(note)),
+                NotesAction::ExportAll => NotesRequest::Export(None),
+            },
+        ),
+        IpcMessage::SavePage => UiEvent::EvalInTab {
+            tab_id,
+            script: reading_list::SAVE_FOR_LATER_SCRIPT.to_string(),
+        },
+        IpcMessage::SaveLater { url, title, html } => UiEvent::ReadingList(tab_id, ReadingListRequest::Save { url, title, html }),
+        IpcMessage::ReadingList(action) => UiEvent::ReadingList(
+            tab_id,
+            match action {
+                ReadingListAction::MarkRead { entry } => ReadingListRequest::MarkRead(entry, true),
+                ReadingListAction::MarkUnread { entry } => ReadingListRequest::MarkRead(entry, false),
+                ReadingListAction::Remove { entry } => ReadingListRequest::Remove(entry),
+            },
+        ),
+        IpcMessage::Push { token, scope, action } => UiEvent::Push { tab_id, token, scope, action },
+        IpcMessage::WebAuthnCreate { token, request } => UiEvent::WebAuthn {
+            tab_id,
+            token,
+            request: WebAuthnRequest::Create(request),
+        },
+        IpcMessage::WebAuthnGet { token, request } => UiEvent::WebAuthn {
+            tab_id,
+            token,
+            request: WebAuthnRequest::Get(request),
+        },
+        IpcMessage::ErrorPage(ErrorPageAction::Cached { url }) => UiEvent::OpenCachedCopy(tab_id, url),
+        IpcMessage::OpenInNewTab { url } => UiEvent::OpenFromPage { tab_id, url },
+        IpcMessage::Stats { action } => UiEvent::Stats(
+            tab_id,
+            match action {
+                StatsAction::Enable => StatsRequest::Enable,
+                StatsAction::Disable => StatsRequest::Disable,
+                StatsAction::Clear => StatsRequest::Clear,
+            },
+        ),
+        IpcMessage::Settings(action) => UiEvent::Settings(
+            tab_id,
+            match action {
+                SettingsAction::Set { key, value } => SettingsRequest::Set { key, value },
+                SettingsAction::Reset { key } => SettingsRequest::Reset(key),
+            },
+        ),
+        other => return Err(Box::new(other)),
+    };
+    Ok(event)
+}
+
+/// Event for a message that needs no tab
+fn window_event(window_id: usize, message: IpcMessage) -> Option<UiEvent> {
+    let event = match message {
+        IpcMessage::NewWindow => UiEvent::NewWindow,
+        IpcMessage::SwitchTab { tab_id } => UiEvent::SwitchTab(tab_id),
+        IpcMessage::MoveTab { tab_id, index } => UiEvent::MoveTab { tab_id, window_id, index },
+        IpcMessage::GroupTabs => UiEvent::GroupTabs,
+        IpcMessage::ReviewStaleTabs => UiEvent::OpenUrls(vec![tabs::STALE_TABS_PAGE_URL.to_string()]),
+        IpcMessage::NewIdentity => UiEvent::NewIdentity,
+        IpcMessage::Split { action } => UiEvent::Split(
+            window_id,
+            match action {
+                SplitAction::Toggle => SplitRequest::Toggle,
+                SplitAction::Rotate => SplitRequest::Rotate,
+                SplitAction::Grow => SplitRequest::Resize(SPLIT_RESIZE_STEP),
+                SplitAction::Shrink => SplitRequest::Resize(-SPLIT_RESIZE_STEP),
+            },
+        ),
+        IpcMessage::MuteBackgroundTabs => UiEvent::MuteBackgroundTabs,
+        IpcMessage::ShowFeeds => UiEvent::OpenUrls(vec![feeds::FEEDS_PAGE_URL.to_string()]),
+        IpcMessage::ShowNotes => UiEvent::OpenUrls(vec![clipper::NOTES_PAGE_URL.to_string()]),
+        IpcMessage::ShowReadingList => UiEvent::OpenUrls(vec![reading_list::READING_LIST_PAGE_URL.to_string()]),
+        IpcMessage::ShowSettings => UiEvent::OpenUrls(vec![SETTINGS_PAGE_URL.to_string()]),
+        IpcMessage::ToggleCaretBrowsing => UiEvent::ToggleCaretBrowsing,
+        IpcMessage::DropLinks { text } => UiEvent::Download(DownloadRequest::Dropped(text)),
+        IpcMessage::SaveData { url, filename, data } => UiEvent::Download(DownloadRequest::PageData {
+            page_url: url,
+            filename,
+            data_url: data,
+        }),
+        IpcMessage::SaveImages(selection) => UiEvent::Download(DownloadRequest::Batch {
+            folder: selection.folder_name(),
+            urls: selection.urls(GALLERY_MIN_SIZE),
+        }),
+        _ => return None,
+    };
+    Some(event)
+}
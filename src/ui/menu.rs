@@ -1,5 +1,6 @@
 // Menu system placeholder - Tao doesn't have built-in menu support
 // This is a simplified menu system that can be extended later
+use crate::i18n::tr;
 
 /// Simple menu item representation
 #[derive(Debug, Clone)]
@@ -72,93 +73,139 @@ pub fn build_menu() -> MenuBar {
     let mut menu_bar = MenuBar::new();
     
     // File menu
-    let mut file_menu = Menu::new("File");
-    file_menu.add_item(MenuItem::new("New Tab").with_accelerator("Ctrl+T").with_action("new_tab"));
-    file_menu.add_item(MenuItem::new("New Window").with_accelerator("Ctrl+N").with_action("new_window"));
-    file_menu.add_item(MenuItem::new("Close Tab").with_accelerator("Ctrl+W").with_action("close_tab"));
-    file_menu.add_item(MenuItem::new("Close Window").with_accelerator("Ctrl+Shift+W").with_action("close_window"));
-    file_menu.add_item(MenuItem::new("Exit").with_accelerator("Ctrl+Q").with_action("exit"));
+    let mut file_menu = Menu::new(&tr("menu-file"));
+    file_menu.add_item(item("new_tab").with_accelerator("Ctrl+T"));
+    file_menu.add_item(item("new_window").with_accelerator("Ctrl+N"));
+    file_menu.add_item(item("move_tab_to_new_window").with_accelerator("Ctrl+Shift+M"));
+    file_menu.add_item(item("search_tabs").with_accelerator("Ctrl+Shift+A"));
+    file_menu.add_item(item("group_tabs").with_accelerator("Ctrl+Shift+G"));
+    file_menu.add_item(item("review_stale_tabs").with_accelerator("Ctrl+Shift+J"));
+    file_menu.add_item(item("new_identity").with_accelerator("Ctrl+Shift+N"));
+    file_menu.add_item(item("close_tab").with_accelerator("Ctrl+W"));
+    file_menu.add_item(item("close_window").with_accelerator("Ctrl+Shift+W"));
+    file_menu.add_item(item("exit").with_accelerator("Ctrl+Q"));
     menu_bar.add_menu(file_menu);
 
     // Edit menu
-    let mut edit_menu = Menu::new("Edit");
-    edit_menu.add_item(MenuItem::new("Undo").with_accelerator("Ctrl+Z").with_action("undo"));
-    edit_menu.add_item(MenuItem::new("Redo").with_accelerator("Ctrl+Y").with_action("redo"));
-    edit_menu.add_item(MenuItem::new("Cut").with_accelerator("Ctrl+X").with_action("cut"));
-    edit_menu.add_item(MenuItem::new("Copy").with_accelerator("Ctrl+C").with_action("copy"));
-    edit_menu.add_item(MenuItem::new("Paste").with_accelerator("Ctrl+V").with_action("paste"));
-    edit_menu.add_item(MenuItem::new("Select All").with_accelerator("Ctrl+A").with_action("select_all"));
+    let mut edit_menu = Menu::new(&tr("menu-edit"));
+    edit_menu.add_item(item("undo").with_accelerator("Ctrl+Z"));
+    edit_menu.add_item(item("redo").with_accelerator("Ctrl+Y"));
+    edit_menu.add_item(item("cut").with_accelerator("Ctrl+X"));
+    edit_menu.add_item(item("copy").with_accelerator("Ctrl+C"));
+    edit_menu.add_item(item("paste").with_accelerator("Ctrl+V"));
+    edit_menu.add_item(item("select_all").with_accelerator("Ctrl+A"));
     menu_bar.add_menu(edit_menu);
 
     // View menu
-    let mut view_menu = Menu::new("View");
-    view_menu.add_item(MenuItem::new("Reload").with_accelerator("Ctrl+R").with_action("reload"));
-    view_menu.add_item(MenuItem::new("Force Reload").with_accelerator("Ctrl+Shift+R").with_action("force_reload"));
-    view_menu.add_item(MenuItem::new("Zoom In").with_accelerator("Ctrl+Plus").with_action("zoom_in"));
-    view_menu.add_item(MenuItem::new("Zoom Out").with_accelerator("Ctrl+Minus").with_action("zoom_out"));
-    view_menu.add_item(MenuItem::new("Reset Zoom").with_accelerator("Ctrl+0").with_action("reset_zoom"));
-    view_menu.add_item(MenuItem::new("Toggle Developer Tools").with_accelerator("F12").with_action("toggle_devtools"));
+    let mut view_menu = Menu::new(&tr("menu-view"));
+    view_menu.add_item(item("reload").with_accelerator("Ctrl+R"));
+    view_menu.add_item(item("force_reload").with_accelerator("Ctrl+Shift+R"));
+    view_menu.add_item(item("zoom_in").with_accelerator("Ctrl+Plus"));
+    view_menu.add_item(item("zoom_out").with_accelerator("Ctrl+Minus"));
+    view_menu.add_item(item("reset_zoom").with_accelerator("Ctrl+0"));
+    view_menu.add_item(item("zoom_text_only").with_accelerator("Ctrl+Shift+0"));
+    view_menu.add_item(item("mute_tab").with_accelerator("Ctrl+M"));
+    view_menu.add_item(item("mute_background_tabs"));
+    view_menu.add_item(item("picture_in_picture"));
+    view_menu.add_item(item("stop_recording"));
+    view_menu.add_item(item("split_view").with_accelerator("Ctrl+\\"));
+    view_menu.add_item(item("rotate_split").with_accelerator("Ctrl+Shift+\\"));
+    view_menu.add_item(item("translate_page"));
+    view_menu.add_item(item("show_original"));
+    view_menu.add_item(item("read_aloud").with_accelerator("Ctrl+Shift+U"));
+    view_menu.add_item(item("stop_read_aloud"));
+    view_menu.add_item(item("screenshot").with_accelerator("Ctrl+Shift+S"));
+    view_menu.add_item(item("screenshot_full_page").with_accelerator("Ctrl+Shift+Alt+S"));
+    view_menu.add_item(item("caret_browsing").with_accelerator("F7"));
+    view_menu.add_item(item("link_hints").with_accelerator("F"));
+    view_menu.add_item(item("toggle_devtools").with_accelerator("F12"));
     menu_bar.add_menu(view_menu);
 
     // History menu
-    let mut history_menu = Menu::new("History");
-    history_menu.add_item(MenuItem::new("Back").with_accelerator("Alt+Left").with_action("go_back"));
-    history_menu.add_item(MenuItem::new("Forward").with_accelerator("Alt+Right").with_action("go_forward"));
-    history_menu.add_item(MenuItem::new("Home").with_accelerator("Alt+Home").with_action("go_home"));
-    history_menu.add_item(MenuItem::new("Show History").with_accelerator("Ctrl+H").with_action("show_history"));
-    history_menu.add_item(MenuItem::new("Clear History").with_action("clear_history"));
+    let mut history_menu = Menu::new(&tr("menu-history"));
+    history_menu.add_item(item("go_back").with_accelerator("Alt+Left"));
+    history_menu.add_item(item("go_forward").with_accelerator("Alt+Right"));
+    history_menu.add_item(item("go_home").with_accelerator("Alt+Home"));
+    history_menu.add_item(item("show_history").with_accelerator("Ctrl+H"));
+    history_menu.add_item(item("clear_history"));
     menu_bar.add_menu(history_menu);
 
     // Bookmarks menu
-    let mut bookmarks_menu = Menu::new("Bookmarks");
-    bookmarks_menu.add_item(MenuItem::new("Bookmark This Page").with_accelerator("Ctrl+D").with_action("bookmark_page"));
-    bookmarks_menu.add_item(MenuItem::new("Show Bookmarks").with_accelerator("Ctrl+Shift+B").with_action("show_bookmarks"));
-    bookmarks_menu.add_item(MenuItem::new("Bookmark Manager").with_action("bookmark_manager"));
+    let mut bookmarks_menu = Menu::new(&tr("menu-bookmarks"));
+    bookmarks_menu.add_item(item("bookmark_page").with_accelerator("Ctrl+D"));
+    bookmarks_menu.add_item(item("show_bookmarks").with_accelerator("Ctrl+Shift+B"));
+    bookmarks_menu.add_item(item("bookmark_manager"));
+    bookmarks_menu.add_item(item("save_for_later").with_accelerator("Ctrl+Shift+D"));
+    bookmarks_menu.add_item(item("show_reading_list").with_accelerator("Ctrl+Shift+H"));
+    bookmarks_menu.add_item(item("subscribe_feed").with_accelerator("Ctrl+Shift+F"));
+    bookmarks_menu.add_item(item("show_feeds").with_accelerator("Ctrl+Shift+E"));
+    bookmarks_menu.add_item(item("clip_to_notes").with_accelerator("Ctrl+Shift+K"));
+    bookmarks_menu.add_item(item("show_notes").with_accelerator("Ctrl+Shift+L"));
     menu_bar.add_menu(bookmarks_menu);
 
     // Help menu
-    let mut help_menu = Menu::new("Help");
-    help_menu.add_item(MenuItem::new("About WebX").with_action("about"));
-    help_menu.add_item(MenuItem::new("Check for Updates").with_action("check_updates"));
-    help_menu.add_item(MenuItem::new("Report Issue").with_action("report_issue"));
-    help_menu.add_item(MenuItem::new("Documentation").with_action("documentation"));
+    let mut help_menu = Menu::new(&tr("menu-help"));
+    help_menu.add_item(item("about"));
+    help_menu.add_item(item("check_updates"));
+    help_menu.add_item(item("report_issue"));
+    help_menu.add_item(item("export_diagnostics"));
+    help_menu.add_item(item("show_stats"));
+    help_menu.add_item(item("documentation"));
     menu_bar.add_menu(help_menu);
 
     menu_bar
 }
 
+// Private helper functions
+
+/// Item for a menu action, labelled by the `menu-<action>` message
+fn item(action: &str) -> MenuItem {
+    MenuItem::new(&tr(&format!("menu-{}", action.replace('_', "-")))).with_action(action)
+}
+
 /// Process menu action
 pub fn handle_menu_action(action: &str) {
     match action {
-        "new_tab" => println!("New tab requested"),
-        "new_window" => println!("New window requested"),
-        "close_tab" => println!("Close tab requested"),
-        "close_window" => println!("Close window requested"),
-        "exit" => println!("Exit requested"),
-        "undo" => println!("Undo requested"),
-        "redo" => println!("Redo requested"),
-        "cut" => println!("Cut requested"),
-        "copy" => println!("Copy requested"),
-        "paste" => println!("Paste requested"),
-        "select_all" => println!("Select all requested"),
-        "reload" => println!("Reload requested"),
-        "force_reload" => println!("Force reload requested"),
-        "zoom_in" => println!("Zoom in requested"),
-        "zoom_out" => println!("Zoom out requested"),
-        "reset_zoom" => println!("Reset zoom requested"),
-        "toggle_devtools" => println!("Toggle devtools requested"),
-        "go_back" => println!("Go back requested"),
-        "go_forward" => println!("Go forward requested"),
-        "go_home" => println!("Go home requested"),
-        "show_history" => println!("Show history requested"),
-        "clear_history" => println!("Clear history requested"),
-        "bookmark_page" => println!("Bookmark page requested"),
-        "show_bookmarks" => println!("Show bookmarks requested"),
-        "bookmark_manager" => println!("Bookmark manager requested"),
-        "about" => println!("About requested"),
-        "check_updates" => println!("Check updates requested"),
-        "report_issue" => println!("Report issue requested"),
-        "documentation" => println!("Documentation requested"),
-        _ => println!("Unknown menu action: {}", action),
+        "new_tab" => tracing::info!("New tab requested"),
+        "new_window" => tracing::info!("New window requested"),
+        "move_tab_to_new_window" => tracing::info!("Move tab to new window requested"),
+        "new_identity" => tracing::info!("New identity requested"),
+        "close_tab" => tracing::info!("Close tab requested"),
+        "close_window" => tracing::info!("Close window requested"),
+        "exit" => tracing::info!("Exit requested"),
+        "undo" => tracing::info!("Undo requested"),
+        "redo" => tracing::info!("Redo requested"),
+        "cut" => tracing::info!("Cut requested"),
+        "copy" => tracing::info!("Copy requested"),
+        "paste" => tracing::info!("Paste requested"),
+        "select_all" => tracing::info!("Select all requested"),
+        "reload" => tracing::info!("Reload requested"),
+        "force_reload" => tracing::info!("Force reload requested"),
+        "zoom_in" => tracing::info!("Zoom in requested"),
+        "zoom_out" => tracing::info!("Zoom out requested"),
+        "reset_zoom" => tracing::info!("Reset zoom requested"),
+        "zoom_text_only" => tracing::info!("Text-only zoom toggle requested"),
+        "mute_tab" => tracing::info!("Mute tab requested"),
+        "mute_background_tabs" => tracing::info!("Mute background tabs requested"),
+        "picture_in_picture" => tracing::info!("Picture in picture requested"),
+        "stop_recording" => tracing::info!("Stop camera and microphone requested"),
+        "caret_browsing" => tracing::info!("Caret browsing toggle requested"),
+        "link_hints" => tracing::info!("Link hints requested"),
+        "toggle_devtools" => tracing::info!("Toggle devtools requested"),
+        "go_back" => tracing::info!("Go back requested"),
+        "go_forward" => tracing::info!("Go forward requested"),
+        "go_home" => tracing::info!("Go home requested"),
+        "show_history" => tracing::info!("Show history requested"),
+        "clear_history" => tracing::info!("Clear history requested"),
+        "bookmark_page" => tracing::info!("Bookmark page requested"),
+        "show_bookmarks" => tracing::info!("Show bookmarks requested"),
+        "bookmark_manager" => tracing::info!("Bookmark manager requested"),
+        "about" => tracing::info!("About requested"),
+        "check_updates" => tracing::info!("Check updates requested"),
+        "report_issue" => tracing::info!("Report issue requested"),
+        "export_diagnostics" => tracing::info!("Export diagnostics requested"),
+        "show_stats" => tracing::info!("Usage statistics requested"),
+        "documentation" => tracing::info!("Documentation requested"),
+        _ => tracing::warn!("Unknown menu action: {}", action),
     }
 }
@@ -1,18 +1,350 @@
 // WebX Browser UI Module
-use crate::core::BrowserState;
-use crate::config::ConfigManager;
-use crate::features::{TabManager, DownloadManager, PrivacyProtection};
-use crate::features::ui::themes::ThemeManager;
+use crate::core::{BrowserState, WindowGeometry};
+use crate::config::{parse_data_saver, BrowserSettingsProvider, ConfigManager, PolicySet, SettingValue, SettingsEvent, SettingsProvider, SettingsRegistry};
+use crate::error::{ErrorReporter, WebxError};
+use crate::i18n;
+use crate::features::{TabManager, DataUrl, DownloadManager, DownloadPolicy, PrivacyProtection, RetentionEngine, RetentionTargets, RetentionTrigger, SessionRestore};
+use crate::features::tabs::{switcher_script, ContainerManager, TabEvent, STALE_TABS_PAGE_URL};
+use crate::features::productivity::screenshot::{save_screenshot, CaptureEvent, CaptureService};
+use crate::features::productivity::translate::{PageText, SitePreference, TranslateDecision, Translator, COLLECT_TEXT_SCRIPT, RESTORE_TEXT_SCRIPT};
+use crate::features::media::{AutoplayBlocker, MediaCommand, MediaController, MediaEvent, MediaReport, PlaybackState, ReadAloudEvent, ReadAloudService, ReadAloudState, TabAudioIndicator, READ_PAGE_ALOUD_SCRIPT};
+use crate::features::feeds::{detect_feeds, FeedLink, FeedManager};
+use crate::features::productivity::clipper::{self, Notebook};
+use crate::features::productivity::reading_list::ReadingList;
+use crate::features::ui::reader::ReadingMode;
+use crate::features::ui::context_menu::{ContextMenu, ContextMenuEvent, MenuAction, INSPECT_ITEM};
+use crate::features::productivity::printing::{PrintManager, PRINT_SELECTION_SCRIPT};
+use crate::features::productivity::gallery::{gallery_script, GALLERY_MIN_SIZE};
+use crate::features::productivity::keyboard_nav::{KeyboardNav, KEYBOARD_NAV_REFRESH_SCRIPT};
+use crate::features::productivity::share::{share_menu_script, ShareService};
+use crate::features::ui::error_pages::{self, ErrorPageOptions, NavigationError};
+use crate::features::downloads::{download_urls, DownloadStore, MediaSniffer, SniffedResource};
+use crate::features::caching::{HTTPCache, OfflineStorage, SiteStorageManager, SpeculativeLoader};
+use crate::features::history_manager::{FormHistory, VisitTransition};
+use crate::features::ui::themes::{ThemeManager, ACCESSIBILITY_REFRESH_SCRIPT};
+use crate::features::ui::zoom::{SiteZoom, ZoomAction};
+use crate::features::system::metrics::{Metrics, PAGE_LOADS, PAGE_LOAD_TIME, STARTUP_TIME};
+use crate::features::system::backup::{BackupSources, WebDavBackup};
+use crate::features::system::protocol_handlers::{
+    always_label, feed_scheme_url, BrowserFeature, Handoff, HandlerTarget, ProtocolHandlers,
+};
+use crate::features::system::proxy::{ProxyManager, TorController};
+use crate::features::system::user_agent::UserAgentSwitcher;
+use crate::features::system::conditions::ConditionsMonitor;
+use crate::features::system::scheduler::{TaskPriority, TaskScheduler, TaskSpec};
+use crate::features::system::instance::{CaptureRequest, InstanceEvent, SingleInstance};
+use crate::features::system::notifications::{push_settle_script, NotificationManager, PushManager};
+use crate::features::system::tray::TrayAction;
+use crate::features::security::permissions::recording::{stop_script, RecordingEvent, RecordingIndicator, RecordingTracker};
+use crate::features::security::permissions::PermissionManager;
+use crate::features::security::clipboard::ClipboardBroker;
+use crate::features::security::privacy::{
+    BounceProtection, FingerprintShield, IdentityReport, IdentityTargets, NewIdentity, PrivacyReport,
+};
+use crate::features::security::password_manager::{PasswordManager, VaultUnlock};
+use crate::features::security::webauthn::{
+    webauthn_settle_script, CredentialAssertionRequest, CredentialCreationRequest, PlatformAuthenticator,
+    WebAuthnBridge,
+};
+use crate::features::security::mixed_content::{MixedContentEvent, MixedContentPolicy, SecurityIndicator};
+use crate::features::security::sri::{SriChecker, SriEvent};
+use crate::features::security::safe_browsing::{blocked_page_url, SafeBrowsing, SafeBrowsingEvent};
+use crate::features::security::{CspMonitor, SecretStore};
+use crate::features::ui::FindInPage;
+use crate::ipc::{IpcHandler, IpcRouter, PushAction};
+use crate::runtime::BrowserRuntime;
+use crate::utils::{site_domain, LockExt, StateWatchdog};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tao::{
     event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, ModifiersState},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy, EventLoopWindowTarget},
+    window::WindowId,
 };
 
 pub mod window;
+pub mod prompt;
+mod ipc;
 pub mod menu;
+pub mod tray;
+
+pub use window::{BrowserWindow, WindowServices};
+pub use tray::BrowserTray;
+pub use prompt::{Prompt, PromptAnswer, Prompter};
+use prompt::{PromptReply, PromptWindow};
+use ipc::UiIpcHandler;
+
+/// Events delivered to the window event loop from other threads
+#[derive(Debug, Clone)]
+pub enum UiEvent {
+    /// Open URLs as new tabs and bring the focused window to the front
+    OpenUrls(Vec<String>),
+    /// A tab's page asked to open a URL in a new tab, which the pop-up
+    /// blocker lets through or puts to the user
+    OpenFromPage { tab_id: usize, url: String },
+    /// Open a new window with the home page
+    NewWindow,
+    /// Move a tab, by tab ID, out of its window into a new one
+    MoveTabToNewWindow(usize),
+    /// A tab's page reported its media state
+    MediaReport(usize, MediaReport),
+    /// Mute or unmute a tab, by tab ID
+    ToggleMute(usize),
+    /// Change the zoom of a tab's site, by tab ID
+    Zoom(usize, ZoomAction),
+    /// Mute every tab no window is showing
+    MuteBackgroundTabs,
+    /// Pop a tab's video out, or back in, by tab ID
+    TogglePictureInPicture(usize),
+    /// Media state changed or media must be controlled
+    Media(MediaEvent),
+    /// A tab opened or closed the camera or microphone, or must close them
+    Recording(RecordingEvent),
+    /// Close a tab's camera and microphone, by tab ID
+    StopRecording(usize),
+    /// A tab's page loaded insecure content, or left it behind
+    MixedContent(MixedContentEvent),
+    /// Screenshot a tab, by tab ID, into the downloads directory
+    Screenshot { tab_id: usize, full_page: bool },
+    /// Open a page and screenshot it once loaded, for a later invocation
+    CaptureRequest(CaptureRequest),
+    /// A capture script must run in a tab
+    Capture(CaptureEvent),
+    /// A tab finished loading a page
+    PageLoaded { tab_id: usize, url: String, title: String },
+    /// A tab's page changed its title, by tab ID
+    TitleChanged(usize, String),
+    /// A tab is leaving a page scrolled to a position
+    LeavingPage { tab_id: usize, url: String, scroll: (f64, f64) },
+    /// Go back, negative, or forward through a tab's history, by tab ID
+    Traverse(usize, isize),
+    /// A tab's webview started loading a page, by tab ID
+    LoadStarted(usize),
+    /// A tab's webview finished loading a page, whether or not it loaded
+    LoadFinished { tab_id: usize, url: String },
+    /// A tab's page could not be loaded, by tab ID
+    NavigationFailed(usize, NavigationError),
+    /// Show the copy of a page saved for offline reading in a tab, by tab ID
+    OpenCachedCopy(usize, String),
+    /// A tab's page reported its text for translation
+    PageText(usize, PageText),
+    /// Translate a tab, by tab ID, optionally remembering the choice for its
+    /// site; `NeverTranslate` only remembers
+    TranslatePage { tab_id: usize, remember: Option<SitePreference> },
+    /// Put a tab's original text back, by tab ID
+    ShowOriginal(usize),
+    /// Show the tabs matching a search in a tab's switcher, by tab ID;
+    /// `cycle` preselects the previously used tab
+    TabSearch { tab_id: usize, query: String, cycle: bool },
+    /// Show a tab, by tab ID, and focus its window
+    SwitchTab(usize),
+    /// A tab was dropped at a position, or the end, of a window's strip
+    MoveTab { tab_id: usize, window_id: usize, index: Option<usize> },
+    /// Tabs moved within or between windows
+    Tab(TabEvent),
+    /// Change a window's split view, by window ID
+    Split(usize, SplitRequest),
+    /// Group every window's tabs by domain
+    GroupTabs,
+    /// Bring up the stale tab review if tabs went stale since it last came up
+    CheckStaleTabs,
+    /// Re-apply the accessibility options to the pages on screen
+    RefreshAccessibility,
+    /// Turn caret browsing on or off everywhere
+    ToggleCaretBrowsing,
+    /// Re-apply the keyboard navigation options to the pages on screen
+    RefreshKeyboardNav,
+    /// Stale tab review actions from a tab, by tab ID
+    StaleTabs(usize, StaleTabsRequest),
+    /// Start over with a new identity, once the user confirms in a prompt
+    NewIdentity,
+    /// A new identity dropped the session's data; the pages go next
+    IdentityRenewed(IdentityReport),
+    /// Statistics page actions from a tab, by tab ID
+    Stats(usize, StatsRequest),
+    /// Settings page changes from a tab, by tab ID
+    Settings(usize, SettingsRequest),
+    /// Evaluate a script in a tab, by tab ID, if a window shows it
+    EvalInTab { tab_id: usize, script: String },
+    /// Control reading a tab aloud, by tab ID
+    ReadAloud(usize, ReadAloudRequest),
+    /// Read aloud progress
+    ReadAloudProgress(ReadAloudEvent),
+    /// An item was picked in a tab's context menu
+    ContextMenu(ContextMenuEvent),
+    /// Feed detection and feeds page actions from a tab, by tab ID
+    Feeds(usize, FeedsRequest),
+    /// Clipping and notes page actions from a tab, by tab ID
+    Notes(usize, NotesRequest),
+    /// Saving and reading list page actions from a tab, by tab ID
+    ReadingList(usize, ReadingListRequest),
+    /// Start downloads from a page, a drop or the clipboard
+    Download(DownloadRequest),
+    /// A tab's page loaded audio, video or stream playlists, by tab ID
+    MediaFound(usize, Vec<SniffedResource>),
+    /// A tab navigated to a link another application handles, by tab ID
+    ExternalLink { tab_id: usize, url: String },
+    /// The user let a link leave the browser, and maybe stopped the asking
+    /// for links like it
+    LaunchHandoff { handoff: Handoff, always: bool },
+    /// Put a question to the user in a prompt window of its own
+    Prompt { prompt: Prompt, reply: PromptReply },
+    /// The user answered a prompt window
+    PromptAnswered { window_id: WindowId, answer: PromptAnswer },
+    /// A tab's page asked about its push subscription; answered by
+    /// settling the page's request `token`
+    Push { tab_id: usize, token: u64, scope: String, action: PushAction },
+    /// A tab's page asked for a passkey or security key credential;
+    /// answered by settling the page's request `token`
+    WebAuthn { tab_id: usize, token: u64, request: WebAuthnRequest },
+    /// A tray menu item was clicked
+    Tray(TrayAction),
+    /// Download state changed; update the tray icon
+    RefreshTray,
+}
+
+/// Where a download comes from, other than navigation
+#[derive(Debug, Clone)]
+pub enum DownloadRequest {
+    /// Links and images dropped onto a page, as text or a URI list; the
+    /// user confirms first, as the page reports the drop
+    Dropped(String),
+    /// URLs on the system clipboard, from the browser's own shortcut
+    Clipboard,
+    /// A link or image picked in the context menu
+    Url(String),
+    /// A file a page made itself, such as a blob: link with a `download`
+    /// attribute, read into a data: URL
+    PageData { page_url: String, filename: Option<String>, data_url: String },
+    /// Files to save together into a subfolder, such as a page's images
+    Batch { folder: String, urls: Vec<String> },
+    /// Media a tab's page loaded, by URL, or the page's main video or audio
+    Media { tab_id: usize, url: Option<String> },
+}
+
+/// What the user asked read aloud to do
+#[derive(Debug, Clone)]
+pub enum ReadAloudRequest {
+    /// Start reading the page in reader mode, or pause and resume
+    Toggle { html: String, url: String },
+    Next,
+    Previous,
+    Stop,
+    Faster,
+    Slower,
+}
+
+/// What a page asked of web feeds
+#[derive(Debug, Clone)]
+pub enum FeedsRequest {
+    /// A page links feeds in its head
+    Detected { url: String, head: String },
+    /// Subscribe to the first feed the page links
+    Subscribe,
+    MarkRead(u64),
+    /// Mark one feed's items read, or all items
+    MarkAllRead(Option<u64>),
+    Unsubscribe(u64),
+    /// Poll every feed now
+    Refresh,
+}
+
+/// What a page asked of the notebook
+#[derive(Debug, Clone)]
+pub enum NotesRequest {
+    /// Save the selection, or the page simplified when nothing is selected
+    Clip {
+        url: String,
+        title: String,
+        selection: Option<String>,
+        html: Option<String>,
+        tags: Vec<String>,
+    },
+    Delete(u64),
+    /// Export one note, or the whole notebook, into the downloads directory
+    Export(Option<u64>),
+}
+
+/// What a page asked of the reading list
+#[derive(Debug, Clone)]
+pub enum ReadingListRequest {
+    /// Save the page, with its article for reading offline
+    Save { url: String, title: String, html: String },
+    MarkRead(u64, bool),
+    Remove(u64),
+}
+
+/// What the user asked of a window's split view
+#[derive(Debug, Clone, Copy)]
+pub enum SplitRequest {
+    /// Split the window, or show only its active tab again
+    Toggle,
+    /// Turn the panes between side by side and stacked
+    Rotate,
+    /// Grow, or with a negative change shrink, the active tab's pane
+    Resize(f64),
+}
+
+/// How much one key press resizes split view panes
+pub const SPLIT_RESIZE_STEP: f64 = 0.05;
+
+/// What the stale tab review asked for, by tab IDs
+#[derive(Debug, Clone)]
+pub enum StaleTabsRequest {
+    Close(Vec<usize>),
+    /// Keep the tabs, counting them as used now
+    Keep(Vec<usize>),
+}
+
+/// What the statistics page asked for
+#[derive(Debug, Clone, Copy)]
+pub enum StatsRequest {
+    Enable,
+    Disable,
+    Clear,
+}
+
+/// What the settings page asked for
+#[derive(Debug, Clone)]
+pub enum SettingsRequest {
+    Set { key: String, value: SettingValue },
+    Reset(String),
+}
+
+/// A page's call to navigator.credentials
+#[derive(Debug, Clone)]
+pub enum WebAuthnRequest {
+    Create(CredentialCreationRequest),
+    Get(CredentialAssertionRequest),
+}
+
+/// Days of usage metrics the statistics page shows
+pub const STATS_PAGE_DAYS: u32 = 30;
+
+/// Days of tracking protection activity the privacy report shows
+pub const PRIVACY_PAGE_DAYS: u32 = 12 * 7;
+
+/// How often the tray icon picks up download progress
+const TRAY_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often tabs are checked for going stale
+const STALE_TAB_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a page gets to report it loaded after its webview finished
+/// before it is checked for a navigation error
+const PAGE_REPORT_GRACE: Duration = Duration::from_secs(2);
+
+/// Disk space pages saved for offline viewing may take
+const OFFLINE_STORAGE_LIMIT_MB: usize = 500;
 
-pub use window::BrowserWindow;
+/// Size of the in-memory HTTP cache that speculative loading fills
+const HTTP_CACHE_SIZE_MB: usize = 64;
+
+/// How often expired responses are dropped from the HTTP cache
+const HTTP_CACHE_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 /// Main browser application
 pub struct BrowserApp {
@@ -20,13 +352,66 @@ pub struct BrowserApp {
     config: Arc<ConfigManager>,
     tab_manager: Arc<TabManager>,
     download_manager: Arc<DownloadManager>,
+    media_sniffer: Arc<MediaSniffer>,
     privacy_protection: Arc<PrivacyProtection>,
+    bounce_protection: Arc<BounceProtection>,
+    fingerprint_shield: Arc<FingerprintShield>,
+    privacy_report: Arc<PrivacyReport>,
+    safe_browsing: Arc<SafeBrowsing>,
+    csp_monitor: Arc<CspMonitor>,
+    find_in_page: Arc<FindInPage>,
+    new_identity: Arc<NewIdentity>,
     theme_manager: Arc<ThemeManager>,
+    proxy_manager: Arc<Mutex<ProxyManager>>,
+    retention_engine: Arc<Mutex<RetentionEngine>>,
+    error_reporter: Arc<ErrorReporter>,
+    watchdog: Arc<StateWatchdog>,
+    runtime: Arc<BrowserRuntime>,
+    notification_manager: Arc<NotificationManager>,
+    push_manager: Arc<PushManager>,
+    webauthn: Arc<WebAuthnBridge>,
+    media_controller: Arc<MediaController>,
+    autoplay_blocker: Arc<AutoplayBlocker>,
+    recording_tracker: Arc<RecordingTracker>,
+    mixed_content: Arc<MixedContentPolicy>,
+    sri_checker: Arc<SriChecker>,
+    clipboard_broker: Arc<ClipboardBroker>,
+    capture_service: Arc<CaptureService>,
+    translator: Arc<Translator>,
+    read_aloud: Arc<ReadAloudService>,
+    context_menu: Arc<ContextMenu>,
+    print_manager: Arc<PrintManager>,
+    share_service: Arc<ShareService>,
+    form_history: Arc<FormHistory>,
+    keyboard_nav: Arc<KeyboardNav>,
+    site_zoom: Arc<SiteZoom>,
+    feed_manager: Arc<FeedManager>,
+    notebook: Arc<Notebook>,
+    reading_list: Arc<ReadingList>,
+    offline_storage: Arc<Mutex<OfflineStorage>>,
+    metrics: Arc<Metrics>,
+    speculative: Arc<SpeculativeLoader>,
+    protocol_handlers: Arc<ProtocolHandlers>,
+    settings_registry: Arc<SettingsRegistry>,
+    policies: Arc<PolicySet>,
+    scheduler: Arc<TaskScheduler>,
+    prompter: Prompter,
+    /// When the app was created, for timing startup
+    launched_at: Instant,
+    session_restore: SessionRestore,
+    single_instance: Option<SingleInstance>,
+    startup_urls: Vec<String>,
 }
 
 impl BrowserApp {
     /// Create a new browser application
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let launched_at = Instant::now();
+
+        // Managers start their background tasks on the shared runtime
+        let runtime = Arc::new(BrowserRuntime::new(None)?);
+        let runtime_guard = runtime.enter();
+
         let config = Arc::new(ConfigManager::new()?);
         
         let mut state = BrowserState::new();
@@ -35,75 +420,1979 @@ impl BrowserApp {
         state.settings = config.load_settings();
         state.bookmarks = config.load_bookmarks();
         state.history = config.load_history();
+
+        // Tell the user about damaged files instead of silently resetting them
+        let error_reporter = Arc::new(ErrorReporter::new());
+        for failure in config.load_failures() {
+            error_reporter.report("config", &WebxError::Parse(failure.to_string()));
+        }
         
+        // Administrator policies override the user's settings below
+        let policies = Arc::new(PolicySet::load(None));
+        if !policies.issues().is_empty() {
+            let message = format!("{} problem(s) with administrator policies, see webx://policy", policies.issues().len());
+            error_reporter.report("policy", &WebxError::Parse(message));
+        }
+
+        // Reopen the windows and tabs of the last run
+        let mut session_restore = SessionRestore::new(None, None)?;
+        if session_restore.get_config().restore_on_start {
+            if let Some(session) = session_restore.get_last_autosave() {
+                session_restore.apply_session_to_browser(&session, &mut state)?;
+            }
+        }
+
         // Initialize feature managers
         let state_arc = Arc::new(Mutex::new(state));
         let tab_manager = Arc::new(TabManager::new(Arc::clone(&state_arc)));
-        let download_manager = Arc::new(DownloadManager::new(None)?);
-        let privacy_protection = Arc::new(PrivacyProtection::new());
+        // Proxy passwords and backup credentials live in the OS keyring
+        let secret_store = Arc::new(SecretStore::new(None)?);
+        let mut proxy_manager = ProxyManager::new(None, None)?;
+        error_reporter.check("secrets", proxy_manager.set_secret_store(Arc::clone(&secret_store)));
+        if let Some(policy) = policies.proxy() {
+            if let Err(e) = proxy_manager.apply_policy(policy) {
+                error_reporter.report("policy", &e);
+            }
+        }
+        // The Tor profile goes through the browser's own tor, once started
+        let tor = TorController::new(None, None)?;
+        error_reporter.check("proxy", proxy_manager.use_tor(&tor));
+        let tor = Arc::new(tokio::sync::Mutex::new(tor));
+        let proxy_manager = Arc::new(Mutex::new(proxy_manager));
+        let containers = Arc::new(ContainerManager::new(None)?);
+        let user_agents = Arc::new(Mutex::new(UserAgentSwitcher::new(None, None)?));
+        // Magnet links and .torrent files go to the user's torrent client
+        let protocol_handlers = Arc::new(ProtocolHandlers::load(None)?);
+        let mut download_manager = DownloadManager::new(None)?;
+        download_manager.set_protocol_handlers(Arc::clone(&protocol_handlers));
+        download_manager.set_proxy_manager(Arc::clone(&proxy_manager));
+        download_manager.set_runtime(runtime.handle().clone());
+        download_manager.set_policy(DownloadPolicy::load(None, None)?);
+        download_manager.set_store(DownloadStore::new(None));
+        let download_manager = Arc::new(download_manager);
+        // Usage metrics stay off, and on this device, unless the user says otherwise
+        let metrics = Arc::new(Metrics::new(None, None)?);
+        let privacy_report = Arc::new(PrivacyReport::new(None)?);
+        let mut privacy_protection = PrivacyProtection::new(None, None)?;
+        privacy_protection.set_metrics(Arc::clone(&metrics));
+        privacy_protection.set_report(Arc::clone(&privacy_report));
+        let privacy_protection = Arc::new(privacy_protection);
+        // Links bouncing through tracking redirectors go straight to their destination
+        let mut bounce_protection = BounceProtection::new();
+        bounce_protection.set_privacy_protection(Arc::clone(&privacy_protection));
+        let bounce_protection = Arc::new(bounce_protection);
+        let fingerprint_shield = Arc::new(FingerprintShield::new(None)?);
+        let mut http_cache = HTTPCache::new(HTTP_CACHE_SIZE_MB, 60, true);
+        http_cache.set_metrics(Arc::clone(&metrics));
+        let http_cache = Arc::new(Mutex::new(http_cache));
+        let new_identity = Arc::new(NewIdentity::new(IdentityTargets {
+            containers: Some(Arc::clone(&containers)),
+            http_cache: Some(Arc::clone(&http_cache)),
+            user_agents: Some(Arc::clone(&user_agents)),
+            tor: Some(Arc::clone(&tor)),
+            privacy: Some(Arc::clone(&privacy_protection)),
+            bounces: Some(Arc::clone(&bounce_protection)),
+        }));
+        let site_storage = Arc::new(SiteStorageManager::new(None, SiteStorageManager::default_data_dirs())?);
+        // Typed search and form entries, remembered only once the user opts in
+        let form_history = Arc::new(FormHistory::new(None)?);
+        let keyboard_nav = Arc::new(KeyboardNav::new());
+        let site_zoom = Arc::new(SiteZoom::new(None)?);
+        error_reporter.check("zoom", site_zoom.set_default_level(state_arc.lock_or_recover().settings.default_zoom));
+        let retention_engine = Arc::new(Mutex::new(RetentionEngine::new(
+            privacy_protection.retention_policy(),
+            RetentionTargets {
+                containers: Some(Arc::clone(&containers)),
+                http_cache: Some(Arc::clone(&http_cache)),
+                site_storage: Some(Arc::clone(&site_storage)),
+                form_history: Some(Arc::clone(&form_history)),
+                ..Default::default()
+            },
+        )));
+
+        // Battery, metered network and idle state decide when expensive background work runs
+        let conditions = Arc::new(ConditionsMonitor::new(None));
+        ConditionsMonitor::start(Arc::clone(&conditions));
+
+        // Resolve likely sites and fill the cache ahead of the user, as far as settings allow
+        let mut speculative = SpeculativeLoader::new(None, None)?;
+        speculative.set_conditions_monitor(Arc::clone(&conditions));
+        speculative.set_http_cache(Arc::clone(&http_cache));
+        speculative.set_proxy_manager(Arc::clone(&proxy_manager));
+        speculative.set_data_saver(state_arc.lock_or_recover().settings.data_saver);
+        let speculative = Arc::new(speculative);
+
+        // Periodic and idle-time work takes turns, and waits on battery or metered networks
+        let scheduler = Arc::new(TaskScheduler::new(None, Arc::clone(&conditions)));
+        Metrics::schedule(Arc::clone(&metrics), &scheduler);
+        PrivacyReport::schedule(Arc::clone(&privacy_report), &scheduler);
+        SpeculativeLoader::schedule(Arc::clone(&speculative), &scheduler);
+        // Navigations are checked against threat lists before they load
+        let safe_browsing = Arc::new(SafeBrowsing::new(None)?);
+        SafeBrowsing::schedule(Arc::clone(&safe_browsing), &scheduler);
+        // Pages report their policies and violations, and search their text, through these
+        let csp_monitor = Arc::new(CspMonitor::new());
+        let find_in_page = Arc::new(FindInPage::new(None));
+        let gc_cache = Arc::clone(&http_cache);
+        let gc_spec = TaskSpec::new("http-cache-gc", HTTP_CACHE_GC_INTERVAL).with_priority(TaskPriority::Low);
+        scheduler.register(gc_spec, move || {
+            let http_cache = Arc::clone(&gc_cache);
+            async move {
+                http_cache.lock_or_recover().clear_expired();
+                Ok(())
+            }
+        });
+        TaskScheduler::start(Arc::clone(&scheduler));
+        RetentionEngine::start_daily_timer(Arc::clone(&retention_engine));
         let theme_manager = Arc::new(ThemeManager::new(None, None)?);
+
+        // Toast finished downloads and permitted site notifications
+        let permission_manager = Arc::new(PermissionManager::new(None)?);
+        let mut notification_manager = NotificationManager::new(None)?;
+        notification_manager.set_permission_manager(Arc::clone(&permission_manager));
+        let notification_manager = Arc::new(notification_manager);
+        NotificationManager::start_download_notifications(
+            Arc::clone(&notification_manager),
+            Arc::clone(&download_manager),
+        );
+        // Downloads a crash or quit left unfinished resume, or wait for a retry
+        let recovering = Arc::clone(&download_manager);
+        let recovery_reporter = Arc::clone(&error_reporter);
+        runtime.spawn(async move {
+            recovery_reporter.check("downloads", recovering.recover().await);
+        });
+
+        // Show push messages of subscribed sites, open in a tab or not
+        let push_manager = Arc::new(PushManager::new(
+            None,
+            Arc::clone(&notification_manager),
+            Arc::clone(&permission_manager),
+        )?);
+        PushManager::schedule(Arc::clone(&push_manager), &scheduler);
+
+        // Track tab media and hand it to the system's media controls
+        let media_controller = Arc::new(MediaController::new());
+        MediaController::start_system_controls(Arc::clone(&media_controller));
+
+        // Show which tabs record, unless the site or the user turned devices off
+        let mut recording_tracker = RecordingTracker::new();
+        recording_tracker.set_permission_manager(Arc::clone(&permission_manager));
+        let recording_tracker = Arc::new(recording_tracker);
+        // Secure pages load their subresources securely, or show that they do not
+        let mixed_content = Arc::new(MixedContentPolicy::new());
+        let sri_checker = Arc::new(SriChecker::new());
+
+        // Pages read the clipboard only where permitted, and write it sparingly
+        let mut clipboard_broker = ClipboardBroker::new();
+        clipboard_broker.set_permission_manager(Arc::clone(&permission_manager));
+        let clipboard_broker = Arc::new(clipboard_broker);
+
+        // Keep media quiet until the user interacts, except on allowed sites
+        let mut autoplay_blocker = AutoplayBlocker::new(Some(state_arc.lock_or_recover().settings.autoplay));
+        autoplay_blocker.set_permission_manager(permission_manager);
+        let autoplay_blocker = Arc::new(autoplay_blocker);
+        let translator = Arc::new(Translator::new(None, None)?);
+        let read_aloud = Arc::new(ReadAloudService::new(Some(state_arc.lock_or_recover().settings.read_aloud.clone())));
+        let context_menu = Arc::new(ContextMenu::new());
+        if policies.feature_disabled("devtools") {
+            context_menu.remove_item(INSPECT_ITEM);
+        }
+        let print_manager = Arc::new(PrintManager::new(None)?);
+        let feed_manager = Arc::new(FeedManager::new(None, None)?);
+        FeedManager::start_polling(Arc::clone(&feed_manager));
+        let notebook = Arc::new(Notebook::new(None)?);
+        let reading_list = Arc::new(ReadingList::new(None)?);
+        let share_service = Arc::new(ShareService::with_builtin_targets(
+            Arc::clone(&reading_list),
+            Arc::clone(&protocol_handlers),
+        ));
+        // Encrypted snapshots go to the user's WebDAV server once they enter the passphrase
+        let backup_sources = BackupSources::new(Arc::clone(&state_arc), Arc::clone(&config))
+            .with_reading_list(Arc::clone(&reading_list));
+        let mut webdav_backup = WebDavBackup::load(backup_sources, None)?;
+        webdav_backup.set_proxy_manager(Arc::clone(&proxy_manager));
+        error_reporter.check("secrets", webdav_backup.set_secret_store(Arc::clone(&secret_store)));
+        let webdav_backup = Arc::new(webdav_backup);
+
+        // Questions for the user, shown once the event loop runs
+        let prompter = Prompter::new();
+
+        // Passkeys live in the password vault; security keys plugged in now are found
+        let webauthn = Arc::new(WebAuthnBridge::new(None)?);
+        match PasswordManager::open(None, VaultUnlock::Secrets(&secret_store)) {
+            Ok(vault) => {
+                let presence = Arc::new(prompter.clone());
+                webauthn.register_authenticator(Arc::new(PlatformAuthenticator::new(Arc::new(vault), presence)));
+            }
+            Err(e) => {
+                error_reporter.report("passkeys", &e);
+            }
+        }
+        webauthn.discover_usb_authenticators();
+
+        // Every module's options, shown on webx://settings and applied as they change
+        let settings_registry = Arc::new(SettingsRegistry::new(None)?);
+        error_reporter.check("policy", settings_registry.set_policies(&policies));
+        // The system language until a stored choice is applied at registration
+        i18n::localizer().set_locale(i18n::SYSTEM_LOCALE);
+        let mut providers: Vec<Arc<dyn SettingsProvider>> = vec![
+            Arc::new(BrowserSettingsProvider::new(Arc::clone(&state_arc), Arc::clone(&config))),
+            download_manager.clone(),
+            protocol_handlers.clone(),
+            speculative.clone(),
+            metrics.clone(),
+            scheduler.clone(),
+            conditions.clone(),
+            form_history.clone(),
+            keyboard_nav.clone(),
+            site_zoom.clone(),
+            theme_manager.accessibility().clone(),
+            recording_tracker.clone(),
+            mixed_content.clone(),
+            sri_checker.clone(),
+            safe_browsing.clone(),
+            clipboard_broker.clone(),
+            bounce_protection.clone(),
+            fingerprint_shield.clone(),
+            new_identity.clone(),
+            i18n::localizer().clone(),
+        ];
+        if !policies.feature_disabled("backup") {
+            WebDavBackup::schedule(Arc::clone(&webdav_backup), &scheduler);
+            providers.push(webdav_backup);
+        }
+        for provider in providers {
+            if let Err(e) = settings_registry.register(provider) {
+                error_reporter.report("settings", &e);
+            }
+        }
+        let mut settings_events = settings_registry.subscribe_events();
+        let live_speculative = Arc::clone(&speculative);
+        let live_zoom = Arc::clone(&site_zoom);
+        runtime.spawn(async move {
+            while let Some(event) = settings_events.recv().await {
+                if let SettingsEvent::Changed { key, value, .. } = event {
+                    if key == "general.data_saver" {
+                        if let Some(profile) = value.as_str().and_then(parse_data_saver) {
+                            live_speculative.set_data_saver(profile);
+                        }
+                    } else if key == "general.default_zoom" {
+                        if let Some(level) = value.as_f64() {
+                            if let Err(e) = live_zoom.set_default_level(level) {
+                                tracing::warn!("Failed to change the default zoom: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let offline_storage = Arc::new(Mutex::new(OfflineStorage::new(None, OFFLINE_STORAGE_LIMIT_MB)?));
+
+        // Recover components whose lock a panicking thread left poisoned
+        let watchdog = Arc::new(StateWatchdog::new());
+        tab_manager.watch_state(&watchdog);
+        download_manager.watch_state(&watchdog);
+        privacy_protection.watch_state(&watchdog);
+        watchdog.watch("proxy", &proxy_manager);
+        session_restore.set_conditions_monitor(Arc::clone(&conditions));
+        session_restore.start_auto_save(Arc::clone(&state_arc))?;
+        drop(runtime_guard);
         
         Ok(Self {
             state: state_arc,
             config,
             tab_manager,
             download_manager,
+            media_sniffer: Arc::new(MediaSniffer::new()),
             privacy_protection,
+            bounce_protection,
+            fingerprint_shield,
+            privacy_report,
+            safe_browsing,
+            csp_monitor,
+            find_in_page,
+            new_identity,
             theme_manager,
+            proxy_manager,
+            retention_engine,
+            error_reporter,
+            watchdog,
+            runtime,
+            notification_manager,
+            push_manager,
+            webauthn,
+            media_controller,
+            autoplay_blocker,
+            recording_tracker,
+            mixed_content,
+            sri_checker,
+            clipboard_broker,
+            capture_service: Arc::new(CaptureService::new()),
+            translator,
+            read_aloud,
+            context_menu,
+            print_manager,
+            share_service,
+            form_history,
+            keyboard_nav,
+            site_zoom,
+            feed_manager,
+            notebook,
+            reading_list,
+            offline_storage,
+            metrics,
+            speculative,
+            protocol_handlers,
+            settings_registry,
+            policies,
+            scheduler,
+            prompter,
+            launched_at,
+            session_restore,
+            single_instance: None,
+            startup_urls: Vec::new(),
         })
     }
 
+    /// Error reporter that background work sends user-facing errors to
+    pub fn error_reporter(&self) -> Arc<ErrorReporter> {
+        Arc::clone(&self.error_reporter)
+    }
+
+    /// Runtime background work runs on
+    pub fn runtime(&self) -> Arc<BrowserRuntime> {
+        Arc::clone(&self.runtime)
+    }
+
+    /// Notification manager for site and download notifications
+    pub fn notification_manager(&self) -> Arc<NotificationManager> {
+        Arc::clone(&self.notification_manager)
+    }
+
+    /// Media controller for tab audio and video
+    pub fn media_controller(&self) -> Arc<MediaController> {
+        Arc::clone(&self.media_controller)
+    }
+
+    /// Hand URLs from later invocations to this app. The instance must
+    /// hold the instance lock.
+    pub fn set_single_instance(&mut self, single_instance: SingleInstance) {
+        self.single_instance = Some(single_instance);
+    }
+
+    /// Open URLs as new tabs once the window is up
+    pub fn open_on_start(&mut self, urls: Vec<String>) {
+        self.startup_urls = urls;
+    }
+
     /// Run the browser application
     pub fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        let event_loop = EventLoop::new();
-        
-        // Create the main browser window
-        let window = BrowserWindow::new(
-            &event_loop, 
-            self.state.clone(), 
-            self.config.clone(),
-            self.tab_manager.clone(),
-            self.download_manager.clone(),
-            self.privacy_protection.clone(),
-            self.theme_manager.clone(),
-        )?;
+        let runtime = Arc::clone(&self.runtime);
+        let _runtime_guard = runtime.enter();
+        let event_loop = EventLoopBuilder::<UiEvent>::with_user_event().build();
+        StateWatchdog::start_watching(self.watchdog.clone(), Duration::from_secs(5));
+
+        // Forward URLs from later invocations to the event loop
+        let mut single_instance = self.single_instance;
+        if let Some(instance) = single_instance.as_mut() {
+            instance.start_listening()?;
+            let mut events = instance.subscribe_events();
+            let proxy = event_loop.create_proxy();
+            runtime.spawn(async move {
+                while let Some(event) = events.recv().await {
+                    let event = match event {
+                        InstanceEvent::OpenUrls(urls) => UiEvent::OpenUrls(urls),
+                        InstanceEvent::Capture(request) => UiEvent::CaptureRequest(request),
+                    };
+                    if proxy.send_event(event).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let mut media_events = self.media_controller.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = media_events.recv().await {
+                if proxy.send_event(UiEvent::Media(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut recording_events = self.recording_tracker.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = recording_events.recv().await {
+                if proxy.send_event(UiEvent::Recording(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut mixed_content_events = self.mixed_content.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = mixed_content_events.recv().await {
+                if proxy.send_event(UiEvent::MixedContent(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut sri_events = self.sri_checker.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(SriEvent::Failed(failure)) = sri_events.recv().await {
+                // Reported in the page's console, where engines report their own checks
+                let message = format!(
+                    "Blocked {} from {}: {:?}",
+                    failure.url, failure.page_url, failure.reason
+                );
+                let script = format!("console.error({})", serde_json::Value::from(message));
+                if proxy.send_event(UiEvent::EvalInTab { tab_id: failure.tab_id, script }).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut safe_browsing_events = self.safe_browsing.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = safe_browsing_events.recv().await {
+                let SafeBrowsingEvent::NavigationBlocked { tab_id, url, threat } = event else {
+                    continue;
+                };
+                // The warning takes the blocked page's place in the tab's history
+                tracing::warn!("Blocked {} in tab {}: {:?}", url, tab_id, threat);
+                let script = format!("location.replace({});", serde_json::Value::from(blocked_page_url(&url)));
+                if proxy.send_event(UiEvent::EvalInTab { tab_id, script }).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut capture_events = self.capture_service.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = capture_events.recv().await {
+                if proxy.send_event(UiEvent::Capture(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut context_menu_events = self.context_menu.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = context_menu_events.recv().await {
+                if proxy.send_event(UiEvent::ContextMenu(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut read_aloud_events = self.read_aloud.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = read_aloud_events.recv().await {
+                if proxy.send_event(UiEvent::ReadAloudProgress(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        if let Some(mut tab_events) = self.tab_manager.subscribe_events() {
+            let proxy = event_loop.create_proxy();
+            runtime.spawn(async move {
+                while let Some(event) = tab_events.recv().await {
+                    if proxy.send_event(UiEvent::Tab(event)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            let mut timer = tokio::time::interval(STALE_TAB_CHECK_INTERVAL);
+            loop {
+                timer.tick().await;
+                if proxy.send_event(UiEvent::CheckStaleTabs).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut settings_events = self.settings_registry.subscribe_events();
+        let proxy = event_loop.create_proxy();
+        runtime.spawn(async move {
+            while let Some(event) = settings_events.recv().await {
+                let key = match &event {
+                    SettingsEvent::Changed { key, .. } | SettingsEvent::Reset(key) => key,
+                };
+                let refresh = if key.starts_with("accessibility.") {
+                    UiEvent::RefreshAccessibility
+                } else if key.starts_with("keyboard.") {
+                    UiEvent::RefreshKeyboardNav
+                } else if key.starts_with("language.") {
+                    // The tray is rebuilt when its labels change
+                    UiEvent::RefreshTray
+                } else {
+                    continue;
+                };
+                if proxy.send_event(refresh).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Hand page messages to the features that take them
+        let ipc_router = Arc::new(IpcRouter::new());
+        let ipc_handlers: [Arc<dyn IpcHandler>; 16] = [
+            Arc::new(UiIpcHandler::new(event_loop.create_proxy())),
+            self.capture_service.clone(),
+            self.context_menu.clone(),
+            self.print_manager.clone(),
+            self.share_service.clone(),
+            self.form_history.clone(),
+            self.keyboard_nav.clone(),
+            self.site_zoom.clone(),
+            self.theme_manager.accessibility().clone(),
+            self.recording_tracker.clone(),
+            self.mixed_content.clone(),
+            self.clipboard_broker.clone(),
+            self.privacy_report.clone(),
+            self.safe_browsing.clone(),
+            self.find_in_page.clone(),
+            self.csp_monitor.clone(),
+        ];
+        for handler in ipc_handlers {
+            self.error_reporter.check("ipc", ipc_router.register(handler));
+        }
+        if !self.startup_urls.is_empty() {
+            let _ = event_loop.create_proxy().send_event(UiEvent::OpenUrls(self.startup_urls));
+        }
         
+        // Creates the on-screen window for a window in the browser state
+        let open_window = {
+            let services = Arc::new(WindowServices {
+                state: self.state.clone(),
+                config: self.config.clone(),
+                tab_manager: self.tab_manager.clone(),
+                download_manager: self.download_manager.clone(),
+                privacy_protection: self.privacy_protection.clone(),
+                bounce_protection: self.bounce_protection.clone(),
+                fingerprint_shield: self.fingerprint_shield.clone(),
+                privacy_report: self.privacy_report.clone(),
+                safe_browsing: self.safe_browsing.clone(),
+                csp_monitor: self.csp_monitor.clone(),
+                find_in_page: self.find_in_page.clone(),
+                theme_manager: self.theme_manager.clone(),
+                proxy_manager: self.proxy_manager.clone(),
+                autoplay_blocker: self.autoplay_blocker.clone(),
+                feed_manager: self.feed_manager.clone(),
+                notebook: self.notebook.clone(),
+                reading_list: self.reading_list.clone(),
+                metrics: self.metrics.clone(),
+                speculative: self.speculative.clone(),
+                protocol_handlers: self.protocol_handlers.clone(),
+                settings_registry: self.settings_registry.clone(),
+                policies: self.policies.clone(),
+                ipc_router: ipc_router.clone(),
+            });
+            let proxy = event_loop.create_proxy();
+            move |target: &EventLoopWindowTarget<UiEvent>, window_id: usize| {
+                BrowserWindow::new(target, proxy.clone(), window_id, Arc::clone(&services))
+            }
+        };
+
+        // Open every window of the restored session, or a fresh one
+        let mut window_ids: Vec<usize> = self.state.lock_or_recover().windows.keys().copied().collect();
+        window_ids.sort_unstable();
+        if window_ids.is_empty() {
+            window_ids.push(self.state.lock_or_recover().open_window());
+        }
+        let mut windows: HashMap<WindowId, BrowserWindow> = HashMap::new();
+        for window_id in window_ids {
+            let window = open_window(&event_loop, window_id)?;
+            windows.insert(window.window.id(), window);
+        }
+        self.metrics.record_duration(STARTUP_TIME, self.launched_at.elapsed());
+
+        let state = self.state;
+        let config = self.config;
+        let tab_manager = self.tab_manager;
+        let download_manager = self.download_manager;
+        let media_sniffer = self.media_sniffer.clone();
+        let retention_engine = self.retention_engine;
+        let error_reporter = self.error_reporter.clone();
+        let notification_manager = self.notification_manager.clone();
+        let push_manager = self.push_manager.clone();
+        let webauthn = self.webauthn.clone();
+        let media_controller = self.media_controller.clone();
+        let recording_tracker = self.recording_tracker.clone();
+        let mixed_content = self.mixed_content.clone();
+        let csp_monitor = self.csp_monitor.clone();
+        let find_in_page = self.find_in_page.clone();
+        let bounce_protection = self.bounce_protection.clone();
+        let capture_service = self.capture_service.clone();
+        let translator = self.translator.clone();
+        let read_aloud = self.read_aloud.clone();
+        let context_menu = self.context_menu.clone();
+        let feed_manager = self.feed_manager.clone();
+        let notebook = self.notebook.clone();
+        let reading_list = self.reading_list.clone();
+        let reading_mode = ReadingMode::new(None);
+        let proxy_manager = self.proxy_manager.clone();
+        let offline_storage = self.offline_storage.clone();
+        let metrics = self.metrics.clone();
+        let privacy_report = self.privacy_report.clone();
+        let new_identity = self.new_identity.clone();
+        let speculative = self.speculative.clone();
+        let protocol_handlers = self.protocol_handlers.clone();
+        let settings_registry = self.settings_registry.clone();
+        let site_zoom = self.site_zoom.clone();
+        let policies = self.policies.clone();
+        let scheduler = self.scheduler.clone();
+        let prompter = self.prompter.clone();
+        prompter.connect(event_loop.create_proxy());
+        let handle = runtime.handle().clone();
+        let event_proxy = event_loop.create_proxy();
+        let mut session_restore = self.session_restore;
+
+        // Tray icon with quick actions, kept in sync with the downloads
+        let tray_settings = state.lock_or_recover().settings.tray.clone();
+        let mut tray = if tray_settings.enabled {
+            let downloads = download_manager.get_downloads();
+            match BrowserTray::new(tray_settings, &downloads, true, event_loop.create_proxy()) {
+                Ok(tray) => {
+                    let proxy = event_loop.create_proxy();
+                    runtime.spawn(async move {
+                        let mut timer = tokio::time::interval(TRAY_REFRESH_INTERVAL);
+                        loop {
+                            timer.tick().await;
+                            if proxy.send_event(UiEvent::RefreshTray).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    Some(tray)
+                }
+                Err(e) => {
+                    tracing::warn!("Tray icon unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Prompts on screen, by their window
+        let mut prompts: HashMap<WindowId, PromptWindow> = HashMap::new();
+        // Modifier keys held in the browser's windows, for their shortcuts
+        let mut modifiers = ModifiersState::empty();
+        // Whether the user is being asked about links dropped on a page
+        let drop_prompt_open = Arc::new(AtomicBool::new(false));
+        // Tabs whose pop-up the user is being asked about
+        let pending_popups: Arc<Mutex<HashSet<usize>>> = Arc::new(Mutex::new(HashSet::new()));
+        // Captures asked for by later invocations, by tab ID, waiting for the page to load
+        let mut pending_captures: HashMap<usize, CaptureRequest> = HashMap::new();
+        // Latest text each tab reported, kept for translating on request
+        let mut page_texts: HashMap<usize, PageText> = HashMap::new();
+        // Feeds each tab's page links, for subscribing
+        let mut page_feeds: HashMap<usize, Vec<FeedLink>> = HashMap::new();
+        // Page each tab last reported loaded, telling finished loads that failed apart
+        let mut loaded_pages: HashMap<usize, String> = HashMap::new();
+        // When each tab's webview started loading, for timing page loads
+        let mut load_started: HashMap<usize, Instant> = HashMap::new();
+        // Scroll positions to restore, by tab ID, once going back or forward loads
+        let mut pending_scrolls: HashMap<usize, (f64, f64)> = HashMap::new();
+        // Stale tabs the review already came up for, so it comes up once per tab
+        let mut offered_stale_tabs: HashSet<usize> = HashSet::new();
+
         // Run the event loop
-        event_loop.run(move |event, _, control_flow| {
+        event_loop.run(move |event, target, control_flow| {
             *control_flow = ControlFlow::Wait;
+            let mut quit = false;
 
             match event {
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => {
-                    // Save state before closing
-                    if let Ok(state) = window.state.lock() {
-                        let _ = window.config.save_settings(&state.settings);
-                        let _ = window.config.save_bookmarks(&state.bookmarks);
-                        let _ = window.config.save_history(&state.history);
-                    }
-                    *control_flow = ControlFlow::Exit;
-                }
-                Event::WindowEvent {
-                    event: WindowEvent::KeyboardInput { device_id: _, event, is_synthetic: _, .. },
-                    ..
-                } => {
+                Event::WindowEvent { window_id, event, .. } => match event {
+                    // Closing a prompt declines it
+                    WindowEvent::CloseRequested if prompts.contains_key(&window_id) => {
+                        if let Some(prompt) = prompts.remove(&window_id) {
+                            prompt.answer(PromptAnswer::default());
+                        }
+                    }
+                    WindowEvent::CloseRequested => {
+                        if windows.len() > 1 {
+                            // Closing one of several windows closes its tabs
+                            if let Some(window) = windows.remove(&window_id) {
+                                for tab_id in tab_manager.close_window(window.window_id) {
+                                    media_controller.remove_tab(tab_id);
+                                    recording_tracker.remove_tab(tab_id);
+                                    mixed_content.remove_tab(tab_id);
+                                    csp_monitor.remove_tab(tab_id);
+                                    find_in_page.close_tab(tab_id);
+                                    bounce_protection.forget_tab(tab_id);
+                                    media_sniffer.clear_tab(tab_id);
+                                    capture_service.cancel_tab(tab_id);
+                                    pending_captures.remove(&tab_id);
+                                    page_texts.remove(&tab_id);
+                                    page_feeds.remove(&tab_id);
+                                    pending_scrolls.remove(&tab_id);
+                                    loaded_pages.remove(&tab_id);
+                                    load_started.remove(&tab_id);
+                                    read_aloud.stop_tab(tab_id);
+                                    context_menu.forget_tab(tab_id);
+                                }
+                            }
+                        } else if tray.as_ref().is_some_and(|tray| tray.settings().close_to_tray) {
+                            for window in windows.values() {
+                                window.window.set_visible(false);
+                            }
+                        } else {
+                            quit = true;
+                        }
+                    }
+                    WindowEvent::Focused(true) => {
+                        if let Some(window) = windows.get(&window_id) {
+                            state.lock_or_recover().focus_window(window.window_id);
+                        }
+                    }
+                    WindowEvent::Moved(_) => {
+                        if let Some(window) = windows.get(&window_id) {
+                            record_geometry(&state, window);
+                        }
+                    }
+                    WindowEvent::Resized(_) => {
+                        if let Some(window) = windows.get(&window_id) {
+                            record_geometry(&state, window);
+                            if let Err(e) = window.layout_panes() {
+                                tracing::warn!("Failed to lay out window: {}", e);
+                            }
+                        }
+                    }
+                    WindowEvent::ModifiersChanged(state) => modifiers = state,
                     // Handle keyboard shortcuts
-                    if event.state == tao::event::ElementState::Pressed {
-                        // This is where we'd handle Ctrl+T, Ctrl+W, etc.
-                        // For now, we'll just log the key press
-                        if let tao::keyboard::Key::Character(key_char) = &event.logical_key {
-                            println!("Key pressed: {}", key_char);
+                    WindowEvent::KeyboardInput { event, .. } if event.state == tao::event::ElementState::Pressed => {
+                        if let Key::Character(key_char) = &event.logical_key {
+                            tracing::debug!("Key pressed: {}", key_char);
+                            // Ctrl/Cmd + Shift + N: Start over with a new identity; pages
+                            // never see the browser's own keys
+                            let command = modifiers.control_key() || modifiers.super_key();
+                            if command && modifiers.shift_key() && key_char.eq_ignore_ascii_case("n") {
+                                let _ = event_proxy.send_event(UiEvent::NewIdentity);
+                            }
+                            // Ctrl/Cmd + Shift + Y: Download the links on the clipboard
+                            if command && modifiers.shift_key() && key_char.eq_ignore_ascii_case("y") {
+                                let _ = event_proxy.send_event(UiEvent::Download(DownloadRequest::Clipboard));
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                Event::UserEvent(UiEvent::OpenUrls(urls)) => {
+                    if let Some(window) = focused_window(&windows, &state) {
+                        for url in &urls {
+                            tab_manager.create_tab(Some(url.clone()));
+                        }
+                        if let Some(url) = urls.last() {
+                            if let Err(e) = window.navigate(url) {
+                                tracing::warn!("Failed to open {}: {}", url, e);
+                            }
+                        }
+                        window.window.set_visible(true);
+                        window.window.set_focus();
+                    }
+                }
+                Event::UserEvent(UiEvent::OpenFromPage { tab_id, url }) => {
+                    if !matches!(url::Url::parse(&url).map(|u| u.scheme().to_string()).as_deref(), Ok("http" | "https")) {
+                        tracing::warn!("Tab {} asked to open {} in a new tab; not a web page", tab_id, url);
+                    } else if !state.lock_or_recover().settings.block_popups {
+                        let _ = event_proxy.send_event(UiEvent::OpenUrls(vec![url]));
+                    } else if pending_popups.lock_or_recover().insert(tab_id) {
+                        // One question per tab; the page's further requests are blocked meanwhile
+                        let site = loaded_pages.get(&tab_id).and_then(|page| site_domain(page)).unwrap_or_else(|| "This page".to_string());
+                        let message = format!("{} wants to open {} in a new tab.", site, url);
+                        let prompter = prompter.clone();
+                        let pending_popups = Arc::clone(&pending_popups);
+                        let event_proxy = event_proxy.clone();
+                        handle.spawn(async move {
+                            let answer = prompter.ask(Prompt::confirm("Pop-up blocked", &message, "Open")).await;
+                            pending_popups.lock_or_recover().remove(&tab_id);
+                            if answer.accepted {
+                                let _ = event_proxy.send_event(UiEvent::OpenUrls(vec![url]));
+                            }
+                        });
+                    }
+                }
+                Event::UserEvent(UiEvent::NewWindow) => {
+                    let (window_id, _) = tab_manager.create_window(None);
+                    match open_window(target, window_id) {
+                        Ok(window) => {
+                            windows.insert(window.window.id(), window);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to open window: {}", e);
+                            tab_manager.close_window(window_id);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::MoveTabToNewWindow(tab_id)) => {
+                    let source = state.lock_or_recover().window_of_tab(tab_id);
+                    if let Some(window_id) = tab_manager.move_tab_to_new_window(tab_id) {
+                        match open_window(target, window_id) {
+                            Ok(window) => {
+                                windows.insert(window.window.id(), window);
+                            }
+                            Err(e) => tracing::warn!("Failed to open window: {}", e),
+                        }
+
+                        // The tab left its old window; close the window if it was the last one
+                        let source_window = source.and_then(|source| {
+                            windows.iter().find(|(_, window)| window.window_id == source).map(|(id, _)| *id)
+                        });
+                        if let Some(id) = source_window {
+                            let source = windows[&id].window_id;
+                            if tab_manager.get_window_tabs(source).is_empty() {
+                                windows.remove(&id);
+                                tab_manager.close_window(source);
+                            } else {
+                                if let Err(e) = windows[&id].show_active_tab() {
+                                    tracing::warn!("Failed to show tab: {}", e);
+                                }
+                                sync_split(&mut windows, source);
+                            }
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::MediaReport(tab_id, report)) => {
+                    tab_manager.set_tab_audible(tab_id, report.state == PlaybackState::Playing);
+                    media_controller.handle_report(tab_id, report);
+
+                    // Media that started after the tab was muted plays muted too
+                    let muted = state.lock_or_recover().tabs.get(&tab_id).is_some_and(|tab| tab.muted);
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id).filter(|_| muted) {
+                        if let Err(e) = window.eval_script(tab_id, &MediaCommand::SetMuted(true).script()) {
+                            tracing::warn!("Failed to mute tab: {}", e);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::ToggleMute(tab_id)) => {
+                    if let Some(muted) = tab_manager.toggle_tab_mute(tab_id) {
+                        media_controller.set_tab_muted(tab_id, muted);
+                    }
+                }
+                Event::UserEvent(UiEvent::MuteBackgroundTabs) => {
+                    for tab_id in tab_manager.mute_background_tabs() {
+                        media_controller.set_tab_muted(tab_id, true);
+                    }
+                }
+                Event::UserEvent(UiEvent::TogglePictureInPicture(tab_id)) => {
+                    let enabled = media_controller.get_session(tab_id).is_some_and(|s| s.picture_in_picture);
+                    if let Err(e) = media_controller.set_picture_in_picture(tab_id, !enabled) {
+                        tracing::info!("No picture-in-picture: {}", e);
+                    }
+                }
+                Event::UserEvent(UiEvent::Media(event)) => match event {
+                    MediaEvent::Command { tab_id, command } => {
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            if let Err(e) = window.eval_script(tab_id, &command.script()) {
+                                tracing::warn!("Failed to control media: {}", e);
+                            }
+                        }
+                    }
+                    MediaEvent::SessionChanged { tab_id }
+                    | MediaEvent::SessionEnded { tab_id }
+                    | MediaEvent::MuteChanged { tab_id, .. } => {
+                        // Show the tab's audio indicator in the title
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            window.set_title(&indicated_title(window, tab_id, &media_controller, &recording_tracker, &mixed_content));
+                        }
+                    }
+                },
+                Event::UserEvent(UiEvent::Recording(event)) => match event {
+                    RecordingEvent::Changed { tab_id } => {
+                        // Show whether the tab records in the title
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            window.set_title(&indicated_title(window, tab_id, &media_controller, &recording_tracker, &mixed_content));
+                        }
+                    }
+                    RecordingEvent::Revoke { tab_id, camera, microphone } => {
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            if let Err(e) = window.eval_script(tab_id, &stop_script(camera, microphone)) {
+                                tracing::warn!("Failed to stop recording: {}", e);
+                            }
+                        }
+                    }
+                },
+                Event::UserEvent(UiEvent::MixedContent(MixedContentEvent::IndicatorChanged { tab_id, .. })) => {
+                    // Show whether the tab's page is still secure in the title
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                        window.set_title(&indicated_title(window, tab_id, &media_controller, &recording_tracker, &mixed_content));
+                    }
+                }
+                Event::UserEvent(UiEvent::StopRecording(tab_id)) => {
+                    if let Err(e) = recording_tracker.revoke_tab(tab_id, true, true) {
+                        tracing::info!("Nothing to stop: {}", e);
+                    }
+                }
+                Event::UserEvent(UiEvent::Screenshot { tab_id, full_page }) => {
+                    let capture_service = capture_service.clone();
+                    let download_dir = download_manager.download_dir().clone();
+                    let error_reporter = error_reporter.clone();
+                    handle.spawn(async move {
+                        let screenshot = match full_page {
+                            true => capture_service.capture_full_page(tab_id).await,
+                            false => capture_service.capture_visible(tab_id).await,
+                        };
+                        match screenshot.and_then(|screenshot| save_screenshot(&screenshot, &download_dir)) {
+                            Ok(path) => tracing::info!("Saved screenshot to {}", path.display()),
+                            Err(e) => {
+                                error_reporter.report("screenshot", &e);
+                            }
+                        }
+                    });
+                }
+                Event::UserEvent(UiEvent::Download(request)) => {
+                    let download_manager = download_manager.clone();
+                    let media_sniffer = media_sniffer.clone();
+                    let media_controller = media_controller.clone();
+                    let error_reporter = error_reporter.clone();
+                    let prompter = prompter.clone();
+                    let drop_prompt_open = Arc::clone(&drop_prompt_open);
+                    handle.spawn(async move {
+                        let started = match request {
+                            // One question at a time; drops reported meanwhile are dropped
+                            DownloadRequest::Dropped(_) if drop_prompt_open.swap(true, Ordering::SeqCst) => Ok(Vec::new()),
+                            DownloadRequest::Dropped(text) => {
+                                let message = format!("Download the {} link(s) dropped on the page?", download_urls(&text).len());
+                                let accepted = prompter.ask(Prompt::confirm("Download", &message, "Download")).await.accepted;
+                                drop_prompt_open.store(false, Ordering::SeqCst);
+                                match accepted {
+                                    true => download_manager.start_from_text(&text).await,
+                                    false => Ok(Vec::new()),
+                                }
+                            }
+                            DownloadRequest::Clipboard => download_manager.start_from_clipboard().await,
+                            DownloadRequest::Url(url) => download_manager.start_download(&url).await.map(|download_id| vec![download_id]),
+                            DownloadRequest::PageData { page_url, filename, data_url } => {
+                                match DataUrl::parse(&data_url) {
+                                    Ok(data) => {
+                                        let filename = data.filename(filename.as_deref());
+                                        download_manager
+                                            .save_bytes(&page_url, &filename, Some(&data.mime), data.bytes)
+                                            .await
+                                            .map(|download_id| vec![download_id])
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            DownloadRequest::Batch { folder, urls } => {
+                                download_manager.start_batch_download(&folder, &urls).await.map(|batch_id| {
+                                    download_manager.get_batch(batch_id).map(|batch| batch.download_ids).unwrap_or_default()
+                                })
+                            }
+                            DownloadRequest::Media { tab_id, url } => {
+                                // Only media the page was seen loading is fetched
+                                let media = match url {
+                                    Some(url) => media_sniffer.media(tab_id).into_iter().find(|media| media.url == url),
+                                    None => media_sniffer.main_media(tab_id),
+                                };
+                                match media {
+                                    Some(media) if media.format.is_stream() => {
+                                        let title = media_controller
+                                            .get_session(tab_id)
+                                            .map(|session| session.metadata.title)
+                                            .filter(|title| !title.is_empty());
+                                        download_manager
+                                            .start_stream_download(&media.url, title.as_deref())
+                                            .await
+                                            .map(|download_id| vec![download_id])
+                                    }
+                                    Some(media) => download_manager.start_download(&media.url).await.map(|download_id| vec![download_id]),
+                                    None => Err(WebxError::NotFound("Video or audio on this page".to_string())),
+                                }
+                            }
+                        };
+                        match started {
+                            Ok(download_ids) => tracing::info!("Started {} download(s)", download_ids.len()),
+                            Err(e) => {
+                                error_reporter.report("downloads", &e);
+                            }
+                        }
+                    });
+                }
+                Event::UserEvent(UiEvent::MediaFound(tab_id, resources)) => {
+                    let found = resources.iter().filter(|resource| media_sniffer.observe(tab_id, resource)).count();
+                    if found > 0 {
+                        tracing::debug!("Found {} media resource(s) in tab {}", found, tab_id);
+                    }
+                }
+                Event::UserEvent(UiEvent::ExternalLink { url, .. }) => {
+                    if let Some(handoff) = protocol_handlers.resolve_url(&url) {
+                        match handoff.prompt.clone() {
+                            Some(message) => {
+                                // The browser asks, outside the page, so no page can answer for the user
+                                let prompt = Prompt::confirm("Open in another application", &message, "Open")
+                                    .with_checkbox(&always_label(&handoff));
+                                let prompter = prompter.clone();
+                                let proxy = event_proxy.clone();
+                                handle.spawn(async move {
+                                    let answer = prompter.ask(prompt).await;
+                                    if answer.accepted {
+                                        let _ = proxy.send_event(UiEvent::LaunchHandoff { handoff, always: answer.checked });
+                                    }
+                                });
+                            }
+                            None => launch_handoff(&handoff, &protocol_handlers, &feed_manager, &handle, &error_reporter),
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::LaunchHandoff { handoff, always }) => {
+                    if always {
+                        error_reporter.check("protocols", protocol_handlers.always_use(&handoff));
+                    }
+                    launch_handoff(&handoff, &protocol_handlers, &feed_manager, &handle, &error_reporter);
+                }
+                Event::UserEvent(UiEvent::Prompt { prompt, reply }) => {
+                    match PromptWindow::open(target, event_proxy.clone(), &prompt, reply) {
+                        Ok(window) => {
+                            prompts.insert(window.id(), window);
+                        }
+                        // The reply is dropped with the failed window, declining the prompt
+                        Err(e) => tracing::warn!("Failed to open prompt: {}", e),
+                    }
+                }
+                Event::UserEvent(UiEvent::PromptAnswered { window_id, answer }) => {
+                    if let Some(prompt) = prompts.remove(&window_id) {
+                        prompt.answer(answer);
+                    }
+                }
+                Event::UserEvent(UiEvent::Push { tab_id, token, scope, action }) => {
+                    // Subscriptions belong to the site the tab has loaded
+                    let origin = loaded_pages.get(&tab_id).cloned().unwrap_or_default();
+                    let push_manager = Arc::clone(&push_manager);
+                    let proxy = event_proxy.clone();
+                    handle.spawn(async move {
+                        let result = push_manager.page_request(&origin, &scope, action).await;
+                        if let Err(e) = &result {
+                            tracing::warn!("Push request from {} failed: {}", origin, e);
+                        }
+                        let _ = proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: push_settle_script(token, result),
+                        });
+                    });
+                }
+                Event::UserEvent(UiEvent::WebAuthn { tab_id, token, request }) => {
+                    // Credentials are bound to the page the tab has loaded, not the origin it claims
+                    let origin = loaded_pages
+                        .get(&tab_id)
+                        .map(|url| PermissionManager::normalize_origin(url))
+                        .unwrap_or_default();
+                    let webauthn = Arc::clone(&webauthn);
+                    let proxy = event_proxy.clone();
+                    // Security keys block until they are touched, passkeys until the user answers
+                    handle.spawn_blocking(move || {
+                        let result = match request {
+                            WebAuthnRequest::Create(mut request) => {
+                                request.origin = origin;
+                                webauthn
+                                    .create_credential(&request)
+                                    .and_then(|created| Ok(serde_json::to_value(created)?))
+                            }
+                            WebAuthnRequest::Get(mut request) => {
+                                request.origin = origin;
+                                webauthn
+                                    .get_assertion(&request)
+                                    .and_then(|assertion| Ok(serde_json::to_value(assertion)?))
+                            }
+                        };
+                        let _ = proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: webauthn_settle_script(token, result),
+                        });
+                    });
+                }
+                Event::UserEvent(UiEvent::CaptureRequest(request)) => {
+                    if let Some(window) = focused_window(&windows, &state) {
+                        let tab_id = tab_manager.create_tab(Some(request.url.clone()));
+                        if let Err(e) = window.navigate(&request.url) {
+                            tracing::warn!("Failed to open {}: {}", request.url, e);
+                        } else {
+                            pending_captures.insert(tab_id, request);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::PageLoaded { tab_id, url, title }) => {
+                    tab_manager.record_navigation(tab_id, &url, &title);
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                        if let Err(e) = window.set_zoom(tab_id, site_zoom.zoom_for(&url)) {
+                            tracing::warn!("Failed to zoom page: {}", e);
+                        }
+                    }
+                    if url.starts_with("http://") || url.starts_with("https://") {
+                        metrics.increment(PAGE_LOADS, 1);
+                    }
+                    if let Err(e) = speculative.record_visit(&url, VisitTransition::Link) {
+                        tracing::warn!("Failed to record visit for speculative loading: {}", e);
+                    }
+                    loaded_pages.insert(tab_id, url);
+                    tab_manager.apply_group_policy(tab_id);
+                    if let Some((x, y)) = pending_scrolls.remove(&tab_id) {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: format!("window.scrollTo({}, {});", x, y),
+                        });
+                    }
+                    if translator.config().enabled && !policies.feature_disabled("translate") {
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            if let Err(e) = window.eval_script(tab_id, COLLECT_TEXT_SCRIPT) {
+                                tracing::warn!("Failed to read page text: {}", e);
+                            }
+                        }
+                    }
+                    if let Some(request) = pending_captures.remove(&tab_id) {
+                        let capture_service = capture_service.clone();
+                        let error_reporter = error_reporter.clone();
+                        handle.spawn(async move {
+                            let screenshot = match request.full_page {
+                                true => capture_service.capture_full_page(tab_id).await,
+                                false => capture_service.capture_visible(tab_id).await,
+                            };
+                            let saved = screenshot
+                                .and_then(|screenshot| std::fs::write(&request.path, screenshot.png).map_err(WebxError::from));
+                            match saved {
+                                Ok(()) => tracing::info!("Saved screenshot of {} to {}", request.url, request.path.display()),
+                                Err(e) => {
+                                    error_reporter.report("screenshot", &e);
+                                }
+                            }
+                        });
+                    }
+                }
+                Event::UserEvent(UiEvent::LoadStarted(tab_id)) => {
+                    loaded_pages.remove(&tab_id);
+                    media_sniffer.clear_tab(tab_id);
+                    recording_tracker.remove_tab(tab_id);
+                    mixed_content.remove_tab(tab_id);
+                    find_in_page.close_tab(tab_id);
+                    load_started.insert(tab_id, Instant::now());
+                    scheduler.conditions_monitor().note_activity();
+                }
+                Event::UserEvent(UiEvent::LoadFinished { tab_id, url }) => {
+                    // Pages that never report in may have failed; find out why
+                    let web_page = url.starts_with("http://") || url.starts_with("https://");
+                    if let Some(started) = load_started.remove(&tab_id).filter(|_| web_page) {
+                        metrics.record_duration(PAGE_LOAD_TIME, started.elapsed());
+                    }
+                    if web_page && loaded_pages.get(&tab_id) != Some(&url) {
+                        let client = proxy_manager.lock_or_recover().client_for_url(&url);
+                        let proxy = event_proxy.clone();
+                        handle.spawn(async move {
+                            tokio::time::sleep(PAGE_REPORT_GRACE).await;
+                            let error = match client {
+                                Ok((client, route)) => error_pages::diagnose(&client, &route, &url).await,
+                                Err(e) => Some(NavigationError::from_request_error(&e)),
+                            };
+                            if let Some(error) = error {
+                                let _ = proxy.send_event(UiEvent::NavigationFailed(tab_id, error));
+                            }
+                        });
+                    }
+                }
+                Event::UserEvent(UiEvent::NavigationFailed(tab_id, error)) => {
+                    // The page may have come in while it was diagnosed
+                    if loaded_pages.get(&tab_id) != Some(&error.url) {
+                        tracing::warn!("Failed to load {}: {}", error.url, error.detail);
+                        let options = {
+                            let storage = offline_storage.lock_or_recover();
+                            ErrorPageOptions {
+                                cached_copy: storage.page_info(&error.url).map(|page| page.saved_at),
+                                offline_pages: storage.list_pages(),
+                            }
+                        };
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            if let Err(e) = window.show_html(tab_id, &error_pages::render_error_page(&error, &options)) {
+                                tracing::warn!("Failed to show error page: {}", e);
+                            }
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::OpenCachedCopy(tab_id, url)) => {
+                    let page = offline_storage.lock_or_recover().load_page(&url);
+                    match page {
+                        Ok(Some(page)) => {
+                            if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                                if let Err(e) = window.show_html(tab_id, &page.html_content) {
+                                    tracing::warn!("Failed to show cached copy: {}", e);
+                                }
+                            }
+                        }
+                        Ok(None) => tracing::warn!("No cached copy of {}", url),
+                        Err(e) => {
+                            error_reporter.report("offline", &e);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::TitleChanged(tab_id, title)) => {
+                    tab_manager.set_tab_title(tab_id, &title);
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                        if window.beside_tab_id() != Some(tab_id) {
+                            window.set_title(&window.title());
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::LeavingPage { tab_id, url, scroll }) => {
+                    tab_manager.save_scroll_position(tab_id, &url, scroll);
+                }
+                Event::UserEvent(UiEvent::Traverse(tab_id, offset)) => {
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                        match window.go_to_offset(tab_id, offset) {
+                            Ok(Some(entry)) => {
+                                if let Some(scroll) = entry.scroll_position {
+                                    pending_scrolls.insert(tab_id, scroll);
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => tracing::warn!("Failed to go through history: {}", e),
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::PageText(tab_id, page)) => {
+                    let decision = translator.decide(&page);
+                    page_texts.insert(tab_id, page.clone());
+                    match decision {
+                        TranslateDecision::Translate(language) => {
+                            spawn_translation(&handle, &translator, &event_proxy, tab_id, page, language.code);
+                        }
+                        TranslateDecision::Offer(language) => match translator.offer_script(&language) {
+                            Ok(script) => {
+                                let _ = event_proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                            }
+                            Err(e) => tracing::warn!("Failed to offer translation: {}", e),
+                        },
+                        TranslateDecision::Nothing => {}
+                    }
+                }
+                Event::UserEvent(UiEvent::TranslatePage { tab_id, remember }) => {
+                    if let Some(page) = page_texts.get(&tab_id).cloned() {
+                        if let Some(preference) = remember {
+                            error_reporter.check("translate", translator.set_site_preference(&page.url, Some(preference)));
+                        }
+                        if remember != Some(SitePreference::NeverTranslate) {
+                            // Let the service guess when the page's language is unclear
+                            let source = translator
+                                .detect_page_language(&page)
+                                .map(|language| language.code)
+                                .unwrap_or_else(|| "auto".to_string());
+                            spawn_translation(&handle, &translator, &event_proxy, tab_id, page, source);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::ShowOriginal(tab_id)) => {
+                    let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                        tab_id,
+                        script: RESTORE_TEXT_SCRIPT.to_string(),
+                    });
+                }
+                Event::UserEvent(UiEvent::TabSearch { tab_id, query, cycle }) => {
+                    let matches = tab_manager.search(&query);
+                    let selected = if cycle && query.is_empty() && matches.len() > 1 { 1 } else { 0 };
+                    match switcher_script(&matches, selected) {
+                        Ok(script) => {
+                            let _ = event_proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                        }
+                        Err(e) => tracing::warn!("Failed to show tab switcher: {}", e),
+                    }
+                }
+                Event::UserEvent(UiEvent::SwitchTab(tab_id)) => {
+                    if tab_manager.switch_to_tab(tab_id) {
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            if let Err(e) = window.show_active_tab() {
+                                tracing::warn!("Failed to show tab: {}", e);
+                            }
+                            window.window.set_focus();
+                        }
+                        // Switching to the tab beside the active one swaps the panes
+                        let window_id = state.lock_or_recover().window_of_tab(tab_id);
+                        if let Some(window_id) = window_id {
+                            sync_split(&mut windows, window_id);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::MoveTab { tab_id, window_id, index }) => {
+                    if tab_manager.transfer_tab(tab_id, window_id, index).is_none() {
+                        tracing::debug!("Tab {} cannot move to window {}", tab_id, window_id);
+                    }
+                }
+                Event::UserEvent(UiEvent::Tab(event)) => match event {
+                    TabEvent::Detached { window_id, .. } => {
+                        let on_screen = windows.iter().find(|(_, window)| window.window_id == window_id).map(|(id, _)| *id);
+                        if let Some(id) = on_screen {
+                            // A window left without tabs closes, unless it is the last one
+                            if tab_manager.get_window_tabs(window_id).is_empty() && windows.len() > 1 {
+                                windows.remove(&id);
+                                tab_manager.close_window(window_id);
+                            } else {
+                                if let Err(e) = windows[&id].show_active_tab() {
+                                    tracing::warn!("Failed to show tab: {}", e);
+                                }
+                                sync_split(&mut windows, window_id);
+                            }
+                        }
+                    }
+                    TabEvent::Attached { window_id, .. } => {
+                        if !windows.values().any(|window| window.window_id == window_id) {
+                            match open_window(target, window_id) {
+                                Ok(window) => {
+                                    windows.insert(window.window.id(), window);
+                                }
+                                Err(e) => tracing::warn!("Failed to open window: {}", e),
+                            }
+                        } else if let Some(window) = windows.values().find(|window| window.window_id == window_id) {
+                            if let Err(e) = window.show_active_tab() {
+                                tracing::warn!("Failed to show tab: {}", e);
+                            }
+                            window.window.set_focus();
+                            sync_split(&mut windows, window_id);
+                        }
+                    }
+                    _ => {}
+                },
+                Event::UserEvent(UiEvent::Split(window_id, request)) => {
+                    let changed = match request {
+                        SplitRequest::Toggle => {
+                            tab_manager.exit_split_view(window_id).is_some() || tab_manager.enter_split_view(window_id).is_some()
+                        }
+                        SplitRequest::Rotate => tab_manager.rotate_split_view(window_id).is_some(),
+                        SplitRequest::Resize(change) => tab_manager.resize_split_view(window_id, change).is_some(),
+                    };
+                    if changed {
+                        sync_split(&mut windows, window_id);
+                    }
+                }
+                Event::UserEvent(UiEvent::NewIdentity) => {
+                    let new_identity = Arc::clone(&new_identity);
+                    let prompter = prompter.clone();
+                    let proxy = event_proxy.clone();
+                    handle.spawn(async move {
+                        let prompt = Prompt::confirm(
+                            "New Identity",
+                            "Start over with a new identity? Cookies, cache and site data are cleared and open pages start over.",
+                            "Start Over",
+                        );
+                        if !prompter.ask(prompt).await.accepted {
+                            return;
+                        }
+                        let report = new_identity.run().await;
+                        let _ = proxy.send_event(UiEvent::IdentityRenewed(report));
+                    });
+                }
+                Event::UserEvent(UiEvent::IdentityRenewed(report)) => {
+                    for error in &report.errors {
+                        tracing::warn!("New identity left something behind: {}", error);
+                    }
+                    let window_ids: Vec<usize> = windows.values().map(|window| window.window_id).collect();
+                    for window_id in window_ids {
+                        if report.close_tabs {
+                            for closed in tab_manager.reset_window(window_id) {
+                                media_controller.remove_tab(closed);
+                                recording_tracker.remove_tab(closed);
+                                mixed_content.remove_tab(closed);
+                                csp_monitor.remove_tab(closed);
+                                find_in_page.close_tab(closed);
+                                bounce_protection.forget_tab(closed);
+                                media_sniffer.clear_tab(closed);
+                                capture_service.cancel_tab(closed);
+                                pending_captures.remove(&closed);
+                                page_texts.remove(&closed);
+                                pending_scrolls.remove(&closed);
+                                loaded_pages.remove(&closed);
+                                load_started.remove(&closed);
+                                page_feeds.remove(&closed);
+                                read_aloud.stop_tab(closed);
+                                context_menu.forget_tab(closed);
+                                offered_stale_tabs.remove(&closed);
+                            }
+                            sync_split(&mut windows, window_id);
+                        }
+                    }
+                    // The webviews keep cookies and storage of their own
+                    for window in windows.values() {
+                        if let Err(e) = window.clear_browsing_data() {
+                            tracing::warn!("Failed to clear browsing data: {}", e);
+                        }
+                        if let Err(e) = window.reload() {
+                            tracing::warn!("Failed to reload after a new identity: {}", e);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::GroupTabs) => {
+                    tracing::info!("Grouped {} tabs by domain", tab_manager.group_tabs_by_domain());
+                }
+                Event::UserEvent(UiEvent::CheckStaleTabs) => {
+                    let auto_close = state.lock_or_recover().settings.tab_policy.auto_close_stale;
+                    let stale: Vec<usize> = tab_manager.stale_tabs().iter().map(|tab| tab.tab_id).collect();
+                    let reviewing = tab_manager.get_tabs().iter().any(|tab| tab.url.starts_with(STALE_TABS_PAGE_URL));
+                    if auto_close && !reviewing && stale.iter().any(|tab_id| !offered_stale_tabs.contains(tab_id)) {
+                        offered_stale_tabs.extend(stale);
+                        let _ = event_proxy.send_event(UiEvent::OpenUrls(vec![STALE_TABS_PAGE_URL.to_string()]));
+                    }
+                }
+                Event::UserEvent(UiEvent::RefreshAccessibility) => {
+                    for window in windows.values() {
+                        let active = state.lock_or_recover().window_active_tab(window.window_id);
+                        for tab_id in active.into_iter().chain(window.beside_tab_id()) {
+                            if let Err(e) = window.eval_script(tab_id, ACCESSIBILITY_REFRESH_SCRIPT) {
+                                tracing::warn!("Failed to refresh accessibility options: {}", e);
+                            }
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::Zoom(tab_id, action)) => {
+                    let url = state.lock_or_recover().tabs.get(&tab_id).map(|tab| tab.url.clone()).unwrap_or_default();
+                    match site_zoom.apply(&url, action) {
+                        // Every page shown from the site follows its zoom
+                        Ok(zoom) => {
+                            let site = site_domain(&url);
+                            for window in windows.values() {
+                                let same_site: Vec<usize> = {
+                                    let state = state.lock_or_recover();
+                                    state
+                                        .window_active_tab(window.window_id)
+                                        .into_iter()
+                                        .chain(window.beside_tab_id())
+                                        .filter(|id| state.tabs.get(id).is_some_and(|tab| site_domain(&tab.url) == site))
+                                        .collect()
+                                };
+                                for shown_tab in same_site {
+                                    if let Err(e) = window.set_zoom(shown_tab, zoom) {
+                                        tracing::warn!("Failed to zoom page: {}", e);
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => tracing::info!("Not zooming {}: {}", url, e),
+                    }
+                }
+                Event::UserEvent(UiEvent::ToggleCaretBrowsing) => {
+                    let on = settings_registry.get_bool("keyboard.caret_browsing").unwrap_or(false);
+                    error_reporter.check("caret browsing", settings_registry.set("keyboard.caret_browsing", SettingValue::Bool(!on)));
+                }
+                Event::UserEvent(UiEvent::RefreshKeyboardNav) => {
+                    for window in windows.values() {
+                        let active = state.lock_or_recover().window_active_tab(window.window_id);
+                        for tab_id in active.into_iter().chain(window.beside_tab_id()) {
+                            if let Err(e) = window.eval_script(tab_id, KEYBOARD_NAV_REFRESH_SCRIPT) {
+                                tracing::warn!("Failed to refresh keyboard navigation: {}", e);
+                            }
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::StaleTabs(tab_id, request)) => {
+                    match request {
+                        StaleTabsRequest::Close(tab_ids) => {
+                            for closed in tab_manager.close_stale_tabs(&tab_ids) {
+                                media_controller.remove_tab(closed);
+                                recording_tracker.remove_tab(closed);
+                                mixed_content.remove_tab(closed);
+                                csp_monitor.remove_tab(closed);
+                                find_in_page.close_tab(closed);
+                                bounce_protection.forget_tab(closed);
+                                media_sniffer.clear_tab(closed);
+                                capture_service.cancel_tab(closed);
+                                pending_captures.remove(&closed);
+                                page_texts.remove(&closed);
+                                pending_scrolls.remove(&closed);
+                                loaded_pages.remove(&closed);
+                                load_started.remove(&closed);
+                                page_feeds.remove(&closed);
+                                read_aloud.stop_tab(closed);
+                                context_menu.forget_tab(closed);
+                                offered_stale_tabs.remove(&closed);
+                            }
+                        }
+                        StaleTabsRequest::Keep(tab_ids) => {
+                            tab_manager.keep_tabs(&tab_ids);
+                            for kept in &tab_ids {
+                                offered_stale_tabs.remove(kept);
+                            }
+                        }
+                    }
+                    let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                        tab_id,
+                        script: "location.reload()".to_string(),
+                    });
+                }
+                Event::UserEvent(UiEvent::Stats(tab_id, request)) => {
+                    let result = match request {
+                        StatsRequest::Enable => metrics.set_enabled(true),
+                        StatsRequest::Disable => metrics.set_enabled(false),
+                        StatsRequest::Clear => metrics.clear(),
+                    };
+                    error_reporter.check("metrics", result);
+                    let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                        tab_id,
+                        script: "location.reload()".to_string(),
+                    });
+                }
+                Event::UserEvent(UiEvent::Settings(tab_id, request)) => {
+                    let (key, result) = match request {
+                        SettingsRequest::Set { key, value } => {
+                            let result = settings_registry.set(&key, value);
+                            (key, result)
+                        }
+                        SettingsRequest::Reset(key) => {
+                            let result = settings_registry.reset(&key);
+                            (key, result)
+                        }
+                    };
+                    // Rejected values are shown next to the setting rather than as a toast
+                    let script = match result {
+                        Ok(()) => "location.reload()".to_string(),
+                        Err(e) => format!(
+                            "webxSettingError({}, {})",
+                            serde_json::to_string(&key).unwrap_or_default(),
+                            serde_json::to_string(&e.to_string()).unwrap_or_default()
+                        ),
+                    };
+                    let _ = event_proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                }
+                Event::UserEvent(UiEvent::EvalInTab { tab_id, script }) => {
+                    if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                        if let Err(e) = window.eval_script(tab_id, &script) {
+                            tracing::warn!("Failed to run script in tab: {}", e);
+                        }
+                    }
+                }
+                Event::UserEvent(UiEvent::ReadAloud(tab_id, request)) => match request {
+                    ReadAloudRequest::Toggle { html, url } => {
+                        if read_aloud.state().is_some_and(|(reading, _)| reading == tab_id) {
+                            read_aloud.toggle();
+                        } else if let Some(article) = reading_mode.extract_article(&html, &url) {
+                            // Read in reader mode so sentences can be highlighted
+                            if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                                if let Err(e) = window.show_html(tab_id, &reading_mode.generate_reader_html(&article)) {
+                                    tracing::warn!("Failed to show reader mode: {}", e);
+                                }
+                            }
+                            read_aloud.start(tab_id, reading_mode.tokens(&article));
+                        } else {
+                            tracing::info!("No article to read aloud on {}", url);
+                        }
+                    }
+                    ReadAloudRequest::Next => read_aloud.skip_forward(),
+                    ReadAloudRequest::Previous => read_aloud.skip_back(),
+                    ReadAloudRequest::Stop => read_aloud.stop(),
+                    ReadAloudRequest::Faster | ReadAloudRequest::Slower => {
+                        let step = if matches!(request, ReadAloudRequest::Faster) { 0.25 } else { -0.25 };
+                        read_aloud.set_rate(read_aloud.settings().rate + step);
+                        let mut state = state.lock_or_recover();
+                        state.settings.read_aloud = read_aloud.settings();
+                        error_reporter.check("settings", config.save_settings(&state.settings));
+                    }
+                },
+                Event::UserEvent(UiEvent::ReadAloudProgress(event)) => match event {
+                    ReadAloudEvent::Sentence { tab_id, token, .. } => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: reading_mode.highlight_script(Some(&token)),
+                        });
+                    }
+                    ReadAloudEvent::StateChanged { tab_id, state: ReadAloudState::Idle } => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: reading_mode.highlight_script(None),
+                        });
+                    }
+                    ReadAloudEvent::StateChanged { .. } => {}
+                    ReadAloudEvent::Error { message, .. } => {
+                        error_reporter.report("read aloud", &WebxError::Invalid(message));
+                    }
+                },
+                Event::UserEvent(UiEvent::ContextMenu(ContextMenuEvent { tab_id, action })) => match action {
+                    MenuAction::OpenInNewTab(url) => {
+                        let _ = event_proxy.send_event(UiEvent::OpenUrls(vec![url]));
+                    }
+                    MenuAction::SaveImage(url) => {
+                        let _ = event_proxy.send_event(UiEvent::Download(DownloadRequest::Url(url)));
+                    }
+                    MenuAction::SearchSelection(text) => {
+                        let url = state.lock_or_recover().settings.search_engine.search_url(text.trim());
+                        let _ = event_proxy.send_event(UiEvent::OpenUrls(vec![url]));
+                    }
+                    MenuAction::ReadAloud(Some(text)) => read_aloud.start(tab_id, reading_mode.text_tokens(&text)),
+                    MenuAction::ReadAloud(None) => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: READ_PAGE_ALOUD_SCRIPT.to_string(),
+                        });
+                    }
+                    MenuAction::PrintSelection => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: PRINT_SELECTION_SCRIPT.to_string(),
+                        });
+                    }
+                    MenuAction::Share(link) => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: share_menu_script(link.as_deref()),
+                        });
+                    }
+                    MenuAction::SaveImages => {
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: gallery_script(GALLERY_MIN_SIZE),
+                        });
+                    }
+                    MenuAction::Inspect => {
+                        if let Some(window) = window_showing_tab(&windows, &state, tab_id) {
+                            window.open_devtools(tab_id);
+                        }
+                    }
+                    MenuAction::Extension { extension_id, item_id, target } => {
+                        tracing::info!("Extension {} menu item {} picked on {}", extension_id, item_id, target.page_url);
+                    }
+                },
+                Event::UserEvent(UiEvent::Feeds(tab_id, request)) => match request {
+                    FeedsRequest::Detected { url, head } => {
+                        page_feeds.insert(tab_id, detect_feeds(&head, &url));
+                    }
+                    FeedsRequest::Subscribe => match page_feeds.get(&tab_id).and_then(|feeds| feeds.first()) {
+                        Some(feed) => {
+                            let feed_manager = feed_manager.clone();
+                            let error_reporter = error_reporter.clone();
+                            let url = feed.url.clone();
+                            handle.spawn(async move {
+                                match feed_manager.subscribe(&url).await {
+                                    Ok(feed) => tracing::info!("Subscribed to {}", feed.title),
+                                    Err(e) => {
+                                        error_reporter.report("feeds", &e);
+                                    }
+                                }
+                            });
+                        }
+                        None => tracing::info!("The page has no feed to subscribe to"),
+                    },
+                    FeedsRequest::Refresh => {
+                        let feed_manager = feed_manager.clone();
+                        let proxy = event_proxy.clone();
+                        handle.spawn(async move {
+                            for feed in feed_manager.feeds() {
+                                if let Err(e) = feed_manager.poll_feed(feed.id).await {
+                                    tracing::debug!("Failed to poll {}: {}", feed.url, e);
+                                }
+                            }
+                            let script = "location.reload()".to_string();
+                            let _ = proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                        });
+                    }
+                    request => {
+                        let changed = match request {
+                            FeedsRequest::MarkRead(item_id) => feed_manager.mark_read(item_id, true).map(|_| ()),
+                            FeedsRequest::MarkAllRead(feed_id) => feed_manager.mark_all_read(feed_id).map(|_| ()),
+                            FeedsRequest::Unsubscribe(feed_id) => feed_manager.unsubscribe(feed_id).map(|_| ()),
+                            _ => Ok(()),
+                        };
+                        error_reporter.check("feeds", changed);
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: "location.reload()".to_string(),
+                        });
+                    }
+                },
+                Event::UserEvent(UiEvent::Notes(tab_id, request)) => match request {
+                    NotesRequest::Clip { url, title, selection, html, tags } => {
+                        let note = match (selection, html) {
+                            (Some(text), _) => notebook.clip_selection(&url, &title, &text, &tags),
+                            (None, Some(html)) => notebook.clip_article(&reading_mode, &html, &url, &tags),
+                            (None, None) => Err(WebxError::Invalid("Nothing to clip".to_string())),
+                        };
+                        if let Some(note) = error_reporter.check("notes", note) {
+                            tracing::info!("Clipped {} into the notebook", note.title);
+                        }
+                    }
+                    NotesRequest::Delete(note_id) => {
+                        error_reporter.check("notes", notebook.delete(note_id));
+                        // Leave a deleted note's page; refresh the list otherwise
+                        let script = format!(
+                            "if (/\\/\\d+$/.test(location.pathname)) location.href = '{}'; else location.reload();",
+                            clipper::NOTES_PAGE_URL
+                        );
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                    }
+                    NotesRequest::Export(note_id) => {
+                        let download_dir = download_manager.download_dir();
+                        let exported = match note_id {
+                            Some(id) => {
+                                let path = download_dir.join(format!("note-{}.md", id));
+                                notebook.export_note(id, &path).map(|()| path)
+                            }
+                            None => {
+                                let path = download_dir.join("notebook.md");
+                                notebook.export_notebook(&path).map(|_| path)
+                            }
+                        };
+                        if let Some(path) = error_reporter.check("notes", exported) {
+                            tracing::info!("Exported notes to {}", path.display());
+                        }
+                    }
+                },
+                Event::UserEvent(UiEvent::ReadingList(tab_id, request)) => match request {
+                    ReadingListRequest::Save { url, title, html } => {
+                        let saved = reading_list.save(&reading_mode, &url, &title, Some(&html));
+                        if let Some(entry) = error_reporter.check("reading list", saved) {
+                            tracing::info!("Saved {} to the reading list", entry.title);
+                        }
+                    }
+                    ReadingListRequest::MarkRead(entry_id, read) => {
+                        error_reporter.check("reading list", reading_list.mark_read(entry_id, read));
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: "location.reload()".to_string(),
+                        });
+                    }
+                    ReadingListRequest::Remove(entry_id) => {
+                        error_reporter.check("reading list", reading_list.remove(entry_id));
+                        let _ = event_proxy.send_event(UiEvent::EvalInTab {
+                            tab_id,
+                            script: "location.reload()".to_string(),
+                        });
+                    }
+                },
+                Event::UserEvent(UiEvent::Capture(CaptureEvent::RunScript { tab_id, script })) => {
+                    // A tab in the background cannot render; fail its capture right away
+                    match window_showing_tab(&windows, &state, tab_id) {
+                        Some(window) => {
+                            if let Err(e) = window.eval_script(tab_id, &script) {
+                                tracing::warn!("Failed to capture tab: {}", e);
+                                capture_service.cancel_tab(tab_id);
+                            }
+                        }
+                        None => capture_service.cancel_tab(tab_id),
+                    }
+                }
+                Event::UserEvent(UiEvent::Tray(action)) => match action {
+                    TrayAction::ToggleWindow => {
+                        let visible = windows.values().any(|window| window.window.is_visible());
+                        for window in windows.values() {
+                            window.window.set_visible(!visible);
+                        }
+                        if let Some(window) = focused_window(&windows, &state).filter(|_| !visible) {
+                            window.window.set_focus();
+                        }
+                    }
+                    TrayAction::NewTab => {
+                        if let Some(window) = focused_window(&windows, &state) {
+                            tab_manager.create_tab(None);
+                            if let Err(e) = window.show_active_tab() {
+                                tracing::warn!("Failed to show tab: {}", e);
+                            }
+                            window.window.set_visible(true);
+                            window.window.set_focus();
+                        }
+                    }
+                    TrayAction::NewPrivateWindow => {
+                        tracing::warn!("Private windows are not supported yet");
+                    }
+                    TrayAction::NewIdentity => {
+                        let _ = event_proxy.send_event(UiEvent::NewIdentity);
+                    }
+                    TrayAction::OpenDownload(download_id) => {
+                        error_reporter.check("downloads", download_manager.open_download(download_id));
+                    }
+                    TrayAction::RetryDownload(download_id) => {
+                        let download_manager = download_manager.clone();
+                        let error_reporter = error_reporter.clone();
+                        handle.spawn(async move {
+                            error_reporter.check("downloads", download_manager.retry_download(download_id).await);
+                        });
+                    }
+                    TrayAction::Quit => quit = true,
+                },
+                Event::UserEvent(UiEvent::RefreshTray) => {
+                    if let Some(tray) = tray.as_mut() {
+                        let downloads = download_manager.get_downloads();
+                        let visible = windows.values().any(|window| window.window.is_visible());
+                        if let Err(e) = tray.refresh(&downloads, visible) {
+                            tracing::warn!("Failed to update tray icon: {}", e);
                         }
                     }
                 }
                 _ => {}
             }
+
+            if quit {
+                // Apply retention, then save state before closing
+                session_restore.stop_auto_save();
+                let mut retention_engine = retention_engine.lock_or_recover();
+                retention_engine.stop_daily_timer();
+                retention_engine.run(RetentionTrigger::Exit);
+                if let Ok(mut state) = state.lock() {
+                    retention_engine.prune_history_entries(&mut state.history, RetentionTrigger::Exit);
+                    error_reporter.check("session", session_restore.save_autosave(&state));
+                    error_reporter.check("settings", config.save_settings(&state.settings));
+                    error_reporter.check("bookmarks", config.save_bookmarks(&state.bookmarks));
+                    error_reporter.check("history", config.save_history(&state.history));
+                }
+                error_reporter.check("downloads", download_manager.save_downloads());
+                notification_manager.stop_download_notifications();
+                media_controller.stop_system_controls();
+                read_aloud.stop();
+                feed_manager.stop_polling();
+                scheduler.stop();
+                scheduler.conditions_monitor().stop();
+                error_reporter.check("metrics", metrics.flush());
+                error_reporter.check("privacy report", privacy_report.flush());
+                error_reporter.check("feeds", feed_manager.flush());
+                error_reporter.check("notes", notebook.flush());
+                error_reporter.check("reading list", reading_list.flush());
+                if let Some(instance) = single_instance.as_mut() {
+                    instance.stop_listening();
+                }
+                tray.take();
+                windows.clear();
+                *control_flow = ControlFlow::Exit;
+            }
         });
     }
 }
 
+// Private helper functions
+
+/// The window holding the focused window's tabs, or any window
+fn focused_window<'a>(
+    windows: &'a HashMap<WindowId, BrowserWindow>,
+    state: &Mutex<BrowserState>,
+) -> Option<&'a BrowserWindow> {
+    let focused = state.lock_or_recover().focused_window_id;
+    windows
+        .values()
+        .find(|window| Some(window.window_id) == focused)
+        .or_else(|| windows.values().next())
+}
+
+/// The window showing the given tab, as its active tab or beside it
+fn window_showing_tab<'a>(
+    windows: &'a HashMap<WindowId, BrowserWindow>,
+    state: &Mutex<BrowserState>,
+    tab_id: usize,
+) -> Option<&'a BrowserWindow> {
+    let state = state.lock_or_recover();
+    windows.values().find(|window| {
+        state.window_active_tab(window.window_id) == Some(tab_id) || window.beside_tab_id() == Some(tab_id)
+    })
+}
+
+/// Window title with the security, audio and recording indicators of a
+/// tab it shows
+fn indicated_title(
+    window: &BrowserWindow,
+    tab_id: usize,
+    media_controller: &MediaController,
+    recording_tracker: &RecordingTracker,
+    mixed_content: &MixedContentPolicy,
+) -> String {
+    let title = match mixed_content.indicator(tab_id) {
+        SecurityIndicator::ActiveMixedContent => format!("🔓 {}", window.title()),
+        SecurityIndicator::PassiveMixedContent => format!("⚠ {}", window.title()),
+        SecurityIndicator::Secure => window.title(),
+    };
+    let title = match media_controller.audio_indicator(tab_id) {
+        TabAudioIndicator::Playing => format!("🔊 {}", title),
+        TabAudioIndicator::Muted => format!("🔇 {}", title),
+        TabAudioIndicator::None => title,
+    };
+    match recording_tracker.indicator(tab_id) {
+        RecordingIndicator::CameraAndMicrophone => format!("🎥🎙 {}", title),
+        RecordingIndicator::Camera => format!("🎥 {}", title),
+        RecordingIndicator::Microphone => format!("🎙 {}", title),
+        RecordingIndicator::None => title,
+    }
+}
+
+/// Hand a link or file to its handler: a browser feature, or another
+/// application
+fn launch_handoff(
+    handoff: &Handoff,
+    protocol_handlers: &ProtocolHandlers,
+    feed_manager: &Arc<FeedManager>,
+    handle: &tokio::runtime::Handle,
+    error_reporter: &Arc<ErrorReporter>,
+) {
+    match handoff.handler {
+        HandlerTarget::Feature(BrowserFeature::Feeds) => {
+            let Some(url) = feed_scheme_url(&handoff.target) else {
+                tracing::info!("Ignoring feed link {}", handoff.target);
+                return;
+            };
+            let feed_manager = feed_manager.clone();
+            let error_reporter = error_reporter.clone();
+            handle.spawn(async move {
+                match feed_manager.subscribe(&url).await {
+                    Ok(feed) => tracing::info!("Subscribed to {}", feed.title),
+                    Err(e) => {
+                        error_reporter.report("feeds", &e);
+                    }
+                }
+            });
+        }
+        _ => {
+            error_reporter.check("protocols", protocol_handlers.launch(handoff));
+        }
+    }
+}
+
+/// Match a window's panes to its split view after its tabs changed
+fn sync_split(windows: &mut HashMap<WindowId, BrowserWindow>, window_id: usize) {
+    if let Some(window) = windows.values_mut().find(|window| window.window_id == window_id) {
+        if let Err(e) = window.sync_split() {
+            tracing::warn!("Failed to update split view: {}", e);
+        }
+    }
+}
+
+/// Translate a page's text in the background and put it in place
+fn spawn_translation(
+    handle: &tokio::runtime::Handle,
+    translator: &Arc<Translator>,
+    proxy: &EventLoopProxy<UiEvent>,
+    tab_id: usize,
+    page: PageText,
+    source: String,
+) {
+    let translator = Arc::clone(translator);
+    let proxy = proxy.clone();
+    handle.spawn(async move {
+        let script = match translator.translate(&page.texts, &source).await {
+            Ok(translations) => translator.replacement_script(&translations),
+            Err(e) => Err(e),
+        };
+        match script {
+            Ok(script) => {
+                let _ = proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+            }
+            Err(e) => tracing::warn!("Failed to translate {}: {}", page.url, e),
+        }
+    });
+}
+
+/// Remember where a window is, keeping the restored size while maximized
+fn record_geometry(state: &Mutex<BrowserState>, window: &BrowserWindow) {
+    let maximized = window.window.is_maximized();
+    let mut state = state.lock_or_recover();
+    let previous = state.windows.get(&window.window_id).and_then(|w| w.geometry);
+    let geometry = match (maximized, previous, window.window.outer_position()) {
+        (true, Some(previous), _) => WindowGeometry { maximized, ..previous },
+        (_, _, Ok(position)) => {
+            let size = window.window.inner_size();
+            WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+            }
+        }
+        _ => return,
+    };
+    state.set_window_geometry(window.window_id, geometry);
+}
+
 impl Default for BrowserApp {
     fn default() -> Self {
         Self::new().expect("Failed to create browser app")
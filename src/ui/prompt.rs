@@ -0,0 +1,314 @@
+// Browser prompts
+//
+// Questions the browser puts to the user in a small window of its own,
+// outside every page: a page can neither draw one nor answer it, as the
+// prompt's webview takes IPC messages from its own document only.
+use crate::features::security::webauthn::UserPresence;
+use crate::ui::UiEvent;
+use crate::utils::{script_literal, LockExt};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use tao::{
+    dpi::LogicalSize,
+    event_loop::{EventLoopProxy, EventLoopWindowTarget},
+    window::{Window, WindowBuilder, WindowId},
+};
+use tokio::sync::oneshot;
+use wry::{dpi, Rect, WebView, WebViewBuilder};
+
+/// Size of a prompt window, in logical pixels
+const PROMPT_WIDTH: u32 = 440;
+const PROMPT_HEIGHT: u32 = 220;
+
+/// What a prompt asks for besides yes or no
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptInput {
+    None,
+    /// A checkbox, such as "Always open mailto links with Mail"
+    Checkbox(String),
+    /// A password, such as the vault's master password
+    Password,
+}
+
+/// A question for the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub title: String,
+    pub message: String,
+    /// Label of the button agreeing
+    pub accept: String,
+    pub input: PromptInput,
+}
+
+impl Prompt {
+    /// Yes or no question
+    pub fn confirm(title: &str, message: &str, accept: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            message: message.to_string(),
+            accept: accept.to_string(),
+            input: PromptInput::None,
+        }
+    }
+
+    /// Offer a checkbox next to the question
+    pub fn with_checkbox(mut self, label: &str) -> Self {
+        self.input = PromptInput::Checkbox(label.to_string());
+        self
+    }
+
+    /// Ask for a password along with the answer
+    pub fn with_password(mut self) -> Self {
+        self.input = PromptInput::Password;
+        self
+    }
+}
+
+/// How the user answered. Closing the prompt declines it.
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PromptAnswer {
+    pub accepted: bool,
+    /// Whether the checkbox was ticked
+    pub checked: bool,
+    pub password: Option<String>,
+}
+
+impl std::fmt::Debug for PromptAnswer {
+    // Passwords stay out of logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptAnswer")
+            .field("accepted", &self.accepted)
+            .field("checked", &self.checked)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// Where a prompt's answer goes. Dropped unanswered, the asker is told the
+/// prompt was declined.
+#[derive(Clone)]
+pub struct PromptReply(Arc<Mutex<Option<oneshot::Sender<PromptAnswer>>>>);
+
+impl PromptReply {
+    fn send(&self, answer: PromptAnswer) {
+        if let Some(tx) = self.0.lock_or_recover().take() {
+            let _ = tx.send(answer);
+        }
+    }
+}
+
+impl std::fmt::Debug for PromptReply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PromptReply")
+    }
+}
+
+/// Puts prompts on screen from any thread, through the event loop
+#[derive(Clone, Default)]
+pub struct Prompter {
+    proxy: Arc<Mutex<Option<EventLoopProxy<UiEvent>>>>,
+}
+
+impl Prompter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show prompts through the event loop. Until then every prompt is
+    /// declined, as nobody could answer it.
+    pub fn connect(&self, proxy: EventLoopProxy<UiEvent>) {
+        *self.proxy.lock_or_recover() = Some(proxy);
+    }
+
+    /// Ask the user and wait for the answer
+    pub async fn ask(&self, prompt: Prompt) -> PromptAnswer {
+        match self.show(prompt) {
+            Some(answer) => answer.await.unwrap_or_default(),
+            None => PromptAnswer::default(),
+        }
+    }
+
+    /// Ask from a thread that may block, such as a blocking task. Never
+    /// call it on the event loop's thread, which shows the prompt.
+    pub fn ask_blocking(&self, prompt: Prompt) -> PromptAnswer {
+        match self.show(prompt) {
+            Some(answer) => answer.blocking_recv().unwrap_or_default(),
+            None => PromptAnswer::default(),
+        }
+    }
+
+    // Private helper methods
+
+    fn show(&self, prompt: Prompt) -> Option<oneshot::Receiver<PromptAnswer>> {
+        let proxy = self.proxy.lock_or_recover().clone()?;
+        let (tx, rx) = oneshot::channel();
+        let reply = PromptReply(Arc::new(Mutex::new(Some(tx))));
+        proxy.send_event(UiEvent::Prompt { prompt, reply }).ok()?;
+        Some(rx)
+    }
+}
+
+// Passkeys are created and used from blocking tasks, never the event loop
+impl UserPresence for Prompter {
+    fn confirm(&self, message: &str) -> bool {
+        self.ask_blocking(Prompt::confirm("Passkey", message, "Continue")).accepted
+    }
+
+    fn master_password(&self, message: &str) -> Option<String> {
+        let prompt = Prompt::confirm("Passkey", &format!("{} Enter your master password to continue.", message), "Continue")
+            .with_password();
+        let answer = self.ask_blocking(prompt);
+        answer.password.filter(|_| answer.accepted)
+    }
+}
+
+/// A prompt on screen. Dropping it closes the window.
+pub struct PromptWindow {
+    pub window: Window,
+    _webview: WebView,
+    reply: PromptReply,
+}
+
+impl PromptWindow {
+    /// Open a prompt in front of the browser's windows. Its answer comes
+    /// back to the event loop as `UiEvent::PromptAnswered`.
+    pub fn open(
+        target: &EventLoopWindowTarget<UiEvent>,
+        proxy: EventLoopProxy<UiEvent>,
+        prompt: &Prompt,
+        reply: PromptReply,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let window = WindowBuilder::new()
+            .with_title(&prompt.title)
+            .with_inner_size(LogicalSize::new(PROMPT_WIDTH, PROMPT_HEIGHT))
+            .with_resizable(false)
+            .with_always_on_top(true)
+            .build(target)?;
+        let window_id = window.id();
+        let webview = WebViewBuilder::new_as_child(&window)
+            .with_bounds(Rect {
+                position: dpi::LogicalPosition::new(0, 0).into(),
+                size: dpi::LogicalSize::new(PROMPT_WIDTH, PROMPT_HEIGHT).into(),
+            })
+            .with_html(prompt_html(prompt))
+            .with_ipc_handler(move |request| {
+                let answer = serde_json::from_str(request.body()).unwrap_or_default();
+                let _ = proxy.send_event(UiEvent::PromptAnswered { window_id, answer });
+            })
+            .build()?;
+        window.set_focus();
+        Ok(Self {
+            window,
+            _webview: webview,
+            reply,
+        })
+    }
+
+    /// Hand the answer to whoever asked, closing the prompt
+    pub fn answer(self, answer: PromptAnswer) {
+        self.reply.send(answer);
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+}
+
+// Private helper functions
+
+/// The prompt's document; texts are set as text, never parsed as markup
+fn prompt_html(prompt: &Prompt) -> String {
+    let (input, label) = match &prompt.input {
+        PromptInput::None => ("none", String::new()),
+        PromptInput::Checkbox(label) => ("checkbox", label.clone()),
+        PromptInput::Password => ("password", String::new()),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <style>
+        body {{ font: 14px system-ui, sans-serif; margin: 20px; color: #222; }}
+        p {{ margin: 0 0 14px; }}
+        label {{ display: block; margin-bottom: 14px; }}
+        input[type=password] {{ width: 100%; box-sizing: border-box; padding: 6px; margin-bottom: 14px; }}
+        .buttons {{ text-align: right; }}
+        button {{ margin-left: 8px; padding: 6px 16px; }}
+    </style>
+</head>
+<body>
+    <p id="message"></p>
+    <div id="input"></div>
+    <div class="buttons"><button id="cancel">Cancel</button><button id="accept"></button></div>
+    <script>
+        const input = {input};
+        document.getElementById('message').textContent = {message};
+        document.getElementById('accept').textContent = {accept};
+        const field = document.createElement('input');
+        if (input === 'checkbox') {{
+            const label = document.createElement('label');
+            field.type = 'checkbox';
+            label.append(field, ' ', {label});
+            document.getElementById('input').append(label);
+        }} else if (input === 'password') {{
+            field.type = 'password';
+            document.getElementById('input').append(field);
+        }}
+        const answer = (accepted) => window.ipc.postMessage(JSON.stringify({{
+            accepted,
+            checked: accepted && input === 'checkbox' && field.checked,
+            password: accepted && input === 'password' ? field.value : null,
+        }}));
+        document.getElementById('cancel').addEventListener('click', () => answer(false));
+        document.getElementById('accept').addEventListener('click', () => answer(true));
+        document.addEventListener('keydown', (e) => {{
+            if (e.key === 'Escape') answer(false);
+            if (e.key === 'Enter') answer(true);
+        }});
+        (input === 'password' ? field : document.getElementById('accept')).focus();
+    </script>
+</body>
+</html>"#,
+        input = script_literal(input),
+        message = script_literal(&prompt.message),
+        accept = script_literal(&prompt.accept),
+        label = script_literal(&label),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unanswered_prompts_decline() {
+        // Without an event loop nobody can answer, so the prompt declines
+        let prompter = Prompter::new();
+        let prompt = Prompt::confirm("Open", "Open this link?", "Open").with_checkbox("Always");
+        assert_eq!(prompter.ask_blocking(prompt), PromptAnswer::default());
+
+        let answer: PromptAnswer = serde_json::from_str(r#"{"accepted":true,"password":"hunter2"}"#).unwrap();
+        assert!(answer.accepted && !answer.checked);
+        assert!(!format!("{:?}", answer).contains("hunter2"));
+
+        // Only the first answer counts
+        let (tx, mut rx) = oneshot::channel();
+        let reply = PromptReply(Arc::new(Mutex::new(Some(tx))));
+        reply.clone().send(answer.clone());
+        reply.send(PromptAnswer::default());
+        assert_eq!(rx.try_recv().unwrap(), answer);
+    }
+
+    #[test]
+    fn test_page_text_stays_inside_the_prompt_script() {
+        let payload = "</script><script>window.ipc.postMessage(JSON.stringify({accepted:true}))</script>";
+        let prompt = Prompt::confirm("Passkey", &format!("Sign in to example.com as {}?", payload), "Continue")
+            .with_checkbox(payload);
+        let html = prompt_html(&prompt);
+        assert_eq!(html.matches("</script>").count(), 1);
+        assert_eq!(html.matches("<script>").count(), 1);
+        assert!(html.contains("\\u003c/script\\u003e\\u003cscript\\u003e"));
+    }
+}
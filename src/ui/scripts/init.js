@@ -5,18 +5,47 @@ console.log('🌐 WebX Browser initialized');
 console.log('Version 0.1.0');
 console.log('Official system browser for Ledokoz OS');
 
-// Setup IPC communication with the Rust backend
-window.ipc = {
-    send: function (message) {
-        window.ipc.postMessage(JSON.stringify(message));
-    }
-};
+// Setup IPC communication with the Rust backend. Messages are objects with a
+// `type`; a request also carries an `id`, and the browser settles it by
+// calling resolve or reject with that id.
+(function () {
+    const post = window.ipc.postMessage.bind(window.ipc);
+    const pending = new Map();
+    let nextId = 1;
+    const settle = function (id, outcome, value) {
+        const request = pending.get(id);
+        if (!request) return;
+        pending.delete(id);
+        request[outcome](value);
+    };
+    window.ipc = {
+        postMessage: post,
+        send: function (message) {
+            post(JSON.stringify(message));
+        },
+        request: function (message) {
+            const id = nextId++;
+            return new Promise(function (resolve, reject) {
+                pending.set(id, { resolve: resolve, reject: reject });
+                post(JSON.stringify(Object.assign({}, message, { id: id })));
+            });
+        },
+        resolve: function (id, value) {
+            settle(id, 'resolve', value);
+        },
+        reject: function (id, error) {
+            settle(id, 'reject', new Error(error));
+        }
+    };
+})();
 
 // Intercept navigation events
 window.addEventListener('beforeunload', function (e) {
     window.ipc.send({
         type: 'beforeunload',
-        url: window.location.href
+        url: window.location.href,
+        scroll_x: window.scrollX,
+        scroll_y: window.scrollY
     });
 });
 
@@ -42,14 +71,148 @@ titleObserver.observe(
     { subtree: true, characterData: true, childList: true }
 );
 
+// Mouse back and forward buttons
+window.addEventListener('mouseup', function (e) {
+    if (e.button === 3 || e.button === 4) {
+        e.preventDefault();
+        window.ipc.send({ type: 'traverse', offset: e.button === 3 ? -1 : 1 });
+    }
+});
+
 // Keyboard shortcuts
 document.addEventListener('keydown', function (e) {
+    // Alt + Left/Right: Back and forward
+    if (e.altKey && !e.shiftKey && !e.ctrlKey && (e.key === 'ArrowLeft' || e.key === 'ArrowRight')) {
+        e.preventDefault();
+        window.ipc.send({ type: 'traverse', offset: e.key === 'ArrowLeft' ? -1 : 1 });
+    }
+
     // Ctrl/Cmd + T: New tab
     if ((e.ctrlKey || e.metaKey) && e.key === 't') {
         e.preventDefault();
         window.ipc.send({ type: 'newtab' });
     }
 
+    // Ctrl/Cmd + N: New window
+    if ((e.ctrlKey || e.metaKey) && !e.shiftKey && e.key === 'n') {
+        e.preventDefault();
+        window.ipc.send({ type: 'newwindow' });
+    }
+
+    // Ctrl/Cmd + Shift + M: Move tab to a new window
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'm') {
+        e.preventDefault();
+        window.ipc.send({ type: 'movetabtonewwindow' });
+    }
+
+    // Ctrl/Cmd + Plus, Minus or 0: Zoom the site in, out or back; with Shift and 0: zoom text only
+    if ((e.ctrlKey || e.metaKey) && !e.altKey) {
+        const zoom = e.code === 'Digit0' && e.shiftKey ? 'textonly'
+            : e.key === '+' || e.key === '=' ? 'in'
+            : e.key === '-' ? 'out'
+            : e.key === '0' ? 'reset' : null;
+        if (zoom) {
+            e.preventDefault();
+            window.ipc.send({ type: 'zoom', action: zoom });
+        }
+    }
+
+    // Ctrl/Cmd + M: Mute tab
+    if ((e.ctrlKey || e.metaKey) && !e.shiftKey && e.key === 'm') {
+        e.preventDefault();
+        window.ipc.send({ type: 'mutetab' });
+    }
+
+    // Ctrl/Cmd + Shift + S: Screenshot, Alt for the whole page
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 's') {
+        e.preventDefault();
+        window.ipc.send({ type: 'screenshot', full_page: e.altKey });
+    }
+
+    // Ctrl/Cmd + Shift + U: Read aloud, or pause and resume
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'u') {
+        e.preventDefault();
+        window.ipc.send({
+            type: 'readaloud',
+            action: 'toggle',
+            html: document.documentElement.outerHTML,
+            url: window.location.href
+        });
+    }
+
+    // Alt + Shift + Arrows: Skip sentences and change the reading speed
+    if (e.altKey && e.shiftKey && e.key.startsWith('Arrow')) {
+        const actions = { ArrowRight: 'next', ArrowLeft: 'previous', ArrowUp: 'faster', ArrowDown: 'slower' };
+        e.preventDefault();
+        window.ipc.send({ type: 'readaloud', action: actions[e.key] });
+    }
+
+    // Ctrl/Cmd + Shift + F: Subscribe to the page's feed
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'f') {
+        e.preventDefault();
+        window.ipc.send({ type: 'subscribefeed' });
+    }
+
+    // Ctrl/Cmd + Shift + E: Show feeds
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'e') {
+        e.preventDefault();
+        window.ipc.send({ type: 'showfeeds' });
+    }
+
+    // Ctrl/Cmd + Shift + K: Clip the selection, or the article, into notes
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'k') {
+        e.preventDefault();
+        window.ipc.send({ type: 'clippage' });
+    }
+
+    // Ctrl/Cmd + Shift + L: Show notes
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'l') {
+        e.preventDefault();
+        window.ipc.send({ type: 'shownotes' });
+    }
+
+    // Ctrl/Cmd + Shift + D: Save the page for later
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'd') {
+        e.preventDefault();
+        window.ipc.send({ type: 'savepage' });
+    }
+
+    // Ctrl/Cmd + Shift + H: Show the reading list
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'h') {
+        e.preventDefault();
+        window.ipc.send({ type: 'showreadinglist' });
+    }
+
+    // Ctrl/Cmd + ,: Open settings
+    if ((e.ctrlKey || e.metaKey) && !e.shiftKey && e.key === ',') {
+        e.preventDefault();
+        window.ipc.send({ type: 'showsettings' });
+    }
+
+    // Ctrl/Cmd + Shift + G: Group tabs by domain
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'g') {
+        e.preventDefault();
+        window.ipc.send({ type: 'grouptabs' });
+    }
+
+    // Ctrl/Cmd + Shift + J: Review stale tabs
+    if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === 'j') {
+        e.preventDefault();
+        window.ipc.send({ type: 'reviewstaletabs' });
+    }
+
+    // Ctrl/Cmd + \: Split view, or back to one tab; with Shift: rotate the split
+    if ((e.ctrlKey || e.metaKey) && !e.altKey && e.code === 'Backslash') {
+        e.preventDefault();
+        window.ipc.send({ type: 'split', action: e.shiftKey ? 'rotate' : 'toggle' });
+    }
+
+    // Ctrl/Cmd + Alt + ] or [: Grow or shrink the active tab's split view pane
+    if ((e.ctrlKey || e.metaKey) && e.altKey && (e.code === 'BracketRight' || e.code === 'BracketLeft')) {
+        e.preventDefault();
+        window.ipc.send({ type: 'split', action: e.code === 'BracketRight' ? 'grow' : 'shrink' });
+    }
+
     // Ctrl/Cmd + W: Close tab
     if ((e.ctrlKey || e.metaKey) && e.key === 'w') {
         e.preventDefault();
@@ -79,34 +242,55 @@ document.addEventListener('keydown', function (e) {
     }
 
     // Alt + Left: Back
-    if (e.altKey && e.key === 'ArrowLeft') {
+    if (e.altKey && !e.shiftKey && e.key === 'ArrowLeft') {
         e.preventDefault();
         window.ipc.send({ type: 'back' });
     }
 
     // Alt + Right: Forward
-    if (e.altKey && e.key === 'ArrowRight') {
+    if (e.altKey && !e.shiftKey && e.key === 'ArrowRight') {
         e.preventDefault();
         window.ipc.send({ type: 'forward' });
     }
 });
 
-// Context menu customization
-document.addEventListener('contextmenu', function (e) {
-    const target = e.target;
-    const contextData = {
-        type: 'contextmenu',
-        x: e.clientX,
-        y: e.clientY,
-        hasLink: target.tagName === 'A',
-        hasImage: target.tagName === 'IMG',
-        hasSelection: window.getSelection().toString().length > 0,
-        link: target.tagName === 'A' ? target.href : null,
-        image: target.tagName === 'IMG' ? target.src : null,
-        selection: window.getSelection().toString()
-    };
+// Alt + drop: Download dropped links and images instead of opening them
+document.addEventListener('drop', function (e) {
+    if (!e.altKey || e.defaultPrevented || !e.dataTransfer) {
+        return;
+    }
+    const text = e.dataTransfer.getData('text/uri-list') || e.dataTransfer.getData('text/plain');
+    if (text) {
+        e.preventDefault();
+        window.ipc.send({ type: 'droplinks', text: text });
+    }
+}, true);
 
-    window.ipc.send(contextData);
+// Files a page makes itself (blob: and data: links with a download
+// attribute) never reach the network; hand their contents over instead
+document.addEventListener('click', function (e) {
+    const link = e.target.closest && e.target.closest('a[download]');
+    if (!link || e.defaultPrevented || !/^(blob|data):/.test(link.href)) {
+        return;
+    }
+    e.preventDefault();
+    fetch(link.href)
+        .then(function (response) { return response.blob(); })
+        .then(function (blob) {
+            const reader = new FileReader();
+            reader.onload = function () {
+                window.ipc.send({
+                    type: 'savedata',
+                    url: window.location.href,
+                    filename: link.getAttribute('download') || null,
+                    data: reader.result
+                });
+            };
+            reader.readAsDataURL(blob);
+        })
+        .catch(function (error) {
+            console.warn('WebX could not save the download:', error);
+        });
 });
 
 console.log('✅ WebX initialization complete');
@@ -0,0 +1,106 @@
+// System tray icon
+use crate::core::{Download, TraySettings};
+use crate::features::system::tray::{TrayAction, TrayMenuEntry, TrayModel};
+use crate::ui::UiEvent;
+use tao::event_loop::EventLoopProxy;
+use tray_icon::menu::{IsMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+/// Tray icon with the browser's quick actions
+pub struct BrowserTray {
+    tray_icon: TrayIcon,
+    model: TrayModel,
+    /// Menu, tooltip and icon last shown, to skip needless updates
+    shown: Option<(Vec<TrayMenuEntry>, String)>,
+}
+
+impl BrowserTray {
+    /// Create the tray icon. Menu clicks arrive as `UiEvent::Tray` events.
+    pub fn new(
+        settings: TraySettings,
+        downloads: &[Download],
+        window_visible: bool,
+        proxy: EventLoopProxy<UiEvent>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            if let Some(action) = TrayAction::from_id(event.id.as_ref()) {
+                let _ = proxy.send_event(UiEvent::Tray(action));
+            }
+        }));
+
+        let model = TrayModel::new(Some(settings));
+        let tray_icon = TrayIconBuilder::new()
+            .with_icon(tray_image(&model, downloads)?)
+            .with_tooltip(model.tooltip(downloads))
+            .with_menu(Box::new(build_menu(&model.menu(downloads, window_visible))?))
+            .build()?;
+
+        let mut tray = Self {
+            tray_icon,
+            model,
+            shown: None,
+        };
+        tray.refresh(downloads, window_visible)?;
+        Ok(tray)
+    }
+
+    /// Update the menu, tooltip and icon for the current state
+    pub fn refresh(&mut self, downloads: &[Download], window_visible: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let menu = self.model.menu(downloads, window_visible);
+        let tooltip = self.model.tooltip(downloads);
+        if let Some((shown_menu, shown_tooltip)) = &self.shown {
+            if *shown_menu == menu && *shown_tooltip == tooltip {
+                return Ok(());
+            }
+        }
+
+        self.tray_icon.set_icon(Some(tray_image(&self.model, downloads)?))?;
+        self.tray_icon.set_tooltip(Some(&tooltip))?;
+        self.tray_icon.set_menu(Some(Box::new(build_menu(&menu)?)));
+        self.shown = Some((menu, tooltip));
+        Ok(())
+    }
+
+    /// Tray settings
+    pub fn settings(&self) -> &TraySettings {
+        self.model.settings()
+    }
+}
+
+// Private helper functions
+
+fn tray_image(model: &TrayModel, downloads: &[Download]) -> Result<Icon, Box<dyn std::error::Error>> {
+    let image = model.icon(downloads);
+    Ok(Icon::from_rgba(image.rgba, image.width, image.height)?)
+}
+
+fn build_menu(entries: &[TrayMenuEntry]) -> Result<Menu, Box<dyn std::error::Error>> {
+    let menu = Menu::new();
+    for item in build_items(entries)? {
+        menu.append(item.as_ref())?;
+    }
+    Ok(menu)
+}
+
+fn build_items(entries: &[TrayMenuEntry]) -> Result<Vec<Box<dyn IsMenuItem>>, Box<dyn std::error::Error>> {
+    let mut items: Vec<Box<dyn IsMenuItem>> = Vec::new();
+    for entry in entries {
+        match entry {
+            TrayMenuEntry::Item { action, label } => {
+                items.push(Box::new(MenuItem::with_id(action.id(), label, true, None)));
+            }
+            TrayMenuEntry::Label(label) => {
+                items.push(Box::new(MenuItem::new(label, false, None)));
+            }
+            TrayMenuEntry::Submenu { label, entries } => {
+                let submenu = Submenu::new(label, true);
+                for item in build_items(entries)? {
+                    submenu.append(item.as_ref())?;
+                }
+                items.push(Box::new(submenu));
+            }
+            TrayMenuEntry::Separator => items.push(Box::new(PredefinedMenuItem::separator())),
+        }
+    }
+    Ok(items)
+}
@@ -1,80 +1,198 @@
 // Browser window implementation
-use crate::core::BrowserState;
-use crate::config::ConfigManager;
+use crate::core::{BrowserState, NavigationEntry, SplitView};
+use crate::config::policy::{is_policy_page, render_policy_page};
+use crate::config::{is_settings_page, render_settings_page, ConfigManager, PolicySet, SettingsRegistry};
 use crate::features::{TabManager, DownloadManager, PrivacyProtection};
+use crate::features::tabs::{self, TAB_SWITCHER_SCRIPT};
+use crate::features::ui::context_menu::CONTEXT_MENU_SCRIPT;
 use crate::features::ui::themes::ThemeManager;
+use crate::features::downloads::MEDIA_SNIFF_SCRIPT;
+use crate::features::media::{AutoplayBlocker, MEDIA_OBSERVER_SCRIPT};
+use crate::error::WebxError;
+use crate::features::feeds::{self, FeedManager, FeedsPage, FEED_DETECT_SCRIPT};
+use crate::features::productivity::clipper::{self, Notebook, NotesPage};
+use crate::features::productivity::printing::PRINT_SCRIPT;
+use crate::features::productivity::gallery::GALLERY_SCRIPT;
+use crate::features::productivity::keyboard_nav::KEYBOARD_NAV_SCRIPT;
+use crate::features::history_manager::FORM_HISTORY_SCRIPT;
+use crate::features::ui::themes::ACCESSIBILITY_SCRIPT;
+use crate::features::productivity::share::SHARE_SCRIPT;
+use crate::features::security::clipboard::CLIPBOARD_SCRIPT;
+use crate::features::security::mixed_content::MIXED_CONTENT_SCRIPT;
+use crate::features::security::privacy::{self, BounceProtection, FingerprintShield, PrivacyReport};
+use crate::features::security::permissions::recording::RECORDING_SCRIPT;
+use crate::features::security::safe_browsing::{self, NavigationDecision, SafeBrowsing};
+use crate::features::security::webauthn::WEBAUTHN_SCRIPT;
+use crate::features::security::CspMonitor;
+use crate::features::system::notifications::PUSH_SCRIPT;
+use crate::features::productivity::reading_list::{self, ReadingList, ReadingListPage};
+use crate::features::ui::reader::ReadingMode;
+use crate::features::ui::FindInPage;
+use crate::features::ui::zoom::{PageZoom, TEXT_ZOOM_SCRIPT};
+use crate::features::caching::SpeculativeLoader;
+use crate::features::system::metrics::{self, Metrics};
+use crate::features::system::protocol_handlers::ProtocolHandlers;
+use crate::features::system::proxy::{ProxyManager, ProxyRoute, ProxyType};
 use crate::ui::menu::build_menu;
+use crate::ipc::{IpcContext, IpcRouter};
+use crate::ui::{UiEvent, PRIVACY_PAGE_DAYS, STATS_PAGE_DAYS};
+use crate::utils::LockExt;
 use std::sync::{Arc, Mutex};
 use tao::{
-    dpi::LogicalSize,
-    event_loop::EventLoop,
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event_loop::{EventLoopProxy, EventLoopWindowTarget},
     window::{Window, WindowBuilder},
 };
-use wry::{WebView, WebViewBuilder};
+use wry::http::{header::CONTENT_TYPE, Response, StatusCode};
+use wry::{dpi, PageLoadEvent, Rect, WebView, WebViewBuilder};
 
 /// Main browser window
 pub struct BrowserWindow {
+    /// ID of the window in `BrowserState::windows`
+    pub window_id: usize,
     pub window: Window,
+    /// Pane showing the window's active tab
     pub webview: WebView,
+    /// Pane showing the tab beside the active one in split view
+    pub beside: Option<WebView>,
+    beside_tab_id: Option<usize>,
+    panes: PaneContext,
+    pub services: Arc<WindowServices>,
+    pub menu: crate::ui::menu::MenuBar,
+}
+
+/// The browser's managers, shared by every window
+pub struct WindowServices {
     pub state: Arc<Mutex<BrowserState>>,
     pub config: Arc<ConfigManager>,
     pub tab_manager: Arc<TabManager>,
     pub download_manager: Arc<DownloadManager>,
     pub privacy_protection: Arc<PrivacyProtection>,
+    pub bounce_protection: Arc<BounceProtection>,
+    pub fingerprint_shield: Arc<FingerprintShield>,
+    pub privacy_report: Arc<PrivacyReport>,
+    pub safe_browsing: Arc<SafeBrowsing>,
+    pub csp_monitor: Arc<CspMonitor>,
+    pub find_in_page: Arc<FindInPage>,
     pub theme_manager: Arc<ThemeManager>,
-    pub menu: crate::ui::menu::MenuBar,
+    pub proxy_manager: Arc<Mutex<ProxyManager>>,
+    pub autoplay_blocker: Arc<AutoplayBlocker>,
+    pub feed_manager: Arc<FeedManager>,
+    pub notebook: Arc<Notebook>,
+    pub reading_list: Arc<ReadingList>,
+    pub metrics: Arc<Metrics>,
+    pub speculative: Arc<SpeculativeLoader>,
+    pub protocol_handlers: Arc<ProtocolHandlers>,
+    pub settings_registry: Arc<SettingsRegistry>,
+    pub policies: Arc<PolicySet>,
+    pub ipc_router: Arc<IpcRouter>,
 }
 
 impl BrowserWindow {
-    /// Create a new browser window
+    /// Create a new browser window showing the tabs of a window in the
+    /// browser state, with its saved geometry. Window requests from the
+    /// page go to the event loop through `proxy`.
     pub fn new(
-        event_loop: &EventLoop<()>,
-        state: Arc<Mutex<BrowserState>>,
-        config: Arc<ConfigManager>,
-        tab_manager: Arc<TabManager>,
-        download_manager: Arc<DownloadManager>,
-        privacy_protection: Arc<PrivacyProtection>,
-        theme_manager: Arc<ThemeManager>,
+        event_loop: &EventLoopWindowTarget<UiEvent>,
+        proxy: EventLoopProxy<UiEvent>,
+        window_id: usize,
+        services: Arc<WindowServices>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         
-        // Create the window
-        let window = WindowBuilder::new()
+        // Get the initial URL, opening the home page in an empty window
+        let (initial_url, geometry) = {
+            let mut state_lock = services.state.lock_or_recover();
+            let active_url = state_lock
+                .window_active_tab(window_id)
+                .and_then(|id| state_lock.tabs.get(&id))
+                .map(|tab| tab.url.clone());
+            let url = match active_url {
+                Some(url) => url,
+                None => {
+                    let url = state_lock.settings.home_page.clone();
+                    state_lock.add_tab_to_window(window_id, url.clone());
+                    url
+                }
+            };
+            let geometry = state_lock.windows.get(&window_id).and_then(|window| window.geometry);
+            (url, geometry)
+        };
+
+        // Create the window where it was last time
+        let mut builder = WindowBuilder::new()
             .with_title("WebX Browser - Ledokoz OS")
             .with_inner_size(LogicalSize::new(1280, 800))
-            .with_min_inner_size(LogicalSize::new(800, 600))
-            .build(event_loop)?;
+            .with_min_inner_size(LogicalSize::new(800, 600));
+        if let Some(geometry) = geometry {
+            builder = builder
+                .with_position(PhysicalPosition::new(geometry.x, geometry.y))
+                .with_inner_size(PhysicalSize::new(geometry.width, geometry.height))
+                .with_maximized(geometry.maximized);
+        }
+        let window = builder.build(event_loop)?;
 
         // Build the menu
         let menu = build_menu();
 
-        // Get the initial URL
-        let initial_url = {
-            let mut state_lock = state.lock().unwrap();
-            let url = state_lock.settings.home_page.clone();
-            state_lock.add_tab(url.clone());
-            url
+        // The webview takes a single proxy without credentials, so it follows
+        // the route of the start page; downloads are routed per URL
+        let route = services.proxy_manager.lock_or_recover().route_for_url(&initial_url);
+        let webview_proxy = match &route {
+            ProxyRoute::Proxy { config, .. } if config.auth.is_none() => {
+                let endpoint = wry::ProxyEndpoint {
+                    host: config.host.clone(),
+                    port: config.port.to_string(),
+                };
+                match config.proxy_type {
+                    ProxyType::Http => Some(wry::ProxyConfig::Http(endpoint)),
+                    ProxyType::Socks5 => Some(wry::ProxyConfig::Socks5(endpoint)),
+                    _ => None,
+                }
+            }
+            _ => None,
         };
+        if webview_proxy.is_none() && route != ProxyRoute::Direct {
+            tracing::warn!("Web content cannot use proxy {}, loading directly", route.describe());
+        }
 
-        // Build the webview with custom HTML UI
-        let webview = WebViewBuilder::new(&window)
-            .with_url(&initial_url)
-            .with_devtools(true)
-            .with_initialization_script(include_str!("scripts/init.js"))
-            .with_ipc_handler(move |request| {
-                // Handle IPC messages from the webview
-                tracing::info!("IPC message: {}", request.body());
-            })
-            .build()?;
+        // Each pane is a webview of its own inside the window
+        let panes = PaneContext {
+            window_id,
+            services: Arc::clone(&services),
+            proxy,
+            autoplay_script: services.autoplay_blocker.page_script(),
+            fingerprint_script: services
+                .privacy_protection
+                .get_anti_fingerprinting_script(&services.fingerprint_shield.config()),
+            preconnect_script: services.speculative.page_script(),
+            csp_script: services.csp_monitor.get_monitor_script(),
+            find_script: services.find_in_page.get_find_script(),
+            webview_proxy,
+            handle: tokio::runtime::Handle::current(),
+        };
+        let (split, beside_url) = {
+            let state_lock = services.state.lock_or_recover();
+            let split = state_lock.windows.get(&window_id).and_then(|window| window.split);
+            let beside_url = split
+                .and_then(|split| state_lock.tabs.get(&split.secondary_tab_id))
+                .map(|tab| tab.url.clone());
+            (split, beside_url)
+        };
+        let [main_bounds, beside_bounds] = layout(&window, split);
+        let webview = panes.build(&window, Pane::Main, &initial_url, main_bounds)?;
+        let beside = match beside_url {
+            Some(url) => Some(panes.build(&window, Pane::Beside, &url, beside_bounds)?),
+            None => None,
+        };
 
         Ok(Self {
+            window_id,
             window,
             webview,
-            state,
-            config,
-            tab_manager,
-            download_manager,
-            privacy_protection,
-            theme_manager,
+            beside,
+            beside_tab_id: split.map(|split| split.secondary_tab_id),
+            panes,
+            services,
             menu,
         })
     }
@@ -84,8 +202,9 @@ impl BrowserWindow {
         self.webview.load_url(url)?;
         
         // Update state
-        if let Ok(mut state) = self.state.lock() {
-            if let Some(tab) = state.active_tab_mut() {
+        if let Ok(mut state) = self.services.state.lock() {
+            let active = state.window_active_tab(self.window_id);
+            if let Some(tab) = active.and_then(|id| state.tabs.get_mut(&id)) {
                 tab.url = url.to_string();
                 tab.is_loading = true;
             }
@@ -94,33 +213,124 @@ impl BrowserWindow {
         Ok(())
     }
 
-    /// Go back in history
-    pub fn go_back(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // WebView doesn't expose history navigation directly
-        // We'll need to implement our own history management
+    /// Show generated HTML, e.g. reader mode, in place of a shown tab's
+    /// page. The document is rewritten so the page's IPC bridge stays.
+    pub fn show_html(&self, tab_id: usize, html: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let html = serde_json::to_string(html)?;
+        self.tab_webview(tab_id)
+            .evaluate_script(&format!("document.open(); document.write({}); document.close();", html))?;
         Ok(())
     }
 
-    /// Go forward in history
-    pub fn go_forward(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // WebView doesn't expose history navigation directly
-        // We'll need to implement our own history management
+    /// Load this window's active tab, e.g. after a tab moved away
+    pub fn show_active_tab(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let url = self.services.state.lock().ok().and_then(|state| {
+            state
+                .window_active_tab(self.window_id)
+                .and_then(|id| state.tabs.get(&id))
+                .map(|tab| tab.url.clone())
+        });
+        if let Some(url) = url {
+            self.webview.load_url(&url)?;
+        }
         Ok(())
     }
 
+    /// Go back a page in this window's active tab
+    pub fn go_back(&self) -> Result<Option<NavigationEntry>, Box<dyn std::error::Error>> {
+        self.go_to_offset_in_active_tab(-1)
+    }
+
+    /// Go forward a page in this window's active tab
+    pub fn go_forward(&self) -> Result<Option<NavigationEntry>, Box<dyn std::error::Error>> {
+        self.go_to_offset_in_active_tab(1)
+    }
+
+    /// Move through a shown tab's history by a number of pages, negative
+    /// going back, loading the page reached in the tab's pane
+    pub fn go_to_offset(&self, tab_id: usize, offset: isize) -> Result<Option<NavigationEntry>, Box<dyn std::error::Error>> {
+        let Some(entry) = self.services.tab_manager.go_to_offset(tab_id, offset) else {
+            return Ok(None);
+        };
+        self.tab_webview(tab_id).load_url(&entry.url)?;
+        Ok(Some(entry))
+    }
+
     /// Reload the current page
     pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Ok(state) = self.state.lock() {
-            if let Some(tab) = state.active_tab() {
-                self.webview.load_url(&tab.url)?;
-            }
+        self.show_active_tab()
+    }
+
+    /// Drop the cookies, cache and storage of this window's panes
+    pub fn clear_browsing_data(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.webview.clear_all_browsing_data()?;
+        if let Some(beside) = &self.beside {
+            beside.clear_all_browsing_data()?;
         }
         Ok(())
     }
 
-    /// Execute JavaScript in the webview
-    pub fn eval_script(&self, script: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.webview.evaluate_script(script)?;
+    /// Execute JavaScript in the pane showing a tab, or the active tab's pane
+    pub fn eval_script(&self, tab_id: usize, script: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.tab_webview(tab_id).evaluate_script(script)?;
+        Ok(())
+    }
+
+    /// Zoom the pane showing a tab: the whole page, or only its text
+    pub fn set_zoom(&self, tab_id: usize, zoom: PageZoom) -> Result<(), Box<dyn std::error::Error>> {
+        let webview = self.tab_webview(tab_id);
+        webview.zoom(zoom.webview_scale())?;
+        webview.evaluate_script(&zoom.text_script())?;
+        Ok(())
+    }
+
+    /// Open the developer tools of the pane showing a tab
+    pub fn open_devtools(&self, tab_id: usize) {
+        self.tab_webview(tab_id).open_devtools();
+    }
+
+    /// Tab the pane beside the active tab shows in split view
+    pub fn beside_tab_id(&self) -> Option<usize> {
+        self.beside_tab_id
+    }
+
+    /// Open, close or reload the pane beside the active tab to match the
+    /// window's split view, and fit the panes to the window
+    pub fn sync_split(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (split, url) = {
+            let state = self.services.state.lock_or_recover();
+            let split = state.windows.get(&self.window_id).and_then(|window| window.split);
+            let url = split
+                .and_then(|split| state.tabs.get(&split.secondary_tab_id))
+                .map(|tab| tab.url.clone());
+            (split, url)
+        };
+        let [_, beside_bounds] = layout(&self.window, split);
+        match (split, url) {
+            (Some(split), Some(url)) => {
+                match &self.beside {
+                    Some(beside) if self.beside_tab_id != Some(split.secondary_tab_id) => beside.load_url(&url)?,
+                    Some(_) => {}
+                    None => self.beside = Some(self.panes.build(&self.window, Pane::Beside, &url, beside_bounds)?),
+                }
+                self.beside_tab_id = Some(split.secondary_tab_id);
+            }
+            _ => {
+                self.beside = None;
+                self.beside_tab_id = None;
+            }
+        }
+        self.layout_panes()
+    }
+
+    /// Fit the panes to the window, e.g. after it was resized
+    pub fn layout_panes(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let split = self.services.state.lock_or_recover().windows.get(&self.window_id).and_then(|window| window.split);
+        let [main_bounds, beside_bounds] = layout(&self.window, split.filter(|_| self.beside.is_some()));
+        self.webview.set_bounds(pane_rect(main_bounds))?;
+        if let Some(beside) = &self.beside {
+            beside.set_bounds(pane_rect(beside_bounds))?;
+        }
         Ok(())
     }
 
@@ -131,11 +341,257 @@ impl BrowserWindow {
 
     /// Get the window title
     pub fn title(&self) -> String {
-        if let Ok(state) = self.state.lock() {
-            if let Some(tab) = state.active_tab() {
+        if let Ok(state) = self.services.state.lock() {
+            let active = state.window_active_tab(self.window_id);
+            if let Some(tab) = active.and_then(|id| state.tabs.get(&id)) {
                 return format!("{} - WebX Browser", tab.title);
             }
         }
         "WebX Browser".to_string()
     }
+
+    // Private helper methods
+
+    fn go_to_offset_in_active_tab(&self, offset: isize) -> Result<Option<NavigationEntry>, Box<dyn std::error::Error>> {
+        let active = self.services.state.lock_or_recover().window_active_tab(self.window_id);
+        match active {
+            Some(tab_id) => self.go_to_offset(tab_id, offset),
+            None => Ok(None),
+        }
+    }
+
+    fn tab_webview(&self, tab_id: usize) -> &WebView {
+        match &self.beside {
+            Some(beside) if self.beside_tab_id == Some(tab_id) => beside,
+            _ => &self.webview,
+        }
+    }
+}
+
+/// A pane of a window: the active tab's, or the one beside it in split view
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pane {
+    Main,
+    Beside,
+}
+
+/// What a window's panes share, for building them
+struct PaneContext {
+    window_id: usize,
+    services: Arc<WindowServices>,
+    proxy: EventLoopProxy<UiEvent>,
+    autoplay_script: String,
+    fingerprint_script: String,
+    preconnect_script: String,
+    csp_script: String,
+    find_script: String,
+    webview_proxy: Option<wry::ProxyConfig>,
+    handle: tokio::runtime::Handle,
+}
+
+impl PaneContext {
+    /// Build the webview of a pane with custom HTML UI. Window requests
+    /// from the page go to the event loop.
+    fn build(&self, window: &Window, pane: Pane, url: &str, bounds: PaneBounds) -> Result<WebView, wry::Error> {
+        let window_id = self.window_id;
+        let ipc_state = Arc::clone(&self.services.state);
+        let ipc_router = Arc::clone(&self.services.ipc_router);
+        let protocol_feeds = Arc::clone(&self.services.feed_manager);
+        let protocol_notes = Arc::clone(&self.services.notebook);
+        let protocol_reading_list = Arc::clone(&self.services.reading_list);
+        let protocol_state = Arc::clone(&self.services.state);
+        let protocol_tabs = Arc::clone(&self.services.tab_manager);
+        let protocol_metrics = Arc::clone(&self.services.metrics);
+        let protocol_privacy = Arc::clone(&self.services.privacy_report);
+        let protocol_settings = Arc::clone(&self.services.settings_registry);
+        let protocol_policies = Arc::clone(&self.services.policies);
+        let protocol_safe_browsing = Arc::clone(&self.services.safe_browsing);
+        let load_state = Arc::clone(&self.services.state);
+        let load_proxy = self.proxy.clone();
+        let nav_handlers = Arc::clone(&self.services.protocol_handlers);
+        let nav_bounces = Arc::clone(&self.services.bounce_protection);
+        let nav_safe_browsing = Arc::clone(&self.services.safe_browsing);
+        let nav_handle = self.handle.clone();
+        let nav_state = Arc::clone(&self.services.state);
+        let nav_proxy = self.proxy.clone();
+        let handle = self.handle.clone();
+        let proxy = self.proxy.clone();
+        let mut builder = WebViewBuilder::new_as_child(window).with_bounds(pane_rect(bounds));
+        if let Some(proxy) = self.webview_proxy.clone() {
+            builder = builder.with_proxy_config(proxy);
+        }
+        builder
+            .with_url(url)
+            .with_devtools(!self.services.policies.feature_disabled("devtools"))
+            .with_initialization_script(include_str!("scripts/init.js"))
+            .with_initialization_script(MEDIA_OBSERVER_SCRIPT)
+            .with_initialization_script(MEDIA_SNIFF_SCRIPT)
+            .with_initialization_script(&self.autoplay_script)
+            .with_initialization_script(&self.fingerprint_script)
+            .with_initialization_script(&self.preconnect_script)
+            .with_initialization_script(&self.csp_script)
+            .with_initialization_script(&self.find_script)
+            .with_initialization_script(FEED_DETECT_SCRIPT)
+            .with_initialization_script(TAB_SWITCHER_SCRIPT)
+            .with_initialization_script(CONTEXT_MENU_SCRIPT)
+            .with_initialization_script(PRINT_SCRIPT)
+            .with_initialization_script(SHARE_SCRIPT)
+            .with_initialization_script(GALLERY_SCRIPT)
+            .with_initialization_script(FORM_HISTORY_SCRIPT)
+            .with_initialization_script(ACCESSIBILITY_SCRIPT)
+            .with_initialization_script(KEYBOARD_NAV_SCRIPT)
+            .with_initialization_script(TEXT_ZOOM_SCRIPT)
+            .with_initialization_script(RECORDING_SCRIPT)
+            .with_initialization_script(CLIPBOARD_SCRIPT)
+            .with_initialization_script(MIXED_CONTENT_SCRIPT)
+            .with_initialization_script(PUSH_SCRIPT)
+            .with_initialization_script(WEBAUTHN_SCRIPT)
+            .with_navigation_handler(move |url| {
+                // Links another application handles leave the page where it is
+                let Some(tab_id) = pane_tab(&nav_state.lock_or_recover(), window_id, pane) else {
+                    return !nav_handlers.claims_url(&url);
+                };
+                if nav_handlers.claims_url(&url) {
+                    let _ = nav_proxy.send_event(UiEvent::ExternalLink { tab_id, url });
+                    return false;
+                }
+                // Listed sites never load; their blocked event brings up the warning page
+                match nav_safe_browsing.check_navigation_now(tab_id, &url) {
+                    Some(NavigationDecision::Allow) => {}
+                    Some(NavigationDecision::Block { .. }) => return false,
+                    None => {
+                        // Held until the lookup online decides
+                        let safe_browsing = Arc::clone(&nav_safe_browsing);
+                        let proxy = nav_proxy.clone();
+                        nav_handle.spawn(async move {
+                            if safe_browsing.check_navigation(tab_id, &url).await == NavigationDecision::Allow {
+                                let script = format!("location.assign({});", serde_json::Value::from(url));
+                                let _ = proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                            }
+                        });
+                        return false;
+                    }
+                }
+                // Tracking redirects are skipped, leaving no entry in the tab's history
+                match nav_bounces.check_navigation(tab_id, &url) {
+                    Some(destination) => {
+                        let script = format!("location.replace({});", serde_json::Value::from(destination));
+                        let _ = nav_proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .with_on_page_load_handler(move |event, url| {
+                // Loads that finish without the page reporting in are checked for errors
+                let Some(tab_id) = pane_tab(&load_state.lock_or_recover(), window_id, pane) else {
+                    return;
+                };
+                let event = match event {
+                    PageLoadEvent::Started => UiEvent::LoadStarted(tab_id),
+                    PageLoadEvent::Finished => UiEvent::LoadFinished { tab_id, url },
+                };
+                let _ = load_proxy.send_event(event);
+            })
+            .with_asynchronous_custom_protocol("webx".into(), move |request, responder| {
+                // Internal pages; article pages are fetched, so render off the UI thread
+                let url = request.uri().to_string();
+                let feed_manager = Arc::clone(&protocol_feeds);
+                let notebook = Arc::clone(&protocol_notes);
+                let reading_list = Arc::clone(&protocol_reading_list);
+                let browser_state = Arc::clone(&protocol_state);
+                let tab_manager = Arc::clone(&protocol_tabs);
+                let usage_metrics = Arc::clone(&protocol_metrics);
+                let privacy_report = Arc::clone(&protocol_privacy);
+                let settings_registry = Arc::clone(&protocol_settings);
+                let policies = Arc::clone(&protocol_policies);
+                let safe_browsing = Arc::clone(&protocol_safe_browsing);
+                handle.spawn(async move {
+                    let html = if let Some(page) = FeedsPage::parse(&url) {
+                        feeds::render_page(&feed_manager, &ReadingMode::new(None), page).await
+                    } else if let Some(page) = NotesPage::parse(&url) {
+                        clipper::page::render_page(&notebook, &page)
+                    } else if let Some(page) = ReadingListPage::parse(&url) {
+                        reading_list::page::render_page(&reading_list, &ReadingMode::new(None), page)
+                    } else if tabs::is_stale_tabs_page(&url) {
+                        let days = browser_state.lock_or_recover().settings.tab_policy.stale_after_days;
+                        Ok(tabs::render_stale_tabs_page(&tab_manager.stale_tabs(), days))
+                    } else if metrics::is_stats_page(&url) {
+                        usage_metrics
+                            .summary(STATS_PAGE_DAYS)
+                            .map(|summary| metrics::render_stats_page(&summary, &usage_metrics.config()))
+                    } else if privacy::is_privacy_page(&url) {
+                        privacy_report
+                            .export(PRIVACY_PAGE_DAYS)
+                            .map(|export| privacy::render_privacy_page(&privacy_report.summary(PRIVACY_PAGE_DAYS), &export))
+                    } else if is_settings_page(&url) {
+                        Ok(render_settings_page(&settings_registry.pages(), &settings_registry.pending_restart()))
+                    } else if is_policy_page(&url) {
+                        Ok(render_policy_page(&policies, &settings_registry))
+                    } else if safe_browsing::is_blocked_page(&url) {
+                        safe_browsing.render_blocked_page(&url)
+                    } else {
+                        Err(WebxError::NotFound(format!("No internal page {}", url)))
+                    };
+                    match html_response(html) {
+                        Ok(response) => responder.respond(response),
+                        Err(e) => tracing::warn!("Failed to answer internal page request: {}", e),
+                    }
+                });
+            })
+            .with_ipc_handler(move |request| {
+                // Messages concern the tab this pane shows; answers to requests run in it
+                let context = IpcContext {
+                    window_id,
+                    tab_id: pane_tab(&ipc_state.lock_or_recover(), window_id, pane),
+                    origin: request.uri().to_string(),
+                };
+                if let Some((tab_id, script)) = context.tab_id.zip(ipc_router.dispatch(&context, request.body())) {
+                    let _ = proxy.send_event(UiEvent::EvalInTab { tab_id, script });
+                }
+            })
+            .build()
+    }
+}
+
+// Private helper functions
+
+/// `(x, y, width, height)` of a pane in physical pixels
+type PaneBounds = (i32, i32, u32, u32);
+
+/// Bounds of the main pane and the pane beside it in a window
+fn layout(window: &Window, split: Option<SplitView>) -> [PaneBounds; 2] {
+    let size = window.inner_size();
+    match split {
+        Some(split) => split.pane_bounds(size.width, size.height),
+        None => [(0, 0, size.width, size.height), (0, 0, 0, 0)],
+    }
+}
+
+fn pane_rect((x, y, width, height): PaneBounds) -> Rect {
+    Rect {
+        position: dpi::PhysicalPosition::new(x, y).into(),
+        size: dpi::PhysicalSize::new(width, height).into(),
+    }
+}
+
+/// Tab a pane of a window shows
+fn pane_tab(state: &BrowserState, window_id: usize, pane: Pane) -> Option<usize> {
+    match pane {
+        Pane::Main => state.window_active_tab(window_id),
+        Pane::Beside => state.windows.get(&window_id)?.split.map(|split| split.secondary_tab_id),
+    }
+}
+
+/// Answer an internal page request with the page, or the error as text
+fn html_response(html: Result<String, WebxError>) -> Result<Response<Vec<u8>>, wry::http::Error> {
+    match html {
+        Ok(html) => Response::builder()
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(html.into_bytes()),
+        Err(e) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(e.to_string().into_bytes()),
+    }
 }
@@ -1,4 +1,10 @@
 // Utility functions for the browser
+pub mod sync;
+
+pub use sync::{LockExt, Snapshot, StateWatchdog};
+
+use crate::error::WebxError;
+use std::ffi::OsStr;
 use std::path::Path;
 
 /// Extract domain from URL
@@ -8,7 +14,7 @@ pub fn extract_domain(url: &str) -> String {
             return host.replace("www.", "");
         }
     }
-    "New Tab".to_string()
+    crate::i18n::tr("tab-new")
 }
 
 /// Check if URL is secure (HTTPS)
@@ -90,3 +96,78 @@ pub fn truncate_string(s: &str, max_len: usize) -> String {
         format!("{}...", &s[..max_len.saturating_sub(3)])
     }
 }
+
+/// Normalize a URL or host name to the site domain it belongs to
+/// (lowercase, without `www.`)
+pub fn site_domain(input: &str) -> Option<String> {
+    let host = match url::Url::parse(input) {
+        Ok(parsed) => parsed.host_str()?.to_string(),
+        Err(_) => input.trim().trim_end_matches('/').to_string(),
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    let domain = host.strip_prefix("www.").unwrap_or(&host);
+    (!domain.is_empty() && !domain.contains(['/', ' '])).then(|| domain.to_string())
+}
+
+/// Check if a host is a domain or one of its subdomains
+pub fn host_in_domain(host: &str, domain: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    host == domain || host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Open a file or link with the application the system associates with it
+pub fn open_with_system(target: impl AsRef<OsStr>) -> Result<(), WebxError> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command.arg(target).spawn()?;
+    Ok(())
+}
+
+/// Escape text for HTML element content and double-quoted attribute values
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A string as a script literal that cannot end the `<script>` element
+/// it sits in, whatever a page put in it
+pub fn script_literal(text: &str) -> String {
+    serde_json::Value::from(text)
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+/// Check if a URL belongs to a domain or one of its subdomains
+pub fn url_in_domain(url: &str, domain: &str) -> bool {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(|host| host_in_domain(host, domain)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Q&A</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Q&amp;A&lt;/a&gt;"
+        );
+        assert_eq!(script_literal("</script>&"), r#""\u003c/script\u003e\u0026""#);
+    }
+}
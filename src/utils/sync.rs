@@ -0,0 +1,375 @@
+// Poison-safe shared state
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+/// Times a poisoned lock was taken over since startup
+static POISON_RECOVERIES: AtomicU64 = AtomicU64::new(0);
+
+/// Locking that survives a panic in another thread holding the lock
+pub trait LockExt<T> {
+    /// Lock, taking over the data if a panicking thread poisoned the lock.
+    /// The lock stays flagged as poisoned until the watchdog recovers it.
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    #[track_caller]
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            POISON_RECOVERIES.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Using poisoned lock at {}", std::panic::Location::caller());
+            poisoned.into_inner()
+        })
+    }
+}
+
+/// Number of times a poisoned lock was used since startup
+pub fn poison_recoveries() -> u64 {
+    POISON_RECOVERIES.load(Ordering::Relaxed)
+}
+
+/// A component recovered by the watchdog
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredComponent {
+    pub name: String,
+    /// Whether the component's reset hook ran
+    pub reset: bool,
+    pub recovered_at: chrono::DateTime<chrono::Utc>,
+}
+
+struct WatchedComponent {
+    name: String,
+    /// Returns None once the component was dropped, otherwise whether it was
+    /// poisoned and had to be recovered
+    recover: Box<dyn Fn() -> Option<bool> + Send + Sync>,
+    has_reset: bool,
+}
+
+/// Periodically finds components whose lock was poisoned by a panic, logs
+/// them, restores their invariants and clears the poison flag
+pub struct StateWatchdog {
+    components: Mutex<Vec<WatchedComponent>>,
+    recovered: Mutex<Vec<RecoveredComponent>>,
+    running: Arc<AtomicBool>,
+    thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+impl StateWatchdog {
+    /// Create new watchdog
+    pub fn new() -> Self {
+        Self {
+            components: Mutex::new(Vec::new()),
+            recovered: Mutex::new(Vec::new()),
+            running: Arc::new(AtomicBool::new(false)),
+            thread: Mutex::new(None),
+        }
+    }
+
+    /// Watch a component; a poisoned lock is just cleared
+    pub fn watch<T: Send + 'static>(&self, name: &str, state: &Arc<Mutex<T>>) {
+        self.add(name, state, None::<fn(&mut T)>);
+    }
+
+    /// Watch a component, running `reset` on its data before clearing the
+    /// poison flag so a half-finished update cannot leave it inconsistent
+    pub fn watch_with_reset<T, F>(&self, name: &str, state: &Arc<Mutex<T>>, reset: F)
+    where
+        T: Send + 'static,
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.add(name, state, Some(reset));
+    }
+
+    /// Names of the watched components that are still alive
+    pub fn components(&self) -> Vec<String> {
+        self.components.lock_or_recover().iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Recover every poisoned component now
+    pub fn check(&self) -> Vec<RecoveredComponent> {
+        let mut recovered = Vec::new();
+        self.components.lock_or_recover().retain(|component| match (component.recover)() {
+            None => false,
+            Some(was_poisoned) => {
+                if was_poisoned {
+                    tracing::error!("Recovered component {} after a panic", component.name);
+                    recovered.push(RecoveredComponent {
+                        name: component.name.clone(),
+                        reset: component.has_reset,
+                        recovered_at: chrono::Utc::now(),
+                    });
+                }
+                true
+            }
+        });
+
+        self.recovered.lock_or_recover().extend(recovered.iter().cloned());
+        recovered
+    }
+
+    /// Components recovered since startup
+    pub fn recovered(&self) -> Vec<RecoveredComponent> {
+        self.recovered.lock_or_recover().clone()
+    }
+
+    /// Check components in a background thread
+    pub fn start_watching(watchdog: Arc<StateWatchdog>, interval: Duration) {
+        watchdog.stop_watching();
+        watchdog.running.store(true, Ordering::SeqCst);
+
+        let running = watchdog.running.clone();
+        let weak = Arc::downgrade(&watchdog);
+        let handle = std::thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                std::thread::sleep(interval);
+                match weak.upgrade() {
+                    Some(watchdog) => {
+                        watchdog.check();
+                    }
+                    None => break,
+                }
+            }
+        });
+        *watchdog.thread.lock_or_recover() = Some(handle);
+    }
+
+    /// Stop the background thread
+    pub fn stop_watching(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.thread.lock_or_recover().take();
+    }
+
+    /// Check if the background thread is running
+    pub fn is_watching(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+            && self
+                .thread
+                .lock_or_recover()
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    // Private helper methods
+
+    fn add<T, F>(&self, name: &str, state: &Arc<Mutex<T>>, reset: Option<F>)
+    where
+        T: Send + 'static,
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        let has_reset = reset.is_some();
+        let weak: Weak<Mutex<T>> = Arc::downgrade(state);
+        let recover = move || {
+            let state = weak.upgrade()?;
+            if !state.is_poisoned() {
+                return Some(false);
+            }
+            if let Some(reset) = &reset {
+                reset(&mut state.lock_or_recover());
+            }
+            state.clear_poison();
+            Some(true)
+        };
+
+        self.components.lock_or_recover().push(WatchedComponent {
+            name: name.to_string(),
+            recover: Box::new(recover),
+            has_reset,
+        });
+    }
+}
+
+impl Default for StateWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StateWatchdog {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Immutable value shared as an `Arc` and replaced whole, so readers keep
+/// using the old value while a new one is built, e.g. compiled rule sets
+pub struct Snapshot<T> {
+    state: Mutex<SnapshotState<T>>,
+    rebuilt: Condvar,
+}
+
+struct SnapshotState<T> {
+    value: Arc<T>,
+    /// Version of `value`; rebuilds get increasing versions
+    version: u64,
+    /// Version of the most recently started rebuild
+    requested: u64,
+}
+
+impl<T: Send + Sync + 'static> Snapshot<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(SnapshotState {
+                value: Arc::new(value),
+                version: 0,
+                requested: 0,
+            }),
+            rebuilt: Condvar::new(),
+        }
+    }
+
+    /// Current value; holding it does not hold up replacing it
+    pub fn load(&self) -> Arc<T> {
+        Arc::clone(&self.state.lock_or_recover().value)
+    }
+
+    /// Replace the value now, superseding rebuilds still running
+    pub fn store(&self, value: T) {
+        let mut state = self.state.lock_or_recover();
+        state.requested += 1;
+        state.version = state.requested;
+        state.value = Arc::new(value);
+        self.rebuilt.notify_all();
+    }
+
+    /// Build a new value on a blocking worker, or a thread of its own
+    /// outside a runtime, and swap it in when done. A rebuild finishing
+    /// after a later one, or panicking, leaves the current value.
+    pub fn rebuild<F>(snapshot: &Arc<Self>, build: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let version = {
+            let mut state = snapshot.state.lock_or_recover();
+            state.requested += 1;
+            state.requested
+        };
+        let snapshot = Arc::clone(snapshot);
+        let task = move || {
+            let value = std::panic::catch_unwind(AssertUnwindSafe(build));
+            let mut state = snapshot.state.lock_or_recover();
+            match value {
+                Ok(value) if version > state.version => {
+                    state.value = Arc::new(value);
+                    state.version = version;
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    tracing::error!("Rebuild panicked, keeping the previous value");
+                    state.version = state.version.max(version);
+                }
+            }
+            snapshot.rebuilt.notify_all();
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(task);
+            }
+            Err(_) => {
+                std::thread::spawn(task);
+            }
+        }
+    }
+
+    /// Whether a rebuild is still running
+    pub fn is_rebuilding(&self) -> bool {
+        let state = self.state.lock_or_recover();
+        state.version < state.requested
+    }
+
+    /// Wait for every rebuild started so far to finish, returning false if
+    /// `timeout` passed first
+    pub fn wait_for_rebuilds(&self, timeout: Duration) -> bool {
+        let state = self.state.lock_or_recover();
+        let (state, _) = self
+            .rebuilt
+            .wait_timeout_while(state, timeout, |state| state.version < state.requested)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.version >= state.requested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poison<T: Send + 'static>(state: &Arc<Mutex<T>>) {
+        let state = state.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = state.lock().unwrap();
+            panic!("simulated crash while holding the lock");
+        })
+        .join();
+    }
+
+    #[test]
+    fn test_poisoned_lock_still_usable() {
+        let state = Arc::new(Mutex::new(vec![1, 2]));
+        poison(&state);
+        assert!(state.is_poisoned());
+
+        let before = poison_recoveries();
+        state.lock_or_recover().push(3);
+        assert_eq!(*state.lock_or_recover(), vec![1, 2, 3]);
+        assert!(poison_recoveries() >= before + 2);
+    }
+
+    #[test]
+    fn test_watchdog_resets_and_clears_poison() {
+        let watchdog = StateWatchdog::new();
+        let tabs = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let downloads = Arc::new(Mutex::new(0u32));
+        watchdog.watch_with_reset("tabs", &tabs, |tabs: &mut Vec<i32>| tabs.retain(|id| *id != 2));
+        watchdog.watch("downloads", &downloads);
+
+        assert!(watchdog.check().is_empty());
+        poison(&tabs);
+
+        let recovered = watchdog.check();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].name, "tabs");
+        assert!(recovered[0].reset);
+        assert!(!tabs.is_poisoned());
+        assert_eq!(*tabs.lock().unwrap(), vec![1, 3]);
+
+        // Dropped components are no longer watched
+        drop(downloads);
+        watchdog.check();
+        assert_eq!(watchdog.components(), vec!["tabs".to_string()]);
+        assert_eq!(watchdog.recovered().len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_rebuilds_in_background() {
+        let snapshot = Arc::new(Snapshot::new(vec![1]));
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        Snapshot::rebuild(&snapshot, move || {
+            wait.recv().unwrap();
+            vec![1, 2]
+        });
+
+        // Readers see the old value until the rebuild finishes
+        assert!(snapshot.is_rebuilding());
+        let old = snapshot.load();
+        assert_eq!(*old, vec![1]);
+        release.send(()).unwrap();
+        assert!(snapshot.wait_for_rebuilds(Duration::from_secs(5)));
+        assert_eq!(*snapshot.load(), vec![1, 2]);
+        assert_eq!(*old, vec![1]);
+
+        // A slow rebuild cannot overwrite a value stored after it started
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        Snapshot::rebuild(&snapshot, move || {
+            wait.recv().unwrap();
+            vec![9]
+        });
+        snapshot.store(vec![3]);
+        release.send(()).unwrap();
+        // The rebuild lets go of the snapshot once it is done
+        while Arc::strong_count(&snapshot) > 1 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(*snapshot.load(), vec![3]);
+    }
+}